@@ -39,7 +39,9 @@ use itertools::Itertools;
 use lru::LruCache;
 use near_async::futures::{AsyncComputationSpawner, AsyncComputationSpawnerExt};
 use near_async::time::{Clock, Duration, Instant};
-use near_chain_configs::{MutableConfigValue, ReshardingConfig, ReshardingHandle};
+use near_chain_configs::{
+    MutableConfigValue, ReshardingConfig, ReshardingHandle, StateSyncCatchupConfig,
+};
 #[cfg(feature = "new_epoch_sync")]
 use near_chain_primitives::error::epoch_sync::EpochSyncInfoError;
 use near_chain_primitives::error::{BlockKnownError, Error, LogTransientStorageError};
@@ -66,7 +68,7 @@ use near_primitives::merkle::{
 };
 use near_primitives::receipt::Receipt;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
-use near_primitives::shard_layout::{account_id_to_shard_id, ShardLayout, ShardUId};
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::sharding::{
     ChunkHash, ChunkHashHeight, EncodedShardChunk, ReceiptList, ReceiptProof, ShardChunk,
     ShardChunkHeader, ShardInfo, ShardProof, StateSyncInfo,
@@ -264,7 +266,8 @@ pub struct Chain {
     /// naturally prevent state tampering, so we can't *just* modify data in
     /// place in the database. Instead, we will include this "bonus changes" in
     /// the next block we'll be processing, keeping them in this field in the
-    /// meantime.
+    /// meantime. Patches scheduled for a later height are kept until a block
+    /// at that height is processed.
     ///
     /// Note that without `sandbox` feature enabled, `SandboxStatePatch` is
     /// a ZST.  All methods of the type are no-ops which behave as if the object
@@ -282,6 +285,9 @@ pub struct Chain {
     /// Configuration for resharding.
     pub(crate) resharding_config: MutableConfigValue<near_chain_configs::ReshardingConfig>,
 
+    /// Whether every chunk is shadow validated.
+    pub(crate) shadow_chunk_validation: MutableConfigValue<bool>,
+
     // A handle that allows the main process to interrupt resharding if needed.
     // This typically happens when the main process is interrupted.
     pub resharding_handle: ReshardingHandle,
@@ -385,6 +391,7 @@ impl Chain {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            shadow_chunk_validation: MutableConfigValue::new(false, "shadow_chunk_validation"),
             resharding_handle: ReshardingHandle::new(),
         })
     }
@@ -588,6 +595,7 @@ impl Chain {
             requested_state_parts: StateRequestTracker::new(),
             snapshot_callbacks,
             resharding_config: chain_config.resharding_config,
+            shadow_chunk_validation: chain_config.shadow_chunk_validation,
             resharding_handle: ReshardingHandle::new(),
         })
     }
@@ -1696,7 +1704,7 @@ impl Chain {
 
         // 1) preprocess the block where we verify that the block is valid and ready to be processed
         //    No chain updates are applied at this step.
-        let state_patch = self.pending_state_patch.take();
+        let state_patch = self.pending_state_patch.take_for_height(block.header().height());
         let preprocess_timer = metrics::BLOCK_PREPROCESSING_TIME.start_timer();
         let preprocess_res = self.preprocess_block(
             me,
@@ -2599,6 +2607,58 @@ impl Chain {
         Ok(state_part)
     }
 
+    /// Generates a state part of an arbitrary old epoch directly from the
+    /// trie. Unlike `get_state_response_part`, it doesn't need a state snapshot
+    /// and flat storage, which are only available for the latest epochs, but
+    /// it needs the trie nodes of the old epoch, which only archival nodes keep.
+    /// The parts are not cached, because they are unlikely to be requested
+    /// again soon.
+    pub fn get_historical_state_response_part(
+        &self,
+        shard_id: ShardId,
+        part_id: u64,
+        sync_hash: CryptoHash,
+    ) -> Result<Vec<u8>, Error> {
+        let _span = tracing::debug_span!(
+            target: "sync",
+            "get_historical_state_response_part",
+            shard_id,
+            part_id,
+            ?sync_hash)
+        .entered();
+        let block = self
+            .get_block(&sync_hash)
+            .log_storage_error("block has already been checked for existence")?;
+        let epoch_id = block.header().epoch_id();
+        let shard_ids = self.epoch_manager.shard_ids(epoch_id)?;
+        if !shard_ids.contains(&shard_id) {
+            return Err(shard_id_out_of_bounds(shard_id));
+        }
+        let prev_block = self.get_block(block.header().prev_hash())?;
+        if epoch_id == prev_block.header().epoch_id() {
+            return Err(sync_hash_not_first_hash(sync_hash));
+        }
+        let state_root = prev_block
+            .chunks()
+            .get(shard_id as usize)
+            .ok_or_else(|| Error::InvalidShardId(shard_id as ShardId))?
+            .prev_state_root();
+        let state_root_node = self
+            .runtime_adapter
+            .get_state_root_node(shard_id, prev_block.hash(), &state_root)
+            .log_storage_error("get_state_root_node fail")?;
+        let num_parts = get_num_state_parts(state_root_node.memory_usage);
+        if part_id >= num_parts {
+            return Err(part_id_out_of_bounds(part_id, num_parts));
+        }
+        let shard_uid =
+            self.epoch_manager.shard_id_to_uid(shard_id, prev_block.header().epoch_id())?;
+        let trie = self.runtime_adapter.get_tries().get_view_trie_for_shard(shard_uid, state_root);
+        let partial_state =
+            trie.get_trie_nodes_for_part_without_flat_storage(PartId::new(part_id, num_parts))?;
+        Ok(borsh::to_vec(&partial_state)?)
+    }
+
     pub fn set_state_header(
         &mut self,
         shard_id: ShardId,
@@ -2797,6 +2857,7 @@ impl Chain {
         shard_id: ShardId,
         sync_hash: CryptoHash,
         num_parts: u64,
+        catchup_config: Option<StateSyncCatchupConfig>,
         state_parts_task_scheduler: &near_async::messaging::Sender<ApplyStatePartsRequest>,
     ) -> Result<(), Error> {
         let epoch_id = self.get_block_header(&sync_hash)?.epoch_id().clone();
@@ -2812,6 +2873,7 @@ impl Chain {
             num_parts,
             epoch_id,
             sync_hash,
+            catchup_config,
         });
 
         Ok(())
@@ -3027,6 +3089,9 @@ impl Chain {
                         transaction_validity_period,
                     )
                     .map_err(|_| Error::from(Error::InvalidTransactions))?;
+                if transaction.transaction.is_expired(prev_block_header.height()) {
+                    return Err(Error::InvalidTransactions);
+                }
             }
         };
 
@@ -3045,6 +3110,7 @@ impl Chain {
                     self.transaction_validity_period,
                 )
                 .is_ok()
+                && !tx.transaction.is_expired(prev_block_header.height())
         }
     }
 
@@ -3465,7 +3531,7 @@ impl Chain {
         me: &Option<AccountId>,
         block_header: &BlockHeader,
     ) -> Result<bool, Error> {
-        if cfg!(feature = "shadow_chunk_validation") {
+        if self.shadow_chunk_validation.get() {
             return Ok(true);
         }
         let epoch_id = block_header.epoch_id();
@@ -3853,6 +3919,10 @@ fn shard_id_out_of_bounds(shard_id: ShardId) -> Error {
     Error::InvalidStateRequest(format!("shard_id {shard_id:?} out of bounds").into())
 }
 
+fn part_id_out_of_bounds(part_id: u64, num_parts: u64) -> Error {
+    Error::InvalidStateRequest(format!("part_id {part_id} out of bounds of {num_parts} parts"))
+}
+
 fn sync_hash_not_first_hash(sync_hash: CryptoHash) -> Error {
     Error::InvalidStateRequest(
         format!("sync_hash {sync_hash:?} is not the first hash of the epoch").into(),
@@ -4419,7 +4489,7 @@ impl Chain {
     ) -> HashMap<ShardId, Vec<Receipt>> {
         let mut result = HashMap::new();
         for receipt in receipts {
-            let shard_id = account_id_to_shard_id(receipt.receiver_id(), shard_layout);
+            let shard_id = receipt.receiver_shard_id(shard_layout);
             let entry = result.entry(shard_id).or_insert_with(Vec::new);
             entry.push(receipt)
         }
@@ -4439,9 +4509,15 @@ impl Chain {
             shard_layout.shard_ids().map(|shard_id| (shard_id, vec![])).collect();
         let mut cache = HashMap::new();
         for receipt in receipts {
-            let &mut shard_id = cache
-                .entry(receipt.receiver_id())
-                .or_insert_with(|| account_id_to_shard_id(receipt.receiver_id(), shard_layout));
+            // Distribution receipts target a shard directly, so they can't be
+            // cached by their receiver.
+            let shard_id = if receipt.is_distribution() {
+                receipt.receiver_shard_id(shard_layout)
+            } else {
+                *cache
+                    .entry(receipt.receiver_id())
+                    .or_insert_with(|| receipt.receiver_shard_id(shard_layout))
+            };
             // This unwrap should be safe as we pre-populated the map with all
             // valid shard ids.
             result.get_mut(&shard_id).unwrap().push(receipt);
@@ -4604,6 +4680,9 @@ pub struct ApplyStatePartsRequest {
     pub num_parts: u64,
     pub epoch_id: EpochId,
     pub sync_hash: CryptoHash,
+    /// Set if the parts are applied during catch-up, in which case applying
+    /// them is throttled according to the config.
+    pub catchup_config: Option<StateSyncCatchupConfig>,
 }
 
 // Skip `runtime_adapter`, because it's a complex object that has complex logic
@@ -4617,6 +4696,7 @@ impl Debug for ApplyStatePartsRequest {
             .field("num_parts", &self.num_parts)
             .field("epoch_id", &self.epoch_id)
             .field("sync_hash", &self.sync_hash)
+            .field("catchup_config", &self.catchup_config)
             .finish()
     }
 }
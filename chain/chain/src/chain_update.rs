@@ -20,7 +20,7 @@ use near_primitives::block_header::BlockHeader;
 #[cfg(feature = "new_epoch_sync")]
 use near_primitives::epoch_manager::{block_info::BlockInfo, epoch_sync::EpochSyncInfo};
 use near_primitives::hash::CryptoHash;
-use near_primitives::shard_layout::{account_id_to_shard_id, account_id_to_shard_uid, ShardUId};
+use near_primitives::shard_layout::{account_id_to_shard_uid, ShardUId};
 use near_primitives::sharding::ShardChunk;
 use near_primitives::state_sync::{ReceiptProofResponse, ShardStateSyncResponseHeader};
 use near_primitives::types::chunk_extra::ChunkExtra;
@@ -136,12 +136,7 @@ impl<'a> ChainUpdate<'a> {
         let shard_layout = self.epoch_manager.get_shard_layout_from_prev_block(hash)?;
         let outgoing_receipts = outgoing_receipts
             .iter()
-            .map(|receipt| {
-                (
-                    *receipt.receipt_id(),
-                    account_id_to_shard_id(receipt.receiver_id(), &shard_layout),
-                )
-            })
+            .map(|receipt| (*receipt.receipt_id(), receipt.receiver_shard_id(&shard_layout)))
             .collect();
         Ok(outgoing_receipts)
     }
@@ -1043,6 +1043,9 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::LatestWitnessesByIndex => {
                 store_update.delete(col, key);
             }
+            DBCol::ShadowStateWitness => {
+                store_update.delete(col, key);
+            }
             DBCol::DbVersion
             | DBCol::BlockMisc
             | DBCol::_GCCount
@@ -1071,6 +1074,8 @@ impl<'a> ChainStoreUpdate<'a> {
             | DBCol::FlatStateChanges
             | DBCol::FlatStateDeltaMetadata
             | DBCol::FlatStorageStatus
+            | DBCol::FlatStateHistory
+            | DBCol::FlatStateHistoryBlocks
             | DBCol::Misc
             => unreachable!(),
             #[cfg(feature = "new_epoch_sync")]
@@ -271,3 +271,12 @@ pub static SAVED_LATEST_WITNESSES_SIZE: Lazy<IntGauge> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static SAVED_SHADOW_WITNESS_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_saved_shadow_witness_size",
+        "Size of the saved shadow witnesses (in bytes)",
+        &["shard_id"],
+        Some(exponential_buckets(100_000.0, 1.2, 32).unwrap()),
+    )
+    .unwrap()
+});
@@ -22,7 +22,9 @@ use near_store::flat::{
     store_helper, BlockInfo, FlatStorageError, FlatStorageManager, FlatStorageReadyStatus,
     FlatStorageStatus,
 };
-use near_store::resharding::{get_delayed_receipts, get_promise_yield_timeouts};
+use near_store::resharding::{
+    get_delayed_receipts, get_priority_delayed_receipts, get_promise_yield_timeouts,
+};
 use near_store::trie::SnapshotError;
 use near_store::{ShardTries, ShardUId, StorageError, Store, Trie, TrieDBStorage, TrieStorage};
 use std::collections::{HashMap, HashSet};
@@ -164,6 +166,22 @@ fn apply_delayed_receipts<'a>(
         store_update.commit()?;
     }
 
+    // The receipts with a priority are put back in the lanes of their priority in the children.
+    let mut start = None;
+    while let Some((next_position, receipts)) =
+        get_priority_delayed_receipts(&orig_trie_update, start, config.batch_size)?
+    {
+        total_count += receipts.len() as u64;
+        let (store_update, updated_state_roots) = tries.apply_delayed_receipts_to_children_states(
+            &new_state_roots,
+            &receipts,
+            account_id_to_shard_uid,
+        )?;
+        new_state_roots = updated_state_roots;
+        start = Some(next_position);
+        store_update.commit()?;
+    }
+
     tracing::debug!(target: "resharding", ?orig_shard_uid, ?total_count, "Applied delayed receipts");
     Ok(new_state_roots)
 }
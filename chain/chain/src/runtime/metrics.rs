@@ -51,6 +51,16 @@ pub(crate) static PREPARE_TX_GAS: Lazy<HistogramVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static PREPARE_TX_ESTIMATED_WITNESS_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_prepare_tx_estimated_witness_size",
+        "Estimated uncompressed size of the state witness of a produced chunk, including the new transactions and their storage proof",
+        &["shard_id"],
+        Some(exponential_buckets(100_000., 1.5, 20).unwrap()),
+    )
+    .unwrap()
+});
+
 pub(crate) static PREPARE_TX_REJECTED: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_prepare_tx_rejected",
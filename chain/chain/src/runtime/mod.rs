@@ -8,7 +8,8 @@ use borsh::BorshDeserialize;
 use errors::FromStateViewerErrors;
 use near_async::time::{Duration, Instant};
 use near_chain_configs::{
-    GenesisConfig, ProtocolConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP, MIN_GC_NUM_EPOCHS_TO_KEEP,
+    GenesisConfig, MutableConfigValue, ProtocolConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+    MIN_GC_NUM_EPOCHS_TO_KEEP,
 };
 use near_crypto::PublicKey;
 use near_epoch_manager::{EpochManagerAdapter, EpochManagerHandle};
@@ -23,6 +24,8 @@ use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::receipt::{DelayedReceiptIndices, Receipt};
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
+#[cfg(feature = "sandbox")]
+use near_primitives::sandbox::SandboxRuntimeConfigPatch;
 use near_primitives::shard_layout::{
     account_id_to_shard_id, account_id_to_shard_uid, ShardLayout, ShardUId,
 };
@@ -35,15 +38,16 @@ use near_primitives::types::{
 };
 use near_primitives::version::{ProtocolFeature, ProtocolVersion};
 use near_primitives::views::{
-    AccessKeyInfoView, CallResult, ContractCodeView, QueryRequest, QueryResponse,
+    AccessKeyInfoView, CallResult, ContractCodeView, PromiseYieldView, QueryRequest, QueryResponse,
     QueryResponseKind, ViewApplyState, ViewStateResult,
 };
 use near_store::config::StateSnapshotType;
 use near_store::flat::FlatStorageManager;
 use near_store::metadata::DbKind;
 use near_store::{
-    ApplyStatePartResult, DBCol, ShardTries, StateSnapshotConfig, Store, Trie, TrieConfig,
-    TrieUpdate, WrappedTrieChanges, COLD_HEAD_KEY,
+    get_runtime_config_overrides_record, ApplyStatePartResult, DBCol, ShardTries,
+    StateSnapshotConfig, Store, Trie, TrieAccess, TrieConfig, TrieUpdate, WrappedTrieChanges,
+    COLD_HEAD_KEY,
 };
 use near_vm_runner::ContractCode;
 use near_vm_runner::{precompile_contract, ContractRuntimeCache, FilesystemContractRuntimeCache};
@@ -56,7 +60,7 @@ use node_runtime::{
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 
 pub mod errors;
 mod metrics;
@@ -78,6 +82,14 @@ pub struct NightshadeRuntime {
     epoch_manager: Arc<EpochManagerHandle>,
     migration_data: Arc<MigrationData>,
     gc_num_epochs_to_keep: u64,
+    /// Whether every chunk is shadow validated, in which case reads have to be
+    /// recorded regardless of the protocol version.
+    shadow_chunk_validation: MutableConfigValue<bool>,
+    /// Runtime config parameters overridden with sandbox state patches. They
+    /// apply to all protocol versions and are kept in the store, so that they
+    /// survive restarts.
+    #[cfg(feature = "sandbox")]
+    sandbox_runtime_config: std::sync::RwLock<SandboxRuntimeConfigPatch>,
 }
 
 impl NightshadeRuntime {
@@ -92,6 +104,7 @@ impl NightshadeRuntime {
         gc_num_epochs_to_keep: u64,
         trie_config: TrieConfig,
         state_snapshot_config: StateSnapshotConfig,
+        shadow_chunk_validation: MutableConfigValue<bool>,
     ) -> Arc<Self> {
         let runtime_config_store = match runtime_config_store {
             Some(store) => store,
@@ -100,7 +113,14 @@ impl NightshadeRuntime {
 
         let runtime = Runtime::new();
         let trie_viewer = TrieViewer::new(trie_viewer_state_size_limit, max_gas_burnt_view);
-        let flat_storage_manager = FlatStorageManager::new(store.clone());
+        let flat_state_history_blocks = trie_config
+            .flat_state_history_epochs
+            .map_or(0, |epochs| epochs * genesis_config.epoch_length);
+        let flat_storage_manager = FlatStorageManager::with_config(
+            store.clone(),
+            flat_state_history_blocks,
+            trie_config.flat_storage_deltas.clone(),
+        );
         let shard_uids: Vec<_> = genesis_config.shard_layout.shard_uids().collect();
         let tries = ShardTries::new(
             store.clone(),
@@ -119,6 +139,11 @@ impl NightshadeRuntime {
         }
 
         let migration_data = Arc::new(migrations::load_migration_data(&genesis_config.chain_id));
+        #[cfg(feature = "sandbox")]
+        let sandbox_runtime_config = store
+            .get_ser(DBCol::BlockMisc, near_store::SANDBOX_RUNTIME_CONFIG_KEY)
+            .expect("failed to read the sandbox runtime config")
+            .unwrap_or_default();
         Arc::new(NightshadeRuntime {
             genesis_config: genesis_config.clone(),
             compiled_contract_cache,
@@ -130,6 +155,9 @@ impl NightshadeRuntime {
             epoch_manager,
             migration_data,
             gc_num_epochs_to_keep: gc_num_epochs_to_keep.max(MIN_GC_NUM_EPOCHS_TO_KEEP),
+            shadow_chunk_validation,
+            #[cfg(feature = "sandbox")]
+            sandbox_runtime_config: std::sync::RwLock::new(sandbox_runtime_config),
         })
     }
 
@@ -157,7 +185,9 @@ impl NightshadeRuntime {
                 home_dir: home_dir.to_path_buf(),
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
+                incremental: false,
             },
+            MutableConfigValue::new(false, "shadow_chunk_validation"),
         )
     }
 
@@ -185,7 +215,9 @@ impl NightshadeRuntime {
                 home_dir: home_dir.to_path_buf(),
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
+                incremental: false,
             },
+            MutableConfigValue::new(false, "shadow_chunk_validation"),
         )
     }
 
@@ -208,6 +240,37 @@ impl NightshadeRuntime {
         )
     }
 
+    /// Returns the runtime config of the protocol version. Given the state of
+    /// a shard, the runtime config overrides it stores for the epoch of the
+    /// given height are applied on top of it. Each shard receives them from the
+    /// protocol treasury account, see `Runtime::update_runtime_config_overrides`.
+    /// In sandbox, the parameters overridden with state patches are applied last.
+    fn get_runtime_config(
+        &self,
+        protocol_version: ProtocolVersion,
+        state: Option<(&dyn TrieAccess, EpochHeight)>,
+    ) -> Result<Arc<RuntimeConfig>, Error> {
+        let runtime_config = match state {
+            Some((trie, epoch_height))
+                if ProtocolFeature::OnChainRuntimeConfig.enabled(protocol_version) =>
+            {
+                let overrides = get_runtime_config_overrides_record(trie)?.get(epoch_height);
+                self.runtime_config_store.get_config_with_overrides(protocol_version, &overrides)
+            }
+            _ => self.runtime_config_store.get_config(protocol_version).clone(),
+        };
+        #[cfg(feature = "sandbox")]
+        {
+            let patch = self.sandbox_runtime_config.read().unwrap();
+            if !patch.is_empty() {
+                let mut runtime_config = RuntimeConfig::clone(&runtime_config);
+                patch.apply(&mut runtime_config);
+                return Ok(Arc::new(runtime_config));
+            }
+        }
+        Ok(runtime_config)
+    }
+
     fn get_shard_uid_from_prev_hash(
         &self,
         shard_id: ShardId,
@@ -272,6 +335,18 @@ impl NightshadeRuntime {
             is_new_chunk,
             is_first_block_with_chunk_of_version,
         } = chunk;
+        #[cfg(feature = "sandbox")]
+        if let Some(runtime_config) = state_patch.runtime_config() {
+            let mut sandbox_runtime_config = self.sandbox_runtime_config.write().unwrap();
+            sandbox_runtime_config.merge(runtime_config.clone());
+            let mut store_update = self.store.store_update();
+            store_update.set_ser(
+                DBCol::BlockMisc,
+                near_store::SANDBOX_RUNTIME_CONFIG_KEY,
+                &*sandbox_runtime_config,
+            )?;
+            store_update.commit()?;
+        }
         let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(prev_block_hash)?;
         let validator_accounts_update = {
             let epoch_manager = self.epoch_manager.read();
@@ -376,7 +451,8 @@ impl NightshadeRuntime {
             gas_limit: Some(gas_limit),
             random_seed,
             current_protocol_version,
-            config: self.runtime_config_store.get_config(current_protocol_version).clone(),
+            config: self
+                .get_runtime_config(current_protocol_version, Some((&trie, epoch_height)))?,
             cache: Some(self.compiled_contract_cache.handle()),
             is_new_chunk,
             migration_data: Arc::clone(&self.migration_data),
@@ -466,7 +542,9 @@ impl NightshadeRuntime {
         Ok(result)
     }
 
-    fn precompile_contracts(
+    /// Compiles the given contracts with the VM config of the epoch and stores them in the
+    /// compiled contract cache. Uses at most half of the rayon threads.
+    pub fn precompile_contracts(
         &self,
         epoch_id: &EpochId,
         contract_codes: Vec<ContractCode>,
@@ -561,6 +639,17 @@ impl NightshadeRuntime {
 
         let trie_with_state =
             self.tries.get_trie_with_block_hash_for_shard(shard_uid, *state_root, &prev_hash, true);
+        // The memtrie is much faster than the snapshot, so use it if it still
+        // has the requested state root.
+        match trie_with_state.get_trie_nodes_for_part_with_memtries(part_id) {
+            Ok(Some(partial_state)) => {
+                return Ok(borsh::to_vec(&partial_state).expect("serializer should not fail"));
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!(target: "runtime", ?err, part_id.idx, part_id.total, %prev_hash, %state_root, %shard_id, "Can't get state part from memtrie, falling back to the snapshot");
+            }
+        }
         let (partial_state, nibbles_begin, nibbles_end) = match trie_with_state
             .get_state_part_boundaries(part_id)
         {
@@ -644,15 +733,18 @@ impl RuntimeAdapter for NightshadeRuntime {
         epoch_id: &EpochId,
         current_protocol_version: ProtocolVersion,
     ) -> Result<Option<InvalidTxError>, Error> {
-        let runtime_config = self.runtime_config_store.get_config(current_protocol_version);
-
         if let Some(state_root) = state_root {
             let shard_uid =
                 self.account_id_to_shard_uid(transaction.transaction.signer_id(), epoch_id)?;
             let mut state_update = self.tries.new_trie_update(shard_uid, state_root);
+            let epoch_height = self.epoch_manager.get_epoch_info(epoch_id)?.epoch_height();
+            let runtime_config = self.get_runtime_config(
+                current_protocol_version,
+                Some((&state_update, epoch_height)),
+            )?;
 
             match verify_and_charge_transaction(
-                runtime_config,
+                &runtime_config,
                 &mut state_update,
                 gas_price,
                 transaction,
@@ -672,8 +764,9 @@ impl RuntimeAdapter for NightshadeRuntime {
             }
         } else {
             // Doing basic validation without a state root
+            let runtime_config = self.get_runtime_config(current_protocol_version, None)?;
             match validate_transaction(
-                runtime_config,
+                &runtime_config,
                 gas_price,
                 transaction,
                 verify_signature,
@@ -700,7 +793,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         time_limit: Option<Duration>,
     ) -> Result<PreparedTransactions, Error> {
         let start_time = std::time::Instant::now();
-        let PrepareTransactionsChunkContext { shard_id, gas_limit } = chunk;
+        let PrepareTransactionsChunkContext { shard_id, gas_limit, witness_size_budget } = chunk;
 
         let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&prev_block.block_hash)?;
         let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
@@ -731,9 +824,15 @@ impl RuntimeAdapter for NightshadeRuntime {
         // in the current epoch to be able to produce the state witness in the
         // next epoch.
         if checked_feature!("stable", StateWitnessSizeLimit, next_protocol_version)
-            || cfg!(feature = "shadow_chunk_validation")
+            || self.shadow_chunk_validation.get()
         {
-            trie = trie.recording_reads();
+            trie = if ProtocolFeature::ExcludeRemovedValuesFromStorageProof
+                .enabled(next_protocol_version)
+            {
+                trie.recording_reads_excluding_removed_values()
+            } else {
+                trie.recording_reads()
+            };
         }
         let mut state_update = TrieUpdate::new(trie);
 
@@ -741,10 +840,16 @@ impl RuntimeAdapter for NightshadeRuntime {
         let mut total_gas_burnt = 0;
         let mut total_size = 0u64;
 
-        let runtime_config = self.runtime_config_store.get_config(protocol_version);
+        let epoch_height =
+            self.epoch_manager.get_epoch_height_from_prev_block(&prev_block.block_hash)?;
+        let runtime_config =
+            self.get_runtime_config(protocol_version, Some((&state_update, epoch_height)))?;
 
-        let transactions_gas_limit =
-            chunk_tx_gas_limit(protocol_version, runtime_config, &prev_block, shard_id, gas_limit);
+        let mut transactions_gas_limit =
+            chunk_tx_gas_limit(protocol_version, &runtime_config, &prev_block, shard_id, gas_limit);
+        if let Some(budget) = &witness_size_budget {
+            transactions_gas_limit = budget.transactions_gas_limit(transactions_gas_limit);
+        }
 
         let mut result = PreparedTransactions {
             transactions: Vec::new(),
@@ -761,6 +866,10 @@ impl RuntimeAdapter for NightshadeRuntime {
         // a bound of at most 10000 receipts processed in a chunk.
         let delayed_receipts_indices: DelayedReceiptIndices =
             near_store::get(&state_update, &TrieKey::DelayedReceiptIndices)?.unwrap_or_default();
+        let priority_delayed_receipts_indices =
+            near_store::get_priority_delayed_receipt_indices(&state_update)?;
+        let delayed_receipts_count =
+            delayed_receipts_indices.len() + priority_delayed_receipts_indices.len();
         let min_fee = runtime_config.fees.fee(ActionCosts::new_action_receipt).exec_fee();
         let new_receipt_count_limit = if min_fee > 0 {
             // Round up to include at least one receipt.
@@ -772,7 +881,7 @@ impl RuntimeAdapter for NightshadeRuntime {
             // This buffer size is a trade-off between the max queue size and system efficiency
             // under congestion.
             let delayed_receipt_count_limit = max_processed_receipts_in_chunk * 2;
-            delayed_receipt_count_limit.saturating_sub(delayed_receipts_indices.len()) as usize
+            delayed_receipt_count_limit.saturating_sub(delayed_receipts_count) as usize
         } else {
             usize::MAX
         };
@@ -793,7 +902,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         let mut rejected_invalid_for_chain = 0;
 
         // Add new transactions to the result until some limit is hit or the transactions run out.
-        loop {
+        'add_transactions: loop {
             if total_gas_burnt >= transactions_gas_limit {
                 result.limited_by = Some(PrepareTransactionsLimit::Gas);
                 break;
@@ -819,6 +928,14 @@ impl RuntimeAdapter for NightshadeRuntime {
                     break;
                 }
             }
+            if let Some(budget) = &witness_size_budget {
+                let witness_size =
+                    budget.witness_size(total_size, state_update.trie.recorded_storage_size());
+                if witness_size >= budget.soft_limit {
+                    result.limited_by = Some(PrepareTransactionsLimit::WitnessSize);
+                    break;
+                }
+            }
 
             if let Some(iter) = transaction_groups.next() {
                 while let Some(tx) = iter.next() {
@@ -855,7 +972,7 @@ impl RuntimeAdapter for NightshadeRuntime {
 
                     // Verifying the validity of the transaction based on the current state.
                     match verify_and_charge_transaction(
-                        runtime_config,
+                        &runtime_config,
                         &mut state_update,
                         prev_block.next_gas_price,
                         &tx,
@@ -864,6 +981,19 @@ impl RuntimeAdapter for NightshadeRuntime {
                         protocol_version,
                     ) {
                         Ok(verification_result) => {
+                            if let Some(budget) = &witness_size_budget {
+                                let witness_size = budget.witness_size(
+                                    total_size + tx.get_size(),
+                                    state_update.trie.recorded_storage_size(),
+                                );
+                                if witness_size > budget.hard_limit {
+                                    tracing::trace!(target: "runtime", tx=?tx.get_hash(), witness_size, "leaving transaction which exceeds the witness size budget in the pool");
+                                    state_update.rollback();
+                                    iter.put_back(tx);
+                                    result.limited_by = Some(PrepareTransactionsLimit::WitnessSize);
+                                    break 'add_transactions;
+                                }
+                            }
                             tracing::trace!(target: "runtime", tx=?tx.get_hash(), "including transaction that passed validation");
                             state_update.commit(StateChangeCause::NotWritableToDisk);
                             total_gas_burnt += verification_result.gas_burnt;
@@ -900,6 +1030,11 @@ impl RuntimeAdapter for NightshadeRuntime {
             .with_label_values(&[&shard_label, "invalid_block_hash"])
             .observe(rejected_invalid_for_chain as f64);
         metrics::PREPARE_TX_GAS.with_label_values(&[&shard_label]).observe(total_gas_burnt as f64);
+        if let Some(budget) = &witness_size_budget {
+            metrics::PREPARE_TX_ESTIMATED_WITNESS_SIZE.with_label_values(&[&shard_label]).observe(
+                budget.witness_size(total_size, state_update.trie.recorded_storage_size()) as f64,
+            );
+        }
         metrics::CONGESTION_PREPARE_TX_GAS_LIMIT
             .with_label_values(&[&shard_label])
             .set(i64::try_from(transactions_gas_limit).unwrap_or(i64::MAX));
@@ -955,9 +1090,15 @@ impl RuntimeAdapter for NightshadeRuntime {
         // in the current epoch to be able to produce the state witness in the
         // next epoch.
         if checked_feature!("stable", StateWitnessSizeLimit, next_protocol_version)
-            || cfg!(feature = "shadow_chunk_validation")
+            || self.shadow_chunk_validation.get()
         {
-            trie = trie.recording_reads();
+            trie = if ProtocolFeature::ExcludeRemovedValuesFromStorageProof
+                .enabled(next_protocol_version)
+            {
+                trie.recording_reads_excluding_removed_values()
+            } else {
+                trie.recording_reads()
+            };
         }
 
         match self.process_state_update(
@@ -994,8 +1135,9 @@ impl RuntimeAdapter for NightshadeRuntime {
     ) -> Result<QueryResponse, crate::near_chain_primitives::error::QueryError> {
         match request {
             QueryRequest::ViewAccount { account_id } => {
-                let account =
-                    self.view_account(&shard_uid, *state_root, account_id).map_err(|err| {
+                let account = self
+                    .view_account(&shard_uid, *state_root, block_hash, account_id)
+                    .map_err(|err| {
                         crate::near_chain_primitives::error::QueryError::from_view_account_error(
                             err,
                             block_height,
@@ -1010,7 +1152,7 @@ impl RuntimeAdapter for NightshadeRuntime {
             }
             QueryRequest::ViewCode { account_id } => {
                 let contract_code = self
-                    .view_contract_code(&shard_uid,  *state_root, account_id)
+                    .view_contract_code(&shard_uid, *state_root, block_hash, account_id)
                     .map_err(|err| crate::near_chain_primitives::error::QueryError::from_view_contract_code_error(err, block_height, *block_hash))?;
                 let hash = *contract_code.hash();
                 let contract_code_view = ContractCodeView { hash, code: contract_code.into_code() };
@@ -1090,8 +1232,9 @@ impl RuntimeAdapter for NightshadeRuntime {
                 })
             }
             QueryRequest::ViewAccessKeyList { account_id } => {
-                let access_key_list =
-                    self.view_access_keys(&shard_uid, *state_root, account_id).map_err(|err| {
+                let access_key_list = self
+                    .view_access_keys(&shard_uid, *state_root, block_hash, account_id)
+                    .map_err(|err| {
                         crate::near_chain_primitives::error::QueryError::from_view_access_key_error(
                             err,
                             block_height,
@@ -1114,7 +1257,7 @@ impl RuntimeAdapter for NightshadeRuntime {
             }
             QueryRequest::ViewAccessKey { account_id, public_key } => {
                 let access_key = self
-                    .view_access_key(&shard_uid, *state_root, account_id, public_key)
+                    .view_access_key(&shard_uid, *state_root, block_hash, account_id, public_key)
                     .map_err(|err| {
                         crate::near_chain_primitives::error::QueryError::from_view_access_key_error(
                             err,
@@ -1128,6 +1271,29 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
+            QueryRequest::ViewPromiseYield { account_id, data_id } => {
+                let promise_yield = self
+                    .view_promise_yield(
+                        &shard_uid,
+                        *state_root,
+                        block_hash,
+                        block_height,
+                        account_id,
+                        *data_id,
+                    )
+                    .map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::InternalError {
+                            error_message: err.to_string(),
+                            block_height,
+                            block_hash: *block_hash,
+                        }
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::ViewPromiseYield(promise_yield),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
         }
     }
 
@@ -1281,7 +1447,11 @@ impl RuntimeAdapter for NightshadeRuntime {
         }
     }
 
-    fn get_protocol_config(&self, epoch_id: &EpochId) -> Result<ProtocolConfig, Error> {
+    fn get_protocol_config(
+        &self,
+        epoch_id: &EpochId,
+        shard_state: Option<(ShardId, StateRoot)>,
+    ) -> Result<ProtocolConfig, Error> {
         let protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
         let mut genesis_config = self.genesis_config.clone();
         genesis_config.protocol_version = protocol_version;
@@ -1314,8 +1484,16 @@ impl RuntimeAdapter for NightshadeRuntime {
         genesis_config.shuffle_shard_assignment_for_chunk_producers =
             epoch_config.validator_selection_config.shuffle_shard_assignment_for_chunk_producers;
 
-        let runtime_config =
-            self.runtime_config_store.get_config(protocol_version).as_ref().clone();
+        let runtime_config = match shard_state {
+            Some((shard_id, state_root)) => {
+                let shard_uid = self.get_shard_uid_from_epoch_id(shard_id, epoch_id)?;
+                let trie = self.tries.get_view_trie_for_shard(shard_uid, state_root);
+                let epoch_height = self.epoch_manager.get_epoch_info(epoch_id)?.epoch_height();
+                self.get_runtime_config(protocol_version, Some((&trie, epoch_height)))?
+            }
+            None => self.get_runtime_config(protocol_version, None)?,
+        };
+        let runtime_config = runtime_config.as_ref().clone();
         Ok(ProtocolConfig { genesis_config, runtime_config })
     }
 
@@ -1363,9 +1541,11 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
     ) -> Result<Account, node_runtime::state_viewer::errors::ViewAccountError> {
-        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
+        let state_update =
+            self.tries.new_trie_update_view_at_block(*shard_uid, state_root, block_hash);
         self.trie_viewer.view_account(&state_update, account_id)
     }
 
@@ -1373,9 +1553,11 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
     ) -> Result<ContractCode, node_runtime::state_viewer::errors::ViewContractCodeError> {
-        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
+        let state_update =
+            self.tries.new_trie_update_view_at_block(*shard_uid, state_root, block_hash);
         self.trie_viewer.view_contract_code(&state_update, account_id)
     }
 
@@ -1396,7 +1578,8 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
     ) -> Result<Vec<u8>, node_runtime::state_viewer::errors::CallFunctionError> {
-        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
+        let state_update =
+            self.tries.new_trie_update_view_at_block(*shard_uid, state_root, block_hash);
         let view_state = ViewApplyState {
             shard_id: shard_uid.shard_id(),
             block_height: height,
@@ -1423,10 +1606,12 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
         public_key: &PublicKey,
     ) -> Result<AccessKey, node_runtime::state_viewer::errors::ViewAccessKeyError> {
-        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
+        let state_update =
+            self.tries.new_trie_update_view_at_block(*shard_uid, state_root, block_hash);
         self.trie_viewer.view_access_key(&state_update, account_id, public_key)
     }
 
@@ -1434,10 +1619,12 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
     ) -> Result<Vec<(PublicKey, AccessKey)>, node_runtime::state_viewer::errors::ViewAccessKeyError>
     {
-        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
+        let state_update =
+            self.tries.new_trie_update_view_at_block(*shard_uid, state_root, block_hash);
         self.trie_viewer.view_access_keys(&state_update, account_id)
     }
 
@@ -1452,4 +1639,18 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
         self.trie_viewer.view_state(&state_update, account_id, prefix, include_proof)
     }
+
+    fn view_promise_yield(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        block_hash: &CryptoHash,
+        block_height: BlockHeight,
+        account_id: &AccountId,
+        data_id: CryptoHash,
+    ) -> Result<PromiseYieldView, StorageError> {
+        let state_update =
+            self.tries.new_trie_update_view_at_block(*shard_uid, state_root, block_hash);
+        self.trie_viewer.view_promise_yield(&state_update, block_height, account_id, data_id)
+    }
 }
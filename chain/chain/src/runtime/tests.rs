@@ -1,6 +1,6 @@
 use std::collections::BTreeSet;
 
-use crate::types::{ChainConfig, RuntimeStorageConfig};
+use crate::types::{ChainConfig, RuntimeStorageConfig, WitnessSizeBudget};
 use crate::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode};
 use near_chain_configs::test_utils::{TESTING_INIT_BALANCE, TESTING_INIT_STAKE};
 use near_epoch_manager::shard_tracker::ShardTracker;
@@ -12,6 +12,7 @@ use near_pool::{
 use near_primitives::apply::ApplyChunkReason;
 use near_primitives::checked_feature;
 use near_primitives::congestion_info::ExtendedCongestionInfo;
+use near_primitives::receipt::ReceiptPriority;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
 use near_primitives::version::PROTOCOL_VERSION;
@@ -21,8 +22,8 @@ use num_rational::Ratio;
 use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 
 use near_chain_configs::{
-    default_produce_chunk_add_transactions_time_limit, Genesis, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
-    NEAR_BASE,
+    default_produce_chunk_add_transactions_time_limit, Genesis, MutableConfigValue,
+    DEFAULT_GC_NUM_EPOCHS_TO_KEEP, NEAR_BASE,
 };
 use near_crypto::{InMemorySigner, KeyType, Signer};
 use near_o11y::testonly::init_test_logger;
@@ -238,7 +239,9 @@ impl TestEnv {
                 home_dir: PathBuf::from(dir.path()),
                 hot_store_path: PathBuf::from("data"),
                 state_snapshot_subdir: PathBuf::from("state_snapshot"),
+                incremental: false,
             },
+            MutableConfigValue::new(false, "shadow_chunk_validation"),
         );
         let state_roots = get_genesis_state_roots(&store).unwrap().unwrap();
         let genesis_hash = hash(&[0]);
@@ -1633,6 +1636,7 @@ fn prepare_transactions(
     chain: &Chain,
     transaction_groups: &mut dyn TransactionGroupIterator,
     storage_config: RuntimeStorageConfig,
+    witness_size_budget: Option<WitnessSizeBudget>,
 ) -> Result<PreparedTransactions, Error> {
     let shard_id = 0;
     let block = chain.get_block(&env.head.prev_block_hash).unwrap();
@@ -1643,6 +1647,7 @@ fn prepare_transactions(
         PrepareTransactionsChunkContext {
             shard_id,
             gas_limit: env.runtime.genesis_config.gas_limit,
+            witness_size_budget,
         },
         PrepareTransactionsBlockContext {
             next_gas_price: env.runtime.genesis_config.min_gas_price,
@@ -1688,6 +1693,7 @@ fn test_prepare_transactions_storage_proof() {
         &chain,
         &mut PoolIteratorWrapper::new(&mut transaction_pool),
         storage_config,
+        None,
     )
     .unwrap();
 
@@ -1708,6 +1714,7 @@ fn test_prepare_transactions_storage_proof() {
         &chain,
         &mut TransactionGroupIteratorWrapper::new(&proposed_transactions.transactions),
         validator_storage_config,
+        None,
     )
     .unwrap();
 
@@ -1737,6 +1744,7 @@ fn test_prepare_transactions_empty_storage_proof() {
         &chain,
         &mut PoolIteratorWrapper::new(&mut transaction_pool),
         storage_config,
+        None,
     )
     .unwrap();
 
@@ -1757,7 +1765,193 @@ fn test_prepare_transactions_empty_storage_proof() {
         &chain,
         &mut PoolIteratorWrapper::new(&mut transaction_pool),
         validator_storage_config,
+        None,
     );
 
     assert!(validation_result.is_err());
 }
+
+/// Check that the chunk producer stops adding transactions once the witness
+/// size budget is reached, leaves the transactions which don't fit in the pool, and
+/// uses less gas for them when the recent witnesses approach the limit.
+#[test]
+fn test_prepare_transactions_witness_size_budget() {
+    if !checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION) {
+        println!("Test not applicable without StatelessValidation enabled");
+        return;
+    }
+
+    let (env, chain, mut transaction_pool) = get_test_env_with_chain_and_pool();
+    let transactions_count = transaction_pool.len();
+    let storage_config = || RuntimeStorageConfig {
+        state_root: env.state_roots[0],
+        use_flat_storage: true,
+        source: StorageDataSource::Db,
+        state_patch: Default::default(),
+    };
+
+    // The base of the witness alone reaches the soft limit.
+    let budget = WitnessSizeBudget {
+        base_size: 1000,
+        outgoing_receipts_size: 0,
+        soft_limit: 1000,
+        hard_limit: usize::MAX,
+        transactions_gas_limit_percent: 100,
+    };
+    let prepared = prepare_transactions(
+        &env,
+        &chain,
+        &mut PoolIteratorWrapper::new(&mut transaction_pool),
+        storage_config(),
+        Some(budget),
+    )
+    .unwrap();
+    assert!(prepared.transactions.is_empty());
+    assert_eq!(prepared.limited_by, Some(PrepareTransactionsLimit::WitnessSize));
+    assert_eq!(transaction_pool.len(), transactions_count);
+
+    // The first transaction exceeds the hard limit and stays in the pool.
+    let budget = WitnessSizeBudget {
+        base_size: 0,
+        outgoing_receipts_size: 0,
+        soft_limit: usize::MAX,
+        hard_limit: 1,
+        transactions_gas_limit_percent: 100,
+    };
+    let prepared = prepare_transactions(
+        &env,
+        &chain,
+        &mut PoolIteratorWrapper::new(&mut transaction_pool),
+        storage_config(),
+        Some(budget),
+    )
+    .unwrap();
+    assert!(prepared.transactions.is_empty());
+    assert_eq!(prepared.limited_by, Some(PrepareTransactionsLimit::WitnessSize));
+    assert_eq!(transaction_pool.len(), transactions_count);
+
+    // Recent witnesses close to the limit leave no gas for the new transactions.
+    let budget = WitnessSizeBudget {
+        base_size: 0,
+        outgoing_receipts_size: 0,
+        soft_limit: usize::MAX,
+        hard_limit: usize::MAX,
+        transactions_gas_limit_percent: 0,
+    };
+    let prepared = prepare_transactions(
+        &env,
+        &chain,
+        &mut PoolIteratorWrapper::new(&mut transaction_pool),
+        storage_config(),
+        Some(budget),
+    )
+    .unwrap();
+    assert!(prepared.transactions.is_empty());
+    assert_eq!(prepared.limited_by, Some(PrepareTransactionsLimit::Gas));
+    assert_eq!(transaction_pool.len(), transactions_count);
+
+    // A large enough budget doesn't limit the transactions.
+    let budget = WitnessSizeBudget {
+        base_size: 0,
+        outgoing_receipts_size: 0,
+        soft_limit: usize::MAX,
+        hard_limit: usize::MAX,
+        transactions_gas_limit_percent: 100,
+    };
+    let prepared = prepare_transactions(
+        &env,
+        &chain,
+        &mut PoolIteratorWrapper::new(&mut transaction_pool),
+        storage_config(),
+        Some(budget),
+    )
+    .unwrap();
+    assert_eq!(prepared.transactions.len(), transactions_count);
+    assert_eq!(prepared.limited_by, None);
+}
+
+/// Check that the serialized size of the outgoing receipts of the chunk is
+/// charged to the witness size budget and can leave no room for transactions.
+#[test]
+fn test_prepare_transactions_outgoing_receipts_size_budget() {
+    if !checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION) {
+        println!("Test not applicable without StatelessValidation enabled");
+        return;
+    }
+
+    let (env, chain, mut transaction_pool) = get_test_env_with_chain_and_pool();
+    let transactions_count = transaction_pool.len();
+    let storage_config = || RuntimeStorageConfig {
+        state_root: env.state_roots[0],
+        use_flat_storage: true,
+        source: StorageDataSource::Db,
+        state_patch: Default::default(),
+    };
+    let receiver_id: AccountId = "test0".parse().unwrap();
+    let outgoing_receipts: Vec<Receipt> = (0..100)
+        .map(|refund| {
+            Receipt::new_balance_refund(&receiver_id, refund, ReceiptPriority::NoPriority)
+        })
+        .collect();
+    let outgoing_receipts_size = borsh::object_length(&outgoing_receipts).unwrap();
+
+    // The outgoing receipts alone reach the soft limit.
+    let budget = WitnessSizeBudget {
+        base_size: 0,
+        outgoing_receipts_size,
+        soft_limit: outgoing_receipts_size,
+        hard_limit: usize::MAX,
+        transactions_gas_limit_percent: 100,
+    };
+    let prepared = prepare_transactions(
+        &env,
+        &chain,
+        &mut PoolIteratorWrapper::new(&mut transaction_pool),
+        storage_config(),
+        Some(budget),
+    )
+    .unwrap();
+    assert!(prepared.transactions.is_empty());
+    assert_eq!(prepared.limited_by, Some(PrepareTransactionsLimit::WitnessSize));
+    assert_eq!(transaction_pool.len(), transactions_count);
+
+    // Below the soft limit, but no transaction fits next to the outgoing
+    // receipts without exceeding the hard limit.
+    let budget = WitnessSizeBudget {
+        base_size: 0,
+        outgoing_receipts_size,
+        soft_limit: outgoing_receipts_size + 1,
+        hard_limit: outgoing_receipts_size + 1,
+        transactions_gas_limit_percent: 100,
+    };
+    let prepared = prepare_transactions(
+        &env,
+        &chain,
+        &mut PoolIteratorWrapper::new(&mut transaction_pool),
+        storage_config(),
+        Some(budget),
+    )
+    .unwrap();
+    assert!(prepared.transactions.is_empty());
+    assert_eq!(prepared.limited_by, Some(PrepareTransactionsLimit::WitnessSize));
+    assert_eq!(transaction_pool.len(), transactions_count);
+
+    // The same limits without the outgoing receipts let all transactions in.
+    let budget = WitnessSizeBudget {
+        base_size: 0,
+        outgoing_receipts_size: 0,
+        soft_limit: usize::MAX,
+        hard_limit: usize::MAX,
+        transactions_gas_limit_percent: 100,
+    };
+    let prepared = prepare_transactions(
+        &env,
+        &chain,
+        &mut PoolIteratorWrapper::new(&mut transaction_pool),
+        storage_config(),
+        Some(budget),
+    )
+    .unwrap();
+    assert_eq!(prepared.transactions.len(), transactions_count);
+    assert_eq!(prepared.limited_by, None);
+}
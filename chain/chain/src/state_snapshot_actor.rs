@@ -63,7 +63,11 @@ impl StateSnapshotActor {
 
         // We don't need to acquire any locks on flat storage or snapshot.
         let DeleteAndMaybeCreateSnapshotRequest { create_snapshot_request } = msg;
-        self.tries.delete_state_snapshot();
+        // An incremental snapshot is moved to the new block instead of being
+        // recreated, so it's only deleted if no new snapshot is requested.
+        if create_snapshot_request.is_none() || !self.tries.state_snapshot_config().incremental {
+            self.tries.delete_state_snapshot();
+        }
 
         // Optionally send a create_snapshot_request after deletion
         if let Some(create_snapshot_request) = create_snapshot_request {
@@ -20,7 +20,7 @@ impl Chain {
     pub(crate) fn garbage_collect_state_transition_data(&self, block: &Block) -> Result<(), Error> {
         let protocol_version =
             self.epoch_manager.get_epoch_protocol_version(block.header().epoch_id())?;
-        if cfg!(feature = "shadow_chunk_validation")
+        if self.shadow_chunk_validation.get()
             || checked_feature!("stable", StatelessValidationV0, protocol_version)
         {
             let chain_store = self.chain_store();
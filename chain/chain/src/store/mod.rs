@@ -16,7 +16,6 @@ use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::receipt::Receipt;
-use near_primitives::shard_layout::account_id_to_shard_id;
 use near_primitives::shard_layout::{get_block_shard_uid, ShardLayout, ShardUId};
 use near_primitives::sharding::{
     ChunkHash, EncodedShardChunk, PartialEncodedChunk, ReceiptProof, ShardChunk, ShardChunkHeader,
@@ -55,6 +54,7 @@ use near_store::db::{StoreStatistics, STATE_SYNC_DUMP_KEY};
 use std::sync::Arc;
 
 mod latest_witnesses;
+mod shadow_witnesses;
 
 /// lru cache size
 #[cfg(not(feature = "no_cache"))]
@@ -382,8 +382,7 @@ fn filter_incoming_receipts_for_shard(
         let mut filtered_receipts = vec![];
         let ReceiptProof(receipts, shard_proof) = receipt_proof.clone();
         for receipt in receipts {
-            let receiver_shard_id =
-                account_id_to_shard_id(receipt.receiver_id(), target_shard_layout);
+            let receiver_shard_id = receipt.receiver_shard_id(target_shard_layout);
             if receiver_shard_id == target_shard_id {
                 tracing::trace!(target: "chain", receipt_id=?receipt.receipt_id(), "including receipt");
                 filtered_receipts.push(receipt);
@@ -688,9 +687,7 @@ impl ChainStore {
         shard_layout: &ShardLayout,
         shard_id: ShardId,
     ) -> Result<(), Error> {
-        receipts.retain(|receipt| {
-            account_id_to_shard_id(receipt.receiver_id(), &shard_layout) == shard_id
-        });
+        receipts.retain(|receipt| receipt.receiver_shard_id(&shard_layout) == shard_id);
         Ok(())
     }
 
@@ -2812,13 +2809,16 @@ mod tests {
     use near_async::time::Clock;
     use std::sync::Arc;
 
+    use super::ChainStore;
     use crate::test_utils::get_chain;
     use near_primitives::errors::InvalidTxError;
     use near_primitives::hash::hash;
+    use near_primitives::stateless_validation::ChunkStateWitness;
     use near_primitives::test_utils::create_test_signer;
     use near_primitives::test_utils::TestBlockBuilder;
     use near_primitives::types::EpochId;
     use near_primitives::utils::index_to_bytes;
+    use near_store::test_utils::create_test_store;
 
     #[test]
     fn test_tx_validity_long_fork() {
@@ -3012,4 +3012,26 @@ mod tests {
         assert_ne!(block_hash, block_hash1);
         assert_ne!(epoch_id_to_hash, epoch_id_to_hash1);
     }
+
+    #[test]
+    fn test_shadow_state_witnesses_retention() {
+        let chain_store = ChainStore::new(create_test_store(), 0, true);
+        let save = |height, shard_id| {
+            let witness = ChunkStateWitness::new_dummy(height, shard_id, hash(&[height as u8]));
+            chain_store.save_shadow_state_witness(&witness, 10).unwrap();
+        };
+        for height in 1..=20 {
+            save(height, 0);
+            save(height, 1);
+        }
+
+        // Only the witnesses of the last 10 blocks are kept.
+        assert_eq!(chain_store.get_shadow_state_witnesses(None, None).unwrap().len(), 22);
+        assert!(chain_store.get_shadow_state_witnesses(Some(9), None).unwrap().is_empty());
+        let witnesses = chain_store.get_shadow_state_witnesses(Some(15), Some(1)).unwrap();
+        assert_eq!(witnesses.len(), 1);
+        assert_eq!(witnesses[0].chunk_header.height_created(), 15);
+        assert_eq!(witnesses[0].chunk_header.shard_id(), 1);
+        assert_eq!(chain_store.get_shadow_state_witnesses(None, Some(0)).unwrap().len(), 11);
+    }
 }
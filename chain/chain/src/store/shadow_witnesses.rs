@@ -0,0 +1,93 @@
+//! This module is responsible for storing the ChunkStateWitnesses produced by
+//! shadow validation, so that they can be replayed offline and their sizes
+//! analysed. Unlike the latest witnesses, there is exactly one witness per
+//! chunk, and witnesses are kept for a configured number of blocks.
+
+use near_primitives::stateless_validation::ChunkStateWitness;
+use near_primitives::types::{BlockHeight, BlockHeightDelta, ShardId};
+use near_store::DBCol;
+
+use crate::metrics;
+use crate::ChainStoreAccess;
+
+use super::ChainStore;
+
+/// Returns the key of the witness in `DBCol::ShadowStateWitness`. The key starts
+/// with big-endian height and shard_id, so that witnesses can be queried by key
+/// prefix and pruned by height.
+fn shadow_witness_key(witness: &ChunkStateWitness) -> [u8; 48] {
+    let mut result = [0u8; 48];
+    result[..8].copy_from_slice(&witness.chunk_header.height_created().to_be_bytes());
+    result[8..16].copy_from_slice(&witness.chunk_header.shard_id().to_be_bytes());
+    result[16..].copy_from_slice(witness.chunk_header.chunk_hash().0.as_bytes());
+    result
+}
+
+impl ChainStore {
+    /// Saves a `ChunkStateWitness` produced by shadow validation to the database,
+    /// and deletes the witnesses of chunks created more than `retention_blocks`
+    /// blocks before it.
+    pub fn save_shadow_state_witness(
+        &self,
+        witness: &ChunkStateWitness,
+        retention_blocks: BlockHeightDelta,
+    ) -> Result<(), std::io::Error> {
+        let height = witness.chunk_header.height_created();
+        let serialized_witness = borsh::to_vec(witness)?;
+        let mut store_update = self.store().store_update();
+        store_update.set(
+            DBCol::ShadowStateWitness,
+            &shadow_witness_key(witness),
+            &serialized_witness,
+        );
+        if let Some(lowest_kept_height) = height.checked_sub(retention_blocks) {
+            store_update.delete_range(
+                DBCol::ShadowStateWitness,
+                &0u64.to_be_bytes(),
+                &lowest_kept_height.to_be_bytes(),
+            );
+        }
+        store_update.commit()?;
+
+        metrics::SAVED_SHADOW_WITNESS_SIZE
+            .with_label_values(&[witness.chunk_header.shard_id().to_string().as_str()])
+            .observe(serialized_witness.len() as f64);
+        tracing::debug!(
+            target: "client",
+            height,
+            shard_id = witness.chunk_header.shard_id(),
+            size = serialized_witness.len(),
+            "Saved shadow witness",
+        );
+        Ok(())
+    }
+
+    /// Fetch the shadow witnesses with the given height, and optionally shard_id.
+    pub fn get_shadow_state_witnesses(
+        &self,
+        height: Option<BlockHeight>,
+        shard_id: Option<ShardId>,
+    ) -> Result<Vec<ChunkStateWitness>, std::io::Error> {
+        let mut key_prefix: Vec<u8> = Vec::new();
+        if let Some(h) = height {
+            key_prefix.extend_from_slice(&h.to_be_bytes());
+
+            if let Some(id) = shard_id {
+                key_prefix.extend_from_slice(&id.to_be_bytes());
+            }
+        }
+
+        let mut result = Vec::new();
+        for read_result in self
+            .store()
+            .iter_prefix_ser::<ChunkStateWitness>(DBCol::ShadowStateWitness, &key_prefix)
+        {
+            let (_, witness) = read_result?;
+            if shard_id.is_some_and(|id| witness.chunk_header.shard_id() != id) {
+                continue;
+            }
+            result.push(witness);
+        }
+        Ok(result)
+    }
+}
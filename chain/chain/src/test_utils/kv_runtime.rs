@@ -45,7 +45,7 @@ use near_primitives::types::{
 use near_primitives::version::{ProtocolFeature, ProtocolVersion, PROTOCOL_VERSION};
 use near_primitives::views::{
     AccessKeyInfoView, AccessKeyList, CallResult, ContractCodeView, EpochValidatorInfo,
-    QueryRequest, QueryResponse, QueryResponseKind, ViewStateResult,
+    PromiseYieldView, QueryRequest, QueryResponse, QueryResponseKind, ViewStateResult,
 };
 use near_primitives::{checked_feature, shard_layout};
 use near_store::test_utils::TestTriesBuilder;
@@ -957,6 +957,13 @@ impl EpochManagerAdapter for MockEpochManager {
         Ok(true)
     }
 
+    fn verify_chunk_endorsements_batch(
+        &self,
+        endorsements: &[(&ShardChunkHeader, &ChunkEndorsement)],
+    ) -> Vec<Result<bool, Error>> {
+        endorsements.iter().map(|_| Ok(true)).collect()
+    }
+
     fn verify_chunk_state_witness_signature(
         &self,
         _signed_witness: &SignedEncodedChunkStateWitness,
@@ -1104,9 +1111,7 @@ impl RuntimeAdapter for KeyValueRuntime {
         while let Some(iter) = transaction_groups.next() {
             res.push(iter.next().unwrap());
         }
-        let storage_proof = if checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION)
-            || cfg!(feature = "shadow_chunk_validation")
-        {
+        let storage_proof = if checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION) {
             Some(Default::default())
         } else {
             None
@@ -1260,9 +1265,7 @@ impl RuntimeAdapter for KeyValueRuntime {
         let state_root = hash(&data);
         self.state.write().unwrap().insert(state_root, state);
         self.state_size.write().unwrap().insert(state_root, state_size);
-        let storage_proof = if checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION)
-            || cfg!(feature = "shadow_chunk_validation")
-        {
+        let storage_proof = if checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION) {
             Some(Default::default())
         } else {
             None
@@ -1359,6 +1362,15 @@ impl RuntimeAdapter for KeyValueRuntime {
                 block_height,
                 block_hash: *block_hash,
             }),
+            QueryRequest::ViewPromiseYield { .. } => Ok(QueryResponse {
+                kind: QueryResponseKind::ViewPromiseYield(PromiseYieldView {
+                    pending: false,
+                    expires_at: None,
+                    remaining_blocks: None,
+                }),
+                block_height,
+                block_hash: *block_hash,
+            }),
         }
     }
 
@@ -1457,7 +1469,11 @@ impl RuntimeAdapter for KeyValueRuntime {
         }
     }
 
-    fn get_protocol_config(&self, _epoch_id: &EpochId) -> Result<ProtocolConfig, Error> {
+    fn get_protocol_config(
+        &self,
+        _epoch_id: &EpochId,
+        _shard_state: Option<(ShardId, StateRoot)>,
+    ) -> Result<ProtocolConfig, Error> {
         unreachable!("get_protocol_config should not be called in KeyValueRuntime");
     }
 
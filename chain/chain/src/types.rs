@@ -224,6 +224,9 @@ pub struct ChainConfig {
     pub background_migration_threads: usize,
     /// The resharding configuration.
     pub resharding_config: MutableConfigValue<ReshardingConfig>,
+    /// Whether every chunk is shadow validated, which requires state witnesses
+    /// to be produced for all chunks.
+    pub shadow_chunk_validation: MutableConfigValue<bool>,
 }
 
 impl ChainConfig {
@@ -235,6 +238,7 @@ impl ChainConfig {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            shadow_chunk_validation: MutableConfigValue::new(false, "shadow_chunk_validation"),
         }
     }
 }
@@ -343,6 +347,7 @@ pub enum PrepareTransactionsLimit {
     Size,
     Time,
     ReceiptCount,
+    WitnessSize,
 }
 
 pub struct PrepareTransactionsBlockContext {
@@ -366,11 +371,58 @@ impl From<&Block> for PrepareTransactionsBlockContext {
 pub struct PrepareTransactionsChunkContext {
     pub shard_id: ShardId,
     pub gas_limit: Gas,
+    /// Budget for the size of the state witness of the produced chunk. Only set
+    /// by the chunk producer, the validators don't limit the transactions by it.
+    pub witness_size_budget: Option<WitnessSizeBudget>,
 }
 
 impl From<&ShardChunkHeader> for PrepareTransactionsChunkContext {
     fn from(header: &ShardChunkHeader) -> Self {
-        Self { shard_id: header.shard_id(), gas_limit: header.gas_limit() }
+        Self {
+            shard_id: header.shard_id(),
+            gas_limit: header.gas_limit(),
+            witness_size_budget: None,
+        }
+    }
+}
+
+/// Chunk producer limits the transactions included in a chunk so that the
+/// uncompressed state witness of the chunk stays within these sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WitnessSizeBudget {
+    /// Size of the parts of the witness which don't depend on the new
+    /// transactions: the state transitions, source receipt proofs and the
+    /// transactions of the previous chunk.
+    pub base_size: usize,
+    /// Borsh-serialized size in bytes of the outgoing receipts of the produced
+    /// chunk. They are included in the chunk regardless of the budget, so
+    /// they only leave less room for the new transactions.
+    pub outgoing_receipts_size: usize,
+    /// No more transactions are added once the witness reaches this size.
+    pub soft_limit: usize,
+    /// A transaction which would grow the witness past this size is left in the
+    /// pool instead of being added.
+    pub hard_limit: usize,
+    /// Percentage of the transactions gas limit available to the new
+    /// transactions. Lowered when the recent witnesses of the shard approach
+    /// the size limit, because the receipts of the new transactions grow the
+    /// witnesses of the following chunks.
+    pub transactions_gas_limit_percent: u64,
+}
+
+impl WitnessSizeBudget {
+    /// Estimates the size of the witness with transactions of the given total
+    /// size, validated with a storage proof of the given size.
+    pub fn witness_size(&self, transactions_size: u64, storage_proof_size: usize) -> usize {
+        self.base_size
+            .saturating_add(self.outgoing_receipts_size)
+            .saturating_add(transactions_size as usize)
+            .saturating_add(storage_proof_size)
+    }
+
+    /// Reduces the gas limit for the new transactions to `transactions_gas_limit_percent`.
+    pub fn transactions_gas_limit(&self, gas_limit: Gas) -> Gas {
+        (gas_limit as u128 * self.transactions_gas_limit_percent.min(100) as u128 / 100) as Gas
     }
 }
 
@@ -406,8 +458,9 @@ pub trait RuntimeAdapter: Send + Sync {
     fn get_flat_storage_manager(&self) -> FlatStorageManager;
 
     /// Validates a given signed transaction.
-    /// If the state root is given, then the verification will use the account. Otherwise it will
-    /// only validate the transaction math, limits and signatures.
+    /// If the state root is given, then the verification will use the account and the runtime
+    /// config overrides stored in the state of the signer's shard. Otherwise it will only
+    /// validate the transaction math, limits and signatures, none of which can be overridden.
     /// Returns an option of `InvalidTxError`, it contains `Some(InvalidTxError)` if there is
     /// a validation error, or `None` in case the transaction succeeded.
     /// Throws an `Error` with `ErrorKind::StorageError` in case the runtime throws
@@ -524,7 +577,14 @@ pub trait RuntimeAdapter: Send + Sync {
         state_root: &StateRoot,
     ) -> bool;
 
-    fn get_protocol_config(&self, epoch_id: &EpochId) -> Result<ProtocolConfig, Error>;
+    /// Returns the protocol config of the epoch. Given the state of a shard,
+    /// the runtime config overrides it stores for the epoch are applied to the
+    /// runtime config.
+    fn get_protocol_config(
+        &self,
+        epoch_id: &EpochId,
+        shard_state: Option<(ShardId, StateRoot)>,
+    ) -> Result<ProtocolConfig, Error>;
 }
 
 /// The last known / checked height and time when we have processed it.
@@ -10,7 +10,7 @@ use near_primitives::{
     epoch_manager::RngSeed,
     sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader},
     transaction::SignedTransaction,
-    types::{AccountId, ShardId},
+    types::{AccountId, BlockHeight, ShardId},
 };
 
 #[derive(Message, Debug)]
@@ -69,6 +69,13 @@ impl ShardedTransactionPool {
         }
     }
 
+    /// Removes the transactions that expired at the given height from the pools of all shards.
+    pub fn remove_expired_transactions(&mut self, prev_block_height: BlockHeight) {
+        for pool in self.tx_pools.values_mut() {
+            pool.remove_expired_transactions(prev_block_height);
+        }
+    }
+
     /// Computes a deterministic random seed for given `shard_id`.
     /// This seed is used to randomize the transaction pool.
     /// For better security we want the seed to different in each shard.
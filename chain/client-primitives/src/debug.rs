@@ -5,7 +5,7 @@ use near_async::time::Utc;
 use near_primitives::types::EpochId;
 use near_primitives::views::{
     CatchupStatusView, ChainProcessingInfo, EpochValidatorInfo, RequestedStatePartsView,
-    SyncStatusView,
+    StateSyncProgressView, SyncStatusView,
 };
 use near_primitives::{
     block_header::ApprovalInner,
@@ -185,6 +185,8 @@ pub enum DebugStatus {
     ChainProcessingStatus,
     // The state parts already requested.
     RequestedStateParts,
+    // Detailed progress of state sync and catchup, per shard.
+    StateSyncProgress,
 }
 
 impl actix::Message for DebugStatus {
@@ -206,4 +208,6 @@ pub enum DebugStatusResponse {
     ChainProcessingStatus(ChainProcessingInfo),
     // The state parts already requested.
     RequestedStateParts(Vec<RequestedStatePartsView>),
+    // Progress of state sync, followed by progress of every catchup.
+    StateSyncProgress(Vec<StateSyncProgressView>),
 }
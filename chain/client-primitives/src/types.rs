@@ -5,15 +5,16 @@ use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::network::PeerId;
 use near_primitives::sharding::ChunkHash;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockReference, EpochId, EpochReference, MaybeBlockId, ShardId,
-    TransactionOrReceiptId,
+    AccountId, BlockHeight, BlockHeightDelta, BlockReference, EpochId, EpochReference,
+    MaybeBlockId, ShardId, TransactionOrReceiptId,
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, DownloadStatusView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
-    GasPriceView, LightClientBlockLiteView, LightClientBlockView, MaintenanceWindowsView,
-    QueryRequest, QueryResponse, ReceiptView, ShardSyncDownloadView, SplitStorageInfoView,
-    StateChangesKindsView, StateChangesRequestView, StateChangesView, SyncStatusView, TxStatusView,
+    BlockView, ChunkEndorsementsView, ChunkView, DownloadStatusView, EpochValidatorInfo,
+    ExecutionOutcomeWithIdView, GasPriceView, LightClientBlockLiteView, LightClientBlockView,
+    MaintenanceWindowsView, MemTrieStatusView, QueryRequest, QueryResponse, ReceiptView,
+    ShardSyncDownloadView, SplitStorageInfoView, StateChangesKindsView, StateChangesRequestView,
+    StateChangesView, StateSyncPhaseView, SyncStatusView, TxStatusView, WitnessStatsView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 use std::collections::HashMap;
@@ -139,6 +140,23 @@ impl ToString for ShardSyncStatus {
     }
 }
 
+impl From<&ShardSyncStatus> for StateSyncPhaseView {
+    fn from(status: &ShardSyncStatus) -> Self {
+        match status {
+            ShardSyncStatus::StateDownloadHeader => StateSyncPhaseView::Header,
+            ShardSyncStatus::StateDownloadParts => StateSyncPhaseView::Parts,
+            ShardSyncStatus::StateApplyScheduling | ShardSyncStatus::StateApplyComplete => {
+                StateSyncPhaseView::Apply
+            }
+            ShardSyncStatus::StateApplyFinalizing => StateSyncPhaseView::Finalize,
+            ShardSyncStatus::ReshardingScheduling | ShardSyncStatus::ReshardingApplying => {
+                StateSyncPhaseView::Resharding
+            }
+            ShardSyncStatus::StateSyncDone => StateSyncPhaseView::Done,
+        }
+    }
+}
+
 impl From<&DownloadStatus> for DownloadStatusView {
     fn from(status: &DownloadStatus) -> Self {
         DownloadStatusView { done: status.done, error: status.error }
@@ -456,6 +474,8 @@ pub enum GetChunkError {
     InvalidShardId { shard_id: u64 },
     #[error("Chunk with hash {chunk_hash:?} has never been observed on this node")]
     UnknownChunk { chunk_hash: ChunkHash },
+    #[error("The node does not archive the history of shard {shard_id}")]
+    UnavailableShard { shard_id: u64 },
     // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
     // expected cases, we cannot statically guarantee that no other errors will be returned
     // in the future.
@@ -810,6 +830,8 @@ pub enum GetStateChangesError {
     UnknownBlock { error_message: String },
     #[error("There are no fully synchronized blocks yet")]
     NotSyncedYet,
+    #[error("The node does not archive the history of shard {shard_id}")]
+    UnavailableShard { shard_id: u64 },
     // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
     // expected cases, we cannot statically guarantee that no other errors will be returned
     // in the future.
@@ -1134,10 +1156,83 @@ impl From<std::io::Error> for GetSplitStorageInfoError {
     }
 }
 
+#[derive(Debug)]
+pub struct GetChunkEndorsements {
+    /// Number of the latest blocks to return the endorsements of.
+    pub num_blocks: BlockHeightDelta,
+}
+
+impl Message for GetChunkEndorsements {
+    type Result = Result<ChunkEndorsementsView, GetChunkEndorsementsError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetChunkEndorsementsError {
+    #[error("IO Error: {0}")]
+    IOError(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetChunkEndorsementsError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::IOErr(error) => Self::IOError(error.to_string()),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GetMemTrieStatus {}
+
+impl Message for GetMemTrieStatus {
+    type Result = Result<MemTrieStatusView, GetMemTrieStatusError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetMemTrieStatusError {
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+#[derive(Debug)]
+pub struct GetWitnessStats {
+    /// Only return the statistics of this shard, all shards if not set.
+    pub shard_id: Option<ShardId>,
+}
+
+impl Message for GetWitnessStats {
+    type Result = Result<WitnessStatsView, GetWitnessStatsError>;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetWitnessStatsError {
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
 #[cfg(feature = "sandbox")]
 #[derive(Debug)]
 pub enum SandboxMessage {
-    SandboxPatchState(Vec<near_primitives::state_record::StateRecord>),
+    SandboxPatchState {
+        records: Vec<near_primitives::state_record::StateRecord>,
+        /// Height of the block to apply the patch with, the next block if not set.
+        block_height: Option<near_primitives::types::BlockHeight>,
+        runtime_config: Option<near_primitives::sandbox::SandboxRuntimeConfigPatch>,
+    },
     SandboxPatchStateStatus,
     SandboxFastForward(near_primitives::types::BlockHeightDelta),
     SandboxFastForwardStatus,
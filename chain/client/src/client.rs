@@ -8,6 +8,8 @@ use crate::debug::PRODUCTION_TIMES_CACHE_SIZE;
 use crate::stateless_validation::chunk_endorsement_tracker::ChunkEndorsementTracker;
 use crate::stateless_validation::chunk_validator::ChunkValidator;
 use crate::stateless_validation::partial_witness::partial_witness_actor::PartialWitnessSenderForClient;
+use crate::stateless_validation::transaction_limiter::TransactionLimiter;
+use crate::stateless_validation::StateWitnessBaseParts;
 use crate::sync::adapter::SyncShardInfo;
 use crate::sync::block::BlockSync;
 use crate::sync::epoch::EpochSync;
@@ -30,11 +32,11 @@ use near_chain::orphan::OrphanMissingChunks;
 use near_chain::resharding::ReshardingRequest;
 use near_chain::state_snapshot_actor::SnapshotCallbacks;
 use near_chain::test_utils::format_hash;
-use near_chain::types::PrepareTransactionsChunkContext;
 use near_chain::types::{
     ChainConfig, LatestKnown, PreparedTransactions, RuntimeAdapter, RuntimeStorageConfig,
     StorageDataSource,
 };
+use near_chain::types::{PrepareTransactionsChunkContext, WitnessSizeBudget};
 use near_chain::{
     BlockProcessingArtifact, BlockStatus, Chain, ChainGenesis, ChainStoreAccess, Doomslug,
     DoomslugThresholdMode, Provenance,
@@ -64,7 +66,7 @@ use near_primitives::block::{Approval, ApprovalInner, ApprovalMessage, Block, Bl
 use near_primitives::block_header::ApprovalType;
 use near_primitives::challenge::{Challenge, ChallengeBody, PartialState};
 use near_primitives::epoch_manager::RngSeed;
-use near_primitives::errors::EpochError;
+use near_primitives::errors::{EpochError, InvalidTxError};
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, MerklePath, PartialMerkleTree};
 use near_primitives::network::PeerId;
@@ -73,6 +75,7 @@ use near_primitives::sharding::StateSyncInfo;
 use near_primitives::sharding::{
     ChunkHash, EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader, ShardInfo,
 };
+use near_primitives::stateless_validation::StateWitnessCompression;
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{AccountId, ApprovalStake, BlockHeight, EpochId, NumBlocks, ShardId};
@@ -80,7 +83,7 @@ use near_primitives::unwrap_or_return;
 use near_primitives::utils::MaybeValidated;
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{CatchupStatusView, DroppedReason};
+use near_primitives::views::{CatchupStatusView, DroppedReason, StateSyncProgressView};
 use near_store::ShardUId;
 use reed_solomon_erasure::galois_8::ReedSolomon;
 use std::cmp::max;
@@ -195,6 +198,11 @@ pub struct Client {
     pub partial_witness_adapter: PartialWitnessSenderForClient,
     // Optional value used for the Chunk Distribution Network Feature.
     chunk_distribution_network: Option<ChunkDistributionNetwork>,
+    /// Compression used to encode and decode state witnesses, loaded from the config.
+    pub witness_compression: StateWitnessCompression,
+    /// Reduces the transactions included in the produced chunks when their
+    /// state witnesses approach the size limit.
+    transaction_limiter: TransactionLimiter,
 }
 
 impl Client {
@@ -204,6 +212,7 @@ impl Client {
         self.config
             .produce_chunk_add_transactions_time_limit
             .update(update_client_config.produce_chunk_add_transactions_time_limit);
+        self.config.shadow_chunk_validation.update(update_client_config.shadow_chunk_validation);
     }
 }
 
@@ -236,6 +245,9 @@ pub struct ProduceChunkResult {
     pub encoded_chunk_parts_paths: Vec<MerklePath>,
     pub receipts: Vec<Receipt>,
     pub transactions_storage_proof: Option<PartialState>,
+    /// Parts of the state witness collected when producing the chunk, reused
+    /// when the witness is sent.
+    pub state_witness_base_parts: Option<StateWitnessBaseParts>,
 }
 
 impl Client {
@@ -265,6 +277,7 @@ impl Client {
             save_trie_changes: config.save_trie_changes,
             background_migration_threads: config.client_background_migration_threads,
             resharding_config: config.resharding_config.clone(),
+            shadow_chunk_validation: config.shadow_chunk_validation.clone(),
         };
         let chain = Chain::new(
             clock.clone(),
@@ -313,6 +326,7 @@ impl Client {
             config.header_sync_stall_ban_timeout,
             config.header_sync_expected_height_per_second,
             config.expected_shutdown.clone(),
+            config.header_sync_max_peers_per_request,
         );
         let block_sync = BlockSync::new(
             clock.clone(),
@@ -342,6 +356,7 @@ impl Client {
             config.state_sync_timeout,
             &config.chain_id,
             &config.state_sync.sync,
+            &config.state_sync.catchup,
             false,
         );
         let num_block_producer_seats = config.num_block_producer_seats as usize;
@@ -367,9 +382,15 @@ impl Client {
             runtime_adapter.clone(),
             chunk_endorsement_tracker.clone(),
             config.orphan_state_witness_pool_size,
+            config.orphan_state_witness_pool_size_per_shard,
             async_computation_spawner,
+            config.chunk_validation_max_concurrency_per_shard,
+            config.max_state_witnesses_per_producer_per_height,
         );
         let chunk_distribution_network = ChunkDistributionNetwork::from_config(&config);
+        let witness_compression = config.witness_compression.load().map_err(|err| {
+            Error::Other(format!("Failed to load the state witness compression dictionary: {err}"))
+        })?;
         Ok(Self {
             #[cfg(feature = "test_features")]
             adv_produce_blocks: None,
@@ -414,6 +435,8 @@ impl Client {
             chunk_endorsement_tracker,
             partial_witness_adapter,
             chunk_distribution_network,
+            witness_compression,
+            transaction_limiter: TransactionLimiter::new(),
         })
     }
 
@@ -898,8 +921,27 @@ impl Client {
             .get_chunk_extra(&prev_block_hash, &shard_uid)
             .map_err(|err| Error::ChunkProducer(format!("No chunk extra available: {}", err)))?;
 
-        let prepared_transactions =
-            self.prepare_transactions(shard_uid, prev_block, chunk_extra.as_ref())?;
+        let outgoing_receipts = self.chain.get_outgoing_receipts_for_shard(
+            prev_block_hash,
+            shard_id,
+            last_header.height_included(),
+        )?;
+        let (witness_size_budget, state_witness_base_parts) = match self.state_witness_size_budget(
+            epoch_id,
+            prev_block.header(),
+            &last_header,
+            shard_id,
+            &outgoing_receipts,
+        )? {
+            Some((budget, base_parts)) => (Some(budget), Some(base_parts)),
+            None => (None, None),
+        };
+        let prepared_transactions = self.prepare_transactions(
+            shard_uid,
+            prev_block,
+            chunk_extra.as_ref(),
+            witness_size_budget,
+        )?;
         #[cfg(feature = "test_features")]
         let prepared_transactions = Self::maybe_insert_invalid_transaction(
             prepared_transactions,
@@ -908,11 +950,6 @@ impl Client {
         );
         let num_filtered_transactions = prepared_transactions.transactions.len();
         let (tx_root, _) = merklize(&prepared_transactions.transactions);
-        let outgoing_receipts = self.chain.get_outgoing_receipts_for_shard(
-            prev_block_hash,
-            shard_id,
-            last_header.height_included(),
-        )?;
 
         let outgoing_receipts_root = self.calculate_receipts_root(epoch_id, &outgoing_receipts)?;
         let protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
@@ -972,6 +1009,7 @@ impl Client {
             encoded_chunk_parts_paths: merkle_paths,
             receipts: outgoing_receipts,
             transactions_storage_proof: prepared_transactions.storage_proof,
+            state_witness_base_parts,
         }))
     }
 
@@ -1028,6 +1066,7 @@ impl Client {
         shard_uid: ShardUId,
         prev_block: &Block,
         chunk_extra: &ChunkExtra,
+        witness_size_budget: Option<WitnessSizeBudget>,
     ) -> Result<PreparedTransactions, Error> {
         let Self { chain, sharded_tx_pool, runtime_adapter: runtime, .. } = self;
         let shard_id = shard_uid.shard_id as ShardId;
@@ -1043,7 +1082,11 @@ impl Client {
             };
             runtime.prepare_transactions(
                 storage_config,
-                PrepareTransactionsChunkContext { shard_id, gas_limit: chunk_extra.gas_limit() },
+                PrepareTransactionsChunkContext {
+                    shard_id,
+                    gas_limit: chunk_extra.gas_limit(),
+                    witness_size_budget,
+                },
                 prev_block.into(),
                 &mut iter,
                 &mut chain.transaction_validity_check(prev_block.header().clone()),
@@ -1741,6 +1784,7 @@ impl Client {
                 }
             }
         };
+        self.sharded_tx_pool.remove_expired_transactions(block.header().height());
         true
     }
 
@@ -1809,6 +1853,7 @@ impl Client {
                         &last_header,
                         &shard_chunk,
                         result.transactions_storage_proof,
+                        result.state_witness_base_parts,
                     ) {
                         tracing::error!(target: "client", ?err, "Failed to send chunk state witness to chunk validators");
                     }
@@ -2238,6 +2283,10 @@ impl Client {
             debug!(target: "client", ?tx, "Invalid tx: expired or from a different fork");
             return Ok(ProcessTxResponse::InvalidTx(e));
         }
+        if tx.transaction.is_expired(cur_block_header.height()) {
+            debug!(target: "client", ?tx, "Invalid tx: past its valid until height");
+            return Ok(ProcessTxResponse::InvalidTx(InvalidTxError::Expired));
+        }
         let gas_price = cur_block_header.next_gas_price();
         let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&head.last_block_hash)?;
 
@@ -2398,6 +2447,7 @@ impl Client {
                             state_sync_timeout,
                             &self.config.chain_id,
                             &self.config.state_sync.sync,
+                            &self.config.state_sync.catchup,
                             true,
                         ),
                         shards_to_split,
@@ -2492,6 +2542,14 @@ impl Client {
             }
         }
 
+        let num_catchup_shards = self
+            .catchup_state_syncs
+            .values()
+            .flat_map(|(_, shard_sync, _)| shard_sync.values())
+            .filter(|shard_sync| shard_sync.status != ShardSyncStatus::StateSyncDone)
+            .count();
+        metrics::STATE_SYNC_CATCHUP_SHARDS.set(num_catchup_shards as i64);
+
         Ok(())
     }
 
@@ -2738,6 +2796,23 @@ impl Client {
         }
         Ok(ret)
     }
+
+    /// Reports the progress of state sync, if the node is state syncing.
+    pub fn get_node_state_sync_progress(&self) -> Option<StateSyncProgressView> {
+        let SyncStatus::StateSync(status) = &self.sync_status else {
+            return None;
+        };
+        Some(self.state_sync.get_progress_view(status.sync_hash, &status.sync_status, false))
+    }
+
+    /// Reports the progress of state sync, if the node is state syncing, and of every catchup.
+    pub fn get_state_sync_progress(&self) -> Vec<StateSyncProgressView> {
+        let mut ret: Vec<_> = self.get_node_state_sync_progress().into_iter().collect();
+        for (sync_hash, (state_sync, shard_sync_state, _)) in self.catchup_state_syncs.iter() {
+            ret.push(state_sync.get_progress_view(*sync_hash, shard_sync_state, true));
+        }
+        ret
+    }
 }
 
 impl Drop for Client {
@@ -22,7 +22,9 @@ use near_async::actix_wrapper::ActixWrapper;
 use near_async::futures::{
     ActixArbiterHandleFutureSpawner, DelayedActionRunner, DelayedActionRunnerExt, FutureSpawner,
 };
-use near_async::messaging::{self, CanSend, Handler, IntoMultiSender, LateBoundSender, Sender};
+use near_async::messaging::{
+    self, CanSend, Handler, HandlerWithContext, IntoMultiSender, LateBoundSender, Sender,
+};
 use near_async::time::{Clock, Utc};
 use near_async::time::{Duration, Instant};
 use near_async::{MultiSend, MultiSendMessage, MultiSenderFrom};
@@ -47,8 +49,9 @@ use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::client::ShardsManagerResponse;
 use near_chunks::logic::get_shards_cares_about_this_or_next_epoch;
 use near_client_primitives::types::{
-    Error, GetClientConfig, GetClientConfigError, GetNetworkInfo, NetworkInfoResponse,
-    StateSyncStatus, Status, StatusError, StatusSyncInfo, SyncStatus,
+    Error, GetClientConfig, GetClientConfigError, GetNetworkInfo, GetWitnessStats,
+    GetWitnessStatsError, NetworkInfoResponse, StateSyncStatus, Status, StatusError,
+    StatusSyncInfo, SyncStatus,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::{EpochManagerAdapter, RngSeed};
@@ -67,12 +70,13 @@ use near_primitives::block::Tip;
 use near_primitives::block_header::ApprovalType;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
+use near_primitives::stateless_validation::ChunkEndorsement;
 use near_primitives::types::{BlockHeight, EpochId};
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::MaybeValidated;
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{DetailedDebugStatus, ValidatorInfo};
+use near_primitives::views::{DetailedDebugStatus, ValidatorInfo, WitnessStatsView};
 #[cfg(feature = "test_features")]
 use near_store::DBCol;
 use near_store::ShardUId;
@@ -90,6 +94,9 @@ const STATUS_WAIT_TIME_MULTIPLIER: i32 = 10;
 /// `max_block_production_time` times this multiplier is how long we wait before rebroadcasting
 /// the current `head`
 const HEAD_STALL_MULTIPLIER: u32 = 4;
+/// Number of chunk endorsements after which a batch is verified without waiting
+/// for the end of `chunk_endorsement_batch_window`.
+const MAX_CHUNK_ENDORSEMENT_BATCH_SIZE: usize = 64;
 
 pub type ClientActor = ActixWrapper<ClientActorInner>;
 
@@ -274,6 +281,12 @@ pub struct ClientActorInner {
 
     /// Manages updating the config.
     config_updater: Option<ConfigUpdater>,
+
+    /// Chunk endorsements received within the current batching window, whose
+    /// signatures are verified together once the window ends.
+    pending_chunk_endorsements: Vec<ChunkEndorsement>,
+    /// End of the current batching window of chunk endorsements.
+    chunk_endorsements_batch_deadline: Option<Instant>,
 }
 
 impl messaging::Actor for ClientActorInner {
@@ -362,6 +375,8 @@ impl ClientActorInner {
             config_updater,
             sync_jobs_sender,
             state_parts_future_spawner,
+            pending_chunk_endorsements: vec![],
+            chunk_endorsements_batch_deadline: None,
         })
     }
 }
@@ -635,10 +650,20 @@ impl Handler<near_client_primitives::types::SandboxMessage> for ClientActorInner
         msg: near_client_primitives::types::SandboxMessage,
     ) -> near_client_primitives::types::SandboxResponse {
         match msg {
-            near_client_primitives::types::SandboxMessage::SandboxPatchState(state) => {
-                self.client.chain.patch_state(
-                    near_primitives::sandbox::state_patch::SandboxStatePatch::new(state),
-                );
+            near_client_primitives::types::SandboxMessage::SandboxPatchState {
+                records,
+                block_height,
+                runtime_config,
+            } => {
+                let mut patch =
+                    near_primitives::sandbox::state_patch::SandboxStatePatch::new(records);
+                if let Some(runtime_config) = runtime_config {
+                    patch = patch.with_runtime_config(runtime_config);
+                }
+                if let Some(block_height) = block_height {
+                    patch = patch.at_height(block_height);
+                }
+                self.client.chain.patch_state(patch);
                 near_client_primitives::types::SandboxResponse::SandboxNoResponse
             }
             near_client_primitives::types::SandboxMessage::SandboxPatchStateStatus => {
@@ -773,6 +798,7 @@ impl Handler<Status> for ClientActorInner {
                 earliest_block_time,
                 epoch_id: Some(head.epoch_id),
                 epoch_start_height,
+                state_sync: self.client.get_node_state_sync_progress(),
             },
             validator_account_id,
             validator_public_key,
@@ -1179,6 +1205,7 @@ impl ClientActorInner {
         }
 
         self.try_process_unfinished_blocks();
+        self.try_process_chunk_endorsements_batch();
 
         let mut delay = near_async::time::Duration::seconds(1);
         let now = self.clock.now_utc();
@@ -1402,8 +1429,19 @@ impl ClientActorInner {
             info!(target: "client", "Received an empty set of block headers");
             return true;
         }
+        self.client.header_sync.on_headers_received(&peer_id);
+        let num_headers = headers.len();
+        let validation_start = self.clock.now();
         match self.client.sync_block_headers(headers) {
-            Ok(_) => true,
+            Ok(_) => {
+                self.client.header_sync.on_headers_validated(
+                    &self.client.sync_status,
+                    &self.client.chain,
+                    num_headers,
+                    self.clock.now() - validation_start,
+                );
+                true
+            }
             Err(err) => {
                 if err.is_bad_data() {
                     error!(target: "client", ?err, "Error processing sync blocks");
@@ -1511,8 +1549,17 @@ impl ClientActorInner {
     fn find_sync_hash(&mut self) -> Result<CryptoHash, near_chain::Error> {
         let header_head = self.client.chain.header_head()?;
         let sync_hash = header_head.last_block_hash;
-        let epoch_start_sync_hash =
-            StateSync::get_epoch_start_sync_hash(&mut self.client.chain, &sync_hash)?;
+        let head = self.client.chain.head()?;
+        let epoch_start_sync_hash = match self.get_target_epoch_sync_hash(&header_head)? {
+            // Sync to the target epoch only if the node hasn't reached it yet.
+            Some(target_sync_hash)
+                if head.height
+                    < self.client.chain.get_block_header(&target_sync_hash)?.height() =>
+            {
+                target_sync_hash
+            }
+            _ => StateSync::get_epoch_start_sync_hash(&mut self.client.chain, &sync_hash)?,
+        };
 
         let genesis_hash = self.client.chain.genesis().hash();
         tracing::debug!(
@@ -1526,6 +1573,23 @@ impl ClientActorInner {
         Ok(epoch_start_sync_hash)
     }
 
+    /// Returns the first block of the epoch that the node is configured to
+    /// sync the state of, if any. Returns `None` if the headers of that epoch
+    /// aren't known yet.
+    fn get_target_epoch_sync_hash(
+        &self,
+        header_head: &Tip,
+    ) -> Result<Option<CryptoHash>, near_chain::Error> {
+        let Some(target_epoch_id) = &self.client.config.state_sync.target_epoch_id else {
+            return Ok(None);
+        };
+        StateSync::get_epoch_start_sync_hash_for_epoch(
+            &self.client.chain,
+            &header_head.last_block_hash,
+            target_epoch_id,
+        )
+    }
+
     /// Runs catchup on repeat, if this client is a validator.
     /// Schedules itself again if it was not ran as response to state parts job result
     fn catchup(&mut self, ctx: &mut dyn DelayedActionRunner<Self>) {
@@ -1879,6 +1943,20 @@ impl ClientActorInner {
             return Ok(true);
         }
 
+        // A node configured to start from a past epoch syncs its state first,
+        // as soon as it has the headers of that epoch.
+        if let Some(target_epoch_id) = &self.client.config.state_sync.target_epoch_id {
+            let Some(sync_hash) = self.get_target_epoch_sync_hash(&header_head)? else {
+                debug!(target: "sync", ?target_epoch_id, "Waiting for the headers of the target epoch of state sync");
+                return Ok(false);
+            };
+            let head = self.client.chain.head()?;
+            if head.height < self.client.chain.get_block_header(&sync_hash)?.height() {
+                return Ok(true);
+            }
+            // The node is already past the target epoch, keep syncing blocks.
+        }
+
         // Check that we have enough headers to start block sync.
         let min_header_height =
             highest_height.saturating_sub(self.client.config.block_header_fetch_horizon);
@@ -2109,6 +2187,14 @@ impl Handler<GetClientConfig> for ClientActorInner {
     }
 }
 
+impl Handler<GetWitnessStats> for ClientActorInner {
+    fn handle(&mut self, msg: GetWitnessStats) -> Result<WitnessStatsView, GetWitnessStatsError> {
+        tracing::debug!(target: "client", ?msg);
+
+        Ok(self.client.chunk_validator.witness_stats.get_stats(msg.shard_id))
+    }
+}
+
 impl Handler<SyncMessage> for ClientActorInner {
     fn handle(&mut self, msg: SyncMessage) {
         tracing::debug!(target: "client", ?msg);
@@ -2135,11 +2221,47 @@ impl Handler<ProcessChunkStateWitnessMessage> for ClientActorInner {
     }
 }
 
-impl Handler<ChunkEndorsementMessage> for ClientActorInner {
+impl HandlerWithContext<ChunkEndorsementMessage> for ClientActorInner {
     #[perf]
-    fn handle(&mut self, msg: ChunkEndorsementMessage) {
-        if let Err(err) = self.client.process_chunk_endorsement(msg.0) {
-            tracing::error!(target: "client", ?err, "Error processing chunk endorsement");
+    fn handle(&mut self, msg: ChunkEndorsementMessage, ctx: &mut dyn DelayedActionRunner<Self>) {
+        let batch_window = self.client.config.chunk_endorsement_batch_window;
+        if batch_window.is_zero() {
+            if let Err(err) = self.client.process_chunk_endorsement(msg.0) {
+                tracing::error!(target: "client", ?err, "Error processing chunk endorsement");
+            }
+            return;
+        }
+
+        self.pending_chunk_endorsements.push(msg.0);
+        if self.pending_chunk_endorsements.len() >= MAX_CHUNK_ENDORSEMENT_BATCH_SIZE {
+            self.process_chunk_endorsements_batch();
+        } else if self.chunk_endorsements_batch_deadline.is_none() {
+            self.chunk_endorsements_batch_deadline = Some(self.clock.now() + batch_window);
+            ctx.run_later(
+                "ClientActor process_chunk_endorsements_batch",
+                batch_window,
+                move |act, _ctx| act.try_process_chunk_endorsements_batch(),
+            );
         }
     }
 }
+
+impl ClientActorInner {
+    /// Verifies and saves the pending chunk endorsements if their batching window has ended.
+    /// It's called both from a delayed action and `check_triggers`, as delayed actions
+    /// can be postponed while there are messages in the mailbox.
+    fn try_process_chunk_endorsements_batch(&mut self) {
+        if self
+            .chunk_endorsements_batch_deadline
+            .is_some_and(|deadline| deadline <= self.clock.now())
+        {
+            self.process_chunk_endorsements_batch();
+        }
+    }
+
+    fn process_chunk_endorsements_batch(&mut self) {
+        self.chunk_endorsements_batch_deadline = None;
+        let endorsements = std::mem::take(&mut self.pending_chunk_endorsements);
+        self.client.process_chunk_endorsements_batch(endorsements);
+    }
+}
@@ -172,6 +172,9 @@ impl Handler<DebugStatus> for ClientActorInner {
             DebugStatus::RequestedStateParts => Ok(DebugStatusResponse::RequestedStateParts(
                 self.client.chain.get_requested_state_parts(),
             )),
+            DebugStatus::StateSyncProgress => {
+                Ok(DebugStatusResponse::StateSyncProgress(self.client.get_state_sync_progress()))
+            }
             DebugStatus::ChainProcessingStatus => Ok(DebugStatusResponse::ChainProcessingStatus(
                 self.client.chain.get_chain_processing_info(),
             )),
@@ -1,10 +1,11 @@
 use near_o11y::metrics::{
     exponential_buckets, linear_buckets, try_create_counter, try_create_gauge,
-    try_create_histogram, try_create_histogram_vec, try_create_int_counter,
-    try_create_int_counter_vec, try_create_int_gauge, try_create_int_gauge_vec, Counter, Gauge,
-    Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    try_create_histogram, try_create_histogram_vec, try_create_histogram_with_buckets,
+    try_create_int_counter, try_create_int_counter_vec, try_create_int_gauge,
+    try_create_int_gauge_vec, Counter, Gauge, Histogram, HistogramVec, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec,
 };
-use near_primitives::stateless_validation::ChunkStateWitness;
+use near_primitives::stateless_validation::{ChunkStateWitness, EncodedChunkStateWitness};
 use once_cell::sync::Lazy;
 
 pub(crate) static BLOCK_PRODUCED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
@@ -435,6 +436,15 @@ pub(crate) static STATE_SYNC_RETRY_PART: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static STATE_SYNC_INVALID_PARTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_state_sync_invalid_parts_total",
+        "Number of downloaded state parts which failed the verification, by the source of the part",
+        &["shard_id", "source"],
+    )
+    .unwrap()
+});
+
 pub(crate) static STATE_SYNC_HEADER_ERROR: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
         "near_state_sync_header_error_total",
@@ -518,6 +528,50 @@ pub(crate) static STATE_SYNC_EXTERNAL_PARTS_SIZE_DOWNLOADED: Lazy<IntCounterVec>
         .unwrap()
     });
 
+pub(crate) static STATE_SYNC_DOWNLOADED_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_state_sync_downloaded_bytes_total",
+        "Bytes of state headers and parts downloaded by state sync and catch-up",
+        &["shard_id", "sync_type"],
+    )
+    .unwrap()
+});
+
+pub(crate) static STATE_SYNC_APPLY_PART_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_state_sync_apply_part_delay_sec",
+        "Time to apply a single state part by state sync and catch-up",
+        &["shard_id", "sync_type"],
+        Some(exponential_buckets(0.001, 2.0, 20).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static STATE_SYNC_CATCHUP_THROTTLED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_state_sync_catchup_throttled_total",
+        "Number of times catch-up was paused to stay within its resource budget",
+        &["stage"],
+    )
+    .unwrap()
+});
+
+pub(crate) static STATE_SYNC_CATCHUP_SHARDS: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_state_sync_catchup_shards",
+        "Number of shards currently being caught up",
+    )
+    .unwrap()
+});
+
+pub(crate) static STATE_SYNC_HISTORICAL_PARTS_SERVED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_state_sync_historical_parts_served_total",
+        "Number of state parts of old epochs generated for the peers by an archival node",
+    )
+    .unwrap()
+});
+
 pub(crate) static STATE_SYNC_DUMP_PUT_OBJECT_ELAPSED: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_state_sync_dump_put_object_elapsed_sec",
@@ -556,6 +610,38 @@ pub(crate) static SYNC_REQUIREMENT_CURRENT: Lazy<IntGaugeVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static HEADER_SYNC_RESPONSE_LATENCY: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram(
+        "near_header_sync_response_latency",
+        "Time between requesting a batch of headers from a peer and receiving it",
+    )
+    .unwrap()
+});
+
+pub(crate) static HEADER_SYNC_VALIDATION_TIME: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram(
+        "near_header_sync_validation_time",
+        "Time spent validating a batch of headers received during header sync",
+    )
+    .unwrap()
+});
+
+pub(crate) static HEADER_SYNC_PEERS_PER_REQUEST: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_header_sync_peers_per_request",
+        "Number of peers the latest batch of headers was requested from",
+    )
+    .unwrap()
+});
+
+pub(crate) static HEADER_SYNC_PIPELINED_REQUESTS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_header_sync_pipelined_requests_total",
+        "Number of batches of headers requested as soon as the previous batch was validated",
+    )
+    .unwrap()
+});
+
 pub(crate) static SHADOW_CHUNK_VALIDATION_FAILED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
     try_create_int_counter(
         "near_shadow_chunk_validation_failed_total",
@@ -574,6 +660,34 @@ pub(crate) static CHUNK_STATE_WITNESS_VALIDATION_TIME: Lazy<HistogramVec> = Lazy
     .unwrap()
 });
 
+pub(crate) static CHUNK_STATE_WITNESS_VALIDATION_QUEUE_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_chunk_state_witness_validation_queue_size",
+        "Number of state witnesses waiting for a validation slot (by shard_id)",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_STATE_WITNESS_VALIDATION_RUNNING: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_chunk_state_witness_validation_running",
+        "Number of state witnesses being validated at the moment (by shard_id)",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_STATE_WITNESS_VALIDATION_QUEUE_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_chunk_state_witness_validation_queue_time",
+        "Time in seconds a state witness waits in the queue before its validation starts",
+        &["shard_id"],
+        Some(exponential_buckets(0.001, 2.0, 14).unwrap()),
+    )
+    .unwrap()
+});
+
 pub(crate) static CHUNK_STATE_WITNESS_TOTAL_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_chunk_state_witness_total_size",
@@ -584,6 +698,18 @@ pub(crate) static CHUNK_STATE_WITNESS_TOTAL_SIZE: Lazy<HistogramVec> = Lazy::new
     .unwrap()
 });
 
+pub(crate) static CHUNK_STATE_WITNESS_COMPRESSED_VALUES_SIZE: Lazy<HistogramVec> = Lazy::new(
+    || {
+        try_create_histogram_vec(
+            "near_chunk_state_witness_compressed_values_size",
+            "Compressed size in bytes of the trie values of state witnesses compressed per value, included in near_chunk_state_witness_total_size",
+            &["shard_id"],
+            Some(exponential_buckets(100_000.0, 1.2, 32).unwrap()),
+        )
+        .unwrap()
+    },
+);
+
 pub(crate) static CHUNK_STATE_WITNESS_RAW_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_chunk_state_witness_raw_size",
@@ -659,19 +785,50 @@ pub(crate) static CHUNK_STATE_WITNESS_SOURCE_RECEIPT_PROOFS_SIZE: Lazy<Histogram
         .unwrap()
     });
 
+pub(crate) static PRODUCED_CHUNK_WITNESS_BASE_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_produced_chunk_witness_base_size",
+        "Uncompressed size of the state witness of a produced chunk before adding the new transactions",
+        &["shard_id"],
+        Some(buckets_for_witness_field_size()),
+    )
+    .unwrap()
+});
+
+pub(crate) static PRODUCED_CHUNK_OUTGOING_RECEIPTS_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_produced_chunk_outgoing_receipts_size",
+        "Serialized size of the outgoing receipts of a produced chunk, charged to the state witness size budget",
+        &["shard_id"],
+        Some(buckets_for_witness_field_size()),
+    )
+    .unwrap()
+});
+
+pub(crate) static PRODUCED_CHUNK_TRANSACTIONS_GAS_LIMIT_PERCENT: Lazy<IntGaugeVec> = Lazy::new(
+    || {
+        try_create_int_gauge_vec(
+            "near_produced_chunk_transactions_gas_limit_percent",
+            "Percentage of the transactions gas limit available to the last produced chunk, reduced when recent state witnesses approach the size limit",
+            &["shard_id"],
+        )
+        .unwrap()
+    },
+);
+
 pub(crate) fn record_witness_size_metrics(
     decoded_size: usize,
-    encoded_size: usize,
+    encoded: &EncodedChunkStateWitness,
     witness: &ChunkStateWitness,
 ) {
-    if let Err(err) = record_witness_size_metrics_fallible(decoded_size, encoded_size, witness) {
+    if let Err(err) = record_witness_size_metrics_fallible(decoded_size, encoded, witness) {
         tracing::warn!(target:"client", "Failed to record witness size metrics!, error: {}", err);
     }
 }
 
 fn record_witness_size_metrics_fallible(
     decoded_size: usize,
-    encoded_size: usize,
+    encoded: &EncodedChunkStateWitness,
     witness: &ChunkStateWitness,
 ) -> Result<(), std::io::Error> {
     let shard_id = witness.chunk_header.shard_id().to_string();
@@ -680,7 +837,12 @@ fn record_witness_size_metrics_fallible(
         .observe(decoded_size as f64);
     CHUNK_STATE_WITNESS_TOTAL_SIZE
         .with_label_values(&[&shard_id.as_str()])
-        .observe(encoded_size as f64);
+        .observe(encoded.size_bytes() as f64);
+    if let Some(values_size) = encoded.compressed_values_size() {
+        CHUNK_STATE_WITNESS_COMPRESSED_VALUES_SIZE
+            .with_label_values(&[&shard_id.as_str()])
+            .observe(values_size as f64);
+    }
     CHUNK_STATE_WITNESS_MAIN_STATE_TRANSISTION_SIZE
         .with_label_values(&[shard_id.as_str()])
         .observe(borsh::to_vec(&witness.main_state_transition)?.len() as f64);
@@ -746,6 +908,46 @@ pub(crate) static CHUNK_STATE_WITNESS_NETWORK_ROUNDTRIP_TIME: Lazy<HistogramVec>
     },
 );
 
+pub(crate) static ORPHAN_CHUNK_STATE_WITNESSES_DROPPED_TOTAL: Lazy<IntCounterVec> = Lazy::new(
+    || {
+        try_create_int_counter_vec(
+            "near_orphan_chunk_state_witnesses_dropped_total",
+            "Number of orphaned witnesses dropped from OrphanStateWitnessPool without being processed (by shard_id and reason)",
+            &["shard_id", "reason"],
+        )
+        .unwrap()
+    },
+);
+
+pub(crate) static ORPHAN_CHUNK_STATE_WITNESSES_PROMOTED_TOTAL: Lazy<IntCounterVec> = Lazy::new(
+    || {
+        try_create_int_counter_vec(
+            "near_orphan_chunk_state_witnesses_promoted_total",
+            "Number of orphaned witnesses taken out of OrphanStateWitnessPool for processing once their previous block arrived (by shard_id)",
+            &["shard_id"],
+        )
+        .unwrap()
+    },
+);
+
+pub(crate) static CHUNK_ENDORSEMENT_BATCH_SIZE: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram_with_buckets(
+        "near_chunk_endorsement_batch_size",
+        "Number of chunk endorsements whose signatures are verified together in a batch",
+        exponential_buckets(1.0, 2.0, 10).unwrap(),
+    )
+    .unwrap()
+});
+
+pub(crate) static CHUNK_STATE_WITNESSES_RATE_LIMITED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+            "near_chunk_state_witnesses_rate_limited_total",
+            "Number of witnesses dropped because their chunk producer sent too many witnesses for the same shard and height (by shard_id)",
+            &["shard_id"],
+        )
+        .unwrap()
+});
+
 pub(crate) static ORPHAN_CHUNK_STATE_WITNESS_POOL_SIZE: Lazy<IntGaugeVec> = Lazy::new(|| {
     try_create_int_gauge_vec(
         "near_orphan_chunk_state_witness_pool_size",
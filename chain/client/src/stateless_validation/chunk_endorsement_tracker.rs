@@ -11,7 +11,7 @@ use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
 use near_primitives::stateless_validation::{ChunkEndorsement, EndorsementStats};
 use near_primitives::types::AccountId;
 
-use crate::Client;
+use crate::{metrics, Client};
 
 // This is the number of unique chunks for which we would track the chunk endorsements.
 // Ideally, we should not be processing more than num_shards chunks at a time.
@@ -64,6 +64,42 @@ impl Client {
             Err(error) => return Err(error),
         }
     }
+
+    /// Processes a batch of chunk endorsements. The signatures of the endorsements whose
+    /// chunks are known are verified together, the other endorsements wait for their
+    /// chunks in the pending cache. Errors are logged for every endorsement separately.
+    pub fn process_chunk_endorsements_batch(&mut self, endorsements: Vec<ChunkEndorsement>) {
+        let mut endorsements_with_chunks = Vec::with_capacity(endorsements.len());
+        for endorsement in endorsements {
+            match self.chain.chain_store().get_partial_chunk(endorsement.chunk_hash()) {
+                Ok(chunk) => endorsements_with_chunks.push((chunk.cloned_header(), endorsement)),
+                Err(Error::ChunkMissing(_)) => {
+                    tracing::debug!(target: "client", ?endorsement, "Endorsement arrived before chunk.");
+                    if let Err(err) = self
+                        .chunk_endorsement_tracker
+                        .add_chunk_endorsement_to_pending_cache(endorsement)
+                    {
+                        tracing::error!(target: "client", ?err, "Error processing chunk endorsement");
+                    }
+                }
+                Err(err) => {
+                    tracing::error!(target: "client", ?err, "Error processing chunk endorsement")
+                }
+            }
+        }
+        if endorsements_with_chunks.is_empty() {
+            return;
+        }
+        metrics::CHUNK_ENDORSEMENT_BATCH_SIZE.observe(endorsements_with_chunks.len() as f64);
+        for result in self
+            .chunk_endorsement_tracker
+            .process_chunk_endorsements_batch(&endorsements_with_chunks)
+        {
+            if let Err(err) = result {
+                tracing::error!(target: "client", ?err, "Error processing chunk endorsement");
+            }
+        }
+    }
 }
 
 impl ChunkEndorsementTracker {
@@ -88,9 +124,13 @@ impl ChunkEndorsementTracker {
             return;
         };
         tracing::debug!(target: "client", ?chunk_hash, "Processing pending chunk endorsements.");
-        for endorsement in chunk_endorsements.values() {
-            if let Err(error) = self.process_chunk_endorsement(chunk_header, endorsement.clone()) {
-                tracing::debug!(target: "client", ?endorsement, ?error, "Error processing pending chunk endorsement");
+        let endorsements: Vec<_> = chunk_endorsements
+            .into_values()
+            .map(|endorsement| (chunk_header.clone(), endorsement))
+            .collect();
+        for result in self.process_chunk_endorsements_batch(&endorsements) {
+            if let Err(error) = result {
+                tracing::debug!(target: "client", ?chunk_hash, ?error, "Error processing pending chunk endorsement");
             }
         }
     }
@@ -122,9 +162,6 @@ impl ChunkEndorsementTracker {
         endorsement: ChunkEndorsement,
         chunk_header: Option<&ShardChunkHeader>,
     ) -> Result<(), Error> {
-        let chunk_hash = endorsement.chunk_hash();
-        let account_id = &endorsement.account_id;
-
         let endorsement_cache = if chunk_header.is_some() {
             &self.chunk_endorsements
         } else {
@@ -132,10 +169,7 @@ impl ChunkEndorsementTracker {
         };
 
         // If we have already processed this chunk endorsement, return early.
-        if endorsement_cache
-            .get(chunk_hash)
-            .is_some_and(|existing_endorsements| existing_endorsements.contains_key(account_id))
-        {
+        if Self::is_endorsement_known(endorsement_cache, &endorsement) {
             tracing::debug!(target: "client", ?endorsement, "Already received chunk endorsement.");
             return Ok(());
         }
@@ -147,6 +181,59 @@ impl ChunkEndorsementTracker {
             }
         }
 
+        Self::save_endorsement(endorsement_cache, endorsement);
+        Ok(())
+    }
+
+    /// Processes endorsements of chunks whose headers are available the same way as
+    /// `process_chunk_endorsement`, but verifies the signatures of all endorsements with
+    /// a single batched verification, which is much cheaper than verifying them one by one.
+    /// Returns the results of the endorsements which weren't processed before.
+    pub(crate) fn process_chunk_endorsements_batch(
+        &self,
+        endorsements: &[(ShardChunkHeader, ChunkEndorsement)],
+    ) -> Vec<Result<(), Error>> {
+        let _span = tracing::debug_span!(target: "client", "process_chunk_endorsements_batch", num_endorsements=endorsements.len()).entered();
+        let new_endorsements: Vec<_> = endorsements
+            .iter()
+            .filter(|(_, endorsement)| {
+                let known = Self::is_endorsement_known(&self.chunk_endorsements, endorsement);
+                if known {
+                    tracing::debug!(target: "client", ?endorsement, "Already received chunk endorsement.");
+                }
+                !known
+            })
+            .map(|(chunk_header, endorsement)| (chunk_header, endorsement))
+            .collect();
+        let verification_results =
+            self.epoch_manager.verify_chunk_endorsements_batch(&new_endorsements);
+        new_endorsements
+            .into_iter()
+            .zip(verification_results)
+            .map(|((_, endorsement), verification_result)| {
+                if !verification_result? {
+                    tracing::error!(target: "client", ?endorsement, "Invalid chunk endorsement.");
+                    return Err(Error::InvalidChunkEndorsement);
+                }
+                Self::save_endorsement(&self.chunk_endorsements, endorsement.clone());
+                Ok(())
+            })
+            .collect()
+    }
+
+    fn is_endorsement_known(
+        endorsement_cache: &SyncLruCache<ChunkHash, HashMap<AccountId, ChunkEndorsement>>,
+        endorsement: &ChunkEndorsement,
+    ) -> bool {
+        endorsement_cache.get(endorsement.chunk_hash()).is_some_and(|existing_endorsements| {
+            existing_endorsements.contains_key(&endorsement.account_id)
+        })
+    }
+
+    fn save_endorsement(
+        endorsement_cache: &SyncLruCache<ChunkHash, HashMap<AccountId, ChunkEndorsement>>,
+        endorsement: ChunkEndorsement,
+    ) {
         // If we are the current block producer, we store the chunk endorsement for each chunk which
         // would later be used during block production to check whether to include the chunk or not.
         // TODO(stateless_validation): It's possible for a malicious validator to send endorsements
@@ -155,11 +242,9 @@ impl ChunkEndorsementTracker {
         // Maybe add check to ensure we don't accept endorsements from chunks that have too old height_created?
         tracing::debug!(target: "client", ?endorsement, "Received and saved chunk endorsement.");
         let mut guard = endorsement_cache.lock();
-        guard.get_or_insert(chunk_hash.clone(), || HashMap::new());
-        let chunk_endorsements = guard.get_mut(chunk_hash).unwrap();
-        chunk_endorsements.insert(account_id.clone(), endorsement);
-
-        Ok(())
+        guard.get_or_insert(endorsement.chunk_hash().clone(), || HashMap::new());
+        let chunk_endorsements = guard.get_mut(endorsement.chunk_hash()).unwrap();
+        chunk_endorsements.insert(endorsement.account_id.clone(), endorsement);
     }
 
     /// Called by block producer.
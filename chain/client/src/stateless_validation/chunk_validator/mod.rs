@@ -1,12 +1,15 @@
 pub mod orphan_witness_handling;
 pub mod orphan_witness_pool;
+mod validation_scheduler;
+mod witness_rate_limiter;
 
 use super::processing_tracker::ProcessingDoneTracker;
 use crate::stateless_validation::chunk_endorsement_tracker::ChunkEndorsementTracker;
+use crate::stateless_validation::witness_stats::WitnessStatsTracker;
 use crate::{metrics, Client};
 use itertools::Itertools;
 use lru::LruCache;
-use near_async::futures::{AsyncComputationSpawner, AsyncComputationSpawnerExt};
+use near_async::futures::AsyncComputationSpawner;
 use near_async::messaging::{CanSend, Sender};
 use near_chain::chain::{
     apply_new_chunk, apply_old_chunk, NewChunkData, NewChunkResult, OldChunkData, OldChunkResult,
@@ -38,11 +41,14 @@ use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::ShardId;
 use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::views::WitnessStatsSourceView;
 use near_store::{PartialStorage, ShardUId};
 use near_vm_runner::logic::ProtocolVersion;
 use orphan_witness_pool::OrphanStateWitnessPool;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use validation_scheduler::ValidationScheduler;
+use witness_rate_limiter::WitnessRateLimiter;
 
 // After validating a chunk state witness, we ideally need to send the chunk endorsement
 // to just the next block producer at height h. However, it's possible that blocks at height
@@ -77,8 +83,11 @@ pub struct ChunkValidator {
     runtime_adapter: Arc<dyn RuntimeAdapter>,
     chunk_endorsement_tracker: Arc<ChunkEndorsementTracker>,
     orphan_witness_pool: OrphanStateWitnessPool,
-    validation_spawner: Arc<dyn AsyncComputationSpawner>,
+    validation_scheduler: ValidationScheduler,
+    witness_rate_limiter: WitnessRateLimiter,
     main_state_transition_result_cache: MainStateTransitionCache,
+    /// Sizes and validation times of the latest witnesses, shared with shadow validation.
+    pub(crate) witness_stats: Arc<WitnessStatsTracker>,
 }
 
 impl ChunkValidator {
@@ -89,7 +98,10 @@ impl ChunkValidator {
         runtime_adapter: Arc<dyn RuntimeAdapter>,
         chunk_endorsement_tracker: Arc<ChunkEndorsementTracker>,
         orphan_witness_pool_size: usize,
+        orphan_witness_pool_size_per_shard: usize,
         validation_spawner: Arc<dyn AsyncComputationSpawner>,
+        validation_max_concurrency_per_shard: usize,
+        max_witnesses_per_producer_per_height: usize,
     ) -> Self {
         Self {
             my_signer,
@@ -97,15 +109,24 @@ impl ChunkValidator {
             network_sender,
             runtime_adapter,
             chunk_endorsement_tracker,
-            orphan_witness_pool: OrphanStateWitnessPool::new(orphan_witness_pool_size),
-            validation_spawner,
+            orphan_witness_pool: OrphanStateWitnessPool::new(
+                orphan_witness_pool_size,
+                orphan_witness_pool_size_per_shard,
+            ),
+            validation_scheduler: ValidationScheduler::new(
+                validation_spawner,
+                validation_max_concurrency_per_shard,
+            ),
+            witness_rate_limiter: WitnessRateLimiter::new(max_witnesses_per_producer_per_height),
             main_state_transition_result_cache: MainStateTransitionCache::default(),
+            witness_stats: Arc::new(WitnessStatsTracker::default()),
         }
     }
 
     /// Performs the chunk validation logic. When done, it will send the chunk
     /// endorsement message to the block producer. The actual validation logic
-    /// happens in a separate thread.
+    /// happens in a separate thread, once the `ValidationScheduler` has a free
+    /// slot for the shard of the chunk.
     /// The chunk is validated asynchronously, if you want to wait for the processing to finish
     /// you can use the `processing_done_tracker` argument (but it's optional, it's safe to pass None there).
     pub fn start_validating_chunk(
@@ -178,18 +199,29 @@ impl ChunkValidator {
 
         let runtime_adapter = self.runtime_adapter.clone();
         let cache = self.main_state_transition_result_cache.clone();
-        self.validation_spawner.spawn("stateless_validation", move || {
+        let witness_stats = self.witness_stats.clone();
+        let shard_id = chunk_header.shard_id();
+        let height = chunk_header.height_created();
+        self.validation_scheduler.schedule(shard_id, height, move || {
             // processing_done_tracker must survive until the processing is finished.
             let _processing_done_tracker_capture: Option<ProcessingDoneTracker> =
                 processing_done_tracker;
 
-            match validate_chunk_state_witness(
+            let validation_start = std::time::Instant::now();
+            let validation_result = validate_chunk_state_witness(
                 state_witness,
                 pre_validation_result,
                 epoch_manager.as_ref(),
                 runtime_adapter.as_ref(),
                 &cache,
-            ) {
+            );
+            witness_stats.record_validation_time(
+                shard_id,
+                &chunk_header.chunk_hash(),
+                WitnessStatsSourceView::Observed,
+                validation_start.elapsed(),
+            );
+            match validation_result {
                 Ok(()) => {
                     send_chunk_endorsement_to_block_producers(
                         &chunk_header,
@@ -232,7 +264,7 @@ pub(crate) fn validate_prepared_transactions(
 /// Pre-validates the chunk's receipts and transactions against the chain.
 /// We do this before handing off the computationally intensive part to a
 /// validation thread.
-pub(crate) fn pre_validate_chunk_state_witness(
+pub fn pre_validate_chunk_state_witness(
     state_witness: &ChunkStateWitness,
     chain: &Chain,
     epoch_manager: &dyn EpochManagerAdapter,
@@ -517,12 +549,12 @@ impl MainTransition {
     }
 }
 
-pub(crate) struct PreValidationOutput {
+pub struct PreValidationOutput {
     main_transition_params: MainTransition,
     implicit_transition_params: Vec<ApplyChunkBlockContext>,
 }
 
-pub(crate) fn validate_chunk_state_witness(
+pub fn validate_chunk_state_witness(
     state_witness: ChunkStateWitness,
     pre_validation_output: PreValidationOutput,
     epoch_manager: &dyn EpochManagerAdapter,
@@ -723,7 +755,7 @@ impl Client {
         processing_done_tracker: Option<ProcessingDoneTracker>,
     ) -> Result<(), Error> {
         // TODO(stateless_validation): Inefficient, we are decoding the witness twice, but fine for temporary measure
-        let (witness, _) = signed_witness.witness_bytes.decode()?;
+        let (witness, _) = signed_witness.witness_bytes.decode_with(&self.witness_compression)?;
         if !self.epoch_manager.verify_chunk_state_witness_signature(
             &signed_witness,
             &witness.chunk_producer,
@@ -756,6 +788,29 @@ impl Client {
             "process_chunk_state_witness",
         );
 
+        // Drop the witness if its chunk producer has already sent too many witnesses for
+        // the same shard and height. This protects the node from being flooded with
+        // witnesses by a buggy or malicious chunk producer.
+        let shard_id = witness.chunk_header.shard_id();
+        if !self.chunk_validator.witness_rate_limiter.try_accept(
+            &witness.chunk_producer,
+            shard_id,
+            witness.chunk_header.height_created(),
+        ) {
+            tracing::warn!(
+                target: "client",
+                chunk_hash=?witness.chunk_header.chunk_hash(),
+                shard_id,
+                witness_height=witness.chunk_header.height_created(),
+                chunk_producer=%witness.chunk_producer,
+                "Dropping state witness, the chunk producer sent too many witnesses for this height",
+            );
+            metrics::CHUNK_STATE_WITNESSES_RATE_LIMITED_TOTAL
+                .with_label_values(&[&shard_id.to_string()])
+                .inc();
+            return Ok(());
+        }
+
         // Send the acknowledgement for the state witness back to the chunk producer.
         // This is currently used for network roundtrip time measurement, so we do not need to
         // wait for validation to finish.
@@ -764,6 +819,11 @@ impl Client {
         if self.config.save_latest_witnesses {
             self.chain.chain_store.save_latest_chunk_state_witness(&witness)?;
         }
+        self.chunk_validator.witness_stats.record_observed_witness(
+            &witness,
+            raw_witness_size,
+            encoded_witness.size_bytes(),
+        );
 
         // Avoid processing state witness for old chunks.
         // In particular it is impossible for a chunk created at a height
@@ -833,7 +893,7 @@ impl Client {
         encoded_witness: &EncodedChunkStateWitness,
     ) -> Result<(ChunkStateWitness, ChunkStateWitnessSize), Error> {
         let decode_start = std::time::Instant::now();
-        let (witness, raw_witness_size) = encoded_witness.decode()?;
+        let (witness, raw_witness_size) = encoded_witness.decode_with(&self.witness_compression)?;
         let decode_elapsed_seconds = decode_start.elapsed().as_secs_f64();
         let chunk_header = &witness.chunk_header;
         let witness_height = chunk_header.height_created();
@@ -1,5 +1,7 @@
 use lru::LruCache;
-use near_chain_configs::default_orphan_state_witness_pool_size;
+use near_chain_configs::{
+    default_orphan_state_witness_pool_size, default_orphan_state_witness_pool_size_per_shard,
+};
 use near_primitives::hash::CryptoHash;
 use near_primitives::stateless_validation::{ChunkProductionKey, ChunkStateWitness};
 use near_primitives::types::BlockHeight;
@@ -10,8 +12,11 @@ use metrics_tracker::OrphanWitnessMetricsTracker;
 /// To process a ChunkStateWitness we need to have the previous block, but it might happen that a ChunkStateWitness
 /// shows up before the block is available. In such cases the witness is put in `OrphanStateWitnessPool` until the
 /// required block arrives and the witness can be processed.
+/// Besides the total capacity, the number of witnesses of every shard is limited, so that
+/// witnesses of a single shard can't push the witnesses of the other shards out of the pool.
 pub struct OrphanStateWitnessPool {
     witness_cache: LruCache<ChunkProductionKey, CacheEntry>,
+    max_witnesses_per_shard: usize,
 }
 
 struct CacheEntry {
@@ -20,9 +25,10 @@ struct CacheEntry {
 }
 
 impl OrphanStateWitnessPool {
-    /// Create a new `OrphanStateWitnessPool` with a capacity of `cache_capacity` witnesses.
+    /// Create a new `OrphanStateWitnessPool` with a capacity of `cache_capacity` witnesses,
+    /// at most `max_witnesses_per_shard` of which can belong to the same shard.
     /// The `Default` trait implementation provides reasonable defaults.
-    pub fn new(cache_capacity: usize) -> Self {
+    pub fn new(cache_capacity: usize, max_witnesses_per_shard: usize) -> Self {
         if cache_capacity > 128 {
             tracing::warn!(
                 target: "client",
@@ -31,7 +37,10 @@ impl OrphanStateWitnessPool {
                 to performance problems.", cache_capacity);
         }
 
-        OrphanStateWitnessPool { witness_cache: LruCache::new(cache_capacity) }
+        OrphanStateWitnessPool {
+            witness_cache: LruCache::new(cache_capacity),
+            max_witnesses_per_shard,
+        }
     }
 
     /// Add an orphaned chunk state witness to the pool. The witness will be put in a cache and it'll
@@ -41,8 +50,13 @@ impl OrphanStateWitnessPool {
     /// validation it'd be possible to fill the whole cache with spam.
     /// `witness_size` is only used for metrics, it's okay to pass 0 if you don't care about the metrics.
     pub fn add_orphan_state_witness(&mut self, witness: ChunkStateWitness, witness_size: usize) {
-        // Insert the new ChunkStateWitness into the cache
         let cache_key = witness.chunk_production_key();
+        // A witness with the same key replaces the existing one, so it doesn't take more space in the shard.
+        if !self.witness_cache.contains(&cache_key) && !self.make_room_in_shard(&witness) {
+            return;
+        }
+
+        // Insert the new ChunkStateWitness into the cache
         let metrics_tracker = OrphanWitnessMetricsTracker::new(&witness, witness_size);
         let cache_entry = CacheEntry { witness, _metrics_tracker: metrics_tracker };
         if let Some((_, ejected_entry)) = self.witness_cache.push(cache_key, cache_entry) {
@@ -56,9 +70,59 @@ impl OrphanStateWitnessPool {
                 ejected_witness_prev_block = ?header.prev_block_hash(),
                 "Ejecting an orphaned ChunkStateWitness from the cache due to capacity limit. It will not be processed."
             );
+            metrics_tracker::record_witness_dropped(&ejected_entry.witness, "pool_capacity");
         }
     }
 
+    /// Makes sure that adding `witness` doesn't exceed the limit of witnesses of its shard.
+    /// When the shard is full, the witness with the highest height is dropped, as the witnesses
+    /// with lower heights are the ones whose blocks are expected to arrive first.
+    /// Returns false if `witness` itself is the one to drop.
+    fn make_room_in_shard(&mut self, witness: &ChunkStateWitness) -> bool {
+        let shard_id = witness.chunk_header.shard_id();
+        let witness_height = witness.chunk_header.height_created();
+        let shard_keys: Vec<&ChunkProductionKey> = self
+            .witness_cache
+            .iter()
+            .map(|(cache_key, _)| cache_key)
+            .filter(|cache_key| cache_key.shard_id == shard_id)
+            .collect();
+        if shard_keys.len() < self.max_witnesses_per_shard {
+            return true;
+        }
+        let highest_key = shard_keys.into_iter().max_by_key(|cache_key| cache_key.height_created);
+        let highest_key = match highest_key {
+            Some(cache_key) if cache_key.height_created > witness_height => cache_key.clone(),
+            _ => {
+                tracing::debug!(
+                    target: "client",
+                    witness_height,
+                    witness_shard = shard_id,
+                    witness_chunk = ?witness.chunk_header.chunk_hash(),
+                    "Not saving an orphaned ChunkStateWitness because the shard already has \
+                    the maximum number of orphaned witnesses at lower heights."
+                );
+                metrics_tracker::record_witness_dropped(witness, "shard_capacity");
+                return false;
+            }
+        };
+        let ejected_entry = self
+            .witness_cache
+            .pop(&highest_key)
+            .expect("The cache contains this entry, a moment ago it was iterated over");
+        let header = &ejected_entry.witness.chunk_header;
+        tracing::debug!(
+            target: "client",
+            ejected_witness_height = header.height_created(),
+            ejected_witness_shard = header.shard_id(),
+            ejected_witness_chunk = ?header.chunk_hash(),
+            ejected_witness_prev_block = ?header.prev_block_hash(),
+            "Ejecting an orphaned ChunkStateWitness from the cache due to the shard capacity limit. It will not be processed."
+        );
+        metrics_tracker::record_witness_dropped(&ejected_entry.witness, "shard_capacity");
+        true
+    }
+
     /// Find all orphaned witnesses that were waiting for this block and remove them from the pool.
     /// The block has arrived, so they can be now processed, they're no longer orphans.
     pub fn take_state_witnesses_waiting_for_block(
@@ -77,6 +141,7 @@ impl OrphanStateWitnessPool {
                 .witness_cache
                 .pop(&cache_key)
                 .expect("The cache contains this entry, a moment ago it was iterated over");
+            metrics_tracker::record_witness_promoted(&ready_witness.witness);
             result.push(ready_witness.witness);
         }
         result
@@ -106,13 +171,19 @@ impl OrphanStateWitnessPool {
         for cache_key in to_remove {
             let popped = self.witness_cache.pop(&cache_key);
             debug_assert!(popped.is_some());
+            if let Some(cache_entry) = popped {
+                metrics_tracker::record_witness_dropped(&cache_entry.witness, "below_final_height");
+            }
         }
     }
 }
 
 impl Default for OrphanStateWitnessPool {
     fn default() -> OrphanStateWitnessPool {
-        OrphanStateWitnessPool::new(default_orphan_state_witness_pool_size())
+        OrphanStateWitnessPool::new(
+            default_orphan_state_witness_pool_size(),
+            default_orphan_state_witness_pool_size_per_shard(),
+        )
     }
 }
 
@@ -162,6 +233,20 @@ mod metrics_tracker {
         }
     }
 
+    /// Counts a witness which was removed from the pool without being processed.
+    pub fn record_witness_dropped(witness: &ChunkStateWitness, reason: &str) {
+        metrics::ORPHAN_CHUNK_STATE_WITNESSES_DROPPED_TOTAL
+            .with_label_values(&[witness.chunk_header.shard_id().to_string().as_str(), reason])
+            .inc();
+    }
+
+    /// Counts a witness which was taken out of the pool because its previous block arrived.
+    pub fn record_witness_promoted(witness: &ChunkStateWitness) {
+        metrics::ORPHAN_CHUNK_STATE_WITNESSES_PROMOTED_TOTAL
+            .with_label_values(&[witness.chunk_header.shard_id().to_string().as_str()])
+            .inc();
+    }
+
     fn witness_size_to_i64(witness_size: usize) -> i64 {
         witness_size.try_into().expect(
             "Orphaned ChunkStateWitness size can't be converted to i64. \
@@ -246,7 +331,7 @@ mod tests {
     /// Basic functionality - inserting witnesses and fetching them works as expected
     #[test]
     fn basic() {
-        let mut pool = OrphanStateWitnessPool::new(10);
+        let mut pool = OrphanStateWitnessPool::new(10, 10);
 
         let witness1 = make_witness(100, 1, block(99), 0);
         let witness2 = make_witness(100, 2, block(99), 0);
@@ -271,7 +356,7 @@ mod tests {
     /// should replace the old one. The old one should be ejected from the pool.
     #[test]
     fn replacing() {
-        let mut pool = OrphanStateWitnessPool::new(10);
+        let mut pool = OrphanStateWitnessPool::new(10, 10);
 
         // The old witness is replaced when the awaited block is the same
         {
@@ -304,7 +389,7 @@ mod tests {
     /// The pool has limited capacity. Once it hits the capacity, the least-recently used witness will be ejected.
     #[test]
     fn limited_capacity() {
-        let mut pool = OrphanStateWitnessPool::new(2);
+        let mut pool = OrphanStateWitnessPool::new(2, 2);
 
         let witness1 = make_witness(102, 1, block(101), 0);
         let witness2 = make_witness(101, 1, block(100), 0);
@@ -326,10 +411,47 @@ mod tests {
         assert_empty(&pool);
     }
 
+    /// The number of witnesses of a single shard is limited. Once a shard is full, the witness with
+    /// the highest height is dropped, and the witnesses of the other shards aren't affected.
+    #[test]
+    fn limited_shard_capacity() {
+        let mut pool = OrphanStateWitnessPool::new(10, 2);
+
+        let witness1 = make_witness(101, 1, block(100), 0);
+        let witness2 = make_witness(103, 1, block(102), 0);
+        let witness3 = make_witness(101, 2, block(100), 0);
+        pool.add_orphan_state_witness(witness1.clone(), 0);
+        pool.add_orphan_state_witness(witness2, 0);
+        pool.add_orphan_state_witness(witness3.clone(), 0);
+
+        // Shard 1 is full, the new witness has a lower height than witness2, so witness2 is ejected.
+        let witness4 = make_witness(102, 1, block(101), 0);
+        pool.add_orphan_state_witness(witness4, 0);
+
+        // The new witness has the highest height in the full shard, so it's not saved.
+        let witness5 = make_witness(104, 1, block(103), 0);
+        pool.add_orphan_state_witness(witness5, 0);
+
+        // Replacing a witness with the same key works in a full shard.
+        let witness6 = make_witness(102, 1, block(101), 1);
+        pool.add_orphan_state_witness(witness6.clone(), 0);
+
+        let waiting_for_100 = pool.take_state_witnesses_waiting_for_block(&block(100));
+        assert_contents(waiting_for_100, vec![witness1, witness3]);
+        let waiting_for_101 = pool.take_state_witnesses_waiting_for_block(&block(101));
+        assert_contents(waiting_for_101, vec![witness6]);
+        let waiting_for_102 = pool.take_state_witnesses_waiting_for_block(&block(102));
+        assert_contents(waiting_for_102, vec![]);
+        let waiting_for_103 = pool.take_state_witnesses_waiting_for_block(&block(103));
+        assert_contents(waiting_for_103, vec![]);
+
+        assert_empty(&pool);
+    }
+
     /// OrphanStateWitnessPool can handle large shard ids without any problems, it doesn't keep a Vec indexed by shard_id
     #[test]
     fn large_shard_id() {
-        let mut pool = OrphanStateWitnessPool::new(10);
+        let mut pool = OrphanStateWitnessPool::new(10, 10);
 
         let large_shard_id = ShardId::MAX;
         let witness = make_witness(101, large_shard_id, block(99), 0);
@@ -344,7 +466,7 @@ mod tests {
     /// Test that remove_witnesses_below_final_height() works correctly
     #[test]
     fn remove_below_height() {
-        let mut pool = OrphanStateWitnessPool::new(10);
+        let mut pool = OrphanStateWitnessPool::new(10, 10);
 
         let witness1 = make_witness(100, 1, block(99), 0);
         let witness2 = make_witness(101, 1, block(100), 0);
@@ -374,7 +496,7 @@ mod tests {
     /// An OrphanStateWitnessPool with 0 capacity shouldn't crash, it should just ignore all witnesses
     #[test]
     fn zero_capacity() {
-        let mut pool = OrphanStateWitnessPool::new(0);
+        let mut pool = OrphanStateWitnessPool::new(0, 0);
 
         pool.add_orphan_state_witness(make_witness(100, 1, block(99), 0), 0);
         pool.add_orphan_state_witness(make_witness(100, 1, block(99), 0), 1);
@@ -392,7 +514,7 @@ mod tests {
     /// but we can at least test that it doesn't crash. That's always something.
     #[test]
     fn destructor_doesnt_crash() {
-        let mut pool = OrphanStateWitnessPool::new(10);
+        let mut pool = OrphanStateWitnessPool::new(10, 10);
         pool.add_orphan_state_witness(make_witness(100, 0, block(99), 0), 0);
         pool.add_orphan_state_witness(make_witness(100, 2, block(99), 0), 0);
         pool.add_orphan_state_witness(make_witness(100, 2, block(99), 0), 1);
@@ -403,7 +525,7 @@ mod tests {
     /// A longer test scenario
     #[test]
     fn scenario() {
-        let mut pool = OrphanStateWitnessPool::new(5);
+        let mut pool = OrphanStateWitnessPool::new(5, 5);
 
         // Witnesses for shards 0, 1, 2, 3 at height 1000, looking for block 99
         let witness0 = make_witness(100, 0, block(99), 0);
@@ -0,0 +1,254 @@
+use crate::metrics;
+use near_async::futures::{AsyncComputationSpawner, AsyncComputationSpawnerExt};
+use near_primitives::types::{BlockHeight, ShardId};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+type ValidationJob = Box<dyn FnOnce() + Send>;
+
+/// `ValidationScheduler` decides when the validation of a state witness starts.
+/// Witnesses of different shards are validated in parallel, but at most
+/// `max_concurrency_per_shard` witnesses of a single shard are validated at
+/// the same time, so that a burst of witnesses of one shard can't occupy all
+/// validation threads. The remaining witnesses wait in a per-shard queue, and
+/// the witness with the lowest height is validated first, as its endorsement
+/// is the one the next block needs.
+#[derive(Clone)]
+pub struct ValidationScheduler {
+    inner: Arc<SchedulerInner>,
+}
+
+struct SchedulerInner {
+    spawner: Arc<dyn AsyncComputationSpawner>,
+    max_concurrency_per_shard: usize,
+    shards: Mutex<HashMap<ShardId, ShardQueue>>,
+}
+
+#[derive(Default)]
+struct ShardQueue {
+    running: usize,
+    pending: BinaryHeap<PendingJob>,
+    /// Sequence number of the next job, used to keep the FIFO order of jobs with the same height.
+    next_seq: u64,
+}
+
+struct PendingJob {
+    height: BlockHeight,
+    seq: u64,
+    enqueued_at: Instant,
+    job: ValidationJob,
+}
+
+impl PartialEq for PendingJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PendingJob {}
+
+impl PartialOrd for PendingJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PendingJob {
+    /// `BinaryHeap` is a max-heap, so the ordering is reversed to pop the lowest height first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.height, other.seq).cmp(&(self.height, self.seq))
+    }
+}
+
+impl ValidationScheduler {
+    pub fn new(
+        spawner: Arc<dyn AsyncComputationSpawner>,
+        max_concurrency_per_shard: usize,
+    ) -> Self {
+        assert!(max_concurrency_per_shard > 0, "max_concurrency_per_shard must be positive");
+        Self {
+            inner: Arc::new(SchedulerInner {
+                spawner,
+                max_concurrency_per_shard,
+                shards: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Schedules the validation of the witness of the chunk at `height` in `shard_id`.
+    /// The validation starts immediately if the shard has a free slot, otherwise
+    /// it waits until the validations which were started before finish.
+    pub fn schedule(
+        &self,
+        shard_id: ShardId,
+        height: BlockHeight,
+        job: impl FnOnce() + Send + 'static,
+    ) {
+        let ready_jobs = {
+            let mut shards = self.inner.shards.lock().unwrap();
+            let queue = shards.entry(shard_id).or_default();
+            let seq = queue.next_seq;
+            queue.next_seq += 1;
+            queue.pending.push(PendingJob {
+                height,
+                seq,
+                enqueued_at: Instant::now(),
+                job: Box::new(job),
+            });
+            self.inner.take_ready_jobs(shard_id, queue)
+        };
+        // Spawning happens outside of the lock, as the spawner may run the job right away.
+        for job in ready_jobs {
+            self.inner.clone().spawn(shard_id, job);
+        }
+    }
+
+    /// Returns the number of witnesses of the shard waiting for a validation slot.
+    #[cfg(test)]
+    fn num_pending(&self, shard_id: ShardId) -> usize {
+        self.inner.shards.lock().unwrap().get(&shard_id).map_or(0, |queue| queue.pending.len())
+    }
+
+    /// Returns the number of witnesses of the shard which are being validated.
+    #[cfg(test)]
+    fn num_running(&self, shard_id: ShardId) -> usize {
+        self.inner.shards.lock().unwrap().get(&shard_id).map_or(0, |queue| queue.running)
+    }
+}
+
+impl SchedulerInner {
+    /// Takes the jobs which can be started given the free slots of the shard,
+    /// and marks them as running.
+    fn take_ready_jobs(&self, shard_id: ShardId, queue: &mut ShardQueue) -> Vec<PendingJob> {
+        let mut ready_jobs = Vec::new();
+        while queue.running < self.max_concurrency_per_shard {
+            let Some(job) = queue.pending.pop() else {
+                break;
+            };
+            queue.running += 1;
+            ready_jobs.push(job);
+        }
+        let shard_label = shard_id.to_string();
+        metrics::CHUNK_STATE_WITNESS_VALIDATION_QUEUE_SIZE
+            .with_label_values(&[shard_label.as_str()])
+            .set(queue.pending.len() as i64);
+        metrics::CHUNK_STATE_WITNESS_VALIDATION_RUNNING
+            .with_label_values(&[shard_label.as_str()])
+            .set(queue.running as i64);
+        ready_jobs
+    }
+
+    fn spawn(self: Arc<Self>, shard_id: ShardId, pending_job: PendingJob) {
+        metrics::CHUNK_STATE_WITNESS_VALIDATION_QUEUE_TIME
+            .with_label_values(&[shard_id.to_string().as_str()])
+            .observe(pending_job.enqueued_at.elapsed().as_secs_f64());
+        let spawner = self.spawner.clone();
+        spawner.spawn("stateless_validation", move || {
+            (pending_job.job)();
+            self.on_job_finished(shard_id);
+        });
+    }
+
+    fn on_job_finished(self: Arc<Self>, shard_id: ShardId) {
+        let ready_jobs = {
+            let mut shards = self.shards.lock().unwrap();
+            let queue = shards.get_mut(&shard_id).expect("running job must have a shard queue");
+            queue.running -= 1;
+            self.take_ready_jobs(shard_id, queue)
+        };
+        for job in ready_jobs {
+            self.clone().spawn(shard_id, job);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValidationScheduler;
+    use near_async::futures::AsyncComputationSpawner;
+    use near_primitives::types::{BlockHeight, ShardId};
+    use std::sync::{Arc, Mutex};
+
+    /// Spawner which keeps the spawned computations until the test runs them.
+    #[derive(Default)]
+    struct ManualSpawner {
+        spawned: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
+    }
+
+    impl AsyncComputationSpawner for ManualSpawner {
+        fn spawn_boxed(&self, _name: &str, f: Box<dyn FnOnce() + Send>) {
+            self.spawned.lock().unwrap().push(f);
+        }
+    }
+
+    impl ManualSpawner {
+        /// Runs the computation which was spawned first.
+        fn run_next(&self) {
+            let f = self.spawned.lock().unwrap().remove(0);
+            f();
+        }
+
+        fn num_spawned(&self) -> usize {
+            self.spawned.lock().unwrap().len()
+        }
+    }
+
+    fn schedule(
+        scheduler: &ValidationScheduler,
+        log: &Arc<Mutex<Vec<(ShardId, BlockHeight)>>>,
+        shard_id: ShardId,
+        height: BlockHeight,
+    ) {
+        let log = log.clone();
+        scheduler.schedule(shard_id, height, move || log.lock().unwrap().push((shard_id, height)));
+    }
+
+    #[test]
+    fn test_concurrency_is_bounded_per_shard() {
+        let spawner = Arc::new(ManualSpawner::default());
+        let scheduler = ValidationScheduler::new(spawner.clone(), 2);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for height in 1..=4 {
+            schedule(&scheduler, &log, 0, height);
+        }
+        schedule(&scheduler, &log, 1, 1);
+
+        // Two jobs of shard 0 and the job of shard 1 are running.
+        assert_eq!(spawner.num_spawned(), 3);
+        assert_eq!(scheduler.num_running(0), 2);
+        assert_eq!(scheduler.num_pending(0), 2);
+        assert_eq!(scheduler.num_running(1), 1);
+        assert_eq!(scheduler.num_pending(1), 0);
+
+        // A finished job frees a slot for the next job of the same shard.
+        spawner.run_next();
+        assert_eq!(spawner.num_spawned(), 3);
+        assert_eq!(scheduler.num_running(0), 2);
+        assert_eq!(scheduler.num_pending(0), 1);
+
+        while spawner.num_spawned() > 0 {
+            spawner.run_next();
+        }
+        assert_eq!(scheduler.num_running(0), 0);
+        assert_eq!(scheduler.num_pending(0), 0);
+        assert_eq!(log.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_lowest_height_is_validated_first() {
+        let spawner = Arc::new(ManualSpawner::default());
+        let scheduler = ValidationScheduler::new(spawner.clone(), 1);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for height in [5, 8, 6, 7, 6] {
+            schedule(&scheduler, &log, 0, height);
+        }
+        while spawner.num_spawned() > 0 {
+            spawner.run_next();
+        }
+        // The first job starts right away, the queued ones run by height.
+        let heights: Vec<_> = log.lock().unwrap().iter().map(|(_, height)| *height).collect();
+        assert_eq!(heights, vec![5, 6, 6, 7, 8]);
+    }
+}
@@ -0,0 +1,98 @@
+use lru::LruCache;
+use near_primitives::types::{AccountId, BlockHeight, BlockHeightDelta, ShardId};
+use std::collections::HashMap;
+
+/// Number of (chunk producer, shard, height) triples for which the number of
+/// received witnesses is remembered.
+const WITNESS_COUNTS_CACHE_SIZE: usize = 1000;
+
+/// Number of heights after exceeding the limit during which a chunk producer
+/// may send only one witness per shard and height.
+const PENALTY_NUM_HEIGHTS: BlockHeightDelta = 100;
+
+/// Limits how many state witnesses a single chunk producer can send for the
+/// same shard and height. A well-behaved chunk producer sends one witness per
+/// chunk, and produces more than one chunk at a height only when the chain
+/// forks, so a few witnesses per height are enough. Witnesses above the limit
+/// are dropped, and the chunk producer is penalized: for the next
+/// `PENALTY_NUM_HEIGHTS` heights only its first witness per shard and height is
+/// accepted.
+pub struct WitnessRateLimiter {
+    max_witnesses_per_height: usize,
+    witness_counts: LruCache<(AccountId, ShardId, BlockHeight), usize>,
+    /// Chunk producers which exceeded the limit, mapped to the last height of their penalty.
+    penalized_until: HashMap<AccountId, BlockHeight>,
+}
+
+impl WitnessRateLimiter {
+    pub fn new(max_witnesses_per_height: usize) -> Self {
+        assert!(max_witnesses_per_height > 0, "max_witnesses_per_height must be positive");
+        Self {
+            max_witnesses_per_height,
+            witness_counts: LruCache::new(WITNESS_COUNTS_CACHE_SIZE),
+            penalized_until: HashMap::new(),
+        }
+    }
+
+    /// Counts a witness sent by `chunk_producer` for the chunk at `height` in
+    /// `shard_id`. Returns false if the witness is over the limit and should be dropped.
+    pub fn try_accept(
+        &mut self,
+        chunk_producer: &AccountId,
+        shard_id: ShardId,
+        height: BlockHeight,
+    ) -> bool {
+        let limit = match self.penalized_until.get(chunk_producer) {
+            Some(penalized_until) if height <= *penalized_until => 1,
+            _ => self.max_witnesses_per_height,
+        };
+        let key = (chunk_producer.clone(), shard_id, height);
+        let count = self.witness_counts.get(&key).copied().unwrap_or(0);
+        if count < limit {
+            self.witness_counts.put(key, count + 1);
+            return true;
+        }
+        let penalized_until = self.penalized_until.entry(chunk_producer.clone()).or_default();
+        *penalized_until = (*penalized_until).max(height + PENALTY_NUM_HEIGHTS);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WitnessRateLimiter, PENALTY_NUM_HEIGHTS};
+    use near_primitives::types::AccountId;
+
+    #[test]
+    fn test_limit_per_shard_and_height() {
+        let mut limiter = WitnessRateLimiter::new(2);
+        let producer: AccountId = "alice".parse().unwrap();
+        let other_producer: AccountId = "bob".parse().unwrap();
+        assert!(limiter.try_accept(&producer, 0, 10));
+        assert!(limiter.try_accept(&producer, 0, 10));
+        assert!(!limiter.try_accept(&producer, 0, 10));
+        // The limit is separate for every shard, height and chunk producer.
+        assert!(limiter.try_accept(&producer, 1, 10));
+        assert!(limiter.try_accept(&other_producer, 0, 10));
+        assert!(limiter.try_accept(&other_producer, 0, 10));
+    }
+
+    #[test]
+    fn test_penalty() {
+        let mut limiter = WitnessRateLimiter::new(2);
+        let producer: AccountId = "alice".parse().unwrap();
+        for _ in 0..3 {
+            limiter.try_accept(&producer, 0, 10);
+        }
+        // After the penalty the usual limit applies again.
+        let height = 11 + PENALTY_NUM_HEIGHTS;
+        assert!(limiter.try_accept(&producer, 0, height));
+        assert!(limiter.try_accept(&producer, 0, height));
+        // While penalized, only the first witness per shard and height is accepted.
+        // Exceeding the limit again extends the penalty.
+        assert!(limiter.try_accept(&producer, 1, 10 + PENALTY_NUM_HEIGHTS));
+        assert!(!limiter.try_accept(&producer, 1, 10 + PENALTY_NUM_HEIGHTS));
+        assert!(limiter.try_accept(&producer, 2, height));
+        assert!(!limiter.try_accept(&producer, 2, height));
+    }
+}
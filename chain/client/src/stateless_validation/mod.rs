@@ -5,3 +5,7 @@ pub mod processing_tracker;
 mod shadow_validate;
 mod state_witness_producer;
 pub mod state_witness_tracker;
+pub(crate) mod transaction_limiter;
+pub mod witness_stats;
+
+pub use state_witness_producer::StateWitnessBaseParts;
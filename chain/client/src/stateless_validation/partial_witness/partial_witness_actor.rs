@@ -17,7 +17,7 @@ use near_primitives::reed_solomon::reed_solomon_encode;
 use near_primitives::sharding::ShardChunkHeader;
 use near_primitives::stateless_validation::{
     ChunkStateWitness, ChunkStateWitnessAck, EncodedChunkStateWitness, PartialEncodedStateWitness,
-    SignedEncodedChunkStateWitness,
+    SignedEncodedChunkStateWitness, StateWitnessCompression,
 };
 use near_primitives::types::{AccountId, EpochId};
 use near_primitives::validator_signer::ValidatorSigner;
@@ -42,6 +42,8 @@ pub struct PartialWitnessActor {
     /// Reed Solomon encoder for encoding state witness parts.
     /// We keep one wrapper for each length of chunk_validators to avoid re-creating the encoder.
     rs_map: RsMap,
+    /// Compression used to encode the state witness.
+    witness_compression: StateWitnessCompression,
 }
 
 impl Actor for PartialWitnessActor {}
@@ -98,6 +100,7 @@ impl PartialWitnessActor {
         client_sender: ClientSenderForPartialWitness,
         my_signer: Arc<dyn ValidatorSigner>,
         epoch_manager: Arc<dyn EpochManagerAdapter>,
+        witness_compression: StateWitnessCompression,
     ) -> Self {
         let partial_witness_tracker =
             PartialEncodedStateWitnessTracker::new(client_sender, epoch_manager.clone());
@@ -108,6 +111,7 @@ impl PartialWitnessActor {
             partial_witness_tracker,
             state_witness_tracker: ChunkStateWitnessTracker::new(clock),
             rs_map: RsMap::new(),
+            witness_compression,
         }
     }
 
@@ -133,7 +137,7 @@ impl PartialWitnessActor {
             "distribute_chunk_state_witness",
         );
 
-        let witness_bytes = compress_witness(&state_witness)?;
+        let witness_bytes = compress_witness(&state_witness, &self.witness_compression)?;
 
         // Record the witness in order to match the incoming acks for measuring round-trip times.
         // See process_chunk_state_witness_ack for the handling of the ack messages.
@@ -363,14 +367,18 @@ impl PartialWitnessActor {
     }
 }
 
-fn compress_witness(witness: &ChunkStateWitness) -> Result<EncodedChunkStateWitness, Error> {
+fn compress_witness(
+    witness: &ChunkStateWitness,
+    compression: &StateWitnessCompression,
+) -> Result<EncodedChunkStateWitness, Error> {
     let shard_id_label = witness.chunk_header.shard_id().to_string();
     let encode_timer = metrics::CHUNK_STATE_WITNESS_ENCODE_TIME
         .with_label_values(&[shard_id_label.as_str()])
         .start_timer();
-    let (witness_bytes, raw_witness_size) = EncodedChunkStateWitness::encode(&witness)?;
+    let (witness_bytes, raw_witness_size) =
+        EncodedChunkStateWitness::encode_with(&witness, compression)?;
     encode_timer.observe_duration();
 
-    metrics::record_witness_size_metrics(raw_witness_size, witness_bytes.size_bytes(), witness);
+    metrics::record_witness_size_metrics(raw_witness_size, &witness_bytes, witness);
     Ok(witness_bytes)
 }
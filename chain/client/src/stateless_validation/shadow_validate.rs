@@ -5,6 +5,7 @@ use near_chain::{Block, BlockHeader};
 use near_chain_primitives::Error;
 use near_primitives::sharding::{ShardChunk, ShardChunkHeader};
 use near_primitives::stateless_validation::EncodedChunkStateWitness;
+use near_primitives::views::WitnessStatsSourceView;
 
 use crate::stateless_validation::chunk_validator::{
     pre_validate_chunk_state_witness, validate_chunk_state_witness, validate_prepared_transactions,
@@ -16,16 +17,20 @@ impl Client {
     // Temporary feature to make node produce state witness for every chunk in every processed block
     // and then self-validate it.
     pub(crate) fn shadow_validate_block_chunks(&mut self, block: &Block) -> Result<(), Error> {
-        if !cfg!(feature = "shadow_chunk_validation") {
+        if !self.config.shadow_chunk_validation.get() {
             return Ok(());
         }
         let block_hash = block.hash();
         tracing::debug!(target: "client", ?block_hash, "shadow validation for block chunks");
         let prev_block = self.chain.get_block(block.header().prev_hash())?;
         let prev_block_chunks = prev_block.chunks();
-        for chunk in
-            block.chunks().iter().filter(|chunk| chunk.is_new_chunk(block.header().height()))
-        {
+        let shadow_validation_shards = self.config.shadow_validation_shards.clone();
+        for chunk in block.chunks().iter().filter(|chunk| {
+            chunk.is_new_chunk(block.header().height())
+                && shadow_validation_shards
+                    .as_ref()
+                    .map_or(true, |shards| shards.contains(&chunk.shard_id()))
+        }) {
             let chunk = self.chain.get_chunk_clone_from_header(chunk)?;
             let prev_chunk_header = prev_block_chunks.get(chunk.shard_id() as usize).unwrap();
             if let Err(err) =
@@ -82,26 +87,35 @@ impl Client {
             prev_chunk_header,
             chunk,
             validated_transactions.storage_proof,
+            None,
         )?;
         if self.config.save_latest_witnesses {
             self.chain.chain_store.save_latest_chunk_state_witness(&witness)?;
         }
+        if self.config.save_shadow_witnesses {
+            self.chain.chain_store.save_shadow_state_witness(
+                &witness,
+                self.config.shadow_witnesses_retention_blocks,
+            )?;
+        }
         let (encoded_witness, raw_witness_size) = {
             let shard_id_label = shard_id.to_string();
             let encode_timer = metrics::CHUNK_STATE_WITNESS_ENCODE_TIME
                 .with_label_values(&[shard_id_label.as_str()])
                 .start_timer();
-            let (encoded_witness, raw_witness_size) = EncodedChunkStateWitness::encode(&witness)?;
+            let (encoded_witness, raw_witness_size) =
+                EncodedChunkStateWitness::encode_with(&witness, &self.witness_compression)?;
             encode_timer.observe_duration();
-            metrics::record_witness_size_metrics(
+            metrics::record_witness_size_metrics(raw_witness_size, &encoded_witness, &witness);
+            self.chunk_validator.witness_stats.record_shadow_witness(
+                &witness,
                 raw_witness_size,
                 encoded_witness.size_bytes(),
-                &witness,
             );
             let decode_timer = metrics::CHUNK_STATE_WITNESS_DECODE_TIME
                 .with_label_values(&[shard_id_label.as_str()])
                 .start_timer();
-            encoded_witness.decode()?;
+            encoded_witness.decode_with(&self.witness_compression)?;
             decode_timer.observe_duration();
             (encoded_witness, raw_witness_size)
         };
@@ -123,15 +137,23 @@ impl Client {
         );
         let epoch_manager = self.epoch_manager.clone();
         let runtime_adapter = self.runtime_adapter.clone();
+        let witness_stats = self.chunk_validator.witness_stats.clone();
         rayon::spawn(move || {
             let validation_start = Instant::now();
-            match validate_chunk_state_witness(
+            let validation_result = validate_chunk_state_witness(
                 witness,
                 pre_validation_result,
                 epoch_manager.as_ref(),
                 runtime_adapter.as_ref(),
                 &MainStateTransitionCache::default(),
-            ) {
+            );
+            witness_stats.record_validation_time(
+                shard_id,
+                &chunk_hash,
+                WitnessStatsSourceView::Shadow,
+                validation_start.elapsed(),
+            );
+            match validation_result {
                 Ok(()) => {
                     tracing::debug!(
                         target: "client",
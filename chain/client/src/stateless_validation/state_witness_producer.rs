@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use near_async::messaging::{CanSend, IntoSender};
+use near_chain::types::WitnessSizeBudget;
 use near_chain::{BlockHeader, Chain, ChainStoreAccess};
 use near_chain_primitives::Error;
 use near_o11y::log_assert_fail;
@@ -12,13 +13,28 @@ use near_primitives::sharding::{ChunkHash, ReceiptProof, ShardChunk, ShardChunkH
 use near_primitives::stateless_validation::{
     ChunkStateTransition, ChunkStateWitness, StoredChunkStateTransitionData,
 };
-use near_primitives::types::{AccountId, EpochId};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{AccountId, EpochId, ShardId};
 
+use crate::metrics;
 use crate::stateless_validation::chunk_validator::send_chunk_endorsement_to_block_producers;
 use crate::Client;
 
 use super::partial_witness::partial_witness_actor::DistributeStateWitnessRequest;
 
+/// Parts of the state witness which don't depend on the transactions of the new chunk.
+/// The chunk producer collects and measures them once, before preparing the transactions,
+/// and reuses them to create the witness of the produced chunk.
+pub struct StateWitnessBaseParts {
+    main_state_transition: ChunkStateTransition,
+    implicit_transitions: Vec<ChunkStateTransition>,
+    applied_receipts_hash: CryptoHash,
+    source_receipt_proofs: HashMap<ChunkHash, ReceiptProof>,
+    prev_chunk_transactions: Vec<SignedTransaction>,
+    /// Serialized size of the parts above.
+    size: usize,
+}
+
 impl Client {
     /// Distributes the chunk state witness to chunk validators that are
     /// selected to validate this chunk.
@@ -29,6 +45,7 @@ impl Client {
         prev_chunk_header: &ShardChunkHeader,
         chunk: &ShardChunk,
         transactions_storage_proof: Option<PartialState>,
+        base_parts: Option<StateWitnessBaseParts>,
     ) -> Result<(), Error> {
         let protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
         if !checked_feature!("stable", StatelessValidationV0, protocol_version) {
@@ -45,6 +62,7 @@ impl Client {
             prev_chunk_header,
             chunk,
             transactions_storage_proof,
+            base_parts,
         )?;
 
         if self.config.save_latest_witnesses {
@@ -76,6 +94,8 @@ impl Client {
         Ok(())
     }
 
+    /// Creates the state witness for `chunk`. `base_parts` are collected here
+    /// unless they were already collected when producing the chunk.
     pub(crate) fn create_state_witness(
         &mut self,
         chunk_producer: AccountId,
@@ -83,13 +103,27 @@ impl Client {
         prev_chunk_header: &ShardChunkHeader,
         chunk: &ShardChunk,
         transactions_storage_proof: Option<PartialState>,
+        base_parts: Option<StateWitnessBaseParts>,
     ) -> Result<ChunkStateWitness, Error> {
         let chunk_header = chunk.cloned_header();
         let epoch_id =
             self.epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
-        let prev_chunk = self.chain.get_chunk(&prev_chunk_header.chunk_hash())?;
-        let (main_state_transition, implicit_transitions, applied_receipts_hash) =
-            self.collect_state_transition_data(&chunk_header, prev_chunk_header)?;
+        let base_parts = match base_parts {
+            Some(base_parts) => base_parts,
+            None => self.collect_state_witness_base_parts(
+                prev_block_header,
+                prev_chunk_header,
+                chunk_header.shard_id(),
+            )?,
+        };
+        let StateWitnessBaseParts {
+            main_state_transition,
+            implicit_transitions,
+            applied_receipts_hash,
+            source_receipt_proofs,
+            prev_chunk_transactions,
+            size: _,
+        } = base_parts;
 
         let new_transactions = chunk.transactions().to_vec();
         let new_transactions_validation_state = if new_transactions.is_empty() {
@@ -104,9 +138,6 @@ impl Client {
             })?
         };
 
-        let source_receipt_proofs =
-            self.collect_source_receipt_proofs(prev_block_header, prev_chunk_header)?;
-
         let witness = ChunkStateWitness::new(
             chunk_producer,
             epoch_id,
@@ -117,7 +148,7 @@ impl Client {
             // that defeats the purpose of this check being a debugging
             // mechanism.)
             applied_receipts_hash,
-            prev_chunk.transactions().to_vec(),
+            prev_chunk_transactions,
             implicit_transitions,
             new_transactions,
             new_transactions_validation_state,
@@ -125,21 +156,103 @@ impl Client {
         Ok(witness)
     }
 
+    /// Collects the parts of the state witness of the chunk of `shard_id` on top of
+    /// `prev_block_header` which don't depend on the new transactions, together with
+    /// their size.
+    pub(crate) fn collect_state_witness_base_parts(
+        &mut self,
+        prev_block_header: &BlockHeader,
+        prev_chunk_header: &ShardChunkHeader,
+        shard_id: ShardId,
+    ) -> Result<StateWitnessBaseParts, Error> {
+        let (main_state_transition, implicit_transitions, applied_receipts_hash) = self
+            .collect_state_transition_data(prev_block_header.hash(), shard_id, prev_chunk_header)?;
+        let source_receipt_proofs =
+            self.collect_source_receipt_proofs(prev_block_header, prev_chunk_header)?;
+        let prev_chunk_transactions =
+            self.chain.get_chunk(&prev_chunk_header.chunk_hash())?.transactions().to_vec();
+        let size = borsh::object_length(&main_state_transition)?
+            + borsh::object_length(&implicit_transitions)?
+            + borsh::object_length(&source_receipt_proofs)?
+            + borsh::object_length(&prev_chunk_transactions)?;
+        Ok(StateWitnessBaseParts {
+            main_state_transition,
+            implicit_transitions,
+            applied_receipts_hash,
+            source_receipt_proofs,
+            prev_chunk_transactions,
+            size,
+        })
+    }
+
+    /// Returns the budget for the size of the state witness of the chunk produced on top
+    /// of `prev_block_header` with the given outgoing receipts, or None if no state witness
+    /// is produced for the chunk. The parts of the witness which don't depend on the new
+    /// transactions are collected here and returned, so that they can be reused to create
+    /// the witness.
+    pub(crate) fn state_witness_size_budget(
+        &mut self,
+        epoch_id: &EpochId,
+        prev_block_header: &BlockHeader,
+        prev_chunk_header: &ShardChunkHeader,
+        shard_id: ShardId,
+        outgoing_receipts: &[Receipt],
+    ) -> Result<Option<(WitnessSizeBudget, StateWitnessBaseParts)>, Error> {
+        let protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
+        if !checked_feature!("stable", StatelessValidationV0, protocol_version) {
+            return Ok(None);
+        }
+        let base_parts =
+            self.collect_state_witness_base_parts(prev_block_header, prev_chunk_header, shard_id)?;
+        let base_size = base_parts.size;
+        let outgoing_receipts_size = borsh::object_length(outgoing_receipts)?;
+
+        let config = &self.config.witness_size_budget;
+        let soft_limit = config.soft_limit.as_u64() as usize;
+        self.transaction_limiter.record_base_size(shard_id, base_size + outgoing_receipts_size);
+        let transactions_gas_limit_percent =
+            self.transaction_limiter.transactions_gas_limit_percent(
+                shard_id,
+                config.transactions_limiting_threshold.as_u64() as usize,
+                soft_limit,
+            );
+        let budget = WitnessSizeBudget {
+            base_size,
+            outgoing_receipts_size,
+            soft_limit,
+            hard_limit: config.hard_limit.as_u64() as usize,
+            transactions_gas_limit_percent,
+        };
+        let shard_label = shard_id.to_string();
+        metrics::PRODUCED_CHUNK_WITNESS_BASE_SIZE
+            .with_label_values(&[&shard_label])
+            .observe(base_size as f64);
+        metrics::PRODUCED_CHUNK_OUTGOING_RECEIPTS_SIZE
+            .with_label_values(&[&shard_label])
+            .observe(outgoing_receipts_size as f64);
+        metrics::PRODUCED_CHUNK_TRANSACTIONS_GAS_LIMIT_PERCENT
+            .with_label_values(&[&shard_label])
+            .set(transactions_gas_limit_percent as i64);
+        if budget.witness_size(0, 0) >= budget.soft_limit {
+            tracing::warn!(target: "client", shard_id, base_size, outgoing_receipts_size, ?config, "State witness exceeds the size budget before adding transactions, producing a chunk without transactions");
+        }
+        Ok(Some((budget, base_parts)))
+    }
+
     /// Collect state transition data necessary to produce state witness for
-    /// `chunk_header`.
+    /// the chunk of `shard_id` on top of `prev_block_hash`.
     fn collect_state_transition_data(
         &mut self,
-        chunk_header: &ShardChunkHeader,
+        prev_block_hash: &CryptoHash,
+        shard_id: ShardId,
         prev_chunk_header: &ShardChunkHeader,
     ) -> Result<(ChunkStateTransition, Vec<ChunkStateTransition>, CryptoHash), Error> {
         let store = self.chain.chain_store().store();
-        let shard_id = chunk_header.shard_id();
-        let epoch_id =
-            self.epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(prev_block_hash)?;
         let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
         let prev_chunk_height_included = prev_chunk_header.height_included();
         let mut prev_blocks = self.chain.get_blocks_until_height(
-            *chunk_header.prev_block_hash(),
+            *prev_block_hash,
             prev_chunk_height_included,
             true,
         )?;
@@ -157,7 +270,7 @@ impl Client {
                     let message = format!(
                         "Missing main transition state proof for block {main_block} and shard {shard_id}"
                     );
-                    if !cfg!(feature = "shadow_chunk_validation") {
+                    if !self.config.shadow_chunk_validation.get() {
                         log_assert_fail!("{message}");
                     }
                     Error::Other(message)
@@ -181,7 +294,7 @@ impl Client {
                     let message = format!(
                         "Missing implicit transition state proof for block {block_hash} and shard {shard_id}"
                     );
-                    if !cfg!(feature = "shadow_chunk_validation") {
+                    if !self.config.shadow_chunk_validation.get() {
                         log_assert_fail!("{message}");
                     }
                     Error::Other(message)
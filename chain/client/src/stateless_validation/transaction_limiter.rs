@@ -0,0 +1,102 @@
+use near_primitives::types::ShardId;
+use std::collections::{HashMap, VecDeque};
+
+/// Number of recent chunks of a shard whose witness sizes are taken into account.
+const NUM_RECENT_WITNESSES: usize = 8;
+
+/// The gas available to the new transactions is never reduced below this
+/// percentage of the transactions gas limit.
+const MIN_TRANSACTIONS_GAS_LIMIT_PERCENT: u64 = 10;
+
+/// Reduces the gas available to the new transactions in the chunks of a shard
+/// when the recent state witnesses of the shard approach the size limit.
+/// Most of a witness is made up of the storage proofs and receipts produced by
+/// the transactions included in earlier chunks, so once the witness is large
+/// it's too late to keep it in check by leaving out the new transactions of the
+/// chunk; the following chunks have to include fewer of them.
+pub(crate) struct TransactionLimiter {
+    /// Base sizes of the witnesses of the recently produced chunks, per shard.
+    recent_base_sizes: HashMap<ShardId, VecDeque<usize>>,
+}
+
+impl TransactionLimiter {
+    pub fn new() -> Self {
+        Self { recent_base_sizes: HashMap::new() }
+    }
+
+    /// Records the size of the witness of a chunk produced for `shard_id`
+    /// before adding the new transactions.
+    pub fn record_base_size(&mut self, shard_id: ShardId, base_size: usize) {
+        let sizes = self.recent_base_sizes.entry(shard_id).or_default();
+        if sizes.len() == NUM_RECENT_WITNESSES {
+            sizes.pop_front();
+        }
+        sizes.push_back(base_size);
+    }
+
+    /// Returns the percentage of the transactions gas limit which the next chunk
+    /// of `shard_id` may use. It's 100 while all recent witnesses are below
+    /// `threshold` and decreases linearly down to
+    /// `MIN_TRANSACTIONS_GAS_LIMIT_PERCENT` as the largest of them approaches
+    /// `soft_limit`.
+    pub fn transactions_gas_limit_percent(
+        &self,
+        shard_id: ShardId,
+        threshold: usize,
+        soft_limit: usize,
+    ) -> u64 {
+        let max_base_size = self
+            .recent_base_sizes
+            .get(&shard_id)
+            .and_then(|sizes| sizes.iter().max().copied())
+            .unwrap_or(0);
+        if max_base_size <= threshold {
+            return 100;
+        }
+        if max_base_size >= soft_limit {
+            return MIN_TRANSACTIONS_GAS_LIMIT_PERCENT;
+        }
+        let excess = (max_base_size - threshold) as u128;
+        let range = (soft_limit - threshold) as u128;
+        let reduction = (100 - MIN_TRANSACTIONS_GAS_LIMIT_PERCENT) as u128 * excess / range;
+        100 - reduction as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TransactionLimiter, MIN_TRANSACTIONS_GAS_LIMIT_PERCENT, NUM_RECENT_WITNESSES};
+
+    #[test]
+    fn test_transactions_gas_limit_percent() {
+        let mut limiter = TransactionLimiter::new();
+        assert_eq!(limiter.transactions_gas_limit_percent(0, 1000, 2000), 100);
+
+        limiter.record_base_size(0, 1000);
+        assert_eq!(limiter.transactions_gas_limit_percent(0, 1000, 2000), 100);
+        limiter.record_base_size(0, 1500);
+        assert_eq!(limiter.transactions_gas_limit_percent(0, 1000, 2000), 55);
+        limiter.record_base_size(0, 3000);
+        assert_eq!(
+            limiter.transactions_gas_limit_percent(0, 1000, 2000),
+            MIN_TRANSACTIONS_GAS_LIMIT_PERCENT
+        );
+        // Other shards are not affected.
+        assert_eq!(limiter.transactions_gas_limit_percent(1, 1000, 2000), 100);
+    }
+
+    #[test]
+    fn test_only_recent_witnesses_count() {
+        let mut limiter = TransactionLimiter::new();
+        limiter.record_base_size(0, 3000);
+        for _ in 1..NUM_RECENT_WITNESSES {
+            limiter.record_base_size(0, 500);
+        }
+        assert_eq!(
+            limiter.transactions_gas_limit_percent(0, 1000, 2000),
+            MIN_TRANSACTIONS_GAS_LIMIT_PERCENT
+        );
+        limiter.record_base_size(0, 500);
+        assert_eq!(limiter.transactions_gas_limit_percent(0, 1000, 2000), 100);
+    }
+}
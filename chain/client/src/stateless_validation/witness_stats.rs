@@ -0,0 +1,194 @@
+use near_primitives::sharding::ChunkHash;
+use near_primitives::stateless_validation::ChunkStateWitness;
+use near_primitives::types::ShardId;
+use near_primitives::views::{
+    ShardWitnessStatsView, WitnessSizeBreakdownView, WitnessStatsEntryView, WitnessStatsSourceView,
+    WitnessStatsView,
+};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of the latest witnesses kept per shard.
+const WITNESS_STATS_MAX_ENTRIES_PER_SHARD: usize = 32;
+
+/// Keeps the sizes and validation times of the latest state witnesses of every
+/// shard, so that they can be queried with the `EXPERIMENTAL_witness_stats`
+/// RPC method. The same values are recorded into metrics, this only keeps
+/// the recent ones around.
+#[derive(Default)]
+pub struct WitnessStatsTracker {
+    shards: Mutex<BTreeMap<ShardId, VecDeque<WitnessStatsEntryView>>>,
+}
+
+impl WitnessStatsTracker {
+    /// Records a witness received by this node as a chunk validator.
+    pub fn record_observed_witness(
+        &self,
+        witness: &ChunkStateWitness,
+        raw_size: usize,
+        encoded_size: usize,
+    ) {
+        self.record(witness, WitnessStatsSourceView::Observed, raw_size, encoded_size, None);
+    }
+
+    /// Records a witness produced by shadow validation, including the sizes of its parts.
+    pub fn record_shadow_witness(
+        &self,
+        witness: &ChunkStateWitness,
+        raw_size: usize,
+        encoded_size: usize,
+    ) {
+        let size_breakdown = match witness_size_breakdown(witness) {
+            Ok(size_breakdown) => Some(size_breakdown),
+            Err(err) => {
+                tracing::warn!(
+                    target: "client",
+                    ?err,
+                    "Failed to compute the witness size breakdown"
+                );
+                None
+            }
+        };
+        self.record(
+            witness,
+            WitnessStatsSourceView::Shadow,
+            raw_size,
+            encoded_size,
+            size_breakdown,
+        );
+    }
+
+    /// Sets the validation time of a recorded witness. Does nothing if the witness
+    /// was already evicted by newer witnesses of the shard.
+    pub fn record_validation_time(
+        &self,
+        shard_id: ShardId,
+        chunk_hash: &ChunkHash,
+        source: WitnessStatsSourceView,
+        validation_time: Duration,
+    ) {
+        let mut shards = self.shards.lock().unwrap();
+        let Some(entries) = shards.get_mut(&shard_id) else {
+            return;
+        };
+        if let Some(entry) = entries
+            .iter_mut()
+            .rev()
+            .find(|entry| entry.chunk_hash == chunk_hash.0 && entry.source == source)
+        {
+            entry.validation_time_ms = Some(validation_time.as_millis() as u64);
+        }
+    }
+
+    /// Returns the statistics of the given shard, or of all shards if `shard_id` is None.
+    pub fn get_stats(&self, shard_id: Option<ShardId>) -> WitnessStatsView {
+        let shards = self.shards.lock().unwrap();
+        let shards = shards
+            .iter()
+            .filter(|(id, _)| shard_id.map_or(true, |shard_id| **id == shard_id))
+            .map(|(id, entries)| ShardWitnessStatsView {
+                shard_id: *id,
+                witnesses: entries.iter().cloned().collect(),
+            })
+            .collect();
+        WitnessStatsView { shards }
+    }
+
+    fn record(
+        &self,
+        witness: &ChunkStateWitness,
+        source: WitnessStatsSourceView,
+        raw_size: usize,
+        encoded_size: usize,
+        size_breakdown: Option<WitnessSizeBreakdownView>,
+    ) {
+        let chunk_header = &witness.chunk_header;
+        let entry = WitnessStatsEntryView {
+            chunk_hash: chunk_header.chunk_hash().0,
+            height: chunk_header.height_created(),
+            source,
+            raw_size: raw_size as u64,
+            encoded_size: encoded_size as u64,
+            size_breakdown,
+            validation_time_ms: None,
+        };
+        let mut shards = self.shards.lock().unwrap();
+        let entries = shards.entry(chunk_header.shard_id()).or_default();
+        if entries.len() == WITNESS_STATS_MAX_ENTRIES_PER_SHARD {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+fn witness_size_breakdown(
+    witness: &ChunkStateWitness,
+) -> Result<WitnessSizeBreakdownView, std::io::Error> {
+    Ok(WitnessSizeBreakdownView {
+        main_state_transition: borsh::to_vec(&witness.main_state_transition)?.len() as u64,
+        new_transactions: borsh::to_vec(&witness.new_transactions)?.len() as u64,
+        new_transactions_validation_state: borsh::to_vec(
+            &witness.new_transactions_validation_state,
+        )?
+        .len() as u64,
+        source_receipt_proofs: borsh::to_vec(&witness.source_receipt_proofs)?.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WitnessStatsTracker, WITNESS_STATS_MAX_ENTRIES_PER_SHARD};
+    use near_primitives::hash::hash;
+    use near_primitives::stateless_validation::ChunkStateWitness;
+    use near_primitives::types::{BlockHeight, ShardId};
+    use near_primitives::views::WitnessStatsSourceView;
+    use std::time::Duration;
+
+    fn make_witness(height: BlockHeight, shard_id: ShardId) -> ChunkStateWitness {
+        ChunkStateWitness::new_dummy(height, shard_id, hash(&height.to_be_bytes()))
+    }
+
+    #[test]
+    fn test_witness_stats() {
+        let tracker = WitnessStatsTracker::default();
+        let observed = make_witness(10, 0);
+        tracker.record_observed_witness(&observed, 1000, 400);
+        tracker.record_shadow_witness(&make_witness(10, 1), 2000, 800);
+        tracker.record_validation_time(
+            0,
+            &observed.chunk_header.chunk_hash(),
+            WitnessStatsSourceView::Observed,
+            Duration::from_millis(120),
+        );
+
+        let stats = tracker.get_stats(None);
+        assert_eq!(stats.shards.len(), 2);
+        let observed_entry = &stats.shards[0].witnesses[0];
+        assert_eq!(observed_entry.source, WitnessStatsSourceView::Observed);
+        assert_eq!((observed_entry.raw_size, observed_entry.encoded_size), (1000, 400));
+        assert_eq!(observed_entry.validation_time_ms, Some(120));
+        assert!(observed_entry.size_breakdown.is_none());
+        let shadow_entry = &stats.shards[1].witnesses[0];
+        assert_eq!(shadow_entry.source, WitnessStatsSourceView::Shadow);
+        assert!(shadow_entry.size_breakdown.is_some());
+        assert_eq!(shadow_entry.validation_time_ms, None);
+
+        let stats = tracker.get_stats(Some(1));
+        assert_eq!(stats.shards.len(), 1);
+        assert_eq!(stats.shards[0].shard_id, 1);
+    }
+
+    #[test]
+    fn test_witness_stats_keeps_latest_entries() {
+        let tracker = WitnessStatsTracker::default();
+        let num_witnesses = WITNESS_STATS_MAX_ENTRIES_PER_SHARD as u64 + 5;
+        for height in 0..num_witnesses {
+            tracker.record_observed_witness(&make_witness(height, 0), 100, 50);
+        }
+        let witnesses = tracker.get_stats(Some(0)).shards.remove(0).witnesses;
+        assert_eq!(witnesses.len(), WITNESS_STATS_MAX_ENTRIES_PER_SHARD);
+        assert_eq!(witnesses.first().unwrap().height, 5);
+        assert_eq!(witnesses.last().unwrap().height, num_witnesses - 1);
+    }
+}
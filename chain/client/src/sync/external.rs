@@ -1,5 +1,6 @@
 use crate::metrics;
 use futures::TryStreamExt;
+use near_primitives::state_sync::StatePartCompression;
 use near_primitives::types::{EpochId, ShardId};
 use std::io::{Read, Write};
 use std::path::PathBuf;
@@ -11,6 +12,7 @@ use std::time::Instant;
 pub enum StateFileType {
     StatePart { part_id: u64, num_parts: u64 },
     StateHeader,
+    StateManifest,
 }
 
 impl ToString for StateFileType {
@@ -18,6 +20,7 @@ impl ToString for StateFileType {
         match self {
             StateFileType::StatePart { .. } => StateFileType::part_str(),
             StateFileType::StateHeader => StateFileType::header_str(),
+            StateFileType::StateManifest => StateFileType::manifest_str(),
         }
     }
 }
@@ -31,12 +34,17 @@ impl StateFileType {
         String::from("header")
     }
 
+    pub fn manifest_str() -> String {
+        String::from("manifest")
+    }
+
     pub fn filename(&self) -> String {
         match self {
             StateFileType::StatePart { part_id, num_parts } => {
                 format!("state_part_{:06}_of_{:06}", part_id, num_parts)
             }
             StateFileType::StateHeader => "header".to_string(),
+            StateFileType::StateManifest => "manifest".to_string(),
         }
     }
 }
@@ -58,11 +66,38 @@ pub enum ExternalConnection {
         reqwest_client: Arc<reqwest::Client>,
         bucket: String,
     },
+    Azure {
+        reqwest_client: Arc<reqwest::Client>,
+        // Name of the storage account.
+        account: String,
+        container: String,
+        // Shared access signature appended to every request. Required for
+        // uploading and listing state parts, and for downloading them unless
+        // the container allows anonymous reads.
+        sas_token: Option<String>,
+    },
+    /// State dump served by another node, see `start_state_parts_server`.
+    /// Read-only.
+    Http {
+        reqwest_client: Arc<reqwest::Client>,
+        // Base URL of the server, without the trailing slash.
+        url: String,
+    },
 }
 
 const GCS_ENCODE_SET: &percent_encoding::AsciiSet =
     &percent_encoding::NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_');
 
+/// Paths keep their slashes, e.g. Azure treats them as virtual directories.
+const PATH_ENCODE_SET: &percent_encoding::AsciiSet = &GCS_ENCODE_SET.remove(b'/');
+
+/// Version of the Azure Blob Storage REST API used for all requests.
+const AZURE_API_VERSION: &str = "2021-08-06";
+
+/// Environment variable with the shared access signature used to access
+/// Azure Blob Storage when no credentials file is given.
+pub const AZURE_SAS_TOKEN_ENV_VAR: &str = "AZURE_STORAGE_SAS_TOKEN";
+
 impl ExternalConnection {
     pub async fn get_file(
         &self,
@@ -110,6 +145,42 @@ impl ExternalConnection {
                     }
                 }
             }
+            ExternalConnection::Azure { reqwest_client, account, container, sas_token } => {
+                let url = azure_blob_url(account, container, location, sas_token.as_deref());
+                let response = send_azure_request(reqwest_client.get(&url)).await;
+
+                match response {
+                    Err(e) => {
+                        tracing::debug!(target: "sync", %shard_id, location, error = ?e, "Azure state_part request failed");
+                        Err(e.into())
+                    }
+                    Ok(r) => {
+                        let bytes = r.bytes().await.map_err(reqwest::Error::without_url)?.to_vec();
+                        tracing::debug!(target: "sync", %shard_id, location, num_bytes = bytes.len(), "Azure state_part request finished");
+                        Ok(bytes)
+                    }
+                }
+            }
+            ExternalConnection::Http { reqwest_client, url } => {
+                let url = format!(
+                    "{}/{}",
+                    url,
+                    percent_encoding::percent_encode(location.as_bytes(), PATH_ENCODE_SET)
+                );
+                let response = reqwest_client.get(&url).send().await?.error_for_status();
+
+                match response {
+                    Err(e) => {
+                        tracing::debug!(target: "sync", %shard_id, location, error = ?e, "HTTP state_part request failed");
+                        Err(e.into())
+                    }
+                    Ok(r) => {
+                        let bytes = r.bytes().await?.to_vec();
+                        tracing::debug!(target: "sync", %shard_id, location, num_bytes = bytes.len(), "HTTP state_part request finished");
+                        Ok(bytes)
+                    }
+                }
+            }
         }
     }
 
@@ -168,6 +239,22 @@ impl ExternalConnection {
                 tracing::debug!(target: "state_sync_dump", shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to GCS");
                 Ok(())
             }
+            ExternalConnection::Azure { reqwest_client, account, container, sas_token } => {
+                let url = azure_blob_url(account, container, location, sas_token.as_deref());
+                send_azure_request(
+                    reqwest_client
+                        .put(&url)
+                        .header("x-ms-blob-type", "BlockBlob")
+                        .header("Content-Type", "application/octet-stream")
+                        .body(data.to_vec()),
+                )
+                .await?;
+                tracing::debug!(target: "state_sync_dump", shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to Azure");
+                Ok(())
+            }
+            ExternalConnection::Http { .. } => {
+                anyhow::bail!("HTTP external storage is read-only, dump the state to the filesystem of the serving node instead")
+            }
         }
     }
 
@@ -179,7 +266,7 @@ impl ExternalConnection {
         return path_buf.file_name().unwrap().to_str().unwrap().to_string();
     }
 
-    /// When using GCS external connection, this function requires credentials.
+    /// When using GCS or Azure external connection, this function requires credentials.
     /// Thus, this function shouldn't be used for sync node that is expected to operate anonymously.
     /// Only dump nodes should use this function.
     pub async fn list_objects(
@@ -238,6 +325,37 @@ impl ExternalConnection {
                     .flatten()
                     .collect())
             }
+            ExternalConnection::Azure { reqwest_client, account, container, sas_token } => {
+                let prefix = format!("{}/", directory_path);
+                tracing::debug!(target: "state_sync_dump", shard_id, ?directory_path, "List state parts in Azure");
+                let mut file_names = vec![];
+                let mut marker = None;
+                loop {
+                    let url = azure_list_url(
+                        account,
+                        container,
+                        &prefix,
+                        marker.as_deref(),
+                        sas_token.as_deref(),
+                    );
+                    let response = send_azure_request(reqwest_client.get(&url))
+                        .await?
+                        .text()
+                        .await
+                        .map_err(reqwest::Error::without_url)?;
+                    let (names, next_marker) = parse_azure_blob_list(&response);
+                    file_names
+                        .extend(names.into_iter().map(Self::extract_file_name_from_full_path));
+                    match next_marker {
+                        Some(next_marker) => marker = Some(next_marker),
+                        None => break,
+                    }
+                }
+                Ok(file_names)
+            }
+            ExternalConnection::Http { .. } => {
+                anyhow::bail!("listing is not supported by HTTP external storage")
+            }
         }
     }
 
@@ -249,26 +367,89 @@ impl ExternalConnection {
         epoch_id: &EpochId,
         epoch_height: u64,
     ) -> Result<bool, anyhow::Error> {
-        let file_type = StateFileType::StateHeader;
+        self.is_file_stored_for_epoch(
+            shard_id,
+            chain_id,
+            epoch_id,
+            epoch_height,
+            &StateFileType::StateHeader,
+        )
+        .await
+    }
+
+    /// Check if the state parts manifest exists in the external storage.
+    pub async fn is_state_parts_manifest_stored_for_epoch(
+        &self,
+        shard_id: ShardId,
+        chain_id: &String,
+        epoch_id: &EpochId,
+        epoch_height: u64,
+    ) -> Result<bool, anyhow::Error> {
+        self.is_file_stored_for_epoch(
+            shard_id,
+            chain_id,
+            epoch_id,
+            epoch_height,
+            &StateFileType::StateManifest,
+        )
+        .await
+    }
+
+    async fn is_file_stored_for_epoch(
+        &self,
+        shard_id: ShardId,
+        chain_id: &String,
+        epoch_id: &EpochId,
+        epoch_height: u64,
+        file_type: &StateFileType,
+    ) -> Result<bool, anyhow::Error> {
         let directory_path = external_storage_location_directory(
             chain_id,
             epoch_id,
             epoch_height,
             shard_id,
-            &file_type,
+            file_type,
         );
         let file_names = self.list_objects(shard_id, &directory_path).await?;
-        let header_exits = file_names.contains(&file_type.filename());
+        let file_exists = file_names.contains(&file_type.filename());
         tracing::debug!(
             target: "state_sync_dump",
             ?directory_path,
-            "{}",
-            match header_exits {
-                true => "Header has already been dumped.",
-                false => "Header has not been dumped.",
-            }
+            ?file_type,
+            file_exists,
+            "Checked whether the file has already been dumped."
         );
-        Ok(header_exits)
+        Ok(file_exists)
+    }
+}
+
+/// Magic number that starts every zstd frame.
+/// Borsh-serialized state parts always start with a zero enum tag, therefore
+/// compressed and uncompressed parts can't be confused.
+const ZSTD_MAGIC_NUMBER: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression level used for state parts. Higher levels barely improve the
+/// ratio of trie data while making the dump noticeably slower.
+const STATE_PART_ZSTD_LEVEL: i32 = 3;
+
+/// Prepares a state part for writing to external storage.
+pub fn compress_state_part(
+    part: &[u8],
+    compression: StatePartCompression,
+) -> Result<Vec<u8>, std::io::Error> {
+    match compression {
+        StatePartCompression::None => Ok(part.to_vec()),
+        StatePartCompression::Zstd => zstd::encode_all(part, STATE_PART_ZSTD_LEVEL),
+    }
+}
+
+/// Converts a state part file read from external storage into a borsh-serialized state part.
+/// Works for both compressed and uncompressed files.
+pub fn decompress_state_part(data: Vec<u8>) -> Result<Vec<u8>, std::io::Error> {
+    if data.starts_with(&ZSTD_MAGIC_NUMBER) {
+        zstd::decode_all(data.as_slice())
+    } else {
+        Ok(data)
     }
 }
 
@@ -309,7 +490,7 @@ pub fn location_prefix(
             "chain_id={}/epoch_height={}/epoch_id={}/shard_id={}",
             chain_id, epoch_height, epoch_id.0, shard_id
         ),
-        StateFileType::StateHeader => format!(
+        StateFileType::StateHeader | StateFileType::StateManifest => format!(
             "chain_id={}/epoch_height={}/epoch_id={}/headers/shard_id={}",
             chain_id, epoch_height, epoch_id.0, shard_id
         ),
@@ -403,13 +584,134 @@ fn create_bucket(
     Ok(bucket)
 }
 
+/// Reads the shared access signature for Azure Blob Storage from the
+/// credentials file, or from the `AZURE_STORAGE_SAS_TOKEN` environment
+/// variable if no file is given. Without either, the container is accessed
+/// anonymously.
+pub fn create_azure_connection(
+    account: &str,
+    container: &str,
+    timeout: Duration,
+    credentials_file: Option<PathBuf>,
+) -> Result<ExternalConnection, anyhow::Error> {
+    let sas_token = match credentials_file {
+        Some(credentials_file) => Some(std::fs::read_to_string(credentials_file)?),
+        None => std::env::var(AZURE_SAS_TOKEN_ENV_VAR).ok(),
+    }
+    .map(|sas_token| sas_token.trim().trim_start_matches('?').to_string())
+    .filter(|sas_token| !sas_token.is_empty());
+    // Ensure requests finish in finite amount of time.
+    let reqwest_client = reqwest::Client::builder().timeout(timeout).build()?;
+    Ok(ExternalConnection::Azure {
+        reqwest_client: Arc::new(reqwest_client),
+        account: account.to_string(),
+        container: container.to_string(),
+        sas_token,
+    })
+}
+
+/// Sends a request to Azure Blob Storage and checks its status. The request
+/// URLs carry the shared access signature in the query string, so the URL is
+/// removed from the errors, which end up in logs.
+async fn send_azure_request(
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, reqwest::Error> {
+    request
+        .header("x-ms-version", AZURE_API_VERSION)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(reqwest::Error::without_url)
+}
+
+fn azure_container_url(account: &str, container: &str) -> String {
+    format!(
+        "https://{}.blob.core.windows.net/{}",
+        percent_encoding::percent_encode(account.as_bytes(), GCS_ENCODE_SET),
+        percent_encoding::percent_encode(container.as_bytes(), GCS_ENCODE_SET),
+    )
+}
+
+fn azure_blob_url(account: &str, container: &str, blob: &str, sas_token: Option<&str>) -> String {
+    let mut url = format!(
+        "{}/{}",
+        azure_container_url(account, container),
+        percent_encoding::percent_encode(blob.as_bytes(), PATH_ENCODE_SET),
+    );
+    if let Some(sas_token) = sas_token {
+        url.push('?');
+        url.push_str(sas_token);
+    }
+    url
+}
+
+/// URL of a page of the List Blobs operation, listing the blobs directly
+/// under `prefix` starting at `marker`.
+fn azure_list_url(
+    account: &str,
+    container: &str,
+    prefix: &str,
+    marker: Option<&str>,
+    sas_token: Option<&str>,
+) -> String {
+    let mut url = format!(
+        "{}?restype=container&comp=list&delimiter=%2F&prefix={}",
+        azure_container_url(account, container),
+        percent_encoding::percent_encode(prefix.as_bytes(), GCS_ENCODE_SET),
+    );
+    if let Some(marker) = marker {
+        url.push_str("&marker=");
+        url.extend(percent_encoding::percent_encode(marker.as_bytes(), GCS_ENCODE_SET));
+    }
+    if let Some(sas_token) = sas_token {
+        url.push('&');
+        url.push_str(sas_token);
+    }
+    url
+}
+
+/// Extracts the names of the blobs and the marker of the next page, if any,
+/// from a response of the List Blobs operation. The `BlobPrefix` entries of
+/// the virtual subdirectories are skipped.
+fn parse_azure_blob_list(xml: &str) -> (Vec<String>, Option<String>) {
+    let mut names = vec![];
+    let mut rest = xml;
+    while let Some(start) = rest.find("<Blob>") {
+        rest = &rest[start + "<Blob>".len()..];
+        let end = rest.find("</Blob>").unwrap_or(rest.len());
+        if let Some(name) = xml_element_text(&rest[..end], "Name") {
+            names.push(name);
+        }
+        rest = &rest[end..];
+    }
+    // An empty marker is returned as `<NextMarker />` on the last page.
+    let next_marker = xml_element_text(xml, "NextMarker").filter(|marker| !marker.is_empty());
+    (names, next_marker)
+}
+
+fn xml_element_text(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(
+        xml[start..end]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
 #[cfg(test)]
 mod test {
     use crate::sync::external::{
+        azure_blob_url, azure_list_url, compress_state_part, decompress_state_part,
         get_num_parts_from_filename, get_part_id_from_filename, is_part_filename,
-        ExternalConnection, StateFileType,
+        parse_azure_blob_list, send_azure_request, ExternalConnection, StateFileType,
     };
     use near_o11y::testonly::init_test_logger;
+    use near_primitives::state_sync::StatePartCompression;
     use rand::distributions::{Alphanumeric, DistString};
 
     fn random_string(rand_len: usize) -> String {
@@ -429,6 +731,71 @@ mod test {
         assert_eq!(get_part_id_from_filename("123123"), None);
     }
 
+    #[test]
+    fn test_state_part_compression() {
+        // Borsh-serialized state parts start with a zero enum tag.
+        let part: Vec<u8> = [0u8, 3, 0, 0, 0].into_iter().chain([7u8; 1000]).collect();
+
+        let raw = compress_state_part(&part, StatePartCompression::None).unwrap();
+        assert_eq!(raw, part);
+        assert_eq!(decompress_state_part(raw).unwrap(), part);
+
+        let compressed = compress_state_part(&part, StatePartCompression::Zstd).unwrap();
+        assert!(compressed.len() < part.len());
+        assert_eq!(decompress_state_part(compressed).unwrap(), part);
+    }
+
+    #[test]
+    fn test_azure_urls() {
+        let location =
+            "chain_id=test/epoch_height=1/epoch_id=abc/shard_id=0/state_part_000000_of_000001";
+        assert_eq!(
+            azure_blob_url("account", "state-parts", location, None),
+            "https://account.blob.core.windows.net/state-parts/chain_id%3Dtest/epoch_height%3D1/epoch_id%3Dabc/shard_id%3D0/state_part_000000_of_000001"
+        );
+        assert_eq!(
+            azure_blob_url("account", "state-parts", "a/b", Some("sv=2021&sig=x%2By")),
+            "https://account.blob.core.windows.net/state-parts/a/b?sv=2021&sig=x%2By"
+        );
+        assert_eq!(
+            azure_list_url("account", "state-parts", "a=1/b/", Some("2!72!abc"), Some("sig=x")),
+            "https://account.blob.core.windows.net/state-parts?restype=container&comp=list&delimiter=%2F&prefix=a%3D1%2Fb%2F&marker=2%2172%21abc&sig=x"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_azure_request_error_hides_sas_token() {
+        // Nothing listens on the port, so the request fails to connect.
+        let url = azure_blob_url("account", "state-parts", "a/b", Some("sig=secret"))
+            .replace("https://account.blob.core.windows.net", "http://127.0.0.1:1");
+        assert!(url.contains("sig=secret"));
+        let err = send_azure_request(reqwest::Client::new().get(&url)).await.unwrap_err();
+        assert!(err.url().is_none());
+        assert!(!format!("{err:?}").contains("secret"));
+        assert!(!anyhow::Error::from(err).to_string().contains("secret"));
+    }
+
+    #[test]
+    fn test_parse_azure_blob_list() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<EnumerationResults ServiceEndpoint="https://account.blob.core.windows.net/" ContainerName="state-parts">
+  <Prefix>dir/</Prefix>
+  <Delimiter>/</Delimiter>
+  <Blobs>
+    <Blob><Name>dir/state_part_000000_of_000002</Name><Properties><Content-Length>10</Content-Length></Properties></Blob>
+    <BlobPrefix><Name>dir/subdir/</Name></BlobPrefix>
+    <Blob><Name>dir/a&amp;b</Name><Properties /></Blob>
+  </Blobs>
+  <NextMarker>2!72!marker</NextMarker>
+</EnumerationResults>"#;
+        let (names, next_marker) = parse_azure_blob_list(xml);
+        assert_eq!(names, vec!["dir/state_part_000000_of_000002", "dir/a&b"]);
+        assert_eq!(next_marker.as_deref(), Some("2!72!marker"));
+
+        let xml = "<EnumerationResults><Blobs /><NextMarker /></EnumerationResults>";
+        assert_eq!(parse_azure_blob_list(xml), (vec![], None));
+    }
+
     /// This test should be ignored by default, as it requires gcloud credentials to run.
     /// Specify the path to service account json  in `SERVICE_ACCOUNT` variable to run the test.
     #[test]
@@ -1,3 +1,4 @@
+use crate::metrics;
 use near_async::messaging::CanSend;
 use near_async::time::{Clock, Duration, Utc};
 use near_chain::{Chain, ChainStoreAccess};
@@ -6,10 +7,12 @@ use near_network::types::PeerManagerMessageRequest;
 use near_network::types::{HighestHeightPeerInfo, NetworkRequests, PeerManagerAdapter};
 use near_primitives::block::Tip;
 use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
 use near_primitives::types::BlockHeight;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use std::cmp::min;
+use std::collections::HashMap;
 use tracing::{debug, warn};
 
 /// Maximum number of block headers send over the network.
@@ -20,6 +23,9 @@ pub const MAX_BLOCK_HEADER_HASHES: usize = 20;
 
 pub const NS_PER_SECOND: u128 = 1_000_000_000;
 
+/// Weight of the latest observation in the moving averages of `HeaderSyncStats`.
+const STATS_SMOOTHING_FACTOR: f64 = 0.25;
+
 /// Progress of downloading the currently requested batch of headers.
 struct BatchProgress {
     /// An intermediate timeout by which a certain number of headers is expected.
@@ -31,6 +37,42 @@ struct BatchProgress {
     highest_height_of_peers: BlockHeight,
 }
 
+/// Observed performance of header sync, used to adapt how headers are requested.
+#[derive(Default)]
+struct HeaderSyncStats {
+    /// When headers were requested from each peer, for requests that weren't answered yet.
+    pending_requests: HashMap<PeerId, Utc>,
+    /// Moving average of how long it takes a peer to respond to a request.
+    response_latency: Option<Duration>,
+    /// Moving average of how long it takes to validate a full batch of headers.
+    validation_time: Option<Duration>,
+}
+
+impl HeaderSyncStats {
+    fn update_average(average: &mut Option<Duration>, sample: Duration) {
+        *average = Some(match *average {
+            Some(average) => {
+                average * (1.0 - STATS_SMOOTHING_FACTOR) + sample * STATS_SMOOTHING_FACTOR
+            }
+            None => sample,
+        });
+    }
+
+    /// Returns how many peers to request the same batch of headers from.
+    /// If peers take longer to respond than it takes to validate a batch,
+    /// the node is waiting for the network. Requesting the batch from several
+    /// peers and using the first response hides the slow peers. Otherwise the
+    /// extra responses would only waste bandwidth.
+    fn num_peers_to_query(&self, max_peers: usize) -> usize {
+        let (Some(latency), Some(validation_time)) = (self.response_latency, self.validation_time)
+        else {
+            return 1;
+        };
+        let ratio = latency.as_seconds_f64() / validation_time.as_seconds_f64().max(f64::EPSILON);
+        (ratio.ceil() as usize).clamp(1, max_peers.max(1))
+    }
+}
+
 /// Helper to keep track of sync headers.
 /// Handles major re-orgs by finding closest header that matches and re-downloading headers from that point.
 pub struct HeaderSync {
@@ -64,6 +106,19 @@ pub struct HeaderSync {
     /// Expected height when node will be automatically shut down, so header
     /// sync can be stopped.
     shutdown_height: near_chain_configs::MutableConfigValue<Option<BlockHeight>>,
+
+    /// Maximum number of peers to request the same batch of headers from.
+    max_peers_per_request: usize,
+
+    /// Peers with the highest height as of the last call of `run()`.
+    /// Used to request the next batch of headers without waiting for `run()`.
+    highest_height_peers: Vec<HighestHeightPeerInfo>,
+
+    /// Height of the header after which the headers were last requested.
+    /// Used to request every batch of headers only once.
+    last_requested_height: BlockHeight,
+
+    stats: HeaderSyncStats,
 }
 
 impl HeaderSync {
@@ -75,6 +130,7 @@ impl HeaderSync {
         stall_ban_timeout: Duration,
         expected_height_per_second: u64,
         shutdown_height: near_chain_configs::MutableConfigValue<Option<BlockHeight>>,
+        max_peers_per_request: usize,
     ) -> Self {
         HeaderSync {
             clock: clock.clone(),
@@ -92,6 +148,10 @@ impl HeaderSync {
             stall_ban_timeout,
             expected_height_per_second,
             shutdown_height,
+            max_peers_per_request,
+            highest_height_peers: vec![],
+            last_requested_height: 0,
+            stats: HeaderSyncStats::default(),
         }
     }
 
@@ -109,6 +169,7 @@ impl HeaderSync {
             tracing::debug_span!(target: "sync", "run_sync", sync_type = "HeaderSync").entered();
         let head = chain.head()?;
         let header_head = chain.header_head()?;
+        self.highest_height_peers = highest_height_peers.to_vec();
 
         // Check if we need to start a new request for a batch of header.
         if !self.header_sync_due(sync_status, &header_head, highest_height) {
@@ -152,17 +213,86 @@ impl HeaderSync {
         });
 
         self.syncing_peer = None;
-        // Pick a new random peer to request the next batch of headers.
-        if let Some(peer) = highest_height_peers.choose(&mut thread_rng()).cloned() {
-            let shutdown_height = self.shutdown_height.get().unwrap_or(u64::MAX);
-            let highest_height = peer.highest_block_height.min(shutdown_height);
-            if highest_height > header_head.height {
-                self.syncing_peer = self.request_headers(chain, peer);
+        // Pick new random peers to request the next batch of headers.
+        let peers = self.peers_ahead_of(header_head.height);
+        if !peers.is_empty() {
+            if let Ok(locator) = self.get_locator(chain) {
+                self.syncing_peer = self.request_headers(locator, &peers);
+                self.last_requested_height = header_head.height;
             }
         }
         Ok(())
     }
 
+    /// Records how long the peer took to respond.
+    pub fn on_headers_received(&mut self, peer_id: &PeerId) {
+        if let Some(requested_at) = self.stats.pending_requests.remove(peer_id) {
+            let latency = self.clock.now_utc() - requested_at;
+            metrics::HEADER_SYNC_RESPONSE_LATENCY.observe(latency.as_seconds_f64());
+            HeaderSyncStats::update_average(&mut self.stats.response_latency, latency);
+        }
+    }
+
+    /// Records how long it took to validate a batch of headers.
+    /// If it was a full batch, requests the next batch right away rather than
+    /// waiting for `run()`. The next batch is only requested once the previous
+    /// one is validated, so that peers are never asked for the headers after
+    /// a batch which turns out to be invalid.
+    pub fn on_headers_validated(
+        &mut self,
+        sync_status: &SyncStatus,
+        chain: &Chain,
+        num_headers: usize,
+        elapsed: Duration,
+    ) {
+        metrics::HEADER_SYNC_VALIDATION_TIME.observe(elapsed.as_seconds_f64());
+        // Only full batches are comparable to each other.
+        if (num_headers as u64) < MAX_BLOCK_HEADERS {
+            return;
+        }
+        HeaderSyncStats::update_average(&mut self.stats.validation_time, elapsed);
+
+        if !matches!(sync_status, SyncStatus::HeaderSync { .. }) {
+            return;
+        }
+        let Ok(header_head) = chain.header_head() else {
+            return;
+        };
+        // The next batch was already requested, e.g. if this is a response to
+        // a batch requested from several peers.
+        if header_head.height <= self.last_requested_height {
+            return;
+        }
+        let peers = self.peers_ahead_of(header_head.height);
+        if peers.is_empty() {
+            return;
+        }
+        let Ok(locator) = self.get_locator(chain) else {
+            return;
+        };
+        debug!(target: "sync", header_head_height = header_head.height, "Sync: requesting the next batch of headers");
+        metrics::HEADER_SYNC_PIPELINED_REQUESTS.inc();
+        let now = self.clock.now_utc();
+        self.batch_progress = BatchProgress {
+            timeout: now + self.initial_timeout,
+            expected_height: self.compute_expected_height(header_head.height, self.initial_timeout),
+            header_head_height: header_head.height,
+            highest_height_of_peers: self.batch_progress.highest_height_of_peers,
+        };
+        self.syncing_peer = self.request_headers(locator, &peers);
+        self.last_requested_height = header_head.height;
+    }
+
+    /// Returns the peers that have headers beyond the given height.
+    fn peers_ahead_of(&self, height: BlockHeight) -> Vec<HighestHeightPeerInfo> {
+        let shutdown_height = self.shutdown_height.get().unwrap_or(u64::MAX);
+        self.highest_height_peers
+            .iter()
+            .filter(|peer| peer.highest_block_height.min(shutdown_height) > height)
+            .cloned()
+            .collect()
+    }
+
     /// Returns the height that we expect to reach starting from `old_height` after `time_delta`.
     fn compute_expected_height(
         &self,
@@ -313,23 +443,29 @@ impl HeaderSync {
         }
     }
 
-    /// Request headers from a given peer to advance the chain.
+    /// Requests headers from random peers to advance the chain.
+    /// The number of peers adapts to the observed latency and validation time.
+    /// Returns one of the peers, to be held accountable if sync stalls.
     fn request_headers(
         &mut self,
-        chain: &Chain,
-        peer: HighestHeightPeerInfo,
+        locator: Vec<CryptoHash>,
+        peers: &[HighestHeightPeerInfo],
     ) -> Option<HighestHeightPeerInfo> {
-        if let Ok(locator) = self.get_locator(chain) {
+        let num_peers = self.stats.num_peers_to_query(self.max_peers_per_request);
+        metrics::HEADER_SYNC_PEERS_PER_REQUEST.set(num_peers as i64);
+        let now = self.clock.now_utc();
+        let peers: Vec<_> = peers.choose_multiple(&mut thread_rng(), num_peers).cloned().collect();
+        for peer in &peers {
             debug!(target: "sync", "Sync: request headers: asking {} for headers, {:?}", peer.peer_info.id, locator);
             self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
                 NetworkRequests::BlockHeadersRequest {
-                    hashes: locator,
+                    hashes: locator.clone(),
                     peer_id: peer.peer_info.id.clone(),
                 },
             ));
-            return Some(peer);
+            self.stats.pending_requests.insert(peer.peer_info.id.clone(), now);
         }
-        None
+        peers.into_iter().next()
     }
 
     // The remote side will return MAX_BLOCK_HEADERS headers, starting from the first hash in
@@ -386,7 +522,7 @@ mod test {
     use near_async::time::{Clock, Duration, FakeClock, Utc};
     use near_chain::test_utils::{process_block_sync, setup, setup_with_tx_validity_period};
     use near_chain::types::Tip;
-    use near_chain::{BlockProcessingArtifact, Provenance};
+    use near_chain::{BlockProcessingArtifact, Chain, Provenance};
     use near_chain_configs::MutableConfigValue;
     use near_client_primitives::types::SyncStatus;
     use near_crypto::{KeyType, PublicKey};
@@ -404,7 +540,9 @@ mod test {
     use std::sync::Arc;
     use std::thread;
 
-    use crate::sync::header::{get_locator_ordinals, HeaderSync, MAX_BLOCK_HEADERS};
+    use crate::sync::header::{
+        get_locator_ordinals, HeaderSync, HeaderSyncStats, MAX_BLOCK_HEADERS,
+    };
 
     #[test]
     fn test_get_locator_ordinals() {
@@ -457,6 +595,7 @@ mod test {
             Duration::seconds(120),
             1_000_000_000,
             MutableConfigValue::new(None, "expected_shutdown"),
+            3,
         );
         let (mut chain, _, _, signer) = setup(Clock::real());
         for _ in 0..3 {
@@ -545,6 +684,7 @@ mod test {
             Duration::seconds(120),
             1_000_000_000,
             MutableConfigValue::new(None, "expected_shutdown"),
+            3,
         );
         let (mut chain, _, _, signer) = setup(Clock::real());
         let (mut chain2, _, _, signer2) = setup(Clock::real());
@@ -657,6 +797,7 @@ mod test {
             Duration::seconds(3),
             25,
             MutableConfigValue::new(None, "expected_shutdown"),
+            3,
         );
 
         let set_syncing_peer = |header_sync: &mut HeaderSync| {
@@ -743,27 +884,13 @@ mod test {
         }
     }
 
-    #[test]
-    fn test_sync_from_very_behind() {
-        let mock_adapter = Arc::new(MockPeerManagerAdapter::default());
-        let mut header_sync = HeaderSync::new(
-            Clock::real(),
-            mock_adapter.as_multi_sender(),
-            Duration::seconds(10),
-            Duration::seconds(2),
-            Duration::seconds(120),
-            1_000_000_000,
-            MutableConfigValue::new(None, "expected_shutdown"),
-        );
-
-        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+    /// Sets up a chain with `num_blocks` blocks on top of the test genesis.
+    fn setup_longer_chain(clock: &FakeClock, num_blocks: u64) -> Chain {
         // Don't bother with epoch switches. It's not relevant.
-        let (mut chain, _, _, _) = setup_with_tx_validity_period(clock.clock(), 100, 10000);
         let (mut chain2, _, _, signer2) = setup_with_tx_validity_period(clock.clock(), 100, 10000);
-        // Set up the second chain with 2000+ blocks.
         let mut block_merkle_tree = PartialMerkleTree::default();
-        block_merkle_tree.insert(*chain.genesis().hash()); // for genesis block
-        for _ in 0..(4 * MAX_BLOCK_HEADERS + 10) {
+        block_merkle_tree.insert(*chain2.genesis().hash()); // for genesis block
+        for _ in 0..num_blocks {
             let last_block = chain2.get_block(&chain2.head().unwrap().last_block_hash).unwrap();
             let this_height = last_block.header().height() + 1;
             let (epoch_id, next_epoch_id) = if last_block.header().is_genesis() {
@@ -821,6 +948,27 @@ mod test {
             )
             .unwrap();
         }
+        chain2
+    }
+
+    #[test]
+    fn test_sync_from_very_behind() {
+        let mock_adapter = Arc::new(MockPeerManagerAdapter::default());
+        let mut header_sync = HeaderSync::new(
+            Clock::real(),
+            mock_adapter.as_multi_sender(),
+            Duration::seconds(10),
+            Duration::seconds(2),
+            Duration::seconds(120),
+            1_000_000_000,
+            MutableConfigValue::new(None, "expected_shutdown"),
+            3,
+        );
+
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        // Don't bother with epoch switches. It's not relevant.
+        let (mut chain, _, _, _) = setup_with_tx_validity_period(clock.clock(), 100, 10000);
+        let chain2 = setup_longer_chain(&clock, 4 * MAX_BLOCK_HEADERS + 10);
         let mut sync_status = SyncStatus::NoSync;
         let peer1 = FullPeerInfo {
             peer_info: PeerInfo::random(),
@@ -887,4 +1035,99 @@ mod test {
         let new_tip = chain.header_head().unwrap();
         assert_eq!(new_tip.last_block_hash, chain2.head().unwrap().last_block_hash);
     }
+
+    /// Checks that validating a full batch of headers requests the next batch
+    /// right away, without waiting for `run()`, and that nothing is requested
+    /// before the batch is validated.
+    #[test]
+    fn test_pipelined_header_requests() {
+        let mock_adapter = Arc::new(MockPeerManagerAdapter::default());
+        let mut header_sync = HeaderSync::new(
+            Clock::real(),
+            mock_adapter.as_multi_sender(),
+            Duration::seconds(10),
+            Duration::seconds(2),
+            Duration::seconds(120),
+            1_000_000_000,
+            MutableConfigValue::new(None, "expected_shutdown"),
+            3,
+        );
+
+        let clock = FakeClock::new(Utc::UNIX_EPOCH);
+        let (mut chain, _, _, _) = setup_with_tx_validity_period(clock.clock(), 100, 10000);
+        let chain2 = setup_longer_chain(&clock, 4 * MAX_BLOCK_HEADERS + 10);
+        let peer1 = FullPeerInfo {
+            peer_info: PeerInfo::random(),
+            chain_info: near_network::types::PeerChainInfo {
+                genesis_id: GenesisId {
+                    chain_id: "unittest".to_string(),
+                    hash: *chain.genesis().hash(),
+                },
+                tracked_shards: vec![],
+                archival: false,
+                last_block: Some(BlockInfo {
+                    height: chain2.head().unwrap().height,
+                    hash: chain2.head().unwrap().last_block_hash,
+                }),
+            },
+        };
+        let mut sync_status = SyncStatus::NoSync;
+        let header_head = chain.header_head().unwrap();
+        header_sync
+            .run(
+                &mut sync_status,
+                &mut chain,
+                header_head.height,
+                &[<FullPeerInfo as Into<Option<_>>>::into(peer1.clone()).unwrap()],
+            )
+            .unwrap();
+
+        // Only the first batch is requested by `run()`, the rest are pipelined.
+        for _ in 0..10 {
+            if chain.header_head().unwrap().last_block_hash
+                == chain2.header_head().unwrap().last_block_hash
+            {
+                break;
+            }
+            let message = mock_adapter.pop().expect("No message was sent").as_network_requests();
+            let (hashes, peer_id) = match message {
+                NetworkRequests::BlockHeadersRequest { hashes, peer_id } => (hashes, peer_id),
+                _ => panic!("Unexpected network message: {:?}", message),
+            };
+            assert_eq!(peer_id, peer1.peer_info.id);
+            let headers = chain2.retrieve_headers(hashes, MAX_BLOCK_HEADERS, None).unwrap();
+            let num_headers = headers.len();
+            header_sync.on_headers_received(&peer_id);
+            assert!(mock_adapter.pop().is_none());
+            chain.sync_block_headers(headers, &mut Vec::new()).unwrap();
+            header_sync.on_headers_validated(
+                &sync_status,
+                &chain,
+                num_headers,
+                Duration::milliseconds(100),
+            );
+        }
+        let new_tip = chain.header_head().unwrap();
+        assert_eq!(new_tip.last_block_hash, chain2.head().unwrap().last_block_hash);
+        // The last batch isn't full, so nothing else is requested.
+        assert!(mock_adapter.pop().is_none());
+    }
+
+    #[test]
+    fn test_num_peers_to_query() {
+        let mut stats = HeaderSyncStats::default();
+        // Without observations, a single peer is queried.
+        assert_eq!(stats.num_peers_to_query(3), 1);
+
+        stats.validation_time = Some(Duration::seconds(1));
+        stats.response_latency = Some(Duration::milliseconds(500));
+        assert_eq!(stats.num_peers_to_query(3), 1);
+        stats.response_latency = Some(Duration::milliseconds(1500));
+        assert_eq!(stats.num_peers_to_query(3), 2);
+        stats.response_latency = Some(Duration::seconds(10));
+        assert_eq!(stats.num_peers_to_query(3), 3);
+
+        HeaderSyncStats::update_average(&mut stats.response_latency, Duration::seconds(2));
+        assert_eq!(stats.response_latency, Some(Duration::seconds(8)));
+    }
 }
@@ -13,6 +13,9 @@
 //! (and are high enough) + validators from that epoch that were tracking the shard)
 //! Then for each part that we're missing, we're 'randomly' picking a target from whom we'll request it - but we make
 //! sure to not request more than MAX_STATE_PART_REQUESTS from each.
+//! The network sends the requests to the peers which advertised a snapshot of the shard instead, if any is
+//! connected. Every received part is verified against the state root, and the peers which sent invalid parts
+//! or didn't respond to many requests aren't asked anymore.
 //!
 //! WARNING: with the current design, we're putting quite a load on the validators - as we request a lot of data from
 //!         them (if you assume that we have 100 validators and 30 peers - we send 100/130 of requests to validators).
@@ -22,19 +25,22 @@
 
 use crate::metrics;
 use crate::sync::external::{
-    create_bucket_readonly, external_storage_location, ExternalConnection,
+    create_azure_connection, create_bucket_readonly, decompress_state_part,
+    external_storage_location, ExternalConnection,
 };
 use borsh::BorshDeserialize;
 use futures::{future, FutureExt};
 use near_async::futures::{FutureSpawner, FutureSpawnerExt};
-use near_async::messaging::SendAsync;
+use near_async::messaging::{CanSend, SendAsync};
 use near_async::time::{Clock, Duration, Utc};
 use near_chain::chain::{ApplyStatePartsRequest, LoadMemtrieRequest};
 use near_chain::near_chain_primitives;
 use near_chain::resharding::ReshardingRequest;
 use near_chain::types::RuntimeAdapter;
 use near_chain::Chain;
-use near_chain_configs::{ExternalStorageConfig, ExternalStorageLocation, SyncConfig};
+use near_chain_configs::{
+    ExternalStorageConfig, ExternalStorageLocation, StateSyncCatchupConfig, SyncConfig,
+};
 use near_client_primitives::types::{
     format_shard_sync_phase, DownloadStatus, ShardSyncDownload, ShardSyncStatus,
 };
@@ -48,9 +54,10 @@ use near_primitives::network::PeerId;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_part::PartId;
 use near_primitives::state_sync::{
-    ShardStateSyncResponse, ShardStateSyncResponseHeader, StatePartKey,
+    ShardStateSyncResponse, ShardStateSyncResponseHeader, StatePartKey, StatePartsManifest,
 };
 use near_primitives::types::{AccountId, EpochHeight, EpochId, ShardId, StateRoot};
+use near_primitives::views::{ShardStateSyncProgressView, StateSyncProgressView};
 use near_store::DBCol;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
@@ -97,8 +104,15 @@ impl PendingRequestStatus {
 }
 
 pub enum StateSyncFileDownloadResult {
-    StateHeader { header_length: u64, header: ShardStateSyncResponseHeader },
-    StatePart { part_length: u64 },
+    StateHeader {
+        header_length: u64,
+        header: ShardStateSyncResponseHeader,
+        /// Manifest of the state parts, if the dump node has already uploaded it.
+        manifest: Option<StatePartsManifest>,
+    },
+    StatePart {
+        part_length: u64,
+    },
 }
 
 /// Signals that a state part was downloaded and saved to RocksDB.
@@ -110,6 +124,82 @@ pub struct StateSyncGetFileResult {
     result: Result<StateSyncFileDownloadResult, String>,
 }
 
+/// Progress of the state sync of a shard.
+/// Unlike `ShardSyncDownload`, it is kept when the shard moves to the next phase.
+struct ShardSyncProgress {
+    sync_hash: CryptoHash,
+    start_time: Utc,
+    /// When the node started downloading the state parts.
+    parts_start_time: Option<Utc>,
+    num_parts: Option<u64>,
+    /// Parts found in the database when the download of the parts started.
+    num_parts_resumed: u64,
+    bytes_downloaded: u64,
+    last_error: Option<String>,
+}
+
+impl ShardSyncProgress {
+    fn new(sync_hash: CryptoHash, now: Utc) -> Self {
+        Self {
+            sync_hash,
+            start_time: now,
+            parts_start_time: None,
+            num_parts: None,
+            num_parts_resumed: 0,
+            bytes_downloaded: 0,
+            last_error: None,
+        }
+    }
+}
+
+/// Returns the progress of the given shard, starting over if the shard is synced to a new epoch.
+fn shard_progress(
+    progress: &mut HashMap<ShardId, ShardSyncProgress>,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    now: Utc,
+) -> &mut ShardSyncProgress {
+    let progress =
+        progress.entry(shard_id).or_insert_with(|| ShardSyncProgress::new(sync_hash, now));
+    if progress.sync_hash != sync_hash {
+        *progress = ShardSyncProgress::new(sync_hash, now);
+    }
+    progress
+}
+
+/// Limits the rate of downloading state parts during catch-up.
+/// New part requests are postponed while more bytes were downloaded than the
+/// budget accumulated since the last check allows.
+struct DownloadRateLimiter {
+    bytes_per_second: u64,
+    /// Bytes downloaded in excess of the budget.
+    excess_bytes: u64,
+    last_update: Utc,
+}
+
+impl DownloadRateLimiter {
+    fn new(bytes_per_second: u64, now: Utc) -> Self {
+        Self { bytes_per_second, excess_bytes: 0, last_update: now }
+    }
+
+    fn update(&mut self, now: Utc) {
+        let elapsed = (now - self.last_update).max(Duration::ZERO);
+        let budget = (self.bytes_per_second as f64 * elapsed.as_seconds_f64()) as u64;
+        self.excess_bytes = self.excess_bytes.saturating_sub(budget);
+        self.last_update = now;
+    }
+
+    fn record(&mut self, bytes: u64, now: Utc) {
+        self.update(now);
+        self.excess_bytes += bytes;
+    }
+
+    fn has_budget(&mut self, now: Utc) -> bool {
+        self.update(now);
+        self.excess_bytes == 0
+    }
+}
+
 /// How to retrieve the state data.
 enum StateSyncInner {
     /// Request both the state header and state parts from the peers.
@@ -128,6 +218,8 @@ enum StateSyncInner {
         semaphore: Arc<tokio::sync::Semaphore>,
         /// Connection to the external storage.
         external: ExternalConnection,
+        /// Manifests of the state parts, downloaded together with the state headers.
+        manifests: HashMap<(CryptoHash, ShardId), Arc<StatePartsManifest>>,
     },
 }
 
@@ -157,6 +249,15 @@ pub struct StateSync {
     /// Message queue to process the received state parts.
     state_parts_mpsc_tx: Sender<StateSyncGetFileResult>,
     state_parts_mpsc_rx: Receiver<StateSyncGetFileResult>,
+
+    /// Progress of each shard, reported by the debug RPC.
+    progress: HashMap<ShardId, ShardSyncProgress>,
+
+    /// Resource budget of the catch-up. Not set for the regular state sync.
+    catchup_config: Option<StateSyncCatchupConfig>,
+    download_limiter: Option<DownloadRateLimiter>,
+    /// Either "sync" or "catchup", used as a label of the metrics.
+    sync_type: &'static str,
 }
 
 impl StateSync {
@@ -166,6 +267,7 @@ impl StateSync {
         timeout: Duration,
         chain_id: &str,
         sync_config: &SyncConfig,
+        catchup_config: &StateSyncCatchupConfig,
         catchup: bool,
     ) -> Self {
         let inner = match sync_config {
@@ -198,6 +300,30 @@ impl StateSync {
                         reqwest_client: Arc::new(reqwest::Client::default()),
                         bucket: bucket.clone(),
                     },
+                    ExternalStorageLocation::Azure { account, container } => {
+                        // Reads the shared access signature from the environment, if any.
+                        create_azure_connection(
+                            &account,
+                            &container,
+                            timeout.max(Duration::ZERO).unsigned_abs(),
+                            None,
+                        )
+                        .unwrap_or_else(|err| {
+                            panic!("Failed to create an Azure connection: {}", err)
+                        })
+                    }
+                    ExternalStorageLocation::Http { url } => {
+                        let reqwest_client = reqwest::Client::builder()
+                            .timeout(timeout.max(Duration::ZERO).unsigned_abs())
+                            .build()
+                            .unwrap_or_else(|err| {
+                                panic!("Failed to create an HTTP client: {}", err)
+                            });
+                        ExternalConnection::Http {
+                            reqwest_client: Arc::new(reqwest_client),
+                            url: url.trim_end_matches('/').to_string(),
+                        }
+                    }
                 };
                 let num_permits = if catchup {
                     *num_concurrent_requests_during_catchup
@@ -208,9 +334,14 @@ impl StateSync {
                     chain_id: chain_id.to_string(),
                     semaphore: Arc::new(tokio::sync::Semaphore::new(num_permits)),
                     external,
+                    manifests: HashMap::new(),
                 }
             }
         };
+        let catchup_config = catchup.then_some(*catchup_config);
+        let download_limiter = catchup_config
+            .and_then(|config| config.max_download_bytes_per_second)
+            .map(|limit| DownloadRateLimiter::new(limit.as_u64(), clock.now_utc()));
         let (tx, rx) = channel::<StateSyncGetFileResult>();
         StateSync {
             clock,
@@ -222,9 +353,74 @@ impl StateSync {
             resharding_state_roots: HashMap::new(),
             state_parts_mpsc_rx: rx,
             state_parts_mpsc_tx: tx,
+            progress: HashMap::new(),
+            catchup_config,
+            download_limiter,
+            sync_type: if catchup { "catchup" } else { "sync" },
         }
     }
 
+    /// Describes the progress of state sync of the given shards.
+    pub fn get_progress_view(
+        &self,
+        sync_hash: CryptoHash,
+        sync_status: &HashMap<ShardId, ShardSyncDownload>,
+        catchup: bool,
+    ) -> StateSyncProgressView {
+        let now = self.clock.now_utc();
+        let mut shards: Vec<_> = sync_status
+            .iter()
+            .map(|(&shard_id, shard_sync_download)| {
+                let progress = self.progress.get(&shard_id).filter(|p| p.sync_hash == sync_hash);
+                let num_parts_total = progress.and_then(|p| p.num_parts);
+                let num_parts_resumed = progress.map_or(0, |p| p.num_parts_resumed);
+                let num_parts_done = match shard_sync_download.status {
+                    ShardSyncStatus::StateDownloadHeader => 0,
+                    ShardSyncStatus::StateDownloadParts => {
+                        shard_sync_download.downloads.iter().filter(|d| d.done).count() as u64
+                    }
+                    _ => num_parts_total.unwrap_or(0),
+                };
+                let elapsed = progress.map_or(Duration::ZERO, |p| now - p.start_time);
+                let bytes_downloaded = progress.map_or(0, |p| p.bytes_downloaded);
+                let bytes_per_second = if elapsed.as_seconds_f64() > 0.0 {
+                    (bytes_downloaded as f64 / elapsed.as_seconds_f64()) as u64
+                } else {
+                    0
+                };
+                let eta_secs = match (
+                    &shard_sync_download.status,
+                    progress.and_then(|p| p.parts_start_time),
+                    num_parts_total,
+                ) {
+                    (ShardSyncStatus::StateDownloadParts, Some(parts_start_time), Some(total))
+                        if num_parts_done > num_parts_resumed =>
+                    {
+                        // The resumed parts took no time to download.
+                        let per_part = (now - parts_start_time).as_seconds_f64()
+                            / (num_parts_done - num_parts_resumed) as f64;
+                        Some((per_part * total.saturating_sub(num_parts_done) as f64) as u64)
+                    }
+                    _ => None,
+                };
+                ShardStateSyncProgressView {
+                    shard_id,
+                    phase: (&shard_sync_download.status).into(),
+                    num_parts_done,
+                    num_parts_resumed,
+                    num_parts_total,
+                    bytes_downloaded,
+                    bytes_per_second,
+                    elapsed_secs: elapsed.whole_seconds().max(0) as u64,
+                    eta_secs,
+                    last_error: progress.and_then(|p| p.last_error.clone()),
+                }
+            })
+            .collect();
+        shards.sort_by_key(|shard| shard.shard_id);
+        StateSyncProgressView { sync_hash, catchup, shards }
+    }
+
     // The return value indicates whether state sync is
     // finished, in which case the client will transition to block sync
     fn sync_shards_status(
@@ -267,6 +463,7 @@ impl StateSync {
                 run_shard_state_download = true;
                 ShardSyncDownload::new_download_state_header(now)
             });
+            shard_progress(&mut self.progress, shard_id, sync_hash, now);
 
             let mut shard_sync_done = false;
             match &shard_sync_download.status {
@@ -405,8 +602,12 @@ impl StateSync {
                 let (download_result, download) = match result {
                     Err(err) => (Err(err), None),
                     // Store the header
-                    Ok(StateSyncFileDownloadResult::StateHeader { header_length, header }) => {
-                        info!(target: "sync", ?header_length, ?part_id, "processing state header");
+                    Ok(StateSyncFileDownloadResult::StateHeader {
+                        header_length,
+                        header,
+                        manifest,
+                    }) => {
+                        info!(target: "sync", ?header_length, ?part_id, has_manifest = manifest.is_some(), "processing state header");
                         if shard_sync_download.status != ShardSyncStatus::StateDownloadHeader {
                             continue;
                         }
@@ -414,6 +615,12 @@ impl StateSync {
                         if download.as_ref().and_then(|d| Some(d.done)).unwrap_or(true) {
                             continue;
                         }
+                        if let (Some(manifest), StateSyncInner::External { manifests, .. }) =
+                            (manifest, &mut self.inner)
+                        {
+                            manifests.retain(|(hash, _), _| *hash == sync_hash);
+                            manifests.insert((sync_hash, shard_id), Arc::new(manifest));
+                        }
                         let result = chain
                             .set_state_header(shard_id, sync_hash, header)
                             .map_err(|err| format!("State sync set_state_header error: {err:?}"))
@@ -435,6 +642,20 @@ impl StateSync {
                     }
                 };
 
+                let now = self.clock.now_utc();
+                let progress = shard_progress(&mut self.progress, shard_id, sync_hash, now);
+                match &download_result {
+                    Ok(data_len) => {
+                        progress.bytes_downloaded += data_len;
+                        if let Some(download_limiter) = &mut self.download_limiter {
+                            download_limiter.record(*data_len, now);
+                        }
+                        metrics::STATE_SYNC_DOWNLOADED_BYTES
+                            .with_label_values(&[&shard_id.to_string(), self.sync_type])
+                            .inc_by(*data_len);
+                    }
+                    Err(err) => progress.last_error = Some(err.clone()),
+                }
                 process_download_response(
                     shard_id,
                     sync_hash,
@@ -496,6 +717,32 @@ impl StateSync {
         }
     }
 
+    /// Finds the hash of the first block of the epoch `epoch_id`, which must
+    /// be an ancestor of the block with hash `hash`.
+    /// Returns `None` if no ancestor of the block belongs to the epoch, e.g.
+    /// if the headers of that epoch weren't downloaded yet.
+    pub fn get_epoch_start_sync_hash_for_epoch(
+        chain: &Chain,
+        hash: &CryptoHash,
+        epoch_id: &EpochId,
+    ) -> Result<Option<CryptoHash>, near_chain::Error> {
+        let genesis_hash = *chain.genesis().hash();
+        let mut epoch_start_hash = Self::get_epoch_start_sync_hash(chain, hash)?;
+        loop {
+            let header = chain.get_block_header(&epoch_start_hash)?;
+            if header.epoch_id() == epoch_id {
+                return Ok(Some(epoch_start_hash));
+            }
+            if header.prev_hash() == &genesis_hash || epoch_start_hash == genesis_hash {
+                return Ok(None);
+            }
+            // Jump to the previous epoch using the block info instead of
+            // walking over all of its headers.
+            epoch_start_hash =
+                *chain.epoch_manager.get_block_info(header.prev_hash())?.epoch_first_block();
+        }
+    }
+
     // Function called when our node receives the network response with a part.
     pub fn received_requested_part(
         &mut self,
@@ -530,9 +777,13 @@ impl StateSync {
         &mut self,
         highest_height_peers: &[HighestHeightPeerInfo],
         shard_id: ShardId,
+        archival_only: bool,
     ) -> Result<Vec<PeerId>, near_chain::Error> {
-        let peers: Vec<PeerId> =
-            highest_height_peers.iter().map(|peer| peer.peer_info.id.clone()).collect();
+        let peers: Vec<PeerId> = highest_height_peers
+            .iter()
+            .filter(|peer| peer.archival || !archival_only)
+            .map(|peer| peer.peer_info.id.clone())
+            .collect();
         let res = match &mut self.inner {
             StateSyncInner::Peers { last_part_id_requested, .. } => {
                 last_part_id_requested.retain(|_, request| !request.expired());
@@ -563,7 +814,10 @@ impl StateSync {
         let mut possible_targets = vec![];
         match self.inner {
             StateSyncInner::Peers { .. } => {
-                possible_targets = self.select_peers(highest_height_peers, shard_id)?;
+                // Only archival nodes keep the state of old epochs.
+                let archival_only = is_historical_sync_hash(chain, &sync_hash)?;
+                possible_targets =
+                    self.select_peers(highest_height_peers, shard_id, archival_only)?;
                 if possible_targets.is_empty() {
                     tracing::debug!(target: "sync", "Can't request a state header: No possible targets");
                     // In most cases it means that all the targets are currently busy (that we have a pending request with them).
@@ -587,6 +841,15 @@ impl StateSync {
                 );
             }
             ShardSyncStatus::StateDownloadParts => {
+                if let Some(download_limiter) = &mut self.download_limiter {
+                    if !download_limiter.has_budget(self.clock.now_utc()) {
+                        tracing::debug!(target: "catchup", %shard_id, %sync_hash, "Catch-up download budget is spent, postponing part requests");
+                        metrics::STATE_SYNC_CATCHUP_THROTTLED
+                            .with_label_values(&["download"])
+                            .inc();
+                        return Ok(());
+                    }
+                }
                 self.request_shard_parts(
                     shard_id,
                     sync_hash,
@@ -679,6 +942,11 @@ impl StateSync {
                 // And for each one of them, we'll ask for up to 16 (MAX_STATE_PART_REQUEST) parts.
                 let possible_targets_sampler =
                     SamplerLimited::new(possible_targets, MAX_STATE_PART_REQUEST);
+                // The peers advertise the state snapshots by the hash of the
+                // block before the last block of the previous epoch.
+                let sync_prev_hash = *chain.get_block_header(&sync_hash).unwrap().prev_hash();
+                let sync_prev_prev_hash =
+                    *chain.get_block_header(&sync_prev_hash).unwrap().prev_hash();
 
                 // For every part that needs to be requested it is selected one
                 // peer (target) randomly to request the part from.
@@ -704,11 +972,12 @@ impl StateSync {
                         download,
                         shard_id,
                         sync_hash,
+                        sync_prev_prev_hash,
                         &self.network_adapter,
                     );
                 }
             }
-            StateSyncInner::External { chain_id, semaphore, external } => {
+            StateSyncInner::External { chain_id, semaphore, external, manifests } => {
                 let sync_block_header = chain.get_block_header(&sync_hash).unwrap();
                 let epoch_id = sync_block_header.epoch_id();
                 let epoch_info = chain.epoch_manager.get_epoch_info(epoch_id).unwrap();
@@ -717,6 +986,7 @@ impl StateSync {
                 let shard_state_header = chain.get_state_header(shard_id, sync_hash).unwrap();
                 let state_root = shard_state_header.chunk_prev_state_root();
                 let state_num_parts = shard_state_header.num_state_parts();
+                let manifest = manifests.get(&(sync_hash, shard_id)).cloned();
 
                 for (part_id, download) in parts_to_fetch(new_shard_sync_download) {
                     request_part_from_external_storage(
@@ -729,6 +999,7 @@ impl StateSync {
                         state_num_parts,
                         &chain_id.clone(),
                         state_root,
+                        manifest.clone(),
                         semaphore.clone(),
                         external.clone(),
                         runtime_adapter.clone(),
@@ -815,6 +1086,8 @@ impl StateSync {
             // Mark that we have received this part (this will update info on pending parts from peers etc).
             self.received_requested_part(part_id, shard_id, hash);
         }
+        let now = self.clock.now_utc();
+        let progress = shard_progress(&mut self.progress, shard_id, hash, now);
         match shard_sync_download.status {
             ShardSyncStatus::StateDownloadHeader => {
                 let header_download = shard_sync_download.get_header_download_mut().unwrap();
@@ -826,6 +1099,8 @@ impl StateSync {
                             }
                             Err(err) => {
                                 tracing::error!(target: "sync", %shard_id, %hash, ?err, "State sync set_state_header error");
+                                progress.last_error =
+                                    Some(format!("State sync set_state_header error: {err:?}"));
                                 header_download.error = true;
                             }
                         }
@@ -835,6 +1110,8 @@ impl StateSync {
                     // It may happen because requested node couldn't build state response.
                     if !header_download.done {
                         tracing::info!(target: "sync", %shard_id, %hash, "state_response doesn't have header, should be re-requested");
+                        progress.last_error =
+                            Some("State response doesn't have header".to_string());
                         header_download.error = true;
                     }
                 }
@@ -845,20 +1122,43 @@ impl StateSync {
                     let (part_id, data) = part;
                     if part_id >= num_parts {
                         tracing::error!(target: "sync", %shard_id, %hash, part_id, "State sync received incorrect part_id, potential malicious peer");
+                        progress.last_error = Some(format!("Received incorrect part_id {part_id}"));
                         return;
                     }
                     if !shard_sync_download.downloads[part_id as usize].done {
-                        match chain.set_state_part(
+                        let result = chain.set_state_part(
                             shard_id,
                             hash,
                             PartId::new(part_id, num_parts),
                             &data,
-                        ) {
+                        );
+                        // Lets the network score the peer which sent the part.
+                        self.network_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                            NetworkRequests::StatePartVerified {
+                                shard_id,
+                                sync_hash: hash,
+                                part_id,
+                                valid: result.is_ok(),
+                            },
+                        ));
+                        match result {
                             Ok(()) => {
+                                progress.bytes_downloaded += data.len() as u64;
+                                if let Some(download_limiter) = &mut self.download_limiter {
+                                    download_limiter.record(data.len() as u64, now);
+                                }
+                                metrics::STATE_SYNC_DOWNLOADED_BYTES
+                                    .with_label_values(&[&shard_id.to_string(), self.sync_type])
+                                    .inc_by(data.len() as u64);
                                 shard_sync_download.downloads[part_id as usize].done = true;
                             }
                             Err(err) => {
                                 tracing::error!(target: "sync", %shard_id, %hash, part_id, ?err, "State sync set_state_part error");
+                                metrics::STATE_SYNC_INVALID_PARTS
+                                    .with_label_values(&[&shard_id.to_string(), "peers"])
+                                    .inc();
+                                progress.last_error =
+                                    Some(format!("State sync set_state_part error: {err:?}"));
                                 shard_sync_download.downloads[part_id as usize].error = true;
                             }
                         }
@@ -892,6 +1192,15 @@ impl StateSync {
             // Create the vector with entry for each part.
             *shard_sync_download =
                 ShardSyncDownload::new_download_state_parts(now, state_num_parts);
+            let num_parts_resumed =
+                mark_stored_parts_done(chain, shard_id, sync_hash, shard_sync_download)?;
+            if num_parts_resumed > 0 {
+                tracing::info!(target: "sync", %shard_id, %sync_hash, num_parts_resumed, state_num_parts, "Resuming the download of the state parts stored before the restart");
+            }
+            let progress = shard_progress(&mut self.progress, shard_id, sync_hash, now);
+            progress.num_parts = Some(state_num_parts);
+            progress.num_parts_resumed = num_parts_resumed;
+            progress.parts_start_time = Some(now);
             Ok((false, true))
         } else {
             let download_timeout = now - download.prev_update_time > self.timeout;
@@ -900,6 +1209,8 @@ impl StateSync {
                 metrics::STATE_SYNC_HEADER_TIMEOUT
                     .with_label_values(&[&shard_id.to_string()])
                     .inc();
+                shard_progress(&mut self.progress, shard_id, sync_hash, now).last_error =
+                    Some("State header request timed out".to_string());
             }
             if download.error {
                 tracing::debug!(target: "sync", last_target = ?download.last_target, start_time = ?download.start_time, prev_update_time = ?download.prev_update_time, state_requests_count = download.state_requests_count, "header request error");
@@ -996,6 +1307,7 @@ impl StateSync {
             shard_id,
             sync_hash,
             state_num_parts,
+            self.catchup_config,
             state_parts_task_scheduler,
         ) {
             Ok(()) => {
@@ -1009,6 +1321,8 @@ impl StateSync {
                 // The reasonable behavior here is to start from the very beginning.
                 metrics::STATE_SYNC_DISCARD_PARTS.with_label_values(&[&shard_id.to_string()]).inc();
                 tracing::error!(target: "sync", %shard_id, %sync_hash, ?err, "State sync finalizing error");
+                shard_progress(&mut self.progress, shard_id, sync_hash, now).last_error =
+                    Some(format!("State sync finalizing error: {err:?}"));
                 *shard_sync_download = ShardSyncDownload::new_download_state_header(now);
                 chain.clear_downloaded_parts(shard_id, sync_hash, state_num_parts)?;
             }
@@ -1085,6 +1399,8 @@ impl StateSync {
             // The reasonable behavior here is to start from the very beginning.
             metrics::STATE_SYNC_DISCARD_PARTS.with_label_values(&[&shard_id.to_string()]).inc();
             tracing::error!(target: "sync", %shard_id, %sync_hash, ?err, "State sync finalizing error");
+            shard_progress(&mut self.progress, shard_id, sync_hash, now).last_error =
+                Some(format!("State sync finalizing error: {err:?}"));
             *shard_sync_download = ShardSyncDownload::new_download_state_header(now);
             let shard_state_header = chain.get_state_header(shard_id, sync_hash)?;
             let state_num_parts = shard_state_header.num_state_parts();
@@ -1170,6 +1486,46 @@ impl StateSync {
     }
 }
 
+/// Returns whether the state at `sync_hash` is older than the state that
+/// regular nodes keep, i.e. older than the previous epoch. Only archival nodes
+/// that serve historical state can provide it.
+fn is_historical_sync_hash(
+    chain: &Chain,
+    sync_hash: &CryptoHash,
+) -> Result<bool, near_chain::Error> {
+    let sync_epoch_id = chain.get_block_header(sync_hash)?.epoch_id().clone();
+    let head = chain.header_head()?;
+    if sync_epoch_id == head.epoch_id {
+        return Ok(false);
+    }
+    let prev_epoch_id =
+        chain.epoch_manager.get_prev_epoch_id_from_prev_block(&head.prev_block_hash)?;
+    Ok(sync_epoch_id != prev_epoch_id)
+}
+
+/// Marks the parts which are already in the database as downloaded, so that
+/// the state sync of a shard resumes where it left off when the node restarts.
+/// Parts are stored only after they are validated against the state root, and
+/// only removed once the state is applied. Returns the number of such parts.
+fn mark_stored_parts_done(
+    chain: &Chain,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    shard_sync_download: &mut ShardSyncDownload,
+) -> Result<u64, near_chain::Error> {
+    let store = chain.chain_store().store();
+    let mut num_parts_stored = 0;
+    for (part_id, download) in shard_sync_download.downloads.iter_mut().enumerate() {
+        let key = borsh::to_vec(&StatePartKey(sync_hash, shard_id, part_id as u64))?;
+        if store.exists(DBCol::StateParts, &key)? {
+            download.done = true;
+            download.run_me.store(false, Ordering::SeqCst);
+            num_parts_stored += 1;
+        }
+    }
+    Ok(num_parts_stored)
+}
+
 /// Returns parts that still need to be fetched.
 fn parts_to_fetch(
     new_shard_sync_download: &mut ShardSyncDownload,
@@ -1186,9 +1542,10 @@ async fn download_header_from_external_storage(
     shard_id: ShardId,
     sync_hash: CryptoHash,
     location: String,
+    manifest_location: String,
     external: ExternalConnection,
 ) -> Result<StateSyncFileDownloadResult, std::string::String> {
-    external
+    let (header_length, header) = external
     .get_file(shard_id, &location, &StateFileType::StateHeader)
     .await
     .map_err(|err| err.to_string())
@@ -1196,12 +1553,56 @@ async fn download_header_from_external_storage(
         info!(target: "sync", ?shard_id, "downloaded state header");
         let header_length = data.len() as u64;
         ShardStateSyncResponseHeader::try_from_slice(&data)
-        .map(|header| StateSyncFileDownloadResult::StateHeader { header_length , header })
+        .map(|header| (header_length, header))
         .map_err(|_| {
             tracing::info!(target: "sync", %shard_id, %sync_hash, "Could not parse downloaded header.");
             format!("Could not parse state sync header for shard {shard_id:?}")
         })
-    })
+    })?;
+    let manifest =
+        download_manifest_from_external_storage(shard_id, &header, manifest_location, external)
+            .await;
+    Ok(StateSyncFileDownloadResult::StateHeader { header_length, header, manifest })
+}
+
+/// Fetches the manifest of the state parts.
+/// The manifest is optional, because older dump nodes don't upload it at all.
+/// Dump nodes upload it before the state header, so it is never missing while
+/// the parts of a dump which has one are being downloaded.
+async fn download_manifest_from_external_storage(
+    shard_id: ShardId,
+    header: &ShardStateSyncResponseHeader,
+    location: String,
+    external: ExternalConnection,
+) -> Option<StatePartsManifest> {
+    let data = match external.get_file(shard_id, &location, &StateFileType::StateManifest).await {
+        Ok(data) => data,
+        Err(err) => {
+            tracing::debug!(target: "sync", %shard_id, ?err, "State parts manifest is not available");
+            return None;
+        }
+    };
+    let manifest = match serde_json::from_slice::<StatePartsManifest>(&data) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            tracing::warn!(target: "sync", %shard_id, ?err, "Could not parse state parts manifest");
+            return None;
+        }
+    };
+    let state_root = header.chunk_prev_state_root();
+    let num_parts = header.num_state_parts();
+    if manifest.state_root != state_root || manifest.num_parts() != num_parts {
+        tracing::warn!(
+            target: "sync",
+            %shard_id,
+            ?state_root,
+            num_parts,
+            manifest_state_root = ?manifest.state_root,
+            manifest_num_parts = manifest.num_parts(),
+            "State parts manifest doesn't match the state header, ignoring it");
+        return None;
+    }
+    Some(manifest)
 }
 
 /// Starts an asynchronous network request to external storage to fetch the given header.
@@ -1230,10 +1631,17 @@ fn request_header_from_external_storage(
         shard_id,
         &StateFileType::StateHeader,
     );
+    let manifest_location = external_storage_location(
+        chain_id,
+        epoch_id,
+        epoch_height,
+        shard_id,
+        &StateFileType::StateManifest,
+    );
     state_parts_future_spawner.spawn(
         "download_header_from_external_storage",
         async move {
-            let result = download_header_from_external_storage(shard_id, sync_hash, location, external).await;
+            let result = download_header_from_external_storage(shard_id, sync_hash, location, manifest_location, external).await;
             match state_parts_mpsc_tx.send(StateSyncGetFileResult {
                 sync_hash,
                 shard_id,
@@ -1256,6 +1664,7 @@ async fn download_and_store_part_from_external_storage(
     shard_id: ShardId,
     sync_hash: CryptoHash,
     state_root: StateRoot,
+    manifest: Option<Arc<StatePartsManifest>>,
     external: ExternalConnection,
     runtime_adapter: Arc<dyn RuntimeAdapter>,
 ) -> Result<StateSyncFileDownloadResult, String> {
@@ -1263,8 +1672,17 @@ async fn download_and_store_part_from_external_storage(
     .get_file(shard_id, &location, file_type)
     .await
     .map_err(|err| err.to_string())
-    .and_then(|data|  {
-        info!(target: "sync", ?shard_id, ?part_id, "downloaded state part");
+    .and_then(|data| {
+        info!(target: "sync", ?shard_id, ?part_id, num_bytes = data.len(), "downloaded state part");
+        decompress_state_part(data).map_err(|err| format!("Failed to decompress a state part. err={err:?}, part_id={part_id:?}, shard_id={shard_id}"))
+    })
+    .and_then(|data| {
+        if let Some(manifest) = &manifest {
+            manifest.verify_part(part_id.idx, &data).map_err(|err| {
+                invalid_part_from_external_storage(shard_id, part_id, &location);
+                format!("State part doesn't match the manifest. err={err}, state_root={state_root:?}, shard_id={shard_id}")
+            })?;
+        }
         if runtime_adapter.validate_state_part(&state_root, part_id, &data) {
             let mut store_update = runtime_adapter.store().store_update();
             borsh::to_vec(&StatePartKey(sync_hash, shard_id, part_id.idx))
@@ -1276,10 +1694,20 @@ async fn download_and_store_part_from_external_storage(
             .map(|_| data.len() as u64)
             .map(|part_length| StateSyncFileDownloadResult::StatePart { part_length })
         } else {
+            invalid_part_from_external_storage(shard_id, part_id, &location);
             Err(format!("validate_state_part failed. state_root={state_root:?}, part_id={part_id:?}, shard_id={shard_id}"))
         }
     })
 }
+
+/// Records a state part from external storage which failed the verification.
+/// The external storage is the only source of the parts, so the part is
+/// requested again only after the request timeout, like any other failed
+/// download from external storage.
+fn invalid_part_from_external_storage(shard_id: ShardId, part_id: PartId, location: &str) {
+    tracing::warn!(target: "sync", %shard_id, ?part_id, location, "External storage returned an invalid state part");
+    metrics::STATE_SYNC_INVALID_PARTS.with_label_values(&[&shard_id.to_string(), "external"]).inc();
+}
 /// Starts an asynchronous network request to external storage to fetch the given state part.
 fn request_part_from_external_storage(
     part_id: u64,
@@ -1291,6 +1719,7 @@ fn request_part_from_external_storage(
     num_parts: u64,
     chain_id: &str,
     state_root: StateRoot,
+    manifest: Option<Arc<StatePartsManifest>>,
     semaphore: Arc<Semaphore>,
     external: ExternalConnection,
     runtime_adapter: Arc<dyn RuntimeAdapter>,
@@ -1326,6 +1755,7 @@ fn request_part_from_external_storage(
                         shard_id,
                         sync_hash,
                         state_root,
+                        manifest,
                         external,
                         runtime_adapter)
                         .await;
@@ -1356,12 +1786,15 @@ fn request_part_from_external_storage(
 }
 
 /// Asynchronously requests a state part from a suitable peer.
+/// The network prefers the peers that advertised a snapshot of the state, and
+/// asks `peer_id` only if none of them is connected.
 fn request_part_from_peers(
     part_id: u64,
     peer_id: PeerId,
     download: &mut DownloadStatus,
     shard_id: ShardId,
     sync_hash: CryptoHash,
+    sync_prev_prev_hash: CryptoHash,
     network_adapter: &PeerManagerAdapter,
 ) {
     download.run_me.store(false, Ordering::SeqCst);
@@ -1373,7 +1806,13 @@ fn request_part_from_peers(
         "StateSync",
         network_adapter
             .send_async(PeerManagerMessageRequest::NetworkRequests(
-                NetworkRequests::StateRequestPart { shard_id, sync_hash, part_id, peer_id },
+                NetworkRequests::StateRequestPart {
+                    shard_id,
+                    sync_hash,
+                    sync_prev_prev_hash,
+                    part_id,
+                    peer_id,
+                },
             ))
             .then(move |result| {
                 // TODO: possible optimization - in the current code, even if one of the targets it not present in the network graph
@@ -1521,8 +1960,100 @@ mod test {
     use near_primitives::state_sync::{
         CachedParts, ShardStateSyncResponseHeader, ShardStateSyncResponseV3,
     };
+    use near_primitives::views::StateSyncPhaseView;
     use near_primitives::{test_utils::TestBlockBuilder, types::EpochId};
 
+    #[test]
+    fn test_progress_view() {
+        let clock = near_async::time::FakeClock::default();
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let mut state_sync = StateSync::new(
+            clock.clock(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(1),
+            "chain_id",
+            &SyncConfig::Peers,
+            &StateSyncCatchupConfig::default(),
+            false,
+        );
+        let sync_hash = CryptoHash::hash_bytes(&[1]);
+        let start = clock.now_utc();
+        let progress = shard_progress(&mut state_sync.progress, 0, sync_hash, start);
+        progress.num_parts = Some(4);
+        progress.parts_start_time = Some(start);
+        progress.bytes_downloaded = 2000;
+        progress.last_error = Some("timeout".to_string());
+
+        let mut download = ShardSyncDownload::new_download_state_parts(start, 4);
+        download.downloads[0].done = true;
+        download.downloads[3].done = true;
+        let mut sync_status = HashMap::new();
+        sync_status.insert(0, download);
+        sync_status.insert(1, ShardSyncDownload::new_download_state_header(start));
+
+        clock.advance(Duration::seconds(10));
+        let view = state_sync.get_progress_view(sync_hash, &sync_status, false);
+        assert_eq!(view.sync_hash, sync_hash);
+        assert_eq!(view.shards.len(), 2);
+        let shard = &view.shards[0];
+        assert_eq!(shard.phase, StateSyncPhaseView::Parts);
+        assert_eq!(shard.num_parts_done, 2);
+        assert_eq!(shard.num_parts_total, Some(4));
+        assert_eq!(shard.bytes_per_second, 200);
+        assert_eq!(shard.eta_secs, Some(10));
+        assert_eq!(shard.last_error.as_deref(), Some("timeout"));
+        let shard = &view.shards[1];
+        assert_eq!(shard.phase, StateSyncPhaseView::Header);
+        assert_eq!(shard.num_parts_total, None);
+        assert_eq!(shard.eta_secs, None);
+
+        // Progress of the previous epoch is not reported.
+        let view = state_sync.get_progress_view(CryptoHash::default(), &sync_status, true);
+        assert_eq!(view.shards[0].num_parts_total, None);
+        assert_eq!(view.shards[0].last_error, None);
+    }
+
+    #[test]
+    fn test_download_rate_limiter() {
+        let clock = near_async::time::FakeClock::default();
+        let mut limiter = DownloadRateLimiter::new(1000, clock.now_utc());
+        assert!(limiter.has_budget(clock.now_utc()));
+
+        limiter.record(2500, clock.now_utc());
+        assert!(!limiter.has_budget(clock.now_utc()));
+        clock.advance(Duration::seconds(2));
+        assert!(!limiter.has_budget(clock.now_utc()));
+        clock.advance(Duration::milliseconds(500));
+        assert!(limiter.has_budget(clock.now_utc()));
+
+        // Idle time doesn't accumulate budget for a later burst.
+        clock.advance(Duration::seconds(10));
+        limiter.record(1, clock.now_utc());
+        assert!(!limiter.has_budget(clock.now_utc()));
+    }
+
+    #[test]
+    fn test_resume_stored_parts() {
+        let (chain, _, _, _) = test_utils::setup(Clock::real());
+        let sync_hash = CryptoHash::hash_bytes(&[1]);
+        let mut store_update = chain.chain_store().store().store_update();
+        for part_id in [1, 3] {
+            let key = borsh::to_vec(&StatePartKey(sync_hash, 0, part_id)).unwrap();
+            store_update.set(DBCol::StateParts, &key, &[part_id as u8]);
+        }
+        // Parts of another shard are not resumed.
+        let key = borsh::to_vec(&StatePartKey(sync_hash, 1, 0)).unwrap();
+        store_update.set(DBCol::StateParts, &key, &[0]);
+        store_update.commit().unwrap();
+
+        let mut download = ShardSyncDownload::new_download_state_parts(Utc::UNIX_EPOCH, 4);
+        assert_eq!(mark_stored_parts_done(&chain, 0, sync_hash, &mut download).unwrap(), 2);
+        let done: Vec<_> = download.downloads.iter().map(|d| d.done).collect();
+        assert_eq!(done, vec![false, true, false, true]);
+        assert!(!download.downloads[1].run_me.load(Ordering::SeqCst));
+        assert!(download.downloads[0].run_me.load(Ordering::SeqCst));
+    }
+
     #[test]
     // Start a new state sync - and check that it asks for a header.
     fn test_ask_for_header() {
@@ -1533,6 +2064,7 @@ mod test {
             Duration::seconds(1),
             "chain_id",
             &SyncConfig::Peers,
+            &StateSyncCatchupConfig::default(),
             false,
         );
         let mut new_shard_sync = HashMap::new();
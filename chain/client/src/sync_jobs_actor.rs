@@ -1,3 +1,4 @@
+use crate::metrics;
 use actix::Actor;
 use near_async::actix_wrapper::ActixWrapper;
 use near_async::futures::{DelayedActionRunner, DelayedActionRunnerExt};
@@ -86,10 +87,15 @@ impl SyncJobsActor {
         let store = msg.runtime_adapter.store();
 
         let shard_id = msg.shard_uid.shard_id as ShardId;
+        let sync_type = if msg.catchup_config.is_some() { "catchup" } else { "sync" };
+        let apply_part_delay = metrics::STATE_SYNC_APPLY_PART_DELAY
+            .with_label_values(&[&shard_id.to_string(), sync_type]);
+        let mut batch_size = 0;
         for part_id in 0..msg.num_parts {
             let key = borsh::to_vec(&StatePartKey(msg.sync_hash, shard_id, part_id))?;
             let part = store.get(DBCol::StateParts, &key)?.unwrap();
 
+            let timer = apply_part_delay.start_timer();
             msg.runtime_adapter.apply_state_part(
                 shard_id,
                 &msg.state_root,
@@ -97,6 +103,18 @@ impl SyncJobsActor {
                 &part,
                 &msg.epoch_id,
             )?;
+            timer.observe_duration();
+
+            // Catch-up shares the disk and the CPU with the processing of the
+            // current epoch. Give it a break after every batch of parts.
+            if let Some(catchup_config) = &msg.catchup_config {
+                batch_size += part.len() as u64;
+                if batch_size >= catchup_config.apply_batch_size.as_u64() {
+                    batch_size = 0;
+                    metrics::STATE_SYNC_CATCHUP_THROTTLED.with_label_values(&["apply"]).inc();
+                    std::thread::sleep(catchup_config.apply_batch_delay.unsigned_abs());
+                }
+            }
         }
 
         Ok(())
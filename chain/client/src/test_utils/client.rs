@@ -92,6 +92,7 @@ impl Client {
             encoded_chunk_parts_paths: merkle_paths,
             receipts,
             transactions_storage_proof,
+            state_witness_base_parts,
         } = create_chunk_on_height_for_shard(self, height, shard_id);
         let shard_chunk = self
             .persist_and_distribute_encoded_chunk(
@@ -114,6 +115,7 @@ impl Client {
             &prev_chunk_header,
             &shard_chunk,
             transactions_storage_proof,
+            state_witness_base_parts,
         )
         .unwrap();
         shard_chunk
@@ -165,6 +167,7 @@ pub fn create_chunk(
         encoded_chunk_parts_paths: mut merkle_paths,
         receipts,
         transactions_storage_proof,
+        state_witness_base_parts,
     } = client
         .try_produce_chunk(
             &last_block,
@@ -260,6 +263,7 @@ pub fn create_chunk(
             encoded_chunk_parts_paths: merkle_paths,
             receipts,
             transactions_storage_proof,
+            state_witness_base_parts,
         },
         block,
     )
@@ -124,6 +124,7 @@ pub fn setup(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            shadow_chunk_validation: MutableConfigValue::new(false, "shadow_chunk_validation"),
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -175,6 +176,7 @@ pub fn setup(
         noop().into_multi_sender(),
         signer.clone(),
         epoch_manager.clone(),
+        Default::default(),
     ));
     let partial_witness_adapter = partial_witness_addr.with_auto_span_context();
 
@@ -268,6 +270,7 @@ pub fn setup_only_view(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            shadow_chunk_validation: MutableConfigValue::new(false, "shadow_chunk_validation"),
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -857,6 +860,7 @@ pub fn setup_mock_all_validators(
                     | NetworkRequests::BanPeer { .. }
                     | NetworkRequests::TxStatus(_, _, _)
                     | NetworkRequests::SnapshotHostInfo { .. }
+                    | NetworkRequests::StatePartVerified { .. }
                     | NetworkRequests::Challenge(_)
                     | NetworkRequests::ChunkStateWitness(_, _)
                     | NetworkRequests::ChunkStateWitnessAck(_, _)
@@ -1028,6 +1032,7 @@ pub fn setup_synchronous_shards_manager(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            shadow_chunk_validation: MutableConfigValue::new(false, "shadow_chunk_validation"),
         }, // irrelevant
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -680,7 +680,11 @@ impl TestEnv {
     }
 
     pub fn get_runtime_config(&self, idx: usize, epoch_id: EpochId) -> RuntimeConfig {
-        self.clients[idx].runtime_adapter.get_protocol_config(&epoch_id).unwrap().runtime_config
+        self.clients[idx]
+            .runtime_adapter
+            .get_protocol_config(&epoch_id, None)
+            .unwrap()
+            .runtime_config
     }
 
     /// Create and sign transaction ready for execution.
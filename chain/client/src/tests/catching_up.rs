@@ -282,6 +282,7 @@ fn test_catchup_receipts_sync_common(wait_till: u64, send: u64, sync_hold: bool)
                             sync_hash,
                             part_id,
                             peer_id,
+                            ..
                         } = msg
                         {
                             if sync_hold {
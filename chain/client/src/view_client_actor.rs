@@ -17,11 +17,12 @@ use near_chain_configs::{ClientConfig, ProtocolConfigView};
 use near_chain_primitives::error::EpochErrorResultToChainError;
 use near_client_primitives::types::{
     Error, GetBlock, GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
-    GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome, GetExecutionOutcomeError,
-    GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError, GetMaintenanceWindows,
-    GetMaintenanceWindowsError, GetNextLightClientBlockError, GetProtocolConfig,
-    GetProtocolConfigError, GetReceipt, GetReceiptError, GetSplitStorageInfo,
-    GetSplitStorageInfoError, GetStateChangesError, GetStateChangesWithCauseInBlock,
+    GetBlockWithMerkleTree, GetChunkEndorsements, GetChunkEndorsementsError, GetChunkError,
+    GetExecutionOutcome, GetExecutionOutcomeError, GetExecutionOutcomesForBlock, GetGasPrice,
+    GetGasPriceError, GetMaintenanceWindows, GetMaintenanceWindowsError, GetMemTrieStatus,
+    GetMemTrieStatusError, GetNextLightClientBlockError, GetProtocolConfig, GetProtocolConfigError,
+    GetReceipt, GetReceiptError, GetSplitStorageInfo, GetSplitStorageInfoError,
+    GetStateChangesError, GetStateChangesWithCauseInBlock,
     GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, Query, QueryError,
     TxStatus, TxStatusError,
 };
@@ -48,19 +49,23 @@ use near_primitives::state_sync::{
 };
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockId, BlockReference, EpochReference, Finality, MaybeBlockId,
-    ShardId, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
+    AccountId, BlockHeight, BlockHeightDelta, BlockId, BlockReference, EpochId, EpochReference,
+    Finality, MaybeBlockId, ShardId, SyncCheckpoint, TransactionOrReceiptId,
+    ValidatorInfoIdentifier,
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView, ExecutionStatusView,
-    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasPriceView,
-    LightClientBlockView, MaintenanceWindowsView, QueryRequest, QueryResponse, ReceiptView,
-    SignedTransactionView, SplitStorageInfoView, StateChangesKindsView, StateChangesView,
-    TxExecutionStatus, TxStatusView,
+    BlockChunkEndorsementsView, BlockView, ChunkEndorsementsInfoView, ChunkEndorsementsView,
+    ChunkValidatorEndorsementView, ChunkView, ColdMigrationProgressView, EpochValidatorInfo,
+    ExecutionOutcomeWithIdView, ExecutionStatusView, FinalExecutionOutcomeView,
+    FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasPriceView, LightClientBlockView,
+    MaintenanceWindowsView, MemTrieStatusView, QueryRequest, QueryResponse, ReceiptView,
+    ShardMemTrieStatusView, SignedTransactionView, SplitStorageInfoView, StateChangesKindsView,
+    StateChangesRequestView, StateChangesView, TxExecutionStatus, TxStatusView,
 };
+use near_store::cold_storage::{is_shard_archived, ColdMigrationProgress};
 use near_store::flat::{FlatStorageReadyStatus, FlatStorageStatus};
-use near_store::{DBCol, COLD_HEAD_KEY, FINAL_HEAD_KEY, HEAD_KEY};
+use near_store::{DBCol, COLD_HEAD_KEY, COLD_MIGRATION_PROGRESS_KEY, FINAL_HEAD_KEY, HEAD_KEY};
 use std::cmp::Ordering;
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::hash::Hash;
@@ -71,6 +76,8 @@ use tracing::{error, info, warn};
 const QUERY_REQUEST_LIMIT: usize = 500;
 /// Waiting time between requests, in ms
 const REQUEST_WAIT_TIME: i64 = 1000;
+/// Max number of blocks of which the chunk endorsements can be requested at once.
+const MAX_CHUNK_ENDORSEMENTS_NUM_BLOCKS: BlockHeightDelta = 100;
 
 const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
 
@@ -164,6 +171,77 @@ impl ViewClientActorInner {
         })
     }
 
+    /// Returns whether the history of the shard in the epoch is kept in the
+    /// cold storage.  Partial archival nodes only archive the configured
+    /// shards.
+    fn is_shard_archived(&self, shard_id: ShardId, epoch_id: &EpochId) -> bool {
+        self.epoch_manager.shard_id_to_uid(shard_id, epoch_id).map_or(true, |shard_uid| {
+            is_shard_archived(self.config.archived_shards.as_deref(), &shard_uid)
+        })
+    }
+
+    /// Returns the first of the shards whose data at the block is unavailable,
+    /// being garbage collected from the hot storage and not archived.
+    fn unavailable_shard(
+        &self,
+        header: &BlockHeader,
+        shard_ids: impl IntoIterator<Item = ShardId>,
+    ) -> Result<Option<ShardId>, near_chain::Error> {
+        if self.config.archived_shards.is_none() {
+            return Ok(None);
+        }
+        let tip = self.chain.head()?;
+        if header.height() >= self.runtime.get_gc_stop_height(&tip.last_block_hash) {
+            return Ok(None);
+        }
+        Ok(shard_ids
+            .into_iter()
+            .find(|shard_id| !self.is_shard_archived(*shard_id, header.epoch_id())))
+    }
+
+    /// Fails if the state changes of the block are unavailable for the shards
+    /// of `account_ids`, or for any shard if not given, as otherwise the
+    /// changes of these shards would be silently left out.
+    fn check_state_changes_available(
+        &self,
+        block_hash: &CryptoHash,
+        account_ids: Option<Vec<&AccountId>>,
+    ) -> Result<(), GetStateChangesError> {
+        let header = self.chain.get_block_header(block_hash)?;
+        let shard_ids = match account_ids {
+            Some(account_ids) => account_ids
+                .into_iter()
+                .map(|account_id| {
+                    self.epoch_manager.account_id_to_shard_id(account_id, header.epoch_id())
+                })
+                .collect::<Result<Vec<_>, _>>(),
+            None => self.epoch_manager.shard_ids(header.epoch_id()),
+        }
+        .into_chain_error()?;
+        match self.unavailable_shard(&header, shard_ids)? {
+            Some(shard_id) => Err(GetStateChangesError::UnavailableShard { shard_id }),
+            None => Ok(()),
+        }
+    }
+
+    /// Reports chunks missing because their shard is not archived as an
+    /// unavailable shard rather than as unknown chunks.
+    fn missing_chunk_error(
+        &self,
+        err: near_chain::Error,
+        shard_id: ShardId,
+        epoch_id: &EpochId,
+    ) -> GetChunkError {
+        match err {
+            near_chain::Error::ChunkMissing(_) | near_chain::Error::DBNotFoundErr(_)
+                if !self.is_shard_archived(shard_id, epoch_id) =>
+            {
+                GetChunkError::UnavailableShard { shard_id }
+            }
+            err => err.into(),
+        }
+    }
+
     fn maybe_block_id_to_block_header(
         &self,
         block_id: MaybeBlockId,
@@ -320,6 +398,55 @@ impl ViewClientActorInner {
         Ok(windows)
     }
 
+    /// Returns the endorsements of the chunks included for the first time in the block,
+    /// as recorded in the block body.
+    fn get_block_chunk_endorsements(
+        &self,
+        block: &Block,
+    ) -> Result<BlockChunkEndorsementsView, near_chain::Error> {
+        let epoch_id =
+            self.epoch_manager.get_epoch_id_from_prev_block(block.header().prev_hash())?;
+        let mut chunks = Vec::new();
+        for (chunk_header, signatures) in block.chunks().iter().zip(block.chunk_endorsements()) {
+            // Endorsements are only included with the new chunks, and not before stateless validation.
+            if chunk_header.height_included() != block.header().height() || signatures.is_empty() {
+                continue;
+            }
+            let chunk_validator_assignments = self.epoch_manager.get_chunk_validator_assignments(
+                &epoch_id,
+                chunk_header.shard_id(),
+                chunk_header.height_created(),
+            )?;
+            let validators: Vec<ChunkValidatorEndorsementView> = chunk_validator_assignments
+                .assignments()
+                .iter()
+                .zip(signatures)
+                .map(|((account_id, stake), signature)| ChunkValidatorEndorsementView {
+                    account_id: account_id.clone(),
+                    stake: *stake,
+                    endorsed: signature.is_some(),
+                })
+                .collect();
+            chunks.push(ChunkEndorsementsInfoView {
+                shard_id: chunk_header.shard_id(),
+                chunk_hash: chunk_header.chunk_hash().0,
+                height_created: chunk_header.height_created(),
+                endorsed_stake: validators
+                    .iter()
+                    .filter(|validator| validator.endorsed)
+                    .map(|validator| validator.stake)
+                    .sum(),
+                total_stake: validators.iter().map(|validator| validator.stake).sum(),
+                validators,
+            });
+        }
+        Ok(BlockChunkEndorsementsView {
+            block_hash: *block.hash(),
+            height: block.header().height(),
+            chunks,
+        })
+    }
+
     fn handle_query(&mut self, msg: Query) -> Result<QueryResponse, QueryError> {
         let header = self.get_block_header_by_reference(&msg.block_reference);
         let header = match header {
@@ -341,6 +468,7 @@ impl ViewClientActorInner {
             QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
             QueryRequest::CallFunction { account_id, .. } => account_id,
             QueryRequest::ViewCode { account_id, .. } => account_id,
+            QueryRequest::ViewPromiseYield { account_id, .. } => account_id,
         };
         let shard_id = self
             .epoch_manager
@@ -350,6 +478,13 @@ impl ViewClientActorInner {
             .epoch_manager
             .shard_id_to_uid(shard_id, header.epoch_id())
             .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
+        if self
+            .unavailable_shard(&header, [shard_id])
+            .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?
+            .is_some()
+        {
+            return Err(QueryError::UnavailableShard { requested_shard_id: shard_id });
+        }
 
         let tip = self.chain.head();
         let chunk_extra =
@@ -659,6 +794,12 @@ impl ViewClientActorInner {
         false
     }
 
+    /// Whether the node serves the state of old epochs, for which it doesn't
+    /// have state snapshots.
+    fn serves_historical_state(&self) -> bool {
+        self.config.archive && self.config.state_sync.serve_historical_state
+    }
+
     fn has_state_snapshot(&self, sync_hash: &CryptoHash, shard_id: ShardId) -> Result<bool, Error> {
         let header = self.chain.get_block_header(sync_hash)?;
         let prev_header = self.chain.get_block_header(header.prev_hash())?;
@@ -758,11 +899,15 @@ impl Handler<GetChunk> for ViewClientActorInner {
             }
             GetChunk::BlockHash(block_hash, shard_id) => {
                 let block = self.chain.get_block(&block_hash)?;
-                get_chunk_from_block(block, shard_id, &self.chain)?
+                let epoch_id = *block.header().epoch_id();
+                get_chunk_from_block(block, shard_id, &self.chain)
+                    .map_err(|err| self.missing_chunk_error(err, shard_id, &epoch_id))?
             }
             GetChunk::Height(height, shard_id) => {
                 let block = self.chain.get_block_by_height(height)?;
-                get_chunk_from_block(block, shard_id, &self.chain)?
+                let epoch_id = *block.header().epoch_id();
+                get_chunk_from_block(block, shard_id, &self.chain)
+                    .map_err(|err| self.missing_chunk_error(err, shard_id, &epoch_id))?
             }
         };
 
@@ -867,6 +1012,7 @@ impl Handler<GetStateChangesInBlock> for ViewClientActorInner {
         let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
             .with_label_values(&["GetStateChangesInBlock"])
             .start_timer();
+        self.check_state_changes_available(&msg.block_hash, None)?;
         Ok(self
             .chain
             .chain_store()
@@ -884,6 +1030,18 @@ impl Handler<GetStateChanges> for ViewClientActorInner {
         tracing::debug!(target: "client", ?msg);
         let _timer =
             metrics::VIEW_CLIENT_MESSAGE_TIME.with_label_values(&["GetStateChanges"]).start_timer();
+        let account_ids = match &msg.state_changes_request {
+            StateChangesRequestView::AccountChanges { account_ids }
+            | StateChangesRequestView::AllAccessKeyChanges { account_ids }
+            | StateChangesRequestView::ContractCodeChanges { account_ids }
+            | StateChangesRequestView::DataChanges { account_ids, .. } => {
+                account_ids.iter().collect()
+            }
+            StateChangesRequestView::SingleAccessKeyChanges { keys } => {
+                keys.iter().map(|key| &key.account_id).collect()
+            }
+        };
+        self.check_state_changes_available(&msg.block_hash, Some(account_ids))?;
         Ok(self
             .chain
             .chain_store()
@@ -905,6 +1063,7 @@ impl Handler<GetStateChangesWithCauseInBlock> for ViewClientActorInner {
         let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
             .with_label_values(&["GetStateChangesWithCauseInBlock"])
             .start_timer();
+        self.check_state_changes_available(&msg.block_hash, None)?;
         Ok(self
             .chain
             .chain_store()
@@ -927,6 +1086,7 @@ impl Handler<GetStateChangesWithCauseInBlockForTrackedShards> for ViewClientActo
         let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
             .with_label_values(&["GetStateChangesWithCauseInBlockForTrackedShards"])
             .start_timer();
+        self.check_state_changes_available(&msg.block_hash, None)?;
         let state_changes_with_cause_in_block =
             self.chain.chain_store().get_state_changes_with_cause_in_block(&msg.block_hash)?;
 
@@ -1073,7 +1233,8 @@ impl Handler<GetExecutionOutcome> for ViewClientActorInner {
                     &head.last_block_hash,
                     target_shard_id,
                     true,
-                ) {
+                ) && self.is_shard_archived(target_shard_id, &head.epoch_id)
+                {
                     Err(GetExecutionOutcomeError::UnknownTransactionOrReceipt {
                         transaction_or_receipt_id: id,
                     })
@@ -1157,7 +1318,21 @@ impl Handler<GetProtocolConfig> for ViewClientActorInner {
             }
             Some(header) => header,
         };
-        let config = self.runtime.get_protocol_config(header.epoch_id())?;
+        // The runtime config overrides are read from the state of the first
+        // shard the node has at the block. All shards store the same overrides,
+        // unless the distribution receipts reached some of them too late.
+        let mut shard_state = None;
+        for shard_id in self.epoch_manager.shard_ids(header.epoch_id()).into_chain_error()? {
+            let shard_uid = self
+                .epoch_manager
+                .shard_id_to_uid(shard_id, header.epoch_id())
+                .into_chain_error()?;
+            if let Ok(chunk_extra) = self.chain.get_chunk_extra(header.hash(), &shard_uid) {
+                shard_state = Some((shard_id, *chunk_extra.state_root()));
+                break;
+            }
+        }
+        let config = self.runtime.get_protocol_config(header.epoch_id(), shard_state)?;
         Ok(config.into())
     }
 }
@@ -1354,14 +1529,27 @@ impl Handler<StateRequestPart> for ViewClientActorInner {
             tracing::debug!(target: "sync", ?sync_hash, "Throttle state sync requests");
             return None;
         }
-        if let Err(err) = self.has_state_snapshot(&sync_hash, shard_id) {
-            tracing::debug!(target: "sync", ?err, ?sync_hash, "Node doesn't have a matching state snapshot");
-            return None;
-        }
-        tracing::debug!(target: "sync", ?shard_id, ?sync_hash, ?part_id, "Computing state request part");
+        // Archival nodes can generate the parts of old epochs from the trie,
+        // without a state snapshot.
+        let historical = match self.has_state_snapshot(&sync_hash, shard_id) {
+            Ok(true) => false,
+            _ if self.serves_historical_state() => true,
+            Ok(false) => false,
+            Err(err) => {
+                tracing::debug!(target: "sync", ?err, ?sync_hash, "Node doesn't have a matching state snapshot");
+                return None;
+            }
+        };
+        tracing::debug!(target: "sync", ?shard_id, ?sync_hash, ?part_id, historical, "Computing state request part");
         let part = match self.chain.check_sync_hash_validity(&sync_hash) {
             Ok(true) => {
-                let part = match self.chain.get_state_response_part(shard_id, part_id, sync_hash) {
+                let part = if historical {
+                    metrics::STATE_SYNC_HISTORICAL_PARTS_SERVED.inc();
+                    self.chain.get_historical_state_response_part(shard_id, part_id, sync_hash)
+                } else {
+                    self.chain.get_state_response_part(shard_id, part_id, sync_hash)
+                };
+                let part = match part {
                     Ok(part) => Some((part_id, part)),
                     Err(err) => {
                         error!(target: "sync", ?err, ?sync_hash, shard_id, part_id, "Cannot build state part");
@@ -1493,6 +1681,61 @@ impl Handler<GetMaintenanceWindows> for ViewClientActorInner {
     }
 }
 
+impl Handler<GetChunkEndorsements> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetChunkEndorsements,
+    ) -> Result<ChunkEndorsementsView, GetChunkEndorsementsError> {
+        tracing::debug!(target: "client", ?msg);
+
+        let mut blocks = Vec::new();
+        let mut block_hash = self.chain.head()?.last_block_hash;
+        for _ in 0..msg.num_blocks.min(MAX_CHUNK_ENDORSEMENTS_NUM_BLOCKS) {
+            let block = self.chain.get_block(&block_hash)?;
+            if block.header().height() == self.chain.genesis().height() {
+                break;
+            }
+            blocks.push(self.get_block_chunk_endorsements(&block)?);
+            block_hash = *block.header().prev_hash();
+        }
+        Ok(ChunkEndorsementsView { blocks })
+    }
+}
+
+impl Handler<GetMemTrieStatus> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetMemTrieStatus,
+    ) -> Result<MemTrieStatusView, GetMemTrieStatusError> {
+        tracing::debug!(target: "client", ?msg);
+
+        let tries = self.runtime.get_tries();
+        let shards = tries
+            .loaded_mem_trie_shards()
+            .into_iter()
+            .filter_map(|shard_uid| {
+                // The trie may have been unloaded since the shards were listed.
+                let mem_tries = tries.get_mem_tries(shard_uid)?;
+                let mem_tries = mem_tries.read().unwrap();
+                let root_heights = mem_tries.root_heights();
+                Some(ShardMemTrieStatusView {
+                    shard_id: shard_uid.shard_id as ShardId,
+                    shard_layout_version: shard_uid.version,
+                    num_nodes: mem_tries.num_nodes() as u64,
+                    arena_reserved_bytes: mem_tries.arena_memory_usage_bytes() as u64,
+                    arena_active_bytes: mem_tries.arena_active_bytes() as u64,
+                    num_roots: mem_tries.num_roots() as u64,
+                    min_root_height: root_heights.map(|(min, _)| min),
+                    max_root_height: root_heights.map(|(_, max)| max),
+                })
+            })
+            .collect();
+        Ok(MemTrieStatusView { shards })
+    }
+}
+
 impl Handler<GetSplitStorageInfo> for ViewClientActorInner {
     fn handle(
         &mut self,
@@ -1506,12 +1749,22 @@ impl Handler<GetSplitStorageInfo> for ViewClientActorInner {
         let cold_head = store.get_ser::<Tip>(DBCol::BlockMisc, COLD_HEAD_KEY)?;
 
         let hot_db_kind = store.get_db_kind()?.map(|kind| kind.to_string());
+        let cold_migration = store
+            .get_ser::<ColdMigrationProgress>(DBCol::BlockMisc, COLD_MIGRATION_PROGRESS_KEY)?
+            .map(|progress| ColdMigrationProgressView {
+                target_height: progress.target_height,
+                column: progress.column,
+                copied_bytes: progress.copied_bytes,
+                total_bytes: progress.total_bytes,
+                eta_secs: progress.eta_secs,
+            });
 
         Ok(SplitStorageInfoView {
             head_height: head.map(|tip| tip.height),
             final_head_height: final_head.map(|tip| tip.height),
             cold_head_height: cold_head.map(|tip| tip.height),
             hot_db_kind,
+            cold_migration,
         })
     }
 }
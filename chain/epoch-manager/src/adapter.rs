@@ -1,9 +1,9 @@
 use crate::types::BlockHeaderInfo;
 #[cfg(feature = "new_epoch_sync")]
 use crate::EpochInfoAggregator;
-use crate::EpochManagerHandle;
+use crate::{EpochManager, EpochManagerHandle};
 use near_chain_primitives::Error;
-use near_crypto::Signature;
+use near_crypto::{PublicKey, Signature};
 use near_primitives::block::Tip;
 use near_primitives::block_header::{Approval, ApprovalInner, BlockHeader};
 use near_primitives::epoch_manager::block_info::BlockInfo;
@@ -412,6 +412,14 @@ pub trait EpochManagerAdapter: Send + Sync {
         endorsement: &ChunkEndorsement,
     ) -> Result<bool, Error>;
 
+    /// Verifies the endorsements, each given with the header of its chunk, the same way
+    /// as `verify_chunk_endorsement`, but checks the signatures of all of them with a
+    /// single batched verification. Returns a result for every endorsement.
+    fn verify_chunk_endorsements_batch(
+        &self,
+        endorsements: &[(&ShardChunkHeader, &ChunkEndorsement)],
+    ) -> Vec<Result<bool, Error>>;
+
     // TODO(stateless_validation): Deprecate this function after partial witness
     fn verify_chunk_state_witness_signature(
         &self,
@@ -1043,25 +1051,42 @@ impl EpochManagerAdapter for EpochManagerHandle {
         chunk_header: &ShardChunkHeader,
         endorsement: &ChunkEndorsement,
     ) -> Result<bool, Error> {
-        if &chunk_header.chunk_hash() != endorsement.chunk_hash() {
-            return Err(Error::InvalidChunkEndorsement);
-        }
-        let epoch_manager = self.read();
-        let epoch_id =
-            epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
-        // Note that we are using the chunk_header.height_created param here to determine the chunk validators
-        // This only works when height created for a chunk is the same as the height_included during block production
-        let chunk_validator_assignments = epoch_manager.get_chunk_validator_assignments(
-            &epoch_id,
-            chunk_header.shard_id(),
-            chunk_header.height_created(),
-        )?;
-        if !chunk_validator_assignments.contains(&endorsement.account_id) {
-            return Err(Error::NotAValidator);
-        }
-        let validator =
-            epoch_manager.get_validator_by_account_id(&epoch_id, &endorsement.account_id)?;
-        Ok(endorsement.verify(validator.public_key()))
+        let public_key = chunk_endorsement_public_key(&self.read(), chunk_header, endorsement)?;
+        Ok(endorsement.verify(&public_key))
+    }
+
+    fn verify_chunk_endorsements_batch(
+        &self,
+        endorsements: &[(&ShardChunkHeader, &ChunkEndorsement)],
+    ) -> Vec<Result<bool, Error>> {
+        let public_keys: Vec<_> = {
+            let epoch_manager = self.read();
+            endorsements
+                .iter()
+                .map(|(chunk_header, endorsement)| {
+                    chunk_endorsement_public_key(&epoch_manager, chunk_header, endorsement)
+                })
+                .collect()
+        };
+        let all_valid = {
+            let batch: Vec<_> = endorsements
+                .iter()
+                .zip(&public_keys)
+                .filter_map(|((_, endorsement), public_key)| {
+                    Some((*endorsement, public_key.as_ref().ok()?))
+                })
+                .collect();
+            ChunkEndorsement::verify_batch(&batch)
+        };
+        endorsements
+            .iter()
+            .zip(public_keys)
+            .map(|((_, endorsement), public_key)| {
+                let public_key = public_key?;
+                // If the batch is invalid, the invalid endorsements are found one by one.
+                Ok(all_valid || endorsement.verify(&public_key))
+            })
+            .collect()
     }
 
     // TODO(stateless_validation): Deprecate this function after partial witness
@@ -1151,3 +1176,29 @@ impl EpochManagerAdapter for EpochManagerHandle {
         epoch_manager.epoch_info_aggregator = EpochInfoAggregator::new(epoch_id.clone(), *hash);
     }
 }
+
+/// Returns the public key of the chunk validator which signed the endorsement,
+/// checking that it is assigned to validate the chunk.
+fn chunk_endorsement_public_key(
+    epoch_manager: &EpochManager,
+    chunk_header: &ShardChunkHeader,
+    endorsement: &ChunkEndorsement,
+) -> Result<PublicKey, Error> {
+    if &chunk_header.chunk_hash() != endorsement.chunk_hash() {
+        return Err(Error::InvalidChunkEndorsement);
+    }
+    let epoch_id = epoch_manager.get_epoch_id_from_prev_block(chunk_header.prev_block_hash())?;
+    // Note that we are using the chunk_header.height_created param here to determine the chunk validators
+    // This only works when height created for a chunk is the same as the height_included during block production
+    let chunk_validator_assignments = epoch_manager.get_chunk_validator_assignments(
+        &epoch_id,
+        chunk_header.shard_id(),
+        chunk_header.height_created(),
+    )?;
+    if !chunk_validator_assignments.contains(&endorsement.account_id) {
+        return Err(Error::NotAValidator);
+    }
+    let validator =
+        epoch_manager.get_validator_by_account_id(&epoch_id, &endorsement.account_id)?;
+    Ok(validator.public_key().clone())
+}
@@ -124,6 +124,15 @@ impl EpochInfoProvider for EpochManagerHandle {
     ) -> Result<ShardId, EpochError> {
         EpochManagerAdapter::account_id_to_shard_id(self, account_id, epoch_id)
     }
+
+    fn shard_layout(&self, epoch_id: &EpochId) -> Result<ShardLayout, EpochError> {
+        EpochManagerAdapter::get_shard_layout(self, epoch_id)
+    }
+
+    fn protocol_treasury_account(&self) -> AccountId {
+        let epoch_manager = self.read();
+        epoch_manager.reward_calculator.protocol_treasury_account.clone()
+    }
 }
 
 /// Tracks epoch information across different forks, such as validators.
@@ -34,6 +34,8 @@ pub enum RpcStateChangesError {
     },
     #[error("There are no fully synchronized blocks yet")]
     NotSyncedYet,
+    #[error("The node does not archive the history of shard {shard_id}")]
+    UnavailableShard { shard_id: u64 },
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
     InternalError { error_message: String },
 }
@@ -0,0 +1,59 @@
+use near_primitives::types::BlockHeightDelta;
+use near_primitives::views::ChunkEndorsementsView;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::types::status::RpcStatusError;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcChunkEndorsementsRequest {
+    /// Number of the latest blocks to return the chunk endorsements of.
+    #[serde(default)]
+    pub num_blocks: Option<BlockHeightDelta>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcChunkEndorsementsResponse {
+    #[serde(flatten)]
+    pub result: ChunkEndorsementsView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcChunkEndorsementsError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcChunkEndorsementsError> for crate::errors::RpcError {
+    fn from(error: RpcChunkEndorsementsError) -> Self {
+        let error_data = match &error {
+            RpcChunkEndorsementsError::InternalError { .. } => {
+                Some(Value::String(error.to_string()))
+            }
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcChunkEndorsementsError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
+
+impl RpcChunkEndorsementsError {
+    // Used by the debug page, which reports errors as `RpcStatusError`.
+    pub fn into_rpc_status_error(self) -> RpcStatusError {
+        match self {
+            RpcChunkEndorsementsError::InternalError { error_message } => {
+                RpcStatusError::InternalError { error_message }
+            }
+        }
+    }
+}
@@ -38,6 +38,8 @@ pub enum RpcChunkError {
     InvalidShardId { shard_id: u64 },
     #[error("Chunk with hash {chunk_hash:?} has never been observed on this node")]
     UnknownChunk { chunk_hash: near_primitives::sharding::ChunkHash },
+    #[error("The node does not archive the history of shard {shard_id}")]
+    UnavailableShard { shard_id: u64 },
 }
 
 impl From<RpcChunkError> for crate::errors::RpcError {
@@ -53,6 +55,7 @@ impl From<RpcChunkError> for crate::errors::RpcError {
                 "Chunk Missing (unavailable on the node): ChunkHash(`{}`) \n Cause: Unknown",
                 chunk_hash.0
             ))),
+            RpcChunkError::UnavailableShard { .. } => Some(Value::String(error.to_string())),
         };
 
         let error_data_value = match serde_json::to_value(error) {
@@ -0,0 +1,39 @@
+use near_primitives::views::MemTrieStatusView;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcMemTrieStatusRequest {}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcMemTrieStatusResponse {
+    #[serde(flatten)]
+    pub result: MemTrieStatusView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcMemTrieStatusError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcMemTrieStatusError> for crate::errors::RpcError {
+    fn from(error: RpcMemTrieStatusError) -> Self {
+        let error_data = match &error {
+            RpcMemTrieStatusError::InternalError { .. } => Some(Value::String(error.to_string())),
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcMemTrieStatusError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -1,5 +1,6 @@
 pub mod blocks;
 pub mod changes;
+pub mod chunk_endorsements;
 pub mod chunks;
 pub mod client_config;
 pub mod config;
@@ -7,6 +8,7 @@ pub mod entity_debug;
 pub mod gas_price;
 pub mod light_client;
 pub mod maintenance;
+pub mod memtrie_status;
 pub mod network_info;
 pub mod query;
 pub mod receipts;
@@ -15,3 +17,4 @@ pub mod split_storage;
 pub mod status;
 pub mod transactions;
 pub mod validator;
+pub mod witness_stats;
@@ -81,6 +81,7 @@ pub enum QueryResponseKind {
     CallResult(near_primitives::views::CallResult),
     AccessKey(near_primitives::views::AccessKeyView),
     AccessKeyList(near_primitives::views::AccessKeyList),
+    ViewPromiseYield(near_primitives::views::PromiseYieldView),
 }
 
 impl From<RpcQueryError> for crate::errors::RpcError {
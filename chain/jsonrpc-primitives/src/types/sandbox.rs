@@ -1,9 +1,18 @@
+use near_primitives::sandbox::SandboxRuntimeConfigPatch;
 use near_primitives::state_record::StateRecord;
-use near_primitives::types::BlockHeightDelta;
+use near_primitives::types::{BlockHeight, BlockHeightDelta};
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct RpcSandboxPatchStateRequest {
     pub records: Vec<StateRecord>,
+    /// Applies the patch with the first block at or above this height instead
+    /// of the next block. The response doesn't wait for such patches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<BlockHeight>,
+    /// Runtime config parameters overridden from the block the patch is
+    /// applied with onwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_config: Option<SandboxRuntimeConfigPatch>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
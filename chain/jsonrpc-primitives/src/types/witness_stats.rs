@@ -0,0 +1,44 @@
+use near_primitives::types::ShardId;
+use near_primitives::views::WitnessStatsView;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcWitnessStatsRequest {
+    /// Only return the statistics of this shard, all shards if not set.
+    #[serde(default)]
+    pub shard_id: Option<ShardId>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcWitnessStatsResponse {
+    #[serde(flatten)]
+    pub result: WitnessStatsView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcWitnessStatsError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcWitnessStatsError> for crate::errors::RpcError {
+    fn from(error: RpcWitnessStatsError) -> Self {
+        let error_data = match &error {
+            RpcWitnessStatsError::InternalError { .. } => Some(Value::String(error.to_string())),
+        };
+
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcWitnessStatsError: {:?}", err),
+                )
+            }
+        };
+
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
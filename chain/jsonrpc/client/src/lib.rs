@@ -267,6 +267,31 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_split_storage_info", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_chunk_endorsements(
+        &self,
+        request: near_jsonrpc_primitives::types::chunk_endorsements::RpcChunkEndorsementsRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::chunk_endorsements::RpcChunkEndorsementsResponse>
+    {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_chunk_endorsements", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_memtrie_status(
+        &self,
+        request: near_jsonrpc_primitives::types::memtrie_status::RpcMemTrieStatusRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::memtrie_status::RpcMemTrieStatusResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_memtrie_status", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_witness_stats(
+        &self,
+        request: near_jsonrpc_primitives::types::witness_stats::RpcWitnessStatsRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::witness_stats::RpcWitnessStatsResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_witness_stats", request)
+    }
+
     pub fn validators(
         &self,
         epoch_id_or_block_id: Option<EpochReference>,
@@ -54,6 +54,7 @@ impl RpcFrom<GetChunkError> for RpcChunkError {
             GetChunkError::UnknownBlock { error_message } => Self::UnknownBlock { error_message },
             GetChunkError::InvalidShardId { shard_id } => Self::InvalidShardId { shard_id },
             GetChunkError::UnknownChunk { chunk_hash } => Self::UnknownChunk { chunk_hash },
+            GetChunkError::UnavailableShard { shard_id } => Self::UnavailableShard { shard_id },
             GetChunkError::Unreachable { ref error_message } => {
                 tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
                 crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
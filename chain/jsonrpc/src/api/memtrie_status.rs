@@ -0,0 +1,35 @@
+use near_async::messaging::AsyncSendError;
+use near_client_primitives::types::GetMemTrieStatusError;
+use near_jsonrpc_primitives::{
+    errors::RpcParseError,
+    types::memtrie_status::{RpcMemTrieStatusError, RpcMemTrieStatusRequest},
+};
+use serde_json::Value;
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcMemTrieStatusRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcMemTrieStatusError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetMemTrieStatusError> for RpcMemTrieStatusError {
+    fn rpc_from(error: GetMemTrieStatusError) -> Self {
+        match error {
+            GetMemTrieStatusError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcMemTrieStatusError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
@@ -6,12 +6,14 @@ use near_jsonrpc_primitives::errors::{RpcError, ServerError};
 
 mod blocks;
 mod changes;
+mod chunk_endorsements;
 mod chunks;
 mod client_config;
 mod config;
 mod gas_price;
 mod light_client;
 mod maintenance;
+mod memtrie_status;
 mod network_info;
 mod query;
 mod receipts;
@@ -20,6 +22,7 @@ mod split_storage;
 mod status;
 mod transactions;
 mod validator;
+mod witness_stats;
 
 pub(crate) trait RpcRequest: Sized {
     fn parse(value: Value) -> Result<Self, RpcParseError>;
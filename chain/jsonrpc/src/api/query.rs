@@ -167,6 +167,9 @@ impl RpcFrom<near_primitives::views::QueryResponseKind>
             near_primitives::views::QueryResponseKind::AccessKeyList(access_key_list) => {
                 Self::AccessKeyList(access_key_list)
             }
+            near_primitives::views::QueryResponseKind::ViewPromiseYield(promise_yield_view) => {
+                Self::ViewPromiseYield(promise_yield_view)
+            }
         }
     }
 }
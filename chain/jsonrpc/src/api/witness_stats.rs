@@ -0,0 +1,35 @@
+use near_async::messaging::AsyncSendError;
+use near_client_primitives::types::GetWitnessStatsError;
+use near_jsonrpc_primitives::{
+    errors::RpcParseError,
+    types::witness_stats::{RpcWitnessStatsError, RpcWitnessStatsRequest},
+};
+use serde_json::Value;
+
+use super::{Params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcWitnessStatsRequest {
+    fn parse(value: Value) -> Result<Self, RpcParseError> {
+        Params::parse(value)
+    }
+}
+
+impl RpcFrom<AsyncSendError> for RpcWitnessStatsError {
+    fn rpc_from(error: AsyncSendError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<GetWitnessStatsError> for RpcWitnessStatsError {
+    fn rpc_from(error: GetWitnessStatsError) -> Self {
+        match error {
+            GetWitnessStatsError::Unreachable(ref error_message) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcWitnessStatsError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
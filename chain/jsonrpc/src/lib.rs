@@ -14,15 +14,19 @@ use near_async::messaging::{
 };
 use near_chain_configs::GenesisConfig;
 use near_client::{
-    DebugStatus, GetBlock, GetBlockProof, GetChunk, GetClientConfig, GetExecutionOutcome,
-    GetGasPrice, GetMaintenanceWindows, GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig,
-    GetReceipt, GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
+    DebugStatus, GetBlock, GetBlockProof, GetChunk, GetChunkEndorsements, GetClientConfig,
+    GetExecutionOutcome, GetGasPrice, GetMaintenanceWindows, GetMemTrieStatus, GetNetworkInfo,
+    GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetStateChanges,
+    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, GetWitnessStats,
     ProcessTxRequest, ProcessTxResponse, Query, Status, TxStatus,
 };
 use near_client_primitives::types::GetSplitStorageInfo;
 pub use near_jsonrpc_client as client;
 use near_jsonrpc_primitives::errors::RpcError;
 use near_jsonrpc_primitives::message::{Message, Request};
+use near_jsonrpc_primitives::types::chunk_endorsements::{
+    RpcChunkEndorsementsRequest, RpcChunkEndorsementsResponse,
+};
 use near_jsonrpc_primitives::types::config::RpcProtocolConfigResponse;
 use near_jsonrpc_primitives::types::entity_debug::{EntityDebugHandler, EntityQuery};
 use near_jsonrpc_primitives::types::query::RpcQueryRequest;
@@ -37,7 +41,7 @@ use near_network::tcp;
 use near_o11y::metrics::{prometheus, Encoder, TextEncoder};
 use near_primitives::hash::CryptoHash;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::{AccountId, BlockHeight};
+use near_primitives::types::{AccountId, BlockHeight, BlockHeightDelta};
 use near_primitives::views::{QueryRequest, TxExecutionStatus};
 use serde_json::{json, Value};
 use std::path::PathBuf;
@@ -49,6 +53,9 @@ use tracing::{error, info};
 mod api;
 mod metrics;
 
+/// Number of blocks returned by `EXPERIMENTAL_chunk_endorsements` when not specified in the request.
+const DEFAULT_CHUNK_ENDORSEMENTS_NUM_BLOCKS: BlockHeightDelta = 10;
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
 pub struct RpcPollingConfig {
     pub polling_interval: Duration,
@@ -225,6 +232,7 @@ pub struct ClientSenderForRpc(
     AsyncSender<DebugStatus, ActixResult<DebugStatus>>,
     AsyncSender<GetClientConfig, ActixResult<GetClientConfig>>,
     AsyncSender<GetNetworkInfo, ActixResult<GetNetworkInfo>>,
+    AsyncSender<GetWitnessStats, ActixResult<GetWitnessStats>>,
     AsyncSender<ProcessTxRequest, ActixResult<ProcessTxRequest>>,
     AsyncSender<Status, ActixResult<Status>>,
     Sender<ProcessTxRequest>,
@@ -246,9 +254,11 @@ pub struct ViewClientSenderForRpc(
     AsyncSender<GetBlock, ActixResult<GetBlock>>,
     AsyncSender<GetBlockProof, ActixResult<GetBlockProof>>,
     AsyncSender<GetChunk, ActixResult<GetChunk>>,
+    AsyncSender<GetChunkEndorsements, ActixResult<GetChunkEndorsements>>,
     AsyncSender<GetExecutionOutcome, ActixResult<GetExecutionOutcome>>,
     AsyncSender<GetGasPrice, ActixResult<GetGasPrice>>,
     AsyncSender<GetMaintenanceWindows, ActixResult<GetMaintenanceWindows>>,
+    AsyncSender<GetMemTrieStatus, ActixResult<GetMemTrieStatus>>,
     AsyncSender<GetNextLightClientBlock, ActixResult<GetNextLightClientBlock>>,
     AsyncSender<GetProtocolConfig, ActixResult<GetProtocolConfig>>,
     AsyncSender<GetReceipt, ActixResult<GetReceipt>>,
@@ -357,6 +367,7 @@ impl JsonRpcHandler {
                     QueryRequest::ViewAccessKey { .. } => "query_view_access_key",
                     QueryRequest::ViewAccessKeyList { .. } => "query_view_access_key_list",
                     QueryRequest::CallFunction { .. } => "query_call_function",
+                    QueryRequest::ViewPromiseYield { .. } => "query_view_promise_yield",
                 };
                 (metrics_name.to_string(), process_query_response(self.query(params).await))
             }
@@ -405,6 +416,9 @@ impl JsonRpcHandler {
             "client_config" => {
                 process_method_call(request, |_params: ()| self.client_config()).await
             }
+            "EXPERIMENTAL_chunk_endorsements" => {
+                process_method_call(request, |params| self.chunk_endorsements(params)).await
+            }
             "EXPERIMENTAL_changes" => {
                 process_method_call(request, |params| self.changes_in_block_by_type(params)).await
             }
@@ -441,9 +455,15 @@ impl JsonRpcHandler {
             "EXPERIMENTAL_maintenance_windows" => {
                 process_method_call(request, |params| self.maintenance_windows(params)).await
             }
+            "EXPERIMENTAL_memtrie_status" => {
+                process_method_call(request, |params| self.memtrie_status(params)).await
+            }
             "EXPERIMENTAL_split_storage_info" => {
                 process_method_call(request, |params| self.split_storage_info(params)).await
             }
+            "EXPERIMENTAL_witness_stats" => {
+                process_method_call(request, |params| self.witness_stats(params)).await
+            }
             #[cfg(feature = "sandbox")]
             "sandbox_patch_state" => {
                 process_method_call(request, |params| self.sandbox_patch_state(params)).await
@@ -797,6 +817,9 @@ impl JsonRpcHandler {
                     "/debug/api/requested_state_parts" => {
                         self.client_send(DebugStatus::RequestedStateParts).await?.rpc_into()
                     }
+                    "/debug/api/state_sync_progress" => {
+                        self.client_send(DebugStatus::StateSyncProgress).await?.rpc_into()
+                    }
                     "/debug/api/peer_store" => self
                         .peer_manager_send(near_network::debug::GetDebugStatus::PeerStore)
                         .await?
@@ -826,6 +849,13 @@ impl JsonRpcHandler {
                             .map_err(|e| e.into_rpc_status_error())?;
                         near_jsonrpc_primitives::types::status::DebugStatusResponse::SplitStoreStatus(split_storage_info.result)
                     }
+                    "/debug/api/chunk_endorsements" => {
+                        let chunk_endorsements: RpcChunkEndorsementsResponse = self
+                            .chunk_endorsements(RpcChunkEndorsementsRequest { num_blocks: None })
+                            .await
+                            .map_err(|e| e.into_rpc_status_error())?;
+                        near_jsonrpc_primitives::types::status::DebugStatusResponse::ChunkEndorsements(chunk_endorsements.result)
+                    }
                     _ => return Ok(None),
                 };
             Ok(Some(near_jsonrpc_primitives::types::status::RpcDebugStatusResponse {
@@ -1145,6 +1175,45 @@ impl JsonRpcHandler {
         let split_storage = self.view_client_send(GetSplitStorageInfo {}).await?;
         Ok(RpcSplitStorageInfoResponse { result: split_storage })
     }
+
+    pub async fn memtrie_status(
+        &self,
+        _request_data: near_jsonrpc_primitives::types::memtrie_status::RpcMemTrieStatusRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::memtrie_status::RpcMemTrieStatusResponse,
+        near_jsonrpc_primitives::types::memtrie_status::RpcMemTrieStatusError,
+    > {
+        let memtrie_status = self.view_client_send(GetMemTrieStatus {}).await?;
+        Ok(near_jsonrpc_primitives::types::memtrie_status::RpcMemTrieStatusResponse {
+            result: memtrie_status,
+        })
+    }
+
+    pub async fn chunk_endorsements(
+        &self,
+        request_data: RpcChunkEndorsementsRequest,
+    ) -> Result<
+        RpcChunkEndorsementsResponse,
+        near_jsonrpc_primitives::types::chunk_endorsements::RpcChunkEndorsementsError,
+    > {
+        let num_blocks = request_data.num_blocks.unwrap_or(DEFAULT_CHUNK_ENDORSEMENTS_NUM_BLOCKS);
+        let chunk_endorsements = self.view_client_send(GetChunkEndorsements { num_blocks }).await?;
+        Ok(RpcChunkEndorsementsResponse { result: chunk_endorsements })
+    }
+
+    pub async fn witness_stats(
+        &self,
+        request_data: near_jsonrpc_primitives::types::witness_stats::RpcWitnessStatsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::witness_stats::RpcWitnessStatsResponse,
+        near_jsonrpc_primitives::types::witness_stats::RpcWitnessStatsError,
+    > {
+        let witness_stats =
+            self.client_send(GetWitnessStats { shard_id: request_data.shard_id }).await?;
+        Ok(near_jsonrpc_primitives::types::witness_stats::RpcWitnessStatsResponse {
+            result: witness_stats,
+        })
+    }
 }
 
 #[cfg(feature = "sandbox")]
@@ -1156,12 +1225,23 @@ impl JsonRpcHandler {
         near_jsonrpc_primitives::types::sandbox::RpcSandboxPatchStateResponse,
         near_jsonrpc_primitives::types::sandbox::RpcSandboxPatchStateError,
     > {
+        let near_jsonrpc_primitives::types::sandbox::RpcSandboxPatchStateRequest {
+            records,
+            block_height,
+            runtime_config,
+        } = patch_state_request;
         self.client_sender
-            .send_async(near_client_primitives::types::SandboxMessage::SandboxPatchState(
-                patch_state_request.records,
-            ))
+            .send_async(near_client_primitives::types::SandboxMessage::SandboxPatchState {
+                records,
+                block_height,
+                runtime_config,
+            })
             .await
             .map_err(RpcFrom::rpc_from)?;
+        if block_height.is_some() {
+            // The patch may be scheduled far ahead, don't wait for it.
+            return Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxPatchStateResponse {});
+        }
 
         timeout(self.polling_config.polling_timeout, async {
             loop {
@@ -1482,6 +1562,7 @@ async fn display_debug_html(
         "validator" => Some(debug_page_string!("validator.html", handler)),
         "validator.css" => Some(debug_page_string!("validator.css", handler)),
         "split_store" => Some(debug_page_string!("split_store.html", handler)),
+        "chunk_endorsements" => Some(debug_page_string!("chunk_endorsements.html", handler)),
         _ => None,
     };
 
@@ -818,6 +818,15 @@ impl StateResponseInfo {
         }
     }
 
+    /// Id of the state part carried by the response, if any.
+    pub fn part_id(&self) -> Option<u64> {
+        let part = match self {
+            Self::V1(info) => &info.state_response.part,
+            Self::V2(info) => info.state_response.part(),
+        };
+        part.as_ref().map(|(part_id, _)| *part_id)
+    }
+
     pub fn take_state_response(self) -> ShardStateSyncResponse {
         match self {
             Self::V1(info) => ShardStateSyncResponse::V1(info.state_response),
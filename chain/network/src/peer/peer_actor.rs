@@ -1169,6 +1169,14 @@ impl PeerActor {
                     .flatten()
                     .map(|response| PeerMessage::VersionedStateResponse(*response.0)),
                 PeerMessage::VersionedStateResponse(info) => {
+                    if let Some(part_id) = info.part_id() {
+                        network_state.snapshot_hosts.part_received(
+                            &info.sync_hash(),
+                            info.shard_id(),
+                            part_id,
+                            &peer_id,
+                        );
+                    }
                     //TODO: Route to state sync actor.
                     network_state.client.send_async(StateResponse(info.into())).await.ok();
                     None
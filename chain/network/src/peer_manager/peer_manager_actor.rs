@@ -787,7 +787,35 @@ impl PeerManagerActor {
                     NetworkResponses::RouteNotFound
                 }
             }
-            NetworkRequests::StateRequestPart { shard_id, sync_hash, part_id, peer_id } => {
+            NetworkRequests::StateRequestPart {
+                shard_id,
+                sync_hash,
+                sync_prev_prev_hash,
+                part_id,
+                peer_id,
+            } => {
+                // Spread the requests over the peers which advertised a snapshot of
+                // the state. Only the directly connected peers can be asked for parts.
+                let tier2 = self.state.tier2.load();
+                let peer_id = self
+                    .state
+                    .snapshot_hosts
+                    .select_reachable_host(
+                        &self.clock,
+                        &sync_prev_prev_hash,
+                        &sync_hash,
+                        shard_id,
+                        part_id,
+                        |peer_id| tier2.ready.contains_key(peer_id),
+                    )
+                    .unwrap_or(peer_id);
+                self.state.snapshot_hosts.part_requested(
+                    &self.clock,
+                    &sync_hash,
+                    shard_id,
+                    part_id,
+                    &peer_id,
+                );
                 if self.state.tier2.send_message(
                     peer_id,
                     Arc::new(PeerMessage::StateRequestPart(shard_id, sync_hash, part_id)),
@@ -797,6 +825,16 @@ impl PeerManagerActor {
                     NetworkResponses::RouteNotFound
                 }
             }
+            NetworkRequests::StatePartVerified { shard_id, sync_hash, part_id, valid } => {
+                self.state.snapshot_hosts.part_verified(
+                    &self.clock,
+                    &sync_hash,
+                    shard_id,
+                    part_id,
+                    valid,
+                );
+                NetworkResponses::NoResponse
+            }
             NetworkRequests::SnapshotHostInfo { sync_hash, epoch_height, mut shards } => {
                 if shards.len() > MAX_SHARDS_PER_SNAPSHOT_HOST_INFO {
                     tracing::warn!("PeerManager: Sending out a SnapshotHostInfo message with {} shards, \
@@ -1176,11 +1214,20 @@ impl actix::Handler<GetDebugStatus> for PeerManagerActor {
                     .snapshot_hosts
                     .get_hosts()
                     .iter()
-                    .map(|h| SnapshotHostInfoView {
-                        peer_id: h.peer_id.clone(),
-                        sync_hash: h.sync_hash,
-                        epoch_height: h.epoch_height,
-                        shards: h.shards.clone(),
+                    .map(|h| {
+                        let score = self.state.snapshot_hosts.get_score(&h.peer_id);
+                        SnapshotHostInfoView {
+                            peer_id: h.peer_id.clone(),
+                            sync_hash: h.sync_hash,
+                            epoch_height: h.epoch_height,
+                            shards: h.shards.clone(),
+                            parts_served: score.served,
+                            parts_timed_out: score.timed_out,
+                            invalid_parts: score.invalid,
+                            banned: score
+                                .banned_until
+                                .map_or(false, |banned_until| self.clock.now() < banned_until),
+                        }
                     })
                     .collect::<Vec<_>>(),
             }),
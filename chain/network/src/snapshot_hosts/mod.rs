@@ -7,10 +7,11 @@
 use crate::concurrency;
 use crate::network_protocol::SnapshotHostInfo;
 use crate::network_protocol::SnapshotHostInfoVerificationError;
+use crate::stats::metrics;
 use lru::LruCache;
+use near_async::time;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
-use near_primitives::state_part::PartId;
 use near_primitives::types::ShardId;
 use parking_lot::Mutex;
 use rayon::iter::ParallelBridge;
@@ -41,10 +42,50 @@ pub struct Config {
     pub part_selection_cache_batch_size: u32,
 }
 
-pub(crate) fn priority_score(peer_id: &PeerId, part_id: &PartId) -> [u8; 32] {
+/// Number of requested parts for which no response was received yet, or
+/// whose verification result wasn't reported yet, remembered to score the hosts.
+const MAX_PENDING_PARTS: usize = 10_000;
+
+/// Number of parts a host may fail to return beyond the number of parts it
+/// served before it is banned.
+const MAX_HOST_TIMEOUTS_SLACK: u64 = 10;
+
+/// Number of invalid parts a host may send before it is banned.
+const MAX_HOST_INVALID_PARTS: u64 = 3;
+
+/// How long a banned host isn't asked for parts. The host starts over with a
+/// clean score once the ban expires.
+const HOST_BAN_DURATION: time::Duration = time::Duration::minutes(30);
+
+/// How well a host served the state parts requested from it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct HostScore {
+    /// Parts which passed the verification.
+    pub served: u64,
+    /// Requests which got no response before the part was requested again.
+    pub timed_out: u64,
+    /// Parts which failed the verification.
+    pub invalid: u64,
+    /// The host isn't asked for parts until then.
+    pub banned_until: Option<time::Instant>,
+}
+
+impl HostScore {
+    /// Hosts which sent several invalid parts, or failed to return many more
+    /// parts than they served, are banned for a while.
+    fn is_bad(&self) -> bool {
+        self.invalid >= MAX_HOST_INVALID_PARTS
+            || self.timed_out > self.served + MAX_HOST_TIMEOUTS_SLACK
+    }
+}
+
+/// A state part of a shard at a sync hash.
+type PartKey = (CryptoHash, ShardId, u64);
+
+pub(crate) fn priority_score(peer_id: &PeerId, part_id: u64) -> [u8; 32] {
     let mut h = Sha256::new();
     h.update(peer_id.public_key().key_data());
-    h.update(part_id.idx.to_le_bytes());
+    h.update(part_id.to_le_bytes());
     h.finalize().into()
 }
 
@@ -144,23 +185,23 @@ struct PeerSelector {
 }
 
 impl PeerSelector {
-    fn next(&mut self, part_id: &PartId) -> Option<PeerId> {
-        self.selectors.entry(part_id.idx).or_default().next()
+    fn next(&mut self, part_id: u64) -> Option<PeerId> {
+        self.selectors.entry(part_id).or_default().next()
     }
 
-    fn len(&self, part_id: &PartId) -> usize {
-        match self.selectors.get(&part_id.idx) {
+    fn len(&self, part_id: u64) -> usize {
+        match self.selectors.get(&part_id) {
             Some(s) => s.len(),
             None => 0,
         }
     }
 
-    fn insert_peers<T: IntoIterator<Item = PartPriority>>(&mut self, part_id: &PartId, peers: T) {
-        self.selectors.entry(part_id.idx).or_default().insert_peers(peers);
+    fn insert_peers<T: IntoIterator<Item = PartPriority>>(&mut self, part_id: u64, peers: T) {
+        self.selectors.entry(part_id).or_default().insert_peers(peers);
     }
 
-    fn seen_peers(&self, part_id: &PartId) -> HashSet<PeerId> {
-        match self.selectors.get(&part_id.idx) {
+    fn seen_peers(&self, part_id: u64) -> HashSet<PeerId> {
+        match self.selectors.get(&part_id) {
             Some(s) => {
                 let mut ret = HashSet::new();
                 for p in s.peers.iter() {
@@ -173,15 +214,15 @@ impl PeerSelector {
     }
 
     // have we already returned every peer we know about?
-    fn tried_everybody(&self, part_id: &PartId) -> bool {
-        match self.selectors.get(&part_id.idx) {
+    fn tried_everybody(&self, part_id: u64) -> bool {
+        match self.selectors.get(&part_id) {
             Some(s) => s.tried_everybody(),
             None => true,
         }
     }
 
-    fn clear(&mut self, part_id: &PartId) {
-        self.selectors.remove(&part_id.idx);
+    fn clear(&mut self, part_id: u64) {
+        self.selectors.remove(&part_id);
     }
 }
 
@@ -190,6 +231,15 @@ struct Inner {
     hosts: LruCache<PeerId, Arc<SnapshotHostInfo>>,
     state_part_selectors: HashMap<ShardId, PeerSelector>,
     part_selection_cache_batch_size: usize,
+    /// Hosts asked for the parts for which no response was received yet.
+    requested_parts: LruCache<PartKey, PeerId>,
+    /// Hosts which returned the parts whose verification result wasn't
+    /// reported yet.
+    received_parts: LruCache<PartKey, PeerId>,
+    /// Hosts which sent an invalid copy of the parts which weren't received
+    /// from another host yet. They aren't asked for these parts again.
+    invalid_parts: LruCache<PartKey, HashSet<PeerId>>,
+    scores: LruCache<PeerId, HostScore>,
 }
 
 impl Inner {
@@ -219,7 +269,7 @@ impl Inner {
         &mut self,
         sync_hash: &CryptoHash,
         shard_id: ShardId,
-        part_id: &PartId,
+        part_id: u64,
         max_entries_added: usize,
     ) {
         let selector = self.state_part_selectors.get(&shard_id).unwrap();
@@ -246,6 +296,63 @@ impl Inner {
         let selector = self.state_part_selectors.get_mut(&shard_id).unwrap();
         selector.insert_peers(part_id, new_peers.into_iter().map(Into::into));
     }
+
+    fn select_host(
+        &mut self,
+        sync_hash: &CryptoHash,
+        shard_id: ShardId,
+        part_id: u64,
+    ) -> Option<PeerId> {
+        let num_hosts = self.hosts.len();
+        let selector = self.state_part_selectors.entry(shard_id).or_default();
+
+        if selector.tried_everybody(part_id) && selector.len(part_id) < num_hosts {
+            let max_entries_added = self.part_selection_cache_batch_size;
+            self.insert_part_hosts(sync_hash, shard_id, part_id, max_entries_added);
+        }
+        let selector = self.state_part_selectors.get_mut(&shard_id).unwrap();
+        selector.next(part_id)
+    }
+
+    /// Updates the score of the host with `f`, and bans the host if the
+    /// score got bad.
+    fn update_score(
+        &mut self,
+        now: time::Instant,
+        peer_id: &PeerId,
+        f: impl FnOnce(&mut HostScore),
+    ) {
+        if !self.scores.contains(peer_id) {
+            self.scores.put(peer_id.clone(), HostScore::default());
+        }
+        let score = self.scores.get_mut(peer_id).unwrap();
+        f(score);
+        if score.banned_until.is_none() && score.is_bad() {
+            score.banned_until = Some(now + HOST_BAN_DURATION);
+            tracing::warn!(target: "network", %peer_id, ?score, "Banned a snapshot host from serving state parts");
+            metrics::SNAPSHOT_HOST_BANS.inc();
+        }
+    }
+
+    /// Whether the host is banned at `now`. The score of the hosts whose ban
+    /// expired is reset.
+    fn is_banned_host(&mut self, now: time::Instant, peer_id: &PeerId) -> bool {
+        let Some(score) = self.scores.peek_mut(peer_id) else {
+            return false;
+        };
+        match score.banned_until {
+            Some(banned_until) if now < banned_until => true,
+            Some(_) => {
+                *score = HostScore::default();
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn sent_invalid_part(&self, key: &PartKey, peer_id: &PeerId) -> bool {
+        self.invalid_parts.peek(key).map_or(false, |peer_ids| peer_ids.contains(peer_id))
+    }
 }
 
 pub(crate) struct SnapshotHostsCache(Mutex<Inner>);
@@ -259,6 +366,10 @@ impl SnapshotHostsCache {
             hosts,
             state_part_selectors,
             part_selection_cache_batch_size: config.part_selection_cache_batch_size as usize,
+            requested_parts: LruCache::new(MAX_PENDING_PARTS),
+            received_parts: LruCache::new(MAX_PENDING_PARTS),
+            invalid_parts: LruCache::new(MAX_PENDING_PARTS),
+            scores: LruCache::new(config.snapshot_hosts_cache_size as usize),
         }))
     }
 
@@ -332,39 +443,118 @@ impl SnapshotHostsCache {
     // Selecs a peer to send the request for this part ID to. Chooses based on a priority score
     // calculated as a hash of the Peer ID plus the part ID, and will return different hosts
     // on subsequent calls, eventually iterating over all valid SnapshotHostInfos we know about
-    // TODO: get rid of the dead_code and hook this up to the decentralized state sync
+    // used for testing purposes only, the state sync uses select_reachable_host()
     #[allow(dead_code)]
     pub fn select_host(
         &self,
         sync_hash: &CryptoHash,
         shard_id: ShardId,
-        part_id: &PartId,
+        part_id: u64,
+    ) -> Option<PeerId> {
+        self.0.lock().select_host(sync_hash, shard_id, part_id)
+    }
+
+    // Same as select_host() for the hosts of the snapshot at `snapshot_hash`, but skips the hosts
+    // for which `is_reachable` returns false, e.g. the hosts we aren't connected to, the banned
+    // hosts, and the hosts which already sent an invalid copy of the part for `sync_hash`. Every
+    // known host is tried at most once.
+    pub fn select_reachable_host(
+        &self,
+        clock: &time::Clock,
+        snapshot_hash: &CryptoHash,
+        sync_hash: &CryptoHash,
+        shard_id: ShardId,
+        part_id: u64,
+        is_reachable: impl Fn(&PeerId) -> bool,
     ) -> Option<PeerId> {
+        let now = clock.now();
+        let key = (*sync_hash, shard_id, part_id);
         let mut inner = self.0.lock();
         let num_hosts = inner.hosts.len();
-        let selector = inner.state_part_selectors.entry(shard_id).or_default();
+        for _ in 0..num_hosts {
+            let peer_id = inner.select_host(snapshot_hash, shard_id, part_id)?;
+            if is_reachable(&peer_id)
+                && !inner.is_banned_host(now, &peer_id)
+                && !inner.sent_invalid_part(&key, &peer_id)
+            {
+                return Some(peer_id);
+            }
+        }
+        None
+    }
 
-        if selector.tried_everybody(part_id) && selector.len(part_id) < num_hosts {
-            let max_entries_added = inner.part_selection_cache_batch_size;
-            inner.insert_part_hosts(sync_hash, shard_id, part_id, max_entries_added);
+    // Lets us know that the part was requested from `peer_id`. If the part was already requested
+    // and no response was received since, the previous host is considered to have timed out.
+    pub fn part_requested(
+        &self,
+        clock: &time::Clock,
+        sync_hash: &CryptoHash,
+        shard_id: ShardId,
+        part_id: u64,
+        peer_id: &PeerId,
+    ) {
+        let mut inner = self.0.lock();
+        if let Some(prev_peer_id) =
+            inner.requested_parts.put((*sync_hash, shard_id, part_id), peer_id.clone())
+        {
+            inner.update_score(clock.now(), &prev_peer_id, |score| score.timed_out += 1);
         }
-        let selector = inner.state_part_selectors.get_mut(&shard_id).unwrap();
-        selector.next(part_id)
     }
 
     // Lets us know that we have already successfully retrieved this part, and we can free any data
     // associated with it that we were going to use to respond to future calls to select_host()
-    // TODO: get rid of the dead_code and hook this up to the decentralized state sync
-    #[allow(dead_code)]
-    pub fn part_received(&self, _sync_hash: &CryptoHash, shard_id: ShardId, part_id: &PartId) {
+    pub fn part_received(
+        &self,
+        sync_hash: &CryptoHash,
+        shard_id: ShardId,
+        part_id: u64,
+        peer_id: &PeerId,
+    ) {
         let mut inner = self.0.lock();
         let selector = inner.state_part_selectors.entry(shard_id).or_default();
         selector.clear(part_id);
+        let key = (*sync_hash, shard_id, part_id);
+        inner.requested_parts.pop(&key);
+        inner.received_parts.put(key, peer_id.clone());
+    }
+
+    // Lets us know whether the part received last passed the verification against the state
+    // root, to score the host which sent it. The part is requested from another host next time
+    // if it was invalid.
+    pub fn part_verified(
+        &self,
+        clock: &time::Clock,
+        sync_hash: &CryptoHash,
+        shard_id: ShardId,
+        part_id: u64,
+        valid: bool,
+    ) {
+        let mut inner = self.0.lock();
+        let key = (*sync_hash, shard_id, part_id);
+        let Some(peer_id) = inner.received_parts.pop(&key) else {
+            return;
+        };
+        if valid {
+            inner.invalid_parts.pop(&key);
+            inner.update_score(clock.now(), &peer_id, |score| score.served += 1);
+        } else {
+            // Counted by the client in `near_state_sync_invalid_parts_total`.
+            tracing::warn!(target: "network", %peer_id, shard_id, part_id, "Snapshot host sent an invalid state part");
+            if !inner.invalid_parts.contains(&key) {
+                inner.invalid_parts.put(key, HashSet::new());
+            }
+            inner.invalid_parts.get_mut(&key).unwrap().insert(peer_id.clone());
+            inner.update_score(clock.now(), &peer_id, |score| score.invalid += 1);
+        }
+    }
+
+    pub(crate) fn get_score(&self, peer_id: &PeerId) -> HostScore {
+        self.0.lock().scores.peek(peer_id).copied().unwrap_or_default()
     }
 
     // used for testing purposes only to check that we clear state after part_received() is called
     #[allow(dead_code)]
-    pub(crate) fn part_peer_state_len(&self, shard_id: ShardId, part_id: &PartId) -> usize {
+    pub(crate) fn part_peer_state_len(&self, shard_id: ShardId, part_id: u64) -> usize {
         let inner = self.0.lock();
         match inner.state_part_selectors.get(&shard_id) {
             Some(s) => s.len(part_id),
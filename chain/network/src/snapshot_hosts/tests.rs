@@ -5,6 +5,7 @@ use crate::snapshot_hosts::{priority_score, Config, SnapshotHostInfoError, Snaps
 use crate::testonly::assert_is_superset;
 use crate::testonly::{make_rng, AsSet as _};
 use crate::types::SnapshotHostInfo;
+use near_async::time;
 use near_crypto::SecretKey;
 use near_o11y::testonly::init_test_logger;
 use near_primitives::hash::CryptoHash;
@@ -319,7 +320,7 @@ async fn run_select_peer_test(
                 assert!(err.is_none());
             }
             SelectPeerAction::CallSelect(wanted) => {
-                let peer = cache.select_host(sync_hash, 0, &part_id);
+                let peer = cache.select_host(sync_hash, 0, part_id.idx);
                 let wanted = match wanted {
                     Some(idx) => Some(&peers[*idx].peer_id),
                     None => None,
@@ -327,8 +328,8 @@ async fn run_select_peer_test(
                 assert!(peer.as_ref() == wanted, "got: {:?} want: {:?}", &peer, &wanted);
             }
             SelectPeerAction::PartReceived => {
-                cache.part_received(sync_hash, 0, &part_id);
-                assert_eq!(cache.part_peer_state_len(0, &part_id), 0);
+                cache.part_received(sync_hash, 0, part_id.idx, &peers[0].peer_id);
+                assert_eq!(cache.part_peer_state_len(0, part_id.idx), 0);
             }
         }
     }
@@ -345,7 +346,7 @@ async fn test_select_peer() {
     for _ in 0..num_peers {
         let key = data::make_secret_key(&mut rng);
         let peer_id = PeerId::new(key.public_key());
-        let score = priority_score(&peer_id, &part_id);
+        let score = priority_score(&peer_id, part_id.idx);
         let info = Arc::new(SnapshotHostInfo::new(peer_id, sync_hash, 123, vec![0, 1, 2, 3], &key));
         peers.push((info, score));
     }
@@ -367,3 +368,135 @@ async fn test_select_peer() {
         .await;
     }
 }
+
+#[tokio::test]
+async fn test_select_reachable_host() {
+    init_test_logger();
+    let mut rng = make_rng(2947294234);
+    let rng = &mut rng;
+
+    let clock = time::FakeClock::default();
+    let clock = clock.clock();
+    let config = Config { snapshot_hosts_cache_size: 100, part_selection_cache_batch_size: 1 };
+    let cache = SnapshotHostsCache::new(config);
+
+    let mut infos = vec![];
+    for _ in 0..4 {
+        let key = data::make_secret_key(rng);
+        let peer_id = PeerId::new(key.public_key());
+        infos.push(Arc::new(make_snapshot_host_info(&peer_id, 123, vec![0], &key)));
+    }
+    let (_, err) = cache.insert(infos.clone()).await;
+    assert!(err.is_none());
+    let sync_hash = infos[0].sync_hash;
+
+    // Only the reachable hosts are returned.
+    let reachable: HashSet<PeerId> =
+        [infos[1].peer_id.clone(), infos[3].peer_id.clone()].into_iter().collect();
+    let mut selected = HashSet::new();
+    for _ in 0..10 {
+        let peer_id = cache
+            .select_reachable_host(&clock, &sync_hash, &sync_hash, 0, 0, |peer_id| {
+                reachable.contains(peer_id)
+            })
+            .unwrap();
+        selected.insert(peer_id);
+    }
+    assert_eq!(selected, reachable);
+
+    // Nothing is returned if no host is reachable, or if no host has the shard.
+    assert_eq!(cache.select_reachable_host(&clock, &sync_hash, &sync_hash, 0, 1, |_| false), None);
+    assert_eq!(cache.select_reachable_host(&clock, &sync_hash, &sync_hash, 1, 0, |_| true), None);
+}
+
+#[tokio::test]
+async fn test_host_scores() {
+    init_test_logger();
+    let mut rng = make_rng(2947294234);
+    let rng = &mut rng;
+
+    let fake_clock = time::FakeClock::default();
+    let clock = fake_clock.clock();
+    let config = Config { snapshot_hosts_cache_size: 100, part_selection_cache_batch_size: 1 };
+    let cache = SnapshotHostsCache::new(config);
+
+    let mut infos = vec![];
+    for _ in 0..3 {
+        let key = data::make_secret_key(rng);
+        let peer_id = PeerId::new(key.public_key());
+        infos.push(Arc::new(make_snapshot_host_info(&peer_id, 123, vec![0], &key)));
+    }
+    let (_, err) = cache.insert(infos.clone()).await;
+    assert!(err.is_none());
+    let sync_hash = infos[0].sync_hash;
+    let [host0, host1, host2] = [0, 1, 2].map(|i| infos[i].peer_id.clone());
+
+    // Host 0 serves a valid part and host 1 an invalid one.
+    cache.part_requested(&clock, &sync_hash, 0, 0, &host0);
+    cache.part_received(&sync_hash, 0, 0, &host0);
+    cache.part_verified(&clock, &sync_hash, 0, 0, true);
+    cache.part_requested(&clock, &sync_hash, 0, 1, &host1);
+    cache.part_received(&sync_hash, 0, 1, &host1);
+    cache.part_verified(&clock, &sync_hash, 0, 1, false);
+    assert_eq!(cache.get_score(&host0).served, 1);
+    assert_eq!(cache.get_score(&host1).invalid, 1);
+    assert_eq!(cache.get_score(&host1).banned_until, None);
+
+    // Host 1 isn't asked for the invalid part again, but still for the other parts.
+    for _ in 0..10 {
+        assert_ne!(
+            cache.select_reachable_host(&clock, &sync_hash, &sync_hash, 0, 1, |_| true),
+            Some(host1.clone())
+        );
+    }
+    assert_eq!(
+        cache.select_reachable_host(&clock, &sync_hash, &sync_hash, 0, 1, |peer_id| peer_id
+            == &host1),
+        None
+    );
+    assert_eq!(
+        cache.select_reachable_host(&clock, &sync_hash, &sync_hash, 0, 2, |peer_id| peer_id
+            == &host1),
+        Some(host1.clone())
+    );
+
+    // Host 1 keeps sending invalid parts and gets banned.
+    for part_id in 2..4 {
+        cache.part_requested(&clock, &sync_hash, 0, part_id, &host1);
+        cache.part_received(&sync_hash, 0, part_id, &host1);
+        cache.part_verified(&clock, &sync_hash, 0, part_id, false);
+    }
+    assert_eq!(cache.get_score(&host1).invalid, 3);
+    assert!(cache.get_score(&host1).banned_until.is_some());
+
+    // Host 2 doesn't respond to a part which is then requested again.
+    for _ in 0..12 {
+        cache.part_requested(&clock, &sync_hash, 0, 5, &host2);
+    }
+    assert_eq!(cache.get_score(&host2).timed_out, 11);
+    assert!(cache.get_score(&host2).banned_until.is_some());
+
+    // Only host 0 is asked for parts while the others are banned.
+    for part_id in 10..20 {
+        assert_eq!(
+            cache.select_reachable_host(&clock, &sync_hash, &sync_hash, 0, part_id, |_| true),
+            Some(host0.clone())
+        );
+    }
+    assert_eq!(
+        cache.select_reachable_host(&clock, &sync_hash, &sync_hash, 0, 20, |peer_id| peer_id
+            != &host0),
+        None
+    );
+
+    // The banned hosts are asked for parts again with a clean score once the ban expires.
+    fake_clock.advance(time::Duration::minutes(31));
+    for host in [&host1, &host2] {
+        assert_eq!(
+            cache.select_reachable_host(&clock, &sync_hash, &sync_hash, 0, 21, |peer_id| peer_id
+                == host),
+            Some(host.clone())
+        );
+        assert_eq!(cache.get_score(host), Default::default());
+    }
+}
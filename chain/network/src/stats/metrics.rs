@@ -208,6 +208,13 @@ pub(crate) static SYNC_SNAPSHOT_HOSTS: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub(crate) static SNAPSHOT_HOST_BANS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_snapshot_host_bans_total",
+        "Number of times a snapshot host was banned from serving state parts",
+    )
+    .unwrap()
+});
 
 pub(crate) static REQUEST_COUNT_BY_TYPE_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
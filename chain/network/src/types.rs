@@ -230,7 +230,18 @@ pub enum NetworkRequests {
     /// Request state header for given shard at given state root.
     StateRequestHeader { shard_id: ShardId, sync_hash: CryptoHash, peer_id: PeerId },
     /// Request state part for given shard at given state root.
-    StateRequestPart { shard_id: ShardId, sync_hash: CryptoHash, part_id: u64, peer_id: PeerId },
+    /// The part is requested from a connected peer which advertised a state
+    /// snapshot at `sync_prev_prev_hash`, and from `peer_id` if there is none.
+    StateRequestPart {
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        sync_prev_prev_hash: CryptoHash,
+        part_id: u64,
+        peer_id: PeerId,
+    },
+    /// Result of verifying a state part received from a peer against the
+    /// state root, used to score the peer which sent it.
+    StatePartVerified { shard_id: ShardId, sync_hash: CryptoHash, part_id: u64, valid: bool },
     /// Ban given peer.
     BanPeer { peer_id: PeerId, ban_reason: ReasonForBan },
     /// Announce account
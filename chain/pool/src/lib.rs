@@ -1,5 +1,6 @@
+use std::cmp::Reverse;
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 use crate::types::{PoolKey, TransactionGroup, TransactionGroupIterator};
 
@@ -8,7 +9,7 @@ use near_o11y::metrics::prometheus::core::{AtomicI64, GenericGauge};
 use near_primitives::epoch_manager::RngSeed;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, BlockHeight};
 use std::ops::Bound;
 
 mod metrics;
@@ -30,8 +31,16 @@ pub struct TransactionPool {
     /// NOTE: It's more efficient on average to keep transactions unsorted and with potentially
     /// conflicting nonce than to create a BTreeMap for every transaction.
     transactions: BTreeMap<PoolKey, Vec<SignedTransaction>>,
+    /// Keys of the groups with priority transactions, ordered by the highest priority fee in the
+    /// group. The pool iterator takes these groups before the others.
+    priority_keys: BTreeSet<(Reverse<u64>, PoolKey)>,
     /// Set of all hashes to quickly check if the given transaction is in the pool.
     unique_transactions: HashSet<CryptoHash>,
+    /// Transactions with a `valid_until_height`, ordered by it, so that expired transactions can
+    /// be evicted without scanning the whole pool.
+    transactions_by_expiry: BTreeSet<(BlockHeight, PoolKey, CryptoHash)>,
+    /// The entries of `transactions_by_expiry` by transaction hash.
+    expiry_entries: HashMap<CryptoHash, (BlockHeight, PoolKey)>,
     /// A uniquely generated key seed to randomize PoolKey order.
     key_seed: RngSeed,
     /// The key after which the pool iterator starts. Doesn't have to be present in the pool.
@@ -62,7 +71,10 @@ impl TransactionPool {
         Self {
             key_seed,
             transactions: BTreeMap::new(),
+            priority_keys: BTreeSet::new(),
             unique_transactions: HashSet::new(),
+            transactions_by_expiry: BTreeSet::new(),
+            expiry_entries: HashMap::new(),
             last_used_key: CryptoHash::default(),
             total_transaction_size_limit,
             total_transaction_size: 0,
@@ -105,10 +117,17 @@ impl TransactionPool {
         self.total_transaction_size = new_total_transaction_size;
         let signer_id = signed_transaction.transaction.signer_id();
         let signer_public_key = signed_transaction.transaction.public_key();
-        self.transactions
-            .entry(self.key(signer_id, signer_public_key))
-            .or_insert_with(Vec::new)
-            .push(signed_transaction);
+        let key = self.key(signer_id, signer_public_key);
+        if let Some(valid_until_height) = signed_transaction.transaction.valid_until_height() {
+            let hash = signed_transaction.get_hash();
+            self.transactions_by_expiry.insert((valid_until_height, key, hash));
+            self.expiry_entries.insert(hash, (valid_until_height, key));
+        }
+        let group = self.transactions.entry(key).or_insert_with(Vec::new);
+        let old_priority_fee = max_priority_fee(group);
+        let new_priority_fee = old_priority_fee.max(priority_fee(&signed_transaction));
+        group.push(signed_transaction);
+        update_priority_key(&mut self.priority_keys, key, old_priority_fee, new_priority_fee);
 
         self.transaction_pool_count_metric.inc();
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
@@ -130,7 +149,7 @@ impl TransactionPool {
         let mut grouped_transactions = HashMap::new();
         for tx in transactions {
             // If transaction is not present in the pool, skip it.
-            if !self.unique_transactions.remove(&tx.get_hash()) {
+            if !self.forget_transaction(&tx.get_hash()) {
                 continue;
             }
 
@@ -141,8 +160,18 @@ impl TransactionPool {
                 .or_insert_with(HashSet::new)
                 .insert(tx.get_hash());
         }
+        self.remove_grouped_transactions(grouped_transactions);
+    }
+
+    /// Removes the transactions with the given hashes from their groups. The transactions must
+    /// already be forgotten with `forget_transaction`.
+    fn remove_grouped_transactions(
+        &mut self,
+        grouped_transactions: HashMap<PoolKey, HashSet<CryptoHash>>,
+    ) {
         for (key, hashes) in grouped_transactions {
             if let Entry::Occupied(mut entry) = self.transactions.entry(key) {
+                let old_priority_fee = max_priority_fee(entry.get());
                 entry.get_mut().retain(|tx| {
                     if !hashes.contains(&tx.get_hash()) {
                         return true;
@@ -155,6 +184,13 @@ impl TransactionPool {
                         .expect("Total transaction size dropped below zero");
                     false
                 });
+                let new_priority_fee = max_priority_fee(entry.get());
+                update_priority_key(
+                    &mut self.priority_keys,
+                    key,
+                    old_priority_fee,
+                    new_priority_fee,
+                );
                 if entry.get().is_empty() {
                     entry.remove_entry();
                 }
@@ -166,6 +202,36 @@ impl TransactionPool {
         self.transaction_pool_size_metric.set(self.total_transaction_size as i64);
     }
 
+    /// Removes the hash of the transaction from the indices of the pool. Returns whether the
+    /// transaction was in the pool.
+    fn forget_transaction(&mut self, hash: &CryptoHash) -> bool {
+        if let Some((valid_until_height, key)) = self.expiry_entries.remove(hash) {
+            self.transactions_by_expiry.remove(&(valid_until_height, key, *hash));
+        }
+        self.unique_transactions.remove(hash)
+    }
+
+    /// Removes the transactions that can no longer be included in chunks built on top of the
+    /// block with the given height because of their `valid_until_height`. Returns the number of
+    /// removed transactions.
+    pub fn remove_expired_transactions(&mut self, prev_block_height: BlockHeight) -> usize {
+        let mut grouped_transactions: HashMap<_, HashSet<_>> = HashMap::new();
+        let mut num_expired = 0;
+        while let Some(&(valid_until_height, key, hash)) = self.transactions_by_expiry.first() {
+            if valid_until_height > prev_block_height {
+                break;
+            }
+            self.transactions_by_expiry.pop_first();
+            self.expiry_entries.remove(&hash);
+            if self.unique_transactions.remove(&hash) {
+                grouped_transactions.entry(key).or_default().insert(hash);
+                num_expired += 1;
+            }
+        }
+        self.remove_grouped_transactions(grouped_transactions);
+        num_expired
+    }
+
     /// Returns the number of unique transactions in the pool.
     pub fn len(&self) -> usize {
         self.unique_transactions.len()
@@ -177,6 +243,34 @@ impl TransactionPool {
     }
 }
 
+fn priority_fee(transaction: &SignedTransaction) -> u64 {
+    transaction.transaction.priority_fee().unwrap_or(0)
+}
+
+/// Returns the highest priority fee among the transactions of a group, zero if there is none.
+fn max_priority_fee(transactions: &[SignedTransaction]) -> u64 {
+    transactions.iter().map(priority_fee).max().unwrap_or(0)
+}
+
+/// Updates the position of the group in the priority index after its highest priority fee has
+/// changed. Groups without a priority fee are not indexed.
+fn update_priority_key(
+    priority_keys: &mut BTreeSet<(Reverse<u64>, PoolKey)>,
+    key: PoolKey,
+    old_priority_fee: u64,
+    new_priority_fee: u64,
+) {
+    if old_priority_fee == new_priority_fee {
+        return;
+    }
+    if old_priority_fee > 0 {
+        priority_keys.remove(&(Reverse(old_priority_fee), key));
+    }
+    if new_priority_fee > 0 {
+        priority_keys.insert((Reverse(new_priority_fee), key));
+    }
+}
+
 /// PoolIterator is a structure to pull transactions from the pool.
 /// It implements `TransactionGroupIterator` trait that iterates over transaction groups one by one.
 /// When the wrapper is dropped the remaining transactions are returned back to the pool.
@@ -197,7 +291,9 @@ impl<'a> PoolIteratorWrapper<'a> {
 /// The iterator works with the following algorithm:
 /// On next(), the iterator tries to get a transaction group from the pool, sorts transactions in
 /// it, and add it to the back of the sorted groups queue.
-/// Remembers the last used key, so it can continue from the next key.
+/// Groups with priority transactions are taken first, in the order of their highest priority fee.
+/// The other groups are taken in the key order, starting after the last used key, which the
+/// iterator remembers so it can continue from the next key.
 ///
 /// If the pool is empty, the iterator gets the group from the front of the sorted groups queue.
 ///
@@ -214,20 +310,25 @@ impl<'a> PoolIteratorWrapper<'a> {
 impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
     fn next(&mut self) -> Option<&mut TransactionGroup> {
         if !self.pool.transactions.is_empty() {
-            let key = *self
-                .pool
-                .transactions
-                .range((Bound::Excluded(self.pool.last_used_key), Bound::Unbounded))
-                .next()
-                .map(|(k, _v)| k)
-                .unwrap_or_else(|| {
-                    self.pool
-                        .transactions
-                        .keys()
-                        .next()
-                        .expect("we've just checked that the map is not empty")
-                });
-            self.pool.last_used_key = key;
+            let key = if let Some((_, key)) = self.pool.priority_keys.pop_first() {
+                key
+            } else {
+                let key = *self
+                    .pool
+                    .transactions
+                    .range((Bound::Excluded(self.pool.last_used_key), Bound::Unbounded))
+                    .next()
+                    .map(|(k, _v)| k)
+                    .unwrap_or_else(|| {
+                        self.pool
+                            .transactions
+                            .keys()
+                            .next()
+                            .expect("we've just checked that the map is not empty")
+                    });
+                self.pool.last_used_key = key;
+                key
+            };
             let mut transactions =
                 self.pool.transactions.remove(&key).expect("just checked existence");
             transactions.sort_by_key(|st| std::cmp::Reverse(st.transaction.nonce()));
@@ -242,7 +343,7 @@ impl<'a> TransactionGroupIterator for PoolIteratorWrapper<'a> {
             while let Some(sorted_group) = self.sorted_groups.pop_front() {
                 if sorted_group.transactions.is_empty() {
                     for hash in sorted_group.removed_transaction_hashes {
-                        self.pool.unique_transactions.remove(&hash);
+                        self.pool.forget_transaction(&hash);
                     }
                     // See the comment in `insert_transaction` where we increase the size for reasoning
                     // why panicing here catches a logic error.
@@ -273,7 +374,7 @@ impl<'a> Drop for PoolIteratorWrapper<'a> {
     fn drop(&mut self) {
         for group in self.sorted_groups.drain(..) {
             for hash in group.removed_transaction_hashes {
-                self.pool.unique_transactions.remove(&hash);
+                self.pool.forget_transaction(&hash);
             }
             // See the comment in `insert_transaction` where we increase the size for reasoning
             // why panicing here catches a logic error.
@@ -284,6 +385,8 @@ impl<'a> Drop for PoolIteratorWrapper<'a> {
                 .expect("Total transaction size dropped below zero");
 
             if !group.transactions.is_empty() {
+                let priority_fee = max_priority_fee(&group.transactions);
+                update_priority_key(&mut self.pool.priority_keys, group.key, 0, priority_fee);
                 self.pool.transactions.insert(group.key, group.transactions);
             }
         }
@@ -340,6 +443,7 @@ mod tests {
     use near_crypto::{InMemorySigner, KeyType};
 
     use near_primitives::hash::CryptoHash;
+    use near_primitives::transaction::{Transaction, TransactionV1};
     use near_primitives::types::Balance;
 
     const TEST_SEED: RngSeed = [3; 32];
@@ -554,6 +658,36 @@ mod tests {
         assert_eq!(pool.transaction_size(), 0);
     }
 
+    /// Test that transactions put back to their group stay in the pool.
+    #[test]
+    fn test_pool_iterator_put_back() {
+        let transactions = generate_transactions("alice.near", "alice.near", 1, 10);
+
+        let (nonces, mut pool) = process_txs_to_nonces(transactions.clone(), 0);
+        assert!(nonces.is_empty());
+        let total_size = pool.transaction_size();
+        let mut pool_iter = pool.pool_iterator();
+        let iter = pool_iter.next().unwrap();
+        let first = iter.next().unwrap();
+        let second = iter.next().unwrap();
+        assert_eq!(second.transaction.nonce(), 2);
+        iter.put_back(second);
+        drop(pool_iter);
+        assert_eq!(pool.len(), 9);
+        assert_eq!(pool.transaction_size(), total_size - first.get_size());
+
+        // The transaction which was put back is still known to the pool.
+        assert_eq!(
+            pool.insert_transaction(transactions[1].clone()),
+            InsertTransactionResult::Duplicate
+        );
+        let txs = prepare_transactions(&mut pool, 10);
+        assert_eq!(
+            txs.iter().map(|tx| tx.transaction.nonce()).collect::<Vec<_>>(),
+            (2..=10).collect::<Vec<_>>()
+        );
+    }
+
     /// Test pool iterator remembers the last key.
     #[test]
     fn test_pool_iterator_remembers_the_last_key() {
@@ -595,6 +729,84 @@ mod tests {
         assert_ne!(nonces, new_nonces);
     }
 
+    /// Test pool iterator takes the groups with the highest priority fee first, and that the
+    /// remaining priority groups keep their precedence after the iterator is dropped.
+    #[test]
+    fn test_pool_iterator_priority_fee() {
+        let transactions = (1..=6)
+            .map(|i| {
+                let signer_id = AccountId::try_from(format!("user_{}", i)).unwrap();
+                let signer_seed = signer_id.as_ref();
+                let signer = Arc::new(InMemorySigner::from_seed(
+                    signer_id.clone(),
+                    KeyType::ED25519,
+                    signer_seed,
+                ));
+                // Users 2, 4 and 6 pay a priority fee equal to their number.
+                let priority_fee = if i % 2 == 0 { i } else { 0 };
+                SignedTransaction::from_actions_v1(
+                    i,
+                    signer_id,
+                    "bob.near".parse().unwrap(),
+                    &*signer,
+                    vec![],
+                    CryptoHash::default(),
+                    priority_fee,
+                )
+            })
+            .collect::<Vec<_>>();
+        let (nonces, mut pool) = process_txs_to_nonces(transactions, 2);
+        assert_eq!(nonces, vec![6, 4]);
+        assert_eq!(pool.len(), 4);
+
+        let txs = prepare_transactions(&mut pool, 4);
+        let mut nonces = txs.iter().map(|tx| tx.transaction.nonce()).collect::<Vec<_>>();
+        assert_eq!(nonces[0], 2);
+        nonces[1..].sort();
+        assert_eq!(nonces, vec![2, 1, 3, 5]);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn test_remove_expired_transactions() {
+        let signer_id: AccountId = "alice.near".parse().unwrap();
+        let signer =
+            Arc::new(InMemorySigner::from_seed(signer_id.clone(), KeyType::ED25519, "alice.near"));
+        let transactions = [None, Some(10), Some(20), Some(30)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, valid_until_height)| {
+                Transaction::V1(TransactionV1 {
+                    signer_id: signer_id.clone(),
+                    public_key: signer.public_key(),
+                    nonce: i as u64 + 1,
+                    receiver_id: "bob.near".parse().unwrap(),
+                    block_hash: CryptoHash::default(),
+                    actions: vec![],
+                    priority_fee: 0,
+                    valid_until_height,
+                })
+                .sign(&*signer)
+            })
+            .collect::<Vec<_>>();
+        let mut pool = TransactionPool::new(TEST_SEED, None, "");
+        for tx in transactions {
+            assert_eq!(pool.insert_transaction(tx), InsertTransactionResult::Success);
+        }
+
+        assert_eq!(pool.remove_expired_transactions(9), 0);
+        assert_eq!(pool.remove_expired_transactions(10), 1);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.remove_expired_transactions(20), 1);
+        // Transactions pulled from the pool are no longer tracked for expiry.
+        let nonces: Vec<u64> =
+            prepare_transactions(&mut pool, 10).iter().map(|tx| tx.transaction.nonce()).collect();
+        assert_eq!(nonces, vec![1, 4]);
+        assert!(pool.transactions_by_expiry.is_empty());
+        assert!(pool.expiry_entries.is_empty());
+        assert_eq!(pool.remove_expired_transactions(u64::MAX), 0);
+    }
+
     #[test]
     fn test_transaction_pool_size() {
         let mut pool = TransactionPool::new(TEST_SEED, None, "");
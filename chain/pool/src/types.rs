@@ -36,4 +36,13 @@ impl TransactionGroup {
             None
         }
     }
+
+    /// Returns a transaction pulled with `.next()` back to the group, so that it
+    /// stays in the pool. It must be the last transaction pulled from the group.
+    pub fn put_back(&mut self, tx: SignedTransaction) {
+        let hash = self.removed_transaction_hashes.pop();
+        debug_assert_eq!(hash, Some(tx.get_hash()));
+        self.removed_transaction_size -= tx.get_size();
+        self.transactions.push(tx);
+    }
 }
@@ -157,6 +157,10 @@ fn convert_cause_to_transaction_id(
         StateChangeCauseView::Migration => {
             Ok((TransactionIdentifier::block_event("migration", block_hash), None))
         }
+        StateChangeCauseView::RuntimeConfigOverridesUpdate => Ok((
+            TransactionIdentifier::block_event("block-runtime-config-overrides-update", block_hash),
+            None,
+        )),
         StateChangeCauseView::Resharding => Err(crate::errors::ErrorKind::InternalInvariantError(
             "State Change 'Resharding' should never be observed".to_string(),
         )),
@@ -88,6 +88,9 @@ impl From<near_client_primitives::types::GetStateChangesError> for ErrorKind {
             near_client_primitives::types::GetStateChangesError::UnknownBlock { error_message } => {
                 Self::NotFound(error_message)
             }
+            near_client_primitives::types::GetStateChangesError::UnavailableShard { .. } => {
+                Self::NotFound(err.to_string())
+            }
             near_client_primitives::types::GetStateChangesError::Unreachable { error_message } => {
                 Self::InternalError(error_message)
             }
@@ -275,19 +275,27 @@ where
 }
 
 /// Zero-balance account (NEP-448)
-fn is_zero_balance_account(account: &near_primitives::account::Account) -> bool {
-    account.storage_usage() <= node_runtime::ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT
+fn is_zero_balance_account(
+    account: &near_primitives::account::Account,
+    storage_usage_config: &near_parameters::view::StorageUsageConfigView,
+) -> bool {
+    account.storage_usage() <= storage_usage_config.zero_balance_account_limit
 }
 
 /// Tokens not locked due to staking (=liquid) but reserved for state.
 fn get_liquid_balance_for_storage(
     account: &near_primitives::account::Account,
-    storage_amount_per_byte: near_primitives::types::Balance,
+    runtime_config: &near_parameters::RuntimeConfigView,
 ) -> near_primitives::types::Balance {
-    let staked_for_storage = if is_zero_balance_account(account) {
+    let storage_usage_config = &runtime_config.transaction_costs.storage_usage_config;
+    let staked_for_storage = if is_zero_balance_account(account, storage_usage_config) {
         0
     } else {
-        near_primitives::types::Balance::from(account.storage_usage()) * storage_amount_per_byte
+        let billable_storage_bytes = account
+            .storage_usage()
+            .saturating_sub(storage_usage_config.num_bytes_exempt_from_staking);
+        near_primitives::types::Balance::from(billable_storage_bytes)
+            * runtime_config.storage_amount_per_byte
     };
 
     staked_for_storage.saturating_sub(account.locked())
@@ -311,8 +319,7 @@ impl RosettaAccountBalances {
         let account = account.into();
         let amount = account.amount();
         let locked = account.locked();
-        let liquid_for_storage =
-            get_liquid_balance_for_storage(&account, runtime_config.storage_amount_per_byte);
+        let liquid_for_storage = get_liquid_balance_for_storage(&account, runtime_config);
 
         Self { liquid_for_storage, liquid: amount.saturating_sub(liquid_for_storage), locked }
     }
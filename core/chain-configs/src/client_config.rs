@@ -3,8 +3,11 @@ use crate::ExternalStorageLocation::GCS;
 use crate::MutableConfigValue;
 use bytesize::ByteSize;
 use near_async::time::Duration;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::state_sync::StatePartCompression;
+use near_primitives::stateless_validation::{StateWitnessCompression, WitnessCompressionMode};
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockHeightDelta, Gas, NumBlocks, NumSeats, ShardId,
+    AccountId, BlockHeight, BlockHeightDelta, EpochId, Gas, NumBlocks, NumSeats, ShardId,
 };
 use near_primitives::version::Version;
 use std::cmp::{max, min};
@@ -105,6 +108,18 @@ pub enum ExternalStorageLocation {
     GCS {
         bucket: String,
     },
+    Azure {
+        /// Name of the Azure storage account.
+        account: String,
+        /// Blob container of the storage account holding the state dumps.
+        container: String,
+    },
+    /// Another node serving its state dump over HTTP, see
+    /// `DumpConfig::http_server_addr`. Can only be used to sync state.
+    Http {
+        /// Base URL of the server, e.g. `http://10.0.0.1:3040`.
+        url: String,
+    },
 }
 
 /// Configures how to dump state to external storage.
@@ -125,6 +140,33 @@ pub struct DumpConfig {
     /// Location of a json file with credentials allowing write access to the bucket.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub credentials_file: Option<PathBuf>,
+    /// Compression applied to state parts before writing them to external storage.
+    /// Defaults to `None`. Set `Zstd` to roughly halve the storage egress, once
+    /// the nodes syncing from the dump run binaries which read compressed parts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub compression: Option<StatePartCompression>,
+    /// How many state parts to generate in parallel.
+    /// Defaults to 4. Parts are generated much faster if the memtrie of the
+    /// shard is loaded. The parts of all shards are generated on a dedicated
+    /// pool of this many threads, so that they don't compete with block
+    /// processing for the threads of the global pool.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub parallelism: Option<usize>,
+    /// Limits the total size of the state parts generated per second across
+    /// all shards, which roughly bounds the disk reads of the dump. Applies to
+    /// the parts generated for the manifest and for the dump alike.
+    /// Not limited by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_bytes_per_second: Option<u64>,
+    /// If set, the dumped state is served over HTTP at this address, so that
+    /// other nodes can sync state from this node with the `Http` location.
+    /// Requires the `Filesystem` location.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub http_server_addr: Option<std::net::SocketAddr>,
 }
 
 /// Configures how to fetch state parts during state sync.
@@ -150,6 +192,23 @@ pub struct StateSyncConfig {
     pub dump: Option<DumpConfig>,
     #[serde(skip_serializing_if = "SyncConfig::is_default", default = "SyncConfig::default")]
     pub sync: SyncConfig,
+    /// Limits the resources used by catch-up state sync.
+    #[serde(default)]
+    pub catchup: StateSyncCatchupConfig,
+    /// Whether to serve the state of old epochs to the peers, which lets new
+    /// archival nodes bootstrap from any point in history. Only archival nodes
+    /// keep the state of old epochs. Generating such state parts is slow,
+    /// because they are read from the trie, which is in cold storage.
+    #[serde(default)]
+    pub serve_historical_state: bool,
+    /// If set, the node syncs the state as of the beginning of this epoch
+    /// instead of the latest epoch, and then syncs the blocks that follow.
+    /// Lets a node start from a point in history, e.g. to bootstrap an archival
+    /// node or to fork the network. The peers or the external storage must
+    /// have the state of that epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub target_epoch_id: Option<EpochId>,
 }
 
 impl SyncConfig {
@@ -159,6 +218,38 @@ impl SyncConfig {
     }
 }
 
+/// Configuration of the catch-up state sync, i.e. the state sync of the shards
+/// that the node will track in the next epoch. Catch-up runs while the node
+/// keeps processing blocks and endorsing chunks of the current epoch, and must
+/// not compete with that work for disk and CPU.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct StateSyncCatchupConfig {
+    /// The maximum rate of downloading state parts during catch-up, in bytes
+    /// per second. No limit if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_download_bytes_per_second: Option<ByteSize>,
+
+    /// The soft limit on the total size of state parts applied in a single
+    /// batch during catch-up.
+    pub apply_batch_size: ByteSize,
+
+    /// The delay between applying batches of state parts during catch-up.
+    /// Can be increased if catch-up interferes with regular node operation.
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub apply_batch_delay: Duration,
+}
+
+impl Default for StateSyncCatchupConfig {
+    fn default() -> Self {
+        Self {
+            max_download_bytes_per_second: None,
+            apply_batch_size: ByteSize::mb(10),
+            apply_batch_delay: Duration::milliseconds(50),
+        }
+    }
+}
+
 // A handle that allows the main process to interrupt resharding if needed.
 // This typically happens when the main process is interrupted.
 #[derive(Clone)]
@@ -249,6 +340,10 @@ pub fn default_header_sync_expected_height_per_second() -> u64 {
     10
 }
 
+pub fn default_header_sync_max_peers_per_request() -> usize {
+    3
+}
+
 pub fn default_sync_check_period() -> Duration {
     Duration::seconds(10)
 }
@@ -274,6 +369,9 @@ pub fn default_state_sync() -> Option<StateSyncConfig> {
             num_concurrent_requests_during_catchup:
                 DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL,
         }),
+        catchup: StateSyncCatchupConfig::default(),
+        serve_historical_state: false,
+        target_epoch_id: None,
     })
 }
 
@@ -320,12 +418,42 @@ pub fn default_orphan_state_witness_pool_size() -> usize {
     25
 }
 
+/// Returns the default maximum number of state-witnesses of a single shard in the
+/// OrphanStateWitnessPool.
+pub fn default_orphan_state_witness_pool_size_per_shard() -> usize {
+    5
+}
+
+/// Returns the default number of state witnesses of a single shard which are validated at the
+/// same time.
+pub fn default_chunk_validation_max_concurrency_per_shard() -> usize {
+    2
+}
+
+/// Returns the default maximum number of state witnesses a single chunk producer can send for
+/// the same shard and height.
+pub fn default_max_state_witnesses_per_producer_per_height() -> usize {
+    3
+}
+
+/// Returns the default time window in which chunk endorsements are collected to verify their
+/// signatures in a batch.
+pub fn default_chunk_endorsement_batch_window() -> Duration {
+    Duration::milliseconds(5)
+}
+
 /// Returns the default value for maximum data-size (bytes) for a state witness to be included in
 /// the OrphanStateWitnessPool.
 pub fn default_orphan_state_witness_max_size() -> ByteSize {
     ByteSize::mb(40)
 }
 
+/// Returns the default number of blocks for which the shadow witnesses are kept.
+pub fn default_shadow_witnesses_retention_blocks() -> BlockHeightDelta {
+    // About a day of blocks on mainnet.
+    86_400
+}
+
 /// Config for the Chunk Distribution Network feature.
 /// This allows nodes to push and pull chunks from a central stream.
 /// The two benefits of this approach are: (1) less request/response traffic
@@ -345,6 +473,78 @@ pub struct ChunkDistributionUris {
     pub set: String,
 }
 
+/// Config of the compression of the state witnesses produced and validated by this node.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WitnessCompressionConfig {
+    /// zstd compression level of the produced state witnesses.
+    pub level: i32,
+    /// Compress the produced state witnesses as a whole, or every trie value in them
+    /// separately, which suits a dictionary trained with `--samples per-value`. Only
+    /// enable `per_value` once all chunk validators run a version which can decode it.
+    pub mode: WitnessCompressionMode,
+    /// Path to a zstd dictionary, e.g. trained with `neard view-state train-witness-dictionary`.
+    /// Relative paths are resolved against the home directory.
+    /// The dictionary is always used to decode state witnesses.
+    pub dictionary_path: Option<PathBuf>,
+    /// Compress the produced state witnesses with the dictionary. Only enable this once all
+    /// chunk validators have the same dictionary, as they can't decode the witnesses otherwise.
+    pub compress_with_dictionary: bool,
+}
+
+impl Default for WitnessCompressionConfig {
+    fn default() -> Self {
+        Self {
+            level: StateWitnessCompression::DEFAULT_LEVEL,
+            mode: WitnessCompressionMode::default(),
+            dictionary_path: None,
+            compress_with_dictionary: false,
+        }
+    }
+}
+
+impl WitnessCompressionConfig {
+    /// Reads the dictionary and returns the compression described by this config.
+    pub fn load(&self) -> std::io::Result<StateWitnessCompression> {
+        let dictionary = match &self.dictionary_path {
+            Some(path) => Some(std::fs::read(path)?.into()),
+            None => None,
+        };
+        Ok(StateWitnessCompression {
+            level: self.level,
+            mode: self.mode,
+            dictionary,
+            compress_with_dictionary: self.compress_with_dictionary,
+        })
+    }
+}
+
+/// Budget for the uncompressed size of the state witnesses of the chunks produced by this node.
+/// Only the new transactions are limited by it, the receipts executed by a chunk are limited by
+/// the protocol.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct WitnessSizeBudgetConfig {
+    /// No more transactions are added to a chunk once its witness reaches this size.
+    pub soft_limit: ByteSize,
+    /// Transactions which would grow the witness past this size are left in the pool.
+    pub hard_limit: ByteSize,
+    /// Once the witnesses of the recent chunks of a shard exceed this size before adding
+    /// the new transactions, the gas available to the new transactions is reduced, the
+    /// more the closer the witnesses get to `soft_limit`.
+    pub transactions_limiting_threshold: ByteSize,
+}
+
+impl Default for WitnessSizeBudgetConfig {
+    fn default() -> Self {
+        Self {
+            soft_limit: ByteSize::mb(16),
+            hard_limit: ByteSize::mb(24),
+            transactions_limiting_threshold: ByteSize::mb(8),
+        }
+    }
+}
+
 /// ClientConfig where some fields can be updated at runtime.
 #[derive(Clone, serde::Serialize)]
 pub struct ClientConfig {
@@ -380,6 +580,10 @@ pub struct ClientConfig {
     pub header_sync_stall_ban_timeout: Duration,
     /// Expected increase of header head height per second during header sync
     pub header_sync_expected_height_per_second: u64,
+    /// Maximum number of peers to request the same batch of headers from
+    /// during header sync. The actual number adapts to how long peers take to
+    /// respond compared to how long it takes to validate the headers.
+    pub header_sync_max_peers_per_request: usize,
     /// How long to wait for a response during state sync
     pub state_sync_timeout: Duration,
     /// Minimum number of peers to start syncing.
@@ -418,6 +622,9 @@ pub struct ClientConfig {
     pub tracked_shard_schedule: Vec<Vec<ShardId>>,
     /// Not clear old data, set `true` for archive nodes.
     pub archive: bool,
+    /// Shards whose history is copied to the cold storage, all if not set.
+    /// See `split_storage.archived_shards` in the node config.
+    pub archived_shards: Option<Vec<ShardUId>>,
     /// save_trie_changes should be set to true iff
     /// - archive if false - non-archivale nodes need trie changes to perform garbage collection
     /// - archive is true, cold_store is configured and migration to split_storage is finished - node
@@ -472,17 +679,49 @@ pub struct ClientConfig {
     /// because the previous block isn't available. The witnesses wait in the pool until the
     /// required block appears. This variable controls how many witnesses can be stored in the pool.
     pub orphan_state_witness_pool_size: usize,
+    /// Maximum number of witnesses of a single shard in the OrphanStateWitnessPool.
+    /// When a shard is full, the witness with the highest height is dropped.
+    pub orphan_state_witness_pool_size_per_shard: usize,
     /// Maximum size of state witnesses in the OrphanStateWitnessPool.
     ///
     /// We keep only orphan witnesses which are smaller than this size.
     /// This limits the maximum memory usage of OrphanStateWitnessPool.
     pub orphan_state_witness_max_size: ByteSize,
+    /// Maximum number of state witnesses of a single shard which are validated at the same time.
+    /// Further witnesses of the shard wait in a queue, and the ones with the lowest height are
+    /// validated first.
+    pub chunk_validation_max_concurrency_per_shard: usize,
+    /// Maximum number of state witnesses a single chunk producer can send for the same shard
+    /// and height. Further witnesses are dropped, and for a while afterwards only the first
+    /// witness of the chunk producer per shard and height is accepted.
+    pub max_state_witnesses_per_producer_per_height: usize,
+    /// Chunk endorsements received within this window are verified together with a single
+    /// batched signature verification, which is cheaper than verifying them one by one.
+    /// Zero disables batching.
+    pub chunk_endorsement_batch_window: Duration,
     /// Save observed instances of ChunkStateWitness to the database in DBCol::LatestChunkStateWitnesses.
     /// Saving the latest witnesses is useful for analysis and debugging.
     /// When this option is enabled, the node will save ALL witnesses it oberves, even invalid ones,
     /// which can cause extra load on the database. This option is not recommended for production use,
     /// as a large number of incoming witnesses could cause denial of service.
     pub save_latest_witnesses: bool,
+    /// Produce a state witness for every chunk in every processed block and validate it,
+    /// to test stateless validation without being a chunk validator. Can be toggled on a
+    /// running node.
+    pub shadow_chunk_validation: MutableConfigValue<bool>,
+    /// Shards whose chunks are shadow validated. All shards are validated if not set.
+    pub shadow_validation_shards: Option<Vec<ShardId>>,
+    /// Save the ChunkStateWitnesses produced by shadow validation to the database in
+    /// DBCol::ShadowStateWitness, one per chunk, for offline replay and size analysis.
+    /// Only has an effect when `shadow_chunk_validation` is enabled.
+    pub save_shadow_witnesses: bool,
+    /// Number of blocks for which the shadow witnesses are kept. Witnesses of chunks created
+    /// more than this many blocks before the latest saved one are deleted.
+    pub shadow_witnesses_retention_blocks: BlockHeightDelta,
+    /// Compression of the state witnesses produced and validated by this node.
+    pub witness_compression: WitnessCompressionConfig,
+    /// Budget for the size of the state witnesses of the chunks produced by this node.
+    pub witness_size_budget: WitnessSizeBudgetConfig,
 }
 
 impl ClientConfig {
@@ -523,6 +762,7 @@ impl ClientConfig {
             header_sync_stall_ban_timeout: Duration::seconds(30),
             state_sync_timeout: Duration::seconds(TEST_STATE_SYNC_TIMEOUT),
             header_sync_expected_height_per_second: 1,
+            header_sync_max_peers_per_request: default_header_sync_max_peers_per_request(),
             min_num_peers: 1,
             log_summary_period: Duration::seconds(10),
             produce_empty_blocks: true,
@@ -542,6 +782,7 @@ impl ClientConfig {
             tracked_shards: vec![],
             tracked_shard_schedule: vec![],
             archive,
+            archived_shards: None,
             save_trie_changes,
             log_summary_style: LogSummaryStyle::Colored,
             view_client_threads: 1,
@@ -568,8 +809,21 @@ impl ClientConfig {
             ),
             chunk_distribution_network: None,
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
+            orphan_state_witness_pool_size_per_shard:
+                default_orphan_state_witness_pool_size_per_shard(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
+            chunk_validation_max_concurrency_per_shard:
+                default_chunk_validation_max_concurrency_per_shard(),
+            max_state_witnesses_per_producer_per_height:
+                default_max_state_witnesses_per_producer_per_height(),
+            chunk_endorsement_batch_window: default_chunk_endorsement_batch_window(),
             save_latest_witnesses: false,
+            shadow_chunk_validation: MutableConfigValue::new(false, "shadow_chunk_validation"),
+            shadow_validation_shards: None,
+            save_shadow_witnesses: false,
+            shadow_witnesses_retention_blocks: default_shadow_witnesses_retention_blocks(),
+            witness_compression: WitnessCompressionConfig::default(),
+            witness_size_budget: WitnessSizeBudgetConfig::default(),
         }
     }
 }
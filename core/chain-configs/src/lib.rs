@@ -8,19 +8,23 @@ pub mod test_utils;
 mod updateable_config;
 
 pub use client_config::{
+    default_chunk_endorsement_batch_window, default_chunk_validation_max_concurrency_per_shard,
     default_enable_multiline_logging, default_epoch_sync_enabled,
     default_header_sync_expected_height_per_second, default_header_sync_initial_timeout,
-    default_header_sync_progress_timeout, default_header_sync_stall_ban_timeout,
-    default_log_summary_period, default_orphan_state_witness_max_size,
-    default_orphan_state_witness_pool_size, default_produce_chunk_add_transactions_time_limit,
+    default_header_sync_max_peers_per_request, default_header_sync_progress_timeout,
+    default_header_sync_stall_ban_timeout, default_log_summary_period,
+    default_max_state_witnesses_per_producer_per_height, default_orphan_state_witness_max_size,
+    default_orphan_state_witness_pool_size, default_orphan_state_witness_pool_size_per_shard,
+    default_produce_chunk_add_transactions_time_limit, default_shadow_witnesses_retention_blocks,
     default_state_sync, default_state_sync_enabled, default_state_sync_timeout,
     default_sync_check_period, default_sync_height_threshold, default_sync_step_period,
     default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
     default_tx_routing_height_horizon, default_view_client_threads,
     default_view_client_throttle_period, ChunkDistributionNetworkConfig, ChunkDistributionUris,
     ClientConfig, DumpConfig, ExternalStorageConfig, ExternalStorageLocation, GCConfig,
-    LogSummaryStyle, ReshardingConfig, ReshardingHandle, StateSyncConfig, SyncConfig,
-    DEFAULT_GC_NUM_EPOCHS_TO_KEEP, DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
+    LogSummaryStyle, ReshardingConfig, ReshardingHandle, StateSyncCatchupConfig, StateSyncConfig,
+    SyncConfig, WitnessCompressionConfig, WitnessSizeBudgetConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+    DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
     DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL, MIN_GC_NUM_EPOCHS_TO_KEEP,
     TEST_STATE_SYNC_TIMEOUT,
 };
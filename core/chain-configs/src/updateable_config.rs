@@ -101,4 +101,8 @@ pub struct UpdateableClientConfig {
     #[serde(default)]
     #[serde(with = "near_async::time::serde_opt_duration_as_std")]
     pub produce_chunk_add_transactions_time_limit: Option<Duration>,
+
+    /// Produce and validate a state witness for every chunk in every processed block.
+    #[serde(default)]
+    pub shadow_chunk_validation: bool,
 }
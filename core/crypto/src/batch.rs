@@ -0,0 +1,250 @@
+use crate::{PublicKey, Signature};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+
+/// Returns true if `bytes` is the canonical encoding of a point of the prime order subgroup
+/// other than the identity.
+fn is_canonical_prime_order_point(bytes: &[u8; 32]) -> bool {
+    CompressedEdwardsY(*bytes).decompress().is_some_and(|point| {
+        !point.is_small_order() && point.is_torsion_free() && point.compress().as_bytes() == bytes
+    })
+}
+
+impl Signature {
+    /// Verifies a batch of signatures, each given with the signed data and the public key.
+    /// Returns true if all signatures are valid. When it returns false, at least one of the
+    /// signatures is invalid or unsuitable for batching, and `verify` has to be used to find
+    /// out which ones.
+    ///
+    /// ED25519 signatures are checked with `ed25519_dalek::verify_batch`, which is considerably
+    /// cheaper than verifying them one by one. The batch equation is not sensitive to the
+    /// encoding of R, and its random coefficients can cancel out torsion components, so it can
+    /// accept signatures which `verify` rejects. A signature whose R or public key is not a
+    /// canonically encoded point of the prime order subgroup fails the whole batch up front.
+    /// Honest signers never produce such points. Signatures of other key types are verified one
+    /// by one.
+    pub fn verify_batch(items: &[(&[u8], &Signature, &PublicKey)]) -> bool {
+        let mut messages = Vec::with_capacity(items.len());
+        let mut signatures = Vec::with_capacity(items.len());
+        let mut verifying_keys = Vec::with_capacity(items.len());
+        for (data, signature, public_key) in items {
+            let (Signature::ED25519(signature), PublicKey::ED25519(public_key)) =
+                (signature, public_key)
+            else {
+                if !signature.verify(data, public_key) {
+                    return false;
+                }
+                continue;
+            };
+            if !is_canonical_prime_order_point(signature.r_bytes())
+                || !is_canonical_prime_order_point(&public_key.0)
+            {
+                return false;
+            }
+            let Ok(verifying_key) = ed25519_dalek::VerifyingKey::from_bytes(&public_key.0) else {
+                return false;
+            };
+            messages.push(*data);
+            signatures.push(*signature);
+            verifying_keys.push(verifying_key);
+        }
+        ed25519_dalek::verify_batch(&messages, &signatures, &verifying_keys).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ED25519PublicKey, KeyType, PublicKey, SecretKey, Signature};
+    use curve25519_dalek::constants::{ED25519_BASEPOINT_POINT, EIGHT_TORSION};
+    use curve25519_dalek::edwards::EdwardsPoint;
+    use curve25519_dalek::scalar::Scalar;
+    use sha2::Digest;
+
+    fn sign_messages(num_messages: u8) -> Vec<(Vec<u8>, Signature, PublicKey)> {
+        (0..num_messages)
+            .map(|i| {
+                let secret_key = SecretKey::from_random(KeyType::ED25519);
+                let data = vec![i; 32];
+                let signature = secret_key.sign(&data);
+                (data, signature, secret_key.public_key())
+            })
+            .collect()
+    }
+
+    fn verify_batch(items: &[(Vec<u8>, Signature, PublicKey)]) -> bool {
+        let items: Vec<_> = items
+            .iter()
+            .map(|(data, signature, public_key)| (data.as_slice(), signature, public_key))
+            .collect();
+        Signature::verify_batch(&items)
+    }
+
+    fn verify_one_by_one(items: &[(Vec<u8>, Signature, PublicKey)]) -> bool {
+        items.iter().all(|(data, signature, public_key)| signature.verify(data, public_key))
+    }
+
+    fn ed25519_signature(r: [u8; 32], s: Scalar) -> Signature {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&r);
+        bytes[32..].copy_from_slice(s.as_bytes());
+        Signature::ED25519(ed25519_dalek::Signature::from_bytes(&bytes))
+    }
+
+    /// Signs `data` for `public_key` with the nonce point `r`, where `nonce` and `a` only have
+    /// to match the prime order components of `r` and `public_key`.
+    fn ed25519_sign_raw(
+        data: &[u8],
+        r: &EdwardsPoint,
+        nonce: Scalar,
+        public_key: &EdwardsPoint,
+        a: Scalar,
+    ) -> Signature {
+        let r = r.compress().to_bytes();
+        let k = challenge(&r, &public_key.compress().to_bytes(), data);
+        ed25519_signature(r, nonce + k * a)
+    }
+
+    fn challenge(r: &[u8; 32], public_key: &[u8; 32], data: &[u8]) -> Scalar {
+        let hash = sha2::Sha512::new().chain_update(r).chain_update(public_key).chain_update(data);
+        let mut bytes = [0u8; 64];
+        bytes.copy_from_slice(&hash.finalize());
+        Scalar::from_bytes_mod_order_wide(&bytes)
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        assert!(verify_batch(&[]));
+        let mut items = sign_messages(10);
+        assert!(verify_batch(&items));
+
+        // Signatures of other key types are verified one by one.
+        let secret_key = SecretKey::from_random(KeyType::SECP256K1);
+        let data = vec![42; 32];
+        items.push((data.clone(), secret_key.sign(&data), secret_key.public_key()));
+        assert!(verify_batch(&items));
+    }
+
+    #[test]
+    fn test_verify_batch_invalid_signature() {
+        let mut items = sign_messages(10);
+        items[3].0[0] ^= 1;
+        assert!(!verify_batch(&items));
+
+        let mut items = sign_messages(10);
+        items[5].1 = items[6].1.clone();
+        assert!(!verify_batch(&items));
+
+        let mut items = sign_messages(10);
+        items[7].2 = items[8].2.clone();
+        assert!(!verify_batch(&items));
+    }
+
+    /// The callers fall back to `verify` when the batch fails, so the batch agrees with `verify`
+    /// as long as it never accepts signatures which `verify` rejects.
+    #[test]
+    fn test_verify_batch_agrees_with_verify() {
+        // The identity (0, 1) and the point of order two (0, -1).
+        let identity = {
+            let mut bytes = [0u8; 32];
+            bytes[0] = 1;
+            bytes
+        };
+        let order_two = {
+            let mut bytes = [0xff; 32];
+            bytes[0] = 0xec;
+            bytes[31] = 0x7f;
+            bytes
+        };
+        // The identity encoded with y = p + 1.
+        let non_canonical_identity = {
+            let mut bytes = [0xff; 32];
+            bytes[0] = 0xee;
+            bytes[31] = 0x7f;
+            bytes
+        };
+        let s = Scalar::from(42u8);
+        let s_times_basepoint = (s * ED25519_BASEPOINT_POINT).compress().to_bytes();
+        let honest_key = SecretKey::from_random(KeyType::ED25519).public_key();
+        let cases = [
+            // `verify` accepts any signature with R = s * B for the identity key.
+            (s_times_basepoint, PublicKey::ED25519(ED25519PublicKey(identity)), true),
+            (s_times_basepoint, PublicKey::ED25519(ED25519PublicKey(non_canonical_identity)), true),
+            (identity, honest_key.clone(), false),
+            (order_two, honest_key.clone(), false),
+            (non_canonical_identity, honest_key, false),
+        ];
+        for (r, public_key, valid) in cases {
+            let data = vec![42; 32];
+            let signature = ed25519_signature(r, s);
+            assert_eq!(signature.verify(&data, &public_key), valid);
+
+            let mut items = sign_messages(4);
+            items.push((data, signature, public_key));
+            assert!(!verify_batch(&items));
+            assert_eq!(verify_one_by_one(&items), valid);
+
+            items.swap(0, 4);
+            items.truncate(1);
+            assert!(!verify_batch(&items));
+            assert_eq!(verify_one_by_one(&items), valid);
+        }
+
+        let items = sign_messages(10);
+        assert_eq!(verify_batch(&items), verify_one_by_one(&items));
+    }
+
+    /// R and public keys with a torsion component are canonically encoded and not of small
+    /// order, but the random coefficients of the batch equation can cancel their torsion
+    /// component, so the batch rejects them up front.
+    #[test]
+    fn test_verify_batch_mixed_order_points() {
+        let torsion = EIGHT_TORSION[1];
+        let a = Scalar::from(7u8);
+        let nonce = Scalar::from(11u8);
+        let public_key = a * ED25519_BASEPOINT_POINT;
+        let r = nonce * ED25519_BASEPOINT_POINT;
+        let prime_order_key = PublicKey::ED25519(ED25519PublicKey(public_key.compress().0));
+
+        // A mixed-order R makes `verify` compute the prime order R instead.
+        let data = vec![42; 32];
+        let signature = ed25519_sign_raw(&data, &(r + torsion), nonce, &public_key, a);
+
+        // `verify` computes R - k * T for a mixed-order public key, which differs from R unless
+        // k is a multiple of the order of T.
+        let mixed_public_key = public_key + torsion;
+        let data_mixed_key = (0u8..)
+            .map(|i| vec![i; 32])
+            .find(|data| {
+                let k = challenge(&r.compress().0, &mixed_public_key.compress().0, data);
+                !(k * torsion).is_identity()
+            })
+            .unwrap();
+        let signature_mixed_key =
+            ed25519_sign_raw(&data_mixed_key, &r, nonce, &mixed_public_key, a);
+        let public_key_mixed_key =
+            PublicKey::ED25519(ED25519PublicKey(mixed_public_key.compress().0));
+
+        for (data, signature, public_key) in [
+            (data, signature, prime_order_key.clone()),
+            (data_mixed_key, signature_mixed_key, public_key_mixed_key),
+        ] {
+            assert!(!signature.verify(&data, &public_key));
+
+            let mut items = sign_messages(4);
+            items.push((data, signature, public_key));
+            assert!(!verify_batch(&items));
+            assert!(!verify_one_by_one(&items));
+
+            items.swap(0, 4);
+            items.truncate(1);
+            assert!(!verify_batch(&items));
+            assert!(!verify_one_by_one(&items));
+        }
+
+        // Signatures by the prime order components are accepted by both.
+        let data = vec![42; 32];
+        let signature = ed25519_sign_raw(&data, &r, nonce, &public_key, a);
+        let items = vec![(data, signature, prime_order_key)];
+        assert!(verify_batch(&items));
+        assert!(verify_one_by_one(&items));
+    }
+}
@@ -15,6 +15,7 @@ mod traits;
 #[macro_use]
 mod util;
 
+mod batch;
 mod errors;
 pub mod key_conversion;
 mod key_file;
@@ -3,7 +3,8 @@ use crate::config_store::INITIAL_TESTNET_CONFIG;
 use crate::cost::RuntimeFeesConfig;
 use crate::parameter_table::ParameterTable;
 use near_account_id::AccountId;
-use near_primitives_core::types::{Balance, Gas};
+use near_primitives_core::serialize::dec_format;
+use near_primitives_core::types::{Balance, EpochHeight, Gas};
 use near_primitives_core::version::PROTOCOL_VERSION;
 
 use super::parameter_table::InvalidConfigError;
@@ -208,3 +209,141 @@ impl CongestionControlConfig {
         }
     }
 }
+
+/// Contract data key under which the runtime config overrides are stored in
+/// the state of the protocol account.
+pub const RUNTIME_CONFIG_OVERRIDES_KEY: &[u8] = b"runtime_config_overrides";
+
+/// Number of epochs after which the runtime config overrides read from the
+/// protocol account apply. Leaves an epoch for the distribution receipts to
+/// reach the other shards even when they are buffered.
+pub const RUNTIME_CONFIG_OVERRIDES_ACTIVATION_DELAY: EpochHeight = 2;
+
+/// Runtime config parameters overridden with values stored on chain, see
+/// [`RUNTIME_CONFIG_OVERRIDES_KEY`]. Parameters that are not set keep the value
+/// defined by the protocol version.
+///
+/// Only parameters which are safe to change without a binary release can be
+/// overridden this way.
+#[derive(
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfigOverrides {
+    #[serde(default, with = "dec_format")]
+    pub storage_amount_per_byte: Option<Balance>,
+    #[serde(default)]
+    pub max_congestion_incoming_gas: Option<Gas>,
+    #[serde(default)]
+    pub max_congestion_outgoing_gas: Option<Gas>,
+    #[serde(default)]
+    pub max_congestion_memory_consumption: Option<u64>,
+    #[serde(default)]
+    pub max_congestion_missed_chunks: Option<u64>,
+}
+
+impl RuntimeConfigOverrides {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Checks that the overridden parameters have values the runtime can work
+    /// with. None of them can be zero.
+    pub fn validate(&self) -> Result<(), String> {
+        let zero_values = [
+            ("storage_amount_per_byte", self.storage_amount_per_byte == Some(0)),
+            ("max_congestion_incoming_gas", self.max_congestion_incoming_gas == Some(0)),
+            ("max_congestion_outgoing_gas", self.max_congestion_outgoing_gas == Some(0)),
+            (
+                "max_congestion_memory_consumption",
+                self.max_congestion_memory_consumption == Some(0),
+            ),
+            ("max_congestion_missed_chunks", self.max_congestion_missed_chunks == Some(0)),
+        ];
+        match zero_values.iter().find(|(_, is_zero)| *is_zero) {
+            Some((name, _)) => Err(format!("{} must not be zero", name)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn apply(&self, config: &mut RuntimeConfig) {
+        if let Some(value) = self.storage_amount_per_byte {
+            config.fees.storage_usage_config.storage_amount_per_byte = value;
+        }
+        let congestion_config = &mut config.congestion_control_config;
+        if let Some(value) = self.max_congestion_incoming_gas {
+            congestion_config.max_congestion_incoming_gas = value;
+        }
+        if let Some(value) = self.max_congestion_outgoing_gas {
+            congestion_config.max_congestion_outgoing_gas = value;
+        }
+        if let Some(value) = self.max_congestion_memory_consumption {
+            congestion_config.max_congestion_memory_consumption = value;
+        }
+        if let Some(value) = self.max_congestion_missed_chunks {
+            congestion_config.max_congestion_missed_chunks = value;
+        }
+    }
+}
+
+/// Runtime config overrides stored in the state of every shard, see
+/// `TrieKey::RuntimeConfigOverrides`. The shard of the protocol treasury
+/// account reads them from the account once per epoch and distributes them to
+/// all shards, so each chunk is applied with the values of its own state.
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuntimeConfigOverridesRecord {
+    /// Overrides by the epoch height from which they apply, sorted by height.
+    pub entries: Vec<(EpochHeight, RuntimeConfigOverrides)>,
+    /// Epoch height at which the overrides were last read from the protocol
+    /// treasury account. Only set in the shard of the account.
+    pub last_read_epoch_height: Option<EpochHeight>,
+}
+
+impl RuntimeConfigOverridesRecord {
+    /// Returns the overrides which apply in the epoch of the given height.
+    pub fn get(&self, epoch_height: EpochHeight) -> RuntimeConfigOverrides {
+        self.entries
+            .iter()
+            .rev()
+            .find(|(from_epoch_height, _)| *from_epoch_height <= epoch_height)
+            .map(|(_, overrides)| overrides.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the overrides which were stored last, whether or not they apply
+    /// already.
+    pub fn latest(&self) -> RuntimeConfigOverrides {
+        self.entries.last().map(|(_, overrides)| overrides.clone()).unwrap_or_default()
+    }
+
+    /// Stores overrides which apply from the given epoch height on, unless
+    /// overrides which apply later are stored already. Entries which can't
+    /// apply anymore after the current epoch are dropped.
+    pub fn insert(
+        &mut self,
+        from_epoch_height: EpochHeight,
+        overrides: RuntimeConfigOverrides,
+        current_epoch_height: EpochHeight,
+    ) {
+        if self.entries.last().is_some_and(|(epoch_height, _)| *epoch_height > from_epoch_height) {
+            return;
+        }
+        self.entries.retain(|(epoch_height, _)| *epoch_height < from_epoch_height);
+        self.entries.push((from_epoch_height, overrides));
+        let num_outdated = self
+            .entries
+            .iter()
+            .rposition(|(epoch_height, _)| *epoch_height <= current_epoch_height)
+            .unwrap_or(0);
+        self.entries.drain(..num_outdated);
+    }
+}
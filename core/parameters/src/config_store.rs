@@ -1,6 +1,8 @@
-use crate::config::{CongestionControlConfig, RuntimeConfig};
+use crate::config::{CongestionControlConfig, RuntimeConfig, RuntimeConfigOverrides};
 use crate::parameter_table::{ParameterTable, ParameterTableDiff};
+use crate::vm::VMKind;
 use near_primitives_core::types::ProtocolVersion;
+use near_primitives_core::version::ProtocolFeature;
 use std::collections::BTreeMap;
 use std::ops::Bound;
 use std::sync::Arc;
@@ -46,6 +48,17 @@ static CONFIG_DIFFS: &[(ProtocolVersion, &str)] = &[
     (138, include_config!("138.yaml")),
     // Congestion Control
     (142, include_config!("142.yaml")),
+    // BLS12-381 host functions
+    (144, include_config!("144.yaml")),
+    // Host functions writing results directly into the guest memory
+    (149, include_config!("149.yaml")),
+    // secp256k1 signature verification host function
+    (151, include_config!("151.yaml")),
+    // Introspection of pending yielded promises
+    (152, include_config!("152.yaml")),
+    (153, include_config!("153.yaml")),
+    // Storage staking exemption for small accounts
+    (154, include_config!("154.yaml")),
 ];
 
 /// Testnet parameters for versions <= 29, which (incorrectly) differed from mainnet parameters
@@ -152,6 +165,15 @@ impl RuntimeConfigStore {
         Self::with_one_config(RuntimeConfig::free())
     }
 
+    /// Makes all the configs run contracts with the given VM, regardless of the
+    /// VM chosen by the protocol.
+    pub fn with_vm_kind(mut self, vm_kind: VMKind) -> Self {
+        for config in self.store.values_mut() {
+            Arc::make_mut(config).wasm_config.vm_kind = vm_kind;
+        }
+        self
+    }
+
     /// Returns a `RuntimeConfig` for the corresponding protocol version.
     pub fn get_config(&self, protocol_version: ProtocolVersion) -> &Arc<RuntimeConfig> {
         self.store
@@ -162,6 +184,24 @@ impl RuntimeConfigStore {
             })
             .1
     }
+
+    /// Returns the config of the protocol version with the parameters stored
+    /// on chain applied on top of it. The overrides are ignored for protocol
+    /// versions that don't support them.
+    pub fn get_config_with_overrides(
+        &self,
+        protocol_version: ProtocolVersion,
+        overrides: &RuntimeConfigOverrides,
+    ) -> Arc<RuntimeConfig> {
+        let config = self.get_config(protocol_version);
+        if overrides.is_empty() || !ProtocolFeature::OnChainRuntimeConfig.enabled(protocol_version)
+        {
+            return config.clone();
+        }
+        let mut config = RuntimeConfig::clone(config);
+        overrides.apply(&mut config);
+        Arc::new(config)
+    }
 }
 
 #[cfg(test)]
@@ -250,6 +290,14 @@ mod tests {
         assert_eq!(new_cfg.account_creation_config.min_allowed_top_level_account_length, 0);
     }
 
+    #[test]
+    fn test_with_vm_kind() {
+        let store = RuntimeConfigStore::new(None).with_vm_kind(VMKind::Wasmtime);
+        for config in store.store.values() {
+            assert_eq!(config.wasm_config.vm_kind, VMKind::Wasmtime);
+        }
+    }
+
     #[test]
     fn test_lower_data_receipt_cost() {
         let store = RuntimeConfigStore::new(None);
@@ -330,6 +378,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_config_with_overrides() {
+        let store = RuntimeConfigStore::new(None);
+        let overrides = RuntimeConfigOverrides {
+            storage_amount_per_byte: Some(1),
+            max_congestion_missed_chunks: Some(2),
+            ..Default::default()
+        };
+
+        let version = ProtocolFeature::OnChainRuntimeConfig.protocol_version();
+        let config = store.get_config_with_overrides(version, &overrides);
+        assert_eq!(config.storage_amount_per_byte(), 1);
+        assert_eq!(config.congestion_control_config.max_congestion_missed_chunks, 2);
+        let base_config = store.get_config(version);
+        assert_eq!(
+            config.congestion_control_config.max_congestion_incoming_gas,
+            base_config.congestion_control_config.max_congestion_incoming_gas
+        );
+
+        let config = store.get_config_with_overrides(version - 1, &overrides);
+        assert_eq!(&config, store.get_config(version - 1));
+    }
+
     /// Use snapshot testing to check that the JSON representation of the
     /// configurations of each version is unchanged.
     /// If tests fail after an intended change, run `cargo insta review` accept
@@ -17,6 +17,11 @@ pub struct Fee {
     pub send_not_sir: Gas,
     /// Fee for executing the object.
     pub execution: Gas,
+    /// Compute cost of executing the object. Defaults to the `execution` fee,
+    /// can be set higher to account for undercharged actions without changing
+    /// the gas they burn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_compute: Option<Compute>,
 }
 
 impl Fee {
@@ -33,6 +38,10 @@ impl Fee {
         self.execution
     }
 
+    pub fn exec_compute(&self) -> Compute {
+        self.execution_compute.unwrap_or(self.execution)
+    }
+
     /// The minimum fee to send and execute.
     pub fn min_send_and_exec_fee(&self) -> Gas {
         std::cmp::min(self.send_sir, self.send_not_sir) + self.execution
@@ -107,16 +116,14 @@ impl ExtCostsConfig {
             ExtCosts::storage_remove_ret_value_byte => SAFETY_MULTIPLIER * 3843852,
             ExtCosts::storage_has_key_base => SAFETY_MULTIPLIER * 18013298875,
             ExtCosts::storage_has_key_byte => SAFETY_MULTIPLIER * 10263615,
-            // Here it should be `SAFETY_MULTIPLIER * 0` for consistency, but then
-            // clippy complains with "this operation will always return zero" warning
-            ExtCosts::storage_iter_create_prefix_base => 0,
-            ExtCosts::storage_iter_create_prefix_byte => 0,
-            ExtCosts::storage_iter_create_range_base => 0,
-            ExtCosts::storage_iter_create_from_byte => 0,
-            ExtCosts::storage_iter_create_to_byte => 0,
-            ExtCosts::storage_iter_next_base => 0,
-            ExtCosts::storage_iter_next_key_byte => 0,
-            ExtCosts::storage_iter_next_value_byte => 0,
+            ExtCosts::storage_iter_create_prefix_base => SAFETY_MULTIPLIER * 13743821083,
+            ExtCosts::storage_iter_create_prefix_byte => SAFETY_MULTIPLIER * 10317511,
+            ExtCosts::storage_iter_create_range_base => SAFETY_MULTIPLIER * 13743821083,
+            ExtCosts::storage_iter_create_from_byte => SAFETY_MULTIPLIER * 10317511,
+            ExtCosts::storage_iter_create_to_byte => SAFETY_MULTIPLIER * 10317511,
+            ExtCosts::storage_iter_next_base => SAFETY_MULTIPLIER * 19406191500,
+            ExtCosts::storage_iter_next_key_byte => SAFETY_MULTIPLIER * 4118708,
+            ExtCosts::storage_iter_next_value_byte => SAFETY_MULTIPLIER * 1870335,
             ExtCosts::touching_trie_node => SAFETY_MULTIPLIER * 5367318642,
             ExtCosts::read_cached_trie_node => SAFETY_MULTIPLIER * 760_000_000,
             ExtCosts::promise_and_base => SAFETY_MULTIPLIER * 488337800,
@@ -135,6 +142,24 @@ impl ExtCostsConfig {
             ExtCosts::yield_create_byte => 300_000_000_000_000,
             ExtCosts::yield_resume_base => 300_000_000_000_000,
             ExtCosts::yield_resume_byte => 300_000_000_000_000,
+            ExtCosts::bls12381_p1_sum_base => 16_500_000_000,
+            ExtCosts::bls12381_p1_sum_element => 6_000_000_000,
+            ExtCosts::bls12381_p2_sum_base => 18_600_000_000,
+            ExtCosts::bls12381_p2_sum_element => 15_000_000_000,
+            ExtCosts::bls12381_g1_multiexp_base => 16_500_000_000,
+            ExtCosts::bls12381_g1_multiexp_element => 930_000_000_000,
+            ExtCosts::bls12381_g2_multiexp_base => 18_600_000_000,
+            ExtCosts::bls12381_g2_multiexp_element => 1_995_000_000_000,
+            ExtCosts::bls12381_map_fp_to_g1_base => 1_500_000_000,
+            ExtCosts::bls12381_map_fp_to_g1_element => 252_000_000_000,
+            ExtCosts::bls12381_map_fp2_to_g2_base => 1_500_000_000,
+            ExtCosts::bls12381_map_fp2_to_g2_element => 900_000_000_000,
+            ExtCosts::bls12381_pairing_base => 2_130_000_000_000,
+            ExtCosts::bls12381_pairing_element => 2_130_000_000_000,
+            ExtCosts::result_to_memory_base => SAFETY_MULTIPLIER * 934598287,
+            ExtCosts::result_to_memory_byte => SAFETY_MULTIPLIER * 907924,
+            ExtCosts::secp256k1_verify_base => SAFETY_MULTIPLIER * 90338324260,
+            ExtCosts::yield_status_base => SAFETY_MULTIPLIER * 18013298875,
         }
         .map(|_, value| ParameterCost { gas: value, compute: value * factor });
         ExtCostsConfig { costs }
@@ -230,6 +255,24 @@ pub enum ExtCosts {
     yield_create_byte = 62,
     yield_resume_base = 63,
     yield_resume_byte = 64,
+    bls12381_p1_sum_base = 65,
+    bls12381_p1_sum_element = 66,
+    bls12381_p2_sum_base = 67,
+    bls12381_p2_sum_element = 68,
+    bls12381_g1_multiexp_base = 69,
+    bls12381_g1_multiexp_element = 70,
+    bls12381_g2_multiexp_base = 71,
+    bls12381_g2_multiexp_element = 72,
+    bls12381_map_fp_to_g1_base = 73,
+    bls12381_map_fp_to_g1_element = 74,
+    bls12381_map_fp2_to_g2_base = 75,
+    bls12381_map_fp2_to_g2_element = 76,
+    bls12381_pairing_base = 77,
+    bls12381_pairing_element = 78,
+    result_to_memory_base = 79,
+    result_to_memory_byte = 80,
+    secp256k1_verify_base = 81,
+    yield_status_base = 82,
 }
 
 // Type of an action, used in fees logic.
@@ -342,6 +385,24 @@ impl ExtCosts {
             ExtCosts::yield_create_byte => Parameter::WasmYieldCreateByte,
             ExtCosts::yield_resume_base => Parameter::WasmYieldResumeBase,
             ExtCosts::yield_resume_byte => Parameter::WasmYieldResumeBase,
+            ExtCosts::bls12381_p1_sum_base => Parameter::WasmBls12381P1SumBase,
+            ExtCosts::bls12381_p1_sum_element => Parameter::WasmBls12381P1SumElement,
+            ExtCosts::bls12381_p2_sum_base => Parameter::WasmBls12381P2SumBase,
+            ExtCosts::bls12381_p2_sum_element => Parameter::WasmBls12381P2SumElement,
+            ExtCosts::bls12381_g1_multiexp_base => Parameter::WasmBls12381G1MultiexpBase,
+            ExtCosts::bls12381_g1_multiexp_element => Parameter::WasmBls12381G1MultiexpElement,
+            ExtCosts::bls12381_g2_multiexp_base => Parameter::WasmBls12381G2MultiexpBase,
+            ExtCosts::bls12381_g2_multiexp_element => Parameter::WasmBls12381G2MultiexpElement,
+            ExtCosts::bls12381_map_fp_to_g1_base => Parameter::WasmBls12381MapFpToG1Base,
+            ExtCosts::bls12381_map_fp_to_g1_element => Parameter::WasmBls12381MapFpToG1Element,
+            ExtCosts::bls12381_map_fp2_to_g2_base => Parameter::WasmBls12381MapFp2ToG2Base,
+            ExtCosts::bls12381_map_fp2_to_g2_element => Parameter::WasmBls12381MapFp2ToG2Element,
+            ExtCosts::bls12381_pairing_base => Parameter::WasmBls12381PairingBase,
+            ExtCosts::bls12381_pairing_element => Parameter::WasmBls12381PairingElement,
+            ExtCosts::result_to_memory_base => Parameter::WasmResultToMemoryBase,
+            ExtCosts::result_to_memory_byte => Parameter::WasmResultToMemoryByte,
+            ExtCosts::secp256k1_verify_base => Parameter::WasmSecp256k1VerifyBase,
+            ExtCosts::yield_status_base => Parameter::WasmYieldStatusBase,
         }
     }
 }
@@ -371,6 +432,12 @@ pub struct StorageUsageConfig {
     pub num_bytes_account: u64,
     /// Additional number of bytes for a k/v record
     pub num_extra_bytes_record: u64,
+    /// Accounts using at most this many bytes of storage are zero balance
+    /// accounts, which don't need any balance to cover their storage.
+    pub zero_balance_account_limit: u64,
+    /// Number of bytes of every account which are not counted towards the
+    /// balance required for storage staking.
+    pub num_bytes_exempt_from_staking: u64,
 }
 
 impl RuntimeFeesConfig {
@@ -389,81 +456,97 @@ impl RuntimeFeesConfig {
                     send_sir: 3_850_000_000_000,
                     send_not_sir: 3_850_000_000_000,
                     execution: 3_850_000_000_000,
+                    execution_compute: None,
                 },
                 ActionCosts::delete_account => Fee {
                     send_sir: 147489000000,
                     send_not_sir: 147489000000,
                     execution: 147489000000,
+                    execution_compute: None,
                 },
                 ActionCosts::deploy_contract_base => Fee {
                     send_sir: 184765750000,
                     send_not_sir: 184765750000,
                     execution: 184765750000,
+                    execution_compute: None,
                 },
                 ActionCosts::deploy_contract_byte => Fee {
                     send_sir: 6812999,
                     send_not_sir: 6812999,
                     execution: 6812999,
+                    execution_compute: None,
                 },
                 ActionCosts::function_call_base => Fee {
                     send_sir: 2319861500000,
                     send_not_sir: 2319861500000,
                     execution: 2319861500000,
+                    execution_compute: None,
                 },
                 ActionCosts::function_call_byte => Fee {
                     send_sir: 2235934,
                     send_not_sir: 2235934,
                     execution: 2235934,
+                    execution_compute: None,
                 },
                 ActionCosts::transfer => Fee {
                     send_sir: 115123062500,
                     send_not_sir: 115123062500,
                     execution: 115123062500,
+                    execution_compute: None,
                 },
                 ActionCosts::stake => Fee {
                     send_sir: 141715687500,
                     send_not_sir: 141715687500,
                     execution: 102217625000,
+                    execution_compute: None,
                 },
                 ActionCosts::add_full_access_key => Fee {
                     send_sir: 101765125000,
                     send_not_sir: 101765125000,
                     execution: 101765125000,
+                    execution_compute: None,
                 },
                 ActionCosts::add_function_call_key_base => Fee {
                     send_sir: 102217625000,
                     send_not_sir: 102217625000,
                     execution: 102217625000,
+                    execution_compute: None,
                 },
                 ActionCosts::add_function_call_key_byte => Fee {
                     send_sir: 1925331,
                     send_not_sir: 1925331,
                     execution: 1925331,
+                    execution_compute: None,
                 },
                 ActionCosts::delete_key => Fee {
                     send_sir: 94946625000,
                     send_not_sir: 94946625000,
                     execution: 94946625000,
+                    execution_compute: None,
                 },
                 ActionCosts::new_action_receipt => Fee {
                     send_sir: 108059500000,
                     send_not_sir: 108059500000,
                     execution: 108059500000,
+                    execution_compute: None,
                 },
                 ActionCosts::new_data_receipt_base => Fee {
                     send_sir: 4697339419375,
                     send_not_sir: 4697339419375,
                     execution: 4697339419375,
+                    execution_compute: None,
                 },
                 ActionCosts::new_data_receipt_byte => Fee {
                     send_sir: 59357464,
                     send_not_sir: 59357464,
                     execution: 59357464,
+                    execution_compute: None,
                 },
                 ActionCosts::delegate => Fee {
                     send_sir: 200_000_000_000,
                     send_not_sir: 200_000_000_000,
                     execution: 200_000_000_000,
+                    execution_compute: None,
                 },
             },
         }
@@ -472,7 +555,7 @@ impl RuntimeFeesConfig {
     pub fn free() -> Self {
         Self {
             action_fees: enum_map::enum_map! {
-                _ => Fee { send_sir: 0, send_not_sir: 0, execution: 0 }
+                _ => Fee { send_sir: 0, send_not_sir: 0, execution: 0, execution_compute: None }
             },
             storage_usage_config: StorageUsageConfig::free(),
             burnt_gas_reward: Rational32::from_integer(0),
@@ -496,11 +579,19 @@ impl StorageUsageConfig {
             num_bytes_account: 100,
             num_extra_bytes_record: 40,
             storage_amount_per_byte: 909 * 100_000_000_000_000_000,
+            zero_balance_account_limit: 770,
+            num_bytes_exempt_from_staking: 0,
         }
     }
 
     pub(crate) fn free() -> StorageUsageConfig {
-        Self { num_bytes_account: 0, num_extra_bytes_record: 0, storage_amount_per_byte: 0 }
+        Self {
+            num_bytes_account: 0,
+            num_extra_bytes_record: 0,
+            storage_amount_per_byte: 0,
+            zero_balance_account_limit: 770,
+            num_bytes_exempt_from_staking: 0,
+        }
     }
 }
 
@@ -514,7 +605,38 @@ pub fn transfer_exec_fee(
     eth_implicit_accounts_enabled: bool,
     receiver_account_type: AccountType,
 ) -> Gas {
-    let transfer_fee = cfg.fee(ActionCosts::transfer).exec_fee();
+    transfer_exec_cost(
+        cfg,
+        implicit_account_creation_allowed,
+        eth_implicit_accounts_enabled,
+        receiver_account_type,
+        Fee::exec_fee,
+    )
+}
+
+pub fn transfer_exec_compute(
+    cfg: &RuntimeFeesConfig,
+    implicit_account_creation_allowed: bool,
+    eth_implicit_accounts_enabled: bool,
+    receiver_account_type: AccountType,
+) -> Compute {
+    transfer_exec_cost(
+        cfg,
+        implicit_account_creation_allowed,
+        eth_implicit_accounts_enabled,
+        receiver_account_type,
+        Fee::exec_compute,
+    )
+}
+
+fn transfer_exec_cost(
+    cfg: &RuntimeFeesConfig,
+    implicit_account_creation_allowed: bool,
+    eth_implicit_accounts_enabled: bool,
+    receiver_account_type: AccountType,
+    cost: fn(&Fee) -> u64,
+) -> u64 {
+    let transfer_fee = cost(cfg.fee(ActionCosts::transfer));
     match (implicit_account_creation_allowed, eth_implicit_accounts_enabled, receiver_account_type)
     {
         // Regular transfer to a named account.
@@ -525,13 +647,13 @@ pub fn transfer_exec_fee(
         (true, false, AccountType::EthImplicitAccount) => transfer_fee,
         // Extra fee for the CreateAccount.
         (true, true, AccountType::EthImplicitAccount) => {
-            transfer_fee + cfg.fee(ActionCosts::create_account).exec_fee()
+            transfer_fee + cost(cfg.fee(ActionCosts::create_account))
         }
         // Extra fees for the CreateAccount and AddFullAccessKey.
         (true, _, AccountType::NearImplicitAccount) => {
             transfer_fee
-                + cfg.fee(ActionCosts::create_account).exec_fee()
-                + cfg.fee(ActionCosts::add_full_access_key).exec_fee()
+                + cost(cfg.fee(ActionCosts::create_account))
+                + cost(cfg.fee(ActionCosts::add_full_access_key))
         }
     }
 }
@@ -6,11 +6,13 @@ pub mod parameter_table;
 pub mod view;
 pub mod vm;
 
-pub use config::{AccountCreationConfig, RuntimeConfig};
+pub use config::{
+    AccountCreationConfig, RuntimeConfig, RuntimeConfigOverrides, RuntimeConfigOverridesRecord,
+};
 pub use config_store::RuntimeConfigStore;
 pub use cost::{
-    transfer_exec_fee, transfer_send_fee, ActionCosts, ExtCosts, ExtCostsConfig, Fee,
-    ParameterCost, RuntimeFeesConfig, StorageUsageConfig,
+    transfer_exec_compute, transfer_exec_fee, transfer_send_fee, ActionCosts, ExtCosts,
+    ExtCostsConfig, Fee, ParameterCost, RuntimeFeesConfig, StorageUsageConfig,
 };
 pub use parameter::Parameter;
 pub use view::{RuntimeConfigView, RuntimeFeesConfigView};
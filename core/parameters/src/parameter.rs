@@ -33,6 +33,8 @@ pub enum Parameter {
     StorageAmountPerByte,
     StorageNumBytesAccount,
     StorageNumExtraBytesRecord,
+    StorageZeroBalanceAccountLimit,
+    StorageNumBytesExemptFromStaking,
 
     // Static action costs
     // send_sir / send_not_sir is burned when creating a receipt on the signer shard.
@@ -124,6 +126,24 @@ pub enum Parameter {
     WasmYieldCreateByte,
     WasmYieldResumeBase,
     WasmYieldResumeByte,
+    WasmBls12381P1SumBase,
+    WasmBls12381P1SumElement,
+    WasmBls12381P2SumBase,
+    WasmBls12381P2SumElement,
+    WasmBls12381G1MultiexpBase,
+    WasmBls12381G1MultiexpElement,
+    WasmBls12381G2MultiexpBase,
+    WasmBls12381G2MultiexpElement,
+    WasmBls12381MapFpToG1Base,
+    WasmBls12381MapFpToG1Element,
+    WasmBls12381MapFp2ToG2Base,
+    WasmBls12381MapFp2ToG2Element,
+    WasmBls12381PairingBase,
+    WasmBls12381PairingElement,
+    WasmResultToMemoryBase,
+    WasmResultToMemoryByte,
+    WasmSecp256k1VerifyBase,
+    WasmYieldStatusBase,
 
     // Smart contract limits
     MaxGasBurnt,
@@ -155,6 +175,7 @@ pub enum Parameter {
     AccountIdValidityRulesVersion,
     YieldTimeoutLengthInBlocks,
     MaxYieldPayloadSize,
+    MaxStorageIterators,
 
     // Contract runtime features
     #[strum(serialize = "disable_9393_fix")]
@@ -169,6 +190,11 @@ pub enum Parameter {
     VmKind,
     EthImplicitAccounts,
     YieldResume,
+    Bls12381,
+    ResultsToMemory,
+    Secp256k1Verify,
+    PromiseYieldStatus,
+    StorageIterators,
 
     // Congestion Control
     MaxCongestionIncomingGas,
@@ -250,6 +276,7 @@ impl Parameter {
             Parameter::YieldTimeoutLengthInBlocks,
             Parameter::MaxYieldPayloadSize,
             Parameter::StorageProofSizeReceiptLimit,
+            Parameter::MaxStorageIterators,
         ]
         .iter()
     }
@@ -18,7 +18,7 @@ pub(crate) enum ParameterValue {
     U64(u64),
     Rational { numerator: i32, denominator: i32 },
     ParameterCost { gas: u64, compute: u64 },
-    Fee { send_sir: u64, send_not_sir: u64, execution: u64 },
+    Fee { send_sir: u64, send_not_sir: u64, execution: u64, execution_compute: Option<u64> },
     // Can be used to store either a string or u128. Ideally, we would use a dedicated enum member
     // for u128, but this is currently impossible to express in YAML (see
     // `canonicalize_yaml_string`).
@@ -137,8 +137,9 @@ impl TryFrom<&ParameterValue> for Fee {
 
     fn try_from(value: &ParameterValue) -> Result<Self, Self::Error> {
         match value {
-            &ParameterValue::Fee { send_sir, send_not_sir, execution } => {
-                Ok(Fee { send_sir, send_not_sir, execution })
+            // If not specified, the compute cost defaults to the execution fee.
+            &ParameterValue::Fee { send_sir, send_not_sir, execution, execution_compute } => {
+                Ok(Fee { send_sir, send_not_sir, execution, execution_compute })
             }
             _ => Err(ValueConversionError::ParseType(std::any::type_name::<Fee>(), value.clone())),
         }
@@ -204,7 +205,7 @@ impl core::fmt::Display for ParameterValue {
             ParameterValue::ParameterCost { gas, compute } => {
                 write!(f, "{:>20}, compute: {:>20}", format_number(*gas), format_number(*compute))
             }
-            ParameterValue::Fee { send_sir, send_not_sir, execution } => {
+            ParameterValue::Fee { send_sir, send_not_sir, execution, execution_compute } => {
                 write!(
                     f,
                     r#"
@@ -214,7 +215,11 @@ impl core::fmt::Display for ParameterValue {
                     format_number(*send_sir),
                     format_number(*send_not_sir),
                     format_number(*execution)
-                )
+                )?;
+                if let Some(compute) = execution_compute {
+                    write!(f, "\n- compute:      {:>20}", format_number(*compute))?;
+                }
+                Ok(())
             }
             ParameterValue::String(v) => write!(f, "{v}"),
             ParameterValue::Flag(b) => write!(f, "{b:?}"),
@@ -301,6 +306,10 @@ impl TryFrom<&ParameterTable> for RuntimeConfig {
                     storage_amount_per_byte: params.get(Parameter::StorageAmountPerByte)?,
                     num_bytes_account: params.get(Parameter::StorageNumBytesAccount)?,
                     num_extra_bytes_record: params.get(Parameter::StorageNumExtraBytesRecord)?,
+                    zero_balance_account_limit: params
+                        .get(Parameter::StorageZeroBalanceAccountLimit)?,
+                    num_bytes_exempt_from_staking: params
+                        .get(Parameter::StorageNumBytesExemptFromStaking)?,
                 },
             },
             wasm_config: Config {
@@ -327,6 +336,11 @@ impl TryFrom<&ParameterTable> for RuntimeConfig {
                 function_call_weight: params.get(Parameter::FunctionCallWeight)?,
                 eth_implicit_accounts: params.get(Parameter::EthImplicitAccounts)?,
                 yield_resume_host_functions: params.get(Parameter::YieldResume)?,
+                bls12381: params.get(Parameter::Bls12381)?,
+                results_to_memory: params.get(Parameter::ResultsToMemory)?,
+                secp256k1_verify: params.get(Parameter::Secp256k1Verify)?,
+                promise_yield_status: params.get(Parameter::PromiseYieldStatus)?,
+                storage_iterators: params.get(Parameter::StorageIterators)?,
             },
             account_creation_config: AccountCreationConfig {
                 min_allowed_top_level_account_length: params
@@ -127,6 +127,10 @@ pub struct StorageUsageConfigView {
     pub num_bytes_account: u64,
     /// Additional number of bytes for a k/v record
     pub num_extra_bytes_record: u64,
+    /// Accounts using at most this many bytes don't need any balance for storage.
+    pub zero_balance_account_limit: u64,
+    /// Number of bytes of every account not counted towards storage staking.
+    pub num_bytes_exempt_from_staking: u64,
 }
 
 impl From<crate::RuntimeConfig> for RuntimeConfigView {
@@ -177,6 +181,14 @@ impl From<crate::RuntimeConfig> for RuntimeConfigView {
                 storage_usage_config: StorageUsageConfigView {
                     num_bytes_account: config.fees.storage_usage_config.num_bytes_account,
                     num_extra_bytes_record: config.fees.storage_usage_config.num_extra_bytes_record,
+                    zero_balance_account_limit: config
+                        .fees
+                        .storage_usage_config
+                        .zero_balance_account_limit,
+                    num_bytes_exempt_from_staking: config
+                        .fees
+                        .storage_usage_config
+                        .num_bytes_exempt_from_staking,
                 },
                 burnt_gas_reward: config.fees.burnt_gas_reward,
                 pessimistic_gas_price_inflation_ratio: config
@@ -227,6 +239,16 @@ pub struct VMConfigView {
     pub eth_implicit_accounts: bool,
     /// See [`VMConfig::yield_resume_host_functions`].
     pub yield_resume_host_functions: bool,
+    /// See [`VMConfig::bls12381`].
+    pub bls12381: bool,
+    /// See [`VMConfig::results_to_memory`].
+    pub results_to_memory: bool,
+    /// See [`VMConfig::secp256k1_verify`].
+    pub secp256k1_verify: bool,
+    /// See [`VMConfig::promise_yield_status`].
+    pub promise_yield_status: bool,
+    /// See [`VMConfig::storage_iterators`].
+    pub storage_iterators: bool,
 
     /// Describes limits for VM and Runtime.
     ///
@@ -253,6 +275,11 @@ impl From<crate::vm::Config> for VMConfigView {
             vm_kind: config.vm_kind,
             eth_implicit_accounts: config.eth_implicit_accounts,
             yield_resume_host_functions: config.yield_resume_host_functions,
+            bls12381: config.bls12381,
+            results_to_memory: config.results_to_memory,
+            secp256k1_verify: config.secp256k1_verify,
+            promise_yield_status: config.promise_yield_status,
+            storage_iterators: config.storage_iterators,
         }
     }
 }
@@ -275,6 +302,11 @@ impl From<VMConfigView> for crate::vm::Config {
             vm_kind: view.vm_kind,
             eth_implicit_accounts: view.eth_implicit_accounts,
             yield_resume_host_functions: view.yield_resume_host_functions,
+            bls12381: view.bls12381,
+            results_to_memory: view.results_to_memory,
+            secp256k1_verify: view.secp256k1_verify,
+            promise_yield_status: view.promise_yield_status,
+            storage_iterators: view.storage_iterators,
         }
     }
 }
@@ -401,7 +433,7 @@ pub struct ExtCostsConfigView {
     pub storage_iter_next_base: Gas,
     /// Trie iterator next key byte cost
     pub storage_iter_next_key_byte: Gas,
-    /// Trie iterator next key byte cost
+    /// Trie iterator next value byte cost
     pub storage_iter_next_value_byte: Gas,
 
     /// Cost per reading trie node from DB
@@ -455,6 +487,52 @@ pub struct ExtCostsConfigView {
     pub yield_resume_base: Gas,
     /// Per byte cost of resume payload.
     pub yield_resume_byte: Gas,
+
+    // #############
+    // # BLS12-381 #
+    // #############
+    /// Base cost for sum of G1 points
+    pub bls12381_p1_sum_base: Gas,
+    /// Per element cost for sum of G1 points
+    pub bls12381_p1_sum_element: Gas,
+    /// Base cost for sum of G2 points
+    pub bls12381_p2_sum_base: Gas,
+    /// Per element cost for sum of G2 points
+    pub bls12381_p2_sum_element: Gas,
+    /// Base cost for G1 multiexp
+    pub bls12381_g1_multiexp_base: Gas,
+    /// Per element cost for G1 multiexp
+    pub bls12381_g1_multiexp_element: Gas,
+    /// Base cost for G2 multiexp
+    pub bls12381_g2_multiexp_base: Gas,
+    /// Per element cost for G2 multiexp
+    pub bls12381_g2_multiexp_element: Gas,
+    /// Base cost for mapping Fp elements to G1
+    pub bls12381_map_fp_to_g1_base: Gas,
+    /// Per element cost for mapping Fp elements to G1
+    pub bls12381_map_fp_to_g1_element: Gas,
+    /// Base cost for mapping Fp2 elements to G2
+    pub bls12381_map_fp2_to_g2_base: Gas,
+    /// Per element cost for mapping Fp2 elements to G2
+    pub bls12381_map_fp2_to_g2_element: Gas,
+    /// Base cost for pairing check
+    pub bls12381_pairing_base: Gas,
+    /// Per element cost for pairing check
+    pub bls12381_pairing_element: Gas,
+
+    // #####################
+    // # Results to memory #
+    // #####################
+    /// Base cost for writing a host function result directly into the guest memory
+    pub result_to_memory_base: Gas,
+    /// Per byte cost for writing a host function result directly into the guest memory
+    pub result_to_memory_byte: Gas,
+
+    /// Cost of verifying a secp256k1 signature
+    pub secp256k1_verify_base: Gas,
+
+    /// Cost of looking up the status of a yielded promise
+    pub yield_status_base: Gas,
 }
 
 impl From<crate::ExtCostsConfig> for ExtCostsConfigView {
@@ -529,6 +607,25 @@ impl From<crate::ExtCostsConfig> for ExtCostsConfigView {
             yield_create_byte: config.gas_cost(ExtCosts::yield_create_byte),
             yield_resume_base: config.gas_cost(ExtCosts::yield_resume_base),
             yield_resume_byte: config.gas_cost(ExtCosts::yield_resume_byte),
+            bls12381_p1_sum_base: config.gas_cost(ExtCosts::bls12381_p1_sum_base),
+            bls12381_p1_sum_element: config.gas_cost(ExtCosts::bls12381_p1_sum_element),
+            bls12381_p2_sum_base: config.gas_cost(ExtCosts::bls12381_p2_sum_base),
+            bls12381_p2_sum_element: config.gas_cost(ExtCosts::bls12381_p2_sum_element),
+            bls12381_g1_multiexp_base: config.gas_cost(ExtCosts::bls12381_g1_multiexp_base),
+            bls12381_g1_multiexp_element: config.gas_cost(ExtCosts::bls12381_g1_multiexp_element),
+            bls12381_g2_multiexp_base: config.gas_cost(ExtCosts::bls12381_g2_multiexp_base),
+            bls12381_g2_multiexp_element: config.gas_cost(ExtCosts::bls12381_g2_multiexp_element),
+            bls12381_map_fp_to_g1_base: config.gas_cost(ExtCosts::bls12381_map_fp_to_g1_base),
+            bls12381_map_fp_to_g1_element: config.gas_cost(ExtCosts::bls12381_map_fp_to_g1_element),
+            bls12381_map_fp2_to_g2_base: config.gas_cost(ExtCosts::bls12381_map_fp2_to_g2_base),
+            bls12381_map_fp2_to_g2_element: config
+                .gas_cost(ExtCosts::bls12381_map_fp2_to_g2_element),
+            bls12381_pairing_base: config.gas_cost(ExtCosts::bls12381_pairing_base),
+            bls12381_pairing_element: config.gas_cost(ExtCosts::bls12381_pairing_element),
+            result_to_memory_base: config.gas_cost(ExtCosts::result_to_memory_base),
+            result_to_memory_byte: config.gas_cost(ExtCosts::result_to_memory_byte),
+            secp256k1_verify_base: config.gas_cost(ExtCosts::secp256k1_verify_base),
+            yield_status_base: config.gas_cost(ExtCosts::yield_status_base),
             // removed parameters
             contract_compile_base: 0,
             contract_compile_bytes: 0,
@@ -604,6 +701,24 @@ impl From<ExtCostsConfigView> for crate::ExtCostsConfig {
                 ExtCosts::yield_create_byte => view.yield_create_byte,
                 ExtCosts::yield_resume_base => view.yield_resume_base,
                 ExtCosts::yield_resume_byte => view.yield_resume_byte,
+                ExtCosts::bls12381_p1_sum_base => view.bls12381_p1_sum_base,
+                ExtCosts::bls12381_p1_sum_element => view.bls12381_p1_sum_element,
+                ExtCosts::bls12381_p2_sum_base => view.bls12381_p2_sum_base,
+                ExtCosts::bls12381_p2_sum_element => view.bls12381_p2_sum_element,
+                ExtCosts::bls12381_g1_multiexp_base => view.bls12381_g1_multiexp_base,
+                ExtCosts::bls12381_g1_multiexp_element => view.bls12381_g1_multiexp_element,
+                ExtCosts::bls12381_g2_multiexp_base => view.bls12381_g2_multiexp_base,
+                ExtCosts::bls12381_g2_multiexp_element => view.bls12381_g2_multiexp_element,
+                ExtCosts::bls12381_map_fp_to_g1_base => view.bls12381_map_fp_to_g1_base,
+                ExtCosts::bls12381_map_fp_to_g1_element => view.bls12381_map_fp_to_g1_element,
+                ExtCosts::bls12381_map_fp2_to_g2_base => view.bls12381_map_fp2_to_g2_base,
+                ExtCosts::bls12381_map_fp2_to_g2_element => view.bls12381_map_fp2_to_g2_element,
+                ExtCosts::bls12381_pairing_base => view.bls12381_pairing_base,
+                ExtCosts::bls12381_pairing_element => view.bls12381_pairing_element,
+                ExtCosts::result_to_memory_base => view.result_to_memory_base,
+                ExtCosts::result_to_memory_byte => view.result_to_memory_byte,
+                ExtCosts::secp256k1_verify_base => view.secp256k1_verify_base,
+                ExtCosts::yield_status_base => view.yield_status_base,
         }
         .map(|_, value| ParameterCost { gas: value, compute: value });
         Self { costs }
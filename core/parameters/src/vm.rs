@@ -138,6 +138,12 @@ pub struct LimitConfig {
     pub max_yield_payload_size: u64,
     /// Maximum size of the recorded trie storage proof.
     pub storage_proof_size_receipt_limit: usize,
+    /// Maximum number of storage iterators a function call can create.
+    ///
+    /// Each iterator keeps the flat storage changes of its range in memory
+    /// until the end of the call, so their number is bounded independently of
+    /// gas.
+    pub max_storage_iterators: u64,
 }
 
 /// Dynamic configuration parameters required for the WASM runtime to
@@ -192,6 +198,23 @@ pub struct Config {
     /// Enable the `promise_yield_create` and `promise_yield_resume` host functions.
     pub yield_resume_host_functions: bool,
 
+    /// Enable the host functions added by the `BLS12381` protocol feature.
+    pub bls12381: bool,
+
+    /// Enable the host functions writing their results directly into the
+    /// guest memory instead of a register.
+    pub results_to_memory: bool,
+
+    /// Enable the `secp256k1_verify` host function.
+    pub secp256k1_verify: bool,
+
+    /// Enable the `promise_yield_status` host function.
+    pub promise_yield_status: bool,
+
+    /// Enable the `storage_iter_*` host functions, which fail as deprecated
+    /// otherwise.
+    pub storage_iterators: bool,
+
     /// Describes limits for VM and Runtime.
     pub limit_config: LimitConfig,
 }
@@ -217,6 +240,11 @@ impl Config {
     }
 
     pub fn enable_all_features(&mut self) {
+        self.storage_iterators = true;
+        self.promise_yield_status = true;
+        self.secp256k1_verify = true;
+        self.results_to_memory = true;
+        self.bls12381 = true;
         self.yield_resume_host_functions = true;
         self.eth_implicit_accounts = true;
         self.function_call_weight = true;
@@ -159,6 +159,46 @@ pub enum ProtocolFeature {
     CongestionControl,
     // Stateless validation: Distribute state witness as reed solomon encoded parts
     PartialEncodedStateWitness,
+    /// Host functions for the BLS12-381 curve operations, introduced in
+    /// https://github.com/near/NEPs/pull/488.
+    BLS12381,
+    /// Stateless validation: don't include values of keys removed or
+    /// overwritten by the chunk in the storage proof, because only their
+    /// hashes are needed to update the trie.
+    ExcludeRemovedValuesFromStorageProof,
+    /// Transactions with a priority fee. Receipts created by them carry the
+    /// fee as their priority and skip ahead of other receipts when delayed.
+    TransactionPriority,
+    /// Contract code deployed once and referenced by hash from any number of
+    /// accounts with the `DeployGlobalContract` and `UseGlobalContract` actions.
+    GlobalContracts,
+    /// Transactions with a `valid_until_height`, after which they can no
+    /// longer be included in a chunk.
+    TransactionExpiry,
+    /// Host functions writing their results directly into the guest memory,
+    /// skipping the copy through a register.
+    HostResultsToMemory,
+    /// Runtime config parameters overridden with values stored in the state of
+    /// the protocol treasury account, read once per epoch.
+    OnChainRuntimeConfig,
+    /// The `secp256k1_verify` host function, checking an ECDSA signature
+    /// against a known public key without recovering it.
+    Secp256k1Verify,
+    /// The `promise_yield_status` host function and `view_promise_yield` RPC
+    /// query, reporting whether a yielded promise is still pending and how
+    /// many blocks are left until its timeout. Timed-out yields also produce
+    /// an execution outcome for their resume receipt.
+    PromiseYieldStatus,
+    /// Reintroduces the `storage_iter_*` host functions, iterating over the
+    /// keys of the contract's own storage, with non-zero costs.
+    StorageIterators,
+    /// Raises the storage limit of zero balance accounts and exempts the first
+    /// bytes of every account from storage staking. Both are configured by the
+    /// runtime config.
+    StorageStakingExemption,
+    /// Reports the number and size of storage reads, writes and removals made
+    /// by contracts in the execution metadata of receipt outcomes.
+    StorageAccessMetadata,
 }
 
 impl ProtocolFeature {
@@ -236,6 +276,18 @@ impl ProtocolFeature {
             // TODO(#11201): When stabilizing this feature in mainnet, also remove the temporary code
             // that always enables this for mocknet (see config_mocknet function).
             ProtocolFeature::ShuffleShardAssignments => 143,
+            ProtocolFeature::BLS12381 => 144,
+            ProtocolFeature::ExcludeRemovedValuesFromStorageProof => 145,
+            ProtocolFeature::TransactionPriority => 146,
+            ProtocolFeature::GlobalContracts => 147,
+            ProtocolFeature::TransactionExpiry => 148,
+            ProtocolFeature::HostResultsToMemory => 149,
+            ProtocolFeature::OnChainRuntimeConfig => 150,
+            ProtocolFeature::Secp256k1Verify => 151,
+            ProtocolFeature::PromiseYieldStatus => 152,
+            ProtocolFeature::StorageIterators => 153,
+            ProtocolFeature::StorageStakingExemption => 154,
+            ProtocolFeature::StorageAccessMetadata => 155,
         }
     }
 
@@ -255,7 +307,7 @@ pub const PROTOCOL_VERSION: ProtocolVersion = if cfg!(feature = "statelessnet_pr
     86
 } else if cfg!(feature = "nightly_protocol") {
     // On nightly, pick big enough version to support all features.
-    143
+    155
 } else {
     // Enable all stable features.
     STABLE_PROTOCOL_VERSION
@@ -512,6 +512,8 @@ pub enum ActionErrorKind {
     DelegateActionNonceTooLarge { delegate_nonce: Nonce, upper_bound: Nonce },
     /// Non-refundable storage transfer to an existing account is not allowed according to NEP-491.
     NonRefundableTransferToExistingAccount { account_id: AccountId },
+    /// The global contract code with the given hash hasn't been deployed.
+    GlobalContractDoesNotExist { code_hash: CryptoHash },
 }
 
 impl From<ActionErrorKind> for ActionError {
@@ -887,6 +889,7 @@ impl Display for ActionErrorKind {
             ActionErrorKind::NonRefundableTransferToExistingAccount { account_id} => {
                 write!(f, "Can't make non-refundable storage transfer to {} because it already exists", account_id)
             }
+            ActionErrorKind::GlobalContractDoesNotExist { code_hash } => write!(f, "Global contract with code hash {} doesn't exist", code_hash),
         }
     }
 }
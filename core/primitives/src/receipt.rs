@@ -1,10 +1,12 @@
 use crate::hash::CryptoHash;
 use crate::serialize::dec_format;
+use crate::shard_layout::{account_id_to_shard_id, ShardLayout, ShardVersion};
 use crate::transaction::{Action, TransferAction};
-use crate::types::{AccountId, Balance, BlockHeight, ShardId};
+use crate::types::{AccountId, Balance, BlockHeight, EpochHeight, ShardId};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::{KeyType, PublicKey};
 use near_fmt::AbbrBytes;
+use near_parameters::RuntimeConfigOverrides;
 use serde_with::base64::Base64;
 use serde_with::serde_as;
 use std::borrow::Borrow;
@@ -154,6 +156,7 @@ impl BorshDeserialize for Receipt {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ReceiptPriority {
     /// Used in ReceiptV1
     Priority(u64),
@@ -180,6 +183,30 @@ impl Borrow<CryptoHash> for Receipt {
 }
 
 impl Receipt {
+    /// Creates a receipt with the given priority. Receipts without priority
+    /// are created as `V0`, so that they are encoded as before priorities
+    /// were introduced.
+    pub fn new(
+        predecessor_id: AccountId,
+        receiver_id: AccountId,
+        receipt_id: CryptoHash,
+        receipt: ReceiptEnum,
+        priority: ReceiptPriority,
+    ) -> Self {
+        match priority {
+            ReceiptPriority::Priority(priority) => Receipt::V1(ReceiptV1 {
+                predecessor_id,
+                receiver_id,
+                receipt_id,
+                receipt,
+                priority,
+            }),
+            ReceiptPriority::NoPriority => {
+                Receipt::V0(ReceiptV0 { predecessor_id, receiver_id, receipt_id, receipt })
+            }
+        }
+    }
+
     pub fn receiver_id(&self) -> &AccountId {
         match self {
             Receipt::V0(receipt) => &receipt.receiver_id,
@@ -255,6 +282,43 @@ impl Receipt {
         *self.receipt_id()
     }
 
+    /// The shard the receipt has to be applied in. Receipts are routed to the
+    /// shard of their receiver, except for distribution receipts which target
+    /// a shard directly. If the shard layout changed since the distribution
+    /// receipt was sent, it falls back to the shard of its receiver and the
+    /// distribution restarts in the new layout.
+    pub fn receiver_shard_id(&self, shard_layout: &ShardLayout) -> ShardId {
+        if let Some((shard_layout_version, target_shard)) = self.distribution_target() {
+            if shard_layout_version == shard_layout.version()
+                && shard_layout.shard_ids().any(|shard_id| shard_id == target_shard)
+            {
+                return target_shard;
+            }
+        }
+        account_id_to_shard_id(self.receiver_id(), shard_layout)
+    }
+
+    /// Whether the receipt is delivered to a shard chosen by its sender rather
+    /// than to the shard of its receiver.
+    pub fn is_distribution(&self) -> bool {
+        self.distribution_target().is_some()
+    }
+
+    fn distribution_target(&self) -> Option<(ShardVersion, ShardId)> {
+        match self.receipt() {
+            ReceiptEnum::GlobalContractDistribution(receipt) => {
+                Some((receipt.shard_layout_version, receipt.target_shard))
+            }
+            ReceiptEnum::RuntimeConfigOverridesDistribution(receipt) => {
+                Some((receipt.shard_layout_version, receipt.target_shard))
+            }
+            ReceiptEnum::Action(_)
+            | ReceiptEnum::Data(_)
+            | ReceiptEnum::PromiseYield(_)
+            | ReceiptEnum::PromiseResume(_) => None,
+        }
+    }
+
     /// Generates a receipt with a transfer from system for a given balance without a receipt_id.
     /// This should be used for token refunds instead of gas refunds. It inherits priority from the parent receipt.
     /// It doesn't refund the allowance of the access key. For gas refunds use `new_gas_refund`.
@@ -263,37 +327,20 @@ impl Receipt {
         refund: Balance,
         priority: ReceiptPriority,
     ) -> Self {
-        match priority {
-            ReceiptPriority::Priority(priority) => Receipt::V1(ReceiptV1 {
-                predecessor_id: "system".parse().unwrap(),
-                receiver_id: receiver_id.clone(),
-                receipt_id: CryptoHash::default(),
-
-                receipt: ReceiptEnum::Action(ActionReceipt {
-                    signer_id: "system".parse().unwrap(),
-                    signer_public_key: PublicKey::empty(KeyType::ED25519),
-                    gas_price: 0,
-                    output_data_receivers: vec![],
-                    input_data_ids: vec![],
-                    actions: vec![Action::Transfer(TransferAction { deposit: refund })],
-                }),
-                priority,
-            }),
-            ReceiptPriority::NoPriority => Receipt::V0(ReceiptV0 {
-                predecessor_id: "system".parse().unwrap(),
-                receiver_id: receiver_id.clone(),
-                receipt_id: CryptoHash::default(),
-
-                receipt: ReceiptEnum::Action(ActionReceipt {
-                    signer_id: "system".parse().unwrap(),
-                    signer_public_key: PublicKey::empty(KeyType::ED25519),
-                    gas_price: 0,
-                    output_data_receivers: vec![],
-                    input_data_ids: vec![],
-                    actions: vec![Action::Transfer(TransferAction { deposit: refund })],
-                }),
+        Receipt::new(
+            "system".parse().unwrap(),
+            receiver_id.clone(),
+            CryptoHash::default(),
+            ReceiptEnum::Action(ActionReceipt {
+                signer_id: "system".parse().unwrap(),
+                signer_public_key: PublicKey::empty(KeyType::ED25519),
+                gas_price: 0,
+                output_data_receivers: vec![],
+                input_data_ids: vec![],
+                actions: vec![Action::Transfer(TransferAction { deposit: refund })],
             }),
-        }
+            priority,
+        )
     }
 
     /// Generates a receipt with a transfer action from system for a given balance without a
@@ -309,37 +356,20 @@ impl Receipt {
         signer_public_key: PublicKey,
         priority: ReceiptPriority,
     ) -> Self {
-        match priority {
-            ReceiptPriority::Priority(priority) => Receipt::V1(ReceiptV1 {
-                predecessor_id: "system".parse().unwrap(),
-                receiver_id: receiver_id.clone(),
-                receipt_id: CryptoHash::default(),
-
-                receipt: ReceiptEnum::Action(ActionReceipt {
-                    signer_id: receiver_id.clone(),
-                    signer_public_key,
-                    gas_price: 0,
-                    output_data_receivers: vec![],
-                    input_data_ids: vec![],
-                    actions: vec![Action::Transfer(TransferAction { deposit: refund })],
-                }),
-                priority,
-            }),
-            ReceiptPriority::NoPriority => Receipt::V0(ReceiptV0 {
-                predecessor_id: "system".parse().unwrap(),
-                receiver_id: receiver_id.clone(),
-                receipt_id: CryptoHash::default(),
-
-                receipt: ReceiptEnum::Action(ActionReceipt {
-                    signer_id: receiver_id.clone(),
-                    signer_public_key,
-                    gas_price: 0,
-                    output_data_receivers: vec![],
-                    input_data_ids: vec![],
-                    actions: vec![Action::Transfer(TransferAction { deposit: refund })],
-                }),
+        Receipt::new(
+            "system".parse().unwrap(),
+            receiver_id.clone(),
+            CryptoHash::default(),
+            ReceiptEnum::Action(ActionReceipt {
+                signer_id: receiver_id.clone(),
+                signer_public_key,
+                gas_price: 0,
+                output_data_receivers: vec![],
+                input_data_ids: vec![],
+                actions: vec![Action::Transfer(TransferAction { deposit: refund })],
             }),
-        }
+            priority,
+        )
     }
 }
 
@@ -359,6 +389,71 @@ pub enum ReceiptEnum {
     Data(DataReceipt),
     PromiseYield(ActionReceipt),
     PromiseResume(DataReceipt),
+    GlobalContractDistribution(GlobalContractDistributionReceipt),
+    RuntimeConfigOverridesDistribution(RuntimeConfigOverridesDistributionReceipt),
+}
+
+/// Carries the code of a global contract from shard to shard until it is
+/// stored in every shard of the layout it was sent in.
+#[serde_as]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Hash,
+    PartialEq,
+    Eq,
+    Clone,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct GlobalContractDistributionReceipt {
+    #[serde_as(as = "Base64")]
+    pub code: Vec<u8>,
+    /// Version of the shard layout in which `target_shard` and
+    /// `already_delivered_shards` are defined.
+    pub shard_layout_version: ShardVersion,
+    /// The shard this receipt has to be applied in.
+    pub target_shard: ShardId,
+    /// Shards which already store the code.
+    pub already_delivered_shards: Vec<ShardId>,
+}
+
+impl fmt::Debug for GlobalContractDistributionReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GlobalContractDistributionReceipt")
+            .field("code", &format_args!("{}", AbbrBytes(Some(self.code.as_slice()))))
+            .field("shard_layout_version", &self.shard_layout_version)
+            .field("target_shard", &self.target_shard)
+            .field("already_delivered_shards", &self.already_delivered_shards)
+            .finish()
+    }
+}
+
+/// Carries the runtime config overrides read from the protocol treasury
+/// account from shard to shard until every shard of the layout it was sent in
+/// stores them.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Hash,
+    PartialEq,
+    Eq,
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct RuntimeConfigOverridesDistributionReceipt {
+    pub overrides: RuntimeConfigOverrides,
+    /// Height of the epoch from which the overrides apply.
+    pub from_epoch_height: EpochHeight,
+    /// Version of the shard layout in which `target_shard` and
+    /// `already_delivered_shards` are defined.
+    pub shard_layout_version: ShardVersion,
+    /// The shard this receipt has to be applied in.
+    pub target_shard: ShardId,
+    /// Shards which already store the overrides.
+    pub already_delivered_shards: Vec<ShardId>,
 }
 
 /// ActionReceipt is derived from an Action from `Transaction or from Receipt`
@@ -519,6 +614,34 @@ pub struct BufferedReceiptIndices {
     pub shard_buffers: BTreeMap<ShardId, TrieQueueIndices>,
 }
 
+/// Stores indices for the persistent queues of delayed receipts with a
+/// priority, one queue per lane.
+///
+/// The lane of a receipt is the bit length of its priority, so that the lanes
+/// order the receipts by the magnitude of their priority fee. Receipts in
+/// higher lanes are processed first, and receipts within a lane in the order
+/// they were delayed. There are at most 64 lanes and they are never removed,
+/// so the indices of each queue keep growing.
+///
+/// This is the singleton value stored in the `PRIORITY_DELAYED_RECEIPT_OR_INDICES`
+/// trie column.
+#[derive(Default, BorshSerialize, BorshDeserialize, Clone, PartialEq, Debug)]
+pub struct PriorityDelayedReceiptIndices {
+    pub lanes: BTreeMap<u8, TrieQueueIndices>,
+}
+
+impl PriorityDelayedReceiptIndices {
+    /// Returns the lane of the receipts with the given priority.
+    pub fn lane(priority: u64) -> u8 {
+        (u64::BITS - priority.leading_zeros()) as u8
+    }
+
+    /// Returns the number of receipts in all the lanes.
+    pub fn len(&self) -> u64 {
+        self.lanes.values().map(TrieQueueIndices::len).sum()
+    }
+}
+
 /// Map of shard to list of receipts to send to it.
 pub type ReceiptResult = HashMap<ShardId, Vec<Receipt>>;
 
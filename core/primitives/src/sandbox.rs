@@ -1,6 +1,73 @@
+use crate::types::{Balance, Gas};
+use near_parameters::RuntimeConfig;
+
+/// Runtime config parameters overridden via sandbox-only state patching
+/// feature. Parameters that are not set keep the value defined by the protocol
+/// version.
+#[derive(
+    borsh::BorshSerialize,
+    borsh::BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+)]
+pub struct SandboxRuntimeConfigPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_amount_per_byte: Option<Balance>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gas_burnt: Option<Gas>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_prepaid_gas: Option<Gas>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_contract_size: Option<u64>,
+}
+
+impl SandboxRuntimeConfigPatch {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Overrides the parameters with the ones set in `other`.
+    pub fn merge(&mut self, other: SandboxRuntimeConfigPatch) {
+        let SandboxRuntimeConfigPatch {
+            storage_amount_per_byte,
+            max_gas_burnt,
+            max_total_prepaid_gas,
+            max_contract_size,
+        } = other;
+        self.storage_amount_per_byte = storage_amount_per_byte.or(self.storage_amount_per_byte);
+        self.max_gas_burnt = max_gas_burnt.or(self.max_gas_burnt);
+        self.max_total_prepaid_gas = max_total_prepaid_gas.or(self.max_total_prepaid_gas);
+        self.max_contract_size = max_contract_size.or(self.max_contract_size);
+    }
+
+    pub fn apply(&self, config: &mut RuntimeConfig) {
+        if let Some(value) = self.storage_amount_per_byte {
+            config.fees.storage_usage_config.storage_amount_per_byte = value;
+        }
+        let limit_config = &mut config.wasm_config.limit_config;
+        if let Some(value) = self.max_gas_burnt {
+            limit_config.max_gas_burnt = value;
+        }
+        if let Some(value) = self.max_total_prepaid_gas {
+            limit_config.max_total_prepaid_gas = value;
+        }
+        if let Some(value) = self.max_contract_size {
+            limit_config.max_contract_size = value;
+        }
+    }
+}
+
 #[cfg(feature = "sandbox")]
 pub mod state_patch {
+    use super::SandboxRuntimeConfigPatch;
     use crate::state_record::StateRecord;
+    use crate::types::BlockHeight;
+    use std::collections::BTreeMap;
 
     /// Changes to the state to be applied via sandbox-only state patching
     /// feature.
@@ -9,30 +76,74 @@ pub mod state_patch {
     /// object can be non-empty only if `sandbox` feature is enabled.  On
     /// non-sandbox build, this struct is ZST and its methods are essentially
     /// short-circuited by treating the type as always empty.
-    #[derive(Default)]
+    #[derive(Default, Debug)]
     pub struct SandboxStatePatch {
         records: Vec<StateRecord>,
+        runtime_config: Option<SandboxRuntimeConfigPatch>,
+        /// Patches to be applied with the first block at or above the height.
+        /// They are not considered by [`Self::is_empty`] until they are due.
+        scheduled: BTreeMap<BlockHeight, SandboxStatePatch>,
     }
 
     impl SandboxStatePatch {
         pub fn new(records: Vec<StateRecord>) -> SandboxStatePatch {
-            SandboxStatePatch { records }
+            SandboxStatePatch { records, ..Default::default() }
+        }
+
+        /// Also overrides the runtime config parameters, starting with the
+        /// block the patch is applied with.
+        pub fn with_runtime_config(mut self, runtime_config: SandboxRuntimeConfigPatch) -> Self {
+            self.runtime_config = Some(runtime_config);
+            self
+        }
+
+        /// Postpones the patch until the block at the given height.
+        pub fn at_height(self, height: BlockHeight) -> Self {
+            Self { scheduled: BTreeMap::from([(height, self)]), ..Default::default() }
         }
 
         pub fn is_empty(&self) -> bool {
-            self.records.is_empty()
+            self.records.is_empty() && self.runtime_config.is_none()
+        }
+
+        pub fn runtime_config(&self) -> Option<&SandboxRuntimeConfigPatch> {
+            self.runtime_config.as_ref()
         }
 
+        /// Drops the changes that are due, keeping the scheduled ones.
         pub fn clear(&mut self) {
             self.records.clear();
+            self.runtime_config = None;
         }
 
         pub fn take(&mut self) -> SandboxStatePatch {
-            Self { records: core::mem::take(&mut self.records) }
+            Self {
+                records: core::mem::take(&mut self.records),
+                runtime_config: self.runtime_config.take(),
+                scheduled: BTreeMap::new(),
+            }
+        }
+
+        /// Takes the changes to apply with the block at the given height,
+        /// including the scheduled patches which are due.
+        pub fn take_for_height(&mut self, height: BlockHeight) -> SandboxStatePatch {
+            let later = self.scheduled.split_off(&height.saturating_add(1));
+            let due = core::mem::replace(&mut self.scheduled, later);
+            let mut patch = self.take();
+            for (_, scheduled) in due {
+                patch.merge(scheduled);
+            }
+            patch
         }
 
         pub fn merge(&mut self, other: SandboxStatePatch) {
             self.records.extend(other.records);
+            if let Some(runtime_config) = other.runtime_config {
+                self.runtime_config.get_or_insert_with(Default::default).merge(runtime_config);
+            }
+            for (height, scheduled) in other.scheduled {
+                self.scheduled.entry(height).or_default().merge(scheduled);
+            }
         }
     }
 
@@ -44,11 +155,57 @@ pub mod state_patch {
             self.records.into_iter()
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::account::Account;
+        use crate::hash::CryptoHash;
+        use crate::version::PROTOCOL_VERSION;
+
+        fn account_record(amount: u128) -> StateRecord {
+            StateRecord::Account {
+                account_id: "alice.near".parse().unwrap(),
+                account: Account::new(amount, 0, 0, CryptoHash::default(), 0, PROTOCOL_VERSION),
+            }
+        }
+
+        #[test]
+        fn test_take_for_height() {
+            let mut pending = SandboxStatePatch::default();
+            pending.merge(SandboxStatePatch::new(vec![account_record(1)]));
+            pending.merge(SandboxStatePatch::new(vec![account_record(2)]).at_height(5));
+            pending.merge(
+                SandboxStatePatch::default()
+                    .with_runtime_config(SandboxRuntimeConfigPatch {
+                        max_gas_burnt: Some(10),
+                        ..Default::default()
+                    })
+                    .at_height(7),
+            );
+            assert!(!pending.is_empty());
+
+            let patch = pending.take_for_height(3);
+            assert_eq!(patch.into_iter().collect::<Vec<_>>(), vec![account_record(1)]);
+            assert!(pending.is_empty());
+
+            // Blocks may skip heights, scheduled patches are applied with the
+            // first block at or above their height.
+            let patch = pending.take_for_height(6);
+            assert!(patch.runtime_config().is_none());
+            assert_eq!(patch.into_iter().collect::<Vec<_>>(), vec![account_record(2)]);
+
+            let patch = pending.take_for_height(7);
+            assert_eq!(patch.runtime_config().unwrap().max_gas_burnt, Some(10));
+            assert!(pending.take_for_height(100).is_empty());
+        }
+    }
 }
 
 #[cfg(not(feature = "sandbox"))]
 pub mod state_patch {
     use crate::state_record::StateRecord;
+    use crate::types::BlockHeight;
 
     #[derive(Default)]
     pub struct SandboxStatePatch;
@@ -65,6 +222,10 @@ pub mod state_patch {
             Self
         }
         #[inline(always)]
+        pub fn take_for_height(&mut self, _height: BlockHeight) -> Self {
+            Self
+        }
+        #[inline(always)]
         pub fn merge(&self, _other: SandboxStatePatch) {}
     }
 
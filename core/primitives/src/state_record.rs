@@ -103,6 +103,18 @@ impl StateRecord {
                 let receipt = Receipt::try_from_slice(&value)?;
                 Some(StateRecord::DelayedReceipt(Box::new(receipt)))
             }
+            col::PRIORITY_DELAYED_RECEIPT_OR_INDICES
+                if key.len() == TrieKey::PriorityDelayedReceiptIndices.len() =>
+            {
+                None
+            }
+            col::PRIORITY_DELAYED_RECEIPT_OR_INDICES => {
+                let receipt = Receipt::try_from_slice(&value)?;
+                Some(StateRecord::DelayedReceipt(Box::new(receipt)))
+            }
+            col::GLOBAL_CONTRACT_CODE => None,
+            col::PROMISE_YIELD_EXPIRY => None,
+            col::RUNTIME_CONFIG_OVERRIDES => None,
             _ => {
                 println!("key[0]: {} is unreachable", key[0]);
                 None
@@ -5,7 +5,7 @@ use crate::sharding::{
 };
 use crate::types::{BlockHeight, EpochId, ShardId, StateRoot, StateRootNode};
 use borsh::{BorshDeserialize, BorshSerialize};
-use near_primitives_core::types::EpochHeight;
+use near_primitives_core::types::{EpochHeight, ProtocolVersion};
 use std::sync::Arc;
 
 #[derive(PartialEq, Eq, Clone, Debug, BorshSerialize, BorshDeserialize)]
@@ -280,9 +280,82 @@ pub enum StateSyncDumpProgress {
     },
 }
 
+/// Compression applied to state part files in external storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum StatePartCompression {
+    /// Files contain borsh-serialized state parts as is.
+    #[default]
+    None,
+    /// Files contain zstd-compressed borsh-serialized state parts.
+    Zstd,
+}
+
+/// Describes a single state part listed in a `StatePartsManifest`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StatePartManifestEntry {
+    /// Size of the uncompressed state part in bytes.
+    pub size: u64,
+    /// Hash of the uncompressed state part.
+    pub hash: CryptoHash,
+}
+
+/// Index of all state parts of a shard dumped to external storage for one epoch.
+/// Allows the downloading node to verify each part before applying it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StatePartsManifest {
+    /// State root that the parts correspond to.
+    pub state_root: StateRoot,
+    /// Protocol version of the epoch the parts were dumped for.
+    pub protocol_version: ProtocolVersion,
+    /// Compression of the part files.
+    pub compression: StatePartCompression,
+    /// Entries ordered by part id.
+    pub parts: Vec<StatePartManifestEntry>,
+}
+
+impl StatePartsManifest {
+    pub fn num_parts(&self) -> u64 {
+        self.parts.len() as u64
+    }
+
+    /// Total size of the uncompressed state parts in bytes.
+    pub fn total_size(&self) -> u64 {
+        self.parts.iter().map(|part| part.size).sum()
+    }
+
+    /// Checks that the given uncompressed part data matches the manifest entry.
+    pub fn verify_part(&self, part_id: u64, data: &[u8]) -> Result<(), String> {
+        let Some(entry) = self.parts.get(part_id as usize) else {
+            return Err(format!(
+                "part_id {part_id} is out of range, manifest lists {} parts",
+                self.parts.len()
+            ));
+        };
+        if entry.size != data.len() as u64 {
+            return Err(format!(
+                "part {part_id} size mismatch: expected {} bytes, got {}",
+                entry.size,
+                data.len()
+            ));
+        }
+        let hash = CryptoHash::hash_bytes(data);
+        if entry.hash != hash {
+            return Err(format!(
+                "part {part_id} hash mismatch: expected {}, got {hash}",
+                entry.hash
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::state_sync::{get_num_state_parts, STATE_PART_MEMORY_LIMIT};
+    use crate::hash::CryptoHash;
+    use crate::state_sync::{
+        get_num_state_parts, StatePartCompression, StatePartManifestEntry, StatePartsManifest,
+        STATE_PART_MEMORY_LIMIT,
+    };
 
     #[test]
     fn test_get_num_state_parts() {
@@ -293,4 +366,31 @@ mod tests {
         assert_eq!(get_num_state_parts(STATE_PART_MEMORY_LIMIT.as_u64() * 100), 100);
         assert_eq!(get_num_state_parts(STATE_PART_MEMORY_LIMIT.as_u64() * 100 + 1), 101);
     }
+
+    #[test]
+    fn test_manifest_verify_part() {
+        let parts: Vec<Vec<u8>> = vec![vec![0, 1, 2], vec![3, 4]];
+        let manifest = StatePartsManifest {
+            state_root: CryptoHash::default(),
+            protocol_version: 1,
+            compression: StatePartCompression::Zstd,
+            parts: parts
+                .iter()
+                .map(|part| StatePartManifestEntry {
+                    size: part.len() as u64,
+                    hash: CryptoHash::hash_bytes(part),
+                })
+                .collect(),
+        };
+        assert_eq!(manifest.num_parts(), 2);
+        assert_eq!(manifest.total_size(), 5);
+        assert!(manifest.verify_part(0, &parts[0]).is_ok());
+        assert!(manifest.verify_part(1, &parts[1]).is_ok());
+        assert!(manifest.verify_part(0, &parts[1]).is_err());
+        assert!(manifest.verify_part(1, &[3, 5]).is_err());
+        assert!(manifest.verify_part(2, &parts[0]).is_err());
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert_eq!(serde_json::from_str::<StatePartsManifest>(&json).unwrap(), manifest);
+    }
 }
@@ -1,5 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
+use std::io::Read;
+use std::sync::Arc;
 
 use crate::challenge::PartialState;
 use crate::congestion_info::CongestionInfo;
@@ -131,6 +133,72 @@ impl PartialEncodedStateWitnessInner {
     }
 }
 
+/// How a state witness is split into zstd frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WitnessCompressionMode {
+    /// The borsh-serialized witness is compressed as a single frame.
+    #[default]
+    WholeWitness,
+    /// Every trie node and value in the partial states of the witness is
+    /// compressed as a separate frame, and the rest of the witness as another
+    /// one. Meant for dictionaries trained on the values of the witnesses.
+    PerValue,
+}
+
+/// Prefix of the witnesses encoded with `WitnessCompressionMode::PerValue`.
+/// It's the header of an empty zstd skippable frame, so it can't be confused
+/// with the start of a witness compressed as a whole.
+const PER_VALUE_MAGIC: [u8; 8] = [0x5E, 0x2A, 0x4D, 0x18, 0, 0, 0, 0];
+
+/// Witness encoded with `WitnessCompressionMode::PerValue`, after the prefix.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct PerValueEncodedWitness {
+    /// The values of the partial states of the witness, compressed one by one
+    /// in the order of `ChunkStateWitness::partial_states_mut`.
+    values: Vec<Box<[u8]>>,
+    /// The rest of the witness, with the values replaced by empty ones,
+    /// compressed as a whole.
+    rest: Box<[u8]>,
+}
+
+/// zstd compression used to encode and decode state witnesses.
+#[derive(Clone, Debug)]
+pub struct StateWitnessCompression {
+    /// zstd compression level of the encoded witnesses.
+    pub level: i32,
+    /// How the encoded witnesses are split into zstd frames. Witnesses are
+    /// decoded in the mode they were encoded in, regardless of this setting.
+    pub mode: WitnessCompressionMode,
+    /// zstd dictionary. Witnesses compressed with a dictionary can only be
+    /// decoded with the same dictionary, while witnesses compressed without one
+    /// can be decoded either way.
+    pub dictionary: Option<Arc<[u8]>>,
+    /// Whether to compress with the dictionary, or only use it for decoding.
+    /// Allows to roll out a dictionary to all chunk validators before any
+    /// chunk producer starts to use it.
+    pub compress_with_dictionary: bool,
+}
+
+impl StateWitnessCompression {
+    pub const DEFAULT_LEVEL: i32 = 3;
+
+    fn encoder_dictionary(&self) -> Option<&[u8]> {
+        self.dictionary.as_deref().filter(|_| self.compress_with_dictionary)
+    }
+}
+
+impl Default for StateWitnessCompression {
+    fn default() -> Self {
+        Self {
+            level: Self::DEFAULT_LEVEL,
+            mode: WitnessCompressionMode::default(),
+            dictionary: None,
+            compress_with_dictionary: false,
+        }
+    }
+}
+
 /// Represents bytes of encoded ChunkStateWitness.
 /// This is the compressed version of borsh-serialized state witness.
 #[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
@@ -144,17 +212,41 @@ impl EncodedChunkStateWitness {
         Self(data)
     }
 
-    /// Borsh-serialize and compress state witness.
+    /// Borsh-serialize and compress state witness with the default compression.
     /// Returns encoded witness along with the raw (uncompressed) witness size.
     pub fn encode(witness: &ChunkStateWitness) -> std::io::Result<(Self, ChunkStateWitnessSize)> {
-        const STATE_WITNESS_COMPRESSION_LEVEL: i32 = 3;
+        Self::encode_with(witness, &StateWitnessCompression::default())
+    }
+
+    /// Borsh-serialize and compress state witness with the given compression.
+    /// Returns encoded witness along with the raw (uncompressed) witness size.
+    pub fn encode_with(
+        witness: &ChunkStateWitness,
+        compression: &StateWitnessCompression,
+    ) -> std::io::Result<(Self, ChunkStateWitnessSize)> {
+        match compression.mode {
+            WitnessCompressionMode::WholeWitness => {
+                Self::encode_whole_witness(witness, compression)
+            }
+            WitnessCompressionMode::PerValue => Self::encode_per_value(witness, compression),
+        }
+    }
 
+    fn encode_whole_witness(
+        witness: &ChunkStateWitness,
+        compression: &StateWitnessCompression,
+    ) -> std::io::Result<(Self, ChunkStateWitnessSize)> {
         // Flow of data: State witness --> Borsh serialization --> Counting write --> zstd compression --> Bytes.
         // CountingWrite will count the number of bytes for the Borsh-serialized witness, before compression.
-        let mut counting_write = CountingWrite::new(zstd::stream::Encoder::new(
-            Vec::new().writer(),
-            STATE_WITNESS_COMPRESSION_LEVEL,
-        )?);
+        let encoder = match compression.encoder_dictionary() {
+            Some(dictionary) => zstd::stream::Encoder::with_dictionary(
+                Vec::new().writer(),
+                compression.level,
+                dictionary,
+            )?,
+            None => zstd::stream::Encoder::new(Vec::new().writer(), compression.level)?,
+        };
+        let mut counting_write = CountingWrite::new(encoder);
         borsh::to_writer(&mut counting_write, witness)?;
 
         let borsh_bytes_len = counting_write.bytes_written();
@@ -163,14 +255,50 @@ impl EncodedChunkStateWitness {
         Ok((Self(encoded_bytes.into()), borsh_bytes_len.as_u64() as usize))
     }
 
+    fn encode_per_value(
+        witness: &ChunkStateWitness,
+        compression: &StateWitnessCompression,
+    ) -> std::io::Result<(Self, ChunkStateWitnessSize)> {
+        let mut compressor = match compression.encoder_dictionary() {
+            Some(dictionary) => {
+                zstd::bulk::Compressor::with_dictionary(compression.level, dictionary)?
+            }
+            None => zstd::bulk::Compressor::new(compression.level)?,
+        };
+        let mut rest = witness.clone();
+        let mut values = vec![];
+        let mut values_size = 0;
+        for PartialState::TrieValues(partial_state_values) in rest.partial_states_mut() {
+            for value in partial_state_values.iter_mut() {
+                values_size += value.len();
+                values.push(compressor.compress(&value[..])?.into_boxed_slice());
+                *value = Arc::from([]);
+            }
+        }
+        let (rest, rest_size) = Self::encode_whole_witness(&rest, compression)?;
+        let mut encoded_bytes = PER_VALUE_MAGIC.to_vec();
+        borsh::to_writer(&mut encoded_bytes, &PerValueEncodedWitness { values, rest: rest.0 })?;
+        Ok((Self(encoded_bytes.into()), rest_size + values_size))
+    }
+
     /// Decompress and borsh-deserialize encoded witness bytes.
     /// Returns decoded witness along with the raw (uncompressed) witness size.
     pub fn decode(&self) -> std::io::Result<(ChunkStateWitness, ChunkStateWitnessSize)> {
+        self.decode_with(&StateWitnessCompression::default())
+    }
+
+    /// Decompress and borsh-deserialize encoded witness bytes, using the dictionary
+    /// of the given compression if there is one.
+    /// Returns decoded witness along with the raw (uncompressed) witness size.
+    pub fn decode_with(
+        &self,
+        compression: &StateWitnessCompression,
+    ) -> std::io::Result<(ChunkStateWitness, ChunkStateWitnessSize)> {
         // We want to limit the size of decompressed data to address "Zip bomb" attack.
         // The value here is the same as NETWORK_MESSAGE_MAX_SIZE_BYTES.
         const MAX_WITNESS_SIZE: ByteSize = ByteSize::mib(512);
 
-        self.decode_with_limit(MAX_WITNESS_SIZE)
+        self.decode_with_limit_and_compression(MAX_WITNESS_SIZE, compression)
     }
 
     /// Decompress and borsh-deserialize encoded witness bytes.
@@ -178,13 +306,36 @@ impl EncodedChunkStateWitness {
     pub fn decode_with_limit(
         &self,
         limit: ByteSize,
+    ) -> std::io::Result<(ChunkStateWitness, ChunkStateWitnessSize)> {
+        self.decode_with_limit_and_compression(limit, &StateWitnessCompression::default())
+    }
+
+    fn decode_with_limit_and_compression(
+        &self,
+        limit: ByteSize,
+        compression: &StateWitnessCompression,
+    ) -> std::io::Result<(ChunkStateWitness, ChunkStateWitnessSize)> {
+        match self.0.strip_prefix(&PER_VALUE_MAGIC) {
+            Some(body) => Self::decode_per_value(body, limit, compression),
+            None => self.decode_whole_witness(limit, compression),
+        }
+    }
+
+    fn decode_whole_witness(
+        &self,
+        limit: ByteSize,
+        compression: &StateWitnessCompression,
     ) -> std::io::Result<(ChunkStateWitness, ChunkStateWitnessSize)> {
         // Flow of data: Bytes --> zstd decompression --> Counting read --> Borsh deserialization --> State witness.
         // CountingRead will count the number of bytes for the Borsh-deserialized witness, after decompression.
-        let mut counting_read = CountingRead::new_with_limit(
-            zstd::stream::Decoder::new(self.0.as_ref().reader())?,
-            limit,
-        );
+        // Frames compressed without a dictionary decode the same way with one.
+        let decoder = match &compression.dictionary {
+            Some(dictionary) => {
+                zstd::stream::Decoder::with_dictionary(self.0.as_ref().reader(), dictionary)?
+            }
+            None => zstd::stream::Decoder::new(self.0.as_ref().reader())?,
+        };
+        let mut counting_read = CountingRead::new_with_limit(decoder, limit);
 
         match borsh::from_reader(&mut counting_read) {
             Err(err) => {
@@ -203,6 +354,66 @@ impl EncodedChunkStateWitness {
         }
     }
 
+    fn decode_per_value(
+        body: &[u8],
+        limit: ByteSize,
+        compression: &StateWitnessCompression,
+    ) -> std::io::Result<(ChunkStateWitness, ChunkStateWitnessSize)> {
+        let PerValueEncodedWitness { values, rest } = borsh::from_slice(body)?;
+        let (mut witness, mut raw_size) = Self(rest).decode_whole_witness(limit, compression)?;
+        let dictionary = compression.dictionary.as_deref().map(zstd::dict::DecoderDictionary::copy);
+        let mut values = values.iter();
+        for PartialState::TrieValues(partial_state_values) in witness.partial_states_mut() {
+            for value in partial_state_values.iter_mut() {
+                let Some(compressed) = values.next() else {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "fewer compressed values than values in the witness",
+                    ));
+                };
+                let decoder = match &dictionary {
+                    Some(dictionary) => zstd::stream::Decoder::with_prepared_dictionary(
+                        &compressed[..],
+                        dictionary,
+                    )?,
+                    None => zstd::stream::Decoder::with_buffer(&compressed[..])?,
+                };
+                let remaining = limit.as_u64().saturating_sub(raw_size as u64);
+                let mut decompressed = vec![];
+                decoder.take(remaining + 1).read_to_end(&mut decompressed)?;
+                if decompressed.len() as u64 > remaining {
+                    return Err(std::io::Error::other(format!(
+                        "Decompressed data exceeded limit of {limit}"
+                    )));
+                }
+                raw_size += decompressed.len();
+                *value = decompressed.into();
+            }
+        }
+        if values.next().is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "more compressed values than values in the witness",
+            ));
+        }
+        Ok((witness, raw_size))
+    }
+
+    /// Returns the total compressed size of the values of a witness encoded
+    /// with `WitnessCompressionMode::PerValue`, or None for a witness
+    /// compressed as a whole.
+    pub fn compressed_values_size(&self) -> Option<usize> {
+        let mut body = self.0.strip_prefix(&PER_VALUE_MAGIC)?;
+        let num_values = u32::deserialize(&mut body).ok()?;
+        let mut size = 0;
+        for _ in 0..num_values {
+            let len = u32::deserialize(&mut body).ok()? as usize;
+            body = body.get(len..)?;
+            size += len;
+        }
+        Some(size)
+    }
+
     pub fn size_bytes(&self) -> ChunkStateWitnessSize {
         self.0.len()
     }
@@ -358,6 +569,16 @@ impl ChunkStateWitness {
         }
     }
 
+    /// Returns the partial states of the main state transition, the implicit
+    /// transitions and the validation of the new transactions, in this order.
+    pub fn partial_states_mut(&mut self) -> impl Iterator<Item = &mut PartialState> {
+        std::iter::once(&mut self.main_state_transition.base_state)
+            .chain(
+                self.implicit_transitions.iter_mut().map(|transition| &mut transition.base_state),
+            )
+            .chain(std::iter::once(&mut self.new_transactions_validation_state))
+    }
+
     pub fn new_dummy(height: BlockHeight, shard_id: ShardId, prev_block_hash: CryptoHash) -> Self {
         let header = ShardChunkHeader::V3(ShardChunkHeaderV3::new(
             PROTOCOL_VERSION,
@@ -432,6 +653,23 @@ impl ChunkEndorsement {
         self.signature.verify(&data, public_key)
     }
 
+    /// Verifies the signatures of the endorsements, each given with the public key of its
+    /// chunk validator, with a single batched verification. Returns true if all are valid.
+    pub fn verify_batch(endorsements: &[(&ChunkEndorsement, &PublicKey)]) -> bool {
+        let data: Vec<_> = endorsements
+            .iter()
+            .map(|(endorsement, _)| borsh::to_vec(&endorsement.inner).unwrap())
+            .collect();
+        let items: Vec<_> = endorsements
+            .iter()
+            .zip(&data)
+            .map(|((endorsement, public_key), data)| {
+                (data.as_slice(), &endorsement.signature, *public_key)
+            })
+            .collect();
+        Signature::verify_batch(&items)
+    }
+
     pub fn chunk_hash(&self) -> &ChunkHash {
         &self.inner.chunk_hash
     }
@@ -555,10 +793,15 @@ impl ChunkValidatorAssignments {
 
 #[cfg(test)]
 mod tests {
-    use crate::stateless_validation::{ChunkStateWitness, EncodedChunkStateWitness};
+    use crate::challenge::PartialState;
+    use crate::stateless_validation::{
+        ChunkStateWitness, EncodedChunkStateWitness, StateWitnessCompression,
+        WitnessCompressionMode,
+    };
     use bytesize::ByteSize;
     use near_primitives_core::hash::CryptoHash;
     use std::io::ErrorKind;
+    use std::sync::Arc;
 
     #[test]
     fn encode_decode_state_dummy_witness_default_limit() {
@@ -612,4 +855,98 @@ mod tests {
             .unwrap_err();
         assert_eq!(error.kind(), ErrorKind::Other);
     }
+
+    #[test]
+    fn encode_decode_state_dummy_witness_with_dictionary() {
+        let original_witness = ChunkStateWitness::new_dummy(42, 0, CryptoHash::default());
+        // Any content can be used as a raw zstd dictionary.
+        let dictionary =
+            borsh::to_vec(&ChunkStateWitness::new_dummy(41, 0, CryptoHash::default())).unwrap();
+        let compression = StateWitnessCompression {
+            level: 19,
+            dictionary: Some(dictionary.into()),
+            compress_with_dictionary: true,
+            ..Default::default()
+        };
+        let (encoded_witness, _) =
+            EncodedChunkStateWitness::encode_with(&original_witness, &compression).unwrap();
+        let (default_encoded_witness, _) =
+            EncodedChunkStateWitness::encode(&original_witness).unwrap();
+        assert!(encoded_witness.size_bytes() < default_encoded_witness.size_bytes());
+
+        let (decoded_witness, _) = encoded_witness.decode_with(&compression).unwrap();
+        assert_eq!(decoded_witness, original_witness);
+        assert!(encoded_witness.decode().is_err());
+
+        // Witnesses compressed without the dictionary can still be decoded with it.
+        let (decoded_witness, _) = default_encoded_witness.decode_with(&compression).unwrap();
+        assert_eq!(decoded_witness, original_witness);
+
+        // The dictionary is only used for decoding unless enabled for compression.
+        let decode_only =
+            StateWitnessCompression { compress_with_dictionary: false, ..compression };
+        let (encoded_witness, _) =
+            EncodedChunkStateWitness::encode_with(&original_witness, &decode_only).unwrap();
+        assert_eq!(encoded_witness.decode().unwrap().0, original_witness);
+    }
+
+    fn dummy_witness_with_values() -> ChunkStateWitness {
+        let mut witness = ChunkStateWitness::new_dummy(42, 0, CryptoHash::default());
+        let values = |prefix: &str| -> Vec<Arc<[u8]>> {
+            (0..10).map(|i| format!("{prefix} trie value {i}").into_bytes().into()).collect()
+        };
+        witness.main_state_transition.base_state = PartialState::TrieValues(values("main"));
+        witness.new_transactions_validation_state = PartialState::TrieValues(values("new"));
+        witness
+    }
+
+    #[test]
+    fn encode_decode_state_witness_per_value() {
+        let original_witness = dummy_witness_with_values();
+        // Any content can be used as a raw zstd dictionary.
+        let dictionary = b"main new trie value".to_vec();
+        let compression = StateWitnessCompression {
+            mode: WitnessCompressionMode::PerValue,
+            dictionary: Some(dictionary.into()),
+            compress_with_dictionary: true,
+            ..Default::default()
+        };
+        let (encoded_witness, raw_size_from_encode) =
+            EncodedChunkStateWitness::encode_with(&original_witness, &compression).unwrap();
+        assert_eq!(raw_size_from_encode, borsh::to_vec(&original_witness).unwrap().len());
+        let compressed_values_size = encoded_witness.compressed_values_size().unwrap();
+        assert!(
+            0 < compressed_values_size && compressed_values_size < encoded_witness.size_bytes()
+        );
+
+        let (decoded_witness, raw_size_from_decode) =
+            encoded_witness.decode_with(&compression).unwrap();
+        assert_eq!(decoded_witness, original_witness);
+        assert_eq!(raw_size_from_decode, raw_size_from_encode);
+        // The values were compressed with the dictionary.
+        assert!(encoded_witness.decode().is_err());
+
+        // Witnesses are decoded in the mode they were encoded in.
+        let (whole_witness, _) = EncodedChunkStateWitness::encode(&original_witness).unwrap();
+        assert_eq!(whole_witness.compressed_values_size(), None);
+        assert_eq!(whole_witness.decode_with(&compression).unwrap().0, original_witness);
+    }
+
+    #[test]
+    fn encode_decode_state_witness_per_value_exceeds_limit() {
+        let original_witness = dummy_witness_with_values();
+        let compression = StateWitnessCompression {
+            mode: WitnessCompressionMode::PerValue,
+            ..Default::default()
+        };
+        let (encoded_witness, raw_size) =
+            EncodedChunkStateWitness::encode_with(&original_witness, &compression).unwrap();
+        let (decoded_witness, _) =
+            encoded_witness.decode_with_limit(ByteSize::b(raw_size as u64)).unwrap();
+        assert_eq!(decoded_witness, original_witness);
+        // The rest of the witness fits in the limit, its values don't.
+        let error =
+            encoded_witness.decode_with_limit(ByteSize::b(raw_size as u64 - 1)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Other);
+    }
 }
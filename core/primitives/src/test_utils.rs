@@ -7,6 +7,7 @@ use crate::errors::EpochError;
 use crate::hash::CryptoHash;
 use crate::merkle::PartialMerkleTree;
 use crate::num_rational::Ratio;
+use crate::shard_layout::ShardLayout;
 use crate::sharding::{ShardChunkHeader, ShardChunkHeaderV3};
 use crate::transaction::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
@@ -63,6 +64,7 @@ impl Transaction {
             block_hash,
             actions: vec![],
             priority_fee,
+            valid_until_height: None,
         })
     }
 
@@ -178,6 +180,7 @@ impl SignedTransaction {
             block_hash,
             actions,
             priority_fee,
+            valid_until_height: None,
         })
         .sign(signer)
     }
@@ -706,6 +709,14 @@ impl EpochInfoProvider for MockEpochInfoProvider {
     ) -> Result<ShardId, EpochError> {
         Ok(0)
     }
+
+    fn shard_layout(&self, _epoch_id: &EpochId) -> Result<ShardLayout, EpochError> {
+        Ok(ShardLayout::v0_single_shard())
+    }
+
+    fn protocol_treasury_account(&self) -> AccountId {
+        "near".parse().unwrap()
+    }
 }
 
 /// Encode array of `u64` to be passed as a smart contract argument.
@@ -1,13 +1,13 @@
 use crate::errors::TxExecutionError;
 use crate::hash::{hash, CryptoHash};
 use crate::merkle::MerklePath;
-use crate::types::{AccountId, Balance, Gas, Nonce};
+use crate::types::{AccountId, Balance, BlockHeight, Gas, Nonce};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::{PublicKey, Signature};
 use near_fmt::{AbbrBytes, Slice};
 use near_primitives_core::serialize::{from_base64, to_base64};
 use near_primitives_core::types::Compute;
-use near_vm_runner::ProfileDataV3;
+use near_vm_runner::{ProfileDataV3, StorageAccessStats};
 use serde::de::Error as DecodeError;
 use serde::ser::Error as EncodeError;
 use std::borrow::Borrow;
@@ -19,11 +19,15 @@ use std::io::{Error, ErrorKind, Read, Write};
 pub use crate::action::NonrefundableStorageTransferAction;
 pub use crate::action::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
-    DeployContractAction, FunctionCallAction, StakeAction, TransferAction,
+    DeployContractAction, DeployGlobalContractAction, FunctionCallAction, StakeAction,
+    TransferAction, UseGlobalContractAction,
 };
 
 pub type LogEntry = String;
 
+/// The unit of [`TransactionV1::priority_fee`] in yoctoNEAR.
+pub const PRIORITY_FEE_UNIT: Balance = 1_000_000_000_000;
+
 #[derive(BorshSerialize, BorshDeserialize, serde::Serialize, PartialEq, Eq, Debug, Clone)]
 pub struct TransactionV0 {
     /// An account on which behalf transaction is signed
@@ -60,6 +64,9 @@ pub struct TransactionV1 {
     pub actions: Vec<Action>,
     /// Priority fee. Unit is 10^12 yotcoNEAR
     pub priority_fee: u64,
+    /// If set, the transaction can only be included in chunks built on top of
+    /// blocks with a lower height.
+    pub valid_until_height: Option<BlockHeight>,
 }
 
 impl Transaction {
@@ -132,6 +139,20 @@ impl Transaction {
             Transaction::V1(tx) => Some(tx.priority_fee),
         }
     }
+
+    pub fn valid_until_height(&self) -> Option<BlockHeight> {
+        match self {
+            Transaction::V0(_) => None,
+            Transaction::V1(tx) => tx.valid_until_height,
+        }
+    }
+
+    /// Whether the transaction can no longer be included in a chunk built on
+    /// top of the block with the given height.
+    pub fn is_expired(&self, prev_block_height: BlockHeight) -> bool {
+        self.valid_until_height()
+            .map_or(false, |valid_until_height| prev_block_height >= valid_until_height)
+    }
 }
 
 impl BorshSerialize for Transaction {
@@ -198,6 +219,7 @@ impl BorshDeserialize for Transaction {
             let block_hash = CryptoHash::deserialize_reader(reader)?;
             let actions = Vec::<Action>::deserialize_reader(reader)?;
             let priority_fee = u64::deserialize_reader(reader)?;
+            let valid_until_height = Option::<BlockHeight>::deserialize_reader(reader)?;
             Ok(Transaction::V1(TransactionV1 {
                 signer_id,
                 public_key,
@@ -206,6 +228,7 @@ impl BorshDeserialize for Transaction {
                 block_hash,
                 actions,
                 priority_fee,
+                valid_until_height,
             }))
         }
     }
@@ -404,6 +427,8 @@ pub enum ExecutionMetadata {
     V2(crate::profile_data_v2::ProfileDataV2),
     /// V3: With ProfileData by gas parameters
     V3(Box<ProfileDataV3>),
+    /// V4: V3 with counts of the storage operations made by contracts
+    V4(Box<ProfileDataV3>, StorageAccessStats),
 }
 
 impl fmt::Debug for ExecutionOutcome {
@@ -582,6 +607,7 @@ mod tests {
                 }),
             ],
             priority_fee: 1,
+            valid_until_height: Some(100),
         }
     }
 
@@ -613,6 +639,21 @@ mod tests {
         assert_eq!(transaction_v1, deserialized_tx_v1);
     }
 
+    #[test]
+    fn test_transaction_expiry() {
+        let transaction = Transaction::V0(create_transaction_v0());
+        assert!(!transaction.is_expired(u64::MAX));
+
+        let mut transaction_v1 = create_transaction_v1();
+        transaction_v1.valid_until_height = None;
+        assert!(!Transaction::V1(transaction_v1.clone()).is_expired(u64::MAX));
+
+        transaction_v1.valid_until_height = Some(100);
+        let transaction = Transaction::V1(transaction_v1);
+        assert!(!transaction.is_expired(99));
+        assert!(transaction.is_expired(100));
+    }
+
     #[test]
     fn test_outcome_to_hashes() {
         let outcome = ExecutionOutcome {
@@ -55,12 +55,31 @@ pub mod col {
     /// backpressure on the receiving shard.
     /// (`primitives::receipt::Receipt`).
     pub const BUFFERED_RECEIPT: u8 = 14;
-    /// All columns except those used for the delayed receipts queue, the yielded promises
-    /// queue, and the outgoing receipts buffer, which are global state for the shard.
-
-    // NOTE: NEW_COLUMN = 15 will be the last unique nibble in the trie!
-    // Consider demultiplexing on 15 and using 2-nibble prefixes.
-    pub const COLUMNS_WITH_ACCOUNT_ID_IN_KEY: [(u8, &str); 9] = [
+    /// This column id is used when storing:
+    /// * the indices of the priority delayed receipts queue (a singleton per shard)
+    /// * the delayed receipts with a priority themselves
+    /// Like `DELAYED_RECEIPT_OR_INDICES`, the identifier is shared between two key types
+    /// which are told apart by their length, to save the last unique nibble.
+    pub const PRIORITY_DELAYED_RECEIPT_OR_INDICES: u8 = 15;
+    /// This column id is used when storing contract code deployed globally, keyed by the hash
+    /// of the code.
+    pub const GLOBAL_CONTRACT_CODE: u8 = 16;
+    /// This column id is used when storing the block height at which a pending PromiseYield
+    /// times out, keyed by the receiver and the `data_id` of the yield.
+    pub const PROMISE_YIELD_EXPIRY: u8 = 17;
+    /// This column id is used when storing the runtime config overrides which the shard
+    /// received from the protocol treasury account. A singleton per shard.
+    pub const RUNTIME_CONFIG_OVERRIDES: u8 = 18;
+    /// All columns except those used for the delayed receipts queues, the yielded promises
+    /// queue, the outgoing receipts buffer, the global contracts and the runtime config
+    /// overrides, which are global state for the shard.
+
+    // NOTE: Columns 0 to 15 start with the zero nibble, so there are no unique
+    // second nibbles left for them. Columns from 16 on start with the nibble 1
+    // and are told apart by their second nibble: GLOBAL_CONTRACT_CODE = 16,
+    // PROMISE_YIELD_EXPIRY = 17 and RUNTIME_CONFIG_OVERRIDES = 18. The next
+    // new column should be 19.
+    pub const COLUMNS_WITH_ACCOUNT_ID_IN_KEY: [(u8, &str); 10] = [
         (ACCOUNT, "Account"),
         (CONTRACT_CODE, "ContractCode"),
         (ACCESS_KEY, "AccessKey"),
@@ -70,6 +89,7 @@ pub mod col {
         (POSTPONED_RECEIPT, "PostponedReceipt"),
         (CONTRACT_DATA, "ContractData"),
         (PROMISE_YIELD_RECEIPT, "PromiseYieldReceipt"),
+        (PROMISE_YIELD_EXPIRY, "PromiseYieldExpiry"),
     ];
 }
 
@@ -125,6 +145,23 @@ pub enum TrieKey {
     /// per ordered shard pair. The trie for shard X stores all queues for pairs
     /// (X,*) without (X,X).
     BufferedReceipt { receiving_shard: ShardId, index: u64 },
+    /// Used to store indices of the priority delayed receipts queue.
+    /// NOTE: It is a singleton per shard.
+    PriorityDelayedReceiptIndices,
+    /// Used to store a delayed receipt with a priority `primitives::receipt::Receipt` for a
+    /// given index `u64` in the queue of the lane `u8` of its priority. The queues are unique
+    /// per shard.
+    PriorityDelayedReceipt { lane: u8, index: u64 },
+    /// Used to store `Vec<u8>` contract code deployed globally for a given hash of the code.
+    /// The code can be used by any account of the shard.
+    GlobalContractCode { code_hash: CryptoHash },
+    /// Used to store the block height `BlockHeight` at which the pending promise yield
+    /// for a given receiver's `AccountId` and a given `data_id` times out.
+    PromiseYieldExpiry { receiver_id: AccountId, data_id: CryptoHash },
+    /// Used to store the `near_parameters::RuntimeConfigOverridesRecord` the chunks of the
+    /// shard are applied with.
+    /// NOTE: It is a singleton per shard.
+    RuntimeConfigOverrides,
 }
 
 /// Provides `len` function.
@@ -200,6 +237,24 @@ impl TrieKey {
                     + std::mem::size_of::<u16>()
                     + std::mem::size_of_val(index)
             }
+            TrieKey::PriorityDelayedReceiptIndices => {
+                col::PRIORITY_DELAYED_RECEIPT_OR_INDICES.len()
+            }
+            TrieKey::PriorityDelayedReceipt { lane, index } => {
+                col::PRIORITY_DELAYED_RECEIPT_OR_INDICES.len()
+                    + std::mem::size_of_val(lane)
+                    + std::mem::size_of_val(index)
+            }
+            TrieKey::GlobalContractCode { code_hash } => {
+                col::GLOBAL_CONTRACT_CODE.len() + code_hash.as_ref().len()
+            }
+            TrieKey::PromiseYieldExpiry { receiver_id, data_id } => {
+                col::PROMISE_YIELD_EXPIRY.len()
+                    + receiver_id.len()
+                    + ACCOUNT_DATA_SEPARATOR.len()
+                    + data_id.as_ref().len()
+            }
+            TrieKey::RuntimeConfigOverrides => col::RUNTIME_CONFIG_OVERRIDES.len(),
         }
     }
 
@@ -280,6 +335,25 @@ impl TrieKey {
                 buf.extend(&(*receiving_shard as u16).to_le_bytes());
                 buf.extend(&index.to_le_bytes());
             }
+            TrieKey::PriorityDelayedReceiptIndices => {
+                buf.push(col::PRIORITY_DELAYED_RECEIPT_OR_INDICES);
+            }
+            TrieKey::PriorityDelayedReceipt { lane, index } => {
+                buf.push(col::PRIORITY_DELAYED_RECEIPT_OR_INDICES);
+                buf.push(*lane);
+                buf.extend(&index.to_le_bytes());
+            }
+            TrieKey::GlobalContractCode { code_hash } => {
+                buf.push(col::GLOBAL_CONTRACT_CODE);
+                buf.extend(code_hash.as_ref());
+            }
+            TrieKey::PromiseYieldExpiry { receiver_id, data_id } => {
+                buf.push(col::PROMISE_YIELD_EXPIRY);
+                buf.extend(receiver_id.as_bytes());
+                buf.push(ACCOUNT_DATA_SEPARATOR);
+                buf.extend(data_id.as_ref());
+            }
+            TrieKey::RuntimeConfigOverrides => buf.push(col::RUNTIME_CONFIG_OVERRIDES),
         };
         debug_assert_eq!(expected_len, buf.len() - start_len);
     }
@@ -308,6 +382,11 @@ impl TrieKey {
             TrieKey::PromiseYieldReceipt { receiver_id, .. } => Some(receiver_id.clone()),
             TrieKey::BufferedReceiptIndices => None,
             TrieKey::BufferedReceipt { .. } => None,
+            TrieKey::PriorityDelayedReceiptIndices => None,
+            TrieKey::PriorityDelayedReceipt { .. } => None,
+            TrieKey::GlobalContractCode { .. } => None,
+            TrieKey::PromiseYieldExpiry { receiver_id, .. } => Some(receiver_id.clone()),
+            TrieKey::RuntimeConfigOverrides => None,
         }
     }
 }
@@ -728,6 +807,34 @@ mod tests {
         assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
     }
 
+    #[test]
+    fn test_key_for_priority_delayed_receipts_consistency() {
+        let key = TrieKey::PriorityDelayedReceiptIndices;
+        let raw_key = key.to_vec();
+        assert_eq!(raw_key.len(), key.len());
+        assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
+        let key = TrieKey::PriorityDelayedReceipt { lane: 1, index: 0 };
+        let raw_key = key.to_vec();
+        assert_eq!(raw_key.len(), key.len());
+        assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_key_for_global_contract_code_consistency() {
+        let key = TrieKey::GlobalContractCode { code_hash: CryptoHash::hash_bytes(b"code") };
+        let raw_key = key.to_vec();
+        assert_eq!(raw_key.len(), key.len());
+        assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_key_for_runtime_config_overrides_consistency() {
+        let key = TrieKey::RuntimeConfigOverrides;
+        let raw_key = key.to_vec();
+        assert_eq!(raw_key.len(), key.len());
+        assert!(trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().is_none());
+    }
+
     #[test]
     fn test_key_for_promise_yield_consistency() {
         let key = TrieKey::PromiseYieldIndices;
@@ -747,6 +854,16 @@ mod tests {
                 trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().unwrap(),
                 account_id
             );
+            let key = TrieKey::PromiseYieldExpiry {
+                receiver_id: account_id.clone(),
+                data_id: CryptoHash::default(),
+            };
+            let raw_key = key.to_vec();
+            assert_eq!(raw_key.len(), key.len());
+            assert_eq!(
+                trie_key_parsers::parse_account_id_from_raw_key(&raw_key).unwrap().unwrap(),
+                account_id
+            );
         }
     }
 
@@ -808,6 +925,12 @@ mod tests {
                 None
             );
             assert_eq!(TrieKey::DelayedReceiptIndices.get_account_id(), None);
+            assert_eq!(
+                TrieKey::PriorityDelayedReceipt { lane: 1, index: Default::default() }
+                    .get_account_id(),
+                None
+            );
+            assert_eq!(TrieKey::PriorityDelayedReceiptIndices.get_account_id(), None);
             assert_eq!(
                 TrieKey::PromiseYieldTimeout { index: Default::default() }.get_account_id(),
                 None
@@ -821,6 +944,14 @@ mod tests {
                 .get_account_id(),
                 Some(account_id.clone())
             );
+            assert_eq!(
+                TrieKey::PromiseYieldExpiry {
+                    receiver_id: account_id.clone(),
+                    data_id: CryptoHash::new(),
+                }
+                .get_account_id(),
+                Some(account_id.clone())
+            );
             assert_eq!(
                 TrieKey::ContractData { account_id: account_id.clone(), key: Default::default() }
                     .get_account_id(),
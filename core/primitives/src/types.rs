@@ -4,6 +4,7 @@ use crate::errors::EpochError;
 use crate::hash::CryptoHash;
 use crate::receipt::{PromiseYieldTimeout, Receipt};
 use crate::serialize::dec_format;
+use crate::shard_layout::ShardLayout;
 use crate::trie_key::TrieKey;
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::PublicKey;
@@ -191,6 +192,10 @@ pub enum StateChangeCause {
     Migration,
     /// State changes for building states for re-sharding
     Resharding,
+    /// State change that happens when the runtime config overrides are read from the protocol
+    /// treasury account at the start of an epoch. Not associated with any specific transaction
+    /// or receipt.
+    RuntimeConfigOverridesUpdate,
 }
 
 /// This represents the committed changes in the Trie with a change cause.
@@ -381,6 +386,11 @@ impl StateChanges {
                 TrieKey::PromiseYieldReceipt { .. } => {}
                 TrieKey::BufferedReceiptIndices => {}
                 TrieKey::BufferedReceipt { .. } => {}
+                TrieKey::PriorityDelayedReceiptIndices => {}
+                TrieKey::PriorityDelayedReceipt { .. } => {}
+                TrieKey::GlobalContractCode { .. } => {}
+                TrieKey::PromiseYieldExpiry { .. } => {}
+                TrieKey::RuntimeConfigOverrides => {}
             }
         }
 
@@ -1108,6 +1118,12 @@ pub trait EpochInfoProvider {
         account_id: &AccountId,
         epoch_id: &EpochId,
     ) -> Result<ShardId, EpochError>;
+
+    /// The shard layout of the given epoch.
+    fn shard_layout(&self, epoch_id: &EpochId) -> Result<ShardLayout, EpochError>;
+
+    /// The account which receives the protocol treasury rewards.
+    fn protocol_treasury_account(&self) -> AccountId;
 }
 
 /// Mode of the trie cache.
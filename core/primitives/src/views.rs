@@ -18,8 +18,12 @@ use crate::errors::TxExecutionError;
 use crate::hash::{hash, CryptoHash};
 use crate::merkle::{combine_hash, MerklePath};
 use crate::network::PeerId;
-use crate::receipt::{ActionReceipt, DataReceipt, DataReceiver, Receipt, ReceiptEnum, ReceiptV1};
+use crate::receipt::{
+    ActionReceipt, DataReceipt, DataReceiver, GlobalContractDistributionReceipt, Receipt,
+    ReceiptEnum, ReceiptV1, RuntimeConfigOverridesDistributionReceipt,
+};
 use crate::serialize::dec_format;
+use crate::shard_layout::ShardVersion;
 use crate::sharding::{
     ChunkHash, ShardChunk, ShardChunkHeader, ShardChunkHeaderInner, ShardChunkHeaderInnerV2,
     ShardChunkHeaderInnerV3, ShardChunkHeaderV3,
@@ -28,9 +32,10 @@ use crate::sharding::{
 use crate::transaction::NonrefundableStorageTransferAction;
 use crate::transaction::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
-    DeployContractAction, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithIdAndProof,
-    ExecutionStatus, FunctionCallAction, PartialExecutionOutcome, PartialExecutionStatus,
-    SignedTransaction, StakeAction, TransferAction,
+    DeployContractAction, DeployGlobalContractAction, ExecutionMetadata, ExecutionOutcome,
+    ExecutionOutcomeWithIdAndProof, ExecutionStatus, FunctionCallAction, PartialExecutionOutcome,
+    PartialExecutionStatus, SignedTransaction, StakeAction, TransferAction,
+    UseGlobalContractAction,
 };
 use crate::types::{
     AccountId, AccountWithPublicKey, Balance, BlockHeight, EpochHeight, EpochId, FunctionArgs, Gas,
@@ -43,8 +48,9 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use near_async::time::Utc;
 use near_crypto::{PublicKey, Signature};
 use near_fmt::{AbbrBytes, Slice};
-use near_parameters::{ActionCosts, ExtCosts};
+use near_parameters::{ActionCosts, ExtCosts, RuntimeConfigOverrides};
 use near_primitives_core::version::PROTOCOL_VERSION;
+use near_vm_runner::StorageAccessStats;
 use serde_with::base64::Base64;
 use serde_with::serde_as;
 use std::collections::HashMap;
@@ -267,6 +273,21 @@ impl FromIterator<AccessKeyInfoView> for AccessKeyList {
     }
 }
 
+/// Status of a promise yield created by `promise_yield_create`.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct PromiseYieldView {
+    /// Whether the yield is still waiting to be resumed or to time out.
+    pub pending: bool,
+    /// Height at which the pending yield times out. Not known for yields created before the
+    /// `PromiseYieldStatus` protocol feature was enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<BlockHeight>,
+    /// Number of blocks left until the pending yield times out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_blocks: Option<NumBlocks>,
+}
+
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct KnownPeerStateView {
@@ -294,6 +315,18 @@ pub struct SnapshotHostInfoView {
     pub sync_hash: CryptoHash,
     pub epoch_height: u64,
     pub shards: Vec<u64>,
+    /// Number of state parts received from the host which passed the verification.
+    #[serde(default)]
+    pub parts_served: u64,
+    /// Number of state part requests the host didn't respond to.
+    #[serde(default)]
+    pub parts_timed_out: u64,
+    /// Number of state parts received from the host which failed the verification.
+    #[serde(default)]
+    pub invalid_parts: u64,
+    /// Whether the host is banned from serving state parts for now.
+    #[serde(default)]
+    pub banned: bool,
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -305,6 +338,7 @@ pub enum QueryResponseKind {
     CallResult(CallResult),
     AccessKey(AccessKeyView),
     AccessKeyList(AccessKeyList),
+    ViewPromiseYield(PromiseYieldView),
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -336,6 +370,10 @@ pub enum QueryRequest {
         #[serde(rename = "args_base64")]
         args: FunctionArgs,
     },
+    ViewPromiseYield {
+        account_id: AccountId,
+        data_id: CryptoHash,
+    },
 }
 
 fn is_false(v: &bool) -> bool {
@@ -363,6 +401,11 @@ pub struct StatusSyncInfo {
     pub earliest_block_time: Option<Utc>,
     pub epoch_id: Option<EpochId>,
     pub epoch_start_height: Option<BlockHeight>,
+    /// Progress of the state sync of each shard, while the node syncs its
+    /// state. The catchup of the shards tracked in the next epoch is reported
+    /// by the `/debug/api/state_sync_progress` endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_sync: Option<StateSyncProgressView>,
 }
 
 // TODO: add more information to ValidatorInfo
@@ -541,6 +584,55 @@ pub struct RequestedStatePartsView {
     pub shard_requested_parts: HashMap<ShardId, Vec<PartElapsedTimeView>>,
 }
 
+/// Phase of the state sync of a single shard.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateSyncPhaseView {
+    /// Downloading the state header.
+    Header,
+    /// Downloading the state parts.
+    Parts,
+    /// Applying the downloaded state parts.
+    Apply,
+    /// Creating flat storage and loading the in-memory trie.
+    Finalize,
+    /// Splitting the state because the shard layout changes.
+    Resharding,
+    Done,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct ShardStateSyncProgressView {
+    pub shard_id: ShardId,
+    pub phase: StateSyncPhaseView,
+    /// Number of state parts downloaded and stored.
+    pub num_parts_done: u64,
+    /// Number of state parts that were stored before the node restarted and
+    /// didn't need to be downloaded again. Included in `num_parts_done`.
+    pub num_parts_resumed: u64,
+    /// Total number of state parts. Unknown until the state header is downloaded.
+    pub num_parts_total: Option<u64>,
+    /// Size of the downloaded state header and state parts.
+    pub bytes_downloaded: u64,
+    /// Average download throughput since the beginning of the state sync of the shard.
+    pub bytes_per_second: u64,
+    /// Time since the beginning of the state sync of the shard.
+    pub elapsed_secs: u64,
+    /// Estimated time until all parts are downloaded. Available only while downloading parts.
+    pub eta_secs: Option<u64>,
+    /// The most recent error, cleared only when the state sync of the shard restarts.
+    pub last_error: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct StateSyncProgressView {
+    /// First block of the epoch that the state is synced to.
+    pub sync_hash: CryptoHash,
+    /// Whether the node is catching up shards it will track in the next epoch.
+    pub catchup: bool,
+    /// Shards sorted by shard id.
+    pub shards: Vec<ShardStateSyncProgressView>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
 pub struct BlockStatusView {
     pub height: BlockHeight,
@@ -1256,6 +1348,13 @@ pub enum ActionView {
         delegate_action: DelegateAction,
         signature: Signature,
     },
+    DeployGlobalContract {
+        #[serde_as(as = "Base64")]
+        code: Vec<u8>,
+    },
+    UseGlobalContract {
+        code_hash: CryptoHash,
+    },
 }
 
 impl From<Action> for ActionView {
@@ -1292,6 +1391,13 @@ impl From<Action> for ActionView {
                 delegate_action: action.delegate_action,
                 signature: action.signature,
             },
+            Action::DeployGlobalContract(action) => {
+                let code = hash(&action.code).as_ref().to_vec();
+                ActionView::DeployGlobalContract { code }
+            }
+            Action::UseGlobalContract(action) => {
+                ActionView::UseGlobalContract { code_hash: action.code_hash }
+            }
         }
     }
 }
@@ -1333,6 +1439,12 @@ impl TryFrom<ActionView> for Action {
             ActionView::Delegate { delegate_action, signature } => {
                 Action::Delegate(Box::new(SignedDelegateAction { delegate_action, signature }))
             }
+            ActionView::DeployGlobalContract { code } => {
+                Action::DeployGlobalContract(DeployGlobalContractAction { code })
+            }
+            ActionView::UseGlobalContract { code_hash } => {
+                Action::UseGlobalContract(Box::new(UseGlobalContractAction { code_hash }))
+            }
         })
     }
 }
@@ -1354,6 +1466,8 @@ pub struct SignedTransactionView {
     pub receiver_id: AccountId,
     pub actions: Vec<ActionView>,
     pub priority_fee: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until_height: Option<BlockHeight>,
     pub signature: Signature,
     pub hash: CryptoHash,
 }
@@ -1363,6 +1477,7 @@ impl From<SignedTransaction> for SignedTransactionView {
         let hash = signed_tx.get_hash();
         let transaction = signed_tx.transaction;
         let priority_fee = transaction.priority_fee().unwrap_or_default();
+        let valid_until_height = transaction.valid_until_height();
         SignedTransactionView {
             signer_id: transaction.signer_id().clone(),
             public_key: transaction.public_key().clone(),
@@ -1372,6 +1487,7 @@ impl From<SignedTransaction> for SignedTransactionView {
             signature: signed_tx.signature,
             hash,
             priority_fee,
+            valid_until_height,
         }
     }
 }
@@ -1502,6 +1618,55 @@ pub struct CostGasUsed {
 pub struct ExecutionMetadataView {
     pub version: u32,
     pub gas_profile: Option<Vec<CostGasUsed>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_stats: Option<StorageAccessStatsView>,
+}
+
+/// Storage operations made by contracts while executing a receipt.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    PartialEq,
+    Clone,
+    Copy,
+    Eq,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct StorageAccessStatsView {
+    /// Number of reads of existing keys, including `storage_has_key` and
+    /// `storage_iter_next`.
+    pub reads: u64,
+    /// Total size of the keys and values read.
+    pub read_bytes: u64,
+    /// Number of `storage_write` calls.
+    pub writes: u64,
+    /// Total size of the keys and values written.
+    pub written_bytes: u64,
+    /// Number of `storage_remove` calls which removed an existing key.
+    pub removals: u64,
+    /// Total size of the keys and values removed.
+    pub removed_bytes: u64,
+    /// Number of trie nodes read from the database.
+    pub trie_node_db_reads: u64,
+    /// Number of trie nodes read from the chunk cache.
+    pub trie_node_mem_reads: u64,
+}
+
+impl From<StorageAccessStats> for StorageAccessStatsView {
+    fn from(stats: StorageAccessStats) -> Self {
+        Self {
+            reads: stats.reads,
+            read_bytes: stats.read_bytes,
+            writes: stats.writes,
+            written_bytes: stats.written_bytes,
+            removals: stats.removals,
+            removed_bytes: stats.removed_bytes,
+            trie_node_db_reads: stats.trie_node_db_reads,
+            trie_node_mem_reads: stats.trie_node_mem_reads,
+        }
+    }
 }
 
 impl Default for ExecutionMetadataView {
@@ -1516,6 +1681,11 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
             ExecutionMetadata::V1 => 1,
             ExecutionMetadata::V2(_) => 2,
             ExecutionMetadata::V3(_) => 3,
+            ExecutionMetadata::V4(..) => 4,
+        };
+        let storage_stats = match &metadata {
+            ExecutionMetadata::V4(_, stats) => Some((*stats).into()),
+            _ => None,
         };
         let mut gas_profile = match metadata {
             ExecutionMetadata::V1 => None,
@@ -1548,7 +1718,7 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
 
                 Some(costs)
             }
-            ExecutionMetadata::V3(profile) => {
+            ExecutionMetadata::V3(profile) | ExecutionMetadata::V4(profile, _) => {
                 // Add actions, wasm op, and ext costs in groups.
                 // actions costs are 1-to-1
                 let mut costs: Vec<CostGasUsed> = ActionCosts::iter()
@@ -1597,7 +1767,7 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
                 lhs.cost_category.cmp(&rhs.cost_category).then_with(|| lhs.cost.cmp(&rhs.cost))
             });
         }
-        ExecutionMetadataView { version, gas_profile }
+        ExecutionMetadataView { version, gas_profile, storage_stats }
     }
 }
 
@@ -1980,6 +2150,20 @@ pub enum ReceiptEnumView {
         #[serde(default = "default_is_promise")]
         is_promise_resume: bool,
     },
+    GlobalContractDistribution {
+        #[serde_as(as = "Base64")]
+        code: Vec<u8>,
+        shard_layout_version: ShardVersion,
+        target_shard: ShardId,
+        already_delivered_shards: Vec<ShardId>,
+    },
+    RuntimeConfigOverridesDistribution {
+        overrides: RuntimeConfigOverrides,
+        from_epoch_height: EpochHeight,
+        shard_layout_version: ShardVersion,
+        target_shard: ShardId,
+        already_delivered_shards: Vec<ShardId>,
+    },
 }
 
 // Default value used when deserializing ReceiptEnumViews which are missing either the
@@ -2029,6 +2213,23 @@ impl From<Receipt> for ReceiptView {
                         is_promise_resume,
                     }
                 }
+                ReceiptEnum::GlobalContractDistribution(distribution_receipt) => {
+                    ReceiptEnumView::GlobalContractDistribution {
+                        code: distribution_receipt.code,
+                        shard_layout_version: distribution_receipt.shard_layout_version,
+                        target_shard: distribution_receipt.target_shard,
+                        already_delivered_shards: distribution_receipt.already_delivered_shards,
+                    }
+                }
+                ReceiptEnum::RuntimeConfigOverridesDistribution(distribution_receipt) => {
+                    ReceiptEnumView::RuntimeConfigOverridesDistribution {
+                        overrides: distribution_receipt.overrides,
+                        from_epoch_height: distribution_receipt.from_epoch_height,
+                        shard_layout_version: distribution_receipt.shard_layout_version,
+                        target_shard: distribution_receipt.target_shard,
+                        already_delivered_shards: distribution_receipt.already_delivered_shards,
+                    }
+                }
             },
             priority,
         }
@@ -2086,6 +2287,32 @@ impl TryFrom<ReceiptView> for Receipt {
                         ReceiptEnum::Data(data_receipt)
                     }
                 }
+                ReceiptEnumView::GlobalContractDistribution {
+                    code,
+                    shard_layout_version,
+                    target_shard,
+                    already_delivered_shards,
+                } => ReceiptEnum::GlobalContractDistribution(GlobalContractDistributionReceipt {
+                    code,
+                    shard_layout_version,
+                    target_shard,
+                    already_delivered_shards,
+                }),
+                ReceiptEnumView::RuntimeConfigOverridesDistribution {
+                    overrides,
+                    from_epoch_height,
+                    shard_layout_version,
+                    target_shard,
+                    already_delivered_shards,
+                } => ReceiptEnum::RuntimeConfigOverridesDistribution(
+                    RuntimeConfigOverridesDistributionReceipt {
+                        overrides,
+                        from_epoch_height,
+                        shard_layout_version,
+                        target_shard,
+                        already_delivered_shards,
+                    },
+                ),
             },
             priority: receipt_view.priority,
         }))
@@ -2326,6 +2553,7 @@ pub enum StateChangeCauseView {
     ValidatorAccountsUpdate,
     Migration,
     Resharding,
+    RuntimeConfigOverridesUpdate,
 }
 
 impl From<StateChangeCause> for StateChangeCauseView {
@@ -2352,6 +2580,7 @@ impl From<StateChangeCause> for StateChangeCauseView {
             StateChangeCause::ValidatorAccountsUpdate => Self::ValidatorAccountsUpdate,
             StateChangeCause::Migration => Self::Migration,
             StateChangeCause::Resharding => Self::Resharding,
+            StateChangeCause::RuntimeConfigOverridesUpdate => Self::RuntimeConfigOverridesUpdate,
         }
     }
 }
@@ -2458,6 +2687,132 @@ pub struct SplitStorageInfoView {
     pub cold_head_height: Option<BlockHeight>,
 
     pub hot_db_kind: Option<String>,
+
+    /// Progress of the initial population of the cold storage, while it is running.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cold_migration: Option<ColdMigrationProgressView>,
+}
+
+/// Progress of the initial population of the cold storage.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ColdMigrationProgressView {
+    /// Height of the cold head once the population is finished.
+    pub target_height: BlockHeight,
+    /// Column being copied.
+    pub column: String,
+    pub copied_bytes: u64,
+    /// Estimated size of all the data to copy.
+    pub total_bytes: Option<u64>,
+    /// Estimated number of seconds until the population is finished.
+    pub eta_secs: Option<u64>,
+}
+
+/// Where the statistics of a state witness were collected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum WitnessStatsSourceView {
+    /// The witness was received by this node as a chunk validator.
+    Observed,
+    /// The witness was produced and validated by shadow chunk validation.
+    Shadow,
+}
+
+/// Sizes in bytes of the borsh-serialized parts of a state witness.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WitnessSizeBreakdownView {
+    pub main_state_transition: u64,
+    pub new_transactions: u64,
+    pub new_transactions_validation_state: u64,
+    pub source_receipt_proofs: u64,
+}
+
+/// Statistics of a single state witness.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WitnessStatsEntryView {
+    pub chunk_hash: CryptoHash,
+    pub height: BlockHeight,
+    pub source: WitnessStatsSourceView,
+    /// Size of the witness before compression.
+    pub raw_size: u64,
+    /// Size of the compressed witness.
+    pub encoded_size: u64,
+    /// Only set for the witnesses of shadow validation.
+    pub size_breakdown: Option<WitnessSizeBreakdownView>,
+    /// Not set until the validation of the witness finishes.
+    pub validation_time_ms: Option<u64>,
+}
+
+/// The latest state witnesses of a shard, from the oldest to the newest.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ShardWitnessStatsView {
+    pub shard_id: ShardId,
+    pub witnesses: Vec<WitnessStatsEntryView>,
+}
+
+/// Contains the statistics of the latest state witnesses seen by the node.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WitnessStatsView {
+    pub shards: Vec<ShardWitnessStatsView>,
+}
+
+/// Memory used by the in-memory trie of a shard.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ShardMemTrieStatusView {
+    pub shard_id: ShardId,
+    pub shard_layout_version: u32,
+    /// Number of trie nodes held in memory.
+    pub num_nodes: u64,
+    /// Memory reserved by the arena holding the trie nodes.
+    pub arena_reserved_bytes: u64,
+    /// Part of the reserved memory used by the trie nodes.
+    pub arena_active_bytes: u64,
+    /// Number of state roots held, one per block which was applied and not
+    /// garbage collected yet.
+    pub num_roots: u64,
+    pub min_root_height: Option<BlockHeight>,
+    pub max_root_height: Option<BlockHeight>,
+}
+
+/// Contains the status of the in-memory tries loaded by the node.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MemTrieStatusView {
+    pub shards: Vec<ShardMemTrieStatusView>,
+}
+
+/// Whether a chunk validator endorsed a chunk.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChunkValidatorEndorsementView {
+    pub account_id: AccountId,
+    #[serde(with = "dec_format")]
+    pub stake: Balance,
+    pub endorsed: bool,
+}
+
+/// Endorsements of a chunk included in a block, taken from the block body.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChunkEndorsementsInfoView {
+    pub shard_id: ShardId,
+    pub chunk_hash: CryptoHash,
+    pub height_created: BlockHeight,
+    #[serde(with = "dec_format")]
+    pub endorsed_stake: Balance,
+    #[serde(with = "dec_format")]
+    pub total_stake: Balance,
+    /// The chunk validators in the order of the endorsement signatures in the block.
+    pub validators: Vec<ChunkValidatorEndorsementView>,
+}
+
+/// Endorsements of the new chunks of a block.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BlockChunkEndorsementsView {
+    pub block_hash: CryptoHash,
+    pub height: BlockHeight,
+    pub chunks: Vec<ChunkEndorsementsInfoView>,
+}
+
+/// Contains the chunk endorsements of the latest blocks, from the newest to the oldest.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChunkEndorsementsView {
+    pub blocks: Vec<BlockChunkEndorsementsView>,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -2505,7 +2860,7 @@ mod tests {
     use super::ExecutionMetadataView;
     use crate::profile_data_v2::ProfileDataV2;
     use crate::transaction::ExecutionMetadata;
-    use near_vm_runner::ProfileDataV3;
+    use near_vm_runner::{ProfileDataV3, StorageAccessStats};
 
     /// The JSON representation used in RPC responses must not remove or rename
     /// fields, only adding fields is allowed or we risk breaking clients.
@@ -2543,4 +2898,13 @@ mod tests {
         let view = ExecutionMetadataView::from(metadata);
         insta::assert_json_snapshot!(view);
     }
+
+    /// `ExecutionMetadataView` with profile V4 displayed on the RPC should not change.
+    #[test]
+    fn test_exec_metadata_v4_view() {
+        let metadata =
+            ExecutionMetadata::V4(ProfileDataV3::test().into(), StorageAccessStats::test());
+        let view = ExecutionMetadataView::from(metadata);
+        insta::assert_json_snapshot!(view);
+    }
 }
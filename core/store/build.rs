@@ -0,0 +1,9 @@
+fn main() -> anyhow::Result<()> {
+    println!("cargo:rerun-if-changed=src/remote/store.proto");
+    protobuf_codegen::Codegen::new()
+        .pure()
+        .includes(["src/"])
+        .input("src/remote/store.proto")
+        .cargo_out_dir("proto")
+        .run()
+}
@@ -1,13 +1,18 @@
 use crate::columns::DBKeyType;
-use crate::db::{ColdDB, COLD_HEAD_KEY, HEAD_KEY};
-use crate::{metrics, DBCol, DBTransaction, Database, Store, TrieChanges};
-
-use borsh::BorshDeserialize;
+use crate::db::refcount::{add_positive_refcount, decode_value_with_rc};
+use crate::db::{
+    ColdDB, StatsValue, COLD_HEAD_KEY, COLD_MIGRATION_PROGRESS_KEY, COLD_STATE_REFCOUNTS_KEY,
+    COLD_TAIL_KEY, HEAD_KEY,
+};
+use crate::{metrics, DBCol, DBTransaction, Database, Store, StoreUpdate, TrieChanges};
+
+use borsh::{BorshDeserialize, BorshSerialize};
 use near_primitives::block::{Block, BlockHeader, Tip};
 use near_primitives::hash::CryptoHash;
-use near_primitives::shard_layout::ShardLayout;
+use near_primitives::shard_layout::{account_id_to_shard_uid, ShardLayout, ShardUId};
 use near_primitives::sharding::ShardChunk;
-use near_primitives::types::BlockHeight;
+use near_primitives::trie_key::trie_key_parsers;
+use near_primitives::types::{BlockHeight, ShardId};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::collections::HashMap;
 use std::io;
@@ -44,8 +49,8 @@ pub trait ColdMigrationStore {
 }
 
 /// The BatchTransaction can be used to write multiple set operations to the cold db in batches.
-/// [`write`] is called every time `transaction_size` overgrows `threshold_transaction_size`.
-/// [`write`] should also be called manually before dropping BatchTransaction to write any leftovers.
+/// [`write`] should be called every time [`is_full`] returns true, i.e. `transaction_size` overgrows
+/// `threshold_transaction_size`, and before dropping BatchTransaction to write any leftovers.
 struct BatchTransaction {
     cold_db: std::sync::Arc<ColdDB>,
     transaction: DBTransaction,
@@ -56,7 +61,8 @@ struct BatchTransaction {
 }
 
 /// Updates provided cold database from provided hot store with information about block at `height`.
-/// Returns if the block was copied (false only if height is not present in `hot_store`).
+/// Returns the size in bytes of the copied keys and values, or `None` if height is not present
+/// in `hot_store`.
 /// Block as `height` has to be final.
 ///
 /// First, we read from hot store information necessary
@@ -74,30 +80,40 @@ struct BatchTransaction {
 /// 1. add it to `DBCol::is_cold` list
 /// 2. define `DBCol::key_type` for it (if it isn't already defined)
 /// 3. add new clause in `get_keys_from_store` for new key types used for this column (if there are any)
+///
+/// If `archived_shards` is set, only the data of these shards is copied, while
+/// the blocks themselves, with the headers of all the chunks, are always copied.
 pub fn update_cold_db(
     cold_db: &ColdDB,
     hot_store: &Store,
     shard_layout: &ShardLayout,
+    archived_shards: Option<&[ShardUId]>,
     height: &BlockHeight,
     num_threads: usize,
-) -> io::Result<bool> {
+) -> io::Result<Option<u64>> {
     let _span = tracing::debug_span!(target: "cold_store", "update cold db", height = height);
     let _timer = metrics::COLD_COPY_DURATION.start_timer();
 
     if hot_store.get_for_cold(DBCol::BlockHeight, &height.to_le_bytes())?.is_none() {
-        return Ok(false);
+        return Ok(None);
     }
 
     let height_key = height.to_le_bytes();
     let block_hash_vec = hot_store.get_or_err_for_cold(DBCol::BlockHeight, &height_key)?;
     let block_hash_key = block_hash_vec.as_slice();
 
-    let key_type_to_keys =
-        get_keys_from_store(&hot_store, shard_layout, &height_key, block_hash_key)?;
+    let key_type_to_keys = get_keys_from_store(
+        &hot_store,
+        shard_layout,
+        archived_shards,
+        false,
+        &height_key,
+        block_hash_key,
+    )?;
     let cold_columns = DBCol::iter().filter(|col| col.is_cold()).collect::<Vec<DBCol>>();
 
     // Create new thread pool with `num_threads`.
-    rayon::ThreadPoolBuilder::new()
+    let size = rayon::ThreadPoolBuilder::new()
         .num_threads(num_threads)
         .build()
         .map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to create rayon pool"))?
@@ -105,29 +121,25 @@ pub fn update_cold_db(
             cold_columns
                 .into_par_iter() // Process every cold column as a separate task in thread pool in parallel.
                 // Copy column to cold db.
-                .map(|col: DBCol| -> io::Result<()> {
+                .map(|col: DBCol| -> io::Result<u64> {
                     if col == DBCol::State {
-                        copy_state_from_store(shard_layout, block_hash_key, cold_db, &hot_store)
+                        copy_state_from_store(
+                            shard_layout,
+                            archived_shards,
+                            block_hash_key,
+                            cold_db,
+                            &hot_store,
+                        )
                     } else {
                         let keys = combine_keys(&key_type_to_keys, &col.key_type());
                         copy_from_store(cold_db, &hot_store, col, keys)
                     }
                 })
-                // Return first found error, or Ok(())
-                .reduce(
-                    || Ok(()), // Ok(()) by default
-                    // First found Err, or Ok(())g
-                    |left, right| -> io::Result<()> {
-                        vec![left, right]
-                            .into_iter()
-                            .filter(|res| res.is_err())
-                            .next()
-                            .unwrap_or(Ok(()))
-                    },
-                )
+                // Return first found error, or the total size
+                .reduce(|| Ok(0), |left, right| -> io::Result<u64> { Ok(left? + right?) })
         })?;
 
-    Ok(true)
+    Ok(Some(size))
 }
 
 // Correctly set the key and value on DBTransaction, taking reference counting
@@ -164,16 +176,20 @@ fn rc_aware_set(
 // the node belongs to.
 fn copy_state_from_store(
     shard_layout: &ShardLayout,
+    archived_shards: Option<&[ShardUId]>,
     block_hash_key: &[u8],
     cold_db: &ColdDB,
     hot_store: &Store,
-) -> io::Result<()> {
+) -> io::Result<u64> {
     let col = DBCol::State;
     let _span = tracing::debug_span!(target: "cold_store", "copy_state_from_store", %col);
     let instant = std::time::Instant::now();
 
     let mut transaction = DBTransaction::new();
-    for shard_uid in shard_layout.shard_uids() {
+    let mut size = 0;
+    for shard_uid in
+        shard_layout.shard_uids().filter(|shard_uid| is_shard_archived(archived_shards, shard_uid))
+    {
         debug_assert_eq!(
             DBCol::TrieChanges.key_type(),
             &[DBKeyType::BlockHash, DBKeyType::ShardUId]
@@ -187,12 +203,28 @@ fn copy_state_from_store(
         let Some(trie_changes) = trie_changes else { continue };
         for op in trie_changes.insertions() {
             let key = join_two_keys(&shard_uid_key, op.hash().as_bytes());
-            let value = op.payload().to_vec();
 
             tracing::trace!(target: "cold_store", pretty_key=?near_fmt::StorageKey(&key), "copying state node to colddb");
-            rc_aware_set(&mut transaction, DBCol::State, key, value);
+            if cold_db.state_pruning() {
+                let value = add_positive_refcount(op.payload(), op.rc());
+                size += (key.len() + value.len()) as u64;
+                transaction.update_refcount(DBCol::State, key, value);
+            } else {
+                let value = op.payload().to_vec();
+                size += rc_aware_set(&mut transaction, DBCol::State, key, value) as u64;
+            }
+        }
+        // The deletions are applied to the cold State when the block is pruned.
+        if cold_db.state_pruning() {
+            let deletions = borsh::to_vec(&trie_changes.without_insertions())?;
+            transaction.set(DBCol::TrieChanges, key, deletions);
         }
     }
+    if !cold_db.state_pruning() {
+        // The reference counts are pinned to 1 again, so they can no longer be
+        // relied on if state pruning is enabled later.
+        transaction.delete(DBCol::BlockMisc, COLD_STATE_REFCOUNTS_KEY.to_vec());
+    }
 
     let read_duration = instant.elapsed();
 
@@ -202,18 +234,18 @@ fn copy_state_from_store(
 
     tracing::trace!(target: "cold_store", ?read_duration, ?write_duration, "finished");
 
-    Ok(())
+    Ok(size)
 }
 
 /// Gets values for given keys in a column from provided hot_store.
 /// Creates a transaction based on that values with set DBOp s.
-/// Writes that transaction to cold_db.
+/// Writes that transaction to cold_db and returns its size in bytes.
 fn copy_from_store(
     cold_db: &ColdDB,
     hot_store: &Store,
     col: DBCol,
     keys: Vec<StoreKey>,
-) -> io::Result<()> {
+) -> io::Result<u64> {
     debug_assert!(col.is_cold());
 
     // note this function should only be used for state in tests where it's
@@ -224,6 +256,7 @@ fn copy_from_store(
 
     let mut transaction = DBTransaction::new();
     let mut good_keys = 0;
+    let mut size = 0;
     let total_keys = keys.len();
     for key in keys {
         // TODO: Look into using RocksDB’s multi_key function.  It
@@ -240,7 +273,7 @@ fn copy_from_store(
             // re-adding the reference count.
 
             good_keys += 1;
-            rc_aware_set(&mut transaction, col, key, value);
+            size += rc_aware_set(&mut transaction, col, key, value) as u64;
         }
     }
 
@@ -252,7 +285,7 @@ fn copy_from_store(
 
     tracing::trace!(target: "cold_store", ?col, ?good_keys, ?total_keys, ?read_duration, ?write_duration, "finished");
 
-    return Ok(());
+    return Ok(size);
 }
 
 /// This function sets the cold head to the Tip that reflect provided height in two places:
@@ -308,33 +341,373 @@ pub enum CopyAllDataToColdStatus {
     Interrupted,
 }
 
+/// Progress of `copy_all_data_to_cold`.  It is written to the cold database
+/// together with every batch, so that an interrupted migration resumes from
+/// the last written key, and mirrored to the hot database, where the status
+/// RPC reads it.  Both copies are deleted once everything is copied.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct ColdMigrationProgress {
+    /// Height to which the cold head is set once the migration is finished.
+    pub target_height: BlockHeight,
+    /// Name of the column being copied.  Columns are copied in the order of
+    /// `DBCol::iter`.
+    pub column: String,
+    /// Last key of `column` written to the cold database.
+    pub last_key: Option<Vec<u8>>,
+    /// Size of the keys and values copied so far, in all columns.
+    pub copied_bytes: u64,
+    /// Estimated size of the cold columns in the hot database.
+    pub total_bytes: Option<u64>,
+    /// Estimated number of seconds until the migration is finished, based on
+    /// the speed of copying since the migration was last started.
+    pub eta_secs: Option<u64>,
+}
+
+impl ColdMigrationProgress {
+    fn new(target_height: BlockHeight) -> Self {
+        Self {
+            target_height,
+            column: String::new(),
+            last_key: None,
+            copied_bytes: 0,
+            total_bytes: None,
+            eta_secs: None,
+        }
+    }
+
+    fn update_eta(&mut self, started: std::time::Instant, copied_bytes_at_start: u64) {
+        let copied = self.copied_bytes.saturating_sub(copied_bytes_at_start);
+        let elapsed = started.elapsed().as_secs_f64();
+        self.eta_secs = self.total_bytes.filter(|_| copied > 0).map(|total_bytes| {
+            let remaining = total_bytes.saturating_sub(self.copied_bytes);
+            (remaining as f64 * elapsed / copied as f64) as u64
+        });
+    }
+}
+
+/// Reads the progress of an unfinished initial migration from `cold_db`.
+pub fn get_cold_migration_progress(cold_db: &ColdDB) -> io::Result<Option<ColdMigrationProgress>> {
+    cold_db
+        .get_raw_bytes(DBCol::BlockMisc, COLD_MIGRATION_PROGRESS_KEY)?
+        .as_deref()
+        .map(ColdMigrationProgress::try_from_slice)
+        .transpose()
+}
+
+/// Estimates the size of the cold columns in `hot_store` from the RocksDB
+/// statistics.  Returns `None` if the database doesn't provide them.
+fn estimate_cold_columns_size(hot_store: &Store) -> Option<u64> {
+    let statistics = hot_store.get_store_statistics()?;
+    let (_, values) =
+        statistics.data.iter().find(|(name, _)| name == "rocksdb.estimate-live-data-size")?;
+    let size = values
+        .iter()
+        .filter_map(|value| match value {
+            StatsValue::ColumnValue(col, size) if col.is_cold() => Some(*size as u64),
+            _ => None,
+        })
+        .sum();
+    Some(size)
+}
+
 /// Copies all contents of all cold columns from `hot_store` to `cold_db`.
 /// Does it column by column, and because columns can be huge, writes in batches of ~`batch_size`.
+///
+/// The progress is saved with every batch.  If `cold_db` contains the progress
+/// of an interrupted migration to the same `target_height`, the copy resumes
+/// from the column and key it stopped at.  Otherwise it starts from scratch.
 pub fn copy_all_data_to_cold(
     cold_db: std::sync::Arc<ColdDB>,
     hot_store: &Store,
+    target_height: BlockHeight,
     batch_size: usize,
     keep_going: &std::sync::Arc<std::sync::atomic::AtomicBool>,
 ) -> io::Result<CopyAllDataToColdStatus> {
-    for col in DBCol::iter() {
-        if col.is_cold() {
-            tracing::info!(target: "cold_store", ?col, "Started column migration");
-            let mut transaction = BatchTransaction::new(cold_db.clone(), batch_size);
-            for result in hot_store.iter(col) {
-                if !keep_going.load(std::sync::atomic::Ordering::Relaxed) {
-                    tracing::debug!(target: "cold_store", "stopping copy_all_data_to_cold");
-                    return Ok(CopyAllDataToColdStatus::Interrupted);
-                }
-                let (key, value) = result?;
-                transaction.set_and_write_if_full(col, key.to_vec(), value.to_vec())?;
+    let cold_columns = DBCol::iter().filter(|col| col.is_cold()).collect::<Vec<DBCol>>();
+    let resumed = get_cold_migration_progress(&cold_db)?
+        .filter(|progress| progress.target_height == target_height)
+        .and_then(|progress| {
+            let position =
+                cold_columns.iter().position(|col| <&str>::from(col) == progress.column)?;
+            Some((position, progress))
+        });
+    let (first_column, mut progress) = match resumed {
+        Some((position, progress)) => {
+            tracing::info!(target: "cold_store", column = progress.column, copied_bytes = progress.copied_bytes, "Resuming the interrupted migration");
+            (position, progress)
+        }
+        None if cold_db.state_pruning() => {
+            // State is copied with the reference counts of the hot database and
+            // keeps them from now on, which makes it possible to prune it.
+            let mut transaction = DBTransaction::new();
+            transaction.set(
+                DBCol::BlockMisc,
+                COLD_STATE_REFCOUNTS_KEY.to_vec(),
+                borsh::to_vec(&target_height)?,
+            );
+            cold_db.write(transaction)?;
+            (0, ColdMigrationProgress::new(target_height))
+        }
+        None => (0, ColdMigrationProgress::new(target_height)),
+    };
+    progress.total_bytes = estimate_cold_columns_size(hot_store).or(progress.total_bytes);
+    let started = std::time::Instant::now();
+    let copied_bytes_at_start = progress.copied_bytes;
+
+    for &col in &cold_columns[first_column..] {
+        // Resuming from the last written key copies it again, which is harmless
+        // since the values are overwritten.  A State entry gets a larger
+        // reference count, which only delays pruning it.
+        let lower_bound =
+            if progress.column == <&str>::from(col) { progress.last_key.take() } else { None };
+        progress.column = col.to_string();
+        tracing::info!(target: "cold_store", ?col, "Started column migration");
+        let mut transaction = BatchTransaction::new(cold_db.clone(), batch_size);
+        let keep_refcounts = col == DBCol::State && cold_db.state_pruning();
+        let iter = if keep_refcounts {
+            iter_raw_bytes_from(hot_store, col, lower_bound.as_deref())
+        } else {
+            hot_store.iter_range(col, lower_bound.as_deref(), None)
+        };
+        for result in iter {
+            if !keep_going.load(std::sync::atomic::Ordering::Relaxed) {
+                tracing::debug!(target: "cold_store", "stopping copy_all_data_to_cold");
+                return Ok(CopyAllDataToColdStatus::Interrupted);
+            }
+            let (key, value) = result?;
+            progress.copied_bytes += (key.len() + value.len()) as u64;
+            progress.last_key = Some(key.to_vec());
+            if keep_refcounts {
+                transaction.update_refcount(col, key.to_vec(), value.to_vec());
+            } else {
+                transaction.set(col, key.to_vec(), value.to_vec());
+            }
+            if transaction.is_full() {
+                progress.update_eta(started, copied_bytes_at_start);
+                transaction.write(&progress)?;
+                save_progress_to_hot(hot_store, Some(&progress))?;
             }
-            transaction.write()?;
-            tracing::info!(target: "cold_store", ?col, "Finished column migration");
         }
+        transaction.write(&progress)?;
+        save_progress_to_hot(hot_store, Some(&progress))?;
+        tracing::info!(target: "cold_store", ?col, "Finished column migration");
     }
+
+    let mut transaction = DBTransaction::new();
+    transaction.delete(DBCol::BlockMisc, COLD_MIGRATION_PROGRESS_KEY.to_vec());
+    cold_db.write(transaction)?;
+    save_progress_to_hot(hot_store, None)?;
     Ok(CopyAllDataToColdStatus::EverythingCopied)
 }
 
+/// Iterates over the raw values of the reference counted `col`, reference
+/// counts included, from `lower_bound` on.  The values which are no longer
+/// referenced are skipped.
+fn iter_raw_bytes_from<'a>(
+    store: &'a Store,
+    col: DBCol,
+    lower_bound: Option<&[u8]>,
+) -> crate::db::DBIterator<'a> {
+    debug_assert!(col.is_rc());
+    let iter = store
+        .iter_raw_bytes_range(col, lower_bound, None)
+        .filter(|item| item.as_ref().map_or(true, |(_, value)| decode_value_with_rc(value).1 > 0));
+    Box::new(iter)
+}
+
+/// Mirrors the migration progress to the hot database, or deletes it if `None`.
+fn save_progress_to_hot(
+    hot_store: &Store,
+    progress: Option<&ColdMigrationProgress>,
+) -> io::Result<()> {
+    let mut transaction = DBTransaction::new();
+    match progress {
+        Some(progress) => transaction.set(
+            DBCol::BlockMisc,
+            COLD_MIGRATION_PROGRESS_KEY.to_vec(),
+            borsh::to_vec(progress)?,
+        ),
+        None => transaction.delete(DBCol::BlockMisc, COLD_MIGRATION_PROGRESS_KEY.to_vec()),
+    }
+    hot_store.storage.write(transaction)
+}
+
+/// Copies the data of the block at `height` from `cold_store` back to
+/// `hot_store`, which is the inverse of `update_cold_db` for all the cold
+/// columns except State. Used to restore the recent blocks in a hot database
+/// created from an archival one without its cold columns.
+/// Returns if the block was copied (false only if height is not present in `hot_store`).
+///
+/// Values of reference counted columns get their count incremented, so that
+/// garbage collecting every block which references them decrements it once.
+/// Copying the same block twice therefore leaks the values it references. The
+/// data is added to `store_update` of `hot_store` instead of being written
+/// right away, so that callers can commit their progress together with it.
+pub fn copy_block_from_cold(
+    cold_store: &Store,
+    hot_store: &Store,
+    shard_layout: &ShardLayout,
+    height: &BlockHeight,
+    store_update: &mut StoreUpdate,
+) -> io::Result<bool> {
+    let _span = tracing::debug_span!(target: "cold_store", "copy block from cold", height = height);
+
+    let height_key = height.to_le_bytes();
+    let Some(block_hash_vec) = hot_store.get_for_cold(DBCol::BlockHeight, &height_key)? else {
+        return Ok(false);
+    };
+    let block_hash_key = block_hash_vec.as_slice();
+
+    let key_type_to_keys =
+        get_keys_from_store(&cold_store, shard_layout, None, false, &height_key, block_hash_key)?;
+    let mut transaction = DBTransaction::new();
+    for col in DBCol::iter().filter(|col| col.is_cold() && *col != DBCol::State) {
+        for key in combine_keys(&key_type_to_keys, &col.key_type()) {
+            if let Some(value) = cold_store.get_for_cold(col, &key)? {
+                rc_aware_set(&mut transaction, col, key, value);
+            }
+        }
+    }
+    store_update.merge(StoreUpdate { transaction, storage: hot_store.storage.clone() });
+    Ok(true)
+}
+
+/// Reads the data of the block at `height` which the cold storage keeps, from
+/// every cold column except `State`, as `(column, key, value)` entries.  Unlike
+/// [`update_cold_db`], `source` doesn't need `BlockHeight` or `TrieChanges`,
+/// so it can be the store of another archival node, e.g. to repair gaps in
+/// the cold storage with [`write_cold_block_data`] once the entries are
+/// verified.
+pub fn get_cold_block_data(
+    source: &Store,
+    shard_layout: &ShardLayout,
+    archived_shards: Option<&[ShardUId]>,
+    height: &BlockHeight,
+    block_hash: &CryptoHash,
+) -> io::Result<Vec<(DBCol, StoreKey, Vec<u8>)>> {
+    let height_key = height.to_le_bytes();
+    let key_type_to_keys = get_keys_from_store(
+        source,
+        shard_layout,
+        archived_shards,
+        true,
+        &height_key,
+        block_hash.as_bytes(),
+    )?;
+    let mut data = vec![];
+    for col in DBCol::iter().filter(|col| col.is_cold() && *col != DBCol::State) {
+        for key in combine_keys(&key_type_to_keys, &col.key_type()) {
+            if let Some(value) = source.get_for_cold(col, &key)? {
+                data.push((col, key, value));
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// Writes the entries read by [`get_cold_block_data`] to the cold db.
+pub fn write_cold_block_data(
+    cold_db: &ColdDB,
+    data: Vec<(DBCol, StoreKey, Vec<u8>)>,
+) -> io::Result<()> {
+    let mut transaction = DBTransaction::new();
+    for (col, key, value) in data {
+        rc_aware_set(&mut transaction, col, key, value);
+    }
+    cold_db.write(transaction)
+}
+
+/// Deletes the data of the block at `height` from the cold db and moves the
+/// cold tail above `height`.  Returns whether anything was deleted, which is
+/// false if no block was produced at `height` or it is already pruned.
+///
+/// Every cold column is pruned, including the chunks, transactions, receipts,
+/// outcomes and state changes of the block.  The trie nodes deleted by the
+/// block are removed from `State` once no retained state refers to them, see
+/// [`prune_cold_state`].  The chunks of the block which were included at a
+/// lower height are pruned together with that height, so heights have to be
+/// pruned in increasing order.  `Block` is deleted last, so that a pruning
+/// interrupted midway is redone when the height is pruned again.
+pub fn prune_cold_block(
+    cold_db: &ColdDB,
+    cold_store: &Store,
+    hot_store: &Store,
+    shard_layout: &ShardLayout,
+    archived_shards: Option<&[ShardUId]>,
+    height: &BlockHeight,
+) -> io::Result<bool> {
+    let _span = tracing::debug_span!(target: "cold_store", "prune cold block", height = height);
+    let _timer = metrics::COLD_PRUNE_DURATION.start_timer();
+
+    // BlockHeight is never garbage collected from the hot store.
+    let height_key = height.to_le_bytes();
+    let block_hash_vec = hot_store.get_for_cold(DBCol::BlockHeight, &height_key)?;
+    let pruned = match block_hash_vec {
+        Some(block_hash_vec) if cold_store.exists(DBCol::Block, &block_hash_vec)? => {
+            let block_hash_key = block_hash_vec.as_slice();
+            let key_type_to_keys = get_keys_from_store(
+                &cold_store,
+                shard_layout,
+                archived_shards,
+                true,
+                &height_key,
+                block_hash_key,
+            )?;
+            for col in DBCol::iter()
+                .filter(|col| col.is_cold() && *col != DBCol::State && *col != DBCol::Block)
+            {
+                cold_db.prune(col, combine_keys(&key_type_to_keys, &col.key_type()))?;
+            }
+            prune_cold_state(cold_db, cold_store, shard_layout, archived_shards, block_hash_key)?;
+            cold_db.prune(DBCol::Block, [block_hash_key.to_vec()])?;
+            true
+        }
+        _ => false,
+    };
+
+    let tail = height + 1;
+    let mut transaction = DBTransaction::new();
+    transaction.set(DBCol::BlockMisc, COLD_TAIL_KEY.to_vec(), borsh::to_vec(&tail)?);
+    cold_db.write(transaction)?;
+    metrics::COLD_TAIL_HEIGHT.set(tail as i64);
+    Ok(pruned)
+}
+
+/// Decrements the reference counts of the trie nodes deleted by the block
+/// with `block_hash_key`, as recorded in the `TrieChanges` which the cold db
+/// keeps with state pruning enabled, and removes the `TrieChanges`.  The nodes
+/// of the state before the block are gone once no retained state refers to
+/// them.
+///
+/// The reference counts are only decremented if `State` has kept them since
+/// the initial migration of the cold db.  Otherwise they may be lower than the
+/// number of references and the trie nodes are kept.
+fn prune_cold_state(
+    cold_db: &ColdDB,
+    cold_store: &Store,
+    shard_layout: &ShardLayout,
+    archived_shards: Option<&[ShardUId]>,
+    block_hash_key: &[u8],
+) -> io::Result<()> {
+    let has_refcounts = cold_store.exists(DBCol::BlockMisc, COLD_STATE_REFCOUNTS_KEY)?;
+    for shard_uid in
+        shard_layout.shard_uids().filter(|shard_uid| is_shard_archived(archived_shards, shard_uid))
+    {
+        let shard_uid_key = shard_uid.to_bytes();
+        let key = join_two_keys(block_hash_key, &shard_uid_key);
+        let Some(trie_changes) = cold_store.get_ser::<TrieChanges>(DBCol::TrieChanges, &key)?
+        else {
+            continue;
+        };
+        let deletions = if has_refcounts { trie_changes.deletions() } else { &[] };
+        let state_refcounts = deletions.iter().map(|deletion| {
+            (join_two_keys(&shard_uid_key, deletion.hash().as_bytes()), deletion.rc())
+        });
+        cold_db.prune_state(key, state_refcounts)?;
+    }
+    Ok(())
+}
+
 // The copy_state_from_store function depends on the state nodes to be present
 // in the trie changes. This isn't the case for genesis so instead this method
 // can be used to copy the genesis records from hot to cold.
@@ -377,15 +750,26 @@ pub fn test_get_store_initial_writes(column: DBCol) -> u64 {
 fn get_keys_from_store(
     store: &Store,
     shard_layout: &ShardLayout,
+    archived_shards: Option<&[ShardUId]>,
+    new_chunks_only: bool,
     height_key: &[u8],
     block_hash_key: &[u8],
 ) -> io::Result<HashMap<DBKeyType, Vec<StoreKey>>> {
     let mut key_type_to_keys = HashMap::new();
 
     let block: Block = store.get_ser_or_err_for_cold(DBCol::Block, &block_hash_key)?;
+    let shard_ids = || {
+        shard_layout
+            .shard_ids()
+            .filter(|shard_id| is_archived(shard_layout, archived_shards, *shard_id))
+    };
     let chunks = block
         .chunks()
         .iter()
+        .filter(|chunk_header| is_archived(shard_layout, archived_shards, chunk_header.shard_id()))
+        .filter(|chunk_header| {
+            !new_chunks_only || chunk_header.is_new_chunk(block.header().height())
+        })
         .map(|chunk_header| {
             store.get_ser_or_err_for_cold(DBCol::Chunks, chunk_header.chunk_hash().as_bytes())
         })
@@ -408,12 +792,12 @@ fn get_keys_from_store(
                 DBKeyType::PreviousBlockHash => {
                     vec![block.header().prev_hash().as_bytes().to_vec()]
                 }
-                DBKeyType::ShardId => shard_layout
-                    .shard_ids()
-                    .map(|shard_id| shard_id.to_le_bytes().to_vec())
-                    .collect(),
+                DBKeyType::ShardId => {
+                    shard_ids().map(|shard_id| shard_id.to_le_bytes().to_vec()).collect()
+                }
                 DBKeyType::ShardUId => shard_layout
                     .shard_uids()
+                    .filter(|shard_uid| is_shard_archived(archived_shards, shard_uid))
                     .map(|shard_uid| shard_uid.to_bytes().to_vec())
                     .collect(),
                 // TODO: write StateChanges values to colddb directly, not to cache.
@@ -425,7 +809,9 @@ fn get_keys_from_store(
                         |full_key| {
                             let mut full_key = Vec::from(full_key);
                             full_key.drain(..block_hash_key.len());
-                            keys.push(full_key);
+                            if is_trie_key_archived(shard_layout, archived_shards, &full_key) {
+                                keys.push(full_key);
+                            }
                         },
                     )?;
                     keys
@@ -448,8 +834,7 @@ fn get_keys_from_store(
                         DBCol::OutcomeIds.key_type(),
                         &[DBKeyType::BlockHash, DBKeyType::ShardId]
                     );
-                    shard_layout
-                        .shard_ids()
+                    shard_ids()
                         .map(|shard_id| {
                             store.get_ser(
                                 DBCol::OutcomeIds,
@@ -476,6 +861,41 @@ fn get_keys_from_store(
     Ok(key_type_to_keys)
 }
 
+/// Returns whether the data of the shard is copied to the cold storage.
+/// All the shards are archived if `archived_shards` is not set.  The shards
+/// are identified by their `ShardUId`, as the same shard id refers to
+/// different shards in different shard layouts.
+pub fn is_shard_archived(archived_shards: Option<&[ShardUId]>, shard_uid: &ShardUId) -> bool {
+    archived_shards.map_or(true, |shards| shards.contains(shard_uid))
+}
+
+fn is_archived(
+    shard_layout: &ShardLayout,
+    archived_shards: Option<&[ShardUId]>,
+    shard_id: ShardId,
+) -> bool {
+    is_shard_archived(archived_shards, &ShardUId::from_shard_id_and_layout(shard_id, shard_layout))
+}
+
+/// Returns whether a state change under `trie_key` belongs to an archived
+/// shard.  Keys which don't contain an account id, such as the delayed receipt
+/// queue, can't be attributed to a shard and are always archived.
+fn is_trie_key_archived(
+    shard_layout: &ShardLayout,
+    archived_shards: Option<&[ShardUId]>,
+    trie_key: &[u8],
+) -> bool {
+    if archived_shards.is_none() {
+        return true;
+    }
+    match trie_key_parsers::parse_account_id_from_raw_key(trie_key) {
+        Ok(Some(account_id)) => {
+            is_shard_archived(archived_shards, &account_id_to_shard_uid(&account_id, shard_layout))
+        }
+        Ok(None) | Err(_) => true,
+    }
+}
+
 pub fn join_two_keys(prefix_key: &[u8], suffix_key: &[u8]) -> StoreKey {
     [prefix_key, suffix_key].concat()
 }
@@ -588,25 +1008,25 @@ impl BatchTransaction {
     }
 
     /// Adds a set DBOp to `self.transaction`. Updates `self.transaction_size`.
-    /// If `self.transaction_size` becomes too big, calls for write.
-    pub fn set_and_write_if_full(
-        &mut self,
-        col: DBCol,
-        key: Vec<u8>,
-        value: Vec<u8>,
-    ) -> io::Result<()> {
-        let size = rc_aware_set(&mut self.transaction, col, key, value);
-        self.transaction_size += size;
+    pub fn set(&mut self, col: DBCol, key: Vec<u8>, value: Vec<u8>) {
+        self.transaction_size += rc_aware_set(&mut self.transaction, col, key, value);
+    }
 
-        if self.transaction_size > self.threshold_transaction_size {
-            self.write()?;
-        }
-        Ok(())
+    /// Adds an update refcount DBOp, whose value carries the reference count,
+    /// to `self.transaction`. Updates `self.transaction_size`.
+    pub fn update_refcount(&mut self, col: DBCol, key: Vec<u8>, value: Vec<u8>) {
+        self.transaction_size += key.len() + value.len();
+        self.transaction.update_refcount(col, key, value);
+    }
+
+    /// Whether `self.transaction` has grown big enough to be written.
+    pub fn is_full(&self) -> bool {
+        self.transaction_size > self.threshold_transaction_size
     }
 
-    /// Writes `self.transaction` and replaces it with new empty DBTransaction.
-    /// Sets `self.transaction_size` to 0.
-    fn write(&mut self) -> io::Result<()> {
+    /// Writes `self.transaction` together with `progress` and replaces it with
+    /// new empty DBTransaction. Sets `self.transaction_size` to 0.
+    fn write(&mut self, progress: &ColdMigrationProgress) -> io::Result<()> {
         if self.transaction.ops.is_empty() {
             return Ok(());
         }
@@ -626,7 +1046,14 @@ impl BatchTransaction {
                 tx_size_in_megabytes = self.transaction_size as f64 / 1e6,
                 "Writing a Cold Store transaction");
 
-        let transaction = std::mem::take(&mut self.transaction);
+        // The progress is written in the same batch as the data, so that it
+        // never gets ahead of what is stored in the cold database.
+        let mut transaction = std::mem::take(&mut self.transaction);
+        transaction.set(
+            DBCol::BlockMisc,
+            COLD_MIGRATION_PROGRESS_KEY.to_vec(),
+            borsh::to_vec(progress)?,
+        );
         self.cold_db.write(transaction)?;
         self.transaction_size = 0;
 
@@ -700,4 +1127,76 @@ mod test {
             HashSet::<StoreKey>::from_iter(vec![vec![]])
         );
     }
+
+    /// Checks that a copy resumes from the saved progress only if it has the
+    /// same target height, and that the progress is removed once finished.
+    #[test]
+    fn test_copy_all_data_to_cold_resumes() {
+        use super::{copy_all_data_to_cold, get_cold_migration_progress, ColdMigrationProgress};
+        use crate::metadata::{DbKind, DB_VERSION};
+        use crate::test_utils::create_test_node_storage_with_cold;
+        use crate::{DBCol, COLD_MIGRATION_PROGRESS_KEY};
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+
+        let (storage, _, _) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Archive);
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().unwrap();
+        let cold_db = storage.cold_db().unwrap().clone();
+        let mut store_update = hot_store.store_update();
+        for key in 1..=3u8 {
+            store_update.insert(DBCol::Block, vec![key], vec![key]);
+        }
+        store_update.insert(DBCol::BlockInfo, vec![4], vec![4]);
+        store_update.commit().unwrap();
+
+        let mut progress = ColdMigrationProgress::new(10);
+        progress.column = DBCol::Block.to_string();
+        progress.last_key = Some(vec![2]);
+        let mut store_update = cold_store.store_update();
+        store_update.set_ser(DBCol::BlockMisc, COLD_MIGRATION_PROGRESS_KEY, &progress).unwrap();
+        store_update.commit().unwrap();
+
+        let keep_going = Arc::new(AtomicBool::new(true));
+        copy_all_data_to_cold(cold_db.clone(), &hot_store, 10, 1, &keep_going).unwrap();
+        assert!(!cold_store.exists(DBCol::Block, &[1]).unwrap());
+        assert!(cold_store.exists(DBCol::Block, &[2]).unwrap());
+        assert!(cold_store.exists(DBCol::Block, &[3]).unwrap());
+        assert!(cold_store.exists(DBCol::BlockInfo, &[4]).unwrap());
+        assert_eq!(get_cold_migration_progress(&cold_db).unwrap(), None);
+        assert!(!hot_store.exists(DBCol::BlockMisc, COLD_MIGRATION_PROGRESS_KEY).unwrap());
+
+        let mut store_update = cold_store.store_update();
+        store_update.set_ser(DBCol::BlockMisc, COLD_MIGRATION_PROGRESS_KEY, &progress).unwrap();
+        store_update.commit().unwrap();
+        copy_all_data_to_cold(cold_db.clone(), &hot_store, 11, 1, &keep_going).unwrap();
+        assert!(cold_store.exists(DBCol::Block, &[1]).unwrap());
+    }
+
+    /// Checks that state changes are attributed to the shards of their
+    /// accounts, and that keys without an account are always archived.
+    #[test]
+    fn test_is_trie_key_archived() {
+        use super::is_trie_key_archived;
+        use near_primitives::shard_layout::{ShardLayout, ShardUId};
+        use near_primitives::trie_key::TrieKey;
+
+        let shard_layout = ShardLayout::v1(vec!["m".parse().unwrap()], None, 1);
+        let archived = [ShardUId { version: 1, shard_id: 0 }];
+        let account = |account_id: &str| {
+            TrieKey::Account { account_id: account_id.parse().unwrap() }.to_vec()
+        };
+        assert!(is_trie_key_archived(&shard_layout, Some(&archived), &account("alice")));
+        assert!(!is_trie_key_archived(&shard_layout, Some(&archived), &account("zoe")));
+        assert!(is_trie_key_archived(&shard_layout, None, &account("zoe")));
+        assert!(is_trie_key_archived(
+            &shard_layout,
+            Some(&archived),
+            &TrieKey::DelayedReceiptIndices.to_vec()
+        ));
+
+        // Shard 0 of another shard layout is a different shard.
+        let next_layout = ShardLayout::v1(vec!["m".parse().unwrap()], None, 2);
+        assert!(!is_trie_key_archived(&next_layout, Some(&archived), &account("alice")));
+    }
 }
@@ -294,6 +294,23 @@ pub enum DBCol {
     /// Witnesses with the lowest index are garbage collected first.
     /// u64 -> LatestWitnessesKey
     LatestWitnessesByIndex,
+    /// Column to store the ChunkStateWitnesses produced by shadow validation, one per chunk.
+    /// Only written when `save_shadow_witnesses` is enabled, used for offline replay and
+    /// size analysis.
+    /// - *Rows*: BlockHeight (big-endian) || ShardId (big-endian) || ChunkHash - 48 bytes
+    /// - *Column type*: `ChunkStateWitness`
+    ShadowStateWitness,
+    /// Previous flat state values of the keys changed by the blocks which
+    /// became flat head, kept for `flat_state_history_epochs` to serve reads
+    /// at recent heights below the flat head.
+    /// - *Rows*: `shard_uid` + trie key length (u32, big-endian) + trie key + BlockHeight (big-endian)
+    /// - *Column type*: `Option<FlatStateValue>`, the value before the block at the height
+    FlatStateHistory,
+    /// Blocks the flat head moved over which are covered by `FlatStateHistory`,
+    /// with the trie keys they changed, used to prune the history.
+    /// - *Rows*: `shard_uid` + BlockHeight (big-endian)
+    /// - *Column type*: `FlatStateHistoryBlock`
+    FlatStateHistoryBlocks,
     /// Column to store data for Epoch Sync.
     /// Does not contain data for genesis epoch.
     /// - *Rows*: `epoch_id`
@@ -473,6 +490,10 @@ impl DBCol {
             // LatestChunkStateWitnesses stores the last N observed witnesses, used only for debugging.
             DBCol::LatestChunkStateWitnesses => false,
             DBCol::LatestWitnessesByIndex => false,
+            // ShadowStateWitness is pruned by its own retention, used only for debugging.
+            DBCol::ShadowStateWitness => false,
+            // Flat state history is pruned by flat storage itself.
+            DBCol::FlatStateHistory | DBCol::FlatStateHistoryBlocks => false,
 
             // Columns that are not GC-ed need not be copied to the cold storage.
             DBCol::BlockHeader
@@ -507,8 +528,11 @@ impl DBCol {
     }
 
     /// Whether this column exists in cold storage.
+    ///
+    /// TrieChanges is not copied, but the cold storage keeps their deletions
+    /// when it prunes the state, see [`crate::db::ColdDB::with_state_pruning`].
     pub(crate) const fn is_in_colddb(&self) -> bool {
-        matches!(*self, DBCol::DbVersion | DBCol::BlockMisc) || self.is_cold()
+        matches!(*self, DBCol::DbVersion | DBCol::BlockMisc | DBCol::TrieChanges) || self.is_cold()
     }
 
     /// Vector of DBKeyType s concatenation of which results in key for the column.
@@ -574,6 +598,13 @@ impl DBCol {
             DBCol::StateTransitionData => &[DBKeyType::BlockHash, DBKeyType::ShardId],
             DBCol::LatestChunkStateWitnesses => &[DBKeyType::LatestWitnessesKey],
             DBCol::LatestWitnessesByIndex => &[DBKeyType::LatestWitnessIndex],
+            DBCol::ShadowStateWitness => {
+                &[DBKeyType::BlockHeight, DBKeyType::ShardId, DBKeyType::ChunkHash]
+            }
+            DBCol::FlatStateHistory => {
+                &[DBKeyType::ShardUId, DBKeyType::TrieKey, DBKeyType::BlockHeight]
+            }
+            DBCol::FlatStateHistoryBlocks => &[DBKeyType::ShardUId, DBKeyType::BlockHeight],
             #[cfg(feature = "new_epoch_sync")]
             DBCol::EpochSyncInfo => &[DBKeyType::EpochId],
         }
@@ -2,7 +2,9 @@ use crate::trie::{
     DEFAULT_SHARD_CACHE_DELETIONS_QUEUE_CAPACITY, DEFAULT_SHARD_CACHE_TOTAL_SIZE_LIMIT,
 };
 use crate::DBCol;
+use near_async::time::Duration;
 use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{BlockHeightDelta, ShardId};
 use std::{collections::HashMap, iter::FromIterator};
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -38,6 +40,16 @@ pub struct StoreConfig {
     /// Cache size for DBCol::FlatState column.
     pub col_flat_state_cache_size: bytesize::ByteSize,
 
+    /// Cache size for every column without a dedicated cache size.
+    /// Default value: 32MiB.
+    pub default_col_cache_size: bytesize::ByteSize,
+
+    /// Cache sizes for specific columns, by column name, for example
+    /// `{"BlockHeader": "256 MiB"}`.  Overrides the cache sizes above, so the
+    /// cache can be biased towards the columns the node's workload reads most.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub col_cache_sizes: HashMap<String, bytesize::ByteSize>,
+
     /// Block size used internally in RocksDB.
     /// Default value: 16KiB.
     /// We're still experimenting with this parameter and it seems decreasing its value can improve
@@ -63,12 +75,24 @@ pub struct StoreConfig {
     pub claim_sweat_prefetch_config: Vec<PrefetchConfig>,
     pub kaiching_prefetch_config: Vec<PrefetchConfig>,
 
-    /// List of shard UIDs for which we should load the tries in memory.
+    /// List of shards for which we should load the tries in memory, either as
+    /// shard ids like `[0, 3]` or as shard UIDs like `["s0.v3", "s3.v3"]`.
+    /// Only the tracked shards are loaded.
     /// TODO(#9511): This does not automatically survive resharding. We may need to figure out a
     /// strategy for that.
-    pub load_mem_tries_for_shards: Vec<ShardUId>,
+    pub load_mem_tries_for_shards: Vec<MemTrieShard>,
     /// If true, load mem trie for each shard being tracked; this has priority over `load_mem_tries_for_shards`.
     pub load_mem_tries_for_tracked_shards: bool,
+    /// Maximum number of shards whose mem tries are loaded at the same time on
+    /// startup.  Loading more shards at once shortens the startup, but needs
+    /// more disk bandwidth to read their flat state.
+    pub load_mem_tries_parallelism: usize,
+    /// If true, the mem tries are saved to the `mem_tries` directory next to
+    /// the database when the node stops, and restored from there on the next
+    /// start instead of being built from flat storage, if the flat storage
+    /// didn't change in between.  The saved mem tries take about as much disk
+    /// space as the mem tries take memory.
+    pub persist_mem_tries: bool,
 
     /// Path where to create RocksDB checkpoints during database migrations or
     /// `false` to disable that feature.
@@ -99,6 +123,186 @@ pub struct StoreConfig {
 
     // TODO (#9989): To be phased out in favor of state_snapshot_config
     pub state_snapshot_enabled: bool,
+
+    /// Compression of specific columns, by column name, for example
+    /// `{"State": {"algorithm": "zstd", "level": 19}}`.  It replaces the
+    /// default compression of all levels of the column but the first two,
+    /// which stay uncompressed.  Changing it needs no migration: the files
+    /// written by flushes and compactions from then on use the new compression,
+    /// and `neard database compact-database` rewrites the existing files.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub col_compression: HashMap<String, ColumnCompression>,
+
+    /// Apply the writes to the hot database on a background thread, so that
+    /// committing a block doesn't wait for the disk.  The writes are applied
+    /// in the order they were committed and reads see the pending writes.
+    /// The writes not applied yet are lost if the node crashes, just like
+    /// with an unsynced write-ahead log.
+    pub async_writes: bool,
+
+    /// When the write-ahead log of RocksDB is synced to disk.
+    pub wal_sync: WalSyncPolicy,
+
+    /// Sync the write-ahead log in the background every time this many bytes
+    /// are written to it, which smooths out the disk writes.  Zero leaves it
+    /// to the operating system.
+    pub wal_bytes_per_sync: bytesize::ByteSize,
+
+    /// Number of checkpoints created with `neard database checkpoint` to keep.
+    /// When a new checkpoint is created, the oldest ones beyond this number are
+    /// deleted.  All checkpoints are kept if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoints_to_keep: Option<usize>,
+
+    /// Storage engine of the database.  RocksDB is the supported engine; the
+    /// alternatives are meant for benchmarking and require neard built with
+    /// the corresponding feature.  The options above which tune RocksDB have
+    /// no effect on other engines.
+    pub backend: StoreBackend,
+
+    /// Bucket holding the database when `backend` is `object_storage`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_storage: Option<ObjectStorageConfig>,
+
+    /// How much history the cold database keeps.  Only valid for the
+    /// `cold_store` config.  The blocks outside of the retention window are
+    /// pruned together with their chunks, outcomes, state changes and state,
+    /// which makes the node semi-archival.  The whole history is kept if not
+    /// set.  The state is kept with the `object_storage` backend, which can't
+    /// update reference counts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<ColdRetentionConfig>,
+
+    /// Size of the in-memory LRU cache of the values read from the database.
+    /// Only valid for the `cold_store` config, where it saves the reads of
+    /// the frequently queried historical data from slow disks.  No cache is
+    /// used if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_cache_size: Option<bytesize::ByteSize>,
+
+    /// Number of epochs for which flat storage keeps the previous values of
+    /// the changed keys, so that view queries at heights below the flat head
+    /// are answered from flat storage instead of walking the trie.  Meant for
+    /// RPC and archival nodes; only the state at the flat head and above is
+    /// served from flat storage if not set.  On non-archival nodes it should
+    /// not exceed `gc_num_epochs_to_keep`, as the state values of the older
+    /// blocks are garbage collected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flat_state_history_epochs: Option<u64>,
+
+    /// How the flat storage of each shard keeps the deltas of the blocks
+    /// above its flat head.
+    pub flat_storage_deltas: FlatStorageDeltasConfig,
+}
+
+/// Shard listed in `StoreConfig::load_mem_tries_for_shards`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum MemTrieShard {
+    /// The shard with this id in any shard layout.
+    ShardId(ShardId),
+    /// The shard of a single shard layout.
+    ShardUId(ShardUId),
+}
+
+/// Retention window of the cold database.  A block is kept as long as it is
+/// inside any of the windows which are set.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ColdRetentionConfig {
+    /// Number of blocks below the cold head to keep.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<BlockHeightDelta>,
+    /// Age of the oldest block to keep, for example
+    /// `{"secs": 63072000, "nanos": 0}` for two years.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "near_async::time::serde_opt_duration_as_std")]
+    pub duration: Option<Duration>,
+    /// Maximum number of heights pruned each time the cold store loop catches
+    /// up with the final head, so that pruning doesn't delay copying.
+    #[serde(default = "ColdRetentionConfig::default_max_pruned_heights")]
+    pub max_pruned_heights: u64,
+}
+
+impl ColdRetentionConfig {
+    fn default_max_pruned_heights() -> u64 {
+        100
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    #[default]
+    RocksDB,
+    /// SQLite database in a single file, available with the `sqlite` feature.
+    /// State snapshots, needed for resharding and for serving state parts,
+    /// are not supported.
+    Sqlite,
+    /// Segment objects in an S3 compatible bucket, with a local index of the
+    /// keys and a read-through cache in the database directory.  Available
+    /// with the `object_storage` feature and only for the cold database,
+    /// since reads not served by the cache are requests to the bucket.
+    #[serde(rename = "object_storage")]
+    ObjectStorage,
+}
+
+/// Location of a database kept in object storage.  The credentials are read
+/// from the `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY` environment
+/// variables.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ObjectStorageConfig {
+    pub bucket: String,
+    /// Region of the bucket, e.g. `us-west-1`.
+    pub region: String,
+    /// Endpoint of an S3 compatible service other than AWS, e.g.
+    /// `https://storage.googleapis.com` for GCS with HMAC keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Prefix of the names of the objects, so that several databases can
+    /// share a bucket.
+    #[serde(default)]
+    pub prefix: String,
+    /// Maximum size of the local read-through cache.
+    #[serde(default = "ObjectStorageConfig::default_cache_size")]
+    pub cache_size: bytesize::ByteSize,
+}
+
+impl ObjectStorageConfig {
+    fn default_cache_size() -> bytesize::ByteSize {
+        bytesize::ByteSize::gib(100)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ColumnCompression {
+    pub algorithm: CompressionAlgorithm,
+    /// Compression level, the default level of the algorithm if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    None,
+    Snappy,
+    Lz4,
+    Lz4hc,
+    Zlib,
+    Zstd,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalSyncPolicy {
+    /// Writes return once the log is handed to the operating system, which
+    /// writes it out later.  Recent writes may be lost on a machine crash but
+    /// not on a crash of the node.
+    #[default]
+    Os,
+    /// Every write waits until the log is synced to disk.  Nothing is lost on
+    /// a machine crash but writes are much slower on slow disks.
+    EveryWrite,
 }
 
 /// Config used to control state snapshot creation. This is used for state sync and resharding.
@@ -106,6 +310,12 @@ pub struct StoreConfig {
 #[serde(default)]
 pub struct StateSnapshotConfig {
     pub state_snapshot_type: StateSnapshotType,
+    /// Whether to keep the state snapshot across epochs and move its flat
+    /// storage forward with the flat state deltas of the blocks processed in
+    /// the meantime, instead of checkpointing the whole hot database at
+    /// every epoch boundary.  Falls back to a new checkpoint if the snapshot
+    /// can't be moved, e.g. when the shard layout changes.
+    pub incremental: bool,
 }
 
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -176,11 +386,14 @@ impl StoreConfig {
     }
 
     /// Returns cache size for given column.
-    pub const fn col_cache_size(&self, col: DBCol) -> bytesize::ByteSize {
+    pub fn col_cache_size(&self, col: DBCol) -> bytesize::ByteSize {
+        if let Some(size) = self.col_cache_sizes.get(<&str>::from(col)) {
+            return *size;
+        }
         match col {
             DBCol::State => self.col_state_cache_size,
             DBCol::FlatState => self.col_flat_state_cache_size,
-            _ => bytesize::ByteSize::mib(32),
+            _ => self.default_col_cache_size,
         }
     }
 }
@@ -214,6 +427,10 @@ impl Default for StoreConfig {
             // #9389.
             col_flat_state_cache_size: bytesize::ByteSize::mib(128),
 
+            default_col_cache_size: bytesize::ByteSize::mib(32),
+
+            col_cache_sizes: Default::default(),
+
             // This value was taken from the Openethereum default parameter and
             // we use it since then.
             block_size: bytesize::ByteSize::kib(16),
@@ -273,6 +490,8 @@ impl Default for StoreConfig {
             // requires more RAM and takes several minutes on startup.
             load_mem_tries_for_shards: Default::default(),
             load_mem_tries_for_tracked_shards: false,
+            load_mem_tries_parallelism: 6,
+            persist_mem_tries: false,
 
             migration_snapshot: Default::default(),
 
@@ -280,6 +499,21 @@ impl Default for StoreConfig {
 
             // TODO: To be phased out in favor of state_snapshot_config
             state_snapshot_enabled: false,
+
+            col_compression: Default::default(),
+
+            async_writes: false,
+            wal_sync: WalSyncPolicy::default(),
+            wal_bytes_per_sync: bytesize::ByteSize::b(0),
+
+            checkpoints_to_keep: None,
+
+            backend: StoreBackend::default(),
+            object_storage: None,
+            retention: None,
+            read_cache_size: None,
+            flat_state_history_epochs: None,
+            flat_storage_deltas: Default::default(),
         }
     }
 }
@@ -345,6 +579,49 @@ impl Default for TrieCacheConfig {
     }
 }
 
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FlatStorageDeltasConfig {
+    /// Policy of the shards not listed in `per_shard`.
+    pub default: FlatStorageDeltasPolicy,
+    /// Overwrites `default` for specific shards, e.g. to compact the deltas
+    /// of high-traffic shards earlier.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub per_shard: HashMap<ShardUId, FlatStorageDeltasPolicy>,
+}
+
+impl FlatStorageDeltasConfig {
+    /// Policy of the flat storage of the given shard.
+    pub fn shard_policy(&self, shard_uid: ShardUId) -> FlatStorageDeltasPolicy {
+        self.per_shard.get(&shard_uid).copied().unwrap_or(self.default)
+    }
+}
+
+/// How the flat storage of a shard keeps the deltas of the blocks above its
+/// flat head in memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FlatStorageDeltasPolicy {
+    /// Number of blocks with flat state changes, up to the last final block,
+    /// whose deltas are kept in memory instead of being merged into the flat
+    /// state when the flat head moves.  State snapshots need at least 2.
+    pub flat_head_gap: BlockHeightDelta,
+    /// Reading a block needs to go through the deltas of all blocks with
+    /// changes between it and the flat head, which gets slow when finality
+    /// lags.  Once a new block is this many deltas away from the flat head,
+    /// its chain of deltas is merged into a single one in the background, so
+    /// that the reads at the next blocks only go through the deltas above it.
+    /// The merged delta is dropped when the flat head moves.  Zero disables
+    /// the compaction.
+    pub compaction_threshold: usize,
+}
+
+impl Default for FlatStorageDeltasPolicy {
+    fn default() -> Self {
+        Self { flat_head_gap: 2, compaction_threshold: 0 }
+    }
+}
+
 /// Parameters for prefetching certain contract calls.
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
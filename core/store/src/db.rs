@@ -6,20 +6,34 @@ pub(crate) mod rocksdb;
 
 mod colddb;
 mod mixeddb;
+#[cfg(feature = "object_storage")]
+mod objectstoragedb;
+mod pipelineddb;
+#[cfg(unix)]
+mod remotedb;
 mod splitdb;
 
 pub mod refcount;
 mod slice;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 mod testdb;
 
 mod database_tests;
 
 pub use self::colddb::ColdDB;
-pub use self::mixeddb::{MixedDB, ReadOrder};
+pub use self::mixeddb::{MixedDB, MixedDBReadStats, ReadOrder, WriteMode};
+#[cfg(feature = "object_storage")]
+pub use self::objectstoragedb::ObjectStorageDB;
+pub use self::pipelineddb::PipelinedDB;
+#[cfg(unix)]
+pub use self::remotedb::RemoteDB;
 pub use self::rocksdb::RocksDB;
 pub use self::splitdb::SplitDB;
 
 pub use self::slice::DBSlice;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteDB;
 pub use self::testdb::TestDB;
 
 // `DBCol::BlockMisc` keys
@@ -34,8 +48,16 @@ pub const LARGEST_TARGET_HEIGHT_KEY: &[u8; 21] = b"LARGEST_TARGET_HEIGHT";
 pub const GENESIS_JSON_HASH_KEY: &[u8; 17] = b"GENESIS_JSON_HASH";
 pub const GENESIS_STATE_ROOTS_KEY: &[u8; 19] = b"GENESIS_STATE_ROOTS";
 pub const COLD_HEAD_KEY: &[u8; 9] = b"COLD_HEAD";
+/// Lowest height kept in the cold db when it is pruned by the retention config.
+pub const COLD_TAIL_KEY: &[u8; 9] = b"COLD_TAIL";
+pub const COLD_MIGRATION_PROGRESS_KEY: &[u8; 23] = b"COLD_MIGRATION_PROGRESS";
+/// Height of the initial migration of a cold db whose `State` column has kept
+/// the reference counts since then, which is required to prune the state.
+pub const COLD_STATE_REFCOUNTS_KEY: &[u8; 20] = b"COLD_STATE_REFCOUNTS";
 pub const STATE_SYNC_DUMP_KEY: &[u8; 15] = b"STATE_SYNC_DUMP";
 pub const STATE_SNAPSHOT_KEY: &[u8; 18] = b"STATE_SNAPSHOT_KEY";
+/// Runtime config parameters overridden with sandbox state patches.
+pub const SANDBOX_RUNTIME_CONFIG_KEY: &[u8; 22] = b"SANDBOX_RUNTIME_CONFIG";
 
 // `DBCol::Misc` keys
 pub const FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS_KEY: &[u8] =
@@ -43,11 +65,12 @@ pub const FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS_KEY: &[u8] =
 pub const STATE_TRANSITION_START_HEIGHTS: &[u8] = b"STATE_TRANSITION_START_HEIGHTS";
 pub const LATEST_WITNESSES_INFO: &[u8] = b"LATEST_WITNESSES_INFO";
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct DBTransaction {
     pub(crate) ops: Vec<DBOp>,
 }
 
+#[derive(Clone)]
 pub(crate) enum DBOp {
     /// Sets `key` to `value`, without doing any checks.
     Set { col: DBCol, key: Vec<u8>, value: Vec<u8> },
@@ -161,6 +184,19 @@ impl DBTransaction {
     }
 }
 
+/// Returns the smallest key larger than all keys starting with `prefix`, or
+/// `None` if there is no such key.
+pub(crate) fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut bound = prefix.to_vec();
+    while let Some(last) = bound.pop() {
+        if last < u8::MAX {
+            bound.push(last + 1);
+            return Some(bound);
+        }
+    }
+    None
+}
+
 pub type DBIteratorItem = io::Result<(Box<[u8]>, Box<[u8]>)>;
 pub type DBIterator<'a> = Box<dyn Iterator<Item = DBIteratorItem> + 'a>;
 
@@ -226,6 +262,33 @@ pub trait Database: Sync + Send {
     /// want this method.
     fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a>;
 
+    /// Iterate over raw bytes of items in given column whose keys are between
+    /// [lower_bound, upper_bound), bypassing reference count decoding if any.
+    ///
+    /// This is to [`Self::iter_raw_bytes`] what [`Self::iter_range`] is to
+    /// [`Self::iter`].  The default implementation scans the column from the
+    /// start, so databases which can seek to the lower bound override it.
+    fn iter_raw_bytes_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        let lower_bound = lower_bound.map(<[u8]>::to_vec);
+        let upper_bound = upper_bound.map(<[u8]>::to_vec);
+        let iter = self
+            .iter_raw_bytes(col)
+            .skip_while(move |item| match (item, &lower_bound) {
+                (Ok((key, _)), Some(lower_bound)) => key.as_ref() < lower_bound.as_slice(),
+                _ => false,
+            })
+            .take_while(move |item| match (item, &upper_bound) {
+                (Ok((key, _)), Some(upper_bound)) => key.as_ref() < upper_bound.as_slice(),
+                _ => true,
+            });
+        Box::new(iter)
+    }
+
     /// Atomically apply all operations in given batch at once.
     fn write(&self, batch: DBTransaction) -> io::Result<()>;
 
@@ -1,25 +1,89 @@
+use std::sync::{Arc, Mutex};
+
 use near_o11y::{log_assert, log_assert_fail};
 
-use crate::db::refcount::set_refcount;
+use crate::db::refcount::{decode_value_with_rc, encode_negative_refcount, set_refcount};
 use crate::db::{DBIterator, DBOp, DBSlice, DBTransaction, Database};
-use crate::DBCol;
+use crate::{metrics, DBCol};
 
 /// A database which provides access to the cold storage.
 ///
 /// The data is stored in the same format as in the regular database but for the
 /// reference counted columns the rc is always set to 1. This struct handles
-/// setting the rc to one transparently to the user.
+/// setting the rc to one transparently to the user.  With state pruning enabled,
+/// `State` keeps its reference counts, so that the trie nodes of pruned blocks
+/// can be removed once no retained state refers to them.
 ///
 /// Lastly, since no data is ever deleted from cold storage, trying to decrease
 /// reference of a value count or delete data is ignored and if debug assertions
-/// are enabled will cause a panic.
+/// are enabled will cause a panic.  The only exceptions are [`ColdDB::prune`]
+/// and [`ColdDB::prune_state`].
+///
+/// Optionally, the values read are kept in an in-memory LRU cache, since
+/// historical queries tend to read the same recent blocks and the cold storage
+/// is usually on slow disks.
 pub struct ColdDB {
     cold: std::sync::Arc<dyn Database>,
+    read_cache: Option<ReadCache>,
+    /// Whether the `TrieChanges` of the copied blocks are kept, so that their
+    /// state can be pruned.
+    state_pruning: bool,
 }
 
 impl ColdDB {
     pub fn new(cold: std::sync::Arc<dyn Database>) -> Self {
-        Self { cold }
+        Self { cold, read_cache: None, state_pruning: false }
+    }
+
+    /// Creates the database with a read cache holding up to `max_size` bytes
+    /// of keys and values.  No cache is used if `max_size` is zero.
+    pub fn with_read_cache(
+        cold: std::sync::Arc<dyn Database>,
+        max_size: bytesize::ByteSize,
+    ) -> Self {
+        let read_cache = (max_size.as_u64() > 0).then(|| ReadCache::new(max_size.as_u64()));
+        Self { cold, read_cache, state_pruning: false }
+    }
+
+    /// Keeps the reference counts of `State` and the deletions of the
+    /// `TrieChanges` of the blocks copied to the cold storage, which
+    /// [`ColdDB::prune_state`] needs to prune their state.
+    ///
+    /// The underlying database has to support reference count updates.
+    pub fn with_state_pruning(mut self) -> Self {
+        self.state_pruning = true;
+        self
+    }
+
+    pub fn state_pruning(&self) -> bool {
+        self.state_pruning
+    }
+
+    /// Writes `transaction` to the underlying database and then removes the
+    /// keys it wrote from the read cache.  Invalidating after the write, and
+    /// bumping the generation of the cache, keeps a read which raced with the
+    /// write from caching the old value, see [`ReadCache::put`].
+    fn write_and_invalidate_cache(&self, transaction: DBTransaction) -> std::io::Result<()> {
+        let Some(read_cache) = &self.read_cache else {
+            return self.cold.write(transaction);
+        };
+        let keys: Option<Vec<_>> = transaction
+            .ops
+            .iter()
+            .map(|op| match op {
+                DBOp::Set { col, key, .. }
+                | DBOp::Insert { col, key, .. }
+                | DBOp::UpdateRefcount { col, key, .. }
+                | DBOp::Delete { col, key } => Some((*col, key.clone())),
+                DBOp::DeleteAll { .. } | DBOp::DeleteRange { .. } => None,
+            })
+            .collect();
+        let result = self.cold.write(transaction);
+        match keys {
+            Some(keys) => read_cache.remove(keys),
+            None => read_cache.clear(),
+        }
+        result
     }
 
     fn err_msg(col: DBCol) -> String {
@@ -38,18 +102,157 @@ impl ColdDB {
     fn log_assert_is_in_colddb(col: DBCol) {
         log_assert!(col.is_in_colddb(), "{}", Self::err_msg(col));
     }
+
+    /// Deletes `keys` from `col`.
+    ///
+    /// Unlike [`Database::write`], which never deletes data from the cold
+    /// storage, this removes the keys from the underlying database.  It is only
+    /// meant for removing data which is not reachable from the canonical chain,
+    /// such as blocks on forks, or which is outside of the retention window.
+    pub fn prune(
+        &self,
+        col: DBCol,
+        keys: impl IntoIterator<Item = Vec<u8>>,
+    ) -> std::io::Result<()> {
+        Self::check_is_in_colddb(col)?;
+        let mut transaction = DBTransaction::new();
+        for key in keys {
+            transaction.delete(col, key);
+        }
+        self.write_and_invalidate_cache(transaction)
+    }
+
+    /// Decrements the reference counts of the `State` entries deleted by the
+    /// `TrieChanges` at `trie_changes_key`, removing the entries which are no
+    /// longer referenced, and deletes the `TrieChanges`.
+    ///
+    /// Both happen in a single write, so that pruning the same block again
+    /// after an interruption never decrements the reference counts twice.
+    pub fn prune_state(
+        &self,
+        trie_changes_key: Vec<u8>,
+        state_refcounts: impl IntoIterator<Item = (Vec<u8>, std::num::NonZeroU32)>,
+    ) -> std::io::Result<()> {
+        let mut transaction = DBTransaction::new();
+        for (key, rc) in state_refcounts {
+            transaction.update_refcount(DBCol::State, key, encode_negative_refcount(rc).to_vec());
+        }
+        transaction.delete(DBCol::TrieChanges, trie_changes_key);
+        self.write_and_invalidate_cache(transaction)
+    }
+}
+
+/// LRU cache of the raw values read from the cold storage, bounded by the
+/// total size of the keys and values.  Only existing values are cached.
+struct ReadCache {
+    inner: Mutex<ReadCacheInner>,
+    max_size: u64,
+}
+
+struct ReadCacheInner {
+    entries: lru::LruCache<(DBCol, Vec<u8>), Arc<[u8]>>,
+    size: u64,
+    /// Incremented whenever entries are invalidated.
+    generation: u64,
+}
+
+impl ReadCache {
+    fn new(max_size: u64) -> Self {
+        let inner = ReadCacheInner { entries: lru::LruCache::unbounded(), size: 0, generation: 0 };
+        Self { inner: Mutex::new(inner), max_size }
+    }
+
+    /// Returns the generation to pass to [`ReadCache::put`] for a value read
+    /// after this call.
+    fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    fn entry_size(key: &[u8], value: &[u8]) -> u64 {
+        (key.len() + value.len()) as u64
+    }
+
+    fn get(&self, col: DBCol, key: &[u8]) -> Option<Arc<[u8]>> {
+        let value = self.inner.lock().unwrap().entries.get(&(col, key.to_vec())).cloned();
+        let counter = match value {
+            Some(_) => &metrics::COLD_READ_CACHE_HITS,
+            None => &metrics::COLD_READ_CACHE_MISSES,
+        };
+        counter.with_label_values(&[<&str>::from(col)]).inc();
+        value
+    }
+
+    /// Caches a value read from the database after [`ReadCache::generation`]
+    /// returned `generation`.  The value isn't cached if anything was
+    /// invalidated since, as it may have been read before a write which
+    /// replaced it.
+    fn put(&self, col: DBCol, key: &[u8], value: &[u8], generation: u64) {
+        let entry_size = Self::entry_size(key, value);
+        if entry_size > self.max_size {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation != generation {
+            return;
+        }
+        if let Some(old) = inner.entries.put((col, key.to_vec()), value.into()) {
+            inner.size -= Self::entry_size(key, &old);
+        }
+        inner.size += entry_size;
+        while inner.size > self.max_size {
+            let Some(((_, key), value)) = inner.entries.pop_lru() else {
+                break;
+            };
+            inner.size -= Self::entry_size(&key, &value);
+        }
+        metrics::COLD_READ_CACHE_SIZE.set(inner.size as i64);
+    }
+
+    fn remove(&self, keys: Vec<(DBCol, Vec<u8>)>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation += 1;
+        for key in keys {
+            if let Some(value) = inner.entries.pop(&key) {
+                inner.size -= Self::entry_size(&key.1, &value);
+            }
+        }
+        metrics::COLD_READ_CACHE_SIZE.set(inner.size as i64);
+    }
+
+    fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation += 1;
+        inner.entries.clear();
+        inner.size = 0;
+        metrics::COLD_READ_CACHE_SIZE.set(0);
+    }
 }
 
 impl Database for ColdDB {
     /// Returns raw bytes for given `key` ignoring any reference count decoding if any.
     fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> std::io::Result<Option<DBSlice<'_>>> {
         Self::check_is_in_colddb(col)?;
-        self.cold.get_raw_bytes(col, key)
+        let Some(read_cache) = &self.read_cache else {
+            return self.cold.get_raw_bytes(col, key);
+        };
+        if let Some(value) = read_cache.get(col, key) {
+            return Ok(Some(DBSlice::from_vec(value.to_vec())));
+        }
+        let generation = read_cache.generation();
+        let value = self.cold.get_raw_bytes(col, key)?;
+        if let Some(value) = &value {
+            read_cache.put(col, key, value.as_slice(), generation);
+        }
+        Ok(value)
     }
 
     /// Returns value for given `key` forcing a reference count decoding.
     fn get_with_rc_stripped(&self, col: DBCol, key: &[u8]) -> std::io::Result<Option<DBSlice<'_>>> {
         Self::check_is_in_colddb(col)?;
+        if self.read_cache.is_some() {
+            assert!(col.is_rc());
+            return Ok(self.get_raw_bytes(col, key)?.and_then(DBSlice::strip_refcount));
+        }
         self.cold.get_with_rc_stripped(col, key)
     }
 
@@ -71,6 +274,16 @@ impl Database for ColdDB {
         self.cold.iter_raw_bytes(col)
     }
 
+    fn iter_raw_bytes_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        Self::log_assert_is_in_colddb(col);
+        self.cold.iter_raw_bytes_range(col, lower_bound, upper_bound)
+    }
+
     /// Iterate over items in given column whose keys are between [lower_bound, upper_bound)
     fn iter_range<'a>(
         &'a self,
@@ -90,13 +303,13 @@ impl Database for ColdDB {
     fn write(&self, mut transaction: DBTransaction) -> std::io::Result<()> {
         let mut idx = 0;
         while idx < transaction.ops.len() {
-            if adjust_op(&mut transaction.ops[idx]) {
+            if adjust_op(&mut transaction.ops[idx], self.state_pruning) {
                 idx += 1;
             } else {
                 transaction.ops.swap_remove(idx);
             }
         }
-        self.cold.write(transaction)
+        self.write_and_invalidate_cache(transaction)
     }
 
     fn compact(&self) -> std::io::Result<()> {
@@ -124,14 +337,24 @@ impl Database for ColdDB {
 ///
 /// Returns whether the operation should be kept or dropped.  Generally, dropped
 /// columns indicate an unexpected operation which should have never been issued
-/// for cold storage.
-fn adjust_op(op: &mut DBOp) -> bool {
+/// for cold storage.  The reference counts of `State` are only kept with
+/// `state_pruning`, otherwise they are set to 1 like in the other columns.
+fn adjust_op(op: &mut DBOp, state_pruning: bool) -> bool {
     if !op.col().is_in_colddb() {
         return false;
     }
 
     match op {
         DBOp::Set { .. } | DBOp::Insert { .. } => true,
+        DBOp::UpdateRefcount { col: DBCol::State, key, value } if state_pruning => {
+            // The reference counts of State are kept, but they are only ever
+            // decremented by `ColdDB::prune_state`.
+            if decode_value_with_rc(value).1 > 0 {
+                return true;
+            }
+            log_assert_fail!("Unexpected refcount decrement of State in cold store: {key:?}");
+            false
+        }
         DBOp::UpdateRefcount { col, key, value } => {
             assert!(col.is_rc());
             // There is no point in keeping track of ref count in the cold store so
@@ -349,4 +572,110 @@ mod test {
         let got = db.get_raw_bytes(col, key).unwrap();
         assert_eq!(Some([VALUE, ONE].concat().as_slice()), got.as_deref());
     }
+
+    #[test]
+    fn test_prune() {
+        let db = create_test_cold_db();
+        let other = [1u8; 32];
+        db.write(DBTransaction { ops: vec![set(DBCol::Block, HASH), set(DBCol::Block, &other)] })
+            .unwrap();
+
+        db.prune(DBCol::Block, [HASH.to_vec()]).unwrap();
+        assert_eq!(None, db.get_raw_bytes(DBCol::Block, HASH).unwrap());
+        assert!(db.get_raw_bytes(DBCol::Block, &other).unwrap().is_some());
+
+        assert!(db.prune(DBCol::FlatState, [HASH.to_vec()]).is_err());
+    }
+
+    #[test]
+    fn test_prune_state() {
+        let db = create_test_cold_db().with_state_pruning();
+        let key = [SHARD, HASH].concat();
+        let other = [SHARD, &[1u8; 32]].concat();
+        let update_refcount = |key: &[u8], rc: &[u8]| DBOp::UpdateRefcount {
+            col: DBCol::State,
+            key: key.to_vec(),
+            value: [VALUE, rc].concat(),
+        };
+        let ops = vec![
+            update_refcount(&key, ONE),
+            update_refcount(&key, ONE),
+            update_refcount(&other, ONE),
+            set(DBCol::TrieChanges, HASH),
+        ];
+        db.write(DBTransaction { ops: ops.clone() }).unwrap();
+
+        // Without state pruning, the reference count is set to 1 like in other
+        // columns.
+        let db_without_pruning = create_test_cold_db();
+        db_without_pruning.write(DBTransaction { ops }).unwrap();
+        let got = db_without_pruning.get_raw_bytes(DBCol::State, &key).unwrap();
+        assert_eq!(Some([VALUE, ONE].concat().as_slice()), got.as_deref());
+
+        // Unlike other columns, State keeps its reference counts.
+        let got = db.get_raw_bytes(DBCol::State, &key).unwrap();
+        assert_eq!(Some([VALUE, &2i64.to_le_bytes()].concat().as_slice()), got.as_deref());
+
+        let one = std::num::NonZeroU32::new(1).unwrap();
+        db.prune_state(HASH.to_vec(), [(key.clone(), one), (other.clone(), one)]).unwrap();
+        assert_eq!(Some(VALUE), db.get_with_rc_stripped(DBCol::State, &key).unwrap().as_deref());
+        assert_eq!(None, db.get_with_rc_stripped(DBCol::State, &other).unwrap());
+        assert_eq!(None, db.get_raw_bytes(DBCol::TrieChanges, HASH).unwrap());
+    }
+
+    #[test]
+    fn test_read_cache() {
+        let cold = crate::db::testdb::TestDB::new();
+        let db = ColdDB::with_read_cache(cold.clone(), bytesize::ByteSize::kib(1));
+        db.write(DBTransaction { ops: vec![set(DBCol::Block, HASH)] }).unwrap();
+
+        // The value is cached after the first read.
+        assert_eq!(Some(VALUE), db.get_raw_bytes(DBCol::Block, HASH).unwrap().as_deref());
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::Block, HASH.to_vec(), b"Other".to_vec());
+        cold.write(transaction).unwrap();
+        assert_eq!(Some(VALUE), db.get_raw_bytes(DBCol::Block, HASH).unwrap().as_deref());
+
+        // Writing and pruning through the cold db invalidate the cache.
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::Block, HASH.to_vec(), b"Third".to_vec());
+        db.write(transaction).unwrap();
+        let got = db.get_raw_bytes(DBCol::Block, HASH).unwrap();
+        assert_eq!(Some(b"Third".as_slice()), got.as_deref());
+        db.prune(DBCol::Block, [HASH.to_vec()]).unwrap();
+        assert_eq!(None, db.get_raw_bytes(DBCol::Block, HASH).unwrap());
+
+        // Values larger than the cache are not cached.
+        let large = vec![0u8; 2048];
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::Block, HASH.to_vec(), large.clone());
+        db.write(transaction).unwrap();
+        assert_eq!(
+            Some(large.as_slice()),
+            db.get_raw_bytes(DBCol::Block, HASH).unwrap().as_deref()
+        );
+        assert_eq!(0, db.read_cache.as_ref().unwrap().inner.lock().unwrap().size);
+    }
+
+    /// A value read before a write which replaced it is not cached once the
+    /// write invalidated the cache.
+    #[test]
+    fn test_read_cache_racing_write() {
+        let cold = crate::db::testdb::TestDB::new();
+        let db = ColdDB::with_read_cache(cold.clone(), bytesize::ByteSize::kib(1));
+        db.write(DBTransaction { ops: vec![set(DBCol::Block, HASH)] }).unwrap();
+        let read_cache = db.read_cache.as_ref().unwrap();
+
+        // A reader misses the cache and reads the old value...
+        let generation = read_cache.generation();
+        let old = cold.get_raw_bytes(DBCol::Block, HASH).unwrap().unwrap();
+        // ...while the value is replaced...
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::Block, HASH.to_vec(), b"Other".to_vec());
+        db.write(transaction).unwrap();
+        // ...and only then caches what it read.
+        read_cache.put(DBCol::Block, HASH, old.as_slice(), generation);
+        let got = db.get_raw_bytes(DBCol::Block, HASH).unwrap();
+        assert_eq!(Some(b"Other".as_slice()), got.as_deref());
+    }
 }
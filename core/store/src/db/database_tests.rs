@@ -10,11 +10,20 @@ mod tests {
         DBCol, NodeStorage,
     };
 
-    // Returns test & rocksDB databases.
+    // Returns test & rocksDB databases, and the SQLite one if it's enabled.
     fn test_and_rocksdb() -> Vec<Arc<dyn Database>> {
         let (_tmp_dir, opener) = NodeStorage::test_opener();
         let store = opener.open().unwrap().get_hot_store();
-        vec![TestDB::new(), store.storage.clone()]
+        #[allow(unused_mut)]
+        let mut dbs: Vec<Arc<dyn Database>> = vec![TestDB::new(), store.storage.clone()];
+        #[cfg(feature = "sqlite")]
+        {
+            let config = crate::StoreConfig::test_config();
+            let path = _tmp_dir.path().join("sqlite");
+            let db = crate::db::SqliteDB::open(&path, &config, crate::Mode::Create).unwrap();
+            dbs.push(Arc::new(db));
+        }
+        dbs
     }
 
     /// Tests the behavior of the iterators. Iterators don't really work over cold storage, so we're not testing it here.
@@ -40,6 +49,12 @@ mod tests {
                 .map(|data| String::from_utf8(data.unwrap().0.to_vec()).unwrap())
                 .collect();
             assert_eq!(keys, vec!["aa", "aa1"]);
+
+            let keys: Vec<_> = db
+                .iter_raw_bytes_range(DBCol::Block, Some("aa1".as_bytes()), None)
+                .map(|data| String::from_utf8(data.unwrap().0.to_vec()).unwrap())
+                .collect();
+            assert_eq!(keys, vec!["aa1", "bb1", "cc1"]);
         }
     }
 }
@@ -1,4 +1,5 @@
 use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use crate::db::{DBIterator, DBSlice, DBTransaction, Database, StoreStatistics};
@@ -11,6 +12,31 @@ pub enum ReadOrder {
     WriteDBFirst,
 }
 
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub enum WriteMode {
+    /// Writes go to the write DB only, the read DB is never modified.
+    WriteDBOnly,
+    /// Writes go to the write DB and are mirrored to the read DB.
+    WriteThrough,
+}
+
+/// Numbers of point reads served by each of the databases of a [`MixedDB`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MixedDBReadStats {
+    pub write_db: u64,
+    pub read_db: u64,
+    /// Reads of keys present in neither of the databases.
+    pub not_found: u64,
+}
+
+#[derive(Default)]
+struct ReadCounters {
+    write_db: AtomicU64,
+    read_db: AtomicU64,
+    not_found: AtomicU64,
+}
+
 /// MixedDB allows to have dedicated read-only db, and specify the order of data retrieval.
 /// With `ReadOrder::ReadDBFirst` you can overwrite some information in DB, without actually modifying it.
 /// With `ReadOrder::WriteDBFirst` you can record results of any operations only in a separate DB.
@@ -21,14 +47,25 @@ pub enum ReadOrder {
 /// But it also has several assertions about types of columns we can retrieve from `read_db`,
 /// And it is suitable for production,
 ///
+/// With `WriteMode::WriteThrough` the writes are also applied to the read DB, which is
+/// then no longer read-only.
+///
+/// MixedDB counts which of the databases served each point read, see [`MixedDB::read_stats`].
+/// To also tell apart the reads served by hot and cold storage, use as the read DB another
+/// `MixedDB { read_db: cold_db, write_db: hot_db, read_order: ReadOrder::WriteDBFirst }`
+/// in place of a SplitDB, and keep it around to query its statistics.
+///
 /// MixedDB is designed to be used with neard tools, and is not planned for integration into production.
 pub struct MixedDB {
-    /// Read-only DB.
+    /// Read-only DB, unless writes go through to it.
     read_db: Arc<dyn Database>,
     /// DB for writes.
     write_db: Arc<dyn Database>,
     /// order of data lookup.
     read_order: ReadOrder,
+    /// Whether the writes are mirrored to the read DB.
+    write_mode: WriteMode,
+    read_counters: ReadCounters,
 }
 
 impl MixedDB {
@@ -37,8 +74,39 @@ impl MixedDB {
         read_db: Arc<dyn Database>,
         write_db: Arc<dyn Database>,
         read_order: ReadOrder,
+        write_mode: WriteMode,
     ) -> Arc<Self> {
-        return Arc::new(MixedDB { read_db, write_db, read_order });
+        return Arc::new(MixedDB {
+            read_db,
+            write_db,
+            read_order,
+            write_mode,
+            read_counters: ReadCounters::default(),
+        });
+    }
+
+    /// Returns the numbers of point reads served by each database so far.
+    pub fn read_stats(&self) -> MixedDBReadStats {
+        MixedDBReadStats {
+            write_db: self.read_counters.write_db.load(Ordering::Relaxed),
+            read_db: self.read_counters.read_db.load(Ordering::Relaxed),
+            not_found: self.read_counters.not_found.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Counts a point read which found a value in the first or in the second DB
+    /// in the order of data lookup, or in neither of them.
+    fn count_read(&self, first: bool, found: bool) {
+        let counter = match (found, first, self.read_order) {
+            (false, _, _) => &self.read_counters.not_found,
+            (true, true, ReadOrder::WriteDBFirst) | (true, false, ReadOrder::ReadDBFirst) => {
+                &self.read_counters.write_db
+            }
+            (true, true, ReadOrder::ReadDBFirst) | (true, false, ReadOrder::WriteDBFirst) => {
+                &self.read_counters.read_db
+            }
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
     }
 
     /// Return the first DB in the order of data lookup
@@ -66,18 +134,24 @@ impl MixedDB {
 impl Database for MixedDB {
     fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
         if let Some(first_result) = self.first_db().get_raw_bytes(col, key)? {
+            self.count_read(true, true);
             return Ok(Some(first_result));
         }
-        self.second_db().get_raw_bytes(col, key)
+        let second_result = self.second_db().get_raw_bytes(col, key)?;
+        self.count_read(false, second_result.is_some());
+        Ok(second_result)
     }
 
     fn get_with_rc_stripped(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
         assert!(col.is_rc());
 
         if let Some(first_result) = self.first_db().get_with_rc_stripped(col, key)? {
+            self.count_read(true, true);
             return Ok(Some(first_result));
         }
-        self.second_db().get_with_rc_stripped(col, key)
+        let second_result = self.second_db().get_with_rc_stripped(col, key)?;
+        self.count_read(false, second_result.is_some());
+        Ok(second_result)
     }
 
     fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
@@ -111,11 +185,17 @@ impl Database for MixedDB {
     }
 
     fn write(&self, batch: DBTransaction) -> io::Result<()> {
+        if let WriteMode::WriteThrough = self.write_mode {
+            self.read_db.write(batch.clone())?;
+        }
         self.write_db.write(batch)
     }
 
     /// There is no need to flush a read-only DB.
     fn flush(&self) -> io::Result<()> {
+        if let WriteMode::WriteThrough = self.write_mode {
+            self.read_db.flush()?;
+        }
         self.write_db.flush()
     }
 
@@ -139,3 +219,58 @@ impl Database for MixedDB {
         self.write_db.create_checkpoint(path, columns_to_keep)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MixedDB, MixedDBReadStats, ReadOrder, WriteMode};
+    use crate::db::{DBTransaction, Database, TestDB};
+    use crate::DBCol;
+
+    fn set(db: &dyn Database, key: &[u8]) {
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::Block, key.to_vec(), vec![1]);
+        db.write(transaction).unwrap();
+    }
+
+    #[test]
+    fn test_read_stats() {
+        let read_db = TestDB::new();
+        let write_db = TestDB::new();
+        set(read_db.as_ref(), b"read");
+        set(write_db.as_ref(), b"write");
+
+        for read_order in [ReadOrder::ReadDBFirst, ReadOrder::WriteDBFirst] {
+            let db =
+                MixedDB::new(read_db.clone(), write_db.clone(), read_order, WriteMode::WriteDBOnly);
+            for key in [&b"read"[..], b"write", b"write", b"missing"] {
+                db.get_raw_bytes(DBCol::Block, key).unwrap();
+            }
+            assert_eq!(db.read_stats(), MixedDBReadStats { write_db: 2, read_db: 1, not_found: 1 });
+        }
+    }
+
+    #[test]
+    fn test_write_through() {
+        let read_db = TestDB::new();
+        let write_db = TestDB::new();
+        let db = MixedDB::new(
+            read_db.clone(),
+            write_db.clone(),
+            ReadOrder::WriteDBFirst,
+            WriteMode::WriteDBOnly,
+        );
+        set(db.as_ref(), b"only");
+        assert!(read_db.get_raw_bytes(DBCol::Block, b"only").unwrap().is_none());
+        assert!(write_db.get_raw_bytes(DBCol::Block, b"only").unwrap().is_some());
+
+        let db = MixedDB::new(
+            read_db.clone(),
+            write_db.clone(),
+            ReadOrder::WriteDBFirst,
+            WriteMode::WriteThrough,
+        );
+        set(db.as_ref(), b"through");
+        assert!(read_db.get_raw_bytes(DBCol::Block, b"through").unwrap().is_some());
+        assert!(write_db.get_raw_bytes(DBCol::Block, b"through").unwrap().is_some());
+    }
+}
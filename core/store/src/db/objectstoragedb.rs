@@ -0,0 +1,672 @@
+use crate::db::{
+    prefix_upper_bound, refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database, RocksDB,
+    StoreStatistics,
+};
+use crate::{metadata, DBCol, Mode, StoreConfig, Temperature};
+use borsh::{BorshDeserialize, BorshSerialize};
+use s3::error::S3Error;
+use s3::Bucket;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Size of the values after which a transaction continues in a new segment.
+const MAX_SEGMENT_SIZE: usize = 64 * bytesize::MIB as usize;
+
+/// Maximum number of requests to the object storage running at once.
+const MAX_CONCURRENT_REQUESTS: usize = 64;
+
+/// Number of values the iterators read at once.
+const ITER_BATCH_SIZE: usize = 1000;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Database kept in an S3 compatible object storage, e.g. AWS S3 or GCS.
+///
+/// Every transaction is written as immutable segment objects named
+/// `<prefix>segments/<id>`, which hold the values of the transaction followed
+/// by the list of its keys, so that a write costs one request per
+/// [`MAX_SEGMENT_SIZE`] rather than one per key.  The location of the current
+/// value of every key is kept in a local RocksDB index in the `index`
+/// subdirectory of the database directory, which is updated in a single batch
+/// once the segments of a transaction are uploaded.  Reads look the key up in
+/// the index and read the range of its value, and iterators walk the index.
+///
+/// Segments are never modified or deleted, so a checkpoint of the index is a
+/// consistent snapshot of the database.  A failed write leaves behind
+/// segments which no key refers to.  The values read are cached in local
+/// files.
+pub struct ObjectStorageDB {
+    bucket: Arc<Bucket>,
+    prefix: String,
+    /// Runtime running the requests to the object storage, `None` only while
+    /// the database is being dropped.
+    runtime: Option<tokio::runtime::Runtime>,
+    index: RocksDB,
+    cache: ObjectCache,
+    /// Id of the last segment written.
+    last_segment_id: Mutex<u64>,
+    read_only: bool,
+}
+
+/// Location of a value, as stored in the index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+struct ValueLocation {
+    segment_id: u64,
+    range: ValueRange,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+struct ValueRange {
+    offset: u64,
+    len: u32,
+}
+
+/// Entry of the list of keys at the end of a segment; `None` marks a deleted
+/// key.
+#[derive(Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+struct SegmentEntry {
+    col: String,
+    key: Vec<u8>,
+    range: Option<ValueRange>,
+}
+
+/// Segment being built.  It consists of the values, the borsh encoded list
+/// of its entries and the length of the list as a little endian `u64`.
+#[derive(Default)]
+struct Segment {
+    data: Vec<u8>,
+    entries: Vec<SegmentEntry>,
+}
+
+impl Segment {
+    fn finish(mut self) -> io::Result<Vec<u8>> {
+        let entries = borsh::to_vec(&self.entries)?;
+        self.data.extend_from_slice(&entries);
+        self.data.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+        Ok(self.data)
+    }
+}
+
+/// Update of the index: the key with the position of its segment in the
+/// transaction and the range of its value, or `None` if it's deleted.
+type IndexUpdate = (DBCol, Vec<u8>, Option<(usize, ValueRange)>);
+
+/// Splits the operations of a transaction into segments, starting a new one
+/// once the values of the current one would exceed `max_size`.
+fn build_segments(ops: Vec<DBOp>, max_size: usize) -> io::Result<(Vec<Segment>, Vec<IndexUpdate>)> {
+    let mut segments = vec![Segment::default()];
+    let mut updates = Vec::with_capacity(ops.len());
+    for op in ops {
+        match op {
+            DBOp::Set { col, key, value } | DBOp::Insert { col, key, value } => {
+                let mut segment = segments.last_mut().unwrap();
+                if !segment.data.is_empty() && segment.data.len() + value.len() > max_size {
+                    segments.push(Segment::default());
+                    segment = segments.last_mut().unwrap();
+                }
+                let len = u32::try_from(value.len())
+                    .map_err(|_| io::Error::other(format!("value in {col} is too large")))?;
+                let range = ValueRange { offset: segment.data.len() as u64, len };
+                segment.data.extend_from_slice(&value);
+                segment.entries.push(SegmentEntry {
+                    col: <&str>::from(col).to_string(),
+                    key: key.clone(),
+                    range: Some(range),
+                });
+                updates.push((col, key, Some((segments.len() - 1, range))));
+            }
+            DBOp::Delete { col, key } => {
+                let segment = segments.last_mut().unwrap();
+                segment.entries.push(SegmentEntry {
+                    col: <&str>::from(col).to_string(),
+                    key: key.clone(),
+                    range: None,
+                });
+                updates.push((col, key, None));
+            }
+            DBOp::UpdateRefcount { col, .. }
+            | DBOp::DeleteAll { col }
+            | DBOp::DeleteRange { col, .. } => {
+                let msg = format!("unsupported operation on {col} in object storage");
+                return Err(io::Error::other(msg));
+            }
+        }
+    }
+    Ok((segments, updates))
+}
+
+impl ObjectStorageDB {
+    /// Opens the database in the bucket configured in `store_config`, keeping
+    /// the index and the cache in the directory at `path`.
+    pub fn open(path: &Path, store_config: &StoreConfig, mode: Mode) -> io::Result<Self> {
+        let config = store_config.object_storage.as_ref().ok_or_else(|| {
+            io::Error::other("`object_storage` must be configured for the object storage backend")
+        })?;
+        let index_path = path.join("index");
+        if !mode.can_create() && !index_path.exists() {
+            let msg = format!("database index does not exist at {}", index_path.display());
+            return Err(io::Error::new(io::ErrorKind::NotFound, msg));
+        }
+        let region = match &config.endpoint {
+            Some(endpoint) => {
+                s3::Region::Custom { region: config.region.clone(), endpoint: endpoint.clone() }
+            }
+            None => config.region.parse().map_err(io::Error::other)?,
+        };
+        let credentials = s3::creds::Credentials::default().map_err(io::Error::other)?;
+        let mut bucket =
+            Bucket::new(&config.bucket, region, credentials).map_err(io::Error::other)?;
+        if config.endpoint.is_some() {
+            bucket = bucket.with_path_style();
+        }
+        bucket.set_request_timeout(Some(REQUEST_TIMEOUT));
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(4)
+            .thread_name("object_storage")
+            .enable_all()
+            .build()?;
+        let db = Self {
+            bucket: Arc::new(bucket),
+            prefix: config.prefix.clone(),
+            runtime: Some(runtime),
+            index: RocksDB::open(&index_path, store_config, mode, Temperature::Cold)?,
+            cache: ObjectCache::open(path.join("cache"), config.cache_size.as_u64())?,
+            last_segment_id: Mutex::new(0),
+            read_only: mode.read_only(),
+        };
+
+        let exists = db.index.get_raw_bytes(DBCol::DbVersion, metadata::VERSION_KEY)?.is_some();
+        if mode.must_create() && (exists || db.has_segments()?) {
+            let msg = format!(
+                "database already exists in bucket {} with prefix {:?}",
+                config.bucket, config.prefix
+            );
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, msg));
+        }
+        if !mode.can_create() && !exists {
+            let msg = format!("database does not exist at {}", index_path.display());
+            return Err(io::Error::new(io::ErrorKind::NotFound, msg));
+        }
+        Ok(db)
+    }
+
+    /// Returns version and kind of the database, or `None` if it doesn’t exist.
+    pub(crate) fn get_metadata(
+        path: &Path,
+        config: &StoreConfig,
+    ) -> io::Result<Option<metadata::DbMetadata>> {
+        let db = match Self::open(path, config, Mode::ReadOnly) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            db => db?,
+        };
+        Some(metadata::DbMetadata::read(&db)).transpose()
+    }
+
+    /// Whether the bucket holds any segments under the prefix, i.e. another
+    /// database whose index is elsewhere.
+    fn has_segments(&self) -> io::Result<bool> {
+        let bucket = self.bucket.clone();
+        let prefix = self.segment_name_prefix();
+        let mut pages = self.run(vec![async move {
+            bucket.list_page(prefix, None, None, None, Some(1)).await.map_err(io::Error::other)
+        }])?;
+        let (page, _) = pages.pop().unwrap();
+        Ok(!page.contents.is_empty())
+    }
+
+    fn segment_name_prefix(&self) -> String {
+        format!("{}segments/", self.prefix)
+    }
+
+    fn segment_name(&self, segment_id: u64) -> String {
+        format!("{}{segment_id:020}", self.segment_name_prefix())
+    }
+
+    /// Returns an id larger than the ids of all segments written before.
+    ///
+    /// The ids are the time of the write in microseconds, so that they don't
+    /// repeat after a restart or after the index is restored from a
+    /// snapshot, when the segments of the writes after the snapshot still
+    /// exist and their values may be cached.
+    fn next_segment_id(&self) -> u64 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_micros() as u64);
+        let mut last_segment_id = self.last_segment_id.lock().unwrap();
+        *last_segment_id = now.max(*last_segment_id + 1);
+        *last_segment_id
+    }
+
+    /// Runs the requests, at most [`MAX_CONCURRENT_REQUESTS`] at a time, and
+    /// waits for all of them to finish.
+    ///
+    /// The requests run on the runtime of the database rather than on the
+    /// calling thread, which may itself belong to an async runtime.
+    fn run<T, F>(&self, requests: Vec<F>) -> io::Result<Vec<T>>
+    where
+        T: Send + 'static,
+        F: Future<Output = io::Result<T>> + Send + 'static,
+    {
+        if requests.is_empty() {
+            return Ok(vec![]);
+        }
+        let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_REQUESTS));
+        self.runtime.as_ref().expect("the runtime is only taken on drop").spawn(async move {
+            let handles = requests
+                .into_iter()
+                .map(|request| {
+                    let semaphore = semaphore.clone();
+                    tokio::spawn(async move {
+                        let _permit = semaphore.acquire_owned().await;
+                        request.await
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut results = Vec::with_capacity(handles.len());
+            for handle in handles {
+                results.push(handle.await.unwrap_or_else(|err| Err(io::Error::other(err))));
+            }
+            let _ = sender.send(results.into_iter().collect::<io::Result<Vec<T>>>());
+        });
+        receiver.recv().map_err(|_| io::Error::other("the object storage runtime has stopped"))?
+    }
+
+    /// Reads the values at the locations stored in the index, from the cache
+    /// where possible.
+    fn read_values(&self, locations: &[&[u8]]) -> io::Result<Vec<Vec<u8>>> {
+        let locations = locations
+            .iter()
+            .map(|location| ValueLocation::try_from_slice(location))
+            .collect::<io::Result<Vec<_>>>()?;
+        let names = locations
+            .iter()
+            .map(|location| {
+                let range = location.range;
+                format!("{}:{}:{}", self.segment_name(location.segment_id), range.offset, range.len)
+            })
+            .collect::<Vec<_>>();
+        let mut values = names.iter().map(|name| self.cache.get(name)).collect::<Vec<_>>();
+        let missing = (0..names.len()).filter(|&i| values[i].is_none()).collect::<Vec<_>>();
+        let requests = missing
+            .iter()
+            .map(|&i| {
+                let name = self.segment_name(locations[i].segment_id);
+                get_object_range(self.bucket.clone(), name, locations[i].range)
+            })
+            .collect::<Vec<_>>();
+        for (i, value) in missing.into_iter().zip(self.run(requests)?) {
+            self.cache.put(&names[i], &value);
+            values[i] = Some(value);
+        }
+        Ok(values.into_iter().map(Option::unwrap).collect())
+    }
+
+    fn iter_raw_bytes_internal<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> ObjectStorageIterator<'a> {
+        ObjectStorageIterator {
+            db: self,
+            locations: self.index.iter_raw_bytes_range(col, lower_bound, upper_bound),
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+async fn get_object_range(
+    bucket: Arc<Bucket>,
+    name: String,
+    range: ValueRange,
+) -> io::Result<Vec<u8>> {
+    if range.len == 0 {
+        return Ok(vec![]);
+    }
+    let end = range.offset + u64::from(range.len) - 1;
+    let response = match bucket.get_object_range(&name, range.offset, Some(end)).await {
+        Ok(response) => response,
+        Err(S3Error::Http(404, _)) => {
+            return Err(io::Error::other(format!("segment {name} is missing")))
+        }
+        Err(err) => return Err(io::Error::other(err)),
+    };
+    if response.status_code() / 100 != 2 {
+        return Err(io::Error::other(format!(
+            "reading segment {name} failed with status {}",
+            response.status_code()
+        )));
+    }
+    let data = response.bytes().to_vec();
+    if data.len() != range.len as usize {
+        let msg = format!("segment {name} is truncated at offset {}", range.offset);
+        return Err(io::Error::other(msg));
+    }
+    Ok(data)
+}
+
+async fn put_object(bucket: Arc<Bucket>, name: String, data: Vec<u8>) -> io::Result<()> {
+    let response = bucket.put_object(&name, &data).await.map_err(io::Error::other)?;
+    if response.status_code() / 100 != 2 {
+        let msg = format!("writing object {name} failed with status {}", response.status_code());
+        return Err(io::Error::other(msg));
+    }
+    Ok(())
+}
+
+/// Iterates over the keys in the index, reading the values of every batch of
+/// keys concurrently.
+struct ObjectStorageIterator<'a> {
+    db: &'a ObjectStorageDB,
+    /// Keys in the index with the locations of their values.
+    locations: DBIterator<'a>,
+    buffer: VecDeque<(Box<[u8]>, Box<[u8]>)>,
+    done: bool,
+}
+
+impl<'a> ObjectStorageIterator<'a> {
+    fn fetch(&mut self) -> io::Result<()> {
+        let batch = self
+            .locations
+            .by_ref()
+            .take(ITER_BATCH_SIZE)
+            .collect::<io::Result<Vec<(Box<[u8]>, Box<[u8]>)>>>()?;
+        self.done = batch.len() < ITER_BATCH_SIZE;
+        let locations = batch.iter().map(|(_, location)| &**location).collect::<Vec<_>>();
+        let values = self.db.read_values(&locations)?;
+        for ((key, _), value) in batch.into_iter().zip(values) {
+            self.buffer.push_back((key, value.into_boxed_slice()));
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for ObjectStorageIterator<'a> {
+    type Item = io::Result<(Box<[u8]>, Box<[u8]>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.is_empty() && !self.done {
+            if let Err(err) = self.fetch() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+impl Database for ObjectStorageDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        let Some(location) = self.index.get_raw_bytes(col, key)? else {
+            return Ok(None);
+        };
+        let value = self.read_values(&[&*location])?.pop().unwrap();
+        Ok(Some(DBSlice::from_vec(value)))
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        refcount::iter_with_rc_logic(col, self.iter_raw_bytes_internal(col, None, None))
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        let upper_bound = prefix_upper_bound(key_prefix);
+        let iter = self.iter_raw_bytes_internal(col, Some(key_prefix), upper_bound.as_deref());
+        refcount::iter_with_rc_logic(col, iter)
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        let iter = self.iter_raw_bytes_internal(col, lower_bound, upper_bound);
+        refcount::iter_with_rc_logic(col, iter)
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        Box::new(self.iter_raw_bytes_internal(col, None, None))
+    }
+
+    fn iter_raw_bytes_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        Box::new(self.iter_raw_bytes_internal(col, lower_bound, upper_bound))
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::Error::other("the database is opened in read-only mode"));
+        }
+        if transaction.ops.is_empty() {
+            return Ok(());
+        }
+        let (segments, updates) = build_segments(transaction.ops, MAX_SEGMENT_SIZE)?;
+        let segment_ids = segments.iter().map(|_| self.next_segment_id()).collect::<Vec<_>>();
+        let mut puts = Vec::with_capacity(segments.len());
+        for (segment, &segment_id) in segments.into_iter().zip(&segment_ids) {
+            let name = self.segment_name(segment_id);
+            puts.push(put_object(self.bucket.clone(), name, segment.finish()?));
+        }
+        self.run(puts)?;
+
+        // The index is only updated once all the segments are uploaded, in a
+        // single batch, which makes the transaction atomic.
+        let mut index_transaction = DBTransaction::new();
+        for (col, key, location) in updates {
+            match location {
+                Some((segment, range)) => {
+                    let location = ValueLocation { segment_id: segment_ids[segment], range };
+                    index_transaction.set(col, key, borsh::to_vec(&location)?);
+                }
+                None => index_transaction.delete(col, key),
+            }
+        }
+        self.index.write(index_transaction)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.index.flush()
+    }
+
+    fn compact(&self) -> io::Result<()> {
+        self.index.compact()
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        None
+    }
+
+    /// Creates a checkpoint of the index in the `index` subdirectory of
+    /// `path`, mirroring the layout of the database directory.
+    fn create_checkpoint(
+        &self,
+        path: &std::path::Path,
+        columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        std::fs::create_dir_all(path)?;
+        self.index.create_checkpoint(&path.join("index"), columns_to_keep)
+    }
+}
+
+impl Drop for ObjectStorageDB {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which panics if the database is dropped
+        // on a thread of another runtime.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Read-through cache of values in local files, which evicts the least
+/// recently used values once their total size exceeds the limit.
+///
+/// The values are named after their segment and range, which never change.
+/// The files are named after the hash of that name and spread over
+/// subdirectories by the first byte of the hash.  The cache survives restarts;
+/// on open the files are ordered by their modification time.
+struct ObjectCache {
+    dir: PathBuf,
+    max_size: u64,
+    /// File names of the cached objects with their sizes, and the total size.
+    index: Mutex<(lru::LruCache<String, u64>, u64)>,
+}
+
+impl ObjectCache {
+    fn open(dir: PathBuf, max_size: u64) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let mut files = Vec::new();
+        for subdir in std::fs::read_dir(&dir)? {
+            let subdir = subdir?;
+            if !subdir.file_type()?.is_dir() {
+                continue;
+            }
+            for file in std::fs::read_dir(subdir.path())? {
+                let file = file?;
+                let metadata = file.metadata()?;
+                if let (true, Ok(name)) = (metadata.is_file(), file.file_name().into_string()) {
+                    files.push((metadata.modified()?, name, metadata.len()));
+                }
+            }
+        }
+        files.sort();
+        let mut entries = lru::LruCache::unbounded();
+        let mut total_size = 0;
+        for (_, name, size) in files {
+            entries.put(name, size);
+            total_size += size;
+        }
+        let cache = Self { dir, max_size, index: Mutex::new((entries, total_size)) };
+        cache.evict(&mut cache.index.lock().unwrap());
+        Ok(cache)
+    }
+
+    fn file_name(object_name: &str) -> String {
+        hex::encode(near_primitives::hash::hash(object_name.as_bytes()).as_ref())
+    }
+
+    fn path(&self, file_name: &str) -> PathBuf {
+        self.dir.join(&file_name[..2]).join(file_name)
+    }
+
+    fn get(&self, object_name: &str) -> Option<Vec<u8>> {
+        let file_name = Self::file_name(object_name);
+        self.index.lock().unwrap().0.get(&file_name)?;
+        // The file may have been evicted in the meantime, which is a miss.
+        std::fs::read(self.path(&file_name)).ok()
+    }
+
+    fn put(&self, object_name: &str, data: &[u8]) {
+        let size = data.len() as u64;
+        if size > self.max_size {
+            return;
+        }
+        let file_name = Self::file_name(object_name);
+        let path = self.path(&file_name);
+        // The file is written under a temporary name, so that readers never
+        // see it partially written.
+        let result = std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|()| tempfile::NamedTempFile::new_in(&self.dir))
+            .and_then(|mut file| {
+                std::io::Write::write_all(&mut file, data)?;
+                file.persist(&path).map_err(|err| err.error)
+            });
+        if let Err(err) = result {
+            tracing::warn!(target: "store", ?err, "Failed to cache an object");
+            return;
+        }
+        let mut index = self.index.lock().unwrap();
+        if let Some(old_size) = index.0.put(file_name, size) {
+            index.1 -= old_size;
+        }
+        index.1 += size;
+        self.evict(&mut index);
+    }
+
+    fn evict(&self, index: &mut (lru::LruCache<String, u64>, u64)) {
+        while index.1 > self.max_size {
+            let Some((file_name, size)) = index.0.pop_lru() else {
+                break;
+            };
+            index.1 -= size;
+            let _ = std::fs::remove_file(self.path(&file_name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_segments, ObjectCache, SegmentEntry, ValueRange};
+    use crate::db::DBOp;
+    use crate::DBCol;
+    use borsh::BorshDeserialize;
+
+    #[test]
+    fn test_build_segments() {
+        let ops = vec![
+            DBOp::Set { col: DBCol::Block, key: vec![1], value: vec![1; 4] },
+            DBOp::Insert { col: DBCol::Chunks, key: vec![2], value: vec![2; 4] },
+            DBOp::Delete { col: DBCol::BlockMisc, key: vec![3] },
+            DBOp::Set { col: DBCol::Block, key: vec![4], value: vec![4; 4] },
+        ];
+        let (segments, updates) = build_segments(ops, 8).unwrap();
+        let range = |offset, len| ValueRange { offset, len };
+        assert_eq!(
+            updates,
+            vec![
+                (DBCol::Block, vec![1], Some((0, range(0, 4)))),
+                (DBCol::Chunks, vec![2], Some((0, range(4, 4)))),
+                (DBCol::BlockMisc, vec![3], None),
+                (DBCol::Block, vec![4], Some((1, range(0, 4)))),
+            ]
+        );
+
+        let data =
+            segments.into_iter().map(|segment| segment.finish().unwrap()).collect::<Vec<_>>();
+        assert_eq!(data[0][..8], [1, 1, 1, 1, 2, 2, 2, 2]);
+        assert_eq!(data[1][..4], [4, 4, 4, 4]);
+        let entries_len = u64::from_le_bytes(data[0][data[0].len() - 8..].try_into().unwrap());
+        let entries = Vec::<SegmentEntry>::try_from_slice(&data[0][8..data[0].len() - 8]).unwrap();
+        assert_eq!(entries_len as usize, data[0].len() - 16);
+        assert_eq!(
+            entries,
+            vec![
+                SegmentEntry { col: "Block".to_string(), key: vec![1], range: Some(range(0, 4)) },
+                SegmentEntry { col: "Chunks".to_string(), key: vec![2], range: Some(range(4, 4)) },
+                SegmentEntry { col: "BlockMisc".to_string(), key: vec![3], range: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_segments_rejects_refcount_updates() {
+        let ops = vec![DBOp::UpdateRefcount { col: DBCol::State, key: vec![1], value: vec![1] }];
+        assert!(build_segments(ops, 8).is_err());
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ObjectCache::open(dir.path().to_path_buf(), 10).unwrap();
+        cache.put("a", &[1; 4]);
+        cache.put("b", &[2; 4]);
+        assert_eq!(cache.get("a"), Some(vec![1; 4]));
+        cache.put("c", &[3; 4]);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(vec![1; 4]));
+        drop(cache);
+
+        let cache = ObjectCache::open(dir.path().to_path_buf(), 10).unwrap();
+        assert_eq!(cache.get("c"), Some(vec![3; 4]));
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard};
+
+use crate::db::{DBIterator, DBOp, DBSlice, DBTransaction, Database, StoreStatistics};
+use crate::DBCol;
+
+/// Maximum number of batches waiting to be written.  Writes block once the
+/// queue is full so that the background writer can't fall behind indefinitely.
+const MAX_QUEUED_BATCHES: usize = 16;
+
+/// A database which applies writes on a background thread.
+///
+/// [`Database::write`] queues the batch and returns without waiting for the
+/// underlying database.  The batches are applied one by one in the order in
+/// which they were queued, so a batch never reaches the disk before the
+/// batches queued earlier.  In particular, the chain head is never updated on
+/// disk before the blocks and chunks it refers to.
+///
+/// Reads see the queued writes.  Values set or deleted by queued batches are
+/// served from memory.  Reads from a column with queued reference count
+/// updates or range deletions, which can't be resolved in memory, and
+/// iterators over a column with any queued operations wait until these
+/// batches are written.
+///
+/// If writing a batch fails, the following batches are discarded, since
+/// writing them would break the ordering, and all further operations return
+/// the error.
+pub struct PipelinedDB {
+    db: Arc<dyn Database>,
+    /// Sender of the queued batches with their sequence numbers.  The lock
+    /// also makes sure that batches are queued in the order of their sequence
+    /// numbers.
+    sender: Mutex<Option<mpsc::SyncSender<(u64, DBTransaction)>>>,
+    pending: Arc<(Mutex<PendingWrites>, Condvar)>,
+    writer: Option<std::thread::JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct PendingWrites {
+    /// Sequence number of the last queued batch.
+    queued: u64,
+    /// Sequence number of the last written batch.
+    written: u64,
+    /// Latest queued value of every key set or deleted by the queued batches,
+    /// with the sequence number of the batch.  `None` is a deletion.
+    values: HashMap<(DBCol, Vec<u8>), (u64, Option<Vec<u8>>)>,
+    /// Columns with queued operations which can't be resolved in memory, with
+    /// the sequence number of the last batch containing such operation.
+    unresolved: HashMap<DBCol, u64>,
+    /// Columns with any queued operations, with the sequence number of the
+    /// last batch containing an operation on the column.
+    columns: HashMap<DBCol, u64>,
+    /// Error of a failed write.
+    error: Option<String>,
+}
+
+impl PendingWrites {
+    fn queue(&mut self, batch: &DBTransaction) -> u64 {
+        self.queued += 1;
+        let seq = self.queued;
+        for op in &batch.ops {
+            self.columns.insert(op.col(), seq);
+            match op {
+                DBOp::Set { col, key, value } | DBOp::Insert { col, key, value } => {
+                    self.values.insert((*col, key.clone()), (seq, Some(value.clone())));
+                }
+                DBOp::Delete { col, key } => {
+                    self.values.insert((*col, key.clone()), (seq, None));
+                }
+                DBOp::UpdateRefcount { col, .. }
+                | DBOp::DeleteAll { col }
+                | DBOp::DeleteRange { col, .. } => {
+                    self.unresolved.insert(*col, seq);
+                }
+            }
+        }
+        seq
+    }
+
+    fn mark_written(&mut self, seq: u64) {
+        self.written = seq;
+        self.values.retain(|_, (value_seq, _)| *value_seq > seq);
+        self.unresolved.retain(|_, col_seq| *col_seq > seq);
+        self.columns.retain(|_, col_seq| *col_seq > seq);
+    }
+
+    fn check_error(&self) -> io::Result<()> {
+        match &self.error {
+            Some(error) => Err(io::Error::other(format!("asynchronous write failed: {error}"))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PipelinedDB {
+    pub fn new(db: Arc<dyn Database>) -> Arc<Self> {
+        let (sender, receiver) = mpsc::sync_channel(MAX_QUEUED_BATCHES);
+        let pending = Arc::new((Mutex::new(PendingWrites::default()), Condvar::new()));
+        let writer = {
+            let db = db.clone();
+            let pending = pending.clone();
+            std::thread::Builder::new()
+                .name("store_writer".to_string())
+                .spawn(move || Self::run_writer(db.as_ref(), receiver, &pending))
+                .expect("failed to spawn the store writer thread")
+        };
+        Arc::new(Self { db, sender: Mutex::new(Some(sender)), pending, writer: Some(writer) })
+    }
+
+    fn run_writer(
+        db: &dyn Database,
+        receiver: mpsc::Receiver<(u64, DBTransaction)>,
+        pending: &(Mutex<PendingWrites>, Condvar),
+    ) {
+        let (lock, condvar) = pending;
+        for (seq, batch) in receiver {
+            let failed = lock.lock().unwrap().error.is_some();
+            let result = if failed { Ok(()) } else { db.write(batch) };
+            let mut pending = lock.lock().unwrap();
+            if let Err(err) = result {
+                tracing::error!(target: "store", ?err, "Failed to write a queued batch");
+                pending.error = Some(err.to_string());
+            }
+            pending.mark_written(seq);
+            condvar.notify_all();
+        }
+    }
+
+    /// Waits until `done` returns true for the pending writes and returns them
+    /// locked.
+    fn wait_until(
+        &self,
+        done: impl Fn(&PendingWrites) -> bool,
+    ) -> io::Result<MutexGuard<'_, PendingWrites>> {
+        let (lock, condvar) = &*self.pending;
+        let pending = condvar
+            .wait_while(lock.lock().unwrap(), |pending| pending.error.is_none() && !done(pending))
+            .unwrap();
+        pending.check_error()?;
+        Ok(pending)
+    }
+
+    /// Waits until there are no queued operations on the column.
+    fn wait_for_column(&self, col: DBCol) -> io::Result<()> {
+        self.wait_until(|pending| !pending.columns.contains_key(&col)).map(drop)
+    }
+
+    /// Waits until all queued batches are written.
+    fn wait_for_all(&self) -> io::Result<()> {
+        self.wait_until(|pending| pending.written == pending.queued).map(drop)
+    }
+
+    fn iter_after_writes<'a>(
+        &'a self,
+        col: DBCol,
+        iter: impl FnOnce() -> DBIterator<'a>,
+    ) -> DBIterator<'a> {
+        match self.wait_for_column(col) {
+            Ok(()) => iter(),
+            Err(err) => Box::new(std::iter::once(Err(err))),
+        }
+    }
+}
+
+impl Database for PipelinedDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        {
+            let pending = self.wait_until(|pending| !pending.unresolved.contains_key(&col))?;
+            if let Some((_, value)) = pending.values.get(&(col, key.to_vec())) {
+                return Ok(value.clone().map(DBSlice::from_vec));
+            }
+        }
+        self.db.get_raw_bytes(col, key)
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        self.iter_after_writes(col, || self.db.iter(col))
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        self.iter_after_writes(col, || self.db.iter_prefix(col, key_prefix))
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        self.iter_after_writes(col, || self.db.iter_range(col, lower_bound, upper_bound))
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        self.iter_after_writes(col, || self.db.iter_raw_bytes(col))
+    }
+
+    fn iter_raw_bytes_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        self.iter_after_writes(col, || self.db.iter_raw_bytes_range(col, lower_bound, upper_bound))
+    }
+
+    fn write(&self, batch: DBTransaction) -> io::Result<()> {
+        let sender = self.sender.lock().unwrap();
+        let seq = {
+            let mut pending = self.pending.0.lock().unwrap();
+            pending.check_error()?;
+            pending.queue(&batch)
+        };
+        sender
+            .as_ref()
+            .and_then(|sender| sender.send((seq, batch)).ok())
+            .ok_or_else(|| io::Error::other("the store writer thread has stopped"))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.wait_for_all()?;
+        self.db.flush()
+    }
+
+    fn compact(&self) -> io::Result<()> {
+        self.wait_for_all()?;
+        self.db.compact()
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        self.db.get_store_statistics()
+    }
+
+    fn create_checkpoint(
+        &self,
+        path: &std::path::Path,
+        columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        self.wait_for_all()?;
+        self.db.create_checkpoint(path, columns_to_keep)
+    }
+}
+
+impl Drop for PipelinedDB {
+    /// Writes the queued batches before the database is closed.
+    fn drop(&mut self) {
+        self.sender.lock().unwrap().take();
+        if let Some(writer) = self.writer.take() {
+            if writer.join().is_err() {
+                tracing::error!(target: "store", "The store writer thread panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PipelinedDB;
+    use crate::db::{refcount, DBTransaction, Database, TestDB};
+    use crate::DBCol;
+
+    #[test]
+    fn test_reads_see_queued_writes() {
+        let inner = TestDB::new();
+        let db = PipelinedDB::new(inner.clone());
+
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::Block, vec![1], vec![1]);
+        transaction.set(DBCol::Block, vec![2], vec![2]);
+        transaction.update_refcount(
+            DBCol::State,
+            vec![3],
+            refcount::add_positive_refcount(&[3], std::num::NonZeroU32::new(1).unwrap()),
+        );
+        db.write(transaction).unwrap();
+        let mut transaction = DBTransaction::new();
+        transaction.delete(DBCol::Block, vec![2]);
+        db.write(transaction).unwrap();
+
+        assert_eq!(db.get_raw_bytes(DBCol::Block, &[1]).unwrap().as_deref(), Some(&[1][..]));
+        assert!(db.get_raw_bytes(DBCol::Block, &[2]).unwrap().is_none());
+        assert_eq!(db.get_with_rc_stripped(DBCol::State, &[3]).unwrap().as_deref(), Some(&[3][..]));
+        let keys: Vec<_> = db.iter(DBCol::Block).map(|item| item.unwrap().0).collect();
+        assert_eq!(keys, vec![vec![1].into_boxed_slice()]);
+
+        db.flush().unwrap();
+        assert!(inner.get_raw_bytes(DBCol::Block, &[1]).unwrap().is_some());
+        assert!(inner.get_raw_bytes(DBCol::Block, &[2]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_drop_writes_queued_batches() {
+        let inner = TestDB::new();
+        let db = PipelinedDB::new(inner.clone());
+        for i in 0..100u8 {
+            let mut transaction = DBTransaction::new();
+            transaction.set(DBCol::Block, vec![i], vec![i]);
+            db.write(transaction).unwrap();
+        }
+        drop(db);
+        assert_eq!(inner.iter(DBCol::Block).count(), 100);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::db::{
+    prefix_upper_bound, refcount, DBIterator, DBSlice, DBTransaction, Database, StoreStatistics,
+};
+use crate::remote::{RemoteStore, MAX_ITER_ENTRIES};
+use crate::DBCol;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Read-only database reading from the [`crate::remote::StoreServer`] of
+/// another node, so that its data can be accessed through a [`crate::Store`],
+/// e.g. to repair the cold storage from another archival node.
+///
+/// The server strips the reference counts, so values of reference-counted
+/// columns are returned with a reference count of one.  Writes fail.
+///
+/// The requests block on a runtime owned by the database, so it must not be
+/// used from within an async context.
+pub struct RemoteDB {
+    runtime: tokio::runtime::Runtime,
+    client: Mutex<RemoteStore>,
+}
+
+impl RemoteDB {
+    /// Connects to the store server listening on the socket at `path`.
+    pub fn connect(path: &Path) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let client = runtime.block_on(RemoteStore::connect(path))?;
+        Ok(Self { runtime, client: Mutex::new(client) })
+    }
+
+    fn get(&self, col: DBCol, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        self.runtime.block_on(self.client.lock().unwrap().get(col, key))
+    }
+
+    /// Iterates over the entries with keys in `[lower_bound, upper_bound)`,
+    /// fetching them from the server [`MAX_ITER_ENTRIES`] at a time.
+    fn iter_remote<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<Vec<u8>>,
+        upper_bound: Option<Vec<u8>>,
+    ) -> DBIterator<'a> {
+        let mut next = lower_bound;
+        let mut batch = Vec::new().into_iter();
+        let mut done = false;
+        Box::new(std::iter::from_fn(move || loop {
+            if let Some((key, value)) = batch.next() {
+                return Some(Ok((Vec::into_boxed_slice(key), Vec::into_boxed_slice(value))));
+            }
+            if done {
+                return None;
+            }
+            let entries = self.runtime.block_on(self.client.lock().unwrap().iter_range(
+                col,
+                next.as_deref(),
+                upper_bound.as_deref(),
+                MAX_ITER_ENTRIES,
+            ));
+            match entries {
+                Ok(entries) => {
+                    done = entries.len() < MAX_ITER_ENTRIES as usize;
+                    // The smallest key after the last returned one.
+                    next = entries.last().map(|(key, _)| [key.as_slice(), &[0]].concat());
+                    batch = entries.into_iter();
+                }
+                Err(err) => {
+                    done = true;
+                    return Some(Err(err));
+                }
+            }
+        }))
+    }
+}
+
+impl Database for RemoteDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        let value = self.get(col, key)?;
+        Ok(value.map(|value| match col.is_rc() {
+            true => DBSlice::from_vec(refcount::add_positive_refcount(
+                &value,
+                std::num::NonZeroU32::MIN,
+            )),
+            false => DBSlice::from_vec(value),
+        }))
+    }
+
+    fn get_with_rc_stripped(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        assert!(col.is_rc());
+        Ok(self.get(col, key)?.map(DBSlice::from_vec))
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        self.iter_remote(col, None, None)
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        self.iter_remote(col, Some(key_prefix.to_vec()), prefix_upper_bound(key_prefix))
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        self.iter_remote(col, lower_bound.map(<[u8]>::to_vec), upper_bound.map(<[u8]>::to_vec))
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        let msg = format!("iterating over raw bytes of {col} is not supported by the remote db");
+        Box::new(std::iter::once(Err(io::Error::other(msg))))
+    }
+
+    fn write(&self, _batch: DBTransaction) -> io::Result<()> {
+        Err(io::Error::other("the remote db is read-only"))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn compact(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        None
+    }
+
+    fn create_checkpoint(
+        &self,
+        _path: &Path,
+        _columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("checkpoints of the remote db are not supported")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RemoteDB;
+    use crate::remote::StoreServer;
+    use crate::test_utils::create_test_store;
+    use crate::{DBCol, NodeStorage};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_remote_db() {
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        for i in 0..10u8 {
+            store_update.set(DBCol::BlockHeight, &[1, i], &[i * 2]);
+        }
+        store_update.increment_refcount(DBCol::State, &[1], &[42]);
+        store_update.commit().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.sock");
+        let server = StoreServer::start(store, &path).unwrap();
+        let remote = NodeStorage::new(Arc::new(RemoteDB::connect(&path).unwrap())).get_hot_store();
+
+        assert_eq!(remote.get(DBCol::BlockHeight, &[1, 3]).unwrap().as_deref(), Some(&[6][..]));
+        assert_eq!(remote.get(DBCol::State, &[1]).unwrap().as_deref(), Some(&[42][..]));
+        let keys: Vec<_> =
+            remote.iter_prefix(DBCol::BlockHeight, &[1]).map(|item| item.unwrap().0).collect();
+        assert_eq!(keys.len(), 10);
+        let mut store_update = remote.store_update();
+        store_update.set(DBCol::BlockHeight, &[2], &[0]);
+        assert!(store_update.commit().is_err());
+
+        drop(remote);
+        server.stop();
+    }
+}
@@ -1,8 +1,12 @@
 use crate::config::Mode;
 use crate::db::{refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database, StatsValue};
-use crate::{metadata, metrics, DBCol, StoreConfig, StoreStatistics, Temperature};
+use crate::{
+    metadata, metrics, ColumnCompression, CompressionAlgorithm, DBCol, StoreConfig,
+    StoreStatistics, Temperature, WalSyncPolicy,
+};
 use ::rocksdb::{
-    BlockBasedOptions, Cache, ColumnFamily, Env, IteratorMode, Options, ReadOptions, WriteBatch, DB,
+    BlockBasedOptions, Cache, ColumnFamily, Env, IteratorMode, Options, ReadOptions, WriteBatch,
+    WriteOptions, DB,
 };
 use anyhow::Context;
 use itertools::Itertools;
@@ -46,6 +50,7 @@ static CF_PROPERTY_NAMES: Lazy<Vec<std::ffi::CString>> = Lazy::new(|| {
 pub struct RocksDB {
     db: DB,
     db_opt: Options,
+    write_opt: WriteOptions,
 
     /// Map from [`DBCol`] to a column family handler in the RocksDB.
     ///
@@ -80,8 +85,8 @@ impl RocksDB {
     /// database version different than expected.
     ///
     /// `temp` specifies whether the database is cold or hot which affects
-    /// whether refcount merge operator is configured on reference counted
-    /// column.
+    /// whether the empty value compaction filter is configured on reference
+    /// counted columns.
     pub fn open(
         path: &Path,
         store_config: &StoreConfig,
@@ -119,7 +124,9 @@ impl RocksDB {
             .map_err(io::Error::other)?;
         let (db, db_opt) = Self::open_db(path, store_config, mode, temp, columns)?;
         let cf_handles = Self::get_cf_handles(&db, columns);
-        Ok(Self { db, db_opt, cf_handles, _instance_tracker: counter })
+        let mut write_opt = WriteOptions::default();
+        write_opt.set_sync(store_config.wal_sync == WalSyncPolicy::EveryWrite);
+        Ok(Self { db, db_opt, write_opt, cf_handles, _instance_tracker: counter })
     }
 
     /// Opens the database with given column families configured.
@@ -298,6 +305,39 @@ impl RocksDB {
         Ok(())
     }
 
+    /// Compacts the keys of the column between `start` (inclusive) and `end`
+    /// (exclusive). A missing bound means the beginning or the end of the column.
+    pub fn compact_column_range(
+        &self,
+        col: DBCol,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> io::Result<()> {
+        tracing::debug!(target: "store::db::rocksdb", col = %col, ?start, ?end, "RocksDB::compact_column_range");
+        self.db.compact_range_cf(self.cf_handle(col)?, start, end);
+        Ok(())
+    }
+
+    /// Runs the RocksDB repair routine on the database at `path`.  It recovers
+    /// as much data of every column family as possible from the SST files and
+    /// the write-ahead log after a corruption.  Unreadable files are moved to
+    /// the `lost` directory inside the database directory.
+    pub fn repair(path: &Path, store_config: &StoreConfig) -> io::Result<()> {
+        let options = rocksdb_options(store_config, Mode::ReadWriteExisting);
+        DB::repair(&options, path).map_err(io::Error::other)
+    }
+
+    /// Returns the number of live SST files of each column.
+    pub fn sst_file_counts(&self) -> io::Result<enum_map::EnumMap<DBCol, usize>> {
+        let mut counts = enum_map::EnumMap::default();
+        let files = self.db.live_files().map_err(io::Error::other)?;
+        for (col, _) in self.cf_handles() {
+            let name = col_name(col);
+            counts[col] = files.iter().filter(|file| file.column_family_name == name).count();
+        }
+        Ok(counts)
+    }
+
     #[tracing::instrument(
         target = "store::db::rocksdb",
         level = "trace",
@@ -381,6 +421,15 @@ impl Database for RocksDB {
         refcount::iter_with_rc_logic(col, iter)
     }
 
+    fn iter_raw_bytes_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        Box::new(self.iter_raw_bytes_internal(col, None, lower_bound, upper_bound))
+    }
+
     #[tracing::instrument(
         target = "store::db::rocksdb",
         level = "trace",
@@ -399,7 +448,7 @@ impl Database for RocksDB {
                 backtrace = %std::backtrace::Backtrace::force_capture()
             );
         }
-        self.db.write(batch).map_err(io::Error::other)
+        self.db.write_opt(batch, &self.write_opt).map_err(io::Error::other)
     }
 
     #[tracing::instrument(
@@ -544,6 +593,7 @@ fn rocksdb_options(store_config: &StoreConfig, mode: Mode) -> Options {
     opts.create_missing_column_families(mode.read_write());
     opts.create_if_missing(mode.can_create());
     opts.set_max_open_files(store_config.max_open_files.try_into().unwrap_or(i32::MAX));
+    opts.set_wal_bytes_per_sync(store_config.wal_bytes_per_sync.as_u64());
     // TODO(mina86): Perhaps enable statistics even in read-only mode?
     if mode.read_write() && store_config.enable_statistics {
         // Rust API doesn't permit choosing stats level. The default stats level
@@ -578,8 +628,8 @@ fn rocksdb_block_based_options(store_config: &StoreConfig, db_col: DBCol) -> Blo
 
     let mut block_opts = BlockBasedOptions::default();
     block_opts.set_block_size(store_config.block_size.as_u64().try_into().unwrap());
-    // We create block_cache for each of the columns, so the total cache size is (num_of_columns - 2) * 32MiB
-    // Plus the 128MiB from FlatState and 512MiB from State columns
+    // We create block_cache for each of the columns, so by default the total cache size is
+    // (num_of_columns - 2) * 32MiB plus the 128MiB from FlatState and 512MiB from State columns
     block_opts.set_block_cache(&Cache::new_lru_cache(cache_size.as_u64().try_into().unwrap()));
     if use_block_cache_for_index_and_filter_blocks(db_col) {
         block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
@@ -614,13 +664,54 @@ fn rocksdb_column_options(col: DBCol, store_config: &StoreConfig, temp: Temperat
     opts.optimize_level_style_compaction(memtable_memory_budget);
 
     opts.set_target_file_size_base(64 * bytesize::MIB);
-    if temp == Temperature::Hot && col.is_rc() {
+    if let Some(compression) = store_config.col_compression.get(<&str>::from(col)) {
+        set_column_compression(&mut opts, compression);
+    }
+    if col.is_rc() {
+        // The cold store only writes plain values, except for State with state
+        // pruning enabled, but a cold database created from a checkpoint of an
+        // archival one may still contain the merge operands written by the
+        // archival node, see `cold-store split-in-place`, so the operator is
+        // needed to read them.
         opts.set_merge_operator("refcount merge", RocksDB::refcount_merge, RocksDB::refcount_merge);
-        opts.set_compaction_filter("empty value filter", RocksDB::empty_value_compaction_filter);
+        // Pruning the cold State leaves entries whose reference count dropped
+        // to zero, which are removed on compaction like in the hot store.
+        if temp == Temperature::Hot || col == DBCol::State {
+            opts.set_compaction_filter(
+                "empty value filter",
+                RocksDB::empty_value_compaction_filter,
+            );
+        }
     }
     opts
 }
 
+/// Replaces the compression set by [`set_compression_options`] and
+/// `optimize_level_style_compaction` with the one configured for the column.
+/// The first two levels stay uncompressed as they are short-lived.
+fn set_column_compression(opts: &mut Options, compression: &ColumnCompression) {
+    use rocksdb::DBCompressionType;
+    let algorithm = match compression.algorithm {
+        CompressionAlgorithm::None => DBCompressionType::None,
+        CompressionAlgorithm::Snappy => DBCompressionType::Snappy,
+        CompressionAlgorithm::Lz4 => DBCompressionType::Lz4,
+        CompressionAlgorithm::Lz4hc => DBCompressionType::Lz4hc,
+        CompressionAlgorithm::Zlib => DBCompressionType::Zlib,
+        CompressionAlgorithm::Zstd => DBCompressionType::Zstd,
+    };
+    // 32767 means the default level of the algorithm.
+    let level = compression.level.unwrap_or(32767);
+    // The default number of levels is 7.
+    let mut per_level = vec![DBCompressionType::None; 2];
+    per_level.extend([algorithm; 5]);
+    opts.set_compression_per_level(&per_level);
+    opts.set_compression_options(-14, level, 0, 0);
+    opts.set_bottommost_compression_type(algorithm);
+    // Keep the dictionary of the default bottommost compression for ZSTD.
+    let dict_size = if compression.algorithm == CompressionAlgorithm::Zstd { 2 * 16384 } else { 0 };
+    opts.set_bottommost_compression_options(-14, level, 0, dict_size, true);
+}
+
 fn set_compression_options(opts: &mut Options) {
     opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
     opts.set_bottommost_compression_type(rocksdb::DBCompressionType::Zstd);
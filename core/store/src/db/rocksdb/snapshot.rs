@@ -81,6 +81,21 @@ impl Snapshot {
         db_path: &std::path::Path,
         config: &crate::StoreConfig,
         temp: Temperature,
+    ) -> Result<Self, SnapshotError> {
+        Self::with_checkpoint(db_path, config, |snapshot_path| {
+            let db = super::RocksDB::open(db_path, config, crate::Mode::ReadWriteExisting, temp)?;
+            let cp = Checkpoint::new(&db.db).map_err(io::Error::other)?;
+            cp.create_checkpoint(snapshot_path).map_err(io::Error::other)
+        })
+    }
+
+    /// Like [`Self::new`] but creates the snapshot with the given function,
+    /// which is called with the path of the snapshot.  Used for databases
+    /// other than RocksDB.
+    pub fn with_checkpoint(
+        db_path: &std::path::Path,
+        config: &crate::StoreConfig,
+        create_checkpoint: impl FnOnce(&std::path::Path) -> io::Result<()>,
     ) -> Result<Self, SnapshotError> {
         let snapshot_path = match config.migration_snapshot.get_path(db_path) {
             Some(snapshot_path) => snapshot_path,
@@ -93,9 +108,7 @@ impl Snapshot {
             return Err(SnapshotError::AlreadyExists(snapshot_path));
         }
 
-        let db = super::RocksDB::open(db_path, config, crate::Mode::ReadWriteExisting, temp)?;
-        let cp = Checkpoint::new(&db.db).map_err(io::Error::other)?;
-        cp.create_checkpoint(&snapshot_path)?;
+        create_checkpoint(&snapshot_path)?;
 
         Ok(Self(Some(snapshot_path)))
     }
@@ -0,0 +1,516 @@
+use crate::db::{
+    prefix_upper_bound, refcount, DBIterator, DBOp, DBSlice, DBTransaction, Database,
+    StoreStatistics,
+};
+use crate::{metadata, DBCol, Mode, StoreConfig};
+use anyhow::Context;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use strum::IntoEnumIterator;
+
+/// Name of the SQLite file inside of the database directory.
+const DB_FILE_NAME: &str = "db.sqlite";
+
+/// Number of rows the iterators fetch from SQLite at once.
+const ITER_BATCH_SIZE: usize = 1000;
+
+/// Database stored in a single SQLite file, with one table per column.
+///
+/// It is an alternative to [`super::RocksDB`] meant for comparing the
+/// performance of storage engines, e.g. on read-heavy RPC nodes.  Keys are
+/// compared as blobs, i.e. byte-wise, so the iteration order is the same as in
+/// RocksDB.  Reads go through a pool of connections so that they can run
+/// concurrently, while writes are serialised through a single connection.
+pub struct SqliteDB {
+    writer: Mutex<Connection>,
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
+    /// Path and page cache size of the database file, for the connections of
+    /// iterators which find all the readers busy.
+    file: PathBuf,
+    cache_size_kib: u64,
+}
+
+impl SqliteDB {
+    /// Opens the database in the directory at `path`.
+    pub fn open(path: &Path, store_config: &StoreConfig, mode: Mode) -> io::Result<Self> {
+        let file = path.join(DB_FILE_NAME);
+        if mode.must_create() && file.exists() {
+            let msg = format!("database already exists at {}", file.display());
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, msg));
+        }
+        let writer_flags = if mode.read_only() {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        } else if mode.can_create() {
+            std::fs::create_dir_all(path)?;
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        };
+        let num_readers = num_cpus::get();
+        // SQLite keeps a separate page cache for every connection.
+        let cache_size_kib =
+            store_config.col_state_cache_size.as_u64() / 1024 / (num_readers as u64 + 1);
+
+        let writer = Self::connect(&file, writer_flags, cache_size_kib)?;
+        if !mode.read_only() {
+            writer.execute_batch("PRAGMA journal_mode = WAL").map_err(io::Error::other)?;
+            for col in DBCol::iter() {
+                let sql = format!(
+                    "CREATE TABLE IF NOT EXISTS \"{}\" (key BLOB PRIMARY KEY, value BLOB NOT NULL) WITHOUT ROWID",
+                    <&str>::from(col)
+                );
+                writer.execute(&sql, []).map_err(io::Error::other)?;
+            }
+        }
+        let readers = (0..num_readers)
+            .map(|_| {
+                Self::connect(&file, OpenFlags::SQLITE_OPEN_READ_ONLY, cache_size_kib)
+                    .map(Mutex::new)
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            writer: Mutex::new(writer),
+            readers,
+            next_reader: AtomicUsize::new(0),
+            file,
+            cache_size_kib,
+        })
+    }
+
+    fn connect(file: &Path, flags: OpenFlags, cache_size_kib: u64) -> io::Result<Connection> {
+        let conn = Connection::open_with_flags(file, flags | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+            .map_err(io::Error::other)?;
+        conn.execute_batch(&format!(
+            "PRAGMA synchronous = NORMAL; PRAGMA cache_size = -{cache_size_kib}"
+        ))
+        .map_err(io::Error::other)?;
+        Ok(conn)
+    }
+
+    /// Returns version and kind of the database in the directory at `path`,
+    /// or `None` if it doesn’t exist.
+    pub(crate) fn get_metadata(
+        path: &Path,
+        config: &StoreConfig,
+    ) -> io::Result<Option<metadata::DbMetadata>> {
+        if !path.join(DB_FILE_NAME).is_file() {
+            return Ok(None);
+        }
+        let db = Self::open(path, config, Mode::ReadOnly)?;
+        Some(metadata::DbMetadata::read(&db)).transpose()
+    }
+
+    /// Returns an idle connection for reading, or waits for one if all of them
+    /// are busy.
+    fn reader(&self) -> MutexGuard<'_, Connection> {
+        let first = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        self.idle_reader(first)
+            .unwrap_or_else(|| self.readers[first % self.readers.len()].lock().unwrap())
+    }
+
+    fn idle_reader(&self, first: usize) -> Option<MutexGuard<'_, Connection>> {
+        let num_readers = self.readers.len();
+        (0..num_readers).find_map(|i| self.readers[(first + i) % num_readers].try_lock().ok())
+    }
+
+    /// Returns a connection for an iterator, which keeps it for as long as
+    /// it’s reading.  Rather than waiting for a busy reader, which may be held
+    /// by another iterator of the same thread, it opens a new connection.
+    fn iter_reader(&self) -> io::Result<IterConnection<'_>> {
+        let first = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        if let Some(conn) = self.idle_reader(first) {
+            return Ok(IterConnection::Pooled(conn));
+        }
+        let conn =
+            Self::connect(&self.file, OpenFlags::SQLITE_OPEN_READ_ONLY, self.cache_size_kib)?;
+        Ok(IterConnection::Owned(conn))
+    }
+
+    fn iter_raw_bytes_internal<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<Vec<u8>>,
+    ) -> SqliteIterator<'a> {
+        SqliteIterator {
+            db: self,
+            conn: None,
+            col,
+            lower_bound: lower_bound.map(|key| (key.to_vec(), true)),
+            upper_bound,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+enum IterConnection<'a> {
+    Pooled(MutexGuard<'a, Connection>),
+    Owned(Connection),
+}
+
+impl std::ops::Deref for IterConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            Self::Pooled(conn) => conn,
+            Self::Owned(conn) => conn,
+        }
+    }
+}
+
+/// Iterates over the rows of a table in the order of the keys, fetching them
+/// in batches so that large columns don’t have to be loaded at once.
+///
+/// All the batches are read in one read transaction, which the iterator holds
+/// until it has read the last batch or is dropped, so that it sees the rows
+/// as they were when it started regardless of the writes in the meantime.
+struct SqliteIterator<'a> {
+    db: &'a SqliteDB,
+    /// Connection with the open read transaction, `None` before the first
+    /// batch and after the last one.
+    conn: Option<IterConnection<'a>>,
+    col: DBCol,
+    /// Bound of the rows which haven’t been fetched yet, and whether it’s inclusive.
+    lower_bound: Option<(Vec<u8>, bool)>,
+    upper_bound: Option<Vec<u8>>,
+    buffer: VecDeque<(Box<[u8]>, Box<[u8]>)>,
+    done: bool,
+}
+
+impl<'a> SqliteIterator<'a> {
+    fn fetch(&mut self) -> io::Result<()> {
+        let mut conditions = Vec::new();
+        let mut params: Vec<&[u8]> = Vec::new();
+        if let Some((key, inclusive)) = &self.lower_bound {
+            conditions.push(if *inclusive { "key >= ?" } else { "key > ?" });
+            params.push(key);
+        }
+        if let Some(key) = &self.upper_bound {
+            conditions.push("key < ?");
+            params.push(key);
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT key, value FROM \"{}\" {where_clause} ORDER BY key LIMIT {ITER_BATCH_SIZE}",
+            <&str>::from(self.col)
+        );
+        if self.conn.is_none() {
+            let conn = self.db.iter_reader()?;
+            conn.execute_batch("BEGIN").map_err(io::Error::other)?;
+            self.conn = Some(conn);
+        }
+        let conn = self.conn.as_ref().unwrap();
+        let mut stmt = conn.prepare_cached(&sql).map_err(io::Error::other)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params), |row| {
+                Ok((row.get::<_, Vec<u8>>(0)?.into_boxed_slice(), row.get::<_, Vec<u8>>(1)?.into()))
+            })
+            .map_err(io::Error::other)?;
+        for row in rows {
+            self.buffer.push_back(row.map_err(io::Error::other)?);
+        }
+        drop(stmt);
+        self.done = self.buffer.len() < ITER_BATCH_SIZE;
+        self.lower_bound = self.buffer.back().map(|(key, _)| (key.to_vec(), false));
+        if self.done {
+            self.finish();
+        }
+        Ok(())
+    }
+
+    /// Ends the read transaction and releases the connection.
+    fn finish(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            if let Err(err) = conn.execute_batch("COMMIT") {
+                tracing::warn!(target: "store", ?err, "Failed to end an SQLite read transaction");
+            }
+        }
+    }
+}
+
+impl Drop for SqliteIterator<'_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl<'a> Iterator for SqliteIterator<'a> {
+    type Item = io::Result<(Box<[u8]>, Box<[u8]>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() && !self.done {
+            if let Err(err) = self.fetch() {
+                self.done = true;
+                self.finish();
+                return Some(Err(err));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+impl Database for SqliteDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<DBSlice<'_>>> {
+        let sql = format!("SELECT value FROM \"{}\" WHERE key = ?1", <&str>::from(col));
+        let conn = self.reader();
+        let value = conn
+            .prepare_cached(&sql)
+            .and_then(|mut stmt| stmt.query_row([key], |row| row.get::<_, Vec<u8>>(0)).optional())
+            .map_err(io::Error::other)?;
+        Ok(value.map(DBSlice::from_vec))
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        refcount::iter_with_rc_logic(col, self.iter_raw_bytes_internal(col, None, None))
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        let iter =
+            self.iter_raw_bytes_internal(col, Some(key_prefix), prefix_upper_bound(key_prefix));
+        refcount::iter_with_rc_logic(col, iter)
+    }
+
+    fn iter_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        let iter = self.iter_raw_bytes_internal(col, lower_bound, upper_bound.map(<[u8]>::to_vec));
+        refcount::iter_with_rc_logic(col, iter)
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, col: DBCol) -> DBIterator<'a> {
+        Box::new(self.iter_raw_bytes_internal(col, None, None))
+    }
+
+    fn iter_raw_bytes_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        Box::new(self.iter_raw_bytes_internal(col, lower_bound, upper_bound.map(<[u8]>::to_vec)))
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        let conn = self.writer.lock().unwrap();
+        let tx = conn.unchecked_transaction().map_err(io::Error::other)?;
+        for op in transaction.ops {
+            let table = <&str>::from(op.col());
+            let result = match op {
+                DBOp::Set { key, value, .. } => tx
+                    .prepare_cached(&format!("INSERT OR REPLACE INTO \"{table}\" VALUES (?1, ?2)"))
+                    .and_then(|mut stmt| stmt.execute(rusqlite::params![key, value])),
+                DBOp::Insert { col, key, value } => {
+                    if cfg!(debug_assertions) {
+                        if let Some(old_value) = get_in_transaction(&tx, col, &key)? {
+                            super::assert_no_overwrite(col, &key, &value, &old_value)
+                        }
+                    }
+                    tx.prepare_cached(&format!(
+                        "INSERT OR REPLACE INTO \"{table}\" VALUES (?1, ?2)"
+                    ))
+                    .and_then(|mut stmt| stmt.execute(rusqlite::params![key, value]))
+                }
+                DBOp::UpdateRefcount { col, key, value } => {
+                    let existing = get_in_transaction(&tx, col, &key)?;
+                    let merged = refcount::refcount_merge(existing.as_deref(), [value.as_slice()]);
+                    if merged.is_empty() {
+                        tx.prepare_cached(&format!("DELETE FROM \"{table}\" WHERE key = ?1"))
+                            .and_then(|mut stmt| stmt.execute([key]))
+                    } else {
+                        debug_assert!(
+                            refcount::decode_value_with_rc(&merged).1 > 0,
+                            "Inserting value with non-positive refcount"
+                        );
+                        tx.prepare_cached(&format!(
+                            "INSERT OR REPLACE INTO \"{table}\" VALUES (?1, ?2)"
+                        ))
+                        .and_then(|mut stmt| stmt.execute(rusqlite::params![key, merged]))
+                    }
+                }
+                DBOp::Delete { key, .. } => tx
+                    .prepare_cached(&format!("DELETE FROM \"{table}\" WHERE key = ?1"))
+                    .and_then(|mut stmt| stmt.execute([key])),
+                DBOp::DeleteAll { .. } => tx.execute(&format!("DELETE FROM \"{table}\""), []),
+                DBOp::DeleteRange { from, to, .. } => tx
+                    .prepare_cached(&format!(
+                        "DELETE FROM \"{table}\" WHERE key >= ?1 AND key < ?2"
+                    ))
+                    .and_then(|mut stmt| stmt.execute([from, to])),
+            };
+            result.map_err(io::Error::other)?;
+        }
+        tx.commit().map_err(io::Error::other)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let conn = self.writer.lock().unwrap();
+        conn.execute_batch("PRAGMA wal_checkpoint(FULL)").map_err(io::Error::other)
+    }
+
+    fn compact(&self) -> io::Result<()> {
+        let conn = self.writer.lock().unwrap();
+        conn.execute_batch("VACUUM").map_err(io::Error::other)
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        None
+    }
+
+    fn create_checkpoint(
+        &self,
+        path: &std::path::Path,
+        columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        std::fs::create_dir_all(path)
+            .with_context(|| format!("failed to create checkpoint at {}", path.display()))?;
+        let file = path.join(DB_FILE_NAME);
+        let file_name = file.to_str().context("checkpoint path is not valid UTF-8")?;
+        let conn = self.writer.lock().unwrap();
+        conn.execute("VACUUM INTO ?1", [file_name])
+            .with_context(|| format!("failed to create checkpoint at {}", path.display()))?;
+        drop(conn);
+
+        let Some(columns_to_keep) = columns_to_keep else {
+            return Ok(());
+        };
+        let checkpoint = Connection::open(&file)
+            .with_context(|| format!("failed to open checkpoint at {}", path.display()))?;
+        for col in DBCol::iter() {
+            // DbVersion is needed to read the metadata of the checkpoint.
+            if col != DBCol::DbVersion && !columns_to_keep.contains(&col) {
+                checkpoint.execute(&format!("DELETE FROM \"{}\"", <&str>::from(col)), [])?;
+            }
+        }
+        checkpoint.execute_batch("VACUUM")?;
+        Ok(())
+    }
+}
+
+/// Reads a value within the write transaction, so that the writes made earlier
+/// in the same transaction are visible.
+fn get_in_transaction(
+    tx: &rusqlite::Transaction,
+    col: DBCol,
+    key: &[u8],
+) -> io::Result<Option<Vec<u8>>> {
+    let sql = format!("SELECT value FROM \"{}\" WHERE key = ?1", <&str>::from(col));
+    tx.prepare_cached(&sql)
+        .and_then(|mut stmt| stmt.query_row([key], |row| row.get(0)).optional())
+        .map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prefix_upper_bound, SqliteDB};
+    use crate::db::{DBTransaction, Database};
+    use crate::{DBCol, Mode, StoreConfig};
+
+    fn keys(iter: crate::db::DBIterator) -> Vec<Vec<u8>> {
+        iter.map(|item| item.unwrap().0.to_vec()).collect()
+    }
+
+    #[test]
+    fn test_prefix_upper_bound() {
+        assert_eq!(prefix_upper_bound(&[1, 2]), Some(vec![1, 3]));
+        assert_eq!(prefix_upper_bound(&[1, 255]), Some(vec![2]));
+        assert_eq!(prefix_upper_bound(&[255, 255]), None);
+        assert_eq!(prefix_upper_bound(&[]), None);
+    }
+
+    #[test]
+    fn test_sqlite_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = StoreConfig::test_config();
+        let db = SqliteDB::open(dir.path(), &config, Mode::Create).unwrap();
+
+        let mut transaction = DBTransaction::new();
+        for key in [&[1u8][..], &[1, 0], &[1, 255], &[2], &[255, 255]] {
+            transaction.set(DBCol::BlockMisc, key.to_vec(), vec![7]);
+        }
+        transaction.update_refcount(DBCol::State, vec![1], vec![42, 2, 0, 0, 0, 0, 0, 0, 0]);
+        db.write(transaction).unwrap();
+
+        assert_eq!(db.get_raw_bytes(DBCol::BlockMisc, &[2]).unwrap().as_deref(), Some(&[7][..]));
+        assert!(db.get_raw_bytes(DBCol::BlockMisc, &[3]).unwrap().is_none());
+        assert_eq!(
+            keys(db.iter_prefix(DBCol::BlockMisc, &[1])),
+            vec![vec![1], vec![1, 0], vec![1, 255]]
+        );
+        assert_eq!(keys(db.iter_prefix(DBCol::BlockMisc, &[255])), vec![vec![255, 255]]);
+        assert_eq!(
+            keys(db.iter_range(DBCol::BlockMisc, Some(&[1, 0]), Some(&[2]))),
+            vec![vec![1, 0], vec![1, 255]]
+        );
+
+        // The refcount drops to zero and the value is removed.
+        let mut transaction = DBTransaction::new();
+        transaction.update_refcount(
+            DBCol::State,
+            vec![1],
+            vec![254, 255, 255, 255, 255, 255, 255, 255],
+        );
+        transaction.delete_range(DBCol::BlockMisc, vec![1], vec![2]);
+        db.write(transaction).unwrap();
+        assert!(db.get_raw_bytes(DBCol::State, &[1]).unwrap().is_none());
+        assert_eq!(keys(db.iter(DBCol::BlockMisc)), vec![vec![2], vec![255, 255]]);
+
+        // The data is persisted across reopening.
+        drop(db);
+        let db = SqliteDB::open(dir.path(), &config, Mode::ReadOnly).unwrap();
+        assert_eq!(keys(db.iter(DBCol::BlockMisc)), vec![vec![2], vec![255, 255]]);
+    }
+
+    #[test]
+    fn test_iter_reads_one_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SqliteDB::open(dir.path(), &StoreConfig::test_config(), Mode::Create).unwrap();
+        let num_keys = super::ITER_BATCH_SIZE * 2 + 1;
+        let mut transaction = DBTransaction::new();
+        for i in 0..num_keys as u32 {
+            transaction.set(DBCol::BlockMisc, i.to_be_bytes().to_vec(), vec![]);
+        }
+        db.write(transaction).unwrap();
+
+        // Rows written and deleted after the first batch is read don't show
+        // up in the later batches.
+        let mut iter = db.iter(DBCol::BlockMisc);
+        let first = iter.next().unwrap().unwrap().0.to_vec();
+        let mut transaction = DBTransaction::new();
+        transaction.delete_range(DBCol::BlockMisc, vec![], u32::MAX.to_be_bytes().to_vec());
+        transaction.set(DBCol::BlockMisc, u32::MAX.to_be_bytes().to_vec(), vec![]);
+        db.write(transaction).unwrap();
+        let mut iterated = vec![first];
+        iterated.extend(keys(iter));
+        let expected: Vec<_> = (0..num_keys as u32).map(|i| i.to_be_bytes().to_vec()).collect();
+        assert_eq!(iterated, expected);
+        assert_eq!(keys(db.iter(DBCol::BlockMisc)), vec![u32::MAX.to_be_bytes().to_vec()]);
+    }
+
+    #[test]
+    fn test_iter_in_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SqliteDB::open(dir.path(), &StoreConfig::test_config(), Mode::Create).unwrap();
+        let num_keys = super::ITER_BATCH_SIZE * 2 + 1;
+        let mut transaction = DBTransaction::new();
+        for i in 0..num_keys as u32 {
+            transaction.set(DBCol::BlockMisc, i.to_be_bytes().to_vec(), vec![]);
+        }
+        db.write(transaction).unwrap();
+        let expected: Vec<_> = (0..num_keys as u32).map(|i| i.to_be_bytes().to_vec()).collect();
+        assert_eq!(keys(db.iter(DBCol::BlockMisc)), expected);
+    }
+}
@@ -1,3 +1,6 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
 use crate::flat::store_helper;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
@@ -57,6 +60,46 @@ impl FlatStorageChunkView {
         store_helper::iter_flat_state_entries(self.flat_storage.shard_uid(), &self.store, from, to)
     }
 
+    /// Returns changes to the keys within `[from, to)` which are not yet
+    /// applied to the flat state at the flat head, see
+    /// [`FlatStorage::get_range_changes`].
+    pub fn get_range_changes(
+        &self,
+        from: &[u8],
+        to: &[u8],
+    ) -> Result<BTreeMap<Vec<u8>, Option<FlatStateValue>>, crate::StorageError> {
+        self.flat_storage.get_range_changes(&self.block_hash, from, to)
+    }
+
+    /// Returns the first entry with `from <= key < to`, taken from the state
+    /// corresponding to `FlatStorageChunkView::block_hash`.
+    ///
+    /// `changes` must be the result of [`Self::get_range_changes`] for a range
+    /// which includes `[from, to)`.
+    pub fn get_first_in_range(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        changes: &BTreeMap<Vec<u8>, Option<FlatStateValue>>,
+    ) -> Result<Option<(Vec<u8>, FlatStateValue)>, crate::StorageError> {
+        let mut from_head = None;
+        for entry in self.iter_flat_state_entries(Some(from), Some(to)) {
+            let (key, value) = entry?;
+            // Values of the changed keys are taken from `changes` below.
+            if !changes.contains_key(&key) {
+                from_head = Some((key, value));
+                break;
+            }
+        }
+        let from_changes = changes
+            .range::<[u8], _>((Bound::Included(from), Bound::Excluded(to)))
+            .find_map(|(key, value)| Some((key.clone(), value.clone()?)));
+        Ok(match (from_head, from_changes) {
+            (Some(head), Some(changed)) => Some(if head.0 < changed.0 { head } else { changed }),
+            (head, changed) => head.or(changed),
+        })
+    }
+
     pub fn get_head_hash(&self) -> CryptoHash {
         self.flat_storage.get_head_hash()
     }
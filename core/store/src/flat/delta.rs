@@ -10,7 +10,7 @@ use std::sync::Arc;
 use super::{store_helper, BlockInfo};
 use crate::{CryptoHash, StoreUpdate};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FlatStateDelta {
     pub metadata: FlatStateDeltaMetadata,
     pub changes: FlatStateChanges,
@@ -135,7 +135,7 @@ impl FlatStateChanges {
 
 /// `FlatStateChanges` which uses hash of raw `TrieKey`s instead of keys themselves.
 /// Used to reduce memory used by deltas and serves read queries.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct CachedFlatStateChanges(HashMap<CryptoHash, Option<ValueRef>>);
 
 #[derive(Debug)]
@@ -171,6 +171,11 @@ impl CachedFlatStateChanges {
         self.0.len()
     }
 
+    /// Merges the changes of a later block into the current ones.
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.0.extend(other.0.iter().map(|(key, value)| (*key, value.clone())));
+    }
+
     /// Total size in bytes consumed by delta. May be changed if we implement inlining of `ValueRef`s.
     pub(crate) fn total_size(&self) -> u64 {
         (self.0.capacity() as u64) * (Self::ENTRY_SIZE as u64)
@@ -1,3 +1,4 @@
+use crate::config::FlatStorageDeltasConfig;
 use crate::flat::{
     store_helper, BlockInfo, FlatStorageReadyStatus, FlatStorageStatus, POISONED_LOCK_ERR,
 };
@@ -34,11 +35,51 @@ pub struct FlatStorageManagerInner {
     /// this epoch can share the same `head` and `tail`, similar for shards for the next epoch,
     /// but such overhead is negligible comparing the delta sizes, so we think it's ok.
     flat_storages: Mutex<HashMap<ShardUId, FlatStorage>>,
+    /// Number of blocks below the flat head covered by the flat state history.
+    history_length: BlockHeight,
+    /// How the flat storage of each shard keeps its deltas.
+    deltas_config: FlatStorageDeltasConfig,
+    /// Where the flat state deltas of the shards of an incremental state
+    /// snapshot go, so that its flat heads can be moved to the next epoch
+    /// boundary.
+    snapshot_for_deltas: Mutex<Option<SnapshotDeltas>>,
+}
+
+/// Flat state deltas of the shards of an incremental state snapshot.
+enum SnapshotDeltas {
+    /// The snapshot is being created or moved, and the deltas of the blocks
+    /// processed meanwhile are kept until it can receive them.
+    Buffered { shard_uids: Vec<ShardUId>, deltas: Vec<(ShardUId, FlatStateDelta)> },
+    /// The deltas are written to the store of the snapshot.
+    Forwarded { store: Store, shard_uids: Vec<ShardUId> },
 }
 
 impl FlatStorageManager {
     pub fn new(store: Store) -> Self {
-        Self(Arc::new(FlatStorageManagerInner { store, flat_storages: Default::default() }))
+        Self::with_history(store, 0)
+    }
+
+    /// Creates a manager whose flat storages keep the previous values of the
+    /// keys changed by the last `history_length` blocks below the flat head,
+    /// to serve reads at these blocks.  The history is disabled if zero.
+    pub fn with_history(store: Store, history_length: BlockHeight) -> Self {
+        Self::with_config(store, history_length, Default::default())
+    }
+
+    /// Same as `with_history`, with the flat storages keeping their deltas
+    /// according to `deltas_config`.
+    pub fn with_config(
+        store: Store,
+        history_length: BlockHeight,
+        deltas_config: FlatStorageDeltasConfig,
+    ) -> Self {
+        Self(Arc::new(FlatStorageManagerInner {
+            store,
+            flat_storages: Default::default(),
+            history_length,
+            deltas_config,
+            snapshot_for_deltas: Default::default(),
+        }))
     }
 
     /// When a node starts from an empty database, this function must be called to ensure
@@ -72,7 +113,12 @@ impl FlatStorageManager {
     pub fn create_flat_storage_for_shard(&self, shard_uid: ShardUId) -> Result<(), StorageError> {
         tracing::debug!(target: "store", ?shard_uid, "Creating flat storage for shard");
         let mut flat_storages = self.0.flat_storages.lock().expect(POISONED_LOCK_ERR);
-        let flat_storage = FlatStorage::new(self.0.store.clone(), shard_uid)?;
+        let flat_storage = FlatStorage::new(
+            self.0.store.clone(),
+            shard_uid,
+            self.0.history_length,
+            self.0.deltas_config.shard_policy(shard_uid),
+        )?;
         let original_value = flat_storages.insert(shard_uid, flat_storage);
         if original_value.is_some() {
             // Generally speaking this shouldn't happen. It may only happen when
@@ -169,9 +215,19 @@ impl FlatStorageManager {
             },
         };
 
+        self.write_delta_to_snapshot(shard_uid, &delta);
         let store_update = if let Some(flat_storage) = self.get_flat_storage_for_shard(shard_uid) {
             // If flat storage exists, we add a block to it.
-            flat_storage.add_delta(delta).map_err(|e| StorageError::from(e))?
+            let store_update = flat_storage.add_delta(delta).map_err(|e| StorageError::from(e))?;
+            if self.0.deltas_config.shard_policy(shard_uid).compaction_threshold > 0 {
+                rayon::spawn(move || {
+                    if let Err(err) = flat_storage.compact_deltas(&block_hash) {
+                        // The flat head may have moved past a fork of the block.
+                        debug!(target: "store", %shard_uid, %block_hash, ?err, "Cannot compact flat storage deltas");
+                    }
+                });
+            }
+            store_update
         } else {
             // Otherwise, save delta to disk so it will be used for flat storage creation later.
             debug!(target: "store", %shard_uid, "Add delta for flat storage creation");
@@ -183,6 +239,89 @@ impl FlatStorageManager {
         Ok(store_update)
     }
 
+    /// Makes `save_flat_state_changes` keep the deltas of `shard_uids` in
+    /// memory until `forward_deltas_to_snapshot` is called.  Called before an
+    /// incremental state snapshot is created or moved, so that the deltas of
+    /// the blocks processed meanwhile reach it even if they are not on disk
+    /// yet when it's ready.
+    pub fn buffer_deltas_for_snapshot(&self, shard_uids: Vec<ShardUId>) {
+        *self.0.snapshot_for_deltas.lock().expect(POISONED_LOCK_ERR) =
+            Some(SnapshotDeltas::Buffered { shard_uids, deltas: vec![] });
+    }
+
+    /// Makes `save_flat_state_changes` also write the deltas of `shard_uids`
+    /// to `store`, the store of an incremental state snapshot.  The deltas of
+    /// these shards already stored on disk and the buffered ones are copied
+    /// to it under the same lock, so that none of the blocks processed since
+    /// the snapshot was taken is missed.
+    pub fn forward_deltas_to_snapshot(
+        &self,
+        store: Store,
+        shard_uids: Vec<ShardUId>,
+    ) -> Result<(), StorageError> {
+        let mut snapshot = self.0.snapshot_for_deltas.lock().expect(POISONED_LOCK_ERR);
+        let buffered = match snapshot.take() {
+            Some(SnapshotDeltas::Buffered { deltas, .. }) => deltas,
+            _ => vec![],
+        };
+        let mut store_update = store.store_update();
+        for shard_uid in &shard_uids {
+            for metadata in store_helper::get_all_deltas_metadata(&self.0.store, *shard_uid)? {
+                let block_hash = metadata.block.hash;
+                let Some(changes) =
+                    store_helper::get_delta_changes(&self.0.store, *shard_uid, block_hash)?
+                else {
+                    continue;
+                };
+                let delta = FlatStateDelta { changes, metadata };
+                store_helper::set_delta(&mut store_update, *shard_uid, &delta);
+            }
+        }
+        for (shard_uid, delta) in &buffered {
+            if shard_uids.contains(shard_uid) {
+                store_helper::set_delta(&mut store_update, *shard_uid, delta);
+            }
+        }
+        store_update.commit().map_err(|err| {
+            StorageError::StorageInconsistentState(format!(
+                "failed to copy flat state deltas to the state snapshot: {err}"
+            ))
+        })?;
+        tracing::debug!(target: "store", ?shard_uids, num_buffered = buffered.len(), "Forwarding flat state deltas to the state snapshot");
+        *snapshot = Some(SnapshotDeltas::Forwarded { store, shard_uids });
+        Ok(())
+    }
+
+    /// Stops writing or buffering deltas for the state snapshot.
+    pub fn stop_forwarding_deltas_to_snapshot(&self) {
+        *self.0.snapshot_for_deltas.lock().expect(POISONED_LOCK_ERR) = None;
+    }
+
+    fn write_delta_to_snapshot(&self, shard_uid: ShardUId, delta: &FlatStateDelta) {
+        let mut snapshot = self.0.snapshot_for_deltas.lock().expect(POISONED_LOCK_ERR);
+        let store = match &mut *snapshot {
+            Some(SnapshotDeltas::Buffered { shard_uids, deltas }) => {
+                if shard_uids.contains(&shard_uid) {
+                    deltas.push((shard_uid, delta.clone()));
+                }
+                return;
+            }
+            Some(SnapshotDeltas::Forwarded { store, shard_uids })
+                if shard_uids.contains(&shard_uid) =>
+            {
+                store
+            }
+            _ => return,
+        };
+        let mut store_update = store.store_update();
+        store_helper::set_delta(&mut store_update, shard_uid, delta);
+        if let Err(err) = store_update.commit() {
+            // Moving the snapshot over this block fails then, and the
+            // snapshot is recreated from scratch at the next epoch boundary.
+            tracing::warn!(target: "store", ?err, %shard_uid, block_hash = ?delta.metadata.block.hash, "Failed to write flat state delta to the state snapshot");
+        }
+    }
+
     pub fn get_flat_storage_status(&self, shard_uid: ShardUId) -> FlatStorageStatus {
         store_helper::get_flat_storage_status(&self.0.store, shard_uid)
             .expect("failed to read flat storage status")
@@ -277,3 +416,67 @@ impl FlatStorageManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::FlatStorageManager;
+    use crate::flat::store_helper;
+    use crate::test_utils::create_test_store;
+    use near_primitives::hash::hash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::trie_key::TrieKey;
+    use near_primitives::types::{RawStateChange, RawStateChangesWithTrieKey, StateChangeCause};
+
+    fn state_changes(value: u8) -> Vec<RawStateChangesWithTrieKey> {
+        vec![RawStateChangesWithTrieKey {
+            trie_key: TrieKey::ContractCode { account_id: "alice".parse().unwrap() },
+            changes: vec![RawStateChange {
+                cause: StateChangeCause::InitialState,
+                data: Some(vec![value]),
+            }],
+        }]
+    }
+
+    /// A delta saved while the incremental snapshot is being created, but
+    /// committed only after deltas start being forwarded to it, must still
+    /// reach the snapshot.
+    #[test]
+    fn test_forward_deltas_saved_while_creating_snapshot() {
+        let store = create_test_store();
+        let manager = FlatStorageManager::new(store.clone());
+        let shard_uid = ShardUId::single_shard();
+        let (block1, block2) = (hash(&[1]), hash(&[2]));
+
+        manager.buffer_deltas_for_snapshot(vec![shard_uid]);
+        let store_update = manager
+            .save_flat_state_changes(block1, hash(&[0]), 1, shard_uid, &state_changes(1))
+            .unwrap();
+        let snapshot_store = create_test_store();
+        manager.forward_deltas_to_snapshot(snapshot_store.clone(), vec![shard_uid]).unwrap();
+        store_update.commit().unwrap();
+        assert!(store_helper::get_delta_changes(&snapshot_store, shard_uid, block1)
+            .unwrap()
+            .is_some());
+
+        // Once forwarding, the deltas are written to the snapshot directly.
+        let store_update = manager
+            .save_flat_state_changes(block2, block1, 2, shard_uid, &state_changes(2))
+            .unwrap();
+        assert!(store_helper::get_delta_changes(&snapshot_store, shard_uid, block2)
+            .unwrap()
+            .is_some());
+        store_update.commit().unwrap();
+
+        // Deltas are neither buffered nor forwarded after stopping.
+        manager.stop_forwarding_deltas_to_snapshot();
+        let block3 = hash(&[3]);
+        manager
+            .save_flat_state_changes(block3, block2, 3, shard_uid, &state_changes(3))
+            .unwrap()
+            .commit()
+            .unwrap();
+        assert!(store_helper::get_delta_changes(&snapshot_store, shard_uid, block3)
+            .unwrap()
+            .is_none());
+    }
+}
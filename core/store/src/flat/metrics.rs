@@ -11,6 +11,8 @@ pub(crate) struct FlatStorageMetrics {
     cached_deltas: IntGauge,
     cached_changes_num_items: IntGauge,
     cached_changes_size: IntGauge,
+    compacted_deltas: IntGauge,
+    merged_deltas: IntCounter,
 }
 
 impl FlatStorageMetrics {
@@ -29,6 +31,10 @@ impl FlatStorageMetrics {
                 .with_label_values(&[&shard_uid_label]),
             cached_changes_size: flat_state_metrics::FLAT_STORAGE_CACHED_CHANGES_SIZE
                 .with_label_values(&[&shard_uid_label]),
+            compacted_deltas: flat_state_metrics::FLAT_STORAGE_COMPACTED_DELTAS
+                .with_label_values(&[&shard_uid_label]),
+            merged_deltas: flat_state_metrics::FLAT_STORAGE_MERGED_DELTAS
+                .with_label_values(&[&shard_uid_label]),
         }
     }
 
@@ -51,6 +57,14 @@ impl FlatStorageMetrics {
         self.cached_changes_num_items.set(cached_changes_num_items as i64);
         self.cached_changes_size.set(cached_changes_size as i64);
     }
+
+    pub(crate) fn set_compacted_deltas(&self, compacted_deltas: usize) {
+        self.compacted_deltas.set(compacted_deltas as i64);
+    }
+
+    pub(crate) fn inc_merged_deltas(&self) {
+        self.merged_deltas.inc();
+    }
 }
 
 /// Metrics reporting about flat storage creation progress on each status update.
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
 
 use near_primitives::errors::StorageError;
@@ -8,7 +8,8 @@ use near_primitives::state::FlatStateValue;
 use near_primitives::types::BlockHeight;
 use tracing::{debug, warn};
 
-use crate::flat::delta::{BlockWithChangesInfo, CachedFlatStateChanges};
+use crate::config::FlatStorageDeltasPolicy;
+use crate::flat::delta::{BlockWithChangesInfo, CachedFlatStateChanges, FlatStateChanges};
 use crate::flat::BlockInfo;
 use crate::flat::{FlatStorageReadyStatus, FlatStorageStatus};
 use crate::{Store, StoreUpdate};
@@ -48,9 +49,33 @@ pub(crate) struct FlatStorageInner {
     /// The flag has a numerical value and not a bool, to let us detect attempts
     /// to disable move head multiple times.
     move_head_enabled: bool,
+    /// Number of blocks below the flat head for which the previous values of
+    /// the changed keys are kept in the flat state history, zero if disabled.
+    history_length: BlockHeight,
+    /// Heights of the blocks covered by the flat state history.
+    history_heights: HashMap<CryptoHash, BlockHeight>,
+    /// How the deltas are kept in memory.
+    policy: FlatStorageDeltasPolicy,
+    /// Deltas from the flat head up to some block merged together, if they
+    /// were compacted since the flat head last moved.
+    compacted_deltas: Option<CompactedDeltas>,
+    /// Whether `FlatStorage::compact_deltas` is merging deltas.
+    compaction_running: bool,
     metrics: FlatStorageMetrics,
 }
 
+/// Changes of the chain of deltas from the flat head up to `block_hash`
+/// merged together, so that reads at the blocks descending from it check
+/// them at once instead of delta by delta.
+struct CompactedDeltas {
+    /// The last block of the chain, which has flat state changes, so that the
+    /// walks from its descendants to the flat head go through it.
+    block_hash: CryptoHash,
+    /// Number of deltas merged.
+    num_deltas: usize,
+    changes: Arc<CachedFlatStateChanges>,
+}
+
 impl FlatStorageInner {
     /// Expected limits for in-memory stored changes, under which flat storage must keep working.
     /// If they are exceeded, warnings are displayed. Flat storage still will work, but its
@@ -62,8 +87,6 @@ impl FlatStorageInner {
     /// means 150 MiB per shards.
     const CACHED_CHANGES_SIZE_LIMIT: bytesize::ByteSize = bytesize::ByteSize(150 * bytesize::MIB);
 
-    const BLOCKS_WITH_CHANGES_FLAT_HEAD_GAP: BlockHeight = 2;
-
     /// Creates `BlockNotSupported` error for the given block.
     /// In the context of updating the flat head, the error is handled gracefully.
     fn create_block_not_supported_error(&self, block_hash: &CryptoHash) -> FlatStorageError {
@@ -89,11 +112,44 @@ impl FlatStorageInner {
         &self,
         target_block_hash: &CryptoHash,
     ) -> Result<Vec<CryptoHash>, FlatStorageError> {
+        self.get_blocks_to(target_block_hash, None).map(|(blocks, _)| blocks)
+    }
+
+    /// Gets the blocks whose deltas need to be read to get the values at
+    /// `target_block_hash`, in backwards chain order, and the compacted
+    /// deltas to read after them if `target_block_hash` descends from them.
+    fn get_deltas_to_read(
+        &self,
+        target_block_hash: &CryptoHash,
+    ) -> Result<(Vec<CryptoHash>, Option<Arc<CachedFlatStateChanges>>), FlatStorageError> {
+        let compacted_deltas = self.compacted_deltas.as_ref();
+        let (blocks, reached_compacted_deltas) = self.get_blocks_to(
+            target_block_hash,
+            compacted_deltas.map(|compacted_deltas| &compacted_deltas.block_hash),
+        )?;
+        let compacted_changes = compacted_deltas
+            .filter(|_| reached_compacted_deltas)
+            .map(|compacted_deltas| compacted_deltas.changes.clone());
+        Ok((blocks, compacted_changes))
+    }
+
+    /// Same as `get_blocks_to_head`, but stops before `stop_at` if it is on
+    /// the way.  Also returns whether `stop_at` was reached.
+    fn get_blocks_to(
+        &self,
+        target_block_hash: &CryptoHash,
+        stop_at: Option<&CryptoHash>,
+    ) -> Result<(Vec<CryptoHash>, bool), FlatStorageError> {
         let flat_head = &self.flat_head;
         let mut block_hash = *target_block_hash;
         let mut blocks = vec![];
         let mut first_height = None;
+        let mut reached_stop_at = false;
         while block_hash != flat_head.hash {
+            if stop_at == Some(&block_hash) {
+                reached_stop_at = true;
+                break;
+            }
             let metadata = self
                 .deltas
                 .get(&block_hash)
@@ -138,7 +194,7 @@ impl FlatStorageInner {
                 "Flat storage needs too many hops to access a block");
         }
 
-        Ok(blocks)
+        Ok((blocks, reached_stop_at))
     }
 
     /// Updates metrics related to deltas, displays a warning if they are off.
@@ -150,12 +206,19 @@ impl FlatStorageInner {
             cached_changes_num_items += changes.changes.len();
             cached_changes_size += changes.changes.total_size();
         }
+        let mut compacted_deltas = 0;
+        if let Some(compacted) = &self.compacted_deltas {
+            compacted_deltas = compacted.num_deltas;
+            cached_changes_num_items += compacted.changes.len();
+            cached_changes_size += compacted.changes.total_size();
+        }
 
         self.metrics.set_cached_deltas(
             cached_deltas,
             cached_changes_num_items,
             cached_changes_size,
         );
+        self.metrics.set_compacted_deltas(compacted_deltas);
 
         let cached_changes_size_bytes = bytesize::ByteSize(cached_changes_size);
         if cached_changes_size_bytes >= Self::CACHED_CHANGES_SIZE_LIMIT {
@@ -190,7 +253,7 @@ impl FlatStorageInner {
         // Delays updating flat head, keeps this many blocks with non-empty flat
         // state changes between the requested flat head and the chosen head to
         // make flat state snapshots function properly.
-        while blocks_with_changes < Self::BLOCKS_WITH_CHANGES_FLAT_HEAD_GAP {
+        while blocks_with_changes < self.policy.flat_head_gap {
             if new_head == current_flat_head_hash {
                 return Ok(current_flat_head_hash);
             }
@@ -204,7 +267,7 @@ impl FlatStorageInner {
                 None => {
                     // The block has flat state changes.
                     blocks_with_changes += 1;
-                    if blocks_with_changes == Self::BLOCKS_WITH_CHANGES_FLAT_HEAD_GAP {
+                    if blocks_with_changes == self.policy.flat_head_gap {
                         break;
                     }
                     metadata.block.prev_hash
@@ -221,6 +284,101 @@ impl FlatStorageInner {
         Ok(new_head)
     }
 
+    /// Returns the height of `block_hash` if it is below the flat head and its
+    /// values can be read from the flat state history.
+    fn get_history_height(&self, block_hash: &CryptoHash) -> Option<BlockHeight> {
+        self.history_heights
+            .get(block_hash)
+            .copied()
+            .filter(|height| *height < self.flat_head.height)
+    }
+
+    /// Reads the value of `key` at `height` from the flat state history.
+    fn get_history_value(
+        &self,
+        height: BlockHeight,
+        key: &[u8],
+    ) -> Result<Option<FlatStateValue>, FlatStorageError> {
+        match store_helper::get_flat_state_history_value(&self.store, self.shard_uid, key, height)?
+        {
+            Some(value) => Ok(value),
+            None => store_helper::get_flat_state_value(&self.store, self.shard_uid, key),
+        }
+    }
+
+    /// Records `block`, which is about to become the flat head, in the flat
+    /// state history together with the current values of the keys it changes
+    /// and the blocks without changes between it and the current flat head.
+    /// Prunes the blocks which fall out of the history.
+    fn record_history(
+        &mut self,
+        store_update: &mut StoreUpdate,
+        block: &BlockInfo,
+        changes: &FlatStateChanges,
+    ) -> Result<(), FlatStorageError> {
+        let shard_uid = self.shard_uid;
+        if self.history_heights.is_empty() {
+            // The history starts at the current flat head.
+            let flat_head = self.flat_head;
+            store_helper::set_flat_state_history_block(
+                store_update,
+                shard_uid,
+                flat_head.height,
+                flat_head.hash,
+                vec![],
+            );
+            self.history_heights.insert(flat_head.hash, flat_head.height);
+        }
+        let prev_values = changes
+            .0
+            .keys()
+            .map(|key| {
+                Ok((key.clone(), store_helper::get_flat_state_value(&self.store, shard_uid, key)?))
+            })
+            .collect::<Result<_, FlatStorageError>>()?;
+        store_helper::set_flat_state_history_block(
+            store_update,
+            shard_uid,
+            block.height,
+            block.hash,
+            prev_values,
+        );
+        self.history_heights.insert(block.hash, block.height);
+        let mut prev_hash = block.prev_hash;
+        while prev_hash != self.flat_head.hash {
+            let prev_block = self
+                .deltas
+                .get(&prev_hash)
+                .ok_or_else(|| missing_delta_error(&prev_hash))?
+                .metadata
+                .block;
+            store_helper::set_flat_state_history_block(
+                store_update,
+                shard_uid,
+                prev_block.height,
+                prev_block.hash,
+                vec![],
+            );
+            self.history_heights.insert(prev_block.hash, prev_block.height);
+            prev_hash = prev_block.prev_hash;
+        }
+
+        let tail = block.height.saturating_sub(self.history_length);
+        for (height, history_block) in
+            store_helper::get_flat_state_history_blocks(&self.store, shard_uid, Some(tail))?
+        {
+            store_helper::remove_flat_state_history_block(
+                store_update,
+                shard_uid,
+                height,
+                &history_block,
+            );
+        }
+        // Blocks recorded by this update below the tail are pruned by the next one.
+        self.history_heights.retain(|_, height| *height >= tail);
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn test_get_new_flat_head(
         &self,
@@ -235,7 +393,14 @@ impl FlatStorage {
     /// Create a new FlatStorage for `shard_uid` using flat head if it is stored on storage.
     /// We also load all blocks with height between flat head to `latest_block_height`
     /// including those on forks into the returned FlatStorage.
-    pub fn new(store: Store, shard_uid: ShardUId) -> Result<Self, StorageError> {
+    /// The previous values of the keys changed by the last `history_length`
+    /// blocks below the flat head are kept to serve reads at these blocks.
+    pub fn new(
+        store: Store,
+        shard_uid: ShardUId,
+        history_length: BlockHeight,
+        policy: FlatStorageDeltasPolicy,
+    ) -> Result<Self, StorageError> {
         let shard_id = shard_uid.shard_id();
         let flat_head = match store_helper::get_flat_storage_status(&store, shard_uid) {
             Ok(FlatStorageStatus::Ready(ready_status)) => ready_status.flat_head,
@@ -272,12 +437,34 @@ impl FlatStorage {
             );
         }
 
+        let history_blocks = store_helper::get_flat_state_history_blocks(&store, shard_uid, None)
+            .unwrap_or_else(|_| {
+                panic!("Cannot read flat state history for shard {shard_id} from storage")
+            });
+        let mut history_heights = HashMap::new();
+        if history_length > 0 {
+            history_heights = history_blocks
+                .into_iter()
+                .map(|(height, block)| (block.block_hash, height))
+                .collect();
+        } else if !history_blocks.is_empty() {
+            // The history is not updated while disabled, so it would become stale.
+            let mut store_update = store.store_update();
+            store_helper::remove_all_flat_state_history(&mut store_update, shard_uid);
+            store_update.commit().unwrap();
+        }
+
         let inner = FlatStorageInner {
             store,
             shard_uid,
             flat_head,
             deltas,
             move_head_enabled: true,
+            history_length,
+            history_heights,
+            policy,
+            compacted_deltas: None,
+            compaction_running: false,
             metrics,
         };
         inner.update_delta_metrics();
@@ -301,8 +488,11 @@ impl FlatStorage {
         key: &[u8],
     ) -> Result<Option<FlatStateValue>, crate::StorageError> {
         let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
-        let blocks_to_head =
-            guard.get_blocks_to_head(block_hash).map_err(|e| StorageError::from(e))?;
+        if let Some(height) = guard.get_history_height(block_hash) {
+            return Ok(guard.get_history_value(height, key)?);
+        }
+        let (blocks_to_head, compacted_changes) =
+            guard.get_deltas_to_read(block_hash).map_err(|e| StorageError::from(e))?;
         for block_hash in blocks_to_head.iter() {
             // If we found a key in changes, we can return a value because it is the most recent key update.
             let changes = guard.get_block_changes(block_hash)?;
@@ -313,6 +503,9 @@ impl FlatStorage {
                 None => {}
             };
         }
+        if let Some(value_ref) = compacted_changes.as_ref().and_then(|changes| changes.get(key)) {
+            return Ok(value_ref.clone().map(|value_ref| FlatStateValue::Ref(value_ref)));
+        }
 
         let value = store_helper::get_flat_state_value(&guard.store, guard.shard_uid, key)?;
         Ok(value)
@@ -325,8 +518,11 @@ impl FlatStorage {
         key: &[u8],
     ) -> Result<bool, crate::StorageError> {
         let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
-        let blocks_to_head =
-            guard.get_blocks_to_head(block_hash).map_err(|e| StorageError::from(e))?;
+        if let Some(height) = guard.get_history_height(block_hash) {
+            return Ok(guard.get_history_value(height, key)?.is_some());
+        }
+        let (blocks_to_head, compacted_changes) =
+            guard.get_deltas_to_read(block_hash).map_err(|e| StorageError::from(e))?;
         for block_hash in blocks_to_head.iter() {
             // If we found a key in changes, we can return a value because it is the most recent key update.
             let changes = guard.get_block_changes(block_hash)?;
@@ -335,6 +531,9 @@ impl FlatStorage {
                 None => {}
             };
         }
+        if let Some(value_ref) = compacted_changes.as_ref().and_then(|changes| changes.get(key)) {
+            return Ok(value_ref.is_some());
+        }
 
         let db_key = store_helper::encode_flat_state_db_key(guard.shard_uid, key);
         Ok(guard.store.exists(crate::DBCol::FlatState, &db_key).map_err(|err| {
@@ -342,13 +541,53 @@ impl FlatStorage {
         })?)
     }
 
+    /// Whether the values at `block_hash` can be read from the flat storage,
+    /// either through the deltas above the flat head or from the flat state
+    /// history below it.
+    pub fn supports_block(&self, block_hash: &CryptoHash) -> bool {
+        let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
+        guard.get_history_height(block_hash).is_some()
+            || guard.get_blocks_to_head(block_hash).is_ok()
+    }
+
+    /// Returns changes to the keys within `[from, to)` made by the blocks after
+    /// the flat head, up to and including `block_hash`. Only the latest change
+    /// is kept for each key.
+    ///
+    /// Cached deltas only store hashes of the keys, so the changes are read
+    /// from the store.
+    pub fn get_range_changes(
+        &self,
+        block_hash: &CryptoHash,
+        from: &[u8],
+        to: &[u8],
+    ) -> Result<BTreeMap<Vec<u8>, Option<FlatStateValue>>, crate::StorageError> {
+        let guard = self.0.read().expect(super::POISONED_LOCK_ERR);
+        let blocks_to_head =
+            guard.get_blocks_to_head(block_hash).map_err(|e| StorageError::from(e))?;
+        let mut range_changes = BTreeMap::new();
+        // Blocks go from the newest one, so the first change seen for a key is the latest one.
+        for block_hash in blocks_to_head.iter() {
+            let changes =
+                store_helper::get_delta_changes(&guard.store, guard.shard_uid, *block_hash)?
+                    .ok_or_else(|| missing_delta_error(block_hash))?;
+            for (key, value) in changes.0 {
+                if from <= key.as_slice() && key.as_slice() < to {
+                    range_changes.entry(key).or_insert(value);
+                }
+            }
+        }
+        Ok(range_changes)
+    }
+
     /// Update the head of the flat storage, including updating the flat state
     /// in memory and on disk and updating the flat state to reflect the state
     /// at the new head. If updating to given head is not possible, returns an
     /// error.
     /// If `strict`, then unconditionally sets flat head to the given block.
     /// If not `strict`, then it updates the flat head to the latest block X,
-    /// such that [X, block_hash] contains 2 blocks with flat state changes. If possible.
+    /// such that [X, block_hash] contains `flat_head_gap` (2 by default) blocks with flat state
+    /// changes. If possible.
     ///
     /// The function respects the current flat head and will never try to
     /// set flat head to a block older than the current flat head.
@@ -364,7 +603,7 @@ impl FlatStorage {
     //                 |
     //              new_head
     //
-    // The segment [new_head, block_hash] contains two blocks with flat state changes.
+    // The segment [new_head, block_hash] contains `flat_head_gap` blocks with flat state changes.
     pub fn update_flat_head(
         &self,
         block_hash: &CryptoHash,
@@ -393,13 +632,16 @@ impl FlatStorage {
             // path from old to new head. Otherwise we return internal error.
             let changes = store_helper::get_delta_changes(&guard.store, shard_uid, block_hash)?
                 .ok_or_else(|| missing_delta_error(&block_hash))?;
-            changes.apply_to_flat_state(&mut store_update, guard.shard_uid);
             let metadata = guard
                 .deltas
                 .get(&block_hash)
                 .ok_or_else(|| missing_delta_error(&block_hash))?
                 .metadata;
             let block = metadata.block;
+            if guard.history_length > 0 {
+                guard.record_history(&mut store_update, &block, &changes)?;
+            }
+            changes.apply_to_flat_state(&mut store_update, guard.shard_uid);
             let block_height = block.height;
             store_helper::set_flat_storage_status(
                 &mut store_update,
@@ -429,8 +671,12 @@ impl FlatStorage {
             }
 
             store_update.commit().unwrap();
+            guard.metrics.inc_merged_deltas();
             debug!(target: "store", %shard_id, %block_hash, %block_height, "Moved flat storage head");
         }
+        // The compacted deltas would also hold the changes which are in the
+        // flat state now, so they are compacted again from the new flat head.
+        guard.compacted_deltas = None;
         guard.update_delta_metrics();
 
         Ok(())
@@ -463,12 +709,90 @@ impl FlatStorage {
         Ok(store_update)
     }
 
+    /// Merges the deltas from the flat head up to `block_hash` into a single
+    /// one if reading at `block_hash` needs to go through at least
+    /// `compaction_threshold` deltas, so that the reads at `block_hash` and
+    /// its descendants don't need to go through each of them.  Only the last
+    /// compacted chain is kept.  The deltas are merged without holding the
+    /// lock, so that the reads aren't blocked while it runs in the background.
+    /// Returns whether the deltas were compacted.
+    pub fn compact_deltas(&self, block_hash: &CryptoHash) -> Result<bool, FlatStorageError> {
+        let (flat_head, prev_compacted_block, base, mut blocks_changes, compacted_block) = {
+            let mut guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+            let threshold = guard.policy.compaction_threshold;
+            if threshold == 0 || guard.compaction_running {
+                return Ok(false);
+            }
+            let (mut blocks, compacted_changes) = guard.get_deltas_to_read(block_hash)?;
+            // The target block is included even if it has no changes, but
+            // the compacted deltas need to end at a block with changes.
+            if blocks.first() == Some(block_hash)
+                && guard.deltas.get(block_hash).map_or(false, |delta| !delta.metadata.has_changes())
+            {
+                blocks.remove(0);
+            }
+            if blocks.len() < threshold {
+                return Ok(false);
+            }
+            let blocks_changes = blocks
+                .iter()
+                .map(|block_hash| guard.get_block_changes(block_hash))
+                .collect::<Result<Vec<_>, _>>()?;
+            let prev_compacted_block =
+                guard.compacted_deltas.as_ref().map(|compacted| compacted.block_hash);
+            // The previous compacted deltas are extended if `block_hash`
+            // descends from them, and replaced otherwise.
+            let base = compacted_changes.map(|changes| {
+                (
+                    guard.compacted_deltas.as_ref().map_or(0, |compacted| compacted.num_deltas),
+                    changes,
+                )
+            });
+            guard.compaction_running = true;
+            (guard.flat_head.hash, prev_compacted_block, base, blocks_changes, blocks[0])
+        };
+
+        // Blocks go from the newest one, so they are merged in reverse for
+        // the latest change of each key to win.
+        blocks_changes.reverse();
+        let mut num_deltas = blocks_changes.len();
+        let mut changes = CachedFlatStateChanges::default();
+        if let Some((base_num_deltas, base_changes)) = base {
+            changes = (*base_changes).clone();
+            num_deltas += base_num_deltas;
+        }
+        for block_changes in &blocks_changes {
+            changes.merge(block_changes);
+        }
+
+        let mut guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+        guard.compaction_running = false;
+        let current_compacted_block =
+            guard.compacted_deltas.as_ref().map(|compacted| compacted.block_hash);
+        if guard.flat_head.hash != flat_head || current_compacted_block != prev_compacted_block {
+            // The flat head moved in the meantime, so the changes may already
+            // be in the flat state.
+            return Ok(false);
+        }
+        debug!(target: "store", shard_uid = %guard.shard_uid, %compacted_block, num_deltas, "Compacted flat storage deltas");
+        guard.compacted_deltas = Some(CompactedDeltas {
+            block_hash: compacted_block,
+            num_deltas,
+            changes: Arc::new(changes),
+        });
+        guard.update_delta_metrics();
+        Ok(true)
+    }
+
     /// Clears all State key-value pairs from flat storage.
     pub fn clear_state(&self, store_update: &mut StoreUpdate) -> Result<(), StorageError> {
-        let guard = self.0.write().expect(super::POISONED_LOCK_ERR);
+        let mut guard = self.0.write().expect(super::POISONED_LOCK_ERR);
         let shard_uid = guard.shard_uid;
         store_helper::remove_all_flat_state_values(store_update, shard_uid);
         store_helper::remove_all_deltas(store_update, shard_uid);
+        store_helper::remove_all_flat_state_history(store_update, shard_uid);
+        guard.history_heights.clear();
+        guard.compacted_deltas = None;
         store_helper::set_flat_storage_status(store_update, shard_uid, FlatStorageStatus::Empty);
         guard.update_delta_metrics();
         Ok(())
@@ -502,11 +826,12 @@ fn missing_delta_error(block_hash: &CryptoHash) -> FlatStorageError {
 
 #[cfg(test)]
 mod tests {
+    use crate::config::{FlatStorageDeltasConfig, FlatStorageDeltasPolicy};
     use crate::flat::delta::{
         BlockWithChangesInfo, FlatStateChanges, FlatStateDelta, FlatStateDeltaMetadata,
     };
     use crate::flat::manager::FlatStorageManager;
-    use crate::flat::storage::FlatStorageInner;
+    use crate::flat::storage::{FlatStorage, FlatStorageInner};
     use crate::flat::test_utils::MockChain;
     use crate::flat::types::FlatStorageError;
     use crate::flat::{store_helper, FlatStorageReadyStatus, FlatStorageStatus};
@@ -812,6 +1137,254 @@ mod tests {
         );
     }
 
+    /// Checks that the reads at the blocks descending from the compacted
+    /// deltas see the same values as without the compaction.
+    #[test]
+    fn flat_storage_compacted_deltas() {
+        // Block i sets key &[1] to &[i] and key &[i + 1] to &[i].
+        let mut chain = MockChain::linear_chain(10);
+        let shard_uid = ShardUId::single_shard();
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        store_helper::set_flat_state_value(
+            &mut store_update,
+            shard_uid,
+            vec![1],
+            Some(FlatStateValue::value_ref(&[0])),
+        );
+        for i in 1..10 {
+            let delta = FlatStateDelta {
+                changes: FlatStateChanges::from([
+                    (vec![1], Some(FlatStateValue::value_ref(&[i as u8]))),
+                    (vec![i as u8 + 1], Some(FlatStateValue::value_ref(&[i as u8]))),
+                ]),
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block(i),
+                    prev_block_with_changes: None,
+                },
+            };
+            store_helper::set_delta(&mut store_update, shard_uid, &delta);
+        }
+        store_update.commit().unwrap();
+
+        let policy = FlatStorageDeltasPolicy { flat_head_gap: 2, compaction_threshold: 5 };
+        let deltas_config = FlatStorageDeltasConfig { default: policy, ..Default::default() };
+        let flat_storage_manager = FlatStorageManager::with_config(store.clone(), 0, deltas_config);
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+
+        // Block 4 is too close to the flat head to be compacted, block 9 is not.
+        assert!(!flat_storage.compact_deltas(&chain.get_block_hash(4)).unwrap());
+        assert!(flat_storage.compact_deltas(&chain.get_block_hash(9)).unwrap());
+        let get_deltas_to_read = |block_hash| {
+            let guard = flat_storage.0.read().unwrap();
+            let (blocks, compacted_changes) = guard.get_deltas_to_read(&block_hash).unwrap();
+            (blocks.len(), compacted_changes.is_some())
+        };
+        assert_eq!(get_deltas_to_read(chain.get_block_hash(9)), (0, true));
+        assert_eq!(get_deltas_to_read(chain.get_block_hash(4)), (4, false));
+        assert!(!flat_storage.compact_deltas(&chain.get_block_hash(9)).unwrap());
+
+        let chunk_view9 =
+            flat_storage_manager.chunk_view(shard_uid, chain.get_block_hash(9)).unwrap();
+        let chunk_view4 =
+            flat_storage_manager.chunk_view(shard_uid, chain.get_block_hash(4)).unwrap();
+        assert_eq!(chunk_view9.get_value(&[1]).unwrap(), Some(FlatStateValue::value_ref(&[9])));
+        assert_eq!(chunk_view9.get_value(&[5]).unwrap(), Some(FlatStateValue::value_ref(&[4])));
+        assert!(chunk_view9.contains_key(&[10]).unwrap());
+        assert_eq!(chunk_view4.get_value(&[1]).unwrap(), Some(FlatStateValue::value_ref(&[4])));
+        assert_eq!(chunk_view4.get_value(&[6]).unwrap(), None);
+
+        // The blocks above the compacted deltas only go through their own deltas.
+        let hash = chain.create_block();
+        let store_update = flat_storage
+            .add_delta(FlatStateDelta {
+                changes: FlatStateChanges::from([(vec![1], None)]),
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block_info(&hash),
+                    prev_block_with_changes: None,
+                },
+            })
+            .unwrap();
+        store_update.commit().unwrap();
+        assert_eq!(get_deltas_to_read(hash), (1, true));
+        let chunk_view10 = flat_storage_manager.chunk_view(shard_uid, hash).unwrap();
+        assert_eq!(chunk_view10.get_value(&[1]).unwrap(), None);
+        assert_eq!(chunk_view10.get_value(&[3]).unwrap(), Some(FlatStateValue::value_ref(&[2])));
+
+        // Moving the flat head drops the compacted deltas.
+        flat_storage.update_flat_head(&chain.get_block_hash(5), true).unwrap();
+        assert_eq!(get_deltas_to_read(hash), (5, false));
+        assert_eq!(chunk_view10.get_value(&[1]).unwrap(), None);
+        assert_eq!(chunk_view10.get_value(&[7]).unwrap(), Some(FlatStateValue::value_ref(&[6])));
+    }
+
+    /// Checks that the values at the blocks below the flat head are read from
+    /// the flat state history while they are within the history length.
+    #[test]
+    fn flat_storage_history() {
+        // Block i sets key &[1] to &[i], except block 7 which has no changes.
+        // Block 3 also sets key &[2].
+        let chain = MockChain::linear_chain(10);
+        let shard_uid = ShardUId::single_shard();
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        store_helper::set_flat_state_value(
+            &mut store_update,
+            shard_uid,
+            vec![1],
+            Some(FlatStateValue::value_ref(&[0])),
+        );
+        for i in 1..10 {
+            let mut changes = vec![(vec![1], Some(FlatStateValue::value_ref(&[i as u8])))];
+            if i == 3 {
+                changes.push((vec![2], Some(FlatStateValue::value_ref(&[3]))));
+            }
+            let (changes, prev_block_with_changes) = match i {
+                7 => (
+                    vec![],
+                    Some(BlockWithChangesInfo { hash: chain.get_block_hash(6), height: 6 }),
+                ),
+                _ => (changes, None),
+            };
+            let delta = FlatStateDelta {
+                changes: FlatStateChanges::from(changes),
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block(i),
+                    prev_block_with_changes,
+                },
+            };
+            store_helper::set_delta(&mut store_update, shard_uid, &delta);
+        }
+        store_update.commit().unwrap();
+
+        let flat_storage_manager = FlatStorageManager::with_history(store.clone(), 4);
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+        let value_at = |flat_storage: &FlatStorage, height: BlockHeight, key: u8| {
+            flat_storage.get_value(&chain.get_block_hash(height), &[key]).unwrap()
+        };
+        let value = |v: u8| Some(FlatStateValue::value_ref(&[v]));
+
+        // The history covers blocks 1 to 4 below the flat head.
+        flat_storage.update_flat_head(&chain.get_block_hash(5), true).unwrap();
+        assert!(!flat_storage.supports_block(&chain.get_block_hash(0)));
+        for height in 1..10 {
+            assert!(flat_storage.supports_block(&chain.get_block_hash(height)));
+            assert_eq!(
+                value_at(&flat_storage, height, 1),
+                value(if height == 7 { 6 } else { height as u8 })
+            );
+            assert_eq!(
+                value_at(&flat_storage, height, 2),
+                if height < 3 { None } else { value(3) }
+            );
+        }
+        assert!(!flat_storage.contains_key(&chain.get_block_hash(2), &[2]).unwrap());
+        assert!(flat_storage.contains_key(&chain.get_block_hash(4), &[2]).unwrap());
+
+        // Blocks 1 to 4 fall out of the history.
+        flat_storage.update_flat_head(&chain.get_block_hash(9), true).unwrap();
+        for height in 1..5 {
+            assert!(!flat_storage.supports_block(&chain.get_block_hash(height)));
+        }
+        for height in 5..10 {
+            assert_eq!(
+                value_at(&flat_storage, height, 1),
+                value(if height == 7 { 6 } else { height as u8 })
+            );
+            assert_eq!(value_at(&flat_storage, height, 2), value(3));
+        }
+
+        // The history is loaded on restart and removed if it gets disabled.
+        let flat_storage_manager = FlatStorageManager::with_history(store.clone(), 4);
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+        assert_eq!(value_at(&flat_storage, 7, 1), value(6));
+        let flat_storage_manager = FlatStorageManager::new(store.clone());
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage = flat_storage_manager.get_flat_storage_for_shard(shard_uid).unwrap();
+        assert!(!flat_storage.supports_block(&chain.get_block_hash(7)));
+        assert!(store_helper::get_flat_state_history_blocks(&store, shard_uid, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn flat_storage_first_in_range() {
+        // Flat head is at block 0 with keys &[1], &[3] and &[5]. The following blocks change some
+        // of them and add new ones.
+        let chain = MockChain::linear_chain(3);
+        let shard_uid = ShardUId::single_shard();
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head: chain.get_block(0) }),
+        );
+        for key in [1, 3, 5] {
+            store_helper::set_flat_state_value(
+                &mut store_update,
+                shard_uid,
+                vec![key],
+                Some(FlatStateValue::value_ref(&[0])),
+            );
+        }
+        let block_changes = [
+            vec![(vec![1], None), (vec![2], Some(FlatStateValue::value_ref(&[1])))],
+            vec![
+                (vec![2], None),
+                (vec![3], Some(FlatStateValue::value_ref(&[2]))),
+                (vec![4], Some(FlatStateValue::value_ref(&[2]))),
+            ],
+        ];
+        for (i, changes) in block_changes.into_iter().enumerate() {
+            let delta = FlatStateDelta {
+                changes: FlatStateChanges::from(changes),
+                metadata: FlatStateDeltaMetadata {
+                    block: chain.get_block(i as BlockHeight + 1),
+                    prev_block_with_changes: None,
+                },
+            };
+            store_helper::set_delta(&mut store_update, shard_uid, &delta);
+        }
+        store_update.commit().unwrap();
+
+        let flat_storage_manager = FlatStorageManager::new(store);
+        flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+
+        let scan = |height: BlockHeight| {
+            let chunk_view =
+                flat_storage_manager.chunk_view(shard_uid, chain.get_block_hash(height)).unwrap();
+            let changes = chunk_view.get_range_changes(&[0], &[10]).unwrap();
+            let mut entries = vec![];
+            let mut from = vec![0];
+            while let Some((key, value)) =
+                chunk_view.get_first_in_range(&from, &[10], &changes).unwrap()
+            {
+                from = key.clone();
+                from.push(0);
+                entries.push((key[0], value));
+            }
+            entries
+        };
+        let value = |v: u8| FlatStateValue::value_ref(&[v]);
+        assert_eq!(scan(0), vec![(1, value(0)), (3, value(0)), (5, value(0))]);
+        assert_eq!(scan(1), vec![(2, value(1)), (3, value(0)), (5, value(0))]);
+        assert_eq!(scan(2), vec![(3, value(2)), (4, value(2)), (5, value(0))]);
+    }
+
     #[test]
     fn flat_storage_with_hops() {
         init_test_logger();
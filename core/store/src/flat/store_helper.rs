@@ -3,7 +3,8 @@
 
 use super::delta::{FlatStateDelta, FlatStateDeltaMetadata};
 use super::types::{
-    FlatStateIterator, FlatStateValuesInliningMigrationStatus, FlatStorageResult, FlatStorageStatus,
+    FlatStateHistoryBlock, FlatStateIterator, FlatStateValuesInliningMigrationStatus,
+    FlatStorageResult, FlatStorageStatus,
 };
 use crate::db::FLAT_STATE_VALUES_INLINING_MIGRATION_STATUS_KEY;
 use crate::flat::delta::{BlockWithChangesInfo, FlatStateChanges, KeyForFlatStateDelta};
@@ -14,6 +15,7 @@ use borsh::BorshDeserialize;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state::FlatStateValue;
+use near_primitives::types::BlockHeight;
 use std::io;
 
 pub fn get_delta_changes(
@@ -229,6 +231,141 @@ pub fn set_flat_storage_status(
         .expect("Borsh should not have failed here")
 }
 
+/// Encodes the key of `DBCol::FlatStateHistory` without the height.  The trie
+/// key is prefixed with its length, so that the entries of a key are not mixed
+/// with the entries of the keys it is a prefix of.
+fn encode_flat_state_history_key_prefix(shard_uid: ShardUId, key: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(8 + 4 + key.len() + 8);
+    buffer.extend_from_slice(&shard_uid.to_bytes());
+    buffer.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(key);
+    buffer
+}
+
+fn encode_flat_state_history_block_key(shard_uid: ShardUId, height: BlockHeight) -> Vec<u8> {
+    [&shard_uid.to_bytes()[..], &height.to_be_bytes()].concat()
+}
+
+/// Returns the value `key` had before its first change recorded in the flat
+/// state history above `height`, or `None` if no change is recorded, in which
+/// case the value at `height` is the one at the flat head.
+pub fn get_flat_state_history_value(
+    store: &Store,
+    shard_uid: ShardUId,
+    key: &[u8],
+    height: BlockHeight,
+) -> FlatStorageResult<Option<Option<FlatStateValue>>> {
+    let prefix = encode_flat_state_history_key_prefix(shard_uid, key);
+    let lower_bound = [&prefix[..], &(height + 1).to_be_bytes()].concat();
+    let upper_bound = crate::db::prefix_upper_bound(&prefix);
+    let entry = store
+        .iter_range(DBCol::FlatStateHistory, Some(&lower_bound), upper_bound.as_deref())
+        .next()
+        .transpose()
+        .map_err(|err| {
+            FlatStorageError::StorageInternalError(format!(
+                "failed to read FlatStateHistory value: {err}"
+            ))
+        })?;
+    entry
+        .map(|(_, value)| {
+            Option::<FlatStateValue>::try_from_slice(&value).map_err(|err| {
+                FlatStorageError::StorageInternalError(format!(
+                    "invalid FlatStateHistory value format: {err}"
+                ))
+            })
+        })
+        .transpose()
+}
+
+/// Records the block at `height` in the flat state history, together with
+/// the values the keys it changes had before it.
+pub fn set_flat_state_history_block(
+    store_update: &mut StoreUpdate,
+    shard_uid: ShardUId,
+    height: BlockHeight,
+    block_hash: CryptoHash,
+    prev_values: Vec<(Vec<u8>, Option<FlatStateValue>)>,
+) {
+    let mut keys = Vec::with_capacity(prev_values.len());
+    for (key, value) in prev_values {
+        let db_key =
+            [&encode_flat_state_history_key_prefix(shard_uid, &key)[..], &height.to_be_bytes()]
+                .concat();
+        store_update
+            .set_ser(DBCol::FlatStateHistory, &db_key, &value)
+            .expect("Borsh should not have failed here");
+        keys.push(key);
+    }
+    store_update
+        .set_ser(
+            DBCol::FlatStateHistoryBlocks,
+            &encode_flat_state_history_block_key(shard_uid, height),
+            &FlatStateHistoryBlock { block_hash, keys },
+        )
+        .expect("Borsh should not have failed here");
+}
+
+/// Returns the blocks recorded in the flat state history below `height`,
+/// or all of them if `height` is `None`, in increasing order of height.
+pub fn get_flat_state_history_blocks(
+    store: &Store,
+    shard_uid: ShardUId,
+    height: Option<BlockHeight>,
+) -> FlatStorageResult<Vec<(BlockHeight, FlatStateHistoryBlock)>> {
+    let lower_bound = shard_uid.to_bytes();
+    let upper_bound = match height {
+        Some(height) => encode_flat_state_history_block_key(shard_uid, height),
+        None => ShardUId::next_shard_prefix(&lower_bound).to_vec(),
+    };
+    store
+        .iter_range(DBCol::FlatStateHistoryBlocks, Some(&lower_bound), Some(&upper_bound))
+        .map(|result| {
+            let (key, value) = result.map_err(|err| {
+                FlatStorageError::StorageInternalError(format!(
+                    "failed to read FlatStateHistoryBlocks: {err}"
+                ))
+            })?;
+            let height =
+                <[u8; 8]>::try_from(&key[8..]).map(BlockHeight::from_be_bytes).map_err(|err| {
+                    FlatStorageError::StorageInternalError(format!(
+                        "invalid FlatStateHistoryBlocks key format: {err}"
+                    ))
+                })?;
+            let block = FlatStateHistoryBlock::try_from_slice(&value).map_err(|err| {
+                FlatStorageError::StorageInternalError(format!(
+                    "invalid FlatStateHistoryBlocks value format: {err}"
+                ))
+            })?;
+            Ok((height, block))
+        })
+        .collect()
+}
+
+/// Removes the block at `height` and its values from the flat state history.
+pub fn remove_flat_state_history_block(
+    store_update: &mut StoreUpdate,
+    shard_uid: ShardUId,
+    height: BlockHeight,
+    block: &FlatStateHistoryBlock,
+) {
+    for key in &block.keys {
+        let db_key =
+            [&encode_flat_state_history_key_prefix(shard_uid, key)[..], &height.to_be_bytes()]
+                .concat();
+        store_update.delete(DBCol::FlatStateHistory, &db_key);
+    }
+    store_update.delete(
+        DBCol::FlatStateHistoryBlocks,
+        &encode_flat_state_history_block_key(shard_uid, height),
+    );
+}
+
+pub fn remove_all_flat_state_history(store_update: &mut StoreUpdate, shard_uid: ShardUId) {
+    remove_range_by_shard_uid(store_update, shard_uid, DBCol::FlatStateHistory);
+    remove_range_by_shard_uid(store_update, shard_uid, DBCol::FlatStateHistoryBlocks);
+}
+
 /// Returns iterator over flat storage entries for a given shard and range of
 /// state keys. `None` means that there is no bound in respective direction.
 /// It reads data only from `FlatState` column which represents the state at
@@ -17,6 +17,16 @@ impl BlockInfo {
     }
 }
 
+/// Block covered by the flat state history, stored in
+/// `DBCol::FlatStateHistoryBlocks` by its height.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FlatStateHistoryBlock {
+    pub block_hash: CryptoHash,
+    /// Trie keys changed by the block, which have their previous values
+    /// stored in `DBCol::FlatStateHistory`.
+    pub keys: Vec<Vec<u8>>,
+}
+
 #[derive(strum::AsRefStr, strum::Display, Debug, PartialEq, Eq, thiserror::Error)]
 pub enum FlatStorageError {
     /// This means we can't find a path from `flat_head` to the block. Includes
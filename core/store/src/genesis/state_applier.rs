@@ -313,7 +313,10 @@ impl GenesisStateApplier {
                         set_promise_yield_receipt(state_update, &receipt);
                     });
                 }
-                ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => {
+                ReceiptEnum::Data(_)
+                | ReceiptEnum::PromiseResume(_)
+                | ReceiptEnum::GlobalContractDistribution(_)
+                | ReceiptEnum::RuntimeConfigOverridesDistribution(_) => {
                     panic!("Expected action receipt")
                 }
             }
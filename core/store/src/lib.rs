@@ -7,25 +7,27 @@ pub use crate::trie::{
     estimator, resharding, ApplyStatePartResult, KeyForStateChanges, KeyLookupMode, NibbleSlice,
     PartialStorage, PrefetchApi, PrefetchError, RawTrieNode, RawTrieNodeWithSize, ShardTries,
     StateSnapshot, StateSnapshotConfig, Trie, TrieAccess, TrieCache, TrieCachingStorage,
-    TrieChanges, TrieConfig, TrieDBStorage, TrieStorage, WrappedTrieChanges,
+    TrieChanges, TrieConfig, TrieDBStorage, TrieRange, TrieStorage, WrappedTrieChanges,
     STATE_SNAPSHOT_COLUMNS,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 pub use columns::DBCol;
 pub use db::{
-    CHUNK_TAIL_KEY, COLD_HEAD_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, GENESIS_JSON_HASH_KEY,
-    GENESIS_STATE_ROOTS_KEY, HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY,
-    LATEST_KNOWN_KEY, STATE_SNAPSHOT_KEY, STATE_SYNC_DUMP_KEY, TAIL_KEY,
+    CHUNK_TAIL_KEY, COLD_HEAD_KEY, COLD_MIGRATION_PROGRESS_KEY, COLD_STATE_REFCOUNTS_KEY,
+    COLD_TAIL_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, GENESIS_JSON_HASH_KEY, GENESIS_STATE_ROOTS_KEY,
+    HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY, LATEST_KNOWN_KEY,
+    SANDBOX_RUNTIME_CONFIG_KEY, STATE_SNAPSHOT_KEY, STATE_SYNC_DUMP_KEY, TAIL_KEY,
 };
 use metadata::{DbKind, DbVersion, KIND_KEY, VERSION_KEY};
 use near_crypto::PublicKey;
 use near_fmt::{AbbrBytes, StorageKey};
+use near_parameters::RuntimeConfigOverridesRecord;
 use near_primitives::account::{AccessKey, Account};
 pub use near_primitives::errors::{MissingTrieValueContext, StorageError};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{
-    BufferedReceiptIndices, DelayedReceiptIndices, PromiseYieldIndices, PromiseYieldTimeout,
-    Receipt, ReceiptEnum, ReceivedData,
+    BufferedReceiptIndices, DelayedReceiptIndices, PriorityDelayedReceiptIndices,
+    PromiseYieldIndices, PromiseYieldTimeout, Receipt, ReceiptEnum, ReceivedData,
 };
 pub use near_primitives::shard_layout::ShardUId;
 use near_primitives::trie_key::{trie_key_parsers, TrieKey};
@@ -49,12 +51,18 @@ pub mod metadata;
 pub mod metrics;
 pub mod migrations;
 mod opener;
+#[cfg(unix)]
+pub mod remote;
 mod rocksdb_metrics;
 mod sync_utils;
 pub mod test_utils;
 pub mod trie;
 
-pub use crate::config::{Mode, StoreConfig};
+pub use crate::config::{
+    ColdRetentionConfig, ColumnCompression, CompressionAlgorithm, FlatStorageDeltasConfig,
+    FlatStorageDeltasPolicy, MemTrieShard, Mode, ObjectStorageConfig, StoreBackend, StoreConfig,
+    WalSyncPolicy,
+};
 pub use crate::opener::{
     checkpoint_hot_storage_and_cleanup_columns, StoreMigrator, StoreOpener, StoreOpenerError,
 };
@@ -119,21 +127,12 @@ impl NodeStorage {
         StoreOpener::new(home_dir, archive, config, cold_config)
     }
 
-    /// Constructs new object backed by given database.
-    fn from_rocksdb(
-        hot_storage: crate::db::RocksDB,
-        cold_storage: Option<crate::db::RocksDB>,
+    /// Constructs new object backed by given databases.
+    fn from_databases(
+        hot_storage: Arc<dyn Database>,
+        cold_storage: Option<crate::db::ColdDB>,
     ) -> Self {
-        let hot_storage = Arc::new(hot_storage);
-        let cold_storage = cold_storage.map(|storage| Arc::new(storage));
-
-        let cold_db = if let Some(cold_storage) = cold_storage {
-            Some(Arc::new(crate::db::ColdDB::new(cold_storage)))
-        } else {
-            None
-        };
-
-        Self { hot_storage, cold_storage: cold_db }
+        Self { hot_storage, cold_storage: cold_storage.map(Arc::new) }
     }
 
     /// Initialises an opener for a new temporary test store.
@@ -317,6 +316,17 @@ impl Store {
         self.storage.iter_prefix(col, key_prefix)
     }
 
+    /// Iterates over raw bytes of a range of keys, see
+    /// [`Database::iter_raw_bytes_range`]. Upper bound key is not included.
+    pub fn iter_raw_bytes_range<'a>(
+        &'a self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+    ) -> DBIterator<'a> {
+        self.storage.iter_raw_bytes_range(col, lower_bound, upper_bound)
+    }
+
     /// Iterates over a range of keys. Upper bound key is not included.
     pub fn iter_range<'a>(
         &'a self,
@@ -397,6 +407,17 @@ impl Store {
     pub fn get_store_statistics(&self) -> Option<StoreStatistics> {
         self.storage.get_store_statistics()
     }
+
+    /// Creates a checkpoint of the storage at `path`, only keeping the columns
+    /// in `columns_to_keep` if given. On the same file system the files of the
+    /// checkpoint are hard links to the files of the storage.
+    pub fn create_checkpoint(
+        &self,
+        path: &Path,
+        columns_to_keep: Option<&[DBCol]>,
+    ) -> anyhow::Result<()> {
+        self.storage.create_checkpoint(path, columns_to_keep)
+    }
 }
 
 impl Store {
@@ -899,12 +920,43 @@ pub fn has_promise_yield_receipt(
     trie.contains_key(&TrieKey::PromiseYieldReceipt { receiver_id, data_id })
 }
 
+pub fn set_promise_yield_expiry(
+    state_update: &mut TrieUpdate,
+    receiver_id: AccountId,
+    data_id: CryptoHash,
+    expires_at: BlockHeight,
+) {
+    set(state_update, TrieKey::PromiseYieldExpiry { receiver_id, data_id }, &expires_at);
+}
+
+pub fn remove_promise_yield_expiry(
+    state_update: &mut TrieUpdate,
+    receiver_id: &AccountId,
+    data_id: CryptoHash,
+) {
+    state_update.remove(TrieKey::PromiseYieldExpiry { receiver_id: receiver_id.clone(), data_id });
+}
+
+pub fn get_promise_yield_expiry(
+    trie: &dyn TrieAccess,
+    receiver_id: &AccountId,
+    data_id: CryptoHash,
+) -> Result<Option<BlockHeight>, StorageError> {
+    get(trie, &TrieKey::PromiseYieldExpiry { receiver_id: receiver_id.clone(), data_id })
+}
+
 pub fn get_buffered_receipt_indices(
     trie: &dyn TrieAccess,
 ) -> Result<BufferedReceiptIndices, StorageError> {
     Ok(get(trie, &TrieKey::BufferedReceiptIndices)?.unwrap_or_default())
 }
 
+pub fn get_priority_delayed_receipt_indices(
+    trie: &dyn TrieAccess,
+) -> Result<PriorityDelayedReceiptIndices, StorageError> {
+    Ok(get(trie, &TrieKey::PriorityDelayedReceiptIndices)?.unwrap_or_default())
+}
+
 pub fn set_access_key(
     state_update: &mut TrieUpdate,
     account_id: AccountId,
@@ -957,6 +1009,31 @@ pub fn get_code(
     trie.get(&key).map(|opt| opt.map(|code| ContractCode::new(code, code_hash)))
 }
 
+pub fn set_global_contract_code(state_update: &mut TrieUpdate, code: &ContractCode) {
+    state_update.set(TrieKey::GlobalContractCode { code_hash: *code.hash() }, code.code().to_vec());
+}
+
+pub fn get_global_contract_code(
+    trie: &dyn TrieAccess,
+    code_hash: CryptoHash,
+) -> Result<Option<ContractCode>, StorageError> {
+    let key = TrieKey::GlobalContractCode { code_hash };
+    trie.get(&key).map(|opt| opt.map(|code| ContractCode::new(code, Some(code_hash))))
+}
+
+pub fn get_runtime_config_overrides_record(
+    trie: &dyn TrieAccess,
+) -> Result<RuntimeConfigOverridesRecord, StorageError> {
+    Ok(get(trie, &TrieKey::RuntimeConfigOverrides)?.unwrap_or_default())
+}
+
+pub fn set_runtime_config_overrides_record(
+    state_update: &mut TrieUpdate,
+    record: &RuntimeConfigOverridesRecord,
+) {
+    set(state_update, TrieKey::RuntimeConfigOverrides, record)
+}
+
 /// Removes account, code and all access keys associated to it.
 pub fn remove_account(
     state_update: &mut TrieUpdate,
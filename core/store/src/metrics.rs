@@ -232,6 +232,39 @@ pub static COLD_MIGRATION_READS: Lazy<IntCounterVec> = Lazy::new(|| {
 pub static COLD_HEAD_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge("near_cold_head_height", "Height of the head of cold storage").unwrap()
 });
+pub static COLD_READ_CACHE_HITS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_cold_read_cache_hits",
+        "Reads from cold storage served by the read cache",
+        &["col"],
+    )
+    .unwrap()
+});
+pub static COLD_READ_CACHE_MISSES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_cold_read_cache_misses",
+        "Reads from cold storage not served by the read cache",
+        &["col"],
+    )
+    .unwrap()
+});
+pub static COLD_READ_CACHE_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_cold_read_cache_size_bytes",
+        "Total size of the keys and values in the cold storage read cache",
+    )
+    .unwrap()
+});
+pub static COLD_TAIL_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge("near_cold_tail_height", "Lowest height kept in cold storage").unwrap()
+});
+pub static COLD_PRUNE_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram(
+        "near_cold_prune_duration",
+        "Time it takes to prune one height from cold storage",
+    )
+    .unwrap()
+});
 pub static COLD_COPY_DURATION: Lazy<Histogram> = Lazy::new(|| {
     try_create_histogram(
         "near_cold_copy_duration",
@@ -437,6 +470,22 @@ pub mod flat_state_metrics {
         )
         .unwrap()
     });
+    pub static FLAT_STORAGE_COMPACTED_DELTAS: Lazy<IntGaugeVec> = Lazy::new(|| {
+        try_create_int_gauge_vec(
+            "near_flat_storage_compacted_deltas",
+            "Number of cached deltas in flat storage merged into the compacted deltas",
+            &["shard_uid"],
+        )
+        .unwrap()
+    });
+    pub static FLAT_STORAGE_MERGED_DELTAS: Lazy<IntCounterVec> = Lazy::new(|| {
+        try_create_int_counter_vec(
+            "near_flat_storage_merged_deltas",
+            "Number of deltas merged into the flat state when moving flat storage head",
+            &["shard_uid"],
+        )
+        .unwrap()
+    });
     pub static FLAT_STORAGE_CACHED_CHANGES_NUM_ITEMS: Lazy<IntGaugeVec> = Lazy::new(|| {
         try_create_int_gauge_vec(
             "near_flat_storage_cached_changes_num_items",
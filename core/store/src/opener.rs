@@ -1,7 +1,14 @@
 use crate::db::rocksdb::snapshot::{Snapshot, SnapshotError, SnapshotRemoveError};
 use crate::db::rocksdb::RocksDB;
+#[cfg(feature = "object_storage")]
+use crate::db::ObjectStorageDB;
+#[cfg(feature = "sqlite")]
+use crate::db::SqliteDB;
+use crate::db::{ColdDB, Database, PipelinedDB};
 use crate::metadata::{DbKind, DbMetadata, DbVersion, DB_VERSION};
-use crate::{DBCol, DBTransaction, Mode, NodeStorage, Store, StoreConfig, Temperature};
+use crate::{
+    DBCol, DBTransaction, Mode, NodeStorage, Store, StoreBackend, StoreConfig, Temperature,
+};
 use std::sync::Arc;
 
 #[derive(Debug, thiserror::Error)]
@@ -189,8 +196,8 @@ struct DBOpener<'a> {
 
     /// Temperature of the database.
     ///
-    /// This affects whether refcount merge operator is configured on reference
-    /// counted column.  It’s important that the value is correct.  RPC and
+    /// This affects whether empty values are removed from reference counted
+    /// columns by compaction.  It’s important that the value is correct.  RPC and
     /// Archive databases are considered hot.
     temp: Temperature,
 }
@@ -228,6 +235,13 @@ impl<'a> StoreOpener<'a> {
         &self.hot.path
     }
 
+    /// Returns path to the cold database if one was configured.
+    ///
+    /// Does not check whether the database actually exists.
+    pub fn cold_path(&self) -> Option<&std::path::Path> {
+        self.cold.as_ref().map(|cold| cold.path.as_path())
+    }
+
     #[cfg(test)]
     pub(crate) fn config(&self) -> &StoreConfig {
         self.hot.config
@@ -240,7 +254,7 @@ impl<'a> StoreOpener<'a> {
         self.open_in_mode(Mode::ReadWrite)
     }
 
-    /// Opens the RocksDB database(s) for hot and cold (if configured) storages.
+    /// Opens the database(s) for hot and cold (if configured) storages.
     ///
     /// When opening in read-only mode, verifies that the database version is
     /// what the node expects and fails if it isn’t.  If database doesn’t exist,
@@ -271,15 +285,31 @@ impl<'a> StoreOpener<'a> {
             Snapshot::none()
         };
 
-        let (hot_db, _) = self.hot.open(mode, DB_VERSION)?;
+        let (mut hot_db, _) = self.hot.open(mode, DB_VERSION)?;
+        if mode.read_write() && self.hot.config.async_writes {
+            hot_db = PipelinedDB::new(hot_db);
+        }
         let cold_db = self
             .cold
             .as_ref()
-            .map(|cold| cold.open(mode, DB_VERSION))
-            .transpose()?
-            .map(|(db, _)| db);
-
-        let storage = NodeStorage::from_rocksdb(hot_db, cold_db);
+            .map(|cold| {
+                let read_cache_size = cold.config.read_cache_size.unwrap_or_default();
+                // Object storage can't update reference counts, so its State
+                // keeps every trie node.
+                let state_pruning = cold.config.retention.is_some()
+                    && cold.config.backend != StoreBackend::ObjectStorage;
+                cold.open(mode, DB_VERSION).map(|(db, _)| {
+                    let cold_db = ColdDB::with_read_cache(db, read_cache_size);
+                    if state_pruning {
+                        cold_db.with_state_pruning()
+                    } else {
+                        cold_db
+                    }
+                })
+            })
+            .transpose()?;
+
+        let storage = NodeStorage::from_databases(hot_db, cold_db);
 
         hot_snapshot.remove()?;
         cold_snapshot.remove()?;
@@ -338,8 +368,7 @@ impl<'a> StoreOpener<'a> {
             None if mode.can_create() => {
                 tracing::info!(target: "db_opener", path=%opener.path.display(), "The database doesn't exist, creating it.");
 
-                let db = opener.create()?;
-                let store = Store { storage: Arc::new(db) };
+                let store = Store { storage: opener.create()? };
                 store.set_db_version(DB_VERSION)?;
                 return Ok(());
             }
@@ -466,20 +495,17 @@ impl<'a> StoreOpener<'a> {
         opener: &DBOpener,
         version: DbVersion,
     ) -> Result<Store, StoreOpenerError> {
-        let (db, _) = opener.open(mode, version)?;
-        let store = Store { storage: Arc::new(db) };
-        Ok(store)
+        let (storage, _) = opener.open(mode, version)?;
+        Ok(Store { storage })
     }
 
     fn open_store_unsafe(mode: Mode, opener: &DBOpener) -> Result<Store, StoreOpenerError> {
-        let db = opener.open_unsafe(mode)?;
-        let store = Store { storage: Arc::new(db) };
-        Ok(store)
+        Ok(Store { storage: opener.open_unsafe(mode)? })
     }
 }
 
 impl<'a> DBOpener<'a> {
-    /// Constructs new opener for a single RocksDB builder.
+    /// Constructs new opener for a single database.
     ///
     /// The path to the database is resolved based on the path in config with
     /// given home_dir as base directory for resolving relative paths.
@@ -499,7 +525,36 @@ impl<'a> DBOpener<'a> {
     /// introduced, the kind is returned as `None`.  Otherwise, it’s also
     /// fetched and if it’s not there error is returned.
     fn get_metadata(&self) -> std::io::Result<Option<DbMetadata>> {
-        RocksDB::get_metadata(&self.path, self.config)
+        match self.config.backend {
+            StoreBackend::RocksDB => RocksDB::get_metadata(&self.path, self.config),
+            #[cfg(feature = "sqlite")]
+            StoreBackend::Sqlite => SqliteDB::get_metadata(&self.path, self.config),
+            #[cfg(not(feature = "sqlite"))]
+            StoreBackend::Sqlite => Err(sqlite_not_supported()),
+            #[cfg(feature = "object_storage")]
+            StoreBackend::ObjectStorage => ObjectStorageDB::get_metadata(&self.path, self.config),
+            #[cfg(not(feature = "object_storage"))]
+            StoreBackend::ObjectStorage => Err(object_storage_not_supported()),
+        }
+    }
+
+    /// Opens the database with the engine selected in the configuration.
+    fn open_db(&self, mode: Mode) -> std::io::Result<Arc<dyn Database>> {
+        match self.config.backend {
+            StoreBackend::RocksDB => {
+                Ok(Arc::new(RocksDB::open(&self.path, &self.config, mode, self.temp)?))
+            }
+            #[cfg(feature = "sqlite")]
+            StoreBackend::Sqlite => Ok(Arc::new(SqliteDB::open(&self.path, &self.config, mode)?)),
+            #[cfg(not(feature = "sqlite"))]
+            StoreBackend::Sqlite => Err(sqlite_not_supported()),
+            #[cfg(feature = "object_storage")]
+            StoreBackend::ObjectStorage => {
+                Ok(Arc::new(ObjectStorageDB::open(&self.path, &self.config, mode)?))
+            }
+            #[cfg(not(feature = "object_storage"))]
+            StoreBackend::ObjectStorage => Err(object_storage_not_supported()),
+        }
     }
 
     /// Opens the database in given mode checking expected version and kind.
@@ -518,9 +573,13 @@ impl<'a> DBOpener<'a> {
     /// new version.
     ///
     /// Use [`Self::create`] to create a new database.
-    fn open(&self, mode: Mode, want_version: DbVersion) -> std::io::Result<(RocksDB, DbMetadata)> {
-        let db = RocksDB::open(&self.path, &self.config, mode, self.temp)?;
-        let metadata = DbMetadata::read(&db)?;
+    fn open(
+        &self,
+        mode: Mode,
+        want_version: DbVersion,
+    ) -> std::io::Result<(Arc<dyn Database>, DbMetadata)> {
+        let db = self.open_db(mode)?;
+        let metadata = DbMetadata::read(db.as_ref())?;
         if want_version != metadata.version {
             let msg = format!("unexpected DbVersion {}; expected {want_version}", metadata.version);
             Err(std::io::Error::other(msg))
@@ -533,22 +592,43 @@ impl<'a> DBOpener<'a> {
     ///
     /// This is only suitable when creating the database or setting the version
     /// and kind for the first time.
-    fn open_unsafe(&self, mode: Mode) -> std::io::Result<RocksDB> {
-        let db = RocksDB::open(&self.path, &self.config, mode, self.temp)?;
-        Ok(db)
+    fn open_unsafe(&self, mode: Mode) -> std::io::Result<Arc<dyn Database>> {
+        self.open_db(mode)
     }
 
     /// Creates a new database.
-    fn create(&self) -> std::io::Result<RocksDB> {
-        RocksDB::open(&self.path, &self.config, Mode::Create, self.temp)
+    fn create(&self) -> std::io::Result<Arc<dyn Database>> {
+        self.open_db(Mode::Create)
     }
 
     /// Creates a new snapshot for the database.
     fn snapshot(&self) -> Result<Snapshot, SnapshotError> {
-        Snapshot::new(&self.path, &self.config, self.temp)
+        match self.config.backend {
+            StoreBackend::RocksDB => Snapshot::new(&self.path, &self.config, self.temp),
+            StoreBackend::Sqlite | StoreBackend::ObjectStorage => {
+                Snapshot::with_checkpoint(&self.path, &self.config, |path| {
+                    let db = self.open_db(Mode::ReadWriteExisting)?;
+                    db.create_checkpoint(path, None).map_err(std::io::Error::other)
+                })
+            }
+        }
     }
 }
 
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_not_supported() -> std::io::Error {
+    std::io::Error::other(
+        "the SQLite storage backend requires neard built with the `sqlite` feature",
+    )
+}
+
+#[cfg(not(feature = "object_storage"))]
+fn object_storage_not_supported() -> std::io::Error {
+    std::io::Error::other(
+        "the object storage backend requires neard built with the `object_storage` feature",
+    )
+}
+
 pub trait StoreMigrator {
     /// Checks whether migrator supports database versions starting at given.
     ///
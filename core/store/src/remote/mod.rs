@@ -0,0 +1,373 @@
+//! Read-only access to the store over gRPC on a Unix socket.
+//!
+//! [`StoreServer`] serves the reads of a [`Store`] to auxiliary processes,
+//! such as indexers and analytics jobs, which can then read the columns
+//! without opening RocksDB as secondary or copying the database.  The
+//! `near.store.Store` service is defined in `store.proto`, so that clients can
+//! be generated for any language with gRPC support.  [`RemoteStore`] is the
+//! Rust client.
+//!
+//! The messages are generated with `protobuf-codegen` like the network
+//! messages, so the service and the codec are implemented here rather than
+//! generated.
+
+use crate::{DBCol, Store};
+use bytes::{Buf, BufMut};
+use proto::{Entry, GetRequest, GetResponse, IterRequest, IterResponse};
+use std::io;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::codegen::{http, Body, BoxFuture, Context, Poll, Service, StdError};
+use tonic::transport::{Channel, Endpoint, Uri};
+use tonic::Status;
+
+mod _proto {
+    include!(concat!(env!("OUT_DIR"), "/proto/mod.rs"));
+}
+
+pub use _proto::store as proto;
+
+/// Maximum size of a single request or response.
+const MAX_MESSAGE_SIZE: usize = 256 * bytesize::MIB as usize;
+
+/// Maximum number of entries returned by a single `Iter` call.
+pub const MAX_ITER_ENTRIES: u32 = 10_000;
+
+const SERVICE_NAME: &str = "near.store.Store";
+const GET_PATH: &str = "/near.store.Store/Get";
+const ITER_PATH: &str = "/near.store.Store/Iter";
+
+/// gRPC codec sending `E` and receiving `D`.
+struct ProtoCodec<E, D>(PhantomData<fn(E) -> D>);
+
+impl<E, D> Default for ProtoCodec<E, D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: protobuf::Message, D: protobuf::Message> Codec for ProtoCodec<E, D> {
+    type Encode = E;
+    type Decode = D;
+    type Encoder = Self;
+    type Decoder = Self;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        Self::default()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        Self::default()
+    }
+}
+
+impl<E: protobuf::Message, D> Encoder for ProtoCodec<E, D> {
+    type Item = E;
+    type Error = Status;
+
+    fn encode(&mut self, item: E, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        item.write_to_writer(&mut dst.writer()).map_err(|err| Status::internal(err.to_string()))
+    }
+}
+
+impl<E, D: protobuf::Message> Decoder for ProtoCodec<E, D> {
+    type Item = D;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<D>, Status> {
+        D::parse_from_reader(&mut src.reader())
+            .map(Some)
+            .map_err(|err| Status::invalid_argument(err.to_string()))
+    }
+}
+
+fn resolve_column(name: &str) -> Result<DBCol, Status> {
+    DBCol::iter()
+        .find(|col| <&str>::from(col) == name)
+        .ok_or_else(|| Status::invalid_argument(format!("column {name} does not exist")))
+}
+
+fn handle_get(store: &Store, request: GetRequest) -> Result<GetResponse, Status> {
+    let col = resolve_column(&request.col)?;
+    let value = store.get(col, &request.key).map_err(|err| Status::internal(err.to_string()))?;
+    Ok(GetResponse { value: value.map(Vec::from), ..Default::default() })
+}
+
+fn handle_iter(store: &Store, request: IterRequest) -> Result<IterResponse, Status> {
+    let col = resolve_column(&request.col)?;
+    let entries = store
+        .iter_range(col, request.lower_bound.as_deref(), request.upper_bound.as_deref())
+        .take(request.limit.min(MAX_ITER_ENTRIES) as usize)
+        .map(|item| {
+            let (key, value) = item.map_err(|err| Status::internal(err.to_string()))?;
+            Ok(Entry { key: key.into_vec(), value: value.into_vec(), ..Default::default() })
+        })
+        .collect::<Result<_, Status>>()?;
+    Ok(IterResponse { entries, ..Default::default() })
+}
+
+/// A method of the store service.  The store is read on a blocking thread,
+/// so that slow reads don't hold up the other requests.
+struct UnaryMethod<Req, Resp> {
+    store: Store,
+    handler: fn(&Store, Req) -> Result<Resp, Status>,
+}
+
+impl<Req: Send + 'static, Resp: Send + 'static> tonic::server::UnaryService<Req>
+    for UnaryMethod<Req, Resp>
+{
+    type Response = Resp;
+    type Future = BoxFuture<tonic::Response<Resp>, Status>;
+
+    fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+        let store = self.store.clone();
+        let handler = self.handler;
+        Box::pin(async move {
+            let response =
+                tokio::task::spawn_blocking(move || handler(&store, request.into_inner()))
+                    .await
+                    .map_err(|err| Status::internal(err.to_string()))??;
+            Ok(tonic::Response::new(response))
+        })
+    }
+}
+
+/// The `near.store.Store` gRPC service.
+#[derive(Clone)]
+struct StoreService {
+    store: Store,
+}
+
+impl StoreService {
+    async fn unary<B, Req, Resp>(
+        store: Store,
+        handler: fn(&Store, Req) -> Result<Resp, Status>,
+        request: http::Request<B>,
+    ) -> http::Response<tonic::body::BoxBody>
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+        Req: protobuf::Message,
+        Resp: protobuf::Message,
+    {
+        let mut grpc = tonic::server::Grpc::new(ProtoCodec::<Resp, Req>::default())
+            .max_decoding_message_size(MAX_MESSAGE_SIZE)
+            .max_encoding_message_size(MAX_MESSAGE_SIZE);
+        grpc.unary(UnaryMethod { store, handler }, request).await
+    }
+}
+
+impl<B> Service<http::Request<B>> for StoreService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = std::convert::Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let store = self.store.clone();
+        match request.uri().path() {
+            GET_PATH => Box::pin(async move { Ok(Self::unary(store, handle_get, request).await) }),
+            ITER_PATH => {
+                Box::pin(async move { Ok(Self::unary(store, handle_iter, request).await) })
+            }
+            _ => Box::pin(async move {
+                Ok(http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", tonic::Code::Unimplemented as i32)
+                    .header("content-type", "application/grpc")
+                    .body(tonic::codegen::empty_body())
+                    .unwrap())
+            }),
+        }
+    }
+}
+
+impl tonic::server::NamedService for StoreService {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+/// Serves read-only access to a [`Store`] over gRPC on a Unix socket.
+///
+/// The server runs on its own thread.  It holds clones of the store, so it has
+/// to be stopped with [`StoreServer::stop`] before the databases can be closed.
+pub struct StoreServer {
+    path: PathBuf,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl StoreServer {
+    /// Starts serving `store` on a socket at `path`.  A socket file left at
+    /// `path` by a previous run is replaced.
+    pub fn start(store: Store, path: &Path) -> io::Result<Self> {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        let listener = std::os::unix::net::UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let (shutdown, shutdown_receiver) = tokio::sync::oneshot::channel::<()>();
+        let thread =
+            std::thread::Builder::new().name("store_server".to_string()).spawn(move || {
+                let result = runtime.block_on(async move {
+                    let listener = tokio::net::UnixListener::from_std(listener)?;
+                    tonic::transport::Server::builder()
+                        .add_service(StoreService { store })
+                        .serve_with_incoming_shutdown(UnixListenerStream::new(listener), async {
+                            let _ = shutdown_receiver.await;
+                        })
+                        .await
+                        .map_err(io::Error::other)
+                });
+                if let Err(err) = result {
+                    tracing::error!(target: "store", ?err, "The store server failed");
+                }
+            })?;
+        tracing::info!(target: "store", path = %path.display(), "Started the store server");
+        Ok(Self { path: path.to_path_buf(), shutdown: Some(shutdown), thread: Some(thread) })
+    }
+
+    /// Stops accepting connections and waits for the requests in progress to
+    /// finish.
+    pub fn stop(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                tracing::error!(target: "store", "The store server thread panicked");
+            }
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Connects the gRPC channel of [`RemoteStore`] to the socket of the server.
+#[derive(Clone)]
+struct UnixConnector(Arc<PathBuf>);
+
+impl Service<Uri> for UnixConnector {
+    type Response = tokio::net::UnixStream;
+    type Error = io::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let path = self.0.clone();
+        Box::pin(async move { tokio::net::UnixStream::connect(path.as_path()).await })
+    }
+}
+
+/// Client of a [`StoreServer`].
+#[derive(Clone)]
+pub struct RemoteStore {
+    grpc: tonic::client::Grpc<Channel>,
+}
+
+impl RemoteStore {
+    pub async fn connect(path: &Path) -> io::Result<Self> {
+        // The URI is required by the endpoint but not used by the connector.
+        let channel = Endpoint::from_static("http://[::]:50051")
+            .connect_with_connector(UnixConnector(Arc::new(path.to_path_buf())))
+            .await
+            .map_err(io::Error::other)?;
+        let grpc = tonic::client::Grpc::new(channel)
+            .max_decoding_message_size(MAX_MESSAGE_SIZE)
+            .max_encoding_message_size(MAX_MESSAGE_SIZE);
+        Ok(Self { grpc })
+    }
+
+    async fn unary<Req, Resp>(&mut self, path: &'static str, request: Req) -> io::Result<Resp>
+    where
+        Req: protobuf::Message,
+        Resp: protobuf::Message,
+    {
+        self.grpc.ready().await.map_err(io::Error::other)?;
+        let response = self
+            .grpc
+            .unary(
+                tonic::Request::new(request),
+                http::uri::PathAndQuery::from_static(path),
+                ProtoCodec::<Req, Resp>::default(),
+            )
+            .await
+            .map_err(io::Error::other)?;
+        Ok(response.into_inner())
+    }
+
+    /// Reads the value of `key`, with the reference count stripped in
+    /// reference-counted columns.
+    pub async fn get(&mut self, col: DBCol, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let request = GetRequest { col: col.to_string(), key: key.to_vec(), ..Default::default() };
+        let response: GetResponse = self.unary(GET_PATH, request).await?;
+        Ok(response.value)
+    }
+
+    /// Reads up to `limit` entries with keys in `[lower_bound, upper_bound)`.
+    /// At most [`MAX_ITER_ENTRIES`] entries are returned at once.
+    pub async fn iter_range(
+        &mut self,
+        col: DBCol,
+        lower_bound: Option<&[u8]>,
+        upper_bound: Option<&[u8]>,
+        limit: u32,
+    ) -> io::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let request = IterRequest {
+            col: col.to_string(),
+            lower_bound: lower_bound.map(<[u8]>::to_vec),
+            upper_bound: upper_bound.map(<[u8]>::to_vec),
+            limit,
+            ..Default::default()
+        };
+        let response: IterResponse = self.unary(ITER_PATH, request).await?;
+        Ok(response.entries.into_iter().map(|entry| (entry.key, entry.value)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RemoteStore, StoreServer};
+    use crate::test_utils::create_test_store;
+    use crate::DBCol;
+
+    #[tokio::test]
+    async fn test_remote_reads() {
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        for i in 0..10u8 {
+            store_update.set(DBCol::BlockHeight, &[i], &[i * 2]);
+        }
+        store_update.increment_refcount(DBCol::State, &[1], &[42]);
+        store_update.commit().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.sock");
+        let server = StoreServer::start(store, &path).unwrap();
+        let mut client = RemoteStore::connect(&path).await.unwrap();
+
+        assert_eq!(client.get(DBCol::BlockHeight, &[3]).await.unwrap(), Some(vec![6]));
+        assert_eq!(client.get(DBCol::BlockHeight, &[10]).await.unwrap(), None);
+        assert_eq!(client.get(DBCol::State, &[1]).await.unwrap(), Some(vec![42]));
+        let entries =
+            client.iter_range(DBCol::BlockHeight, Some(&[2]), Some(&[8]), 3).await.unwrap();
+        assert_eq!(entries, vec![(vec![2], vec![4]), (vec![3], vec![6]), (vec![4], vec![8])]);
+
+        drop(client);
+        tokio::task::spawn_blocking(move || server.stop()).await.unwrap();
+        assert!(!path.exists());
+    }
+}
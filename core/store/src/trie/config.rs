@@ -1,7 +1,8 @@
-use crate::config::{PrefetchConfig, TrieCacheConfig};
+use crate::config::{FlatStorageDeltasConfig, MemTrieShard, PrefetchConfig, TrieCacheConfig};
 use crate::StoreConfig;
 use near_primitives::shard_layout::ShardUId;
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, ShardId};
+use std::path::PathBuf;
 use std::str::FromStr;
 use tracing::error;
 
@@ -36,8 +37,23 @@ pub struct TrieConfig {
 
     /// List of shards we will load into memory.
     pub load_mem_tries_for_shards: Vec<ShardUId>,
+    /// Ids of shards we will load into memory, whatever their shard layout.
+    pub load_mem_tries_for_shard_ids: Vec<ShardId>,
     /// Whether mem-trie should be loaded for each tracked shard.
     pub load_mem_tries_for_tracked_shards: bool,
+    /// Maximum number of shards whose mem-tries are loaded at the same time
+    /// on startup; zero is treated as one.
+    pub load_mem_tries_parallelism: usize,
+    /// Directory to save the mem-tries to on shutdown and to restore them
+    /// from on startup, if `StoreConfig::persist_mem_tries` is enabled.
+    pub mem_tries_dump_dir: Option<PathBuf>,
+
+    /// Number of epochs of flat state history to keep, see
+    /// `StoreConfig::flat_state_history_epochs`.
+    pub flat_state_history_epochs: Option<u64>,
+    /// Policies of the flat storage deltas, see
+    /// `StoreConfig::flat_storage_deltas`.
+    pub flat_storage_deltas: FlatStorageDeltasConfig,
 }
 
 impl TrieConfig {
@@ -63,12 +79,31 @@ impl TrieConfig {
         }
         this.claim_sweat_prefetch_config.clone_from(&config.claim_sweat_prefetch_config);
         this.kaiching_prefetch_config.clone_from(&config.kaiching_prefetch_config);
-        this.load_mem_tries_for_shards.clone_from(&config.load_mem_tries_for_shards);
+        for shard in &config.load_mem_tries_for_shards {
+            match shard {
+                MemTrieShard::ShardId(shard_id) => {
+                    this.load_mem_tries_for_shard_ids.push(*shard_id)
+                }
+                MemTrieShard::ShardUId(shard_uid) => {
+                    this.load_mem_tries_for_shards.push(*shard_uid)
+                }
+            }
+        }
         this.load_mem_tries_for_tracked_shards = config.load_mem_tries_for_tracked_shards;
+        this.load_mem_tries_parallelism = config.load_mem_tries_parallelism;
+        this.flat_state_history_epochs = config.flat_state_history_epochs;
+        this.flat_storage_deltas = config.flat_storage_deltas.clone();
 
         this
     }
 
+    /// Whether the in-memory trie of the shard should be loaded, if the shard is tracked.
+    pub fn load_mem_trie_for_shard(&self, shard_uid: &ShardUId) -> bool {
+        self.load_mem_tries_for_tracked_shards
+            || self.load_mem_tries_for_shards.contains(shard_uid)
+            || self.load_mem_tries_for_shard_ids.contains(&shard_uid.shard_id())
+    }
+
     /// Size limit in bytes per single value for caching in shard caches.
     pub fn max_cached_value_size() -> usize {
         TRIE_LIMIT_CACHED_VALUE_SIZE
@@ -94,3 +129,22 @@ impl TrieConfig {
             || !self.kaiching_prefetch_config.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TrieConfig;
+    use crate::StoreConfig;
+    use near_primitives::shard_layout::ShardUId;
+
+    #[test]
+    fn test_load_mem_tries_for_shards() {
+        let store_config: StoreConfig =
+            serde_json::from_str(r#"{"load_mem_tries_for_shards": [0, "s3.v1"]}"#).unwrap();
+        let trie_config = TrieConfig::from_store_config(&store_config);
+        assert!(trie_config.load_mem_trie_for_shard(&ShardUId { version: 1, shard_id: 0 }));
+        assert!(trie_config.load_mem_trie_for_shard(&ShardUId { version: 3, shard_id: 0 }));
+        assert!(trie_config.load_mem_trie_for_shard(&ShardUId { version: 1, shard_id: 3 }));
+        assert!(!trie_config.load_mem_trie_for_shard(&ShardUId { version: 3, shard_id: 3 }));
+        assert!(!trie_config.load_mem_trie_for_shard(&ShardUId { version: 1, shard_id: 1 }));
+    }
+}
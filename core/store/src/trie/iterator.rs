@@ -393,10 +393,12 @@ enum IterStep {
     Value(CryptoHash),
 }
 
-impl<'a> Iterator for DiskTrieIterator<'a> {
-    type Item = Result<TrieItem, StorageError>;
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'a> DiskTrieIterator<'a> {
+    /// Advances the iterator like [`Iterator::next`], but returns the hash of
+    /// the value instead of retrieving the value itself.
+    pub(super) fn next_with_value_hash(
+        &mut self,
+    ) -> Option<Result<(Vec<u8>, CryptoHash), StorageError>> {
         loop {
             let iter_step = self.iter_step()?;
 
@@ -416,16 +418,22 @@ impl<'a> Iterator for DiskTrieIterator<'a> {
                     Ok(_) => (),
                     Err(err) => return Some(Err(err)),
                 },
-                (IterStep::Value(hash), true) => {
-                    return Some(
-                        self.trie.retrieve_value(&hash).map(|value| (self.key(), value.to_vec())),
-                    )
-                }
+                (IterStep::Value(hash), true) => return Some(Ok((self.key(), hash))),
             }
         }
     }
 }
 
+impl<'a> Iterator for DiskTrieIterator<'a> {
+    type Item = Result<TrieItem, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_with_value_hash()?.and_then(|(key, hash)| {
+            self.trie.retrieve_value(&hash).map(|value| (key, value.to_vec()))
+        }))
+    }
+}
+
 pub enum TrieIterator<'a> {
     Disk(DiskTrieIterator<'a>),
     Memtrie(MemTrieIterator<'a>),
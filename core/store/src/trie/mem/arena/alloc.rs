@@ -4,7 +4,9 @@ use crate::trie::mem::arena::metrics::{
     MEM_TRIE_ARENA_ACTIVE_ALLOCS_BYTES, MEM_TRIE_ARENA_MEMORY_USAGE_BYTES,
 };
 use crate::trie::mem::flexible_data::encoding::BorshFixedSize;
+use borsh::{BorshDeserialize, BorshSerialize};
 use near_o11y::metrics::IntGauge;
+use std::io;
 
 /// Simple bump allocator with freelists.
 ///
@@ -42,6 +44,16 @@ pub struct Allocator {
     memory_usage_gauge: IntGauge,
 }
 
+/// The part of the allocator state which is saved together with the arena
+/// memory to restore the allocator.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct AllocatorState {
+    freelists: Vec<ArenaPos>,
+    next_alloc_pos: ArenaPos,
+    active_allocs_bytes: u64,
+    active_allocs_count: u64,
+}
+
 const MAX_ALLOC_SIZE: usize = 16 * 1024;
 const ROUND_UP_TO_8_BYTES_UNDER: usize = 256;
 const ROUND_UP_TO_64_BYTES_UNDER: usize = 1024;
@@ -88,6 +100,38 @@ impl Allocator {
         }
     }
 
+    /// Writes the freelists, the next allocation position and the stats of
+    /// the allocator.
+    pub fn save(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        let state = AllocatorState {
+            freelists: self.freelists.to_vec(),
+            next_alloc_pos: self.next_alloc_pos,
+            active_allocs_bytes: self.active_allocs_bytes as u64,
+            active_allocs_count: self.active_allocs_count as u64,
+        };
+        borsh::to_writer(writer, &state)
+    }
+
+    /// Restores an allocator written by `save` for the given arena memory.
+    pub fn load(
+        name: String,
+        memory: &STArenaMemory,
+        reader: &mut impl io::Read,
+    ) -> io::Result<Self> {
+        let state = AllocatorState::deserialize_reader(reader)?;
+        let freelists = <[ArenaPos; NUM_ALLOCATION_CLASSES]>::try_from(state.freelists)
+            .map_err(|_| io::Error::other("wrong number of allocation classes"))?;
+        let mut allocator = Self::new(name);
+        allocator.freelists = freelists;
+        allocator.next_alloc_pos = state.next_alloc_pos;
+        allocator.active_allocs_bytes = state.active_allocs_bytes as usize;
+        allocator.active_allocs_count = state.active_allocs_count as usize;
+        allocator.active_allocs_bytes_gauge.set(allocator.active_allocs_bytes as i64);
+        allocator.active_allocs_count_gauge.set(allocator.active_allocs_count as i64);
+        allocator.memory_usage_gauge.set(memory.chunks.len() as i64 * CHUNK_SIZE as i64);
+        Ok(allocator)
+    }
+
     /// Adds a new chunk to the arena, and updates the next_alloc_pos to the beginning of
     /// the new chunk.
     fn new_chunk(&mut self, memory: &mut STArenaMemory) {
@@ -141,10 +185,13 @@ impl Allocator {
         self.freelists[size_class] = pos;
     }
 
-    #[cfg(test)]
     pub fn num_active_allocs(&self) -> usize {
         self.active_allocs_count
     }
+
+    pub fn num_active_allocs_bytes(&self) -> usize {
+        self.active_allocs_bytes
+    }
 }
 
 #[cfg(test)]
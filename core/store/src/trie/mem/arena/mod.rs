@@ -7,6 +7,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use derive_where::derive_where;
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::Hash;
+use std::io;
 use std::mem::size_of;
 
 /// Single-threaded `Arena` to store in-memory trie nodes.
@@ -153,11 +154,47 @@ impl STArena {
         Self { memory: STArenaMemory::new(), allocator: Allocator::new(name) }
     }
 
+    /// Number of bytes of memory reserved by the arena, including the memory
+    /// that is free for future allocations.
+    pub fn num_reserved_bytes(&self) -> usize {
+        self.memory.chunks.iter().map(|chunk| chunk.len()).sum()
+    }
+
     /// Number of active allocations (alloc calls minus dealloc calls).
-    #[cfg(test)]
     pub fn num_active_allocs(&self) -> usize {
         self.allocator.num_active_allocs()
     }
+
+    /// Total size in bytes of the active allocations.
+    pub fn num_active_allocs_bytes(&self) -> usize {
+        self.allocator.num_active_allocs_bytes()
+    }
+
+    /// Writes the memory and the allocator state of the arena, so that it can
+    /// be restored as is by `load`. The positions of the allocations don't
+    /// change, so the pointers stored in the memory stay valid.
+    pub fn save(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        borsh::to_writer(&mut *writer, &(self.memory.chunks.len() as u64))?;
+        for chunk in &self.memory.chunks {
+            borsh::to_writer(&mut *writer, &(chunk.len() as u64))?;
+            writer.write_all(chunk)?;
+        }
+        self.allocator.save(writer)
+    }
+
+    /// Restores an arena written by `save`.
+    pub fn load(name: String, reader: &mut impl io::Read) -> io::Result<Self> {
+        let num_chunks = u64::deserialize_reader(reader)?;
+        let mut memory = STArenaMemory::new();
+        for _ in 0..num_chunks {
+            let len = u64::deserialize_reader(reader)?;
+            let mut chunk = vec![0; len as usize];
+            reader.read_exact(&mut chunk)?;
+            memory.chunks.push(chunk);
+        }
+        let allocator = Allocator::load(name, &memory, reader)?;
+        Ok(Self { memory, allocator })
+    }
 }
 
 impl Arena for STArena {
@@ -87,7 +87,7 @@ pub fn load_trie_from_flat_state(
     Ok(tries)
 }
 
-fn get_state_root(
+pub(super) fn get_state_root(
     store: &Store,
     block_hash: CryptoHash,
     shard_uid: ShardUId,
@@ -8,7 +8,7 @@ use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::{BlockHeight, StateRoot};
 use std::collections::{BTreeMap, HashMap};
 
-mod arena;
+pub(crate) mod arena;
 mod construction;
 pub(crate) mod flexible_data;
 mod freelist;
@@ -17,6 +17,7 @@ pub mod loading;
 pub mod lookup;
 pub mod metrics;
 pub mod node;
+pub mod persistence;
 pub mod updating;
 
 /// Check this, because in the code we conveniently assume usize is 8 bytes.
@@ -87,9 +88,7 @@ impl MemTries {
         if new_ref == 1 {
             self.roots.entry(state_root).or_default().push(mem_root);
         }
-        MEM_TRIE_NUM_ROOTS
-            .with_label_values(&[&self.shard_uid.to_string()])
-            .set(self.roots.len() as i64);
+        self.update_metrics();
     }
 
     /// Returns a root node corresponding to the given state root.
@@ -132,16 +131,46 @@ impl MemTries {
         } else {
             debug_assert!(false, "Deleting non-existent root: {}", state_root);
         }
+        self.update_metrics();
+    }
+
+    /// The number of nodes is exported by the arena, as its number of active
+    /// allocations.
+    fn update_metrics(&self) {
         MEM_TRIE_NUM_ROOTS
             .with_label_values(&[&self.shard_uid.to_string()])
             .set(self.roots.len() as i64);
     }
 
-    /// Used for unit testing and integration testing.
+    /// Number of bytes of memory reserved by the arena holding the tries.
+    pub fn arena_memory_usage_bytes(&self) -> usize {
+        self.arena.num_reserved_bytes()
+    }
+
+    /// Number of bytes of the arena allocated to trie nodes, which is less
+    /// than the reserved memory because of the freed allocations.
+    pub fn arena_active_bytes(&self) -> usize {
+        self.arena.num_active_allocs_bytes()
+    }
+
+    /// Number of trie nodes in the arena. Every node is a single allocation.
+    pub fn num_nodes(&self) -> usize {
+        self.arena.num_active_allocs()
+    }
+
+    /// Number of roots held, counting a root once for every height it is
+    /// held at.
     pub fn num_roots(&self) -> usize {
         self.heights.iter().map(|(_, v)| v.len()).sum()
     }
 
+    /// Lowest and highest block heights at which roots are held.
+    pub fn root_heights(&self) -> Option<(BlockHeight, BlockHeight)> {
+        let (lowest, _) = self.heights.first_key_value()?;
+        let (highest, _) = self.heights.last_key_value()?;
+        Some((*lowest, *highest))
+    }
+
     pub fn update(
         &self,
         root: CryptoHash,
@@ -243,6 +272,7 @@ mod tests {
             // Expire some roots.
             tries.delete_until_height(height - 20);
             available_hashes.retain(|(h, _)| *h >= height - 20);
+            assert_eq!(tries.root_heights(), Some((height.max(120) - 20, height)));
             // Sanity check that the roots that are supposed to exist still exist.
             for (_, state_root) in &available_hashes {
                 let root = tries.get_root(state_root).unwrap().id();
@@ -251,7 +281,8 @@ mod tests {
         }
         // Expire all roots, and now the number of allocs should be zero.
         tries.delete_until_height(201);
-        assert_eq!(tries.arena.num_active_allocs(), 0);
+        assert_eq!(tries.num_nodes(), 0);
         assert_eq!(tries.num_roots(), 0);
+        assert_eq!(tries.root_heights(), None);
     }
 }
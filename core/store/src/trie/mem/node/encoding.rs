@@ -323,7 +323,7 @@ impl<'a, M: ArenaMemory> MemTrieNodePtr<'a, M> {
 
     /// Calculates the size of the allocation with only a pointer to the start
     /// of the trie node's allocation.
-    fn size_of_allocation(&self) -> usize {
+    pub fn size_of_allocation(&self) -> usize {
         let mut decoder = self.decoder();
         let kind = decoder.peek::<CommonHeader>().kind;
         match kind {
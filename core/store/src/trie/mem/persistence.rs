@@ -0,0 +1,309 @@
+use super::arena::{ArenaPos, STArena};
+use super::loading::get_state_root;
+use super::node::MemTrieNodeId;
+use super::MemTries;
+use crate::flat::store_helper::{get_all_deltas_metadata, get_flat_storage_status};
+use crate::flat::{BlockInfo, FlatStorageStatus};
+use crate::Store;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::errors::StorageError;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{BlockHeight, StateRoot};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Version of the format of the memtrie dumps. Dumps of other versions are
+/// ignored, so the version needs to be bumped whenever the layout of the
+/// nodes in the arena or of the dump changes.
+const MEM_TRIE_DUMP_VERSION: u32 = 2;
+
+/// Everything but the arena saved in a memtrie dump.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct MemTrieDumpHeader {
+    version: u32,
+    shard_uid: ShardUId,
+    /// Flat head of the shard when the memtries were saved. The dump is only
+    /// valid as long as the flat storage of the shard didn't change.
+    flat_head: BlockInfo,
+    roots: Vec<(StateRoot, Vec<ArenaPos>)>,
+    heights: Vec<(BlockHeight, Vec<StateRoot>)>,
+}
+
+/// Path of the memtrie dump of the shard in the given directory.
+pub fn mem_trie_dump_path(dir: &Path, shard_uid: ShardUId) -> PathBuf {
+    dir.join(format!("{shard_uid}.memtrie"))
+}
+
+/// Saves the memtries of a shard to `path`, so that they can be restored by
+/// `load_mem_tries_from_dump` as long as the flat storage of the shard stays
+/// at `flat_head`. The dump is written to a temporary file first, so that an
+/// interrupted save never leaves a partial dump behind. It ends with the CRC32
+/// checksum of everything before it, which is verified on load.
+pub fn save_mem_tries(mem_tries: &MemTries, flat_head: BlockInfo, path: &Path) -> io::Result<()> {
+    let header = MemTrieDumpHeader {
+        version: MEM_TRIE_DUMP_VERSION,
+        shard_uid: mem_tries.shard_uid,
+        flat_head,
+        roots: mem_tries
+            .roots
+            .iter()
+            .map(|(state_root, ids)| (*state_root, ids.iter().map(|id| id.pos).collect()))
+            .collect(),
+        heights: mem_tries
+            .heights
+            .iter()
+            .map(|(height, state_roots)| (*height, state_roots.clone()))
+            .collect(),
+    };
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = ChecksumWriter {
+        inner: BufWriter::new(File::create(&tmp_path)?),
+        hasher: Default::default(),
+    };
+    borsh::to_writer(&mut writer, &header)?;
+    mem_tries.arena.save(&mut writer)?;
+    let ChecksumWriter { inner: mut writer, hasher } = writer;
+    io::Write::write_all(&mut writer, &hasher.finalize().to_le_bytes())?;
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)
+}
+
+/// Restores the memtries of a shard saved by `save_mem_tries`, if the dump at
+/// `path` exists and is valid for the current flat storage of the shard: it
+/// has to be taken at the current flat head and hold the state roots of the
+/// flat head and of every flat storage delta. Returns `None` otherwise, in
+/// which case the memtries need to be loaded from flat storage.
+///
+/// The dump is deleted, so that it's never used once the node moved on.
+pub fn load_mem_tries_from_dump(
+    store: &Store,
+    shard_uid: ShardUId,
+    path: &Path,
+) -> Result<Option<MemTries>, StorageError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            warn!(target: "memtrie", %shard_uid, ?path, ?err, "Failed to open memtrie dump");
+            return Ok(None);
+        }
+    };
+    let load_start = Instant::now();
+    let result = read_mem_tries(&mut BufReader::new(file), shard_uid);
+    if let Err(err) = std::fs::remove_file(path) {
+        warn!(target: "memtrie", %shard_uid, ?path, ?err, "Failed to remove memtrie dump");
+    }
+    let (flat_head, mem_tries) = match result {
+        Ok(dump) => dump,
+        Err(err) => {
+            warn!(target: "memtrie", %shard_uid, ?path, ?err, "Failed to read memtrie dump");
+            return Ok(None);
+        }
+    };
+
+    let current_flat_head = match get_flat_storage_status(store, shard_uid)? {
+        FlatStorageStatus::Ready(status) => status.flat_head,
+        _ => return Ok(None),
+    };
+    if flat_head != current_flat_head {
+        info!(target: "memtrie", %shard_uid, ?flat_head, ?current_flat_head, "Memtrie dump was saved at another flat head, ignoring it");
+        return Ok(None);
+    }
+    let mut expected_roots = HashSet::from([get_state_root(store, flat_head.hash, shard_uid)?]);
+    for delta in get_all_deltas_metadata(store, shard_uid)? {
+        if delta.has_changes() {
+            expected_roots.insert(get_state_root(store, delta.block.hash, shard_uid)?);
+        }
+    }
+    for state_root in &expected_roots {
+        if *state_root != StateRoot::default() && mem_tries.get_root(state_root).is_none() {
+            info!(target: "memtrie", %shard_uid, %state_root, "Memtrie dump doesn't have a state root of the flat storage, ignoring it");
+            return Ok(None);
+        }
+    }
+    info!(target: "memtrie", %shard_uid, num_roots = mem_tries.num_roots(), "Loaded memtrie dump in {:?}", load_start.elapsed());
+    Ok(Some(mem_tries))
+}
+
+/// Computes the checksum of the bytes written through it.
+struct ChecksumWriter<W> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: io::Write> io::Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.hasher.update(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Computes the checksum of the bytes read through it.
+struct ChecksumReader<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: io::Read> io::Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.hasher.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+fn read_mem_tries(
+    reader: &mut impl io::Read,
+    shard_uid: ShardUId,
+) -> io::Result<(BlockInfo, MemTries)> {
+    let mut checksum_reader = ChecksumReader { inner: &mut *reader, hasher: Default::default() };
+    let header = MemTrieDumpHeader::deserialize_reader(&mut checksum_reader)?;
+    if header.version != MEM_TRIE_DUMP_VERSION {
+        return Err(io::Error::other(format!("unsupported dump version {}", header.version)));
+    }
+    if header.shard_uid != shard_uid {
+        return Err(io::Error::other(format!("dump of shard {}", header.shard_uid)));
+    }
+    let arena = STArena::load(shard_uid.to_string(), &mut checksum_reader)?;
+    let checksum = checksum_reader.hasher.finalize();
+    let expected_checksum = u32::deserialize_reader(reader)?;
+    if checksum != expected_checksum {
+        return Err(io::Error::other(format!(
+            "checksum {checksum:#x} doesn't match the expected {expected_checksum:#x}"
+        )));
+    }
+    let mut mem_tries = MemTries::new(shard_uid);
+    mem_tries.arena = arena;
+    mem_tries.roots = header
+        .roots
+        .into_iter()
+        .map(|(state_root, positions)| {
+            (state_root, positions.into_iter().map(|pos| MemTrieNodeId { pos }).collect())
+        })
+        .collect();
+    mem_tries.heights = header.heights.into_iter().collect();
+    // Checks that the root nodes are where the dump says they are.
+    for (state_root, ids) in &mem_tries.roots {
+        for id in ids {
+            let node_hash = id.as_ptr(mem_tries.arena.memory()).view().node_hash();
+            if node_hash != *state_root {
+                return Err(io::Error::other(format!("root {state_root} has hash {node_hash}")));
+            }
+        }
+    }
+    mem_tries.update_metrics();
+    Ok((header.flat_head, mem_tries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_mem_tries_from_dump, mem_trie_dump_path, save_mem_tries};
+    use crate::flat::{store_helper, BlockInfo, FlatStorageReadyStatus, FlatStorageStatus};
+    use crate::test_utils::{create_test_store, test_populate_trie, TestTriesBuilder};
+    use crate::trie::mem::loading::load_trie_from_flat_state_and_delta;
+    use crate::trie::mem::lookup::memtrie_lookup;
+    use crate::{DBCol, Trie};
+    use near_primitives::congestion_info::CongestionInfo;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
+    use near_primitives::state::FlatStateValue;
+    use near_primitives::types::chunk_extra::ChunkExtra;
+    use near_primitives::types::StateRoot;
+    use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
+
+    #[test]
+    fn test_save_and_load_mem_tries() {
+        let store = create_test_store();
+        let shard_tries = TestTriesBuilder::new().with_store(store.clone()).build();
+        let shard_uid = ShardUId::single_shard();
+        let flat_head = BlockInfo::genesis(CryptoHash::hash_bytes(b"head"), 10);
+        let entries: Vec<_> =
+            (0..100u32).map(|i| (i.to_le_bytes().to_vec(), Some(vec![i as u8; 40]))).collect();
+
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head }),
+        );
+        for (key, value) in &entries {
+            let value = FlatStateValue::inlined(value.as_ref().unwrap());
+            store_helper::set_flat_state_value(
+                &mut store_update,
+                shard_uid,
+                key.clone(),
+                Some(value),
+            );
+        }
+        store_update.commit().unwrap();
+        let state_root =
+            test_populate_trie(&shard_tries, &Trie::EMPTY_ROOT, shard_uid, entries.clone());
+        write_state_root(&store, flat_head.hash, shard_uid, state_root);
+
+        let mem_tries = load_trie_from_flat_state_and_delta(&store, shard_uid, None).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = mem_trie_dump_path(dir.path(), shard_uid);
+        save_mem_tries(&mem_tries, flat_head, &path).unwrap();
+
+        let loaded = load_mem_tries_from_dump(&store, shard_uid, &path).unwrap().unwrap();
+        assert!(!path.exists());
+        assert_eq!(loaded.num_nodes(), mem_tries.num_nodes());
+        assert_eq!(loaded.num_roots(), 1);
+        let root = loaded.get_root(&state_root).unwrap();
+        for (key, value) in &entries {
+            let loaded_value = memtrie_lookup(root, key, None).map(|value| value.to_flat_value());
+            assert_eq!(loaded_value, Some(FlatStateValue::inlined(value.as_ref().unwrap())));
+        }
+
+        // A corrupted dump is ignored.
+        save_mem_tries(&mem_tries, flat_head, &path).unwrap();
+        let mut dump = std::fs::read(&path).unwrap();
+        let middle = dump.len() / 2;
+        dump[middle] ^= 1;
+        std::fs::write(&path, dump).unwrap();
+        assert!(load_mem_tries_from_dump(&store, shard_uid, &path).unwrap().is_none());
+        assert!(!path.exists());
+
+        // A dump saved at another flat head is ignored.
+        let other_flat_head = BlockInfo::genesis(CryptoHash::hash_bytes(b"other"), 11);
+        save_mem_tries(&mem_tries, other_flat_head, &path).unwrap();
+        assert!(load_mem_tries_from_dump(&store, shard_uid, &path).unwrap().is_none());
+        assert!(!path.exists());
+    }
+
+    fn write_state_root(
+        store: &crate::Store,
+        block_hash: CryptoHash,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+    ) {
+        let congestion_info = ProtocolFeature::CongestionControl
+            .enabled(PROTOCOL_VERSION)
+            .then(CongestionInfo::default);
+        let chunk_extra = ChunkExtra::new(
+            PROTOCOL_VERSION,
+            &state_root,
+            CryptoHash::default(),
+            Vec::new(),
+            0,
+            0,
+            0,
+            congestion_info,
+        );
+        let mut store_update = store.store_update();
+        store_update
+            .set_ser(DBCol::ChunkExtra, &get_block_shard_uid(&block_hash, &shard_uid), &chunk_extra)
+            .unwrap();
+        store_update.commit().unwrap();
+    }
+}
@@ -438,6 +438,10 @@ impl TrieRefcountAddition {
         self.trie_node_or_value.as_slice()
     }
 
+    pub fn rc(&self) -> std::num::NonZeroU32 {
+        self.rc
+    }
+
     pub fn revert(&self) -> TrieRefcountSubtraction {
         TrieRefcountSubtraction::new(self.trie_node_or_value_hash, self.rc)
     }
@@ -447,6 +451,14 @@ impl TrieRefcountSubtraction {
     pub fn new(trie_node_or_value_hash: CryptoHash, rc: std::num::NonZeroU32) -> Self {
         Self { trie_node_or_value_hash, _ignored: Default::default(), rc }
     }
+
+    pub fn hash(&self) -> &CryptoHash {
+        &self.trie_node_or_value_hash
+    }
+
+    pub fn rc(&self) -> std::num::NonZeroU32 {
+        self.rc
+    }
 }
 
 /// Helps produce a list of additions and subtractions to the trie,
@@ -554,6 +566,13 @@ impl TrieChanges {
     pub fn deletions(&self) -> &[TrieRefcountSubtraction] {
         self.deletions.as_slice()
     }
+
+    /// Drops the insertions, which are no longer needed once they are applied
+    /// and only take space when the changes are kept for their deletions.
+    pub(crate) fn without_insertions(mut self) -> Self {
+        self.insertions = vec![];
+        self
+    }
 }
 
 /// Result of applying state part to Trie.
@@ -590,6 +609,27 @@ pub enum OptimizedValueRef {
     AvailableValue(ValueAccessToken),
 }
 
+/// A range of keys `[start, end)` which is scanned with [`Trie::get_first_in_range`].
+///
+/// If flat storage is used, changes to the range which are not yet in the flat
+/// state are loaded once, when the range is created, rather than on every
+/// lookup.
+pub struct TrieRange {
+    start: Vec<u8>,
+    end: Vec<u8>,
+    flat_changes: Option<BTreeMap<Vec<u8>, Option<FlatStateValue>>>,
+}
+
+impl TrieRange {
+    pub fn start(&self) -> &[u8] {
+        &self.start
+    }
+
+    pub fn end(&self) -> &[u8] {
+        &self.end
+    }
+}
+
 /// Opaque wrapper around Vec<u8> so that the value cannot be used directly and
 /// must instead be dereferenced via `Trie::deref_optimized`, so that gas
 /// accounting is never skipped.
@@ -663,17 +703,34 @@ impl Trie {
     /// Makes a new trie that has everything the same except that access
     /// through that trie accumulates a state proof for all nodes accessed.
     pub fn recording_reads(&self) -> Self {
+        self.recording_reads_with(TrieRecorder::new())
+    }
+
+    /// Same as `recording_reads`, but values of keys removed or overwritten by
+    /// `update` are not added to the state proof, as updating their refcounts
+    /// only requires their hashes.
+    pub fn recording_reads_excluding_removed_values(&self) -> Self {
+        self.recording_reads_with(TrieRecorder::new_excluding_removed_values())
+    }
+
+    fn recording_reads_with(&self, recorder: TrieRecorder) -> Self {
         let mut trie = Self::new_with_memtries(
             self.storage.clone(),
             self.memtries.clone(),
             self.root,
             self.flat_storage_chunk_view.clone(),
         );
-        trie.recorder = Some(RefCell::new(TrieRecorder::new()));
+        trie.recorder = Some(RefCell::new(recorder));
         trie.charge_gas_for_trie_node_access = self.charge_gas_for_trie_node_access;
         trie
     }
 
+    /// Whether the values of removed or overwritten keys must be read, so that
+    /// they are added to the state proof.
+    fn records_removed_values(&self) -> bool {
+        self.recorder.as_ref().is_some_and(|recorder| recorder.borrow().records_removed_values())
+    }
+
     /// Takes the recorded state proof out of the trie.
     pub fn recorded_storage(&self) -> Option<PartialStorage> {
         self.recorder.as_ref().map(|recorder| recorder.borrow_mut().recorded_storage())
@@ -730,7 +787,10 @@ impl Trie {
     }
 
     /// Request recording of the code for the given account.
-    pub fn request_code_recording(&self, account_id: AccountId) {
+    ///
+    /// If the account doesn't store its own code, it uses the global contract
+    /// with the given code hash, and the global code is recorded instead.
+    pub fn request_code_recording(&self, account_id: AccountId, code_hash: CryptoHash) {
         let Some(recorder) = &self.recorder else {
             return;
         };
@@ -746,6 +806,29 @@ impl Trie {
         // recorded state.
         let key = TrieKey::ContractCode { account_id };
         let value_ref = self.get_optimized_ref(&key.to_vec(), KeyLookupMode::FlatStorage);
+        match value_ref {
+            Ok(Some(value_ref)) => {
+                let mut r = recorder.borrow_mut();
+                r.record_code_len(value_ref.len());
+            }
+            Ok(None) if code_hash != CryptoHash::default() => {
+                self.request_global_code_recording(code_hash);
+            }
+            _ => {}
+        }
+    }
+
+    /// Request recording of the global contract code with the given hash.
+    pub fn request_global_code_recording(&self, code_hash: CryptoHash) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+        if !recorder.borrow_mut().global_codes_to_record.insert(code_hash) {
+            return;
+        }
+
+        let key = TrieKey::GlobalContractCode { code_hash };
+        let value_ref = self.get_optimized_ref(&key.to_vec(), KeyLookupMode::FlatStorage);
         if let Ok(Some(value_ref)) = value_ref {
             let mut r = recorder.borrow_mut();
             r.record_code_len(value_ref.len());
@@ -832,7 +915,11 @@ impl Trie {
     ) -> Result<(), StorageError> {
         match value {
             ValueHandle::HashAndSize(value) => {
-                self.internal_retrieve_trie_node(&value.hash, true)?;
+                // Only the hash is needed to update the refcount. The value
+                // itself is read only to include it in the state proof.
+                if self.records_removed_values() {
+                    self.internal_retrieve_trie_node(&value.hash, true)?;
+                }
                 memory.refcount_changes.subtract(value.hash, 1);
             }
             ValueHandle::InMemory(_) => {
@@ -1529,15 +1616,20 @@ impl Trie {
         I: IntoIterator<Item = (Vec<u8>, Option<Vec<u8>>)>,
     {
         // Call `get` for contract codes requested to be recorded.
-        let codes_to_record = if let Some(recorder) = &self.recorder {
-            recorder.borrow().codes_to_record.clone()
+        let (codes_to_record, global_codes_to_record) = if let Some(recorder) = &self.recorder {
+            let recorder = recorder.borrow();
+            (recorder.codes_to_record.clone(), recorder.global_codes_to_record.clone())
         } else {
-            HashSet::default()
+            (HashSet::default(), HashSet::default())
         };
         for account_id in codes_to_record {
             let trie_key = TrieKey::ContractCode { account_id: account_id.clone() };
             let _ = self.get(&trie_key.to_vec());
         }
+        for code_hash in global_codes_to_record {
+            let trie_key = TrieKey::GlobalContractCode { code_hash };
+            let _ = self.get(&trie_key.to_vec());
+        }
 
         match &self.memtries {
             Some(memtries) => {
@@ -1580,14 +1672,17 @@ impl Trie {
                     for (node_hash, serialized_node) in trie_accesses.nodes {
                         recorder.borrow_mut().record(&node_hash, serialized_node);
                     }
-                    for (value_hash, value) in trie_accesses.values {
-                        let value = match value {
-                            FlatStateValue::Ref(_) => {
-                                self.storage.retrieve_raw_bytes(&value_hash)?
-                            }
-                            FlatStateValue::Inlined(value) => value.into(),
-                        };
-                        recorder.borrow_mut().record(&value_hash, value);
+                    // Accessed values are the values of removed or overwritten keys.
+                    if recorder.borrow().records_removed_values() {
+                        for (value_hash, value) in trie_accesses.values {
+                            let value = match value {
+                                FlatStateValue::Ref(_) => {
+                                    self.storage.retrieve_raw_bytes(&value_hash)?
+                                }
+                                FlatStateValue::Inlined(value) => value.into(),
+                            };
+                            recorder.borrow_mut().record(&value_hash, value);
+                        }
                     }
                 }
                 Ok(trie_changes)
@@ -1612,6 +1707,72 @@ impl Trie {
         }
     }
 
+    /// Creates a range of keys `[start, end)` to be scanned with
+    /// [`Self::get_first_in_range`].
+    pub fn range(&self, start: Vec<u8>, end: Vec<u8>) -> Result<TrieRange, StorageError> {
+        let flat_changes = match &self.flat_storage_chunk_view {
+            Some(chunk_view) => Some(chunk_view.get_range_changes(&start, &end)?),
+            None => None,
+        };
+        Ok(TrieRange { start, end, flat_changes })
+    }
+
+    /// Returns the first key within `range` which is not smaller than `from`,
+    /// together with its value.
+    ///
+    /// The key is looked up in flat storage if it is present. The trie is
+    /// walked regardless and the visited nodes are always charged for, because
+    /// a single lookup may have to skip an arbitrary number of nodes of
+    /// removed keys. This also keeps the costs the same when the lookup is
+    /// replayed from the recorded storage. The value is charged for in the
+    /// same way as in `Trie::get`.
+    pub fn get_first_in_range(
+        &self,
+        range: &TrieRange,
+        from: &[u8],
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, StorageError> {
+        let from = std::cmp::max(from, range.start.as_slice());
+        if from >= range.end.as_slice() {
+            return Ok(None);
+        }
+        match (&self.flat_storage_chunk_view, &range.flat_changes) {
+            (Some(chunk_view), Some(flat_changes)) => {
+                let entry = chunk_view.get_first_in_range(from, &range.end, flat_changes)?;
+                // Intermediate trie nodes are needed to prove that there are
+                // no other keys between `from` and the returned one.
+                let entry_from_trie = self.first_in_range_from_state_column(from, &range.end)?;
+                debug_assert_eq!(
+                    entry_from_trie.as_ref().map(|(key, _)| key),
+                    entry.as_ref().map(|(key, _)| key)
+                );
+                let Some((key, value)) = entry else { return Ok(None) };
+                let value = self.deref_optimized(&OptimizedValueRef::from_flat_value(value))?;
+                Ok(Some((key, value)))
+            }
+            _ => {
+                let entry = self.first_in_range_from_state_column(from, &range.end)?;
+                let Some((key, value_hash)) = entry else { return Ok(None) };
+                Ok(Some((key, self.retrieve_value(&value_hash)?)))
+            }
+        }
+    }
+
+    /// Finds the first key within `[from, to)` by walking the trie nodes
+    /// stored in the `DBCol::State` column and returns it with the hash of
+    /// its value. All visited nodes are charged for.
+    fn first_in_range_from_state_column(
+        &self,
+        from: &[u8],
+        to: &[u8],
+    ) -> Result<Option<(Vec<u8>, CryptoHash)>, StorageError> {
+        let mut iter = DiskTrieIterator::new(self, None)?;
+        iter.seek_nibble_slice(NibbleSlice::new(from), false)?;
+        match iter.next_with_value_hash().transpose()? {
+            Some((key, value_hash)) if key.as_slice() < to => Ok(Some((key, value_hash))),
+            _ => Ok(None),
+        }
+    }
+
     /// Returns an iterator that can be used to traverse any range in the trie.
     /// This only uses the on-disk trie. If memtrie iteration is desired, see
     /// `lock_for_iter`.
@@ -1,13 +1,15 @@
 use crate::{get, set, TrieAccess, TrieUpdate};
 use near_primitives::errors::{IntegerOverflowError, StorageError};
-use near_primitives::receipt::{BufferedReceiptIndices, Receipt, TrieQueueIndices};
+use near_primitives::receipt::{
+    BufferedReceiptIndices, PriorityDelayedReceiptIndices, Receipt, TrieQueueIndices,
+};
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::ShardId;
 
 /// Read-only iterator over receipt queues stored in the state trie.
 ///
-/// This iterator currently supports delayed receipts and buffered outgoing
-/// receipts.
+/// This iterator currently supports delayed receipts, both with and without
+/// priority, and buffered outgoing receipts.
 pub struct ReceiptIterator<'a> {
     indices: std::ops::Range<u64>,
     trie_queue: &'a dyn TrieQueue,
@@ -27,6 +29,26 @@ pub struct DelayedReceiptQueue {
     indices: TrieQueueIndices,
 }
 
+/// Type safe access to the queues of delayed receipts with a priority, which
+/// are processed before the receipts in [`DelayedReceiptQueue`]. Only use one
+/// at the time, for the same reasons as with `DelayedReceiptQueue`.
+///
+/// Receipts are pushed to the lane of their priority and popped from the
+/// highest non-empty lane, see [`PriorityDelayedReceiptIndices`].
+pub struct PriorityDelayedReceiptQueue {
+    lanes_indices: PriorityDelayedReceiptIndices,
+}
+
+/// Type safe access to the delayed receipts of a single priority lane.
+///
+/// Construct this from a parent `PriorityDelayedReceiptQueue` by calling
+/// [`PriorityDelayedReceiptQueue::to_lane`]. Like with `OutgoingReceiptBuffer`,
+/// the mutable borrow prevents modifying two lanes independently.
+pub struct PriorityDelayedReceiptLane<'parent> {
+    lane: u8,
+    parent: &'parent mut PriorityDelayedReceiptQueue,
+}
+
 /// Type safe access to outgoing receipt buffers from this shard to all other
 /// shards. Only use one at the time!
 ///
@@ -201,6 +223,86 @@ impl TrieQueue for DelayedReceiptQueue {
     }
 }
 
+impl PriorityDelayedReceiptQueue {
+    pub fn load(trie: &dyn TrieAccess) -> Result<Self, StorageError> {
+        let lanes_indices = crate::get_priority_delayed_receipt_indices(trie)?;
+        Ok(Self { lanes_indices })
+    }
+
+    pub fn to_lane(&mut self, lane: u8) -> PriorityDelayedReceiptLane {
+        PriorityDelayedReceiptLane { lane, parent: self }
+    }
+
+    /// Adds the receipt to the end of the lane of its priority.
+    pub fn push(
+        &mut self,
+        state_update: &mut TrieUpdate,
+        receipt: &Receipt,
+    ) -> Result<(), IntegerOverflowError> {
+        let lane = PriorityDelayedReceiptIndices::lane(receipt.priority().value());
+        self.to_lane(lane).push(state_update, receipt)
+    }
+
+    /// Removes the first receipt of the highest non-empty lane.
+    pub fn pop(&mut self, state_update: &mut TrieUpdate) -> Result<Option<Receipt>, StorageError> {
+        let lane = self
+            .lanes_indices
+            .lanes
+            .iter()
+            .rev()
+            .find(|(_, indices)| indices.len() > 0)
+            .map(|(lane, _)| *lane);
+        match lane {
+            Some(lane) => self.to_lane(lane).pop(state_update),
+            None => Ok(None),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.lanes_indices.len()
+    }
+
+    /// Iterates over the receipts in the order they would be popped.
+    pub fn iter<'a>(
+        &'a self,
+        trie: &'a dyn TrieAccess,
+    ) -> impl Iterator<Item = Result<Receipt, StorageError>> + 'a {
+        self.lanes_indices.lanes.iter().rev().flat_map(move |(&lane, indices)| {
+            (indices.first_index..indices.next_available_index).map(move |index| {
+                get_queued_receipt(trie, &TrieKey::PriorityDelayedReceipt { lane, index })
+            })
+        })
+    }
+
+    fn write_indices(&self, state_update: &mut TrieUpdate) {
+        set(state_update, TrieKey::PriorityDelayedReceiptIndices, &self.lanes_indices);
+    }
+}
+
+impl TrieQueue for PriorityDelayedReceiptLane<'_> {
+    fn load_indices(&self, trie: &dyn TrieAccess) -> Result<TrieQueueIndices, StorageError> {
+        let all_indices = crate::get_priority_delayed_receipt_indices(trie)?;
+        let indices = all_indices.lanes.get(&self.lane).cloned().unwrap_or_default();
+        Ok(indices)
+    }
+
+    fn indices(&self) -> TrieQueueIndices {
+        self.parent.lanes_indices.lanes.get(&self.lane).cloned().unwrap_or_default()
+    }
+
+    fn indices_mut(&mut self) -> &mut TrieQueueIndices {
+        self.parent.lanes_indices.lanes.entry(self.lane).or_default()
+    }
+
+    fn write_indices(&self, state_update: &mut TrieUpdate) {
+        self.parent.write_indices(state_update);
+    }
+
+    fn trie_key(&self, index: u64) -> TrieKey {
+        TrieKey::PriorityDelayedReceipt { lane: self.lane, index }
+    }
+}
+
 impl ShardsOutgoingReceiptBuffer {
     pub fn load(trie: &dyn TrieAccess) -> Result<Self, StorageError> {
         let shards_indices = crate::get_buffered_receipt_indices(trie)?;
@@ -250,20 +352,23 @@ impl TrieQueue for OutgoingReceiptBuffer<'_> {
     }
 }
 
+fn get_queued_receipt(trie: &dyn TrieAccess, key: &TrieKey) -> Result<Receipt, StorageError> {
+    match get(trie, key) {
+        Err(e) => Err(e),
+        Ok(None) => Err(StorageError::StorageInconsistentState(
+            "Receipt referenced by index should be in the state".to_owned(),
+        )),
+        Ok(Some(receipt)) => Ok(receipt),
+    }
+}
+
 impl<'a> Iterator for ReceiptIterator<'a> {
     type Item = Result<Receipt, StorageError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         let index = self.indices.next()?;
         let key = self.trie_queue.trie_key(index);
-        let result = match get(self.trie, &key) {
-            Err(e) => Err(e),
-            Ok(None) => Err(StorageError::StorageInconsistentState(
-                "Receipt referenced by index should be in the state".to_owned(),
-            )),
-            Ok(Some(receipt)) => Ok(receipt),
-        };
-        Some(result)
+        Some(get_queued_receipt(self.trie, &key))
     }
 }
 
@@ -271,14 +376,7 @@ impl<'a> DoubleEndedIterator for ReceiptIterator<'a> {
     fn next_back(&mut self) -> Option<Self::Item> {
         let index = self.indices.next_back()?;
         let key = self.trie_queue.trie_key(index);
-        let result = match get(self.trie, &key) {
-            Err(e) => Err(e),
-            Ok(None) => Err(StorageError::StorageInconsistentState(
-                "Receipt referenced by index should be in the state".to_owned(),
-            )),
-            Ok(Some(receipt)) => Ok(receipt),
-        };
-        Some(result)
+        Some(get_queued_receipt(self.trie, &key))
     }
 }
 
@@ -288,6 +386,7 @@ mod tests {
     use crate::test_utils::{gen_receipts, TestTriesBuilder};
     use crate::Trie;
     use near_primitives::shard_layout::ShardUId;
+    use rand::Rng;
 
     #[test]
     fn test_delayed_receipts_queue() {
@@ -322,6 +421,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_priority_delayed_receipts_queue() {
+        let mut rng = rand::thread_rng();
+        let mut input_receipts = gen_receipts(&mut rng, 10);
+        for receipt in &mut input_receipts {
+            if let Receipt::V1(receipt) = receipt {
+                receipt.priority = rng.gen_range(1..1000);
+            }
+        }
+        let mut trie = init_state();
+
+        {
+            let mut queue = PriorityDelayedReceiptQueue::load(&trie).unwrap();
+            for receipt in &input_receipts {
+                queue.push(&mut trie, receipt).expect("pushing must not fail");
+            }
+        }
+
+        // The priority queue must not share the storage with the normal queue.
+        assert_eq!(DelayedReceiptQueue::load(&trie).unwrap().len(), 0);
+
+        // Receipts come out of the highest lane first, and in the order they
+        // were pushed within a lane.
+        let mut expected_receipts = input_receipts.clone();
+        expected_receipts.sort_by_key(|receipt| {
+            std::cmp::Reverse(PriorityDelayedReceiptIndices::lane(receipt.priority().value()))
+        });
+        let mut queue = PriorityDelayedReceiptQueue::load(&trie).unwrap();
+        assert_eq!(queue.len(), input_receipts.len() as u64);
+        let iterated_receipts: Vec<Receipt> =
+            queue.iter(&trie).collect::<Result<_, _>>().expect("iterating should not fail");
+        assert_eq!(expected_receipts, iterated_receipts);
+        let mut popped = vec![];
+        while let Some(receipt) = queue.pop(&mut trie).expect("pop must not fail") {
+            popped.push(receipt);
+        }
+        assert_eq!(expected_receipts, popped);
+    }
+
     #[test]
     fn test_outgoing_receipt_buffer_separately() {
         // empty queues
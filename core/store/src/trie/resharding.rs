@@ -1,17 +1,19 @@
 use crate::flat::FlatStateChanges;
 use crate::{
-    get, get_delayed_receipt_indices, get_promise_yield_indices, set, ShardTries, StoreUpdate,
-    Trie, TrieUpdate,
+    get, get_delayed_receipt_indices, get_priority_delayed_receipt_indices,
+    get_promise_yield_indices, set, ShardTries, StoreUpdate, Trie, TrieUpdate,
 };
 use borsh::BorshDeserialize;
 use bytesize::ByteSize;
 use near_primitives::account::id::AccountId;
 use near_primitives::errors::StorageError;
-use near_primitives::receipt::{PromiseYieldTimeout, Receipt};
+use near_primitives::receipt::{
+    PriorityDelayedReceiptIndices, PromiseYieldTimeout, Receipt, TrieQueueIndices,
+};
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_part::PartId;
 use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
-use near_primitives::trie_key::TrieKey;
+use near_primitives::trie_key::{col, TrieKey};
 use near_primitives::types::{
     ConsolidatedStateChange, StateChangeCause, StateChangesForResharding, StateRoot,
 };
@@ -43,6 +45,7 @@ impl ShardTries {
     ) -> Result<HashMap<ShardUId, TrieUpdate>, StorageError> {
         let mut trie_updates: HashMap<_, _> = self.get_trie_updates(state_roots);
         let mut inserted_receipts = Vec::new();
+        let mut inserted_priority_receipts = Vec::new();
         let mut inserted_timeouts = Vec::new();
         for ConsolidatedStateChange { trie_key, value } in changes.changes {
             match &trie_key {
@@ -93,6 +96,7 @@ impl ShardTries {
                 | TrieKey::PendingDataCount { receiver_id: account_id, .. }
                 | TrieKey::PostponedReceipt { receiver_id: account_id, .. }
                 | TrieKey::PromiseYieldReceipt { receiver_id: account_id, .. }
+                | TrieKey::PromiseYieldExpiry { receiver_id: account_id, .. }
                 | TrieKey::ContractData { account_id, .. } => {
                     let new_shard_uid = account_id_to_shard_uid(account_id);
                     // we can safely unwrap here because the caller of this function guarantees trie_updates
@@ -106,6 +110,34 @@ impl ShardTries {
                 // TODO(congestion_control) - integration with resharding
                 TrieKey::BufferedReceiptIndices => todo!(),
                 TrieKey::BufferedReceipt { .. } => todo!(),
+                TrieKey::PriorityDelayedReceiptIndices => {}
+                TrieKey::PriorityDelayedReceipt { lane, index } => match value {
+                    Some(value) => {
+                        let receipt = Receipt::try_from_slice(&value).map_err(|err| {
+                            StorageError::StorageInconsistentState(format!(
+                                "invalid priority delayed receipt {:?}, err: {}",
+                                value, err,
+                            ))
+                        })?;
+                        // Like for DelayedReceipt, insertions are applied in the order of the
+                        // queue, which here is only defined within a lane.
+                        inserted_priority_receipts.push(((*lane, *index), receipt));
+                    }
+                    None => {
+                        // The erased receipts are in `changes.processed_delayed_receipts`,
+                        // together with those of the normal delayed receipts queue.
+                    }
+                },
+                // Global contracts are available to all accounts of the shard, and the runtime
+                // config overrides apply to all of its chunks, so every child shard keeps a copy.
+                TrieKey::GlobalContractCode { .. } | TrieKey::RuntimeConfigOverrides => {
+                    for trie_update in trie_updates.values_mut() {
+                        match &value {
+                            Some(value) => trie_update.set(trie_key.clone(), value.clone()),
+                            None => trie_update.remove(trie_key.clone()),
+                        }
+                    }
+                }
             }
         }
         for (_, update) in trie_updates.iter_mut() {
@@ -116,8 +148,12 @@ impl ShardTries {
         }
 
         inserted_receipts.sort_by_key(|it| it.0);
-        let inserted_receipts: Vec<_> =
-            inserted_receipts.into_iter().map(|(_, receipt)| receipt).collect();
+        inserted_priority_receipts.sort_by_key(|it| it.0);
+        let inserted_receipts: Vec<_> = inserted_receipts
+            .into_iter()
+            .map(|(_, receipt)| receipt)
+            .chain(inserted_priority_receipts.into_iter().map(|(_, receipt)| receipt))
+            .collect();
         apply_delayed_receipts_to_children_states_impl(
             &mut trie_updates,
             &inserted_receipts,
@@ -175,6 +211,19 @@ impl ShardTries {
     ) -> Result<(StoreUpdate, HashMap<ShardUId, StateRoot>), StorageError> {
         let mut changes_by_shard: HashMap<_, Vec<_>> = HashMap::new();
         for (raw_key, value) in values.into_iter() {
+            // Global contracts are available to all accounts of the shard, and the runtime
+            // config overrides apply to all of its chunks, so every child shard keeps a copy.
+            if raw_key.first() == Some(&col::GLOBAL_CONTRACT_CODE)
+                || raw_key.first() == Some(&col::RUNTIME_CONFIG_OVERRIDES)
+            {
+                for shard_uid in state_roots.keys() {
+                    changes_by_shard
+                        .entry(*shard_uid)
+                        .or_default()
+                        .push((raw_key.clone(), value.clone()));
+                }
+                continue;
+            }
             if let Some(new_shard_uid) = key_to_shard_id(&raw_key)? {
                 changes_by_shard.entry(new_shard_uid).or_default().push((raw_key, value));
             }
@@ -261,8 +310,12 @@ fn apply_delayed_receipts_to_children_states_impl(
     account_id_to_shard_uid: &dyn Fn(&AccountId) -> ShardUId,
 ) -> Result<(), StorageError> {
     let mut delayed_receipts_indices_by_shard = HashMap::new();
+    let mut priority_delayed_receipts_indices_by_shard = HashMap::new();
     for (shard_uid, update) in trie_updates.iter() {
-        delayed_receipts_indices_by_shard.insert(*shard_uid, get_delayed_receipt_indices(update)?);
+        delayed_receipts_indices_by_shard
+            .insert(*shard_uid, TrieQueueIndices::from(get_delayed_receipt_indices(update)?));
+        priority_delayed_receipts_indices_by_shard
+            .insert(*shard_uid, get_priority_delayed_receipt_indices(update)?);
     }
 
     for receipt in insert_receipts {
@@ -278,15 +331,18 @@ fn apply_delayed_receipts_to_children_states_impl(
         }
         // we already checked that new_shard_uid is in trie_updates and delayed_receipts_indices
         // so we can safely unwrap here
-        let delayed_receipts_indices =
-            delayed_receipts_indices_by_shard.get_mut(&new_shard_uid).unwrap();
+        let (lane, indices) = delayed_receipt_queue_of(
+            receipt,
+            delayed_receipts_indices_by_shard.get_mut(&new_shard_uid).unwrap(),
+            priority_delayed_receipts_indices_by_shard.get_mut(&new_shard_uid).unwrap(),
+        );
         set(
             trie_updates.get_mut(&new_shard_uid).unwrap(),
-            TrieKey::DelayedReceipt { index: delayed_receipts_indices.next_available_index },
+            delayed_receipt_key(lane, indices.next_available_index),
             receipt,
         );
-        delayed_receipts_indices.next_available_index =
-            delayed_receipts_indices.next_available_index.checked_add(1).ok_or_else(|| {
+        indices.next_available_index =
+            indices.next_available_index.checked_add(1).ok_or_else(|| {
                 StorageError::StorageInconsistentState(
                     "Next available index for delayed receipt exceeded the integer limit"
                         .to_string(),
@@ -305,18 +361,21 @@ fn apply_delayed_receipts_to_children_states_impl(
             );
             return Err(StorageError::StorageInconsistentState(err));
         }
-        let delayed_receipts_indices =
-            delayed_receipts_indices_by_shard.get_mut(&new_shard_uid).unwrap();
+        let (lane, indices) = delayed_receipt_queue_of(
+            receipt,
+            delayed_receipts_indices_by_shard.get_mut(&new_shard_uid).unwrap(),
+            priority_delayed_receipts_indices_by_shard.get_mut(&new_shard_uid).unwrap(),
+        );
 
         let trie_update = trie_updates.get_mut(&new_shard_uid).unwrap();
-        let trie_key = TrieKey::DelayedReceipt { index: delayed_receipts_indices.first_index };
+        let trie_key = delayed_receipt_key(lane, indices.first_index);
 
         let stored_receipt = get::<Receipt>(trie_update, &trie_key)?
             .expect("removed receipt does not exist in new state");
         // check that the receipt to remove is at the first of delayed receipt queue
         assert_eq!(&stored_receipt, receipt);
         trie_update.remove(trie_key);
-        delayed_receipts_indices.first_index += 1;
+        indices.first_index += 1;
     }
 
     // commit the trie_updates and update state_roots
@@ -326,6 +385,15 @@ fn apply_delayed_receipts_to_children_states_impl(
             TrieKey::DelayedReceiptIndices,
             delayed_receipts_indices_by_shard.get(shard_uid).unwrap(),
         );
+        let priority_delayed_receipts_indices =
+            priority_delayed_receipts_indices_by_shard.get(shard_uid).unwrap();
+        if !priority_delayed_receipts_indices.lanes.is_empty() {
+            set(
+                trie_update,
+                TrieKey::PriorityDelayedReceiptIndices,
+                priority_delayed_receipts_indices,
+            );
+        }
         // StateChangeCause should always be Resharding for processing resharding.
         // We do not want to commit the state_changes from resharding as they are already handled while
         // processing parent shard
@@ -334,6 +402,29 @@ fn apply_delayed_receipts_to_children_states_impl(
     Ok(())
 }
 
+/// Returns the queue holding the delayed receipt, as the lane of its priority
+/// if it has one (`None` stands for the normal delayed receipts queue), and
+/// the indices of this queue.
+fn delayed_receipt_queue_of<'a>(
+    receipt: &Receipt,
+    delayed_receipts_indices: &'a mut TrieQueueIndices,
+    priority_delayed_receipts_indices: &'a mut PriorityDelayedReceiptIndices,
+) -> (Option<u8>, &'a mut TrieQueueIndices) {
+    let priority = receipt.priority().value();
+    if priority == 0 {
+        return (None, delayed_receipts_indices);
+    }
+    let lane = PriorityDelayedReceiptIndices::lane(priority);
+    (Some(lane), priority_delayed_receipts_indices.lanes.entry(lane).or_default())
+}
+
+fn delayed_receipt_key(lane: Option<u8>, index: u64) -> TrieKey {
+    match lane {
+        Some(lane) => TrieKey::PriorityDelayedReceipt { lane, index },
+        None => TrieKey::DelayedReceipt { index },
+    }
+}
+
 fn apply_promise_yield_timeouts_to_children_states_impl(
     trie_updates: &mut HashMap<ShardUId, TrieUpdate>,
     insert_timeouts: &[PromiseYieldTimeout],
@@ -454,6 +545,57 @@ pub fn get_delayed_receipts(
     Ok(Some((delayed_receipt_indices.first_index, receipts)))
 }
 
+/// Retrieve delayed receipts with a priority starting with `start` until `memory_limit` is hit.
+///
+/// The position in the queues is given as a pair of the lane and of the index in the lane.
+/// Returns the position of the first receipt which was not read in this batch and a vec of
+/// receipts which were read, ordered by lane and then by index.
+///
+/// Returns None if there are no receipts at or after `start`.
+pub fn get_priority_delayed_receipts(
+    state_update: &TrieUpdate,
+    start: Option<(u8, u64)>,
+    memory_limit: ByteSize,
+) -> Result<Option<((u8, u64), Vec<Receipt>)>, StorageError> {
+    let lanes_indices = get_priority_delayed_receipt_indices(state_update)?;
+    let mut position = start.unwrap_or_default();
+    let mut used_memory = 0;
+    let mut receipts = vec![];
+    let mut found_any = false;
+
+    for (&lane, indices) in lanes_indices.lanes.range(position.0..) {
+        let mut index = if lane == position.0 { position.1 } else { 0 };
+        index = index.max(indices.first_index);
+        if index < indices.next_available_index {
+            found_any = true;
+        }
+        while used_memory < memory_limit.as_u64() && index < indices.next_available_index {
+            let key = TrieKey::PriorityDelayedReceipt { lane, index };
+            let data = state_update.get(&key)?.ok_or_else(|| {
+                StorageError::StorageInconsistentState(format!(
+                    "Priority delayed receipt #{} of lane {} should be in the state",
+                    index, lane
+                ))
+            })?;
+            used_memory += data.len() as u64;
+            index += 1;
+
+            let receipt = Receipt::try_from_slice(&data).map_err(|_| {
+                StorageError::StorageInconsistentState("Failed to deserialize".to_string())
+            })?;
+            receipts.push(receipt);
+        }
+        position = (lane, index);
+        if used_memory >= memory_limit.as_u64() {
+            break;
+        }
+    }
+    if !found_any {
+        return Ok(None);
+    }
+    Ok(Some((position, receipts)))
+}
+
 /// Retrieve PromiseYield timeouts starting with `start_index` until `memory_limit` is hit.
 ///
 /// Returns an updated start_index (the first index which was not read in this batch)
@@ -501,19 +643,23 @@ mod tests {
     use crate::resharding::{
         apply_delayed_receipts_to_children_states_impl,
         apply_promise_yield_timeouts_to_children_states_impl, get_delayed_receipts,
-        get_promise_yield_timeouts,
+        get_priority_delayed_receipts, get_promise_yield_timeouts,
     };
     use crate::test_utils::{
         gen_changes, gen_receipts, gen_timeouts, get_all_delayed_receipts,
         get_all_promise_yield_timeouts, test_populate_trie, TestTriesBuilder,
     };
+    use crate::trie::receipts_column_helper::{
+        DelayedReceiptQueue, PriorityDelayedReceiptQueue, TrieQueue,
+    };
 
     use crate::{set, ShardTries, ShardUId, Trie};
     use near_primitives::account::id::AccountId;
 
     use near_primitives::hash::hash;
     use near_primitives::receipt::{
-        DelayedReceiptIndices, PromiseYieldIndices, PromiseYieldTimeout, Receipt,
+        DelayedReceiptIndices, PriorityDelayedReceiptIndices, PromiseYieldIndices,
+        PromiseYieldTimeout, Receipt,
     };
     use near_primitives::trie_key::TrieKey;
     use near_primitives::types::{NumShards, StateChangeCause, StateRoot};
@@ -566,6 +712,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_add_shard_wide_values_to_children_states() {
+        let tries = TestTriesBuilder::new().build();
+        let state_roots: HashMap<_, _> =
+            (0..4).map(|shard_id| (ShardUId { version: 1, shard_id }, Trie::EMPTY_ROOT)).collect();
+        let code_key = TrieKey::GlobalContractCode { code_hash: hash(b"code") };
+        let overrides_key = TrieKey::RuntimeConfigOverrides;
+        let changes = vec![
+            (code_key.to_vec(), Some(b"code".to_vec())),
+            (overrides_key.to_vec(), Some(b"overrides".to_vec())),
+        ];
+        let (store_update, new_state_roots) = tries
+            .add_values_to_children_states_impl(&state_roots, changes, &|_| {
+                panic!("shard wide values are not assigned to a single shard")
+            })
+            .unwrap();
+        store_update.commit().unwrap();
+        for (shard_uid, state_root) in new_state_roots {
+            let trie = tries.get_view_trie_for_shard(shard_uid, state_root);
+            assert_eq!(trie.get(&code_key.to_vec()).unwrap(), Some(b"code".to_vec()));
+            assert_eq!(trie.get(&overrides_key.to_vec()).unwrap(), Some(b"overrides".to_vec()));
+        }
+    }
+
     #[test]
     fn test_get_delayed_receipts() {
         let mut rng = rand::thread_rng();
@@ -750,6 +920,85 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_apply_priority_delayed_receipts_to_new_states() {
+        let mut rng = rand::thread_rng();
+        let num_shards = 4;
+        let account_id_to_shard_id = |account_id: &AccountId| ShardUId {
+            shard_id: (hash(account_id.as_bytes()).0[0] as NumShards % num_shards) as u32,
+            version: 1,
+        };
+
+        for _ in 0..10 {
+            let memory_limit = bytesize::ByteSize::b(rng.gen_range(200..1000));
+            let mut all_receipts = gen_receipts(&mut rng, 200);
+            for receipt in &mut all_receipts {
+                if let Receipt::V1(receipt) = receipt {
+                    receipt.priority = rng.gen_range(1..1 << 20);
+                }
+            }
+
+            let tries = TestTriesBuilder::new().build();
+            let mut trie_update = tries.new_trie_update(ShardUId::single_shard(), Trie::EMPTY_ROOT);
+            let mut queue = PriorityDelayedReceiptQueue::load(&trie_update).unwrap();
+            for receipt in &all_receipts {
+                queue.push(&mut trie_update, receipt).unwrap();
+            }
+            trie_update.commit(StateChangeCause::Resharding);
+            let (_, trie_changes, _) = trie_update.finalize().unwrap();
+            let mut store_update = tries.store_update();
+            let state_root =
+                tries.apply_all(&trie_changes, ShardUId::single_shard(), &mut store_update);
+            store_update.commit().unwrap();
+
+            let mut state_roots: HashMap<_, _> = (0..num_shards)
+                .map(|x| (ShardUId { version: 1, shard_id: x as u32 }, Trie::EMPTY_ROOT))
+                .collect();
+            let trie_update = tries.new_trie_update(ShardUId::single_shard(), state_root);
+            let mut start = None;
+            let mut read_receipts = 0;
+            while let Some((next_position, receipts)) =
+                get_priority_delayed_receipts(&trie_update, start, memory_limit).unwrap()
+            {
+                read_receipts += receipts.len();
+                let (store_update, new_state_roots) = tries
+                    .apply_delayed_receipts_to_children_states(
+                        &state_roots,
+                        &receipts,
+                        &account_id_to_shard_id,
+                    )
+                    .unwrap();
+                store_update.commit().unwrap();
+                state_roots = new_state_roots;
+                start = Some(next_position);
+            }
+            assert_eq!(read_receipts, all_receipts.len());
+
+            // Each child keeps its receipts in the lanes of their priority, in the original
+            // order within a lane.
+            for (shard_uid, state_root) in &state_roots {
+                let mut expected_receipts: Vec<_> = all_receipts
+                    .iter()
+                    .filter(|receipt| account_id_to_shard_id(receipt.receiver_id()) == *shard_uid)
+                    .cloned()
+                    .collect();
+                expected_receipts.sort_by_key(|receipt| {
+                    std::cmp::Reverse(PriorityDelayedReceiptIndices::lane(
+                        receipt.priority().value(),
+                    ))
+                });
+                let trie_update = tries.new_trie_update(*shard_uid, *state_root);
+                let receipts: Vec<_> = PriorityDelayedReceiptQueue::load(&trie_update)
+                    .unwrap()
+                    .iter(&trie_update)
+                    .collect::<Result<_, _>>()
+                    .unwrap();
+                assert_eq!(expected_receipts, receipts);
+                assert_eq!(DelayedReceiptQueue::load(&trie_update).unwrap().len(), 0);
+            }
+        }
+    }
+
     fn test_apply_promise_yield_timeouts(
         tries: &ShardTries,
         new_timeouts: &[PromiseYieldTimeout],
@@ -1,10 +1,11 @@
 use super::mem::MemTries;
 use super::state_snapshot::{StateSnapshot, StateSnapshotConfig};
 use super::TrieRefcountSubtraction;
-use crate::flat::store_helper::remove_all_state_values;
+use crate::flat::store_helper::{self, remove_all_state_values};
 use crate::flat::{FlatStorageManager, FlatStorageStatus};
 use crate::trie::config::TrieConfig;
 use crate::trie::mem::loading::load_trie_from_flat_state_and_delta;
+use crate::trie::mem::persistence::{load_mem_tries_from_dump, mem_trie_dump_path, save_mem_tries};
 use crate::trie::mem::updating::apply_memtrie_changes;
 use crate::trie::prefetching_trie_storage::PrefetchingThreadsHandle;
 use crate::trie::trie_storage::{TrieCache, TrieCachingStorage};
@@ -18,10 +19,11 @@ use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
     BlockHeight, RawStateChange, RawStateChangesWithTrieKey, StateChangeCause, StateRoot,
 };
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
 use tracing::info;
 
 struct ShardTriesInner {
@@ -91,6 +93,28 @@ impl ShardTries {
         TrieUpdate::new(self.get_view_trie_for_shard(shard_uid, state_root))
     }
 
+    /// Same as `new_trie_update_view`, but reads the values from flat storage
+    /// if it supports `block_hash`, including the blocks below the flat head
+    /// covered by the flat state history.  Without the history, the flat head
+    /// could move past the block while the view is in use, so the trie is
+    /// used then.
+    pub fn new_trie_update_view_at_block(
+        &self,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+        block_hash: &CryptoHash,
+    ) -> TrieUpdate {
+        let flat_storage_supports_block =
+            self.0.trie_config.flat_state_history_epochs.map_or(false, |epochs| epochs > 0)
+                && self
+                    .0
+                    .flat_storage_manager
+                    .get_flat_storage_for_shard(shard_uid)
+                    .map_or(false, |flat_storage| flat_storage.supports_block(block_hash));
+        let block_hash = flat_storage_supports_block.then_some(*block_hash);
+        TrieUpdate::new(self.get_trie_for_shard_internal(shard_uid, state_root, true, block_hash))
+    }
+
     #[tracing::instrument(
         level = "trace",
         target = "store::trie::shard_tries",
@@ -419,7 +443,17 @@ impl ShardTries {
         state_root: Option<StateRoot>,
     ) -> Result<(), StorageError> {
         info!(target: "memtrie", "Loading trie to memory for shard {:?}...", shard_uid);
-        let mem_tries = load_trie_from_flat_state_and_delta(&self.0.store, *shard_uid, state_root)?;
+        let dumped_mem_tries = match (&self.0.trie_config.mem_tries_dump_dir, state_root) {
+            (Some(dir), None) => {
+                let path = mem_trie_dump_path(dir, *shard_uid);
+                load_mem_tries_from_dump(&self.0.store, *shard_uid, &path)?
+            }
+            _ => None,
+        };
+        let mem_tries = match dumped_mem_tries {
+            Some(mem_tries) => mem_tries,
+            None => load_trie_from_flat_state_and_delta(&self.0.store, *shard_uid, state_root)?,
+        };
         self.0.mem_tries.write().unwrap().insert(*shard_uid, Arc::new(RwLock::new(mem_tries)));
         info!(target: "memtrie", "Memtrie loading complete for shard {:?}", shard_uid);
         Ok(())
@@ -432,7 +466,7 @@ impl ShardTries {
         shard_uid: &ShardUId,
         state_root: &StateRoot,
     ) -> Result<(), StorageError> {
-        if !self.0.trie_config.load_mem_tries_for_tracked_shards {
+        if !self.0.trie_config.load_mem_trie_for_shard(shard_uid) {
             return Ok(());
         }
         // It should not happen that memtrie is already loaded for a shard
@@ -443,7 +477,7 @@ impl ShardTries {
 
     /// Loads in-memory tries upon startup. The given shard_uids are possible candidates to load,
     /// but which exact shards to load depends on configuration. This may only be called when flat
-    /// storage is ready.
+    /// storage is ready. At most `load_mem_tries_parallelism` shards are loaded at the same time.
     pub fn load_mem_tries_for_enabled_shards(
         &self,
         tracked_shards: &[ShardUId],
@@ -452,30 +486,93 @@ impl ShardTries {
         let shard_uids_to_load = tracked_shards
             .iter()
             .copied()
-            .filter(|shard_uid| {
-                trie_config.load_mem_tries_for_tracked_shards
-                    || trie_config.load_mem_tries_for_shards.contains(shard_uid)
-            })
+            .filter(|shard_uid| trie_config.load_mem_trie_for_shard(shard_uid))
             .collect::<Vec<_>>();
 
-        info!(target: "memtrie", "Loading tries to memory for shards {:?}...", shard_uids_to_load);
-        shard_uids_to_load
-            .par_iter()
-            .map(|shard_uid| self.load_mem_trie(shard_uid, None))
-            .collect::<Vec<Result<_, _>>>()
-            .into_iter()
-            .collect::<Result<_, _>>()?;
+        let num_shards = shard_uids_to_load.len();
+        let parallelism = trie_config.load_mem_tries_parallelism.clamp(1, num_shards.max(1));
+        info!(target: "memtrie", "Loading tries to memory for shards {:?}, {} at a time...", shard_uids_to_load, parallelism);
+        let load_start = Instant::now();
+        let next_shard = AtomicUsize::new(0);
+        let num_loaded = AtomicUsize::new(0);
+        let error = Mutex::new(None);
+        // Each worker loads the next shard not taken yet, so that a slow shard
+        // doesn't hold back the loading of the others.
+        rayon::scope(|scope| {
+            for _ in 0..parallelism {
+                scope.spawn(|_| {
+                    while let Some(shard_uid) =
+                        shard_uids_to_load.get(next_shard.fetch_add(1, Ordering::Relaxed))
+                    {
+                        let shard_load_start = Instant::now();
+                        if let Err(err) = self.load_mem_trie(shard_uid, None) {
+                            error.lock().unwrap().get_or_insert(err);
+                            return;
+                        }
+                        let num_loaded = num_loaded.fetch_add(1, Ordering::Relaxed) + 1;
+                        info!(
+                            target: "memtrie",
+                            %shard_uid,
+                            "Memtrie loaded in {:?}, {}/{} shards done",
+                            shard_load_start.elapsed(),
+                            num_loaded,
+                            num_shards
+                        );
+                    }
+                });
+            }
+        });
+        if let Some(err) = error.into_inner().unwrap() {
+            return Err(err);
+        }
 
-        info!(target: "memtrie", "Memtries loading complete for shards {:?}", shard_uids_to_load);
+        info!(target: "memtrie", "Memtries loading complete for shards {:?}, took {:?}", shard_uids_to_load, load_start.elapsed());
         Ok(())
     }
 
+    /// Saves the loaded in-memory tries to `mem_tries_dump_dir`, if configured,
+    /// so that they are restored on the next start. Needs to be called once
+    /// no more blocks are processed, as the saved tries are only used if the
+    /// flat storage stays the same.
+    pub fn save_mem_tries(&self) {
+        let Some(dir) = &self.0.trie_config.mem_tries_dump_dir else { return };
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            tracing::warn!(target: "memtrie", ?dir, ?err, "Failed to create the memtrie dump directory");
+            return;
+        }
+        for shard_uid in self.loaded_mem_trie_shards() {
+            let Ok(FlatStorageStatus::Ready(status)) =
+                store_helper::get_flat_storage_status(&self.0.store, shard_uid)
+            else {
+                continue;
+            };
+            let Some(mem_tries) = self.get_mem_tries(shard_uid) else { continue };
+            let save_start = Instant::now();
+            let path = mem_trie_dump_path(dir, shard_uid);
+            match save_mem_tries(&mem_tries.read().unwrap(), status.flat_head, &path) {
+                Ok(()) => {
+                    info!(target: "memtrie", %shard_uid, ?path, "Saved memtrie in {:?}", save_start.elapsed())
+                }
+                Err(err) => {
+                    tracing::warn!(target: "memtrie", %shard_uid, ?path, ?err, "Failed to save memtrie")
+                }
+            }
+        }
+    }
+
     /// Retrieves the in-memory tries for the shard.
     pub fn get_mem_tries(&self, shard_uid: ShardUId) -> Option<Arc<RwLock<MemTries>>> {
         let guard = self.0.mem_tries.read().unwrap();
         guard.get(&shard_uid).cloned()
     }
 
+    /// Returns the shards for which in-memory tries are loaded.
+    pub fn loaded_mem_trie_shards(&self) -> Vec<ShardUId> {
+        let mut shard_uids: Vec<_> = self.0.mem_tries.read().unwrap().keys().copied().collect();
+        shard_uids.sort_by_key(|shard_uid| (shard_uid.version, shard_uid.shard_id));
+        shard_uids
+    }
+
     /// Garbage collects the in-memory tries for the shard up to (and including) the given
     /// height.
     pub fn delete_memtrie_roots_up_to_height(&self, shard_uid: ShardUId, height: BlockHeight) {
@@ -532,6 +629,11 @@ impl WrappedTrieChanges {
         &self.state_changes
     }
 
+    /// Returns a view of the trie before the changes were applied.
+    pub fn old_trie(&self) -> Trie {
+        self.tries.get_view_trie_for_shard(self.shard_uid, self.trie_changes.old_root)
+    }
+
     pub fn apply_mem_changes(&self) {
         self.tries.apply_memtrie_changes(&self.trie_changes, self.shard_uid, self.block_height);
     }
@@ -18,6 +18,8 @@
 
 use crate::flat::{FlatStateChanges, FlatStateIterator};
 use crate::trie::iterator::TrieTraversalItem;
+use crate::trie::mem::arena::STArenaMemory;
+use crate::trie::mem::node::{MemTrieNodePtr, MemTrieNodeView};
 use crate::trie::nibble_slice::NibbleSlice;
 use crate::trie::trie_storage::TrieMemoryPartialStorage;
 use crate::trie::{
@@ -71,8 +73,7 @@ impl Trie {
             return Ok(LAST_STATE_PART_BOUNDARY.to_vec());
         }
         let root_node = self.retrieve_node(&self.root)?.1;
-        let total_size = root_node.memory_usage;
-        let size_start = total_size / num_parts * part_id + part_id.min(total_size % num_parts);
+        let size_start = state_part_memory_threshold(root_node.memory_usage, part_id, num_parts);
         self.find_node_in_dfs_order(&root_node, size_start)
     }
 
@@ -88,6 +89,47 @@ impl Trie {
         Ok(recorded.nodes)
     }
 
+    /// Generates state parts using the memtrie instead of the trie storage.
+    /// Reading the nodes from memory avoids random disk reads, which lets many
+    /// parts be generated in parallel. Only the values that are too large to be
+    /// inlined in the memtrie are read from State.
+    /// Returns `None` if the memtrie isn't loaded or doesn't have the state
+    /// root anymore.
+    pub fn get_trie_nodes_for_part_with_memtries(
+        &self,
+        part_id: PartId,
+    ) -> Result<Option<PartialState>, StorageError> {
+        let Some(memtries) = &self.memtries else {
+            return Ok(None);
+        };
+        let _span = tracing::debug_span!(
+            target: "state-parts",
+            "get_trie_nodes_for_part_with_memtries",
+            part_id = part_id.idx,
+            num_parts = part_id.total)
+        .entered();
+        // Hold the lock only while copying the nodes, because it blocks the
+        // updates of the memtrie.
+        let (mut all_nodes, value_refs) = {
+            let memtries = memtries.read().unwrap();
+            let Some(root) = memtries.get_root(&self.root) else {
+                return Ok(None);
+            };
+            collect_memtrie_nodes_for_part(root, part_id)
+        };
+        for value_hash in value_refs {
+            let value = self.retrieve_value(&value_hash)?;
+            all_nodes.insert(value_hash, value.into());
+        }
+
+        // The collected nodes are a superset of the nodes of the state part.
+        // Traverse them to pick the ones that belong to it.
+        let trie = Trie::new(Rc::new(TrieMemoryPartialStorage::new(all_nodes)), self.root, None);
+        trie.visit_nodes_for_state_part(part_id)?;
+        let storage = trie.storage.as_partial_storage().unwrap();
+        Ok(Some(storage.partial_state()))
+    }
+
     /// Helper to create iterator over flat storage entries corresponding to
     /// its head, shard for which trie was created and the range of keys given
     /// in nibbles.
@@ -505,6 +547,59 @@ impl Trie {
     }
 }
 
+/// Returns the prefix memory usage at which the given state part boundary is.
+fn state_part_memory_threshold(total_size: u64, part_id: u64, num_parts: u64) -> u64 {
+    total_size / num_parts * part_id + part_id.min(total_size % num_parts)
+}
+
+/// Collects the memtrie nodes needed to generate the given state part: the
+/// nodes whose subtrees overlap with the memory range of the part, and their
+/// children, which are needed to find the boundaries of the part.
+/// Returns the serialized nodes together with the inlined values, and the
+/// hashes of the values that are not inlined.
+fn collect_memtrie_nodes_for_part(
+    root: MemTrieNodePtr<STArenaMemory>,
+    part_id: PartId,
+) -> (HashMap<CryptoHash, Arc<[u8]>>, Vec<CryptoHash>) {
+    let total_size = root.view().memory_usage();
+    let size_start = state_part_memory_threshold(total_size, part_id.idx, part_id.total);
+    let size_end = state_part_memory_threshold(total_size, part_id.idx + 1, part_id.total);
+
+    let mut nodes = HashMap::new();
+    let mut value_refs = vec![];
+    // Nodes to visit, with the prefix memory usage at which their subtrees start.
+    let mut stack = vec![(root, 0u64)];
+    while let Some((node, position)) = stack.pop() {
+        let view = node.view();
+        let memory_usage = view.memory_usage();
+        let raw_node = borsh::to_vec(&view.to_raw_trie_node_with_size()).unwrap();
+        nodes.insert(view.node_hash(), raw_node.into());
+        if position + memory_usage < size_start || position > size_end {
+            continue;
+        }
+
+        if let MemTrieNodeView::Leaf { value, .. }
+        | MemTrieNodeView::BranchWithValue { value, .. } = &view
+        {
+            match value.to_flat_value() {
+                FlatStateValue::Ref(value_ref) => value_refs.push(value_ref.hash),
+                FlatStateValue::Inlined(value) => {
+                    nodes.insert(hash(&value), value.into());
+                }
+            }
+        }
+        let children: Vec<_> =
+            view.iter_children().map(|child| (child, child.view().memory_usage())).collect();
+        let mut child_position =
+            position + memory_usage - children.iter().map(|(_, size)| size).sum::<u64>();
+        for (child, child_memory_usage) in children {
+            stack.push((child, child_position));
+            child_position += child_memory_usage;
+        }
+    }
+    (nodes, value_refs)
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
@@ -1142,6 +1237,43 @@ mod tests {
         }
     }
 
+    /// Checks that state parts generated from the memtrie are the same as the
+    /// ones generated from the trie storage.
+    #[test]
+    fn test_get_trie_nodes_for_part_with_memtries() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let tries =
+                TestTriesBuilder::new().with_flat_storage(true).with_in_memory_tries(true).build();
+            let shard_uid = ShardUId::single_shard();
+            let mut trie_changes = gen_changes(&mut rng, 10);
+            // A value that is too large to be inlined in the memtrie.
+            trie_changes.push((b"large".to_vec(), Some(vec![7; 5000])));
+            let state_root = test_populate_trie(&tries, &Trie::EMPTY_ROOT, shard_uid, trie_changes);
+            let trie = tries.get_trie_for_shard(shard_uid, state_root);
+
+            for _ in 0..10 {
+                let num_parts: u64 = rng.gen_range(1..10);
+                let part_id = PartId::new(rng.gen_range(0..num_parts), num_parts);
+                let state_part =
+                    trie.get_trie_nodes_for_part_with_memtries(part_id).unwrap().unwrap();
+                let expected_state_part =
+                    trie.get_trie_nodes_for_part_without_flat_storage(part_id).unwrap();
+                assert_eq!(
+                    Trie::validate_state_part(&state_root, part_id, state_part.clone()),
+                    Ok(())
+                );
+                assert_eq!(state_part, expected_state_part);
+            }
+        }
+
+        // The memtrie doesn't have an unknown state root.
+        let tries =
+            TestTriesBuilder::new().with_flat_storage(true).with_in_memory_tries(true).build();
+        let trie = tries.get_trie_for_shard(ShardUId::single_shard(), hash(&[1]));
+        assert_eq!(trie.get_trie_nodes_for_part_with_memtries(PartId::new(0, 1)), Ok(None));
+    }
+
     /// Checks sanity of generating state part using flat storage.
     #[test]
     fn get_trie_nodes_for_part_with_flat_storage() {
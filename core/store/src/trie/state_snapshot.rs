@@ -137,6 +137,9 @@ pub struct StateSnapshotConfig {
     pub home_dir: PathBuf,
     pub hot_store_path: PathBuf,
     pub state_snapshot_subdir: PathBuf,
+    /// Whether to move the existing snapshot to the next epoch boundary with
+    /// the flat state deltas instead of recreating it.
+    pub incremental: bool,
 }
 
 pub const STATE_SNAPSHOT_COLUMNS: &[DBCol] = &[
@@ -196,16 +199,74 @@ impl ShardTries {
             tracing::error!(target: "state_snapshot", ?prev_block_hash, ?state_snapshot.prev_block_hash, "Requested a state snapshot but that is already available with a different hash");
         }
 
-        let StateSnapshotConfig { home_dir, hot_store_path, state_snapshot_subdir, .. } =
-            self.state_snapshot_config();
+        let previous_state_snapshot = state_snapshot_lock.take();
+        let incremental = self.state_snapshot_config().incremental;
+        if incremental {
+            self.get_flat_storage_manager().buffer_deltas_for_snapshot(shard_uids.to_vec());
+        }
+        let moved_state_snapshot = match previous_state_snapshot {
+            Some(state_snapshot) if incremental => {
+                self.move_state_snapshot(state_snapshot, prev_block_hash, shard_uids, block)
+            }
+            _ => None,
+        };
+        let state_snapshot = match moved_state_snapshot {
+            Some(state_snapshot) => state_snapshot,
+            None => {
+                self.checkpoint_state_snapshot(prev_block_hash, shard_uids, block).inspect_err(
+                    |_| self.get_flat_storage_manager().stop_forwarding_deltas_to_snapshot(),
+                )?
+            }
+        };
+        if incremental {
+            self.get_flat_storage_manager().forward_deltas_to_snapshot(
+                state_snapshot.store.clone(),
+                state_snapshot.get_shard_uids(),
+            )?;
+        }
+        *state_snapshot_lock = Some(state_snapshot);
+
+        // this will set the new hash for state snapshot in rocksdb. will retry until success.
+        let mut set_state_snapshot_in_db = false;
+        while !set_state_snapshot_in_db {
+            set_state_snapshot_in_db = match self.set_state_snapshot_hash(Some(prev_block_hash)) {
+                Ok(_) => true,
+                Err(err) => {
+                    // This will be retried.
+                    tracing::debug!(target: "state_snapshot", ?err, "Failed to set the new state snapshot for BlockMisc::STATE_SNAPSHOT_KEY in rocksdb");
+                    false
+                }
+            }
+        }
+
+        metrics::HAS_STATE_SNAPSHOT.set(1);
+        tracing::info!(target: "state_snapshot", ?prev_block_hash, "Made a checkpoint");
+        Ok(Some(state_snapshot_lock.as_ref().unwrap().get_shard_uids()))
+    }
+
+    /// Checkpoints the flat storage columns of the hot store into a new state
+    /// snapshot and moves its flat heads to the given block.
+    fn checkpoint_state_snapshot(
+        &self,
+        prev_block_hash: CryptoHash,
+        shard_uids: &[ShardUId],
+        block: &Block,
+    ) -> Result<StateSnapshot, anyhow::Error> {
+        let StateSnapshotConfig {
+            home_dir,
+            hot_store_path,
+            state_snapshot_subdir,
+            incremental,
+            ..
+        } = self.state_snapshot_config();
+        if *incremental {
+            // The incremental snapshot is always in the same directory, which
+            // needs to be emptied first.
+            self.delete_all_state_snapshots(home_dir, hot_store_path, state_snapshot_subdir)?;
+        }
         let storage = checkpoint_hot_storage_and_cleanup_columns(
             &self.get_store(),
-            &Self::get_state_snapshot_base_dir(
-                &prev_block_hash,
-                home_dir,
-                hot_store_path,
-                state_snapshot_subdir,
-            ),
+            &self.get_state_snapshot_dir(&prev_block_hash),
             // TODO: Cleanup Changes and DeltaMetadata to avoid extra memory usage.
             // Can't be cleaned up now because these columns are needed to `update_flat_head()`.
             Some(STATE_SNAPSHOT_COLUMNS),
@@ -215,30 +276,82 @@ impl ShardTries {
         // it is used only for reading flat storage in the snapshot a
         // doesn't introduce memory overhead.
         let flat_storage_manager = FlatStorageManager::new(store.clone());
-        *state_snapshot_lock = Some(StateSnapshot::new(
+        Ok(StateSnapshot::new(
             store,
             prev_block_hash,
             flat_storage_manager,
             shard_uids,
             Some(block),
-        ));
+        ))
+    }
 
-        // this will set the new hash for state snapshot in rocksdb. will retry until success.
-        let mut set_state_snapshot_in_db = false;
-        while !set_state_snapshot_in_db {
-            set_state_snapshot_in_db = match self.set_state_snapshot_hash(Some(prev_block_hash)) {
-                Ok(_) => true,
-                Err(err) => {
-                    // This will be retried.
-                    tracing::debug!(target: "state_snapshot", ?err, "Failed to set the new state snapshot for BlockMisc::STATE_SNAPSHOT_KEY in rocksdb");
-                    false
-                }
+    /// Moves the flat heads of an incremental state snapshot to the given
+    /// block, applying the deltas forwarded to it since it was taken.
+    /// Returns `None` if the snapshot can't be moved and needs to be recreated.
+    fn move_state_snapshot(
+        &self,
+        state_snapshot: StateSnapshot,
+        prev_block_hash: CryptoHash,
+        shard_uids: &[ShardUId],
+        block: &Block,
+    ) -> Option<StateSnapshot> {
+        let mut snapshot_shard_uids = state_snapshot.get_shard_uids();
+        snapshot_shard_uids.sort();
+        let mut requested_shard_uids = shard_uids.to_vec();
+        requested_shard_uids.sort();
+        if snapshot_shard_uids != requested_shard_uids {
+            tracing::info!(target: "state_snapshot", ?snapshot_shard_uids, ?requested_shard_uids, "Shards of the state snapshot changed, recreating it");
+            return None;
+        }
+
+        // Loads the deltas forwarded to the snapshot store.
+        let StateSnapshot { store, .. } = state_snapshot;
+        let flat_storage_manager = FlatStorageManager::new(store.clone());
+        let state_snapshot = StateSnapshot::new(
+            store,
+            prev_block_hash,
+            flat_storage_manager,
+            shard_uids,
+            Some(block),
+        );
+        for shard_uid in shard_uids {
+            let desired_flat_head = block
+                .chunks()
+                .get(shard_uid.shard_id as usize)
+                .map(|chunk| *chunk.prev_block_hash());
+            let flat_head = state_snapshot
+                .flat_storage_manager
+                .get_flat_storage_for_shard(*shard_uid)
+                .map(|flat_storage| flat_storage.get_head_hash());
+            if desired_flat_head.is_none() || flat_head != desired_flat_head {
+                tracing::warn!(target: "state_snapshot", ?shard_uid, ?flat_head, ?desired_flat_head, "Failed to move the state snapshot, recreating it");
+                return None;
             }
         }
+        tracing::info!(target: "state_snapshot", ?prev_block_hash, "Moved the state snapshot");
+        Some(state_snapshot)
+    }
 
-        metrics::HAS_STATE_SNAPSHOT.set(1);
-        tracing::info!(target: "state_snapshot", ?prev_block_hash, "Made a checkpoint");
-        Ok(Some(state_snapshot_lock.as_ref().unwrap().get_shard_uids()))
+    /// Directory of the state snapshot taken at the given block.  An
+    /// incremental snapshot keeps the directory it was created in.
+    fn get_state_snapshot_dir(&self, prev_block_hash: &CryptoHash) -> PathBuf {
+        let StateSnapshotConfig {
+            home_dir,
+            hot_store_path,
+            state_snapshot_subdir,
+            incremental,
+            ..
+        } = self.state_snapshot_config();
+        if *incremental {
+            home_dir.join(hot_store_path).join(state_snapshot_subdir).join("incremental")
+        } else {
+            Self::get_state_snapshot_base_dir(
+                prev_block_hash,
+                home_dir,
+                hot_store_path,
+                state_snapshot_subdir,
+            )
+        }
     }
 
     /// Deletes all snapshots and unsets the STATE_SNAPSHOT_KEY.
@@ -249,6 +362,7 @@ impl ShardTries {
 
         // get snapshot_hash after acquiring write lock
         let mut state_snapshot_lock = self.state_snapshot().write().unwrap();
+        self.get_flat_storage_manager().stop_forwarding_deltas_to_snapshot();
         if state_snapshot_lock.is_some() {
             // Drop Store before deleting the underlying data.
             *state_snapshot_lock = None;
@@ -335,18 +449,10 @@ impl ShardTries {
         let _span =
             tracing::info_span!(target: "state_snapshot", "maybe_open_state_snapshot").entered();
         metrics::HAS_STATE_SNAPSHOT.set(0);
-        let StateSnapshotConfig { home_dir, hot_store_path, state_snapshot_subdir, .. } =
-            self.state_snapshot_config();
-
         // directly return error if no snapshot is found
         let snapshot_hash = self.get_state_snapshot_hash()?;
 
-        let snapshot_path = Self::get_state_snapshot_base_dir(
-            &snapshot_hash,
-            &home_dir,
-            &hot_store_path,
-            &state_snapshot_subdir,
-        );
+        let snapshot_path = self.get_state_snapshot_dir(&snapshot_hash);
         let parent_path = snapshot_path
             .parent()
             .ok_or_else(|| anyhow::anyhow!("{snapshot_path:?} needs to have a parent dir"))?;
@@ -354,15 +460,25 @@ impl ShardTries {
 
         let store_config = StoreConfig::default();
 
+        let incremental = self.state_snapshot_config().incremental;
         let opener = NodeStorage::opener(&snapshot_path, false, &store_config, None);
-        let storage = opener.open_in_mode(Mode::ReadOnly)?;
+        // An incremental snapshot receives the deltas of the new blocks.
+        let mode = if incremental { Mode::ReadWriteExisting } else { Mode::ReadOnly };
+        let storage = opener.open_in_mode(mode)?;
         let store = storage.get_hot_store();
         let flat_storage_manager = FlatStorageManager::new(store.clone());
 
         let shard_uids = get_shard_uids_fn(snapshot_hash)?;
         let mut guard = self.state_snapshot().write().unwrap();
-        *guard =
-            Some(StateSnapshot::new(store, snapshot_hash, flat_storage_manager, &shard_uids, None));
+        let state_snapshot =
+            StateSnapshot::new(store, snapshot_hash, flat_storage_manager, &shard_uids, None);
+        if incremental {
+            self.get_flat_storage_manager().forward_deltas_to_snapshot(
+                state_snapshot.store.clone(),
+                state_snapshot.get_shard_uids(),
+            )?;
+        }
+        *guard = Some(state_snapshot);
         metrics::HAS_STATE_SNAPSHOT.set(1);
         tracing::info!(target: "runtime", ?snapshot_hash, ?snapshot_path, "Detected and opened a state snapshot.");
         Ok(())
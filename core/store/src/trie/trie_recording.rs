@@ -16,6 +16,12 @@ pub struct TrieRecorder {
     code_len_counter: usize,
     /// Account IDs for which the code should be recorded.
     pub codes_to_record: HashSet<AccountId>,
+    /// Hashes of the global contract codes which should be recorded.
+    pub global_codes_to_record: HashSet<CryptoHash>,
+    /// Whether values of keys removed or overwritten by the trie update are
+    /// recorded. Only the hashes of such values are needed to update the
+    /// refcounts, so they can be left out of the state proof.
+    record_removed_values: bool,
 }
 
 impl TrieRecorder {
@@ -26,9 +32,21 @@ impl TrieRecorder {
             removal_counter: 0,
             code_len_counter: 0,
             codes_to_record: Default::default(),
+            global_codes_to_record: Default::default(),
+            record_removed_values: true,
         }
     }
 
+    /// Creates a recorder which doesn't record values of removed or
+    /// overwritten keys, unless they were read explicitly.
+    pub fn new_excluding_removed_values() -> Self {
+        Self { record_removed_values: false, ..Self::new() }
+    }
+
+    pub fn records_removed_values(&self) -> bool {
+        self.record_removed_values
+    }
+
     pub fn record(&mut self, hash: &CryptoHash, node: Arc<[u8]>) {
         let size = node.len();
         if self.recorded.insert(*hash, node).is_none() {
@@ -77,15 +95,16 @@ mod trie_recording_tests {
     };
     use crate::trie::mem::metrics::MEM_TRIE_NUM_LOOKUPS;
     use crate::trie::TrieNodesCount;
-    use crate::{DBCol, KeyLookupMode, PartialStorage, ShardTries, Store, Trie};
+    use crate::{DBCol, KeyLookupMode, PartialStorage, ShardTries, Store, Trie, TrieUpdate};
     use borsh::BorshDeserialize;
     use near_primitives::challenge::PartialState;
     use near_primitives::congestion_info::CongestionInfo;
     use near_primitives::hash::{hash, CryptoHash};
     use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
     use near_primitives::state::ValueRef;
+    use near_primitives::trie_key::TrieKey;
     use near_primitives::types::chunk_extra::ChunkExtra;
-    use near_primitives::types::StateRoot;
+    use near_primitives::types::{StateChangeCause, StateRoot};
     use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
     use rand::prelude::SliceRandom;
     use rand::{random, thread_rng, Rng};
@@ -472,4 +491,91 @@ mod trie_recording_tests {
     fn test_trie_recording_consistency_with_flat_storage_with_accounting_cache_and_missing_keys() {
         test_trie_recording_consistency(true, true, true);
     }
+
+    /// Verifies that values of removed or overwritten keys are not recorded
+    /// when the recorder excludes them, both with on-disk and in-memory tries,
+    /// and that the trie update can still be replayed using the recorded
+    /// storage alone.
+    #[test]
+    fn test_trie_recording_excluding_removed_values() {
+        for _ in 0..NUM_ITERATIONS_PER_TEST {
+            let PreparedTrie { store, shard_uid, data_in_trie, updates, state_root, .. } =
+                prepare_trie(false, 1.0, 0.0);
+            let tries = TestTriesBuilder::new().with_store(store.clone()).build();
+
+            let trie = tries.get_trie_for_shard(shard_uid, state_root).recording_reads();
+            let expected_root = trie.update(updates.iter().cloned()).unwrap().new_root;
+            let full_storage = trie.recorded_storage().unwrap();
+
+            let trie = tries
+                .get_trie_for_shard(shard_uid, state_root)
+                .recording_reads_excluding_removed_values();
+            assert_eq!(trie.update(updates.iter().cloned()).unwrap().new_root, expected_root);
+            let partial_storage = trie.recorded_storage().unwrap();
+
+            let PartialState::TrieValues(full_nodes) = &full_storage.nodes;
+            let PartialState::TrieValues(nodes) = &partial_storage.nodes;
+            let full_nodes: HashSet<&[u8]> = full_nodes.iter().map(|node| &node[..]).collect();
+            let nodes: HashSet<&[u8]> = nodes.iter().map(|node| &node[..]).collect();
+            assert!(nodes.is_subset(&full_nodes));
+            for (key, _) in &updates {
+                let removed_value = data_in_trie.get(key).unwrap();
+                assert!(full_nodes.contains(&removed_value[..]));
+                assert!(!nodes.contains(&removed_value[..]));
+            }
+
+            tries.load_mem_trie(&shard_uid, None).unwrap();
+            destructively_delete_in_memory_state_from_disk(&store, &data_in_trie);
+            let trie = tries
+                .get_trie_for_shard(shard_uid, state_root)
+                .recording_reads_excluding_removed_values();
+            assert_eq!(trie.update(updates.iter().cloned()).unwrap().new_root, expected_root);
+            assert_partial_storage(&partial_storage, &trie.recorded_storage().unwrap());
+
+            let trie = Trie::from_recorded_storage(partial_storage, state_root, false);
+            assert_eq!(trie.update(updates.iter().cloned()).unwrap().new_root, expected_root);
+        }
+    }
+
+    /// Values read after being written earlier in the chunk are served from
+    /// the `TrieUpdate` without touching the trie, and a value read several
+    /// times is recorded once, so neither adds to the storage proof.
+    #[test]
+    fn test_trie_recording_reads_after_writes_and_repeated_reads() {
+        let tries = TestTriesBuilder::new().build();
+        let shard_uid = ShardUId::single_shard();
+        let key = |key: &[u8]| TrieKey::ContractData {
+            account_id: "alice.near".parse().unwrap(),
+            key: key.to_vec(),
+        };
+        let written_value = vec![1u8; 100];
+        let read_value = vec![2u8; 100];
+        let state_root = test_populate_trie(
+            &tries,
+            &Trie::EMPTY_ROOT,
+            shard_uid,
+            vec![
+                (key(b"written").to_vec(), Some(written_value.clone())),
+                (key(b"read").to_vec(), Some(read_value.clone())),
+            ],
+        );
+
+        let trie = tries
+            .get_trie_for_shard(shard_uid, state_root)
+            .recording_reads_excluding_removed_values();
+        let mut update = TrieUpdate::new(trie);
+        update.set(key(b"written"), vec![3; 100]);
+        update.commit(StateChangeCause::InitialState);
+        assert_eq!(update.get(&key(b"written")).unwrap(), Some(vec![3; 100]));
+        assert_eq!(update.trie().recorded_storage_size(), 0);
+        assert_eq!(update.get(&key(b"read")).unwrap(), Some(read_value.clone()));
+        let recorded_size = update.trie().recorded_storage_size();
+        assert_eq!(update.get(&key(b"read")).unwrap(), Some(read_value.clone()));
+        assert_eq!(update.trie().recorded_storage_size(), recorded_size);
+
+        let (trie, _, _) = update.finalize().unwrap();
+        let PartialState::TrieValues(nodes) = trie.recorded_storage().unwrap().nodes;
+        assert!(nodes.iter().any(|node| node[..] == read_value[..]));
+        assert!(nodes.iter().all(|node| node[..] != written_value[..]));
+    }
 }
@@ -1,5 +1,5 @@
 pub use self::iterator::TrieUpdateIterator;
-use super::{OptimizedValueRef, Trie, TrieWithReadLock};
+use super::{OptimizedValueRef, Trie, TrieRange, TrieWithReadLock};
 use crate::trie::{KeyLookupMode, TrieChanges};
 use crate::{StorageError, TrieStorage};
 use near_primitives::hash::CryptoHash;
@@ -10,6 +10,7 @@ use near_primitives::types::{
 };
 use near_vm_runner::ContractCode;
 use std::collections::BTreeMap;
+use std::ops::Bound;
 use std::rc::Rc;
 
 mod iterator;
@@ -137,28 +138,39 @@ impl TrieUpdate {
 
     /// Gets code from trie updates or directly from contract storage,
     /// bypassing the trie.
+    ///
+    /// Accounts using a global contract don't store their own copy of the
+    /// code, so the global code with the same hash is looked up as well.
     pub fn get_code(
         &self,
         account_id: AccountId,
         code_hash: CryptoHash,
     ) -> Option<near_vm_runner::ContractCode> {
-        let key = TrieKey::ContractCode { account_id }.to_vec();
-        let raw_code_update = if let Some(key_value) = self.prospective.get(&key) {
+        let key = TrieKey::ContractCode { account_id };
+        let global_key = TrieKey::GlobalContractCode { code_hash };
+        let raw_code = match self.get_uncommitted(&key) {
+            Some(Some(code)) => Some(code),
+            _ => self.get_uncommitted(&global_key).flatten(),
+        };
+        match raw_code {
+            Some(code) => Some(near_vm_runner::ContractCode::new(code, Some(code_hash))),
+            None => self.contract_storage.get(code_hash),
+        }
+    }
+
+    /// Returns the value from the updates which are not in the trie yet, if
+    /// the key was updated.
+    fn get_uncommitted(&self, key: &TrieKey) -> Option<Option<Vec<u8>>> {
+        let key = key.to_vec();
+        if let Some(key_value) = self.prospective.get(&key) {
             Some(key_value.value.as_ref().map(<Vec<u8>>::clone))
         } else if let Some(changes_with_trie_key) = self.committed.get(&key) {
-            if let Some(RawStateChange { data, .. }) = changes_with_trie_key.changes.last() {
-                Some(data.as_ref().map(<Vec<u8>>::clone))
-            } else {
-                None
-            }
+            changes_with_trie_key
+                .changes
+                .last()
+                .map(|RawStateChange { data, .. }| data.as_ref().map(<Vec<u8>>::clone))
         } else {
             None
-        };
-        match raw_code_update {
-            Some(raw_code) => {
-                raw_code.map(|code| near_vm_runner::ContractCode::new(code, Some(code_hash)))
-            }
-            None => self.contract_storage.get(code_hash),
         }
     }
 
@@ -249,6 +261,60 @@ impl TrieUpdate {
         TrieUpdateIterator::new(self, key_prefix, Some(lock))
     }
 
+    /// Returns the first key within `range` which is not smaller than `from`,
+    /// together with its value, taking the changes in this update into
+    /// account. See [`Trie::get_first_in_range`].
+    pub fn get_first_in_range(
+        &self,
+        range: &TrieRange,
+        from: &[u8],
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, StorageError> {
+        let from = std::cmp::max(from, range.start());
+        if from >= range.end() {
+            return Ok(None);
+        }
+        let bounds = (Bound::Included(from), Bound::Excluded(range.end()));
+        let from_prospective = self
+            .prospective
+            .range::<[u8], _>(bounds)
+            .find_map(|(key, key_value)| Some((key, key_value.value.as_ref()?)));
+        let from_committed = self
+            .committed
+            .range::<[u8], _>(bounds)
+            .filter(|(key, _)| !self.prospective.contains_key(*key))
+            .find_map(|(key, changes_with_trie_key)| {
+                Some((key, changes_with_trie_key.changes.last()?.data.as_ref()?))
+            });
+        let from_update = match (from_prospective, from_committed) {
+            (Some(prospective), Some(committed)) => {
+                Some(if prospective.0 < committed.0 { prospective } else { committed })
+            }
+            (prospective, committed) => prospective.or(committed),
+        };
+
+        // Keys changed in this update are skipped in the trie, their values
+        // (if any) are taken from the update instead.
+        let mut trie_from = from.to_vec();
+        let from_trie = loop {
+            match self.trie.get_first_in_range(range, &trie_from)? {
+                Some((key, _))
+                    if self.prospective.contains_key(&key) || self.committed.contains_key(&key) =>
+                {
+                    trie_from = key;
+                    trie_from.push(0);
+                }
+                entry => break entry,
+            }
+        };
+
+        Ok(match (from_trie, from_update) {
+            (Some(trie), Some((key, _))) if &trie.0 < key => Some(trie),
+            (None, None) => None,
+            (Some(trie), None) => Some(trie),
+            (_, Some((key, value))) => Some((key.clone(), value.clone())),
+        })
+    }
+
     pub fn get_root(&self) -> &StateRoot {
         self.trie.get_root()
     }
@@ -419,4 +485,48 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn trie_first_in_range() {
+        let tries = TestTriesBuilder::new().build();
+        let mut trie_update = tries.new_trie_update(ShardUId::single_shard(), Trie::EMPTY_ROOT);
+        trie_update.set(test_key(b"aaa".to_vec()), b"puppy".to_vec());
+        trie_update.set(test_key(b"dog".to_vec()), b"puppy".to_vec());
+        trie_update.set(test_key(b"dog5".to_vec()), b"puppy".to_vec());
+        trie_update.set(test_key(b"xxx".to_vec()), b"puppy".to_vec());
+        trie_update
+            .commit(StateChangeCause::TransactionProcessing { tx_hash: CryptoHash::default() });
+        let trie_changes = trie_update.finalize().unwrap().1;
+        let mut store_update = tries.store_update();
+        let new_root = tries.apply_all(&trie_changes, ShardUId::single_shard(), &mut store_update);
+        store_update.commit().unwrap();
+
+        let mut trie_update = tries.new_trie_update(ShardUId::single_shard(), new_root);
+        trie_update.set(test_key(b"dog2".to_vec()), b"cat".to_vec());
+        trie_update.remove(test_key(b"dog5".to_vec()));
+        trie_update
+            .commit(StateChangeCause::TransactionProcessing { tx_hash: CryptoHash::default() });
+        trie_update.set(test_key(b"dog".to_vec()), b"dog".to_vec());
+        trie_update.set(test_key(b"dog3".to_vec()), b"kitten".to_vec());
+
+        let range = trie_update
+            .trie()
+            .range(test_key(b"dog".to_vec()).to_vec(), test_key(b"dog9".to_vec()).to_vec())
+            .unwrap();
+        let mut entries = vec![];
+        let mut from = range.start().to_vec();
+        while let Some((key, value)) = trie_update.get_first_in_range(&range, &from).unwrap() {
+            from = key.clone();
+            from.push(0);
+            entries.push((key, value));
+        }
+        assert_eq!(
+            entries,
+            vec![
+                (test_key(b"dog".to_vec()).to_vec(), b"dog".to_vec()),
+                (test_key(b"dog2".to_vec()).to_vec(), b"cat".to_vec()),
+                (test_key(b"dog3".to_vec()).to_vec(), b"kitten".to_vec()),
+            ]
+        );
+    }
 }
@@ -0,0 +1,276 @@
+//! Generates genesis records for large synthetic states, so that storage and
+//! resharding work can be done against states of realistic size without
+//! mainnet data.
+//!
+//! Everything is derived from a seed, so the same parameters always produce
+//! the same records, and the keys of the accounts can be derived again to sign
+//! transactions, see `synthetic_signer`.
+
+use near_chain_configs::{Genesis, GenesisConfig};
+use near_crypto::{InMemorySigner, KeyType};
+use near_primitives::account::{AccessKey, Account};
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::state_record::StateRecord;
+use near_primitives::types::{AccountId, Balance, NumShards};
+use near_primitives::utils::get_num_seats_per_shard;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Number of accounts whose records are generated by a single task.
+const ACCOUNTS_PER_BATCH: u64 = 10_000;
+/// Number of batches generated in parallel before they are written out,
+/// bounding the memory used for the records that are waiting to be written.
+const BATCHES_PER_ROUND: u64 = 64;
+
+/// Parameters of the generated state.
+#[derive(Clone, Debug)]
+pub struct SyntheticStateConfig {
+    pub num_accounts: u64,
+    /// Full access keys added to every account.
+    pub access_keys_per_account: u64,
+    /// Number of accounts the contract is deployed to.
+    pub num_contract_accounts: u64,
+    /// Number of storage entries of every account with the contract.
+    pub data_entries_per_contract: u64,
+    /// Size of the values of the storage entries, in bytes.
+    pub data_value_size: usize,
+    /// Number of shards of the genesis shard layout.
+    pub num_shards: NumShards,
+    /// Balance of every account.
+    pub balance: Balance,
+    pub seed: u64,
+}
+
+/// Summary of the generated records.
+#[derive(Debug, Default, PartialEq)]
+pub struct GeneratedRecords {
+    pub num_records: u64,
+    pub total_balance: Balance,
+}
+
+/// Returns the id of the account with the given index. Ids start with 16
+/// hexadecimal digits derived from the seed and the index, which spreads the
+/// accounts evenly over the shards of `synthetic_shard_layout`.
+pub fn synthetic_account_id(seed: u64, index: u64) -> AccountId {
+    let hash = CryptoHash::hash_borsh((seed, index));
+    let prefix = u64::from_be_bytes(hash.0[..8].try_into().unwrap());
+    format!("{:016x}-{}", prefix, index).parse().unwrap()
+}
+
+/// Returns the signer of the access key with the given index of the account.
+pub fn synthetic_signer(account_id: &AccountId, key_index: u64) -> InMemorySigner {
+    InMemorySigner::from_seed(
+        account_id.clone(),
+        KeyType::ED25519,
+        &format!("{}:{}", account_id, key_index),
+    )
+}
+
+/// Returns a shard layout splitting the synthetic accounts evenly into
+/// `num_shards` shards. Other accounts, e.g. the validators, are usually in the
+/// last shard, as their ids sort after the hexadecimal digits.
+pub fn synthetic_shard_layout(num_shards: NumShards) -> ShardLayout {
+    if num_shards <= 1 {
+        return ShardLayout::v0_single_shard();
+    }
+    let step = u64::MAX / num_shards;
+    let boundary_accounts =
+        (1..num_shards).map(|i| format!("{:016x}", i * step).parse().unwrap()).collect();
+    ShardLayout::v1(boundary_accounts, None, 1)
+}
+
+/// Writes the records of `base` followed by the synthetic records to
+/// `records_path` as a JSON array, and returns the config of `base` with the
+/// shard layout and total supply updated to match. The records aren't
+/// validated, as that takes long for large states.
+pub fn generate_genesis(
+    base: &Genesis,
+    config: &SyntheticStateConfig,
+    contract_code: &[u8],
+    records_path: &Path,
+) -> anyhow::Result<(GenesisConfig, GeneratedRecords)> {
+    anyhow::ensure!(
+        config.num_contract_accounts <= config.num_accounts,
+        "can't deploy the contract to more accounts than there are"
+    );
+    // Read before the output is created, as `base` may be backed by the same
+    // records file.
+    let mut base_records = vec![];
+    base.for_each_record(|record| base_records.push(serde_json::to_vec(record).unwrap()));
+
+    let mut out = BufWriter::new(File::create(records_path)?);
+    let mut generated = GeneratedRecords::default();
+    out.write_all(b"[")?;
+    let mut write_record = |out: &mut BufWriter<File>, record: &[u8]| -> std::io::Result<()> {
+        out.write_all(if generated.num_records == 0 { b"\n" } else { b",\n" })?;
+        generated.num_records += 1;
+        out.write_all(record)
+    };
+    for record in &base_records {
+        write_record(&mut out, record)?;
+    }
+
+    let code_hash = CryptoHash::hash_bytes(contract_code);
+    let num_batches = config.num_accounts.div_ceil(ACCOUNTS_PER_BATCH);
+    for round_start in (0..num_batches).step_by(BATCHES_PER_ROUND as usize) {
+        let round_end = (round_start + BATCHES_PER_ROUND).min(num_batches);
+        let batches: Vec<Vec<Vec<u8>>> = (round_start..round_end)
+            .into_par_iter()
+            .map(|batch| {
+                let start = batch * ACCOUNTS_PER_BATCH;
+                let end = (start + ACCOUNTS_PER_BATCH).min(config.num_accounts);
+                let mut records = vec![];
+                for index in start..end {
+                    for record in account_records(config, index, contract_code, code_hash, base) {
+                        records.push(serde_json::to_vec(&record).unwrap());
+                    }
+                }
+                records
+            })
+            .collect();
+        for record in batches.iter().flatten() {
+            write_record(&mut out, record)?;
+        }
+        println!(
+            "Generated the records of {}/{} accounts",
+            (round_end * ACCOUNTS_PER_BATCH).min(config.num_accounts),
+            config.num_accounts
+        );
+    }
+    out.write_all(b"\n]\n")?;
+    out.flush()?;
+
+    let mut genesis_config = base.config.clone();
+    generated.total_balance = config.balance * config.num_accounts as Balance;
+    genesis_config.total_supply += generated.total_balance;
+    genesis_config.shard_layout = synthetic_shard_layout(config.num_shards);
+    genesis_config.num_block_producer_seats_per_shard =
+        get_num_seats_per_shard(config.num_shards, genesis_config.num_block_producer_seats);
+    genesis_config.avg_hidden_validator_seats_per_shard = vec![0; config.num_shards as usize];
+    Ok((genesis_config, generated))
+}
+
+/// Returns the records of the account with the given index.
+fn account_records(
+    config: &SyntheticStateConfig,
+    index: u64,
+    contract_code: &[u8],
+    code_hash: CryptoHash,
+    base: &Genesis,
+) -> Vec<StateRecord> {
+    let account_id = synthetic_account_id(config.seed, index);
+    let has_contract = index < config.num_contract_accounts;
+    let account = Account::new(
+        config.balance,
+        0,
+        0,
+        if has_contract { code_hash } else { CryptoHash::default() },
+        0,
+        base.config.protocol_version,
+    );
+    let mut records = vec![StateRecord::Account { account_id: account_id.clone(), account }];
+    for key_index in 0..config.access_keys_per_account {
+        records.push(StateRecord::AccessKey {
+            account_id: account_id.clone(),
+            public_key: synthetic_signer(&account_id, key_index).public_key,
+            access_key: AccessKey::full_access(),
+        });
+    }
+    if has_contract {
+        records.push(StateRecord::Contract {
+            account_id: account_id.clone(),
+            code: contract_code.to_vec(),
+        });
+        let mut rng = StdRng::from_seed(CryptoHash::hash_borsh((config.seed, index, "data")).0);
+        for _ in 0..config.data_entries_per_contract {
+            let mut data_key = vec![0; 32];
+            rng.fill_bytes(&mut data_key);
+            let mut value = vec![0; config.data_value_size];
+            rng.fill_bytes(&mut value);
+            records.push(StateRecord::Data {
+                account_id: account_id.clone(),
+                data_key: data_key.into(),
+                value: value.into(),
+            });
+        }
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        generate_genesis, synthetic_account_id, synthetic_shard_layout, GeneratedRecords,
+        SyntheticStateConfig,
+    };
+    use near_chain_configs::Genesis;
+    use near_primitives::shard_layout::account_id_to_shard_id;
+    use near_primitives::state_record::StateRecord;
+
+    #[test]
+    fn test_synthetic_shard_layout() {
+        let shard_layout = synthetic_shard_layout(4);
+        let mut num_accounts_per_shard = vec![0; 4];
+        for index in 0..1000 {
+            let account_id = synthetic_account_id(42, index);
+            num_accounts_per_shard[account_id_to_shard_id(&account_id, &shard_layout) as usize] +=
+                1;
+        }
+        for num_accounts in num_accounts_per_shard {
+            assert!(num_accounts > 150, "{}", num_accounts);
+        }
+    }
+
+    #[test]
+    fn test_generate_genesis() {
+        let base = Genesis::test(vec!["test0".parse().unwrap()], 1);
+        let config = SyntheticStateConfig {
+            num_accounts: 25_000,
+            access_keys_per_account: 2,
+            num_contract_accounts: 10,
+            data_entries_per_contract: 3,
+            data_value_size: 10,
+            num_shards: 4,
+            balance: 1_000,
+            seed: 7,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let records_path = dir.path().join("records.json");
+        let (genesis_config, generated) =
+            generate_genesis(&base, &config, b"code", &records_path).unwrap();
+        let mut num_base_records = 0;
+        base.for_each_record(|_| num_base_records += 1);
+        assert_eq!(
+            generated,
+            GeneratedRecords {
+                num_records: num_base_records + 25_000 * 3 + 10 * 4,
+                total_balance: 25_000_000,
+            }
+        );
+        assert_eq!(genesis_config.total_supply, base.config.total_supply + 25_000_000);
+        assert_eq!(genesis_config.shard_layout.shard_ids().count(), 4);
+        let genesis = Genesis::new_with_path(genesis_config, &records_path).unwrap();
+
+        let mut num_records = 0;
+        genesis.for_each_record(|record| {
+            num_records += 1;
+            if let StateRecord::Contract { code, .. } = record {
+                assert_eq!(code, b"code");
+            }
+        });
+        assert_eq!(num_records, generated.num_records);
+
+        // The same parameters generate the same records.
+        let other_records_path = dir.path().join("other_records.json");
+        generate_genesis(&base, &config, b"code", &other_records_path).unwrap();
+        assert_eq!(
+            std::fs::read(&records_path).unwrap(),
+            std::fs::read(&other_records_path).unwrap()
+        );
+    }
+}
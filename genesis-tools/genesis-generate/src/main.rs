@@ -0,0 +1,86 @@
+use anyhow::Context;
+use genesis_generate::{generate_genesis, SyntheticStateConfig};
+use near_chain_configs::{GenesisValidationMode, NEAR_BASE};
+use near_primitives::types::{Balance, NumShards};
+use nearcore::config::{Config, CONFIG_FILENAME};
+use nearcore::{get_default_home, load_config};
+use std::path::PathBuf;
+
+/// Generates a genesis with a large synthetic state on top of the genesis of
+/// an initialized home directory. The records are written to a separate file
+/// which `config.json` is updated to point to.
+#[derive(clap::Parser)]
+struct Cli {
+    /// Directory for config and data.
+    #[clap(long, default_value_os_t = get_default_home())]
+    home: PathBuf,
+    /// Number of accounts to generate.
+    #[clap(long)]
+    num_accounts: u64,
+    /// Number of full access keys of every account.
+    #[clap(long, default_value_t = 1)]
+    access_keys_per_account: u64,
+    /// Number of accounts the contract is deployed to.
+    #[clap(long, default_value_t = 0)]
+    num_contract_accounts: u64,
+    /// Number of storage entries written for every account with the contract.
+    #[clap(long, default_value_t = 0)]
+    data_entries_per_contract: u64,
+    /// Size of the values of the storage entries, in bytes.
+    #[clap(long, default_value_t = 100)]
+    data_value_size: usize,
+    /// Number of shards of the genesis shard layout.
+    #[clap(long, default_value_t = 1)]
+    num_shards: NumShards,
+    /// Balance of every account, in yoctoNEAR.
+    #[clap(long, default_value_t = 1_000 * NEAR_BASE)]
+    balance: Balance,
+    /// Seed the account ids, keys and storage entries are derived from.
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// Wasm file of the contract to deploy, defaults to the test contract.
+    #[clap(long)]
+    contract_file: Option<PathBuf>,
+    /// Name of the records file written to the home directory.
+    #[clap(long, default_value = "synthetic_records.json")]
+    records_file: String,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli: Cli = clap::Parser::parse();
+    let near_config = load_config(&cli.home, GenesisValidationMode::UnsafeFast)
+        .context("failed loading the config")?;
+    let contract_code = match &cli.contract_file {
+        Some(path) => std::fs::read(path)
+            .with_context(|| format!("failed reading the contract from {}", path.display()))?,
+        None => near_test_contracts::rs_contract().to_vec(),
+    };
+    let config = SyntheticStateConfig {
+        num_accounts: cli.num_accounts,
+        access_keys_per_account: cli.access_keys_per_account,
+        num_contract_accounts: cli.num_contract_accounts,
+        data_entries_per_contract: cli.data_entries_per_contract,
+        data_value_size: cli.data_value_size,
+        num_shards: cli.num_shards,
+        balance: cli.balance,
+        seed: cli.seed,
+    };
+
+    let records_path = cli.home.join(&cli.records_file);
+    let (genesis_config, generated) =
+        generate_genesis(&near_config.genesis, &config, &contract_code, &records_path)?;
+    genesis_config.to_file(cli.home.join(&near_config.config.genesis_file));
+
+    let config_path = cli.home.join(CONFIG_FILENAME);
+    let mut node_config = Config::from_file(&config_path)?;
+    node_config.genesis_records_file = Some(cli.records_file);
+    node_config.write_to_file(&config_path)?;
+
+    println!(
+        "Wrote {} records with {} yoctoNEAR in new accounts to {}",
+        generated.num_records,
+        generated.total_balance,
+        records_path.display()
+    );
+    Ok(())
+}
@@ -183,7 +183,7 @@ impl GenesisBuilder {
         }
         let mut state_update =
             self.state_updates.remove(&shard_idx).expect("State updates are always available");
-        let protocol_config = self.runtime.get_protocol_config(&EpochId::default())?;
+        let protocol_config = self.runtime.get_protocol_config(&EpochId::default(), None)?;
         let storage_usage_config = protocol_config.runtime_config.fees.storage_usage_config;
 
         // Compute storage usage and update accounts.
@@ -7,24 +7,29 @@ use near_crypto::{InMemorySigner, KeyType};
 use near_epoch_manager::EpochManager;
 use near_o11y::testonly::init_test_logger;
 use near_primitives::block::Tip;
+use near_primitives::shard_layout::ShardLayout;
 use near_primitives::sharding::{PartialEncodedChunk, ShardChunk};
 use near_primitives::transaction::{
     Action, DeployContractAction, FunctionCallAction, SignedTransaction,
 };
+use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives_core::hash::CryptoHash;
 use near_primitives_core::types::AccountId;
 use near_store::cold_storage::{
-    copy_all_data_to_cold, test_cold_genesis_update, test_get_store_initial_writes,
-    test_get_store_reads, update_cold_db, update_cold_head,
+    copy_all_data_to_cold, copy_block_from_cold, prune_cold_block, test_cold_genesis_update,
+    test_get_store_initial_writes, test_get_store_reads, update_cold_db, update_cold_head,
 };
+use near_store::db::ColdDB;
 use near_store::metadata::DbKind;
 use near_store::metadata::DB_VERSION;
-use near_store::test_utils::create_test_node_storage_with_cold;
-use near_store::{DBCol, Store, COLD_HEAD_KEY, HEAD_KEY};
+use near_store::test_utils::{create_test_node_storage_with_cold, create_test_store};
+use near_store::{DBCol, Store, Trie, TrieDBStorage, COLD_HEAD_KEY, COLD_TAIL_KEY, HEAD_KEY};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 use nearcore::{cold_storage::spawn_cold_store_loop, NearConfig};
 use std::collections::HashSet;
+use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
 use strum::IntoEnumIterator;
 
 fn check_key(first_store: &Store, second_store: &Store, col: DBCol, key: &[u8]) {
@@ -157,7 +162,7 @@ fn test_storage_after_commit_of_cold_update() {
         let client_store = client.runtime_adapter.store();
         let epoch_id = client.epoch_manager.get_epoch_id_from_prev_block(&last_hash).unwrap();
         let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
-        update_cold_db(cold_db, &client_store, &shard_layout, &height, 4).unwrap();
+        update_cold_db(cold_db, &client_store, &shard_layout, None, &height, 4).unwrap();
 
         last_hash = *block.hash();
     }
@@ -297,7 +302,7 @@ fn test_cold_db_copy_with_height_skips() {
         let client = &env.clients[0];
         let epoch_id = client.epoch_manager.get_epoch_id_from_prev_block(&last_hash).unwrap();
         let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
-        update_cold_db(&cold_db, &client.runtime_adapter.store(), &shard_layout, &height, 1)
+        update_cold_db(&cold_db, &client.runtime_adapter.store(), &shard_layout, None, &height, 1)
             .unwrap();
 
         if block.is_some() {
@@ -378,7 +383,15 @@ fn test_initial_copy_to_cold(batch_size: usize) {
     let cold_db = storage.cold_db().unwrap();
     let cold_store = storage.get_cold_store().unwrap();
     let client_store = env.clients[0].runtime_adapter.store();
-    copy_all_data_to_cold(cold_db.clone(), &client_store, batch_size, &keep_going).unwrap();
+    let final_head_height = env.clients[0].chain.final_head().unwrap().height;
+    copy_all_data_to_cold(
+        cold_db.clone(),
+        &client_store,
+        final_head_height,
+        batch_size,
+        &keep_going,
+    )
+    .unwrap();
 
     for col in DBCol::iter() {
         if !col.is_cold() {
@@ -414,6 +427,185 @@ fn test_initial_copy_to_cold_medium_batch() {
     test_initial_copy_to_cold(5000);
 }
 
+/// Producing 4 epochs of blocks, copying everything to cold storage and then
+/// copying the blocks of the last 2 epochs back to an empty hot store, like
+/// when converting an archival database in place.
+/// Checks that the restored data matches the original store.
+#[test]
+fn test_copy_block_from_cold() {
+    init_test_logger();
+
+    let epoch_length = 5;
+    let max_height = epoch_length * 4;
+    let restored_height = epoch_length * 2;
+
+    let mut genesis = Genesis::test(vec![test0(), test1()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+
+    let (storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Archive);
+
+    let mut last_hash = *env.clients[0].chain.genesis().hash();
+    for height in 1..max_height {
+        let signer = InMemorySigner::from_seed(test0(), KeyType::ED25519, "test0");
+        for i in 0..5 {
+            let tx = create_tx_send_money(height * 10 + i, &signer, last_hash);
+            assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+        }
+
+        let block = env.clients[0].produce_block(height).unwrap().unwrap();
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+        last_hash = *block.hash();
+    }
+
+    let keep_going = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+    let cold_db = storage.cold_db().unwrap();
+    let cold_store = storage.get_cold_store().unwrap();
+    let client_store = env.clients[0].runtime_adapter.store();
+    let final_head_height = env.clients[0].chain.final_head().unwrap().height;
+    copy_all_data_to_cold(
+        cold_db.clone(),
+        &client_store,
+        final_head_height,
+        usize::MAX,
+        &keep_going,
+    )
+    .unwrap();
+
+    // BlockHeight isn't a cold column, so it's kept in the hot store.
+    let hot_store = create_test_store();
+    let mut store_update = hot_store.store_update();
+    for (key, value) in client_store.iter(DBCol::BlockHeight).map(Result::unwrap) {
+        store_update.set(DBCol::BlockHeight, &key, &value);
+    }
+    store_update.commit().unwrap();
+
+    let client = &env.clients[0];
+    for height in restored_height..max_height {
+        let block_hash = client.chain.get_block_hash_by_height(height).unwrap();
+        let epoch_id = client.epoch_manager.get_epoch_id(&block_hash).unwrap();
+        let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
+        let mut store_update = hot_store.store_update();
+        assert!(copy_block_from_cold(
+            &cold_store,
+            &hot_store,
+            &shard_layout,
+            &height,
+            &mut store_update
+        )
+        .unwrap());
+        store_update.commit().unwrap();
+    }
+    assert!(!copy_block_from_cold(
+        &cold_store,
+        &hot_store,
+        &ShardLayout::v0_single_shard(),
+        &max_height,
+        &mut hot_store.store_update()
+    )
+    .unwrap());
+
+    for col in DBCol::iter() {
+        if !col.is_cold() || col == DBCol::State {
+            continue;
+        }
+        let num_checks = check_iter(&hot_store, &client_store, col, &vec![]);
+        if col == DBCol::Block {
+            assert_eq!(num_checks, max_height - restored_height);
+        }
+    }
+}
+
+/// Checks that pruning removes the blocks below the pruned height together with
+/// their chunks and the trie nodes which only their state refers to, and keeps
+/// the later blocks with their state.
+#[test]
+fn test_prune_cold_block() {
+    init_test_logger();
+
+    let epoch_length = 5;
+    let max_height = epoch_length * 4;
+    let pruned_height = epoch_length * 2;
+
+    let mut genesis = Genesis::test(vec![test0(), test1()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+
+    let (storage, _, cold) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Archive);
+    let cold_db = Arc::new(ColdDB::new(cold).with_state_pruning());
+    let cold_store = storage.get_cold_store().unwrap();
+    let client_store = env.clients[0].runtime_adapter.store();
+
+    // The initial migration of the genesis makes the cold db keep the
+    // reference counts of the state.
+    let keep_going = Arc::new(std::sync::atomic::AtomicBool::new(true));
+    let genesis_height = env.clients[0].chain.genesis().height();
+    copy_all_data_to_cold(cold_db.clone(), &client_store, genesis_height, usize::MAX, &keep_going)
+        .unwrap();
+
+    let mut last_hash = *env.clients[0].chain.genesis().hash();
+    for height in 1..max_height {
+        let signer = InMemorySigner::from_seed(test0(), KeyType::ED25519, "test0");
+        for i in 0..5 {
+            let tx = create_tx_send_money(height * 10 + i, &signer, last_hash);
+            assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+        }
+
+        let block = env.clients[0].produce_block(height).unwrap().unwrap();
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+
+        let client = &env.clients[0];
+        let epoch_id = client.epoch_manager.get_epoch_id_from_prev_block(&last_hash).unwrap();
+        let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
+        update_cold_db(&cold_db, &client_store, &shard_layout, None, &height, 1).unwrap();
+
+        last_hash = *block.hash();
+    }
+    let num_state_nodes = cold_store.iter(DBCol::State).count();
+
+    let client = &env.clients[0];
+    for height in 1..pruned_height {
+        let block_hash = client.chain.get_block_hash_by_height(height).unwrap();
+        let epoch_id = client.epoch_manager.get_epoch_id(&block_hash).unwrap();
+        let shard_layout = client.epoch_manager.get_shard_layout(&epoch_id).unwrap();
+        let prune =
+            || prune_cold_block(&cold_db, &cold_store, &client_store, &shard_layout, None, &height);
+        assert!(prune().unwrap());
+        assert!(!prune().unwrap());
+    }
+    let cold_tail = cold_store.get_ser::<u64>(DBCol::BlockMisc, COLD_TAIL_KEY).unwrap();
+    assert_eq!(cold_tail, Some(pruned_height));
+
+    for height in 1..max_height {
+        let block = client.chain.get_block_by_height(height).unwrap();
+        let kept = height >= pruned_height;
+        assert_eq!(cold_store.exists(DBCol::Block, block.hash().as_ref()).unwrap(), kept);
+        for chunk_header in block.chunks().iter() {
+            if chunk_header.is_new_chunk(height) {
+                let chunk_hash = chunk_header.chunk_hash();
+                assert_eq!(cold_store.exists(DBCol::Chunks, chunk_hash.as_ref()).unwrap(), kept);
+            }
+        }
+        let shard_layout =
+            client.epoch_manager.get_shard_layout(block.header().epoch_id()).unwrap();
+        for shard_uid in shard_layout.shard_uids() {
+            let key = [block.hash().as_ref(), &shard_uid.to_bytes()].concat();
+            assert_eq!(cold_store.exists(DBCol::TrieChanges, &key).unwrap(), kept);
+            if !kept {
+                continue;
+            }
+            // The whole state of the kept blocks is still in the cold db.
+            let chunk_extra = cold_store.get_ser::<ChunkExtra>(DBCol::ChunkExtra, &key).unwrap();
+            let storage = Rc::new(TrieDBStorage::new(cold_store.clone(), shard_uid));
+            let trie = Trie::new(storage, *chunk_extra.unwrap().state_root(), None);
+            for item in trie.disk_iter().unwrap() {
+                item.unwrap();
+            }
+        }
+    }
+    assert!(cold_store.iter(DBCol::State).count() < num_state_nodes);
+}
+
 /// This test checks that garbage collection does not remove data needed for cold storage migration prematurely.
 /// Test flow:
 /// - Produce a lot of blocks.
@@ -421,9 +613,8 @@ fn test_initial_copy_to_cold_medium_batch() {
 /// - Produce a lot more blocks for hot tail to reach its boundary.
 /// - Spawn a cold store loop (just like we do in neard).
 /// - Wait 10 seconds.
-/// - Check that cold head progressed.
-#[test]
-fn test_cold_loop_on_gc_boundary() {
+/// - Check that cold head progressed and that the blocks below it were copied.
+fn test_cold_loop_on_gc_boundary(num_cold_store_copy_workers: usize) {
     init_test_logger();
 
     let epoch_length = 5;
@@ -460,7 +651,8 @@ fn test_cold_loop_on_gc_boundary() {
     let keep_going = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
 
     let cold_db = storage.cold_db().unwrap();
-    copy_all_data_to_cold(cold_db.clone(), &hot_store, 1000000, &keep_going).unwrap();
+    copy_all_data_to_cold(cold_db.clone(), &hot_store, height_delta - 1, 1000000, &keep_going)
+        .unwrap();
 
     update_cold_head(cold_db, &hot_store, &(height_delta - 1)).unwrap();
 
@@ -495,6 +687,10 @@ fn test_cold_loop_on_gc_boundary() {
     .unwrap();
     near_config.client_config = env.clients[0].config.clone();
     near_config.config.save_trie_changes = Some(true);
+    near_config.config.split_storage = Some(nearcore::config::SplitStorageConfig {
+        num_cold_store_copy_workers,
+        ..Default::default()
+    });
 
     let epoch_manager = EpochManager::new_arc_handle(storage.get_hot_store(), &genesis.config);
     spawn_cold_store_loop(&near_config, &storage, epoch_manager).unwrap();
@@ -509,4 +705,21 @@ fn test_cold_loop_on_gc_boundary() {
         start_cold_head,
         end_cold_head
     );
+    for height in start_cold_head + 1..=end_cold_head {
+        let block_hash = hot_store
+            .get_ser::<CryptoHash>(DBCol::BlockHeight, &height.to_le_bytes())
+            .unwrap()
+            .unwrap();
+        assert!(cold_store.exists(DBCol::Block, block_hash.as_ref()).unwrap(), "height {height}");
+    }
+}
+
+#[test]
+fn test_cold_loop_on_gc_boundary_single_worker() {
+    test_cold_loop_on_gc_boundary(1);
+}
+
+#[test]
+fn test_cold_loop_on_gc_boundary_parallel_workers() {
+    test_cold_loop_on_gc_boundary(4);
 }
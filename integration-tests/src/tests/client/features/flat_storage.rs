@@ -129,7 +129,9 @@ fn test_flat_storage_upgrade() {
                 .outcome_with_id
                 .outcome
                 .metadata;
-            if let ExecutionMetadata::V3(profile_data) = metadata {
+            if let ExecutionMetadata::V3(profile_data) | ExecutionMetadata::V4(profile_data, _) =
+                metadata
+            {
                 profile_data.get_ext_cost(ExtCosts::touching_trie_node)
             } else {
                 panic!("Too old version of metadata: {metadata:?}");
@@ -23,8 +23,8 @@ use near_chain::types::RuntimeAdapter;
 use near_chain::ChainGenesis;
 use near_chain_configs::test_genesis::TestGenesisBuilder;
 use near_chain_configs::{
-    ClientConfig, DumpConfig, ExternalStorageConfig, ExternalStorageLocation, StateSyncConfig,
-    SyncConfig,
+    ClientConfig, DumpConfig, ExternalStorageConfig, ExternalStorageLocation,
+    StateSyncCatchupConfig, StateSyncConfig, SyncConfig,
 };
 use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::client::ShardsManagerResponse;
@@ -219,6 +219,10 @@ fn test_client_with_multi_test_loop() {
                 iteration_delay: Some(Duration::seconds(1)),
                 location: external_storage_location.clone(),
                 credentials_file: None,
+                compression: None,
+                parallelism: None,
+                max_bytes_per_second: None,
+                http_server_addr: None,
                 restart_dump_for_shards: None,
             }),
             sync: SyncConfig::ExternalStorage(ExternalStorageConfig {
@@ -226,6 +230,9 @@ fn test_client_with_multi_test_loop() {
                 num_concurrent_requests: 1,
                 num_concurrent_requests_during_catchup: 1,
             }),
+            catchup: StateSyncCatchupConfig::default(),
+            serve_historical_state: false,
+            target_epoch_id: None,
         };
         client_config.tracked_shards = Vec::new();
 
@@ -362,6 +369,7 @@ fn test_client_with_multi_test_loop() {
                 .into_wrapped_multi_sender::<ClientSenderForPartialWitnessMessage, _>(),
             validator_signer,
             epoch_manager.clone(),
+            Default::default(),
         );
 
         let future_spawner = builder.sender().for_index(idx).into_future_spawner();
@@ -1,3 +1,4 @@
+use assert_matches::assert_matches;
 use near_epoch_manager::{EpochManager, EpochManagerAdapter};
 use near_primitives::stateless_validation::{ChunkStateWitness, EncodedChunkStateWitness};
 use near_store::test_utils::create_test_store;
@@ -15,7 +16,10 @@ use near_primitives::num_rational::Rational32;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::state_record::StateRecord;
 use near_primitives::test_utils::create_test_signer;
-use near_primitives::transaction::SignedTransaction;
+use near_primitives::transaction::{
+    Action, DeployGlobalContractAction, FunctionCallAction, SignedTransaction,
+    UseGlobalContractAction,
+};
 use near_primitives::types::{AccountInfo, EpochId};
 use near_primitives::views::FinalExecutionStatus;
 use near_primitives_core::account::{AccessKey, Account};
@@ -27,17 +31,11 @@ use nearcore::test_utils::TestEnvNightshadeSetupExt;
 
 const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
 
-fn run_chunk_validation_test(seed: u64, prob_missing_chunk: f64) {
-    init_integration_logger();
-
-    if !checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION) {
-        println!("Test not applicable without StatelessValidation enabled");
-        return;
-    }
-
+/// Creates an env of 8 validators, each producing chunks for one of 4 shards,
+/// with the accounts `account0`..`account8` spread across the shards.
+fn setup_chunk_validation_env() -> (TestEnv, Vec<AccountId>) {
     let initial_balance = 100 * ONE_NEAR;
     let validator_stake = 1000000 * ONE_NEAR;
-    let blocks_to_produce = 50;
     let num_accounts = 9;
     let accounts = (0..num_accounts)
         .map(|i| format!("account{}", i).parse().unwrap())
@@ -126,26 +124,87 @@ fn run_chunk_validation_test(seed: u64, prob_missing_chunk: f64) {
         genesis_config.total_supply += initial_balance + staked;
     }
     let genesis = Genesis::new(genesis_config, GenesisRecords(records)).unwrap();
-    let mut env = TestEnv::builder(&genesis.config)
+    let env = TestEnv::builder(&genesis.config)
         .clients(accounts.iter().take(8).cloned().collect())
         .epoch_managers_with_test_overrides(epoch_config_test_overrides)
         // Disable congestion control in order to avoid rejecting transactions
         // in tests with missing chunks.
         .nightshade_runtimes_congestion_control_disabled(&genesis)
         .build();
+    (env, accounts)
+}
+
+/// Produces the next block, applies it on all clients and validates the state
+/// witnesses of its chunks. Each client misses the chunks with the given
+/// probability.
+///
+/// Returns whether some state witness had differing post state roots due to
+/// implicit state transitions.
+fn produce_block_and_validate_chunks(
+    env: &mut TestEnv,
+    rng: &mut StdRng,
+    prob_missing_chunk: f64,
+) -> bool {
+    let heads = env
+        .clients
+        .iter()
+        .map(|client| client.chain.head().unwrap().last_block_hash)
+        .collect::<HashSet<_>>();
+    assert_eq!(heads.len(), 1, "All clients should have the same head");
+    let tip = env.clients[0].chain.head().unwrap();
+
+    let block_producer = env.get_block_producer_at_offset(&tip, 1);
+    tracing::debug!(
+        target: "client",
+        "Producing block at height {} by {}", tip.height + 1, block_producer
+    );
+    let block = env.client(&block_producer).produce_block(tip.height + 1).unwrap().unwrap();
+
+    // Apply the block.
+    for i in 0..env.clients.len() {
+        let validator_id = env.get_client_id(i);
+        tracing::debug!(
+            target: "client",
+            "Applying block at height {} at {}", block.header().height(), validator_id
+        );
+        let blocks_processed = if rng.gen_bool(prob_missing_chunk) {
+            env.clients[i]
+                .process_block_test_no_produce_chunk(block.clone().into(), Provenance::NONE)
+                .unwrap()
+        } else {
+            env.clients[i].process_block_test(block.clone().into(), Provenance::NONE).unwrap()
+        };
+        assert_eq!(blocks_processed, vec![*block.hash()]);
+    }
+
+    env.process_partial_encoded_chunks();
+    for j in 0..env.clients.len() {
+        env.process_shards_manager_responses_and_finish_processing_blocks(j);
+    }
+
+    let output = env.propagate_chunk_state_witnesses(false);
+    env.propagate_chunk_endorsements(false);
+
+    output.found_differing_post_state_root_due_to_state_transitions
+}
+
+fn run_chunk_validation_test(seed: u64, prob_missing_chunk: f64) {
+    init_integration_logger();
+
+    if !checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION) {
+        println!("Test not applicable without StatelessValidation enabled");
+        return;
+    }
+
+    let blocks_to_produce = 50;
+    let (mut env, accounts) = setup_chunk_validation_env();
+    let num_accounts = accounts.len();
     let mut tx_hashes = vec![];
 
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed);
     let mut found_differing_post_state_root_due_to_state_transitions = false;
     for round in 0..blocks_to_produce {
-        let heads = env
-            .clients
-            .iter()
-            .map(|client| client.chain.head().unwrap().last_block_hash)
-            .collect::<HashSet<_>>();
-        assert_eq!(heads.len(), 1, "All clients should have the same head");
         let tip = env.clients[0].chain.head().unwrap();
-
         let sender_account = accounts[round % num_accounts].clone();
         let receiver_account = accounts[(round + 1) % num_accounts].clone();
         let signer = InMemorySigner::from_seed(
@@ -166,40 +225,8 @@ fn run_chunk_validation_test(seed: u64, prob_missing_chunk: f64) {
             let _ = env.clients[0].process_tx(tx, false, false);
         }
 
-        let block_producer = env.get_block_producer_at_offset(&tip, 1);
-        tracing::debug!(
-            target: "client",
-            "Producing block at height {} by {}", tip.height + 1, block_producer
-        );
-        let block = env.client(&block_producer).produce_block(tip.height + 1).unwrap().unwrap();
-
-        // Apply the block.
-        for i in 0..env.clients.len() {
-            let validator_id = env.get_client_id(i);
-            tracing::debug!(
-                target: "client",
-                "Applying block at height {} at {}", block.header().height(), validator_id
-            );
-            let blocks_processed = if rng.gen_bool(prob_missing_chunk) {
-                env.clients[i]
-                    .process_block_test_no_produce_chunk(block.clone().into(), Provenance::NONE)
-                    .unwrap()
-            } else {
-                env.clients[i].process_block_test(block.clone().into(), Provenance::NONE).unwrap()
-            };
-            assert_eq!(blocks_processed, vec![*block.hash()]);
-        }
-
-        env.process_partial_encoded_chunks();
-        for j in 0..env.clients.len() {
-            env.process_shards_manager_responses_and_finish_processing_blocks(j);
-        }
-
-        let output = env.propagate_chunk_state_witnesses(false);
-        env.propagate_chunk_endorsements(false);
-
         found_differing_post_state_root_due_to_state_transitions |=
-            output.found_differing_post_state_root_due_to_state_transitions;
+            produce_block_and_validate_chunks(&mut env, &mut rng, prob_missing_chunk);
     }
 
     // Check that at least one tx was fully executed, ensuring that executing
@@ -244,6 +271,88 @@ fn test_chunk_validation_high_missing_chunks() {
     run_chunk_validation_test(44, 0.81);
 }
 
+/// Submits the transaction to all clients and produces blocks, validating their
+/// chunks, until the transaction is executed.
+fn execute_tx_with_chunk_validation(
+    env: &mut TestEnv,
+    tx: SignedTransaction,
+) -> FinalExecutionStatus {
+    let tx_hash = tx.get_hash();
+    for client in env.clients.iter_mut() {
+        let _ = client.process_tx(tx.clone(), false, false);
+    }
+    let mut rng: StdRng = SeedableRng::seed_from_u64(0);
+    for _ in 0..20 {
+        produce_block_and_validate_chunks(env, &mut rng, 0.0);
+        let Ok(outcome) = env.clients[0].chain.get_partial_transaction_result(&tx_hash) else {
+            continue;
+        };
+        match outcome.status {
+            FinalExecutionStatus::NotStarted | FinalExecutionStatus::Started => {}
+            status => return status,
+        }
+    }
+    panic!("transaction {tx_hash} was not executed");
+}
+
+/// Deploys a global contract from one shard and uses and calls it from an
+/// account on another shard. The code is not stored in the account itself, so
+/// the state witnesses of the chunks have to carry the global code for the
+/// chunks to be validated and executed.
+#[test]
+fn test_chunk_validation_global_contract() {
+    init_integration_logger();
+
+    if !checked_feature!("stable", StatelessValidationV0, PROTOCOL_VERSION)
+        || !checked_feature!("stable", GlobalContracts, PROTOCOL_VERSION)
+    {
+        println!("Test not applicable without StatelessValidation and GlobalContracts enabled");
+        return;
+    }
+
+    let (mut env, accounts) = setup_chunk_validation_env();
+    // `account0` lives in the first shard and `account7` in the last one.
+    let deployer = InMemorySigner::from_seed(accounts[0].clone(), KeyType::ED25519, "account0");
+    let user = InMemorySigner::from_seed(accounts[7].clone(), KeyType::ED25519, "account7");
+    let code = near_test_contracts::rs_contract().to_vec();
+    let code_hash = CryptoHash::hash_bytes(&code);
+
+    let deploy = env.tx_from_actions(
+        vec![Action::DeployGlobalContract(DeployGlobalContractAction { code })],
+        &deployer,
+        deployer.account_id.clone(),
+    );
+    assert_matches!(
+        execute_tx_with_chunk_validation(&mut env, deploy),
+        FinalExecutionStatus::SuccessValue(_)
+    );
+
+    let use_global_contract = env.tx_from_actions(
+        vec![Action::UseGlobalContract(Box::new(UseGlobalContractAction { code_hash }))],
+        &user,
+        user.account_id.clone(),
+    );
+    assert_matches!(
+        execute_tx_with_chunk_validation(&mut env, use_global_contract),
+        FinalExecutionStatus::SuccessValue(_)
+    );
+
+    let call = env.tx_from_actions(
+        vec![Action::FunctionCall(Box::new(FunctionCallAction {
+            method_name: "log_something".to_string(),
+            args: vec![],
+            gas: 100 * 10u64.pow(12),
+            deposit: 0,
+        }))],
+        &user,
+        user.account_id.clone(),
+    );
+    assert_matches!(
+        execute_tx_with_chunk_validation(&mut env, call),
+        FinalExecutionStatus::SuccessValue(_)
+    );
+}
+
 #[test]
 fn test_protocol_upgrade_81() {
     init_integration_logger();
@@ -6,6 +6,7 @@ use ethabi::ethereum_types::U256;
 use near_chain_configs::{Genesis, NEAR_BASE};
 use near_client::{test_utils::TestEnv, ProcessTxResponse};
 use near_crypto::{InMemorySigner, KeyType, PublicKey, SecretKey};
+use near_parameters::RuntimeConfigStore;
 use near_primitives::account::id::AccountIdRef;
 use near_primitives::account::{AccessKeyPermission, FunctionCallPermission};
 use near_primitives::errors::{InvalidAccessKeyError, InvalidTxError};
@@ -25,7 +26,6 @@ use near_store::ShardUId;
 use near_vm_runner::ContractCode;
 use near_wallet_contract::{wallet_contract, wallet_contract_magic_bytes};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
-use node_runtime::ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT;
 use testlib::runtime_utils::{alice_account, bob_account};
 
 use crate::tests::client::process_blocks::produce_blocks_from_height;
@@ -110,6 +110,11 @@ fn test_eth_implicit_account_creation() {
     }
 
     let magic_bytes = wallet_contract_magic_bytes(chain_id);
+    let zero_balance_account_limit = RuntimeConfigStore::new(None)
+        .get_config(PROTOCOL_VERSION)
+        .fees
+        .storage_usage_config
+        .zero_balance_account_limit;
 
     // Verify the ETH-implicit account has zero balance and appropriate code hash.
     // Check that the account storage fits within zero balance account limit.
@@ -118,7 +123,7 @@ fn test_eth_implicit_account_creation() {
         QueryResponseKind::ViewAccount(view) => {
             assert_eq!(view.amount, 0);
             assert_eq!(view.code_hash, *magic_bytes.hash());
-            assert!(view.storage_usage <= ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT)
+            assert!(view.storage_usage <= zero_balance_account_limit)
         }
         _ => panic!("wrong query response"),
     }
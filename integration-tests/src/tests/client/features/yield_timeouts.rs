@@ -8,10 +8,13 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::ReceiptEnum::{PromiseResume, PromiseYield};
 use near_primitives::shard_layout::account_id_to_shard_id;
 use near_primitives::transaction::{
-    Action, DeployContractAction, FunctionCallAction, SignedTransaction,
+    Action, DeployContractAction, ExecutionStatus, FunctionCallAction, SignedTransaction,
 };
 use near_primitives::types::AccountId;
-use near_primitives::views::FinalExecutionStatus;
+use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
+use near_primitives::views::{
+    FinalExecutionStatus, PromiseYieldView, QueryRequest, QueryResponseKind,
+};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 
 // The height of the block in which the promise yield is created.
@@ -385,3 +388,74 @@ fn skip_timeout_height() {
         FinalExecutionStatus::SuccessValue(vec![0u8]),
     );
 }
+
+/// Returns the id of the PromiseResume receipt produced by the latest block for given yield.
+fn find_resume_receipt_id_from_latest_block(env: &TestEnv, data_id: CryptoHash) -> CryptoHash {
+    let genesis_block = env.clients[0].chain.get_block_by_height(0).unwrap();
+    let epoch_id = genesis_block.header().epoch_id().clone();
+    let shard_layout = env.clients[0].epoch_manager.get_shard_layout(&epoch_id).unwrap();
+    let shard_id = account_id_to_shard_id(&"test0".parse::<AccountId>().unwrap(), &shard_layout);
+    let last_block_hash = env.clients[0].chain.head().unwrap().last_block_hash;
+    let last_block_height = env.clients[0].chain.head().unwrap().height;
+    env.clients[0]
+        .chain
+        .get_outgoing_receipts_for_shard(last_block_hash, shard_id, last_block_height)
+        .unwrap()
+        .into_iter()
+        .find(|receipt| {
+            matches!(receipt.receipt(), PromiseResume(data_receipt) if data_receipt.data_id == data_id)
+        })
+        .map(|receipt| *receipt.receipt_id())
+        .expect("PromiseResume receipt should be produced")
+}
+
+/// The pending yield can be viewed with its timeout, and once it times out the resume receipt
+/// created by the runtime gets an execution outcome pointing at the yield receipt.
+#[test]
+fn yield_timeout_status_and_outcome() {
+    if !ProtocolFeature::PromiseYieldStatus.enabled(PROTOCOL_VERSION) {
+        return;
+    }
+    let (mut env, yield_tx_hash, data_id) = prepare_env_with_yield(vec![], None);
+    let request = QueryRequest::ViewPromiseYield { account_id: "test0".parse().unwrap(), data_id };
+
+    // The yield was created at YIELD_CREATE_HEIGHT, which is visible in the state once the next
+    // block is produced.
+    env.produce_block(0, NEXT_BLOCK_HEIGHT_AFTER_SETUP);
+    let QueryResponseKind::ViewPromiseYield(view) = env.query_view(request.clone()).unwrap().kind
+    else {
+        panic!("unexpected query response");
+    };
+    assert!(view.pending);
+    assert_eq!(view.expires_at, Some(YIELD_TIMEOUT_HEIGHT));
+
+    for block_height in (NEXT_BLOCK_HEIGHT_AFTER_SETUP + 1)..=YIELD_TIMEOUT_HEIGHT {
+        env.produce_block(0, block_height);
+    }
+    let resume_receipt_id = find_resume_receipt_id_from_latest_block(&env, data_id);
+
+    // In this block the resume receipt is applied and the callback is executed.
+    env.produce_block(0, YIELD_TIMEOUT_HEIGHT + 1);
+    assert_eq!(
+        env.clients[0].chain.get_partial_transaction_result(&yield_tx_hash).unwrap().status,
+        FinalExecutionStatus::SuccessValue(vec![0u8]),
+    );
+    let outcome = env.clients[0].chain.get_execution_outcome(&resume_receipt_id).unwrap();
+    let ExecutionStatus::SuccessReceiptId(yield_receipt_id) =
+        outcome.outcome_with_id.outcome.status
+    else {
+        panic!("unexpected timeout outcome {:?}", outcome.outcome_with_id.outcome.status);
+    };
+    assert!(env.clients[0].chain.get_execution_outcome(&yield_receipt_id).is_ok());
+
+    env.produce_block(0, YIELD_TIMEOUT_HEIGHT + 2);
+    let response = env.query_view(request).unwrap();
+    assert_eq!(
+        response.kind,
+        QueryResponseKind::ViewPromiseYield(PromiseYieldView {
+            pending: false,
+            expires_at: None,
+            remaining_blocks: None,
+        })
+    );
+}
@@ -11,10 +11,18 @@ use near_primitives::errors::{ActionError, ActionErrorKind, InvalidTxError, TxEx
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::transaction::Action::AddKey;
 use near_primitives::transaction::{Action, AddKeyAction, DeleteKeyAction, SignedTransaction};
-use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
+use near_primitives::types::StorageUsage;
+use near_primitives::version::ProtocolFeature;
 use near_primitives::views::{FinalExecutionStatus, QueryRequest, QueryResponseKind};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
-use node_runtime::ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT;
+
+/// Storage limit of zero balance accounts at the protocol version they were
+/// introduced in.
+fn zero_balance_account_limit() -> StorageUsage {
+    let config_store = RuntimeConfigStore::new(None);
+    let config = config_store.get_config(ProtocolFeature::ZeroBalanceAccount.protocol_version());
+    config.fees.storage_usage_config.zero_balance_account_limit
+}
 
 /// Assert that an account exists and has zero balance
 fn assert_zero_balance_account(env: &TestEnv, account_id: &AccountId) {
@@ -36,7 +44,7 @@ fn assert_zero_balance_account(env: &TestEnv, account_id: &AccountId) {
     match response.kind {
         QueryResponseKind::ViewAccount(view) => {
             assert_eq!(view.amount, 0);
-            assert!(view.storage_usage <= ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT)
+            assert!(view.storage_usage <= zero_balance_account_limit())
         }
         _ => panic!("wrong query response"),
     }
@@ -80,7 +88,7 @@ fn test_zero_balance_account_creation() {
 
     // create a zero balance account with contract deployed. The transaction should fail
     let new_account_id: AccountId = "hell.test0".parse().unwrap();
-    let contract = near_test_contracts::sized_contract(ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT as usize);
+    let contract = near_test_contracts::sized_contract(zero_balance_account_limit() as usize);
     let create_account_tx = SignedTransaction::create_contract(
         2,
         signer0.account_id.clone(),
@@ -125,6 +133,8 @@ fn test_zero_balance_account_add_key() {
         storage_amount_per_byte: 10u128.pow(19),
         num_bytes_account: 100,
         num_extra_bytes_record: 40,
+        zero_balance_account_limit: 770,
+        num_bytes_exempt_from_staking: 0,
     };
     runtime_config.wasm_config.ext_costs = ExtCostsConfig::test();
     let runtime_config_store = RuntimeConfigStore::with_one_config(runtime_config);
@@ -334,7 +344,7 @@ fn test_storage_usage_components() {
     );
 
     let config_store = RuntimeConfigStore::new(None);
-    let config = config_store.get_config(PROTOCOL_VERSION);
+    let config = config_store.get_config(ProtocolFeature::ZeroBalanceAccount.protocol_version());
     let account_overhead = config.fees.storage_usage_config.num_bytes_account as usize;
     let record_overhead = config.fees.storage_usage_config.num_extra_bytes_record as usize;
     // The NEP proposes to fit 4 full access keys + 2 fn access keys in an zero balance account
@@ -343,5 +353,5 @@ fn test_storage_usage_components() {
     let fn_access =
         PUBLIC_KEY_STORAGE_USAGE + FUNCTION_ACCESS_PERMISSION_STORAGE_USAGE + record_overhead;
     let total = account_overhead + 4 * full_access + 2 * fn_access;
-    assert_eq!(total as u64, ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT);
+    assert_eq!(total as u64, config.fees.storage_usage_config.zero_balance_account_limit);
 }
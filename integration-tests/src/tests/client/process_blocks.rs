@@ -1470,7 +1470,8 @@ fn test_archival_gc_common(
         blocks.push(block);
 
         if i <= max_cold_head_height {
-            update_cold_db(storage.cold_db().unwrap(), hot_store, &shard_layout, &i, 1).unwrap();
+            update_cold_db(storage.cold_db().unwrap(), hot_store, &shard_layout, None, &i, 1)
+                .unwrap();
             update_cold_head(storage.cold_db().unwrap(), &hot_store, &i).unwrap();
         }
     }
@@ -2425,7 +2426,7 @@ fn test_catchup_gas_price_change() {
             .unwrap();
         }
     });
-    env.clients[1].chain.schedule_apply_state_parts(0, sync_hash, num_parts, &f).unwrap();
+    env.clients[1].chain.schedule_apply_state_parts(0, sync_hash, num_parts, None, &f).unwrap();
     env.clients[1].chain.set_state_finalize(0, sync_hash).unwrap();
     let chunk_extra_after_sync =
         env.clients[1].chain.get_chunk_extra(blocks[4].hash(), &ShardUId::single_shard()).unwrap();
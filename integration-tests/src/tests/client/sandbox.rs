@@ -3,13 +3,17 @@ use near_chain_configs::Genesis;
 use near_client::test_utils::TestEnv;
 use near_client::ProcessTxResponse;
 use near_crypto::{InMemorySigner, KeyType};
-use near_primitives::account::Account;
+use near_primitives::account::{AccessKey, Account};
+use near_primitives::hash::CryptoHash;
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
+use near_primitives::sandbox::SandboxRuntimeConfigPatch;
 use near_primitives::state_record::StateRecord;
 use near_primitives::transaction::{
     Action, DeployContractAction, FunctionCallAction, SignedTransaction,
 };
 use near_primitives::types::{AccountId, BlockHeight, Nonce};
+use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::views::{QueryRequest, QueryResponseKind};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 
 fn test_setup() -> (TestEnv, InMemorySigner) {
@@ -105,3 +109,62 @@ fn test_patch_account() {
     let test1_after = env.query_account("test1".parse().unwrap());
     assert_eq!(test1_after.amount, 10);
 }
+
+#[test]
+fn test_patch_new_account_at_height() {
+    let (mut env, _signer) = test_setup();
+    let account_id: AccountId = "test2".parse().unwrap();
+    let signer = InMemorySigner::from_seed(account_id.clone(), KeyType::ED25519, "test2");
+    let code = near_test_contracts::rs_contract().to_vec();
+
+    // The account is created with an access key and a contract, and the code hash of the
+    // account is set by the contract record.
+    env.clients[0].chain.patch_state(
+        SandboxStatePatch::new(vec![
+            StateRecord::Account {
+                account_id: account_id.clone(),
+                account: Account::new(10, 0, 0, CryptoHash::default(), 0, PROTOCOL_VERSION),
+            },
+            StateRecord::AccessKey {
+                account_id: account_id.clone(),
+                public_key: signer.public_key.clone(),
+                access_key: AccessKey::full_access(),
+            },
+            StateRecord::Contract { account_id: account_id.clone(), code: code.clone() },
+        ])
+        .at_height(15),
+    );
+    assert!(!env.clients[0].chain.patch_state_in_progress());
+
+    do_blocks(&mut env, 9, 14);
+    let view = QueryRequest::ViewAccount { account_id: account_id.clone() };
+    assert!(env.query_view(view.clone()).is_err());
+
+    do_blocks(&mut env, 14, 20);
+    let QueryResponseKind::ViewAccount(account) = env.query_view(view).unwrap().kind else {
+        panic!("expected an account view");
+    };
+    assert_eq!(account.amount, 10);
+    assert_eq!(account.code_hash, CryptoHash::hash_bytes(&code));
+    let access_key =
+        QueryRequest::ViewAccessKey { account_id, public_key: signer.public_key.clone() };
+    assert!(env.query_view(access_key).is_ok());
+}
+
+#[test]
+fn test_patch_runtime_config() {
+    let (mut env, signer) = test_setup();
+    let deploy = vec![Action::DeployContract(DeployContractAction {
+        code: near_test_contracts::trivial_contract().to_vec(),
+    })];
+
+    env.clients[0].chain.patch_state(SandboxStatePatch::default().with_runtime_config(
+        SandboxRuntimeConfigPatch { max_contract_size: Some(10), ..Default::default() },
+    ));
+    do_blocks(&mut env, 9, 11);
+    assert!(!env.clients[0].chain.patch_state_in_progress());
+
+    let response =
+        send_tx(&mut env, 3, "test0".parse().unwrap(), "test0".parse().unwrap(), &signer, deploy);
+    assert!(matches!(response, ProcessTxResponse::InvalidTx(_)), "{response:?}");
+}
@@ -1,6 +1,6 @@
 use assert_matches::assert_matches;
 
-use near_async::time::{Clock, Duration};
+use near_async::time::{Clock, Duration, FakeClock};
 use near_chain::near_chain_primitives::error::QueryError;
 use near_chain::{ChainGenesis, ChainStoreAccess, Provenance};
 use near_chain_configs::ExternalStorageLocation::Filesystem;
@@ -15,7 +15,7 @@ use near_primitives::block::Tip;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state::FlatStateValue;
 use near_primitives::state_part::PartId;
-use near_primitives::state_sync::StatePartKey;
+use near_primitives::state_sync::{StatePartKey, StatePartsManifest};
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::BlockHeight;
 use near_primitives::views::{QueryRequest, QueryResponseKind};
@@ -55,6 +55,10 @@ fn test_state_dump() {
             restart_dump_for_shards: None,
             iteration_delay: Some(Duration::ZERO),
             credentials_file: None,
+            compression: None,
+            parallelism: None,
+            max_bytes_per_second: None,
+            http_server_addr: None,
         });
 
         let mut state_sync_dumper = StateSyncDumper {
@@ -114,6 +118,115 @@ fn test_state_dump() {
     });
 }
 
+#[test]
+/// Dumps state with a rate limit and checks that the part is only generated
+/// once the parts generated for the manifest fit in the limit.
+fn test_state_dump_throttled() {
+    init_test_logger();
+
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = 25;
+
+    near_actix_test_utils::run_actix(async {
+        let mut env = TestEnv::builder(&genesis.config)
+            .clients_count(1)
+            .use_state_snapshots()
+            .real_stores()
+            .nightshade_runtimes(&genesis)
+            .build();
+
+        let chain = &env.clients[0].chain;
+        let epoch_manager = env.clients[0].epoch_manager.clone();
+        let runtime = env.clients[0].runtime_adapter.clone();
+        let shard_tracker = chain.shard_tracker.clone();
+        let mut config = env.clients[0].config.clone();
+        let root_dir = tempfile::Builder::new().prefix("state_dump").tempdir().unwrap();
+        const MAX_BYTES_PER_SECOND: u64 = 1000;
+        config.state_sync.dump = Some(DumpConfig {
+            location: Filesystem { root_dir: root_dir.path().to_path_buf() },
+            restart_dump_for_shards: None,
+            iteration_delay: Some(Duration::ZERO),
+            credentials_file: None,
+            compression: None,
+            parallelism: None,
+            max_bytes_per_second: Some(MAX_BYTES_PER_SECOND),
+            http_server_addr: None,
+        });
+
+        // The dumper waits on the fake clock, so the test controls when the
+        // throttled part may be generated.
+        let fake_clock = FakeClock::default();
+        let mut state_sync_dumper = StateSyncDumper {
+            clock: fake_clock.clock(),
+            client_config: config.clone(),
+            chain_genesis: ChainGenesis::new(&genesis.config),
+            epoch_manager: epoch_manager.clone(),
+            shard_tracker,
+            runtime,
+            account_id: Some("test0".parse().unwrap()),
+            dump_future_runner: StateSyncDumper::arbiter_dump_future_runner(),
+            handle: None,
+        };
+        state_sync_dumper.start().unwrap();
+
+        const MAX_HEIGHT: BlockHeight = 37;
+        for i in 1..=MAX_HEIGHT {
+            let block = env.clients[0].produce_block(i as u64).unwrap().unwrap();
+            env.process_block(0, block, Provenance::PRODUCED);
+        }
+        let head = &env.clients[0].chain.head().unwrap();
+        let epoch_id = head.clone().epoch_id;
+        let epoch_height = epoch_manager.get_epoch_info(&epoch_id).unwrap().epoch_height();
+        let shard_id = 0;
+        let num_parts = 1;
+        let location = |file_type: &StateFileType| {
+            root_dir.path().join(external_storage_location(
+                "unittest",
+                &epoch_id,
+                epoch_height,
+                shard_id,
+                file_type,
+            ))
+        };
+        let manifest_path = location(&StateFileType::StateManifest);
+        let part_path = location(&StateFileType::StatePart { part_id: 0, num_parts });
+
+        // The manifest is written once the part was generated to build it,
+        // and then the dumper waits for the limit before generating more.
+        wait_or_timeout(100, 10000, || async {
+            if manifest_path.exists() && fake_clock.first_waiter().is_some() {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .await
+        .unwrap();
+        let manifest: StatePartsManifest =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest.num_parts(), num_parts);
+        let delay = fake_clock.first_waiter().unwrap() - fake_clock.now();
+        let expected_delay = manifest.total_size() as f64 / MAX_BYTES_PER_SECOND as f64;
+        assert!((delay.as_seconds_f64() - expected_delay).abs() < 1e-3);
+        assert!(!part_path.exists());
+
+        // The part generated for the manifest is read back from the DB, so
+        // it doesn't count towards the limit again.
+        fake_clock.advance(delay);
+        wait_or_timeout(100, 10000, || async {
+            if part_path.exists() {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(fake_clock.first_waiter(), None);
+        actix_rt::System::current().stop();
+    });
+}
+
 /// This function tests that after a node does state sync, it has the data that corresponds to the state of the epoch previous to the dumping node's final block.
 /// The way the test works:
 /// set up 2 nodes: env.client[0] dumps state parts, env.client[1] state syncs with the dumped state parts.
@@ -160,6 +273,10 @@ fn run_state_sync_with_dumped_parts(
             restart_dump_for_shards: None,
             iteration_delay: Some(Duration::ZERO),
             credentials_file: None,
+            compression: None,
+            parallelism: None,
+            max_bytes_per_second: None,
+            http_server_addr: None,
         });
         let mut state_sync_dumper = StateSyncDumper {
             clock: Clock::real(),
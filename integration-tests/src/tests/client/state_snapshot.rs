@@ -49,6 +49,7 @@ impl StateSnaptshotTestEnv {
             home_dir: home_dir.clone(),
             hot_store_path: hot_store_path.clone(),
             state_snapshot_subdir: state_snapshot_subdir.clone(),
+            incremental: false,
         };
         let shard_tries = ShardTries::new(
             store.clone(),
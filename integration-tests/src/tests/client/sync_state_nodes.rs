@@ -8,6 +8,7 @@ use near_chain::chain::ApplyStatePartsRequest;
 use near_chain::Provenance;
 use near_chain_configs::ExternalStorageLocation::Filesystem;
 use near_chain_configs::{DumpConfig, ExternalStorageConfig, Genesis, SyncConfig};
+use near_client::sync::state::StateSync;
 use near_client::test_utils::TestEnv;
 use near_client::{GetBlock, ProcessTxResponse};
 use near_client_primitives::types::GetValidatorInfo;
@@ -453,6 +454,10 @@ fn sync_state_dump() {
                 restart_dump_for_shards: None,
                 iteration_delay: Some(Duration::milliseconds(500)),
                 credentials_file: None,
+                compression: None,
+                parallelism: None,
+                max_bytes_per_second: None,
+                http_server_addr: None,
             });
             near1.config.store.state_snapshot_enabled = true;
 
@@ -702,7 +707,10 @@ fn test_dump_epoch_missing_chunk_in_last_block() {
             });
 
             tracing::info!(target: "test", "state sync - schedule");
-            env.clients[1].chain.schedule_apply_state_parts(0, sync_hash, num_parts, &f).unwrap();
+            env.clients[1]
+                .chain
+                .schedule_apply_state_parts(0, sync_hash, num_parts, None, &f)
+                .unwrap();
 
             tracing::info!(target: "test", "state sync - set state finalize");
             env.clients[1].chain.set_state_finalize(0, sync_hash).unwrap();
@@ -1086,3 +1094,79 @@ fn test_state_sync_headers_no_tracked_shards() {
         });
     });
 }
+
+/// An archival node generates the state parts of an old epoch from the trie,
+/// without a state snapshot.
+#[test]
+fn test_historical_state_parts() {
+    init_test_logger();
+    let epoch_length = 5;
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config)
+        .nightshade_runtimes(&genesis)
+        .archive(true)
+        .save_trie_changes(true)
+        .build();
+
+    let mut blocks = vec![env.clients[0].chain.get_block_by_height(0).unwrap()];
+    for i in 1..=epoch_length * 6 {
+        let block = env.clients[0].produce_block(i).unwrap().unwrap();
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+        blocks.push(block);
+    }
+
+    // The first block of the second epoch.
+    let sync_hash = *blocks[epoch_length as usize + 1].hash();
+    let client = &env.clients[0];
+    let state_sync_header = client.chain.get_state_response_header(0, sync_hash).unwrap();
+    let num_parts = state_sync_header.num_state_parts();
+    let state_root = state_sync_header.chunk_prev_state_root();
+    for part_id in 0..num_parts {
+        let part = client.chain.get_historical_state_response_part(0, part_id, sync_hash).unwrap();
+        assert!(client.runtime_adapter.validate_state_part(
+            &state_root,
+            PartId::new(part_id, num_parts),
+            &part
+        ));
+    }
+    assert!(client.chain.get_historical_state_response_part(0, num_parts, sync_hash).is_err());
+}
+
+/// Checks that the first block of a past epoch is found for point-in-time state sync.
+#[test]
+fn test_get_epoch_start_sync_hash_for_epoch() {
+    init_test_logger();
+    let epoch_length = 5;
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+
+    let mut blocks = vec![env.clients[0].chain.get_block_by_height(0).unwrap()];
+    for i in 1..=epoch_length * 4 {
+        let block = env.clients[0].produce_block(i).unwrap().unwrap();
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+        blocks.push(block);
+    }
+
+    let chain = &env.clients[0].chain;
+    let head_hash = chain.head().unwrap().last_block_hash;
+    for epoch_start_height in [epoch_length + 1, 2 * epoch_length + 1, 3 * epoch_length + 1] {
+        let epoch_start_block = &blocks[epoch_start_height as usize];
+        let sync_hash = StateSync::get_epoch_start_sync_hash_for_epoch(
+            chain,
+            &head_hash,
+            epoch_start_block.header().epoch_id(),
+        )
+        .unwrap();
+        assert_eq!(sync_hash, Some(*epoch_start_block.hash()));
+    }
+
+    // The epoch of the head isn't an ancestor of an older block.
+    let old_block_hash = *blocks[epoch_length as usize + 2].hash();
+    let head_epoch_id = chain.head().unwrap().epoch_id;
+    let sync_hash =
+        StateSync::get_epoch_start_sync_hash_for_epoch(chain, &old_block_hash, &head_epoch_id)
+            .unwrap();
+    assert_eq!(sync_hash, None);
+}
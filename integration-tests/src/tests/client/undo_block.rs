@@ -1,11 +1,12 @@
+use near_chain::types::RuntimeAdapter;
 use near_chain::{ChainStore, ChainStoreAccess, Provenance};
 use near_chain_configs::Genesis;
 use near_client::test_utils::TestEnv;
-use near_epoch_manager::EpochManagerAdapter;
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
 use near_o11y::testonly::init_test_logger;
 use near_store::test_utils::create_test_store;
 use near_store::Store;
-use near_undo_block::undo_block;
+use near_undo_block::{check_consistency, undo_blocks};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 use std::sync::Arc;
 
@@ -17,8 +18,8 @@ fn setup_env(genesis: &Genesis, store: Store) -> (TestEnv, Arc<dyn EpochManagerA
     (env, epoch_manager)
 }
 
-// Checks that Near client can successfully undo block on given height and then produce and process block normally after restart
-fn test_undo_block(epoch_length: u64, stop_height: u64) {
+// Checks that Near client can successfully undo blocks down from given height and then produce and process blocks normally after restart
+fn test_undo_blocks(epoch_length: u64, stop_height: u64, num_blocks: u64, past_final: bool) {
     init_test_logger();
 
     let save_trie_changes = true;
@@ -37,20 +38,25 @@ fn test_undo_block(epoch_length: u64, stop_height: u64) {
     let mut chain_store =
         ChainStore::new(store.clone(), genesis.config.genesis_height, save_trie_changes);
 
-    let current_head = chain_store.head().unwrap();
-    let prev_block_hash = current_head.prev_block_hash;
+    let new_head_height = stop_height - num_blocks;
+    let new_head_hash = chain_store.get_block_hash_by_height(new_head_height).unwrap();
+    let epoch_manager_handle = EpochManager::new_arc_handle(store.clone(), &genesis.config);
+    let tries = env.clients[0].runtime_adapter.get_tries();
 
-    undo_block(&mut chain_store, &*epoch_manager).unwrap();
+    undo_blocks(&mut chain_store, &epoch_manager_handle, &tries, num_blocks, past_final).unwrap();
+    check_consistency(&chain_store, &*epoch_manager, &tries).unwrap();
 
-    // after undo, the current head should be the prev_block_hash
-    assert_eq!(chain_store.head().unwrap().last_block_hash.as_bytes(), prev_block_hash.as_bytes());
-    assert_eq!(chain_store.head().unwrap().height, stop_height - 1);
+    // after undo, the current head should be the block num_blocks below stop_height
+    assert_eq!(chain_store.head().unwrap().last_block_hash, new_head_hash);
+    assert_eq!(chain_store.head().unwrap().height, new_head_height);
 
     // set up an environment again with the same store
     let (mut env, _) = setup_env(&genesis, store);
-    // the new env should be able to produce block normally
-    let block = env.clients[0].produce_block(stop_height).unwrap().unwrap();
-    env.process_block(0, block, Provenance::PRODUCED);
+    // the new env should be able to produce blocks normally
+    for i in new_head_height + 1..=stop_height {
+        let block = env.clients[0].produce_block(i).unwrap().unwrap();
+        env.process_block(0, block, Provenance::PRODUCED);
+    }
 
     // after processing the new block, the head should now be at stop_height
     assert_eq!(chain_store.head().unwrap().height, stop_height);
@@ -58,15 +64,26 @@ fn test_undo_block(epoch_length: u64, stop_height: u64) {
 
 #[test]
 fn test_undo_block_middle_of_epoch() {
-    test_undo_block(5, 3)
+    test_undo_blocks(5, 3, 1, false)
 }
 
 #[test]
 fn test_undo_block_end_of_epoch() {
-    test_undo_block(5, 5)
+    test_undo_blocks(5, 5, 1, false)
 }
 
 #[test]
 fn test_undo_block_start_of_epoch() {
-    test_undo_block(5, 6)
+    test_undo_blocks(5, 6, 1, false)
+}
+
+#[test]
+fn test_undo_blocks_past_final() {
+    test_undo_blocks(5, 13, 6, true)
+}
+
+#[test]
+#[should_panic(expected = "Cannot revert past final block")]
+fn test_undo_blocks_past_final_not_allowed() {
+    test_undo_blocks(5, 13, 6, false)
 }
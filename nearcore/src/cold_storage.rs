@@ -1,14 +1,21 @@
 use std::sync::{atomic::AtomicBool, Arc};
 
+use bytesize::ByteSize;
+use near_async::time::{Clock, Duration};
 use near_chain::types::Tip;
 use near_epoch_manager::{EpochManagerAdapter, EpochManagerHandle};
+use near_primitives::block::BlockHeader;
 use near_primitives::errors::EpochError;
+use near_primitives::shard_layout::ShardUId;
 use near_primitives::{hash::CryptoHash, types::BlockHeight};
-use near_store::cold_storage::{copy_all_data_to_cold, CopyAllDataToColdStatus};
+use near_store::cold_storage::{
+    copy_all_data_to_cold, get_cold_migration_progress, prune_cold_block, CopyAllDataToColdStatus,
+};
 use near_store::{
     cold_storage::{update_cold_db, update_cold_head},
     db::ColdDB,
-    DBCol, NodeStorage, Store, FINAL_HEAD_KEY, HEAD_KEY, TAIL_KEY,
+    ColdRetentionConfig, DBCol, NodeStorage, Store, COLD_TAIL_KEY, FINAL_HEAD_KEY, HEAD_KEY,
+    TAIL_KEY,
 };
 
 use crate::config::SplitStorageConfig;
@@ -80,15 +87,22 @@ impl From<EpochError> for ColdStoreError {
 }
 
 /// Checks if cold store head is behind the final head and if so copies data
-/// for the next available produced block after current cold store head.
-/// Updates cold store head after.
+/// for the next available produced blocks after current cold store head, up to
+/// `num_workers` of them in parallel.  Updates cold store head after, to the
+/// last block such that it and all the blocks before it were copied.
+///
+/// If `max_write_rate` is set, sleeps after copying for as long as needed to
+/// keep the average number of written bytes per second under it.
 fn cold_store_copy(
     hot_store: &Store,
     cold_store: &Store,
     cold_db: &Arc<ColdDB>,
     genesis_height: BlockHeight,
     epoch_manager: &EpochManagerHandle,
+    archived_shards: Option<&[ShardUId]>,
     num_threads: usize,
+    num_workers: usize,
+    max_write_rate: Option<ByteSize>,
 ) -> anyhow::Result<ColdStoreCopyResult, ColdStoreError> {
     // If COLD_HEAD is not set for hot storage we default it to genesis_height.
     let cold_head = cold_store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?;
@@ -113,31 +127,90 @@ fn cold_store_copy(
     }
 
     // Here it should be sufficient to just read from hot storage.
-    // Because BlockHeight is never garbage collectable and is not even copied to cold.
-    let cold_head_hash =
-        hot_store.get_ser::<CryptoHash>(DBCol::BlockHeight, &cold_head_height.to_le_bytes())?;
-    let cold_head_hash =
-        cold_head_hash.ok_or(ColdStoreError::ColdHeadHashReadError { cold_head_height })?;
-
-    // The previous block is the cold head so we can use it to get epoch id.
-    let epoch_id = epoch_manager.get_epoch_id_from_prev_block(&cold_head_hash)?;
-    let shard_layout = epoch_manager.get_shard_layout(&epoch_id)?;
-
+    // Because BlockHeight and BlockHeader are never garbage collectable and are not even
+    // copied to cold.  The blocks of a batch may belong to different epochs, so the shard
+    // layout is determined for each of them.
+    let mut batch = vec![];
     let mut next_height = cold_head_height + 1;
-    while !update_cold_db(cold_db, hot_store, &shard_layout, &next_height, num_threads)? {
-        next_height += 1;
-        if next_height > hot_final_head_height {
-            return Err(ColdStoreError::SkippedBlocksBetweenColdHeadAndNextHeightError {
-                cold_head_height,
-                next_height,
-                hot_final_head_height,
-            });
+    while batch.len() < num_workers.max(1) && next_height <= hot_final_head_height {
+        if let Some(block_hash) =
+            hot_store.get_ser::<CryptoHash>(DBCol::BlockHeight, &next_height.to_le_bytes())?
+        {
+            let header = hot_store
+                .get_ser::<BlockHeader>(DBCol::BlockHeader, block_hash.as_ref())?
+                .ok_or_else(|| ColdStoreError::Error {
+                    message: format!("block header of height {next_height} not found"),
+                })?;
+            batch.push((next_height, epoch_manager.get_shard_layout(header.epoch_id())?));
         }
+        next_height += 1;
+    }
+    if batch.is_empty() {
+        return Err(ColdStoreError::SkippedBlocksBetweenColdHeadAndNextHeightError {
+            cold_head_height,
+            next_height,
+            hot_final_head_height,
+        });
     }
 
-    update_cold_head(cold_db, hot_store, &next_height)?;
+    let instant = std::time::Instant::now();
+    let results: Vec<std::io::Result<Option<u64>>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = batch
+            .iter()
+            .map(|(height, shard_layout)| {
+                scope.spawn(move || {
+                    update_cold_db(
+                        cold_db,
+                        hot_store,
+                        shard_layout,
+                        archived_shards,
+                        height,
+                        num_threads,
+                    )
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|err| std::panic::resume_unwind(err)))
+            .collect()
+    });
+
+    // The cold head only moves over the blocks copied without a gap below
+    // them.  The blocks copied after a failed one are copied again later.
+    let mut copied_height = None;
+    let mut written_bytes = 0;
+    let mut error = None;
+    for ((height, _), result) in batch.iter().zip(results) {
+        match result {
+            Ok(size) => {
+                written_bytes += size.unwrap_or(0);
+                if error.is_none() {
+                    copied_height = Some(*height);
+                }
+            }
+            Err(err) => {
+                error.get_or_insert(err);
+            }
+        }
+    }
+    if let Some(copied_height) = copied_height {
+        update_cold_head(cold_db, hot_store, &copied_height)?;
+    }
+    if let Some(max_write_rate) = max_write_rate {
+        let min_duration = std::time::Duration::from_secs_f64(
+            written_bytes as f64 / max_write_rate.as_u64().max(1) as f64,
+        );
+        if let Some(sleep_duration) = min_duration.checked_sub(instant.elapsed()) {
+            tracing::trace!(target: "cold_store", written_bytes, ?sleep_duration, "throttling cold store copy");
+            std::thread::sleep(sleep_duration);
+        }
+    }
+    if let Some(err) = error {
+        return Err(err.into());
+    }
 
-    let result = if next_height >= hot_final_head_height {
+    let result = if copied_height.map_or(false, |height| height >= hot_final_head_height) {
         Ok(ColdStoreCopyResult::LatestBlockCopied)
     } else {
         Ok(ColdStoreCopyResult::OtherBlockCopied)
@@ -147,6 +220,75 @@ fn cold_store_copy(
     result
 }
 
+/// Prunes the heights which are outside of the retention window from the cold
+/// store, going up from the cold tail.  Examines at most
+/// `retention.max_pruned_heights` heights and returns how many it examined.
+/// The genesis block and the cold head are always kept.
+fn cold_store_prune(
+    hot_store: &Store,
+    cold_store: &Store,
+    cold_db: &ColdDB,
+    genesis_height: BlockHeight,
+    epoch_manager: &EpochManagerHandle,
+    archived_shards: Option<&[ShardUId]>,
+    retention: &ColdRetentionConfig,
+    clock: &Clock,
+) -> anyhow::Result<u64, ColdStoreError> {
+    let Some(cold_head) = cold_store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)? else {
+        return Ok(0);
+    };
+    let cold_tail = cold_store.get_ser::<BlockHeight>(DBCol::BlockMisc, COLD_TAIL_KEY)?;
+    let start_height = cold_tail.unwrap_or(genesis_height).max(genesis_height + 1);
+
+    let now = clock.now_utc();
+    let mut examined = 0;
+    for height in start_height..cold_head.height {
+        if examined >= retention.max_pruned_heights {
+            break;
+        }
+        // BlockHeight and BlockHeader are never garbage collected from the hot store.
+        let Some(block_hash) =
+            hot_store.get_ser::<CryptoHash>(DBCol::BlockHeight, &height.to_le_bytes())?
+        else {
+            examined += 1;
+            continue;
+        };
+        let header = hot_store
+            .get_ser::<BlockHeader>(DBCol::BlockHeader, block_hash.as_ref())?
+            .ok_or_else(|| ColdStoreError::Error {
+                message: format!("block header of height {height} not found"),
+            })?;
+        let age = now - header.timestamp();
+        if !is_outside_retention(retention, cold_head.height, height, age) {
+            break;
+        }
+        let shard_layout = epoch_manager.get_shard_layout(header.epoch_id())?;
+        prune_cold_block(cold_db, cold_store, hot_store, &shard_layout, archived_shards, &height)?;
+        examined += 1;
+    }
+    if examined > 0 {
+        tracing::debug!(target: "cold_store", start_height, examined, "pruned cold store");
+    }
+    Ok(examined)
+}
+
+/// Returns whether the block at `height`, produced `age` ago, is outside of
+/// all the windows set in `retention`.
+fn is_outside_retention(
+    retention: &ColdRetentionConfig,
+    cold_head_height: BlockHeight,
+    height: BlockHeight,
+    age: Duration,
+) -> bool {
+    if retention.blocks.is_none() && retention.duration.is_none() {
+        return false;
+    }
+    let outside_blocks =
+        retention.blocks.map_or(true, |blocks| cold_head_height.saturating_sub(height) >= blocks);
+    let outside_duration = retention.duration.map_or(true, |duration| age > duration);
+    outside_blocks && outside_duration
+}
+
 // Check some basic sanity conditions.
 // * cold head <= hot final head
 // * cold head >= hot tail
@@ -262,6 +404,20 @@ fn cold_store_migration(
         return Ok(ColdStoreMigrationResult::NoNeedForMigration);
     }
 
+    // An interrupted migration is resumed with the cold head height it started
+    // with, since the columns copied before the interruption don't contain the
+    // blocks produced since then.
+    if let Some(progress) = get_cold_migration_progress(cold_db)? {
+        tracing::info!(target: "cold_store", target_height = progress.target_height, column = progress.column, "Resuming population of cold store.");
+        return populate_cold_store(
+            split_storage_config,
+            keep_going,
+            hot_store,
+            cold_db,
+            progress.target_height,
+        );
+    }
+
     tracing::info!(target: "cold_store", "Starting population of cold store.");
     let new_cold_height = match hot_store.get_db_kind()? {
         None => {
@@ -287,8 +443,26 @@ fn cold_store_migration(
 
     tracing::info!(target: "cold_store", new_cold_height, "Determined cold storage head height after migration");
 
+    populate_cold_store(split_storage_config, keep_going, hot_store, cold_db, new_cold_height)
+}
+
+/// Copies all data to the cold store, resuming an interrupted copy to the
+/// same height, and updates the cold head to `new_cold_height`.
+fn populate_cold_store(
+    split_storage_config: &SplitStorageConfig,
+    keep_going: &Arc<AtomicBool>,
+    hot_store: &Store,
+    cold_db: &Arc<ColdDB>,
+    new_cold_height: BlockHeight,
+) -> anyhow::Result<ColdStoreMigrationResult> {
     let batch_size = split_storage_config.cold_store_initial_migration_batch_size;
-    match copy_all_data_to_cold(cold_db.clone(), hot_store, batch_size, keep_going)? {
+    match copy_all_data_to_cold(
+        cold_db.clone(),
+        hot_store,
+        new_cold_height,
+        batch_size,
+        keep_going,
+    )? {
         CopyAllDataToColdStatus::EverythingCopied => {
             tracing::info!(target: "cold_store", new_cold_height, "Cold storage population was successful, writing cold head.");
             update_cold_head(cold_db, hot_store, &new_cold_height)?;
@@ -349,8 +523,9 @@ fn cold_store_migration_loop(
 
 // This method will copy data from hot storage to cold storage in a loop.
 // It will try to copy blocks as fast as possible up until cold head = final head.
-// Once the cold head reaches the final head it will sleep for one second before
-// trying to copy data at the next height.
+// Once the cold head reaches the final head it will prune the blocks outside of
+// the retention window, if configured, and sleep for one second before trying
+// to copy data at the next height.
 // TODO clean up the interface, currently we need to pass hot store, cold store and
 // cold_db which is redundant.
 fn cold_store_loop(
@@ -361,8 +536,10 @@ fn cold_store_loop(
     cold_db: Arc<ColdDB>,
     genesis_height: BlockHeight,
     epoch_manager: &EpochManagerHandle,
+    retention: Option<&ColdRetentionConfig>,
 ) {
     tracing::info!(target : "cold_store", "Starting the cold store loop");
+    let clock = Clock::real();
 
     loop {
         if !keep_going.load(std::sync::atomic::Ordering::Relaxed) {
@@ -377,7 +554,10 @@ fn cold_store_loop(
             &cold_db,
             genesis_height,
             epoch_manager,
+            split_storage_config.archived_shards.as_deref(),
             split_storage_config.num_cold_store_read_threads,
+            split_storage_config.num_cold_store_copy_workers,
+            split_storage_config.cold_store_max_write_rate,
         );
         let duration = instant.elapsed();
 
@@ -387,6 +567,28 @@ fn cold_store_loop(
             tracing::debug!(target : "cold_store", "cold_store_copy took {}s", duration.as_secs_f64());
         }
 
+        // Pruning only runs once the cold head caught up with the final head,
+        // so that it never delays copying.  It continues without sleeping while
+        // there are more heights to prune.
+        if let (Ok(ColdStoreCopyResult::NoBlockCopied), Some(retention)) = (&result, retention) {
+            match cold_store_prune(
+                &hot_store,
+                &cold_store,
+                &cold_db,
+                genesis_height,
+                epoch_manager,
+                split_storage_config.archived_shards.as_deref(),
+                retention,
+                &clock,
+            ) {
+                Ok(examined) if examined >= retention.max_pruned_heights => continue,
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::error!(target : "cold_store", error = format!("{err:#?}"), "cold_store_prune failed");
+                }
+            }
+        }
+
         let sleep_duration = split_storage_config.cold_store_loop_sleep_duration;
         match result {
             Err(err) => {
@@ -455,6 +657,7 @@ pub fn spawn_cold_store_loop(
     sanity_check(&hot_store, &cold_store, genesis_height)?;
 
     let split_storage_config = config.config.split_storage.clone().unwrap_or_default();
+    let retention = config.config.cold_store.as_ref().and_then(|config| config.retention.clone());
 
     tracing::info!(target : "cold_store", "Spawning the cold store loop");
     let join_handle =
@@ -475,6 +678,7 @@ pub fn spawn_cold_store_loop(
                 cold_db,
                 genesis_height,
                 epoch_manager.as_ref(),
+                retention.as_ref(),
             )
         })?;
 
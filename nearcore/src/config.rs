@@ -9,23 +9,26 @@ use near_chain_configs::test_utils::{
     TESTING_INIT_BALANCE, TESTING_INIT_STAKE,
 };
 use near_chain_configs::{
+    default_chunk_endorsement_batch_window, default_chunk_validation_max_concurrency_per_shard,
     default_enable_multiline_logging, default_epoch_sync_enabled,
     default_header_sync_expected_height_per_second, default_header_sync_initial_timeout,
-    default_header_sync_progress_timeout, default_header_sync_stall_ban_timeout,
-    default_log_summary_period, default_orphan_state_witness_max_size,
-    default_orphan_state_witness_pool_size, default_produce_chunk_add_transactions_time_limit,
+    default_header_sync_max_peers_per_request, default_header_sync_progress_timeout,
+    default_header_sync_stall_ban_timeout, default_log_summary_period,
+    default_max_state_witnesses_per_producer_per_height, default_orphan_state_witness_max_size,
+    default_orphan_state_witness_pool_size, default_orphan_state_witness_pool_size_per_shard,
+    default_produce_chunk_add_transactions_time_limit, default_shadow_witnesses_retention_blocks,
     default_state_sync, default_state_sync_enabled, default_state_sync_timeout,
     default_sync_check_period, default_sync_height_threshold, default_sync_step_period,
     default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
     default_tx_routing_height_horizon, default_view_client_threads,
     default_view_client_throttle_period, get_initial_supply, ChunkDistributionNetworkConfig,
     ClientConfig, GCConfig, Genesis, GenesisConfig, GenesisValidationMode, LogSummaryStyle,
-    MutableConfigValue, ReshardingConfig, StateSyncConfig, BLOCK_PRODUCER_KICKOUT_THRESHOLD,
-    CHUNK_PRODUCER_KICKOUT_THRESHOLD, EXPECTED_EPOCH_LENGTH, FISHERMEN_THRESHOLD,
-    GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME, INITIAL_GAS_LIMIT, MAX_INFLATION_RATE,
-    MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE, NUM_BLOCKS_PER_YEAR,
-    NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE, PROTOCOL_UPGRADE_STAKE_THRESHOLD,
-    TRANSACTION_VALIDITY_PERIOD,
+    MutableConfigValue, ReshardingConfig, StateSyncConfig, WitnessCompressionConfig,
+    WitnessSizeBudgetConfig, BLOCK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_PRODUCER_KICKOUT_THRESHOLD,
+    EXPECTED_EPOCH_LENGTH, FISHERMEN_THRESHOLD, GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME,
+    INITIAL_GAS_LIMIT, MAX_INFLATION_RATE, MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE,
+    NUM_BLOCKS_PER_YEAR, NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE,
+    PROTOCOL_UPGRADE_STAKE_THRESHOLD, TRANSACTION_VALIDITY_PERIOD,
 };
 use near_config_utils::{ValidationError, ValidationErrors};
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey, Signer};
@@ -35,8 +38,10 @@ use near_jsonrpc::RpcConfig;
 use near_network::config::NetworkConfig;
 use near_network::tcp;
 use near_o11y::log_config::LogConfig;
+use near_parameters::vm::VMKind;
+use near_parameters::RuntimeConfigStore;
 use near_primitives::hash::CryptoHash;
-use near_primitives::shard_layout::ShardLayout;
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::types::{
     AccountId, AccountInfo, Balance, BlockHeight, BlockHeightDelta, Gas, NumSeats, NumShards,
@@ -158,6 +163,9 @@ pub struct Consensus {
     /// Expected increase of header head weight per second during header sync
     #[serde(default = "default_header_sync_expected_height_per_second")]
     pub header_sync_expected_height_per_second: u64,
+    /// Maximum number of peers to request the same batch of headers from during header sync
+    #[serde(default = "default_header_sync_max_peers_per_request")]
+    pub header_sync_max_peers_per_request: usize,
     /// How frequently we check whether we need to sync
     #[serde(default = "default_sync_check_period")]
     #[serde(with = "near_async::time::serde_duration_as_std")]
@@ -195,6 +203,7 @@ impl Default for Consensus {
             state_sync_timeout: default_state_sync_timeout(),
             header_sync_expected_height_per_second: default_header_sync_expected_height_per_second(
             ),
+            header_sync_max_peers_per_request: default_header_sync_max_peers_per_request(),
             sync_check_period: default_sync_check_period(),
             sync_step_period: default_sync_step_period(),
             doomslug_step_period: default_doomslug_step_period(),
@@ -258,6 +267,13 @@ pub struct Config {
     /// Configuration for the split storage.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub split_storage: Option<SplitStorageConfig>,
+    /// If set, the node serves read-only access to its store over gRPC on a
+    /// Unix socket at this path, so that auxiliary processes such as indexers can read the
+    /// columns without opening the database.  A relative path is relative to
+    /// the home directory.  With split storage, reads are served from both the
+    /// hot and the cold database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_server_socket: Option<PathBuf>,
     /// The node will stop after the head exceeds this height.
     /// The node usually stops within several seconds after reaching the target height.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -299,22 +315,72 @@ pub struct Config {
     /// because the previous block isn't available. The witnesses wait in the pool untl the
     /// required block appears. This variable controls how many witnesses can be stored in the pool.
     pub orphan_state_witness_pool_size: usize,
+    /// Maximum number of witnesses of a single shard in the OrphanStateWitnessPool.
+    /// When a shard is full, the witness with the highest height is dropped.
+    pub orphan_state_witness_pool_size_per_shard: usize,
     /// Maximum size (number of bytes) of state witnesses in the OrphanStateWitnessPool.
     ///
     /// We keep only orphan witnesses which are smaller than this size.
     /// This limits the maximum memory usage of OrphanStateWitnessPool.
     /// TODO(#10259) - consider merging this limit with the non-orphan witness size limit.
     pub orphan_state_witness_max_size: ByteSize,
+    /// Maximum number of state witnesses of a single shard which are validated at the same time.
+    /// Further witnesses of the shard wait in a queue, and the ones with the lowest height are
+    /// validated first.
+    pub chunk_validation_max_concurrency_per_shard: usize,
+    /// Maximum number of state witnesses a single chunk producer can send for the same shard
+    /// and height. Further witnesses are dropped, and for a while afterwards only the first
+    /// witness of the chunk producer per shard and height is accepted.
+    pub max_state_witnesses_per_producer_per_height: usize,
+    /// Chunk endorsements received within this window are verified together with a single
+    /// batched signature verification, which is cheaper than verifying them one by one.
+    /// Zero disables batching.
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub chunk_endorsement_batch_window: Duration,
     /// The number of the contracts kept loaded up for execution.
     ///
     /// Each loaded contract will increase the baseline memory use of the node appreciably.
     pub max_loaded_contracts: usize,
+    /// The maximum total size of the compiled contracts kept on disk.
+    ///
+    /// The least recently used contracts are removed once the cache grows past this size, and are
+    /// compiled again the next time they are called.
+    pub max_compiled_contracts_size: ByteSize,
     /// Save observed instances of ChunkStateWitness to the database in DBCol::LatestChunkStateWitnesses.
     /// Saving the latest witnesses is useful for analysis and debugging.
     /// When this option is enabled, the node will save ALL witnesses it oberves, even invalid ones,
     /// which can cause extra load on the database. This option is not recommended for production use,
     /// as a large number of incoming witnesses could cause denial of service.
     pub save_latest_witnesses: bool,
+    /// Produce a state witness for every chunk in every processed block and validate it,
+    /// to test stateless validation without being a chunk validator. Can be toggled on a
+    /// running node.
+    pub shadow_chunk_validation: bool,
+    /// Shadow validate only the chunks of these shards, e.g. `[2, 3]`. Chunks of all shards
+    /// are validated if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow_validation_shards: Option<Vec<ShardId>>,
+    /// Save the ChunkStateWitnesses produced by shadow validation to the database in
+    /// DBCol::ShadowStateWitness, one per chunk, for offline replay and size analysis.
+    /// Only has an effect when `shadow_chunk_validation` is enabled.
+    pub save_shadow_witnesses: bool,
+    /// Number of blocks for which the shadow witnesses are kept. Witnesses of chunks created
+    /// more than this many blocks before the latest saved one are deleted.
+    pub shadow_witnesses_retention_blocks: BlockHeightDelta,
+    /// Compression of the state witnesses produced and validated by this node.
+    pub witness_compression: WitnessCompressionConfig,
+    /// Budget for the uncompressed size of the state witnesses of the chunks produced by this
+    /// node. Transactions are left out of a chunk once its witness reaches the soft limit, and
+    /// a transaction which would grow the witness past the hard limit is never included.
+    pub witness_size_budget: WitnessSizeBudgetConfig,
+    /// Overrides the VM used to run contracts, regardless of the protocol version.
+    ///
+    /// The VMs are expected to produce identical outcomes, but only the one selected by the
+    /// protocol is battle-tested. This option is meant for running alternative backends such as
+    /// wasmtime on real traffic from RPC and archival nodes. It is refused on validators, since
+    /// validators disagreeing on the VM is a consensus risk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vm_kind: Option<VMKind>,
 }
 
 fn is_false(value: &bool) -> bool {
@@ -350,6 +416,7 @@ impl Default for Config {
             store: near_store::StoreConfig::default(),
             cold_store: None,
             split_storage: None,
+            store_server_socket: None,
             expected_shutdown: None,
             state_sync: default_state_sync(),
             state_sync_enabled: default_state_sync_enabled(),
@@ -361,9 +428,24 @@ impl Default for Config {
                 default_produce_chunk_add_transactions_time_limit(),
             chunk_distribution_network: None,
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
+            orphan_state_witness_pool_size_per_shard:
+                default_orphan_state_witness_pool_size_per_shard(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
+            chunk_validation_max_concurrency_per_shard:
+                default_chunk_validation_max_concurrency_per_shard(),
+            max_state_witnesses_per_producer_per_height:
+                default_max_state_witnesses_per_producer_per_height(),
+            chunk_endorsement_batch_window: default_chunk_endorsement_batch_window(),
             max_loaded_contracts: 256,
+            max_compiled_contracts_size: ByteSize::gib(32),
             save_latest_witnesses: false,
+            shadow_chunk_validation: false,
+            shadow_validation_shards: None,
+            save_shadow_witnesses: false,
+            shadow_witnesses_retention_blocks: default_shadow_witnesses_retention_blocks(),
+            witness_compression: WitnessCompressionConfig::default(),
+            witness_size_budget: WitnessSizeBudgetConfig::default(),
+            vm_kind: None,
         }
     }
 }
@@ -384,6 +466,10 @@ fn default_num_cold_store_read_threads() -> usize {
     4
 }
 
+fn default_num_cold_store_copy_workers() -> usize {
+    1
+}
+
 fn default_cold_store_loop_sleep_duration() -> Duration {
     Duration::seconds(1)
 }
@@ -405,6 +491,29 @@ pub struct SplitStorageConfig {
 
     #[serde(default = "default_num_cold_store_read_threads")]
     pub num_cold_store_read_threads: usize,
+
+    /// Number of heights the cold store loop copies in parallel while the cold
+    /// head is behind the final head, e.g. when catching up after downtime.
+    /// Every height is copied with `num_cold_store_read_threads` threads.
+    #[serde(default = "default_num_cold_store_copy_workers")]
+    pub num_cold_store_copy_workers: usize,
+
+    /// If set, bounds the average number of bytes per second the cold store
+    /// loop writes to the cold storage, so that catching up doesn't starve the
+    /// node of disk bandwidth.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cold_store_max_write_rate: Option<ByteSize>,
+
+    /// If set, the cold store loop copies the chunks, state and other shard
+    /// data only for these shards, while the blocks are copied in full.  The
+    /// history of the other shards is unavailable once garbage collected from
+    /// the hot storage.  All the shards are archived by default.
+    ///
+    /// The shards are given as shard uids, e.g. `"s0.v3"`, since the same
+    /// shard id refers to different accounts in different shard layouts.
+    /// The shards created by a resharding are only archived once listed here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_shards: Option<Vec<ShardUId>>,
 }
 
 impl Default for SplitStorageConfig {
@@ -417,6 +526,9 @@ impl Default for SplitStorageConfig {
                 default_cold_store_initial_migration_loop_sleep_duration(),
             cold_store_loop_sleep_duration: default_cold_store_loop_sleep_duration(),
             num_cold_store_read_threads: default_num_cold_store_read_threads(),
+            num_cold_store_copy_workers: default_num_cold_store_copy_workers(),
+            cold_store_max_write_rate: None,
+            archived_shards: None,
         }
     }
 }
@@ -513,6 +625,10 @@ impl NearConfig {
         network_key_pair: KeyFile,
         validator_signer: Option<Arc<dyn ValidatorSigner>>,
     ) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            config.vm_kind.is_none() || validator_signer.is_none(),
+            "vm_kind cannot be overridden on a validator node"
+        );
         Ok(NearConfig {
             config: config.clone(),
             client_config: ClientConfig {
@@ -537,6 +653,9 @@ impl NearConfig {
                 header_sync_expected_height_per_second: config
                     .consensus
                     .header_sync_expected_height_per_second,
+                header_sync_max_peers_per_request: config
+                    .consensus
+                    .header_sync_max_peers_per_request,
                 state_sync_timeout: config.consensus.state_sync_timeout,
                 min_num_peers: config.consensus.min_num_peers,
                 log_summary_period: config.log_summary_period,
@@ -554,6 +673,10 @@ impl NearConfig {
                 tracked_shards: config.tracked_shards,
                 tracked_shard_schedule: config.tracked_shard_schedule.unwrap_or(vec![]),
                 archive: config.archive,
+                archived_shards: config
+                    .split_storage
+                    .as_ref()
+                    .and_then(|split_storage| split_storage.archived_shards.clone()),
                 save_trie_changes: config.save_trie_changes.unwrap_or(!config.archive),
                 log_summary_style: config.log_summary_style,
                 gc: config.gc,
@@ -581,8 +704,24 @@ impl NearConfig {
                 ),
                 chunk_distribution_network: config.chunk_distribution_network,
                 orphan_state_witness_pool_size: config.orphan_state_witness_pool_size,
+                orphan_state_witness_pool_size_per_shard: config
+                    .orphan_state_witness_pool_size_per_shard,
                 orphan_state_witness_max_size: config.orphan_state_witness_max_size,
+                chunk_validation_max_concurrency_per_shard: config
+                    .chunk_validation_max_concurrency_per_shard,
+                max_state_witnesses_per_producer_per_height: config
+                    .max_state_witnesses_per_producer_per_height,
+                chunk_endorsement_batch_window: config.chunk_endorsement_batch_window,
                 save_latest_witnesses: config.save_latest_witnesses,
+                shadow_chunk_validation: MutableConfigValue::new(
+                    config.shadow_chunk_validation,
+                    "shadow_chunk_validation",
+                ),
+                shadow_validation_shards: config.shadow_validation_shards,
+                save_shadow_witnesses: config.save_shadow_witnesses,
+                shadow_witnesses_retention_blocks: config.shadow_witnesses_retention_blocks,
+                witness_compression: config.witness_compression,
+                witness_size_budget: config.witness_size_budget,
             },
             network_config: NetworkConfig::new(
                 config.network,
@@ -649,25 +788,32 @@ impl NightshadeRuntime {
         if config.config.store.state_snapshot_enabled {
             state_snapshot_type = StateSnapshotType::EveryEpoch;
         }
+        let hot_store_path =
+            config.config.store.path.clone().unwrap_or_else(|| PathBuf::from("data"));
+        let mut trie_config = TrieConfig::from_store_config(&config.config.store);
+        if config.config.store.persist_mem_tries {
+            trie_config.mem_tries_dump_dir = Some(home_dir.join(&hot_store_path).join("mem_tries"));
+        }
         let state_snapshot_config = StateSnapshotConfig {
             state_snapshot_type,
             home_dir: home_dir.to_path_buf(),
-            hot_store_path: config
-                .config
-                .store
-                .path
-                .clone()
-                .unwrap_or_else(|| PathBuf::from("data")),
+            hot_store_path,
             state_snapshot_subdir: PathBuf::from("state_snapshot"),
+            incremental: config.config.store.state_snapshot_config.incremental,
         };
         // FIXME: this (and other contract runtime resources) should probably get constructed by
         // the caller and passed into this `NightshadeRuntime::from_config` here. But that's a big
         // refactor...
-        let contract_cache = FilesystemContractRuntimeCache::with_memory_cache(
+        let contract_cache = FilesystemContractRuntimeCache::with_limits(
             home_dir,
             config.config.store.path.as_ref(),
             config.config.max_loaded_contracts,
+            Some(config.config.max_compiled_contracts_size.as_u64()),
         )?;
+        let runtime_config_store = config.config.vm_kind.map(|vm_kind| {
+            warn!(target: "neard", ?vm_kind, "overriding the VM used to run contracts");
+            RuntimeConfigStore::for_chain_id(&config.genesis.config.chain_id).with_vm_kind(vm_kind)
+        });
         Ok(NightshadeRuntime::new(
             store,
             ContractRuntimeCache::handle(&contract_cache),
@@ -675,10 +821,11 @@ impl NightshadeRuntime {
             epoch_manager,
             config.client_config.trie_viewer_state_size_limit,
             config.client_config.max_gas_burnt_view,
-            None,
+            runtime_config_store,
             config.config.gc.gc_num_epochs_to_keep(),
-            TrieConfig::from_store_config(&config.config.store),
+            trie_config,
             state_snapshot_config,
+            config.client_config.shadow_chunk_validation.clone(),
         ))
     }
 }
@@ -1226,7 +1373,10 @@ pub fn load_config(
     let mut validation_errors = ValidationErrors::new();
 
     // if config.json has file issues, the program will directly panic
-    let config = Config::from_file_skip_validation(&dir.join(CONFIG_FILENAME))?;
+    let mut config = Config::from_file_skip_validation(&dir.join(CONFIG_FILENAME))?;
+    if let Some(dictionary_path) = &mut config.witness_compression.dictionary_path {
+        *dictionary_path = dir.join(&dictionary_path);
+    }
     // do config.json validation later so that genesis_file, validator_file and genesis_file can be validated before program panic
     if let Err(e) = config.validate() {
         validation_errors.push_errors(e)
@@ -1249,6 +1399,16 @@ pub fn load_config(
         None
     };
 
+    if validator_signer.is_some() && config.vm_kind.is_some() {
+        // Validators running different VMs could disagree on the outcome of a chunk, so the
+        // override is only allowed on nodes which don't validate.
+        let error_message = format!(
+            "vm_kind is set in config.json, but {} holds a validator key. The VM can only be overridden on non-validator nodes",
+            validator_file.display()
+        );
+        validation_errors.push_cross_file_semantics_error(error_message);
+    }
+
     let node_key_path = dir.join(&config.node_key_file);
     let network_signer_result = NodeKeyFile::from_file(&node_key_path);
     let network_signer = match network_signer_result {
@@ -1338,16 +1498,20 @@ mod tests {
     use std::io::Write;
     use std::path::Path;
     use std::str::FromStr;
+    use std::sync::Arc;
 
     use near_async::time::Duration;
     use near_chain_configs::{GCConfig, Genesis, GenesisValidationMode};
     use near_crypto::InMemorySigner;
+    use near_parameters::vm::VMKind;
     use near_primitives::shard_layout::account_id_to_shard_id;
     use near_primitives::types::{AccountId, NumShards};
+    use near_primitives::validator_signer::ValidatorSigner;
     use tempfile::tempdir;
 
     use crate::config::{
-        create_testnet_configs, generate_or_load_key, init_configs, Config, CONFIG_FILENAME,
+        create_testnet_configs, generate_or_load_key, init_configs, Config, NearConfig,
+        CONFIG_FILENAME,
     };
 
     #[test]
@@ -1572,6 +1736,27 @@ mod tests {
         assert_eq!(genesis.config.shard_layout.shard_ids().count() as NumShards, num_shards);
     }
 
+    #[test]
+    fn test_vm_kind_override_refused_on_validators() {
+        let (mut configs, validator_signers, network_signers, genesis, _shard_keys) =
+            create_testnet_configs(1, 1, 0, "node", true, false, vec![]);
+        let mut config = configs.remove(0);
+        config.vm_kind = Some(VMKind::Wasmtime);
+
+        let validator_signer = Arc::new(validator_signers[0].clone()) as Arc<dyn ValidatorSigner>;
+        let result = NearConfig::new(
+            config.clone(),
+            genesis.clone(),
+            network_signers[0].clone().into(),
+            Some(validator_signer),
+        );
+        assert!(result.is_err());
+
+        let near_config =
+            NearConfig::new(config, genesis, network_signers[0].clone().into(), None).unwrap();
+        assert_eq!(near_config.config.vm_kind, Some(VMKind::Wasmtime));
+    }
+
     #[test]
     fn test_generate_or_load_key() {
         let tmp = tempfile::tempdir().unwrap();
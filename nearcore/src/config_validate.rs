@@ -1,7 +1,10 @@
 use near_chain_configs::{ExternalStorageLocation, SyncConfig};
 use near_config_utils::{ValidationError, ValidationErrors};
+use near_store::{DBCol, StoreBackend};
+use near_vm_runner::internal::VMKindExt;
 use std::collections::HashSet;
 use std::path::Path;
+use strum::IntoEnumIterator;
 
 use crate::config::Config;
 
@@ -43,6 +46,25 @@ impl<'a> ConfigValidator<'a> {
             self.validation_errors.push_config_semantics_error(error_message);
         }
 
+        let archived_shards =
+            self.config.split_storage.as_ref().and_then(|config| config.archived_shards.as_ref());
+        if archived_shards.map_or(false, |shards| shards.is_empty()) {
+            let error_message = "split_storage.archived_shards is empty, no shard would be archived. Remove it to archive all the shards.".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
+        if let Some(split_storage) = &self.config.split_storage {
+            if split_storage.num_cold_store_copy_workers == 0 {
+                let error_message =
+                    "split_storage.num_cold_store_copy_workers should be at least 1.".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if split_storage.cold_store_max_write_rate.map_or(false, |rate| rate.as_u64() == 0) {
+                let error_message = "split_storage.cold_store_max_write_rate is zero, nothing would be copied to the cold storage.".to_string();
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
         if self.config.consensus.min_block_production_delay
             > self.config.consensus.max_block_production_delay
         {
@@ -65,12 +87,26 @@ impl<'a> ConfigValidator<'a> {
             self.validation_errors.push_config_semantics_error(error_message);
         }
 
+        if self.config.consensus.header_sync_max_peers_per_request == 0 {
+            let error_message =
+                "consensus.header_sync_max_peers_per_request should not be 0".to_string();
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
         if self.config.consensus.header_sync_expected_height_per_second == 0 {
             let error_message =
                 "consensus.header_sync_expected_height_per_second should not be 0".to_string();
             self.validation_errors.push_config_semantics_error(error_message);
         }
 
+        if let Some(vm_kind) = self.config.vm_kind {
+            if !vm_kind.is_available() {
+                let error_message =
+                    format!("vm_kind: {vm_kind:?} has not been enabled at compile time");
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
         if self.config.gc.gc_blocks_limit == 0
             || self.config.gc.gc_fork_clean_step == 0
             || self.config.gc.gc_num_epochs_to_keep == 0
@@ -108,6 +144,30 @@ impl<'a> ConfigValidator<'a> {
                             self.validation_errors.push_config_semantics_error(error_message);
                         }
                     }
+                    ExternalStorageLocation::Azure { account, container } => {
+                        if account.is_empty() || container.is_empty() {
+                            let error_message = format!("'config.state_sync.dump.location.Azure.account' and 'config.state_sync.dump.location.Azure.container' need to be specified when 'config.state_sync.dump.location.Azure' is present.");
+                            self.validation_errors.push_config_semantics_error(error_message);
+                        }
+                    }
+                    ExternalStorageLocation::Http { .. } => {
+                        let error_message = format!("'config.state_sync.dump.location.Http' can only be used to sync state, use 'config.state_sync.dump.location.Filesystem' with 'config.state_sync.dump.http_server_addr' to serve the dump over HTTP.");
+                        self.validation_errors.push_config_semantics_error(error_message);
+                    }
+                }
+
+                if dump_config.http_server_addr.is_some()
+                    && !matches!(dump_config.location, ExternalStorageLocation::Filesystem { .. })
+                {
+                    let error_message = format!("'config.state_sync.dump.http_server_addr' requires 'config.state_sync.dump.location.Filesystem'.");
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+
+                if dump_config.max_bytes_per_second == Some(0) {
+                    let error_message = format!(
+                        "'config.state_sync.dump.max_bytes_per_second' needs to be greater than 0."
+                    );
+                    self.validation_errors.push_config_semantics_error(error_message);
                 }
 
                 if let Some(credentials_file) = &dump_config.credentials_file {
@@ -139,6 +199,18 @@ impl<'a> ConfigValidator<'a> {
                                 self.validation_errors.push_config_semantics_error(error_message);
                             }
                         }
+                        ExternalStorageLocation::Azure { account, container } => {
+                            if account.is_empty() || container.is_empty() {
+                                let error_message = format!("'config.state_sync.sync.ExternalStorage.location.Azure.account' and 'config.state_sync.sync.ExternalStorage.location.Azure.container' need to be specified when 'config.state_sync.sync.ExternalStorage.location.Azure' is present.");
+                                self.validation_errors.push_config_semantics_error(error_message);
+                            }
+                        }
+                        ExternalStorageLocation::Http { url } => {
+                            if url.is_empty() {
+                                let error_message = format!("'config.state_sync.sync.ExternalStorage.location.Http.url' needs to be specified when 'config.state_sync.sync.ExternalStorage.location.Http' is present.");
+                                self.validation_errors.push_config_semantics_error(error_message);
+                            }
+                        }
                     }
                     if config.num_concurrent_requests == 0 {
                         let error_message = format!("'config.state_sync.sync.ExternalStorage.num_concurrent_requests' needs to be greater than 0");
@@ -146,6 +218,135 @@ impl<'a> ConfigValidator<'a> {
                     }
                 }
             }
+            if state_sync.serve_historical_state && !self.config.archive {
+                let error_message = format!("'config.state_sync.serve_historical_state' can only be enabled on archival nodes, because other nodes don't keep the state of old epochs.");
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if state_sync.target_epoch_id.is_some() && !self.config.state_sync_enabled {
+                let error_message = format!("'config.state_sync.target_epoch_id' requires 'config.state_sync_enabled' to be true.");
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+        }
+
+        for (name, store_config) in std::iter::once(("store", &self.config.store))
+            .chain(self.config.cold_store.iter().map(|config| ("cold_store", config)))
+        {
+            let columns = store_config
+                .col_cache_sizes
+                .keys()
+                .map(|column| ("col_cache_sizes", column))
+                .chain(
+                    store_config.col_compression.keys().map(|column| ("col_compression", column)),
+                );
+            for (field, column) in columns {
+                if !DBCol::iter().any(|col| <&str>::from(col) == column.as_str()) {
+                    let error_message =
+                        format!("'config.{name}.{field}' contains unknown column {column}.");
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+            }
+            if store_config.backend == StoreBackend::ObjectStorage {
+                if name != "cold_store" {
+                    let error_message = format!(
+                        "'config.{name}.backend' can be object_storage only for the cold store."
+                    );
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+                if store_config.object_storage.is_none() {
+                    let error_message = format!("'config.{name}.backend' is object_storage, but 'config.{name}.object_storage' is not set.");
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+            }
+            if store_config.read_cache_size.is_some() && name != "cold_store" {
+                let error_message =
+                    format!("'config.{name}.read_cache_size' can be set only for the cold store.");
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if let Some(retention) = &store_config.retention {
+                if name != "cold_store" {
+                    let error_message =
+                        format!("'config.{name}.retention' can be set only for the cold store.");
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+                if retention.blocks.is_none() && retention.duration.is_none() {
+                    let error_message = format!(
+                        "'config.{name}.retention' needs 'blocks' or 'duration' to be set."
+                    );
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+                if retention.max_pruned_heights == 0 {
+                    let error_message = format!(
+                        "'config.{name}.retention.max_pruned_heights' needs to be at least 1."
+                    );
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+            }
+            if store_config.load_mem_tries_parallelism == 0 {
+                let error_message =
+                    format!("'config.{name}.load_mem_tries_parallelism' needs to be at least 1.");
+                self.validation_errors.push_config_semantics_error(error_message);
+            }
+            if let Some(epochs) = store_config.flat_state_history_epochs {
+                if name == "cold_store" {
+                    let error_message = format!(
+                        "'config.{name}.flat_state_history_epochs' cannot be set for the cold store."
+                    );
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+                if epochs == 0 {
+                    let error_message = format!(
+                        "'config.{name}.flat_state_history_epochs' needs to be at least 1."
+                    );
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+            }
+            let deltas_config = &store_config.flat_storage_deltas;
+            let policies = std::iter::once(("default".to_owned(), &deltas_config.default)).chain(
+                deltas_config
+                    .per_shard
+                    .iter()
+                    .map(|(shard_uid, policy)| (format!("per_shard.{shard_uid}"), policy)),
+            );
+            for (policy_name, policy) in policies {
+                if policy.flat_head_gap < 2 {
+                    let error_message = format!(
+                        "'config.{name}.flat_storage_deltas.{policy_name}.flat_head_gap' needs to be at least 2."
+                    );
+                    self.validation_errors.push_config_semantics_error(error_message);
+                }
+            }
+        }
+
+        let witness_compression = &self.config.witness_compression;
+        if witness_compression.compress_with_dictionary
+            && witness_compression.dictionary_path.is_none()
+        {
+            let error_message = format!("'config.witness_compression.compress_with_dictionary' requires 'config.witness_compression.dictionary_path' to be set.");
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
+        let witness_size_budget = &self.config.witness_size_budget;
+        if witness_size_budget.soft_limit > witness_size_budget.hard_limit {
+            let error_message = format!("'config.witness_size_budget.soft_limit' must not be greater than 'config.witness_size_budget.hard_limit', got {} and {}.", witness_size_budget.soft_limit, witness_size_budget.hard_limit);
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+        if witness_size_budget.transactions_limiting_threshold > witness_size_budget.soft_limit {
+            let error_message = format!("'config.witness_size_budget.transactions_limiting_threshold' must not be greater than 'config.witness_size_budget.soft_limit', got {} and {}.", witness_size_budget.transactions_limiting_threshold, witness_size_budget.soft_limit);
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
+        if self.config.chunk_validation_max_concurrency_per_shard == 0 {
+            let error_message = format!(
+                "'config.chunk_validation_max_concurrency_per_shard' needs to be at least 1."
+            );
+            self.validation_errors.push_config_semantics_error(error_message);
+        }
+
+        if self.config.max_state_witnesses_per_producer_per_height == 0 {
+            let error_message = format!(
+                "'config.max_state_witnesses_per_producer_per_height' needs to be at least 1."
+            );
+            self.validation_errors.push_config_semantics_error(error_message);
         }
 
         let tx_routing_height_horizon = self.config.tx_routing_height_horizon;
@@ -231,6 +432,43 @@ mod tests {
         validate_config(&config).unwrap();
     }
 
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: split_storage.archived_shards is empty, no shard would be archived. Remove it to archive all the shards."
+    )]
+    fn test_empty_archived_shards() {
+        let mut config = Config::default();
+        config.split_storage = Some(crate::config::SplitStorageConfig {
+            archived_shards: Some(vec![]),
+            ..Default::default()
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: split_storage.num_cold_store_copy_workers should be at least 1."
+    )]
+    fn test_no_cold_store_copy_workers() {
+        let mut config = Config::default();
+        config.split_storage = Some(crate::config::SplitStorageConfig {
+            num_cold_store_copy_workers: 0,
+            ..Default::default()
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.store.col_cache_sizes' contains unknown column Headers."
+    )]
+    fn test_col_cache_sizes_unknown_column() {
+        let mut config = Config::default();
+        config.store.col_cache_sizes.insert("BlockHeader".to_string(), bytesize::ByteSize::mib(64));
+        config.store.col_cache_sizes.insert("Headers".to_string(), bytesize::ByteSize::mib(64));
+        validate_config(&config).unwrap();
+    }
+
     #[test]
     #[should_panic(
         expected = "\\nconfig.json semantic issue: 'config.tx_routing_height_horizon' needs to be at least 2, got 1."
@@ -250,4 +488,130 @@ mod tests {
         config.tx_routing_height_horizon = 1_000_000_000;
         validate_config(&config).unwrap();
     }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.state_sync.serve_historical_state' can only be enabled on archival nodes"
+    )]
+    fn test_serve_historical_state_without_archive() {
+        let mut config = Config::default();
+        config.archive = false;
+        config.state_sync = Some(near_chain_configs::StateSyncConfig {
+            serve_historical_state: true,
+            ..Default::default()
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.store.backend' can be object_storage only for the cold store."
+    )]
+    fn test_object_storage_backend_for_hot_store() {
+        let mut config = Config::default();
+        config.store.backend = near_store::StoreBackend::ObjectStorage;
+        config.store.object_storage = Some(near_store::ObjectStorageConfig {
+            bucket: "bucket".to_string(),
+            region: "us-west-1".to_string(),
+            endpoint: None,
+            prefix: String::new(),
+            cache_size: bytesize::ByteSize::gib(1),
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.cold_store.retention' needs 'blocks' or 'duration' to be set."
+    )]
+    fn test_cold_store_retention_without_window() {
+        let mut config = Config::default();
+        config.save_trie_changes = Some(true);
+        config.cold_store = Some(near_store::StoreConfig {
+            retention: Some(near_store::ColdRetentionConfig {
+                blocks: None,
+                duration: None,
+                max_pruned_heights: 100,
+            }),
+            ..config.store.clone()
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.store.flat_state_history_epochs' needs to be at least 1."
+    )]
+    fn test_zero_flat_state_history_epochs() {
+        let mut config = Config::default();
+        config.store.flat_state_history_epochs = Some(0);
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.store.flat_storage_deltas.per_shard.s3.v1.flat_head_gap' needs to be at least 2."
+    )]
+    fn test_small_flat_storage_deltas_flat_head_gap() {
+        let mut config = Config::default();
+        config.store.flat_storage_deltas.per_shard.insert(
+            near_store::ShardUId { version: 1, shard_id: 3 },
+            near_store::FlatStorageDeltasPolicy { flat_head_gap: 1, compaction_threshold: 10 },
+        );
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.store.load_mem_tries_parallelism' needs to be at least 1."
+    )]
+    fn test_zero_load_mem_tries_parallelism() {
+        let mut config = Config::default();
+        config.store.load_mem_tries_parallelism = 0;
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.state_sync.dump.max_bytes_per_second' needs to be greater than 0."
+    )]
+    fn test_zero_state_sync_dump_max_bytes_per_second() {
+        let mut config = Config::default();
+        config.state_sync = Some(near_chain_configs::StateSyncConfig {
+            dump: Some(near_chain_configs::DumpConfig {
+                location: ExternalStorageLocation::Filesystem { root_dir: "/tmp/dump".into() },
+                restart_dump_for_shards: None,
+                iteration_delay: None,
+                credentials_file: None,
+                compression: None,
+                parallelism: None,
+                max_bytes_per_second: Some(0),
+                http_server_addr: None,
+            }),
+            ..Default::default()
+        });
+        validate_config(&config).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "\\nconfig.json semantic issue: 'config.state_sync.dump.http_server_addr' requires 'config.state_sync.dump.location.Filesystem'."
+    )]
+    fn test_state_sync_dump_http_server_without_filesystem() {
+        let mut config = Config::default();
+        config.state_sync = Some(near_chain_configs::StateSyncConfig {
+            dump: Some(near_chain_configs::DumpConfig {
+                location: ExternalStorageLocation::GCS { bucket: "state-parts".to_string() },
+                restart_dump_for_shards: None,
+                iteration_delay: None,
+                credentials_file: None,
+                compression: None,
+                parallelism: None,
+                max_bytes_per_second: None,
+                http_server_addr: Some("0.0.0.0:3040".parse().unwrap()),
+            }),
+            ..Default::default()
+        });
+        validate_config(&config).unwrap();
+    }
 }
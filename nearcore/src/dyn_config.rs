@@ -49,6 +49,7 @@ pub fn get_updateable_client_config(config: Config) -> UpdateableClientConfig {
         expected_shutdown: config.expected_shutdown,
         resharding_config: config.resharding_config,
         produce_chunk_add_transactions_time_limit: config.produce_chunk_add_transactions_time_limit,
+        shadow_chunk_validation: config.shadow_chunk_validation,
     }
 }
 
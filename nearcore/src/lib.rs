@@ -20,7 +20,7 @@ use near_chain::state_snapshot_actor::{
 use near_chain::types::RuntimeAdapter;
 use near_chain::{Chain, ChainGenesis};
 use near_chain_configs::ReshardingHandle;
-use near_chain_configs::SyncConfig;
+use near_chain_configs::{DumpConfig, ExternalStorageLocation, SyncConfig};
 use near_chunks::shards_manager_actor::start_shards_manager;
 use near_client::adapter::client_sender_for_network;
 use near_client::gc_actor::GCActor;
@@ -39,7 +39,7 @@ use near_store::flat::FlatStateValuesInliningMigrationHandle;
 use near_store::genesis::initialize_sharded_genesis_state;
 use near_store::metadata::DbKind;
 use near_store::metrics::spawn_db_metrics_loop;
-use near_store::{DBCol, Mode, NodeStorage, Store, StoreOpenerError};
+use near_store::{DBCol, Mode, NodeStorage, ShardTries, Store, StoreOpenerError};
 use near_telemetry::TelemetryActor;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
@@ -59,6 +59,7 @@ mod entity_debug;
 mod entity_debug_serializer;
 mod metrics;
 pub mod migrations;
+mod state_parts_server;
 pub mod state_sync;
 pub mod test_utils;
 
@@ -219,6 +220,9 @@ pub struct NearNode {
     /// The cold_store_loop_handle will only be set if the cold store is configured.
     /// It's a handle to a background thread that copies data from the hot store to the cold store.
     pub cold_store_loop_handle: Option<ColdStoreLoopHandle>,
+    /// Serves read-only access to the store if `store_server_socket` is configured.
+    #[cfg(unix)]
+    pub store_server: Option<near_store::remote::StoreServer>,
     /// Contains handles to background threads that may be dumping state to S3.
     pub state_sync_dumper: StateSyncDumper,
     /// A handle to control background flat state values inlining migration.
@@ -227,6 +231,9 @@ pub struct NearNode {
     // A handle that allows the main process to interrupt resharding if needed.
     // This typically happens when the main process is interrupted.
     pub resharding_handle: ReshardingHandle,
+    /// Tries of the node, to save the mem tries once the node is stopped if
+    /// `store.persist_mem_tries` is enabled.
+    pub shard_tries: ShardTries,
 }
 
 pub fn start_with_config(home_dir: &Path, config: NearConfig) -> anyhow::Result<NearNode> {
@@ -304,6 +311,17 @@ pub fn start_with_config_and_synchronization(
 
     let cold_store_loop_handle = spawn_cold_store_loop(&config, &storage, epoch_manager.clone())?;
 
+    #[cfg(unix)]
+    let store_server = match &config.config.store_server_socket {
+        Some(socket) => {
+            let store = split_store.clone().unwrap_or_else(|| storage.get_hot_store());
+            let server = near_store::remote::StoreServer::start(store, &home_dir.join(socket))
+                .context("failed to start the store server")?;
+            Some(server)
+        }
+        None => None,
+    };
+
     let telemetry = ActixWrapper::new(TelemetryActor::new(config.telemetry_config.clone())).start();
     let chain_genesis = ChainGenesis::new(&config.genesis.config);
     let genesis_block =
@@ -362,6 +380,11 @@ pub fn start_with_config_and_synchronization(
 
     let (partial_witness_actor, partial_witness_arbiter) = if config.validator_signer.is_some() {
         let my_signer = config.validator_signer.clone().unwrap();
+        let witness_compression = config
+            .client_config
+            .witness_compression
+            .load()
+            .context("failed to load the state witness compression dictionary")?;
         let (partial_witness_actor, partial_witness_arbiter) =
             spawn_actix_actor(PartialWitnessActor::new(
                 Clock::real(),
@@ -369,6 +392,7 @@ pub fn start_with_config_and_synchronization(
                 client_adapter_for_partial_witness_actor.as_multi_sender(),
                 my_signer,
                 epoch_manager.clone(),
+                witness_compression,
             ));
         (Some(partial_witness_actor), Some(partial_witness_arbiter))
     } else {
@@ -431,6 +455,7 @@ pub fn start_with_config_and_synchronization(
             config.client_config.client_background_migration_threads,
         );
 
+    let shard_tries = runtime.get_tries();
     let mut state_sync_dumper = StateSyncDumper {
         clock: Clock::real(),
         client_config: config.client_config.clone(),
@@ -447,6 +472,17 @@ pub fn start_with_config_and_synchronization(
     let hot_store = storage.get_hot_store();
 
     let mut rpc_servers = Vec::new();
+    if let Some(DumpConfig {
+        location: ExternalStorageLocation::Filesystem { root_dir },
+        http_server_addr: Some(addr),
+        ..
+    }) = &config.client_config.state_sync.dump
+    {
+        rpc_servers.push((
+            "State parts",
+            state_parts_server::start_state_parts_server(*addr, root_dir.clone())?,
+        ));
+    }
     let network_actor = PeerManagerActor::spawn(
         time::Clock::real(),
         storage.into_inner(near_store::Temperature::Hot),
@@ -520,9 +556,12 @@ pub fn start_with_config_and_synchronization(
         rpc_servers,
         arbiters,
         cold_store_loop_handle,
+        #[cfg(unix)]
+        store_server,
         state_sync_dumper,
         flat_state_migration_handle,
         resharding_handle,
+        shard_tries,
     })
 }
 
@@ -0,0 +1,74 @@
+use actix_web::{web, App, HttpResponse, HttpServer};
+use anyhow::Context;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+
+/// Serves the state dumped to the local filesystem over HTTP, so that other
+/// nodes can sync state from this node with the `Http` external storage
+/// location, without setting up S3-compatible storage.
+///
+/// The files are served read-only at the same paths relative to `root_dir`
+/// as the dump writes them.
+pub(crate) fn start_state_parts_server(
+    addr: SocketAddr,
+    root_dir: PathBuf,
+) -> anyhow::Result<actix_web::dev::ServerHandle> {
+    let root_dir = web::Data::new(root_dir);
+    let server = HttpServer::new(move || {
+        App::new().app_data(root_dir.clone()).route("/{path:.*}", web::get().to(get_file))
+    })
+    .bind(addr)
+    .with_context(|| format!("failed to bind the state parts server to {}", addr))?
+    .workers(1)
+    .shutdown_timeout(5)
+    .disable_signals()
+    .run();
+    tracing::info!(target: "state_sync_dump", %addr, "Serving the dumped state parts over HTTP");
+    let handle = server.handle();
+    tokio::spawn(server);
+    Ok(handle)
+}
+
+async fn get_file(path: web::Path<String>, root_dir: web::Data<PathBuf>) -> HttpResponse {
+    let path = path.into_inner();
+    if !is_served_path(&path) {
+        return HttpResponse::NotFound().finish();
+    }
+    match tokio::fs::read(root_dir.join(&path)).await {
+        Ok(data) => {
+            tracing::debug!(target: "state_sync_dump", %path, num_bytes = data.len(), "Serving a file");
+            HttpResponse::Ok().content_type("application/octet-stream").body(data)
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => HttpResponse::NotFound().finish(),
+        Err(err) => {
+            tracing::warn!(target: "state_sync_dump", %path, ?err, "Failed to read a file");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Only the files inside the root directory are served.
+fn is_served_path(path: &str) -> bool {
+    let path = Path::new(path);
+    path.components().next().is_some()
+        && path.components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_served_path;
+
+    #[test]
+    fn test_is_served_path() {
+        assert!(is_served_path(
+            "chain_id=test/epoch_height=1/epoch_id=abc/shard_id=0/state_part_000000_of_000001"
+        ));
+        assert!(is_served_path(
+            "chain_id=test/epoch_height=1/epoch_id=abc/headers/shard_id=0/header"
+        ));
+        assert!(!is_served_path(""));
+        assert!(!is_served_path("../config.json"));
+        assert!(!is_served_path("chain_id=test/../../node_key.json"));
+        assert!(!is_served_path("/etc/passwd"));
+    }
+}
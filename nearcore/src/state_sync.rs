@@ -9,7 +9,8 @@ use near_chain::types::RuntimeAdapter;
 use near_chain::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode, Error};
 use near_chain_configs::{ClientConfig, ExternalStorageLocation};
 use near_client::sync::external::{
-    create_bucket_readwrite, external_storage_location, StateFileType,
+    compress_state_part, create_azure_connection, create_bucket_readwrite,
+    external_storage_location, StateFileType,
 };
 use near_client::sync::external::{
     external_storage_location_directory, get_part_id_from_filename, is_part_filename,
@@ -20,13 +21,17 @@ use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::hash::CryptoHash;
 use near_primitives::state_part::PartId;
-use near_primitives::state_sync::{StatePartKey, StateSyncDumpProgress};
+use near_primitives::state_sync::{
+    StatePartCompression, StatePartKey, StatePartManifestEntry, StatePartsManifest,
+    StateSyncDumpProgress,
+};
 use near_primitives::types::{AccountId, EpochHeight, EpochId, ShardId, StateRoot};
-use near_store::DBCol;
+use near_store::{DBCol, Store};
 use rand::{thread_rng, Rng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use std::collections::HashSet;
 use std::sync::atomic::AtomicBool;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 pub struct StateSyncDumper {
     pub clock: Clock,
@@ -76,6 +81,11 @@ impl StateSyncDumper {
                     bucket,
                 }
             },
+            ExternalStorageLocation::Azure { account, container } => create_azure_connection(&account, &container, std::time::Duration::from_secs(30), dump_config.credentials_file).expect(
+                "Failed to create a connection to Azure. Please either provide a shared access signature in AZURE_STORAGE_SAS_TOKEN in the environment, or write it to a credentials file and link it in config.json as 'credentials_file'."),
+            ExternalStorageLocation::Http { .. } => {
+                anyhow::bail!("State can't be dumped to the Http location, dump it to the Filesystem location and set 'http_server_addr' to serve it")
+            }
         };
 
         // Determine how many threads to start.
@@ -97,6 +107,16 @@ impl StateSyncDumper {
 
         let chain_id = self.client_config.chain_id.clone();
         let keep_running = Arc::new(AtomicBool::new(true));
+        let parallelism = dump_config.parallelism.unwrap_or(4).max(1);
+        let pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(parallelism)
+                .thread_name(|i| format!("state_part_{}", i))
+                .build()?,
+        );
+        let throttle = dump_config
+            .max_bytes_per_second
+            .map(|max_bytes_per_second| Arc::new(StatePartThrottle::new(max_bytes_per_second)));
         // Start a thread for each shard.
         let handles = shard_ids
             .into_iter()
@@ -125,6 +145,10 @@ impl StateSyncDumper {
                         dump_config.restart_dump_for_shards.clone().unwrap_or_default(),
                         external.clone(),
                         dump_config.iteration_delay.unwrap_or(Duration::seconds(10)),
+                        dump_config.compression.unwrap_or_default(),
+                        parallelism,
+                        pool.clone(),
+                        throttle.clone(),
                         self.account_id.clone(),
                         keep_running.clone(),
                     )
@@ -174,6 +198,47 @@ impl StateSyncDumpHandle {
     }
 }
 
+/// Limits the total size of the state parts generated per second by the dump
+/// loops of all shards.
+struct StatePartThrottle {
+    max_bytes_per_second: u64,
+    /// When the next batch of parts may be generated.
+    ready_at: Mutex<Option<Instant>>,
+}
+
+impl StatePartThrottle {
+    fn new(max_bytes_per_second: u64) -> Self {
+        Self { max_bytes_per_second, ready_at: Mutex::new(None) }
+    }
+
+    /// Waits until the parts generated so far fit in the limit.
+    async fn wait(&self, clock: &Clock, shard_id: ShardId) {
+        let ready_at = *self.ready_at.lock().unwrap();
+        let Some(ready_at) = ready_at else {
+            return;
+        };
+        let delay = ready_at.saturating_duration_since(clock.now());
+        if delay.is_zero() {
+            return;
+        }
+        tracing::debug!(target: "state_sync_dump", shard_id, ?delay, "Throttling state part generation");
+        metrics::STATE_SYNC_DUMP_THROTTLED_ELAPSED
+            .with_label_values(&[&shard_id.to_string()])
+            .observe(delay.as_secs_f64());
+        clock.sleep_until(ready_at).await;
+    }
+
+    /// Records that parts of `num_bytes` in total were generated, which
+    /// delays the next batch of parts of any shard.
+    fn record(&self, now: Instant, num_bytes: u64) {
+        let cost =
+            std::time::Duration::from_secs_f64(num_bytes as f64 / self.max_bytes_per_second as f64);
+        let mut ready_at = self.ready_at.lock().unwrap();
+        // Idle time doesn't accumulate, so that the limit also holds for bursts.
+        *ready_at = Some(ready_at.map_or(now, |ready_at| ready_at.max(now)) + cost);
+    }
+}
+
 /// Fetches the state sync header from DB and serializes it.
 fn get_serialized_header(
     shard_id: ShardId,
@@ -321,6 +386,158 @@ async fn upload_state_header(
     }
 }
 
+/// Uploads the manifest of the state parts to external storage, unless it is already there.
+/// Returns true if the manifest is in external storage.
+async fn upload_state_parts_manifest_if_missing(
+    clock: &Clock,
+    chain_id: &String,
+    epoch_id: &EpochId,
+    epoch_height: u64,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    sync_prev_prev_hash: &CryptoHash,
+    state_root: &StateRoot,
+    num_parts: u64,
+    compression: StatePartCompression,
+    chain: &Chain,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime: &dyn RuntimeAdapter,
+    pool: &rayon::ThreadPool,
+    parallelism: usize,
+    throttle: Option<&StatePartThrottle>,
+    keep_running: &AtomicBool,
+    external: &ExternalConnection,
+) -> bool {
+    match external
+        .is_state_parts_manifest_stored_for_epoch(shard_id, chain_id, epoch_id, epoch_height)
+        .await
+    {
+        Ok(true) => return true,
+        Ok(false) => {}
+        Err(err) => {
+            tracing::error!(target: "state_sync_dump", ?err, ?shard_id, "Failed to determine manifest presence in external storage.");
+            return false;
+        }
+    }
+    let manifest = build_state_parts_manifest(
+        clock,
+        shard_id,
+        epoch_id,
+        sync_hash,
+        sync_prev_prev_hash,
+        state_root,
+        num_parts,
+        compression,
+        chain,
+        epoch_manager,
+        runtime,
+        pool,
+        parallelism,
+        throttle,
+        keep_running,
+    )
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|manifest| {
+        manifest
+            .map(|manifest| serde_json::to_vec_pretty(&manifest))
+            .transpose()
+            .map_err(anyhow::Error::from)
+    });
+    let manifest = match manifest {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => {
+            tracing::debug!(target: "state_sync_dump", ?shard_id, "Stopped building the state parts manifest.");
+            return false;
+        }
+        Err(err) => {
+            tracing::error!(target: "state_sync_dump", ?err, ?shard_id, "Failed to build the state parts manifest.");
+            return false;
+        }
+    };
+    let file_type = StateFileType::StateManifest;
+    let location =
+        external_storage_location(&chain_id, &epoch_id, epoch_height, shard_id, &file_type);
+    match external.put_file(file_type, &manifest, shard_id, &location).await {
+        Err(err) => {
+            tracing::warn!(target: "state_sync_dump", shard_id, epoch_height, ?err, "Failed to put manifest into external storage. Will retry next iteration.");
+            false
+        }
+        Ok(_) => {
+            tracing::debug!(target: "state_sync_dump", shard_id, epoch_height, "Manifest saved to external storage.");
+            true
+        }
+    }
+}
+
+/// Describes all state parts of the epoch.
+/// Parts that were already obtained by this node are read from the DB, the rest are obtained on
+/// the dump pool and stored, so that they don't need to be obtained again to be dumped. The
+/// parts are obtained in batches of `parallelism` parts under the same throttle as the dumped
+/// parts. Returns `None` if the node is stopped before all parts are described; the parts
+/// obtained so far are stored and are only read from the DB in the next attempt.
+async fn build_state_parts_manifest(
+    clock: &Clock,
+    shard_id: ShardId,
+    epoch_id: &EpochId,
+    sync_hash: CryptoHash,
+    sync_prev_prev_hash: &CryptoHash,
+    state_root: &StateRoot,
+    num_parts: u64,
+    compression: StatePartCompression,
+    chain: &Chain,
+    epoch_manager: &dyn EpochManagerAdapter,
+    runtime: &dyn RuntimeAdapter,
+    pool: &rayon::ThreadPool,
+    parallelism: usize,
+    throttle: Option<&StatePartThrottle>,
+    keep_running: &AtomicBool,
+) -> Result<Option<StatePartsManifest>, Error> {
+    let protocol_version = epoch_manager.get_epoch_protocol_version(epoch_id)?;
+    let store = chain.chain_store().store();
+    let mut parts = Vec::with_capacity(num_parts as usize);
+    while (parts.len() as u64) < num_parts {
+        if !keep_running.load(std::sync::atomic::Ordering::Relaxed) {
+            return Ok(None);
+        }
+        if let Some(throttle) = throttle {
+            throttle.wait(clock, shard_id).await;
+        }
+        let first_part_id = parts.len() as u64;
+        let last_part_id = (first_part_id + parallelism as u64).min(num_parts);
+        let state_parts = pool.install(|| {
+            (first_part_id..last_part_id)
+                .into_par_iter()
+                .map(|part_id| {
+                    get_or_obtain_state_part(
+                        runtime,
+                        shard_id,
+                        sync_hash,
+                        sync_prev_prev_hash,
+                        state_root,
+                        part_id,
+                        num_parts,
+                        store,
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        })?;
+        if let Some(throttle) = throttle {
+            let num_bytes = state_parts
+                .iter()
+                .filter(|(_, obtained)| *obtained)
+                .map(|(state_part, _)| state_part.len() as u64)
+                .sum();
+            throttle.record(clock.now(), num_bytes);
+        }
+        parts.extend(state_parts.iter().map(|(state_part, _)| StatePartManifestEntry {
+            size: state_part.len() as u64,
+            hash: CryptoHash::hash_bytes(state_part),
+        }));
+    }
+    Ok(Some(StatePartsManifest { state_root: *state_root, protocol_version, compression, parts }))
+}
+
 const FAILURES_ALLOWED_PER_ITERATION: u32 = 10;
 
 async fn state_sync_dump(
@@ -334,6 +551,10 @@ async fn state_sync_dump(
     restart_dump_for_shards: Vec<ShardId>,
     external: ExternalConnection,
     iteration_delay: Duration,
+    compression: StatePartCompression,
+    parallelism: usize,
+    pool: Arc<rayon::ThreadPool>,
+    throttle: Option<Arc<StatePartThrottle>>,
     account_id: Option<AccountId>,
     keep_running: Arc<AtomicBool>,
 ) {
@@ -369,163 +590,251 @@ async fn state_sync_dump(
                         None
                     }
                     Ok((state_root, num_parts, sync_prev_prev_hash)) => {
-                        // Upload header
-                        let header_in_external_storage = match external
-                            .is_state_sync_header_stored_for_epoch(
-                                shard_id,
-                                &chain_id,
-                                &epoch_id,
-                                epoch_height,
-                            )
-                            .await
-                        {
-                            Err(err) => {
-                                tracing::error!(target: "state_sync_dump", ?err, ?shard_id, "Failed to determine header presence in external storage.");
-                                false
-                            }
-                            // Header is already stored
-                            Ok(true) => true,
-                            // Header is missing
-                            Ok(false) => {
-                                upload_state_header(
-                                    &chain_id,
-                                    &epoch_id,
-                                    epoch_height,
-                                    shard_id,
-                                    get_serialized_header(shard_id, sync_hash, &chain),
-                                    &external,
-                                )
-                                .await
-                            }
-                        };
-
-                        let header_upload_status = if header_in_external_storage {
-                            None
-                        } else {
-                            Some(StateSyncDumpProgress::InProgress {
-                                epoch_id: epoch_id.clone(),
-                                epoch_height,
-                                sync_hash,
-                            })
-                        };
-
-                        // Upload parts
-                        let parts_upload_status = match get_missing_part_ids_for_epoch(
-                            shard_id,
+                        // Upload the manifest before the header and the parts, so that the
+                        // nodes which find the header can verify every part they download.
+                        let manifest_in_external_storage = upload_state_parts_manifest_if_missing(
+                            &clock,
                             &chain_id,
                             &epoch_id,
                             epoch_height,
+                            shard_id,
+                            sync_hash,
+                            &sync_prev_prev_hash,
+                            &state_root,
                             num_parts,
+                            compression,
+                            &chain,
+                            epoch_manager.as_ref(),
+                            runtime.as_ref(),
+                            &pool,
+                            parallelism,
+                            throttle.as_deref(),
+                            &keep_running,
                             &external,
                         )
-                        .await
-                        {
-                            Err(err) => {
-                                tracing::error!(target: "state_sync_dump", ?err, ?shard_id, "Failed to determine missing parts");
-                                None
-                            }
-                            Ok(missing_parts) if missing_parts.is_empty() => {
-                                update_dumped_size_and_cnt_metrics(
-                                    &shard_id,
+                        .await;
+                        if !manifest_in_external_storage {
+                            // No progress made. Wait before retrying.
+                            None
+                        } else {
+                            // Upload header
+                            let header_in_external_storage = match external
+                                .is_state_sync_header_stored_for_epoch(
+                                    shard_id,
+                                    &chain_id,
+                                    &epoch_id,
                                     epoch_height,
-                                    None,
-                                    num_parts,
-                                    num_parts,
-                                );
-                                Some(StateSyncDumpProgress::AllDumped { epoch_id, epoch_height })
-                            }
-                            Ok(missing_parts) => {
-                                let mut parts_to_dump = missing_parts.clone();
-                                let timer = Instant::now();
-                                let mut dumped_any_state_part = false;
-                                let mut failures_cnt = 0;
-                                // Stop if the node is stopped.
-                                // Note that without this check the state dumping thread is unstoppable, i.e. non-interruptable.
-                                while keep_running.load(std::sync::atomic::Ordering::Relaxed)
-                                    && timer.elapsed().as_secs()
-                                        <= STATE_DUMP_ITERATION_TIME_LIMIT_SECS
-                                    && !parts_to_dump.is_empty()
-                                    && failures_cnt < FAILURES_ALLOWED_PER_ITERATION
-                                {
-                                    let _timer = metrics::STATE_SYNC_DUMP_ITERATION_ELAPSED
-                                        .with_label_values(&[&shard_id.to_string()])
-                                        .start_timer();
-
-                                    let (part_id, selected_idx) =
-                                        select_random_part_id_with_index(&parts_to_dump);
-
-                                    let state_part = obtain_and_store_state_part(
-                                        runtime.as_ref(),
-                                        shard_id,
-                                        sync_hash,
-                                        &sync_prev_prev_hash,
-                                        &state_root,
-                                        part_id,
-                                        num_parts,
-                                        &chain,
-                                    );
-                                    let state_part = match state_part {
-                                        Ok(state_part) => state_part,
-                                        Err(err) => {
-                                            tracing::warn!(target: "state_sync_dump", shard_id, epoch_height, part_id, ?err, "Failed to obtain and store part. Will skip this part.");
-                                            failures_cnt += 1;
-                                            continue;
-                                        }
-                                    };
-
-                                    let file_type = StateFileType::StatePart { part_id, num_parts };
-                                    let location = external_storage_location(
+                                )
+                                .await
+                            {
+                                Err(err) => {
+                                    tracing::error!(target: "state_sync_dump", ?err, ?shard_id, "Failed to determine header presence in external storage.");
+                                    false
+                                }
+                                // Header is already stored
+                                Ok(true) => true,
+                                // Header is missing
+                                Ok(false) => {
+                                    upload_state_header(
                                         &chain_id,
                                         &epoch_id,
                                         epoch_height,
                                         shard_id,
-                                        &file_type,
-                                    );
-                                    if let Err(err) = external
-                                        .put_file(file_type, &state_part, shard_id, &location)
-                                        .await
-                                    {
-                                        // no need to break if there's an error, we should keep dumping other parts.
-                                        // reason is we are dumping random selected parts, so it's fine if we are not able to finish all of them
-                                        tracing::warn!(target: "state_sync_dump", shard_id, epoch_height, part_id, ?err, "Failed to put a store part into external storage. Will skip this part.");
-                                        failures_cnt += 1;
-                                        continue;
-                                    }
+                                        get_serialized_header(shard_id, sync_hash, &chain),
+                                        &external,
+                                    )
+                                    .await
+                                }
+                            };
+
+                            let header_upload_status = if header_in_external_storage {
+                                None
+                            } else {
+                                Some(StateSyncDumpProgress::InProgress {
+                                    epoch_id: epoch_id.clone(),
+                                    epoch_height,
+                                    sync_hash,
+                                })
+                            };
 
-                                    // Remove the dumped part from parts_to_dump so that we draw without replacement.
-                                    parts_to_dump.swap_remove(selected_idx);
+                            // Upload parts
+                            let parts_upload_status = match get_missing_part_ids_for_epoch(
+                                shard_id,
+                                &chain_id,
+                                &epoch_id,
+                                epoch_height,
+                                num_parts,
+                                &external,
+                            )
+                            .await
+                            {
+                                Err(err) => {
+                                    tracing::error!(target: "state_sync_dump", ?err, ?shard_id, "Failed to determine missing parts");
+                                    None
+                                }
+                                Ok(missing_parts) if missing_parts.is_empty() => {
                                     update_dumped_size_and_cnt_metrics(
                                         &shard_id,
                                         epoch_height,
-                                        Some(state_part.len()),
-                                        num_parts.checked_sub(parts_to_dump.len() as u64).unwrap(),
+                                        None,
+                                        num_parts,
                                         num_parts,
                                     );
-                                    dumped_any_state_part = true;
-                                }
-                                if parts_to_dump.is_empty() {
                                     Some(StateSyncDumpProgress::AllDumped {
                                         epoch_id,
                                         epoch_height,
                                     })
-                                } else if dumped_any_state_part {
-                                    Some(StateSyncDumpProgress::InProgress {
-                                        epoch_id,
-                                        epoch_height,
-                                        sync_hash,
-                                    })
-                                } else {
-                                    // No progress made. Wait before retrying.
-                                    None
                                 }
+                                Ok(missing_parts) => {
+                                    let mut parts_to_dump = missing_parts.clone();
+                                    let mut parts_dumped = num_parts - missing_parts.len() as u64;
+                                    let timer = Instant::now();
+                                    let mut dumped_any_state_part = false;
+                                    let mut failures_cnt = 0;
+                                    // Stop if the node is stopped.
+                                    // Note that without this check the state dumping thread is unstoppable, i.e. non-interruptable.
+                                    while keep_running.load(std::sync::atomic::Ordering::Relaxed)
+                                        && timer.elapsed().as_secs()
+                                            <= STATE_DUMP_ITERATION_TIME_LIMIT_SECS
+                                        && !parts_to_dump.is_empty()
+                                        && failures_cnt < FAILURES_ALLOWED_PER_ITERATION
+                                    {
+                                        if let Some(throttle) = &throttle {
+                                            throttle.wait(&clock, shard_id).await;
+                                        }
+                                        let _timer = metrics::STATE_SYNC_DUMP_ITERATION_ELAPSED
+                                            .with_label_values(&[&shard_id.to_string()])
+                                            .start_timer();
+
+                                        // Draw a batch of parts without replacement. Parts that fail to be
+                                        // dumped are put back.
+                                        let mut batch = Vec::with_capacity(parallelism);
+                                        while batch.len() < parallelism && !parts_to_dump.is_empty()
+                                        {
+                                            let (part_id, selected_idx) =
+                                                select_random_part_id_with_index(&parts_to_dump);
+                                            parts_to_dump.swap_remove(selected_idx);
+                                            batch.push(part_id);
+                                        }
+                                        let store = chain.chain_store().store();
+                                        let state_parts: Vec<_> = pool.install(|| {
+                                            batch
+                                                .into_par_iter()
+                                                .map(|part_id| {
+                                                    let _timer =
+                                                    metrics::STATE_SYNC_DUMP_OBTAIN_PART_ELAPSED
+                                                        .with_label_values(&[&shard_id.to_string()])
+                                                        .start_timer();
+                                                    let state_part = get_or_obtain_state_part(
+                                                        runtime.as_ref(),
+                                                        shard_id,
+                                                        sync_hash,
+                                                        &sync_prev_prev_hash,
+                                                        &state_root,
+                                                        part_id,
+                                                        num_parts,
+                                                        store,
+                                                    );
+                                                    (part_id, state_part)
+                                                })
+                                                .collect()
+                                        });
+                                        if let Some(throttle) = &throttle {
+                                            let num_bytes = state_parts
+                                                .iter()
+                                                .filter_map(|(_, state_part)| {
+                                                    state_part.as_ref().ok()
+                                                })
+                                                .filter(|(_, obtained)| *obtained)
+                                                .map(|(state_part, _)| state_part.len() as u64)
+                                                .sum();
+                                            throttle.record(clock.now(), num_bytes);
+                                        }
+
+                                        for (part_id, state_part) in state_parts {
+                                            let state_part = match state_part {
+                                                Ok((state_part, _)) => state_part,
+                                                Err(err) => {
+                                                    tracing::warn!(target: "state_sync_dump", shard_id, epoch_height, part_id, ?err, "Failed to obtain and store part. Will skip this part.");
+                                                    parts_to_dump.push(part_id);
+                                                    failures_cnt += 1;
+                                                    continue;
+                                                }
+                                            };
+                                            let state_part = match compress_state_part(
+                                                &state_part,
+                                                compression,
+                                            ) {
+                                                Ok(state_part) => state_part,
+                                                Err(err) => {
+                                                    tracing::warn!(target: "state_sync_dump", shard_id, epoch_height, part_id, ?err, "Failed to compress part. Will skip this part.");
+                                                    parts_to_dump.push(part_id);
+                                                    failures_cnt += 1;
+                                                    continue;
+                                                }
+                                            };
+
+                                            let file_type =
+                                                StateFileType::StatePart { part_id, num_parts };
+                                            let location = external_storage_location(
+                                                &chain_id,
+                                                &epoch_id,
+                                                epoch_height,
+                                                shard_id,
+                                                &file_type,
+                                            );
+                                            if let Err(err) = external
+                                                .put_file(
+                                                    file_type,
+                                                    &state_part,
+                                                    shard_id,
+                                                    &location,
+                                                )
+                                                .await
+                                            {
+                                                // no need to break if there's an error, we should keep dumping other parts.
+                                                // reason is we are dumping random selected parts, so it's fine if we are not able to finish all of them
+                                                tracing::warn!(target: "state_sync_dump", shard_id, epoch_height, part_id, ?err, "Failed to put a store part into external storage. Will skip this part.");
+                                                parts_to_dump.push(part_id);
+                                                failures_cnt += 1;
+                                                continue;
+                                            }
+
+                                            parts_dumped += 1;
+                                            update_dumped_size_and_cnt_metrics(
+                                                &shard_id,
+                                                epoch_height,
+                                                Some(state_part.len()),
+                                                parts_dumped,
+                                                num_parts,
+                                            );
+                                            dumped_any_state_part = true;
+                                        }
+                                    }
+                                    if parts_to_dump.is_empty() {
+                                        Some(StateSyncDumpProgress::AllDumped {
+                                            epoch_id,
+                                            epoch_height,
+                                        })
+                                    } else if dumped_any_state_part {
+                                        Some(StateSyncDumpProgress::InProgress {
+                                            epoch_id,
+                                            epoch_height,
+                                            sync_hash,
+                                        })
+                                    } else {
+                                        // No progress made. Wait before retrying.
+                                        None
+                                    }
+                                }
+                            };
+                            match (&parts_upload_status, &header_upload_status) {
+                                (
+                                    Some(StateSyncDumpProgress::AllDumped { .. }),
+                                    Some(StateSyncDumpProgress::InProgress { .. }),
+                                ) => header_upload_status,
+                                _ => parts_upload_status,
                             }
-                        };
-                        match (&parts_upload_status, &header_upload_status) {
-                            (
-                                Some(StateSyncDumpProgress::AllDumped { .. }),
-                                Some(StateSyncDumpProgress::InProgress { .. }),
-                            ) => header_upload_status,
-                            _ => parts_upload_status,
                         }
                     }
                 }
@@ -602,7 +911,37 @@ fn update_dumped_size_and_cnt_metrics(
         .set(num_parts as i64);
 }
 
-/// Obtains and then saves the part data.
+/// Reads the state part from the DB if this node already obtained it, e.g. to
+/// build the manifest, and obtains and stores it otherwise.
+/// Returns the part and whether it was obtained, so that only obtained parts
+/// count towards the throttle.
+fn get_or_obtain_state_part(
+    runtime: &dyn RuntimeAdapter,
+    shard_id: ShardId,
+    sync_hash: CryptoHash,
+    sync_prev_prev_hash: &CryptoHash,
+    state_root: &StateRoot,
+    part_id: u64,
+    num_parts: u64,
+    store: &Store,
+) -> Result<(Vec<u8>, bool), Error> {
+    let key = borsh::to_vec(&StatePartKey(sync_hash, shard_id, part_id))?;
+    if let Some(state_part) = store.get(DBCol::StateParts, &key)? {
+        return Ok((state_part.to_vec(), false));
+    }
+    let state_part = obtain_and_store_state_part(
+        runtime,
+        shard_id,
+        sync_hash,
+        sync_prev_prev_hash,
+        state_root,
+        part_id,
+        num_parts,
+        store,
+    )?;
+    Ok((state_part, true))
+}
+
 fn obtain_and_store_state_part(
     runtime: &dyn RuntimeAdapter,
     shard_id: ShardId,
@@ -611,7 +950,7 @@ fn obtain_and_store_state_part(
     state_root: &StateRoot,
     part_id: u64,
     num_parts: u64,
-    chain: &Chain,
+    store: &Store,
 ) -> Result<Vec<u8>, Error> {
     let state_part = runtime.obtain_state_part(
         shard_id,
@@ -621,7 +960,7 @@ fn obtain_and_store_state_part(
     )?;
 
     let key = borsh::to_vec(&StatePartKey(sync_hash, shard_id, part_id))?;
-    let mut store_update = chain.chain_store().store().store_update();
+    let mut store_update = store.store_update();
     store_update.set(DBCol::StateParts, &key, &state_part);
     store_update.commit()?;
     Ok(state_part)
@@ -65,7 +65,7 @@ fn test_burn_mint() {
     let mut env = setup_env(&genesis);
     let config = env.clients[0]
         .runtime_adapter
-        .get_protocol_config(&EpochId::default())
+        .get_protocol_config(&EpochId::default(), None)
         .unwrap()
         .runtime_config;
     let fee_helper = FeeHelper::new(config, genesis.config.min_gas_price);
@@ -22,12 +22,14 @@ use near_ping::PingCommand;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::compute_root_from_path;
 use near_primitives::types::{Gas, NumSeats, NumShards};
+use near_snapshot_server::cli::{DownloadSnapshotCommand, SnapshotServerCommand};
 use near_state_parts::cli::StatePartsCommand;
 use near_state_parts_dump_check::cli::StatePartsDumpCheckCommand;
 use near_state_viewer::StateViewerSubCommand;
 use near_store::db::RocksDB;
 use near_store::Mode;
 use near_undo_block::cli::UndoBlockCommand;
+use near_validate_witness::cli::{ReplayWitnessesCommand, ValidateWitnessCommand};
 use serde_json::Value;
 use std::fs::File;
 use std::io::BufReader;
@@ -132,6 +134,12 @@ impl NeardCmd {
             NeardSubCommand::UndoBlock(cmd) => {
                 cmd.run(&home_dir, genesis_validation)?;
             }
+            NeardSubCommand::ValidateWitness(cmd) => {
+                cmd.run(&home_dir, genesis_validation)?;
+            }
+            NeardSubCommand::ReplayWitnesses(cmd) => {
+                cmd.run(&home_dir, genesis_validation)?;
+            }
             NeardSubCommand::Database(cmd) => {
                 cmd.run(&home_dir)?;
             }
@@ -146,6 +154,12 @@ impl NeardCmd {
             NeardSubCommand::StatePartsDumpCheck(cmd) => {
                 cmd.run()?;
             }
+            NeardSubCommand::SnapshotServer(cmd) => {
+                cmd.run(&home_dir)?;
+            }
+            NeardSubCommand::DownloadSnapshot(cmd) => {
+                cmd.run(&home_dir)?;
+            }
             #[cfg(feature = "new_epoch_sync")]
             NeardSubCommand::EpochSync(cmd) => {
                 cmd.run(&home_dir)?;
@@ -267,9 +281,19 @@ pub(super) enum NeardSubCommand {
     /// validate config files including genesis.json and config.json
     ValidateConfig(ValidateConfigCommand),
 
-    /// reset the head of the chain locally to the prev block of current head
+    /// reset the head of the chain locally to the prev block of current head,
+    /// or further back with --num-blocks
     UndoBlock(UndoBlockCommand),
 
+    /// Validate a chunk state witness against the local chain, the same way a
+    /// chunk validator does, and print the timings or the reason of the failure
+    ValidateWitness(ValidateWitnessCommand),
+
+    /// Validate all state witnesses saved by the node for a range of heights
+    /// against the local chain, and report the validation time of every chunk
+    /// and the witnesses whose validation diverged
+    ReplayWitnesses(ReplayWitnessesCommand),
+
     /// Set of commands to run on database
     Database(DatabaseCommand),
 
@@ -279,6 +303,15 @@ pub(super) enum NeardSubCommand {
     /// Check completeness of dumped state parts of an epoch
     StatePartsDumpCheck(StatePartsDumpCheckCommand),
 
+    /// Checkpoints the databases of the node and serves them over HTTP, so that
+    /// other nodes can be provisioned with `download-snapshot`. The node must be
+    /// stopped while the checkpoint is created.
+    SnapshotServer(SnapshotServerCommand),
+
+    /// Downloads a snapshot served by `snapshot-server` into the databases of
+    /// this node, resuming interrupted downloads.
+    DownloadSnapshot(DownloadSnapshotCommand),
+
     #[cfg(feature = "new_epoch_sync")]
     /// Testing tool for epoch sync
     EpochSync(EpochSyncCommand),
@@ -530,7 +563,7 @@ impl RunCmd {
             broadcast::channel::<Result<UpdateableConfigs, Arc<UpdateableConfigLoaderError>>>(16);
         let sys = actix::System::new();
 
-        sys.block_on(async move {
+        let shard_tries = sys.block_on(async move {
             // Initialize the subscriber that takes care of both logging and tracing.
             let _subscriber_guard = default_subscriber_with_opentelemetry(
                 make_env_filter(verbose_target).unwrap(),
@@ -555,9 +588,12 @@ impl RunCmd {
             let nearcore::NearNode {
                 rpc_servers,
                 cold_store_loop_handle,
+                #[cfg(unix)]
+                store_server,
                 mut state_sync_dumper,
                 flat_state_migration_handle,
                 resharding_handle,
+                shard_tries,
                 ..
             } = nearcore::start_with_config_and_synchronization(
                 home_dir,
@@ -581,6 +617,10 @@ impl RunCmd {
             if let Some(handle) = cold_store_loop_handle {
                 handle.stop()
             }
+            #[cfg(unix)]
+            if let Some(server) = store_server {
+                server.stop()
+            }
             state_sync_dumper.stop();
             resharding_handle.stop();
             flat_state_migration_handle.stop();
@@ -592,8 +632,12 @@ impl RunCmd {
             actix::System::current().stop();
             // Disable the subscriber to properly shutdown the tracer.
             near_o11y::reload(Some("error"), None, Some("off")).unwrap();
+            shard_tries
         });
         sys.run().unwrap();
+        // No blocks are processed anymore, so the mem tries match the flat storage.
+        shard_tries.save_mem_tries();
+        drop(shard_tries);
         info!(target: "neard", "Waiting for RocksDB to gracefully shutdown");
         RocksDB::block_until_all_instances_are_dropped();
     }
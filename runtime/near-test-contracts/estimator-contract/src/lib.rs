@@ -40,6 +40,13 @@ extern "C" {
     fn alt_bn128_g1_multiexp(value_len: u64, value_ptr: u64, register_id: u64);
     fn alt_bn128_g1_sum(value_len: u64, value_ptr: u64, register_id: u64);
     fn alt_bn128_pairing_check(value_len: u64, value_ptr: u64) -> u64;
+    fn bls12381_p1_sum(value_len: u64, value_ptr: u64, register_id: u64);
+    fn bls12381_p2_sum(value_len: u64, value_ptr: u64, register_id: u64);
+    fn bls12381_g1_multiexp(value_len: u64, value_ptr: u64, register_id: u64);
+    fn bls12381_g2_multiexp(value_len: u64, value_ptr: u64, register_id: u64);
+    fn bls12381_map_fp_to_g1(value_len: u64, value_ptr: u64, register_id: u64);
+    fn bls12381_map_fp2_to_g2(value_len: u64, value_ptr: u64, register_id: u64);
+    fn bls12381_pairing_check(value_len: u64, value_ptr: u64) -> u64;
     fn random_seed(register_id: u64);
     fn sha256(value_len: u64, value_ptr: u64, register_id: u64);
     fn keccak256(value_len: u64, value_ptr: u64, register_id: u64);
@@ -62,6 +69,14 @@ extern "C" {
         pub_key_len: u64,
         pub_key_ptr: u64,
     ) -> u64;
+    fn secp256k1_verify(
+        signature_len: u64,
+        signature_ptr: u64,
+        message_len: u64,
+        message_ptr: u64,
+        public_key_len: u64,
+        public_key_ptr: u64,
+    ) -> u64;
     // #####################
     // # Miscellaneous API #
     // #####################
@@ -111,6 +126,7 @@ extern "C" {
         payload_len: u64,
         payload_ptr: u64,
     ) -> u32;
+    fn promise_yield_status(data_id_len: u64, data_id_ptr: u64) -> u64;
     // #######################
     // # Promise API actions #
     // #######################
@@ -176,8 +192,12 @@ extern "C" {
         register_id: u64,
     ) -> u64;
     fn storage_read(key_len: u64, key_ptr: u64, register_id: u64) -> u64;
+    fn storage_read_to_memory(key_len: u64, key_ptr: u64, value_len: u64, value_ptr: u64) -> u64;
     fn storage_remove(key_len: u64, key_ptr: u64, register_id: u64) -> u64;
     fn storage_has_key(key_len: u64, key_ptr: u64) -> u64;
+    fn storage_iter_prefix(prefix_len: u64, prefix_ptr: u64) -> u64;
+    fn storage_iter_range(start_len: u64, start_ptr: u64, end_len: u64, end_ptr: u64) -> u64;
+    fn storage_iter_next(iterator_id: u64, key_register_id: u64, value_register_id: u64) -> u64;
 }
 
 // Function that does not do anything at all.
@@ -576,6 +596,48 @@ pub unsafe fn ed25519_verify_16kib_64() {
     }
 }
 
+/// Function to measure `secp256k1_verify_base`. Also measures `base` and the
+/// cost of reading the inputs from memory, which are negligible compared to
+/// the signature verification.
+#[no_mangle]
+pub unsafe fn secp256k1_verify_10k() {
+    // sha256("kajdlfkjalkfjaklfjdkladjfkljadsk")
+    let hash: [u8; 32] = [
+        0x24, 0x48, 0xd8, 0x40, 0x2c, 0xb6, 0x0b, 0x15, 0x94, 0x21, 0xed, 0x55, 0xfb, 0x6c, 0xb1,
+        0xb1, 0xa7, 0x00, 0xa0, 0xd3, 0x43, 0x82, 0xe3, 0x33, 0xc6, 0x96, 0xcd, 0xd6, 0x4e, 0xc3,
+        0x6f, 0x39,
+    ];
+
+    // compressed public key
+    let public_key: [u8; 33] = [
+        0x02, 0x68, 0x93, 0x20, 0x6d, 0x0e, 0x86, 0xde, 0xc0, 0x3b, 0x98, 0xce, 0x15, 0x07, 0x27,
+        0x6c, 0x0e, 0x49, 0x20, 0x82, 0x34, 0xe6, 0x82, 0x42, 0x2c, 0x77, 0x09, 0xb2, 0x45, 0xd4,
+        0x9d, 0x5b, 0xcc,
+    ];
+
+    let signature: [u8; 64] = [
+        0x17, 0xf5, 0x32, 0x89, 0xea, 0xc9, 0x61, 0xe5, 0xad, 0xc8, 0x58, 0xd3, 0xca, 0x50, 0xda,
+        0xb0, 0x56, 0xdd, 0xca, 0x7a, 0x1a, 0x90, 0x6c, 0x08, 0x15, 0xa0, 0x36, 0x93, 0x12, 0xd1,
+        0xaa, 0x49, 0x70, 0xf2, 0x46, 0xa0, 0x5a, 0xee, 0xc2, 0x6a, 0x7c, 0x0b, 0x43, 0x6e, 0xf8,
+        0x38, 0x62, 0xab, 0xbc, 0x0b, 0x51, 0x88, 0xc6, 0xaf, 0xc5, 0xbe, 0xc2, 0x6c, 0x32, 0x35,
+        0xc3, 0x5b, 0x63, 0xc2,
+    ];
+
+    for _ in 0..10_000 {
+        let result = secp256k1_verify(
+            signature.len() as _,
+            signature.as_ptr() as _,
+            hash.len() as _,
+            hash.as_ptr() as _,
+            public_key.len() as _,
+            public_key.as_ptr() as _,
+        );
+        // check that result was positive, as negative results could have exited
+        // early and do not reflect the full cost.
+        assert!(result == 1);
+    }
+}
+
 #[repr(C)]
 struct MultiexpElem([u8; 64], [u8; 32]);
 
@@ -744,6 +806,273 @@ pub unsafe fn alt_bn128_pairing_check_10_10() {
     }
 }
 
+// #############
+// # BLS12-381 #
+// #############
+
+// Generator of G1 in the uncompressed big-endian encoding.
+#[rustfmt::skip]
+const BLS12381_G1: [u8; 96] = [23, 241, 211, 167, 49, 151, 215, 148, 38, 149, 99, 140, 79, 169, 172, 15, 195, 104, 140, 79, 151, 116, 185, 5, 161, 78, 58, 63, 23, 27, 172, 88, 108, 85, 232, 63, 249, 122, 26, 239, 251, 58, 240, 10, 219, 34, 198, 187, 8, 179, 244, 129, 227, 170, 160, 241, 160, 158, 48, 237, 116, 29, 138, 228, 252, 245, 224, 149, 213, 208, 10, 246, 0, 219, 24, 203, 44, 4, 179, 237, 208, 60, 199, 68, 162, 136, 138, 228, 12, 170, 35, 41, 70, 197, 231, 225];
+// Generator of G2 in the uncompressed big-endian encoding.
+#[rustfmt::skip]
+const BLS12381_G2: [u8; 192] = [19, 224, 43, 96, 82, 113, 159, 96, 125, 172, 211, 160, 136, 39, 79, 101, 89, 107, 208, 208, 153, 32, 182, 26, 181, 218, 97, 187, 220, 127, 80, 73, 51, 76, 241, 18, 19, 148, 93, 87, 229, 172, 125, 5, 93, 4, 43, 126, 2, 74, 162, 178, 240, 143, 10, 145, 38, 8, 5, 39, 45, 197, 16, 81, 198, 228, 122, 212, 250, 64, 59, 2, 180, 81, 11, 100, 122, 227, 209, 119, 11, 172, 3, 38, 168, 5, 187, 239, 212, 128, 86, 200, 193, 33, 189, 184, 6, 6, 196, 160, 46, 167, 52, 204, 50, 172, 210, 176, 43, 194, 139, 153, 203, 62, 40, 126, 133, 167, 99, 175, 38, 116, 146, 171, 87, 46, 153, 171, 63, 55, 13, 39, 92, 236, 29, 161, 170, 169, 7, 95, 240, 95, 121, 190, 12, 229, 213, 39, 114, 125, 110, 17, 140, 201, 205, 198, 218, 46, 53, 26, 173, 253, 155, 170, 140, 189, 211, 167, 109, 66, 154, 105, 81, 96, 209, 44, 146, 58, 201, 204, 59, 172, 162, 137, 225, 147, 84, 134, 8, 184, 40, 1];
+// A 256-bit scalar, the most expensive input for the multiexp functions.
+const BLS12381_SCALAR: [u8; 32] = [0xff; 32];
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Bls12381P1SumElem(u8, [u8; 96]);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Bls12381P2SumElem(u8, [u8; 192]);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Bls12381G1MultiexpElem([u8; 96], [u8; 32]);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Bls12381G2MultiexpElem([u8; 192], [u8; 32]);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Bls12381PairingElem([u8; 96], [u8; 192]);
+
+// Function to measure `bls12381_p1_sum_base` and `bls12381_p1_sum_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute p1_sum on 1 element 1k times.
+#[no_mangle]
+pub unsafe fn bls12381_p1_sum_1_1k() {
+    let buffer: [Bls12381P1SumElem; 1] = [Bls12381P1SumElem(0, BLS12381_G1); 1];
+    for _ in 0..1_000 {
+        bls12381_p1_sum(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_p1_sum_base` and `bls12381_p1_sum_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute p1_sum on 10 elements 1k times.
+#[no_mangle]
+pub unsafe fn bls12381_p1_sum_10_1k() {
+    let buffer: [Bls12381P1SumElem; 10] = [Bls12381P1SumElem(0, BLS12381_G1); 10];
+    for _ in 0..1_000 {
+        bls12381_p1_sum(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_p2_sum_base` and `bls12381_p2_sum_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute p2_sum on 1 element 1k times.
+#[no_mangle]
+pub unsafe fn bls12381_p2_sum_1_1k() {
+    let buffer: [Bls12381P2SumElem; 1] = [Bls12381P2SumElem(0, BLS12381_G2); 1];
+    for _ in 0..1_000 {
+        bls12381_p2_sum(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_p2_sum_base` and `bls12381_p2_sum_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute p2_sum on 10 elements 1k times.
+#[no_mangle]
+pub unsafe fn bls12381_p2_sum_10_1k() {
+    let buffer: [Bls12381P2SumElem; 10] = [Bls12381P2SumElem(0, BLS12381_G2); 10];
+    for _ in 0..1_000 {
+        bls12381_p2_sum(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_g1_multiexp_base` and `bls12381_g1_multiexp_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute g1_multiexp on 1 element 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_g1_multiexp_1_10() {
+    let buffer: [Bls12381G1MultiexpElem; 1] =
+        [Bls12381G1MultiexpElem(BLS12381_G1, BLS12381_SCALAR); 1];
+    for _ in 0..10 {
+        bls12381_g1_multiexp(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_g1_multiexp_base` and `bls12381_g1_multiexp_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute g1_multiexp on 10 elements 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_g1_multiexp_10_10() {
+    let buffer: [Bls12381G1MultiexpElem; 10] =
+        [Bls12381G1MultiexpElem(BLS12381_G1, BLS12381_SCALAR); 10];
+    for _ in 0..10 {
+        bls12381_g1_multiexp(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_g2_multiexp_base` and `bls12381_g2_multiexp_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute g2_multiexp on 1 element 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_g2_multiexp_1_10() {
+    let buffer: [Bls12381G2MultiexpElem; 1] =
+        [Bls12381G2MultiexpElem(BLS12381_G2, BLS12381_SCALAR); 1];
+    for _ in 0..10 {
+        bls12381_g2_multiexp(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_g2_multiexp_base` and `bls12381_g2_multiexp_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute g2_multiexp on 10 elements 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_g2_multiexp_10_10() {
+    let buffer: [Bls12381G2MultiexpElem; 10] =
+        [Bls12381G2MultiexpElem(BLS12381_G2, BLS12381_SCALAR); 10];
+    for _ in 0..10 {
+        bls12381_g2_multiexp(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_map_fp_to_g1_base` and `bls12381_map_fp_to_g1_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute map_fp_to_g1 on 1 element 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_map_fp_to_g1_1_10() {
+    let mut buffer: [[u8; 48]; 1] = [[0u8; 48]; 1];
+    for fp in buffer.iter_mut() {
+        fp.copy_from_slice(&BLS12381_G1[..48]);
+    }
+    for _ in 0..10 {
+        bls12381_map_fp_to_g1(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_map_fp_to_g1_base` and `bls12381_map_fp_to_g1_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute map_fp_to_g1 on 10 elements 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_map_fp_to_g1_10_10() {
+    let mut buffer: [[u8; 48]; 10] = [[0u8; 48]; 10];
+    for fp in buffer.iter_mut() {
+        fp.copy_from_slice(&BLS12381_G1[..48]);
+    }
+    for _ in 0..10 {
+        bls12381_map_fp_to_g1(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_map_fp2_to_g2_base` and `bls12381_map_fp2_to_g2_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute map_fp2_to_g2 on 1 element 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_map_fp2_to_g2_1_10() {
+    let mut buffer: [[u8; 96]; 1] = [[0u8; 96]; 1];
+    for fp2 in buffer.iter_mut() {
+        fp2.copy_from_slice(&BLS12381_G2[..96]);
+    }
+    for _ in 0..10 {
+        bls12381_map_fp2_to_g2(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_map_fp2_to_g2_base` and `bls12381_map_fp2_to_g2_element`. Also measures `base`, `write_register_base`,
+// and `write_register_byte`. However the curve computation is more expensive than register writing
+// so we are okay overcharging it.
+// Compute map_fp2_to_g2 on 10 elements 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_map_fp2_to_g2_10_10() {
+    let mut buffer: [[u8; 96]; 10] = [[0u8; 96]; 10];
+    for fp2 in buffer.iter_mut() {
+        fp2.copy_from_slice(&BLS12381_G2[..96]);
+    }
+    for _ in 0..10 {
+        bls12381_map_fp2_to_g2(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+            0,
+        );
+    }
+}
+
+// Function to measure `bls12381_pairing_base` and `bls12381_pairing_element`. Also measures `base`.
+// Compute pairing_check on 1 element 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_pairing_1_10() {
+    let buffer: [Bls12381PairingElem; 1] = [Bls12381PairingElem(BLS12381_G1, BLS12381_G2); 1];
+    for _ in 0..10 {
+        bls12381_pairing_check(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+        );
+    }
+}
+
+// Function to measure `bls12381_pairing_base` and `bls12381_pairing_element`. Also measures `base`.
+// Compute pairing_check on 10 elements 10 times.
+#[no_mangle]
+pub unsafe fn bls12381_pairing_10_10() {
+    let buffer: [Bls12381PairingElem; 10] = [Bls12381PairingElem(BLS12381_G1, BLS12381_G2); 10];
+    for _ in 0..10 {
+        bls12381_pairing_check(
+            core::mem::size_of_val(&buffer) as u64,
+            buffer.as_ptr() as *const u64 as u64,
+        );
+    }
+}
+
 // ###############
 // # Storage API #
 // ###############
@@ -809,6 +1138,18 @@ storage_bench!(key, 10, value, 10240, 1000, storage_read_10b_key_10kib_value_1k,
     storage_read(10, key.as_ptr() as _, 0);
 });
 
+// Function to measure `storage_read_base + result_to_memory_base`.
+// Reads from storage directly into memory 1k times.
+storage_bench!(key, 10, value, 10, 1000, storage_read_to_memory_10b_key_10b_value_1k, {
+    storage_read_to_memory(10, key.as_ptr() as _, 10, value.as_mut_ptr() as _);
+});
+
+// Function to measure `storage_read_base + storage_read_value_byte + result_to_memory_byte`.
+// Reads 10kib values from storage directly into memory 1k times.
+storage_bench!(key, 10, value, 10240, 1000, storage_read_to_memory_10b_key_10kib_value_1k, {
+    storage_read_to_memory(10, key.as_ptr() as _, 10240, value.as_mut_ptr() as _);
+});
+
 // Storage removing.
 
 // Function to measure `storage_remove_base`.
@@ -849,6 +1190,67 @@ storage_bench!(key, 10, value, 10240, 1000, storage_has_key_10b_key_10kib_value_
     storage_has_key(10, key.as_ptr() as _);
 });
 
+// Storage iterators.
+
+// Function to measure `storage_iter_create_prefix_base`.
+// Creates 1k iterators with a 10b prefix.
+storage_bench!(key, 10, value, 10, 1000, storage_iter_create_prefix_10b_1k, {
+    storage_iter_prefix(10, key.as_ptr() as _);
+});
+
+// Function to measure `storage_iter_create_prefix_base + storage_iter_create_prefix_byte`.
+// Creates 1k iterators with a 10kib prefix.
+storage_bench!(key, 10240, value, 10, 1000, storage_iter_create_prefix_10kib_1k, {
+    storage_iter_prefix(10240, key.as_ptr() as _);
+});
+
+// Function to measure `storage_iter_create_range_base`.
+// Creates 1k iterators with 10b bounds.
+storage_bench!(key, 10, value, 10, 1000, storage_iter_create_range_10b_1k, {
+    storage_iter_range(10, key.as_ptr() as _, 10, value.as_ptr() as _);
+});
+
+// Function to measure `storage_iter_create_range_base + storage_iter_create_from_byte`.
+// Creates 1k iterators with a 10kib start bound.
+storage_bench!(key, 10240, value, 10, 1000, storage_iter_create_range_10kib_from_1k, {
+    storage_iter_range(10240, key.as_ptr() as _, 10, value.as_ptr() as _);
+});
+
+// Function to measure `storage_iter_create_range_base + storage_iter_create_to_byte`.
+// Creates 1k iterators with a 10kib end bound.
+storage_bench!(key, 10, value, 10240, 1000, storage_iter_create_range_10kib_to_1k, {
+    storage_iter_range(10, key.as_ptr() as _, 10240, value.as_ptr() as _);
+});
+
+// Iterates over all keys of the contract, expecting to find exactly 1k of them.
+unsafe fn storage_iter_next_1k() {
+    let iterator_id = storage_iter_prefix(0, 0);
+    for _ in 0..1000 {
+        assert_eq!(storage_iter_next(iterator_id, 0, 1), 1);
+    }
+}
+
+// Function to measure `storage_iter_next_base`.
+// Iterates over the keys written by `storage_write_10b_key_10b_value_1k`.
+#[no_mangle]
+pub unsafe fn storage_iter_next_10b_key_10b_value_1k() {
+    storage_iter_next_1k();
+}
+
+// Function to measure `storage_iter_next_base + storage_iter_next_key_byte`.
+// Iterates over the keys written by `storage_write_10kib_key_10b_value_1k`.
+#[no_mangle]
+pub unsafe fn storage_iter_next_10kib_key_10b_value_1k() {
+    storage_iter_next_1k();
+}
+
+// Function to measure `storage_iter_next_base + storage_iter_next_value_byte`.
+// Iterates over the keys written by `storage_write_10b_key_10kib_value_1k`.
+#[no_mangle]
+pub unsafe fn storage_iter_next_10b_key_10kib_value_1k() {
+    storage_iter_next_1k();
+}
+
 // Function to measure `promise_and_base`.
 #[no_mangle]
 pub unsafe fn promise_and_100k() {
@@ -1086,6 +1488,17 @@ pub unsafe fn yield_resume_payload() {
     }
 }
 
+#[no_mangle]
+pub unsafe fn yield_status_base() {
+    for i in 0..255u8 {
+        match storage_read(1, core::ptr::addr_of!(i) as u64, 0) {
+            0 => panic!("storage_read did not produce data_id"),
+            1 => assert_ne!(promise_yield_status(u64::MAX, 0), u64::MAX),
+            _ => panic!("unexpected storage_read return"),
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe fn cpu_ram_soak_test() {
     let mut buf = [0u8; 100 * 1024];
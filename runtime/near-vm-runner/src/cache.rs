@@ -11,7 +11,7 @@ use std::collections::HashMap;
 use std::fmt;
 use std::io::{Read, Write};
 use std::num::NonZeroUsize;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 
 #[derive(Debug, Clone, BorshSerialize)]
 enum ContractCacheKey {
@@ -198,11 +198,16 @@ impl fmt::Debug for MockContractRuntimeCache {
 /// directory will be removed when the last instance of this cache is dropped.
 ///
 /// Clones of this type share the same underlying state and information. The cache is thread safe
-/// and atomic.
+/// and atomic. Opening a directory that is already open in this process returns the existing
+/// cache, so that all the users of the directory (e.g. the runtimes of the client and of the view
+/// client) share the loaded artifacts rather than keeping duplicate copies of popular contracts.
+/// Reopening it with a different size limit is an error, while a different memory cache size
+/// is only reported, as the existing memory cache is kept.
 ///
-/// This cache however does not implement any clean-up policies. While it is possible to truncate
-/// a file that has been written to the cache before (`put` an empty buffer), the file will remain
-/// in place until an operator (or somebody else) removes files at their own discretion.
+/// Unless created with [`Self::with_limits`], this cache does not implement any clean-up
+/// policies. While it is possible to truncate a file that has been written to the cache before
+/// (`put` an empty buffer), the file will remain in place until an operator (or somebody else)
+/// removes files at their own discretion.
 #[derive(Clone)]
 pub struct FilesystemContractRuntimeCache {
     state: Arc<FilesystemContractRuntimeCacheState>,
@@ -211,9 +216,17 @@ pub struct FilesystemContractRuntimeCache {
 struct FilesystemContractRuntimeCacheState {
     dir: rustix::fd::OwnedFd,
     any_cache: AnyCache,
+    memory_cache_size: usize,
+    /// Tracks the files in the directory when their total size is limited.
+    files: Option<Mutex<CachedFiles>>,
     test_temp_dir: Option<tempfile::TempDir>,
 }
 
+/// Caches opened in this process, keyed by the canonical path of their directory.
+static OPEN_CACHES: once_cell::sync::Lazy<
+    Mutex<HashMap<std::path::PathBuf, Weak<FilesystemContractRuntimeCacheState>>>,
+> = once_cell::sync::Lazy::new(Default::default);
+
 impl FilesystemContractRuntimeCache {
     pub fn new<SP: AsRef<std::path::Path> + ?Sized>(
         home_dir: &std::path::Path,
@@ -234,33 +247,174 @@ impl FilesystemContractRuntimeCache {
         home_dir: &std::path::Path,
         store_path: Option<&SP>,
         memory_cache_size: usize,
+    ) -> std::io::Result<Self> {
+        Self::with_limits(home_dir, store_path, memory_cache_size, None)
+    }
+
+    /// Like [`Self::with_memory_cache`], but if `max_bytes` is set, also keeps the total size of
+    /// the files in the cache within this budget by removing the least recently used ones.
+    ///
+    /// The recency of the files is kept in their modification times, so that the eviction order
+    /// survives restarts. Files evicted from the cache are compiled again when needed.
+    pub fn with_limits<SP: AsRef<std::path::Path> + ?Sized>(
+        home_dir: &std::path::Path,
+        store_path: Option<&SP>,
+        memory_cache_size: usize,
+        max_bytes: Option<u64>,
     ) -> std::io::Result<Self> {
         let store_path = store_path.map(AsRef::as_ref).unwrap_or_else(|| "data".as_ref());
         let path: std::path::PathBuf =
             [home_dir, store_path, "contracts".as_ref()].into_iter().collect();
         std::fs::create_dir_all(&path)?;
-        let dir =
-            rustix::fs::open(&path, rustix::fs::OFlags::DIRECTORY, rustix::fs::Mode::empty())?;
+        let path = std::fs::canonicalize(path)?;
+        let mut open_caches = OPEN_CACHES.lock().unwrap();
+        open_caches.retain(|_, state| state.strong_count() > 0);
+        if let Some(state) = open_caches.get(&path).and_then(Weak::upgrade) {
+            let open_max_bytes = state.files.as_ref().map(|files| files.lock().unwrap().max_bytes);
+            if open_max_bytes != max_bytes {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "contract cache at {} is already open with a size limit of {:?} bytes, cannot reopen it with {:?}",
+                        path.display(),
+                        open_max_bytes,
+                        max_bytes
+                    ),
+                ));
+            }
+            if state.memory_cache_size != memory_cache_size {
+                tracing::warn!(
+                    target: "vm",
+                    path = %path.display(),
+                    open_size = state.memory_cache_size,
+                    requested_size = memory_cache_size,
+                    "contract cache is already open with a different memory cache size, keeping the existing one"
+                );
+            }
+            return Ok(Self { state });
+        }
+        let cache = Self::open(&path, memory_cache_size, max_bytes)?;
+        open_caches.insert(path, Arc::downgrade(&cache.state));
+        Ok(cache)
+    }
+
+    fn open(
+        path: &std::path::Path,
+        memory_cache_size: usize,
+        max_bytes: Option<u64>,
+    ) -> std::io::Result<Self> {
+        let dir = rustix::fs::open(path, rustix::fs::OFlags::DIRECTORY, rustix::fs::Mode::empty())?;
         tracing::debug!(
             target: "vm",
             path = %path.display(),
             message = "opened a contract executable cache directory"
         );
-        Ok(Self {
+        let files = match max_bytes {
+            Some(max_bytes) => Some(Mutex::new(CachedFiles::scan(path, max_bytes)?)),
+            None => None,
+        };
+        let cache = Self {
             state: Arc::new(FilesystemContractRuntimeCacheState {
                 dir,
                 any_cache: AnyCache::new(memory_cache_size),
+                memory_cache_size,
+                files,
                 test_temp_dir: None,
             }),
-        })
+        };
+        // The budget may have been lowered since the last run.
+        cache.evict(None);
+        Ok(cache)
     }
 
     pub fn test() -> std::io::Result<Self> {
         let tempdir = tempfile::TempDir::new()?;
-        let mut cache = Self::new(tempdir.path(), None::<&str>)?;
+        let mut cache = Self::open(tempdir.path(), 0, None)?;
         Arc::get_mut(&mut cache.state).unwrap().test_temp_dir = Some(tempdir);
         Ok(cache)
     }
+
+    /// Records a new file in the cache and removes the least recently used files if the
+    /// cache is over its budget.
+    fn evict(&self, new_file: Option<(CryptoHash, u64)>) {
+        let Some(files) = &self.state.files else { return };
+        let evicted = {
+            let mut files = files.lock().unwrap();
+            if let Some((key, size)) = new_file {
+                files.insert(key, size);
+            }
+            files.evict()
+        };
+        for key in evicted {
+            tracing::debug!(target: "vm", %key, "evicting compiled contract from the cache");
+            match rustix::fs::unlinkat(
+                &self.state.dir,
+                key.to_string(),
+                rustix::fs::AtFlags::empty(),
+            ) {
+                Ok(()) | Err(rustix::io::Errno::NOENT) => {}
+                Err(err) => {
+                    tracing::warn!(target: "vm", %key, %err, "could not remove compiled contract")
+                }
+            }
+        }
+    }
+}
+
+/// Sizes of the files in a [`FilesystemContractRuntimeCache`], from the least to the most
+/// recently used.
+struct CachedFiles {
+    lru: lru::LruCache<CryptoHash, u64>,
+    total_bytes: u64,
+    max_bytes: u64,
+}
+
+impl CachedFiles {
+    /// Lists the files already in the cache directory, ordered by their modification time.
+    fn scan(path: &std::path::Path, max_bytes: u64) -> std::io::Result<Self> {
+        let mut found = vec![];
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            // Skips the temporary files left behind by interrupted writes.
+            let Some(key) =
+                entry.file_name().to_str().and_then(|name| name.parse::<CryptoHash>().ok())
+            else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                found.push((metadata.modified()?, key, metadata.len()));
+            }
+        }
+        found.sort();
+        let mut files = Self { lru: lru::LruCache::unbounded(), total_bytes: 0, max_bytes };
+        for (_, key, size) in found {
+            files.insert(key, size);
+        }
+        Ok(files)
+    }
+
+    fn insert(&mut self, key: CryptoHash, size: u64) {
+        if let Some(old_size) = self.lru.put(key, size) {
+            self.total_bytes -= old_size;
+        }
+        self.total_bytes += size;
+    }
+
+    fn touch(&mut self, key: &CryptoHash) {
+        self.lru.promote(key);
+    }
+
+    /// Removes the least recently used files until the total size is within the budget.
+    fn evict(&mut self) -> Vec<CryptoHash> {
+        let mut evicted = vec![];
+        while self.total_bytes > self.max_bytes {
+            let Some((key, size)) = self.lru.pop_lru() else { break };
+            self.total_bytes -= size;
+            evicted.push(key);
+        }
+        evicted
+    }
 }
 
 /// Byte added after a serialized payload representing a compilation failure.
@@ -314,11 +468,13 @@ impl ContractRuntimeCache for FilesystemContractRuntimeCache {
             }
         }
         temp_file.write_all(&value.wasm_bytes.to_le_bytes())?;
+        let size = temp_file.as_file().metadata()?.len();
         let temp_filename = temp_file.into_temp_path();
         // This is atomic, so there wouldn't be instances where getters see an intermediate state.
         rustix::fs::renameat(&self.state.dir, &*temp_filename, &self.state.dir, final_filename)?;
         // Don't attempt deleting the temporary file now that it has been moved.
         std::mem::forget(temp_filename);
+        self.evict(Some((*key, size)));
         Ok(())
     }
 
@@ -347,6 +503,12 @@ impl ContractRuntimeCache for FilesystemContractRuntimeCache {
         let mut buffer = Vec::with_capacity(stat.st_size.try_into().unwrap());
         let mut file = std::fs::File::from(file);
         file.read_to_end(&mut buffer)?;
+        if let Some(files) = &self.state.files {
+            files.lock().unwrap().touch(key);
+            // Persist the recency of the file for the eviction order after a restart. This is
+            // best effort, the file is still in the cache if it fails.
+            let _ = file.set_modified(std::time::SystemTime::now());
+        }
         if buffer.len() < 9 {
             // The file turns out to be empty/truncated? Treat as if there's no cached file.
             return Ok(None);
@@ -580,4 +742,58 @@ mod tests {
         );
         assert!(matches!(result, Err("mikan")));
     }
+
+    fn code(size: usize) -> CompiledContractInfo {
+        CompiledContractInfo { wasm_bytes: 0, compiled: CompiledContract::Code(vec![0; size]) }
+    }
+
+    #[test]
+    fn filesystem_cache_evicts_least_recently_used() {
+        let home_dir = tempfile::TempDir::new().unwrap();
+        let open = |max_bytes| {
+            FilesystemContractRuntimeCache::with_limits(
+                home_dir.path(),
+                None::<&str>,
+                0,
+                Some(max_bytes),
+            )
+            .unwrap()
+        };
+        let [a, b, c] = [b"a", b"b", b"c"].map(|k| CryptoHash::hash_bytes(k));
+        // Files also contain a 9 byte trailer, so only two of them fit in the budget.
+        let cache = open(3000);
+        cache.put(&a, code(1000)).unwrap();
+        cache.put(&b, code(1000)).unwrap();
+        // Reading `a` makes `b` the least recently used file.
+        assert_eq!(cache.get(&a).unwrap(), Some(code(1000)));
+        cache.put(&c, code(1000)).unwrap();
+        assert!(cache.has(&a).unwrap());
+        assert!(!cache.has(&b).unwrap());
+        assert!(cache.has(&c).unwrap());
+        drop(cache);
+
+        // The files are found again after a restart and a lower budget is enforced.
+        let cache = open(1500);
+        let remaining = [a, c].iter().filter(|key| cache.has(key).unwrap()).count();
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn filesystem_cache_is_shared_within_process() {
+        let home_dir = tempfile::TempDir::new().unwrap();
+        let first =
+            FilesystemContractRuntimeCache::with_memory_cache(home_dir.path(), None::<&str>, 1)
+                .unwrap();
+        let second = FilesystemContractRuntimeCache::new(home_dir.path(), None::<&str>).unwrap();
+        assert!(Arc::ptr_eq(&first.state, &second.state));
+
+        let other_dir = tempfile::TempDir::new().unwrap();
+        let other = FilesystemContractRuntimeCache::new(other_dir.path(), None::<&str>).unwrap();
+        assert!(!Arc::ptr_eq(&first.state, &other.state));
+
+        // Reopening the directory with a different size limit is refused rather than ignored.
+        let limited =
+            FilesystemContractRuntimeCache::with_limits(home_dir.path(), None::<&str>, 1, Some(10));
+        assert_eq!(limited.err().unwrap().kind(), std::io::ErrorKind::InvalidInput);
+    }
 }
@@ -137,6 +137,13 @@ imports! {
         pub_key_len: u64,
         pub_key_ptr: u64
     ] -> [u64]>,
+    #[secp256k1_verify] secp256k1_verify<[signature_len: u64,
+        signature_ptr: u64,
+        message_len: u64,
+        message_ptr: u64,
+        public_key_len: u64,
+        public_key_ptr: u64
+    ] -> [u64]>,
     #[math_extension] ripemd160<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     #[math_extension] ecrecover<[hash_len: u64, hash_ptr: u64, sign_len: u64, sig_ptr: u64, v: u64, malleability_flag: u64, register_id: u64] -> [u64]>,
     // #####################
@@ -251,17 +258,20 @@ imports! {
         payload_len: u64,
         payload_ptr: u64
     ] -> [u32]>,
+    #[promise_yield_status] promise_yield_status<[data_id_len: u64, data_id_ptr: u64] -> [u64]>,
     // #######################
     // # Promise API results #
     // #######################
     promise_results_count<[] -> [u64]>,
     promise_result<[result_idx: u64, register_id: u64] -> [u64]>,
+    #[results_to_memory] promise_result_to_memory<[result_idx: u64, value_len: u64, value_ptr: u64] -> [u64]>,
     promise_return<[promise_idx: u64] -> []>,
     // ###############
     // # Storage API #
     // ###############
     storage_write<[key_len: u64, key_ptr: u64, value_len: u64, value_ptr: u64, register_id: u64] -> [u64]>,
     storage_read<[key_len: u64, key_ptr: u64, register_id: u64] -> [u64]>,
+    #[results_to_memory] storage_read_to_memory<[key_len: u64, key_ptr: u64, value_len: u64, value_ptr: u64] -> [u64]>,
     storage_remove<[key_len: u64, key_ptr: u64, register_id: u64] -> [u64]>,
     storage_has_key<[key_len: u64, key_ptr: u64] -> [u64]>,
     storage_iter_prefix<[prefix_len: u64, prefix_ptr: u64] -> [u64]>,
@@ -281,6 +291,16 @@ imports! {
     #[alt_bn128] alt_bn128_g1_sum<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     #[alt_bn128] alt_bn128_pairing_check<[value_len: u64, value_ptr: u64] -> [u64]>,
     // #############
+    // # BLS12-381 #
+    // #############
+    #[bls12381] bls12381_p1_sum<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
+    #[bls12381] bls12381_p2_sum<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
+    #[bls12381] bls12381_g1_multiexp<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
+    #[bls12381] bls12381_g2_multiexp<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
+    #[bls12381] bls12381_map_fp_to_g1<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
+    #[bls12381] bls12381_map_fp2_to_g2<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
+    #[bls12381] bls12381_pairing_check<[value_len: u64, value_ptr: u64] -> [u64]>,
+    // #############
     // #  Sandbox  #
     // #############
     ##["sandbox"] sandbox_debug_log<[len: u64, ptr: u64] -> []>,
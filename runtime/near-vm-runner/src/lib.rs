@@ -35,7 +35,7 @@ pub use cache::{
 };
 pub use code::ContractCode;
 pub use metrics::{report_metrics, reset_metrics};
-pub use profile::ProfileDataV3;
+pub use profile::{ProfileDataV3, StorageAccessStats};
 pub use runner::{run, VM};
 
 /// This is public for internal experimentation use only, and should otherwise be considered an
@@ -1,5 +1,5 @@
 //! External dependencies of the near-vm-logic.
-use super::types::ReceiptIndex;
+use super::types::{PromiseYieldStatus, ReceiptIndex};
 use super::VMLogicError;
 use near_crypto::PublicKey;
 use near_parameters::vm::StorageGetMode;
@@ -255,6 +255,58 @@ pub trait External {
     /// ```
     fn storage_has_key(&mut self, key: &[u8], mode: StorageGetMode) -> Result<bool>;
 
+    /// Creates an iterator over the keys with the given `prefix` in the storage trie associated
+    /// with the current account. Returns the id of the iterator, which is passed to
+    /// [`External::storage_iter_next`].
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - a prefix of all keys to iterate over
+    fn storage_iter_prefix(&mut self, prefix: &[u8]) -> Result<u64>;
+
+    /// Creates an iterator over the keys `start <= key < end` in the storage trie associated with
+    /// the current account. Returns the id of the iterator, which is passed to
+    /// [`External::storage_iter_next`].
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - the first key of the range
+    /// * `end` - the key right after the range
+    fn storage_iter_range(&mut self, start: &[u8], end: &[u8]) -> Result<u64>;
+
+    /// Advances the iterator and returns the next key in the lexicographic order together with
+    /// its value, or `None` once the iterator is exhausted.
+    ///
+    /// The iterator continues after the key it returned last in the current state of the storage,
+    /// so keys written after the iterator was created are returned as well if they come later.
+    ///
+    /// # Errors
+    ///
+    /// If `iterator_id` does not correspond to an existing iterator returns
+    /// `InvalidIteratorIndex`.
+    ///
+    /// # Example
+    /// ```
+    /// # use near_vm_runner::logic::mocks::mock_external::MockedExternal;
+    /// # use near_vm_runner::logic::External;
+    ///
+    /// # let mut external = MockedExternal::new();
+    /// external.storage_set(b"key2", b"value2").unwrap();
+    /// external.storage_set(b"key1", b"value1").unwrap();
+    /// external.storage_set(b"other", b"value").unwrap();
+    /// let iterator_id = external.storage_iter_prefix(b"key").unwrap();
+    /// assert_eq!(
+    ///     external.storage_iter_next(iterator_id),
+    ///     Ok(Some((b"key1".to_vec(), b"value1".to_vec())))
+    /// );
+    /// assert_eq!(
+    ///     external.storage_iter_next(iterator_id),
+    ///     Ok(Some((b"key2".to_vec(), b"value2".to_vec())))
+    /// );
+    /// assert_eq!(external.storage_iter_next(iterator_id), Ok(None));
+    /// ```
+    fn storage_iter_next(&mut self, iterator_id: u64) -> Result<Option<(Vec<u8>, Vec<u8>)>>;
+
     fn generate_data_id(&mut self) -> CryptoHash;
 
     /// Returns amount of touched trie nodes by storage operations
@@ -317,6 +369,17 @@ pub trait External {
         data: Vec<u8>,
     ) -> Result<bool, VMLogicError>;
 
+    /// Looks up the status of a yielded promise created under the specified `data_id` by the
+    /// current account.
+    ///
+    /// Yields which were already resumed, including those resumed by the current function call,
+    /// are reported as [`PromiseYieldStatus::NotFound`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data_id` - `data_id` of the yield, as returned by `create_promise_yield_receipt`
+    fn promise_yield_status(&self, data_id: CryptoHash) -> Result<PromiseYieldStatus>;
+
     /// Attach the [`CreateAccountAction`] action to an existing receipt.
     ///
     /// # Arguments
@@ -173,6 +173,8 @@ pub enum HostError {
     InvalidReceiptIndex { receipt_index: u64 },
     /// Iterator index `iterator_index` does not exist
     InvalidIteratorIndex { iterator_index: u64 },
+    /// The number of storage iterators created by the function call exceeded the limit.
+    NumberOfIteratorsExceeded { limit: u64 },
     /// VM Logic returned an invalid account id
     InvalidAccountId,
     /// VM Logic returned an invalid method name
@@ -204,9 +206,15 @@ pub enum HostError {
     /// Invalid input to alt_bn128 familiy of functions (e.g., point which isn't
     /// on the curve).
     AltBn128InvalidInput { msg: String },
+    /// Invalid input to the BLS12-381 family of functions (e.g., point which
+    /// isn't on the curve or in the subgroup).
+    BLS12381InvalidInput { msg: String },
     /// Invalid input to ed25519 signature verification function (e.g. signature cannot be
     /// derived from bytes).
     Ed25519VerifyInvalidInput { msg: String },
+    /// Invalid input to secp256k1 signature verification function (e.g. the
+    /// message isn't a 32-byte hash).
+    Secp256k1VerifyInvalidInput { msg: String },
     /// Yield payload length exceeds the maximum permitted.
     YieldPayloadLength { length: u64, limit: u64 },
     /// Yield resumption data id is malformed.
@@ -377,6 +385,9 @@ impl std::fmt::Display for HostError {
             InvalidIteratorIndex { iterator_index } => {
                 write!(f, "Iterator index {:?} does not exist", iterator_index)
             }
+            NumberOfIteratorsExceeded { limit } => {
+                write!(f, "The number of storage iterators will exceed the limit {}", limit)
+            }
             InvalidPromiseIndex { promise_idx } => {
                 write!(f, "{:?} does not correspond to existing promises", promise_idx)
             }
@@ -438,10 +449,14 @@ impl std::fmt::Display for HostError {
                 write!(f, "Attempted to call deprecated host function {}", method_name)
             }
             AltBn128InvalidInput { msg } => write!(f, "AltBn128 invalid input: {}", msg),
+            BLS12381InvalidInput { msg } => write!(f, "BLS12-381 invalid input: {}", msg),
             ECRecoverError { msg } => write!(f, "ECDSA recover error: {}", msg),
             Ed25519VerifyInvalidInput { msg } => {
                 write!(f, "ED25519 signature verification error: {}", msg)
             }
+            Secp256k1VerifyInvalidInput { msg } => {
+                write!(f, "secp256k1 signature verification error: {}", msg)
+            }
             YieldPayloadLength { length, limit } => write!(
                 f,
                 "Yield resume payload is {length} bytes which exceeds the {limit} byte limit"
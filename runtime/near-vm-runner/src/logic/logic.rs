@@ -1,13 +1,13 @@
 use super::context::VMContext;
-use super::dependencies::{External, MemSlice, MemoryLike};
+use super::dependencies::{External, MemSlice, MemoryLike, TrieNodesCount};
 use super::errors::{FunctionCallError, InconsistentStateError};
 use super::gas_counter::{FastGasCounter, GasCounter};
 use super::recorded_storage_counter::RecordedStorageCounter;
-use super::types::{PromiseIndex, PromiseResult, ReceiptIndex, ReturnData};
+use super::types::{PromiseIndex, PromiseResult, PromiseYieldStatus, ReceiptIndex, ReturnData};
 use super::utils::split_method_names;
 use super::ValuePtr;
 use super::{HostError, VMLogicError};
-use crate::ProfileDataV3;
+use crate::{ProfileDataV3, StorageAccessStats};
 use near_crypto::Secp256K1Signature;
 use near_parameters::vm::{Config, StorageGetMode};
 use near_parameters::{
@@ -56,6 +56,8 @@ pub struct VMLogic<'a> {
     gas_counter: GasCounter,
     /// Tracks size of the recorded trie storage proof.
     recorded_storage_counter: RecordedStorageCounter,
+    /// Storage operations made by the contract, reported in the outcome.
+    storage_stats: StorageAccessStats,
     /// What method returns.
     return_data: ReturnData,
     /// Logs written by the runtime.
@@ -68,6 +70,8 @@ pub struct VMLogic<'a> {
     promises: Vec<Promise>,
     /// Tracks the total log length. The sum of length of all logs.
     total_log_length: u64,
+    /// Number of storage iterators created by the function call.
+    num_storage_iterators: u64,
 
     /// Stores the amount of stack space remaining
     remaining_stack: u64,
@@ -169,11 +173,13 @@ impl<'a> VMLogic<'a> {
             current_storage_usage,
             gas_counter,
             recorded_storage_counter,
+            storage_stats: StorageAccessStats::default(),
             return_data: ReturnData::None,
             logs: vec![],
             registers: Default::default(),
             promises: vec![],
             total_log_length: 0,
+            num_storage_iterators: 0,
             remaining_stack: u64::from(config.limit_config.max_stack_height),
         }
     }
@@ -430,6 +436,17 @@ impl<'a> VMLogic<'a> {
         }
     }
 
+    /// Counts a new storage iterator, failing with `NumberOfIteratorsExceeded` if the function
+    /// call already created `max_storage_iterators` of them.
+    fn checked_add_storage_iterator(&mut self) -> Result<()> {
+        let limit = self.config.limit_config.max_storage_iterators;
+        if self.num_storage_iterators >= limit {
+            return Err(HostError::NumberOfIteratorsExceeded { limit }.into());
+        }
+        self.num_storage_iterators += 1;
+        Ok(())
+    }
+
     /// Adds a given promise to the vector of promises and returns a new promise index.
     /// Throws `NumberPromisesExceeded` if the total number of promises exceeded the limit.
     fn checked_push_promise(&mut self, promise: Promise) -> Result<PromiseIndex> {
@@ -891,6 +908,295 @@ impl<'a> VMLogic<'a> {
         Ok(res as u64)
     }
 
+    /// Computes the sum of signed G1 points on the BLS12-381 curve
+    /// \sum_i (-1)^{sign_i} p_{1 i}.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - sequence of (sign:bool, p1:G1), where G1 is an uncompressed
+    ///   point (x:Fp, y:Fp) on the BLS12-381 curve Y^2 = X^3 + 4 over Fp.
+    ///
+    ///   `value` is encoded as packed `[(u8, ([u8; 48], [u8; 48]))]` slice,
+    ///   where coordinates are big-endian. `0u8` is positive sign, `1u8` --
+    ///   negative. The point at infinity has the `0x40` flag set in the first
+    ///   byte and all the other bytes zero. The result is written to the
+    ///   register in the same 96-byte point encoding.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers
+    /// use more memory than the limit, the function returns `MemoryAccessViolation`.
+    ///
+    /// If point coordinates are not on curve, are not canonical field
+    /// elements, the point is compressed, sign is not 0 or 1, or
+    /// `value.len()%97!=0`, the function returns `BLS12381InvalidInput`.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes +
+    /// bls12381_p1_sum_base + bls12381_p1_sum_element * num_elements`
+    pub fn bls12381_p1_sum(
+        &mut self,
+        value_len: u64,
+        value_ptr: u64,
+        register_id: u64,
+    ) -> Result<()> {
+        self.gas_counter.pay_base(bls12381_p1_sum_base)?;
+        let data = get_memory_or_register!(self, value_ptr, value_len)?;
+
+        let elements = super::bls12381::split_elements(&data)?;
+        self.gas_counter.pay_per(bls12381_p1_sum_element, elements.len() as u64)?;
+
+        let res = super::bls12381::p1_sum(elements)?;
+
+        self.registers.set(&mut self.gas_counter, &self.config.limit_config, register_id, res)
+    }
+
+    /// Computes the sum of signed G2 points on the BLS12-381 curve
+    /// \sum_i (-1)^{sign_i} p_{2 i}.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - sequence of (sign:bool, p2:G2), where G2 is an uncompressed
+    ///   point (x:Fp2, y:Fp2) on the BLS12-381 twist Y^2 = X^3 + 4(u + 1)
+    ///   over Fp2, and Fp2 element c0 + c1 * u is encoded as `c1 || c0`.
+    ///
+    ///   `value` is encoded as packed `[(u8, ([u8; 96], [u8; 96]))]` slice,
+    ///   where coordinates are big-endian. `0u8` is positive sign, `1u8` --
+    ///   negative. The result is written to the register in the same
+    ///   192-byte point encoding.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers
+    /// use more memory than the limit, the function returns `MemoryAccessViolation`.
+    ///
+    /// If point coordinates are not on curve, are not canonical field
+    /// elements, the point is compressed, sign is not 0 or 1, or
+    /// `value.len()%193!=0`, the function returns `BLS12381InvalidInput`.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes +
+    /// bls12381_p2_sum_base + bls12381_p2_sum_element * num_elements`
+    pub fn bls12381_p2_sum(
+        &mut self,
+        value_len: u64,
+        value_ptr: u64,
+        register_id: u64,
+    ) -> Result<()> {
+        self.gas_counter.pay_base(bls12381_p2_sum_base)?;
+        let data = get_memory_or_register!(self, value_ptr, value_len)?;
+
+        let elements = super::bls12381::split_elements(&data)?;
+        self.gas_counter.pay_per(bls12381_p2_sum_element, elements.len() as u64)?;
+
+        let res = super::bls12381::p2_sum(elements)?;
+
+        self.registers.set(&mut self.gas_counter, &self.config.limit_config, register_id, res)
+    }
+
+    /// Computes multiexp on the G1 subgroup of the BLS12-381 curve
+    /// \sum_i s_i g_{1 i}.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - sequence of (g1:G1, s:u256), where G1 is a point encoded as
+    ///   in [`Self::bls12381_p1_sum`] and `s` is a little-endian scalar.
+    ///
+    ///   `value` is encoded as packed `[(([u8; 48], [u8; 48]), [u8; 32])]`
+    ///   slice.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers
+    /// use more memory than the limit, the function returns `MemoryAccessViolation`.
+    ///
+    /// If point coordinates are not on curve, point is not in the subgroup,
+    /// the point is compressed or `value.len()%128!=0`, the function returns
+    /// `BLS12381InvalidInput`.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes +
+    ///  bls12381_g1_multiexp_base +
+    ///  bls12381_g1_multiexp_element * num_elements`
+    pub fn bls12381_g1_multiexp(
+        &mut self,
+        value_len: u64,
+        value_ptr: u64,
+        register_id: u64,
+    ) -> Result<()> {
+        self.gas_counter.pay_base(bls12381_g1_multiexp_base)?;
+        let data = get_memory_or_register!(self, value_ptr, value_len)?;
+
+        let elements = super::bls12381::split_elements(&data)?;
+        self.gas_counter.pay_per(bls12381_g1_multiexp_element, elements.len() as u64)?;
+
+        let res = super::bls12381::g1_multiexp(elements)?;
+
+        self.registers.set(&mut self.gas_counter, &self.config.limit_config, register_id, res)
+    }
+
+    /// Computes multiexp on the G2 subgroup of the BLS12-381 twist
+    /// \sum_i s_i g_{2 i}.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - sequence of (g2:G2, s:u256), where G2 is a point encoded as
+    ///   in [`Self::bls12381_p2_sum`] and `s` is a little-endian scalar.
+    ///
+    ///   `value` is encoded as packed `[(([u8; 96], [u8; 96]), [u8; 32])]`
+    ///   slice.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers
+    /// use more memory than the limit, the function returns `MemoryAccessViolation`.
+    ///
+    /// If point coordinates are not on curve, point is not in the subgroup,
+    /// the point is compressed or `value.len()%224!=0`, the function returns
+    /// `BLS12381InvalidInput`.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes +
+    ///  bls12381_g2_multiexp_base +
+    ///  bls12381_g2_multiexp_element * num_elements`
+    pub fn bls12381_g2_multiexp(
+        &mut self,
+        value_len: u64,
+        value_ptr: u64,
+        register_id: u64,
+    ) -> Result<()> {
+        self.gas_counter.pay_base(bls12381_g2_multiexp_base)?;
+        let data = get_memory_or_register!(self, value_ptr, value_len)?;
+
+        let elements = super::bls12381::split_elements(&data)?;
+        self.gas_counter.pay_per(bls12381_g2_multiexp_element, elements.len() as u64)?;
+
+        let res = super::bls12381::g2_multiexp(elements)?;
+
+        self.registers.set(&mut self.gas_counter, &self.config.limit_config, register_id, res)
+    }
+
+    /// Maps each Fp element to a point in the G1 subgroup of the BLS12-381
+    /// curve using the simplified SWU map from the hash-to-curve standard
+    /// (RFC 9380).
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - sequence of big-endian Fp elements encoded as packed
+    ///   `[[u8; 48]]` slice. The resulting points are written to the register
+    ///   as packed `[([u8; 48], [u8; 48])]` slice in the same order.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers
+    /// use more memory than the limit, the function returns `MemoryAccessViolation`.
+    ///
+    /// If an element is not a canonical field element or `value.len()%48!=0`,
+    /// the function returns `BLS12381InvalidInput`.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes +
+    ///  bls12381_map_fp_to_g1_base +
+    ///  bls12381_map_fp_to_g1_element * num_elements`
+    pub fn bls12381_map_fp_to_g1(
+        &mut self,
+        value_len: u64,
+        value_ptr: u64,
+        register_id: u64,
+    ) -> Result<()> {
+        self.gas_counter.pay_base(bls12381_map_fp_to_g1_base)?;
+        let data = get_memory_or_register!(self, value_ptr, value_len)?;
+
+        let elements = super::bls12381::split_elements(&data)?;
+        self.gas_counter.pay_per(bls12381_map_fp_to_g1_element, elements.len() as u64)?;
+
+        let res = super::bls12381::map_fp_to_g1(elements)?;
+
+        self.registers.set(&mut self.gas_counter, &self.config.limit_config, register_id, res)
+    }
+
+    /// Maps each Fp2 element to a point in the G2 subgroup of the BLS12-381
+    /// twist using the simplified SWU map from the hash-to-curve standard
+    /// (RFC 9380).
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - sequence of Fp2 elements c0 + c1 * u encoded as packed
+    ///   `[([u8; 48], [u8; 48])]` slice of big-endian `(c1, c0)`. The
+    ///   resulting points are written to the register as packed 192-byte
+    ///   points in the same order.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers
+    /// use more memory than the limit, the function returns `MemoryAccessViolation`.
+    ///
+    /// If an element is not a canonical field element or `value.len()%96!=0`,
+    /// the function returns `BLS12381InvalidInput`.
+    ///
+    /// # Cost
+    ///
+    /// `base + write_register_base + write_register_byte * num_bytes +
+    ///  bls12381_map_fp2_to_g2_base +
+    ///  bls12381_map_fp2_to_g2_element * num_elements`
+    pub fn bls12381_map_fp2_to_g2(
+        &mut self,
+        value_len: u64,
+        value_ptr: u64,
+        register_id: u64,
+    ) -> Result<()> {
+        self.gas_counter.pay_base(bls12381_map_fp2_to_g2_base)?;
+        let data = get_memory_or_register!(self, value_ptr, value_len)?;
+
+        let elements = super::bls12381::split_elements(&data)?;
+        self.gas_counter.pay_per(bls12381_map_fp2_to_g2_element, elements.len() as u64)?;
+
+        let res = super::bls12381::map_fp2_to_g2(elements)?;
+
+        self.registers.set(&mut self.gas_counter, &self.config.limit_config, register_id, res)
+    }
+
+    /// Computes pairing check on the BLS12-381 curve.
+    /// \prod_i e(g_{1 i}, g_{2 i}) should be equal one, e(g1, g2) is the
+    /// optimal Ate pairing.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - sequence of (g1:G1, g2:G2), where the points are encoded
+    ///   as in [`Self::bls12381_p1_sum`] and [`Self::bls12381_p2_sum`].
+    ///   Pairs containing the point at infinity are skipped.
+    ///
+    ///   `value` is encoded as packed `[([u8; 96], [u8; 192])]` slice.
+    ///
+    /// # Errors
+    ///
+    /// If `value_len + value_ptr` points outside the memory or the registers
+    /// use more memory than the limit, the function returns `MemoryAccessViolation`.
+    ///
+    /// If point coordinates are not on curve, point is not in the subgroup,
+    /// the point is compressed or `value.len()%288!=0`, the function returns
+    /// `BLS12381InvalidInput`.
+    ///
+    /// # Cost
+    ///
+    /// `base + bls12381_pairing_base + bls12381_pairing_element * num_elements`
+    pub fn bls12381_pairing_check(&mut self, value_len: u64, value_ptr: u64) -> Result<u64> {
+        self.gas_counter.pay_base(bls12381_pairing_base)?;
+        let data = get_memory_or_register!(self, value_ptr, value_len)?;
+
+        let elements = super::bls12381::split_elements(&data)?;
+        self.gas_counter.pay_per(bls12381_pairing_element, elements.len() as u64)?;
+
+        let res = super::bls12381::pairing_check(elements)?;
+
+        Ok(res as u64)
+    }
+
     /// Writes random seed into the register.
     ///
     /// # Errors
@@ -1198,6 +1504,94 @@ impl<'a> VMLogic<'a> {
         }
     }
 
+    /// Verify a secp256k1 ECDSA signature of a 32-byte message hash given the
+    /// signer's public key.
+    ///
+    /// The signature is in the 64-byte compact `r || s` encoding. The public
+    /// key is either SEC1 encoded, compressed (33 bytes) or uncompressed (65
+    /// bytes), or the 64-byte `x || y` encoding returned by `ecrecover`.
+    ///
+    /// Returns a bool indicating success (1) or failure (0) as a `u64`.
+    /// Signatures with `s` in the upper half of the curve order are not valid.
+    ///
+    /// # Errors
+    ///
+    /// * If the message hash's size is not equal to 32, the signature size is
+    ///   not equal to 64, or the public key's size is not one of 33, 64 or 65,
+    ///   returns [HostError::Secp256k1VerifyInvalidInput].
+    /// * If any of the signature, message or public key arguments are out of
+    ///   memory bounds, returns [`HostError::MemoryAccessViolation`]
+    ///
+    /// # Cost
+    ///
+    /// Each input can either be in memory or in a register, see
+    /// [`Self::ed25519_verify`] for the definition of `input_cost`.
+    ///
+    /// `input_cost(num_bytes_signature) + input_cost(num_bytes_message) +
+    ///  input_cost(num_bytes_public_key) + secp256k1_verify_base`
+    pub fn secp256k1_verify(
+        &mut self,
+        signature_len: u64,
+        signature_ptr: u64,
+        message_len: u64,
+        message_ptr: u64,
+        public_key_len: u64,
+        public_key_ptr: u64,
+    ) -> Result<u64> {
+        static SECP256K1: once_cell::sync::Lazy<secp256k1::Secp256k1<secp256k1::VerifyOnly>> =
+            once_cell::sync::Lazy::new(secp256k1::Secp256k1::verification_only);
+
+        self.gas_counter.pay_base(secp256k1_verify_base)?;
+
+        let invalid_input = |msg: &str| {
+            VMLogicError::HostError(HostError::Secp256k1VerifyInvalidInput { msg: msg.to_string() })
+        };
+
+        let signature = {
+            let vec = get_memory_or_register!(self, signature_ptr, signature_len)?;
+            if vec.len() != 64 {
+                return Err(invalid_input("invalid signature length"));
+            }
+            match secp256k1::ecdsa::Signature::from_compact(&vec) {
+                Ok(signature) => signature,
+                Err(_) => return Ok(false as u64),
+            }
+        };
+
+        let message = {
+            let vec = get_memory_or_register!(self, message_ptr, message_len)?;
+            if vec.len() != 32 {
+                return Err(invalid_input("invalid message hash length"));
+            }
+            secp256k1::Message::from_slice(&vec).expect("message hash is 32 bytes")
+        };
+
+        let public_key = {
+            let vec = get_memory_or_register!(self, public_key_ptr, public_key_len)?;
+            let parsed = match vec.len() {
+                33 | 65 => secp256k1::PublicKey::from_slice(&vec),
+                64 => {
+                    let mut bytes = [0u8; 65];
+                    bytes[0] = 0x04;
+                    bytes[1..].copy_from_slice(&vec);
+                    secp256k1::PublicKey::from_slice(&bytes)
+                }
+                _ => return Err(invalid_input("invalid public key length")),
+            };
+            match parsed {
+                Ok(public_key) => public_key,
+                Err(_) => return Ok(false as u64),
+            }
+        };
+
+        // Unlike `ecrecover`, there is no flag to accept malleable signatures:
+        // `verify_ecdsa` only accepts signatures with a low `s`.
+        match SECP256K1.verify_ecdsa(&message, &signature, &public_key) {
+            Err(_) => Ok(false as u64),
+            Ok(()) => Ok(true as u64),
+        }
+    }
+
     /// Consume gas. Counts both towards `burnt_gas` and `used_gas`.
     ///
     /// # Errors
@@ -2190,6 +2584,48 @@ impl<'a> VMLogic<'a> {
         self.ext.submit_promise_resume_data(data_id, payload).map(u32::from)
     }
 
+    /// Looks up a yield promise which is awaiting its value.
+    ///
+    /// The `data_id` pair of parameters must refer to a resumption token generated by a call to
+    /// the [`promise_yield_create`] made by the same account.
+    ///
+    /// Returns the number of blocks left until the yield times out. Yields created by the current
+    /// function call report the full timeout length. If there is no pending yield for the given
+    /// `data_id`, because it was already resumed, timed out or was never created, returns
+    /// `u64::MAX`.
+    ///
+    /// # Errors
+    ///
+    /// * If `data_id_len + data_id_ptr` points outside the memory of the guest or host
+    /// returns `MemoryAccessViolation`;
+    /// * If a malformed data id is passed, returns `DataIdMalformed`;
+    /// * If called as view function returns `ProhibitedInView`;
+    /// * Runs out of gas.
+    ///
+    /// # Cost
+    ///
+    /// `base + yield_status_base` + fees for reading the `data_id`.
+    pub fn promise_yield_status(&mut self, data_id_len: u64, data_id_ptr: u64) -> Result<u64> {
+        self.gas_counter.pay_base(base)?;
+        if self.context.is_view() {
+            return Err(HostError::ProhibitedInView {
+                method_name: "promise_yield_status".to_string(),
+            }
+            .into());
+        }
+        self.gas_counter.pay_base(yield_status_base)?;
+        let data_id = get_memory_or_register!(self, data_id_ptr, data_id_len)?;
+        let data_id: [_; CryptoHash::LENGTH] =
+            (&*data_id).try_into().map_err(|_| HostError::DataIdMalformed)?;
+        Ok(match self.ext.promise_yield_status(CryptoHash(data_id))? {
+            PromiseYieldStatus::NotFound => u64::MAX,
+            PromiseYieldStatus::Created => self.config.limit_config.yield_timeout_length_in_blocks,
+            PromiseYieldStatus::Pending { expires_at } => {
+                expires_at.saturating_sub(self.context.block_height)
+            }
+        })
+    }
+
     /// If the current function is invoked by a callback we can access the execution results of the
     /// promises that caused the callback. This function returns the number of complete and
     /// incomplete callbacks.
@@ -2263,6 +2699,51 @@ impl<'a> VMLogic<'a> {
         }
     }
 
+    /// Same as [`Self::promise_result`], but copies the blob of a successful promise result
+    /// directly into the guest memory at `value_ptr` instead of a register. At most `value_len`
+    /// bytes are copied.
+    ///
+    /// # Returns
+    ///
+    /// * If promise result is complete and successful returns the length of the whole blob, which
+    ///   is larger than `value_len` if the blob was truncated;
+    /// * Otherwise returns `u64::MAX`.
+    ///
+    /// # Errors
+    ///
+    /// * If `result_id` does not correspond to an existing result returns `InvalidPromiseResultIndex`;
+    /// * If the copied part of the blob extends outside the guest memory returns
+    ///   `MemoryAccessViolation`;
+    /// * If called as view function returns `ProhibitedInView`.
+    ///
+    /// # Cost
+    ///
+    /// `base + result_to_memory_base + result_to_memory_byte * num_copied_bytes`
+    pub fn promise_result_to_memory(
+        &mut self,
+        result_idx: u64,
+        value_len: u64,
+        value_ptr: u64,
+    ) -> Result<u64> {
+        self.gas_counter.pay_base(base)?;
+        if self.context.is_view() {
+            return Err(HostError::ProhibitedInView {
+                method_name: "promise_result_to_memory".to_string(),
+            }
+            .into());
+        }
+        match self
+            .promise_results
+            .get(result_idx as usize)
+            .ok_or(HostError::InvalidPromiseResultIndex { result_idx })?
+        {
+            PromiseResult::Successful(data) => {
+                self.memory.set_result(&mut self.gas_counter, value_ptr, value_len, data)
+            }
+            PromiseResult::NotReady | PromiseResult::Failed => Ok(u64::MAX),
+        }
+    }
+
     /// When promise `promise_idx` finishes executing its result is considered to be the result of
     /// the current function.
     ///
@@ -2577,8 +3058,9 @@ impl<'a> VMLogic<'a> {
             tn_db_reads = nodes_delta.db_reads,
         );
 
-        self.gas_counter.add_trie_fees(&nodes_delta)?;
+        self.add_trie_fees(&nodes_delta)?;
         self.ext.storage_set(&key, &value)?;
+        self.storage_stats.record_write(key.len() + value.len());
         let storage_config = &self.fees_config.storage_usage_config;
         self.recorded_storage_counter.observe_size(self.ext.get_recorded_storage_size())?;
         match evicted {
@@ -2616,6 +3098,16 @@ impl<'a> VMLogic<'a> {
         }
     }
 
+    /// Charges for the trie nodes touched by a storage operation and records
+    /// them in the storage stats.
+    fn add_trie_fees(&mut self, nodes_delta: &TrieNodesCount) -> Result<()> {
+        self.storage_stats.trie_node_db_reads =
+            self.storage_stats.trie_node_db_reads.saturating_add(nodes_delta.db_reads);
+        self.storage_stats.trie_node_mem_reads =
+            self.storage_stats.trie_node_mem_reads.saturating_add(nodes_delta.mem_reads);
+        self.gas_counter.add_trie_fees(nodes_delta)
+    }
+
     fn deref_value<'s>(
         gas_counter: &mut GasCounter,
         cost_per_byte: ExtCosts,
@@ -2666,8 +3158,11 @@ impl<'a> VMLogic<'a> {
             .get_trie_nodes_count()
             .checked_sub(&nodes_before)
             .ok_or(InconsistentStateError::IntegerOverflow)?;
-        self.gas_counter.add_trie_fees(&nodes_delta)?;
+        self.add_trie_fees(&nodes_delta)?;
         let read = Self::deref_value(&mut self.gas_counter, storage_read_value_byte, read?)?;
+        if let Some(value) = &read {
+            self.storage_stats.record_read(key.len() + value.len());
+        }
 
         #[cfg(feature = "io_trace")]
         tracing::trace!(
@@ -2694,6 +3189,76 @@ impl<'a> VMLogic<'a> {
         }
     }
 
+    /// Same as [`Self::storage_read`], but copies the value directly into the guest memory at
+    /// `value_ptr` instead of a register, which saves the cost of the register round trip for
+    /// large values. At most `value_len` bytes are copied.
+    ///
+    /// * If key is used returns the length of the whole value, which is larger than `value_len` if
+    ///   the value was truncated;
+    /// * If key is not present then does not modify the memory. Returns `u64::MAX`.
+    ///
+    /// # Errors
+    ///
+    /// * If `key_len + key_ptr` exceeds the memory container or points to an unused register it
+    ///   returns `MemoryAccessViolation`;
+    /// * If the copied part of the value extends outside the guest memory returns
+    ///   `MemoryAccessViolation`;
+    /// * If the length of the key exceeds `max_length_storage_key` returns `KeyLengthExceeded`.
+    ///
+    /// # Cost
+    ///
+    /// `base + storage_read_base + storage_read_key_byte * num_key_bytes + storage_read_value_byte * num_value_bytes
+    ///  + result_to_memory_base + result_to_memory_byte * num_copied_bytes + cost to read key from register`.
+    pub fn storage_read_to_memory(
+        &mut self,
+        key_len: u64,
+        key_ptr: u64,
+        value_len: u64,
+        value_ptr: u64,
+    ) -> Result<u64> {
+        self.gas_counter.pay_base(base)?;
+        self.gas_counter.pay_base(storage_read_base)?;
+        let key = get_memory_or_register!(self, key_ptr, key_len)?;
+        if key.len() as u64 > self.config.limit_config.max_length_storage_key {
+            return Err(HostError::KeyLengthExceeded {
+                length: key.len() as u64,
+                limit: self.config.limit_config.max_length_storage_key,
+            }
+            .into());
+        }
+        self.gas_counter.pay_per(storage_read_key_byte, key.len() as u64)?;
+        let nodes_before = self.ext.get_trie_nodes_count();
+        let read = self.ext.storage_get(&key, self.config.storage_get_mode);
+        let nodes_delta = self
+            .ext
+            .get_trie_nodes_count()
+            .checked_sub(&nodes_before)
+            .ok_or(InconsistentStateError::IntegerOverflow)?;
+        self.add_trie_fees(&nodes_delta)?;
+        let read = Self::deref_value(&mut self.gas_counter, storage_read_value_byte, read?)?;
+        if let Some(value) = &read {
+            self.storage_stats.record_read(key.len() + value.len());
+        }
+
+        #[cfg(feature = "io_trace")]
+        tracing::trace!(
+            target = "io_tracer",
+            storage_op = "read",
+            key = base64(&key),
+            size = read.as_ref().map(Vec::len),
+            tn_db_reads = nodes_delta.db_reads,
+            tn_mem_reads = nodes_delta.mem_reads,
+        );
+
+        self.recorded_storage_counter.observe_size(self.ext.get_recorded_storage_size())?;
+        match read {
+            Some(value) => {
+                self.memory.set_result(&mut self.gas_counter, value_ptr, value_len, &value)
+            }
+            None => Ok(u64::MAX),
+        }
+    }
+
     /// Removes the value stored under the given key.
     /// * If key is used, removes the key-value from the trie and copies the content of the value
     ///   into the `register_id`, even if the content is zero bytes. Returns `1`;
@@ -2738,6 +3303,9 @@ impl<'a> VMLogic<'a> {
             Self::deref_value(&mut self.gas_counter, storage_remove_ret_value_byte, removed_ptr)?;
 
         self.ext.storage_remove(&key)?;
+        if let Some(value) = &removed {
+            self.storage_stats.record_removal(key.len() + value.len());
+        }
         let nodes_delta = self
             .ext
             .get_trie_nodes_count()
@@ -2754,7 +3322,7 @@ impl<'a> VMLogic<'a> {
             tn_db_reads = nodes_delta.db_reads,
         );
 
-        self.gas_counter.add_trie_fees(&nodes_delta)?;
+        self.add_trie_fees(&nodes_delta)?;
         let storage_config = &self.fees_config.storage_usage_config;
         self.recorded_storage_counter.observe_size(self.ext.get_recorded_storage_size())?;
         match removed {
@@ -2806,6 +3374,9 @@ impl<'a> VMLogic<'a> {
         self.gas_counter.pay_per(storage_has_key_byte, key.len() as u64)?;
         let nodes_before = self.ext.get_trie_nodes_count();
         let res = self.ext.storage_has_key(&key, self.config.storage_get_mode);
+        if let Ok(true) = res {
+            self.storage_stats.record_read(key.len());
+        }
         let nodes_delta = self
             .ext
             .get_trie_nodes_count()
@@ -2821,7 +3392,7 @@ impl<'a> VMLogic<'a> {
             tn_db_reads = nodes_delta.db_reads,
         );
 
-        self.gas_counter.add_trie_fees(&nodes_delta)?;
+        self.add_trie_fees(&nodes_delta)?;
         self.recorded_storage_counter.observe_size(self.ext.get_recorded_storage_size())?;
         Ok(res? as u64)
     }
@@ -2843,34 +3414,54 @@ impl<'a> VMLogic<'a> {
         Ok(())
     }
 
-    /// DEPRECATED
-    /// Creates an iterator object inside the host. Returns the identifier that uniquely
-    /// differentiates the given iterator from other iterators that can be simultaneously created.
-    /// * It iterates over the keys that have the provided prefix. The order of iteration is defined
-    ///   by the lexicographic order of the bytes in the keys;
-    /// * If there are no keys, it creates an empty iterator, see below on empty iterators.
+    /// Creates an iterator over the keys with the given prefix in the storage of the current
+    /// account. Returns the identifier of the iterator which is passed to `storage_iter_next`.
+    /// * The keys are iterated in the lexicographic order of their bytes;
+    /// * If there are no keys with the prefix, the iterator is empty.
+    ///
+    /// Before the `StorageIterators` protocol feature the function is deprecated and always
+    /// fails.
     ///
     /// # Errors
     ///
-    /// * If `prefix_len + prefix_ptr` exceeds the memory container it returns
-    ///   `MemoryAccessViolation`.
+    /// * If `prefix_len + prefix_ptr` exceeds the memory container or points to an unused register
+    ///   it returns `MemoryAccessViolation`.
     /// * If the length of the prefix exceeds `max_length_storage_key` returns `KeyLengthExceeded`.
+    /// * If the function call already created `max_storage_iterators` iterators returns
+    ///   `NumberOfIteratorsExceeded`.
     ///
     /// # Cost
     ///
-    /// `base + storage_iter_create_prefix_base + storage_iter_create_key_byte * num_prefix_bytes
-    ///  cost of reading the prefix`.
-    pub fn storage_iter_prefix(&mut self, _prefix_len: u64, _prefix_ptr: u64) -> Result<u64> {
-        Err(VMLogicError::HostError(HostError::Deprecated {
-            method_name: "storage_iter_prefix".to_string(),
-        }))
+    /// `base + storage_iter_create_prefix_base + storage_iter_create_prefix_byte * num_prefix_bytes
+    ///  + cost of reading the prefix`.
+    pub fn storage_iter_prefix(&mut self, prefix_len: u64, prefix_ptr: u64) -> Result<u64> {
+        if !self.config.storage_iterators {
+            return Err(VMLogicError::HostError(HostError::Deprecated {
+                method_name: "storage_iter_prefix".to_string(),
+            }));
+        }
+        self.gas_counter.pay_base(base)?;
+        self.gas_counter.pay_base(storage_iter_create_prefix_base)?;
+        let prefix = get_memory_or_register!(self, prefix_ptr, prefix_len)?;
+        if prefix.len() as u64 > self.config.limit_config.max_length_storage_key {
+            return Err(HostError::KeyLengthExceeded {
+                length: prefix.len() as u64,
+                limit: self.config.limit_config.max_length_storage_key,
+            }
+            .into());
+        }
+        self.gas_counter.pay_per(storage_iter_create_prefix_byte, prefix.len() as u64)?;
+        self.checked_add_storage_iterator()?;
+        self.ext.storage_iter_prefix(&prefix)
     }
 
-    /// DEPRECATED
-    /// Iterates over all key-values such that keys are between `start` and `end`, where `start` is
-    /// inclusive and `end` is exclusive. Unless lexicographically `start < end`, it creates an
-    /// empty iterator. Note, this definition allows for `start` or `end` keys to not actually exist
-    /// on the given trie.
+    /// Creates an iterator over the keys in the storage of the current account which are between
+    /// `start` and `end`, where `start` is inclusive and `end` is exclusive. Unless
+    /// lexicographically `start < end`, the iterator is empty. Neither `start` nor `end` have to
+    /// actually exist in the storage.
+    ///
+    /// Before the `StorageIterators` protocol feature the function is deprecated and always
+    /// fails.
     ///
     /// # Errors
     ///
@@ -2878,6 +3469,8 @@ impl<'a> VMLogic<'a> {
     ///   an unused register it returns `MemoryAccessViolation`.
     /// * If the length of the `start` exceeds `max_length_storage_key` returns `KeyLengthExceeded`.
     /// * If the length of the `end` exceeds `max_length_storage_key` returns `KeyLengthExceeded`.
+    /// * If the function call already created `max_storage_iterators` iterators returns
+    ///   `NumberOfIteratorsExceeded`.
     ///
     /// # Cost
     ///
@@ -2885,39 +3478,53 @@ impl<'a> VMLogic<'a> {
     ///  + storage_iter_create_to_byte * num_to_bytes + reading from prefix + reading to prefix`.
     pub fn storage_iter_range(
         &mut self,
-        _start_len: u64,
-        _start_ptr: u64,
-        _end_len: u64,
-        _end_ptr: u64,
+        start_len: u64,
+        start_ptr: u64,
+        end_len: u64,
+        end_ptr: u64,
     ) -> Result<u64> {
-        Err(VMLogicError::HostError(HostError::Deprecated {
-            method_name: "storage_iter_range".to_string(),
-        }))
+        if !self.config.storage_iterators {
+            return Err(VMLogicError::HostError(HostError::Deprecated {
+                method_name: "storage_iter_range".to_string(),
+            }));
+        }
+        self.gas_counter.pay_base(base)?;
+        self.gas_counter.pay_base(storage_iter_create_range_base)?;
+        let start = get_memory_or_register!(self, start_ptr, start_len)?;
+        let end = get_memory_or_register!(self, end_ptr, end_len)?;
+        for key in [&start, &end] {
+            if key.len() as u64 > self.config.limit_config.max_length_storage_key {
+                return Err(HostError::KeyLengthExceeded {
+                    length: key.len() as u64,
+                    limit: self.config.limit_config.max_length_storage_key,
+                }
+                .into());
+            }
+        }
+        self.gas_counter.pay_per(storage_iter_create_from_byte, start.len() as u64)?;
+        self.gas_counter.pay_per(storage_iter_create_to_byte, end.len() as u64)?;
+        self.checked_add_storage_iterator()?;
+        self.ext.storage_iter_range(&start, &end)
     }
 
-    /// DEPRECATED
-    /// Advances iterator and saves the next key and value in the register.
+    /// Advances iterator and saves the next key and value in the registers.
     /// * If iterator is not empty (after calling next it points to a key-value), copies the key
     ///   into `key_register_id` and value into `value_register_id` and returns `1`;
     /// * If iterator is empty returns `0`;
     /// This allows us to iterate over the keys that have zero bytes stored in values.
     ///
+    /// The iterator continues after the key it returned last, in the current state of the storage.
+    /// Keys written or removed after the iterator was created are therefore taken into account if
+    /// they come after that key. Once the iterator returned `0`, it stays empty.
+    ///
+    /// Before the `StorageIterators` protocol feature the function is deprecated and always
+    /// fails.
+    ///
     /// # Errors
     ///
     /// * If `key_register_id == value_register_id` returns `MemoryAccessViolation`;
     /// * If the registers exceed the memory limit returns `MemoryAccessViolation`;
-    /// * If `iterator_id` does not correspond to an existing iterator returns `InvalidIteratorId`;
-    /// * If between the creation of the iterator and calling `storage_iter_next` the range over
-    ///   which it iterates was modified returns `IteratorWasInvalidated`. Specifically, if
-    ///   `storage_write` or `storage_remove` was invoked on the key key such that:
-    ///   * in case of `storage_iter_prefix`. `key` has the given prefix and:
-    ///     * Iterator was not called next yet.
-    ///     * `next` was already called on the iterator and it is currently pointing at the `key`
-    ///       `curr` such that `curr <= key`.
-    ///   * in case of `storage_iter_range`. `start<=key<end` and:
-    ///     * Iterator was not called `next` yet.
-    ///     * `next` was already called on the iterator and it is currently pointing at the key
-    ///       `curr` such that `curr<=key<end`.
+    /// * If `iterator_id` does not correspond to an existing iterator returns `InvalidIteratorIndex`.
     ///
     /// # Cost
     ///
@@ -2925,13 +3532,50 @@ impl<'a> VMLogic<'a> {
     ///  + writing key to register + writing value to register`.
     pub fn storage_iter_next(
         &mut self,
-        _iterator_id: u64,
-        _key_register_id: u64,
-        _value_register_id: u64,
+        iterator_id: u64,
+        key_register_id: u64,
+        value_register_id: u64,
     ) -> Result<u64> {
-        Err(VMLogicError::HostError(HostError::Deprecated {
-            method_name: "storage_iter_next".to_string(),
-        }))
+        if !self.config.storage_iterators {
+            return Err(VMLogicError::HostError(HostError::Deprecated {
+                method_name: "storage_iter_next".to_string(),
+            }));
+        }
+        self.gas_counter.pay_base(base)?;
+        self.gas_counter.pay_base(storage_iter_next_base)?;
+        if key_register_id == value_register_id {
+            return Err(HostError::MemoryAccessViolation.into());
+        }
+        let nodes_before = self.ext.get_trie_nodes_count();
+        let next = self.ext.storage_iter_next(iterator_id);
+        let nodes_delta = self
+            .ext
+            .get_trie_nodes_count()
+            .checked_sub(&nodes_before)
+            .ok_or(InconsistentStateError::IntegerOverflow)?;
+        self.add_trie_fees(&nodes_delta)?;
+        self.recorded_storage_counter.observe_size(self.ext.get_recorded_storage_size())?;
+        match next? {
+            Some((key, value)) => {
+                self.storage_stats.record_read(key.len() + value.len());
+                self.gas_counter.pay_per(storage_iter_next_key_byte, key.len() as u64)?;
+                self.gas_counter.pay_per(storage_iter_next_value_byte, value.len() as u64)?;
+                self.registers.set(
+                    &mut self.gas_counter,
+                    &self.config.limit_config,
+                    key_register_id,
+                    key,
+                )?;
+                self.registers.set(
+                    &mut self.gas_counter,
+                    &self.config.limit_config,
+                    value_register_id,
+                    value,
+                )?;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
     }
 
     /// Computes the outcome of the execution.
@@ -2956,6 +3600,7 @@ impl<'a> VMLogic<'a> {
             compute_usage,
             logs: self.logs,
             profile,
+            storage_stats: self.storage_stats,
             aborted: None,
         }
     }
@@ -3064,6 +3709,7 @@ pub struct VMOutcome {
     pub logs: Vec<String>,
     /// Data collected from making a contract call
     pub profile: ProfileDataV3,
+    pub storage_stats: StorageAccessStats,
     pub aborted: Option<FunctionCallError>,
 }
 
@@ -3096,6 +3742,7 @@ impl VMOutcome {
             compute_usage: 0,
             logs: Vec::new(),
             profile: ProfileDataV3::default(),
+            storage_stats: StorageAccessStats::default(),
             aborted: Some(error),
         }
     }
@@ -1,7 +1,7 @@
-use crate::logic::types::ReceiptIndex;
-use crate::logic::{External, StorageGetMode, ValuePtr};
+use crate::logic::types::{PromiseYieldStatus, ReceiptIndex};
+use crate::logic::{External, HostError, StorageGetMode, ValuePtr};
 use near_primitives_core::hash::{hash, CryptoHash};
-use near_primitives_core::types::{AccountId, Balance, Gas, GasWeight};
+use near_primitives_core::types::{AccountId, Balance, BlockHeight, Gas, GasWeight};
 use std::collections::HashMap;
 
 #[derive(serde::Serialize)]
@@ -77,9 +77,21 @@ pub struct MockedExternal {
     pub fake_trie: HashMap<Vec<u8>, Vec<u8>>,
     pub validators: HashMap<AccountId, Balance>,
     pub action_log: Vec<MockAction>,
+    /// Yields created by earlier function calls, mapped to the height at which they time out.
+    pub pending_yields: HashMap<CryptoHash, BlockHeight>,
+    storage_iterators: Vec<MockedStorageIterator>,
     data_count: u64,
 }
 
+/// Iterator over the keys `from <= key < end` of the fake trie which also start with `prefix`.
+#[derive(Clone)]
+struct MockedStorageIterator {
+    prefix: Vec<u8>,
+    /// The key to continue from, `None` once the iterator is exhausted.
+    from: Option<Vec<u8>>,
+    end: Option<Vec<u8>>,
+}
+
 pub struct MockedValuePtr {
     value: Vec<u8>,
 }
@@ -138,6 +150,48 @@ impl External for MockedExternal {
         Ok(self.fake_trie.contains_key(key))
     }
 
+    fn storage_iter_prefix(&mut self, prefix: &[u8]) -> Result<u64> {
+        self.storage_iterators.push(MockedStorageIterator {
+            prefix: prefix.to_vec(),
+            from: Some(prefix.to_vec()),
+            end: None,
+        });
+        Ok(self.storage_iterators.len() as u64 - 1)
+    }
+
+    fn storage_iter_range(&mut self, start: &[u8], end: &[u8]) -> Result<u64> {
+        self.storage_iterators.push(MockedStorageIterator {
+            prefix: vec![],
+            from: Some(start.to_vec()),
+            end: Some(end.to_vec()),
+        });
+        Ok(self.storage_iterators.len() as u64 - 1)
+    }
+
+    fn storage_iter_next(&mut self, iterator_id: u64) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let iterator = self
+            .storage_iterators
+            .get_mut(iterator_id as usize)
+            .ok_or(HostError::InvalidIteratorIndex { iterator_index: iterator_id })?;
+        let Some(from) = &iterator.from else { return Ok(None) };
+        let next = self
+            .fake_trie
+            .iter()
+            .filter(|(key, _)| {
+                *key >= from
+                    && key.starts_with(&iterator.prefix)
+                    && iterator.end.as_ref().map_or(true, |end| *key < end)
+            })
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(key, value)| (key.clone(), value.clone()));
+        iterator.from = next.as_ref().map(|(key, _)| {
+            let mut from = key.clone();
+            from.push(0);
+            from
+        });
+        Ok(next)
+    }
+
     fn generate_data_id(&mut self) -> CryptoHash {
         // Generates some hash for the data ID to receive data. This hash should not be functionally
         // used in any mocked contexts.
@@ -202,6 +256,25 @@ impl External for MockedExternal {
         Ok(false)
     }
 
+    fn promise_yield_status(&self, data_id: CryptoHash) -> Result<PromiseYieldStatus> {
+        if let Some(&expires_at) = self.pending_yields.get(&data_id) {
+            return Ok(PromiseYieldStatus::Pending { expires_at });
+        }
+        let mut status = PromiseYieldStatus::NotFound;
+        for action in &self.action_log {
+            match action {
+                MockAction::YieldCreate { data_id: did, .. } if *did == data_id => {
+                    status = PromiseYieldStatus::Created;
+                }
+                MockAction::YieldResume { data_id: did, .. } if *did == data_id => {
+                    status = PromiseYieldStatus::NotFound;
+                }
+                _ => {}
+            }
+        }
+        Ok(status)
+    }
+
     fn append_action_create_account(
         &mut self,
         receipt_index: ReceiptIndex,
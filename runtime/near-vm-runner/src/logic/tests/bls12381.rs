@@ -0,0 +1,227 @@
+use crate::logic::tests::vm_logic_builder::{TestVMLogic, VMLogicBuilder};
+use crate::logic::{HostError, VMLogicError};
+
+/// Generator of G1 in the uncompressed `x || y` encoding.
+const G1: &str = concat!(
+    "17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb",
+    "08b3f481e3aaa0f1a09e30ed741d8ae4fcf5e095d5d00af600db18cb2c04b3edd03cc744a2888ae40caa232946c5e7e1",
+);
+/// Negation of [`G1`].
+const NEG_G1: &str = concat!(
+    "17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb",
+    "114d1d6855d545a8aa7d76c8cf2e21f267816aef1db507c96655b9d5caac42364e6f38ba0ecb751bad54dcd6b939c2ca",
+);
+/// `2 * G1`.
+const G1_DOUBLE: &str = concat!(
+    "0572cbea904d67468808c8eb50a9450c9721db309128012543902d0ac358a62ae28f75bb8f1c7c42c39a8c5529bf0f4e",
+    "166a9d8cabc673a322fda673779d8e3822ba3ecb8670e461f73bb9021d5fd76a4c56d9d4cd16bd1bba86881979749d28",
+);
+/// Generator of G2 in the uncompressed `x.c1 || x.c0 || y.c1 || y.c0` encoding.
+const G2: &str = concat!(
+    "13e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e",
+    "024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8",
+    "0606c4a02ea734cc32acd2b02bc28b99cb3e287e85a763af267492ab572e99ab3f370d275cec1da1aaa9075ff05f79be",
+    "0ce5d527727d6e118cc9cdc6da2e351aadfd9baa8cbdd3a76d429a695160d12c923ac9cc3baca289e193548608b82801",
+);
+/// The field modulus, which is not a canonical encoding of an Fp element.
+const P: &str = "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab";
+
+fn point(s: &str) -> Vec<u8> {
+    hex::decode(s).unwrap()
+}
+
+fn infinity(len: usize) -> Vec<u8> {
+    let mut res = vec![0u8; len];
+    res[0] = 0x40;
+    res
+}
+
+fn scalar(value: u64) -> Vec<u8> {
+    let mut res = value.to_le_bytes().to_vec();
+    res.resize(32, 0);
+    res
+}
+
+#[track_caller]
+fn check_result<T, U>(
+    actual: Result<T, VMLogicError>,
+    expected: Result<U, &str>,
+) -> Option<(T, U)> {
+    match (actual, expected) {
+        (Ok(actual), Ok(expected)) => Some((actual, expected)),
+        (Err(VMLogicError::HostError(HostError::BLS12381InvalidInput { msg: err })), Err(msg)) => {
+            assert!(err.contains(msg), "expected `{msg}` error, got {err}");
+            None
+        }
+        (Ok(_), Err(msg)) => panic!("expected `{msg}` error"),
+        (Err(err), _) => panic!("unexpected error: `{}`", err),
+    }
+}
+
+type RegisterFn = fn(&mut TestVMLogic<'_>, u64, u64, u64) -> Result<(), VMLogicError>;
+
+#[track_caller]
+fn check(f: RegisterFn, input: &[u8], expected: Result<&[u8], &str>) {
+    let mut logic_builder = VMLogicBuilder::default();
+    let mut logic = logic_builder.build();
+    let input = logic.internal_mem_write(input);
+
+    let res = f(&mut logic, input.len, input.ptr, 0);
+    if let Some(((), expected)) = check_result(res, expected) {
+        let got = logic.registers().get_for_free(0).unwrap();
+        assert_eq!(hex::encode(expected), hex::encode(got));
+    }
+}
+
+/// Runs `f` on `input` and returns the content of the output register.
+#[track_caller]
+fn run(f: RegisterFn, input: &[u8]) -> Vec<u8> {
+    let mut logic_builder = VMLogicBuilder::default();
+    let mut logic = logic_builder.build();
+    let input = logic.internal_mem_write(input);
+    f(&mut logic, input.len, input.ptr, 0).unwrap();
+    logic.registers().get_for_free(0).unwrap().to_vec()
+}
+
+#[test]
+fn test_bls12381_p1_sum() {
+    let f: RegisterFn = |logic, len, ptr, register| logic.bls12381_p1_sum(len, ptr, register);
+    let g1 = point(G1);
+
+    check(f, &[], Ok(&infinity(96)));
+    check(f, &[&[0][..], &g1[..]].concat(), Ok(&g1));
+    check(f, &[&[0][..], &g1[..], &[0][..], &g1[..]].concat(), Ok(&point(G1_DOUBLE)));
+    check(f, &[&[0][..], &g1[..], &[1][..], &g1[..]].concat(), Ok(&infinity(96)));
+    check(f, &[&[1][..], &g1[..]].concat(), Ok(&point(NEG_G1)));
+    check(f, &[&[0][..], &infinity(96)[..], &[0][..], &g1[..]].concat(), Ok(&g1));
+
+    check(f, &g1, Err("slice of size 96 cannot be precisely split into chunks of size 97"));
+    check(f, &[&[2][..], &g1[..]].concat(), Err("invalid bool"));
+    let mut not_on_curve = g1.clone();
+    not_on_curve[95] ^= 1;
+    check(f, &[&[0][..], &not_on_curve[..]].concat(), Err("invalid g1"));
+    let mut compressed = g1.clone();
+    compressed[0] |= 0x80;
+    check(f, &[&[0][..], &compressed[..]].concat(), Err("compressed g1 point"));
+}
+
+#[test]
+fn test_bls12381_p2_sum() {
+    let f: RegisterFn = |logic, len, ptr, register| logic.bls12381_p2_sum(len, ptr, register);
+    let g2 = point(G2);
+
+    check(f, &[], Ok(&infinity(192)));
+    check(f, &[&[0][..], &g2[..]].concat(), Ok(&g2));
+    check(f, &[&[0][..], &g2[..], &[1][..], &g2[..]].concat(), Ok(&infinity(192)));
+
+    let double = run(f, &[&[0][..], &g2[..], &[0][..], &g2[..]].concat());
+    let multiexp: RegisterFn =
+        |logic, len, ptr, register| logic.bls12381_g2_multiexp(len, ptr, register);
+    check(multiexp, &[&g2[..], &scalar(2)[..]].concat(), Ok(&double));
+
+    check(f, &g2, Err("slice of size 192 cannot be precisely split into chunks of size 193"));
+    check(f, &[&[7][..], &g2[..]].concat(), Err("invalid bool"));
+    let mut not_on_curve = g2.clone();
+    not_on_curve[191] ^= 1;
+    check(f, &[&[0][..], &not_on_curve[..]].concat(), Err("invalid g2"));
+}
+
+#[test]
+fn test_bls12381_g1_multiexp() {
+    let f: RegisterFn = |logic, len, ptr, register| logic.bls12381_g1_multiexp(len, ptr, register);
+    let g1 = point(G1);
+
+    check(f, &[], Ok(&infinity(96)));
+    check(f, &[&g1[..], &scalar(1)[..]].concat(), Ok(&g1));
+    check(f, &[&g1[..], &scalar(0)[..]].concat(), Ok(&infinity(96)));
+    check(f, &[&g1[..], &scalar(2)[..]].concat(), Ok(&point(G1_DOUBLE)));
+    check(f, &[&g1[..], &scalar(1)[..], &g1[..], &scalar(1)[..]].concat(), Ok(&point(G1_DOUBLE)));
+    check(
+        f,
+        &[&point(NEG_G1)[..], &scalar(1)[..], &g1[..], &scalar(1)[..]].concat(),
+        Ok(&infinity(96)),
+    );
+
+    check(f, &g1, Err("slice of size 96 cannot be precisely split into chunks of size 128"));
+    let mut not_on_curve = g1.clone();
+    not_on_curve[95] ^= 1;
+    check(f, &[&not_on_curve[..], &scalar(1)[..]].concat(), Err("invalid g1"));
+}
+
+#[test]
+fn test_bls12381_g2_multiexp() {
+    let f: RegisterFn = |logic, len, ptr, register| logic.bls12381_g2_multiexp(len, ptr, register);
+    let g2 = point(G2);
+
+    check(f, &[], Ok(&infinity(192)));
+    check(f, &[&g2[..], &scalar(1)[..]].concat(), Ok(&g2));
+    check(f, &[&g2[..], &scalar(0)[..]].concat(), Ok(&infinity(192)));
+
+    check(f, &g2, Err("slice of size 192 cannot be precisely split into chunks of size 224"));
+}
+
+#[test]
+fn test_bls12381_map_fp_to_g1() {
+    let f: RegisterFn = |logic, len, ptr, register| logic.bls12381_map_fp_to_g1(len, ptr, register);
+    let multiexp: RegisterFn =
+        |logic, len, ptr, register| logic.bls12381_g1_multiexp(len, ptr, register);
+
+    check(f, &[], Ok(&[]));
+    let mut fp = vec![0u8; 48];
+    fp[47] = 5;
+    let res = run(f, &[&fp[..], &fp[..]].concat());
+    assert_eq!(res.len(), 2 * 96);
+    assert_eq!(res[..96], res[96..]);
+    // The multiexp checks that the point is in the subgroup.
+    check(multiexp, &[&res[..96], &scalar(1)[..]].concat(), Ok(&res[..96]));
+
+    check(f, &[0u8; 47], Err("slice of size 47 cannot be precisely split into chunks of size 48"));
+    check(f, &point(P), Err("invalid fp"));
+}
+
+#[test]
+fn test_bls12381_map_fp2_to_g2() {
+    let f: RegisterFn =
+        |logic, len, ptr, register| logic.bls12381_map_fp2_to_g2(len, ptr, register);
+    let multiexp: RegisterFn =
+        |logic, len, ptr, register| logic.bls12381_g2_multiexp(len, ptr, register);
+
+    check(f, &[], Ok(&[]));
+    let mut fp2 = vec![0u8; 96];
+    fp2[47] = 3;
+    fp2[95] = 5;
+    let res = run(f, &fp2);
+    assert_eq!(res.len(), 192);
+    // The multiexp checks that the point is in the subgroup.
+    check(multiexp, &[&res[..], &scalar(1)[..]].concat(), Ok(&res));
+
+    check(f, &[0u8; 48], Err("slice of size 48 cannot be precisely split into chunks of size 96"));
+    check(f, &[&point(P)[..], &[0u8; 48][..]].concat(), Err("invalid fp"));
+}
+
+#[test]
+fn test_bls12381_pairing_check() {
+    #[track_caller]
+    fn check(input: &[u8], expected: Result<u64, &str>) {
+        let mut logic_builder = VMLogicBuilder::default();
+        let mut logic = logic_builder.build();
+        let input = logic.internal_mem_write(input);
+
+        let res = logic.bls12381_pairing_check(input.len, input.ptr);
+        if let Some((res, expected)) = check_result(res, expected) {
+            assert_eq!(res, expected);
+        }
+    }
+    let (g1, neg_g1, g2) = (point(G1), point(NEG_G1), point(G2));
+
+    check(&[], Ok(1));
+    check(&[&g1[..], &g2[..]].concat(), Ok(0));
+    check(&[&g1[..], &g2[..], &neg_g1[..], &g2[..]].concat(), Ok(1));
+    check(&[&infinity(96)[..], &g2[..]].concat(), Ok(1));
+    check(&[&g1[..], &infinity(192)[..]].concat(), Ok(1));
+
+    check(&g1, Err("slice of size 96 cannot be precisely split into chunks of size 288"));
+    let mut not_on_curve = g1.clone();
+    not_on_curve[95] ^= 1;
+    check(&[&not_on_curve[..], &g2[..]].concat(), Err("invalid g1"));
+}
@@ -241,6 +241,7 @@ fn check_action_gas_exceeds_limit(
         send_sir: gas_limit / num_action_paid + 1,
         send_not_sir: gas_limit / num_action_paid + 10,
         execution: 1, // exec part is `used`, make it small
+        execution_compute: None,
     };
     let mut logic_builder = VMLogicBuilder::default();
     logic_builder.config.limit_config.max_gas_burnt = gas_limit;
@@ -293,6 +294,7 @@ fn check_action_gas_exceeds_attached(
         send_sir: 1,      // make burnt gas small
         send_not_sir: 10, // make it easy to distinguish `sir` / `not_sir`
         execution: gas_attached / num_action_paid + 1,
+        execution_compute: None,
     };
     let mut logic_builder = VMLogicBuilder::default();
     logic_builder.config.limit_config.max_gas_burnt = gas_limit;
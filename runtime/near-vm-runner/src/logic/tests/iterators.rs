@@ -1,9 +1,10 @@
-use crate::logic::tests::vm_logic_builder::VMLogicBuilder;
+use crate::logic::tests::vm_logic_builder::{TestVMLogic, VMLogicBuilder};
 use crate::logic::{HostError, VMLogicError};
 
 #[test]
 fn test_iterator_deprecated() {
     let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.config.storage_iterators = false;
     let mut logic = logic_builder.build();
     assert_eq!(
         Err(VMLogicError::HostError(HostError::Deprecated {
@@ -24,3 +25,112 @@ fn test_iterator_deprecated() {
         logic.storage_iter_next(0, 0, 1)
     );
 }
+
+fn write_keys(logic: &mut TestVMLogic, keys: &[&[u8]]) {
+    for key in keys {
+        let key = logic.internal_mem_write(key);
+        let value = logic.internal_mem_write(b"value");
+        logic.storage_write(key.len, key.ptr, value.len, value.ptr, 0).unwrap();
+    }
+}
+
+#[track_caller]
+fn assert_iterates(logic: &mut TestVMLogic, iterator_id: u64, want: &[&[u8]]) {
+    for key in want {
+        assert_eq!(logic.storage_iter_next(iterator_id, 0, 1), Ok(1));
+        logic.assert_read_register(key, 0);
+        logic.assert_read_register(b"value", 1);
+    }
+    assert_eq!(logic.storage_iter_next(iterator_id, 0, 1), Ok(0));
+    // Exhausted iterators stay exhausted.
+    assert_eq!(logic.storage_iter_next(iterator_id, 0, 1), Ok(0));
+}
+
+#[test]
+fn test_iterator_prefix() {
+    let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.config.storage_iterators = true;
+    let mut logic = logic_builder.build();
+    write_keys(&mut logic, &[b"aa", b"b", b"ab", b"a", b"ba"]);
+
+    let prefix = logic.internal_mem_write(b"a");
+    let iterator_id = logic.storage_iter_prefix(prefix.len, prefix.ptr).unwrap();
+    assert_iterates(&mut logic, iterator_id, &[b"a", b"aa", b"ab"]);
+
+    let prefix = logic.internal_mem_write(b"c");
+    let iterator_id = logic.storage_iter_prefix(prefix.len, prefix.ptr).unwrap();
+    assert_iterates(&mut logic, iterator_id, &[]);
+}
+
+#[test]
+fn test_iterator_range() {
+    let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.config.storage_iterators = true;
+    let mut logic = logic_builder.build();
+    write_keys(&mut logic, &[b"aa", b"b", b"ab", b"a", b"ba"]);
+
+    let start = logic.internal_mem_write(b"aa");
+    let end = logic.internal_mem_write(b"ba");
+    let iterator_id = logic.storage_iter_range(start.len, start.ptr, end.len, end.ptr).unwrap();
+    assert_iterates(&mut logic, iterator_id, &[b"aa", b"ab", b"b"]);
+
+    // Empty ranges yield nothing.
+    let iterator_id = logic.storage_iter_range(end.len, end.ptr, start.len, start.ptr).unwrap();
+    assert_iterates(&mut logic, iterator_id, &[]);
+}
+
+#[test]
+fn test_iterator_sees_writes() {
+    let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.config.storage_iterators = true;
+    let mut logic = logic_builder.build();
+    write_keys(&mut logic, &[b"a", b"c"]);
+
+    let prefix = logic.internal_mem_write(b"");
+    let iterator_id = logic.storage_iter_prefix(prefix.len, prefix.ptr).unwrap();
+    assert_eq!(logic.storage_iter_next(iterator_id, 0, 1), Ok(1));
+    logic.assert_read_register(b"a", 0);
+
+    // Keys written after the cursor are visible, removed ones are not.
+    write_keys(&mut logic, &[b"b"]);
+    let key = logic.internal_mem_write(b"c");
+    logic.storage_remove(key.len, key.ptr, 0).unwrap();
+    assert_iterates(&mut logic, iterator_id, &[b"b"]);
+}
+
+#[test]
+fn test_iterator_errors() {
+    let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.config.storage_iterators = true;
+    let mut logic = logic_builder.build();
+    assert_eq!(
+        logic.storage_iter_next(0, 0, 1),
+        Err(HostError::InvalidIteratorIndex { iterator_index: 0 }.into())
+    );
+    let prefix = logic.internal_mem_write(b"");
+    let iterator_id = logic.storage_iter_prefix(prefix.len, prefix.ptr).unwrap();
+    assert_eq!(
+        logic.storage_iter_next(iterator_id, 0, 0),
+        Err(HostError::MemoryAccessViolation.into())
+    );
+}
+
+#[test]
+fn test_iterator_limit() {
+    let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.config.storage_iterators = true;
+    logic_builder.config.limit_config.max_storage_iterators = 2;
+    let mut logic = logic_builder.build();
+    let start = logic.internal_mem_write(b"a");
+    let end = logic.internal_mem_write(b"b");
+    logic.storage_iter_prefix(start.len, start.ptr).unwrap();
+    logic.storage_iter_range(start.len, start.ptr, end.len, end.ptr).unwrap();
+    assert_eq!(
+        logic.storage_iter_prefix(start.len, start.ptr),
+        Err(HostError::NumberOfIteratorsExceeded { limit: 2 }.into())
+    );
+    assert_eq!(
+        logic.storage_iter_range(start.len, start.ptr, end.len, end.ptr),
+        Err(HostError::NumberOfIteratorsExceeded { limit: 2 }.into())
+    );
+}
@@ -1,4 +1,5 @@
 mod alt_bn128;
+mod bls12381;
 mod context;
 mod ed25519_verify;
 mod gas_counter;
@@ -8,6 +9,7 @@ mod logs;
 mod miscs;
 mod promises;
 mod registers;
+mod secp256k1_verify;
 mod storage_read_write;
 mod storage_usage;
 mod view_method;
@@ -2,8 +2,10 @@ use crate::logic::mocks::mock_external::MockedExternal;
 use crate::logic::tests::helpers::*;
 use crate::logic::tests::vm_logic_builder::VMLogicBuilder;
 use crate::logic::types::PromiseResult;
+use crate::logic::HostError;
 
 use near_crypto::PublicKey;
+use near_primitives_core::hash::CryptoHash;
 use serde_json;
 
 fn vm_receipts<'a>(ext: &'a MockedExternal) -> Vec<impl serde::Serialize + 'a> {
@@ -31,6 +33,30 @@ fn test_promise_results() {
     logic.assert_read_register(b"test", 0);
 }
 
+#[test]
+fn test_promise_result_to_memory() {
+    let promise_results = vec![
+        PromiseResult::Successful(b"test".to_vec()),
+        PromiseResult::Failed,
+        PromiseResult::NotReady,
+    ];
+
+    let mut logic_builder = VMLogicBuilder::default();
+    logic_builder.promise_results = promise_results;
+    let mut logic = logic_builder.build();
+    let out = logic.internal_mem_write(&[0; 6]);
+
+    assert_eq!(logic.promise_result_to_memory(0, out.len, out.ptr), Ok(4));
+    assert_eq!(logic.internal_mem_read(out.ptr, out.len), b"test\0\0");
+    assert_eq!(logic.promise_result_to_memory(1, out.len, out.ptr), Ok(u64::MAX));
+    assert_eq!(logic.promise_result_to_memory(2, out.len, out.ptr), Ok(u64::MAX));
+    logic.promise_result_to_memory(3, out.len, out.ptr).expect_err("result index is out of range");
+
+    let out = logic.internal_mem_write(&[0; 2]);
+    assert_eq!(logic.promise_result_to_memory(0, out.len, out.ptr), Ok(4));
+    assert_eq!(logic.internal_mem_read(out.ptr, out.len), b"te");
+}
+
 #[test]
 fn test_promise_batch_action_function_call() {
     let mut logic_builder = VMLogicBuilder::default();
@@ -630,3 +656,48 @@ fn test_promise_batch_then() {
         ]"#]]
     .assert_eq(&serde_json::to_string_pretty(&vm_receipts(&logic_builder.ext)).unwrap());
 }
+
+#[test]
+fn test_promise_yield_status() {
+    let mut logic_builder = VMLogicBuilder::default();
+    let earlier_data_id = CryptoHash::hash_bytes(b"earlier");
+    logic_builder.ext.pending_yields.insert(earlier_data_id, 15);
+    let timeout = logic_builder.config.limit_config.yield_timeout_length_in_blocks;
+    let mut logic = logic_builder.build();
+
+    // Yields created by an earlier call report the blocks left until their timeout.
+    let earlier = logic.internal_mem_write(earlier_data_id.as_bytes());
+    assert_eq!(logic.promise_yield_status(earlier.len, earlier.ptr), Ok(15 - 10));
+
+    // Yields created by the current call have the full timeout ahead of them.
+    let method_name = logic.internal_mem_write(b"callback");
+    logic
+        .promise_yield_create(method_name.len, method_name.ptr, 0, 0, 0, 1, 0)
+        .expect("should create a yield");
+    assert_eq!(logic.promise_yield_status(u64::MAX, 0), Ok(timeout));
+
+    // Once resumed, the yield is no longer pending.
+    let payload = logic.internal_mem_write(b"payload");
+    assert_eq!(logic.promise_yield_resume(u64::MAX, 0, payload.len, payload.ptr), Ok(1));
+    assert_eq!(logic.promise_yield_status(u64::MAX, 0), Ok(u64::MAX));
+
+    let unknown = logic.internal_mem_write(CryptoHash::default().as_bytes());
+    assert_eq!(logic.promise_yield_status(unknown.len, unknown.ptr), Ok(u64::MAX));
+
+    let malformed = logic.internal_mem_write(b"malformed");
+    assert_eq!(
+        logic.promise_yield_status(malformed.len, malformed.ptr),
+        Err(HostError::DataIdMalformed.into())
+    );
+}
+
+#[test]
+fn test_promise_yield_status_prohibited_in_view() {
+    let mut logic_builder = VMLogicBuilder::view();
+    let mut logic = logic_builder.build();
+    let data_id = logic.internal_mem_write(CryptoHash::default().as_bytes());
+    assert_eq!(
+        logic.promise_yield_status(data_id.len, data_id.ptr),
+        Err(HostError::ProhibitedInView { method_name: "promise_yield_status".to_string() }.into())
+    );
+}
@@ -0,0 +1,112 @@
+use crate::logic::tests::vm_logic_builder::VMLogicBuilder;
+use crate::logic::{HostError, VMLogicError};
+
+/// Order of the secp256k1 curve.
+const CURVE_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+const MESSAGE_HASH: [u8; 32] = [7; 32];
+
+struct Signed {
+    signature: [u8; 64],
+    compressed: [u8; 33],
+    uncompressed: [u8; 65],
+}
+
+fn sign(secret: u8, message_hash: &[u8; 32]) -> Signed {
+    let secp = secp256k1::Secp256k1::new();
+    let secret_key = secp256k1::SecretKey::from_slice(&[secret; 32]).unwrap();
+    let public_key = secp256k1::PublicKey::from_secret_key(&secp, &secret_key);
+    let message = secp256k1::Message::from_slice(message_hash).unwrap();
+    let signature = secp.sign_ecdsa(&message, &secret_key);
+    Signed {
+        signature: signature.serialize_compact(),
+        compressed: public_key.serialize(),
+        uncompressed: public_key.serialize_uncompressed(),
+    }
+}
+
+/// Replaces `s` with `n - s`, which is an equally valid but malleated
+/// signature.
+fn malleate(signature: &[u8; 64]) -> [u8; 64] {
+    let mut res = *signature;
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let diff = CURVE_ORDER[i] as i16 - signature[32 + i] as i16 - borrow;
+        borrow = (diff < 0) as i16;
+        res[32 + i] = diff.rem_euclid(256) as u8;
+    }
+    res
+}
+
+#[track_caller]
+fn check(signature: &[u8], message: &[u8], public_key: &[u8], want: Result<u64, &str>) {
+    let mut logic_builder = VMLogicBuilder::default();
+    let mut logic = logic_builder.build();
+    let signature = logic.internal_mem_write(signature);
+    let message = logic.internal_mem_write(message);
+    let public_key = logic.internal_mem_write(public_key);
+
+    let res = logic.secp256k1_verify(
+        signature.len,
+        signature.ptr,
+        message.len,
+        message.ptr,
+        public_key.len,
+        public_key.ptr,
+    );
+    match (res, want) {
+        (Ok(got), Ok(want)) => assert_eq!(got, want),
+        (
+            Err(VMLogicError::HostError(HostError::Secp256k1VerifyInvalidInput { msg })),
+            Err(want),
+        ) => {
+            assert_eq!(msg, want)
+        }
+        (res, want) => panic!("expected {want:?}, got {res:?}"),
+    }
+}
+
+#[test]
+fn test_secp256k1_verify() {
+    let signed = sign(0x42, &MESSAGE_HASH);
+    let raw = &signed.uncompressed[1..];
+
+    check(&signed.signature, &MESSAGE_HASH, &signed.compressed, Ok(1));
+    check(&signed.signature, &MESSAGE_HASH, &signed.uncompressed, Ok(1));
+    check(&signed.signature, &MESSAGE_HASH, raw, Ok(1));
+
+    // Wrong message or signer.
+    check(&signed.signature, &[8; 32], &signed.compressed, Ok(0));
+    check(&signed.signature, &MESSAGE_HASH, &sign(0x43, &MESSAGE_HASH).compressed, Ok(0));
+
+    // Malleated signatures with a high `s` are rejected.
+    check(&malleate(&signed.signature), &MESSAGE_HASH, &signed.compressed, Ok(0));
+
+    // Keys and signatures which can't be decoded are not valid either.
+    let mut not_on_curve = signed.compressed;
+    not_on_curve[0] = 0x05;
+    check(&signed.signature, &MESSAGE_HASH, &not_on_curve, Ok(0));
+    check(&[0xff; 64], &MESSAGE_HASH, &signed.compressed, Ok(0));
+}
+
+#[test]
+fn test_secp256k1_verify_invalid_input() {
+    let signed = sign(0x42, &MESSAGE_HASH);
+
+    check(
+        &signed.signature[..63],
+        &MESSAGE_HASH,
+        &signed.compressed,
+        Err("invalid signature length"),
+    );
+    check(&signed.signature, &[7; 31], &signed.compressed, Err("invalid message hash length"));
+    check(
+        &signed.signature,
+        &MESSAGE_HASH,
+        &signed.compressed[..32],
+        Err("invalid public key length"),
+    );
+}
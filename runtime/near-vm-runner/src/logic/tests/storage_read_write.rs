@@ -1,5 +1,6 @@
 use crate::logic::tests::vm_logic_builder::VMLogicBuilder;
 use crate::logic::{External, StorageGetMode};
+use crate::StorageAccessStats;
 
 #[test]
 fn test_storage_write_with_register() {
@@ -63,3 +64,63 @@ fn test_storage_has_key_with_register() {
 
     assert_eq!(logic.storage_has_key(u64::MAX, 1 as _), Ok(1));
 }
+
+#[test]
+fn test_storage_read_to_memory() {
+    let mut logic_builder = VMLogicBuilder::default();
+
+    let key: &[u8] = b"foo";
+    let val: &[u8] = b"bar";
+    logic_builder.ext.storage_set(key, val).unwrap();
+
+    let mut logic = logic_builder.build();
+    let key = logic.internal_mem_write(key);
+    let missing_key = logic.internal_mem_write(b"baz");
+    let out = logic.internal_mem_write(&[0; 8]);
+
+    assert_eq!(logic.storage_read_to_memory(key.len, key.ptr, out.len, out.ptr), Ok(3));
+    assert_eq!(logic.internal_mem_read(out.ptr, out.len), b"bar\0\0\0\0\0");
+
+    // A value larger than the buffer is truncated, the full length is still returned.
+    let out = logic.internal_mem_write(&[0; 3]);
+    assert_eq!(logic.storage_read_to_memory(key.len, key.ptr, 2, out.ptr), Ok(3));
+    assert_eq!(logic.internal_mem_read(out.ptr, out.len), b"ba\0");
+
+    assert_eq!(
+        logic.storage_read_to_memory(missing_key.len, missing_key.ptr, out.len, out.ptr),
+        Ok(u64::MAX)
+    );
+    assert_eq!(logic.internal_mem_read(out.ptr, out.len), b"ba\0");
+}
+
+#[test]
+fn test_storage_access_stats() {
+    let mut logic_builder = VMLogicBuilder::default();
+    let mut logic = logic_builder.build();
+    let key = logic.internal_mem_write(b"foo");
+    let val = logic.internal_mem_write(b"barbaz");
+    let missing_key = logic.internal_mem_write(b"qux");
+
+    logic.storage_write(key.len, key.ptr, val.len, val.ptr, 0).expect("storage write ok");
+    logic.storage_read(key.len, key.ptr, 0).expect("storage read ok");
+    logic.storage_read(missing_key.len, missing_key.ptr, 0).expect("storage read ok");
+    logic.storage_has_key(key.len, key.ptr).expect("storage has key ok");
+    logic.storage_has_key(missing_key.len, missing_key.ptr).expect("storage has key ok");
+    logic.storage_remove(key.len, key.ptr, 0).expect("storage remove ok");
+    logic.storage_remove(missing_key.len, missing_key.ptr, 0).expect("storage remove ok");
+
+    let outcome = logic.compute_outcome();
+    assert_eq!(
+        outcome.storage_stats,
+        StorageAccessStats {
+            reads: 2,
+            read_bytes: 9 + 3,
+            writes: 1,
+            written_bytes: 9,
+            removals: 1,
+            removed_bytes: 9,
+            trie_node_db_reads: 0,
+            trie_node_mem_reads: 0,
+        }
+    );
+}
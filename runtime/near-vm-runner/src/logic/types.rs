@@ -37,3 +37,17 @@ pub enum PromiseResult {
     Successful(Vec<u8>),
     Failed,
 }
+
+/// Status of a yielded promise created by `promise_yield_create`, as seen by the account which
+/// created it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PromiseYieldStatus {
+    /// There is no pending yield for the given `data_id`. It was either never created by this
+    /// account, or it has already been resumed or timed out.
+    NotFound,
+    /// The yield was created by the current function call and hasn't been resumed yet.
+    Created,
+    /// The yield was created by an earlier function call and times out at the given height,
+    /// unless resumed before.
+    Pending { expires_at: BlockHeight },
+}
@@ -92,6 +92,25 @@ impl<'a> Memory<'a> {
         self.0.write_memory(offset, buf).map_err(|_| HostError::MemoryAccessViolation.into())
     }
 
+    /// Copies the result of a host function into guest memory, skipping the
+    /// round trip through a register.
+    ///
+    /// Only the first `len` bytes of the result are copied.  Returns the length
+    /// of the whole result so that the guest can detect truncation.
+    pub(super) fn set_result(
+        &mut self,
+        gas_counter: &mut GasCounter,
+        offset: u64,
+        len: u64,
+        result: &[u8],
+    ) -> Result<u64> {
+        let copied = &result[..result.len().min(usize::try_from(len).unwrap_or(usize::MAX))];
+        gas_counter.pay_base(result_to_memory_base)?;
+        gas_counter.pay_per(result_to_memory_byte, copied.len() as _)?;
+        self.0.write_memory(offset, copied).map_err(|_| HostError::MemoryAccessViolation)?;
+        Ok(result.len() as u64)
+    }
+
     #[cfg(test)]
     pub(super) fn set_for_free(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
         self.0.write_memory(offset, buf).map_err(|_| HostError::MemoryAccessViolation.into())
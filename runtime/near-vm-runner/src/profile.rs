@@ -170,6 +170,75 @@ impl BorshSerialize for ProfileDataV3 {
     }
 }
 
+/// Storage operations made while executing a contract and the number of bytes
+/// they accessed.
+///
+/// Stored in the execution metadata next to the gas profile, so that state
+/// growth and IO can be attributed to contracts without replaying receipts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct StorageAccessStats {
+    /// Number of reads of existing keys, including `storage_has_key` and
+    /// `storage_iter_next`.
+    pub reads: u64,
+    /// Total size of the keys and values read.
+    pub read_bytes: u64,
+    /// Number of `storage_write` calls.
+    pub writes: u64,
+    /// Total size of the keys and values written.
+    pub written_bytes: u64,
+    /// Number of `storage_remove` calls which removed an existing key.
+    pub removals: u64,
+    /// Total size of the keys and values removed.
+    pub removed_bytes: u64,
+    /// Number of trie nodes read from the database.
+    pub trie_node_db_reads: u64,
+    /// Number of trie nodes read from the chunk cache.
+    pub trie_node_mem_reads: u64,
+}
+
+impl StorageAccessStats {
+    /// Test instance with unique numbers in each field.
+    pub fn test() -> Self {
+        Self {
+            reads: 1,
+            read_bytes: 2,
+            writes: 3,
+            written_bytes: 4,
+            removals: 5,
+            removed_bytes: 6,
+            trie_node_db_reads: 7,
+            trie_node_mem_reads: 8,
+        }
+    }
+
+    pub fn merge(&mut self, other: &StorageAccessStats) {
+        self.reads = self.reads.saturating_add(other.reads);
+        self.read_bytes = self.read_bytes.saturating_add(other.read_bytes);
+        self.writes = self.writes.saturating_add(other.writes);
+        self.written_bytes = self.written_bytes.saturating_add(other.written_bytes);
+        self.removals = self.removals.saturating_add(other.removals);
+        self.removed_bytes = self.removed_bytes.saturating_add(other.removed_bytes);
+        self.trie_node_db_reads = self.trie_node_db_reads.saturating_add(other.trie_node_db_reads);
+        self.trie_node_mem_reads =
+            self.trie_node_mem_reads.saturating_add(other.trie_node_mem_reads);
+    }
+
+    pub fn record_read(&mut self, bytes: usize) {
+        self.reads = self.reads.saturating_add(1);
+        self.read_bytes = self.read_bytes.saturating_add(bytes as u64);
+    }
+
+    pub fn record_write(&mut self, bytes: usize) {
+        self.writes = self.writes.saturating_add(1);
+        self.written_bytes = self.written_bytes.saturating_add(bytes as u64);
+    }
+
+    pub fn record_removal(&mut self, bytes: usize) {
+        self.removals = self.removals.saturating_add(1);
+        self.removed_bytes = self.removed_bytes.saturating_add(bytes as u64);
+    }
+}
+
 /// Fixed index of an action cost for borsh (de)serialization.
 ///
 /// We use borsh to store profiles on the DB and borsh is quite fragile with
@@ -266,20 +335,20 @@ mod test {
             keccak256_byte -> 18 [0% host]
             keccak512_base -> 19 [0% host]
             keccak512_byte -> 20 [0% host]
-            ripemd160_base -> 21 [1% host]
-            ripemd160_block -> 22 [1% host]
-            ecrecover_base -> 23 [1% host]
-            log_base -> 24 [1% host]
-            log_byte -> 25 [1% host]
-            storage_write_base -> 26 [1% host]
-            storage_write_key_byte -> 27 [1% host]
-            storage_write_value_byte -> 28 [1% host]
-            storage_write_evicted_byte -> 29 [1% host]
-            storage_read_base -> 30 [1% host]
-            storage_read_key_byte -> 31 [1% host]
-            storage_read_value_byte -> 32 [1% host]
-            storage_remove_base -> 33 [1% host]
-            storage_remove_key_byte -> 34 [1% host]
+            ripemd160_base -> 21 [0% host]
+            ripemd160_block -> 22 [0% host]
+            ecrecover_base -> 23 [0% host]
+            log_base -> 24 [0% host]
+            log_byte -> 25 [0% host]
+            storage_write_base -> 26 [0% host]
+            storage_write_key_byte -> 27 [0% host]
+            storage_write_value_byte -> 28 [0% host]
+            storage_write_evicted_byte -> 29 [0% host]
+            storage_read_base -> 30 [0% host]
+            storage_read_key_byte -> 31 [0% host]
+            storage_read_value_byte -> 32 [0% host]
+            storage_remove_base -> 33 [0% host]
+            storage_remove_key_byte -> 34 [0% host]
             storage_remove_ret_value_byte -> 35 [1% host]
             storage_has_key_base -> 36 [1% host]
             storage_has_key_byte -> 37 [1% host]
@@ -287,29 +356,47 @@ mod test {
             storage_iter_create_prefix_byte -> 39 [1% host]
             storage_iter_create_range_base -> 40 [1% host]
             storage_iter_create_from_byte -> 41 [1% host]
-            storage_iter_create_to_byte -> 42 [2% host]
-            storage_iter_next_base -> 43 [2% host]
-            storage_iter_next_key_byte -> 44 [2% host]
-            storage_iter_next_value_byte -> 45 [2% host]
-            touching_trie_node -> 46 [2% host]
-            read_cached_trie_node -> 47 [2% host]
-            promise_and_base -> 48 [2% host]
-            promise_and_per_promise -> 49 [2% host]
-            promise_return -> 50 [2% host]
-            validator_stake_base -> 51 [2% host]
-            validator_total_stake_base -> 52 [2% host]
-            alt_bn128_g1_multiexp_base -> 53 [2% host]
-            alt_bn128_g1_multiexp_element -> 54 [2% host]
-            alt_bn128_pairing_check_base -> 55 [2% host]
-            alt_bn128_pairing_check_element -> 56 [2% host]
-            alt_bn128_g1_sum_base -> 57 [2% host]
-            alt_bn128_g1_sum_element -> 58 [2% host]
-            ed25519_verify_base -> 59 [2% host]
-            ed25519_verify_byte -> 60 [2% host]
-            yield_create_base -> 61 [2% host]
-            yield_create_byte -> 62 [2% host]
-            yield_resume_base -> 63 [3% host]
-            yield_resume_byte -> 64 [3% host]
+            storage_iter_create_to_byte -> 42 [1% host]
+            storage_iter_next_base -> 43 [1% host]
+            storage_iter_next_key_byte -> 44 [1% host]
+            storage_iter_next_value_byte -> 45 [1% host]
+            touching_trie_node -> 46 [1% host]
+            read_cached_trie_node -> 47 [1% host]
+            promise_and_base -> 48 [1% host]
+            promise_and_per_promise -> 49 [1% host]
+            promise_return -> 50 [1% host]
+            validator_stake_base -> 51 [1% host]
+            validator_total_stake_base -> 52 [1% host]
+            alt_bn128_g1_multiexp_base -> 53 [1% host]
+            alt_bn128_g1_multiexp_element -> 54 [1% host]
+            alt_bn128_pairing_check_base -> 55 [1% host]
+            alt_bn128_pairing_check_element -> 56 [1% host]
+            alt_bn128_g1_sum_base -> 57 [1% host]
+            alt_bn128_g1_sum_element -> 58 [1% host]
+            ed25519_verify_base -> 59 [1% host]
+            ed25519_verify_byte -> 60 [1% host]
+            yield_create_base -> 61 [1% host]
+            yield_create_byte -> 62 [1% host]
+            yield_resume_base -> 63 [1% host]
+            yield_resume_byte -> 64 [1% host]
+            bls12381_p1_sum_base -> 65 [1% host]
+            bls12381_p1_sum_element -> 66 [1% host]
+            bls12381_p2_sum_base -> 67 [1% host]
+            bls12381_p2_sum_element -> 68 [1% host]
+            bls12381_g1_multiexp_base -> 69 [2% host]
+            bls12381_g1_multiexp_element -> 70 [2% host]
+            bls12381_g2_multiexp_base -> 71 [2% host]
+            bls12381_g2_multiexp_element -> 72 [2% host]
+            bls12381_map_fp_to_g1_base -> 73 [2% host]
+            bls12381_map_fp_to_g1_element -> 74 [2% host]
+            bls12381_map_fp2_to_g2_base -> 75 [2% host]
+            bls12381_map_fp2_to_g2_element -> 76 [2% host]
+            bls12381_pairing_base -> 77 [2% host]
+            bls12381_pairing_element -> 78 [2% host]
+            result_to_memory_base -> 79 [2% host]
+            result_to_memory_byte -> 80 [2% host]
+            secp256k1_verify_base -> 81 [2% host]
+            yield_status_base -> 82 [2% host]
             ------ Actions --------
             create_account -> 1000
             delete_account -> 1001
@@ -1,5 +1,6 @@
 mod cache;
 mod compile_errors;
+mod differential;
 mod fuzzers;
 mod regression_tests;
 mod rs_contract;
@@ -21,10 +21,10 @@ use std::sync::Arc;
 fn test_caches_compilation_error() {
     let config = test_vm_config();
     with_vm_variants(&config, |vm_kind: VMKind| {
-        // The cache is currently properly implemented only for NearVM
+        // The cache is currently properly implemented only for NearVM and Wasmtime
         match vm_kind {
-            VMKind::NearVm => {}
-            VMKind::Wasmer0 | VMKind::Wasmer2 | VMKind::Wasmtime => return,
+            VMKind::NearVm | VMKind::Wasmtime => {}
+            VMKind::Wasmer0 | VMKind::Wasmer2 => return,
         }
         let cache = MockContractRuntimeCache::default();
         let code = [42; 1000];
@@ -62,11 +62,6 @@ fn test_caches_compilation_error() {
 fn test_does_not_cache_io_error() {
     let config = test_vm_config();
     with_vm_variants(&config, |vm_kind: VMKind| {
-        match vm_kind {
-            VMKind::Wasmer0 | VMKind::Wasmer2 | VMKind::NearVm => {}
-            VMKind::Wasmtime => return,
-        }
-
         let code = near_test_contracts::trivial_contract();
         let code = ContractCode::new(code.to_vec(), None);
         let code_hash = *code.hash();
@@ -107,6 +102,50 @@ fn test_does_not_cache_io_error() {
     })
 }
 
+/// The in-memory cache is shared by the VMs of all the configs, so a module
+/// compiled under one config must not be used under another one.
+#[test]
+#[cfg(feature = "wasmtime_vm")]
+fn test_wasmtime_memory_cache_is_keyed_by_config() {
+    let config = Config { vm_kind: VMKind::Wasmtime, ..test_vm_config() };
+    let mut limited_config = config.clone();
+    limited_config.limit_config.max_functions_number_per_contract = Some(0);
+    let dir = tempfile::tempdir().unwrap();
+    let cache =
+        crate::FilesystemContractRuntimeCache::with_memory_cache(dir.path(), None::<&str>, 10)
+            .unwrap();
+    let code = near_test_contracts::trivial_contract();
+    let code = ContractCode::new(code.to_vec(), None);
+    let code_hash = *code.hash();
+    let prepaid_gas = 10u64.pow(12);
+
+    let outcome = make_cached_contract_call_vm(
+        &config,
+        &cache,
+        code_hash,
+        Some(&code),
+        "main",
+        prepaid_gas,
+        VMKind::Wasmtime,
+    )
+    .unwrap();
+    assert_eq!(outcome.aborted, None);
+    let outcome = make_cached_contract_call_vm(
+        &limited_config,
+        &cache,
+        code_hash,
+        Some(&code),
+        "main",
+        prepaid_gas,
+        VMKind::Wasmtime,
+    )
+    .unwrap();
+    assert_matches!(
+        outcome.aborted,
+        Some(crate::logic::errors::FunctionCallError::CompilationError(_))
+    );
+}
+
 fn make_cached_contract_call_vm(
     config: &Config,
     cache: &dyn ContractRuntimeCache,
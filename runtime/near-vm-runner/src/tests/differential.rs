@@ -0,0 +1,163 @@
+//! Differential execution of the contract runtimes.
+//!
+//! Every contract in the corpus is run with each of the backends being compared
+//! and the outcomes, including the gas profile and the resulting state, must be
+//! identical. Each call is made twice: once compiling the contract and once
+//! loading it back from the contract cache.
+#![cfg(all(feature = "wasmtime_vm", feature = "near_vm", target_arch = "x86_64"))]
+
+use super::{create_context, test_vm_config};
+use crate::logic::errors::{CompilationError, FunctionCallError};
+use crate::logic::mocks::mock_external::MockedExternal;
+use crate::logic::VMOutcome;
+use crate::runner::VMKindExt;
+use crate::{ContractCode, MockContractRuntimeCache};
+use near_parameters::vm::{ContractPrepareVersion, VMKind};
+use near_parameters::RuntimeFeesConfig;
+
+/// A call of a contract method with the given input.
+struct Case {
+    name: String,
+    code: ContractCode,
+    method: String,
+    input: Vec<u8>,
+}
+
+impl Case {
+    fn new(name: &str, code: &[u8], method: &str, input: Vec<u8>) -> Self {
+        Case {
+            name: name.to_string(),
+            code: ContractCode::new(code.to_vec(), None),
+            method: method.to_string(),
+            input,
+        }
+    }
+}
+
+/// Encode array of `u64` to be passed as a smart contract argument.
+fn encode(xs: &[u64]) -> Vec<u8> {
+    xs.iter().flat_map(|it| it.to_le_bytes()).collect()
+}
+
+/// Contract returning the bit pattern of a NaN produced by an arithmetic
+/// operation, which is only deterministic if the runtime canonicalizes NaNs.
+const NAN_CONTRACT: &str = r#"
+(module
+  (import "env" "value_return" (func $value_return (param i64 i64)))
+  (memory 1)
+  (func (export "main")
+    (i64.store (i32.const 0)
+      (i64.reinterpret_f64 (f64.div (f64.const 0) (f64.const 0))))
+    (call $value_return (i64.const 8) (i64.const 0)))
+)"#;
+
+const TRAP_CONTRACT: &str = r#"
+(module
+  (memory 1 1)
+  (func (export "unreachable") unreachable)
+  (func (export "div_by_zero") (drop (i32.div_u (i32.const 1) (i32.const 0))))
+  (func (export "out_of_bounds") (drop (i32.load (i32.const 65536))))
+  (func (export "overflow") (drop (i32.trunc_f64_s (f64.const 1e100))))
+  (func (export "invalid_signature") (param i32))
+)"#;
+
+fn corpus() -> Vec<Case> {
+    let rs = near_test_contracts::rs_contract();
+    let mut cases = vec![
+        Case::new("trivial", near_test_contracts::trivial_contract(), "main", vec![]),
+        Case::new("missing_method", near_test_contracts::trivial_contract(), "missing", vec![]),
+        Case::new("invalid_wasm", &[42; 100], "main", vec![]),
+        Case::new("nan", &near_test_contracts::wat_contract(NAN_CONTRACT), "main", vec![]),
+        Case::new("write_key_value", rs, "write_key_value", encode(&[10, 20])),
+        Case::new("write_random_value", rs, "write_random_value", vec![]),
+        Case::new("ext_sha256", rs, "ext_sha256", b"tesdsst".to_vec()),
+        Case::new("ext_used_gas", rs, "ext_used_gas", vec![]),
+        Case::new("log_something", rs, "log_something", vec![]),
+        Case::new("sum_with_input", rs, "sum_with_input", encode(&[1, 2, 3])),
+        Case::new("sum_n", rs, "sum_n", encode(&[10_000])),
+        Case::new("fibonacci", rs, "fibonacci", vec![20]),
+        Case::new("insert_strings", rs, "insert_strings", encode(&[0, 100])),
+        Case::new("recurse", rs, "recurse", encode(&[1_000])),
+        Case::new("deep_recurse", rs, "recurse", encode(&[1_000_000])),
+        Case::new("abort_with_zero", rs, "abort_with_zero", vec![]),
+        Case::new("panic_with_message", rs, "panic_with_message", vec![]),
+        Case::new("panic_after_logging", rs, "panic_after_logging", vec![]),
+        Case::new("loop_forever", rs, "loop_forever", vec![]),
+        Case::new("out_of_memory", rs, "out_of_memory", vec![]),
+    ];
+    let traps = near_test_contracts::wat_contract(TRAP_CONTRACT);
+    for method in ["unreachable", "div_by_zero", "out_of_bounds", "overflow", "invalid_signature"] {
+        cases.push(Case::new(method, &traps, method, vec![]));
+    }
+    for seed in [2, 3, 5, 7, 11, 13, 17] {
+        let code = near_test_contracts::arbitrary_contract(seed);
+        let code = ContractCode::new(code, None);
+        let method = super::fuzzers::find_entry_point(&code).unwrap_or_else(|| "main".to_string());
+        cases.push(Case { name: format!("arbitrary_{seed}"), code, method, input: vec![] });
+    }
+    cases
+}
+
+/// The observable result of a call: the outcome and the state of the external.
+#[derive(Debug, PartialEq)]
+struct Observed {
+    outcome: VMOutcome,
+    trie: Vec<(Vec<u8>, Vec<u8>)>,
+    actions: String,
+}
+
+/// Runs the case with the given backend, with and without the contract being
+/// in the cache.
+fn run(case: &Case, vm_kind: VMKind) -> [Observed; 2] {
+    let mut config = test_vm_config();
+    config.limit_config.contract_prepare_version = ContractPrepareVersion::V2;
+    let cache = MockContractRuntimeCache::default();
+    let fees = RuntimeFeesConfig::test();
+    let call = |code: Option<&ContractCode>| {
+        let mut ext = MockedExternal::new();
+        let context = create_context(case.input.clone());
+        let runtime = vm_kind.runtime(config.clone()).expect("runtime has not been compiled");
+        let outcome = runtime
+            .run(
+                *case.code.hash(),
+                code,
+                &case.method,
+                &mut ext,
+                &context,
+                &fees,
+                &[],
+                Some(&cache),
+            )
+            .unwrap_or_else(|err| panic!("{}: fatal error with {vm_kind:?}: {err:?}", case.name));
+        let mut trie: Vec<_> = ext.fake_trie.into_iter().collect();
+        trie.sort();
+        Observed { outcome: censor(outcome), trie, actions: format!("{:?}", ext.action_log) }
+    };
+    let compiled = call(Some(&case.code));
+    let cached = call(None);
+    [compiled, cached]
+}
+
+/// Removes the parts of the outcome which are produced by the backend itself
+/// and can legitimately differ, such as the messages of compilation errors.
+fn censor(mut outcome: VMOutcome) -> VMOutcome {
+    match &mut outcome.aborted {
+        Some(FunctionCallError::LinkError { msg })
+        | Some(FunctionCallError::CompilationError(CompilationError::WasmerCompileError { msg })) => {
+            *msg = "[censored]".to_owned()
+        }
+        _ => {}
+    }
+    outcome
+}
+
+#[test]
+fn near_vm_and_wasmtime_agree_on_corpus() {
+    for case in corpus() {
+        let near_vm = run(&case, VMKind::NearVm);
+        let wasmtime = run(&case, VMKind::Wasmtime);
+        assert_eq!(near_vm[0], near_vm[1], "{}: near_vm differs when cached", case.name);
+        assert_eq!(wasmtime[0], wasmtime[1], "{}: wasmtime differs when cached", case.name);
+        assert_eq!(near_vm[0], wasmtime[0], "{}: near_vm and wasmtime disagree", case.name);
+    }
+}
@@ -1,4 +1,6 @@
+use crate::cache::CompiledContractInfo;
 use crate::errors::ContractPrecompilatonResult;
+use crate::features::WasmFeatures;
 use crate::logic::errors::{
     CacheError, CompilationError, FunctionCallError, MethodResolveError, PrepareError,
     VMLogicError, VMRunnerError, WasmTrap,
@@ -6,12 +8,16 @@ use crate::logic::errors::{
 use crate::logic::types::PromiseResult;
 use crate::logic::Config;
 use crate::logic::{External, MemSlice, MemoryLike, VMContext, VMLogic, VMOutcome};
-use crate::{imports, prepare, ContractCode, ContractRuntimeCache};
+use crate::{
+    get_contract_cache_key, imports, prepare, CompiledContract, ContractCode, ContractRuntimeCache,
+    NoContractRuntimeCache,
+};
 use near_parameters::vm::VMKind;
 use near_parameters::RuntimeFeesConfig;
 use near_primitives_core::hash::CryptoHash;
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::sync::Mutex;
 use wasmtime::ExternType::Func;
 use wasmtime::{Engine, Linker, Memory, MemoryType, Module, Store};
 
@@ -120,59 +126,170 @@ impl IntoVMError for anyhow::Error {
     }
 }
 
-#[cfg(not(feature = "lightbeam"))]
-#[allow(clippy::needless_pass_by_ref_mut)]
-pub fn get_engine(config: &mut wasmtime::Config) -> Engine {
-    Engine::new(config).unwrap()
+/// Returns the engine compiling contracts with the given Wasm features.
+///
+/// A compiled [`Module`] can only be instantiated within the engine that
+/// compiled it and modules outlive the VMs through the in-memory contract
+/// cache, so engines are shared by all the VMs in the process.
+fn get_engine(features: WasmFeatures) -> Engine {
+    static ENGINES: Mutex<Vec<(WasmFeatures, Engine)>> = Mutex::new(Vec::new());
+    let mut engines = ENGINES.lock().unwrap();
+    if let Some((_, engine)) = engines.iter().find(|(f, _)| *f == features) {
+        return engine.clone();
+    }
+    let engine = Engine::new(&default_wasmtime_config(features))
+        .unwrap_or_else(|err| panic!("could not create the wasmtime engine: {err}"));
+    engines.push((features, engine.clone()));
+    engine
 }
 
-#[cfg(feature = "lightbeam")]
-pub fn get_engine(config: &mut wasmtime::Config) -> Engine {
-    Engine::new(config.strategy(wasmtime::Strategy::Lightbeam).unwrap()).unwrap()
+/// The wasmtime configuration used to compile and run contracts.
+///
+/// Everything affecting the observable behaviour of a contract is set
+/// explicitly rather than left to the wasmtime defaults, which can differ
+/// between hosts and versions.
+fn default_wasmtime_config(features: WasmFeatures) -> wasmtime::Config {
+    let mut config = wasmtime::Config::from(features);
+    config.strategy(wasmtime::Strategy::Cranelift);
+    config.cranelift_opt_level(wasmtime::OptLevel::Speed);
+    // near-vm canonicalizes the NaNs, so contracts must observe the same bit
+    // patterns when running under wasmtime.
+    config.cranelift_nan_canonicalization(true);
+    // wasm stack metering is implemented by instrumentation, we don't want
+    // wasmtime to trap before that.
+    config.max_wasm_stack(1024 * 1024 * 1024);
+    config
 }
 
 pub(crate) fn wasmtime_vm_hash() -> u64 {
     // TODO: take into account compiler and engine used to compile the contract.
-    64
+    65
 }
 
 pub(crate) struct WasmtimeVM {
     config: Config,
+    engine: Engine,
 }
 
 impl WasmtimeVM {
     pub(crate) fn new(config: Config) -> Self {
-        Self { config }
+        let features = WasmFeatures::from(config.limit_config.contract_prepare_version);
+        Self { config, engine: get_engine(features) }
     }
 
-    pub(crate) fn default_wasmtime_config(&self) -> wasmtime::Config {
-        let features =
-            crate::features::WasmFeatures::from(self.config.limit_config.contract_prepare_version);
-        let mut config = wasmtime::Config::from(features);
-        config.max_wasm_stack(1024 * 1024 * 1024); // wasm stack metering is implemented by instrumentation, we don't want wasmtime to trap before that
-        config
+    pub(crate) fn compile_uncached(&self, code: &ContractCode) -> Result<Module, CompilationError> {
+        let _span = tracing::debug_span!(target: "vm", "WasmtimeVM::compile_uncached").entered();
+        let start = std::time::Instant::now();
+        let prepared_code = prepare::prepare_contract(code.code(), &self.config, VMKind::Wasmtime)
+            .map_err(CompilationError::PrepareError)?;
+        let module = Module::new(&self.engine, prepared_code).map_err(|err| {
+            tracing::error!(?err, "wasmtime failed to compile the prepared code");
+            CompilationError::WasmerCompileError { msg: err.to_string() }
+        })?;
+        crate::metrics::compilation_duration(VMKind::Wasmtime, start.elapsed());
+        Ok(module)
+    }
+
+    fn compile_and_cache(
+        &self,
+        code: &ContractCode,
+        cache: &dyn ContractRuntimeCache,
+    ) -> Result<Result<Module, CompilationError>, CacheError> {
+        let module_or_error = self.compile_uncached(code);
+        let key = get_contract_cache_key(*code.hash(), &self.config);
+        let record = CompiledContractInfo {
+            wasm_bytes: code.code().len() as u64,
+            compiled: match &module_or_error {
+                Ok(module) => {
+                    let code = module
+                        .serialize()
+                        .map_err(|_e| CacheError::SerializationError { hash: key.0 })?;
+                    CompiledContract::Code(code)
+                }
+                Err(err) => CompiledContract::CompileModuleError(err.clone()),
+            },
+        };
+        cache.put(&key, record).map_err(CacheError::WriteError)?;
+        Ok(module_or_error)
+    }
+
+    /// Returns the size of the Wasm code and the compiled module, looking it
+    /// up in the in-memory and the persistent caches before compiling it.
+    ///
+    /// Both caches are keyed by the contract cache key rather than the code
+    /// hash, since the module compiled for a contract depends on the VM and its
+    /// config, and the in-memory cache is shared by all the VMs in the process.
+    fn compile_or_load(
+        &self,
+        code_hash: CryptoHash,
+        code: Option<&ContractCode>,
+        cache: &dyn ContractRuntimeCache,
+    ) -> Result<(u64, Result<Module, CompilationError>), VMRunnerError> {
+        // (wasm code size, compilation result)
+        type MemoryCacheType = (u64, Result<Module, CompilationError>);
+        let to_any = |v: MemoryCacheType| -> Box<dyn std::any::Any + Send> { Box::new(v) };
+        let mut is_cache_hit = true;
+        let key = get_contract_cache_key(code_hash, &self.config);
+        let result = cache.memory_cache().try_lookup(
+            key,
+            || match code {
+                None => {
+                    let _span = tracing::debug_span!(target: "vm", "WasmtimeVM::fetch_from_cache")
+                        .entered();
+                    let cache_record = cache.get(&key).map_err(CacheError::ReadError)?;
+                    let Some(code) = cache_record else {
+                        return Err(VMRunnerError::CacheError(CacheError::ReadError(
+                            std::io::Error::from(std::io::ErrorKind::NotFound),
+                        )));
+                    };
+                    match &code.compiled {
+                        CompiledContract::CompileModuleError(err) => {
+                            Ok::<_, VMRunnerError>(to_any((code.wasm_bytes, Err(err.clone()))))
+                        }
+                        CompiledContract::Code(serialized_module) => {
+                            // SAFETY: the `serialized_module` must have been produced by a
+                            // prior call to `serialize`. wasmtime checks that the module was
+                            // produced by a compatible engine, but can't detect data
+                            // corrupted while at rest.
+                            let module =
+                                unsafe { Module::deserialize(&self.engine, serialized_module) }
+                                    .map_err(|_| CacheError::DeserializationError)?;
+                            Ok(to_any((code.wasm_bytes, Ok(module))))
+                        }
+                    }
+                }
+                Some(code) => {
+                    is_cache_hit = false;
+                    Ok(to_any((code.code().len() as u64, self.compile_and_cache(code, cache)?)))
+                }
+            },
+            move |value| {
+                let &(wasm_bytes, ref downcast) = value
+                    .downcast_ref::<MemoryCacheType>()
+                    .expect("downcast should always succeed");
+                (wasm_bytes, downcast.clone())
+            },
+        )?;
+        crate::metrics::record_compiled_contract_cache_lookup(is_cache_hit);
+        Ok(result)
     }
 }
 
 impl crate::runner::VM for WasmtimeVM {
     fn run(
         &self,
-        _code_hash: CryptoHash,
+        code_hash: CryptoHash,
         code: Option<&ContractCode>,
         method_name: &str,
         ext: &mut dyn External,
         context: &VMContext,
         fees_config: &RuntimeFeesConfig,
         promise_results: &[PromiseResult],
-        _cache: Option<&dyn ContractRuntimeCache>,
+        cache: Option<&dyn ContractRuntimeCache>,
     ) -> Result<VMOutcome, VMRunnerError> {
-        let Some(code) = code else {
-            return Err(VMRunnerError::CacheError(CacheError::ReadError(std::io::Error::from(
-                std::io::ErrorKind::NotFound,
-            ))));
-        };
-        let mut config = self.default_wasmtime_config();
-        let engine = get_engine(&mut config);
+        let cache = cache.unwrap_or(&NoContractRuntimeCache);
+        let (wasm_bytes, module_result) = self.compile_or_load(code_hash, code, cache)?;
+        let engine = self.engine.clone();
         let mut store = Store::new(&engine, ());
         let mut memory = WasmtimeMemory::new(
             &mut store,
@@ -184,25 +301,17 @@ impl crate::runner::VM for WasmtimeVM {
         let mut logic =
             VMLogic::new(ext, context, &self.config, fees_config, promise_results, &mut memory);
 
-        let result = logic.before_loading_executable(method_name, code.code().len() as u64);
+        let result = logic.before_loading_executable(method_name, wasm_bytes);
         if let Err(e) = result {
             return Ok(VMOutcome::abort(logic, e));
         }
-
-        let prepared_code =
-            match prepare::prepare_contract(code.code(), &self.config, VMKind::Wasmtime) {
-                Ok(code) => code,
-                Err(err) => return Ok(VMOutcome::abort(logic, FunctionCallError::from(err))),
-            };
-        let start = std::time::Instant::now();
-        let module = match Module::new(&engine, prepared_code) {
+        let module = match module_result {
             Ok(module) => module,
-            Err(err) => return Ok(VMOutcome::abort(logic, err.into_vm_error()?)),
+            Err(e) => return Ok(VMOutcome::abort(logic, FunctionCallError::CompilationError(e))),
         };
-        crate::metrics::compilation_duration(VMKind::Wasmtime, start.elapsed());
         let mut linker = Linker::new(&engine);
 
-        let result = logic.after_loading_executable(code.code().len() as u64);
+        let result = logic.after_loading_executable(wasm_bytes);
         if let Err(e) = result {
             return Ok(VMOutcome::abort(logic, e));
         }
@@ -254,12 +363,14 @@ impl crate::runner::VM for WasmtimeVM {
 
     fn precompile(
         &self,
-        _code: &ContractCode,
-        _cache: &dyn ContractRuntimeCache,
+        code: &ContractCode,
+        cache: &dyn ContractRuntimeCache,
     ) -> Result<
         Result<ContractPrecompilatonResult, CompilationError>,
         crate::logic::errors::CacheError,
     > {
-        Ok(Ok(ContractPrecompilatonResult::CacheNotAvailable))
+        Ok(self
+            .compile_and_cache(code, cache)?
+            .map(|_| ContractPrecompilatonResult::ContractCompiled))
     }
 }
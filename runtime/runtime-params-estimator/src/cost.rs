@@ -438,6 +438,13 @@ pub enum Cost {
     /// In the end, the cost should be low enough, compared to the base cost,
     /// that it does not matter all that much if we overestimate it a bit.
     Ed25519VerifyByte,
+    /// Estimates `secp256k1_verify_base`, which covers the full cost of the
+    /// host function `secp256k1_verify` to verify an ECDSA signature against
+    /// a given public key.
+    ///
+    /// Estimation: Use a fixed signature embedded in the test contract and
+    /// verify it `N` times in a loop and divide by `N`.
+    Secp256k1VerifyBase,
     // `storage_write` records a single key-value pair, initially in the
     // prospective changes in-memory hash map, and then once a full block has
     // been processed, in the on-disk trie. If there was already a value
@@ -487,6 +494,19 @@ pub enum Cost {
     /// Estimation: Contract call that reads N big values (10kiB) and divide
     /// the cost by total number of value bytes.
     StorageReadValueByte,
+    /// Estimates `ExtCost::result_to_memory_base` which is charged once every
+    /// time a host function writes its result directly into WASM memory, as
+    /// done by `storage_read_to_memory` and `promise_result_to_memory`.
+    ///
+    /// Estimation: Contract call that reads N small values directly into WASM
+    /// memory and divide the cost by N.
+    ResultToMemoryBase,
+    /// Estimates `ExtCost::result_to_memory_byte` which is charged for each
+    /// byte a host function writes directly into WASM memory.
+    ///
+    /// Estimation: Contract call that reads N big values (10kiB) directly into
+    /// WASM memory and divide the cost by total number of value bytes.
+    ResultToMemoryByte,
 
     // `storage_remove` adds a deletion transaction to the prospective changes,
     // which is applied at the end of the block.
@@ -525,21 +545,57 @@ pub enum Cost {
     /// (10kiB) and divide the cost by total key bytes.
     StorageHasKeyByte,
 
-    /// DEPRECATED: Was charged in `storage_iter_prefix`
+    // Storage iterators walk over the keys of the contract's own storage.
+    // Creating an iterator only records its bounds, the trie is accessed in
+    // `storage_iter_next`.
+    /// Estimates `ExtCost::storage_iter_create_prefix_base` which is charged
+    /// once per call to `storage_iter_prefix`.
+    ///
+    /// Estimation: Contract call that creates N iterators with small prefixes
+    /// and divide the cost by N.
     StorageIterCreatePrefixBase,
-    /// DEPRECATED: Was charged in `storage_iter_prefix`
+    /// Estimates `ExtCost::storage_iter_create_prefix_byte` which is charged
+    /// for each byte of the prefix in calls to `storage_iter_prefix`.
+    ///
+    /// Estimation: Contract call that creates N iterators with big prefixes
+    /// (10kiB) and divide the cost by total prefix bytes.
     StorageIterCreatePrefixByte,
-    /// DEPRECATED: Was charged in `storage_iter_range`
+    /// Estimates `ExtCost::storage_iter_create_range_base` which is charged
+    /// once per call to `storage_iter_range`.
+    ///
+    /// Estimation: Contract call that creates N iterators with small bounds
+    /// and divide the cost by N.
     StorageIterCreateRangeBase,
-    /// DEPRECATED: Was charged in `storage_iter_range`
+    /// Estimates `ExtCost::storage_iter_create_from_byte` which is charged for
+    /// each byte of the start bound in calls to `storage_iter_range`.
+    ///
+    /// Estimation: Contract call that creates N iterators with a big start
+    /// bound (10kiB) and divide the cost by total start bound bytes.
     StorageIterCreateFromByte,
-    /// DEPRECATED: Was charged in `storage_iter_range`
+    /// Estimates `ExtCost::storage_iter_create_to_byte` which is charged for
+    /// each byte of the end bound in calls to `storage_iter_range`.
+    ///
+    /// Estimation: Contract call that creates N iterators with a big end bound
+    /// (10kiB) and divide the cost by total end bound bytes.
     StorageIterCreateToByte,
-    /// DEPRECATED: Was charged in `storage_iter_next`
+    /// Estimates `ExtCost::storage_iter_next_base` which is charged once per
+    /// call to `storage_iter_next`.
+    ///
+    /// Estimation: Write N small values with small keys in a separate block,
+    /// then make a contract call that iterates over them and divide the cost
+    /// by N.
     StorageIterNextBase,
-    /// DEPRECATED: Was charged in `storage_iter_next`
+    /// Estimates `ExtCost::storage_iter_next_key_byte` which is charged for
+    /// each byte of the keys returned by `storage_iter_next`.
+    ///
+    /// Estimation: Same as `StorageIterNextBase` but with big keys (10kiB),
+    /// divide the cost by total key bytes.
     StorageIterNextKeyByte,
-    /// DEPRECATED: Was charged in `storage_iter_next`
+    /// Estimates `ExtCost::storage_iter_next_value_byte` which is charged for
+    /// each byte of the values returned by `storage_iter_next`.
+    ///
+    /// Estimation: Same as `StorageIterNextBase` but with big values (10kiB),
+    /// divide the cost by total value bytes.
     StorageIterNextValueByte,
 
     /// Estimates `touching_trie_node` which is charged when smart contracts
@@ -606,6 +662,21 @@ pub enum Cost {
     AltBn128G1SumBase,
     AltBn128G1SumElement,
 
+    Bls12381P1SumBase,
+    Bls12381P1SumElement,
+    Bls12381P2SumBase,
+    Bls12381P2SumElement,
+    Bls12381G1MultiexpBase,
+    Bls12381G1MultiexpElement,
+    Bls12381G2MultiexpBase,
+    Bls12381G2MultiexpElement,
+    Bls12381MapFpToG1Base,
+    Bls12381MapFpToG1Element,
+    Bls12381MapFp2ToG2Base,
+    Bls12381MapFp2ToG2Element,
+    Bls12381PairingBase,
+    Bls12381PairingElement,
+
     // Costs used only in estimator
     //
     /// Costs associated with applying an empty block. This overhead is not
@@ -725,6 +796,14 @@ pub enum Cost {
     /// `promise_yield_resume` host function.
     YieldResumeByte,
 
+    /// Estimates `yield_status_base`, which covers the full cost of the host function
+    /// `promise_yield_status`.
+    ///
+    /// Estimation: Same as for `yield_resume_base`, we prepare 255 yields in a separate block
+    /// and write their data IDs into the storage. Then measure a contract that reads the data IDs
+    /// from the storage and looks up the status of each yield.
+    YieldStatusBase,
+
     __Count,
 }
 
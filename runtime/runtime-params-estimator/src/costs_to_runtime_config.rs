@@ -49,7 +49,12 @@ fn runtime_fees_config(cost_table: &CostTable) -> anyhow::Result<RuntimeFeesConf
         let total_gas =
             cost_table.get(cost).with_context(|| format!("undefined cost: {}", cost))?;
         // Split the total cost evenly between send and execution fee.
-        Ok(Fee { send_sir: total_gas / 2, send_not_sir: total_gas / 2, execution: total_gas / 2 })
+        Ok(Fee {
+            send_sir: total_gas / 2,
+            send_not_sir: total_gas / 2,
+            execution: total_gas / 2,
+            execution_compute: None,
+        })
     };
 
     let config_store = RuntimeConfigStore::new(None);
@@ -81,16 +86,6 @@ fn runtime_fees_config(cost_table: &CostTable) -> anyhow::Result<RuntimeFeesConf
 fn ext_costs_config(cost_table: &CostTable) -> anyhow::Result<ExtCostsConfig> {
     Ok(ExtCostsConfig {
         costs: enum_map::enum_map! {
-            // TODO: storage_iter_* operations below are deprecated, so just hardcode zero price,
-            // and remove those operations ASAP.
-            ExtCosts::storage_iter_create_prefix_base => 0,
-            ExtCosts::storage_iter_create_prefix_byte => 0,
-            ExtCosts::storage_iter_create_range_base => 0,
-            ExtCosts::storage_iter_create_from_byte => 0,
-            ExtCosts::storage_iter_create_to_byte => 0,
-            ExtCosts::storage_iter_next_base => 0,
-            ExtCosts::storage_iter_next_key_byte => 0,
-            ExtCosts::storage_iter_next_value_byte => 0,
             // TODO: accurately price host functions that expose validator information.
             ExtCosts::validator_stake_base => 303944908800,
             ExtCosts::validator_total_stake_base => 303944908800,
@@ -128,6 +123,7 @@ fn estimation(cost: ExtCosts) -> Option<Cost> {
         ExtCosts::ecrecover_base => Cost::EcrecoverBase,
         ExtCosts::ed25519_verify_base => Cost::Ed25519VerifyBase,
         ExtCosts::ed25519_verify_byte => Cost::Ed25519VerifyByte,
+        ExtCosts::secp256k1_verify_base => Cost::Secp256k1VerifyBase,
         ExtCosts::log_base => Cost::LogBase,
         ExtCosts::log_byte => Cost::LogByte,
         ExtCosts::storage_write_base => Cost::StorageWriteBase,
@@ -137,6 +133,8 @@ fn estimation(cost: ExtCosts) -> Option<Cost> {
         ExtCosts::storage_read_base => Cost::StorageReadBase,
         ExtCosts::storage_read_key_byte => Cost::StorageReadKeyByte,
         ExtCosts::storage_read_value_byte => Cost::StorageReadValueByte,
+        ExtCosts::result_to_memory_base => Cost::ResultToMemoryBase,
+        ExtCosts::result_to_memory_byte => Cost::ResultToMemoryByte,
         ExtCosts::storage_remove_base => Cost::StorageRemoveBase,
         ExtCosts::storage_remove_key_byte => Cost::StorageRemoveKeyByte,
         ExtCosts::storage_remove_ret_value_byte => Cost::StorageRemoveRetValueByte,
@@ -153,8 +151,31 @@ fn estimation(cost: ExtCosts) -> Option<Cost> {
         ExtCosts::alt_bn128_g1_multiexp_element => Cost::AltBn128G1MultiexpElement,
         ExtCosts::alt_bn128_pairing_check_base => Cost::AltBn128PairingCheckBase,
         ExtCosts::alt_bn128_pairing_check_element => Cost::AltBn128PairingCheckElement,
+        ExtCosts::bls12381_p1_sum_base => Cost::Bls12381P1SumBase,
+        ExtCosts::bls12381_p1_sum_element => Cost::Bls12381P1SumElement,
+        ExtCosts::bls12381_p2_sum_base => Cost::Bls12381P2SumBase,
+        ExtCosts::bls12381_p2_sum_element => Cost::Bls12381P2SumElement,
+        ExtCosts::bls12381_g1_multiexp_base => Cost::Bls12381G1MultiexpBase,
+        ExtCosts::bls12381_g1_multiexp_element => Cost::Bls12381G1MultiexpElement,
+        ExtCosts::bls12381_g2_multiexp_base => Cost::Bls12381G2MultiexpBase,
+        ExtCosts::bls12381_g2_multiexp_element => Cost::Bls12381G2MultiexpElement,
+        ExtCosts::bls12381_map_fp_to_g1_base => Cost::Bls12381MapFpToG1Base,
+        ExtCosts::bls12381_map_fp_to_g1_element => Cost::Bls12381MapFpToG1Element,
+        ExtCosts::bls12381_map_fp2_to_g2_base => Cost::Bls12381MapFp2ToG2Base,
+        ExtCosts::bls12381_map_fp2_to_g2_element => Cost::Bls12381MapFp2ToG2Element,
+        ExtCosts::bls12381_pairing_base => Cost::Bls12381PairingBase,
+        ExtCosts::bls12381_pairing_element => Cost::Bls12381PairingElement,
         ExtCosts::yield_create_base => Cost::YieldCreateBase,
         ExtCosts::yield_create_byte => Cost::YieldCreateByte,
+        ExtCosts::yield_status_base => Cost::YieldStatusBase,
+        ExtCosts::storage_iter_create_prefix_base => Cost::StorageIterCreatePrefixBase,
+        ExtCosts::storage_iter_create_prefix_byte => Cost::StorageIterCreatePrefixByte,
+        ExtCosts::storage_iter_create_range_base => Cost::StorageIterCreateRangeBase,
+        ExtCosts::storage_iter_create_from_byte => Cost::StorageIterCreateFromByte,
+        ExtCosts::storage_iter_create_to_byte => Cost::StorageIterCreateToByte,
+        ExtCosts::storage_iter_next_base => Cost::StorageIterNextBase,
+        ExtCosts::storage_iter_next_key_byte => Cost::StorageIterNextKeyByte,
+        ExtCosts::storage_iter_next_value_byte => Cost::StorageIterNextValueByte,
         _ => return None,
     })
 }
@@ -138,6 +138,7 @@ impl<'c> EstimatorContext<'c> {
             max_promises_per_function_call_action: u64::MAX,
             max_number_input_data_dependencies: u64::MAX,
             max_length_storage_key: u64::MAX,
+            max_storage_iterators: u64::MAX,
 
             max_total_prepaid_gas: u64::MAX,
 
@@ -221,17 +221,34 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::EcrecoverBase, ecrecover_base),
     (Cost::Ed25519VerifyBase, ed25519_verify_base),
     (Cost::Ed25519VerifyByte, ed25519_verify_byte),
+    (Cost::Secp256k1VerifyBase, secp256k1_verify_base),
     (Cost::AltBn128G1MultiexpBase, alt_bn128g1_multiexp_base),
     (Cost::AltBn128G1MultiexpElement, alt_bn128g1_multiexp_element),
     (Cost::AltBn128G1SumBase, alt_bn128g1_sum_base),
     (Cost::AltBn128G1SumElement, alt_bn128g1_sum_element),
     (Cost::AltBn128PairingCheckBase, alt_bn128_pairing_check_base),
     (Cost::AltBn128PairingCheckElement, alt_bn128_pairing_check_element),
+    (Cost::Bls12381P1SumBase, bls12381_p1_sum_base),
+    (Cost::Bls12381P1SumElement, bls12381_p1_sum_element),
+    (Cost::Bls12381P2SumBase, bls12381_p2_sum_base),
+    (Cost::Bls12381P2SumElement, bls12381_p2_sum_element),
+    (Cost::Bls12381G1MultiexpBase, bls12381_g1_multiexp_base),
+    (Cost::Bls12381G1MultiexpElement, bls12381_g1_multiexp_element),
+    (Cost::Bls12381G2MultiexpBase, bls12381_g2_multiexp_base),
+    (Cost::Bls12381G2MultiexpElement, bls12381_g2_multiexp_element),
+    (Cost::Bls12381MapFpToG1Base, bls12381_map_fp_to_g1_base),
+    (Cost::Bls12381MapFpToG1Element, bls12381_map_fp_to_g1_element),
+    (Cost::Bls12381MapFp2ToG2Base, bls12381_map_fp2_to_g2_base),
+    (Cost::Bls12381MapFp2ToG2Element, bls12381_map_fp2_to_g2_element),
+    (Cost::Bls12381PairingBase, bls12381_pairing_base),
+    (Cost::Bls12381PairingElement, bls12381_pairing_element),
     (Cost::StorageHasKeyBase, storage_has_key_base),
     (Cost::StorageHasKeyByte, storage_has_key_byte),
     (Cost::StorageReadBase, storage_read_base),
     (Cost::StorageReadKeyByte, storage_read_key_byte),
     (Cost::StorageReadValueByte, storage_read_value_byte),
+    (Cost::ResultToMemoryBase, result_to_memory_base),
+    (Cost::ResultToMemoryByte, result_to_memory_byte),
     (Cost::StorageWriteBase, storage_write_base),
     (Cost::StorageWriteKeyByte, storage_write_key_byte),
     (Cost::StorageWriteValueByte, storage_write_value_byte),
@@ -262,6 +279,24 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::YieldResumeBase, yield_resume_base),
     #[cfg(feature = "nightly")]
     (Cost::YieldResumeByte, yield_resume_byte),
+    #[cfg(feature = "nightly")]
+    (Cost::YieldStatusBase, yield_status_base),
+    #[cfg(feature = "nightly")]
+    (Cost::StorageIterCreatePrefixBase, storage_iter_create_prefix_base),
+    #[cfg(feature = "nightly")]
+    (Cost::StorageIterCreatePrefixByte, storage_iter_create_prefix_byte),
+    #[cfg(feature = "nightly")]
+    (Cost::StorageIterCreateRangeBase, storage_iter_create_range_base),
+    #[cfg(feature = "nightly")]
+    (Cost::StorageIterCreateFromByte, storage_iter_create_from_byte),
+    #[cfg(feature = "nightly")]
+    (Cost::StorageIterCreateToByte, storage_iter_create_to_byte),
+    #[cfg(feature = "nightly")]
+    (Cost::StorageIterNextBase, storage_iter_next_base),
+    #[cfg(feature = "nightly")]
+    (Cost::StorageIterNextKeyByte, storage_iter_next_key_byte),
+    #[cfg(feature = "nightly")]
+    (Cost::StorageIterNextValueByte, storage_iter_next_value_byte),
     (Cost::CpuBenchmarkSha256, cpu_benchmark_sha256),
     (Cost::OneCPUInstruction, one_cpu_instruction),
     (Cost::OneNanosecond, one_nanosecond),
@@ -1044,6 +1079,10 @@ fn ed25519_verify_byte(ctx: &mut EstimatorContext) -> GasCost {
     byte - base / iteration_bytes
 }
 
+fn secp256k1_verify_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "secp256k1_verify_10k", ExtCosts::secp256k1_verify_base, 10_000)
+}
+
 fn alt_bn128g1_multiexp_base(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost(ctx, "alt_bn128_g1_multiexp_1_10", ExtCosts::alt_bn128_g1_multiexp_base, 10)
 }
@@ -1070,6 +1109,55 @@ fn alt_bn128_pairing_check_element(ctx: &mut EstimatorContext) -> GasCost {
     )
 }
 
+fn bls12381_p1_sum_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_p1_sum_1_1k", ExtCosts::bls12381_p1_sum_base, 1000)
+}
+fn bls12381_p1_sum_element(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_p1_sum_10_1k", ExtCosts::bls12381_p1_sum_element, 10 * 1000)
+}
+
+fn bls12381_p2_sum_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_p2_sum_1_1k", ExtCosts::bls12381_p2_sum_base, 1000)
+}
+fn bls12381_p2_sum_element(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_p2_sum_10_1k", ExtCosts::bls12381_p2_sum_element, 10 * 1000)
+}
+
+fn bls12381_g1_multiexp_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_g1_multiexp_1_10", ExtCosts::bls12381_g1_multiexp_base, 10)
+}
+fn bls12381_g1_multiexp_element(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_g1_multiexp_10_10", ExtCosts::bls12381_g1_multiexp_element, 10 * 10)
+}
+
+fn bls12381_g2_multiexp_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_g2_multiexp_1_10", ExtCosts::bls12381_g2_multiexp_base, 10)
+}
+fn bls12381_g2_multiexp_element(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_g2_multiexp_10_10", ExtCosts::bls12381_g2_multiexp_element, 10 * 10)
+}
+
+fn bls12381_map_fp_to_g1_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_map_fp_to_g1_1_10", ExtCosts::bls12381_map_fp_to_g1_base, 10)
+}
+fn bls12381_map_fp_to_g1_element(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_map_fp_to_g1_10_10", ExtCosts::bls12381_map_fp_to_g1_element, 10 * 10)
+}
+
+fn bls12381_map_fp2_to_g2_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_map_fp2_to_g2_1_10", ExtCosts::bls12381_map_fp2_to_g2_base, 10)
+}
+fn bls12381_map_fp2_to_g2_element(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_map_fp2_to_g2_10_10", ExtCosts::bls12381_map_fp2_to_g2_element, 10 * 10)
+}
+
+fn bls12381_pairing_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_pairing_1_10", ExtCosts::bls12381_pairing_base, 10)
+}
+fn bls12381_pairing_element(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "bls12381_pairing_10_10", ExtCosts::bls12381_pairing_element, 10 * 10)
+}
+
 fn storage_has_key_base(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost_with_setup(
         ctx,
@@ -1126,6 +1214,27 @@ fn storage_read_value_byte(ctx: &mut EstimatorContext) -> GasCost {
     )
 }
 
+fn result_to_memory_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost_with_setup(
+        ctx,
+        "storage_write_10b_key_10b_value_1k",
+        "storage_read_to_memory_10b_key_10b_value_1k",
+        ExtCosts::result_to_memory_base,
+        1000,
+        0,
+    )
+}
+fn result_to_memory_byte(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost_with_setup(
+        ctx,
+        "storage_write_10b_key_10kib_value_1k",
+        "storage_read_to_memory_10b_key_10kib_value_1k",
+        ExtCosts::result_to_memory_byte,
+        10 * 1024 * 1000,
+        0,
+    )
+}
+
 fn storage_write_base(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost(ctx, "storage_write_10b_key_10b_value_1k", ExtCosts::storage_write_base, 1000)
 }
@@ -1343,6 +1452,99 @@ fn yield_resume_byte(ctx: &mut EstimatorContext) -> GasCost {
     with_payload.saturating_sub(&baseline, &NonNegativeTolerance::PER_MILLE) / 1000
 }
 
+#[cfg(feature = "nightly")]
+fn yield_status_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost_with_setup(
+        ctx,
+        "yield_resume_base_prepare",
+        "yield_status_base",
+        ExtCosts::yield_status_base,
+        255,
+        1,
+    )
+}
+
+#[cfg(feature = "nightly")]
+fn storage_iter_create_prefix_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(
+        ctx,
+        "storage_iter_create_prefix_10b_1k",
+        ExtCosts::storage_iter_create_prefix_base,
+        1000,
+    )
+}
+
+#[cfg(feature = "nightly")]
+fn storage_iter_create_prefix_byte(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(
+        ctx,
+        "storage_iter_create_prefix_10kib_1k",
+        ExtCosts::storage_iter_create_prefix_byte,
+        10 * 1024 * 1000,
+    )
+}
+
+#[cfg(feature = "nightly")]
+fn storage_iter_create_range_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "storage_iter_create_range_10b_1k", ExtCosts::storage_iter_create_range_base, 1000)
+}
+
+#[cfg(feature = "nightly")]
+fn storage_iter_create_from_byte(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(
+        ctx,
+        "storage_iter_create_range_10kib_from_1k",
+        ExtCosts::storage_iter_create_from_byte,
+        10 * 1024 * 1000,
+    )
+}
+
+#[cfg(feature = "nightly")]
+fn storage_iter_create_to_byte(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(
+        ctx,
+        "storage_iter_create_range_10kib_to_1k",
+        ExtCosts::storage_iter_create_to_byte,
+        10 * 1024 * 1000,
+    )
+}
+
+#[cfg(feature = "nightly")]
+fn storage_iter_next_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost_with_setup(
+        ctx,
+        "storage_write_10b_key_10b_value_1k",
+        "storage_iter_next_10b_key_10b_value_1k",
+        ExtCosts::storage_iter_next_base,
+        1000,
+        0,
+    )
+}
+
+#[cfg(feature = "nightly")]
+fn storage_iter_next_key_byte(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost_with_setup(
+        ctx,
+        "storage_write_10kib_key_10b_value_1k",
+        "storage_iter_next_10kib_key_10b_value_1k",
+        ExtCosts::storage_iter_next_key_byte,
+        10 * 1024 * 1000,
+        0,
+    )
+}
+
+#[cfg(feature = "nightly")]
+fn storage_iter_next_value_byte(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost_with_setup(
+        ctx,
+        "storage_write_10b_key_10kib_value_1k",
+        "storage_iter_next_10b_key_10kib_value_1k",
+        ExtCosts::storage_iter_next_value_byte,
+        10 * 1024 * 1000,
+        0,
+    )
+}
+
 fn gas_metering(ctx: &mut EstimatorContext) -> (GasCost, GasCost) {
     if let Some(cached) = ctx.cached.gas_metering_cost_base_per_op.clone() {
         return cached;
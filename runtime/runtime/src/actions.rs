@@ -1,5 +1,5 @@
 use crate::config::{
-    safe_add_compute, safe_add_gas, total_prepaid_exec_fees, total_prepaid_gas,
+    safe_add_balance, safe_add_compute, safe_add_gas, total_prepaid_exec_fees, total_prepaid_gas,
     total_prepaid_send_fees,
 };
 use crate::ext::{ExternalError, RuntimeExt};
@@ -7,23 +7,31 @@ use crate::receipt_manager::ReceiptManager;
 use crate::{metrics, ActionResult, ApplyState};
 
 use near_crypto::PublicKey;
-use near_parameters::{AccountCreationConfig, ActionCosts, RuntimeConfig, RuntimeFeesConfig};
+use near_parameters::{
+    AccountCreationConfig, ActionCosts, RuntimeConfig, RuntimeConfigOverrides, RuntimeFeesConfig,
+};
 use near_primitives::account::{AccessKey, AccessKeyPermission, Account};
 use near_primitives::action::delegate::{DelegateAction, SignedDelegateAction};
 use near_primitives::checked_feature;
 use near_primitives::config::ViewConfig;
-use near_primitives::errors::{ActionError, ActionErrorKind, InvalidAccessKeyError, RuntimeError};
+use near_primitives::errors::{
+    ActionError, ActionErrorKind, IntegerOverflowError, InvalidAccessKeyError, RuntimeError,
+};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{
-    ActionReceipt, DataReceipt, Receipt, ReceiptEnum, ReceiptPriority, ReceiptV0,
+    ActionReceipt, DataReceipt, GlobalContractDistributionReceipt, Receipt, ReceiptEnum,
+    ReceiptPriority, RuntimeConfigOverridesDistributionReceipt,
 };
+use near_primitives::shard_layout::ShardLayout;
 use near_primitives::transaction::{
     Action, AddKeyAction, DeleteAccountAction, DeleteKeyAction, DeployContractAction,
-    FunctionCallAction, StakeAction,
+    DeployGlobalContractAction, FunctionCallAction, StakeAction, UseGlobalContractAction,
 };
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{
-    AccountId, Balance, BlockHeight, EpochInfoProvider, Gas, StorageUsage, TrieCacheMode,
+    AccountId, Balance, BlockHeight, EpochHeight, EpochInfoProvider, Gas, ShardId, StorageUsage,
+    TrieCacheMode,
 };
 use near_primitives::utils::{account_is_implicit, create_random_seed};
 use near_primitives::version::{
@@ -31,9 +39,10 @@ use near_primitives::version::{
 };
 use near_primitives_core::account::id::AccountType;
 use near_store::{
-    enqueue_promise_yield_timeout, get_access_key, get_code, get_promise_yield_indices,
-    remove_access_key, remove_account, set_access_key, set_code, set_promise_yield_indices,
-    StorageError, TrieUpdate,
+    enqueue_promise_yield_timeout, get_access_key, get_code, get_global_contract_code,
+    get_promise_yield_indices, get_runtime_config_overrides_record, remove_access_key,
+    remove_account, set_access_key, set_code, set_global_contract_code, set_promise_yield_expiry,
+    set_promise_yield_indices, set_runtime_config_overrides_record, StorageError, TrieUpdate,
 };
 use near_vm_runner::logic::errors::{
     CacheError, CompilationError, FunctionCallError, InconsistentStateError, VMRunnerError,
@@ -253,7 +262,7 @@ pub(crate) fn action_function_call(
         )
         .into());
     }
-    state_update.trie.request_code_recording(account_id.clone());
+    state_update.trie.request_code_recording(account_id.clone(), account.code_hash());
     let mut receipt_manager = ReceiptManager::default();
     let mut runtime_ext = RuntimeExt::new(
         state_update,
@@ -335,25 +344,42 @@ pub(crate) fn action_function_call(
     result.compute_usage = safe_add_compute(result.compute_usage, outcome.compute_usage)?;
     result.logs.extend(outcome.logs);
     result.profile.merge(&outcome.profile);
+    result.storage_stats.merge(&outcome.storage_stats);
     if execution_succeeded {
         // Fetch metadata for PromiseYield timeout queue
         let mut promise_yield_indices = get_promise_yield_indices(state_update).unwrap_or_default();
         let initial_promse_yield_indices = promise_yield_indices.clone();
 
+        // Receipts created by the contract inherit the priority of the receipt
+        // which created them.
+        let priority = receipt.priority();
         let mut new_receipts: Vec<_> = receipt_manager
             .action_receipts
             .into_iter()
             .map(|receipt| {
                 // If the newly created receipt is a PromiseYield, enqueue a timeout for it
                 if receipt.is_promise_yield {
+                    let expires_at = apply_state.block_height
+                        + config.wasm_config.limit_config.yield_timeout_length_in_blocks;
                     enqueue_promise_yield_timeout(
                         state_update,
                         &mut promise_yield_indices,
                         account_id.clone(),
                         receipt.input_data_ids[0],
-                        apply_state.block_height
-                            + config.wasm_config.limit_config.yield_timeout_length_in_blocks,
+                        expires_at,
                     );
+                    // Remember when the yield times out, so that its status can be looked up
+                    // by the `data_id` alone.
+                    if ProtocolFeature::PromiseYieldStatus
+                        .enabled(apply_state.current_protocol_version)
+                    {
+                        set_promise_yield_expiry(
+                            state_update,
+                            account_id.clone(),
+                            receipt.input_data_ids[0],
+                            expires_at,
+                        );
+                    }
                 }
 
                 let new_action_receipt = ActionReceipt {
@@ -365,18 +391,19 @@ pub(crate) fn action_function_call(
                     actions: receipt.actions,
                 };
 
-                Receipt::V0(ReceiptV0 {
-                    predecessor_id: account_id.clone(),
-                    receiver_id: receipt.receiver_id,
+                Receipt::new(
+                    account_id.clone(),
+                    receipt.receiver_id,
                     // Actual receipt ID is set in the Runtime.apply_action_receipt(...) in the
                     // "Generating receipt IDs" section
-                    receipt_id: CryptoHash::default(),
-                    receipt: if receipt.is_promise_yield {
+                    CryptoHash::default(),
+                    if receipt.is_promise_yield {
                         ReceiptEnum::PromiseYield(new_action_receipt)
                     } else {
                         ReceiptEnum::Action(new_action_receipt)
                     },
-                })
+                    priority,
+                )
             })
             .collect();
 
@@ -384,18 +411,19 @@ pub(crate) fn action_function_call(
         new_receipts.extend(receipt_manager.data_receipts.into_iter().map(|receipt| {
             let new_data_receipt = DataReceipt { data_id: receipt.data_id, data: receipt.data };
 
-            Receipt::V0(ReceiptV0 {
-                predecessor_id: account_id.clone(),
-                receiver_id: account_id.clone(),
+            Receipt::new(
+                account_id.clone(),
+                account_id.clone(),
                 // Actual receipt ID is set in the Runtime.apply_action_receipt(...) in the
                 // "Generating receipt IDs" section
-                receipt_id: CryptoHash::default(),
-                receipt: if receipt.is_promise_resume {
+                CryptoHash::default(),
+                if receipt.is_promise_resume {
                     ReceiptEnum::PromiseResume(new_data_receipt)
                 } else {
                     ReceiptEnum::Data(new_data_receipt)
                 },
-            })
+                priority,
+            )
         }));
 
         // Commit metadata for yielded promises queue
@@ -697,6 +725,231 @@ pub(crate) fn action_deploy_contract(
     Ok(())
 }
 
+/// Stores the code in the current shard and sends distribution receipts which
+/// store it in every other shard of the layout.
+///
+/// The code is not owned by anyone. The deployer burns the storage cost of the
+/// code in all shards upfront, so the code stays available when the deployer
+/// is deleted. Deploying code which is already stored is a no-op.
+pub(crate) fn action_deploy_global_contract(
+    state_update: &mut TrieUpdate,
+    account: &mut Account,
+    account_id: &AccountId,
+    deploy_global_contract: &DeployGlobalContractAction,
+    apply_state: &ApplyState,
+    shard_layout: &ShardLayout,
+    result: &mut ActionResult,
+) -> Result<(), RuntimeError> {
+    let _span = tracing::debug_span!(target: "runtime", "action_deploy_global_contract").entered();
+    let code = ContractCode::new(deploy_global_contract.code.clone(), None);
+    if get_global_contract_code(state_update, *code.hash())?.is_some() {
+        return Ok(());
+    }
+    let num_shards = shard_layout.shard_ids().count() as Balance;
+    let storage_cost = apply_state
+        .config
+        .storage_amount_per_byte()
+        .checked_mul(code.code().len() as Balance)
+        .and_then(|cost| cost.checked_mul(num_shards))
+        .ok_or(IntegerOverflowError)?;
+    let Some(amount) = account.amount().checked_sub(storage_cost) else {
+        result.result = Err(ActionErrorKind::LackBalanceForState {
+            account_id: account_id.clone(),
+            amount: storage_cost - account.amount(),
+        }
+        .into());
+        return Ok(());
+    };
+    account.set_amount(amount);
+    result.other_burnt_amount = safe_add_balance(result.other_burnt_amount, storage_cost)?;
+
+    set_global_contract_code(state_update, &code);
+    precompile_contract(&code, &apply_state.config.wasm_config, apply_state.cache.as_deref()).ok();
+    result.new_receipts.extend(global_contract_distribution_receipts(
+        account_id,
+        deploy_global_contract.code.clone(),
+        shard_layout,
+        vec![apply_state.shard_id],
+    ));
+    Ok(())
+}
+
+/// Stores the code carried by the distribution receipt in the current shard if
+/// it isn't stored yet.
+///
+/// The receipts for all shards are sent at once by the deployer's shard, so
+/// nothing is sent further, unless the receipt was sent before a resharding.
+pub(crate) fn apply_global_contract_distribution_receipt(
+    state_update: &mut TrieUpdate,
+    apply_state: &ApplyState,
+    receipt: &Receipt,
+    distribution_receipt: &GlobalContractDistributionReceipt,
+    shard_layout: &ShardLayout,
+) -> Result<Vec<Receipt>, StorageError> {
+    let _span =
+        tracing::debug_span!(target: "runtime", "apply_global_contract_distribution_receipt")
+            .entered();
+    let code = ContractCode::new(distribution_receipt.code.clone(), None);
+    if get_global_contract_code(state_update, *code.hash())?.is_none() {
+        set_global_contract_code(state_update, &code);
+        precompile_contract(&code, &apply_state.config.wasm_config, apply_state.cache.as_deref())
+            .ok();
+    }
+    if distribution_receipt.shard_layout_version == shard_layout.version() {
+        return Ok(vec![]);
+    }
+    // The receipt was sent before a resharding, so the shards of the new
+    // layout it was meant for are not known. The distribution starts over in
+    // the new layout. Shards which store the code already just skip storing it.
+    Ok(global_contract_distribution_receipts(
+        receipt.predecessor_id(),
+        distribution_receipt.code.clone(),
+        shard_layout,
+        vec![apply_state.shard_id],
+    ))
+}
+
+/// Creates the receipts which store the code in every shard of the layout which
+/// doesn't store it yet.
+fn global_contract_distribution_receipts(
+    deployer_id: &AccountId,
+    code: Vec<u8>,
+    shard_layout: &ShardLayout,
+    already_delivered_shards: Vec<ShardId>,
+) -> Vec<Receipt> {
+    shard_layout
+        .shard_ids()
+        .filter(|shard_id| !already_delivered_shards.contains(shard_id))
+        .map(|target_shard| {
+            Receipt::new(
+                deployer_id.clone(),
+                deployer_id.clone(),
+                // Actual receipt ID is set when the receipt is forwarded.
+                CryptoHash::default(),
+                ReceiptEnum::GlobalContractDistribution(GlobalContractDistributionReceipt {
+                    code: code.clone(),
+                    shard_layout_version: shard_layout.version(),
+                    target_shard,
+                    already_delivered_shards: already_delivered_shards.clone(),
+                }),
+                ReceiptPriority::NoPriority,
+            )
+        })
+        .collect()
+}
+
+/// Stores the runtime config overrides carried by the distribution receipt in
+/// the current shard, unless they arrive too late.
+///
+/// The epoch from which the overrides apply is chosen by the shard of the
+/// protocol treasury account and carried in the receipt. If the receipt was
+/// delayed, e.g. buffered by congestion control, until that epoch started, the
+/// shard has already applied chunks of it without the overrides. Storing them
+/// then would make the shard apply them from a different epoch than the other
+/// shards, so they are rejected. The treasury shard sends its latest overrides
+/// again in the next epoch.
+pub(crate) fn apply_runtime_config_overrides_distribution_receipt(
+    state_update: &mut TrieUpdate,
+    apply_state: &ApplyState,
+    receipt: &Receipt,
+    distribution_receipt: &RuntimeConfigOverridesDistributionReceipt,
+    shard_layout: &ShardLayout,
+) -> Result<Vec<Receipt>, StorageError> {
+    let _span = tracing::debug_span!(
+        target: "runtime",
+        "apply_runtime_config_overrides_distribution_receipt"
+    )
+    .entered();
+    let mut record = get_runtime_config_overrides_record(state_update)?;
+    if apply_state.epoch_height >= distribution_receipt.from_epoch_height {
+        tracing::warn!(
+            target: "runtime",
+            shard_id = %apply_state.shard_id,
+            epoch_height = apply_state.epoch_height,
+            from_epoch_height = distribution_receipt.from_epoch_height,
+            "Rejecting runtime config overrides which arrived too late"
+        );
+        metrics::LATE_RUNTIME_CONFIG_OVERRIDES_TOTAL.inc();
+    } else if distribution_receipt.overrides != record.latest() {
+        record.insert(
+            distribution_receipt.from_epoch_height,
+            distribution_receipt.overrides.clone(),
+            apply_state.epoch_height,
+        );
+        set_runtime_config_overrides_record(state_update, &record);
+    }
+    if distribution_receipt.shard_layout_version == shard_layout.version() {
+        return Ok(vec![]);
+    }
+    // Like for global contracts, the distribution starts over in the new
+    // layout if the receipt was sent before a resharding.
+    Ok(runtime_config_overrides_distribution_receipts(
+        receipt.predecessor_id(),
+        distribution_receipt.overrides.clone(),
+        distribution_receipt.from_epoch_height,
+        shard_layout,
+        vec![apply_state.shard_id],
+    ))
+}
+
+/// Creates the receipts which store the runtime config overrides in every shard
+/// of the layout which doesn't store them yet.
+pub(crate) fn runtime_config_overrides_distribution_receipts(
+    treasury_account_id: &AccountId,
+    overrides: RuntimeConfigOverrides,
+    from_epoch_height: EpochHeight,
+    shard_layout: &ShardLayout,
+    already_delivered_shards: Vec<ShardId>,
+) -> Vec<Receipt> {
+    shard_layout
+        .shard_ids()
+        .filter(|shard_id| !already_delivered_shards.contains(shard_id))
+        .map(|target_shard| {
+            Receipt::new(
+                treasury_account_id.clone(),
+                treasury_account_id.clone(),
+                // Actual receipt ID is set when the receipt is forwarded.
+                CryptoHash::default(),
+                ReceiptEnum::RuntimeConfigOverridesDistribution(
+                    RuntimeConfigOverridesDistributionReceipt {
+                        overrides: overrides.clone(),
+                        from_epoch_height,
+                        shard_layout_version: shard_layout.version(),
+                        target_shard,
+                        already_delivered_shards: already_delivered_shards.clone(),
+                    },
+                ),
+                ReceiptPriority::NoPriority,
+            )
+        })
+        .collect()
+}
+
+/// Replaces the contract of the account with the global contract of the given
+/// hash. The account no longer pays for the storage of its own code.
+pub(crate) fn action_use_global_contract(
+    state_update: &mut TrieUpdate,
+    account: &mut Account,
+    account_id: &AccountId,
+    use_global_contract: &UseGlobalContractAction,
+    result: &mut ActionResult,
+) -> Result<(), StorageError> {
+    let _span = tracing::debug_span!(target: "runtime", "action_use_global_contract").entered();
+    let code_hash = use_global_contract.code_hash;
+    state_update.trie.request_global_code_recording(code_hash);
+    if get_global_contract_code(state_update, code_hash)?.is_none() {
+        result.result = Err(ActionErrorKind::GlobalContractDoesNotExist { code_hash }.into());
+        return Ok(());
+    }
+    if let Some(prev_code) = get_code(state_update, account_id, Some(account.code_hash()))? {
+        let prev_code_length = prev_code.code().len() as u64;
+        account.set_storage_usage(account.storage_usage().saturating_sub(prev_code_length));
+        state_update.remove(TrieKey::ContractCode { account_id: account_id.clone() });
+    }
+    account.set_code_hash(code_hash);
+    Ok(())
+}
+
 pub(crate) fn action_delete_account(
     state_update: &mut TrieUpdate,
     account: &mut Option<Account>,
@@ -830,7 +1083,7 @@ pub(crate) fn apply_delegate_action(
     sender_id: &AccountId,
     signed_delegate_action: &SignedDelegateAction,
     result: &mut ActionResult,
-    _priority: ReceiptPriority,
+    priority: ReceiptPriority,
 ) -> Result<(), RuntimeError> {
     let delegate_action = &signed_delegate_action.delegate_action;
 
@@ -859,12 +1112,11 @@ pub(crate) fn apply_delegate_action(
     }
 
     // Generate a new receipt from DelegateAction.
-    let new_receipt = Receipt::V0(ReceiptV0 {
-        predecessor_id: sender_id.clone(),
-        receiver_id: delegate_action.receiver_id.clone(),
-        receipt_id: CryptoHash::default(),
-
-        receipt: ReceiptEnum::Action(ActionReceipt {
+    let new_receipt = Receipt::new(
+        sender_id.clone(),
+        delegate_action.receiver_id.clone(),
+        CryptoHash::default(),
+        ReceiptEnum::Action(ActionReceipt {
             signer_id: action_receipt.signer_id.clone(),
             signer_public_key: action_receipt.signer_public_key.clone(),
             gas_price: action_receipt.gas_price,
@@ -872,7 +1124,8 @@ pub(crate) fn apply_delegate_action(
             input_data_ids: vec![],
             actions: delegate_action.get_actions(),
         }),
-    });
+        priority,
+    );
 
     // Note, Relayer prepaid all fees and all things required by actions: attached deposits and attached gas.
     // If something goes wrong, deposit is refunded to the predecessor, this is sender_id/Sender in DelegateAction.
@@ -914,7 +1167,10 @@ fn receipt_required_gas(apply_state: &ApplyState, receipt: &Receipt) -> Result<G
 
             required_gas
         }
-        ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => 0,
+        ReceiptEnum::Data(_)
+        | ReceiptEnum::PromiseResume(_)
+        | ReceiptEnum::GlobalContractDistribution(_)
+        | ReceiptEnum::RuntimeConfigOverridesDistribution(_) => 0,
     })
 }
 
@@ -1040,7 +1296,12 @@ pub(crate) fn check_actor_permissions(
     account_id: &AccountId,
 ) -> Result<(), ActionError> {
     match action {
-        Action::DeployContract(_) | Action::Stake(_) | Action::AddKey(_) | Action::DeleteKey(_) => {
+        Action::DeployContract(_)
+        | Action::DeployGlobalContract(_)
+        | Action::UseGlobalContract(_)
+        | Action::Stake(_)
+        | Action::AddKey(_)
+        | Action::DeleteKey(_) => {
             if actor_id != account_id {
                 return Err(ActionErrorKind::ActorNoPermission {
                     account_id: account_id.clone(),
@@ -1152,7 +1413,9 @@ pub(crate) fn check_account_existence(
         | Action::AddKey(_)
         | Action::DeleteKey(_)
         | Action::DeleteAccount(_)
-        | Action::Delegate(_) => {
+        | Action::Delegate(_)
+        | Action::DeployGlobalContract(_)
+        | Action::UseGlobalContract(_) => {
             if account.is_none() {
                 return Err(ActionErrorKind::AccountDoesNotExist {
                     account_id: account_id.clone(),
@@ -1196,9 +1459,9 @@ mod tests {
     use near_primitives::action::delegate::NonDelegateAction;
     use near_primitives::errors::InvalidAccessKeyError;
     use near_primitives::hash::hash;
+    use near_primitives::receipt::ReceiptV0;
     use near_primitives::runtime::migration_data::MigrationFlags;
     use near_primitives::transaction::CreateAccountAction;
-    use near_primitives::trie_key::TrieKey;
     use near_primitives::types::{EpochId, StateChangeCause};
     use near_primitives_core::version::PROTOCOL_VERSION;
     use near_store::set_account;
@@ -1390,6 +1653,231 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_use_global_contract() {
+        let tries = TestTriesBuilder::new().build();
+        let mut state_update =
+            tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+        let apply_state = create_apply_state(1);
+        let code = vec![1; 100];
+        let code_hash = hash(&code);
+
+        let shard_layout = ShardLayout::v0(4, 0);
+        let storage_cost = apply_state.config.storage_amount_per_byte() * 100 * 4;
+
+        let deployer_id: AccountId = "alice".parse().unwrap();
+        let mut deployer =
+            Account::new(storage_cost - 1, 0, 0, CryptoHash::default(), 100, PROTOCOL_VERSION);
+        let deploy = DeployGlobalContractAction { code: code.clone() };
+        let mut action_result = ActionResult::default();
+        action_deploy_global_contract(
+            &mut state_update,
+            &mut deployer,
+            &deployer_id,
+            &deploy,
+            &apply_state,
+            &shard_layout,
+            &mut action_result,
+        )
+        .unwrap();
+        assert_eq!(
+            action_result.result,
+            Err(ActionErrorKind::LackBalanceForState {
+                account_id: deployer_id.clone(),
+                amount: 1
+            }
+            .into())
+        );
+        assert!(get_global_contract_code(&state_update, code_hash).unwrap().is_none());
+
+        deployer.set_amount(storage_cost);
+        for deployment in 0..2 {
+            let mut action_result = ActionResult::default();
+            action_deploy_global_contract(
+                &mut state_update,
+                &mut deployer,
+                &deployer_id,
+                &deploy,
+                &apply_state,
+                &shard_layout,
+                &mut action_result,
+            )
+            .unwrap();
+            assert!(action_result.result.is_ok());
+            // The code is only paid for and distributed when it is deployed for the first
+            // time. The deployer doesn't own it, so its storage usage doesn't change.
+            assert_eq!(deployer.amount(), 0);
+            assert_eq!(deployer.storage_usage(), 100);
+            if deployment == 0 {
+                assert_eq!(action_result.other_burnt_amount, storage_cost);
+                assert_eq!(action_result.new_receipts.len(), 1);
+            } else {
+                assert_eq!(action_result.other_burnt_amount, 0);
+                assert!(action_result.new_receipts.is_empty());
+            }
+        }
+
+        let account_id: AccountId = "bob".parse().unwrap();
+        let own_code = vec![0; 50];
+        let mut account = Account::new(100, 0, 0, hash(&own_code), 150, PROTOCOL_VERSION);
+        state_update.set(TrieKey::ContractCode { account_id: account_id.clone() }, own_code);
+
+        let mut action_result = ActionResult::default();
+        let missing_hash = hash(&[2; 100]);
+        action_use_global_contract(
+            &mut state_update,
+            &mut account,
+            &account_id,
+            &UseGlobalContractAction { code_hash: missing_hash },
+            &mut action_result,
+        )
+        .unwrap();
+        assert_eq!(
+            action_result.result,
+            Err(ActionErrorKind::GlobalContractDoesNotExist { code_hash: missing_hash }.into())
+        );
+        assert_eq!(account.storage_usage(), 150);
+
+        let mut action_result = ActionResult::default();
+        action_use_global_contract(
+            &mut state_update,
+            &mut account,
+            &account_id,
+            &UseGlobalContractAction { code_hash },
+            &mut action_result,
+        )
+        .unwrap();
+        assert!(action_result.result.is_ok());
+        assert_eq!(account.storage_usage(), 100);
+        assert_eq!(account.code_hash(), code_hash);
+        assert!(get_code(&state_update, &account_id, None).unwrap().is_none());
+        let contract = state_update.get_code(account_id, code_hash).unwrap();
+        assert_eq!(contract.code(), code.as_slice());
+    }
+
+    #[test]
+    fn test_global_contract_distribution() {
+        let tries = TestTriesBuilder::new().build();
+        let shard_layout = ShardLayout::v0(3, 0);
+        let code = vec![1; 100];
+        let code_hash = hash(&code);
+        let deployer_id: AccountId = "alice".parse().unwrap();
+        let mut apply_state = create_apply_state(1);
+        apply_state.shard_id = 1;
+
+        let mut state_update =
+            tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+        let mut deployer = Account::new(
+            apply_state.config.storage_amount_per_byte() * 100 * 3,
+            0,
+            0,
+            CryptoHash::default(),
+            100,
+            PROTOCOL_VERSION,
+        );
+        let mut action_result = ActionResult::default();
+        action_deploy_global_contract(
+            &mut state_update,
+            &mut deployer,
+            &deployer_id,
+            &DeployGlobalContractAction { code: code.clone() },
+            &apply_state,
+            &shard_layout,
+            &mut action_result,
+        )
+        .unwrap();
+
+        // Every other shard of the layout gets its own receipt right away.
+        assert_eq!(action_result.new_receipts.len(), 2);
+        for (receipt, expected_shard) in action_result.new_receipts.iter().zip([0, 2]) {
+            let ReceiptEnum::GlobalContractDistribution(distribution_receipt) = receipt.receipt()
+            else {
+                panic!("expected a global contract distribution receipt");
+            };
+            assert_eq!(receipt.receiver_shard_id(&shard_layout), expected_shard);
+            apply_state.shard_id = expected_shard;
+            let mut state_update =
+                tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+            let next_receipts = apply_global_contract_distribution_receipt(
+                &mut state_update,
+                &apply_state,
+                receipt,
+                distribution_receipt,
+                &shard_layout,
+            )
+            .unwrap();
+            assert!(get_global_contract_code(&state_update, code_hash).unwrap().is_some());
+            assert!(next_receipts.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_runtime_config_overrides_distribution() {
+        let tries = TestTriesBuilder::new().build();
+        let shard_layout = ShardLayout::v0(3, 0);
+        let treasury_id: AccountId = "near".parse().unwrap();
+        let overrides =
+            RuntimeConfigOverrides { storage_amount_per_byte: Some(1), ..Default::default() };
+        let mut apply_state = create_apply_state(1);
+        apply_state.epoch_height = 5;
+        let receipts = runtime_config_overrides_distribution_receipts(
+            &treasury_id,
+            overrides.clone(),
+            7,
+            &shard_layout,
+            vec![1],
+        );
+
+        // The receipts are sent to every other shard of the layout at once.
+        assert_eq!(receipts.len(), 2);
+        for (receipt, expected_shard) in receipts.iter().zip([0, 2]) {
+            let ReceiptEnum::RuntimeConfigOverridesDistribution(distribution_receipt) =
+                receipt.receipt()
+            else {
+                panic!("expected a runtime config overrides distribution receipt");
+            };
+            assert_eq!(receipt.receiver_shard_id(&shard_layout), expected_shard);
+            apply_state.shard_id = expected_shard;
+            let mut state_update =
+                tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+            let next_receipts = apply_runtime_config_overrides_distribution_receipt(
+                &mut state_update,
+                &apply_state,
+                receipt,
+                distribution_receipt,
+                &shard_layout,
+            )
+            .unwrap();
+            assert!(next_receipts.is_empty());
+            let record = get_runtime_config_overrides_record(&state_update).unwrap();
+            assert_eq!(record.get(6), RuntimeConfigOverrides::default());
+            assert_eq!(record.get(7), overrides);
+        }
+
+        // A receipt which arrives once the epoch from which the overrides
+        // apply has started is rejected.
+        apply_state.epoch_height = 7;
+        let receipt = &receipts[0];
+        let ReceiptEnum::RuntimeConfigOverridesDistribution(distribution_receipt) =
+            receipt.receipt()
+        else {
+            panic!("expected a runtime config overrides distribution receipt");
+        };
+        let mut state_update =
+            tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+        apply_runtime_config_overrides_distribution_receipt(
+            &mut state_update,
+            &apply_state,
+            receipt,
+            distribution_receipt,
+            &shard_layout,
+        )
+        .unwrap();
+        let record = get_runtime_config_overrides_record(&state_update).unwrap();
+        assert_eq!(record.get(7), RuntimeConfigOverrides::default());
+        assert!(record.entries.is_empty());
+    }
+
     fn create_delegate_action_receipt() -> (ActionReceipt, SignedDelegateAction) {
         let signed_delegate_action = SignedDelegateAction {
             delegate_action: DelegateAction {
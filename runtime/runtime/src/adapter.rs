@@ -1,12 +1,13 @@
 use crate::near_primitives::shard_layout::ShardUId;
 use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account};
+use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{
     AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, MerkleHash,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::ViewStateResult;
+use near_primitives::views::{PromiseYieldView, ViewStateResult};
 use near_vm_runner::ContractCode;
 
 /// Adapter for querying runtime.
@@ -15,6 +16,7 @@ pub trait ViewRuntimeAdapter {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
     ) -> Result<Account, crate::state_viewer::errors::ViewAccountError>;
 
@@ -22,6 +24,7 @@ pub trait ViewRuntimeAdapter {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
     ) -> Result<ContractCode, crate::state_viewer::errors::ViewContractCodeError>;
 
@@ -47,6 +50,7 @@ pub trait ViewRuntimeAdapter {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
         public_key: &PublicKey,
     ) -> Result<AccessKey, crate::state_viewer::errors::ViewAccessKeyError>;
@@ -55,6 +59,7 @@ pub trait ViewRuntimeAdapter {
         &self,
         shard_uid: &ShardUId,
         state_root: MerkleHash,
+        block_hash: &CryptoHash,
         account_id: &AccountId,
     ) -> Result<Vec<(PublicKey, AccessKey)>, crate::state_viewer::errors::ViewAccessKeyError>;
 
@@ -66,4 +71,14 @@ pub trait ViewRuntimeAdapter {
         prefix: &[u8],
         include_proof: bool,
     ) -> Result<ViewStateResult, crate::state_viewer::errors::ViewStateError>;
+
+    fn view_promise_yield(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        block_hash: &CryptoHash,
+        block_height: BlockHeight,
+        account_id: &AccountId,
+        data_id: CryptoHash,
+    ) -> Result<PromiseYieldView, StorageError>;
 }
@@ -15,8 +15,8 @@ use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{AccountId, Balance};
 use near_store::trie::receipts_column_helper::{ShardsOutgoingReceiptBuffer, TrieQueue};
 use near_store::{
-    get, get_account, get_postponed_receipt, get_promise_yield_receipt, Trie, TrieAccess,
-    TrieUpdate,
+    get, get_account, get_postponed_receipt, get_priority_delayed_receipt_indices,
+    get_promise_yield_receipt, Trie, TrieAccess, TrieUpdate,
 };
 use std::collections::{BTreeSet, HashSet};
 
@@ -24,10 +24,11 @@ use std::collections::{BTreeSet, HashSet};
 fn get_delayed_receipts(
     state: &dyn TrieAccess,
     indexes: std::ops::Range<u64>,
+    trie_key: impl Fn(u64) -> TrieKey,
 ) -> Result<Vec<Receipt>, StorageError> {
     indexes
         .map(|index| {
-            get(state, &TrieKey::DelayedReceipt { index })?.ok_or_else(|| {
+            get(state, &trie_key(index))?.ok_or_else(|| {
                 StorageError::StorageInconsistentState(format!(
                     "Delayed receipt #{} should be in the state",
                     index
@@ -64,7 +65,10 @@ fn receipt_cost(
             }
             total_cost
         }
-        ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => 0,
+        ReceiptEnum::Data(_)
+        | ReceiptEnum::PromiseResume(_)
+        | ReceiptEnum::GlobalContractDistribution(_)
+        | ReceiptEnum::RuntimeConfigOverridesDistribution(_) => 0,
     })
 }
 
@@ -251,6 +255,8 @@ fn potential_postponed_receipt_ids(
                     account_id.clone(),
                     data_receipt.data_id,
                 ))),
+                ReceiptEnum::GlobalContractDistribution(_)
+                | ReceiptEnum::RuntimeConfigOverridesDistribution(_) => None,
             }
         })
         .collect::<Result<HashSet<_>, StorageError>>()
@@ -279,18 +285,40 @@ pub(crate) fn check_balance(
         get(initial_state, &TrieKey::DelayedReceiptIndices)?.unwrap_or_default();
     let final_delayed_receipt_indices: DelayedReceiptIndices =
         get(final_state, &TrieKey::DelayedReceiptIndices)?.unwrap_or_default();
+    let initial_priority_delayed_receipt_indices =
+        get_priority_delayed_receipt_indices(initial_state)?;
+    let final_priority_delayed_receipt_indices = get_priority_delayed_receipt_indices(final_state)?;
 
     // Previously delayed receipts that were processed this time.
-    let processed_delayed_receipts = get_delayed_receipts(
+    let mut processed_delayed_receipts = get_delayed_receipts(
         initial_state,
         initial_delayed_receipt_indices.first_index..final_delayed_receipt_indices.first_index,
+        |index| TrieKey::DelayedReceipt { index },
     )?;
     // Receipts that were not processed this time and are delayed now.
-    let new_delayed_receipts = get_delayed_receipts(
+    let mut new_delayed_receipts = get_delayed_receipts(
         final_state,
         initial_delayed_receipt_indices.next_available_index
             ..final_delayed_receipt_indices.next_available_index,
+        |index| TrieKey::DelayedReceipt { index },
     )?;
+    // Priority lanes are never removed, so a lane missing from the initial
+    // state is one created by this chunk.
+    for (&lane, final_indices) in &final_priority_delayed_receipt_indices.lanes {
+        let initial_indices =
+            initial_priority_delayed_receipt_indices.lanes.get(&lane).cloned().unwrap_or_default();
+        let trie_key = |index| TrieKey::PriorityDelayedReceipt { lane, index };
+        processed_delayed_receipts.extend(get_delayed_receipts(
+            initial_state,
+            initial_indices.first_index..final_indices.first_index,
+            trie_key,
+        )?);
+        new_delayed_receipts.extend(get_delayed_receipts(
+            final_state,
+            initial_indices.next_available_index..final_indices.next_available_index,
+            trie_key,
+        )?);
+    }
 
     // Buffered receipts
     let (forwarded_receipts, new_buffered_receipts) =
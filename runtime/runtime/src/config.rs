@@ -3,14 +3,20 @@
 use near_primitives::account::AccessKeyPermission;
 use near_primitives::errors::IntegerOverflowError;
 use near_primitives::version::FIXED_MINIMUM_NEW_RECEIPT_GAS_VERSION;
+use near_primitives_core::account::id::AccountType;
 use near_primitives_core::types::ProtocolVersion;
 use num_bigint::BigUint;
 use num_traits::cast::ToPrimitive;
 use num_traits::pow::Pow;
 // Just re-exporting RuntimeConfig for backwards compatibility.
-use near_parameters::{transfer_exec_fee, transfer_send_fee, ActionCosts, RuntimeConfig};
+use near_parameters::{
+    transfer_exec_compute, transfer_exec_fee, transfer_send_fee, ActionCosts, Fee, RuntimeConfig,
+    RuntimeFeesConfig,
+};
 pub use near_primitives::num_rational::Rational32;
-use near_primitives::transaction::{Action, DeployContractAction, Transaction};
+use near_primitives::transaction::{
+    Action, DeployContractAction, DeployGlobalContractAction, Transaction, PRIORITY_FEE_UNIT,
+};
 use near_primitives::types::{AccountId, Balance, Compute, Gas};
 
 /// Describes the cost of converting this transaction into a receipt.
@@ -150,6 +156,17 @@ pub fn total_send_fees(
                         &delegate_action.receiver_id,
                     )?
             }
+            // TODO(global_contracts) Before stabilizing, consider using separate gas cost
+            // parameters for global contracts.
+            DeployGlobalContract(DeployGlobalContractAction { code }) => {
+                let num_bytes = code.len() as u64;
+                fees.fee(ActionCosts::deploy_contract_base).send_fee(sender_is_receiver)
+                    + fees.fee(ActionCosts::deploy_contract_byte).send_fee(sender_is_receiver)
+                        * num_bytes
+            }
+            UseGlobalContract(_) => {
+                fees.fee(ActionCosts::deploy_contract_base).send_fee(sender_is_receiver)
+            }
         };
         result = safe_add_gas(result, delta)?;
     }
@@ -188,24 +205,40 @@ pub fn total_prepaid_send_fees(
 }
 
 pub fn exec_fee(config: &RuntimeConfig, action: &Action, receiver_id: &AccountId) -> Gas {
+    exec_cost(config, action, receiver_id, Fee::exec_fee, transfer_exec_fee)
+}
+
+/// Compute cost of executing the action, which matches [`exec_fee`] unless
+/// the compute costs of the action fees are set higher than the gas.
+pub fn exec_compute(config: &RuntimeConfig, action: &Action, receiver_id: &AccountId) -> Compute {
+    exec_cost(config, action, receiver_id, Fee::exec_compute, transfer_exec_compute)
+}
+
+fn exec_cost(
+    config: &RuntimeConfig,
+    action: &Action,
+    receiver_id: &AccountId,
+    cost: fn(&Fee) -> u64,
+    transfer_cost: fn(&RuntimeFeesConfig, bool, bool, AccountType) -> u64,
+) -> u64 {
     use Action::*;
     let fees = &config.fees;
     match action {
-        CreateAccount(_) => fees.fee(ActionCosts::create_account).exec_fee(),
+        CreateAccount(_) => cost(fees.fee(ActionCosts::create_account)),
         DeployContract(DeployContractAction { code }) => {
             let num_bytes = code.len() as u64;
-            fees.fee(ActionCosts::deploy_contract_base).exec_fee()
-                + fees.fee(ActionCosts::deploy_contract_byte).exec_fee() * num_bytes
+            cost(fees.fee(ActionCosts::deploy_contract_base))
+                + cost(fees.fee(ActionCosts::deploy_contract_byte)) * num_bytes
         }
         FunctionCall(function_call_action) => {
             let num_bytes = function_call_action.method_name.as_bytes().len() as u64
                 + function_call_action.args.len() as u64;
-            fees.fee(ActionCosts::function_call_base).exec_fee()
-                + fees.fee(ActionCosts::function_call_byte).exec_fee() * num_bytes
+            cost(fees.fee(ActionCosts::function_call_base))
+                + cost(fees.fee(ActionCosts::function_call_byte)) * num_bytes
         }
         Transfer(_) => {
             // Account for implicit account creation
-            transfer_exec_fee(
+            transfer_cost(
                 fees,
                 config.wasm_config.implicit_account_creation,
                 config.wasm_config.eth_implicit_accounts,
@@ -215,14 +248,14 @@ pub fn exec_fee(config: &RuntimeConfig, action: &Action, receiver_id: &AccountId
         #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
         NonrefundableStorageTransfer(_) => {
             // Account for implicit account creation
-            transfer_exec_fee(
+            transfer_cost(
                 fees,
                 config.wasm_config.implicit_account_creation,
                 config.wasm_config.eth_implicit_accounts,
                 receiver_id.get_account_type(),
             )
         }
-        Stake(_) => fees.fee(ActionCosts::stake).exec_fee(),
+        Stake(_) => cost(fees.fee(ActionCosts::stake)),
         AddKey(add_key_action) => match &add_key_action.access_key.permission {
             AccessKeyPermission::FunctionCall(call_perm) => {
                 let num_bytes = call_perm
@@ -231,16 +264,20 @@ pub fn exec_fee(config: &RuntimeConfig, action: &Action, receiver_id: &AccountId
                     // Account for null-terminating characters.
                     .map(|name| name.as_bytes().len() as u64 + 1)
                     .sum::<u64>();
-                fees.fee(ActionCosts::add_function_call_key_base).exec_fee()
-                    + num_bytes * fees.fee(ActionCosts::add_function_call_key_byte).exec_fee()
-            }
-            AccessKeyPermission::FullAccess => {
-                fees.fee(ActionCosts::add_full_access_key).exec_fee()
+                cost(fees.fee(ActionCosts::add_function_call_key_base))
+                    + num_bytes * cost(fees.fee(ActionCosts::add_function_call_key_byte))
             }
+            AccessKeyPermission::FullAccess => cost(fees.fee(ActionCosts::add_full_access_key)),
         },
-        DeleteKey(_) => fees.fee(ActionCosts::delete_key).exec_fee(),
-        DeleteAccount(_) => fees.fee(ActionCosts::delete_account).exec_fee(),
-        Delegate(_) => fees.fee(ActionCosts::delegate).exec_fee(),
+        DeleteKey(_) => cost(fees.fee(ActionCosts::delete_key)),
+        DeleteAccount(_) => cost(fees.fee(ActionCosts::delete_account)),
+        Delegate(_) => cost(fees.fee(ActionCosts::delegate)),
+        DeployGlobalContract(DeployGlobalContractAction { code }) => {
+            let num_bytes = code.len() as u64;
+            cost(fees.fee(ActionCosts::deploy_contract_base))
+                + cost(fees.fee(ActionCosts::deploy_contract_byte)) * num_bytes
+        }
+        UseGlobalContract(_) => cost(fees.fee(ActionCosts::deploy_contract_base)),
     }
 }
 
@@ -301,7 +338,14 @@ pub fn tx_cost(
         gas_remaining,
         total_prepaid_exec_fees(config, transaction.actions(), transaction.receiver_id())?,
     )?;
-    let burnt_amount = safe_gas_to_balance(gas_price, gas_burnt)?;
+    let mut burnt_amount = safe_gas_to_balance(gas_price, gas_burnt)?;
+    // The priority fee is burnt on top of the gas.
+    if let Some(priority_fee) = transaction.priority_fee() {
+        let priority_fee_amount = Balance::from(priority_fee)
+            .checked_mul(PRIORITY_FEE_UNIT)
+            .ok_or(IntegerOverflowError)?;
+        burnt_amount = safe_add_balance(burnt_amount, priority_fee_amount)?;
+    }
     let remaining_gas_amount = safe_gas_to_balance(receipt_gas_price, gas_remaining)?;
     let mut total_cost = safe_add_balance(burnt_amount, remaining_gas_amount)?;
     total_cost = safe_add_balance(total_cost, total_deposit(&transaction.actions())?)?;
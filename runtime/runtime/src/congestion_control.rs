@@ -9,7 +9,7 @@ use near_primitives::receipt::{Receipt, ReceiptEnum};
 use near_primitives::types::{EpochInfoProvider, Gas, ShardId};
 use near_primitives::version::ProtocolFeature;
 use near_store::trie::receipts_column_helper::{
-    DelayedReceiptQueue, ShardsOutgoingReceiptBuffer, TrieQueue,
+    DelayedReceiptQueue, PriorityDelayedReceiptQueue, ShardsOutgoingReceiptBuffer, TrieQueue,
 };
 use near_store::{StorageError, TrieAccess, TrieUpdate};
 use near_vm_runner::logic::ProtocolVersion;
@@ -54,6 +54,10 @@ enum ReceiptForwarding {
 /// A wrapper around `DelayedReceiptQueue` to accumulate changes in gas and
 /// bytes.
 ///
+/// With `TransactionPriority` enabled, it also holds the queues of delayed
+/// receipts with a priority. Those are popped before all other receipts, from
+/// the highest priority lane first.
+///
 /// This struct exists for two reasons. One, to encapsulate the accounting of
 /// gas and bytes in functions that can be called in all necessary places. Two,
 /// to accumulate changes and only apply them to `CongestionInfo` in the end,
@@ -64,6 +68,7 @@ enum ReceiptForwarding {
 /// the associated additional overflow conditions.
 pub(crate) struct DelayedReceiptQueueWrapper {
     queue: DelayedReceiptQueue,
+    priority_queue: Option<PriorityDelayedReceiptQueue>,
     new_delayed_gas: Gas,
     new_delayed_bytes: u64,
     removed_delayed_gas: Gas,
@@ -214,8 +219,8 @@ impl ReceiptSinkV2<'_> {
         state_update: &mut TrieUpdate,
         epoch_info_provider: &dyn EpochInfoProvider,
     ) -> Result<(), RuntimeError> {
-        let shard = epoch_info_provider
-            .account_id_to_shard_id(receipt.receiver_id(), &apply_state.epoch_id)?;
+        let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+        let shard = receipt.receiver_shard_id(&shard_layout);
         if shard == apply_state.shard_id {
             // No limits on receipts that stay on the same shard. Backpressure
             // wouldn't help, the receipt takes the same memory if buffered or
@@ -332,6 +337,15 @@ pub(crate) fn receipt_congestion_gas(
             // of it without expensive state lookups.
             Ok(0)
         }
+        ReceiptEnum::GlobalContractDistribution(_) => {
+            // Storing the code is paid for by the deployer upfront and does
+            // not use any gas.
+            Ok(0)
+        }
+        ReceiptEnum::RuntimeConfigOverridesDistribution(_) => {
+            // Sent by the runtime itself and does not use any gas.
+            Ok(0)
+        }
     }
 }
 
@@ -388,9 +402,13 @@ pub fn bootstrap_congestion_info(
 }
 
 impl DelayedReceiptQueueWrapper {
-    pub fn new(queue: DelayedReceiptQueue) -> Self {
+    pub fn new(
+        queue: DelayedReceiptQueue,
+        priority_queue: Option<PriorityDelayedReceiptQueue>,
+    ) -> Self {
         Self {
             queue,
+            priority_queue,
             new_delayed_gas: 0,
             new_delayed_bytes: 0,
             removed_delayed_gas: 0,
@@ -408,7 +426,12 @@ impl DelayedReceiptQueueWrapper {
         let delayed_bytes = receipt_size(receipt)? as u64;
         self.new_delayed_gas = safe_add_gas(self.new_delayed_gas, delayed_gas)?;
         self.new_delayed_bytes = safe_add_gas(self.new_delayed_bytes, delayed_bytes)?;
-        self.queue.push(trie_update, receipt)?;
+        match &mut self.priority_queue {
+            Some(priority_queue) if receipt.priority().value() > 0 => {
+                priority_queue.push(trie_update, receipt)?
+            }
+            _ => self.queue.push(trie_update, receipt)?,
+        }
         Ok(())
     }
 
@@ -417,7 +440,13 @@ impl DelayedReceiptQueueWrapper {
         trie_update: &mut TrieUpdate,
         config: &RuntimeConfig,
     ) -> Result<Option<Receipt>, RuntimeError> {
-        let receipt = self.queue.pop(trie_update)?;
+        let mut receipt = match &mut self.priority_queue {
+            Some(priority_queue) => priority_queue.pop(trie_update)?,
+            None => None,
+        };
+        if receipt.is_none() {
+            receipt = self.queue.pop(trie_update)?;
+        }
         if let Some(receipt) = &receipt {
             let delayed_gas = receipt_congestion_gas(receipt, &config)?;
             let delayed_bytes = receipt_size(receipt)? as u64;
@@ -428,7 +457,7 @@ impl DelayedReceiptQueueWrapper {
     }
 
     pub(crate) fn len(&self) -> u64 {
-        self.queue.len()
+        self.queue.len() + self.priority_queue.as_ref().map_or(0, |queue| queue.len())
     }
 
     pub(crate) fn apply_congestion_changes(
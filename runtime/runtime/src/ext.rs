@@ -6,10 +6,13 @@ use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::{AccountId, Balance, EpochId, EpochInfoProvider, Gas, TrieCacheMode};
 use near_primitives::utils::create_receipt_id_from_action_hash;
 use near_primitives::version::ProtocolVersion;
-use near_store::{has_promise_yield_receipt, KeyLookupMode, TrieUpdate, TrieUpdateValuePtr};
+use near_store::{
+    get_promise_yield_expiry, has_promise_yield_receipt, KeyLookupMode, TrieRange, TrieUpdate,
+    TrieUpdateValuePtr,
+};
 use near_vm_runner::logic::errors::{AnyError, VMLogicError};
-use near_vm_runner::logic::types::ReceiptIndex;
-use near_vm_runner::logic::{External, StorageGetMode, ValuePtr};
+use near_vm_runner::logic::types::{PromiseYieldStatus, ReceiptIndex};
+use near_vm_runner::logic::{External, HostError, StorageGetMode, ValuePtr};
 use near_vm_runner::ContractCode;
 
 pub struct RuntimeExt<'a> {
@@ -23,6 +26,14 @@ pub struct RuntimeExt<'a> {
     last_block_hash: &'a CryptoHash,
     epoch_info_provider: &'a dyn EpochInfoProvider,
     current_protocol_version: ProtocolVersion,
+    storage_iterators: Vec<StorageIterator>,
+}
+
+/// Iterator created by the contract over the keys of its own storage.
+struct StorageIterator {
+    range: TrieRange,
+    /// Raw key to continue from, `None` once the iterator is exhausted.
+    from: Option<Vec<u8>>,
 }
 
 /// Error used by `RuntimeExt`.
@@ -76,6 +87,7 @@ impl<'a> RuntimeExt<'a> {
             last_block_hash,
             epoch_info_provider,
             current_protocol_version,
+            storage_iterators: vec![],
         }
     }
 
@@ -92,6 +104,13 @@ impl<'a> RuntimeExt<'a> {
         TrieKey::ContractData { account_id: self.account_id.clone(), key: key.to_vec() }
     }
 
+    fn create_storage_iterator(&mut self, start: Vec<u8>, end: Vec<u8>) -> ExtResult<u64> {
+        let range = self.trie_update.trie().range(start, end).map_err(wrap_storage_error)?;
+        let from = Some(range.start().to_vec());
+        self.storage_iterators.push(StorageIterator { range, from });
+        Ok(self.storage_iterators.len() as u64 - 1)
+    }
+
     pub fn set_trie_cache_mode(&mut self, state: TrieCacheMode) {
         self.trie_update.set_trie_cache_mode(state);
     }
@@ -106,6 +125,21 @@ impl<'a> RuntimeExt<'a> {
     }
 }
 
+/// Returns the smallest key which is larger than all keys starting with `prefix`.
+///
+/// Raw keys of contract data start with the column byte, so `prefix` never
+/// consists of `0xff` bytes only.
+fn prefix_range_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while end.last() == Some(&u8::MAX) {
+        end.pop();
+    }
+    if let Some(last) = end.last_mut() {
+        *last += 1;
+    }
+    end
+}
+
 fn wrap_storage_error(error: StorageError) -> VMLogicError {
     VMLogicError::from(ExternalError::StorageError(error))
 }
@@ -176,6 +210,44 @@ impl<'a> External for RuntimeExt<'a> {
         Ok(())
     }
 
+    fn storage_iter_prefix(&mut self, prefix: &[u8]) -> ExtResult<u64> {
+        let start = trie_key_parsers::get_raw_prefix_for_contract_data(self.account_id, prefix);
+        let end = prefix_range_end(&start);
+        self.create_storage_iterator(start, end)
+    }
+
+    fn storage_iter_range(&mut self, start: &[u8], end: &[u8]) -> ExtResult<u64> {
+        let start = self.create_storage_key(start).to_vec();
+        let end = self.create_storage_key(end).to_vec();
+        self.create_storage_iterator(start, end)
+    }
+
+    fn storage_iter_next(&mut self, iterator_id: u64) -> ExtResult<Option<(Vec<u8>, Vec<u8>)>> {
+        let iterator = self
+            .storage_iterators
+            .get_mut(iterator_id as usize)
+            .ok_or(HostError::InvalidIteratorIndex { iterator_index: iterator_id })?;
+        let Some(from) = &iterator.from else { return Ok(None) };
+        let next = self
+            .trie_update
+            .get_first_in_range(&iterator.range, from)
+            .map_err(wrap_storage_error)?;
+        iterator.from = next.as_ref().map(|(raw_key, _)| {
+            let mut from = raw_key.clone();
+            from.push(0);
+            from
+        });
+        let Some((raw_key, value)) = next else { return Ok(None) };
+        let key =
+            trie_key_parsers::parse_data_key_from_contract_data_key(&raw_key, self.account_id)
+                .map_err(|_e| {
+                    wrap_storage_error(StorageError::StorageInconsistentState(
+                        "Can't parse data key from raw key for ContractData".to_string(),
+                    ))
+                })?;
+        Ok(Some((key.to_vec(), value)))
+    }
+
     fn generate_data_id(&mut self) -> CryptoHash {
         let data_id = create_receipt_id_from_action_hash(
             self.current_protocol_version,
@@ -252,6 +324,27 @@ impl<'a> External for RuntimeExt<'a> {
         self.receipt_manager.checked_resolve_promise_yield(data_id, data)
     }
 
+    fn promise_yield_status(
+        &self,
+        data_id: CryptoHash,
+    ) -> Result<PromiseYieldStatus, VMLogicError> {
+        // The expiry is recorded for every yield created by a previous transaction and removed
+        // once the yield is resumed or times out. Yields created before `PromiseYieldStatus` was
+        // enabled have no expiry recorded and are reported as not found.
+        if let Some(expires_at) =
+            get_promise_yield_expiry(self.trie_update, self.account_id, data_id)
+                .map_err(wrap_storage_error)?
+        {
+            return Ok(PromiseYieldStatus::Pending { expires_at });
+        }
+
+        // Yields created by the current transaction are only tracked by the receipt manager.
+        if self.receipt_manager.has_promise_yield(&data_id) {
+            return Ok(PromiseYieldStatus::Created);
+        }
+        Ok(PromiseYieldStatus::NotFound)
+    }
+
     fn append_action_create_account(
         &mut self,
         receipt_index: ReceiptIndex,
@@ -1,20 +1,21 @@
 use crate::actions::*;
 use crate::balance_checker::check_balance;
 use crate::config::{
-    exec_fee, safe_add_balance, safe_add_compute, safe_add_gas, safe_gas_to_balance, total_deposit,
-    total_prepaid_exec_fees, total_prepaid_gas,
+    exec_compute, exec_fee, safe_add_balance, safe_add_compute, safe_add_gas, safe_gas_to_balance,
+    total_deposit, total_prepaid_exec_fees, total_prepaid_gas,
 };
 use crate::congestion_control::DelayedReceiptQueueWrapper;
 use crate::prefetch::TriePrefetcher;
 use crate::verifier::{check_storage_stake, validate_receipt, StorageStakingError};
-pub use crate::verifier::{
-    validate_transaction, verify_and_charge_transaction, ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT,
-};
+pub use crate::verifier::{validate_transaction, verify_and_charge_transaction};
 use config::total_prepaid_send_fees;
 pub use congestion_control::bootstrap_congestion_info;
 use congestion_control::ReceiptSink;
 pub use near_crypto;
-use near_parameters::{ActionCosts, RuntimeConfig};
+use near_parameters::config::{
+    RUNTIME_CONFIG_OVERRIDES_ACTIVATION_DELAY, RUNTIME_CONFIG_OVERRIDES_KEY,
+};
+use near_parameters::{ActionCosts, RuntimeConfig, RuntimeConfigOverrides};
 pub use near_primitives;
 use near_primitives::account::Account;
 use near_primitives::checked_feature;
@@ -25,10 +26,11 @@ use near_primitives::errors::{
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{
     ActionReceipt, DataReceipt, DelayedReceiptIndices, PromiseYieldIndices, PromiseYieldTimeout,
-    Receipt, ReceiptEnum, ReceiptV0, ReceivedData,
+    Receipt, ReceiptEnum, ReceiptPriority, ReceiptV0, ReceivedData,
 };
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
 use near_primitives::sandbox::state_patch::SandboxStatePatch;
+use near_primitives::shard_layout::account_id_to_shard_id;
 use near_primitives::state_record::StateRecord;
 #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
 use near_primitives::transaction::NonrefundableStorageTransferAction;
@@ -48,12 +50,14 @@ use near_primitives::utils::{
 };
 use near_primitives::version::{ProtocolFeature, ProtocolVersion};
 use near_primitives_core::apply::ApplyChunkReason;
-use near_store::trie::receipts_column_helper::DelayedReceiptQueue;
+use near_store::trie::receipts_column_helper::{DelayedReceiptQueue, PriorityDelayedReceiptQueue};
 use near_store::{
     get, get_account, get_postponed_receipt, get_promise_yield_receipt, get_received_data,
-    has_received_data, remove_postponed_receipt, remove_promise_yield_receipt, set, set_access_key,
-    set_account, set_code, set_postponed_receipt, set_promise_yield_receipt, set_received_data,
-    PartialStorage, StorageError, Trie, TrieAccess, TrieChanges, TrieUpdate,
+    get_runtime_config_overrides_record, has_received_data, remove_postponed_receipt,
+    remove_promise_yield_expiry, remove_promise_yield_receipt, set, set_access_key, set_account,
+    set_code, set_postponed_receipt, set_promise_yield_receipt, set_received_data,
+    set_runtime_config_overrides_record, PartialStorage, StorageError, Trie, TrieAccess,
+    TrieChanges, TrieUpdate,
 };
 use near_vm_runner::logic::types::PromiseResult;
 use near_vm_runner::logic::ReturnData;
@@ -61,6 +65,7 @@ pub use near_vm_runner::with_ext_cost_counter;
 use near_vm_runner::ContractCode;
 use near_vm_runner::ContractRuntimeCache;
 use near_vm_runner::ProfileDataV3;
+use near_vm_runner::StorageAccessStats;
 use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -194,6 +199,9 @@ pub struct ActionResult {
     pub new_receipts: Vec<Receipt>,
     pub validator_proposals: Vec<ValidatorStake>,
     pub profile: Box<ProfileDataV3>,
+    pub storage_stats: StorageAccessStats,
+    /// Tokens burnt by the actions other than for gas.
+    pub other_burnt_amount: Balance,
 }
 
 impl ActionResult {
@@ -213,6 +221,9 @@ impl ActionResult {
         self.gas_used = safe_add_gas(self.gas_used, next_result.gas_used)?;
         self.compute_usage = safe_add_compute(self.compute_usage, next_result.compute_usage)?;
         self.profile.merge(&next_result.profile);
+        self.storage_stats.merge(&next_result.storage_stats);
+        self.other_burnt_amount =
+            safe_add_balance(self.other_burnt_amount, next_result.other_burnt_amount)?;
         self.result = next_result.result;
         self.logs.append(&mut next_result.logs);
         if let Ok(ReturnData::ReceiptIndex(ref mut receipt_index)) = self.result {
@@ -242,6 +253,8 @@ impl Default for ActionResult {
             new_receipts: vec![],
             validator_proposals: vec![],
             profile: Default::default(),
+            storage_stats: Default::default(),
+            other_burnt_amount: 0,
         }
     }
 }
@@ -312,11 +325,17 @@ impl Runtime {
                     &apply_state.prev_block_hash,
                     &apply_state.block_hash,
                 );
-                let receipt = Receipt::V0(ReceiptV0 {
-                    predecessor_id: transaction.signer_id().clone(),
-                    receiver_id: transaction.receiver_id().clone(),
+                // Only transactions with a priority fee produce receipts with
+                // a priority, and those are rejected before `TransactionPriority`.
+                let priority = match transaction.priority_fee() {
+                    Some(priority_fee) => ReceiptPriority::Priority(priority_fee),
+                    None => ReceiptPriority::NoPriority,
+                };
+                let receipt = Receipt::new(
+                    transaction.signer_id().clone(),
+                    transaction.receiver_id().clone(),
                     receipt_id,
-                    receipt: ReceiptEnum::Action(ActionReceipt {
+                    ReceiptEnum::Action(ActionReceipt {
                         signer_id: transaction.signer_id().clone(),
                         signer_public_key: transaction.public_key().clone(),
                         gas_price: verification_result.receipt_gas_price,
@@ -324,7 +343,8 @@ impl Runtime {
                         input_data_ids: vec![],
                         actions: transaction.actions().to_vec(),
                     }),
-                });
+                    priority,
+                );
                 stats.tx_burnt_amount =
                     safe_add_balance(stats.tx_burnt_amount, verification_result.burnt_amount)?;
                 let gas_burnt = verification_result.gas_burnt;
@@ -381,8 +401,7 @@ impl Runtime {
         let mut result = ActionResult::default();
         result.gas_used = exec_fees;
         result.gas_burnt = exec_fees;
-        // TODO(#8806): Support compute costs for actions. For now they match burnt gas.
-        result.compute_usage = exec_fees;
+        result.compute_usage = exec_compute(&apply_state.config, action, receipt.receiver_id());
         let account_id = receipt.receiver_id();
         let is_refund = receipt.predecessor_id().is_system();
         let is_the_only_action = actions.len() == 1;
@@ -408,6 +427,11 @@ impl Runtime {
             return Ok(result);
         }
         metrics::ACTION_CALLED_COUNT.with_label_values(&[action.as_ref()]).inc();
+        if result.compute_usage > result.gas_burnt {
+            metrics::ACTION_COMPUTE_EXCEEDING_GAS_TOTAL
+                .with_label_values(&[action.as_ref()])
+                .inc_by(result.compute_usage - result.gas_burnt);
+        }
         match action {
             Action::CreateAccount(_) => {
                 action_create_account(
@@ -532,6 +556,27 @@ impl Runtime {
                     receipt.priority(),
                 )?;
             }
+            Action::DeployGlobalContract(deploy_global_contract) => {
+                let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+                action_deploy_global_contract(
+                    state_update,
+                    account.as_mut().expect(EXPECT_ACCOUNT_EXISTS),
+                    account_id,
+                    deploy_global_contract,
+                    apply_state,
+                    &shard_layout,
+                    &mut result,
+                )?;
+            }
+            Action::UseGlobalContract(use_global_contract) => {
+                action_use_global_contract(
+                    state_update,
+                    account.as_mut().expect(EXPECT_ACCOUNT_EXISTS),
+                    account_id,
+                    use_global_contract,
+                    &mut result,
+                )?;
+            }
         };
         Ok(result)
     }
@@ -590,11 +635,10 @@ impl Runtime {
         let mut account = get_account(state_update, account_id)?;
         let mut actor_id = receipt.predecessor_id().clone();
         let mut result = ActionResult::default();
-        let exec_fees = apply_state.config.fees.fee(ActionCosts::new_action_receipt).exec_fee();
-        result.gas_used = exec_fees;
-        result.gas_burnt = exec_fees;
-        // TODO(#8806): Support compute costs for actions. For now they match burnt gas.
-        result.compute_usage = exec_fees;
+        let receipt_fee = apply_state.config.fees.fee(ActionCosts::new_action_receipt);
+        result.gas_used = receipt_fee.exec_fee();
+        result.gas_burnt = receipt_fee.exec_fee();
+        result.compute_usage = receipt_fee.exec_compute();
         #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
         let mut nonrefundable_amount_burnt: Balance = 0;
 
@@ -648,14 +692,25 @@ impl Runtime {
             }
         }
 
-        // Going to check balance covers account's storage.
+        // Going to check balance covers account's storage. Balance refunds only increase the
+        // balance, so since the storage staking exemption they are credited even to accounts
+        // which don't cover their storage instead of being burnt.
+        let is_refund = receipt.predecessor_id().is_system();
+        let skip_storage_check = is_refund
+            && ProtocolFeature::StorageStakingExemption
+                .enabled(apply_state.current_protocol_version);
         if result.result.is_ok() {
             if let Some(ref mut account) = account {
-                match check_storage_stake(
-                    account,
-                    &apply_state.config,
-                    apply_state.current_protocol_version,
-                ) {
+                let storage_stake_check = if skip_storage_check {
+                    Ok(())
+                } else {
+                    check_storage_stake(
+                        account,
+                        &apply_state.config,
+                        apply_state.current_protocol_version,
+                    )
+                };
+                match storage_stake_check {
                     Ok(()) => {
                         set_account(state_update, account_id.clone(), account);
                     }
@@ -728,6 +783,10 @@ impl Runtime {
                 state_update.rollback();
             }
         };
+        if result.result.is_ok() {
+            stats.other_burnt_amount =
+                safe_add_balance(stats.other_burnt_amount, result.other_burnt_amount)?;
+        }
         // If the receipt was successfully applied, we update `other_burnt_amount` statistic with the non-refundable amount burnt.
         #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
         if result.result.is_ok() {
@@ -799,15 +858,16 @@ impl Runtime {
                 };
                 result.new_receipts.extend(action_receipt.output_data_receivers.iter().map(
                     |data_receiver| {
-                        Receipt::V0(ReceiptV0 {
-                            predecessor_id: account_id.clone(),
-                            receiver_id: data_receiver.receiver_id.clone(),
-                            receipt_id: CryptoHash::default(),
-                            receipt: ReceiptEnum::Data(DataReceipt {
+                        Receipt::new(
+                            account_id.clone(),
+                            data_receiver.receiver_id.clone(),
+                            CryptoHash::default(),
+                            ReceiptEnum::Data(DataReceipt {
                                 data_id: data_receiver.data_id,
                                 data: data.clone(),
                             }),
-                        })
+                            receipt.priority(),
+                        )
                     },
                 ));
             };
@@ -866,6 +926,13 @@ impl Runtime {
 
         Self::print_log(&result.logs);
 
+        let metadata = if ProtocolFeature::StorageAccessMetadata
+            .enabled(apply_state.current_protocol_version)
+        {
+            ExecutionMetadata::V4(result.profile, result.storage_stats)
+        } else {
+            ExecutionMetadata::V3(result.profile)
+        };
         Ok(ExecutionOutcomeWithId {
             id: *receipt.receipt_id(),
             outcome: ExecutionOutcome {
@@ -876,7 +943,7 @@ impl Runtime {
                 compute_usage: Some(result.compute_usage),
                 tokens_burnt,
                 executor_id: account_id.clone(),
-                metadata: ExecutionMetadata::V3(result.profile),
+                metadata,
             },
         })
     }
@@ -1116,6 +1183,11 @@ impl Runtime {
                 {
                     // Remove the receipt from the state
                     remove_promise_yield_receipt(state_update, account_id, data_receipt.data_id);
+                    if ProtocolFeature::PromiseYieldStatus
+                        .enabled(apply_state.current_protocol_version)
+                    {
+                        remove_promise_yield_expiry(state_update, account_id, data_receipt.data_id);
+                    }
 
                     // Save the data into the state keyed by the data_id
                     set_received_data(
@@ -1145,6 +1217,64 @@ impl Runtime {
                     return Ok(None);
                 }
             }
+            ReceiptEnum::GlobalContractDistribution(ref distribution_receipt) => {
+                let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+                let next_receipts = apply_global_contract_distribution_receipt(
+                    state_update,
+                    apply_state,
+                    receipt,
+                    distribution_receipt,
+                    &shard_layout,
+                )?;
+                for (receipt_index, mut next_receipt) in next_receipts.into_iter().enumerate() {
+                    next_receipt.set_receipt_id(create_receipt_id_from_receipt_id(
+                        apply_state.current_protocol_version,
+                        receipt.receipt_id(),
+                        &apply_state.prev_block_hash,
+                        &apply_state.block_hash,
+                        receipt_index,
+                    ));
+                    receipt_sink.forward_or_buffer_receipt(
+                        next_receipt,
+                        apply_state,
+                        state_update,
+                        epoch_info_provider,
+                    )?;
+                }
+                state_update.commit(StateChangeCause::ReceiptProcessing {
+                    receipt_hash: receipt.get_hash(),
+                });
+                return Ok(None);
+            }
+            ReceiptEnum::RuntimeConfigOverridesDistribution(ref distribution_receipt) => {
+                let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+                let next_receipts = apply_runtime_config_overrides_distribution_receipt(
+                    state_update,
+                    apply_state,
+                    receipt,
+                    distribution_receipt,
+                    &shard_layout,
+                )?;
+                for (receipt_index, mut next_receipt) in next_receipts.into_iter().enumerate() {
+                    next_receipt.set_receipt_id(create_receipt_id_from_receipt_id(
+                        apply_state.current_protocol_version,
+                        receipt.receipt_id(),
+                        &apply_state.prev_block_hash,
+                        &apply_state.block_hash,
+                        receipt_index,
+                    ));
+                    receipt_sink.forward_or_buffer_receipt(
+                        next_receipt,
+                        apply_state,
+                        state_update,
+                        epoch_info_provider,
+                    )?;
+                }
+                state_update.commit(StateChangeCause::ReceiptProcessing {
+                    receipt_hash: receipt.get_hash(),
+                });
+                return Ok(None);
+            }
         };
         // We didn't trigger execution, so we need to commit the state.
         state_update
@@ -1152,6 +1282,41 @@ impl Runtime {
         Ok(None)
     }
 
+    /// Returns an outcome for the PromiseResume `receipt` if it was created by the runtime to
+    /// resolve a timed-out yield, and the yield was still pending when it arrived.
+    ///
+    /// Such receipts don't execute any code, but recording an outcome for them lets clients tell
+    /// a timed-out yield apart from a resumed one. The outcome points at the yield receipt, which
+    /// was executed with `yield_outcome`.
+    fn promise_yield_timeout_outcome(
+        protocol_version: ProtocolVersion,
+        receipt: &Receipt,
+        yield_outcome: &ExecutionOutcomeWithId,
+    ) -> Option<ExecutionOutcomeWithId> {
+        if !ProtocolFeature::PromiseYieldStatus.enabled(protocol_version) {
+            return None;
+        }
+        match receipt.receipt() {
+            // Resume receipts created by `promise_yield_resume` always carry a payload.
+            ReceiptEnum::PromiseResume(DataReceipt { data: None, .. }) => {
+                Some(ExecutionOutcomeWithId {
+                    id: *receipt.receipt_id(),
+                    outcome: ExecutionOutcome {
+                        status: ExecutionStatus::SuccessReceiptId(yield_outcome.id),
+                        logs: vec![],
+                        receipt_ids: vec![],
+                        gas_burnt: 0,
+                        compute_usage: Some(0),
+                        tokens_burnt: 0,
+                        executor_id: receipt.receiver_id().clone(),
+                        metadata: ExecutionMetadata::V1,
+                    },
+                })
+            }
+            _ => None,
+        }
+    }
+
     /// Iterates over the validators in the current shard and updates their accounts to return stake
     /// and allocate rewards. Also updates protocol treasury account if it belongs to the current
     /// shard.
@@ -1277,6 +1442,95 @@ impl Runtime {
         Ok(())
     }
 
+    /// Reads the runtime config overrides from the state of the protocol treasury account in the
+    /// first new chunk of each epoch of its shard. If they changed, they are stored in the shard
+    /// and apply from [`RUNTIME_CONFIG_OVERRIDES_ACTIVATION_DELAY`] epochs later on. Every chunk
+    /// is applied with the overrides stored in the state of its own shard.
+    ///
+    /// The latest overrides are sent to all other shards in every epoch, together with the
+    /// epoch from which they apply, so the distribution receipts have time to arrive even when
+    /// they are buffered. Shards which store them already ignore them, and shards which
+    /// rejected them because they arrived too late store them to apply from the later epoch.
+    ///
+    /// The value stored in the account is rejected if it can't be parsed or isn't valid, and the
+    /// overrides stored before keep applying.
+    fn update_runtime_config_overrides(
+        &self,
+        state_update: &mut TrieUpdate,
+        apply_state: &ApplyState,
+        receipt_sink: &mut ReceiptSink,
+        epoch_info_provider: &dyn EpochInfoProvider,
+    ) -> Result<(), RuntimeError> {
+        let treasury_account_id = epoch_info_provider.protocol_treasury_account();
+        let shard_layout = epoch_info_provider.shard_layout(&apply_state.epoch_id)?;
+        if account_id_to_shard_id(&treasury_account_id, &shard_layout) != apply_state.shard_id {
+            return Ok(());
+        }
+        let mut record = get_runtime_config_overrides_record(state_update)?;
+        if record
+            .last_read_epoch_height
+            .is_some_and(|epoch_height| epoch_height >= apply_state.epoch_height)
+        {
+            return Ok(());
+        }
+        record.last_read_epoch_height = Some(apply_state.epoch_height);
+
+        let key = TrieKey::ContractData {
+            account_id: treasury_account_id.clone(),
+            key: RUNTIME_CONFIG_OVERRIDES_KEY.to_vec(),
+        };
+        let overrides = match state_update.get(&key)? {
+            Some(value) => serde_json::from_slice::<RuntimeConfigOverrides>(&value)
+                .map_err(|err| err.to_string())
+                .and_then(|overrides| overrides.validate().map(|()| overrides)),
+            None => Ok(RuntimeConfigOverrides::default()),
+        };
+        let from_epoch_height =
+            apply_state.epoch_height + RUNTIME_CONFIG_OVERRIDES_ACTIVATION_DELAY;
+        match overrides {
+            Ok(overrides) if overrides != record.latest() => {
+                record.insert(from_epoch_height, overrides, apply_state.epoch_height);
+            }
+            Ok(_) => {}
+            Err(err) => {
+                tracing::warn!(
+                    target: "runtime",
+                    account_id = %treasury_account_id,
+                    %err,
+                    "Rejecting invalid runtime config overrides"
+                );
+                metrics::INVALID_RUNTIME_CONFIG_OVERRIDES_TOTAL.inc();
+            }
+        }
+        if !record.entries.is_empty() {
+            let receipts = runtime_config_overrides_distribution_receipts(
+                &treasury_account_id,
+                record.latest(),
+                from_epoch_height,
+                &shard_layout,
+                vec![apply_state.shard_id],
+            );
+            for (receipt_index, mut receipt) in receipts.into_iter().enumerate() {
+                receipt.set_receipt_id(create_receipt_id_from_receipt_id(
+                    apply_state.current_protocol_version,
+                    &CryptoHash::hash_bytes(RUNTIME_CONFIG_OVERRIDES_KEY),
+                    &apply_state.prev_block_hash,
+                    &apply_state.block_hash,
+                    receipt_index,
+                ));
+                receipt_sink.forward_or_buffer_receipt(
+                    receipt,
+                    apply_state,
+                    state_update,
+                    epoch_info_provider,
+                )?;
+            }
+        }
+        set_runtime_config_overrides_record(state_update, &record);
+        state_update.commit(StateChangeCause::RuntimeConfigOverridesUpdate);
+        Ok(())
+    }
+
     pub fn apply_migrations(
         &self,
         state_update: &mut TrieUpdate,
@@ -1395,7 +1649,16 @@ impl Runtime {
         };
 
         let delayed_receipts_queue = DelayedReceiptQueue::load(&state_update)?;
-        let mut delayed_receipts = DelayedReceiptQueueWrapper::new(delayed_receipts_queue);
+        let priority_delayed_receipts_queue =
+            if ProtocolFeature::TransactionPriority.enabled(protocol_version) {
+                Some(PriorityDelayedReceiptQueue::load(&state_update)?)
+            } else {
+                None
+            };
+        let mut delayed_receipts = DelayedReceiptQueueWrapper::new(
+            delayed_receipts_queue,
+            priority_delayed_receipts_queue,
+        );
         let mut own_congestion_info =
             apply_state.own_congestion_info(protocol_version, &state_update)?;
 
@@ -1437,6 +1700,15 @@ impl Runtime {
             &mut outgoing_receipts,
         )?;
 
+        if ProtocolFeature::OnChainRuntimeConfig.enabled(protocol_version) {
+            self.update_runtime_config_overrides(
+                &mut state_update,
+                apply_state,
+                &mut receipt_sink,
+                epoch_info_provider,
+            )?;
+        }
+
         // Forward buffered receipts from previous chunks.
         receipt_sink.forward_from_buffer(&mut state_update, apply_state)?;
 
@@ -1538,6 +1810,11 @@ impl Runtime {
                 if !checked_feature!("stable", ComputeCosts, protocol_version) {
                     assert_eq!(total.compute, total.gas, "Compute usage must match burnt gas");
                 }
+                if let Some(timeout_outcome) =
+                    Self::promise_yield_timeout_outcome(protocol_version, receipt, &outcome_with_id)
+                {
+                    outcomes.push(timeout_outcome);
+                }
                 outcomes.push(outcome_with_id);
             }
             Ok(())
@@ -1560,11 +1837,15 @@ impl Runtime {
             _ = prefetcher.prefetch_receipts_data(&local_receipts);
         }
         for receipt in local_receipts.iter() {
-            if total.compute >= compute_limit
+            let compute_exhausted = total.compute >= compute_limit;
+            if compute_exhausted
                 || proof_size_limit.is_some_and(|limit| {
                     state_update.trie.recorded_storage_size_upper_bound() > limit
                 })
             {
+                if compute_exhausted {
+                    metrics.receipt_delayed_by_compute_limit();
+                }
                 delayed_receipts.push(&mut state_update, receipt, &apply_state.config)?;
             } else {
                 // NOTE: We don't need to validate the local receipt, because it's just validated in
@@ -1638,11 +1919,15 @@ impl Runtime {
                 protocol_version,
             )
             .map_err(RuntimeError::ReceiptValidationError)?;
-            if total.compute >= compute_limit
+            let compute_exhausted = total.compute >= compute_limit;
+            if compute_exhausted
                 || proof_size_limit.is_some_and(|limit| {
                     state_update.trie.recorded_storage_size_upper_bound() > limit
                 })
             {
+                if compute_exhausted {
+                    metrics.receipt_delayed_by_compute_limit();
+                }
                 delayed_receipts.push(&mut state_update, receipt, &apply_state.config)?;
             } else {
                 process_receipt(receipt, &mut state_update, &mut total)?;
@@ -1856,11 +2141,13 @@ impl Runtime {
                     state_update.set(TrieKey::ContractData { key: data_key.into(), account_id }, value.into());
                 }
                 StateRecord::Contract { account_id, code } => {
-                    let acc = get_account(state_update, &account_id).expect("Failed to read state").expect("Code state record should be preceded by the corresponding account record");
-                    // Recompute contract code hash.
+                    let mut acc = get_account(state_update, &account_id).expect("Failed to read state").expect("Code state record should be preceded by the corresponding account record");
+                    // Recompute contract code hash and point the account to it, so that the code
+                    // can be patched without patching the account.
                     let code = ContractCode::new(code, None);
+                    acc.set_code_hash(*code.hash());
+                    set_account(state_update, account_id.clone(), &acc);
                     set_code(state_update, account_id, &code);
-                    assert_eq!(*code.hash(), acc.code_hash());
                 }
                 StateRecord::AccessKey { account_id, public_key, access_key } => {
                     set_access_key(state_update, account_id, public_key, &access_key);
@@ -1975,14 +2262,13 @@ impl TotalResourceGuard {
 mod tests {
     use assert_matches::assert_matches;
     use near_crypto::{InMemorySigner, KeyType, PublicKey, Signer};
-    use near_parameters::{ExtCosts, ParameterCost, RuntimeConfig};
+    use near_parameters::{ExtCosts, Fee, ParameterCost, RuntimeConfig};
     use near_primitives::account::AccessKey;
     use near_primitives::action::delegate::{
         DelegateAction, NonDelegateAction, SignedDelegateAction,
     };
     use near_primitives::congestion_info::CongestionControl;
     use near_primitives::hash::hash;
-    use near_primitives::receipt::ReceiptPriority;
     use near_primitives::shard_layout::ShardUId;
     use near_primitives::test_utils::{account_new, MockEpochInfoProvider};
     use near_primitives::transaction::{
@@ -2190,6 +2476,51 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_apply_refund_to_account_lacking_storage_stake() {
+        let refund = 1000;
+        let (runtime, tries, root, mut apply_state, _, epoch_info_provider) =
+            setup_runtime(0, 0, 10u64.pow(15));
+        // The account doesn't cover its storage and is too large to be a zero balance account.
+        let mut state_update = tries.new_trie_update(ShardUId::single_shard(), root);
+        let mut account = get_account(&state_update, &alice_account()).unwrap().unwrap();
+        account.set_storage_usage(2000);
+        set_account(&mut state_update, alice_account(), &account);
+        state_update.commit(StateChangeCause::InitialState);
+        let trie_changes = state_update.finalize().unwrap().1;
+        let mut store_update = tries.store_update();
+        let root = tries.apply_all(&trie_changes, ShardUId::single_shard(), &mut store_update);
+        store_update.commit().unwrap();
+
+        // Before the storage staking exemption the refund fails and is burnt.
+        let protocol_version = ProtocolFeature::StorageStakingExemption.protocol_version();
+        for (protocol_version, expected_amount, expected_burnt) in
+            [(protocol_version - 1, 0, refund), (protocol_version, refund, 0)]
+        {
+            apply_state.current_protocol_version = protocol_version;
+            let apply_result = runtime
+                .apply(
+                    tries.get_trie_for_shard(ShardUId::single_shard(), root),
+                    &None,
+                    &apply_state,
+                    &[Receipt::new_balance_refund(
+                        &alice_account(),
+                        refund,
+                        ReceiptPriority::NoPriority,
+                    )],
+                    &[],
+                    &epoch_info_provider,
+                    Default::default(),
+                )
+                .unwrap();
+            assert_eq!(apply_result.stats.other_burnt_amount, expected_burnt);
+            let root = commit_apply_result(&apply_result, &mut apply_state, &tries);
+            let state = tries.new_trie_update(ShardUId::single_shard(), root);
+            let account = get_account(&state, &alice_account()).unwrap().unwrap();
+            assert_eq!(account.amount(), expected_amount);
+        }
+    }
+
     #[test]
     fn test_apply_refund_receipts() {
         let initial_balance = to_yocto(1_000_000);
@@ -3082,6 +3413,58 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_action_compute_usage_limit() {
+        let (runtime, tries, mut root, mut apply_state, signer, epoch_info_provider) =
+            setup_runtime(to_yocto(1_000_000), to_yocto(500_000), 1);
+
+        // Transfers are free in gas, but each of them fills the compute limit.
+        let transfer_compute = Compute::from(10_000_000_000_000u64);
+        let mut free_config = RuntimeConfig::free();
+        free_config.fees.action_fees[ActionCosts::transfer] = Fee {
+            send_sir: 0,
+            send_not_sir: 0,
+            execution: 0,
+            execution_compute: Some(transfer_compute),
+        };
+        apply_state.config = Arc::new(free_config);
+        apply_state.gas_limit = Some(transfer_compute);
+
+        let receipts: Vec<_> = (0..3)
+            .map(|_| {
+                create_receipt_with_actions(
+                    alice_account(),
+                    signer.clone(),
+                    vec![Action::Transfer(TransferAction { deposit: 1 })],
+                )
+            })
+            .collect();
+
+        // Receipts which don't fit into the compute limit are delayed in order.
+        for (i, receipt) in receipts.iter().enumerate() {
+            let incoming = if i == 0 { &receipts[..] } else { &[] };
+            let apply_result = runtime
+                .apply(
+                    tries.get_trie_for_shard(ShardUId::single_shard(), root),
+                    &None,
+                    &apply_state,
+                    incoming,
+                    &[],
+                    &epoch_info_provider,
+                    Default::default(),
+                )
+                .unwrap();
+            root = commit_apply_result(&apply_result, &mut apply_state, &tries);
+
+            assert_matches!(&apply_result.outcomes[..], [ExecutionOutcomeWithId { id, outcome }] => {
+                assert_eq!(id, receipt.receipt_id());
+                assert_eq!(outcome.gas_burnt, 0);
+                assert_eq!(outcome.compute_usage.unwrap(), transfer_compute);
+                assert_matches!(outcome.status, ExecutionStatus::SuccessValue(_));
+            });
+        }
+    }
+
     #[test]
     fn test_compute_usage_limit_with_failed_receipt() {
         let (runtime, tries, root, apply_state, signer, epoch_info_provider) =
@@ -3437,6 +3820,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_runtime_config_overrides_read_once_per_epoch() {
+        if !ProtocolFeature::OnChainRuntimeConfig.enabled(PROTOCOL_VERSION) {
+            return;
+        }
+        let (runtime, tries, mut root, mut apply_state, _, epoch_info_provider) =
+            setup_runtime(to_yocto(1_000_000), 0, 10u64.pow(15));
+        let treasury_key = TrieKey::ContractData {
+            account_id: epoch_info_provider.protocol_treasury_account(),
+            key: RUNTIME_CONFIG_OVERRIDES_KEY.to_vec(),
+        };
+        let mut apply_in_epoch = |epoch_height: EpochHeight, treasury_value: &str| {
+            let mut state_update = tries.new_trie_update(ShardUId::single_shard(), root);
+            state_update.set(treasury_key.clone(), treasury_value.as_bytes().to_vec());
+            state_update.commit(StateChangeCause::InitialState);
+            let trie_changes = state_update.finalize().unwrap().1;
+            let mut store_update = tries.store_update();
+            root = tries.apply_all(&trie_changes, ShardUId::single_shard(), &mut store_update);
+            store_update.commit().unwrap();
+
+            apply_state.epoch_height = epoch_height;
+            let apply_result = runtime
+                .apply(
+                    tries.get_trie_for_shard(ShardUId::single_shard(), root),
+                    &None,
+                    &apply_state,
+                    &[],
+                    &[],
+                    &epoch_info_provider,
+                    Default::default(),
+                )
+                .unwrap();
+            root = commit_apply_result(&apply_result, &mut apply_state, &tries);
+            let trie = tries.get_trie_for_shard(ShardUId::single_shard(), root);
+            get_runtime_config_overrides_record(&trie).unwrap()
+        };
+
+        // Valid overrides apply after the activation delay.
+        let record = apply_in_epoch(1, r#"{"storage_amount_per_byte": "1"}"#);
+        assert_eq!(RUNTIME_CONFIG_OVERRIDES_ACTIVATION_DELAY, 2);
+        assert_eq!(record.get(2), RuntimeConfigOverrides::default());
+        assert_eq!(record.get(3).storage_amount_per_byte, Some(1));
+
+        // The value is only read once per epoch.
+        let record = apply_in_epoch(1, r#"{"storage_amount_per_byte": "2"}"#);
+        assert_eq!(record.get(3).storage_amount_per_byte, Some(1));
+
+        // Malformed or invalid values are rejected and the previous overrides keep applying.
+        for (epoch_height, value) in
+            [(2, r#"{"storage_price": "2"}"#), (3, "{"), (4, r#"{"storage_amount_per_byte": "0"}"#)]
+        {
+            let record = apply_in_epoch(epoch_height, value);
+            assert_eq!(record.last_read_epoch_height, Some(epoch_height));
+            assert_eq!(record.get(epoch_height + 1).storage_amount_per_byte, Some(1));
+        }
+
+        // Empty overrides reset the parameters to the ones of the protocol version.
+        let record = apply_in_epoch(5, "{}");
+        assert_eq!(record.get(6).storage_amount_per_byte, Some(1));
+        assert_eq!(record.get(7), RuntimeConfigOverrides::default());
+    }
+
     // Apply trie changes in `ApplyResult` and update `ApplyState` with new
     // congestion info for the next call to apply().
     fn commit_apply_result(
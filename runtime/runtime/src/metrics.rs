@@ -20,6 +20,40 @@ pub static ACTION_CALLED_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     .unwrap()
 });
 
+pub static ACTION_COMPUTE_EXCEEDING_GAS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_action_compute_exceeding_gas_total",
+        "Compute usage of executed actions in excess of the gas they burnt, since starting this node",
+        &["action"],
+    )
+    .unwrap()
+});
+
+pub static RECEIPTS_DELAYED_BY_COMPUTE_LIMIT_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_receipts_delayed_by_compute_limit_total",
+        "The number of new receipts put in the delayed receipts queue because the compute limit of the chunk was reached, since starting this node",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
+pub static INVALID_RUNTIME_CONFIG_OVERRIDES_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_invalid_runtime_config_overrides_total",
+        "The number of times the runtime config overrides stored in the protocol treasury account were rejected as invalid, since starting this node",
+    )
+    .unwrap()
+});
+
+pub static LATE_RUNTIME_CONFIG_OVERRIDES_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_late_runtime_config_overrides_total",
+        "The number of runtime config overrides distribution receipts rejected because they arrived after the epoch from which the overrides apply had started, since starting this node",
+    )
+    .unwrap()
+});
+
 pub static TRANSACTION_PROCESSED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
     try_create_int_counter(
         "near_transaction_processed_total",
@@ -466,6 +500,7 @@ pub struct ApplyMetrics {
     yield_timeouts_gas: u64,
     yield_timeouts_processed_total: u64,
     yield_timeouts_processing_seconds_total: f64,
+    receipts_delayed_by_compute_limit: u64,
 }
 
 impl ApplyMetrics {
@@ -540,10 +575,19 @@ impl ApplyMetrics {
         self.yield_timeouts_processing_seconds_total += time.as_secs_f64();
     }
 
+    pub fn receipt_delayed_by_compute_limit(&mut self) {
+        self.receipts_delayed_by_compute_limit += 1;
+    }
+
     /// Report statistics
     pub fn report(&mut self, shard_id: &str) {
         const TERA: f64 = 1_000_000_000_000_f64;
 
+        RECEIPTS_DELAYED_BY_COMPUTE_LIMIT_TOTAL
+            .with_label_values(&[shard_id])
+            .inc_by(self.receipts_delayed_by_compute_limit);
+        self.receipts_delayed_by_compute_limit = 0;
+
         LOCAL_RECEIPT_PROCESSED_TOTAL
             .with_label_values(&[shard_id])
             .inc_by(self.local_receipts_processed_total);
@@ -97,7 +97,10 @@ impl TriePrefetcher {
                 ReceiptEnum::Action(action_receipt) | ReceiptEnum::PromiseYield(action_receipt) => {
                     action_receipt
                 }
-                ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => {
+                ReceiptEnum::Data(_)
+                | ReceiptEnum::PromiseResume(_)
+                | ReceiptEnum::GlobalContractDistribution(_)
+                | ReceiptEnum::RuntimeConfigOverridesDistribution(_) => {
                     continue;
                 }
             };
@@ -175,6 +175,12 @@ impl ReceiptManager {
         Ok(())
     }
 
+    /// Returns whether a PromiseYield created under given `data_id` is awaiting its input
+    /// dependency.
+    pub(super) fn has_promise_yield(&self, data_id: &CryptoHash) -> bool {
+        self.promise_yield_receipt_index.contains_key(data_id)
+    }
+
     /// Resolves a PromiseYield input dependency previously created under given `data_id`,
     /// if it exists.
     ///
@@ -6,16 +6,20 @@ use near_crypto::{KeyType, PublicKey};
 use near_parameters::RuntimeConfigStore;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::borsh::BorshDeserialize;
+use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::ActionReceipt;
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
 use near_primitives::transaction::FunctionCallAction;
 use near_primitives::trie_key::trie_key_parsers;
-use near_primitives::types::{AccountId, EpochInfoProvider, Gas};
+use near_primitives::types::{AccountId, BlockHeight, EpochInfoProvider, Gas};
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{StateItem, ViewApplyState, ViewStateResult};
+use near_primitives::views::{PromiseYieldView, StateItem, ViewApplyState, ViewStateResult};
 use near_primitives_core::config::ViewConfig;
-use near_store::{get_access_key, get_account, get_code, TrieUpdate};
+use near_store::{
+    get_access_key, get_account, get_code, get_global_contract_code, get_promise_yield_expiry,
+    has_promise_yield_receipt, TrieUpdate,
+};
 use near_vm_runner::logic::ReturnData;
 use near_vm_runner::ContractCode;
 use std::{str, sync::Arc, time::Instant};
@@ -64,10 +68,12 @@ impl TrieViewer {
         account_id: &AccountId,
     ) -> Result<ContractCode, errors::ViewContractCodeError> {
         let account = self.view_account(state_update, account_id)?;
-        get_code(state_update, account_id, Some(account.code_hash()))?.ok_or_else(|| {
-            errors::ViewContractCodeError::NoContractCode {
-                contract_account_id: account_id.clone(),
-            }
+        let code = match get_code(state_update, account_id, Some(account.code_hash()))? {
+            Some(code) => Some(code),
+            None => get_global_contract_code(state_update, account.code_hash())?,
+        };
+        code.ok_or_else(|| errors::ViewContractCodeError::NoContractCode {
+            contract_account_id: account_id.clone(),
         })
     }
 
@@ -112,6 +118,28 @@ impl TrieViewer {
         access_keys
     }
 
+    /// Returns the status of the promise yield created by `account_id` under `data_id`, as seen
+    /// at `block_height`.
+    pub fn view_promise_yield(
+        &self,
+        state_update: &TrieUpdate,
+        block_height: BlockHeight,
+        account_id: &AccountId,
+        data_id: CryptoHash,
+    ) -> Result<PromiseYieldView, StorageError> {
+        // The expiry is recorded as soon as the yield is created, while the yield receipt is only
+        // stored once it arrives in the next chunk. Yields created before `PromiseYieldStatus` was
+        // enabled only have the receipt.
+        let expires_at = get_promise_yield_expiry(state_update, account_id, data_id)?;
+        let pending = expires_at.is_some()
+            || has_promise_yield_receipt(state_update, account_id.clone(), data_id)?;
+        Ok(PromiseYieldView {
+            pending,
+            expires_at,
+            remaining_blocks: expires_at.map(|expires_at| expires_at.saturating_sub(block_height)),
+        })
+    }
+
     pub fn view_state(
         &self,
         state_update: &TrieUpdate,
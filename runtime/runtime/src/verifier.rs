@@ -13,7 +13,8 @@ use near_primitives::errors::{
 use near_primitives::receipt::{ActionReceipt, DataReceipt, Receipt, ReceiptEnum};
 use near_primitives::transaction::DeleteAccountAction;
 use near_primitives::transaction::{
-    Action, AddKeyAction, DeployContractAction, FunctionCallAction, SignedTransaction, StakeAction,
+    Action, AddKeyAction, DeployContractAction, DeployGlobalContractAction, FunctionCallAction,
+    SignedTransaction, StakeAction,
 };
 use near_primitives::types::{AccountId, Balance};
 use near_primitives::types::{BlockHeight, StorageUsage};
@@ -24,7 +25,9 @@ use near_store::{
 };
 use near_vm_runner::logic::LimitConfig;
 
-pub const ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT: StorageUsage = 770;
+/// Storage limit of zero balance accounts before it became configurable with the
+/// `StorageStakingExemption` protocol feature.
+const LEGACY_ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT: StorageUsage = 770;
 
 /// Possible errors when checking whether an account has enough tokens for storage staking
 /// Read details of state staking
@@ -37,7 +40,9 @@ pub enum StorageStakingError {
 }
 
 /// Checks if given account has enough balance for storage stake, and returns:
-///  - Ok(()) if account has enough balance or is a zero-balance account
+///  - Ok(()) if account has enough balance or is a zero-balance account. Since the
+///    `StorageStakingExemption` protocol feature, the first `num_bytes_exempt_from_staking`
+///    bytes of the account don't require any balance.
 ///  - Err(StorageStakingError::LackBalanceForStorageStaking(amount)) if account doesn't have enough and how much need to be added,
 ///  - Err(StorageStakingError::StorageError(err)) if account has invalid storage usage or amount/locked.
 pub fn check_storage_stake(
@@ -45,14 +50,26 @@ pub fn check_storage_stake(
     runtime_config: &RuntimeConfig,
     current_protocol_version: ProtocolVersion,
 ) -> Result<(), StorageStakingError> {
+    let storage_usage_config = &runtime_config.fees.storage_usage_config;
+    let (zero_balance_account_limit, num_bytes_exempt_from_staking) =
+        if ProtocolFeature::StorageStakingExemption.enabled(current_protocol_version) {
+            (
+                storage_usage_config.zero_balance_account_limit,
+                storage_usage_config.num_bytes_exempt_from_staking,
+            )
+        } else {
+            (LEGACY_ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT, 0)
+        };
     #[cfg(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))]
     let billable_storage_bytes = account.storage_usage();
     #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
     let billable_storage_bytes =
         account.storage_usage().saturating_sub(account.permanent_storage_bytes());
+    let billable_storage_bytes =
+        billable_storage_bytes.saturating_sub(num_bytes_exempt_from_staking);
 
     let required_amount = Balance::from(billable_storage_bytes)
-        .checked_mul(runtime_config.storage_amount_per_byte())
+        .checked_mul(storage_usage_config.storage_amount_per_byte)
         .ok_or_else(|| {
             format!(
                 "Account's billable storage usage {} overflows multiplication",
@@ -75,7 +92,7 @@ pub fn check_storage_stake(
         Ok(())
     } else {
         if checked_feature!("stable", ZeroBalanceAccount, current_protocol_version)
-            && is_zero_balance_account(account)
+            && is_zero_balance_account(account, zero_balance_account_limit)
         {
             return Ok(());
         }
@@ -84,9 +101,10 @@ pub fn check_storage_stake(
 }
 
 /// Zero Balance Account introduced in NEP 448 https://github.com/near/NEPs/pull/448
-/// An account is a zero balance account if and only if the account uses no more than `ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT` bytes
-fn is_zero_balance_account(account: &Account) -> bool {
-    account.storage_usage() <= ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT
+/// An account is a zero balance account if and only if the account uses no more than
+/// `zero_balance_account_limit` bytes
+fn is_zero_balance_account(account: &Account, zero_balance_account_limit: StorageUsage) -> bool {
+    account.storage_usage() <= zero_balance_account_limit
 }
 
 /// Validates the transaction without using the state. It allows any node to validate a
@@ -98,11 +116,18 @@ pub fn validate_transaction(
     verify_signature: bool,
     current_protocol_version: ProtocolVersion,
 ) -> Result<TransactionCost, RuntimeError> {
-    // Don't allow V1 currently. This will be changed when the new protocol version is introduced.
-    if matches!(signed_transaction.transaction, near_primitives::transaction::Transaction::V1(_)) {
+    // V1 transactions carry a priority fee, which is only supported with `TransactionPriority`.
+    if matches!(signed_transaction.transaction, near_primitives::transaction::Transaction::V1(_))
+        && !ProtocolFeature::TransactionPriority.enabled(current_protocol_version)
+    {
         return Err(InvalidTxError::InvalidTransactionVersion.into());
     }
     let transaction = &signed_transaction.transaction;
+    if transaction.valid_until_height().is_some()
+        && !ProtocolFeature::TransactionExpiry.enabled(current_protocol_version)
+    {
+        return Err(InvalidTxError::InvalidTransactionVersion.into());
+    }
     let signer_id = transaction.signer_id();
 
     if verify_signature
@@ -314,6 +339,19 @@ pub(crate) fn validate_receipt(
         ReceiptEnum::Data(data_receipt) | ReceiptEnum::PromiseResume(data_receipt) => {
             validate_data_receipt(limit_config, data_receipt)
         }
+        ReceiptEnum::GlobalContractDistribution(distribution_receipt) => {
+            check_feature_enabled(ProtocolFeature::GlobalContracts, current_protocol_version)
+                .map_err(ReceiptValidationError::ActionsValidation)?;
+            validate_deploy_global_contract_action(
+                limit_config,
+                &DeployGlobalContractAction { code: distribution_receipt.code.clone() },
+            )
+            .map_err(ReceiptValidationError::ActionsValidation)
+        }
+        ReceiptEnum::RuntimeConfigOverridesDistribution(_) => {
+            check_feature_enabled(ProtocolFeature::OnChainRuntimeConfig, current_protocol_version)
+                .map_err(ReceiptValidationError::ActionsValidation)
+        }
     }
 }
 
@@ -423,6 +461,13 @@ pub fn validate_action(
         Action::DeleteKey(_) => Ok(()),
         Action::DeleteAccount(a) => validate_delete_action(a),
         Action::Delegate(a) => validate_delegate_action(limit_config, a, current_protocol_version),
+        Action::DeployGlobalContract(a) => {
+            check_feature_enabled(ProtocolFeature::GlobalContracts, current_protocol_version)?;
+            validate_deploy_global_contract_action(limit_config, a)
+        }
+        Action::UseGlobalContract(_) => {
+            check_feature_enabled(ProtocolFeature::GlobalContracts, current_protocol_version)
+        }
     }
 }
 
@@ -451,6 +496,22 @@ fn validate_deploy_contract_action(
     Ok(())
 }
 
+/// Validates `DeployGlobalContractAction`. Checks that the given contract size doesn't exceed the
+/// limit.
+fn validate_deploy_global_contract_action(
+    limit_config: &LimitConfig,
+    action: &DeployGlobalContractAction,
+) -> Result<(), ActionsValidationError> {
+    if action.code.len() as u64 > limit_config.max_contract_size {
+        return Err(ActionsValidationError::ContractSizeExceeded {
+            size: action.code.len() as u64,
+            limit: limit_config.max_contract_size,
+        });
+    }
+
+    Ok(())
+}
+
 /// Validates `FunctionCallAction`. Checks that the method name length doesn't exceed the limit and
 /// the length of the arguments doesn't exceed the limit.
 fn validate_function_call_action(
@@ -547,7 +608,6 @@ fn validate_delete_action(action: &DeleteAccountAction) -> Result<(), ActionsVal
     Ok(())
 }
 
-#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
 fn check_feature_enabled(
     feature: ProtocolFeature,
     current_protocol_version: ProtocolVersion,
@@ -582,7 +642,8 @@ mod tests {
     use near_primitives::receipt::ReceiptPriority;
     use near_primitives::test_utils::account_new;
     use near_primitives::transaction::{
-        CreateAccountAction, DeleteAccountAction, DeleteKeyAction, StakeAction, TransferAction,
+        CreateAccountAction, DeleteAccountAction, DeleteKeyAction, StakeAction, Transaction,
+        TransactionV1, TransferAction,
     };
     use near_primitives::types::{AccountId, Balance, MerkleHash, StateChangeCause};
     use near_primitives::version::PROTOCOL_VERSION;
@@ -740,8 +801,9 @@ mod tests {
         use crate::near_primitives::account::{
             AccessKeyPermission, Account, FunctionCallPermission,
         };
+        use crate::verifier::is_zero_balance_account;
         use crate::verifier::tests::{setup_accounts, TESTING_INIT_BALANCE};
-        use crate::verifier::{is_zero_balance_account, ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT};
+        use near_parameters::StorageUsageConfig;
         use near_primitives::account::AccessKey;
         use near_store::{get_account, TrieUpdate};
         use testlib::runtime_utils::{alice_account, bob_account};
@@ -779,9 +841,10 @@ mod tests {
         }
 
         /// Testing all combination of access keys in this test to make sure that an account
-        /// is zero balance only if it uses <= `ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT` bytes in storage
+        /// is zero balance only if it uses <= `zero_balance_account_limit` bytes in storage
         #[test]
         fn test_zero_balance_account_with_keys() {
+            let config = StorageUsageConfig::test();
             for num_full_access_key in 0..10 {
                 for num_function_call_access_key in 0..10 {
                     let account_id: AccountId = format!(
@@ -795,13 +858,13 @@ mod tests {
                         num_full_access_key,
                         num_function_call_access_key,
                     );
-                    let res = is_zero_balance_account(&account);
+                    let res = is_zero_balance_account(&account, config.zero_balance_account_limit);
                     assert_eq!(
                         res,
                         num_full_access_key * 82
                             + num_function_call_access_key * 171
                             + std::mem::size_of::<Account>() as u64
-                            <= ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT
+                            <= config.zero_balance_account_limit
                     );
                 }
             }
@@ -829,7 +892,10 @@ mod tests {
                 false,
             )]);
             let account = get_account(&state_update, &account_id).unwrap().unwrap();
-            assert!(!is_zero_balance_account(&account));
+            assert!(!is_zero_balance_account(
+                &account,
+                StorageUsageConfig::test().zero_balance_account_limit
+            ));
         }
     }
 
@@ -1084,6 +1150,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_transaction_valid_until_height() {
+        let config = RuntimeConfig::test();
+        let (signer, _, gas_price) =
+            setup_common(TESTING_INIT_BALANCE, 0, Some(AccessKey::full_access()));
+        let mut transaction = TransactionV1 {
+            signer_id: alice_account(),
+            public_key: signer.public_key(),
+            nonce: 1,
+            receiver_id: bob_account(),
+            block_hash: CryptoHash::default(),
+            actions: vec![Action::Transfer(TransferAction { deposit: 100 })],
+            priority_fee: 0,
+            valid_until_height: None,
+        };
+        let protocol_version = ProtocolFeature::TransactionExpiry.protocol_version() - 1;
+        let signed_tx = Transaction::V1(transaction.clone()).sign(&*signer);
+        validate_transaction(&config, gas_price, &signed_tx, true, protocol_version)
+            .expect("valid transaction");
+
+        transaction.valid_until_height = Some(100);
+        let signed_tx = Transaction::V1(transaction).sign(&*signer);
+        assert_eq!(
+            validate_transaction(&config, gas_price, &signed_tx, true, protocol_version)
+                .expect_err("expected an error"),
+            RuntimeError::InvalidTxError(InvalidTxError::InvalidTransactionVersion),
+        );
+        validate_transaction(
+            &config,
+            gas_price,
+            &signed_tx,
+            true,
+            ProtocolFeature::TransactionExpiry.protocol_version(),
+        )
+        .expect("valid transaction");
+    }
+
     #[test]
     fn test_validate_transaction_invalid_not_enough_balance() {
         let config = RuntimeConfig::test();
@@ -1253,6 +1356,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_transaction_low_balance_exempt_bytes() {
+        let initial_balance = 1_000_000_000;
+        let transfer_amount = 950_000_000;
+        let account_id = alice_account();
+        let access_keys = vec![AccessKey::full_access(); 10];
+        let (signer, mut state_update, gas_price) = setup_accounts(vec![(
+            account_id.clone(),
+            initial_balance,
+            0,
+            access_keys,
+            false,
+            false,
+        )]);
+        let storage_usage =
+            get_account(&state_update, &account_id).unwrap().unwrap().storage_usage();
+        let transaction = SignedTransaction::send_money(
+            1,
+            account_id.clone(),
+            bob_account(),
+            &*signer,
+            transfer_amount,
+            CryptoHash::default(),
+        );
+
+        let mut config = RuntimeConfig::free();
+        config.fees.storage_usage_config.storage_amount_per_byte = 10_000_000;
+        // Only the bytes above the exemption need to be covered by the balance.
+        config.fees.storage_usage_config.num_bytes_exempt_from_staking = storage_usage - 10;
+        let res = verify_and_charge_transaction(
+            &config,
+            &mut state_update,
+            gas_price,
+            &transaction,
+            true,
+            None,
+            ProtocolFeature::StorageStakingExemption.protocol_version(),
+        )
+        .expect_err("expected an error");
+        assert_eq!(
+            res,
+            RuntimeError::InvalidTxError(InvalidTxError::LackBalanceForState {
+                signer_id: account_id,
+                amount: 10 * config.storage_amount_per_byte() - (initial_balance - transfer_amount)
+            })
+        );
+
+        config.fees.storage_usage_config.num_bytes_exempt_from_staking = storage_usage - 5;
+        verify_and_charge_transaction(
+            &config,
+            &mut state_update,
+            gas_price,
+            &transaction,
+            true,
+            None,
+            ProtocolFeature::StorageStakingExemption.protocol_version(),
+        )
+        .expect("balance should cover the storage above the exemption");
+    }
+
     #[test]
     fn test_validate_transaction_invalid_actions_for_function_call() {
         let config = RuntimeConfig::test();
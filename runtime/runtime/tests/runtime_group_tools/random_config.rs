@@ -8,6 +8,7 @@ pub fn random_config() -> RuntimeConfig {
         send_sir: rng.next_u64() % 1000,
         send_not_sir: rng.next_u64() % 1000,
         execution: rng.next_u64() % 1000,
+        execution_compute: None,
     };
     RuntimeConfig {
         fees: RuntimeFeesConfig {
@@ -18,6 +19,7 @@ pub fn random_config() -> RuntimeConfig {
                 num_bytes_account: rng.next_u64() % 10000,
                 num_extra_bytes_record: rng.next_u64() % 10000,
                 storage_amount_per_byte: rng.next_u64() as u128,
+                ..StorageUsageConfig::test()
             },
             burnt_gas_reward: Rational32::new((rng.next_u32() % 100).try_into().unwrap(), 100),
             pessimistic_gas_price_inflation_ratio: Rational32::new(
@@ -1,8 +1,10 @@
+use anyhow::Context;
+use near_parameters::RuntimeConfigOverrides;
 use near_primitives::types::NumBlocks;
-use near_primitives::types::{BlockHeightDelta, NumSeats};
+use near_primitives::types::{AccountId, AccountInfo, BlockHeightDelta, NumSeats};
 use near_primitives::version::ProtocolVersion;
 use num_rational::Rational32;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Amend a genesis/records file created by `dump-state`.
 #[derive(clap::Parser)]
@@ -27,8 +29,18 @@ pub struct AmendGenesisCommand {
     ///   "public_key": <PUBLIC_KEY>,
     ///   "amount": <STAKE>,
     /// }
-    #[clap(long)]
-    validators: PathBuf,
+    /// If not given, the validators in the input genesis file are kept, with the changes
+    /// given by --add-validator and --remove-validator
+    #[clap(long)]
+    validators: Option<PathBuf>,
+    /// validator to add to the output genesis, given as <ACCOUNT_ID>:<PUBLIC_KEY>:<STAKE>.
+    /// If the account is already a validator, its key and stake are replaced. Can be repeated
+    #[clap(long, value_parser = parse_validator)]
+    add_validator: Vec<AccountInfo>,
+    /// validator to remove from the output genesis. Its stake is unlocked in the output
+    /// records. Can be repeated
+    #[clap(long)]
+    remove_validator: Vec<AccountId>,
     /// path to extra records to add to the output state. Right now only Accounts and AccessKey
     /// records are supported, and any added accounts must have zero `code_hash`
     #[clap(long)]
@@ -60,17 +72,51 @@ pub struct AmendGenesisCommand {
     /// maximum inflation to se in the output genesis file. Give a ratio here (e.g. "1/10")
     #[clap(long)]
     max_inflation_rate: Option<Rational32>,
+    /// online_min_threshold to set in the output genesis file. Give a ratio here (e.g. "9/10")
+    #[clap(long)]
+    online_min_threshold: Option<Rational32>,
+    /// online_max_threshold to set in the output genesis file. Give a ratio here (e.g. "99/100")
+    #[clap(long)]
+    online_max_threshold: Option<Rational32>,
+    /// protocol_upgrade_stake_threshold to set in the output genesis file. Give a ratio here (e.g. "4/5")
+    #[clap(long)]
+    protocol_upgrade_stake_threshold: Option<Rational32>,
+    /// minimum_stake_divisor to set in the output genesis file
+    #[clap(long)]
+    minimum_stake_divisor: Option<u64>,
+    /// minimum_stake_ratio to set in the output genesis file. Give a ratio here (e.g. "1/6250")
+    #[clap(long)]
+    minimum_stake_ratio: Option<Rational32>,
+    /// max_kickout_stake_perc to set in the output genesis file
+    #[clap(long)]
+    max_kickout_stake_perc: Option<u8>,
+    /// num_chunk_producer_seats to set in the output genesis file
+    #[clap(long)]
+    num_chunk_producer_seats: Option<NumSeats>,
+    /// num_chunk_validator_seats to set in the output genesis file
+    #[clap(long)]
+    num_chunk_validator_seats: Option<NumSeats>,
+    /// minimum_validators_per_shard to set in the output genesis file
+    #[clap(long)]
+    minimum_validators_per_shard: Option<NumSeats>,
     /// optional file that should contain a JSON-serialized shard layout
     #[clap(long)]
     shard_layout_file: Option<PathBuf>,
     /// runtime fees config `num_bytes_account` value. Used to initialize the `storage_usage` field
-    /// on accounts in the output state
+    /// on accounts in the output state. Defaults to the value in the runtime config of the output
+    /// protocol version
     #[clap(long)]
     num_bytes_account: Option<u64>,
     /// runtime fees config `num_extra_bytes_record` value. Used to initialize the `storage_usage` field
-    /// on accounts in the output state
+    /// on accounts in the output state. Defaults to the value in the runtime config of the output
+    /// protocol version
     #[clap(long)]
     num_extra_bytes_record: Option<u64>,
+    /// optional file that should contain JSON-serialized runtime config overrides, e.g.
+    /// {"storage_amount_per_byte": "10000000000000000000"}. They are stored in the state of
+    /// the protocol treasury account, and apply from the third epoch of the output chain
+    #[clap(long)]
+    runtime_config_overrides_file: Option<PathBuf>,
     /// initial gas limit to set in the output genesis file
     #[clap(long)]
     gas_limit: Option<u64>,
@@ -84,7 +130,15 @@ pub struct AmendGenesisCommand {
 
 impl AmendGenesisCommand {
     pub fn run(self) -> anyhow::Result<()> {
+        let runtime_config_overrides = self
+            .runtime_config_overrides_file
+            .as_deref()
+            .map(parse_runtime_config_overrides)
+            .transpose()?;
         let genesis_changes = crate::GenesisChanges {
+            add_validators: self.add_validator,
+            remove_validators: self.remove_validator,
+            runtime_config_overrides,
             chain_id: self.chain_id,
             protocol_version: self.protocol_version,
             num_seats: self.num_seats,
@@ -94,6 +148,15 @@ impl AmendGenesisCommand {
             max_inflation_rate: self.max_inflation_rate,
             block_producer_kickout_threshold: self.block_producer_kickout_threshold,
             chunk_producer_kickout_threshold: self.chunk_producer_kickout_threshold,
+            online_min_threshold: self.online_min_threshold,
+            online_max_threshold: self.online_max_threshold,
+            protocol_upgrade_stake_threshold: self.protocol_upgrade_stake_threshold,
+            minimum_stake_divisor: self.minimum_stake_divisor,
+            minimum_stake_ratio: self.minimum_stake_ratio,
+            max_kickout_stake_perc: self.max_kickout_stake_perc,
+            num_chunk_producer_seats: self.num_chunk_producer_seats,
+            num_chunk_validator_seats: self.num_chunk_validator_seats,
+            minimum_validators_per_shard: self.minimum_validators_per_shard,
             gas_limit: self.gas_limit,
             min_gas_price: self.min_gas_price,
             max_gas_price: self.max_gas_price,
@@ -104,11 +167,34 @@ impl AmendGenesisCommand {
             &self.records_file_in,
             &self.records_file_out,
             self.extra_records.as_deref(),
-            &self.validators,
+            self.validators.as_deref(),
             self.shard_layout_file.as_deref(),
             &genesis_changes,
-            self.num_bytes_account.unwrap_or(100),
-            self.num_extra_bytes_record.unwrap_or(40),
+            self.num_bytes_account,
+            self.num_extra_bytes_record,
         )
     }
 }
+
+// parses <ACCOUNT_ID>:<PUBLIC_KEY>:<STAKE>. Public keys contain a colon themselves
+// (e.g. "ed25519:..."), so we split off the account ID and the stake on either end
+fn parse_validator(s: &str) -> anyhow::Result<AccountInfo> {
+    let (account_id, rest) = s
+        .split_once(':')
+        .with_context(|| format!("expected <ACCOUNT_ID>:<PUBLIC_KEY>:<STAKE>, got {}", s))?;
+    let (public_key, amount) = rest
+        .rsplit_once(':')
+        .with_context(|| format!("expected <ACCOUNT_ID>:<PUBLIC_KEY>:<STAKE>, got {}", s))?;
+    Ok(AccountInfo {
+        account_id: account_id.parse().context("failed parsing validator account ID")?,
+        public_key: public_key.parse().context("failed parsing validator public key")?,
+        amount: amount.parse().context("failed parsing validator stake")?,
+    })
+}
+
+fn parse_runtime_config_overrides(path: &Path) -> anyhow::Result<RuntimeConfigOverrides> {
+    let s = std::fs::read_to_string(path).with_context(|| {
+        format!("failed reading runtime config overrides file {}", path.display())
+    })?;
+    serde_json::from_str(&s).context("failed deserializing --runtime-config-overrides-file")
+}
@@ -2,12 +2,14 @@ use anyhow::Context;
 
 use near_chain_configs::{Genesis, GenesisValidationMode, NEAR_BASE};
 use near_crypto::PublicKey;
+use near_parameters::config::RUNTIME_CONFIG_OVERRIDES_KEY;
+use near_parameters::{RuntimeConfigOverrides, RuntimeConfigStore, StorageUsageConfig};
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::state_record::StateRecord;
 use near_primitives::types::{AccountId, AccountInfo, StorageUsage};
 use near_primitives::utils;
-use near_primitives::version::ProtocolVersion;
+use near_primitives::version::{ProtocolFeature, ProtocolVersion};
 use near_primitives_core::account::{AccessKey, Account};
 use near_primitives_core::types::{Balance, BlockHeightDelta, NumBlocks, NumSeats, NumShards};
 use near_primitives_core::version::PROTOCOL_VERSION;
@@ -15,7 +17,7 @@ use num_rational::Rational32;
 use serde::ser::{SerializeSeq, Serializer};
 use std::collections::{hash_map, HashMap};
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::Path;
 
 mod cli;
@@ -37,6 +39,11 @@ struct AccountRecords {
     // modifying/adding keys for, we will remember any code records (there really should only be one),
     // and add them to the output only after we write the account record
     extra_records: Vec<StateRecord>,
+    // contract data to set on the account. Data records in the input records with the same keys
+    // are dropped, and the storage they used is remembered in replaced_storage_usage so that
+    // we can subtract it from the storage usage of the account at the end
+    data: HashMap<Vec<u8>, Vec<u8>>,
+    replaced_storage_usage: StorageUsage,
 }
 
 // set the total balance to what's in src, keeping the locked amount the same
@@ -123,6 +130,9 @@ impl AccountRecords {
     {
         match self.account {
             Some(mut account) => {
+                account.set_storage_usage(
+                    account.storage_usage().saturating_sub(self.replaced_storage_usage),
+                );
                 for (public_key, access_key) in self.keys {
                     let storage_usage = account.storage_usage()
                         + public_key.len() as u64
@@ -136,12 +146,26 @@ impl AccountRecords {
                         access_key,
                     })?;
                 }
+                let mut data_records = Vec::with_capacity(self.data.len());
+                for (data_key, value) in self.data {
+                    let storage_usage = account.storage_usage()
+                        + data_key.len() as u64
+                        + value.len() as u64
+                        + num_extra_bytes_record;
+                    account.set_storage_usage(storage_usage);
+
+                    data_records.push(StateRecord::Data {
+                        account_id: account_id.clone(),
+                        data_key: data_key.into(),
+                        value: value.into(),
+                    });
+                }
                 if self.amount_needed {
                     account.set_amount(10_000 * NEAR_BASE);
                 }
                 *total_supply += account.amount() + account.locked();
                 seq.serialize_element(&StateRecord::Account { account_id, account })?;
-                for record in self.extra_records.iter() {
+                for record in self.extra_records.iter().chain(data_records.iter()) {
                     seq.serialize_element(record)?;
                 }
             }
@@ -176,6 +200,37 @@ fn parse_validators(path: &Path) -> anyhow::Result<Vec<AccountInfo>> {
     Ok(validators)
 }
 
+// returns the validators in the --validators file if given, or else the ones in the input genesis,
+// with the validators in genesis_changes removed and added
+fn output_validators(
+    validators: Option<&Path>,
+    genesis_validators: &[AccountInfo],
+    genesis_changes: &GenesisChanges,
+) -> anyhow::Result<Vec<AccountInfo>> {
+    let mut validators = match validators {
+        Some(path) => parse_validators(path)?,
+        None => genesis_validators.to_vec(),
+    };
+    for account_id in genesis_changes.remove_validators.iter() {
+        let num_validators = validators.len();
+        validators.retain(|v| &v.account_id != account_id);
+        if validators.len() == num_validators {
+            anyhow::bail!("can't remove {}, as it is not one of the validators", account_id);
+        }
+    }
+    for validator in genesis_changes.add_validators.iter() {
+        // adding a validator that's already there just sets its key and stake
+        match validators.iter_mut().find(|v| v.account_id == validator.account_id) {
+            Some(v) => *v = validator.clone(),
+            None => validators.push(validator.clone()),
+        }
+    }
+    if validators.is_empty() {
+        anyhow::bail!("no validators left in the output genesis");
+    }
+    Ok(validators)
+}
+
 fn parse_extra_records(
     records_file: &Path,
     num_bytes_account: u64,
@@ -271,6 +326,14 @@ fn wanted_records(
 
 #[derive(Default)]
 pub struct GenesisChanges {
+    /// Validators added to the validator set. The stake and key of validators that are already
+    /// in the set are replaced with the ones given here.
+    pub add_validators: Vec<AccountInfo>,
+    /// Validators removed from the validator set. Their stake is unlocked in the output records.
+    pub remove_validators: Vec<AccountId>,
+    /// Runtime config parameters stored in the state of the protocol treasury account, which
+    /// override the ones of the protocol version from the second epoch of the chain on.
+    pub runtime_config_overrides: Option<RuntimeConfigOverrides>,
     pub chain_id: Option<String>,
     pub protocol_version: Option<ProtocolVersion>,
     pub num_seats: Option<NumSeats>,
@@ -280,23 +343,37 @@ pub struct GenesisChanges {
     pub max_inflation_rate: Option<Rational32>,
     pub block_producer_kickout_threshold: Option<u8>,
     pub chunk_producer_kickout_threshold: Option<u8>,
+    pub online_min_threshold: Option<Rational32>,
+    pub online_max_threshold: Option<Rational32>,
+    pub protocol_upgrade_stake_threshold: Option<Rational32>,
+    pub minimum_stake_divisor: Option<u64>,
+    pub minimum_stake_ratio: Option<Rational32>,
+    pub max_kickout_stake_perc: Option<u8>,
+    pub num_chunk_producer_seats: Option<NumSeats>,
+    pub num_chunk_validator_seats: Option<NumSeats>,
+    pub minimum_validators_per_shard: Option<NumSeats>,
     pub gas_limit: Option<u64>,
     pub min_gas_price: Option<Balance>,
     pub max_gas_price: Option<Balance>,
 }
 
 /// Amend a genesis/records file created by `dump-state`.
+///
+/// If `num_bytes_account` or `num_extra_bytes_record` are not given, the values in the runtime
+/// config of the output protocol version are used. The output is validated at the end, which
+/// checks that the total supply matches the balances in the records, that the validators match
+/// the staked accounts, and that the storage usage of the accounts matches their records.
 pub fn amend_genesis(
     genesis_file_in: &Path,
     genesis_file_out: &Path,
     records_file_in: &Path,
     records_file_out: &Path,
     extra_records: Option<&Path>,
-    validators: &Path,
+    validators: Option<&Path>,
     shard_layout_file: Option<&Path>,
     genesis_changes: &GenesisChanges,
-    num_bytes_account: u64,
-    num_extra_bytes_record: u64,
+    num_bytes_account: Option<u64>,
+    num_extra_bytes_record: Option<u64>,
 ) -> anyhow::Result<()> {
     let mut genesis = Genesis::from_file(genesis_file_in, GenesisValidationMode::UnsafeFast)?;
 
@@ -311,6 +388,17 @@ pub fn amend_genesis(
         None
     };
 
+    let chain_id = genesis_changes.chain_id.as_ref().unwrap_or(&genesis.config.chain_id);
+    let protocol_version =
+        genesis_changes.protocol_version.unwrap_or(genesis.config.protocol_version);
+    let storage_usage_config = output_storage_usage_config(
+        chain_id,
+        protocol_version,
+        genesis_changes.runtime_config_overrides.as_ref(),
+        num_bytes_account,
+        num_extra_bytes_record,
+    )?;
+
     let reader = BufReader::new(File::open(records_file_in).with_context(|| {
         format!("Failed opening input records file {}", records_file_in.display())
     })?);
@@ -320,8 +408,17 @@ pub fn amend_genesis(
     let mut records_ser = serde_json::Serializer::new(records_out);
     let mut records_seq = records_ser.serialize_seq(None).unwrap();
 
-    let validators = parse_validators(validators)?;
-    let mut wanted = wanted_records(&validators, extra_records, num_bytes_account)?;
+    let validators = output_validators(validators, &genesis.config.validators, genesis_changes)?;
+    let mut wanted =
+        wanted_records(&validators, extra_records, storage_usage_config.num_bytes_account)?;
+    let treasury_account = &genesis.config.protocol_treasury_account;
+    if let Some(overrides) = &genesis_changes.runtime_config_overrides {
+        wanted
+            .entry(treasury_account.clone())
+            .or_default()
+            .data
+            .insert(RUNTIME_CONFIG_OVERRIDES_KEY.to_vec(), serde_json::to_vec(overrides)?);
+    }
     let mut total_supply = 0;
 
     near_chain_configs::stream_records_from_file(reader, |mut r| {
@@ -353,20 +450,41 @@ pub fn amend_genesis(
                     records_seq.serialize_element(&r).unwrap();
                 }
             }
+            StateRecord::Data { account_id, data_key, value } => {
+                if let Some(records) = wanted.get_mut(account_id) {
+                    if records.data.contains_key(data_key.as_slice()) {
+                        records.replaced_storage_usage += data_key.len() as u64
+                            + value.len() as u64
+                            + storage_usage_config.num_extra_bytes_record;
+                        return;
+                    }
+                }
+                records_seq.serialize_element(&r).unwrap();
+            }
             _ => {
                 records_seq.serialize_element(&r).unwrap();
             }
         };
     })?;
 
+    if genesis_changes.runtime_config_overrides.is_some()
+        && wanted.get(treasury_account).map_or(true, |r| r.account.is_none())
+    {
+        anyhow::bail!(
+            "can't set the runtime config overrides, as the protocol treasury account {} is not in the records",
+            treasury_account
+        );
+    }
     for (account_id, records) in wanted {
         records.write_out(
             account_id,
             &mut records_seq,
             &mut total_supply,
-            num_extra_bytes_record,
+            storage_usage_config.num_extra_bytes_record,
         )?;
     }
+    records_seq.end()?;
+    records_ser.into_inner().flush()?;
 
     genesis.config.total_supply = total_supply;
     if let Some(n) = genesis_changes.num_seats {
@@ -409,6 +527,34 @@ pub fn amend_genesis(
     if let Some(t) = genesis_changes.chunk_producer_kickout_threshold {
         genesis.config.chunk_producer_kickout_threshold = t;
     }
+    if let Some(t) = genesis_changes.online_min_threshold {
+        genesis.config.online_min_threshold = t;
+    }
+    if let Some(t) = genesis_changes.online_max_threshold {
+        genesis.config.online_max_threshold = t;
+    }
+    if let Some(t) = genesis_changes.protocol_upgrade_stake_threshold {
+        genesis.config.protocol_upgrade_stake_threshold = t;
+    }
+    if let Some(d) = genesis_changes.minimum_stake_divisor {
+        genesis.config.minimum_stake_divisor = d;
+    }
+    if let Some(r) = genesis_changes.minimum_stake_ratio {
+        genesis.config.minimum_stake_ratio = r;
+    }
+    if let Some(p) = genesis_changes.max_kickout_stake_perc {
+        anyhow::ensure!(p <= 100, "max_kickout_stake_perc must be at most 100, got {}", p);
+        genesis.config.max_kickout_stake_perc = p;
+    }
+    if let Some(n) = genesis_changes.num_chunk_producer_seats {
+        genesis.config.num_chunk_producer_seats = n;
+    }
+    if let Some(n) = genesis_changes.num_chunk_validator_seats {
+        genesis.config.num_chunk_validator_seats = n;
+    }
+    if let Some(n) = genesis_changes.minimum_validators_per_shard {
+        genesis.config.minimum_validators_per_shard = n;
+    }
     if let Some(l) = genesis_changes.gas_limit {
         genesis.config.gas_limit = l;
     }
@@ -419,7 +565,84 @@ pub fn amend_genesis(
         genesis.config.max_gas_price = p;
     }
     genesis.to_file(genesis_file_out);
-    records_seq.end()?;
+
+    let genesis = Genesis::new_with_path(genesis.config, records_file_out)
+        .context("the output genesis is not valid")?;
+    validate_storage_usage(&genesis, &storage_usage_config)
+}
+
+// returns the storage usage config of the runtime config the output chain will start with,
+// with the parameters given on the command line applied on top of it
+fn output_storage_usage_config(
+    chain_id: &str,
+    protocol_version: ProtocolVersion,
+    runtime_config_overrides: Option<&RuntimeConfigOverrides>,
+    num_bytes_account: Option<u64>,
+    num_extra_bytes_record: Option<u64>,
+) -> anyhow::Result<StorageUsageConfig> {
+    let store = RuntimeConfigStore::for_chain_id(chain_id);
+    let runtime_config = match runtime_config_overrides {
+        Some(overrides) => {
+            if !ProtocolFeature::OnChainRuntimeConfig.enabled(protocol_version) {
+                anyhow::bail!(
+                    "runtime config overrides are not supported in protocol version {}",
+                    protocol_version
+                );
+            }
+            store.get_config_with_overrides(protocol_version, overrides)
+        }
+        None => store.get_config(protocol_version).clone(),
+    };
+    let mut config = runtime_config.fees.storage_usage_config.clone();
+    if let Some(n) = num_bytes_account {
+        config.num_bytes_account = n;
+    }
+    if let Some(n) = num_extra_bytes_record {
+        config.num_extra_bytes_record = n;
+    }
+    Ok(config)
+}
+
+// checks that the storage_usage field of every account matches the storage used by its records.
+// Accounts whose balance doesn't cover their storage are only logged, since the input state may
+// already contain such accounts, for example if the storage price has been changed
+fn validate_storage_usage(genesis: &Genesis, config: &StorageUsageConfig) -> anyhow::Result<()> {
+    let storage_usage = near_store::genesis::compute_genesis_storage_usage(genesis, config);
+    let mut mismatched = Vec::new();
+    let mut num_underfunded = 0;
+    genesis.for_each_record(|r| {
+        if let StateRecord::Account { account_id, account } = r {
+            let expected = storage_usage.get(account_id).copied().unwrap_or_default();
+            if account.storage_usage() != expected {
+                mismatched.push(format!(
+                    "{}: storage_usage is {} but its records use {}",
+                    account_id,
+                    account.storage_usage(),
+                    expected
+                ));
+            }
+            let billable_bytes = expected.saturating_sub(config.num_bytes_exempt_from_staking);
+            let storage_cost = billable_bytes as Balance * config.storage_amount_per_byte;
+            if expected > config.zero_balance_account_limit
+                && account.amount() + account.locked() < storage_cost
+            {
+                num_underfunded += 1;
+            }
+        }
+    });
+    if num_underfunded > 0 {
+        tracing::warn!(
+            num_underfunded,
+            "some accounts in the output don't have enough balance to cover their storage"
+        );
+    }
+    if !mismatched.is_empty() {
+        anyhow::bail!(
+            "storage usage doesn't match the records for {} accounts:\n{}",
+            mismatched.len(),
+            mismatched.join("\n")
+        );
+    }
     Ok(())
 }
 
@@ -428,12 +651,13 @@ mod test {
     use anyhow::Context;
     use near_async::time::Clock;
     use near_chain_configs::{get_initial_supply, Genesis, GenesisConfig, NEAR_BASE};
+    use near_parameters::RuntimeConfigOverrides;
     use near_primitives::hash::CryptoHash;
     use near_primitives::shard_layout::ShardLayout;
     use near_primitives::state_record::StateRecord;
     use near_primitives::types::{AccountId, AccountInfo};
     use near_primitives::utils::{self, from_timestamp};
-    use near_primitives::version::PROTOCOL_VERSION;
+    use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
     use near_primitives_core::account::{AccessKey, Account};
     use near_primitives_core::types::{Balance, StorageUsage};
     use num_rational::Rational32;
@@ -474,6 +698,11 @@ mod test {
         Contract {
             account_id: &'static str,
         },
+        Data {
+            account_id: &'static str,
+            data_key: &'static str,
+            value: &'static str,
+        },
     }
 
     impl TestStateRecord {
@@ -501,6 +730,11 @@ mod test {
                     account_id: account_id.parse().unwrap(),
                     code: vec![123],
                 },
+                Self::Data { account_id, data_key, value } => StateRecord::Data {
+                    account_id: account_id.parse().unwrap(),
+                    data_key: data_key.as_bytes().to_vec().into(),
+                    value: value.as_bytes().to_vec().into(),
+                },
             }
         }
     }
@@ -534,9 +768,11 @@ mod test {
         let mut got_accounts = HashMap::new();
         let mut got_keys = HashSet::new();
         let mut got_contracts = HashMap::<AccountId, usize>::new();
+        let mut got_data = HashMap::new();
         let mut wanted_accounts = HashMap::new();
         let mut wanted_keys = HashSet::new();
         let mut wanted_contracts = HashMap::<AccountId, usize>::new();
+        let mut wanted_data = HashMap::new();
 
         for r in got_records {
             match r {
@@ -574,6 +810,14 @@ mod test {
                     }
                     *got_contracts.entry(account_id).or_default() += 1;
                 }
+                StateRecord::Data { account_id, data_key, value } => {
+                    if got_data
+                        .insert((account_id.clone(), data_key.to_vec()), value.to_vec())
+                        .is_some()
+                    {
+                        anyhow::bail!("two data records in the output for {}", &account_id);
+                    }
+                }
                 _ => anyhow::bail!("got an unexpected record in the output: {}", r),
             };
         }
@@ -596,6 +840,9 @@ mod test {
                 StateRecord::Contract { account_id, .. } => {
                     *wanted_contracts.entry(account_id).or_default() += 1;
                 }
+                StateRecord::Data { account_id, data_key, value } => {
+                    wanted_data.insert((account_id, data_key.to_vec()), value.to_vec());
+                }
                 _ => anyhow::bail!("got an unexpected record in the output: {}", r),
             };
         }
@@ -603,6 +850,7 @@ mod test {
         assert_eq!(got_accounts, wanted_accounts);
         assert_eq!(got_keys, wanted_keys);
         assert_eq!(got_contracts, wanted_contracts);
+        assert_eq!(got_data, wanted_data);
         Ok(())
     }
 
@@ -674,11 +922,19 @@ mod test {
             })
         }
 
+        fn run(&self) -> anyhow::Result<()> {
+            self.run_with_changes(true, &crate::GenesisChanges::default())
+        }
+
         // take the records in the test case and write them to temp files, and then call amend_genesis() and
         // check that the resulting genesis and records files match what's in self.want_records
         // right now we aren't testing that other kinds of records appearing in the input records file
         // will make it into the output, but that part is pretty simple
-        fn run(&self) -> anyhow::Result<()> {
+        fn run_with_changes(
+            &self,
+            use_validators_file: bool,
+            genesis_changes: &crate::GenesisChanges,
+        ) -> anyhow::Result<()> {
             let ParsedTestCase {
                 genesis,
                 records_file_in,
@@ -711,11 +967,11 @@ mod test {
                 records_file_in.path(),
                 records_file_out.path(),
                 Some(extra_records_file.path()),
-                validators_file.path(),
+                use_validators_file.then_some(validators_file.path()),
                 None,
-                &crate::GenesisChanges::default(),
-                100,
-                40,
+                genesis_changes,
+                Some(100),
+                Some(40),
             )
             .context("amend_genesis() failed")?;
 
@@ -1077,4 +1333,176 @@ mod test {
             t.run().unwrap();
         }
     }
+
+    // removes foo1 from the validators in the input genesis and adds foo2
+    static VALIDATOR_CHANGES_TEST_CASE: TestCase = TestCase {
+        initial_validators: &[
+            TestAccountInfo {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+                amount: 1_000_000,
+            },
+            TestAccountInfo {
+                account_id: "foo1",
+                public_key: "ed25519:FXXrTXiKWpXj1R6r5fBvMLpstd8gPyrBq3qMByqKVzKF",
+                amount: 2_000_000,
+            },
+        ],
+        records_in: &[
+            TestStateRecord::Account {
+                account_id: "foo0",
+                amount: 1_000_000,
+                locked: 1_000_000,
+                storage_usage: 182,
+            },
+            TestStateRecord::AccessKey {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+            },
+            TestStateRecord::Account {
+                account_id: "foo1",
+                amount: 1_000_000,
+                locked: 2_000_000,
+                storage_usage: 182,
+            },
+            TestStateRecord::AccessKey {
+                account_id: "foo1",
+                public_key: "ed25519:FXXrTXiKWpXj1R6r5fBvMLpstd8gPyrBq3qMByqKVzKF",
+            },
+        ],
+        validators_in: &[],
+        extra_records: &[],
+        wanted_records: &[
+            TestStateRecord::Account {
+                account_id: "foo0",
+                amount: 1_000_000,
+                locked: 1_000_000,
+                storage_usage: 182,
+            },
+            TestStateRecord::AccessKey {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+            },
+            TestStateRecord::Account {
+                account_id: "foo1",
+                amount: 3_000_000,
+                locked: 0,
+                storage_usage: 182,
+            },
+            TestStateRecord::AccessKey {
+                account_id: "foo1",
+                public_key: "ed25519:FXXrTXiKWpXj1R6r5fBvMLpstd8gPyrBq3qMByqKVzKF",
+            },
+            TestStateRecord::Account {
+                account_id: "foo2",
+                amount: 10_000 * NEAR_BASE,
+                locked: 3_000_000,
+                storage_usage: 182,
+            },
+            TestStateRecord::AccessKey {
+                account_id: "foo2",
+                public_key: "ed25519:Eo9W44tRMwcYcoua11yM7Xfr1DjgR4EWQFM3RU27MEX8",
+            },
+        ],
+    };
+
+    #[test]
+    fn test_amend_genesis_validator_changes() {
+        let genesis_changes = crate::GenesisChanges {
+            add_validators: vec![TestAccountInfo {
+                account_id: "foo2",
+                public_key: "ed25519:Eo9W44tRMwcYcoua11yM7Xfr1DjgR4EWQFM3RU27MEX8",
+                amount: 3_000_000,
+            }
+            .parse()],
+            remove_validators: vec!["foo1".parse().unwrap()],
+            ..Default::default()
+        };
+        VALIDATOR_CHANGES_TEST_CASE.run_with_changes(false, &genesis_changes).unwrap();
+
+        let genesis_changes = crate::GenesisChanges {
+            remove_validators: vec!["asdf.near".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(VALIDATOR_CHANGES_TEST_CASE.run_with_changes(false, &genesis_changes).is_err());
+    }
+
+    // replaces the runtime config overrides stored in the state of the treasury account
+    static RUNTIME_CONFIG_OVERRIDES_TEST_CASE: TestCase = TestCase {
+        initial_validators: &[TestAccountInfo {
+            account_id: "foo0",
+            public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+            amount: 1_000_000,
+        }],
+        records_in: &[
+            TestStateRecord::Account {
+                account_id: "foo0",
+                amount: 1_000_000,
+                locked: 1_000_000,
+                storage_usage: 182,
+            },
+            TestStateRecord::AccessKey {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+            },
+            TestStateRecord::Account {
+                account_id: "treasury.near",
+                amount: 1_000_000,
+                locked: 0,
+                storage_usage: 166,
+            },
+            TestStateRecord::Data {
+                account_id: "treasury.near",
+                data_key: "runtime_config_overrides",
+                value: "{}",
+            },
+        ],
+        validators_in: &[],
+        extra_records: &[],
+        wanted_records: &[
+            TestStateRecord::Account {
+                account_id: "foo0",
+                amount: 1_000_000,
+                locked: 1_000_000,
+                storage_usage: 182,
+            },
+            TestStateRecord::AccessKey {
+                account_id: "foo0",
+                public_key: "ed25519:He7QeRuwizNEhBioYG3u4DZ8jWXyETiyNzFD3MkTjDMf",
+            },
+            TestStateRecord::Account {
+                account_id: "treasury.near",
+                amount: 1_000_000,
+                locked: 0,
+                storage_usage: 342,
+            },
+            TestStateRecord::Data {
+                account_id: "treasury.near",
+                data_key: "runtime_config_overrides",
+                value: r#"{"storage_amount_per_byte":"1","max_congestion_incoming_gas":null,"max_congestion_outgoing_gas":null,"max_congestion_memory_consumption":null,"max_congestion_missed_chunks":null}"#,
+            },
+        ],
+    };
+
+    #[test]
+    fn test_amend_genesis_runtime_config_overrides() {
+        let overrides =
+            RuntimeConfigOverrides { storage_amount_per_byte: Some(1), ..Default::default() };
+        let genesis_changes = crate::GenesisChanges {
+            runtime_config_overrides: Some(overrides.clone()),
+            protocol_version: Some(ProtocolFeature::OnChainRuntimeConfig.protocol_version()),
+            ..Default::default()
+        };
+        RUNTIME_CONFIG_OVERRIDES_TEST_CASE.run_with_changes(false, &genesis_changes).unwrap();
+
+        // the overrides are rejected if the output protocol version doesn't support them
+        let genesis_changes = crate::GenesisChanges {
+            runtime_config_overrides: Some(overrides),
+            protocol_version: Some(ProtocolFeature::OnChainRuntimeConfig.protocol_version() - 1),
+            ..Default::default()
+        };
+        assert!(RUNTIME_CONFIG_OVERRIDES_TEST_CASE
+            .run_with_changes(false, &genesis_changes)
+            .is_err());
+    }
 }
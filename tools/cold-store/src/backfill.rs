@@ -0,0 +1,507 @@
+use anyhow::Context;
+use borsh::BorshDeserialize;
+use near_chain::validate::validate_chunk_proofs;
+use near_chain_configs::GenesisValidationMode;
+use near_epoch_manager::{EpochManagerAdapter, EpochManagerHandle};
+use near_primitives::block::{Block, BlockHeader, Tip};
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::verify_path;
+use near_primitives::shard_layout::{ShardLayout, ShardUId};
+use near_primitives::sharding::{PartialEncodedChunk, ShardChunk};
+use near_primitives::transaction::{ExecutionOutcomeWithId, ExecutionOutcomeWithProof};
+use near_primitives::types::chunk_extra::ChunkExtra;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_store::cold_storage::{get_cold_block_data, is_shard_archived, write_cold_block_data};
+use near_store::{DBCol, Mode, NodeStorage, Store, COLD_HEAD_KEY, COLD_TAIL_KEY, HEAD_KEY};
+use nearcore::NearConfig;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Entries of the cold columns for a block, see [`get_cold_block_data`].
+type BlockData = Vec<(DBCol, Vec<u8>, Vec<u8>)>;
+
+#[derive(clap::Args)]
+pub(crate) struct BackfillCmd {
+    /// Socket of the store server of an archival node to fetch the missing
+    /// data from, see the `store_server_socket` config option.  The socket of
+    /// a node on another machine can be forwarded with `ssh -L`.
+    #[clap(long, conflicts_with = "source_home", required_unless_present = "source_home")]
+    source_socket: Option<PathBuf>,
+    /// Home directory of an archival node, or of a restored dump of one, to
+    /// fetch the missing data from.  The node must not be running.
+    #[clap(long)]
+    source_home: Option<PathBuf>,
+    /// First height to check, the cold tail or the genesis height by default.
+    #[clap(long)]
+    from_height: Option<BlockHeight>,
+    /// Last height to check, the cold head by default.
+    #[clap(long)]
+    to_height: Option<BlockHeight>,
+    /// Only report the missing heights, without fetching them.
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl BackfillCmd {
+    pub(crate) fn run(
+        self,
+        storage: &NodeStorage,
+        near_config: &NearConfig,
+        epoch_manager: &EpochManagerHandle,
+        readwrite: bool,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.dry_run || readwrite,
+            "backfilling requires --readwrite, use --dry-run to only report the missing heights"
+        );
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().context("cold storage is not configured")?;
+        let cold_db = storage.cold_db().unwrap();
+        let archived_shards = near_config
+            .config
+            .split_storage
+            .as_ref()
+            .and_then(|config| config.archived_shards.as_deref());
+
+        let cold_head = cold_store
+            .get_ser::<Tip>(DBCol::BlockMisc, COLD_HEAD_KEY)?
+            .context("cold head is not set")?;
+        let cold_tail = cold_store.get_ser::<BlockHeight>(DBCol::BlockMisc, COLD_TAIL_KEY)?;
+        let from_height = self
+            .from_height
+            .unwrap_or(cold_tail.unwrap_or(near_config.genesis.config.genesis_height));
+        let to_height = self.to_height.unwrap_or(cold_head.height).min(cold_head.height);
+
+        let missing = find_missing_blocks(
+            &hot_store,
+            &cold_store,
+            epoch_manager,
+            archived_shards,
+            from_height,
+            to_height,
+        )?;
+        println!("Checked heights {from_height} to {to_height}, {} are missing", missing.len());
+        for (start, end) in missing_ranges(&missing) {
+            println!("  {start}..={end}");
+        }
+        if self.dry_run || missing.is_empty() {
+            return Ok(());
+        }
+
+        let source = self.open_source()?;
+        let mut failed = 0;
+        for (height, block_hash) in &missing {
+            let result = backfill_block(
+                &hot_store,
+                &cold_store,
+                &source,
+                epoch_manager,
+                archived_shards,
+                *height,
+                block_hash,
+            )
+            .and_then(|data| Ok(write_cold_block_data(cold_db, data)?));
+            if let Err(err) = result {
+                tracing::warn!(target: "cold-store", height, ?block_hash, ?err, "failed to backfill block");
+                failed += 1;
+            }
+        }
+        println!("Backfilled {} heights", missing.len() - failed);
+        anyhow::ensure!(failed == 0, "failed to backfill {failed} heights");
+        Ok(())
+    }
+
+    fn open_source(&self) -> anyhow::Result<Store> {
+        if let Some(socket) = &self.source_socket {
+            #[cfg(unix)]
+            {
+                let db = near_store::db::RemoteDB::connect(socket)
+                    .with_context(|| format!("failed to connect to {}", socket.display()))?;
+                return Ok(NodeStorage::new(std::sync::Arc::new(db)).get_hot_store());
+            }
+            #[cfg(not(unix))]
+            anyhow::bail!("--source-socket {} is only supported on Unix", socket.display());
+        }
+        let home = self.source_home.as_ref().unwrap();
+        let config = nearcore::config::load_config(home, GenesisValidationMode::UnsafeFast)?;
+        let storage = NodeStorage::opener(
+            home,
+            config.config.archive,
+            &config.config.store,
+            config.config.cold_store.as_ref(),
+        )
+        .open_in_mode(Mode::ReadOnly)?;
+        Ok(storage.get_split_store().unwrap_or_else(|| storage.get_hot_store()))
+    }
+}
+
+/// Returns the canonical blocks between the heights, inclusive, which are
+/// missing from the cold db or miss some of their new chunks.  The canonical
+/// chain is read from `BlockHeight` in the hot db, which is never garbage
+/// collected.
+fn find_missing_blocks(
+    hot_store: &Store,
+    cold_store: &Store,
+    epoch_manager: &EpochManagerHandle,
+    archived_shards: Option<&[ShardUId]>,
+    from_height: BlockHeight,
+    to_height: BlockHeight,
+) -> anyhow::Result<Vec<(BlockHeight, CryptoHash)>> {
+    let mut missing = vec![];
+    for height in from_height..=to_height {
+        let Some(block_hash) =
+            hot_store.get_ser::<CryptoHash>(DBCol::BlockHeight, &height.to_le_bytes())?
+        else {
+            continue;
+        };
+        let Some(block) = cold_store.get_ser::<Block>(DBCol::Block, block_hash.as_ref())? else {
+            missing.push((height, block_hash));
+            continue;
+        };
+        let shard_layout = epoch_manager.get_shard_layout(block.header().epoch_id())?;
+        for chunk_header in block.chunks().iter() {
+            let shard_uid =
+                ShardUId::from_shard_id_and_layout(chunk_header.shard_id(), &shard_layout);
+            if is_shard_archived(archived_shards, &shard_uid)
+                && chunk_header.is_new_chunk(height)
+                && !cold_store.exists(DBCol::Chunks, chunk_header.chunk_hash().as_ref())?
+            {
+                missing.push((height, block_hash));
+                break;
+            }
+        }
+    }
+    Ok(missing)
+}
+
+/// Groups the missing blocks into ranges of consecutive heights.
+fn missing_ranges(missing: &[(BlockHeight, CryptoHash)]) -> Vec<(BlockHeight, BlockHeight)> {
+    let mut ranges: Vec<(BlockHeight, BlockHeight)> = vec![];
+    for (height, _) in missing {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *height => *end = *height,
+            _ => ranges.push((*height, *height)),
+        }
+    }
+    ranges
+}
+
+/// Fetches the data of the block at `height` from `source` and verifies it.
+fn backfill_block(
+    hot_store: &Store,
+    cold_store: &Store,
+    source: &Store,
+    epoch_manager: &EpochManagerHandle,
+    archived_shards: Option<&[ShardUId]>,
+    height: BlockHeight,
+    block_hash: &CryptoHash,
+) -> anyhow::Result<BlockData> {
+    // BlockHeader is never garbage collected from the hot store.
+    let header = hot_store
+        .get_ser::<BlockHeader>(DBCol::BlockHeader, block_hash.as_ref())?
+        .context("block header not found")?;
+    let shard_layout = epoch_manager.get_shard_layout(header.epoch_id())?;
+    let data = get_cold_block_data(source, &shard_layout, archived_shards, &height, block_hash)
+        .context("failed to read the block from the source")?;
+    let outcome_roots =
+        next_outcome_roots(hot_store, cold_store, source, epoch_manager, &shard_layout, height)?;
+    verify_block_data(epoch_manager, &shard_layout, block_hash, &outcome_roots, &data)?;
+    Ok(data)
+}
+
+/// Returns the outcome roots of the chunks applied in the block at `height`,
+/// which the first new chunk of every shard in a later canonical block
+/// commits to as its `prev_outcome_root`.  The outcome roots of the chunk
+/// extras can't be trusted, as they come from the same source as the
+/// outcomes.  The later blocks are read from the cold db, or from the source
+/// if they're missing there too, and verified against the canonical chain of
+/// the hot db.
+fn next_outcome_roots(
+    hot_store: &Store,
+    cold_store: &Store,
+    source: &Store,
+    epoch_manager: &EpochManagerHandle,
+    shard_layout: &ShardLayout,
+    height: BlockHeight,
+) -> anyhow::Result<HashMap<ShardId, CryptoHash>> {
+    let head =
+        hot_store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?.context("hot head is not set")?;
+    let num_shards = shard_layout.shard_ids().count();
+    let mut outcome_roots = HashMap::new();
+    for next_height in height + 1..=head.height {
+        if outcome_roots.len() == num_shards {
+            break;
+        }
+        let Some(block_hash) =
+            hot_store.get_ser::<CryptoHash>(DBCol::BlockHeight, &next_height.to_le_bytes())?
+        else {
+            continue;
+        };
+        let block = match cold_store.get_ser::<Block>(DBCol::Block, block_hash.as_ref())? {
+            Some(block) => block,
+            None => source
+                .get_ser::<Block>(DBCol::Block, block_hash.as_ref())?
+                .with_context(|| format!("block at height {next_height} not found"))?,
+        };
+        anyhow::ensure!(
+            block.hash() == &block_hash && block.check_validity().is_ok(),
+            "block at height {next_height} doesn't match the canonical chain"
+        );
+        anyhow::ensure!(
+            &epoch_manager.get_shard_layout(block.header().epoch_id())? == shard_layout,
+            "the shard layout changes before the next chunks of all shards, at height {next_height}"
+        );
+        for chunk_header in block.chunks().iter() {
+            if chunk_header.is_new_chunk(next_height) {
+                outcome_roots
+                    .entry(chunk_header.shard_id())
+                    .or_insert_with(|| chunk_header.prev_outcome_root());
+            }
+        }
+    }
+    anyhow::ensure!(
+        outcome_roots.len() == num_shards,
+        "some shards have no new chunks after height {height} up to the head at {}",
+        head.height
+    );
+    Ok(outcome_roots)
+}
+
+/// Verifies the fetched data against the hashes committing to it: the block
+/// against its hash, the chunks against the block, the transactions and
+/// receipts against the chunks and the outcomes and the outcome roots of the
+/// chunk extras against `outcome_roots`, see [`next_outcome_roots`].  The
+/// data without such commitments, e.g. the state changes, is taken from the
+/// source as is.
+fn verify_block_data(
+    epoch_manager: &EpochManagerHandle,
+    shard_layout: &ShardLayout,
+    block_hash: &CryptoHash,
+    outcome_roots: &HashMap<ShardId, CryptoHash>,
+    data: &BlockData,
+) -> anyhow::Result<()> {
+    let entries = |col: DBCol| data.iter().filter(move |(c, ..)| *c == col);
+
+    let (_, _, block) = entries(DBCol::Block).next().context("block is missing")?;
+    let block = Block::try_from_slice(block)?;
+    anyhow::ensure!(block.hash() == block_hash, "block hash mismatch: {}", block.hash());
+    anyhow::ensure!(block.check_validity().is_ok(), "block body doesn't match its header");
+    let chunk_hashes: HashSet<_> =
+        block.chunks().iter().map(|chunk_header| chunk_header.chunk_hash()).collect();
+
+    let mut transactions = HashMap::new();
+    let mut receipts = HashMap::new();
+    for (_, key, value) in entries(DBCol::Chunks) {
+        let chunk = ShardChunk::try_from_slice(value)?;
+        let chunk_hash = chunk.chunk_hash();
+        anyhow::ensure!(
+            key.as_slice() == chunk_hash.as_ref()
+                && chunk_hashes.contains(&chunk_hash)
+                && validate_chunk_proofs(&chunk, epoch_manager)?,
+            "chunk {chunk_hash:?} doesn't match the block"
+        );
+        for transaction in chunk.transactions() {
+            transactions.insert(transaction.get_hash(), borsh::to_vec(transaction)?);
+        }
+        for receipt in chunk.prev_outgoing_receipts() {
+            receipts.insert(receipt.get_hash(), borsh::to_vec(receipt)?);
+        }
+    }
+    for (_, key, value) in entries(DBCol::PartialChunks) {
+        let partial_chunk = PartialEncodedChunk::try_from_slice(value)?;
+        let chunk_hash = partial_chunk.chunk_hash();
+        anyhow::ensure!(
+            key.as_slice() == chunk_hash.as_ref()
+                && chunk_hashes.contains(&chunk_hash)
+                && partial_chunk.cloned_header().compute_hash() == chunk_hash,
+            "partial chunk {chunk_hash:?} doesn't match the block"
+        );
+    }
+    for (col, values) in [(DBCol::Transactions, &transactions), (DBCol::Receipts, &receipts)] {
+        for (_, key, value) in entries(col) {
+            let hash = CryptoHash::try_from(key.as_slice())
+                .map_err(|err| anyhow::anyhow!("invalid {col} key: {err}"))?;
+            anyhow::ensure!(
+                values.get(&hash) == Some(value),
+                "{col} {hash} doesn't match the chunks"
+            );
+        }
+    }
+    verify_outcomes(shard_layout, block_hash, outcome_roots, data)
+}
+
+/// Verifies the outcomes and the outcome roots of the chunk extras of the
+/// block against `outcome_roots`.
+fn verify_outcomes(
+    shard_layout: &ShardLayout,
+    block_hash: &CryptoHash,
+    outcome_roots: &HashMap<ShardId, CryptoHash>,
+    data: &BlockData,
+) -> anyhow::Result<()> {
+    let entries = |col: DBCol| data.iter().filter(move |(c, ..)| *c == col);
+
+    for shard_id in shard_layout.shard_ids() {
+        let shard_uid = ShardUId::from_shard_id_and_layout(shard_id, shard_layout);
+        let chunk_extra_key = [block_hash.as_ref(), &shard_uid.to_bytes()].concat();
+        let Some((_, _, chunk_extra)) =
+            entries(DBCol::ChunkExtra).find(|(_, key, _)| *key == chunk_extra_key)
+        else {
+            continue;
+        };
+        anyhow::ensure!(
+            Some(ChunkExtra::try_from_slice(chunk_extra)?.outcome_root())
+                == outcome_roots.get(&shard_id),
+            "chunk extra of shard {shard_id} doesn't match the next chunks"
+        );
+    }
+
+    let mut verified_outcomes = 0;
+    for (_, key, value) in entries(DBCol::OutcomeIds) {
+        let shard_id = ShardId::try_from_slice(&key[CryptoHash::LENGTH..])?;
+        let outcome_root = *outcome_roots
+            .get(&shard_id)
+            .with_context(|| format!("outcome root of shard {shard_id} is unknown"))?;
+        for id in Vec::<CryptoHash>::try_from_slice(value)? {
+            let outcome_key = [id.as_ref(), block_hash.as_ref()].concat();
+            let Some((_, _, outcome)) =
+                entries(DBCol::TransactionResultForBlock).find(|(_, key, _)| *key == outcome_key)
+            else {
+                continue;
+            };
+            let ExecutionOutcomeWithProof { proof, outcome } =
+                ExecutionOutcomeWithProof::try_from_slice(outcome)?;
+            let hashes = ExecutionOutcomeWithId { id, outcome }.to_hashes();
+            anyhow::ensure!(
+                verify_path(outcome_root, &proof, &hashes),
+                "outcome {id} doesn't match the next chunks of shard {shard_id}"
+            );
+            verified_outcomes += 1;
+        }
+    }
+    anyhow::ensure!(
+        verified_outcomes == entries(DBCol::TransactionResultForBlock).count(),
+        "some outcomes are not listed in the outcome ids"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{missing_ranges, verify_outcomes, BlockData};
+    use near_primitives::congestion_info::CongestionInfo;
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::merkle::merklize;
+    use near_primitives::shard_layout::{ShardLayout, ShardUId};
+    use near_primitives::transaction::{ExecutionOutcomeWithId, ExecutionOutcomeWithProof};
+    use near_primitives::types::chunk_extra::ChunkExtra;
+    use near_primitives::types::ShardId;
+    use near_primitives::version::{ProtocolFeature, PROTOCOL_VERSION};
+    use near_store::DBCol;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_missing_ranges() {
+        let missing: Vec<_> = [3, 4, 5, 8, 10, 11]
+            .into_iter()
+            .map(|height| (height, CryptoHash::default()))
+            .collect();
+        assert_eq!(missing_ranges(&missing), vec![(3, 5), (8, 8), (10, 11)]);
+    }
+
+    fn outcome(id: &[u8], gas_burnt: u64) -> ExecutionOutcomeWithId {
+        let mut outcome = ExecutionOutcomeWithId { id: hash(id), ..Default::default() };
+        outcome.outcome.gas_burnt = gas_burnt;
+        outcome
+    }
+
+    /// Returns the data of a block with `outcomes` in the only shard of
+    /// [`ShardLayout::v0_single_shard`], as a source would store it, and their
+    /// outcome root.
+    fn block_data(
+        block_hash: &CryptoHash,
+        outcomes: &[ExecutionOutcomeWithId],
+    ) -> (BlockData, CryptoHash) {
+        let shard_id: ShardId = 0;
+        let shard_uid =
+            ShardUId::from_shard_id_and_layout(shard_id, &ShardLayout::v0_single_shard());
+        let (outcome_root, proofs) =
+            merklize(&outcomes.iter().map(|outcome| outcome.to_hashes()).collect::<Vec<_>>());
+        let congestion_info = ProtocolFeature::CongestionControl
+            .enabled(PROTOCOL_VERSION)
+            .then(CongestionInfo::default);
+        let chunk_extra = ChunkExtra::new(
+            PROTOCOL_VERSION,
+            &CryptoHash::default(),
+            outcome_root,
+            vec![],
+            0,
+            0,
+            0,
+            congestion_info,
+        );
+        let ids: Vec<_> = outcomes.iter().map(|outcome| outcome.id).collect();
+        let mut data = vec![
+            (
+                DBCol::ChunkExtra,
+                [block_hash.as_ref(), &shard_uid.to_bytes()].concat(),
+                borsh::to_vec(&chunk_extra).unwrap(),
+            ),
+            (
+                DBCol::OutcomeIds,
+                [block_hash.as_ref(), &shard_id.to_le_bytes()].concat(),
+                borsh::to_vec(&ids).unwrap(),
+            ),
+        ];
+        for (ExecutionOutcomeWithId { id, outcome }, proof) in outcomes.iter().zip(proofs) {
+            data.push((
+                DBCol::TransactionResultForBlock,
+                [id.as_ref(), block_hash.as_ref()].concat(),
+                borsh::to_vec(&ExecutionOutcomeWithProof { proof, outcome: outcome.clone() })
+                    .unwrap(),
+            ));
+        }
+        (data, outcome_root)
+    }
+
+    #[test]
+    fn test_verify_outcomes() {
+        let shard_layout = ShardLayout::v0_single_shard();
+        let block_hash = hash(b"block");
+        let outcomes = [outcome(b"a", 1), outcome(b"b", 2), outcome(b"c", 3)];
+        let (data, outcome_root) = block_data(&block_hash, &outcomes);
+        // The outcome root committed to by the next chunk of the shard.
+        let outcome_roots = HashMap::from([(0, outcome_root)]);
+        verify_outcomes(&shard_layout, &block_hash, &outcome_roots, &data).unwrap();
+
+        // An outcome which isn't listed in the outcome ids.
+        let mut unlisted = data.clone();
+        unlisted.push((
+            DBCol::TransactionResultForBlock,
+            [hash(b"d").as_ref(), block_hash.as_ref()].concat(),
+            unlisted.last().unwrap().2.clone(),
+        ));
+        verify_outcomes(&shard_layout, &block_hash, &outcome_roots, &unlisted).unwrap_err();
+    }
+
+    /// A source whose outcomes and chunk extras are consistent with each other
+    /// but not with the chain is rejected.
+    #[test]
+    fn test_verify_outcomes_corrupted_source() {
+        let shard_layout = ShardLayout::v0_single_shard();
+        let block_hash = hash(b"block");
+        let outcomes = [outcome(b"a", 1), outcome(b"b", 2), outcome(b"c", 3)];
+        let (_, outcome_root) = block_data(&block_hash, &outcomes);
+        let outcome_roots = HashMap::from([(0, outcome_root)]);
+
+        let corrupted = [outcome(b"a", 1), outcome(b"b", 20), outcome(b"c", 3)];
+        let (data, corrupted_root) = block_data(&block_hash, &corrupted);
+        assert_ne!(corrupted_root, outcome_root);
+        let err = verify_outcomes(&shard_layout, &block_hash, &outcome_roots, &data).unwrap_err();
+        assert!(err.to_string().contains("chunk extra"), "{err}");
+
+        // Without the chunk extra the outcome itself fails to verify.
+        let data: Vec<_> = data.into_iter().filter(|(col, ..)| *col != DBCol::ChunkExtra).collect();
+        let err = verify_outcomes(&shard_layout, &block_hash, &outcome_roots, &data).unwrap_err();
+        assert!(err.to_string().contains("doesn't match the next chunks"), "{err}");
+    }
+}
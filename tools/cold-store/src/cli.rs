@@ -1,4 +1,7 @@
+use crate::backfill::BackfillCmd;
 use crate::cli::SubCommand::CheckStateRoot;
+use crate::gc_orphans::GcOrphansCmd;
+use crate::split_in_place::SplitInPlaceCmd;
 use anyhow;
 use anyhow::Context;
 use borsh::BorshDeserialize;
@@ -56,6 +59,28 @@ enum SubCommand {
     /// Modifies cold db from config to be considered not initialised.
     /// Doesn't actually delete any data, except for HEAD and COLD_HEAD in BlockMisc.
     ResetCold(ResetColdCmd),
+    /// Convert the legacy archival db at config.store.path into a hot db and
+    /// the cold db at config.cold_store.path without copying the bulk of the
+    /// data. Both are checkpoints of the archival db, so their files are hard
+    /// links to its files, and only the recent blocks are copied back to the
+    /// hot db. Progress is saved in the home dir, so running the command again
+    /// resumes an interrupted conversion. Finishes with a verification pass.
+    /// The archival db itself is not modified.
+    SplitInPlace(SplitInPlaceCmd),
+    /// Find the data in the cold db which is not reachable from the canonical
+    /// chain: the blocks on forks up to the cold head, their data and the
+    /// chunks never included in a canonical block. Deletes it, unless
+    /// `--dry-run` is given, in which case only the reclaimable space is
+    /// reported. Requires `--readwrite` to delete, so the node must be stopped.
+    GcOrphans(GcOrphansCmd),
+    /// Find the canonical blocks up to the cold head which are missing from
+    /// the cold db, or miss some of their new chunks, and fetch their data
+    /// from another archival node, either through its store server socket or
+    /// from its home dir. The fetched blocks, chunks, transactions, receipts
+    /// and outcomes are verified against their hashes before being written.
+    /// `--dry-run` only reports the missing heights. Requires `--readwrite`
+    /// to write, so the node must be stopped.
+    Backfill(BackfillCmd),
 }
 
 impl ColdStoreCommand {
@@ -68,6 +93,11 @@ impl ColdStoreCommand {
         )
         .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
 
+        // The cold db doesn't exist yet, so the storage can't be opened as usual.
+        if let SubCommand::SplitInPlace(cmd) = self.subcmd {
+            return cmd.run(home_dir, &near_config);
+        }
+
         let opener = self.get_opener(home_dir, &mut near_config);
 
         let storage =
@@ -91,6 +121,11 @@ impl ColdStoreCommand {
             SubCommand::PrepareHot(cmd) => cmd.run(&storage, &home_dir, &near_config),
             SubCommand::CheckStateRoot(cmd) => cmd.run(&storage),
             SubCommand::ResetCold(cmd) => cmd.run(&storage),
+            SubCommand::GcOrphans(cmd) => cmd.run(&storage, self.readwrite),
+            SubCommand::Backfill(cmd) => {
+                cmd.run(&storage, &near_config, epoch_manager.as_ref(), self.readwrite)
+            }
+            SubCommand::SplitInPlace(_) => unreachable!(),
         }
     }
 
@@ -229,6 +264,7 @@ fn copy_next_block(store: &NodeStorage, config: &NearConfig, epoch_manager: &Epo
         &epoch_manager
             .get_shard_layout(&epoch_manager.get_epoch_id_from_prev_block(&cold_head_hash).unwrap())
             .unwrap(),
+        config.config.split_storage.as_ref().and_then(|config| config.archived_shards.as_deref()),
         &next_height,
         1,
     )
@@ -253,6 +289,7 @@ fn copy_all_blocks(storage: &NodeStorage, batch_size: usize, check: bool) {
     copy_all_data_to_cold(
         (*storage.cold_db().unwrap()).clone(),
         &storage.get_hot_store(),
+        hot_final_head,
         batch_size,
         &keep_going,
     )
@@ -291,7 +328,7 @@ fn check_key(
 /// Checks that `first_store`'s column `col` is fully included in `second_store`
 /// with same values for every key.
 /// Return number of checks performed == number of keys in column `col` of the `first_store`.
-fn check_iter(
+pub(crate) fn check_iter(
     first_store: &near_store::Store,
     second_store: &near_store::Store,
     col: DBCol,
@@ -0,0 +1,441 @@
+use anyhow::Context;
+use borsh::BorshDeserialize;
+use near_primitives::block::{Block, Tip};
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::{ChunkHash, PartialEncodedChunk, ShardChunk};
+use near_primitives::types::BlockHeight;
+use near_store::db::ColdDB;
+use near_store::{DBCol, NodeStorage, Store, COLD_HEAD_KEY};
+use std::collections::{HashMap, HashSet};
+
+/// Cold columns whose keys start with, or contain, the hash of the block the
+/// data belongs to.  See [`block_hash_of_key`].
+const BLOCK_KEYED_COLUMNS: [DBCol; 12] = [
+    DBCol::Block,
+    DBCol::BlockExtra,
+    DBCol::BlockInfo,
+    DBCol::ChunkExtra,
+    DBCol::IncomingReceipts,
+    DBCol::NextBlockHashes,
+    DBCol::OutcomeIds,
+    DBCol::OutgoingReceipts,
+    DBCol::StateChanges,
+    DBCol::StateChangesForSplitStates,
+    DBCol::StateHeaders,
+    DBCol::TransactionResultForBlock,
+];
+
+/// Cold columns keyed by the chunk hash.
+const CHUNK_KEYED_COLUMNS: [DBCol; 2] = [DBCol::Chunks, DBCol::PartialChunks];
+
+/// Number of blocks whose included chunks are cached while scanning chunks.
+const INCLUDED_CHUNKS_CACHE_SIZE: usize = 100_000;
+
+#[derive(clap::Args)]
+pub(crate) struct GcOrphansCmd {
+    /// Only report the orphaned data and the space it takes, without deleting
+    /// anything.
+    #[clap(long)]
+    dry_run: bool,
+    /// Maximum number of keys deleted in a single write.
+    #[clap(long, default_value_t = 10_000)]
+    batch_size: usize,
+    /// Compact the cold db after deleting, so that the space is reclaimed
+    /// right away rather than by the background compactions.
+    #[clap(long)]
+    compact: bool,
+}
+
+/// Number of orphaned keys and their size in a column.
+#[derive(Default)]
+struct ColumnReport {
+    keys: u64,
+    bytes: u64,
+}
+
+impl GcOrphansCmd {
+    pub(crate) fn run(self, storage: &NodeStorage, readwrite: bool) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.dry_run || readwrite,
+            "deleting orphaned data requires --readwrite, use --dry-run to only report it"
+        );
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().context("cold storage is not configured")?;
+        let cold_db = storage.cold_db().unwrap();
+        let cold_head = cold_store
+            .get_ser::<Tip>(DBCol::BlockMisc, COLD_HEAD_KEY)?
+            .context("cold head is not set")?;
+
+        let collector = OrphanCollector {
+            hot_store,
+            cold_store,
+            cold_db,
+            cold_head_height: cold_head.height,
+            dry_run: self.dry_run,
+            batch_size: self.batch_size,
+        };
+        let orphan_blocks = collector.find_orphan_blocks()?;
+        tracing::info!(target: "cold-store", count = orphan_blocks.len(), "found orphaned blocks");
+
+        // Chunks are checked against the blocks, so they go first.
+        let mut reports = Vec::new();
+        for col in CHUNK_KEYED_COLUMNS {
+            reports.push((col, collector.collect_chunks(col, &orphan_blocks)?));
+        }
+        for col in BLOCK_KEYED_COLUMNS {
+            reports.push((col, collector.collect_block_data(col, &orphan_blocks)?));
+        }
+
+        let verb = if self.dry_run { "Reclaimable" } else { "Deleted" };
+        let mut total = ColumnReport::default();
+        for (col, report) in &reports {
+            println!("{col}: {} keys, {}", report.keys, bytesize::ByteSize(report.bytes));
+            total.keys += report.keys;
+            total.bytes += report.bytes;
+        }
+        println!(
+            "{verb}: {} orphaned blocks, {} keys, {}",
+            orphan_blocks.len(),
+            total.keys,
+            bytesize::ByteSize(total.bytes)
+        );
+
+        if !self.dry_run && self.compact {
+            tracing::info!(target: "cold-store", "compacting the cold db");
+            collector.cold_store.compact()?;
+        }
+        Ok(())
+    }
+}
+
+struct OrphanCollector<'a> {
+    hot_store: Store,
+    cold_store: Store,
+    cold_db: &'a ColdDB,
+    /// Data of blocks above the cold head is never considered orphaned, since
+    /// the canonical chain may still change there.
+    cold_head_height: BlockHeight,
+    dry_run: bool,
+    batch_size: usize,
+}
+
+impl<'a> OrphanCollector<'a> {
+    /// Returns whether the block at `height` is `block_hash` on the canonical
+    /// chain, which is kept by the hot db in [`DBCol::BlockHeight`].
+    fn is_canonical(&self, block_hash: &CryptoHash, height: BlockHeight) -> anyhow::Result<bool> {
+        let canonical =
+            self.hot_store.get_ser::<CryptoHash>(DBCol::BlockHeight, &height.to_le_bytes())?;
+        Ok(canonical.as_ref() == Some(block_hash))
+    }
+
+    /// Returns the hashes of the blocks in the cold db, up to the cold head,
+    /// which are not on the canonical chain.
+    fn find_orphan_blocks(&self) -> anyhow::Result<HashSet<CryptoHash>> {
+        let mut orphans = HashSet::new();
+        for (scanned, item) in self.cold_store.iter(DBCol::Block).enumerate() {
+            let (key, value) = item?;
+            let block_hash = CryptoHash::try_from(key.as_ref()).map_err(|e| anyhow::anyhow!(e))?;
+            let height = Block::try_from_slice(&value)?.header().height();
+            if height <= self.cold_head_height && !self.is_canonical(&block_hash, height)? {
+                tracing::debug!(target: "cold-store", %block_hash, height, "orphaned block");
+                orphans.insert(block_hash);
+            }
+            if scanned % 1_000_000 == 0 {
+                tracing::info!(target: "cold-store", scanned, orphans = orphans.len(), "scanning blocks");
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// Collects the data of the orphaned blocks in a column keyed by the
+    /// block hash.
+    fn collect_block_data(
+        &self,
+        col: DBCol,
+        orphan_blocks: &HashSet<CryptoHash>,
+    ) -> anyhow::Result<ColumnReport> {
+        let mut deleter = Deleter::new(self, col);
+        if orphan_blocks.is_empty() {
+            return Ok(deleter.report);
+        }
+        for item in self.cold_store.iter_raw_bytes(col) {
+            let (key, value) = item?;
+            let Some(block_hash) = block_hash_of_key(col, &key) else {
+                tracing::warn!(target: "cold-store", %col, ?key, "unexpected key");
+                continue;
+            };
+            if orphan_blocks.contains(&block_hash) {
+                deleter.push(key.into_vec(), value.len())?;
+            }
+        }
+        deleter.finish()
+    }
+
+    /// Collects the chunks in a column keyed by the chunk hash which were
+    /// never included in a canonical block.  Chunks are only included in the
+    /// child of the block they were produced on, so it's enough to check the
+    /// canonical child of that block.
+    fn collect_chunks(
+        &self,
+        col: DBCol,
+        orphan_blocks: &HashSet<CryptoHash>,
+    ) -> anyhow::Result<ColumnReport> {
+        let mut deleter = Deleter::new(self, col);
+        let mut included_chunks = HashMap::new();
+        for item in self.cold_store.iter(col) {
+            let (key, value) = item?;
+            let (prev_block_hash, height_created) = match col {
+                DBCol::Chunks => {
+                    let chunk = ShardChunk::try_from_slice(&value)?;
+                    (*chunk.prev_block(), chunk.height_created())
+                }
+                DBCol::PartialChunks => {
+                    let chunk = PartialEncodedChunk::try_from_slice(&value)?;
+                    (*chunk.prev_block(), chunk.height_created())
+                }
+                _ => unreachable!(),
+            };
+            // Genesis chunks have no previous block.
+            if height_created > self.cold_head_height || prev_block_hash == CryptoHash::default() {
+                continue;
+            }
+            if included_chunks.len() >= INCLUDED_CHUNKS_CACHE_SIZE {
+                included_chunks.clear();
+            }
+            let included = match included_chunks.entry(prev_block_hash) {
+                std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(self.get_included_chunks(&prev_block_hash, orphan_blocks)?)
+                }
+            };
+            let chunk_hash =
+                ChunkHash(CryptoHash::try_from(key.as_ref()).map_err(|e| anyhow::anyhow!(e))?);
+            if included.as_ref().map_or(false, |included| !included.contains(&chunk_hash)) {
+                deleter.push(key.into_vec(), value.len())?;
+            }
+        }
+        deleter.finish()
+    }
+
+    /// Returns the chunks included in the canonical child of the given block,
+    /// or `None` if it can't be determined, in which case its chunks are kept.
+    fn get_included_chunks(
+        &self,
+        prev_block_hash: &CryptoHash,
+        orphan_blocks: &HashSet<CryptoHash>,
+    ) -> anyhow::Result<Option<HashSet<ChunkHash>>> {
+        // Nothing built on an orphaned block is canonical.
+        if orphan_blocks.contains(prev_block_hash) {
+            return Ok(Some(HashSet::new()));
+        }
+        let Some(block_hash) = self
+            .cold_store
+            .get_ser::<CryptoHash>(DBCol::NextBlockHashes, prev_block_hash.as_ref())?
+        else {
+            return Ok(None);
+        };
+        let Some(block) = self.cold_store.get_ser::<Block>(DBCol::Block, block_hash.as_ref())?
+        else {
+            return Ok(None);
+        };
+        let height = block.header().height();
+        if !self.is_canonical(&block_hash, height)? {
+            return Ok(None);
+        }
+        Ok(Some(
+            block
+                .chunks()
+                .iter()
+                .filter(|chunk| chunk.height_included() == height)
+                .map(|chunk| chunk.chunk_hash())
+                .collect(),
+        ))
+    }
+}
+
+/// Deletes the orphaned keys of a column in batches, unless running dry.
+struct Deleter<'a> {
+    collector: &'a OrphanCollector<'a>,
+    col: DBCol,
+    keys: Vec<Vec<u8>>,
+    report: ColumnReport,
+}
+
+impl<'a> Deleter<'a> {
+    fn new(collector: &'a OrphanCollector<'a>, col: DBCol) -> Self {
+        tracing::info!(target: "cold-store", %col, "collecting orphaned data");
+        Self { collector, col, keys: Vec::new(), report: ColumnReport::default() }
+    }
+
+    fn push(&mut self, key: Vec<u8>, value_len: usize) -> anyhow::Result<()> {
+        self.report.keys += 1;
+        self.report.bytes += (key.len() + value_len) as u64;
+        if !self.collector.dry_run {
+            self.keys.push(key);
+            if self.keys.len() >= self.collector.batch_size {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        if !self.keys.is_empty() {
+            self.collector.cold_db.prune(self.col, self.keys.drain(..))?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> anyhow::Result<ColumnReport> {
+        self.flush()?;
+        Ok(self.report)
+    }
+}
+
+/// Returns the hash of the block the data under `key` in one of the
+/// [`BLOCK_KEYED_COLUMNS`] belongs to.  For [`DBCol::NextBlockHashes`] that's
+/// the previous block, whose successor is irrelevant if it's orphaned.
+fn block_hash_of_key(col: DBCol, key: &[u8]) -> Option<CryptoHash> {
+    let range = match col {
+        // ShardId || BlockHash
+        DBCol::StateHeaders => 8..40,
+        // OutcomeId || BlockHash
+        DBCol::TransactionResultForBlock => 32..64,
+        _ => 0..32,
+    };
+    CryptoHash::try_from(key.get(range)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block_hash_of_key, GcOrphansCmd, BLOCK_KEYED_COLUMNS, CHUNK_KEYED_COLUMNS};
+    use borsh::BorshDeserialize;
+    use near_chain::Provenance;
+    use near_chain_configs::Genesis;
+    use near_client::test_utils::TestEnv;
+    use near_primitives::block::Block;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::sharding::{ChunkHash, PartialEncodedChunk};
+    use near_store::cold_storage::{copy_all_data_to_cold, update_cold_head};
+    use near_store::metadata::{DbKind, DB_VERSION};
+    use near_store::test_utils::create_test_node_storage_with_cold;
+    use near_store::{DBCol, Store};
+    use nearcore::test_utils::TestEnvNightshadeSetupExt;
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn chunk_hashes(store: &Store, col: DBCol) -> HashSet<ChunkHash> {
+        store
+            .iter(col)
+            .map(|item| ChunkHash(CryptoHash::try_from(item.unwrap().0.as_ref()).unwrap()))
+            .collect()
+    }
+
+    /// Produces a chain with a fork, copies all of it to the cold db like the
+    /// initial migration does and checks that the command only deletes the
+    /// fork and the chunks which never made it into a canonical block.
+    #[test]
+    fn test_gc_orphans() {
+        let mut genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+        genesis.config.epoch_length = 5;
+        let (storage, ..) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Archive);
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().unwrap();
+        let mut env = TestEnv::builder(&genesis.config)
+            .stores(vec![hot_store.clone()])
+            .nightshade_runtimes(&genesis)
+            .archive(true)
+            .build();
+
+        let mut blocks = vec![];
+        for height in 1..=4 {
+            let block = env.clients[0].produce_block(height).unwrap().unwrap();
+            env.process_block(0, block.clone(), Provenance::PRODUCED);
+            blocks.push(block);
+        }
+        // The fork at height 5 briefly becomes the head, until the canonical
+        // chain continues from height 4.
+        let fork = env.clients[0].produce_block_on(5, *blocks[2].hash()).unwrap().unwrap();
+        env.process_block(0, fork.clone(), Provenance::PRODUCED);
+        let block = env.clients[0].produce_block_on(6, *blocks[3].hash()).unwrap().unwrap();
+        env.process_block(0, block, Provenance::PRODUCED);
+        for height in 7..=10 {
+            env.produce_block(0, height);
+        }
+
+        let cold_db = storage.cold_db().unwrap();
+        let keep_going = Arc::new(AtomicBool::new(true));
+        copy_all_data_to_cold(cold_db.clone(), &hot_store, 10, usize::MAX, &keep_going).unwrap();
+        update_cold_head(cold_db, &hot_store, &10).unwrap();
+        assert!(cold_store.exists(DBCol::Block, fork.hash().as_ref()).unwrap());
+
+        // The chunks included in the canonical blocks, and the ones produced
+        // on top of the cold head which may still be included, must be kept.
+        let mut expected_chunks = HashSet::new();
+        for height in 0..=10 {
+            let Some(block_hash) =
+                hot_store.get_ser::<CryptoHash>(DBCol::BlockHeight, &height.to_le_bytes()).unwrap()
+            else {
+                continue;
+            };
+            let block =
+                cold_store.get_ser::<Block>(DBCol::Block, block_hash.as_ref()).unwrap().unwrap();
+            expected_chunks.extend(
+                block
+                    .chunks()
+                    .iter()
+                    .filter(|chunk| chunk.height_included() == height)
+                    .map(|chunk| chunk.chunk_hash()),
+            );
+        }
+        for item in cold_store.iter(DBCol::PartialChunks) {
+            let chunk = PartialEncodedChunk::try_from_slice(&item.unwrap().1).unwrap();
+            if chunk.height_created() > 10 {
+                expected_chunks.insert(chunk.chunk_hash());
+            }
+        }
+        let chunks_before = chunk_hashes(&cold_store, DBCol::PartialChunks);
+        assert!(expected_chunks.is_subset(&chunks_before));
+        assert!(chunks_before.len() > expected_chunks.len());
+
+        // A dry run doesn't delete anything.
+        GcOrphansCmd { dry_run: true, batch_size: 1, compact: false }.run(&storage, false).unwrap();
+        assert!(cold_store.exists(DBCol::Block, fork.hash().as_ref()).unwrap());
+        assert_eq!(chunk_hashes(&cold_store, DBCol::PartialChunks), chunks_before);
+
+        GcOrphansCmd { dry_run: false, batch_size: 1, compact: true }.run(&storage, true).unwrap();
+        for col in BLOCK_KEYED_COLUMNS {
+            for item in cold_store.iter_raw_bytes(col) {
+                let (key, _) = item.unwrap();
+                assert_ne!(block_hash_of_key(col, &key), Some(*fork.hash()), "{col}");
+            }
+        }
+        for block in &blocks {
+            assert!(cold_store.exists(DBCol::Block, block.hash().as_ref()).unwrap());
+        }
+        assert_eq!(chunk_hashes(&cold_store, DBCol::PartialChunks), expected_chunks);
+        assert!(chunk_hashes(&cold_store, DBCol::Chunks).is_subset(&expected_chunks));
+    }
+
+    #[test]
+    fn test_columns_are_cold() {
+        for col in BLOCK_KEYED_COLUMNS.iter().chain(CHUNK_KEYED_COLUMNS.iter()) {
+            assert!(col.is_cold(), "{}", col);
+        }
+    }
+
+    #[test]
+    fn test_block_hash_of_key() {
+        let hash = CryptoHash::hash_bytes(b"block");
+        let shard_id = 3u64.to_le_bytes();
+        let outcome_id = CryptoHash::hash_bytes(b"outcome");
+        let key = [hash.as_ref(), &shard_id].concat();
+        assert_eq!(block_hash_of_key(DBCol::ChunkExtra, &key), Some(hash));
+        let key = [&shard_id, hash.as_ref()].concat();
+        assert_eq!(block_hash_of_key(DBCol::StateHeaders, &key), Some(hash));
+        let key = [outcome_id.as_ref(), hash.as_ref()].concat();
+        assert_eq!(block_hash_of_key(DBCol::TransactionResultForBlock, &key), Some(hash));
+        assert_eq!(block_hash_of_key(DBCol::Block, &shard_id), None);
+    }
+}
@@ -1,2 +1,5 @@
+mod backfill;
 pub mod cli;
+mod gc_orphans;
+mod split_in_place;
 pub use cli::ColdStoreCommand;
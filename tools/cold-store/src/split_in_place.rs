@@ -0,0 +1,579 @@
+use crate::cli::check_iter;
+use anyhow::Context;
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_primitives::block::{Block, BlockHeader, Tip};
+use near_primitives::epoch_manager::block_info::BlockInfo;
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
+use near_primitives::state_part::PartId;
+use near_primitives::state_sync::get_num_state_parts;
+use near_primitives::types::chunk_extra::ChunkExtra;
+use near_primitives::types::BlockHeight;
+use near_store::cold_storage::{copy_block_from_cold, update_cold_head};
+use near_store::metadata::DbKind;
+use near_store::{
+    DBCol, Mode, NodeStorage, Store, StoreConfig, StoreUpdate, Trie, TrieCachingStorage,
+    TrieChanges, TrieDBStorage,
+};
+use near_store::{
+    CHUNK_TAIL_KEY, COLD_HEAD_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, HEAD_KEY, TAIL_KEY,
+};
+use nearcore::NearConfig;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use strum::IntoEnumIterator;
+
+/// Written to the home directory, so that an interrupted conversion resumes
+/// from the last finished step.
+const PROGRESS_FILENAME: &str = "split-in-place-progress.json";
+
+/// Keys in `DBCol::BlockMisc` of the hot database under which the restore
+/// steps record how far they got. They are committed together with the data
+/// they describe, as restoring the same data twice would increment the
+/// reference counts twice. Both are deleted when the hot database is
+/// finalized.
+const RESTORE_BLOCKS_KEY: &[u8] = b"SPLIT_IN_PLACE_RESTORE_BLOCKS";
+const RESTORE_STATE_KEY: &[u8] = b"SPLIT_IN_PLACE_RESTORE_STATE";
+
+/// Steps of the conversion, in order.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+enum Stage {
+    CreateCold,
+    CreateHot,
+    RestoreBlocks,
+    RestoreState,
+    Finalize,
+    Verify,
+    Done,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct Progress {
+    /// Final head of the archival database when the conversion started, which
+    /// becomes the head of the cold database.
+    cold_head_height: BlockHeight,
+    /// Head of the archival database when the conversion started. The blocks
+    /// above the final head are restored in the hot database too.
+    head_height: BlockHeight,
+    /// First height whose blocks are kept in the hot database.
+    tail_height: BlockHeight,
+    stage: Stage,
+}
+
+/// Progress of restoring the State of the hot database, see
+/// [`SplitInPlaceCmd::restore_state`].
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+enum RestoreStateProgress {
+    /// The tries before the tail are being written part by part.
+    Parts { shard_index: usize, next_part_id: u64 },
+    /// The trie changes of the blocks are being applied from this height on.
+    TrieChanges { next_height: BlockHeight },
+}
+
+#[derive(clap::Args)]
+pub(crate) struct SplitInPlaceCmd {
+    /// Path of the hot database to create, relative to the home directory.
+    /// Must be on the same file system as the archival database.
+    #[clap(long, default_value = "hot-data")]
+    hot_path: PathBuf,
+    /// Number of heights, spread evenly over the whole chain, whose blocks and
+    /// chunks are checked in the cold database by the verification pass.
+    #[clap(long, default_value_t = 10_000)]
+    verify_samples: u64,
+}
+
+impl SplitInPlaceCmd {
+    pub(crate) fn run(self, home_dir: &Path, near_config: &NearConfig) -> anyhow::Result<()> {
+        let archive_config = &near_config.config.store;
+        let cold_config = near_config.config.cold_store.as_ref().context(
+            "cold_store must be configured with the path of the cold database to create",
+        )?;
+        let archive_path =
+            home_dir.join(archive_config.path.as_deref().unwrap_or(Path::new("data")));
+        let cold_path =
+            home_dir.join(cold_config.path.as_deref().unwrap_or(Path::new("cold-data")));
+        let hot_path = home_dir.join(&self.hot_path);
+        anyhow::ensure!(
+            hot_path != archive_path && hot_path != cold_path && cold_path != archive_path,
+            "the archival, hot and cold databases must have different paths"
+        );
+        if !near_config.client_config.save_trie_changes {
+            tracing::warn!(target: "split-in-place", "save_trie_changes is disabled, it must be enabled to run with split storage");
+        }
+
+        let progress_path = home_dir.join(PROGRESS_FILENAME);
+        let mut progress = if progress_path.exists() {
+            let progress: Progress = serde_json::from_slice(&std::fs::read(&progress_path)?)?;
+            tracing::info!(target: "split-in-place", ?progress, "resuming the conversion");
+            progress
+        } else {
+            self.start(home_dir, near_config, &cold_path, &hot_path)?
+        };
+
+        let hot_config = StoreConfig { path: Some(hot_path.clone()), ..archive_config.clone() };
+        loop {
+            save_progress(&progress_path, &progress)?;
+            progress.stage = match progress.stage {
+                Stage::CreateCold => {
+                    tracing::info!(target: "split-in-place", path = %cold_path.display(), "creating the cold database");
+                    let archive_store = open_single(home_dir, archive_config, Mode::ReadOnly)?;
+                    create_checkpoint(&archive_store, &cold_path, &cold_columns())?;
+                    // The checkpoint still has the kind of the archival database
+                    // and can only be opened as a hot database until it's changed.
+                    let cold_as_hot_config =
+                        StoreConfig { path: Some(cold_path.clone()), ..cold_config.clone() };
+                    open_single(home_dir, &cold_as_hot_config, Mode::ReadWriteExisting)?
+                        .set_db_kind(DbKind::Cold)?;
+                    Stage::CreateHot
+                }
+                Stage::CreateHot => {
+                    tracing::info!(target: "split-in-place", path = %hot_path.display(), "creating the hot database");
+                    let archive_store = open_single(home_dir, archive_config, Mode::ReadOnly)?;
+                    create_checkpoint(&archive_store, &hot_path, &hot_columns())?;
+                    Stage::RestoreBlocks
+                }
+                Stage::RestoreBlocks => {
+                    let storage = open_split(home_dir, &hot_config, cold_config)?;
+                    self.restore_blocks(&storage, near_config, &progress)?;
+                    Stage::RestoreState
+                }
+                Stage::RestoreState => {
+                    let storage = open_split(home_dir, &hot_config, cold_config)?;
+                    self.restore_state(&storage, near_config, &progress)?;
+                    Stage::Finalize
+                }
+                Stage::Finalize => {
+                    let storage = open_split(home_dir, &hot_config, cold_config)?;
+                    finalize(&storage, &progress)?;
+                    Stage::Verify
+                }
+                Stage::Verify => {
+                    let storage = open_split(home_dir, &hot_config, cold_config)?;
+                    self.verify(&storage, near_config, &progress)?;
+                    Stage::Done
+                }
+                Stage::Done => break,
+            };
+        }
+
+        println!(
+            "The archival database at {} was split into the hot database at {} and the cold database at {}.",
+            archive_path.display(),
+            hot_path.display(),
+            cold_path.display()
+        );
+        println!(
+            "Set store.path to {:?} in config.json and make sure save_trie_changes is enabled. \
+             The archival database can be removed once the node runs with split storage.",
+            self.hot_path
+        );
+        Ok(())
+    }
+
+    /// Checks that the databases can be converted and returns the initial
+    /// progress.
+    fn start(
+        &self,
+        home_dir: &Path,
+        near_config: &NearConfig,
+        cold_path: &Path,
+        hot_path: &Path,
+    ) -> anyhow::Result<Progress> {
+        for path in [cold_path, hot_path] {
+            anyhow::ensure!(
+                !path.exists(),
+                "{} already exists, remove it to convert the archival database in place",
+                path.display()
+            );
+        }
+        let store = open_single(home_dir, &near_config.config.store, Mode::ReadOnly)?;
+        let kind = store.get_db_kind()?;
+        anyhow::ensure!(
+            kind == Some(DbKind::Archive),
+            "expected an archival database, got {:?}",
+            kind
+        );
+        let final_head = store
+            .get_ser::<Tip>(DBCol::BlockMisc, FINAL_HEAD_KEY)?
+            .context("the archival database has no final head")?;
+        let head = store
+            .get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?
+            .context("the archival database has no head")?;
+        let tail_height =
+            tail_height(&store, &final_head, near_config.client_config.gc.gc_num_epochs_to_keep())?;
+        tracing::info!(target: "split-in-place", cold_head_height = final_head.height, head_height = head.height, tail_height, "starting the conversion");
+        Ok(Progress {
+            cold_head_height: final_head.height,
+            head_height: head.height,
+            tail_height,
+            stage: Stage::CreateCold,
+        })
+    }
+
+    /// Copies the data of the blocks from the tail up to the head from the
+    /// cold database to the hot one. The next height to copy is committed
+    /// together with the data of every block.
+    fn restore_blocks(
+        &self,
+        storage: &NodeStorage,
+        near_config: &NearConfig,
+        progress: &Progress,
+    ) -> anyhow::Result<()> {
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().unwrap();
+        // Block infos are only in the cold database until they're restored.
+        let epoch_manager = EpochManager::new_arc_handle(
+            storage.get_split_store().unwrap(),
+            &near_config.genesis.config,
+        );
+        let next_height = hot_store
+            .get_ser::<BlockHeight>(DBCol::BlockMisc, RESTORE_BLOCKS_KEY)?
+            .unwrap_or(progress.tail_height);
+        for height in next_height..=progress.head_height {
+            let mut store_update = hot_store.store_update();
+            if let Some(block_hash) = get_block_hash(&hot_store, height)? {
+                let epoch_id = epoch_manager.get_epoch_id(&block_hash)?;
+                let shard_layout = epoch_manager.get_shard_layout(&epoch_id)?;
+                copy_block_from_cold(
+                    &cold_store,
+                    &hot_store,
+                    &shard_layout,
+                    &height,
+                    &mut store_update,
+                )?;
+            }
+            store_update.set_ser(DBCol::BlockMisc, RESTORE_BLOCKS_KEY, &(height + 1))?;
+            store_update.commit()?;
+            if height % 1000 == 0 {
+                tracing::info!(target: "split-in-place", height, head_height = progress.head_height, "restoring blocks in the hot database");
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the State of the hot database, which only has the tries of the
+    /// blocks from the tail on, like the State of a node which synced state
+    /// at the tail. The tries before the tail block are read from the cold
+    /// database and written part by part like state parts, and then the trie
+    /// changes of the blocks from the tail up to the head are applied. The
+    /// reference counts end up as if the node had applied these blocks, so
+    /// garbage collection removes the old nodes as the blocks are collected.
+    fn restore_state(
+        &self,
+        storage: &NodeStorage,
+        near_config: &NearConfig,
+        progress: &Progress,
+    ) -> anyhow::Result<()> {
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().unwrap();
+        let split_store = storage.get_split_store().unwrap();
+        let epoch_manager =
+            EpochManager::new_arc_handle(split_store.clone(), &near_config.genesis.config);
+
+        // The tries before the tail block, or after it if it's the genesis
+        // block, whose trie changes aren't stored.
+        let tail_hash =
+            get_block_hash(&hot_store, progress.tail_height)?.context("no block at the tail")?;
+        let tail_header = split_store
+            .get_ser::<BlockHeader>(DBCol::BlockHeader, tail_hash.as_ref())?
+            .context("no header of the tail block")?;
+        let (base_hash, first_changes_height) = if *tail_header.prev_hash() == CryptoHash::default()
+        {
+            (tail_hash, progress.tail_height + 1)
+        } else {
+            (*tail_header.prev_hash(), progress.tail_height)
+        };
+        let base_layout =
+            epoch_manager.get_shard_layout(&epoch_manager.get_epoch_id(&base_hash)?)?;
+        let head_hash =
+            get_block_hash(&hot_store, progress.head_height)?.context("no block at the head")?;
+        let head_layout =
+            epoch_manager.get_shard_layout(&epoch_manager.get_epoch_id(&head_hash)?)?;
+        anyhow::ensure!(
+            base_layout == head_layout,
+            "the shard layout changed between the tail and the head, wait until the resharding is garbage collected"
+        );
+        let shard_uids: Vec<_> = base_layout.shard_uids().collect();
+
+        let mut next = hot_store
+            .get_ser::<RestoreStateProgress>(DBCol::BlockMisc, RESTORE_STATE_KEY)?
+            .unwrap_or(RestoreStateProgress::Parts { shard_index: 0, next_part_id: 0 });
+        tracing::info!(target: "split-in-place", ?next, "restoring the state in the hot database");
+        while let RestoreStateProgress::Parts { shard_index, next_part_id } = next {
+            let Some(&shard_uid) = shard_uids.get(shard_index) else {
+                next = RestoreStateProgress::TrieChanges { next_height: first_changes_height };
+                break;
+            };
+            let chunk_extra = split_store
+                .get_ser::<ChunkExtra>(
+                    DBCol::ChunkExtra,
+                    &get_block_shard_uid(&base_hash, &shard_uid),
+                )?
+                .with_context(|| format!("no chunk extra of {} before the tail", shard_uid))?;
+            let state_root = *chunk_extra.state_root();
+            let trie = Trie::new(
+                Rc::new(TrieDBStorage::new(cold_store.clone(), shard_uid)),
+                state_root,
+                None,
+            );
+            let num_parts = if state_root == CryptoHash::default() {
+                0
+            } else {
+                get_num_state_parts(trie.retrieve_root_node()?.memory_usage)
+            };
+            let mut store_update = hot_store.store_update();
+            next = if next_part_id < num_parts {
+                let part_id = PartId::new(next_part_id, num_parts);
+                let nodes = trie.get_trie_nodes_for_part_without_flat_storage(part_id)?;
+                let trie_changes = Trie::apply_state_part(&state_root, part_id, nodes).trie_changes;
+                write_insertions(&mut store_update, shard_uid, &trie_changes);
+                RestoreStateProgress::Parts { shard_index, next_part_id: next_part_id + 1 }
+            } else {
+                tracing::info!(target: "split-in-place", %shard_uid, num_parts, "restored the state before the tail");
+                RestoreStateProgress::Parts { shard_index: shard_index + 1, next_part_id: 0 }
+            };
+            store_update.set_ser(DBCol::BlockMisc, RESTORE_STATE_KEY, &next)?;
+            store_update.commit()?;
+        }
+
+        let RestoreStateProgress::TrieChanges { next_height } = next else { unreachable!() };
+        for height in next_height..=progress.head_height {
+            let mut store_update = hot_store.store_update();
+            if let Some(block_hash) = get_block_hash(&hot_store, height)? {
+                for &shard_uid in &shard_uids {
+                    let key = get_block_shard_uid(&block_hash, &shard_uid);
+                    if let Some(trie_changes) =
+                        hot_store.get_ser::<TrieChanges>(DBCol::TrieChanges, &key)?
+                    {
+                        write_insertions(&mut store_update, shard_uid, &trie_changes);
+                    }
+                }
+            }
+            let next = RestoreStateProgress::TrieChanges { next_height: height + 1 };
+            store_update.set_ser(DBCol::BlockMisc, RESTORE_STATE_KEY, &next)?;
+            store_update.commit()?;
+        }
+        tracing::info!(target: "split-in-place", "restored the state in the hot database");
+        Ok(())
+    }
+
+    /// Checks that the hot and cold databases are consistent with each other
+    /// and contain the expected data.
+    fn verify(
+        &self,
+        storage: &NodeStorage,
+        near_config: &NearConfig,
+        progress: &Progress,
+    ) -> anyhow::Result<()> {
+        tracing::info!(target: "split-in-place", "verifying the hot and cold databases");
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().unwrap();
+        anyhow::ensure!(hot_store.get_db_kind()? == Some(DbKind::Hot), "hot database kind");
+        anyhow::ensure!(cold_store.get_db_kind()? == Some(DbKind::Cold), "cold database kind");
+        let cold_head = cold_store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?;
+        anyhow::ensure!(
+            cold_head.map(|tip| tip.height) == Some(progress.cold_head_height),
+            "unexpected cold head {:?}",
+            cold_head
+        );
+
+        // Everything restored in the hot database matches the cold database.
+        for col in DBCol::iter().filter(|col| col.is_cold() && *col != DBCol::State) {
+            let num_checks = check_iter(&hot_store, &cold_store, col);
+            tracing::info!(target: "split-in-place", %col, num_checks, "checked the hot database");
+        }
+        let mut errors = vec![];
+        for height in progress.tail_height..=progress.cold_head_height {
+            let Some(block_hash) = get_block_hash(&hot_store, height)? else { continue };
+            if !hot_store.exists(DBCol::Block, block_hash.as_ref())? {
+                errors.push(format!("block at height {} is missing in the hot database", height));
+            }
+        }
+
+        // Blocks and chunks over the whole chain are in the cold database.
+        let genesis_height = near_config.genesis.config.genesis_height;
+        let num_heights = progress.cold_head_height - genesis_height + 1;
+        let num_samples = self.verify_samples.min(num_heights);
+        for i in 0..num_samples {
+            let height = genesis_height + i * num_heights / num_samples;
+            let Some(block_hash) = get_block_hash(&hot_store, height)? else { continue };
+            let Some(block) = cold_store.get_ser::<Block>(DBCol::Block, block_hash.as_ref())?
+            else {
+                errors.push(format!("block at height {} is missing in the cold database", height));
+                continue;
+            };
+            for chunk in block.chunks().iter() {
+                if chunk.height_included() == height
+                    && !cold_store.exists(DBCol::Chunks, chunk.chunk_hash().as_bytes())?
+                {
+                    errors.push(format!(
+                        "chunk {:?} at height {} is missing in the cold database",
+                        chunk.chunk_hash(),
+                        height
+                    ));
+                }
+            }
+        }
+
+        // The state roots of the cold head are in both databases.
+        let head_hash = get_block_hash(&hot_store, progress.cold_head_height)?
+            .context("no block at the cold head")?;
+        let epoch_manager =
+            EpochManager::new_arc_handle(hot_store.clone(), &near_config.genesis.config);
+        let epoch_id = epoch_manager.get_epoch_id(&head_hash)?;
+        for shard_uid in epoch_manager.get_shard_layout(&epoch_id)?.shard_uids() {
+            let chunk_extra = hot_store
+                .get_ser::<ChunkExtra>(
+                    DBCol::ChunkExtra,
+                    &get_block_shard_uid(&head_hash, &shard_uid),
+                )?
+                .with_context(|| format!("no chunk extra of {} at the cold head", shard_uid))?;
+            let state_root = chunk_extra.state_root();
+            if *state_root == CryptoHash::default() {
+                continue;
+            }
+            let key = [&shard_uid.to_bytes()[..], state_root.as_ref()].concat();
+            for (name, store) in [("hot", &hot_store), ("cold", &cold_store)] {
+                if !store.exists(DBCol::State, &key)? {
+                    errors.push(format!(
+                        "state root {} of {} is missing in the {} database",
+                        state_root, shard_uid, name
+                    ));
+                }
+            }
+        }
+
+        for error in &errors {
+            tracing::error!(target: "split-in-place", "{}", error);
+        }
+        anyhow::ensure!(errors.is_empty(), "verification failed with {} errors", errors.len());
+        tracing::info!(target: "split-in-place", "verification succeeded");
+        Ok(())
+    }
+}
+
+/// Columns kept in the cold database.
+fn cold_columns() -> Vec<DBCol> {
+    DBCol::iter().filter(|col| col.is_cold()).collect()
+}
+
+/// Columns kept in the hot database. The cold columns are dropped and only the
+/// data of the recent blocks and their tries are restored from the cold
+/// database.
+fn hot_columns() -> Vec<DBCol> {
+    DBCol::iter().filter(|col| !col.is_cold()).collect()
+}
+
+/// Adds the trie nodes and values inserted by `trie_changes` to the State of
+/// the hot database.
+fn write_insertions(
+    store_update: &mut StoreUpdate,
+    shard_uid: ShardUId,
+    trie_changes: &TrieChanges,
+) {
+    for insertion in trie_changes.insertions() {
+        let key = TrieCachingStorage::get_key_from_shard_uid_and_hash(shard_uid, insertion.hash());
+        store_update.increment_refcount_by(DBCol::State, &key, insertion.payload(), insertion.rc());
+    }
+}
+
+/// Creates a checkpoint of the archival database at `path` which only keeps
+/// `columns`. The files of the checkpoint are hard links to the files of the
+/// archival database, so no data is copied.
+fn create_checkpoint(store: &Store, path: &Path, columns: &[DBCol]) -> anyhow::Result<()> {
+    // A previous attempt may have been interrupted while creating it.
+    if path.exists() {
+        std::fs::remove_dir_all(path)?;
+    }
+    store.create_checkpoint(path, Some(columns))
+}
+
+/// Sets the cold head and the tail of the hot database, and marks the hot
+/// database as finished.
+fn finalize(storage: &NodeStorage, progress: &Progress) -> anyhow::Result<()> {
+    let hot_store = storage.get_hot_store();
+    update_cold_head(storage.cold_db().unwrap(), &hot_store, &progress.cold_head_height)?;
+    let mut store_update = hot_store.store_update();
+    for key in [TAIL_KEY, CHUNK_TAIL_KEY, FORK_TAIL_KEY] {
+        store_update.set_ser(DBCol::BlockMisc, key, &progress.tail_height)?;
+    }
+    for key in [RESTORE_BLOCKS_KEY, RESTORE_STATE_KEY] {
+        store_update.delete(DBCol::BlockMisc, key);
+    }
+    store_update.commit()?;
+    // The kind is changed last, as garbage collection only considers the cold
+    // head for hot databases.
+    hot_store.set_db_kind(DbKind::Hot)?;
+    let cold_head = hot_store.get_ser::<Tip>(DBCol::BlockMisc, COLD_HEAD_KEY)?;
+    tracing::info!(target: "split-in-place", ?cold_head, tail_height = progress.tail_height, "finalized the hot database");
+    Ok(())
+}
+
+/// Returns the height of the first block of the oldest epoch kept by garbage
+/// collection at `final_head`.
+fn tail_height(
+    store: &Store,
+    final_head: &Tip,
+    num_epochs_to_keep: u64,
+) -> anyhow::Result<BlockHeight> {
+    let get_block_info = |hash: &CryptoHash| -> anyhow::Result<BlockInfo> {
+        store
+            .get_ser::<BlockInfo>(DBCol::BlockInfo, hash.as_ref())?
+            .with_context(|| format!("no block info for {}", hash))
+    };
+    let mut block_hash = final_head.last_block_hash;
+    let mut height = final_head.height;
+    for _ in 0..num_epochs_to_keep {
+        let epoch_first_block = get_block_info(get_block_info(&block_hash)?.epoch_first_block())?;
+        height = epoch_first_block.height();
+        if *epoch_first_block.prev_hash() == CryptoHash::default() {
+            break;
+        }
+        block_hash = *epoch_first_block.prev_hash();
+    }
+    Ok(height)
+}
+
+fn get_block_hash(store: &Store, height: BlockHeight) -> anyhow::Result<Option<CryptoHash>> {
+    Ok(store.get_ser::<CryptoHash>(DBCol::BlockHeight, &height.to_le_bytes())?)
+}
+
+/// Opens a single database as a hot one.
+fn open_single(home_dir: &Path, config: &StoreConfig, mode: Mode) -> anyhow::Result<Store> {
+    Ok(NodeStorage::opener(home_dir, true, config, None).open_in_mode(mode)?.get_hot_store())
+}
+
+fn open_split(
+    home_dir: &Path,
+    hot_config: &StoreConfig,
+    cold_config: &StoreConfig,
+) -> anyhow::Result<NodeStorage> {
+    Ok(NodeStorage::opener(home_dir, true, hot_config, Some(cold_config))
+        .open_in_mode(Mode::ReadWriteExisting)?)
+}
+
+fn save_progress(path: &Path, progress: &Progress) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec_pretty(progress)?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cold_columns, hot_columns};
+    use near_store::DBCol;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_columns_cover_archive() {
+        let cold_columns = cold_columns();
+        let hot_columns = hot_columns();
+        for col in DBCol::iter() {
+            assert!(cold_columns.contains(&col) || hot_columns.contains(&col), "{}", col);
+        }
+        assert!(cold_columns.contains(&DBCol::State) && !hot_columns.contains(&DBCol::State));
+        assert!(!hot_columns.contains(&DBCol::Block));
+    }
+}
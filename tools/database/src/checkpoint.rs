@@ -0,0 +1,250 @@
+use anyhow::Context;
+use near_primitives::block::Tip;
+use near_primitives::types::BlockHeight;
+use near_store::{DBCol, Mode, NodeStorage, StoreConfig, HEAD_KEY};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Name of the directory holding the checkpoints, next to each database.
+const CHECKPOINTS_DIR: &str = "checkpoints";
+
+/// Name of the file with the [`CheckpointMetadata`] in a checkpoint directory.
+const METADATA_FILE: &str = "checkpoint.json";
+
+/// Recorded when a checkpoint is created, since the modification time of the
+/// checkpoint directory changes whenever anything in it does.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+struct CheckpointMetadata {
+    /// Height of the head of the hot database.
+    height: BlockHeight,
+    /// Time of the creation in nanoseconds since the Unix epoch, which orders
+    /// the checkpoints of the same height.
+    created_at: u64,
+}
+
+#[derive(clap::Args)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub(crate) struct CheckpointCommand {
+    /// Name of the checkpoint to create.
+    #[clap(long)]
+    name: Option<String>,
+    #[clap(subcommand)]
+    subcmd: Option<CheckpointSubCommand>,
+}
+
+#[derive(clap::Subcommand)]
+enum CheckpointSubCommand {
+    /// List the checkpoints, oldest first.
+    List,
+    /// Delete a checkpoint.
+    Delete {
+        /// Name of the checkpoint to delete.
+        #[clap(long)]
+        name: String,
+    },
+}
+
+/// The checkpoints of a database are hard links to its files, so they are kept
+/// in a directory next to the database to be on the same file system.
+/// Checkpoint `name` of the hot database is at `<hot path>/../checkpoints/name/hot`
+/// and of the cold database at `<cold path>/../checkpoints/name/cold`.
+struct CheckpointDirs {
+    hot: PathBuf,
+    cold: Option<PathBuf>,
+}
+
+impl CheckpointDirs {
+    fn new(hot_path: &Path, cold_path: Option<&Path>) -> Self {
+        let checkpoints_dir =
+            |db_path: &Path| db_path.parent().unwrap_or(Path::new("")).join(CHECKPOINTS_DIR);
+        Self { hot: checkpoints_dir(hot_path), cold: cold_path.map(checkpoints_dir) }
+    }
+
+    /// Returns the names of the checkpoints, from the oldest to the newest by
+    /// the height of the head they were created at.  Checkpoints without the
+    /// metadata, e.g. interrupted ones, come first.
+    fn list(&self) -> anyhow::Result<Vec<String>> {
+        if !self.hot.exists() {
+            return Ok(vec![]);
+        }
+        let mut checkpoints = Vec::<(Option<CheckpointMetadata>, String)>::new();
+        for entry in std::fs::read_dir(&self.hot)? {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            checkpoints.push((self.read_metadata(&name), name));
+        }
+        checkpoints.sort();
+        Ok(checkpoints.into_iter().map(|(_, name)| name).collect())
+    }
+
+    fn read_metadata(&self, name: &str) -> Option<CheckpointMetadata> {
+        let path = self.hot.join(name).join(METADATA_FILE);
+        let metadata = std::fs::read(&path).ok()?;
+        serde_json::from_slice(&metadata)
+            .map_err(|err| eprintln!("Ignoring invalid {}: {err}", path.display()))
+            .ok()
+    }
+
+    fn create(&self, storage: &NodeStorage, name: &str) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !name.is_empty() && !name.contains(std::path::is_separator) && name != "..",
+            "invalid checkpoint name {name:?}"
+        );
+        anyhow::ensure!(!self.hot.join(name).exists(), "checkpoint {name} already exists");
+        std::fs::create_dir_all(self.hot.join(name))?;
+        let hot_store = storage.get_hot_store();
+        let metadata = CheckpointMetadata {
+            height: hot_store
+                .get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?
+                .map_or(0, |tip| tip.height),
+            created_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64),
+        };
+        hot_store
+            .create_checkpoint(&self.hot.join(name).join("hot"), None)
+            .context("failed to create the checkpoint of the hot database")?;
+        if let (Some(cold_store), Some(cold)) = (storage.get_cold_store(), &self.cold) {
+            std::fs::create_dir_all(cold.join(name))?;
+            cold_store
+                .create_checkpoint(&cold.join(name).join("cold"), None)
+                .context("failed to create the checkpoint of the cold database")?;
+        }
+        // Written last, so that the checkpoint is complete once it's there.
+        std::fs::write(self.hot.join(name).join(METADATA_FILE), serde_json::to_vec(&metadata)?)?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> anyhow::Result<()> {
+        let hot = self.hot.join(name);
+        anyhow::ensure!(hot.exists(), "checkpoint {name} does not exist");
+        std::fs::remove_dir_all(&hot)?;
+        if let Some(cold) = self.cold.as_ref().map(|cold| cold.join(name)) {
+            if cold.exists() {
+                std::fs::remove_dir_all(&cold)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CheckpointCommand {
+    pub(crate) fn run(
+        &self,
+        home_dir: &Path,
+        archive: bool,
+        store_config: &StoreConfig,
+        cold_store_config: Option<&StoreConfig>,
+    ) -> anyhow::Result<()> {
+        let opener = NodeStorage::opener(home_dir, archive, store_config, cold_store_config);
+        let dirs = CheckpointDirs::new(opener.path(), opener.cold_path());
+        match (&self.subcmd, &self.name) {
+            (Some(CheckpointSubCommand::List), _) => {
+                for name in dirs.list()? {
+                    println!("{name}");
+                }
+            }
+            (Some(CheckpointSubCommand::Delete { name }), _) => {
+                dirs.delete(name)?;
+                eprintln!("Deleted checkpoint {name}");
+            }
+            (None, Some(name)) => {
+                let storage = opener.open_in_mode(Mode::ReadWriteExisting)?;
+                dirs.create(&storage, name)?;
+                eprintln!("Created checkpoint {name}");
+                if let Some(checkpoints_to_keep) = store_config.checkpoints_to_keep {
+                    let checkpoints = dirs.list()?;
+                    let num_to_delete = checkpoints.len().saturating_sub(checkpoints_to_keep);
+                    for name in &checkpoints[..num_to_delete] {
+                        dirs.delete(name)?;
+                        eprintln!("Deleted old checkpoint {name}");
+                    }
+                }
+            }
+            (None, None) => anyhow::bail!("either --name or a subcommand is required"),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckpointCommand, CheckpointDirs, CheckpointSubCommand};
+    use near_primitives::block::Tip;
+    use near_primitives::hash::CryptoHash;
+    use near_store::{DBCol, Mode, NodeStorage, StoreConfig, HEAD_KEY};
+
+    fn create(name: &str) -> CheckpointCommand {
+        CheckpointCommand { name: Some(name.to_string()), subcmd: None }
+    }
+
+    fn set_head(opener: &near_store::StoreOpener, height: u64) {
+        let node_storage = opener.open().unwrap();
+        let mut store_update = node_storage.get_hot_store().store_update();
+        let tip = Tip {
+            height,
+            last_block_hash: CryptoHash::default(),
+            prev_block_hash: CryptoHash::default(),
+            epoch_id: Default::default(),
+            next_epoch_id: Default::default(),
+        };
+        store_update.set_ser(DBCol::BlockMisc, HEAD_KEY, &tip).unwrap();
+        store_update.commit().unwrap();
+    }
+
+    /// Creates checkpoints with a retention limit, and checks that the oldest
+    /// checkpoint is deleted and that the kept ones can be opened.
+    #[test]
+    fn test_checkpoints() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let mut store_config = StoreConfig::test_config();
+        store_config.checkpoints_to_keep = Some(2);
+        let opener = NodeStorage::opener(home_dir.path(), false, &store_config, None);
+        {
+            let node_storage = opener.open().unwrap();
+            let mut store_update = node_storage.get_hot_store().store_update();
+            store_update.insert(DBCol::Block, vec![0], vec![42]);
+            store_update.commit().unwrap();
+        }
+
+        for (height, name) in [(1, "a"), (2, "b")] {
+            set_head(&opener, height);
+            create(name).run(home_dir.path(), false, &store_config, None).unwrap();
+        }
+        // Touching the oldest checkpoint doesn't make it the newest one.
+        let dirs = CheckpointDirs::new(opener.path(), None);
+        std::fs::write(dirs.hot.join("a").join("note"), "").unwrap();
+        set_head(&opener, 3);
+        create("c").run(home_dir.path(), false, &store_config, None).unwrap();
+        assert!(create("c").run(home_dir.path(), false, &store_config, None).is_err());
+        assert_eq!(dirs.list().unwrap(), vec!["b", "c"]);
+
+        let checkpoint_path = dirs.hot.join("c").join("hot");
+        let checkpoint_config = StoreConfig { path: Some(checkpoint_path), ..store_config.clone() };
+        let checkpoint = NodeStorage::opener(home_dir.path(), false, &checkpoint_config, None)
+            .open_in_mode(Mode::ReadOnly)
+            .unwrap();
+        assert!(checkpoint.get_hot_store().exists(DBCol::Block, &[0]).unwrap());
+        drop(checkpoint);
+
+        let delete = CheckpointCommand {
+            name: None,
+            subcmd: Some(CheckpointSubCommand::Delete { name: "b".to_string() }),
+        };
+        delete.run(home_dir.path(), false, &store_config, None).unwrap();
+        assert_eq!(dirs.list().unwrap(), vec!["c"]);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::utils::{open_rocksdb, resolve_column};
+use clap::Parser;
+use near_store::db::{Database, RocksDB};
+use near_store::DBCol;
+use rayon::prelude::*;
+use std::path::PathBuf;
+use strum::IntoEnumIterator;
+
+#[derive(Parser)]
+pub(crate) struct ColumnStatsCommand {
+    /// If specified only this column will be analysed
+    #[arg(short, long)]
+    column: Option<String>,
+
+    /// Print the statistics as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct ColumnStats {
+    column: String,
+    entries: u64,
+    /// Total size of all keys in bytes.
+    key_bytes: u64,
+    /// Total size of all values in bytes.
+    value_bytes: u64,
+    average_value_size: f64,
+    sst_files: usize,
+}
+
+impl ColumnStats {
+    fn read(db: &RocksDB, col: DBCol, sst_files: usize) -> anyhow::Result<Self> {
+        let (mut entries, mut key_bytes, mut value_bytes) = (0u64, 0u64, 0u64);
+        for item in db.iter_raw_bytes(col) {
+            let (key, value) = item?;
+            entries += 1;
+            key_bytes += key.len() as u64;
+            value_bytes += value.len() as u64;
+        }
+        let average_value_size =
+            if entries == 0 { 0.0 } else { value_bytes as f64 / entries as f64 };
+        Ok(Self {
+            column: col.to_string(),
+            entries,
+            key_bytes,
+            value_bytes,
+            average_value_size,
+            sst_files,
+        })
+    }
+}
+
+impl ColumnStatsCommand {
+    pub(crate) fn run(&self, home: &PathBuf) -> anyhow::Result<()> {
+        let columns = match &self.column {
+            Some(column_name) => vec![resolve_column(column_name)?],
+            None => DBCol::iter().collect(),
+        };
+        let db = open_rocksdb(home, near_store::Mode::ReadOnly)?;
+        let sst_file_counts = db.sst_file_counts()?;
+        let mut stats = columns
+            .into_par_iter()
+            .map(|col| ColumnStats::read(&db, col, sst_file_counts[col]))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        // The columns taking the most space go first.
+        stats.sort_by_key(|stats| std::cmp::Reverse(stats.key_bytes + stats.value_bytes));
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+            return Ok(());
+        }
+        println!(
+            "{:<40} {:>14} {:>12} {:>12} {:>12} {:>10}",
+            "Column", "Entries", "Key bytes", "Value bytes", "Avg value", "SST files"
+        );
+        for stats in &stats {
+            println!(
+                "{:<40} {:>14} {:>12} {:>12} {:>12.1} {:>10}",
+                stats.column,
+                stats.entries,
+                bytesize::ByteSize(stats.key_bytes).to_string(),
+                bytesize::ByteSize(stats.value_bytes).to_string(),
+                stats.average_value_size,
+                stats.sst_files
+            );
+        }
+        Ok(())
+    }
+}
@@ -4,12 +4,18 @@ use crate::analyse_gas_usage::AnalyseGasUsageCommand;
 use crate::analyse_high_load::HighLoadStatsCommand;
 use crate::analyze_contract_sizes::AnalyzeContractSizesCommand;
 use crate::analyze_delayed_receipt::AnalyzeDelayedReceiptCommand;
-use crate::compact::RunCompactionCommand;
+use crate::checkpoint::CheckpointCommand;
+use crate::column_stats::ColumnStatsCommand;
+use crate::compact::{CompactColumnCommand, RunCompactionCommand};
 use crate::corrupt::CorruptStateSnapshotCommand;
+use crate::export_parquet::ExportParquetCommand;
 use crate::make_snapshot::MakeSnapshotCommand;
 use crate::memtrie::LoadMemTrieCommand;
+use crate::rebuild_flat_storage::RebuildFlatStorageCommand;
+use crate::repair::RepairCommand;
 use crate::run_migrations::RunMigrationsCommand;
 use crate::state_perf::StatePerfCommand;
+use crate::verify_split_storage::VerifySplitStorageCommand;
 use crate::write_to_db::WriteCryptoHashCommand;
 use clap::Parser;
 use std::path::PathBuf;
@@ -32,15 +38,34 @@ enum SubCommand {
     /// Change DbKind of hot or cold db.
     ChangeDbKind(ChangeDbKindCommand),
 
+    /// Create, list or delete checkpoints of the hot and cold databases
+    Checkpoint(CheckpointCommand),
+
+    /// Print entry counts, sizes and SST file counts of the columns
+    ColumnStats(ColumnStatsCommand),
+
     /// Run SST file compaction on database
     CompactDatabase(RunCompactionCommand),
 
+    /// Run SST file compaction on a range of keys of a single column
+    CompactColumn(CompactColumnCommand),
+
     /// Corrupt the state snapshot.
     CorruptStateSnapshot(CorruptStateSnapshotCommand),
 
+    /// Export the transactions, receipts or outcomes of a range of blocks to a
+    /// Parquet file, with the values decoded, for analytics
+    ExportParquet(ExportParquetCommand),
+
     /// Make snapshot of the database
     MakeSnapshot(MakeSnapshotCommand),
 
+    /// Rebuild the flat state of a single shard from the trie at its flat head
+    RebuildFlatStorage(RebuildFlatStorageCommand),
+
+    /// Repair the database after a corruption and check which heights are intact
+    Repair(RepairCommand),
+
     /// Run migrations,
     RunMigrations(RunMigrationsCommand),
 
@@ -48,6 +73,11 @@ enum SubCommand {
     /// Uses RocksDB data specified via --home argument.
     StatePerf(StatePerfCommand),
 
+    /// Check that the hot and cold databases of split storage line up: every
+    /// block up to the cold head is in the cold db, without gaps, and the hot
+    /// db keeps everything not copied yet. Prints the report as JSON
+    VerifySplitStorage(VerifySplitStorageCommand),
+
     /// Loads an in-memory trie for research purposes.
     LoadMemTrie(LoadMemTrieCommand),
     /// Write CryptoHash to DB
@@ -67,8 +97,23 @@ impl DatabaseCommand {
             SubCommand::AnalyseDataSizeDistribution(cmd) => cmd.run(home),
             SubCommand::AnalyseGasUsage(cmd) => cmd.run(home),
             SubCommand::ChangeDbKind(cmd) => cmd.run(home),
+            SubCommand::Checkpoint(cmd) => {
+                let near_config = nearcore::config::load_config(
+                    &home,
+                    near_chain_configs::GenesisValidationMode::UnsafeFast,
+                )?;
+                cmd.run(
+                    home,
+                    near_config.config.archive,
+                    &near_config.config.store,
+                    near_config.config.cold_store.as_ref(),
+                )
+            }
+            SubCommand::ColumnStats(cmd) => cmd.run(home),
             SubCommand::CompactDatabase(cmd) => cmd.run(home),
+            SubCommand::CompactColumn(cmd) => cmd.run(home),
             SubCommand::CorruptStateSnapshot(cmd) => cmd.run(home),
+            SubCommand::ExportParquet(cmd) => cmd.run(home),
             SubCommand::MakeSnapshot(cmd) => {
                 let near_config = nearcore::config::load_config(
                     &home,
@@ -77,8 +122,11 @@ impl DatabaseCommand {
                 .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
                 cmd.run(home, near_config.config.archive, &near_config.config.store)
             }
+            SubCommand::RebuildFlatStorage(cmd) => cmd.run(home),
+            SubCommand::Repair(cmd) => cmd.run(home),
             SubCommand::RunMigrations(cmd) => cmd.run(home),
             SubCommand::StatePerf(cmd) => cmd.run(home),
+            SubCommand::VerifySplitStorage(cmd) => cmd.run(home),
             SubCommand::LoadMemTrie(cmd) => cmd.run(home),
             SubCommand::WriteCryptoHash(cmd) => cmd.run(home),
             SubCommand::HighLoadStats(cmd) => cmd.run(home),
@@ -1,5 +1,6 @@
 use crate::utils::{open_rocksdb, resolve_column};
 use clap::Parser;
+use indicatif::ProgressBar;
 use near_store::db::Database;
 use std::path::PathBuf;
 
@@ -22,3 +23,102 @@ impl RunCompactionCommand {
         Ok(())
     }
 }
+
+#[derive(Parser)]
+pub(crate) struct CompactColumnCommand {
+    /// Column to compact.
+    #[arg(long)]
+    column: String,
+    /// Hex-encoded key from which to compact, inclusive. Defaults to the start of the column.
+    #[arg(long)]
+    from_key: Option<String>,
+    /// Hex-encoded key up to which to compact, exclusive. Defaults to the end of the column.
+    #[arg(long)]
+    to_key: Option<String>,
+    /// Number of parts in which the range is compacted, to report the progress.
+    #[arg(long, default_value_t = 100)]
+    steps: usize,
+}
+
+impl CompactColumnCommand {
+    pub(crate) fn run(&self, home: &PathBuf) -> anyhow::Result<()> {
+        let col = resolve_column(&self.column)?;
+        let from_key = self.from_key.as_deref().map(hex::decode).transpose()?.unwrap_or_default();
+        let to_key = self.to_key.as_deref().map(hex::decode).transpose()?;
+        if let Some(to_key) = &to_key {
+            anyhow::ensure!(from_key < *to_key, "--from-key must be smaller than --to-key");
+        }
+        anyhow::ensure!(self.steps > 0, "--steps must be positive");
+
+        let db = open_rocksdb(home, near_store::Mode::ReadWrite)?;
+        let mut boundaries = vec![from_key.clone()];
+        boundaries.extend(split_key_range(&from_key, to_key.as_deref(), self.steps));
+        let progress = ProgressBar::new(boundaries.len() as u64);
+        for (i, start) in boundaries.iter().enumerate() {
+            let end = boundaries.get(i + 1).or(to_key.as_ref());
+            db.compact_column_range(col, Some(start.as_slice()), end.map(|key| key.as_slice()))?;
+            progress.inc(1);
+        }
+        progress.finish();
+        eprintln!("Compaction of {col} is finished!");
+        Ok(())
+    }
+}
+
+/// Returns up to `steps - 1` increasing keys which split the range between
+/// `from` and `to` into parts of roughly the same number of keys, assuming the
+/// keys are uniformly distributed after their common prefix, as the hashes in
+/// most columns are.
+fn split_key_range(from: &[u8], to: Option<&[u8]>, steps: usize) -> Vec<Vec<u8>> {
+    let to_or_max = match to {
+        Some(to) => to.to_vec(),
+        None => vec![u8::MAX; from.len() + 8],
+    };
+    let prefix_len = from.iter().zip(&to_or_max).take_while(|(a, b)| a == b).count();
+    let read_u64 = |key: &[u8]| {
+        let mut bytes = [0u8; 8];
+        let tail = key.get(prefix_len..).unwrap_or_default();
+        let len = tail.len().min(8);
+        bytes[..len].copy_from_slice(&tail[..len]);
+        u64::from_be_bytes(bytes)
+    };
+    let (start, end) = (read_u64(from), read_u64(&to_or_max));
+    let mut keys: Vec<Vec<u8>> = Vec::new();
+    for i in 1..steps {
+        let offset = (end.saturating_sub(start) as u128 * i as u128 / steps as u128) as u64;
+        let mut key = to_or_max[..prefix_len].to_vec();
+        key.extend_from_slice(&(start + offset).to_be_bytes());
+        let after_previous = keys.last().map_or(key.as_slice() > from, |last| key > *last);
+        if after_previous && to.map_or(true, |to| key.as_slice() < to) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_key_range;
+
+    #[test]
+    fn test_split_key_range() {
+        // Keys of a single shard in the State column: the shard uid followed by a hash.
+        let from = [3, 0, 0, 0, 1, 0, 0, 0];
+        let to = [3, 0, 0, 0, 2, 0, 0, 0];
+        let keys = split_key_range(&from, Some(&to), 4);
+        assert_eq!(
+            keys,
+            vec![
+                vec![3, 0, 0, 0, 1, 64, 0, 0, 0, 0, 0, 0],
+                vec![3, 0, 0, 0, 1, 128, 0, 0, 0, 0, 0, 0],
+                vec![3, 0, 0, 0, 1, 192, 0, 0, 0, 0, 0, 0],
+            ]
+        );
+        assert!(keys.iter().all(|key| key.as_slice() > &from[..] && key.as_slice() < &to[..]));
+
+        let keys = split_key_range(&[], None, 2);
+        assert_eq!(keys, vec![vec![127, 255, 255, 255, 255, 255, 255, 255]]);
+
+        assert!(split_key_range(&from, Some(&to), 1).is_empty());
+    }
+}
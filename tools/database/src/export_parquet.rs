@@ -0,0 +1,456 @@
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use near_chain::{ChainStore, ChainStoreAccess};
+use near_chain_configs::GenesisValidationMode;
+use near_primitives::block::Block;
+use near_primitives::types::BlockHeight;
+use near_primitives::views::{
+    ExecutionOutcomeView, ExecutionStatusView, ReceiptEnumView, ReceiptView, SignedTransactionView,
+};
+use near_store::{Mode, NodeStorage};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser)]
+pub(crate) struct ExportParquetCommand {
+    /// Data to export.
+    #[arg(long, value_enum)]
+    column: ExportedColumn,
+    /// First height to export, inclusive.
+    #[arg(long)]
+    from_height: BlockHeight,
+    /// Last height to export, inclusive.
+    #[arg(long)]
+    to_height: BlockHeight,
+    /// Path of the Parquet file to write, `<column>.parquet` in the current
+    /// directory by default.
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Maximum number of rows in a row group of the file.
+    #[arg(long, default_value_t = 100_000)]
+    row_group_size: usize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportedColumn {
+    /// The transactions of the new chunks of the blocks.
+    Transactions,
+    /// The receipts executed in the blocks.
+    Receipts,
+    /// The outcomes of the transactions and receipts executed in the blocks.
+    Outcomes,
+}
+
+/// Type of a column of an exported table.
+#[derive(Clone, Copy)]
+enum ValueKind {
+    U64,
+    String,
+    OptionalString,
+    Bool,
+}
+
+/// A value of a row, see [`ValueKind`].
+enum Value {
+    U64(u64),
+    String(String),
+    OptionalString(Option<String>),
+    Bool(bool),
+}
+
+/// Columns shared by all the tables, identifying the block and the shard.
+const BLOCK_COLUMNS: [(&str, ValueKind); 4] = [
+    ("block_height", ValueKind::U64),
+    ("block_hash", ValueKind::String),
+    ("block_timestamp", ValueKind::U64),
+    ("shard_id", ValueKind::U64),
+];
+
+const TRANSACTION_COLUMNS: [(&str, ValueKind); 9] = [
+    ("chunk_hash", ValueKind::String),
+    ("hash", ValueKind::String),
+    ("signer_id", ValueKind::String),
+    ("public_key", ValueKind::String),
+    ("nonce", ValueKind::U64),
+    ("receiver_id", ValueKind::String),
+    ("priority_fee", ValueKind::U64),
+    // JSON array of the actions, as returned by the RPC.
+    ("actions", ValueKind::String),
+    ("signature", ValueKind::String),
+];
+
+const RECEIPT_COLUMNS: [(&str, ValueKind); 7] = [
+    ("receipt_id", ValueKind::String),
+    ("predecessor_id", ValueKind::String),
+    ("receiver_id", ValueKind::String),
+    // `action` or `data`.
+    ("kind", ValueKind::String),
+    // Signer of the transaction which created an action receipt.
+    ("signer_id", ValueKind::OptionalString),
+    ("is_promise", ValueKind::Bool),
+    // JSON of the receipt body, as returned by the RPC.
+    ("receipt", ValueKind::String),
+];
+
+const OUTCOME_COLUMNS: [(&str, ValueKind); 8] = [
+    ("id", ValueKind::String),
+    ("executor_id", ValueKind::String),
+    // `unknown`, `failure`, `success_value` or `success_receipt_id`.
+    ("status", ValueKind::String),
+    // JSON of the status, as returned by the RPC.
+    ("status_detail", ValueKind::String),
+    ("gas_burnt", ValueKind::U64),
+    // Decimal, since it doesn't fit 64 bits.
+    ("tokens_burnt", ValueKind::String),
+    // JSON arrays.
+    ("receipt_ids", ValueKind::String),
+    ("logs", ValueKind::String),
+];
+
+impl ExportParquetCommand {
+    pub(crate) fn run(&self, home: &Path) -> anyhow::Result<()> {
+        anyhow::ensure!(self.from_height <= self.to_height, "--from-height is above --to-height");
+        let near_config = nearcore::config::load_config(home, GenesisValidationMode::UnsafeFast)?;
+        let storage = NodeStorage::opener(
+            home,
+            near_config.config.archive,
+            &near_config.config.store,
+            near_config.config.cold_store.as_ref(),
+        )
+        .open_in_mode(Mode::ReadOnly)?;
+        let store = storage.get_split_store().unwrap_or_else(|| storage.get_hot_store());
+        let chain_store = ChainStore::new(store, near_config.genesis.config.genesis_height, false);
+
+        let columns = match self.column {
+            ExportedColumn::Transactions => &TRANSACTION_COLUMNS[..],
+            ExportedColumn::Receipts => &RECEIPT_COLUMNS[..],
+            ExportedColumn::Outcomes => &OUTCOME_COLUMNS[..],
+        };
+        let columns: Vec<_> = BLOCK_COLUMNS.iter().chain(columns).copied().collect();
+        let name = self.column.to_possible_value().unwrap().get_name().to_string();
+        let output =
+            self.output.clone().unwrap_or_else(|| PathBuf::from(format!("{name}.parquet")));
+        let mut writer = TableWriter::create(&output, &columns, self.row_group_size)?;
+
+        let mut rows = 0;
+        let mut missing_chunks = 0;
+        for height in self.from_height..=self.to_height {
+            let block_hash = match chain_store.get_block_hash_by_height(height) {
+                Ok(block_hash) => block_hash,
+                Err(near_chain::Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let block = chain_store
+                .get_block(&block_hash)
+                .with_context(|| format!("block at height {height} not found"))?;
+            let block_rows = match self.column {
+                ExportedColumn::Transactions => {
+                    transaction_rows(&chain_store, &block, &mut missing_chunks)?
+                }
+                ExportedColumn::Receipts => receipt_rows(&chain_store, &block)?,
+                ExportedColumn::Outcomes => outcome_rows(&chain_store, &block)?,
+            };
+            rows += block_rows.len();
+            for row in block_rows {
+                writer.push_row(row)?;
+            }
+        }
+        writer.finish()?;
+
+        eprintln!("Exported {rows} rows of {name} to {}", output.display());
+        if missing_chunks > 0 {
+            eprintln!("Skipped {missing_chunks} chunks missing from the database, the node probably doesn't track their shards");
+        }
+        Ok(())
+    }
+}
+
+fn block_values(block: &Block, shard_id: u64) -> Vec<Value> {
+    vec![
+        Value::U64(block.header().height()),
+        Value::String(block.hash().to_string()),
+        Value::U64(block.header().raw_timestamp()),
+        Value::U64(shard_id),
+    ]
+}
+
+fn transaction_rows(
+    chain_store: &ChainStore,
+    block: &Block,
+    missing_chunks: &mut u64,
+) -> anyhow::Result<Vec<Vec<Value>>> {
+    let mut rows = vec![];
+    for chunk_header in block.chunks().iter() {
+        if !chunk_header.is_new_chunk(block.header().height()) {
+            continue;
+        }
+        let Ok(chunk) = chain_store.get_chunk(&chunk_header.chunk_hash()) else {
+            *missing_chunks += 1;
+            continue;
+        };
+        for transaction in chunk.transactions() {
+            let transaction = SignedTransactionView::from(transaction.clone());
+            let mut row = block_values(block, chunk_header.shard_id());
+            row.extend([
+                Value::String(chunk_header.chunk_hash().0.to_string()),
+                Value::String(transaction.hash.to_string()),
+                Value::String(transaction.signer_id.to_string()),
+                Value::String(transaction.public_key.to_string()),
+                Value::U64(transaction.nonce),
+                Value::String(transaction.receiver_id.to_string()),
+                Value::U64(transaction.priority_fee),
+                Value::String(serde_json::to_string(&transaction.actions)?),
+                Value::String(transaction.signature.to_string()),
+            ]);
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+fn receipt_rows(chain_store: &ChainStore, block: &Block) -> anyhow::Result<Vec<Vec<Value>>> {
+    let mut rows = vec![];
+    for chunk_header in block.chunks().iter() {
+        let shard_id = chunk_header.shard_id();
+        // The executed ids are both transaction hashes and receipt ids, only
+        // the latter are found in the receipts.
+        for id in chain_store.get_outcomes_by_block_hash_and_shard_id(block.hash(), shard_id)? {
+            let Some(receipt) = chain_store.get_receipt(&id)? else {
+                continue;
+            };
+            let receipt = ReceiptView::from(receipt.as_ref().clone());
+            let (kind, signer_id, is_promise) = match &receipt.receipt {
+                ReceiptEnumView::Action { signer_id, is_promise_yield, .. } => {
+                    ("action", Some(signer_id.to_string()), *is_promise_yield)
+                }
+                ReceiptEnumView::Data { is_promise_resume, .. } => {
+                    ("data", None, *is_promise_resume)
+                }
+                ReceiptEnumView::GlobalContractDistribution { .. } => {
+                    ("global_contract_distribution", None, false)
+                }
+                ReceiptEnumView::RuntimeConfigOverridesDistribution { .. } => {
+                    ("runtime_config_overrides_distribution", None, false)
+                }
+            };
+            let mut row = block_values(block, shard_id);
+            row.extend([
+                Value::String(receipt.receipt_id.to_string()),
+                Value::String(receipt.predecessor_id.to_string()),
+                Value::String(receipt.receiver_id.to_string()),
+                Value::String(kind.to_string()),
+                Value::OptionalString(signer_id),
+                Value::Bool(is_promise),
+                Value::String(serde_json::to_string(&receipt.receipt)?),
+            ]);
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+fn outcome_rows(chain_store: &ChainStore, block: &Block) -> anyhow::Result<Vec<Vec<Value>>> {
+    let mut rows = vec![];
+    let mut outcomes: Vec<_> =
+        chain_store.get_block_execution_outcomes(block.hash())?.into_iter().collect();
+    outcomes.sort_by_key(|(shard_id, _)| *shard_id);
+    for (shard_id, outcomes) in outcomes {
+        for outcome in outcomes {
+            let id = outcome.outcome_with_id.id;
+            let outcome = ExecutionOutcomeView::from(outcome.outcome_with_id.outcome);
+            let status = match &outcome.status {
+                ExecutionStatusView::Unknown => "unknown",
+                ExecutionStatusView::Failure(_) => "failure",
+                ExecutionStatusView::SuccessValue(_) => "success_value",
+                ExecutionStatusView::SuccessReceiptId(_) => "success_receipt_id",
+            };
+            let mut row = block_values(block, shard_id);
+            row.extend([
+                Value::String(id.to_string()),
+                Value::String(outcome.executor_id.to_string()),
+                Value::String(status.to_string()),
+                Value::String(serde_json::to_string(&outcome.status)?),
+                Value::U64(outcome.gas_burnt),
+                Value::String(outcome.tokens_burnt.to_string()),
+                Value::String(serde_json::to_string(&outcome.receipt_ids)?),
+                Value::String(serde_json::to_string(&outcome.logs)?),
+            ]);
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+/// Buffered values of a column of the current row group.
+enum ColumnBuffer {
+    U64(Vec<i64>),
+    String(Vec<ByteArray>),
+    /// The present values and the definition levels of all the rows.
+    OptionalString(Vec<ByteArray>, Vec<i16>),
+    Bool(Vec<bool>),
+}
+
+/// Writes rows of a flat table to a Parquet file, in row groups of at most
+/// `row_group_size` rows.
+struct TableWriter {
+    writer: SerializedFileWriter<File>,
+    buffers: Vec<ColumnBuffer>,
+    num_rows: usize,
+    row_group_size: usize,
+}
+
+impl TableWriter {
+    fn create(
+        path: &Path,
+        columns: &[(&str, ValueKind)],
+        row_group_size: usize,
+    ) -> anyhow::Result<Self> {
+        let fields: String = columns
+            .iter()
+            .map(|(name, kind)| match kind {
+                ValueKind::U64 => format!("REQUIRED INT64 {name} (INTEGER(64,false));"),
+                ValueKind::String => format!("REQUIRED BYTE_ARRAY {name} (STRING);"),
+                ValueKind::OptionalString => format!("OPTIONAL BYTE_ARRAY {name} (STRING);"),
+                ValueKind::Bool => format!("REQUIRED BOOLEAN {name};"),
+            })
+            .collect();
+        let schema = Arc::new(parse_message_type(&format!("message schema {{ {fields} }}"))?);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let writer = SerializedFileWriter::new(file, schema, Arc::new(properties))?;
+        let buffers = columns
+            .iter()
+            .map(|(_, kind)| match kind {
+                ValueKind::U64 => ColumnBuffer::U64(vec![]),
+                ValueKind::String => ColumnBuffer::String(vec![]),
+                ValueKind::OptionalString => ColumnBuffer::OptionalString(vec![], vec![]),
+                ValueKind::Bool => ColumnBuffer::Bool(vec![]),
+            })
+            .collect();
+        Ok(Self { writer, buffers, num_rows: 0, row_group_size: row_group_size.max(1) })
+    }
+
+    fn push_row(&mut self, row: Vec<Value>) -> anyhow::Result<()> {
+        assert_eq!(row.len(), self.buffers.len());
+        for (buffer, value) in self.buffers.iter_mut().zip(row) {
+            match (buffer, value) {
+                // Unsigned integers are stored in the signed physical type
+                // with the bits unchanged, as the unsigned annotation requires.
+                (ColumnBuffer::U64(values), Value::U64(value)) => values.push(value as i64),
+                (ColumnBuffer::String(values), Value::String(value)) => {
+                    values.push(ByteArray::from(value.into_bytes()))
+                }
+                (ColumnBuffer::OptionalString(values, levels), Value::OptionalString(value)) => {
+                    levels.push(value.is_some() as i16);
+                    values.extend(value.map(|value| ByteArray::from(value.into_bytes())));
+                }
+                (ColumnBuffer::Bool(values), Value::Bool(value)) => values.push(value),
+                _ => anyhow::bail!("value doesn't match the type of its column"),
+            }
+        }
+        self.num_rows += 1;
+        if self.num_rows >= self.row_group_size {
+            self.flush_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn flush_row_group(&mut self) -> anyhow::Result<()> {
+        if self.num_rows == 0 {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group()?;
+        for buffer in &mut self.buffers {
+            let mut column = row_group.next_column()?.context("more buffers than columns")?;
+            match buffer {
+                ColumnBuffer::U64(values) => {
+                    column.typed::<Int64Type>().write_batch(values, None, None)?;
+                    values.clear();
+                }
+                ColumnBuffer::String(values) => {
+                    column.typed::<ByteArrayType>().write_batch(values, None, None)?;
+                    values.clear();
+                }
+                ColumnBuffer::OptionalString(values, levels) => {
+                    column.typed::<ByteArrayType>().write_batch(
+                        values,
+                        Some(levels.as_slice()),
+                        None,
+                    )?;
+                    values.clear();
+                    levels.clear();
+                }
+                ColumnBuffer::Bool(values) => {
+                    column.typed::<BoolType>().write_batch(values, None, None)?;
+                    values.clear();
+                }
+            }
+            column.close()?;
+        }
+        row_group.close()?;
+        self.num_rows = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> anyhow::Result<()> {
+        self.flush_row_group()?;
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TableWriter, Value, ValueKind};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+
+    /// Checks that the rows can be read back, across row groups and with null
+    /// values.
+    #[test]
+    fn test_table_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.parquet");
+        let columns = [
+            ("height", ValueKind::U64),
+            ("name", ValueKind::String),
+            ("signer", ValueKind::OptionalString),
+            ("flag", ValueKind::Bool),
+        ];
+        let mut writer = TableWriter::create(&path, &columns, 2).unwrap();
+        for i in 0..5u64 {
+            writer
+                .push_row(vec![
+                    Value::U64(u64::MAX - i),
+                    Value::String(format!("name{i}")),
+                    Value::OptionalString((i % 2 == 0).then(|| format!("signer{i}"))),
+                    Value::Bool(i % 3 == 0),
+                ])
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 3);
+        let rows: Vec<_> = reader.get_row_iter(None).unwrap().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 5);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.get_ulong(0).unwrap(), u64::MAX - i as u64);
+            assert_eq!(row.get_string(1).unwrap(), &format!("name{i}"));
+            match i % 2 {
+                0 => assert_eq!(row.get_string(2).unwrap(), &format!("signer{i}")),
+                _ => assert!(row.get_string(2).is_err()),
+            }
+            assert_eq!(row.get_bool(3).unwrap(), i % 3 == 0);
+        }
+    }
+}
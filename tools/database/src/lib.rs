@@ -5,12 +5,18 @@ mod analyse_high_load;
 mod analyze_contract_sizes;
 mod analyze_delayed_receipt;
 mod block_iterators;
+mod checkpoint;
+mod column_stats;
 pub mod commands;
 mod compact;
 mod corrupt;
+mod export_parquet;
 mod make_snapshot;
 mod memtrie;
+mod rebuild_flat_storage;
+mod repair;
 mod run_migrations;
 mod state_perf;
 mod utils;
+mod verify_split_storage;
 mod write_to_db;
@@ -0,0 +1,139 @@
+use anyhow::Context;
+use clap::Parser;
+use near_chain_configs::GenesisValidationMode;
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_primitives::block::Tip;
+use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
+use near_primitives::state::FlatStateValue;
+use near_primitives::types::chunk_extra::ChunkExtra;
+use near_primitives::types::{ShardId, StateRoot};
+use near_store::flat::{
+    store_helper, FlatStorageReadyStatus, FlatStorageStatus, STATE_PART_MEMORY_LIMIT,
+};
+use near_store::{
+    DBCol, Mode, NodeStorage, Store, Trie, TrieDBStorage, TrieTraversalItem, HEAD_KEY,
+};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Rebuilds the flat state of a single shard from the trie at its flat head,
+/// e.g. after the flat state got corrupted.  The other shards and the deltas
+/// above the flat head, which are relative to the flat head, are left as
+/// they are, so the node can be restarted right after.
+///
+/// The flat storage of the shard is marked as empty while the flat state is
+/// rebuilt, so if the command is interrupted the node creates the flat
+/// storage from scratch on the next start.
+#[derive(Parser)]
+pub(crate) struct RebuildFlatStorageCommand {
+    /// Shard to rebuild, in the shard layout of the chain head.
+    #[arg(long)]
+    shard_id: ShardId,
+
+    /// Number of threads reading the trie.
+    #[arg(long, default_value = "8")]
+    num_threads: usize,
+}
+
+impl RebuildFlatStorageCommand {
+    pub(crate) fn run(&self, home: &Path) -> anyhow::Result<()> {
+        let near_config = nearcore::config::load_config(home, GenesisValidationMode::UnsafeFast)?;
+        let storage = NodeStorage::opener(
+            home,
+            near_config.config.archive,
+            &near_config.config.store,
+            near_config.config.cold_store.as_ref(),
+        )
+        .open_in_mode(Mode::ReadWriteExisting)?;
+        let store = storage.get_hot_store();
+        let head = store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?.context("no chain head")?;
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+        let shard_uid = epoch_manager.shard_id_to_uid(self.shard_id, &head.epoch_id)?;
+
+        let status = store_helper::get_flat_storage_status(&store, shard_uid)?;
+        let FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head }) = status else {
+            anyhow::bail!(
+                "the flat storage of shard {shard_uid} is not ready ({status:?}), the node creates it on start"
+            );
+        };
+        let chunk_extra = store
+            .get_ser::<ChunkExtra>(
+                DBCol::ChunkExtra,
+                &get_block_shard_uid(&flat_head.hash, &shard_uid),
+            )?
+            .with_context(|| format!("no chunk extra of shard {shard_uid} at the flat head"))?;
+        let state_root = *chunk_extra.state_root();
+
+        eprintln!(
+            "Rebuilding the flat state of shard {shard_uid} at height {} from state root {state_root}...",
+            flat_head.height
+        );
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Empty,
+        );
+        store_helper::remove_all_flat_state_values(&mut store_update, shard_uid);
+        store_update.commit()?;
+
+        let num_values = write_flat_state(&store, shard_uid, state_root, self.num_threads)?;
+
+        let mut store_update = store.store_update();
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head }),
+        );
+        store_update.commit()?;
+        eprintln!("Rebuilt the flat state of shard {shard_uid} with {num_values} values");
+        Ok(())
+    }
+}
+
+/// Writes the values of the trie to the flat state, reading the state parts
+/// of the trie in parallel.  Returns the number of values written.
+fn write_flat_state(
+    store: &Store,
+    shard_uid: ShardUId,
+    state_root: StateRoot,
+    num_threads: usize,
+) -> anyhow::Result<u64> {
+    let trie = Trie::new(Rc::new(TrieDBStorage::new(store.clone(), shard_uid)), state_root, None);
+    let num_parts = trie.retrieve_root_node()?.memory_usage / STATE_PART_MEMORY_LIMIT.as_u64() + 1;
+    let num_values = AtomicU64::new(0);
+    let num_done_parts = AtomicU64::new(0);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+    pool.install(|| {
+        (0..num_parts).into_par_iter().try_for_each(|part_id| -> anyhow::Result<()> {
+            let trie =
+                Trie::new(Rc::new(TrieDBStorage::new(store.clone(), shard_uid)), state_root, None);
+            let path_begin = trie.find_state_part_boundary(part_id, num_parts)?;
+            let path_end = trie.find_state_part_boundary(part_id + 1, num_parts)?;
+            let mut store_update = store.store_update();
+            let mut num_part_values = 0;
+            for item in trie.disk_iter()?.visit_nodes_interval(&path_begin, &path_end)? {
+                let TrieTraversalItem { hash, key } = item;
+                if let Some(key) = key {
+                    let value = trie.retrieve_value(&hash)?;
+                    store_helper::set_flat_state_value(
+                        &mut store_update,
+                        shard_uid,
+                        key,
+                        Some(FlatStateValue::on_disk(&value)),
+                    );
+                    num_part_values += 1;
+                }
+            }
+            store_update.commit()?;
+            num_values.fetch_add(num_part_values, Ordering::Relaxed);
+            let num_done_parts = num_done_parts.fetch_add(1, Ordering::Relaxed) + 1;
+            eprintln!("Wrote {num_done_parts}/{num_parts} state parts");
+            Ok(())
+        })
+    })?;
+    Ok(num_values.into_inner())
+}
@@ -0,0 +1,145 @@
+use anyhow::Context;
+use clap::Parser;
+use near_chain::{ChainStore, ChainStoreAccess, Error};
+use near_chain_configs::GenesisValidationMode;
+use near_primitives::types::BlockHeight;
+use near_store::db::RocksDB;
+use near_store::{DBCol, Mode, NodeStorage};
+use std::path::Path;
+
+/// Maximum number of corrupted heights printed individually.
+const MAX_PRINTED_HEIGHTS: usize = 100;
+
+#[derive(Parser)]
+pub(crate) struct RepairCommand {
+    /// Only check the chain invariants, without running the RocksDB repair.
+    #[arg(long)]
+    check_only: bool,
+
+    /// Check only this many latest heights instead of all heights since the tail.
+    #[arg(long)]
+    last_blocks: Option<u64>,
+}
+
+/// Outcome of checking the blocks at a single height.
+enum HeightStatus {
+    /// Nothing to check, the height was skipped.
+    Skipped,
+    Ok,
+    /// Some chunks are stored neither in full nor as partial chunks.
+    MissingChunks(usize),
+    Corrupted(String),
+}
+
+impl RepairCommand {
+    pub(crate) fn run(&self, home: &Path) -> anyhow::Result<()> {
+        let near_config = nearcore::config::load_config(home, GenesisValidationMode::UnsafeFast)?;
+        let opener = NodeStorage::opener(
+            home,
+            near_config.config.archive,
+            &near_config.config.store,
+            near_config.config.cold_store.as_ref(),
+        );
+
+        if !self.check_only {
+            eprintln!("Repairing {}...", opener.path().display());
+            RocksDB::repair(opener.path(), &near_config.config.store)?;
+            if let (Some(path), Some(config)) =
+                (opener.cold_path(), near_config.config.cold_store.as_ref())
+            {
+                eprintln!("Repairing {}...", path.display());
+                RocksDB::repair(path, config)?;
+            }
+        }
+
+        let storage = opener.open_in_mode(Mode::ReadOnly)?;
+        let store = storage.get_split_store().unwrap_or_else(|| storage.get_hot_store());
+        let chain_store =
+            ChainStore::new(store.clone(), near_config.genesis.config.genesis_height, false);
+
+        let head = chain_store.head().context("the chain head is missing or corrupted")?;
+        chain_store
+            .get_block(&head.last_block_hash)
+            .context("the block of the chain head is missing or corrupted")?;
+        let tail = chain_store.tail().context("the chain tail is missing or corrupted")?;
+        let from_height = match self.last_blocks {
+            Some(last_blocks) => tail.max(head.height.saturating_sub(last_blocks)),
+            None => tail,
+        };
+        eprintln!("Checking heights {from_height} to {}...", head.height);
+
+        let mut corrupted = Vec::<(BlockHeight, String)>::new();
+        let mut num_missing_chunks = 0;
+        for height in from_height..=head.height {
+            match check_height(&chain_store, &store, height) {
+                HeightStatus::Skipped | HeightStatus::Ok => {}
+                HeightStatus::MissingChunks(count) => num_missing_chunks += count,
+                HeightStatus::Corrupted(reason) => corrupted.push((height, reason)),
+            }
+        }
+
+        if num_missing_chunks > 0 {
+            println!(
+                "{num_missing_chunks} chunks are missing, which is expected for the shards the node doesn't track"
+            );
+        }
+        let Some((first_corrupted, _)) = corrupted.first() else {
+            println!("All heights from {from_height} to {} are intact", head.height);
+            return Ok(());
+        };
+        for (height, reason) in corrupted.iter().take(MAX_PRINTED_HEIGHTS) {
+            println!("Height {height}: {reason}");
+        }
+        if corrupted.len() > MAX_PRINTED_HEIGHTS {
+            println!("... and {} more", corrupted.len() - MAX_PRINTED_HEIGHTS);
+        }
+        if *first_corrupted > from_height {
+            println!(
+                "Heights {from_height} to {} are intact, the node can be recovered by undoing the blocks above them with `neard undo-block`",
+                first_corrupted - 1
+            );
+        } else {
+            println!("No height is intact, the node has to be synced from scratch");
+        }
+        anyhow::bail!("{} heights are corrupted", corrupted.len())
+    }
+}
+
+fn check_height(
+    chain_store: &ChainStore,
+    store: &near_store::Store,
+    height: BlockHeight,
+) -> HeightStatus {
+    let block_hash = match chain_store.get_block_hash_by_height(height) {
+        Ok(block_hash) => block_hash,
+        Err(Error::DBNotFoundErr(_)) => return HeightStatus::Skipped,
+        Err(err) => return HeightStatus::Corrupted(format!("block height index: {err}")),
+    };
+    if let Err(err) = chain_store.get_block_header(&block_hash) {
+        return HeightStatus::Corrupted(format!("header of block {block_hash}: {err}"));
+    }
+    let block = match chain_store.get_block(&block_hash) {
+        Ok(block) => block,
+        Err(err) => return HeightStatus::Corrupted(format!("block {block_hash}: {err}")),
+    };
+    let mut num_missing_chunks = 0;
+    for chunk_header in block.chunks().iter() {
+        if !chunk_header.is_new_chunk(height) {
+            continue;
+        }
+        let chunk_hash = chunk_header.chunk_hash();
+        let exists = store.exists(DBCol::Chunks, chunk_hash.as_ref()).and_then(|exists| {
+            Ok(exists || store.exists(DBCol::PartialChunks, chunk_hash.as_ref())?)
+        });
+        match exists {
+            Ok(true) => {}
+            Ok(false) => num_missing_chunks += 1,
+            Err(err) => return HeightStatus::Corrupted(format!("chunk {chunk_hash:?}: {err}")),
+        }
+    }
+    if num_missing_chunks > 0 {
+        HeightStatus::MissingChunks(num_missing_chunks)
+    } else {
+        HeightStatus::Ok
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::Context;
+use clap::Parser;
+use near_chain_configs::GenesisValidationMode;
+use near_primitives::block::{Block, Tip};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+use near_store::{DBCol, Mode, NodeStorage, Store};
+use near_store::{COLD_HEAD_KEY, COLD_TAIL_KEY, FINAL_HEAD_KEY, HEAD_KEY, TAIL_KEY};
+use std::path::Path;
+use strum::IntoEnumIterator;
+
+/// Maximum number of gaps listed individually in the report.
+const MAX_REPORTED_GAPS: usize = 1000;
+
+/// Cold columns which must have an entry for every block up to the cold head.
+/// `NextBlockHashes` is not set for the cold head itself.
+const PER_BLOCK_COLUMNS: [DBCol; 4] =
+    [DBCol::Block, DBCol::BlockInfo, DBCol::BlockExtra, DBCol::NextBlockHashes];
+
+#[derive(Parser)]
+pub(crate) struct VerifySplitStorageCommand {
+    /// First height to check, the genesis height or the cold tail of a pruned
+    /// cold db by default.
+    #[arg(long)]
+    from_height: Option<BlockHeight>,
+}
+
+/// Result of the verification, printed as JSON.
+#[derive(serde::Serialize, Default)]
+struct Report {
+    hot_head: Option<BlockHeight>,
+    hot_final_head: Option<BlockHeight>,
+    hot_tail: Option<BlockHeight>,
+    /// Cold head as recorded in the cold db.
+    cold_head: Option<BlockHeight>,
+    /// Cold head as recorded in the hot db.
+    hot_cold_head: Option<BlockHeight>,
+    /// Problems with how the heads and tails of the databases line up.
+    boundary_errors: Vec<String>,
+    /// Number of canonical blocks checked in the cold db.
+    checked_blocks: u64,
+    /// Ranges of canonical blocks missing from the cold db.
+    num_gaps: usize,
+    gaps: Vec<HeightRange>,
+    columns: Vec<ColumnReport>,
+    /// New chunks of the checked blocks stored neither in full nor as partial
+    /// chunks, which is expected for the shards the node doesn't track.
+    missing_chunks: u64,
+    ok: bool,
+}
+
+#[derive(serde::Serialize, Clone, Copy)]
+struct HeightRange {
+    start: BlockHeight,
+    end: BlockHeight,
+}
+
+#[derive(serde::Serialize)]
+struct ColumnReport {
+    #[serde(skip)]
+    col: DBCol,
+    column: String,
+    /// Whether the column has no entries at all in the cold db.
+    empty: bool,
+    /// Number of checked blocks without an entry in the column.
+    missing_blocks: u64,
+    first_missing_height: Option<BlockHeight>,
+}
+
+impl VerifySplitStorageCommand {
+    pub(crate) fn run(&self, home: &Path) -> anyhow::Result<()> {
+        let near_config = nearcore::config::load_config(home, GenesisValidationMode::UnsafeFast)?;
+        let storage = NodeStorage::opener(
+            home,
+            near_config.config.archive,
+            &near_config.config.store,
+            near_config.config.cold_store.as_ref(),
+        )
+        .open_in_mode(Mode::ReadOnly)?;
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().context("the node has no split storage")?;
+
+        let mut report = Report::default();
+        check_boundaries(&hot_store, &cold_store, &mut report)?;
+        if let Some(cold_head) = report.cold_head {
+            let cold_tail = cold_store.get_ser::<BlockHeight>(DBCol::BlockMisc, COLD_TAIL_KEY)?;
+            let genesis_height = near_config.genesis.config.genesis_height;
+            let from_height = self.from_height.unwrap_or(cold_tail.unwrap_or(genesis_height));
+            eprintln!("Checking heights {from_height} to {cold_head}...");
+            check_blocks(&hot_store, &cold_store, from_height, cold_head, &mut report)?;
+        }
+        report.ok = report.boundary_errors.is_empty()
+            && report.num_gaps == 0
+            && report.columns.iter().all(|column| column.missing_blocks == 0);
+
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        for error in &report.boundary_errors {
+            eprintln!("{error}");
+        }
+        anyhow::ensure!(report.ok, "the split storage is inconsistent");
+        eprintln!("The split storage is consistent");
+        Ok(())
+    }
+}
+
+/// Checks that the hot db keeps every block which is not in the cold db yet.
+fn check_boundaries(
+    hot_store: &Store,
+    cold_store: &Store,
+    report: &mut Report,
+) -> anyhow::Result<()> {
+    let height = |store: &Store, key: &[u8]| -> anyhow::Result<Option<BlockHeight>> {
+        Ok(store.get_ser::<Tip>(DBCol::BlockMisc, key)?.map(|tip| tip.height))
+    };
+    report.hot_head = height(hot_store, HEAD_KEY)?;
+    report.hot_final_head = height(hot_store, FINAL_HEAD_KEY)?;
+    report.hot_tail = hot_store.get_ser::<BlockHeight>(DBCol::BlockMisc, TAIL_KEY)?;
+    report.cold_head = height(cold_store, COLD_HEAD_KEY)?;
+    report.hot_cold_head = height(hot_store, COLD_HEAD_KEY)?;
+
+    let errors = &mut report.boundary_errors;
+    let Some(cold_head) = report.cold_head else {
+        errors.push("the cold db has no head, it was never populated".to_string());
+        return Ok(());
+    };
+    if report.hot_cold_head != Some(cold_head) {
+        errors.push(format!(
+            "the cold head recorded in the hot db ({:?}) differs from the cold db ({cold_head})",
+            report.hot_cold_head
+        ));
+    }
+    if let Some(final_head) = report.hot_final_head {
+        if cold_head > final_head {
+            errors.push(format!(
+                "the cold head ({cold_head}) is above the final head of the hot db ({final_head})"
+            ));
+        }
+    }
+    if let Some(tail) = report.hot_tail {
+        if tail > cold_head + 1 {
+            errors.push(format!(
+                "heights {} to {} were garbage collected from the hot db before being copied to the cold db",
+                cold_head + 1,
+                tail - 1
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the data of every canonical block up to the cold head is in the
+/// cold db.  The canonical chain is read from `BlockHeight` in the hot db,
+/// which is never garbage collected.
+fn check_blocks(
+    hot_store: &Store,
+    cold_store: &Store,
+    from_height: BlockHeight,
+    cold_head: BlockHeight,
+    report: &mut Report,
+) -> anyhow::Result<()> {
+    let mut columns: Vec<ColumnReport> = DBCol::iter()
+        .filter(DBCol::is_cold)
+        .map(|col| {
+            Ok(ColumnReport {
+                col,
+                column: col.to_string(),
+                empty: cold_store.iter(col).next().transpose()?.is_none(),
+                missing_blocks: 0,
+                first_missing_height: None,
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let mut gap: Option<HeightRange> = None;
+    for height in from_height..=cold_head {
+        let Some(block_hash) =
+            hot_store.get_ser::<CryptoHash>(DBCol::BlockHeight, &height.to_le_bytes())?
+        else {
+            continue;
+        };
+        report.checked_blocks += 1;
+        for col in PER_BLOCK_COLUMNS {
+            if col == DBCol::NextBlockHashes && height == cold_head {
+                continue;
+            }
+            if !cold_store.exists(col, block_hash.as_ref())? {
+                let column = columns.iter_mut().find(|column| column.col == col).unwrap();
+                column.missing_blocks += 1;
+                column.first_missing_height.get_or_insert(height);
+            }
+        }
+
+        let Some(block) = cold_store.get_ser::<Block>(DBCol::Block, block_hash.as_ref())? else {
+            match &mut gap {
+                Some(gap) => gap.end = height,
+                None => gap = Some(HeightRange { start: height, end: height }),
+            }
+            continue;
+        };
+        if let Some(gap) = gap.take() {
+            report.record_gap(gap);
+        }
+        for chunk_header in block.chunks().iter() {
+            if !chunk_header.is_new_chunk(height) {
+                continue;
+            }
+            let chunk_hash = chunk_header.chunk_hash();
+            if !cold_store.exists(DBCol::Chunks, chunk_hash.as_ref())?
+                && !cold_store.exists(DBCol::PartialChunks, chunk_hash.as_ref())?
+            {
+                report.missing_chunks += 1;
+            }
+        }
+    }
+    if let Some(gap) = gap {
+        report.record_gap(gap);
+    }
+    report.columns = columns;
+    Ok(())
+}
+
+impl Report {
+    fn record_gap(&mut self, gap: HeightRange) {
+        self.num_gaps += 1;
+        if self.gaps.len() < MAX_REPORTED_GAPS {
+            self.gaps.push(gap);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_blocks, check_boundaries, Report};
+    use near_primitives::block::Tip;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::types::{BlockHeight, EpochId};
+    use near_store::metadata::{DbKind, DB_VERSION};
+    use near_store::test_utils::create_test_node_storage_with_cold;
+    use near_store::{DBCol, Store, COLD_HEAD_KEY, TAIL_KEY};
+
+    fn set_cold_head(store: &Store, height: BlockHeight) {
+        let tip = Tip {
+            height,
+            last_block_hash: CryptoHash::default(),
+            prev_block_hash: CryptoHash::default(),
+            epoch_id: EpochId::default(),
+            next_epoch_id: EpochId::default(),
+        };
+        let mut store_update = store.store_update();
+        store_update.set_ser(DBCol::BlockMisc, COLD_HEAD_KEY, &tip).unwrap();
+        store_update.commit().unwrap();
+    }
+
+    /// Checks that a hot tail above the cold head and blocks missing from the
+    /// cold db are reported.
+    #[test]
+    fn test_inconsistent_split_storage() {
+        let (storage, _, _) = create_test_node_storage_with_cold(DB_VERSION, DbKind::Hot);
+        let hot_store = storage.get_hot_store();
+        let cold_store = storage.get_cold_store().unwrap();
+        set_cold_head(&hot_store, 10);
+        set_cold_head(&cold_store, 10);
+        let mut store_update = hot_store.store_update();
+        store_update.set_ser(DBCol::BlockMisc, TAIL_KEY, &15u64).unwrap();
+        for height in 8..=10u64 {
+            let block_hash = CryptoHash::hash_bytes(&height.to_le_bytes());
+            store_update.set_ser(DBCol::BlockHeight, &height.to_le_bytes(), &block_hash).unwrap();
+        }
+        store_update.commit().unwrap();
+
+        let mut report = Report::default();
+        check_boundaries(&hot_store, &cold_store, &mut report).unwrap();
+        assert_eq!(report.cold_head, Some(10));
+        assert_eq!(report.hot_cold_head, Some(10));
+        assert_eq!(
+            report.boundary_errors,
+            vec!["heights 11 to 14 were garbage collected from the hot db before being copied to the cold db"]
+        );
+
+        check_blocks(&hot_store, &cold_store, 0, 10, &mut report).unwrap();
+        assert_eq!(report.checked_blocks, 3);
+        assert_eq!(report.num_gaps, 1);
+        assert_eq!((report.gaps[0].start, report.gaps[0].end), (8, 10));
+        let block = report.columns.iter().find(|column| column.col == DBCol::Block).unwrap();
+        assert_eq!((block.missing_blocks, block.first_missing_height), (3, Some(8)));
+        let next = report.columns.iter().find(|column| column.col == DBCol::NextBlockHashes);
+        assert_eq!(next.unwrap().missing_blocks, 2);
+    }
+}
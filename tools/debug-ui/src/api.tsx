@@ -27,6 +27,26 @@ export interface StatusSyncInfo {
     latest_block_time: string;
     latest_state_root: string;
     syncing: boolean;
+    state_sync?: StateSyncProgressView;
+}
+
+export interface StateSyncProgressView {
+    sync_hash: string;
+    catchup: boolean;
+    shards: ShardStateSyncProgressView[];
+}
+
+export interface ShardStateSyncProgressView {
+    shard_id: number;
+    phase: 'Header' | 'Parts' | 'Apply' | 'Finalize' | 'Resharding' | 'Done';
+    num_parts_done: number;
+    num_parts_resumed: number;
+    num_parts_total: number | null;
+    bytes_downloaded: number;
+    bytes_per_second: number;
+    elapsed_secs: number;
+    eta_secs: number | null;
+    last_error: string | null;
 }
 
 export interface ValidatorInfo {
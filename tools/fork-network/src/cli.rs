@@ -14,27 +14,36 @@ use near_parameters::{RuntimeConfig, RuntimeConfigStore};
 use near_primitives::account::id::AccountType;
 use near_primitives::account::{AccessKey, AccessKeyPermission, Account};
 use near_primitives::borsh;
-use near_primitives::hash::CryptoHash;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::receipt::PromiseYieldTimeout;
 use near_primitives::serialize::dec_format;
-use near_primitives::shard_layout::ShardUId;
+use near_primitives::shard_layout::{account_id_to_shard_uid, ShardLayout, ShardUId};
 use near_primitives::state::FlatStateValue;
 use near_primitives::state_record::StateRecord;
-use near_primitives::trie_key::col;
 use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_account_key;
+use near_primitives::trie_key::{col, TrieKey};
 use near_primitives::types::{
-    AccountId, AccountInfo, Balance, BlockHeight, EpochId, NumBlocks, ShardId, StateRoot,
+    AccountId, AccountInfo, Balance, BlockHeight, EpochId, NumBlocks, ShardId, StateChangeCause,
+    StateRoot,
 };
 use near_primitives::version::{ProtocolVersion, PROTOCOL_VERSION};
 use near_store::db::RocksDB;
-use near_store::flat::{store_helper, BlockInfo, FlatStorageManager, FlatStorageStatus};
+use near_store::flat::{
+    store_helper, BlockInfo, FlatStateChanges, FlatStorageManager, FlatStorageReadyStatus,
+    FlatStorageStatus,
+};
+use near_store::trie::receipts_column_helper::{
+    DelayedReceiptQueue, PriorityDelayedReceiptQueue, ShardsOutgoingReceiptBuffer, TrieQueue,
+};
 use near_store::{
-    checkpoint_hot_storage_and_cleanup_columns, DBCol, Store, TrieDBStorage, TrieStorage,
-    FINAL_HEAD_KEY,
+    checkpoint_hot_storage_and_cleanup_columns, enqueue_promise_yield_timeout, get,
+    get_promise_yield_indices, set_promise_yield_indices, DBCol, Store, Trie, TrieDBStorage,
+    TrieStorage, FINAL_HEAD_KEY,
 };
 use nearcore::{load_config, open_storage, NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::Deserialize;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
@@ -45,6 +54,7 @@ use strum::IntoEnumIterator;
 /// Use the following sub-commands:
 /// * init
 /// * amend-access-keys
+/// * set-shard-layout (optional)
 /// * set-validators
 /// * finalize
 ///
@@ -65,6 +75,10 @@ enum SubCommand {
     /// Updates the state to ensure every account has a full access key that is known to us.
     AmendAccessKeys(AmendAccessKeysCmd),
 
+    /// Moves the state to the shards of a different shard layout.
+    /// The new layout is then used by `set-validators` for the new genesis.
+    SetShardLayout(SetShardLayoutCmd),
+
     /// Creates a DB snapshot, then
     /// Reads a list of validator accounts from a file
     /// Adds validator accounts to the state
@@ -91,6 +105,18 @@ struct AmendAccessKeysCmd {
     batch_size: u64,
 }
 
+#[derive(clap::Parser)]
+struct SetShardLayoutCmd {
+    /// Path to the JSON serialized [`ShardLayout`] of the forked network.
+    /// The path can be relative to `home_dir` or an absolute path.
+    /// The version of the layout must differ from the version of the
+    /// current layout, otherwise the new shards would overwrite the old ones.
+    #[arg(short, long)]
+    pub shard_layout: PathBuf,
+    #[arg(short, long, default_value = "2000000")]
+    batch_size: u64,
+}
+
 #[derive(clap::Parser)]
 struct SetValidatorsCmd {
     /// Path to the JSON list of [`Validator`] structs containing account id and public keys.
@@ -115,6 +141,19 @@ struct SetValidatorsCmd {
     /// will be used.
     #[arg(long)]
     pub protocol_version: Option<ProtocolVersion>,
+    /// Path to a CSV or JSON file of [`NewAccount`] structs, with accounts to create or
+    /// modify in addition to the validators. The format is chosen by the file extension.
+    /// The paths can be relative to `home_dir` or absolute paths.
+    /// Accounts which don't exist are created with 10k tokens unless an amount is set.
+    /// Example of a valid CSV file:
+    /// account_id,amount,public_key,code
+    /// user0.test,1000000000000000000000000,ed25519:7PGseFbWxvYVgZ89K1uTJKYoKetWs7BJtbyXDzfbAcqX,
+    /// token.test,,ed25519:7PGseFbWxvYVgZ89K1uTJKYoKetWs7BJtbyXDzfbAcqX,res/token.wasm
+    #[arg(long)]
+    pub accounts: Option<PathBuf>,
+    /// Number of changes after which the accounts are committed to the state.
+    #[arg(long, default_value = "2000000")]
+    pub batch_size: u64,
 }
 
 #[derive(clap::Parser)]
@@ -128,6 +167,29 @@ struct Validator {
     amount: Option<Balance>,
 }
 
+/// Account created or modified by `set-validators --accounts`.
+#[derive(Deserialize)]
+struct NewAccount {
+    account_id: AccountId,
+    /// Sets the liquid balance of the account.
+    #[serde(default, with = "dec_format")]
+    amount: Option<Balance>,
+    /// Adds a full access key to the account.
+    public_key: Option<PublicKey>,
+    /// Path to the contract to deploy to the account.
+    code: Option<PathBuf>,
+}
+
+/// Row of a CSV accounts file. The amount is parsed separately, because the
+/// CSV reader doesn't read balances as strings.
+#[derive(Deserialize)]
+struct NewAccountRow {
+    account_id: AccountId,
+    amount: Option<String>,
+    public_key: Option<PublicKey>,
+    code: Option<PathBuf>,
+}
+
 type MakeSingleShardStorageMutatorFn =
     Arc<dyn Fn(StateRoot) -> anyhow::Result<SingleShardStorageMutator> + Send + Sync>;
 
@@ -183,12 +245,17 @@ impl ForkNetworkCommand {
             SubCommand::AmendAccessKeys(AmendAccessKeysCmd { batch_size }) => {
                 self.amend_access_keys(*batch_size, near_config, home_dir)?;
             }
+            SubCommand::SetShardLayout(SetShardLayoutCmd { shard_layout, batch_size }) => {
+                self.set_shard_layout(shard_layout, *batch_size, near_config, home_dir)?;
+            }
             SubCommand::SetValidators(SetValidatorsCmd {
                 genesis_time,
                 protocol_version,
                 validators,
                 epoch_length,
                 chain_id_suffix,
+                accounts,
+                batch_size,
             }) => {
                 self.set_validators(
                     genesis_time.unwrap_or_else(chrono::Utc::now),
@@ -196,6 +263,8 @@ impl ForkNetworkCommand {
                     validators,
                     *epoch_length,
                     chain_id_suffix,
+                    accounts.as_deref(),
+                    *batch_size,
                     near_config,
                     home_dir,
                 )?;
@@ -325,10 +394,9 @@ impl ForkNetworkCommand {
 
         let epoch_manager =
             EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
-        let num_shards = prev_state_roots.len();
-        let all_shard_uids: Vec<ShardUId> = (0..num_shards)
-            .map(|shard_id| epoch_manager.shard_id_to_uid(shard_id as ShardId, &epoch_id).unwrap())
-            .collect();
+        let shard_layout = self.get_shard_layout(&store, epoch_manager.as_ref(), &epoch_id)?;
+        let all_shard_uids: Vec<ShardUId> = shard_layout.shard_uids().collect();
+        assert_eq!(all_shard_uids.len(), prev_state_roots.len());
         let runtime =
             NightshadeRuntime::from_config(home_dir, store.clone(), &near_config, epoch_manager)
                 .context("could not create the transaction runtime")?;
@@ -350,9 +418,220 @@ impl ForkNetworkCommand {
         Ok(new_state_roots)
     }
 
+    /// Moves the state to the shards of the given shard layout, the same way
+    /// resharding splits a parent shard into its children:
+    /// * Data of an account goes to the shard the account belongs to.
+    /// * The delayed receipts queues are rebuilt for every shard, and the
+    ///   buffered outgoing receipts are added to the delayed receipts of their
+    ///   receivers.
+    /// * The yield timeouts queue is rebuilt for every shard.
+    /// * Global contracts are copied to every shard.
+    fn set_shard_layout(
+        &self,
+        shard_layout: &Path,
+        batch_size: u64,
+        near_config: &mut NearConfig,
+        home_dir: &Path,
+    ) -> anyhow::Result<Vec<StateRoot>> {
+        // Open storage with migration
+        let storage = open_storage(&home_dir, near_config).unwrap();
+        let store = storage.get_hot_store();
+
+        let (prev_state_roots, prev_hash, epoch_id, _block_height) =
+            self.get_state_roots_and_hash(store.clone())?;
+        tracing::info!(?prev_state_roots, ?epoch_id, ?prev_hash);
+
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+        let prev_shard_layout = self.get_shard_layout(&store, epoch_manager.as_ref(), &epoch_id)?;
+        let shard_layout_path = if shard_layout.is_absolute() {
+            PathBuf::from(shard_layout)
+        } else {
+            home_dir.join(&shard_layout)
+        };
+        let file = File::open(&shard_layout_path)
+            .with_context(|| format!("Failed to open the shard layout {shard_layout_path:?}"))?;
+        let shard_layout: ShardLayout = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to read the shard layout {shard_layout_path:?}"))?;
+        anyhow::ensure!(
+            shard_layout.version() != prev_shard_layout.version(),
+            "The new shard layout must have a version different from {}",
+            prev_shard_layout.version()
+        );
+
+        // The flat storage of the new shards starts at the common flat head.
+        let prev_shard_uids: Vec<ShardUId> = prev_shard_layout.shard_uids().collect();
+        let flat_head = get_fork_heads(&prev_shard_uids, store.clone())?[0];
+
+        let runtime =
+            NightshadeRuntime::from_config(home_dir, store.clone(), &near_config, epoch_manager)
+                .context("could not create the transaction runtime")?;
+        let shard_tries = runtime.get_tries();
+        let new_shard_uid =
+            |account_id: &AccountId| account_id_to_shard_uid(account_id, &shard_layout);
+
+        let mut state_roots: HashMap<ShardUId, StateRoot> =
+            shard_layout.shard_uids().map(|shard_uid| (shard_uid, Trie::EMPTY_ROOT)).collect();
+        let mut priority_delayed_receipts = vec![];
+        let mut delayed_receipts = vec![];
+        let mut promise_yield_timeouts = vec![];
+        // Every shard has a copy of the global contracts.
+        let mut global_contracts = HashMap::new();
+        for (prev_shard_uid, prev_state_root) in
+            prev_shard_layout.shard_uids().zip(prev_state_roots.into_iter())
+        {
+            tracing::info!(?prev_shard_uid, "Moving the state of the shard");
+            // Lets us lookup large values in the `State` columns.
+            let trie_storage = TrieDBStorage::new(store.clone(), prev_shard_uid);
+            let mut values = vec![];
+            let mut num_values = 0;
+            for item in store_helper::iter_flat_state_entries(prev_shard_uid, &store, None, None) {
+                let (key, value) = match item {
+                    Ok((key, FlatStateValue::Ref(ref_value))) => {
+                        (key, trie_storage.retrieve_raw_bytes(&ref_value.hash)?.to_vec())
+                    }
+                    Ok((key, FlatStateValue::Inlined(value))) => (key, value),
+                    otherwise => panic!("Unexpected flat state value: {otherwise:?}"),
+                };
+                if key[0] == col::GLOBAL_CONTRACT_CODE {
+                    let code_hash = CryptoHash::try_from(&key[1..])
+                        .map_err(|err| anyhow::anyhow!("Invalid global contract key: {err}"))?;
+                    global_contracts.insert(code_hash, value);
+                    continue;
+                }
+                // Keys without an account, i.e. the queues, are skipped here.
+                values.push((key, Some(value)));
+                num_values += 1;
+                if values.len() >= batch_size as usize {
+                    tracing::info!(?prev_shard_uid, num_values, "commit");
+                    let (store_update, new_state_roots) = shard_tries
+                        .add_values_to_children_states(
+                            &state_roots,
+                            std::mem::take(&mut values),
+                            &new_shard_uid,
+                        )?;
+                    store_update.commit()?;
+                    state_roots = new_state_roots;
+                }
+            }
+            let (store_update, new_state_roots) =
+                shard_tries.add_values_to_children_states(&state_roots, values, &new_shard_uid)?;
+            store_update.commit()?;
+            state_roots = new_state_roots;
+
+            let trie_update = shard_tries.new_trie_update(prev_shard_uid, prev_state_root);
+            for receipt in PriorityDelayedReceiptQueue::load(&trie_update)?.iter(&trie_update) {
+                priority_delayed_receipts.push(receipt?);
+            }
+            for receipt in DelayedReceiptQueue::load(&trie_update)?.iter(&trie_update) {
+                delayed_receipts.push(receipt?);
+            }
+            let mut outgoing_buffers = ShardsOutgoingReceiptBuffer::load(&trie_update)?;
+            for shard_id in outgoing_buffers.shards() {
+                for receipt in outgoing_buffers.to_shard(shard_id).iter(&trie_update) {
+                    delayed_receipts.push(receipt?);
+                }
+            }
+            let promise_yield_indices = get_promise_yield_indices(&trie_update)?;
+            for index in
+                promise_yield_indices.first_index..promise_yield_indices.next_available_index
+            {
+                let timeout: PromiseYieldTimeout =
+                    get(&trie_update, &TrieKey::PromiseYieldTimeout { index })?
+                        .with_context(|| format!("Missing yield timeout #{index}"))?;
+                promise_yield_timeouts.push(timeout);
+            }
+            tracing::info!(
+                ?prev_shard_uid,
+                num_values,
+                num_priority_delayed_receipts = priority_delayed_receipts.len(),
+                num_delayed_receipts = delayed_receipts.len(),
+                num_promise_yield_timeouts = promise_yield_timeouts.len(),
+                num_global_contracts = global_contracts.len(),
+            );
+        }
+
+        // The old shards are not needed anymore, and the state roots of the
+        // new shards replace them.
+        let mut store_update = store.store_update();
+        for prev_shard_uid in prev_shard_layout.shard_uids() {
+            store_helper::remove_all_flat_state_values(&mut store_update, prev_shard_uid);
+            store_update.delete(DBCol::FlatStorageStatus, &prev_shard_uid.to_bytes());
+            store_update.delete(
+                DBCol::Misc,
+                format!("FORK_TOOL_SHARD_ID:{}", prev_shard_uid.shard_id).as_bytes(),
+            );
+        }
+        store_update.set(
+            DBCol::Misc,
+            b"FORK_TOOL_SHARD_LAYOUT",
+            &serde_json::to_vec(&shard_layout)?,
+        );
+        store_update.commit()?;
+
+        let mut new_state_roots = vec![];
+        for shard_uid in shard_layout.shard_uids() {
+            let mut trie_update = shard_tries.new_trie_update(shard_uid, state_roots[&shard_uid]);
+            let mut priority_delayed_receipt_queue =
+                PriorityDelayedReceiptQueue::load(&trie_update)?;
+            for receipt in &priority_delayed_receipts {
+                if new_shard_uid(receipt.receiver_id()) == shard_uid {
+                    priority_delayed_receipt_queue.push(&mut trie_update, receipt)?;
+                }
+            }
+            let mut delayed_receipt_queue = DelayedReceiptQueue::load(&trie_update)?;
+            for receipt in &delayed_receipts {
+                if new_shard_uid(receipt.receiver_id()) == shard_uid {
+                    delayed_receipt_queue.push(&mut trie_update, receipt)?;
+                }
+            }
+            let mut promise_yield_indices = get_promise_yield_indices(&trie_update)?;
+            for timeout in &promise_yield_timeouts {
+                if new_shard_uid(&timeout.account_id) == shard_uid {
+                    enqueue_promise_yield_timeout(
+                        &mut trie_update,
+                        &mut promise_yield_indices,
+                        timeout.account_id.clone(),
+                        timeout.data_id,
+                        timeout.expires_at,
+                    );
+                }
+            }
+            set_promise_yield_indices(&mut trie_update, &promise_yield_indices);
+            for (code_hash, code) in &global_contracts {
+                trie_update
+                    .set(TrieKey::GlobalContractCode { code_hash: *code_hash }, code.clone());
+            }
+            trie_update.commit(StateChangeCause::Resharding);
+
+            let (_, trie_changes, state_changes) = trie_update.finalize()?;
+            let mut store_update = shard_tries.store_update();
+            let state_root = shard_tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+            FlatStateChanges::from_state_changes(&state_changes)
+                .apply_to_flat_state(&mut store_update, shard_uid);
+            store_helper::set_flat_storage_status(
+                &mut store_update,
+                shard_uid,
+                FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head }),
+            );
+            store_update.set_ser(
+                DBCol::Misc,
+                format!("FORK_TOOL_SHARD_ID:{}", shard_uid.shard_id).as_bytes(),
+                &state_root,
+            )?;
+            store_update.commit()?;
+            tracing::info!(?shard_uid, ?state_root, "Shard is ready");
+            new_state_roots.push(state_root);
+        }
+
+        tracing::info!(?new_state_roots, "All done");
+        Ok(new_state_roots)
+    }
+
     /// Creates a DB snapshot, then
     /// Reads a list of validator accounts from a file
     /// Adds validator accounts to the state
+    /// Creates or modifies the accounts listed in the accounts file, if any
     /// Creates a genesis file with the new validators.
     fn set_validators(
         &self,
@@ -361,6 +640,8 @@ impl ForkNetworkCommand {
         validators: &Path,
         epoch_length: u64,
         chain_id_suffix: &str,
+        accounts: Option<&Path>,
+        batch_size: u64,
         near_config: &mut NearConfig,
         home_dir: &Path,
     ) -> anyhow::Result<(Vec<StateRoot>, Vec<AccountInfo>)> {
@@ -373,6 +654,7 @@ impl ForkNetworkCommand {
 
         let epoch_manager =
             EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+        let shard_layout = self.get_shard_layout(&store, epoch_manager.as_ref(), &epoch_id)?;
 
         let runtime =
             NightshadeRuntime::from_config(home_dir, store, &near_config, epoch_manager.clone())
@@ -381,14 +663,29 @@ impl ForkNetworkCommand {
         let runtime_config_store = RuntimeConfigStore::new(None);
         let runtime_config = runtime_config_store.get_config(PROTOCOL_VERSION);
 
-        let storage_mutator = StorageMutator::new(
-            epoch_manager.clone(),
-            &runtime,
-            epoch_id.clone(),
-            prev_state_roots,
-        )?;
+        let storage_mutator =
+            StorageMutator::new(shard_layout.clone(), &runtime, prev_state_roots)?;
         let (new_state_roots, new_validator_accounts) =
             self.add_validator_accounts(validators, runtime_config, home_dir, storage_mutator)?;
+        let (new_state_roots, total_supply_change) = match accounts {
+            Some(accounts) => self.add_accounts(
+                accounts,
+                &new_validator_accounts,
+                batch_size,
+                runtime_config,
+                home_dir,
+                &shard_layout,
+                &runtime,
+                new_state_roots,
+            )?,
+            None => (new_state_roots, 0),
+        };
+        let total_supply = near_config
+            .genesis
+            .config
+            .total_supply
+            .checked_add_signed(total_supply_change)
+            .context("the total supply of the accounts overflows")?;
 
         tracing::info!("Creating a new genesis");
         backup_genesis_file(home_dir, &near_config)?;
@@ -399,8 +696,10 @@ impl ForkNetworkCommand {
             block_height,
             chain_id_suffix,
             &epoch_id,
+            shard_layout,
             new_state_roots.clone(),
             new_validator_accounts.clone(),
+            total_supply,
             epoch_manager,
             home_dir,
             &near_config,
@@ -436,20 +735,25 @@ impl ForkNetworkCommand {
         let block_hash = store.get_ser(DBCol::Misc, b"FORK_TOOL_BLOCK_HASH")?.unwrap();
         let block_height = store.get(DBCol::Misc, b"FORK_TOOL_BLOCK_HEIGHT")?.unwrap();
         let block_height = u64::from_le_bytes(block_height.as_slice().try_into().unwrap());
-        let mut state_roots = vec![];
-        for (shard_id, item) in
-            store.iter_prefix(DBCol::Misc, "FORK_TOOL_SHARD_ID:".as_bytes()).enumerate()
-        {
-            let (key, value) = item?;
-            let key = String::from_utf8(key.to_vec())?;
-            let state_root = borsh::from_slice(&value)?;
-            assert_eq!(key, format!("FORK_TOOL_SHARD_ID:{shard_id}"));
-            state_roots.push(state_root);
-        }
+        let state_roots = get_fork_state_roots(&store)?;
         tracing::info!(?state_roots, ?block_hash, ?epoch_id, block_height);
         Ok((state_roots, block_hash, epoch_id, block_height))
     }
 
+    /// Returns the shard layout of the forked network. It is the layout set by
+    /// `set-shard-layout`, or the layout of the epoch the fork was made at.
+    fn get_shard_layout(
+        &self,
+        store: &Store,
+        epoch_manager: &dyn EpochManagerAdapter,
+        epoch_id: &EpochId,
+    ) -> anyhow::Result<ShardLayout> {
+        match store.get(DBCol::Misc, b"FORK_TOOL_SHARD_LAYOUT")? {
+            Some(shard_layout) => Ok(serde_json::from_slice(&shard_layout)?),
+            None => Ok(epoch_manager.get_shard_layout(epoch_id)?),
+        }
+    }
+
     /// Checks that `~/.near/data/fork-snapshot/data` exists.
     /// Deletes files (not directories) in `~/.near/data`
     /// Moves everything from `~/.near/data/fork-snapshot/data/` to `~/.near/data`.
@@ -756,6 +1060,107 @@ impl ForkNetworkCommand {
         Ok((new_state_roots, new_validator_accounts))
     }
 
+    /// Reads the accounts file (which is a path relative to the home dir),
+    /// creates the accounts which don't exist yet and updates the balance,
+    /// keys and contract of the ones which do.
+    fn add_accounts(
+        &self,
+        accounts: &Path,
+        validators: &[AccountInfo],
+        batch_size: u64,
+        runtime_config: &RuntimeConfig,
+        home_dir: &Path,
+        shard_layout: &ShardLayout,
+        runtime: &NightshadeRuntime,
+        state_roots: Vec<StateRoot>,
+    ) -> anyhow::Result<(Vec<StateRoot>, i128)> {
+        let accounts_path =
+            if accounts.is_absolute() { PathBuf::from(accounts) } else { home_dir.join(accounts) };
+        let new_accounts = read_new_accounts(&accounts_path)?;
+        validate_new_accounts(&new_accounts, validators)?;
+        tracing::info!(?accounts_path, num_accounts = new_accounts.len(), "Adding accounts");
+
+        let storage_usage_config = &runtime_config.fees.storage_usage_config;
+        // Load testing accounts often share a few contracts.
+        let mut contracts: HashMap<PathBuf, (CryptoHash, Vec<u8>)> = HashMap::new();
+        let mut storage_mutator = StorageMutator::new(shard_layout.clone(), runtime, state_roots)?;
+        let mut num_created = 0;
+        let mut num_updated = 0;
+        // The balances created or removed, to keep the total supply of the
+        // genesis equal to the sum of the balances.
+        let mut total_supply_change: i128 = 0;
+        for new_account in new_accounts {
+            let account_id = &new_account.account_id;
+            let (mut account, old_amount) = match storage_mutator.get_account(account_id)? {
+                Some(account) => {
+                    num_updated += 1;
+                    let amount = account.amount();
+                    (account, amount)
+                }
+                None => {
+                    num_created += 1;
+                    let account = Account::new(
+                        10_000 * NEAR_BASE,
+                        0,
+                        0,
+                        CryptoHash::default(),
+                        storage_usage_config.num_bytes_account,
+                        PROTOCOL_VERSION,
+                    );
+                    (account, 0)
+                }
+            };
+            if let Some(amount) = new_account.amount {
+                account.set_amount(amount);
+            }
+            total_supply_change += account.amount() as i128 - old_amount as i128;
+            if let Some(public_key) = new_account.public_key {
+                if storage_mutator.get_access_key(account_id, &public_key)?.is_none() {
+                    let access_key = AccessKey::full_access();
+                    account.set_storage_usage(
+                        account.storage_usage()
+                            + public_key.len() as u64
+                            + borsh::object_length(&access_key)? as u64
+                            + storage_usage_config.num_extra_bytes_record,
+                    );
+                    storage_mutator.set_access_key(account_id, public_key, access_key)?;
+                }
+            }
+            if let Some(code) = new_account.code {
+                anyhow::ensure!(
+                    account.code_hash() == CryptoHash::default(),
+                    "Account {account_id} already has a contract"
+                );
+                let code_path = if code.is_absolute() { code } else { home_dir.join(code) };
+                if !contracts.contains_key(&code_path) {
+                    let code = std::fs::read(&code_path)
+                        .with_context(|| format!("Failed to read the contract {code_path:?}"))?;
+                    contracts.insert(code_path.clone(), (hash(&code), code));
+                }
+                let (code_hash, code) = &contracts[&code_path];
+                account.set_code_hash(*code_hash);
+                account.set_storage_usage(account.storage_usage() + code.len() as u64);
+                storage_mutator.set_code(account_id, code.clone())?;
+            }
+            storage_mutator.set_account(account_id, account)?;
+
+            if storage_mutator.should_commit(batch_size) {
+                tracing::info!(num_created, num_updated, "commit");
+                let state_roots = storage_mutator.commit()?;
+                storage_mutator = StorageMutator::new(shard_layout.clone(), runtime, state_roots)?;
+            }
+        }
+        let new_state_roots = storage_mutator.commit()?;
+        tracing::info!(
+            num_created,
+            num_updated,
+            total_supply_change,
+            ?new_state_roots,
+            "Added accounts"
+        );
+        Ok((new_state_roots, total_supply_change))
+    }
+
     /// Makes a new genesis and writes it to `~/.near/genesis.json`.
     fn make_and_write_genesis(
         &self,
@@ -765,8 +1170,10 @@ impl ForkNetworkCommand {
         height: BlockHeight,
         chain_id_suffix: &str,
         epoch_id: &EpochId,
+        shard_layout: ShardLayout,
         new_state_roots: Vec<StateRoot>,
         new_validator_accounts: Vec<AccountInfo>,
+        total_supply: Balance,
         epoch_manager: Arc<EpochManagerHandle>,
         home_dir: &Path,
         near_config: &NearConfig,
@@ -781,14 +1188,25 @@ impl ForkNetworkCommand {
         };
         let original_config = near_config.genesis.config.clone();
 
+        // The per shard settings have to match the number of shards, which
+        // differs from the epoch config if `set-shard-layout` was used.
+        let num_shards = shard_layout.shard_ids().count();
+        let mut num_block_producer_seats_per_shard =
+            epoch_config.num_block_producer_seats_per_shard.clone();
+        num_block_producer_seats_per_shard
+            .resize(num_shards, epoch_config.num_block_producer_seats);
+        let mut avg_hidden_validator_seats_per_shard =
+            epoch_config.avg_hidden_validator_seats_per_shard.clone();
+        avg_hidden_validator_seats_per_shard.resize(num_shards, 0);
+
         let new_config = GenesisConfig {
             chain_id: original_config.chain_id.clone() + chain_id_suffix,
             genesis_height: height,
             genesis_time,
             epoch_length,
             num_block_producer_seats: epoch_config.num_block_producer_seats,
-            num_block_producer_seats_per_shard: epoch_config.num_block_producer_seats_per_shard,
-            avg_hidden_validator_seats_per_shard: epoch_config.avg_hidden_validator_seats_per_shard,
+            num_block_producer_seats_per_shard,
+            avg_hidden_validator_seats_per_shard,
             block_producer_kickout_threshold: 0,
             chunk_producer_kickout_threshold: 0,
             max_kickout_stake_perc: 0,
@@ -797,7 +1215,7 @@ impl ForkNetworkCommand {
             fishermen_threshold: epoch_config.fishermen_threshold,
             minimum_stake_divisor: epoch_config.minimum_stake_divisor,
             protocol_upgrade_stake_threshold: epoch_config.protocol_upgrade_stake_threshold,
-            shard_layout: epoch_config.shard_layout.clone(),
+            shard_layout,
             num_chunk_only_producer_seats: epoch_config
                 .validator_selection_config
                 .num_chunk_only_producer_seats,
@@ -819,7 +1237,7 @@ impl ForkNetworkCommand {
             num_blocks_per_year: original_config.num_blocks_per_year,
             protocol_reward_rate: original_config.protocol_reward_rate,
             protocol_treasury_account: original_config.protocol_treasury_account.clone(),
-            total_supply: original_config.total_supply,
+            total_supply,
             transaction_validity_period: original_config.transaction_validity_period,
             use_production_config: original_config.use_production_config,
             num_chunk_producer_seats: original_config.num_chunk_producer_seats,
@@ -839,10 +1257,77 @@ impl ForkNetworkCommand {
     }
 }
 
+/// Reads the accounts file, which is parsed as CSV if its extension is `csv`
+/// and as JSON otherwise.
+fn read_new_accounts(path: &Path) -> anyhow::Result<Vec<NewAccount>> {
+    let file = File::open(path).with_context(|| format!("Failed to open the accounts {path:?}"))?;
+    if path.extension().map_or(false, |extension| extension == "csv") {
+        let mut reader = csv::Reader::from_reader(BufReader::new(file));
+        reader
+            .deserialize()
+            .map(|row| {
+                let row: NewAccountRow = row?;
+                let amount = match row.amount {
+                    Some(amount) => Some(amount.parse().with_context(|| {
+                        format!("Invalid amount {amount:?} of account {}", row.account_id)
+                    })?),
+                    None => None,
+                };
+                Ok(NewAccount {
+                    account_id: row.account_id,
+                    amount,
+                    public_key: row.public_key,
+                    code: row.code,
+                })
+            })
+            .collect()
+    } else {
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to read the accounts {path:?}"))
+    }
+}
+
+/// Checks that every account is listed once and that the new genesis
+/// validators are not modified by the accounts file.
+fn validate_new_accounts(
+    new_accounts: &[NewAccount],
+    validators: &[AccountInfo],
+) -> anyhow::Result<()> {
+    let validators: HashSet<_> = validators.iter().map(|validator| &validator.account_id).collect();
+    let mut account_ids = HashSet::new();
+    for new_account in new_accounts {
+        let account_id = &new_account.account_id;
+        anyhow::ensure!(account_ids.insert(account_id), "Account {account_id} is listed twice");
+        anyhow::ensure!(
+            !validators.contains(account_id),
+            "Account {account_id} is a validator of the new genesis"
+        );
+    }
+    Ok(())
+}
+
 fn backup_genesis_file_path(home_dir: &Path, genesis_file: &str) -> PathBuf {
     home_dir.join(format!("{}.backup", &genesis_file))
 }
 
+/// Returns the state roots of the shards of the forked network, ordered by
+/// shard id. The keys are ordered as strings, which puts shard 10 before 2.
+fn get_fork_state_roots(store: &Store) -> anyhow::Result<Vec<StateRoot>> {
+    const PREFIX: &[u8] = b"FORK_TOOL_SHARD_ID:";
+    let mut state_roots = BTreeMap::new();
+    for item in store.iter_prefix(DBCol::Misc, PREFIX) {
+        let (key, value) = item?;
+        let shard_id: ShardId = std::str::from_utf8(&key[PREFIX.len()..])?.parse()?;
+        state_roots.insert(shard_id, borsh::from_slice::<StateRoot>(&value)?);
+    }
+    anyhow::ensure!(
+        state_roots.keys().copied().eq(0..state_roots.len() as ShardId),
+        "State roots are missing for some shards: {:?}",
+        state_roots.keys().collect::<Vec<_>>()
+    );
+    Ok(state_roots.into_values().collect())
+}
+
 /// Returns hash of flat head.
 /// Checks that all shards have flat storage.
 /// Checks that flat heads of all shards match.
@@ -878,3 +1363,35 @@ fn restore_backup_genesis_file(home_dir: &Path, near_config: &NearConfig) -> any
     std::fs::rename(&backup_genesis_file, &original_genesis_file)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::get_fork_state_roots;
+    use near_primitives::hash::hash;
+    use near_store::test_utils::create_test_store;
+    use near_store::DBCol;
+
+    #[test]
+    fn test_get_fork_state_roots_orders_shards_numerically() {
+        let store = create_test_store();
+        let state_roots: Vec<_> =
+            (0..12).map(|shard_id: u64| hash(&shard_id.to_le_bytes())).collect();
+        let mut store_update = store.store_update();
+        for (shard_id, state_root) in state_roots.iter().enumerate() {
+            store_update
+                .set_ser(
+                    DBCol::Misc,
+                    format!("FORK_TOOL_SHARD_ID:{shard_id}").as_bytes(),
+                    state_root,
+                )
+                .unwrap();
+        }
+        store_update.commit().unwrap();
+        assert_eq!(get_fork_state_roots(&store).unwrap(), state_roots);
+
+        let mut store_update = store.store_update();
+        store_update.delete(DBCol::Misc, b"FORK_TOOL_SHARD_ID:10");
+        store_update.commit().unwrap();
+        get_fork_state_roots(&store).unwrap_err();
+    }
+}
@@ -1,7 +1,7 @@
 use near_chain::types::RuntimeAdapter;
 use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account};
-use near_primitives::borsh;
+use near_primitives::borsh::{self, BorshDeserialize};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::Receipt;
 use near_primitives::shard_layout::ShardUId;
@@ -24,6 +24,17 @@ impl SingleShardStorageMutator {
         Ok(Self { updates: Vec::new(), state_root, shard_tries: runtime.get_tries() })
     }
 
+    /// Reads a value from the state the mutator was created with. Changes
+    /// which are not committed yet are not visible.
+    fn get<T: BorshDeserialize>(
+        &self,
+        shard_uid: &ShardUId,
+        key: &TrieKey,
+    ) -> anyhow::Result<Option<T>> {
+        let trie = self.shard_tries.get_trie_for_shard(*shard_uid, self.state_root);
+        Ok(near_store::get(&trie, key)?)
+    }
+
     fn set(&mut self, key: TrieKey, value: Vec<u8>) -> anyhow::Result<()> {
         self.updates.push((key.to_vec(), Some(value)));
         Ok(())
@@ -42,6 +53,14 @@ impl SingleShardStorageMutator {
         self.set(TrieKey::Account { account_id }, borsh::to_vec(&value)?)
     }
 
+    pub(crate) fn get_account(
+        &self,
+        shard_uid: &ShardUId,
+        account_id: AccountId,
+    ) -> anyhow::Result<Option<Account>> {
+        self.get(shard_uid, &TrieKey::Account { account_id })
+    }
+
     pub(crate) fn delete_account(&mut self, account_id: AccountId) -> anyhow::Result<()> {
         self.remove(TrieKey::Account { account_id })
     }
@@ -55,6 +74,15 @@ impl SingleShardStorageMutator {
         self.set(TrieKey::AccessKey { account_id, public_key }, borsh::to_vec(&access_key)?)
     }
 
+    pub(crate) fn get_access_key(
+        &self,
+        shard_uid: &ShardUId,
+        account_id: AccountId,
+        public_key: PublicKey,
+    ) -> anyhow::Result<Option<AccessKey>> {
+        self.get(shard_uid, &TrieKey::AccessKey { account_id, public_key })
+    }
+
     pub(crate) fn delete_access_key(
         &mut self,
         account_id: AccountId,
@@ -141,6 +169,10 @@ impl SingleShardStorageMutator {
         self.updates.len() >= batch_size as usize
     }
 
+    pub(crate) fn num_updates(&self) -> u64 {
+        self.updates.len() as u64
+    }
+
     /// The fake block height is used to allow memtries to garbage collect.
     /// Otherwise it would take significantly more memory holding old nodes.
     pub(crate) fn commit(
@@ -1,44 +1,60 @@
 use crate::single_shard_storage_mutator::SingleShardStorageMutator;
 use near_crypto::PublicKey;
-use near_epoch_manager::EpochManagerAdapter;
 use near_primitives::account::{AccessKey, Account};
-use near_primitives::types::{AccountId, EpochId, StateRoot};
+use near_primitives::shard_layout::{account_id_to_shard_id, account_id_to_shard_uid, ShardLayout};
+use near_primitives::types::{AccountId, StateRoot};
 use nearcore::NightshadeRuntime;
-use std::sync::Arc;
 
 /// Object that updates the existing state. Combines all changes, commits them
 /// and returns new state roots.
 pub(crate) struct StorageMutator {
-    epoch_manager: Arc<dyn EpochManagerAdapter>,
-    epoch_id: EpochId,
+    shard_layout: ShardLayout,
     mutators: Vec<SingleShardStorageMutator>,
 }
 
 impl StorageMutator {
     pub(crate) fn new(
-        epoch_manager: Arc<dyn EpochManagerAdapter>,
+        shard_layout: ShardLayout,
         runtime: &NightshadeRuntime,
-        epoch_id: EpochId,
         state_roots: Vec<StateRoot>,
     ) -> anyhow::Result<Self> {
-        let shard_layout = epoch_manager.get_shard_layout(&epoch_id)?;
         assert_eq!(shard_layout.shard_ids().count(), state_roots.len());
 
         let mut mutators = vec![];
         for state_root in state_roots {
             mutators.push(SingleShardStorageMutator::new(runtime, state_root)?);
         }
-        Ok(Self { epoch_manager, epoch_id, mutators })
+        Ok(Self { shard_layout, mutators })
     }
 
     fn mutator(
         &mut self,
         account_id: &AccountId,
     ) -> anyhow::Result<&mut SingleShardStorageMutator> {
-        let shard_id = self.epoch_manager.account_id_to_shard_id(&account_id, &self.epoch_id)?;
+        let shard_id = account_id_to_shard_id(&account_id, &self.shard_layout);
         Ok(&mut self.mutators[shard_id as usize])
     }
 
+    /// Reads the account from the state the mutator was created with.
+    pub(crate) fn get_account(&self, account_id: &AccountId) -> anyhow::Result<Option<Account>> {
+        let shard_uid = account_id_to_shard_uid(account_id, &self.shard_layout);
+        self.mutators[shard_uid.shard_id as usize].get_account(&shard_uid, account_id.clone())
+    }
+
+    /// Reads the access key from the state the mutator was created with.
+    pub(crate) fn get_access_key(
+        &self,
+        account_id: &AccountId,
+        public_key: &PublicKey,
+    ) -> anyhow::Result<Option<AccessKey>> {
+        let shard_uid = account_id_to_shard_uid(account_id, &self.shard_layout);
+        self.mutators[shard_uid.shard_id as usize].get_access_key(
+            &shard_uid,
+            account_id.clone(),
+            public_key.clone(),
+        )
+    }
+
     pub(crate) fn set_account(
         &mut self,
         account_id: &AccountId,
@@ -56,9 +72,17 @@ impl StorageMutator {
         self.mutator(account_id)?.set_access_key(account_id.clone(), public_key, access_key)
     }
 
+    pub(crate) fn set_code(&mut self, account_id: &AccountId, code: Vec<u8>) -> anyhow::Result<()> {
+        self.mutator(account_id)?.set_code(account_id.clone(), code)
+    }
+
+    pub(crate) fn should_commit(&self, batch_size: u64) -> bool {
+        let num_updates: u64 = self.mutators.iter().map(|mutator| mutator.num_updates()).sum();
+        num_updates >= batch_size
+    }
+
     pub(crate) fn commit(self) -> anyhow::Result<Vec<StateRoot>> {
-        let shard_layout = self.epoch_manager.get_shard_layout(&self.epoch_id)?;
-        let all_shard_uids = shard_layout.shard_uids();
+        let all_shard_uids = self.shard_layout.shard_uids();
         let mut state_roots = vec![];
         for (mutator, shard_uid) in self.mutators.into_iter().zip(all_shard_uids.into_iter()) {
             let state_root = mutator.commit(&shard_uid, 0)?;
@@ -151,6 +151,8 @@ pub(crate) struct TxTracker {
     min_block_production_delay: Duration,
     // optional specific tx send delay
     tx_batch_interval: Option<Duration>,
+    // how many source chain blocks we send per tx send delay
+    speed_multiplier: f64,
     // timestamps in the target chain, used to judge how long to wait before sending a new batch of txs
     recent_block_timestamps: VecDeque<u64>,
     // last source block we'll be sending transactions for
@@ -164,6 +166,7 @@ impl TxTracker {
     pub(crate) fn new<'a, I>(
         min_block_production_delay: Duration,
         tx_batch_interval: Option<Duration>,
+        speed_multiplier: f64,
         next_heights: I,
         stop_height: Option<BlockHeight>,
     ) -> Self
@@ -176,6 +179,7 @@ impl TxTracker {
             next_heights,
             stop_height,
             tx_batch_interval,
+            speed_multiplier,
             // Wait at least 15 seconds before sending any transactions because for
             // a few seconds after the node starts, transaction routing requests
             // will be silently dropped by the peer manager.
@@ -902,7 +906,9 @@ impl TxTracker {
                     }
                 }
             }
-            ReceiptEnumView::Data { .. } => {}
+            ReceiptEnumView::Data { .. }
+            | ReceiptEnumView::GlobalContractDistribution { .. }
+            | ReceiptEnumView::RuntimeConfigOverridesDistribution { .. } => {}
         };
         Ok(())
     }
@@ -1179,10 +1185,13 @@ impl TxTracker {
 
         let (txs_sent, provenance) = match sent_batch {
             SentBatch::MappedBlock(b) => {
-                let block_delay = self.tx_batch_interval.unwrap_or_else(|| {
-                    self.second_longest_recent_block_delay()
-                        .unwrap_or(self.min_block_production_delay + Duration::from_millis(100))
-                });
+                let block_delay = self
+                    .tx_batch_interval
+                    .unwrap_or_else(|| {
+                        self.second_longest_recent_block_delay()
+                            .unwrap_or(self.min_block_production_delay + Duration::from_millis(100))
+                    })
+                    .div_f64(self.speed_multiplier);
                 self.send_time.as_mut().reset(tokio::time::Instant::now() + block_delay);
                 crate::set_last_source_height(db, b.source_height)?;
                 let txs =
@@ -34,6 +34,7 @@ use rocksdb::DB;
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use strum::IntoEnumIterator;
@@ -466,6 +467,98 @@ struct MirrorConfig {
     /// wait this long before sending each mainnet block's worth of transactions.
     /// TODO: add an option to target a specific number of transactions per second
     tx_batch_interval: Option<Duration>,
+    /// Number of source chain blocks to send transactions for in the time we would
+    /// otherwise send one. For example, with a value of 2.0 the source chain is replayed
+    /// at twice its rate. Defaults to 1.0.
+    speed_multiplier: Option<f64>,
+    /// If not empty, only transactions whose signer or receiver matches one of these
+    /// patterns are sent. In a pattern, `*` matches any sequence of characters, e.g.
+    /// `*.aurora` or `sweat_welcome.near`.
+    #[serde(default)]
+    include_accounts: Vec<String>,
+    /// Transactions whose signer or receiver matches one of these patterns are not sent.
+    #[serde(default)]
+    exclude_accounts: Vec<String>,
+    /// Number of extra transactions to send for each source chain transaction made of
+    /// transfers and function calls only. Each extra transaction repeats the actions of
+    /// the source one, so the additional load follows the observed mix of transactions.
+    /// For example, with a value of 0.5 one extra transaction is sent for every other
+    /// such source transaction.
+    synthetic_load: Option<f64>,
+}
+
+impl MirrorConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(speed_multiplier) = self.speed_multiplier {
+            anyhow::ensure!(
+                speed_multiplier > 0.0,
+                "speed_multiplier must be positive, got {}",
+                speed_multiplier
+            );
+        }
+        if let Some(synthetic_load) = self.synthetic_load {
+            anyhow::ensure!(
+                synthetic_load >= 0.0,
+                "synthetic_load must not be negative, got {}",
+                synthetic_load
+            );
+        }
+        Ok(())
+    }
+
+    // whether a source chain transaction should be sent according to the include_accounts and
+    // exclude_accounts patterns
+    fn should_mirror(&self, signer_id: &AccountId, receiver_id: &AccountId) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns.iter().any(|pattern| {
+                account_matches(pattern, signer_id.as_str())
+                    || account_matches(pattern, receiver_id.as_str())
+            })
+        };
+        (self.include_accounts.is_empty() || matches(&self.include_accounts))
+            && !matches(&self.exclude_accounts)
+    }
+
+    // how many synthetic copies we should send of the transaction preceded by `n` others
+    // that synthetic copies can be made of in this run. This spreads the fractional part of
+    // synthetic_load evenly over the transactions.
+    fn num_synthetic_txs(&self, n: usize) -> usize {
+        match self.synthetic_load {
+            Some(load) => {
+                ((n + 1) as f64 * load).floor() as usize - (n as f64 * load).floor() as usize
+            }
+            None => 0,
+        }
+    }
+}
+
+// Returns whether `account_id` matches `pattern`, where `*` matches any sequence of characters.
+fn account_matches(pattern: &str, account_id: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // split() always returns at least one item
+    let prefix = parts.next().unwrap();
+    let mut rest = match account_id.strip_prefix(prefix) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let (suffix, middle) = match parts.split_last() {
+        Some(p) => p,
+        // no wildcard in the pattern
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(suffix)
+}
+
+// Synthetic load only repeats transactions that can be sent again as is.
+fn can_synthesize(actions: &[Action]) -> bool {
+    actions.iter().all(|a| matches!(a, Action::Transfer(_) | Action::FunctionCall(_)))
 }
 
 const CREATE_ACCOUNT_DELTA: usize = 5;
@@ -486,6 +579,10 @@ struct TxMirror<T: ChainAccess> {
     secret: Option<[u8; crate::secret::SECRET_LEN]>,
     default_extra_key: SecretKey,
     config: MirrorConfig,
+    // number of source chain transactions seen so far in this run that synthetic copies
+    // can be made of, so that the fractional part of synthetic_load is spread over
+    // all of them rather than restarting with every chunk.
+    num_synthesizable_txs: AtomicUsize,
 }
 
 fn open_db<P: AsRef<Path>>(home: P, config: &NearConfig) -> anyhow::Result<DB> {
@@ -514,6 +611,7 @@ enum MappedTxProvenance {
     TxCreateAccount(BlockHeight, ShardId, usize),
     ReceiptCreateAccount(BlockHeight, ShardId, usize),
     Unstake(CryptoHash),
+    SyntheticTx(BlockHeight, ShardId, usize),
 }
 
 impl MappedTxProvenance {
@@ -564,6 +662,9 @@ impl std::fmt::Display for MappedTxProvenance {
             Self::Unstake(hash) => {
                 write!(f, "unstake after stake receipt in target block {}", hash,)
             }
+            Self::SyntheticTx(height, shard_id, idx) => {
+                write!(f, "synthetic copy of source #{} shard {} tx #{}", height, shard_id, idx)
+            }
         }
     }
 }
@@ -919,6 +1020,7 @@ impl<T: ChainAccess> TxMirror<T> {
             secret,
             default_extra_key,
             config,
+            num_synthesizable_txs: AtomicUsize::new(0),
         })
     }
 
@@ -1042,7 +1144,7 @@ impl<T: ChainAccess> TxMirror<T> {
                 }
                 // We don't want to mess with the set of validators in the target chain
                 Action::Stake(_) => {}
-                Action::DeployContract(_) => {
+                Action::DeployContract(_) | Action::DeployGlobalContract(_) => {
                     // if we're getting transactions from a ViewClient instead of directly from the DB,
                     // DeployContract actions are silently mangled, so we can't recover the original contract code here
                     if !tx.is_view() {
@@ -1524,6 +1626,9 @@ impl<T: ChainAccess> TxMirror<T> {
             let mut txs = Vec::new();
 
             for (idx, source_tx) in ch.transactions.into_iter().enumerate() {
+                if !self.config.should_mirror(source_tx.signer_id(), source_tx.receiver_id()) {
+                    continue;
+                }
                 let (actions, nonce_updates) = self.map_actions(&source_tx).await?;
                 if actions.is_empty() {
                     // If this is a tx containing only stake actions, skip it.
@@ -1536,16 +1641,22 @@ impl<T: ChainAccess> TxMirror<T> {
                     crate::key_mapping::map_account(source_tx.signer_id(), self.secret.as_ref());
                 let target_receiver_id =
                     crate::key_mapping::map_account(source_tx.receiver_id(), self.secret.as_ref());
+                let num_synthetic_txs = if can_synthesize(&actions) {
+                    let n = self.num_synthesizable_txs.fetch_add(1, Ordering::Relaxed);
+                    self.config.num_synthetic_txs(n)
+                } else {
+                    0
+                };
 
                 let target_tx = self
                     .prepare_tx(
                         tracker,
                         source_tx.signer_id().clone(),
                         source_tx.receiver_id().clone(),
-                        target_signer_id,
-                        target_receiver_id,
+                        target_signer_id.clone(),
+                        target_receiver_id.clone(),
                         &target_private_key,
-                        actions,
+                        actions.clone(),
                         &ref_hash,
                         Some(source_height),
                         MappedTxProvenance::MappedSourceTx(source_height, ch.shard_id, idx),
@@ -1553,6 +1664,24 @@ impl<T: ChainAccess> TxMirror<T> {
                     )
                     .await?;
                 txs.push(target_tx);
+                for _ in 0..num_synthetic_txs {
+                    let synthetic_tx = self
+                        .prepare_tx(
+                            tracker,
+                            source_tx.signer_id().clone(),
+                            source_tx.receiver_id().clone(),
+                            target_signer_id.clone(),
+                            target_receiver_id.clone(),
+                            &target_private_key,
+                            actions.clone(),
+                            &ref_hash,
+                            Some(source_height),
+                            MappedTxProvenance::SyntheticTx(source_height, ch.shard_id, idx),
+                            HashSet::new(),
+                        )
+                        .await?;
+                    txs.push(synthetic_tx);
+                }
                 self.add_tx_function_call_keys(
                     &source_tx,
                     MappedTxProvenance::TxAddKey(source_height, ch.shard_id, idx),
@@ -1805,6 +1934,7 @@ impl<T: ChainAccess> TxMirror<T> {
         let mut tracker = crate::chain_tracker::TxTracker::new(
             self.target_min_block_production_delay,
             self.config.tx_batch_interval,
+            self.config.speed_multiplier.unwrap_or(1.0),
             next_heights.iter(),
             stop_height,
         );
@@ -1860,6 +1990,7 @@ async fn run<P: AsRef<Path>>(
         }
         None => Default::default(),
     };
+    config.validate()?;
     if !online_source {
         let source_chain_access = crate::offline::ChainAccess::new(source_home)?;
         let stop_height = stop_height.unwrap_or(
@@ -1875,3 +2006,48 @@ async fn run<P: AsRef<Path>>(
             .await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{account_matches, MirrorConfig};
+
+    #[test]
+    fn test_account_matches() {
+        assert!(account_matches("alice.near", "alice.near"));
+        assert!(!account_matches("alice.near", "alice.near.x"));
+        assert!(account_matches("*.aurora", "relay.aurora"));
+        assert!(!account_matches("*.aurora", "aurora"));
+        assert!(account_matches("sweat_*", "sweat_welcome.near"));
+        assert!(account_matches("*", "bob.near"));
+        assert!(account_matches("a*b*c", "abc"));
+        assert!(account_matches("a*b*c", "axxbyyc"));
+        assert!(!account_matches("a*b*c", "axxcyyb"));
+        assert!(!account_matches("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_should_mirror() {
+        let config = MirrorConfig {
+            include_accounts: vec!["*.aurora".to_string(), "app.near".to_string()],
+            exclude_accounts: vec!["spam.aurora".to_string()],
+            ..Default::default()
+        };
+        let alice = "alice.near".parse().unwrap();
+        assert!(config.should_mirror(&alice, &"relay.aurora".parse().unwrap()));
+        assert!(config.should_mirror(&"app.near".parse().unwrap(), &alice));
+        assert!(!config.should_mirror(&alice, &"bob.near".parse().unwrap()));
+        assert!(!config.should_mirror(&alice, &"spam.aurora".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_num_synthetic_txs() {
+        let num_txs = |synthetic_load| {
+            let config = MirrorConfig { synthetic_load, ..Default::default() };
+            (0..10).map(|n| config.num_synthetic_txs(n)).collect::<Vec<_>>()
+        };
+        assert_eq!(num_txs(None), vec![0; 10]);
+        assert_eq!(num_txs(Some(2.0)), vec![2; 10]);
+        assert_eq!(num_txs(Some(0.5)), vec![0, 1, 0, 1, 0, 1, 0, 1, 0, 1]);
+        assert_eq!(num_txs(Some(1.5)).iter().sum::<usize>(), 15);
+    }
+}
@@ -19,6 +19,7 @@ use std::task::Poll;
 use std::time::Duration;
 
 pub mod setup;
+pub mod workload;
 
 // For now this is a simple struct with one field just to leave the door
 // open for adding stuff and/or having different configs for different message types later.
@@ -6,6 +6,7 @@
 use actix::System;
 use anyhow::Context;
 use mock_node::setup::{setup_mock_node, MockNode};
+use mock_node::workload::{run_workload, WorkloadConfig};
 use mock_node::MockNetworkConfig;
 use near_actix_test_utils::run_actix;
 use near_chain_configs::GenesisValidationMode;
@@ -46,11 +47,16 @@ use std::time::{Duration, Instant};
 ///
 /// # Mixed: client starts at genesis and tries to catch up with the network, which starts at height 20.
 /// $ mock-node ~/.near/localnet/node0 --network-height 20
+///
+/// # No history: start a new chain in ~/workload_home and feed it with the
+/// # transactions described in workload.json until height 1000.
+/// $ mock-node ~/workload_home --workload workload.json --target-height 1000
 /// ```
 #[derive(clap::Parser)]
 struct Cli {
     /// Existing home dir for the pre-generated chain history. For example, you can use
-    /// the home dir of a near node.
+    /// the home dir of a near node. With `--workload`, the home dir of the new chain
+    /// instead, which must not contain a database yet.
     chain_history_home_dir: String,
     /// Home dir for the new client that will be started. If not specified, the binary will
     /// generate a temporary directory
@@ -83,6 +89,10 @@ struct Cli {
     /// port the mock node should listen on
     #[clap(long)]
     mock_port: Option<u16>,
+    /// Instead of replaying chain history, start a new single-validator chain and
+    /// send it the synthetic transactions described in this JSON file.
+    #[clap(long, conflicts_with_all(&["client-height", "network-height", "start-height"]))]
+    workload: Option<PathBuf>,
 }
 
 async fn target_height_reached(client: &JsonRpcClient, target_height: BlockHeight) -> bool {
@@ -101,10 +111,36 @@ async fn target_height_reached(client: &JsonRpcClient, target_height: BlockHeigh
     }
 }
 
+fn run_workload_mode(
+    home_dir: &Path,
+    workload_path: &Path,
+    target_height: Option<BlockHeight>,
+    addr: tcp::ListenerAddr,
+) -> anyhow::Result<()> {
+    let workload = WorkloadConfig::from_file(&workload_path).with_context(|| {
+        format!("Error loading workload config from {}", workload_path.display())
+    })?;
+    let near_config = workload.near_config(addr)?;
+    std::fs::create_dir_all(home_dir)?;
+    let mut result = Ok(());
+    run_actix(async {
+        result = run_workload(home_dir, near_config, &workload, target_height).await;
+        System::current().stop();
+    });
+    result
+}
+
 fn main() -> anyhow::Result<()> {
     init_integration_logger();
     let args: Cli = clap::Parser::parse();
     let home_dir = Path::new(&args.chain_history_home_dir);
+    let addr = tcp::ListenerAddr::new(SocketAddr::new(
+        "127.0.0.1".parse().unwrap(),
+        args.mock_port.unwrap_or(24566),
+    ));
+    if let Some(workload_path) = &args.workload {
+        return run_workload_mode(home_dir, workload_path, args.target_height, addr);
+    }
     let mut near_config = nearcore::config::load_config(home_dir, GenesisValidationMode::Full)
         .context("Error loading config")?;
     near_config.validator_signer = None;
@@ -139,10 +175,6 @@ fn main() -> anyhow::Result<()> {
 
     let client_height = args.start_height.unwrap_or(args.client_height);
     let network_height = args.start_height.or(args.network_height);
-    let addr = tcp::ListenerAddr::new(SocketAddr::new(
-        "127.0.0.1".parse().unwrap(),
-        args.mock_port.unwrap_or(24566),
-    ));
 
     run_actix(async move {
         let MockNode { target_height, mut mock_peer, rpc_client } = setup_mock_node(
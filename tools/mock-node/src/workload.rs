@@ -0,0 +1,380 @@
+//! Synthetic workload generation for the mock node.
+//!
+//! Instead of replaying a recorded chain history, the workload mode starts a
+//! fresh single-validator chain and keeps feeding its client with
+//! transactions generated from a `WorkloadConfig`: transfers, account
+//! creations and function calls against the supplied contracts, mixed
+//! according to their weights and sent at the configured rate.
+
+use anyhow::Context;
+use futures::future::join_all;
+use near_chain_configs::test_utils::{add_account_with_key, get_initial_supply};
+use near_chain_configs::{Genesis, NEAR_BASE};
+use near_client::{GetBlock, ProcessTxRequest, ProcessTxResponse};
+use near_crypto::{InMemorySigner, KeyType};
+use near_network::tcp;
+use near_o11y::WithSpanContextExt;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::state_record::StateRecord;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{AccountId, Balance, BlockHeight, Gas};
+use nearcore::{load_test_config, start_with_config, NearConfig};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Account of the only validator of the generated chain.
+const VALIDATOR_ACCOUNT: &str = "test0";
+/// How often a new batch of transactions is sent to the client.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+/// How often the progress of the workload is logged.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+/// Amount transferred by the generated transfers.
+const TRANSFER_AMOUNT: Balance = 1;
+/// Amount the newly created accounts are funded with.
+const CREATE_ACCOUNT_AMOUNT: Balance = NEAR_BASE;
+
+/// Function calls against one contract, deployed on its own account in
+/// genesis.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct FunctionCallWorkload {
+    /// Path to the wasm file of the contract.
+    pub contract: PathBuf,
+    pub method_name: String,
+    /// JSON arguments passed to the method. Empty if not given.
+    #[serde(default)]
+    pub args: serde_json::Value,
+    #[serde(default = "default_gas")]
+    pub gas: Gas,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct WorkloadConfig {
+    /// Number of transactions sent to the client per second.
+    pub tps: f64,
+    /// Number of user accounts created in genesis, which sign all the
+    /// generated transactions.
+    #[serde(default = "default_num_accounts")]
+    pub num_accounts: u64,
+    #[serde(default)]
+    pub transfer_weight: u32,
+    #[serde(default)]
+    pub create_account_weight: u32,
+    #[serde(default)]
+    pub function_calls: Vec<FunctionCallWorkload>,
+    /// Seed for choosing the transactions, so that runs can be reproduced.
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn default_gas() -> Gas {
+    100_000_000_000_000
+}
+
+fn default_num_accounts() -> u64 {
+    100
+}
+
+impl WorkloadConfig {
+    pub fn from_file<P: AsRef<Path>>(path: &P) -> anyhow::Result<Self> {
+        let s = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&s)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.tps > 0.0, "tps must be positive, got {}", self.tps);
+        anyhow::ensure!(self.num_accounts > 0, "num_accounts must be positive");
+        anyhow::ensure!(
+            self.weights().iter().any(|w| *w > 0),
+            "at least one of the transaction kinds must have a positive weight"
+        );
+        Ok(())
+    }
+
+    fn weights(&self) -> Vec<u32> {
+        let mut weights = vec![self.transfer_weight, self.create_account_weight];
+        weights.extend(self.function_calls.iter().map(|f| f.weight));
+        weights
+    }
+
+    fn user_account(i: u64) -> AccountId {
+        format!("user{}", i).parse().unwrap()
+    }
+
+    fn contract_account(i: usize) -> AccountId {
+        format!("contract{}", i).parse().unwrap()
+    }
+
+    /// Builds the genesis of the chain the workload runs on: one validator,
+    /// `num_accounts` users, and one account per function call workload with
+    /// its contract deployed.
+    pub fn genesis(&self) -> anyhow::Result<Genesis> {
+        let accounts = std::iter::once(VALIDATOR_ACCOUNT.parse().unwrap())
+            .chain((0..self.num_accounts).map(Self::user_account))
+            .collect();
+        let mut genesis = Genesis::test(accounts, 1);
+        let records = &mut genesis.force_read_records().0;
+        for (i, function_call) in self.function_calls.iter().enumerate() {
+            let code = std::fs::read(&function_call.contract).with_context(|| {
+                format!("failed reading contract {}", function_call.contract.display())
+            })?;
+            let account_id = Self::contract_account(i);
+            let signer = InMemorySigner::from_seed(
+                account_id.clone(),
+                KeyType::ED25519,
+                account_id.as_ref(),
+            );
+            add_account_with_key(
+                records,
+                account_id.clone(),
+                &signer.public_key,
+                CREATE_ACCOUNT_AMOUNT * 1000,
+                0,
+                hash(&code),
+            );
+            records.push(StateRecord::Contract { account_id, code });
+        }
+        let total_supply = get_initial_supply(records);
+        genesis.config.total_supply = total_supply;
+        Ok(genesis)
+    }
+
+    /// Returns the config of the validator node producing the workload chain.
+    pub fn near_config(&self, addr: tcp::ListenerAddr) -> anyhow::Result<NearConfig> {
+        let mut near_config = load_test_config(VALIDATOR_ACCOUNT, addr, self.genesis()?);
+        near_config.client_config.min_num_peers = 0;
+        near_config.client_config.tracked_shards =
+            near_config.genesis.config.shard_layout.shard_ids().collect();
+        Ok(near_config)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TxKind {
+    Transfer,
+    CreateAccount,
+    FunctionCall(usize),
+}
+
+struct User {
+    account_id: AccountId,
+    signer: InMemorySigner,
+    nonce: u64,
+}
+
+/// Generates the transactions of a workload. All of them are signed by the
+/// genesis users, whose nonces are tracked here.
+struct WorkloadGenerator {
+    rng: StdRng,
+    kinds: Vec<TxKind>,
+    kind_index: WeightedIndex<u32>,
+    users: Vec<User>,
+    function_calls: Vec<FunctionCallWorkload>,
+    num_created_accounts: u64,
+}
+
+impl WorkloadGenerator {
+    fn new(config: &WorkloadConfig) -> anyhow::Result<Self> {
+        let mut kinds = vec![TxKind::Transfer, TxKind::CreateAccount];
+        kinds.extend((0..config.function_calls.len()).map(TxKind::FunctionCall));
+        let kind_index = WeightedIndex::new(config.weights())?;
+        let users = (0..config.num_accounts)
+            .map(|i| {
+                let account_id = WorkloadConfig::user_account(i);
+                let signer = InMemorySigner::from_seed(
+                    account_id.clone(),
+                    KeyType::ED25519,
+                    account_id.as_ref(),
+                );
+                User { account_id, signer, nonce: 0 }
+            })
+            .collect();
+        Ok(Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            kinds,
+            kind_index,
+            users,
+            function_calls: config.function_calls.clone(),
+            num_created_accounts: 0,
+        })
+    }
+
+    fn next_tx(&mut self, block_hash: CryptoHash) -> SignedTransaction {
+        let kind = self.kinds[self.kind_index.sample(&mut self.rng)];
+        let receiver_idx = self.rng.gen_range(0..self.users.len());
+        let receiver_id = self.users[receiver_idx].account_id.clone();
+        let signer_idx = self.rng.gen_range(0..self.users.len());
+        let user = &mut self.users[signer_idx];
+        user.nonce += 1;
+        match kind {
+            TxKind::Transfer => SignedTransaction::send_money(
+                user.nonce,
+                user.account_id.clone(),
+                receiver_id,
+                &user.signer,
+                TRANSFER_AMOUNT,
+                block_hash,
+            ),
+            TxKind::CreateAccount => {
+                let new_account_id =
+                    format!("a{}.{}", self.num_created_accounts, user.account_id).parse().unwrap();
+                self.num_created_accounts += 1;
+                SignedTransaction::create_account(
+                    user.nonce,
+                    user.account_id.clone(),
+                    new_account_id,
+                    CREATE_ACCOUNT_AMOUNT,
+                    user.signer.public_key.clone(),
+                    &user.signer,
+                    block_hash,
+                )
+            }
+            TxKind::FunctionCall(i) => {
+                let function_call = &self.function_calls[i];
+                let args = match &function_call.args {
+                    serde_json::Value::Null => vec![],
+                    args => serde_json::to_vec(args).unwrap(),
+                };
+                SignedTransaction::call(
+                    user.nonce,
+                    user.account_id.clone(),
+                    WorkloadConfig::contract_account(i),
+                    &user.signer,
+                    0,
+                    function_call.method_name.clone(),
+                    args,
+                    function_call.gas,
+                    block_hash,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct WorkloadStats {
+    sent: u64,
+    invalid: u64,
+    dropped: u64,
+}
+
+/// Starts the validator node in `home_dir` and sends it the transactions of
+/// the workload until the chain reaches `target_height`, or forever if it's
+/// not given. Must be called from within an actix system.
+pub async fn run_workload(
+    home_dir: &Path,
+    near_config: NearConfig,
+    config: &WorkloadConfig,
+    target_height: Option<BlockHeight>,
+) -> anyhow::Result<()> {
+    let store_path =
+        home_dir.join(near_config.config.store.path.as_deref().unwrap_or(Path::new("data")));
+    anyhow::ensure!(
+        !store_path.exists(),
+        "{} already contains a database, the workload needs a fresh home dir",
+        home_dir.display()
+    );
+    let mut generator = WorkloadGenerator::new(config)?;
+    let nearcore::NearNode { client, view_client, .. } =
+        start_with_config(home_dir, near_config).context("failed starting the node")?;
+
+    let mut interval = tokio::time::interval(TICK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut stats = WorkloadStats::default();
+    let mut last_report = Instant::now();
+    // Fractional number of transactions carried over to the next tick, so
+    // that low rates are still honored.
+    let mut pending = 0.0;
+    loop {
+        interval.tick().await;
+        let block = match view_client.send(GetBlock::latest().with_span_context()).await? {
+            Ok(block) => block,
+            Err(err) => {
+                tracing::debug!(target: "mock_node", ?err, "failed fetching the latest block");
+                continue;
+            }
+        };
+        if target_height.is_some_and(|target_height| block.header.height >= target_height) {
+            tracing::info!(target: "mock_node", height = block.header.height, "workload reached target height");
+            return Ok(());
+        }
+
+        pending += config.tps * TICK_INTERVAL.as_secs_f64();
+        let num_txs = pending as u64;
+        pending -= num_txs as f64;
+        let requests = (0..num_txs).map(|_| {
+            let transaction = generator.next_tx(block.header.hash);
+            client.send(
+                ProcessTxRequest { transaction, is_forwarded: false, check_only: false }
+                    .with_span_context(),
+            )
+        });
+        for response in join_all(requests.collect::<Vec<_>>()).await {
+            match response? {
+                ProcessTxResponse::ValidTx | ProcessTxResponse::RequestRouted => stats.sent += 1,
+                ProcessTxResponse::InvalidTx(err) => {
+                    tracing::debug!(target: "mock_node", ?err, "generated an invalid transaction");
+                    stats.invalid += 1;
+                }
+                ProcessTxResponse::NoResponse | ProcessTxResponse::DoesNotTrackShard => {
+                    stats.dropped += 1
+                }
+            }
+        }
+
+        if last_report.elapsed() >= REPORT_INTERVAL {
+            tracing::info!(
+                target: "mock_node",
+                height = block.header.height,
+                sent = stats.sent,
+                invalid = stats.invalid,
+                dropped = stats.dropped,
+                "workload progress"
+            );
+            last_report = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TxKind, WorkloadConfig, WorkloadGenerator};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::transaction::Action;
+
+    fn config(transfer_weight: u32, create_account_weight: u32) -> WorkloadConfig {
+        serde_json::from_value(serde_json::json!({
+            "tps": 10.0,
+            "num_accounts": 3,
+            "transfer_weight": transfer_weight,
+            "create_account_weight": create_account_weight,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_workload_config_validation() {
+        assert!(config(1, 0).validate().is_ok());
+        assert!(config(0, 0).validate().is_err());
+    }
+
+    #[test]
+    fn test_workload_generator() {
+        let mut generator = WorkloadGenerator::new(&config(0, 1)).unwrap();
+        assert_eq!(generator.kinds[..2], [TxKind::Transfer, TxKind::CreateAccount]);
+        let mut last_nonce = std::collections::HashMap::new();
+        for _ in 0..20 {
+            let tx = generator.next_tx(CryptoHash::default());
+            assert!(matches!(tx.transaction.actions()[0], Action::CreateAccount(_)));
+            let nonce = tx.transaction.nonce();
+            let last = last_nonce.insert(tx.transaction.signer_id().clone(), nonce);
+            assert!(last.map_or(true, |last| last < nonce));
+        }
+        assert_eq!(generator.num_created_accounts, 20);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::download::SnapshotDownloader;
+use crate::{create_snapshot, server, store_paths};
+use anyhow::Context;
+use nearcore::config::{Config, CONFIG_FILENAME};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Parser)]
+pub struct SnapshotServerCommand {
+    /// Address to serve the snapshot on.
+    #[clap(long, default_value = "0.0.0.0:3040")]
+    addr: SocketAddr,
+    /// Directory the snapshot is created in. Any previous snapshot there is
+    /// removed. Defaults to `snapshot` in the home dir. It should be on the
+    /// same filesystem as the databases, so that the checkpoints are made of
+    /// hard links and don't take additional space.
+    #[clap(long)]
+    snapshot_dir: Option<PathBuf>,
+    /// Include the cold database in the snapshot.
+    #[clap(long)]
+    include_cold: bool,
+}
+
+impl SnapshotServerCommand {
+    pub fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        let config = Config::from_file(&home_dir.join(CONFIG_FILENAME))?;
+        let snapshot_dir = self.snapshot_dir.unwrap_or_else(|| home_dir.join("snapshot"));
+        let manifest = create_snapshot(home_dir, &config, &snapshot_dir, self.include_cold)
+            .context("failed creating the snapshot")?;
+        tracing::info!(
+            target: "snapshot-server",
+            id = %manifest.id,
+            head_height = manifest.head_height,
+            num_files = manifest.files.len(),
+            total_size = manifest.total_size(),
+            "created snapshot"
+        );
+        actix::System::new().block_on(server::serve(self.addr, snapshot_dir, manifest))
+    }
+}
+
+#[derive(clap::Parser)]
+pub struct DownloadSnapshotCommand {
+    /// Address of the `snapshot-server` to download from, e.g. `http://10.0.0.1:3040`.
+    #[clap(long)]
+    url: String,
+    /// How many times the download of a file is retried, resuming where it
+    /// stopped.
+    #[clap(long, default_value_t = 10)]
+    max_retries: u32,
+}
+
+impl DownloadSnapshotCommand {
+    /// Downloads the snapshot into the database directories configured in
+    /// the config of the node in `home_dir`.
+    pub fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        let config = Config::from_file(&home_dir.join(CONFIG_FILENAME))?;
+        let (hot_dir, cold_dir) = store_paths(home_dir, &config);
+        SnapshotDownloader::new(&self.url, hot_dir, cold_dir, self.max_retries)?.run()
+    }
+}
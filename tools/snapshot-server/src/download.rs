@@ -0,0 +1,219 @@
+use crate::{file_hash, SnapshotFile, SnapshotManifest, MANIFEST_FILENAME};
+use anyhow::Context;
+use reqwest::header;
+use reqwest::StatusCode;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Written to the hot database directory while a download is in progress, so
+/// that an interrupted download is only resumed from the same snapshot.
+const DOWNLOAD_MARKER_FILENAME: &str = "SNAPSHOT_DOWNLOAD";
+
+pub(crate) struct SnapshotDownloader {
+    client: reqwest::blocking::Client,
+    url: String,
+    hot_dir: PathBuf,
+    cold_dir: Option<PathBuf>,
+    max_retries: u32,
+}
+
+impl SnapshotDownloader {
+    pub(crate) fn new(
+        url: &str,
+        hot_dir: PathBuf,
+        cold_dir: Option<PathBuf>,
+        max_retries: u32,
+    ) -> anyhow::Result<Self> {
+        // The files can be large, so the requests don't time out as a whole.
+        let client = reqwest::blocking::Client::builder()
+            .timeout(None)
+            .connect_timeout(Duration::from_secs(30))
+            .build()?;
+        Ok(Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            hot_dir,
+            cold_dir,
+            max_retries,
+        })
+    }
+
+    pub(crate) fn run(&self) -> anyhow::Result<()> {
+        let manifest = self.get_manifest()?;
+        tracing::info!(
+            target: "snapshot-server",
+            id = %manifest.id,
+            head_height = manifest.head_height,
+            num_files = manifest.files.len(),
+            total_size = manifest.total_size(),
+            "downloading snapshot"
+        );
+        self.check_destination(&manifest)?;
+
+        let mut downloaded = 0;
+        for file in &manifest.files {
+            let Some(path) = self.local_path(file) else {
+                tracing::warn!(target: "snapshot-server", path = %file.path, "no cold store configured, skipping");
+                continue;
+            };
+            self.download_file_with_retries(file, &path)?;
+            downloaded += file.size;
+            tracing::info!(
+                target: "snapshot-server",
+                path = %file.path,
+                progress = %format_args!("{}/{}", downloaded, manifest.total_size()),
+                "downloaded file"
+            );
+        }
+        std::fs::remove_file(self.hot_dir.join(DOWNLOAD_MARKER_FILENAME))?;
+        tracing::info!(target: "snapshot-server", id = %manifest.id, "snapshot downloaded");
+        Ok(())
+    }
+
+    fn get_manifest(&self) -> anyhow::Result<SnapshotManifest> {
+        let url = format!("{}/{}", self.url, MANIFEST_FILENAME);
+        let response = self.client.get(&url).send()?.error_for_status()?;
+        let manifest: SnapshotManifest = serde_json::from_slice(&response.bytes()?)
+            .with_context(|| format!("failed parsing the manifest at {}", url))?;
+        for file in &manifest.files {
+            let valid = match file.path.split_once('/') {
+                Some(("hot" | "cold", name)) => {
+                    !name.is_empty() && !name.contains('/') && name != "." && name != ".."
+                }
+                _ => false,
+            };
+            anyhow::ensure!(valid, "unexpected file {} in the manifest", file.path);
+        }
+        Ok(manifest)
+    }
+
+    /// Makes sure that the destination is empty, or contains a partial
+    /// download of the same snapshot, and marks it as being downloaded.
+    fn check_destination(&self, manifest: &SnapshotManifest) -> anyhow::Result<()> {
+        let marker_path = self.hot_dir.join(DOWNLOAD_MARKER_FILENAME);
+        if marker_path.exists() {
+            let previous: SnapshotManifest = serde_json::from_slice(&std::fs::read(&marker_path)?)?;
+            anyhow::ensure!(
+                previous.id == manifest.id,
+                "{} contains a partial download of snapshot {}, but the server now serves snapshot {}, remove it to start over",
+                self.hot_dir.display(),
+                previous.id,
+                manifest.id
+            );
+            tracing::info!(target: "snapshot-server", "resuming the previous download");
+        } else {
+            for dir in std::iter::once(&self.hot_dir).chain(&self.cold_dir) {
+                anyhow::ensure!(
+                    !dir.exists() || dir.read_dir()?.next().is_none(),
+                    "{} is not empty, refusing to overwrite the database",
+                    dir.display()
+                );
+            }
+        }
+        for dir in std::iter::once(&self.hot_dir).chain(&self.cold_dir) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&marker_path, serde_json::to_vec(manifest)?)?;
+        Ok(())
+    }
+
+    /// Returns where the file is downloaded to, or `None` for the files of the
+    /// cold database if this node doesn't have one.
+    fn local_path(&self, file: &SnapshotFile) -> Option<PathBuf> {
+        match file.path.split_once('/').unwrap() {
+            ("hot", name) => Some(self.hot_dir.join(name)),
+            (_, name) => self.cold_dir.as_ref().map(|dir| dir.join(name)),
+        }
+    }
+
+    fn download_file_with_retries(&self, file: &SnapshotFile, path: &Path) -> anyhow::Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.download_file(file, path) {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt < self.max_retries => {
+                    attempt += 1;
+                    tracing::warn!(target: "snapshot-server", path = %file.path, attempt, ?err, "download failed, retrying");
+                    std::thread::sleep(Duration::from_secs(attempt.into()));
+                }
+                Err(err) => {
+                    return Err(err.context(format!("failed downloading {}", file.path)));
+                }
+            }
+        }
+    }
+
+    /// Downloads the part of the file which isn't on disk yet, and checks the
+    /// hash of the whole file.
+    fn download_file(&self, file: &SnapshotFile, path: &Path) -> anyhow::Result<()> {
+        let mut local_size = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err.into()),
+        };
+        if local_size > file.size {
+            std::fs::remove_file(path)?;
+            local_size = 0;
+        }
+        if local_size == file.size {
+            // Downloaded by a previous run, which may have been interrupted
+            // before checking it.
+            return verify_file(file, path);
+        }
+
+        let mut request = self.client.get(format!("{}/files/{}", self.url, file.path));
+        if local_size > 0 {
+            request = request.header(header::RANGE, format!("bytes={}-", local_size));
+        }
+        let mut response = request.send()?.error_for_status()?;
+        let mut out = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        if response.status() != StatusCode::PARTIAL_CONTENT && local_size > 0 {
+            // The server sent the whole file.
+            out.set_len(0)?;
+        }
+        response.copy_to(&mut out)?;
+        out.sync_all()?;
+        let size = out.metadata()?.len();
+        anyhow::ensure!(size == file.size, "expected {} bytes, got {}", file.size, size);
+        verify_file(file, path)
+    }
+}
+
+/// Checks that the file at `path` has the hash listed in the manifest. A
+/// corrupted file is removed, so that the retry downloads it from scratch.
+fn verify_file(file: &SnapshotFile, path: &Path) -> anyhow::Result<()> {
+    let hash = file_hash(path)?;
+    if hash != file.hash {
+        std::fs::remove_file(path)?;
+        anyhow::bail!("expected hash {}, got {}, removed the file", file.hash, hash);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_file;
+    use crate::SnapshotFile;
+    use near_primitives::hash::hash;
+
+    #[test]
+    fn test_verify_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("000042.sst");
+        let data = b"snapshot file contents";
+        std::fs::write(&path, data).unwrap();
+        let file = SnapshotFile {
+            path: "hot/000042.sst".to_string(),
+            size: data.len() as u64,
+            hash: hash(data),
+        };
+        verify_file(&file, &path).unwrap();
+        assert!(path.exists());
+
+        // Same size, different contents, e.g. a resumed download of a file
+        // whose beginning got corrupted.
+        std::fs::write(&path, b"snapshot file CONTENTS").unwrap();
+        assert!(verify_file(&file, &path).is_err());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,154 @@
+//! Serves checkpoints of the node's databases over HTTP and downloads them, so
+//! that new nodes can be provisioned from existing ones.
+//!
+//! A snapshot is a RocksDB checkpoint of the hot database, and optionally of
+//! the cold database, along with a manifest listing its files. The files are
+//! served with support for range requests, which lets the client resume
+//! interrupted downloads.
+
+use near_primitives::block::Tip;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+use near_store::db::Database;
+use near_store::{DBCol, Mode, NodeStorage, Temperature, HEAD_KEY};
+use nearcore::config::Config;
+use sha2::Digest;
+use std::path::{Path, PathBuf};
+
+pub mod cli;
+mod download;
+mod server;
+
+const MANIFEST_FILENAME: &str = "manifest.json";
+
+/// Describes a snapshot, served at `/manifest.json`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct SnapshotManifest {
+    /// Unique id of the snapshot, so that resumed downloads don't mix the
+    /// files of different snapshots.
+    pub id: String,
+    /// Head of the chain in the snapshot.
+    pub head_height: BlockHeight,
+    pub head_hash: CryptoHash,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// A file of the snapshot. Its path starts with the temperature of the
+/// database it belongs to, e.g. `hot/000042.sst`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub size: u64,
+    /// SHA-256 of the contents of the file, checked once it's downloaded.
+    pub hash: CryptoHash,
+}
+
+impl SnapshotManifest {
+    fn total_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+fn temperature_dir(temperature: Temperature) -> &'static str {
+    match temperature {
+        Temperature::Hot => "hot",
+        Temperature::Cold => "cold",
+    }
+}
+
+/// Computes the SHA-256 of the contents of the file at `path` without reading
+/// it into memory as a whole.
+fn file_hash(path: &Path) -> std::io::Result<CryptoHash> {
+    let mut hasher = sha2::Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(CryptoHash(hasher.finalize().into()))
+}
+
+/// Returns the directories of the hot and cold databases configured in `config`.
+fn store_paths(home_dir: &Path, config: &Config) -> (PathBuf, Option<PathBuf>) {
+    let hot = home_dir.join(config.store.path.as_deref().unwrap_or(Path::new("data")));
+    let cold = config.cold_store.as_ref().map(|cold_store| {
+        home_dir.join(cold_store.path.as_deref().unwrap_or(Path::new("cold-data")))
+    });
+    (hot, cold)
+}
+
+/// Checkpoints the databases of the node in `home_dir` into `snapshot_dir`,
+/// replacing the snapshot that may already be there, and writes its manifest.
+///
+/// The databases are only opened while the checkpoints are created, which
+/// takes seconds as the files are hard links, after which the node can be
+/// started again while the files are hashed.
+fn create_snapshot(
+    home_dir: &Path,
+    config: &Config,
+    snapshot_dir: &Path,
+    include_cold: bool,
+) -> anyhow::Result<SnapshotManifest> {
+    let cold_config = if include_cold {
+        Some(config.cold_store.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("cold store is not configured, can't include it in the snapshot")
+        })?)
+    } else {
+        None
+    };
+    let storage = NodeStorage::opener(home_dir, config.archive, &config.store, cold_config)
+        .open_in_mode(Mode::ReadWriteExisting)
+        .map_err(|err| {
+            anyhow::anyhow!("failed opening the database, is the node still running? {}", err)
+        })?;
+    let head = storage
+        .get_hot_store()
+        .get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)?
+        .ok_or_else(|| anyhow::anyhow!("the database has no head"))?;
+
+    if snapshot_dir.exists() {
+        std::fs::remove_dir_all(snapshot_dir)?;
+    }
+    std::fs::create_dir_all(snapshot_dir)?;
+    let cold_db = storage.cold_db().cloned();
+    let hot_db = storage.into_inner(Temperature::Hot);
+    hot_db.create_checkpoint(&snapshot_dir.join(temperature_dir(Temperature::Hot)), None)?;
+    if let Some(cold_db) = cold_db {
+        cold_db.create_checkpoint(&snapshot_dir.join(temperature_dir(Temperature::Cold)), None)?;
+    }
+    // Hashing the files takes much longer than creating the checkpoints, and
+    // doesn't need the databases.
+    drop(hot_db);
+    tracing::info!(target: "snapshot-server", head_height = head.height, "created checkpoints, the node can be started again");
+
+    let mut files = vec![];
+    for temperature in [Temperature::Hot, Temperature::Cold] {
+        let dir = snapshot_dir.join(temperature_dir(temperature));
+        if !dir.exists() {
+            continue;
+        }
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            anyhow::ensure!(metadata.is_file(), "unexpected directory {:?}", entry.path());
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|name| anyhow::anyhow!("file name {:?} is not valid unicode", name))?;
+            files.push(SnapshotFile {
+                path: format!("{}/{}", temperature_dir(temperature), name),
+                size: metadata.len(),
+                hash: file_hash(&entry.path())?,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let created_at = chrono::Utc::now();
+    let manifest = SnapshotManifest {
+        id: format!("{}-{}", head.height, created_at.timestamp()),
+        head_height: head.height,
+        head_hash: head.last_block_hash,
+        created_at,
+        files,
+    };
+    std::fs::write(snapshot_dir.join(MANIFEST_FILENAME), serde_json::to_vec_pretty(&manifest)?)?;
+    Ok(manifest)
+}
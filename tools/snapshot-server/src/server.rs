@@ -0,0 +1,120 @@
+use crate::{SnapshotManifest, MANIFEST_FILENAME};
+use actix_web::body::SizedStream;
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+struct ServerState {
+    snapshot_dir: PathBuf,
+    manifest: SnapshotManifest,
+    /// Sizes of the files of the snapshot by their path. Only these files are
+    /// served.
+    file_sizes: HashMap<String, u64>,
+}
+
+/// Serves the snapshot in `snapshot_dir` described by `manifest` until the
+/// process is stopped.
+pub(crate) async fn serve(
+    addr: SocketAddr,
+    snapshot_dir: PathBuf,
+    manifest: SnapshotManifest,
+) -> anyhow::Result<()> {
+    let file_sizes = manifest.files.iter().map(|file| (file.path.clone(), file.size)).collect();
+    let state = web::Data::new(ServerState { snapshot_dir, manifest, file_sizes });
+    tracing::info!(target: "snapshot-server", %addr, "serving the snapshot");
+    HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .route(&format!("/{}", MANIFEST_FILENAME), web::get().to(get_manifest))
+            .route("/files/{path:.*}", web::get().to(get_file))
+    })
+    .bind(addr)?
+    .run()
+    .await?;
+    Ok(())
+}
+
+async fn get_manifest(state: web::Data<ServerState>) -> HttpResponse {
+    HttpResponse::Ok().json(&state.manifest)
+}
+
+async fn get_file(
+    request: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<ServerState>,
+) -> actix_web::Result<HttpResponse> {
+    let path = path.into_inner();
+    let Some(&size) = state.file_sizes.get(&path) else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let range = match request.headers().get(header::RANGE) {
+        Some(value) => match value.to_str().ok().and_then(|value| parse_range(value, size)) {
+            Some(range) => Some(range),
+            None => {
+                return Ok(HttpResponse::RangeNotSatisfiable()
+                    .insert_header((header::CONTENT_RANGE, format!("bytes */{}", size)))
+                    .finish())
+            }
+        },
+        None => None,
+    };
+    let (start, end) = range.unwrap_or((0, size));
+
+    let mut file = tokio::fs::File::open(state.snapshot_dir.join(&path)).await?;
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+    let body = SizedStream::new(end - start, ReaderStream::new(file.take(end - start)));
+    tracing::debug!(target: "snapshot-server", %path, start, end, "serving file");
+
+    let mut response = HttpResponse::build(if range.is_some() {
+        StatusCode::PARTIAL_CONTENT
+    } else {
+        StatusCode::OK
+    });
+    response.insert_header((header::ACCEPT_RANGES, "bytes"));
+    if range.is_some() {
+        response.insert_header((
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, end - 1, size),
+        ));
+    }
+    Ok(response.body(body))
+}
+
+/// Parses the value of a `Range` header with a single range of bytes, and
+/// returns the range as start inclusive and end exclusive offsets. Returns
+/// `None` if the range is malformed or not satisfiable.
+fn parse_range(value: &str, size: u64) -> Option<(u64, u64)> {
+    let (start, end) = value.strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        // The last `end` bytes.
+        ("", end) => (size.saturating_sub(end.parse().ok()?), size),
+        (start, "") => (start.parse().ok()?, size),
+        (start, end) => {
+            let end: u64 = end.parse().ok()?;
+            (start.parse().ok()?, end.saturating_add(1).min(size))
+        }
+    };
+    (start < end).then_some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_range;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 100)));
+        assert_eq!(parse_range("bytes=100-", 1000), Some((100, 1000)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 1000)));
+        assert_eq!(parse_range("bytes=900-2000", 1000), Some((900, 1000)));
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=5-1", 1000), None);
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+    }
+}
@@ -252,6 +252,7 @@ fn load_snapshot(load_cmd: LoadCmd) {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            shadow_chunk_validation: MutableConfigValue::new(false, "shadow_chunk_validation"),
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
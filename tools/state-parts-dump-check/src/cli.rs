@@ -2,8 +2,9 @@ use actix_web::{web, App, HttpServer};
 use anyhow::anyhow;
 use borsh::BorshDeserialize;
 use near_client::sync::external::{
-    create_bucket_readonly, external_storage_location, external_storage_location_directory,
-    get_num_parts_from_filename, ExternalConnection, StateFileType,
+    create_bucket_readonly, decompress_state_part, external_storage_location,
+    external_storage_location_directory, get_num_parts_from_filename, ExternalConnection,
+    StateFileType,
 };
 use near_jsonrpc::client::{new_client, JsonRpcClient};
 use near_primitives::hash::CryptoHash;
@@ -808,7 +809,7 @@ async fn process_part(
     let file_type = StateFileType::StatePart { part_id, num_parts };
     let location =
         external_storage_location(&chain_id, &epoch_id, epoch_height, shard_id, &file_type);
-    let part = external.get_file(shard_id, &location, &file_type).await?;
+    let part = decompress_state_part(external.get_file(shard_id, &location, &file_type).await?)?;
     let is_part_valid = validate_state_part(&state_root, PartId::new(part_id, num_parts), &part);
     if is_part_valid {
         crate::metrics::STATE_SYNC_DUMP_CHECK_NUM_PARTS_VALID
@@ -1,4 +1,5 @@
 use crate::cli::ApplyRangeMode;
+use borsh::BorshDeserialize;
 use near_chain::chain::collect_receipts_from_response;
 use near_chain::migrations::check_if_block_is_first_with_chunk_of_version;
 use near_chain::types::{
@@ -8,16 +9,23 @@ use near_chain::types::{
 use near_chain::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
 use near_chain_configs::Genesis;
 use near_epoch_manager::{EpochManagerAdapter, EpochManagerHandle};
+use near_primitives::account::Account;
 use near_primitives::apply::ApplyChunkReason;
+use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::DelayedReceiptIndices;
-use near_primitives::transaction::{Action, ExecutionOutcomeWithId, ExecutionOutcomeWithProof};
+use near_primitives::transaction::{
+    Action, ExecutionOutcomeWithId, ExecutionOutcomeWithProof, ExecutionStatus,
+};
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::chunk_extra::ChunkExtra;
-use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::types::{
+    BlockHeight, RawStateChange, RawStateChangesWithTrieKey, ShardId, StateChangeCause,
+};
 use near_store::flat::{BlockInfo, FlatStateChanges, FlatStorageStatus};
-use near_store::{DBCol, Store};
+use near_store::{DBCol, Store, WrappedTrieChanges};
 use nearcore::NightshadeRuntime;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -113,6 +121,80 @@ fn maybe_add_to_csv(csv_file_mutex: &Mutex<Option<&mut File>>, s: &str) {
     }
 }
 
+/// Writes one row per execution outcome of the applied chunk, so that the gas
+/// and tokens burnt by individual receipts and the balance changes they cause
+/// can be audited without parsing the verbose output.
+fn maybe_add_outcomes_to_csv(
+    receipts_csv_file_mutex: &Mutex<Option<&mut File>>,
+    height: BlockHeight,
+    shard_id: ShardId,
+    outcomes: &[ExecutionOutcomeWithId],
+    trie_changes: &WrappedTrieChanges,
+) {
+    let mut csv_file = receipts_csv_file_mutex.lock().unwrap();
+    let Some(csv_file) = csv_file.as_mut() else {
+        return;
+    };
+    let balance_deltas = balance_deltas(trie_changes);
+    for ExecutionOutcomeWithId { id, outcome } in outcomes {
+        let status = match outcome.status {
+            ExecutionStatus::Unknown => "Unknown",
+            ExecutionStatus::Failure(_) => "Failure",
+            ExecutionStatus::SuccessValue(_) => "SuccessValue",
+            ExecutionStatus::SuccessReceiptId(_) => "SuccessReceiptId",
+        };
+        writeln!(
+            csv_file,
+            "{},{},{},{},{},{},{},{},{}",
+            height,
+            shard_id,
+            id,
+            outcome.executor_id,
+            outcome.gas_burnt,
+            outcome.tokens_burnt,
+            status,
+            outcome.receipt_ids.len(),
+            balance_deltas.get(id).copied().unwrap_or_default(),
+        )
+        .unwrap();
+    }
+}
+
+/// Returns by how much each transaction and receipt changed the total balance,
+/// liquid and locked, of the accounts of the shard, keyed by its id. These are
+/// the deltas the balance checker adds up for the whole chunk.
+fn balance_deltas(trie_changes: &WrappedTrieChanges) -> HashMap<CryptoHash, i128> {
+    let total_balance = |value: &[u8]| {
+        let account = Account::try_from_slice(value).unwrap();
+        (account.amount() + account.locked()) as i128
+    };
+    let old_trie = trie_changes.old_trie();
+    let mut deltas = HashMap::new();
+    for RawStateChangesWithTrieKey { trie_key, changes } in trie_changes.state_changes() {
+        if !matches!(trie_key, TrieKey::Account { .. }) {
+            continue;
+        }
+        let mut balance =
+            old_trie.get(&trie_key.to_vec()).unwrap().map_or(0, |value| total_balance(&value));
+        for RawStateChange { cause, data } in changes {
+            let new_balance = data.as_deref().map_or(0, total_balance);
+            let id = match cause {
+                StateChangeCause::TransactionProcessing { tx_hash } => Some(tx_hash),
+                StateChangeCause::ActionReceiptProcessingStarted { receipt_hash }
+                | StateChangeCause::ActionReceiptGasReward { receipt_hash }
+                | StateChangeCause::ReceiptProcessing { receipt_hash }
+                | StateChangeCause::PostponedReceipt { receipt_hash } => Some(receipt_hash),
+                _ => None,
+            };
+            if let Some(id) = id {
+                *deltas.entry(*id).or_default() += new_balance - balance;
+            }
+            balance = new_balance;
+        }
+    }
+    deltas
+}
+
 fn apply_block_from_range(
     mode: ApplyRangeMode,
     height: BlockHeight,
@@ -124,6 +206,7 @@ fn apply_block_from_range(
     progress_reporter: &ProgressReporter,
     verbose_output: bool,
     csv_file_mutex: &Mutex<Option<&mut File>>,
+    receipts_csv_file_mutex: &Mutex<Option<&mut File>>,
     only_contracts: bool,
     use_flat_storage: bool,
 ) {
@@ -184,12 +267,13 @@ fn apply_block_from_range(
                 maybe_add_to_csv(
                     csv_file_mutex,
                     &format!(
-                        "{},{},{},,,{},,{},,",
+                        "{},{},{},,,{},,{},,,,{}",
                         height,
                         block_hash,
                         block_author,
                         block.header().raw_timestamp(),
-                        chunk_present
+                        chunk_present,
+                        shard_id,
                     ),
                 );
                 progress_reporter.inc_and_report_progress(0);
@@ -316,10 +400,17 @@ fn apply_block_from_range(
             }
         }
     };
+    maybe_add_outcomes_to_csv(
+        receipts_csv_file_mutex,
+        height,
+        shard_id,
+        &apply_result.outcomes,
+        &apply_result.trie_changes,
+    );
     maybe_add_to_csv(
         csv_file_mutex,
         &format!(
-            "{},{},{},{},{},{},{},{},{},{},{}",
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
             height,
             block_hash,
             block_author,
@@ -331,6 +422,7 @@ fn apply_block_from_range(
             apply_result.processed_delayed_receipts.len(),
             delayed_indices.unwrap_or(None).map_or(0, |d| d.next_available_index - d.first_index),
             apply_result.trie_changes.state_changes().len(),
+            shard_id,
         ),
     );
     progress_reporter.inc_and_report_progress(apply_result.total_gas_burnt);
@@ -370,11 +462,12 @@ pub fn apply_chain_range(
     genesis: &Genesis,
     start_height: Option<BlockHeight>,
     end_height: Option<BlockHeight>,
-    shard_id: ShardId,
+    shard_ids: &[ShardId],
     epoch_manager: &EpochManagerHandle,
     runtime_adapter: Arc<NightshadeRuntime>,
     verbose_output: bool,
     csv_file: Option<&mut File>,
+    receipts_csv_file: Option<&mut File>,
     only_contracts: bool,
     use_flat_storage: bool,
 ) {
@@ -384,7 +477,7 @@ pub fn apply_chain_range(
         ?mode,
         ?start_height,
         ?end_height,
-        %shard_id,
+        ?shard_ids,
         only_contracts,
         use_flat_storage)
     .entered();
@@ -396,6 +489,9 @@ pub fn apply_chain_range(
             assert!(use_flat_storage);
             assert!(start_height.is_none());
             assert!(end_height.is_none());
+            let &[shard_id] = shard_ids else {
+                panic!("benchmarking mode applies a single shard, got {shard_ids:?}");
+            };
 
             let chain_store = ChainStore::new(store.clone(), genesis.config.genesis_height, false);
             let final_head = chain_store.final_head().unwrap();
@@ -426,25 +522,30 @@ pub fn apply_chain_range(
     };
 
     println!(
-        "Applying chunks in the range {}..={} for shard_id {}",
-        start_height, end_height, shard_id
+        "Applying chunks in the range {}..={} for shard_ids {:?}",
+        start_height, end_height, shard_ids
     );
 
     println!("Printing results including outcomes of applying receipts");
     let csv_file_mutex = Mutex::new(csv_file);
-    maybe_add_to_csv(&csv_file_mutex, "Height,Hash,Author,#Tx,#Receipt,Timestamp,GasUsed,ChunkPresent,#ProcessedDelayedReceipts,#DelayedReceipts,#StateChanges");
+    maybe_add_to_csv(&csv_file_mutex, "Height,Hash,Author,#Tx,#Receipt,Timestamp,GasUsed,ChunkPresent,#ProcessedDelayedReceipts,#DelayedReceipts,#StateChanges,ShardId");
+    let receipts_csv_file_mutex = Mutex::new(receipts_csv_file);
+    maybe_add_to_csv(
+        &receipts_csv_file_mutex,
+        "Height,ShardId,Id,ExecutorId,GasBurnt,TokensBurnt,Status,#Receipts,BalanceDelta",
+    );
 
     let range = start_height..=end_height;
     let progress_reporter = ProgressReporter {
         cnt: AtomicU64::new(0),
         ts: AtomicU64::new(timestamp_ms()),
-        all: (end_height + 1).saturating_sub(start_height),
+        all: (end_height + 1).saturating_sub(start_height) * shard_ids.len() as u64,
         skipped: AtomicU64::new(0),
         empty_blocks: AtomicU64::new(0),
         non_empty_blocks: AtomicU64::new(0),
         tgas_burned: AtomicU64::new(0),
     };
-    let process_height = |height| {
+    let process_height = |shard_id, height| {
         apply_block_from_range(
             mode,
             height,
@@ -456,6 +557,7 @@ pub fn apply_chain_range(
             &progress_reporter,
             verbose_output,
             &csv_file_mutex,
+            &receipts_csv_file_mutex,
             only_contracts,
             use_flat_storage,
         );
@@ -463,32 +565,43 @@ pub fn apply_chain_range(
 
     match mode {
         ApplyRangeMode::Sequential | ApplyRangeMode::Benchmarking => {
-            range.into_iter().for_each(|height| {
-                let _span = tracing::debug_span!(
-                    target: "state_viewer",
-                    parent: &parent_span,
-                    "process_block_in_order",
-                    height)
-                .entered();
-                process_height(height)
-            });
+            for &shard_id in shard_ids {
+                range.clone().for_each(|height| {
+                    let _span = tracing::debug_span!(
+                        target: "state_viewer",
+                        parent: &parent_span,
+                        "process_block_in_order",
+                        shard_id,
+                        height)
+                    .entered();
+                    process_height(shard_id, height)
+                });
+            }
         }
         ApplyRangeMode::Parallel => {
-            range.into_par_iter().for_each(|height| {
+            // Chunks of all shards and heights are independent of each other,
+            // so they are all processed in parallel rather than one shard at a
+            // time.
+            let chunks: Vec<(ShardId, BlockHeight)> = shard_ids
+                .iter()
+                .flat_map(|&shard_id| range.clone().map(move |height| (shard_id, height)))
+                .collect();
+            chunks.into_par_iter().for_each(|(shard_id, height)| {
                 let _span = tracing::debug_span!(
                 target: "mock_node",
                 parent: &parent_span,
                 "process_block_in_parallel",
+                shard_id,
                 height)
                 .entered();
-                process_height(height)
+                process_height(shard_id, height)
             });
         }
     }
 
     println!(
-        "No differences found after applying chunks in the range {}..={} for shard_id {}",
-        start_height, end_height, shard_id
+        "No differences found after applying chunks in the range {}..={} for shard_ids {:?}",
+        start_height, end_height, shard_ids
     );
 }
 
@@ -634,11 +747,12 @@ mod test {
             &genesis,
             None,
             None,
-            0,
+            &[0],
             epoch_manager.as_ref(),
             runtime,
             true,
             None,
+            None,
             false,
             false,
         );
@@ -671,17 +785,19 @@ mod test {
             epoch_manager.clone(),
         );
         let mut file = tempfile::NamedTempFile::new().unwrap();
+        let mut receipts_file = tempfile::NamedTempFile::new().unwrap();
         apply_chain_range(
             ApplyRangeMode::Parallel,
             store,
             &genesis,
             None,
             None,
-            0,
+            &[0],
             epoch_manager.as_ref(),
             runtime,
             true,
             Some(file.as_file_mut()),
+            Some(receipts_file.as_file_mut()),
             false,
             false,
         );
@@ -702,5 +818,22 @@ mod test {
         }
         assert_eq!(has_tx, 1, "{:#?}", lines);
         assert_eq!(no_tx, 8, "{:#?}", lines);
+
+        // Only the staking transaction and its receipt have outcomes.
+        let mut receipts_csv = String::new();
+        receipts_file.as_file_mut().seek(SeekFrom::Start(0)).unwrap();
+        receipts_file.as_file_mut().read_to_string(&mut receipts_csv).unwrap();
+        let lines: Vec<&str> = receipts_csv.lines().collect();
+        assert!(lines[0].starts_with("Height,ShardId,Id,ExecutorId"));
+        assert!(lines.len() > 1, "{:#?}", lines);
+        for line in &lines[1..] {
+            assert!(line.contains(",0,") && line.contains(",test1,"), "{:#?}", lines);
+        }
+        // The transaction is charged for its gas.
+        assert!(
+            lines[1..].iter().any(|line| line.rsplit(',').next().unwrap().starts_with('-')),
+            "{:#?}",
+            lines
+        );
     }
 }
@@ -12,10 +12,10 @@ use near_primitives::apply::ApplyChunkReason;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::combine_hash;
 use near_primitives::receipt::Receipt;
-use near_primitives::shard_layout;
 use near_primitives::sharding::{ChunkHash, ReceiptProof};
 use near_primitives::state_sync::ReceiptProofResponse;
-use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::{BlockHeight, RawStateChangesWithTrieKey, ShardId, StateChangeCause};
 use near_primitives_core::hash::hash;
 use near_primitives_core::types::Gas;
 use near_store::DBCol;
@@ -106,6 +106,8 @@ pub(crate) fn apply_chunk(
     };
     let prev_timestamp = prev_block.header().raw_timestamp();
     let gas_price = prev_block.header().next_gas_price();
+    let receipts_span =
+        tracing::debug_span!(target: "state-viewer", "get_incoming_receipts").entered();
     let receipts = get_incoming_receipts(
         chain_store,
         epoch_manager,
@@ -117,11 +119,15 @@ pub(crate) fn apply_chunk(
         rng,
     )
     .context("Failed collecting incoming receipts")?;
+    receipts_span.exit();
 
     if use_flat_storage {
         let shard_uid =
             epoch_manager.shard_id_to_uid(shard_id as u64, prev_block.header().epoch_id()).unwrap();
-        runtime.get_flat_storage_manager().create_flat_storage_for_shard(shard_uid).unwrap();
+        let flat_storage_manager = runtime.get_flat_storage_manager();
+        if flat_storage_manager.get_flat_storage_for_shard(shard_uid).is_none() {
+            flat_storage_manager.create_flat_storage_for_shard(shard_uid).unwrap();
+        }
     }
 
     let is_first_block_with_chunk_of_version = check_if_block_is_first_with_chunk_of_version(
@@ -188,8 +194,7 @@ fn find_tx_or_receipt(
             if &receipt.get_hash() == hash {
                 let shard_layout =
                     epoch_manager.get_shard_layout_from_prev_block(chunk.prev_block())?;
-                let to_shard =
-                    shard_layout::account_id_to_shard_id(receipt.receiver_id(), &shard_layout);
+                let to_shard = receipt.receiver_shard_id(&shard_layout);
                 return Ok(Some((HashType::Receipt, to_shard)));
             }
         }
@@ -396,10 +401,7 @@ fn apply_receipt_in_chunk(
                     if receipt.get_hash() == *id {
                         let shard_layout =
                             epoch_manager.get_shard_layout_from_prev_block(chunk.prev_block())?;
-                        let to_shard = shard_layout::account_id_to_shard_id(
-                            receipt.receiver_id(),
-                            &shard_layout,
-                        );
+                        let to_shard = receipt.receiver_shard_id(&shard_layout);
                         to_apply.insert((height, to_shard));
                         println!(
                             "found receipt in chunk {}. Receiver is in shard {}",
@@ -480,6 +482,54 @@ pub(crate) fn apply_receipt(
     }
 }
 
+/// A value of the state changed by a transaction or a receipt.
+pub(crate) struct StateChangeDiff {
+    pub trie_key: TrieKey,
+    pub before: Option<Vec<u8>>,
+    pub after: Option<Vec<u8>>,
+}
+
+fn is_caused_by(cause: &StateChangeCause, id: &CryptoHash) -> bool {
+    match cause {
+        StateChangeCause::TransactionProcessing { tx_hash } => tx_hash == id,
+        StateChangeCause::ActionReceiptProcessingStarted { receipt_hash }
+        | StateChangeCause::ActionReceiptGasReward { receipt_hash }
+        | StateChangeCause::ReceiptProcessing { receipt_hash }
+        | StateChangeCause::PostponedReceipt { receipt_hash } => receipt_hash == id,
+        _ => false,
+    }
+}
+
+/// Returns the changes made to the state by the transaction or receipt `id`
+/// when applying a chunk, together with the value each key had before.
+pub(crate) fn state_changes_caused_by(
+    id: &CryptoHash,
+    apply_result: &ApplyChunkResult,
+) -> anyhow::Result<Vec<StateChangeDiff>> {
+    let trie = apply_result.trie_changes.old_trie();
+    let mut diffs = vec![];
+    for RawStateChangesWithTrieKey { trie_key, changes } in
+        apply_result.trie_changes.state_changes()
+    {
+        if !changes.iter().any(|change| is_caused_by(&change.cause, id)) {
+            continue;
+        }
+        // Earlier items of the chunk may have changed the key already.
+        let mut value = trie.get(&trie_key.to_vec())?;
+        for change in changes {
+            if is_caused_by(&change.cause, id) {
+                diffs.push(StateChangeDiff {
+                    trie_key: trie_key.clone(),
+                    before: value.clone(),
+                    after: change.data.clone(),
+                });
+            }
+            value = change.data.clone();
+        }
+    }
+    Ok(diffs)
+}
+
 #[cfg(test)]
 mod test {
     use near_async::time::Clock;
@@ -492,6 +542,7 @@ mod test {
     use near_primitives::hash::CryptoHash;
     use near_primitives::shard_layout;
     use near_primitives::transaction::SignedTransaction;
+    use near_primitives::trie_key::TrieKey;
     use near_primitives::utils::get_num_seats_per_shard;
     use near_store::genesis::initialize_genesis_state;
     use near_store::test_utils::create_test_store;
@@ -680,13 +731,23 @@ mod test {
                         .unwrap();
                         assert_eq!(results.len(), 1);
                         assert_eq!(results[0].new_root, new_roots[shard_id as usize]);
+
+                        // Processing the transaction charges the signer.
+                        let diffs = crate::apply_chunk::state_changes_caused_by(
+                            &tx.get_hash(),
+                            &results[0],
+                        )
+                        .unwrap();
+                        assert!(diffs.iter().any(|diff| diff.trie_key
+                            == TrieKey::Account {
+                                account_id: tx.transaction.signer_id().clone()
+                            }
+                            && diff.before.is_some()
+                            && diff.before != diff.after));
                     }
 
                     for receipt in chunk.prev_outgoing_receipts() {
-                        let to_shard = shard_layout::account_id_to_shard_id(
-                            receipt.receiver_id(),
-                            &shard_layout,
-                        );
+                        let to_shard = receipt.receiver_shard_id(&shard_layout);
 
                         let results = crate::apply_chunk::apply_receipt(
                             genesis.config.genesis_height,
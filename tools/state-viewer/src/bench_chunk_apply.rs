@@ -0,0 +1,365 @@
+use crate::apply_chunk::apply_chunk;
+use crate::commands::resulting_chunk_extra;
+use anyhow::Context;
+use near_chain::{ChainStore, ChainStoreAccess};
+use near_epoch_manager::EpochManager;
+use near_o11y::tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+use near_o11y::tracing_subscriber::registry::LookupSpan;
+use near_o11y::tracing_subscriber::{Layer, Registry};
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_store::{checkpoint_hot_storage_and_cleanup_columns, Mode, NodeStorage, Store};
+use nearcore::{NearConfig, NightshadeRuntime};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::span;
+
+/// Spans recorded while profiling the chunk application. Storage spans are left
+/// out, as there are too many of them and they would skew the measurements.
+const PROFILE_FILTER: &str = "info,runtime=debug,vm=debug,state-viewer=debug";
+
+/// Maximum number of span names in the stacks listed in the per-stage breakdown.
+const BREAKDOWN_DEPTH: usize = 4;
+
+#[derive(clap::Parser)]
+pub struct BenchChunkApplyCmd {
+    /// Hash of the chunk to apply.
+    #[clap(long, required_unless_present = "height", conflicts_with = "height")]
+    chunk_hash: Option<String>,
+    /// Apply the new chunks of the block at this height instead of a single chunk.
+    #[clap(long)]
+    height: Option<BlockHeight>,
+    /// With `--height`, only apply the chunk of this shard.
+    #[clap(long, requires = "height")]
+    shard_id: Option<ShardId>,
+    /// Number of measured applications.
+    #[clap(long, default_value_t = 10)]
+    iterations: usize,
+    /// Number of applications run before the measured ones, to warm up the caches.
+    #[clap(long, default_value_t = 1)]
+    warmup_iterations: usize,
+    #[clap(long)]
+    use_flat_storage: bool,
+    /// Run against a checkpoint of the database in this directory, which is
+    /// created if it doesn't exist yet. Reusing the same checkpoint across
+    /// runs makes the measurements reproducible while the node keeps running.
+    /// Creating the checkpoint requires opening the database with `--readwrite`.
+    #[clap(long)]
+    checkpoint_dir: Option<PathBuf>,
+    /// Write the time spent in the tracing spans of the measured applications
+    /// to this file in the folded stack format, which flamegraph tools such as
+    /// `inferno-flamegraph` turn into a flamegraph. Work done on other threads,
+    /// e.g. when compiling contracts in parallel, is not included.
+    #[clap(long)]
+    profile_file: Option<PathBuf>,
+}
+
+impl BenchChunkApplyCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        self.run_impl(home_dir, near_config, store).unwrap();
+    }
+
+    fn run_impl(
+        self,
+        home_dir: &Path,
+        near_config: NearConfig,
+        store: Store,
+    ) -> anyhow::Result<()> {
+        let store = match &self.checkpoint_dir {
+            Some(checkpoint_dir) => {
+                open_checkpoint(&store, checkpoint_dir, near_config.config.archive)?
+            }
+            None => store,
+        };
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+        let runtime = NightshadeRuntime::from_config(
+            home_dir,
+            store.clone(),
+            &near_config,
+            epoch_manager.clone(),
+        )
+        .context("could not create the transaction runtime")?;
+        let mut chain_store = ChainStore::new(
+            store,
+            near_config.genesis.config.genesis_height,
+            near_config.client_config.save_trie_changes,
+        );
+
+        let chunks = self.chunks_to_apply(&chain_store)?;
+        println!("Benchmarking the application of {} chunk(s):", chunks.len());
+        for (chunk_hash, _) in &chunks {
+            println!("  {}", chunk_hash.0);
+        }
+
+        let mut apply_all = || {
+            chunks
+                .iter()
+                .map(|(chunk_hash, target_height)| {
+                    apply_chunk(
+                        epoch_manager.as_ref(),
+                        runtime.as_ref(),
+                        &mut chain_store,
+                        chunk_hash.clone(),
+                        *target_height,
+                        None,
+                        self.use_flat_storage,
+                    )
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+
+        for _ in 0..self.warmup_iterations {
+            apply_all()?;
+        }
+        let profiler = SpanProfiler::default();
+        let env_filter = near_o11y::EnvFilterBuilder::new(PROFILE_FILTER).finish()?;
+        let subscriber = Registry::default().with(env_filter).with(profiler.clone());
+        let mut wall_times = vec![];
+        let mut results = vec![];
+        tracing::subscriber::with_default(subscriber, || -> anyhow::Result<()> {
+            for _ in 0..self.iterations {
+                let start = Instant::now();
+                results = apply_all()?;
+                wall_times.push(start.elapsed());
+            }
+            Ok(())
+        })?;
+
+        // Print the results so that they can be checked against the chain.
+        for ((chunk_hash, _), (apply_result, gas_limit)) in chunks.iter().zip(&results) {
+            println!(
+                "chunk {} resulting chunk extra:\n{:?}",
+                chunk_hash.0,
+                resulting_chunk_extra(apply_result, *gas_limit)
+            );
+        }
+        print_wall_times(&mut wall_times);
+        let profile = profiler.0.lock().unwrap();
+        print_breakdown(&profile, self.iterations);
+        if let Some(profile_file) = &self.profile_file {
+            profile
+                .write_folded(profile_file)
+                .with_context(|| format!("failed writing {}", profile_file.display()))?;
+            println!("Wrote the folded stacks to {}", profile_file.display());
+        }
+        Ok(())
+    }
+
+    /// Returns the chunks to apply along with the height they're applied at.
+    fn chunks_to_apply(
+        &self,
+        chain_store: &ChainStore,
+    ) -> anyhow::Result<Vec<(ChunkHash, Option<BlockHeight>)>> {
+        if let Some(chunk_hash) = &self.chunk_hash {
+            let hash = CryptoHash::from_str(chunk_hash)
+                .map_err(|err| anyhow::anyhow!("invalid chunk hash {}: {}", chunk_hash, err))?;
+            return Ok(vec![(ChunkHash(hash), None)]);
+        }
+        let height = self.height.unwrap();
+        let block_hash = chain_store
+            .get_block_hash_by_height(height)
+            .with_context(|| format!("no block at height {}", height))?;
+        let block = chain_store.get_block(&block_hash)?;
+        let chunks: Vec<_> = block
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.height_included() == height)
+            .filter(|chunk| self.shard_id.map_or(true, |shard_id| chunk.shard_id() == shard_id))
+            .map(|chunk| (chunk.chunk_hash(), Some(height)))
+            .collect();
+        anyhow::ensure!(
+            !chunks.is_empty(),
+            "block at height {} has no new chunks to apply",
+            height
+        );
+        Ok(chunks)
+    }
+}
+
+/// Opens the checkpoint in `checkpoint_dir`, creating it from `store` first if
+/// needed.
+fn open_checkpoint(store: &Store, checkpoint_dir: &Path, archive: bool) -> anyhow::Result<Store> {
+    let storage = if checkpoint_dir.join("data").exists() {
+        let mut config = near_store::StoreConfig::default();
+        config.path = Some(checkpoint_dir.join("data"));
+        NodeStorage::opener(checkpoint_dir, archive, &config, None)
+            .open_in_mode(Mode::ReadWriteExisting)?
+    } else {
+        println!("Creating a checkpoint of the database in {}", checkpoint_dir.display());
+        checkpoint_hot_storage_and_cleanup_columns(store, checkpoint_dir, None)
+            .context("failed creating the checkpoint, is the database opened with --readwrite?")?
+    };
+    Ok(storage.get_hot_store())
+}
+
+fn print_wall_times(wall_times: &mut [Duration]) {
+    if wall_times.is_empty() {
+        return;
+    }
+    wall_times.sort();
+    let total: Duration = wall_times.iter().sum();
+    println!(
+        "Wall time over {} iterations: min {:.2?}, median {:.2?}, mean {:.2?}, max {:.2?}",
+        wall_times.len(),
+        wall_times[0],
+        wall_times[wall_times.len() / 2],
+        total / wall_times.len() as u32,
+        wall_times[wall_times.len() - 1],
+    );
+}
+
+fn print_breakdown(profile: &SpanProfile, iterations: usize) {
+    if iterations == 0 {
+        return;
+    }
+    println!("Mean time per iteration by stage:");
+    for (stack, total_time) in &profile.total_time {
+        let depth = stack.split(';').count();
+        if depth > BREAKDOWN_DEPTH {
+            continue;
+        }
+        let name = stack.rsplit(';').next().unwrap();
+        println!(
+            "  {:indent$}{:<40} {:>10.2?}",
+            "",
+            name,
+            *total_time / iterations as u32,
+            indent = 2 * (depth - 1)
+        );
+    }
+}
+
+/// Time spent in the spans, keyed by the `;`-separated names of the spans
+/// from the root to the span.
+#[derive(Default)]
+struct SpanProfile {
+    /// Time spent in the spans including their children.
+    total_time: BTreeMap<String, Duration>,
+    /// Time spent in the spans excluding their children, as needed for the
+    /// folded stack format.
+    self_time: BTreeMap<String, Duration>,
+}
+
+impl SpanProfile {
+    fn write_folded(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for (stack, self_time) in &self.self_time {
+            writeln!(file, "{} {}", stack, self_time.as_micros())?;
+        }
+        file.flush()
+    }
+}
+
+/// Timing of a single span, kept in its extensions until it's closed.
+struct SpanTiming {
+    stack: String,
+    entered: Option<Instant>,
+    busy: Duration,
+    children: Duration,
+}
+
+/// Tracing layer that measures how long the spans are entered and adds it up
+/// into a `SpanProfile`.
+#[derive(Clone, Default)]
+struct SpanProfiler(Arc<Mutex<SpanProfile>>);
+
+impl<S> Layer<S> for SpanProfiler
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: LayerContext<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let name = attrs.metadata().name();
+        let stack = match span.parent() {
+            Some(parent) => match parent.extensions().get::<SpanTiming>() {
+                Some(timing) => format!("{};{}", timing.stack, name),
+                None => name.to_string(),
+            },
+            None => name.to_string(),
+        };
+        span.extensions_mut().insert(SpanTiming {
+            stack,
+            entered: None,
+            busy: Duration::ZERO,
+            children: Duration::ZERO,
+        });
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: LayerContext<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+            timing.entered = Some(Instant::now());
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: LayerContext<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+            if let Some(entered) = timing.entered.take() {
+                timing.busy += entered.elapsed();
+            }
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: LayerContext<'_, S>) {
+        let span = ctx.span(&id).unwrap();
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+        if let Some(parent) = span.parent() {
+            if let Some(parent_timing) = parent.extensions_mut().get_mut::<SpanTiming>() {
+                parent_timing.children += timing.busy;
+            }
+        }
+        let mut profile = self.0.lock().unwrap();
+        *profile.total_time.entry(timing.stack.clone()).or_default() += timing.busy;
+        *profile.self_time.entry(timing.stack).or_default() +=
+            timing.busy.saturating_sub(timing.children);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpanProfiler;
+    use near_o11y::tracing_subscriber::layer::SubscriberExt;
+    use near_o11y::tracing_subscriber::Registry;
+    use std::time::Duration;
+
+    #[test]
+    fn test_span_profiler_aggregates_spans() {
+        let profiler = SpanProfiler::default();
+        let subscriber = Registry::default().with(profiler.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let _outer = tracing::info_span!("outer").entered();
+            std::thread::sleep(Duration::from_millis(1));
+            for _ in 0..2 {
+                let _inner = tracing::info_span!("inner").entered();
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        let profile = profiler.0.lock().unwrap();
+        let stacks: Vec<_> = profile.total_time.keys().map(String::as_str).collect();
+        assert_eq!(stacks, ["outer", "outer;inner"]);
+        let outer = profile.total_time["outer"];
+        let inner = profile.total_time["outer;inner"];
+        assert!(inner >= Duration::from_millis(2));
+        assert!(outer >= inner + Duration::from_millis(1));
+        assert_eq!(profile.self_time["outer"], outer - inner);
+        assert_eq!(profile.self_time["outer;inner"], inner);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        profile.write_folded(file.path()).unwrap();
+        let folded = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            folded,
+            format!("outer {}\nouter;inner {}\n", (outer - inner).as_micros(), inner.as_micros())
+        );
+    }
+}
@@ -1,9 +1,13 @@
+use crate::bench_chunk_apply::BenchChunkApplyCmd;
 use crate::commands::*;
 use crate::contract_accounts::ContractAccountFilter;
 use crate::rocksdb_stats::get_rocksdb_stats;
 use crate::trie_iteration_benchmark::TrieIterationBenchmarkCmd;
+use crate::verify_archive::VerifyArchiveCmd;
 
 use crate::latest_witnesses::LatestWitnessesCmd;
+use crate::memtrie_stats::MemtrieStatsCmd;
+use crate::train_witness_dictionary::TrainWitnessDictionaryCmd;
 use near_chain_configs::{GenesisChangeConfig, GenesisValidationMode};
 use near_primitives::account::id::AccountId;
 use near_primitives::hash::CryptoHash;
@@ -34,6 +38,10 @@ pub enum StateViewerSubCommand {
     /// even if it's not included in any block on disk
     #[clap(alias = "apply_tx")]
     ApplyTx(ApplyTxCmd),
+    /// Repeatedly apply a chunk, or the chunks of a block, and report how long it takes,
+    /// broken down by stage and optionally as a folded-stack profile for flamegraphs.
+    #[clap(alias = "bench_chunk_apply")]
+    BenchChunkApply(BenchChunkApplyCmd),
     /// Print chain from start_index to end_index.
     Chain(ChainCmd),
     /// Check whether the node has all the blocks up to its head.
@@ -47,6 +55,9 @@ pub enum StateViewerSubCommand {
     /// List account names with contracts deployed.
     #[clap(alias = "contract_accounts")]
     ContractAccounts(ContractAccountsCmd),
+    /// Print the contract data of an account at the head of the chain.
+    #[clap(alias = "contract_state")]
+    ContractState(ContractStateCmd),
     /// Dump contract data in storage of given account to binary file.
     #[clap(alias = "dump_account_storage")]
     DumpAccountStorage(DumpAccountStorageCmd),
@@ -65,9 +76,17 @@ pub enum StateViewerSubCommand {
     /// Print `EpochInfo` of an epoch given by `--epoch_id` or by `--epoch_height`.
     #[clap(alias = "epoch_info")]
     EpochInfo(EpochInfoCmd),
+    /// Load the memtrie of a shard and report its memory usage by node type, the distribution
+    /// of key lengths and the accounts with the most resident trie bytes.
+    #[clap(alias = "memtrie_stats")]
+    MemtrieStats(MemtrieStatsCmd),
     /// Looks up a certain partial chunk.
     #[clap(alias = "partial_chunks")]
     PartialChunks(PartialChunksCmd),
+    /// Compile the contracts deployed in the latest state and store them in the compiled contract
+    /// cache, so that their first calls don't have to wait for the compilation.
+    #[clap(alias = "precompile_contracts")]
+    PrecompileContracts(PrecompileContractsCmd),
     /// Looks up a certain receipt.
     Receipts(ReceiptsCmd),
     /// Replay headers from chain.
@@ -75,6 +94,9 @@ pub enum StateViewerSubCommand {
     /// Dump stats for the RocksDB storage.
     #[clap(name = "rocksdb-stats", alias = "rocksdb_stats")]
     RocksDBStats(RocksDBStatsCmd),
+    /// Print the contract data of an account with keys starting with a prefix, read
+    /// from the trie by seeking to the prefix instead of iterating the whole shard.
+    Scan(ScanCmd),
     /// Reads all rows of a DB column and deserializes keys and values and prints them.
     ScanDbColumn(ScanDbColumnCmd),
     /// Iterates over a trie and prints the StateRecords.
@@ -89,6 +111,10 @@ pub enum StateViewerSubCommand {
     StateStats(StateStatsCmd),
     /// Benchmark how long does it take to iterate the trie.
     TrieIterationBenchmark(TrieIterationBenchmarkCmd),
+    /// Re-execute the chunks of a range of blocks in parallel and check that the resulting
+    /// chunk extras match the stored ones, writing a signed report of the verification.
+    #[clap(alias = "verify_archive")]
+    VerifyArchive(VerifyArchiveCmd),
     /// View head of the storage.
     #[clap(alias = "view_chain")]
     ViewChain(ViewChainCmd),
@@ -98,6 +124,9 @@ pub enum StateViewerSubCommand {
     /// Print observed ChunkStateWitnesses at the given block height (and shard id).
     /// Observed witnesses are only saved when `save_latest_witnesses` is set to true in config.json.
     LatestWitnesses(LatestWitnessesCmd),
+    /// Train a zstd dictionary for state witness compression on the saved witnesses,
+    /// and compare the compression with and without it.
+    TrainWitnessDictionary(TrainWitnessDictionaryCmd),
 }
 
 impl StateViewerSubCommand {
@@ -132,30 +161,41 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::ApplyRange(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ApplyReceipt(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ApplyTx(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::BenchChunkApply(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::Chain(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::CheckBlock => check_block_chunk_existence(near_config, store),
             StateViewerSubCommand::Chunks(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::ClearCache => clear_cache(store),
             StateViewerSubCommand::ContractAccounts(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::ContractState(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpAccountStorage(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpCode(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpState(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpStateRedis(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpTx(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::EpochInfo(cmd) => cmd.run(near_config, store),
+            StateViewerSubCommand::MemtrieStats(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::PartialChunks(cmd) => cmd.run(near_config, store),
+            StateViewerSubCommand::PrecompileContracts(cmd) => {
+                cmd.run(home_dir, near_config, store)
+            }
             StateViewerSubCommand::Receipts(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::Replay(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::RocksDBStats(cmd) => cmd.run(store_opener.path()),
+            StateViewerSubCommand::Scan(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ScanDbColumn(cmd) => cmd.run(store),
             StateViewerSubCommand::State => state(home_dir, near_config, store),
             StateViewerSubCommand::StateChanges(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::StateParts(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::StateStats(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::VerifyArchive(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ViewChain(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::ViewTrie(cmd) => cmd.run(store),
             StateViewerSubCommand::TrieIterationBenchmark(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::LatestWitnesses(cmd) => cmd.run(near_config, store),
+            StateViewerSubCommand::TrainWitnessDictionary(cmd) => {
+                cmd.run(near_config, store).unwrap()
+            }
         }
     }
 }
@@ -227,12 +267,17 @@ pub struct ApplyRangeCmd {
     start_index: Option<BlockHeight>,
     #[clap(long)]
     end_index: Option<BlockHeight>,
-    #[clap(long, default_value = "0")]
-    shard_id: ShardId,
+    /// Shards to apply, e.g. `--shard-id 0,1,2`.
+    #[clap(long = "shard-id", default_value = "0", value_delimiter = ',')]
+    shard_ids: Vec<ShardId>,
     #[clap(long)]
     verbose_output: bool,
     #[clap(long, value_parser)]
     csv_file: Option<PathBuf>,
+    /// Writes the gas and tokens burnt and the status of every execution
+    /// outcome to this CSV file.
+    #[clap(long, value_parser)]
+    receipts_csv_file: Option<PathBuf>,
     #[clap(long)]
     only_contracts: bool,
     #[clap(long)]
@@ -247,9 +292,10 @@ impl ApplyRangeCmd {
             self.mode,
             self.start_index,
             self.end_index,
-            self.shard_id,
+            &self.shard_ids,
             self.verbose_output,
             self.csv_file,
+            self.receipts_csv_file,
             home_dir,
             near_config,
             store,
@@ -261,31 +307,38 @@ impl ApplyRangeCmd {
 
 #[derive(clap::Parser)]
 pub struct ApplyReceiptCmd {
-    #[clap(long)]
+    #[clap(long, alias = "receipt-id")]
     hash: String,
     #[clap(long)]
     use_flat_storage: bool,
+    /// Log the host functions called by contracts and their storage accesses.
+    #[clap(long)]
+    trace: bool,
 }
 
 impl ApplyReceiptCmd {
     pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
         let hash = CryptoHash::from_str(&self.hash).unwrap();
-        apply_receipt(home_dir, near_config, store, hash, self.use_flat_storage).unwrap();
+        apply_receipt(home_dir, near_config, store, hash, self.use_flat_storage, self.trace)
+            .unwrap();
     }
 }
 
 #[derive(clap::Parser)]
 pub struct ApplyTxCmd {
-    #[clap(long)]
+    #[clap(long, alias = "tx-hash")]
     hash: String,
     #[clap(long)]
     use_flat_storage: bool,
+    /// Log the host functions called by contracts and their storage accesses.
+    #[clap(long)]
+    trace: bool,
 }
 
 impl ApplyTxCmd {
     pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
         let hash = CryptoHash::from_str(&self.hash).unwrap();
-        apply_tx(home_dir, near_config, store, hash, self.use_flat_storage).unwrap();
+        apply_tx(home_dir, near_config, store, hash, self.use_flat_storage, self.trace).unwrap();
     }
 }
 
@@ -332,6 +385,74 @@ impl ContractAccountsCmd {
     }
 }
 
+/// How `ContractStateCmd` prints keys and values.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ContractStateFormat {
+    /// Keys as UTF-8 strings and values as JSON documents. Keys and values
+    /// which can't be decoded that way are printed in base64.
+    Json,
+    /// Keys and values as hex strings, e.g. to decode borsh encoded values
+    /// with the contract's own schema.
+    BorshHex,
+}
+
+#[derive(clap::Parser)]
+pub struct ContractStateCmd {
+    /// Account whose contract data is printed.
+    #[clap(long)]
+    account: AccountId,
+    /// Only print the keys starting with this prefix.
+    #[clap(long, default_value = "")]
+    key_prefix: String,
+    #[clap(long, value_enum, default_value = "json")]
+    format: ContractStateFormat,
+}
+
+impl ContractStateCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        print_contract_state(
+            self.account,
+            self.key_prefix.as_bytes(),
+            self.format,
+            home_dir,
+            near_config,
+            store,
+        );
+    }
+}
+
+#[derive(clap::Parser)]
+pub struct ScanCmd {
+    /// Account whose contract data is printed.
+    #[clap(long)]
+    account: AccountId,
+    /// Only print the keys starting with this prefix, either a UTF-8 string or
+    /// hex digits starting with `0x`, e.g. `0xabc`, which may be an odd number
+    /// of digits.
+    #[clap(long, default_value = "")]
+    key_prefix: String,
+    /// Maximum number of entries to print.
+    #[clap(long, default_value = "100")]
+    limit: usize,
+    #[clap(long, value_enum, default_value = "json")]
+    format: ContractStateFormat,
+}
+
+impl ScanCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        scan_contract_state(
+            self.account,
+            &self.key_prefix,
+            self.limit,
+            self.format,
+            home_dir,
+            near_config,
+            store,
+        )
+        .unwrap();
+    }
+}
+
 #[derive(clap::Parser)]
 pub struct DumpAccountStorageCmd {
     #[clap(long)]
@@ -500,6 +621,19 @@ impl PartialChunksCmd {
     }
 }
 
+#[derive(clap::Parser)]
+pub struct PrecompileContractsCmd {
+    /// Number of contracts sent for compilation at once.
+    #[clap(long, default_value = "64")]
+    batch_size: usize,
+}
+
+impl PrecompileContractsCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        precompile_contracts(home_dir, store, near_config, self.batch_size).unwrap();
+    }
+}
+
 #[derive(clap::Parser)]
 pub struct ReceiptsCmd {
     #[clap(long)]
@@ -1,5 +1,6 @@
 use crate::apply_chain_range::apply_chain_range;
 use crate::cli::ApplyRangeMode;
+use crate::cli::ContractStateFormat;
 use crate::contract_accounts::ContractAccount;
 use crate::contract_accounts::ContractAccountFilter;
 use crate::contract_accounts::Summary;
@@ -8,6 +9,7 @@ use crate::state_dump::state_dump_redis;
 use crate::tx_dump::dump_tx_from_block;
 use crate::{apply_chunk, epoch_info};
 use anyhow::Context;
+use borsh::BorshDeserialize;
 use bytesize::ByteSize;
 use itertools::GroupBy;
 use itertools::Itertools;
@@ -23,9 +25,11 @@ use near_epoch_manager::types::BlockHeaderInfo;
 use near_epoch_manager::EpochManagerHandle;
 use near_epoch_manager::{EpochManager, EpochManagerAdapter};
 use near_primitives::account::id::AccountId;
+use near_primitives::account::Account;
 use near_primitives::apply::ApplyChunkReason;
 use near_primitives::block::{Block, BlockHeader};
 use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::account_id_to_shard_uid;
 use near_primitives::shard_layout::ShardLayout;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::sharding::ChunkHash;
@@ -36,18 +40,20 @@ use near_primitives::trie_key::col::COLUMNS_WITH_ACCOUNT_ID_IN_KEY;
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{chunk_extra::ChunkExtra, BlockHeight, ShardId, StateRoot};
 use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives_core::serialize::to_base64;
 use near_primitives_core::types::Gas;
 use near_store::flat::FlatStorageChunkView;
 use near_store::flat::FlatStorageManager;
 use near_store::test_utils::create_test_store;
 use near_store::TrieStorage;
 use near_store::{DBCol, Store, Trie, TrieCache, TrieCachingStorage, TrieConfig, TrieDBStorage};
+use near_vm_runner::ContractCode;
 use nearcore::NightshadeRuntimeExt;
 use nearcore::{NearConfig, NightshadeRuntime};
 use node_runtime::adapter::ViewRuntimeAdapter;
 use serde_json::json;
 use std::collections::BinaryHeap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -214,9 +220,10 @@ pub(crate) fn apply_range(
     mode: ApplyRangeMode,
     start_index: Option<BlockHeight>,
     end_index: Option<BlockHeight>,
-    shard_id: ShardId,
+    shard_ids: &[ShardId],
     verbose_output: bool,
     csv_file: Option<PathBuf>,
+    receipts_csv_file: Option<PathBuf>,
     home_dir: &Path,
     near_config: NearConfig,
     store: Store,
@@ -224,6 +231,8 @@ pub(crate) fn apply_range(
     use_flat_storage: bool,
 ) {
     let mut csv_file = csv_file.map(|filename| std::fs::File::create(filename).unwrap());
+    let mut receipts_csv_file =
+        receipts_csv_file.map(|filename| std::fs::File::create(filename).unwrap());
 
     let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
     let runtime = NightshadeRuntime::from_config(
@@ -239,22 +248,70 @@ pub(crate) fn apply_range(
         &near_config.genesis,
         start_index,
         end_index,
-        shard_id,
+        shard_ids,
         epoch_manager.as_ref(),
         runtime,
         verbose_output,
         csv_file.as_mut(),
+        receipts_csv_file.as_mut(),
         only_contracts,
         use_flat_storage,
     );
 }
 
+/// Logs the host functions called by contracts, with their duration, and the
+/// storage accesses they make.
+const EXECUTION_TRACING_FILTER: &str = "host-function=trace,io_tracer=trace,runtime=debug,vm=debug";
+
+/// Runs `f` with the execution of contracts traced to stderr if `trace` is set.
+fn with_execution_tracing<R>(trace: bool, f: impl FnOnce() -> R) -> R {
+    if !trace {
+        return f();
+    }
+    let env_filter = near_o11y::EnvFilterBuilder::new(EXECUTION_TRACING_FILTER).finish().unwrap();
+    let subscriber = near_o11y::tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .with_span_events(near_o11y::tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .finish();
+    tracing::subscriber::with_default(subscriber, f)
+}
+
+/// Prints the outcome of the transaction or receipt `id` and the changes it
+/// made to the state.
+fn print_tx_or_receipt_result(
+    id: &CryptoHash,
+    apply_result: &ApplyChunkResult,
+) -> anyhow::Result<()> {
+    for outcome in apply_result.outcomes.iter().filter(|outcome| &outcome.id == id) {
+        println!("outcome of {}:\n{:#?}", id, outcome.outcome);
+    }
+    let diffs = apply_chunk::state_changes_caused_by(id, apply_result)?;
+    println!("{} state changes made by {}:", diffs.len(), id);
+    for diff in diffs {
+        let format_value = |value: Option<Vec<u8>>| match value {
+            None => "<none>".to_string(),
+            Some(value) => {
+                match StateRecord::from_raw_key_value(diff.trie_key.to_vec(), value.clone()) {
+                    Some(record) => format!("{}", record),
+                    None => format!("{:?}", value),
+                }
+            }
+        };
+        println!("{:?}", diff.trie_key);
+        println!("  before: {}", format_value(diff.before));
+        println!("  after:  {}", format_value(diff.after));
+    }
+    Ok(())
+}
+
 pub(crate) fn apply_receipt(
     home_dir: &Path,
     near_config: NearConfig,
     store: Store,
     hash: CryptoHash,
     use_flat_storage: bool,
+    trace: bool,
 ) -> anyhow::Result<()> {
     let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
     let runtime = NightshadeRuntime::from_config(
@@ -264,15 +321,20 @@ pub(crate) fn apply_receipt(
         epoch_manager.clone(),
     )
     .context("could not create the transaction runtime")?;
-    apply_chunk::apply_receipt(
-        near_config.genesis.config.genesis_height,
-        epoch_manager.as_ref(),
-        runtime.as_ref(),
-        store,
-        hash,
-        use_flat_storage,
-    )
-    .map(|_| ())
+    let results = with_execution_tracing(trace, || {
+        apply_chunk::apply_receipt(
+            near_config.genesis.config.genesis_height,
+            epoch_manager.as_ref(),
+            runtime.as_ref(),
+            store,
+            hash,
+            use_flat_storage,
+        )
+    })?;
+    for result in &results {
+        print_tx_or_receipt_result(&hash, result)?;
+    }
+    Ok(())
 }
 
 pub(crate) fn apply_tx(
@@ -281,6 +343,7 @@ pub(crate) fn apply_tx(
     store: Store,
     hash: CryptoHash,
     use_flat_storage: bool,
+    trace: bool,
 ) -> anyhow::Result<()> {
     let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
     let runtime = NightshadeRuntime::from_config(
@@ -290,15 +353,20 @@ pub(crate) fn apply_tx(
         epoch_manager.clone(),
     )
     .context("could not create the transaction runtime")?;
-    apply_chunk::apply_tx(
-        near_config.genesis.config.genesis_height,
-        epoch_manager.as_ref(),
-        runtime.as_ref(),
-        store,
-        hash,
-        use_flat_storage,
-    )
-    .map(|_| ())
+    let results = with_execution_tracing(trace, || {
+        apply_chunk::apply_tx(
+            near_config.genesis.config.genesis_height,
+            epoch_manager.as_ref(),
+            runtime.as_ref(),
+            store,
+            hash,
+            use_flat_storage,
+        )
+    })?;
+    for result in &results {
+        print_tx_or_receipt_result(&hash, result)?;
+    }
+    Ok(())
 }
 
 pub(crate) fn dump_account_storage(
@@ -350,6 +418,155 @@ pub(crate) fn dump_account_storage(
     std::process::exit(1);
 }
 
+/// Prints the contract data of `account_id` with keys starting with
+/// `key_prefix` as JSON, one entry per line. Only the trie nodes on the path
+/// to the prefix and below it are read.
+pub(crate) fn print_contract_state(
+    account_id: AccountId,
+    key_prefix: &[u8],
+    format: ContractStateFormat,
+    home_dir: &Path,
+    near_config: NearConfig,
+    store: Store,
+) {
+    let (epoch_manager, runtime, state_roots, block_header) =
+        load_trie(store, home_dir, &near_config);
+    let shard_layout = epoch_manager.get_shard_layout(block_header.epoch_id()).unwrap();
+    let shard_uid = account_id_to_shard_uid(&account_id, &shard_layout);
+    // The flat state alone is at the flat head, which lags behind the head by
+    // the blocks whose deltas are not applied yet, so the data is read through
+    // the trie at the state root of the head instead.
+    let state_root = state_roots[shard_uid.shard_id as usize];
+    let trie = runtime
+        .get_trie_for_shard(shard_uid.shard_id(), block_header.prev_hash(), state_root, false)
+        .unwrap();
+    let entries =
+        scan_contract_data(&trie, &account_id, &to_nibbles(key_prefix), usize::MAX).unwrap();
+    for (data_key, value) in &entries {
+        println!("{}", contract_state_entry(format, data_key, value));
+    }
+    let num_entries = entries.len();
+    tracing::info!(target: "state_viewer", %account_id, num_entries, "printed contract state");
+}
+
+fn contract_state_entry(
+    format: ContractStateFormat,
+    key: &[u8],
+    value: &[u8],
+) -> serde_json::Value {
+    match format {
+        ContractStateFormat::Json => {
+            let key = std::str::from_utf8(key).map_or_else(|_| to_base64(key), str::to_owned);
+            let value = serde_json::from_slice(value)
+                .unwrap_or_else(|_| serde_json::Value::String(to_base64(value)));
+            json!({ "key": key, "value": value })
+        }
+        ContractStateFormat::BorshHex => {
+            json!({ "key": hex::encode(key), "value": hex::encode(value) })
+        }
+    }
+}
+
+/// Prints up to `limit` entries of the contract data of `account_id` with keys
+/// starting with `key_prefix`, one per line.
+pub(crate) fn scan_contract_state(
+    account_id: AccountId,
+    key_prefix: &str,
+    limit: usize,
+    format: ContractStateFormat,
+    home_dir: &Path,
+    near_config: NearConfig,
+    store: Store,
+) -> anyhow::Result<()> {
+    let (epoch_manager, runtime, state_roots, block_header) =
+        load_trie(store, home_dir, &near_config);
+    let shard_layout = epoch_manager.get_shard_layout(block_header.epoch_id())?;
+    let shard_uid = account_id_to_shard_uid(&account_id, &shard_layout);
+    let state_root = state_roots[shard_uid.shard_id as usize];
+    let trie = runtime.get_trie_for_shard(
+        shard_uid.shard_id(),
+        block_header.prev_hash(),
+        state_root,
+        false,
+    )?;
+
+    let entries =
+        scan_contract_data(&trie, &account_id, &parse_key_prefix_nibbles(key_prefix)?, limit)?;
+    for (data_key, value) in &entries {
+        println!("{}", contract_state_entry(format, data_key, value));
+    }
+    let num_entries = entries.len();
+    tracing::info!(target: "state_viewer", %account_id, %shard_uid, num_entries, "scanned contract state");
+    Ok(())
+}
+
+/// Returns up to `limit` data keys and values of the contract data of
+/// `account_id` with keys starting with the given nibbles. The trie iterator
+/// seeks to the longest whole-byte prefix, so only the nodes on the path to it
+/// and below it are read. With an odd number of nibbles, the keys under that
+/// prefix which don't start with the last nibble are skipped.
+fn scan_contract_data(
+    trie: &Trie,
+    account_id: &AccountId,
+    key_prefix_nibbles: &[u8],
+    limit: usize,
+) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let account_prefix = TrieKey::ContractData { account_id: account_id.clone(), key: vec![] };
+    let mut prefix = to_nibbles(&account_prefix.to_vec());
+    prefix.extend_from_slice(key_prefix_nibbles);
+    let mut iter = trie.disk_iter()?;
+    iter.seek_prefix(from_nibbles(&prefix[..prefix.len() / 2 * 2]))?;
+    let mut entries = vec![];
+    for item in iter {
+        if entries.len() == limit {
+            break;
+        }
+        let (key, value) = item?;
+        let key_nibbles = to_nibbles(&key);
+        if !key_nibbles.starts_with(&prefix) {
+            // Keys are iterated in order, so the keys before the prefix are
+            // skipped and the first key after it ends the scan.
+            if key_nibbles.as_slice() < prefix.as_slice() {
+                continue;
+            }
+            break;
+        }
+        match StateRecord::from_raw_key_value(key, value) {
+            Some(StateRecord::Data { data_key, value, .. }) => {
+                entries.push((data_key.to_vec(), value.to_vec()))
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(entries)
+}
+
+/// Parses a key prefix given either as hex digits starting with `0x`, which
+/// may be an odd number of digits, or as a UTF-8 string into its nibbles.
+fn parse_key_prefix_nibbles(key_prefix: &str) -> anyhow::Result<Vec<u8>> {
+    let Some(digits) = key_prefix.strip_prefix("0x") else {
+        return Ok(to_nibbles(key_prefix.as_bytes()));
+    };
+    digits
+        .chars()
+        .map(|digit| {
+            let nibble =
+                digit.to_digit(16).with_context(|| format!("invalid hex digit {digit:?}"))?;
+            Ok(nibble as u8)
+        })
+        .collect()
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+/// Packs an even number of nibbles into bytes.
+fn from_nibbles(nibbles: &[u8]) -> Vec<u8> {
+    debug_assert_eq!(nibbles.len() % 2, 0);
+    nibbles.chunks_exact(2).map(|pair| (pair[0] << 4) | pair[1]).collect()
+}
+
 pub(crate) fn dump_code(
     account_id: String,
     output: &Path,
@@ -362,9 +579,12 @@ pub(crate) fn dump_code(
 
     for (shard_id, state_root) in state_roots.iter().enumerate() {
         let shard_uid = epoch_manager.shard_id_to_uid(shard_id as u64, epoch_id).unwrap();
-        if let Ok(contract_code) =
-            runtime.view_contract_code(&shard_uid, *state_root, &account_id.parse().unwrap())
-        {
+        if let Ok(contract_code) = runtime.view_contract_code(
+            &shard_uid,
+            *state_root,
+            header.hash(),
+            &account_id.parse().unwrap(),
+        ) {
             let mut file = File::create(output).unwrap();
             file.write_all(contract_code.code()).unwrap();
             println!("Dump contract of account {} into file {}", account_id, output.display());
@@ -1096,6 +1316,50 @@ pub(crate) fn contract_accounts(
     Ok(())
 }
 
+/// Compiles the contracts deployed in the latest state with the VM config of the current epoch.
+/// A contract deployed by many accounts is compiled once.
+pub(crate) fn precompile_contracts(
+    home_dir: &Path,
+    store: Store,
+    near_config: NearConfig,
+    batch_size: usize,
+) -> anyhow::Result<()> {
+    let (_, runtime, state_roots, header) = load_trie(store, home_dir, &near_config);
+    let epoch_id = header.epoch_id();
+    let mut code_hashes = HashSet::new();
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut num_compiled = 0;
+    for (shard_id, state_root) in state_roots.iter().enumerate() {
+        eprintln!("Starting shard {shard_id}");
+        let get_trie =
+            || runtime.get_trie_for_shard(shard_id as u64, header.prev_hash(), *state_root, false);
+        let trie = get_trie()?;
+        for contract in ContractAccount::in_trie(get_trie()?, ContractAccountFilter::default())? {
+            let account_id = contract?.account_id;
+            let account = trie
+                .get(&TrieKey::Account { account_id: account_id.clone() }.to_vec())?
+                .with_context(|| format!("missing account {account_id}"))?;
+            let code_hash = Account::try_from_slice(&account)?.code_hash();
+            if !code_hashes.insert(code_hash) {
+                continue;
+            }
+            let code = trie
+                .get(&TrieKey::ContractCode { account_id: account_id.clone() }.to_vec())?
+                .with_context(|| format!("missing contract code of {account_id}"))?;
+            batch.push(ContractCode::new(code, Some(code_hash)));
+            if batch.len() >= batch_size {
+                num_compiled += batch.len();
+                runtime.precompile_contracts(epoch_id, std::mem::take(&mut batch))?;
+                eprintln!("Compiled {num_compiled} contracts");
+            }
+        }
+    }
+    num_compiled += batch.len();
+    runtime.precompile_contracts(epoch_id, batch)?;
+    eprintln!("Done, compiled {num_compiled} contracts");
+    Ok(())
+}
+
 pub(crate) fn clear_cache(store: Store) {
     let mut store_update = store.store_update();
     store_update.delete_all(DBCol::CachedContractCode);
@@ -1349,15 +1613,20 @@ impl std::fmt::Debug for StateStatsAccount {
 
 #[cfg(test)]
 mod tests {
+    use super::{contract_state_entry, parse_key_prefix_nibbles, scan_contract_data};
+    use crate::cli::ContractStateFormat;
     use near_chain::types::RuntimeAdapter;
     use near_chain_configs::Genesis;
     use near_client::test_utils::TestEnv;
     use near_crypto::{InMemorySigner, KeyFile, KeyType};
     use near_epoch_manager::EpochManager;
     use near_primitives::shard_layout::ShardUId;
+    use near_primitives::trie_key::TrieKey;
     use near_primitives::types::chunk_extra::ChunkExtra;
     use near_primitives::types::AccountId;
     use near_store::genesis::initialize_genesis_state;
+    use near_store::test_utils::{test_populate_trie, TestTriesBuilder};
+    use near_store::Trie;
     use nearcore::config::Config;
     use nearcore::{NearConfig, NightshadeRuntime};
     use std::sync::Arc;
@@ -1423,4 +1692,59 @@ mod tests {
         assert_eq!(&state_roots[0], chunk_extras[1].state_root());
         assert_eq!(block_header.height(), 2);
     }
+
+    #[test]
+    fn test_contract_state_entry() {
+        let entry = contract_state_entry(ContractStateFormat::Json, b"STATE", b"{\"a\":[1,2]}");
+        assert_eq!(entry.to_string(), r#"{"key":"STATE","value":{"a":[1,2]}}"#);
+
+        // Borsh encoded values aren't valid JSON and are printed in base64.
+        let entry = contract_state_entry(ContractStateFormat::Json, &[0xff, 1], &[3, 0, 0, 0]);
+        assert_eq!(entry.to_string(), r#"{"key":"/wE=","value":"AwAAAA=="}"#);
+
+        let entry = contract_state_entry(ContractStateFormat::BorshHex, b"k", &[3, 0, 0, 0]);
+        assert_eq!(entry.to_string(), r#"{"key":"6b","value":"03000000"}"#);
+    }
+
+    #[test]
+    fn test_scan_contract_data() {
+        let alice: AccountId = "alice.near".parse().unwrap();
+        let bob: AccountId = "bob.near".parse().unwrap();
+        let data_keys: [&[u8]; 6] =
+            [&[0xab, 0x01], &[0xab, 0xc0], &[0xab, 0xcd, 0x01], &[0xab, 0xd0], &[0xac], b"k"];
+        let mut trie_data: Vec<_> = data_keys
+            .iter()
+            .map(|data_key| {
+                let key =
+                    TrieKey::ContractData { account_id: alice.clone(), key: data_key.to_vec() };
+                (key.to_vec(), Some(data_key.to_vec()))
+            })
+            .collect();
+        trie_data.push((
+            TrieKey::ContractData { account_id: bob.clone(), key: vec![0xab, 0xc1] }.to_vec(),
+            Some(vec![1]),
+        ));
+        let tries = TestTriesBuilder::new().build();
+        let root =
+            test_populate_trie(&tries, &Trie::EMPTY_ROOT, ShardUId::single_shard(), trie_data);
+        let trie = tries.get_trie_for_shard(ShardUId::single_shard(), root);
+        let scan = |key_prefix: &str, limit: usize| {
+            let nibbles = parse_key_prefix_nibbles(key_prefix).unwrap();
+            scan_contract_data(&trie, &alice, &nibbles, limit)
+                .unwrap()
+                .into_iter()
+                .map(|(data_key, _)| data_key)
+                .collect::<Vec<_>>()
+        };
+
+        // An odd number of nibbles matches every key starting with the last
+        // nibble, not only the keys where the nibble is followed by zero.
+        assert_eq!(scan("0xabc", 10), vec![vec![0xab, 0xc0], vec![0xab, 0xcd, 0x01]]);
+        assert_eq!(scan("0xab", 10).len(), 4);
+        assert_eq!(scan("0xabc", 1), vec![vec![0xab, 0xc0]]);
+        assert_eq!(scan("k", 10), vec![b"k".to_vec()]);
+        assert_eq!(scan("", 10).len(), data_keys.len());
+        assert!(scan("0xabe", 10).is_empty());
+        assert!(parse_key_prefix_nibbles("0xag").is_err());
+    }
 }
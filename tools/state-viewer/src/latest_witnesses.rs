@@ -27,6 +27,11 @@ pub struct LatestWitnessesCmd {
     /// Print the raw &[u8], can be pasted into rust code
     #[arg(long)]
     binary: bool,
+
+    /// Read the witnesses saved by shadow validation instead of the latest observed ones.
+    /// Shadow witnesses are only saved when `save_shadow_witnesses` is set to true in config.json.
+    #[arg(long, conflicts_with = "epoch_id")]
+    shadow: bool,
 }
 
 impl LatestWitnessesCmd {
@@ -34,9 +39,13 @@ impl LatestWitnessesCmd {
         let chain_store =
             Rc::new(ChainStore::new(store, near_config.genesis.config.genesis_height, false));
 
-        let witnesses = chain_store
-            .get_latest_witnesses(self.height, self.shard_id, self.epoch_id.clone())
-            .unwrap();
+        let witnesses = if self.shadow {
+            chain_store.get_shadow_state_witnesses(self.height, self.shard_id).unwrap()
+        } else {
+            chain_store
+                .get_latest_witnesses(self.height, self.shard_id, self.epoch_id.clone())
+                .unwrap()
+        };
         println!("Found {} witnesses:", witnesses.len());
         for (i, witness) in witnesses.iter().enumerate() {
             println!(
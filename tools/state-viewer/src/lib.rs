@@ -2,17 +2,21 @@
 
 mod apply_chain_range;
 mod apply_chunk;
+mod bench_chunk_apply;
 pub mod cli;
 mod commands;
 mod contract_accounts;
 mod epoch_info;
 mod latest_witnesses;
+mod memtrie_stats;
 mod rocksdb_stats;
 mod scan_db;
 mod state_changes;
 mod state_dump;
 mod state_parts;
+mod train_witness_dictionary;
 mod trie_iteration_benchmark;
 mod tx_dump;
+mod verify_archive;
 
 pub use cli::StateViewerSubCommand;
@@ -0,0 +1,314 @@
+use anyhow::Context;
+use bytesize::ByteSize;
+use near_chain::{ChainStore, ChainStoreAccess};
+use near_epoch_manager::{EpochManager, EpochManagerAdapter};
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
+use near_primitives::types::{AccountId, BlockHeight, ShardId};
+use near_store::flat::store_helper::get_flat_storage_status;
+use near_store::flat::FlatStorageStatus;
+use near_store::trie::mem::loading::load_trie_from_flat_state;
+use near_store::trie::mem::node::MemTrieNodeView;
+use near_store::{NibbleSlice, Store};
+use nearcore::NearConfig;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+#[derive(clap::Parser)]
+pub struct MemtrieStatsCmd {
+    /// Shard whose memtrie is loaded, in the shard layout of the final head.
+    #[clap(long)]
+    shard_id: ShardId,
+    /// Number of accounts listed in the top accounts by resident bytes.
+    #[clap(long, default_value_t = 20)]
+    top_accounts: usize,
+}
+
+impl MemtrieStatsCmd {
+    pub fn run(self, near_config: NearConfig, store: Store) {
+        self.run_impl(near_config, store).unwrap();
+    }
+
+    fn run_impl(self, near_config: NearConfig, store: Store) -> anyhow::Result<()> {
+        let chain_store = ChainStore::new(
+            store.clone(),
+            near_config.genesis.config.genesis_height,
+            near_config.client_config.save_trie_changes,
+        );
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+        let final_head = chain_store.final_head()?;
+        let shard_uid = epoch_manager.shard_id_to_uid(self.shard_id, &final_head.epoch_id)?;
+        let flat_head = match get_flat_storage_status(&store, shard_uid)? {
+            FlatStorageStatus::Ready(ready_status) => ready_status.flat_head,
+            status => anyhow::bail!("flat storage is not ready for {}: {:?}", shard_uid, status),
+        };
+        let state_root = *chain_store.get_chunk_extra(&flat_head.hash, &shard_uid)?.state_root();
+
+        println!(
+            "Loading the memtrie of {} at height {} with state root {}",
+            shard_uid, flat_head.height, state_root
+        );
+        let timer = Instant::now();
+        let tries = load_trie_from_flat_state(&store, shard_uid, state_root, flat_head.height)
+            .context("failed loading the memtrie")?;
+        println!("Loaded the memtrie in {:.2?}", timer.elapsed());
+        if state_root == CryptoHash::default() {
+            println!("The trie is empty");
+            return Ok(());
+        }
+
+        let stats = MemtrieStats::compute(&tries, &state_root);
+        stats.print(
+            shard_uid,
+            flat_head.height,
+            tries.arena_memory_usage_bytes() as u64,
+            self.top_accounts,
+        );
+        Ok(())
+    }
+}
+
+const NODE_KINDS: [&str; 4] = ["Leaf", "Extension", "Branch", "BranchWithValue"];
+
+#[derive(Default, Clone, Copy)]
+struct NodeKindStats {
+    count: u64,
+    bytes: u64,
+}
+
+/// Owner of a subtree of the trie: the account all the keys of the subtree
+/// belong to, if there is one.
+#[derive(Clone, Debug, PartialEq)]
+enum Owner {
+    /// The subtree has no values yet, which is only the case while its
+    /// children haven't been merged in.
+    None,
+    Account(AccountId),
+    /// The keys belong to several accounts, or to no account at all, e.g. for
+    /// delayed receipts.
+    Shared,
+}
+
+impl Owner {
+    fn merge(self, other: Owner) -> Owner {
+        match (self, other) {
+            (Owner::None, owner) | (owner, Owner::None) => owner,
+            (Owner::Account(a), Owner::Account(b)) if a == b => Owner::Account(a),
+            _ => Owner::Shared,
+        }
+    }
+}
+
+/// Traversal step of the trie. Nodes are finished after their children, so
+/// that their owner is known.
+enum Frame<Node> {
+    Visit { node: Node, path_len: usize, nibble: Option<u8> },
+    Finish { bytes: u64, num_children: usize, owner: Owner },
+}
+
+/// Memory used by the nodes of a memtrie, counting the bytes allocated for
+/// each node in the arena.
+#[derive(Default)]
+struct MemtrieStats {
+    node_kinds: [NodeKindStats; NODE_KINDS.len()],
+    /// Number of values by the bucket of the length of their key in bytes,
+    /// see `key_length_bucket`.
+    key_lengths: BTreeMap<usize, u64>,
+    /// Bytes of the nodes whose subtrees only hold keys of a single account.
+    account_bytes: HashMap<AccountId, u64>,
+    /// Bytes of the nodes which can't be attributed to a single account.
+    shared_bytes: u64,
+}
+
+impl MemtrieStats {
+    fn compute(tries: &near_store::trie::mem::MemTries, state_root: &CryptoHash) -> Self {
+        let mut stats = MemtrieStats::default();
+        let root = tries.get_root(state_root).expect("the memtrie must have the state root");
+        // Nibbles of the key from the root to the node being visited.
+        let mut path: Vec<u8> = vec![];
+        let mut stack = vec![Frame::Visit { node: root, path_len: 0, nibble: None }];
+        // Owners of the finished subtrees whose parents aren't finished yet.
+        let mut owners: Vec<Owner> = vec![];
+        while let Some(frame) = stack.pop() {
+            match frame {
+                Frame::Visit { node, path_len, nibble } => {
+                    path.truncate(path_len);
+                    path.extend(nibble);
+                    let bytes = node.size_of_allocation() as u64;
+                    let mut children = vec![];
+                    let (kind, has_value) = match node.view() {
+                        MemTrieNodeView::Leaf { extension, .. } => {
+                            let (extension, _) = NibbleSlice::from_encoded(extension);
+                            path.extend(extension.iter());
+                            (0, true)
+                        }
+                        MemTrieNodeView::Extension { extension, child, .. } => {
+                            let (extension, _) = NibbleSlice::from_encoded(extension);
+                            path.extend(extension.iter());
+                            children.push((child, None));
+                            (1, false)
+                        }
+                        MemTrieNodeView::Branch { children: branch_children, .. } => {
+                            for i in 0..16 {
+                                if let Some(child) = branch_children.get(i) {
+                                    children.push((child, Some(i as u8)));
+                                }
+                            }
+                            (2, false)
+                        }
+                        MemTrieNodeView::BranchWithValue { children: branch_children, .. } => {
+                            for i in 0..16 {
+                                if let Some(child) = branch_children.get(i) {
+                                    children.push((child, Some(i as u8)));
+                                }
+                            }
+                            (3, true)
+                        }
+                    };
+                    stats.node_kinds[kind].count += 1;
+                    stats.node_kinds[kind].bytes += bytes;
+
+                    let owner = if has_value {
+                        let key = NibbleSlice::nibbles_to_bytes(&path);
+                        *stats.key_lengths.entry(key_length_bucket(key.len())).or_default() += 1;
+                        match parse_account_id_from_raw_key(&key) {
+                            Ok(Some(account_id)) => Owner::Account(account_id),
+                            _ => Owner::Shared,
+                        }
+                    } else {
+                        Owner::None
+                    };
+                    stack.push(Frame::Finish { bytes, num_children: children.len(), owner });
+                    // Pushed in reverse so that the children are visited in
+                    // the order of their keys.
+                    for (child, nibble) in children.into_iter().rev() {
+                        stack.push(Frame::Visit { node: child, path_len: path.len(), nibble });
+                    }
+                }
+                Frame::Finish { bytes, num_children, owner } => {
+                    let owner = owners
+                        .drain(owners.len() - num_children..)
+                        .fold(owner, |owner, child_owner| owner.merge(child_owner));
+                    match &owner {
+                        Owner::Account(account_id) => {
+                            *stats.account_bytes.entry(account_id.clone()).or_default() += bytes;
+                        }
+                        Owner::None | Owner::Shared => stats.shared_bytes += bytes,
+                    }
+                    owners.push(owner);
+                }
+            }
+        }
+        stats
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.node_kinds.iter().map(|kind| kind.bytes).sum()
+    }
+
+    fn print(
+        &self,
+        shard_uid: ShardUId,
+        height: BlockHeight,
+        arena_bytes: u64,
+        num_top_accounts: usize,
+    ) {
+        let total_bytes = self.total_bytes();
+        let total_count: u64 = self.node_kinds.iter().map(|kind| kind.count).sum();
+        println!("Memtrie of {} at height {}", shard_uid, height);
+        println!(
+            "  {} nodes using {}, the arena reserves {}",
+            total_count,
+            ByteSize::b(total_bytes),
+            ByteSize::b(arena_bytes)
+        );
+
+        println!("By node type:");
+        for (name, kind) in NODE_KINDS.iter().zip(&self.node_kinds) {
+            println!(
+                "  {:<16} {:>12} nodes {:>12} {:>5.1}% {:>8} bytes on average",
+                name,
+                kind.count,
+                ByteSize::b(kind.bytes).to_string(),
+                percent(kind.bytes, total_bytes),
+                kind.bytes.checked_div(kind.count).unwrap_or_default()
+            );
+        }
+
+        println!("Key length distribution:");
+        let num_keys: u64 = self.key_lengths.values().sum();
+        for (&bucket, &count) in &self.key_lengths {
+            let range = if bucket == 0 {
+                "0".to_string()
+            } else {
+                format!("{}-{}", bucket, 2 * bucket - 1)
+            };
+            println!("  {:>9} bytes {:>12} keys {:>5.1}%", range, count, percent(count, num_keys));
+        }
+
+        println!("Top accounts by resident trie bytes:");
+        let mut accounts: Vec<_> = self.account_bytes.iter().collect();
+        accounts.sort_by(|(a_id, a_bytes), (b_id, b_bytes)| {
+            b_bytes.cmp(a_bytes).then_with(|| a_id.cmp(b_id))
+        });
+        for (account_id, &bytes) in accounts.iter().take(num_top_accounts) {
+            println!(
+                "  {:<64} {:>12} {:>5.1}%",
+                account_id.as_str(),
+                ByteSize::b(bytes).to_string(),
+                percent(bytes, total_bytes)
+            );
+        }
+        println!(
+            "  {} accounts in total, {} ({:.1}%) is shared between accounts or not owned by any",
+            self.account_bytes.len(),
+            ByteSize::b(self.shared_bytes),
+            percent(self.shared_bytes, total_bytes)
+        );
+    }
+}
+
+/// Returns the largest power of two not greater than `len`, or 0 for empty
+/// keys, so that key lengths are bucketed as 0, 1, 2-3, 4-7, 8-15, etc.
+fn key_length_bucket(len: usize) -> usize {
+    if len == 0 {
+        0
+    } else {
+        1 << len.ilog2()
+    }
+}
+
+fn percent(value: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        100.0 * value as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{key_length_bucket, Owner};
+
+    #[test]
+    fn test_key_length_bucket() {
+        assert_eq!(key_length_bucket(0), 0);
+        assert_eq!(key_length_bucket(1), 1);
+        assert_eq!(key_length_bucket(3), 2);
+        assert_eq!(key_length_bucket(8), 8);
+        assert_eq!(key_length_bucket(100), 64);
+    }
+
+    #[test]
+    fn test_owner_merge() {
+        let alice = Owner::Account("alice.near".parse().unwrap());
+        let bob = Owner::Account("bob.near".parse().unwrap());
+        assert_eq!(Owner::None.merge(alice.clone()), alice);
+        assert_eq!(alice.clone().merge(Owner::None), alice);
+        assert_eq!(alice.clone().merge(alice.clone()), alice);
+        assert_eq!(alice.clone().merge(bob), Owner::Shared);
+        assert_eq!(Owner::Shared.merge(alice), Owner::Shared);
+    }
+}
@@ -3,9 +3,9 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use near_async::time::Clock;
 use near_chain::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode};
 use near_client::sync::external::{
-    create_bucket_readonly, create_bucket_readwrite, external_storage_location,
-    external_storage_location_directory, get_num_parts_from_filename, ExternalConnection,
-    StateFileType,
+    create_bucket_readonly, create_bucket_readwrite, decompress_state_part,
+    external_storage_location, external_storage_location_directory, get_num_parts_from_filename,
+    ExternalConnection, StateFileType,
 };
 use near_client::sync::state::StateSync;
 use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
@@ -14,11 +14,15 @@ use near_primitives::challenge::PartialState;
 use near_primitives::epoch_manager::epoch_info::EpochInfo;
 use near_primitives::state_part::PartId;
 use near_primitives::state_record::StateRecord;
+use near_primitives::state_sync::get_num_state_parts;
 use near_primitives::types::{EpochId, StateRoot};
 use near_primitives_core::hash::CryptoHash;
 use near_primitives_core::types::{BlockHeight, EpochHeight, ShardId};
+use near_store::flat::store_helper::get_flat_storage_status;
+use near_store::flat::FlatStorageStatus;
 use near_store::{PartialStorage, Store, Trie};
 use nearcore::{NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -74,6 +78,20 @@ pub(crate) enum StatePartsSubCommand {
         #[clap(subcommand)]
         epoch_selection: EpochSelection,
     },
+    /// Generate state parts of the current state of a shard from both the
+    /// disk trie and the memtrie, and compare how long it takes.
+    /// Doesn't write anything.
+    Benchmark {
+        /// Generate part ids starting from this part.
+        #[clap(long)]
+        part_from: Option<u64>,
+        /// Generate part ids up to this part (exclusive).
+        #[clap(long)]
+        part_to: Option<u64>,
+        /// How many parts to generate from the memtrie in parallel.
+        #[clap(long, default_value = "4")]
+        parallelism: usize,
+    },
     /// Read State Header from the DB
     ReadStateHeader {
         /// Select an epoch to work on.
@@ -186,6 +204,9 @@ impl StatePartsSubCommand {
                     )
                     .await
                 }
+                StatePartsSubCommand::Benchmark { part_from, part_to, parallelism } => {
+                    benchmark_state_parts(shard_id, part_from, part_to, parallelism, &chain, store)
+                }
                 StatePartsSubCommand::ReadStateHeader { epoch_selection } => {
                     read_state_header(epoch_selection, shard_id, &chain, store)
                 }
@@ -377,6 +398,7 @@ async fn load_state_parts(
         let location =
             external_storage_location(chain_id, &epoch_id, epoch_height, shard_id, &file_type);
         let part = external.get_file(shard_id, &location, &file_type).await.unwrap();
+        let part = decompress_state_part(part).unwrap();
 
         match action {
             LoadAction::Apply => {
@@ -517,6 +539,78 @@ async fn dump_state_parts(
     tracing::info!(target: "state-parts", total_elapsed_sec = timer.elapsed().as_secs_f64(), "Wrote all requested state parts");
 }
 
+/// Generates state parts of the state at the flat storage head, first from the
+/// disk trie, and then from the memtrie, serially and in parallel.
+/// Checks that all of them produce the same parts.
+fn benchmark_state_parts(
+    shard_id: ShardId,
+    part_from: Option<u64>,
+    part_to: Option<u64>,
+    parallelism: usize,
+    chain: &Chain,
+    store: Store,
+) {
+    let final_head = chain.final_head().unwrap();
+    let shard_uid = chain.epoch_manager.shard_id_to_uid(shard_id, &final_head.epoch_id).unwrap();
+    let flat_head = match get_flat_storage_status(&store, shard_uid) {
+        Ok(FlatStorageStatus::Ready(ready_status)) => ready_status.flat_head,
+        status => panic!("flat storage is not ready for shard {shard_id}: {status:?}"),
+    };
+    let state_root = *chain.get_chunk_extra(&flat_head.hash, &shard_uid).unwrap().state_root();
+    let tries = chain.runtime_adapter.get_tries();
+    let trie = tries.get_view_trie_for_shard(shard_uid, state_root);
+    let num_parts = get_num_state_parts(trie.retrieve_root_node().unwrap().memory_usage);
+    let part_ids = get_part_ids(part_from, part_to, num_parts);
+    tracing::info!(
+        target: "state-parts",
+        shard_id,
+        flat_head_height = flat_head.height,
+        ?state_root,
+        num_parts,
+        ?part_ids,
+        "Benchmarking state parts generation.",
+    );
+
+    let timer = Instant::now();
+    let disk_parts: Vec<PartialState> = part_ids
+        .clone()
+        .map(|part_id| {
+            trie.get_trie_nodes_for_part_without_flat_storage(PartId::new(part_id, num_parts))
+                .unwrap()
+        })
+        .collect();
+    tracing::info!(target: "state-parts", elapsed_sec = timer.elapsed().as_secs_f64(), "Generated parts from the disk trie");
+
+    let timer = Instant::now();
+    tries.load_mem_trie(&shard_uid, None).unwrap();
+    tracing::info!(target: "state-parts", elapsed_sec = timer.elapsed().as_secs_f64(), "Loaded the memtrie");
+
+    let get_memtrie_part = |part_id| {
+        tries
+            .get_view_trie_for_shard(shard_uid, state_root)
+            .get_trie_nodes_for_part_with_memtries(PartId::new(part_id, num_parts))
+            .unwrap()
+            .expect("memtrie must have the state root")
+    };
+    let timer = Instant::now();
+    let memtrie_parts: Vec<PartialState> = part_ids.clone().map(get_memtrie_part).collect();
+    tracing::info!(target: "state-parts", elapsed_sec = timer.elapsed().as_secs_f64(), "Generated parts from the memtrie");
+
+    let timer = Instant::now();
+    let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(parallelism).build().unwrap();
+    let parallel_memtrie_parts: Vec<PartialState> =
+        thread_pool.install(|| part_ids.clone().into_par_iter().map(get_memtrie_part).collect());
+    tracing::info!(target: "state-parts", elapsed_sec = timer.elapsed().as_secs_f64(), parallelism, "Generated parts from the memtrie in parallel");
+
+    for (part_id, ((disk_part, memtrie_part), parallel_memtrie_part)) in
+        part_ids.zip(disk_parts.iter().zip(memtrie_parts.iter()).zip(parallel_memtrie_parts.iter()))
+    {
+        assert_eq!(disk_part, memtrie_part, "part {part_id} differs");
+        assert_eq!(disk_part, parallel_memtrie_part, "part {part_id} differs");
+    }
+    tracing::info!(target: "state-parts", "All parts are the same");
+}
+
 /// Returns the first `StateRecord` encountered while iterating over a sub-trie in the state part.
 fn get_first_state_record(state_root: &StateRoot, data: &[u8]) -> Option<StateRecord> {
     let trie_nodes = BorshDeserialize::try_from_slice(data).unwrap();
@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use bytesize::ByteSize;
+use clap::Parser;
+use near_chain::ChainStore;
+use near_primitives::challenge::PartialState;
+use near_primitives::stateless_validation::{
+    ChunkStateWitness, EncodedChunkStateWitness, StateWitnessCompression, WitnessCompressionMode,
+};
+use near_store::Store;
+use nearcore::NearConfig;
+
+/// Every witness with an index divisible by this is left out of training and used to
+/// evaluate the dictionary instead.
+const EVALUATION_WITNESS_STRIDE: usize = 10;
+
+/// What the dictionary is trained on.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum WitnessSamples {
+    /// Every borsh-serialized witness is a sample.
+    WholeWitness,
+    /// Every trie node and value in the partial states of the witnesses is a sample.
+    PerValue,
+}
+
+impl WitnessSamples {
+    /// The compression mode which uses a dictionary trained on these samples.
+    fn compression_mode(self) -> WitnessCompressionMode {
+        match self {
+            WitnessSamples::WholeWitness => WitnessCompressionMode::WholeWitness,
+            WitnessSamples::PerValue => WitnessCompressionMode::PerValue,
+        }
+    }
+}
+
+#[derive(Parser)]
+pub struct TrainWitnessDictionaryCmd {
+    /// Train on the witnesses saved by shadow validation instead of the latest observed ones.
+    #[arg(long)]
+    shadow: bool,
+
+    /// What the dictionary is trained on.
+    #[arg(long, value_enum, default_value_t = WitnessSamples::WholeWitness)]
+    samples: WitnessSamples,
+
+    /// Maximum size of the dictionary.
+    #[arg(long, default_value = "112KiB")]
+    max_size: ByteSize,
+
+    /// zstd level used to compare the compression with and without the dictionary.
+    #[arg(long, default_value_t = StateWitnessCompression::DEFAULT_LEVEL)]
+    level: i32,
+
+    /// File the dictionary is written to.
+    #[arg(long)]
+    output: PathBuf,
+}
+
+impl TrainWitnessDictionaryCmd {
+    pub(crate) fn run(&self, near_config: NearConfig, store: Store) -> anyhow::Result<()> {
+        let chain_store = ChainStore::new(store, near_config.genesis.config.genesis_height, false);
+        let witnesses = if self.shadow {
+            chain_store.get_shadow_state_witnesses(None, None)?
+        } else {
+            chain_store.get_latest_witnesses(None, None, None)?
+        };
+        anyhow::ensure!(
+            witnesses.len() > EVALUATION_WITNESS_STRIDE,
+            "found only {} witnesses, need more than {} to train and evaluate a dictionary",
+            witnesses.len(),
+            EVALUATION_WITNESS_STRIDE
+        );
+
+        let (evaluation, training): (Vec<_>, Vec<_>) = witnesses
+            .into_iter()
+            .enumerate()
+            .partition(|(index, _)| index % EVALUATION_WITNESS_STRIDE == 0);
+        let samples: Vec<Vec<u8>> = training
+            .iter()
+            .flat_map(|(_, witness)| match self.samples {
+                WitnessSamples::WholeWitness => vec![borsh::to_vec(witness).unwrap()],
+                WitnessSamples::PerValue => witness_values(witness),
+            })
+            .collect();
+        println!(
+            "Training a dictionary on {} samples from {} witnesses",
+            samples.len(),
+            training.len()
+        );
+        let dictionary = zstd::dict::from_samples(&samples, self.max_size.as_u64() as usize)
+            .context("failed to train the dictionary")?;
+        std::fs::write(&self.output, &dictionary)?;
+        println!(
+            "Wrote a dictionary of {} to {}",
+            ByteSize::b(dictionary.len() as u64),
+            self.output.display()
+        );
+
+        // The witnesses are compressed without the dictionary as a whole, like
+        // by default, and with the dictionary in the mode it was trained for.
+        let without_dictionary =
+            StateWitnessCompression { level: self.level, ..Default::default() };
+        let with_dictionary = StateWitnessCompression {
+            level: self.level,
+            mode: self.samples.compression_mode(),
+            dictionary: Some(dictionary.into()),
+            compress_with_dictionary: true,
+        };
+        let mut raw_size = 0;
+        let mut size_without_dictionary = 0;
+        let mut size_with_dictionary = 0;
+        let mut values_size_with_dictionary = 0;
+        for (_, witness) in &evaluation {
+            let (encoded, size) =
+                EncodedChunkStateWitness::encode_with(witness, &without_dictionary)?;
+            raw_size += size;
+            size_without_dictionary += encoded.size_bytes();
+            let (encoded, _) = EncodedChunkStateWitness::encode_with(witness, &with_dictionary)?;
+            size_with_dictionary += encoded.size_bytes();
+            values_size_with_dictionary += encoded.compressed_values_size().unwrap_or(0);
+        }
+        println!("Evaluated on {} witnesses at level {}:", evaluation.len(), self.level);
+        for (name, size) in [
+            ("without dictionary", size_without_dictionary),
+            ("with dictionary", size_with_dictionary),
+        ] {
+            println!(
+                "  {}: {} ({:.2}x)",
+                name,
+                ByteSize::b(size as u64),
+                raw_size as f64 / size as f64
+            );
+        }
+        if let WitnessSamples::PerValue = self.samples {
+            println!(
+                "  of which values with dictionary: {}",
+                ByteSize::b(values_size_with_dictionary as u64)
+            );
+        }
+        println!("  raw: {}", ByteSize::b(raw_size as u64));
+        Ok(())
+    }
+}
+
+/// Returns the trie nodes and values of all partial states of the witness.
+fn witness_values(witness: &ChunkStateWitness) -> Vec<Vec<u8>> {
+    std::iter::once(&witness.main_state_transition.base_state)
+        .chain(witness.implicit_transitions.iter().map(|transition| &transition.base_state))
+        .chain(std::iter::once(&witness.new_transactions_validation_state))
+        .flat_map(|PartialState::TrieValues(values)| values.iter().map(|value| value.to_vec()))
+        .collect()
+}
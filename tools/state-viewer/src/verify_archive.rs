@@ -0,0 +1,490 @@
+use anyhow::Context;
+use near_chain::chain::collect_receipts_from_response;
+use near_chain::migrations::check_if_block_is_first_with_chunk_of_version;
+use near_chain::types::{
+    ApplyChunkResult, ApplyChunkShardContext, RuntimeAdapter, RuntimeStorageConfig,
+};
+use near_chain::{Chain, ChainStore, ChainStoreAccess, ChainStoreUpdate};
+use near_crypto::{KeyFile, PublicKey, SecretKey, Signature};
+use near_epoch_manager::{EpochManager, EpochManagerAdapter, EpochManagerHandle};
+use near_primitives::apply::ApplyChunkReason;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::chunk_extra::ChunkExtra;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_store::Store;
+use nearcore::{NearConfig, NightshadeRuntime};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(clap::Parser)]
+pub struct VerifyArchiveCmd {
+    /// First height of the range to verify.
+    #[clap(long)]
+    start_height: BlockHeight,
+    /// Last height of the range to verify. Defaults to the final head.
+    #[clap(long)]
+    end_height: Option<BlockHeight>,
+    /// Shards to verify, e.g. `--shard-id 0,1,2`. Defaults to all the shards
+    /// of every block.
+    #[clap(long = "shard-id", value_delimiter = ',')]
+    shard_ids: Vec<ShardId>,
+    /// Where the signed report is written.
+    #[clap(long)]
+    report_file: PathBuf,
+    /// Key file the report is signed with. Defaults to the node key.
+    #[clap(long)]
+    signer_key_file: Option<PathBuf>,
+}
+
+impl VerifyArchiveCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        self.run_impl(home_dir, near_config, store).unwrap();
+    }
+
+    fn run_impl(
+        self,
+        home_dir: &Path,
+        near_config: NearConfig,
+        store: Store,
+    ) -> anyhow::Result<()> {
+        let signer_key = match &self.signer_key_file {
+            Some(path) => {
+                KeyFile::from_file(path)
+                    .with_context(|| format!("failed reading {}", path.display()))?
+                    .secret_key
+            }
+            None => near_config.network_config.node_key.clone(),
+        };
+        let epoch_manager =
+            EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+        let runtime = NightshadeRuntime::from_config(
+            home_dir,
+            store.clone(),
+            &near_config,
+            epoch_manager.clone(),
+        )
+        .context("could not create the transaction runtime")?;
+        let genesis_height = near_config.genesis.config.genesis_height;
+        let chain_store = ChainStore::new(store.clone(), genesis_height, false);
+        let end_height = match self.end_height {
+            Some(end_height) => end_height,
+            None => chain_store.final_head()?.height,
+        };
+        anyhow::ensure!(
+            self.start_height <= end_height,
+            "empty range {}..={}",
+            self.start_height,
+            end_height
+        );
+
+        let started_at = chrono::Utc::now();
+        println!(
+            "Verifying the chunks in the range {}..={} of shards {}",
+            self.start_height,
+            end_height,
+            if self.shard_ids.is_empty() {
+                "all".to_string()
+            } else {
+                format!("{:?}", self.shard_ids)
+            }
+        );
+        let verifier = ArchiveVerifier {
+            store,
+            genesis_height,
+            epoch_manager: epoch_manager.as_ref(),
+            runtime: runtime.as_ref(),
+            shard_ids: &self.shard_ids,
+            num_processed_heights: AtomicU64::new(0),
+            num_heights: end_height - self.start_height + 1,
+        };
+        let results: Vec<HeightVerification> = (self.start_height..=end_height)
+            .into_par_iter()
+            .map(|height| verifier.verify_height(height))
+            .collect();
+
+        let mut report = VerificationReport {
+            chain_id: near_config.genesis.config.chain_id.clone(),
+            start_height: self.start_height,
+            end_height,
+            shard_ids: (!self.shard_ids.is_empty()).then(|| self.shard_ids.clone()),
+            started_at,
+            finished_at: chrono::Utc::now(),
+            num_blocks: 0,
+            num_new_chunks: 0,
+            num_missing_chunks: 0,
+            mismatches: vec![],
+            errors: vec![],
+        };
+        for result in results {
+            let HeightVerification::Block(chunks) = result else {
+                continue;
+            };
+            report.num_blocks += 1;
+            for chunk in chunks {
+                match chunk {
+                    Ok(verification) => {
+                        if verification.is_new_chunk {
+                            report.num_new_chunks += 1;
+                        } else {
+                            report.num_missing_chunks += 1;
+                        }
+                        report.mismatches.extend(verification.mismatches);
+                    }
+                    Err(error) => report.errors.push(error),
+                }
+            }
+        }
+
+        let signed_report = SignedVerificationReport::new(report, &signer_key)?;
+        std::fs::write(&self.report_file, serde_json::to_vec_pretty(&signed_report)?)
+            .with_context(|| format!("failed writing {}", self.report_file.display()))?;
+        let report = &signed_report.report;
+        println!(
+            "Verified {} new and {} missing chunks in {} blocks, found {} mismatches and {} errors, wrote the report signed by {} to {}",
+            report.num_new_chunks,
+            report.num_missing_chunks,
+            report.num_blocks,
+            report.mismatches.len(),
+            report.errors.len(),
+            signed_report.public_key,
+            self.report_file.display()
+        );
+        anyhow::ensure!(
+            report.mismatches.is_empty() && report.errors.is_empty(),
+            "the archive doesn't match the re-executed chunks"
+        );
+        Ok(())
+    }
+}
+
+/// Result of the verification of a block range. Heights without a block,
+/// which are expected when blocks are skipped, aren't reported.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct VerificationReport {
+    pub chain_id: String,
+    pub start_height: BlockHeight,
+    pub end_height: BlockHeight,
+    /// Shards that were verified, or `None` for all of them.
+    pub shard_ids: Option<Vec<ShardId>>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub num_blocks: u64,
+    /// Number of chunks that were re-executed.
+    pub num_new_chunks: u64,
+    /// Number of shards that had no new chunk in a block, for which only the
+    /// state root is verified.
+    pub num_missing_chunks: u64,
+    pub mismatches: Vec<Mismatch>,
+    /// Chunks that couldn't be verified, e.g. because the archive lacks data
+    /// to re-execute them.
+    pub errors: Vec<VerificationError>,
+}
+
+/// A field of the stored chunk extra that differs from the re-executed one.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    pub height: BlockHeight,
+    pub block_hash: CryptoHash,
+    pub shard_id: ShardId,
+    pub field: String,
+    pub stored: String,
+    pub computed: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct VerificationError {
+    pub height: BlockHeight,
+    pub shard_id: Option<ShardId>,
+    pub error: String,
+}
+
+/// The report along with a signature of the hash of its JSON serialization,
+/// attesting who verified the archive.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct SignedVerificationReport {
+    pub report: VerificationReport,
+    pub report_hash: CryptoHash,
+    pub public_key: PublicKey,
+    pub signature: Signature,
+}
+
+impl SignedVerificationReport {
+    pub fn new(report: VerificationReport, key: &SecretKey) -> anyhow::Result<Self> {
+        let report_hash = CryptoHash::hash_bytes(&serde_json::to_vec(&report)?);
+        let signature = key.sign(report_hash.as_ref());
+        Ok(Self { report, report_hash, public_key: key.public_key(), signature })
+    }
+
+    /// Checks that the report wasn't modified since it was signed.
+    pub fn verify(&self) -> anyhow::Result<bool> {
+        let report_hash = CryptoHash::hash_bytes(&serde_json::to_vec(&self.report)?);
+        Ok(report_hash == self.report_hash
+            && self.signature.verify(report_hash.as_ref(), &self.public_key))
+    }
+}
+
+enum HeightVerification {
+    /// There is no block at the height.
+    Skipped,
+    Block(Vec<Result<ChunkVerification, VerificationError>>),
+}
+
+struct ChunkVerification {
+    is_new_chunk: bool,
+    mismatches: Vec<Mismatch>,
+}
+
+struct ArchiveVerifier<'a> {
+    store: Store,
+    genesis_height: BlockHeight,
+    epoch_manager: &'a EpochManagerHandle,
+    runtime: &'a dyn RuntimeAdapter,
+    shard_ids: &'a [ShardId],
+    num_processed_heights: AtomicU64,
+    num_heights: u64,
+}
+
+impl ArchiveVerifier<'_> {
+    fn verify_height(&self, height: BlockHeight) -> HeightVerification {
+        let result = self.verify_block_at_height(height);
+        let num_processed = self.num_processed_heights.fetch_add(1, Ordering::Relaxed) + 1;
+        if num_processed % 1000 == 0 {
+            println!("Processed {}/{} heights", num_processed, self.num_heights);
+        }
+        match result {
+            Ok(result) => result,
+            Err(err) => HeightVerification::Block(vec![Err(VerificationError {
+                height,
+                shard_id: None,
+                error: format!("{:#}", err),
+            })]),
+        }
+    }
+
+    fn verify_block_at_height(&self, height: BlockHeight) -> anyhow::Result<HeightVerification> {
+        let chain_store = ChainStore::new(self.store.clone(), self.genesis_height, false);
+        let Ok(block_hash) = chain_store.get_block_hash_by_height(height) else {
+            return Ok(HeightVerification::Skipped);
+        };
+        let block = chain_store.get_block(&block_hash)?;
+        if block.header().is_genesis() {
+            return Ok(HeightVerification::Skipped);
+        }
+        let shard_ids = if self.shard_ids.is_empty() {
+            self.epoch_manager.shard_ids(block.header().epoch_id())?
+        } else {
+            self.shard_ids.to_vec()
+        };
+        let chunks = shard_ids
+            .into_par_iter()
+            .map(|shard_id| {
+                self.verify_chunk(height, &block_hash, shard_id).map_err(|err| VerificationError {
+                    height,
+                    shard_id: Some(shard_id),
+                    error: format!("{:#}", err),
+                })
+            })
+            .collect();
+        Ok(HeightVerification::Block(chunks))
+    }
+
+    /// Re-executes the chunk of the shard in the block, or only updates the
+    /// state of the shard if the block has no new chunk for it, and compares
+    /// the resulting chunk extra with the stored one.
+    fn verify_chunk(
+        &self,
+        height: BlockHeight,
+        block_hash: &CryptoHash,
+        shard_id: ShardId,
+    ) -> anyhow::Result<ChunkVerification> {
+        let mut chain_store = ChainStore::new(self.store.clone(), self.genesis_height, false);
+        let block = chain_store.get_block(block_hash)?;
+        let epoch_id = block.header().epoch_id();
+        let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, epoch_id)?;
+        let chunk_header = block
+            .chunks()
+            .get(shard_id as usize)
+            .cloned()
+            .with_context(|| format!("block has no chunk for shard {}", shard_id))?;
+        let stored_chunk_extra = chain_store
+            .get_chunk_extra(block_hash, &shard_uid)
+            .context("failed reading the stored chunk extra")?;
+        let is_new_chunk = chunk_header.height_included() == height;
+        let prev_block = chain_store.get_block(block.header().prev_hash())?;
+        // The same context the chain applies the chunk with, including the
+        // gas price of the current block used before `FixApplyChunks`.
+        let block_context = Chain::get_apply_chunk_block_context(
+            self.epoch_manager,
+            &block,
+            prev_block.header(),
+            is_new_chunk,
+        )?;
+
+        let computed_chunk_extra = if is_new_chunk {
+            let chunk = chain_store.get_chunk(&chunk_header.chunk_hash())?;
+            let receipt_proof_response = ChainStoreUpdate::new(&mut chain_store)
+                .get_incoming_receipts_for_shard(
+                    self.epoch_manager,
+                    shard_id,
+                    *block_hash,
+                    prev_block.chunks()[shard_id as usize].height_included(),
+                )?;
+            let receipts = collect_receipts_from_response(&receipt_proof_response);
+            let is_first_block_with_chunk_of_version =
+                check_if_block_is_first_with_chunk_of_version(
+                    &chain_store,
+                    self.epoch_manager,
+                    block.header().prev_hash(),
+                    shard_id,
+                )?;
+            let chunk_inner = chunk.cloned_header().take_inner();
+            let apply_result = self.runtime.apply_chunk(
+                RuntimeStorageConfig::new(*chunk_inner.prev_state_root(), false),
+                ApplyChunkReason::UpdateTrackedShard,
+                ApplyChunkShardContext {
+                    shard_id,
+                    last_validator_proposals: chunk_inner.prev_validator_proposals(),
+                    gas_limit: chunk_inner.gas_limit(),
+                    is_new_chunk: true,
+                    is_first_block_with_chunk_of_version,
+                },
+                block_context,
+                &receipts,
+                chunk.transactions(),
+            )?;
+            let protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
+            let (outcome_root, _) =
+                ApplyChunkResult::compute_outcomes_proof(&apply_result.outcomes);
+            ChunkExtra::new(
+                protocol_version,
+                &apply_result.new_root,
+                outcome_root,
+                apply_result.validator_proposals,
+                apply_result.total_gas_burnt,
+                chunk_inner.gas_limit(),
+                apply_result.total_balance_burnt,
+                apply_result.congestion_info,
+            )
+        } else {
+            // Without a new chunk only the state root changes, the rest of the
+            // chunk extra is copied from the previous block.
+            let prev_chunk_extra =
+                chain_store.get_chunk_extra(block.header().prev_hash(), &shard_uid)?;
+            let apply_result = self.runtime.apply_chunk(
+                RuntimeStorageConfig::new(*prev_chunk_extra.state_root(), false),
+                ApplyChunkReason::UpdateTrackedShard,
+                ApplyChunkShardContext {
+                    shard_id,
+                    last_validator_proposals: prev_chunk_extra.validator_proposals(),
+                    gas_limit: prev_chunk_extra.gas_limit(),
+                    is_new_chunk: false,
+                    is_first_block_with_chunk_of_version: false,
+                },
+                block_context,
+                &[],
+                &[],
+            )?;
+            let mut chunk_extra = ChunkExtra::clone(&prev_chunk_extra);
+            *chunk_extra.state_root_mut() = apply_result.new_root;
+            chunk_extra
+        };
+
+        let mismatches = compare_chunk_extras(&stored_chunk_extra, &computed_chunk_extra)
+            .into_iter()
+            .map(|(field, stored, computed)| Mismatch {
+                height,
+                block_hash: *block_hash,
+                shard_id,
+                field: field.to_string(),
+                stored,
+                computed,
+            })
+            .collect();
+        Ok(ChunkVerification { is_new_chunk, mismatches })
+    }
+}
+
+/// Returns the fields that differ between the chunk extras, along with their
+/// stored and computed values. Like `smart_equals`, the versions of the chunk
+/// extras are ignored.
+fn compare_chunk_extras(
+    stored: &ChunkExtra,
+    computed: &ChunkExtra,
+) -> Vec<(&'static str, String, String)> {
+    let mut mismatches = vec![];
+    let mut compare = |field, stored: String, computed: String| {
+        if stored != computed {
+            mismatches.push((field, stored, computed));
+        }
+    };
+    compare("state_root", stored.state_root().to_string(), computed.state_root().to_string());
+    compare("outcome_root", stored.outcome_root().to_string(), computed.outcome_root().to_string());
+    compare("gas_used", stored.gas_used().to_string(), computed.gas_used().to_string());
+    compare("gas_limit", stored.gas_limit().to_string(), computed.gas_limit().to_string());
+    compare(
+        "balance_burnt",
+        stored.balance_burnt().to_string(),
+        computed.balance_burnt().to_string(),
+    );
+    let proposals = |chunk_extra: &ChunkExtra| {
+        format!("{:?}", chunk_extra.validator_proposals().map(|p| p.into_v1()).collect::<Vec<_>>())
+    };
+    compare("validator_proposals", proposals(stored), proposals(computed));
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_chunk_extras, SignedVerificationReport, VerificationReport};
+    use near_crypto::{KeyType, SecretKey};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::types::chunk_extra::ChunkExtra;
+    use near_primitives::version::PROTOCOL_VERSION;
+
+    #[test]
+    fn test_compare_chunk_extras() {
+        let chunk_extra = |state_root: &CryptoHash, gas_used| {
+            ChunkExtra::new(
+                PROTOCOL_VERSION,
+                state_root,
+                CryptoHash::default(),
+                vec![],
+                gas_used,
+                1000,
+                0,
+                None,
+            )
+        };
+        let root = CryptoHash::hash_bytes(b"root");
+        assert!(compare_chunk_extras(&chunk_extra(&root, 10), &chunk_extra(&root, 10)).is_empty());
+        let mismatches =
+            compare_chunk_extras(&chunk_extra(&root, 10), &chunk_extra(&CryptoHash::default(), 20));
+        let fields: Vec<_> = mismatches.iter().map(|(field, _, _)| *field).collect();
+        assert_eq!(fields, vec!["state_root", "gas_used"]);
+    }
+
+    #[test]
+    fn test_signed_report() {
+        let key = SecretKey::from_random(KeyType::ED25519);
+        let report = VerificationReport {
+            chain_id: "test".to_string(),
+            start_height: 10,
+            end_height: 20,
+            shard_ids: None,
+            started_at: chrono::Utc::now(),
+            finished_at: chrono::Utc::now(),
+            num_blocks: 11,
+            num_new_chunks: 40,
+            num_missing_chunks: 4,
+            mismatches: vec![],
+            errors: vec![],
+        };
+        let signed_report = SignedVerificationReport::new(report, &key).unwrap();
+        let serialized = serde_json::to_string(&signed_report).unwrap();
+        let mut deserialized: SignedVerificationReport = serde_json::from_str(&serialized).unwrap();
+        assert!(deserialized.verify().unwrap());
+        deserialized.report.num_blocks += 1;
+        assert!(!deserialized.verify().unwrap());
+    }
+}
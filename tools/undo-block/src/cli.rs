@@ -1,8 +1,10 @@
+use anyhow::Context;
+use near_chain::types::RuntimeAdapter;
 use near_chain::ChainStore;
 use near_chain_configs::GenesisValidationMode;
 use near_epoch_manager::EpochManager;
 use near_store::{Mode, NodeStorage};
-use nearcore::load_config;
+use nearcore::{load_config, NightshadeRuntime, NightshadeRuntimeExt};
 use std::path::Path;
 
 #[derive(clap::Parser)]
@@ -10,6 +12,13 @@ pub struct UndoBlockCommand {
     /// Only reset the block head to the tail block. Does not reset the header head.
     #[arg(short, long)]
     reset_only_body: bool,
+    /// Number of blocks to undo.
+    #[arg(long, default_value_t = 1)]
+    num_blocks: u64,
+    /// Allow undoing final blocks. Only do this if all nodes of the chain are
+    /// rolled back the same way, e.g. on a private network.
+    #[arg(long)]
+    past_final: bool,
 }
 
 impl UndoBlockCommand {
@@ -35,15 +44,26 @@ impl UndoBlockCommand {
             EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
 
         let mut chain_store = ChainStore::new(
-            store,
+            store.clone(),
             near_config.genesis.config.genesis_height,
             near_config.client_config.save_trie_changes,
         );
 
         if self.reset_only_body {
-            crate::undo_only_block_head(&mut chain_store, &*epoch_manager)
-        } else {
-            crate::undo_block(&mut chain_store, &*epoch_manager)
+            return crate::undo_only_block_head(&mut chain_store, &*epoch_manager);
         }
+
+        let runtime =
+            NightshadeRuntime::from_config(home_dir, store, &near_config, epoch_manager.clone())
+                .context("could not create the transaction runtime")?;
+        let tries = runtime.get_tries();
+        crate::undo_blocks(
+            &mut chain_store,
+            &epoch_manager,
+            &tries,
+            self.num_blocks,
+            self.past_final,
+        )?;
+        crate::check_consistency(&chain_store, &*epoch_manager, &tries)
     }
 }
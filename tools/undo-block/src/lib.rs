@@ -1,43 +1,152 @@
 use chrono::Utc;
 use near_chain::types::{EpochManagerAdapter, LatestKnown};
 use near_chain::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
+use near_epoch_manager::types::EpochInfoAggregator;
+use near_epoch_manager::EpochManagerHandle;
 use near_primitives::block::Tip;
+use near_primitives::epoch_manager::AGGREGATOR_KEY;
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::get_block_shard_uid;
+use near_primitives::state::FlatStateValue;
+use near_primitives::types::RawStateChangesWithTrieKey;
 use near_primitives::utils::to_timestamp;
+use near_store::flat::{store_helper, BlockInfo, FlatStorageReadyStatus, FlatStorageStatus};
+use near_store::{DBCol, ShardTries, TrieChanges};
+use std::collections::BTreeSet;
 
 pub mod cli;
 
-pub fn undo_block(
+/// Undoes the last `num_blocks` blocks of the chain, resetting the head to
+/// the block before them.
+///
+/// Besides the data of the undone blocks, this reverts the trie nodes they
+/// inserted and rewinds flat storage to the new head in every shard where the
+/// flat head was above it. Undoing past the final block is only allowed with
+/// `past_final`, in which case the final head and the epoch info aggregator
+/// are reset as well. That's only safe if every node of the chain is rolled
+/// back, e.g. to recover a stalled private chain.
+pub fn undo_blocks(
     chain_store: &mut ChainStore,
-    epoch_manager: &dyn EpochManagerAdapter,
+    epoch_manager: &EpochManagerHandle,
+    tries: &ShardTries,
+    num_blocks: u64,
+    past_final: bool,
 ) -> anyhow::Result<()> {
-    let current_head = chain_store.head()?;
-    let current_head_hash = current_head.last_block_hash;
-    let prev_block_hash = current_head.prev_block_hash;
-    let prev_header = chain_store.get_block_header(&prev_block_hash)?;
-    let prev_tip = Tip::from_header(&prev_header);
-    let current_head_height = current_head.height;
-    let prev_block_height = prev_tip.height;
+    let store = chain_store.store().clone();
+    let tail_height = chain_store.tail()?;
+    let initial_final_head = chain_store.final_head()?;
+    // Keys changed by the undone blocks, which are reset in flat storage.
+    let mut changed_keys = BTreeSet::new();
 
-    tracing::info!(target: "neard", ?prev_block_hash, ?current_head_hash, ?prev_block_height, ?current_head_height, "Trying to update head");
+    for _ in 0..num_blocks {
+        let current_head = chain_store.head()?;
+        let current_head_hash = current_head.last_block_hash;
+        let prev_block_hash = current_head.prev_block_hash;
+        let prev_header = chain_store.get_block_header(&prev_block_hash)?;
+        let prev_tip = Tip::from_header(&prev_header);
+        let current_head_height = current_head.height;
+        let prev_block_height = prev_tip.height;
 
-    // stop if it's already the final block
-    if chain_store.final_head()?.height >= current_head.height {
-        return Err(anyhow::anyhow!("Cannot revert past final block"));
-    }
+        tracing::info!(target: "neard", ?prev_block_hash, ?current_head_hash, ?prev_block_height, ?current_head_height, "Trying to update head");
 
-    let mut chain_store_update = ChainStoreUpdate::new(chain_store);
+        if current_head_height <= tail_height {
+            return Err(anyhow::anyhow!("Cannot revert past the tail block"));
+        }
+        let final_head = chain_store.final_head()?;
+        if final_head.height >= current_head_height && !past_final {
+            return Err(anyhow::anyhow!("Cannot revert past final block"));
+        }
 
-    chain_store_update.clear_head_block_data(epoch_manager)?;
+        for item in store.iter_prefix_ser::<RawStateChangesWithTrieKey>(
+            DBCol::StateChanges,
+            current_head_hash.as_bytes(),
+        ) {
+            let (_, changes) = item?;
+            changed_keys.insert(changes.trie_key.to_vec());
+        }
 
-    chain_store_update.save_head(&prev_tip)?;
+        let mut store_update = store.store_update();
+        let shard_layout = epoch_manager.get_shard_layout(&current_head.epoch_id)?;
+        for shard_uid in shard_layout.shard_uids() {
+            let key = get_block_shard_uid(&current_head_hash, &shard_uid);
+            if let Some(trie_changes) = store.get_ser::<TrieChanges>(DBCol::TrieChanges, &key)? {
+                tries.revert_insertions(&trie_changes, shard_uid, &mut store_update);
+            }
+        }
 
-    chain_store_update.commit()?;
+        let mut chain_store_update = ChainStoreUpdate::new(chain_store);
+        chain_store_update.clear_head_block_data(epoch_manager)?;
+        chain_store_update.save_head(&prev_tip)?;
+        if final_head.height > prev_block_height {
+            let last_final_block = prev_header.last_final_block();
+            let final_header = if last_final_block == &CryptoHash::default() {
+                let genesis_height = chain_store_update.get_genesis_height();
+                let genesis_hash = chain_store_update.get_block_hash_by_height(genesis_height)?;
+                chain_store_update.get_block_header(&genesis_hash)?
+            } else {
+                chain_store_update.get_block_header(last_final_block)?
+            };
+            chain_store_update.save_final_head(&Tip::from_header(&final_header))?;
+        }
+        chain_store_update.merge(store_update);
+        chain_store_update.commit()?;
+    }
 
+    let new_head = chain_store.head()?;
     chain_store.save_latest_known(LatestKnown {
-        height: prev_tip.height,
+        height: new_head.height,
         seen: to_timestamp(Utc::now()),
     })?;
 
+    let mut store_update = store.store_update();
+    let shard_layout = epoch_manager.get_shard_layout(&new_head.epoch_id)?;
+    for shard_uid in shard_layout.shard_uids() {
+        let FlatStorageStatus::Ready(status) =
+            store_helper::get_flat_storage_status(&store, shard_uid)?
+        else {
+            continue;
+        };
+        if status.flat_head.height <= new_head.height {
+            continue;
+        }
+        // The flat head was at one of the undone blocks, so the flat state
+        // includes their changes. Reset the changed keys to their values at
+        // the new head and move the flat head there.
+        let chunk_extra = chain_store.get_chunk_extra(&new_head.last_block_hash, &shard_uid)?;
+        let trie = tries.get_view_trie_for_shard(shard_uid, *chunk_extra.state_root());
+        for key in changed_keys.iter() {
+            let value = trie.get(key)?;
+            store_helper::set_flat_state_value(
+                &mut store_update,
+                shard_uid,
+                key.clone(),
+                value.map(|value| FlatStateValue::on_disk(&value)),
+            );
+        }
+        let flat_head = BlockInfo {
+            hash: new_head.last_block_hash,
+            height: new_head.height,
+            prev_hash: new_head.prev_block_hash,
+        };
+        tracing::info!(target: "neard", ?shard_uid, old_flat_head = ?status.flat_head, new_flat_head = ?flat_head, "Rewinding flat storage");
+        store_helper::set_flat_storage_status(
+            &mut store_update,
+            shard_uid,
+            FlatStorageStatus::Ready(FlatStorageReadyStatus { flat_head }),
+        );
+    }
+
+    // The aggregator is only ever updated up to the final block, so it has to
+    // be recomputed if the final head moved back.
+    let final_head = chain_store.final_head()?;
+    if final_head.height < initial_final_head.height {
+        let aggregator = epoch_manager
+            .read()
+            .get_epoch_info_aggregator_upto_last(&final_head.last_block_hash)?;
+        store_update.set_ser(DBCol::EpochInfo, AGGREGATOR_KEY, &aggregator)?;
+    }
+    store_update.commit()?;
+
     let new_chain_store_head = chain_store.head()?;
     let new_chain_store_header_head = chain_store.header_head()?;
     let new_head_height = new_chain_store_head.height;
@@ -47,6 +156,116 @@ pub fn undo_block(
     Ok(())
 }
 
+/// Checks that the head of the chain is consistent with the rest of the
+/// storage: the head block and its chunk extras and state roots exist,
+/// nothing is left above it, and flat storage and the epoch info aggregator
+/// don't refer to blocks past it.
+pub fn check_consistency(
+    chain_store: &ChainStore,
+    epoch_manager: &dyn EpochManagerAdapter,
+    tries: &ShardTries,
+) -> anyhow::Result<()> {
+    let store = chain_store.store();
+    let head = chain_store.head()?;
+    let header_head = chain_store.header_head()?;
+    let final_head = chain_store.final_head()?;
+    let mut errors = vec![];
+
+    chain_store.get_block(&head.last_block_hash)?;
+    if header_head.height != head.height {
+        errors.push(format!(
+            "header head at height {} doesn't match the head at height {}",
+            header_head.height, head.height
+        ));
+    }
+    if final_head.height > head.height {
+        errors.push(format!(
+            "final head at height {} is above the head at height {}",
+            final_head.height, head.height
+        ));
+    }
+    if chain_store.get_block_hash_by_height(head.height + 1).is_ok() {
+        errors.push(format!("there is a canonical block above the head at height {}", head.height));
+    }
+
+    let shard_layout = epoch_manager.get_shard_layout(&head.epoch_id)?;
+    for shard_uid in shard_layout.shard_uids() {
+        match chain_store.get_chunk_extra(&head.last_block_hash, &shard_uid) {
+            Ok(chunk_extra) => {
+                let trie = tries.get_view_trie_for_shard(shard_uid, *chunk_extra.state_root());
+                if let Err(err) = trie.retrieve_root_node() {
+                    errors.push(format!(
+                        "state root {} of shard {} is missing: {}",
+                        chunk_extra.state_root(),
+                        shard_uid,
+                        err
+                    ));
+                }
+            }
+            Err(err) => {
+                errors.push(format!("chunk extra of shard {} is missing: {}", shard_uid, err))
+            }
+        }
+
+        let FlatStorageStatus::Ready(status) =
+            store_helper::get_flat_storage_status(store, shard_uid)?
+        else {
+            continue;
+        };
+        let flat_head = status.flat_head;
+        if flat_head.height > head.height {
+            errors.push(format!(
+                "flat head of shard {} at height {} is above the head",
+                shard_uid, flat_head.height
+            ));
+            continue;
+        }
+        // Every block between the flat head and the head needs a delta.
+        let mut block_hash = head.last_block_hash;
+        while block_hash != flat_head.hash {
+            let header = chain_store.get_block_header(&block_hash)?;
+            if header.height() <= flat_head.height {
+                errors.push(format!(
+                    "flat head {} of shard {} is not an ancestor of the head",
+                    flat_head.hash, shard_uid
+                ));
+                break;
+            }
+            if store_helper::get_delta_changes(store, shard_uid, block_hash)?.is_none() {
+                errors.push(format!(
+                    "flat storage delta of shard {} at block {} is missing",
+                    shard_uid, block_hash
+                ));
+            }
+            block_hash = *header.prev_hash();
+        }
+    }
+
+    let aggregator: Option<EpochInfoAggregator> =
+        store.get_ser(DBCol::EpochInfo, AGGREGATOR_KEY)?;
+    if let Some(aggregator) = aggregator {
+        if aggregator.last_block_hash != CryptoHash::default() {
+            match chain_store.get_block_header(&aggregator.last_block_hash) {
+                Ok(header) if header.height() > final_head.height => errors.push(format!(
+                    "epoch info aggregator at height {} is above the final head",
+                    header.height()
+                )),
+                Ok(_) => {}
+                Err(_) => errors.push(format!(
+                    "epoch info aggregator refers to the missing block {}",
+                    aggregator.last_block_hash
+                )),
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(anyhow::anyhow!("Inconsistent storage after undo:\n{}", errors.join("\n")));
+    }
+    tracing::info!(target: "neard", head_height = head.height, "Storage is consistent with the head");
+    Ok(())
+}
+
 pub fn undo_only_block_head(
     chain_store: &mut ChainStore,
     epoch_manager: &dyn EpochManagerAdapter,
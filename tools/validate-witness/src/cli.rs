@@ -0,0 +1,142 @@
+use crate::{SavedWitnesses, WitnessFormat};
+use anyhow::Context;
+use near_async::time::Clock;
+use near_chain::{Chain, ChainGenesis, DoomslugThresholdMode};
+use near_chain_configs::GenesisValidationMode;
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::EpochManager;
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_store::{Mode, NodeStorage, Store};
+use nearcore::{load_config, NearConfig, NightshadeRuntime, NightshadeRuntimeExt};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Parser)]
+pub struct ValidateWitnessCommand {
+    /// File with the state witness to validate.
+    #[arg(long, required_unless_present = "chunk_hash", conflicts_with = "chunk_hash")]
+    file: Option<PathBuf>,
+    /// Format of the file with the state witness.
+    #[arg(long, value_enum, default_value_t = WitnessFormat::Borsh)]
+    format: WitnessFormat,
+    /// Validate the witness of this chunk saved by the node, which requires
+    /// `save_latest_witnesses` or `save_shadow_witnesses` in config.json.
+    #[arg(long)]
+    chunk_hash: Option<CryptoHash>,
+}
+
+impl ValidateWitnessCommand {
+    pub fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        let (near_config, store) = open_store(home_dir, genesis_validation)?;
+
+        let witness = match (&self.file, self.chunk_hash) {
+            (Some(path), _) => crate::read_witness(path, self.format)?,
+            (None, Some(chunk_hash)) => crate::find_saved_witness(&store, &ChunkHash(chunk_hash))?,
+            (None, None) => unreachable!("clap requires either --file or --chunk-hash"),
+        };
+        crate::print_witness_summary(&witness)?;
+
+        let chain = open_chain(home_dir, &near_config, store)?;
+        let timings = crate::validate_witness(&chain, witness)?;
+        println!("Pre-validation passed in {:?}", timings.pre_validation);
+        println!("Validation passed in {:?}", timings.validation);
+        println!(
+            "The witness is valid, total validation time: {:?}",
+            timings.pre_validation + timings.validation
+        );
+        Ok(())
+    }
+}
+
+#[derive(clap::Parser)]
+pub struct ReplayWitnessesCommand {
+    /// Replay the witnesses of chunks created at this height and above.
+    #[arg(long)]
+    from_height: BlockHeight,
+    /// Replay the witnesses of chunks created at this height and below.
+    #[arg(long)]
+    to_height: BlockHeight,
+    /// Replay only the witnesses of this shard.
+    #[arg(long)]
+    shard_id: Option<ShardId>,
+    /// Which of the witnesses saved by the node to replay.
+    #[arg(long, value_enum, default_value_t = SavedWitnesses::Shadow)]
+    witnesses: SavedWitnesses,
+}
+
+impl ReplayWitnessesCommand {
+    pub fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.from_height <= self.to_height,
+            "--from-height must not be greater than --to-height"
+        );
+        let (near_config, store) = open_store(home_dir, genesis_validation)?;
+        let chain = open_chain(home_dir, &near_config, store)?;
+
+        let summary = crate::replay_witnesses(
+            &chain,
+            self.witnesses,
+            self.from_height,
+            self.to_height,
+            self.shard_id,
+        )?;
+        println!(
+            "Replayed {} witnesses, {} diverged, {} invalid when observed, total validation time: {:?}, max validation time: {:?}",
+            summary.num_witnesses,
+            summary.num_divergences,
+            summary.num_invalid,
+            summary.total_validation_time,
+            summary.max_validation_time
+        );
+        anyhow::ensure!(summary.num_witnesses > 0, "no saved witnesses in the height range");
+        anyhow::ensure!(
+            summary.num_divergences == 0,
+            "validation of {} witnesses diverged",
+            summary.num_divergences
+        );
+        Ok(())
+    }
+}
+
+fn open_store(
+    home_dir: &Path,
+    genesis_validation: GenesisValidationMode,
+) -> anyhow::Result<(NearConfig, Store)> {
+    let near_config = load_config(home_dir, genesis_validation).context("Error loading config")?;
+    let store_opener =
+        NodeStorage::opener(home_dir, near_config.config.archive, &near_config.config.store, None);
+    let storage = store_opener.open_in_mode(Mode::ReadOnly)?;
+    let store = storage.get_hot_store();
+    Ok((near_config, store))
+}
+
+fn open_chain(home_dir: &Path, near_config: &NearConfig, store: Store) -> anyhow::Result<Chain> {
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &near_config.genesis.config);
+    let shard_tracker = ShardTracker::new(
+        TrackedConfig::from_config(&near_config.client_config),
+        epoch_manager.clone(),
+    );
+    let runtime =
+        NightshadeRuntime::from_config(home_dir, store, near_config, epoch_manager.clone())
+            .context("could not create the transaction runtime")?;
+    let chain_genesis = ChainGenesis::new(&near_config.genesis.config);
+    Chain::new_for_view_client(
+        Clock::real(),
+        epoch_manager,
+        shard_tracker,
+        runtime,
+        &chain_genesis,
+        DoomslugThresholdMode::TwoThirds,
+        false,
+    )
+    .context("could not open the chain")
+}
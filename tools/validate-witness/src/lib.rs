@@ -0,0 +1,298 @@
+use anyhow::Context;
+use borsh::BorshDeserialize;
+use bytesize::ByteSize;
+use near_chain::Chain;
+use near_client::{
+    pre_validate_chunk_state_witness, validate_chunk_state_witness, MainStateTransitionCache,
+};
+use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
+use near_primitives::stateless_validation::ChunkStateWitness;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_store::{DBCol, Store};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub mod cli;
+
+/// Format of a state witness read from a file.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum WitnessFormat {
+    /// Borsh-serialized `ChunkStateWitness`.
+    Borsh,
+    /// JSON array with the bytes of a borsh-serialized `ChunkStateWitness`, as
+    /// printed by `neard view-state latest-witnesses --binary`.
+    Json,
+}
+
+/// Reads a state witness from the file.
+pub fn read_witness(path: &Path, format: WitnessFormat) -> anyhow::Result<ChunkStateWitness> {
+    let contents = std::fs::read(path)
+        .with_context(|| format!("failed to read the witness from {}", path.display()))?;
+    let bytes = match format {
+        WitnessFormat::Borsh => contents,
+        WitnessFormat::Json => serde_json::from_slice::<Vec<u8>>(&contents)
+            .context("the witness file isn't a JSON array of bytes")?,
+    };
+    ChunkStateWitness::try_from_slice(&bytes).context("failed to deserialize the witness")
+}
+
+/// Finds the witness of the chunk among the witnesses saved by shadow
+/// validation and the latest witnesses observed by the node.
+pub fn find_saved_witness(
+    store: &Store,
+    chunk_hash: &ChunkHash,
+) -> anyhow::Result<ChunkStateWitness> {
+    // The chunk hash is the last part of the key of the shadow witnesses.
+    for item in store.iter(DBCol::ShadowStateWitness) {
+        let (key, value) = item?;
+        if key.ends_with(chunk_hash.as_bytes()) {
+            return Ok(ChunkStateWitness::try_from_slice(&value)?);
+        }
+    }
+    for item in store.iter(DBCol::LatestChunkStateWitnesses) {
+        let (_, value) = item?;
+        let witness = ChunkStateWitness::try_from_slice(&value)?;
+        if &witness.chunk_header.chunk_hash() == chunk_hash {
+            return Ok(witness);
+        }
+    }
+    anyhow::bail!(
+        "no witness of chunk {} is saved, enable save_latest_witnesses or save_shadow_witnesses to keep witnesses",
+        chunk_hash.0
+    )
+}
+
+/// Prints the chunk the witness is for and the sizes of its parts.
+pub fn print_witness_summary(witness: &ChunkStateWitness) -> anyhow::Result<()> {
+    let chunk_header = &witness.chunk_header;
+    println!(
+        "Witness of chunk {} (height: {}, shard_id: {}, epoch_id: {:?}, chunk_producer: {})",
+        chunk_header.chunk_hash().0,
+        chunk_header.height_created(),
+        chunk_header.shard_id(),
+        witness.epoch_id,
+        witness.chunk_producer
+    );
+    let sizes = [
+        ("main state transition", borsh::to_vec(&witness.main_state_transition)?.len()),
+        ("implicit transitions", borsh::to_vec(&witness.implicit_transitions)?.len()),
+        ("source receipt proofs", borsh::to_vec(&witness.source_receipt_proofs)?.len()),
+        ("transactions", borsh::to_vec(&witness.transactions)?.len()),
+        ("new transactions", borsh::to_vec(&witness.new_transactions)?.len()),
+        (
+            "new transactions validation state",
+            borsh::to_vec(&witness.new_transactions_validation_state)?.len(),
+        ),
+    ];
+    println!("  total: {}", ByteSize::b(borsh::to_vec(witness)?.len() as u64));
+    for (name, size) in sizes {
+        println!("  {}: {}", name, ByteSize::b(size as u64));
+    }
+    Ok(())
+}
+
+/// Time spent in each step of the validation.
+#[derive(Debug)]
+pub struct ValidationTimings {
+    pub pre_validation: Duration,
+    pub validation: Duration,
+}
+
+/// Validates the witness against the local chain the same way a chunk
+/// validator does, returning the error of the step which failed.
+pub fn validate_witness(
+    chain: &Chain,
+    witness: ChunkStateWitness,
+) -> anyhow::Result<ValidationTimings> {
+    let epoch_manager = chain.epoch_manager.as_ref();
+    let runtime_adapter = chain.runtime_adapter.as_ref();
+
+    let start = Instant::now();
+    let pre_validation_output =
+        pre_validate_chunk_state_witness(&witness, chain, epoch_manager, runtime_adapter)
+            .context("pre-validation failed")?;
+    let pre_validation = start.elapsed();
+
+    let start = Instant::now();
+    validate_chunk_state_witness(
+        witness,
+        pre_validation_output,
+        epoch_manager,
+        runtime_adapter,
+        &MainStateTransitionCache::default(),
+    )
+    .context("validation failed")?;
+    let validation = start.elapsed();
+
+    Ok(ValidationTimings { pre_validation, validation })
+}
+
+/// Which of the witnesses saved by the node are replayed.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum SavedWitnesses {
+    /// Witnesses produced by shadow validation, one per chunk, saved with `save_shadow_witnesses`.
+    Shadow,
+    /// Witnesses observed by the node, saved with `save_latest_witnesses`.
+    Latest,
+}
+
+/// Outcome of replaying the saved witnesses of a range of heights.
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    pub num_witnesses: usize,
+    /// Number of witnesses whose validation failed, although their chunks were
+    /// accepted by the chain when the witnesses were saved.
+    pub num_divergences: usize,
+    /// Number of witnesses whose validation failed and whose chunks aren't on
+    /// the canonical chain. The latest witnesses include the ones the node
+    /// rejected when it observed them, which are expected to fail again.
+    pub num_invalid: usize,
+    pub total_validation_time: Duration,
+    pub max_validation_time: Duration,
+}
+
+/// How the validation of a replayed witness compares to the chain.
+#[derive(Debug, PartialEq, Eq)]
+enum ReplayOutcome {
+    Valid,
+    /// The witness is invalid, and so was its chunk when it was observed.
+    Invalid,
+    /// The witness is invalid, but its chunk was accepted by the chain.
+    Divergence,
+}
+
+impl ReplayOutcome {
+    fn new(is_valid: bool, is_chunk_accepted: bool) -> Self {
+        match (is_valid, is_chunk_accepted) {
+            (true, _) => ReplayOutcome::Valid,
+            (false, false) => ReplayOutcome::Invalid,
+            (false, true) => ReplayOutcome::Divergence,
+        }
+    }
+}
+
+/// Returns whether the chunk is included in the block of its height on the
+/// canonical chain.
+fn is_chunk_on_canonical_chain(
+    chain: &Chain,
+    chunk_header: &ShardChunkHeader,
+) -> anyhow::Result<bool> {
+    let block = match chain.get_block_by_height(chunk_header.height_created()) {
+        Ok(block) => block,
+        Err(near_chain::Error::DBNotFoundErr(_)) => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    let chunk_hash = chunk_header.chunk_hash();
+    Ok(block.chunks().iter().any(|header| header.chunk_hash() == chunk_hash))
+}
+
+/// Validates all saved witnesses of chunks created at heights from `from_height`
+/// to `to_height` inclusive, optionally of a single shard, and prints the
+/// validation time of every chunk or the reason of the failure.
+///
+/// Shadow witnesses are produced from the chunks applied by the node, so any
+/// failure is a divergence. A failed latest witness is only a divergence if
+/// its chunk is on the canonical chain.
+pub fn replay_witnesses(
+    chain: &Chain,
+    saved_witnesses: SavedWitnesses,
+    from_height: BlockHeight,
+    to_height: BlockHeight,
+    shard_id: Option<ShardId>,
+) -> anyhow::Result<ReplaySummary> {
+    let mut summary = ReplaySummary::default();
+    for height in from_height..=to_height {
+        let witnesses = match saved_witnesses {
+            SavedWitnesses::Shadow => {
+                chain.chain_store().get_shadow_state_witnesses(Some(height), shard_id)?
+            }
+            SavedWitnesses::Latest => {
+                chain.chain_store().get_latest_witnesses(Some(height), shard_id, None)?
+            }
+        };
+        for witness in witnesses {
+            let chunk_header = witness.chunk_header.clone();
+            summary.num_witnesses += 1;
+            let result = validate_witness(chain, witness);
+            let is_chunk_accepted = match saved_witnesses {
+                SavedWitnesses::Shadow => true,
+                SavedWitnesses::Latest => {
+                    result.is_ok() || is_chunk_on_canonical_chain(chain, &chunk_header)?
+                }
+            };
+            match (ReplayOutcome::new(result.is_ok(), is_chunk_accepted), result) {
+                (_, Ok(timings)) => {
+                    let validation_time = timings.pre_validation + timings.validation;
+                    summary.total_validation_time += validation_time;
+                    summary.max_validation_time = summary.max_validation_time.max(validation_time);
+                    println!(
+                        "height {} shard {} chunk {}: valid in {:?} (pre-validation {:?}, validation {:?})",
+                        height,
+                        chunk_header.shard_id(),
+                        chunk_header.chunk_hash().0,
+                        validation_time,
+                        timings.pre_validation,
+                        timings.validation
+                    );
+                }
+                (ReplayOutcome::Invalid, Err(err)) => {
+                    summary.num_invalid += 1;
+                    println!(
+                        "height {} shard {} chunk {}: invalid, the chunk is not on the canonical chain: {:#}",
+                        height,
+                        chunk_header.shard_id(),
+                        chunk_header.chunk_hash().0,
+                        err
+                    );
+                }
+                (_, Err(err)) => {
+                    summary.num_divergences += 1;
+                    println!(
+                        "height {} shard {} chunk {}: DIVERGENCE: {:#}",
+                        height,
+                        chunk_header.shard_id(),
+                        chunk_header.chunk_hash().0,
+                        err
+                    );
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_chunk_on_canonical_chain, ReplayOutcome};
+    use near_async::time::Clock;
+    use near_chain_configs::GenesisConfig;
+    use near_client::test_utils::{create_chunk_on_height, TestEnv};
+
+    #[test]
+    fn test_replay_outcome() {
+        assert_eq!(ReplayOutcome::new(true, true), ReplayOutcome::Valid);
+        // A valid witness of a chunk which didn't make it into a block, e.g.
+        // because the block was skipped.
+        assert_eq!(ReplayOutcome::new(true, false), ReplayOutcome::Valid);
+        assert_eq!(ReplayOutcome::new(false, false), ReplayOutcome::Invalid);
+        assert_eq!(ReplayOutcome::new(false, true), ReplayOutcome::Divergence);
+    }
+
+    #[test]
+    fn test_is_chunk_on_canonical_chain() {
+        let genesis_config = GenesisConfig::test(Clock::real());
+        let mut env = TestEnv::builder(&genesis_config).mock_epoch_managers().build();
+        for height in 1..=3 {
+            env.produce_block(0, height);
+        }
+
+        let chain = &env.clients[0].chain;
+        let included = chain.get_block_by_height(2).unwrap().chunks()[0].clone();
+        assert!(is_chunk_on_canonical_chain(chain, &included).unwrap());
+
+        // Produced on top of the head, but never included in a block.
+        let not_included = create_chunk_on_height(&mut env.clients[0], 4).chunk.cloned_header();
+        let chain = &env.clients[0].chain;
+        assert!(!is_chunk_on_canonical_chain(chain, &not_included).unwrap());
+    }
+}